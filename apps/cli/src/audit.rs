@@ -0,0 +1,90 @@
+//! `nl audit show` - 查看 God Mode 审计日志并校验哈希链
+
+use chrono::{DateTime, Duration, Utc};
+use nl_durable::EventStore;
+use nl_sandbox::audit::{verify_chain, AuditLog, PolicyDecision};
+
+/// 默认事件库路径（与守护进程一致）
+const DEFAULT_DB: &str = "neuroloom.db";
+
+/// 执行 `audit` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    match args.first() {
+        Some(&"show") => show(&args[1..]).await,
+        _ => {
+            println!("Usage: audit show [--since <rfc3339|30m|2h|7d>] [--db <path>]");
+            Ok(())
+        }
+    }
+}
+
+async fn show(args: &[&str]) -> anyhow::Result<()> {
+    let mut since = None;
+    let mut db = DEFAULT_DB;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--since" => since = Some(parse_since(value()?)?),
+            "--db" => db = value()?,
+            other => anyhow::bail!("unknown option: {}", other),
+        }
+    }
+
+    let store = EventStore::open(db).await?;
+    let entries = AuditLog::load(&store).await?;
+    // 始终校验完整链，`--since` 只影响显示范围
+    let broken = verify_chain(&entries).err();
+
+    let mut shown = 0;
+    for entry in entries.iter().filter(|e| since.is_none_or(|since| e.timestamp >= since)) {
+        let decision = match &entry.policy {
+            PolicyDecision::Allowed => "allowed".to_string(),
+            PolicyDecision::Denied { reason } => format!("denied ({})", reason),
+            PolicyDecision::Approved { by } => format!("approved by {}", by),
+        };
+        println!(
+            "#{:<5} {}  {:<12} {:<12} {:<20} {}  {}",
+            entry.seq,
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            entry.actor,
+            entry.action,
+            decision,
+            if entry.success { "ok" } else { "failed" },
+            entry.arguments,
+        );
+        shown += 1;
+    }
+
+    println!("{} of {} entries shown", shown, entries.len());
+    match broken {
+        None => println!("Hash chain verified"),
+        Some(seq) => anyhow::bail!("hash chain broken at entry #{}: audit log has been tampered with", seq),
+    }
+    Ok(())
+}
+
+/// 解析绝对时间（RFC 3339）或相对时长（如 `30m`、`2h`、`7d`）
+fn parse_since(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let split = value.len().saturating_sub(1);
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid --since value: {}", value))?;
+    let duration = match unit {
+        "s" => Duration::seconds(amount),
+        "m" => Duration::minutes(amount),
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        _ => anyhow::bail!("invalid --since value: {}", value),
+    };
+    Ok(Utc::now() - duration)
+}
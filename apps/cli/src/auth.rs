@@ -0,0 +1,156 @@
+//! `nl auth login|status|logout` - 管理 LLM Provider 凭证
+//!
+//! - `login antigravity|gemini-cli`：Google OAuth。桌面环境打开浏览器并在本地端口接收回调；
+//!   无图形界面（SSH 会话等）时改用设备码流程，在任意设备上输入用户码即可。`--device` / `--browser` 强制指定方式
+//! - `login iflow`：用 Cookie（`--cookie`、`IFLOW_COOKIE` 或交互输入）换取 API Key
+//! - `login vertex`：确认 Service Account JSON（`--credentials` 或 `GOOGLE_APPLICATION_CREDENTIALS`）能换取 access token 后保存
+//! - `status`：各凭证的状态、过期时间与账号；`logout <provider>|--all`：删除凭证文件
+//!
+//! 凭证写入各 Provider 默认配置读取的 Token 路径，登录后即可直接使用。
+
+use std::io::{self, BufRead, Write};
+
+use chrono::Utc;
+use nl_llm_new::auth::credentials::{self, CredentialProvider, CredentialStatus};
+use nl_llm_new::auth::device_code::LoginMethod;
+use nl_llm_new::auth::providers::antigravity::AntigravityOAuth;
+use nl_llm_new::auth::providers::gemini_cli::GeminiCliOAuth;
+use nl_llm_new::auth::providers::iflow::IFlowAuth;
+use nl_llm_new::auth::providers::vertex_sa::VertexSAAuth;
+use nl_llm_new::auth::TokenStatus;
+
+const USAGE: &str = "Usage: auth login <antigravity|gemini-cli|iflow|vertex> [--device|--browser] [--cookie <cookie>] [--credentials <sa.json>]\n       auth status\n       auth logout <provider>|--all";
+
+/// 执行 `auth` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    match args {
+        ["login", provider, rest @ ..] => login(provider.parse()?, rest).await,
+        ["status"] => {
+            print_status(CredentialProvider::ALL.into_iter().map(credentials::credential_status));
+            Ok(())
+        }
+        ["logout", "--all"] => {
+            for provider in CredentialProvider::ALL {
+                logout(provider)?;
+            }
+            Ok(())
+        }
+        ["logout", provider] => logout(provider.parse()?),
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+async fn login(provider: CredentialProvider, args: &[&str]) -> anyhow::Result<()> {
+    let mut method = LoginMethod::Auto;
+    let mut cookie = std::env::var("IFLOW_COOKIE").ok();
+    let mut sa_path = std::env::var("GOOGLE_APPLICATION_CREDENTIALS").ok();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--device" => method = LoginMethod::DeviceCode,
+            "--browser" => method = LoginMethod::Loopback,
+            "--cookie" => cookie = Some(value()?),
+            "--credentials" => sa_path = Some(value()?),
+            other => anyhow::bail!("unknown option: {}\n{}", other, USAGE),
+        }
+    }
+
+    let path = provider.credential_path();
+    match provider {
+        CredentialProvider::Antigravity => AntigravityOAuth::from_file(&path)?.interactive_login(method).await?,
+        CredentialProvider::GeminiCli => GeminiCliOAuth::from_file(&path)?.interactive_login(method).await?,
+        CredentialProvider::IFlow => {
+            let cookie = match cookie {
+                Some(cookie) => cookie,
+                None => prompt("Paste the iFlow cookie (BXAuth=...): ")?,
+            };
+            let mut auth = IFlowAuth::from_file(&path)?;
+            auth.set_cookie(&cookie);
+            auth.fetch_api_key().await?;
+        }
+        CredentialProvider::Vertex => {
+            let sa_path = sa_path.ok_or_else(|| anyhow::anyhow!("pass --credentials <service-account.json>"))?;
+            let json = std::fs::read_to_string(&sa_path)?;
+            // 先换一次 access token，确认私钥与账号可用
+            VertexSAAuth::new(json.clone()).ensure_authenticated().await?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, json)?;
+        }
+    }
+
+    println!("Logged in to {} (credentials saved to {})", provider, path.display());
+    print_status([credentials::credential_status(provider)]);
+    Ok(())
+}
+
+fn logout(provider: CredentialProvider) -> anyhow::Result<()> {
+    if credentials::logout(provider)? {
+        println!("Logged out of {} (removed {})", provider, provider.credential_path().display());
+    } else {
+        println!("Not logged in to {}", provider);
+    }
+    Ok(())
+}
+
+fn print_status(statuses: impl IntoIterator<Item = CredentialStatus>) {
+    println!("{:<12} {:<22} {:<30} ACCOUNT", "PROVIDER", "STATUS", "EXPIRES");
+    for status in statuses {
+        let state = match status.status {
+            None => "not logged in",
+            Some(TokenStatus::Valid) => "valid",
+            Some(TokenStatus::ExpiringSoon) => "expiring soon",
+            Some(TokenStatus::Expired) if status.refreshable => "expired (auto-refresh)",
+            Some(TokenStatus::Expired) => "expired",
+            Some(TokenStatus::RefreshFailed) => "refresh failed",
+        };
+        let expires = match (status.status, status.expires_at) {
+            (None, _) => "-".to_string(),
+            (Some(_), None) => "never".to_string(),
+            (Some(_), Some(at)) => format!("{} ({})", at.format("%Y-%m-%d %H:%M"), relative(at - Utc::now())),
+        };
+        println!(
+            "{:<12} {:<22} {:<30} {}",
+            status.provider,
+            state,
+            expires,
+            status.account.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+/// `in 2d 3h` / `5h ago`
+fn relative(delta: chrono::Duration) -> String {
+    let minutes = delta.num_minutes().abs();
+    let span = match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    };
+    if delta.num_seconds() >= 0 {
+        format!("in {}", span)
+    } else {
+        format!("{} ago", span)
+    }
+}
+
+fn prompt(message: &str) -> anyhow::Result<String> {
+    print!("{}", message);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    let line = line.trim().to_string();
+    if line.is_empty() {
+        anyhow::bail!("no input given");
+    }
+    Ok(line)
+}
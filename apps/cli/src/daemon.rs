@@ -0,0 +1,316 @@
+//! `nl daemon install|start|stop|status` - 以系统服务托管守护进程
+//!
+//! - Linux：生成 `Type=notify` 的 systemd unit（默认用户级，`--system` 为系统级），由 systemctl 管理；
+//!   `Delegate=yes` 把服务的 cgroup 子树交给守护进程，供沙箱按任务创建子 cgroup 限制资源
+//! - Windows：通过 `sc.exe` 注册为自动启动的 Windows 服务
+//! - `status` 同时查询服务管理器状态与控制面 `/health`
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// 服务名称（与守护进程一致）
+const SERVICE_NAME: &str = "neuroloom-daemon";
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+const USAGE: &str = "Usage: daemon install [--bin <path>] [--workdir <dir>] [--system]\n       daemon start|stop|status [--system] [--addr <host:port>]";
+
+/// 子命令选项
+#[derive(Debug)]
+struct Options {
+    bin: Option<PathBuf>,
+    workdir: Option<PathBuf>,
+    system: bool,
+    addr: String,
+}
+
+impl Options {
+    fn parse(args: &[&str]) -> anyhow::Result<Self> {
+        let mut options = Self {
+            bin: None,
+            workdir: None,
+            system: false,
+            addr: DEFAULT_ADDR.to_string(),
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .copied()
+                    .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+            };
+            match *arg {
+                "--bin" => options.bin = Some(PathBuf::from(value()?)),
+                "--workdir" => options.workdir = Some(PathBuf::from(value()?)),
+                "--addr" => options.addr = value()?.to_string(),
+                "--system" => options.system = true,
+                other => anyhow::bail!("unknown option: {}", other),
+            }
+        }
+        Ok(options)
+    }
+
+    /// 守护进程可执行文件（默认与 `nl` 同目录）
+    fn daemon_binary(&self) -> anyhow::Result<PathBuf> {
+        let bin = match &self.bin {
+            Some(bin) => bin.clone(),
+            None => std::env::current_exe()?
+                .with_file_name(format!("{}{}", SERVICE_NAME, std::env::consts::EXE_SUFFIX)),
+        };
+        if !bin.exists() {
+            anyhow::bail!("daemon binary not found at {} (use --bin)", bin.display());
+        }
+        Ok(std::fs::canonicalize(bin)?)
+    }
+
+    /// 服务工作目录（默认当前目录）
+    fn workdir(&self) -> anyhow::Result<PathBuf> {
+        Ok(std::fs::canonicalize(match &self.workdir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        })?)
+    }
+}
+
+/// 执行 `daemon` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Some((command, rest)) = args.split_first() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+    let options = Options::parse(rest)?;
+    match *command {
+        "install" => install(&options),
+        "start" => service_command(&options, "start"),
+        "stop" => service_command(&options, "stop"),
+        "status" => status(&options).await,
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+/// 生成 systemd unit 文件内容
+fn systemd_unit(bin: &Path, workdir: &Path, system: bool) -> String {
+    format!(
+        "[Unit]\n\
+         Description=NeuroLoom Daemon\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         Delegate=yes\n\
+         ExecStart={} --service\n\
+         WorkingDirectory={}\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         TimeoutStartSec=60\n\
+         WatchdogSec=30\n\
+         \n\
+         [Install]\n\
+         WantedBy={}\n",
+        bin.display(),
+        workdir.display(),
+        if system { "multi-user.target" } else { "default.target" }
+    )
+}
+
+/// systemd unit 文件路径
+fn unit_path(system: bool) -> anyhow::Result<PathBuf> {
+    if system {
+        return Ok(PathBuf::from("/etc/systemd/system").join(format!("{}.service", SERVICE_NAME)));
+    }
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(
+            std::env::var_os("HOME").ok_or_else(|| anyhow::anyhow!("HOME is not set"))?,
+        )
+        .join(".config"),
+    };
+    Ok(config
+        .join("systemd/user")
+        .join(format!("{}.service", SERVICE_NAME)))
+}
+
+/// 运行外部命令，失败时带上命令输出
+fn exec(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new(program).args(args).output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} {} failed: {}{}",
+            program,
+            args.join(" "),
+            stdout.trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(stdout)
+}
+
+fn systemctl(system: bool, args: &[&str]) -> anyhow::Result<String> {
+    let mut full = Vec::with_capacity(args.len() + 1);
+    if !system {
+        full.push("--user");
+    }
+    full.extend_from_slice(args);
+    exec("systemctl", &full)
+}
+
+/// 注册服务
+fn install(options: &Options) -> anyhow::Result<()> {
+    let bin = options.daemon_binary()?;
+    let workdir = options.workdir()?;
+
+    if cfg!(windows) {
+        let bin_path = format!("\"{}\" --service --workdir \"{}\"", bin.display(), workdir.display());
+        exec(
+            "sc.exe",
+            &[
+                "create",
+                SERVICE_NAME,
+                "binPath=",
+                &bin_path,
+                "start=",
+                "auto",
+                "DisplayName=",
+                "NeuroLoom Daemon",
+            ],
+        )?;
+        println!("Installed Windows service {}", SERVICE_NAME);
+    } else {
+        let path = unit_path(options.system)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, systemd_unit(&bin, &workdir, options.system))?;
+        systemctl(options.system, &["daemon-reload"])?;
+        systemctl(options.system, &["enable", SERVICE_NAME])?;
+        println!("Installed systemd unit {}", path.display());
+    }
+    println!("Logs: {}", workdir.join("logs").display());
+    println!("Run `nl daemon start` to start the service");
+    Ok(())
+}
+
+/// 启动或停止服务
+fn service_command(options: &Options, action: &str) -> anyhow::Result<()> {
+    if cfg!(windows) {
+        exec("sc.exe", &[action, SERVICE_NAME])?;
+    } else {
+        systemctl(options.system, &[action, SERVICE_NAME])?;
+    }
+    println!("Service {}: {} requested", SERVICE_NAME, action);
+    Ok(())
+}
+
+/// 查询服务状态与健康检查
+async fn status(options: &Options) -> anyhow::Result<()> {
+    let state = if cfg!(windows) {
+        exec("sc.exe", &["query", SERVICE_NAME]).map(|out| {
+            out.lines()
+                .find_map(|line| line.trim().strip_prefix("STATE"))
+                .and_then(|state| state.split_whitespace().last())
+                .unwrap_or("UNKNOWN")
+                .to_lowercase()
+        })
+    } else {
+        // `is-active` 对非活动服务返回非零，直接读输出
+        Command::new("systemctl")
+            .args(if options.system { vec!["is-active"] } else { vec!["--user", "is-active"] })
+            .arg(SERVICE_NAME)
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+            .map_err(Into::into)
+    };
+    match state {
+        Ok(state) if !state.is_empty() => println!("Service: {}", state),
+        Ok(_) => println!("Service: not installed"),
+        Err(e) => println!("Service: unknown ({})", e),
+    }
+
+    match health(&options.addr).await {
+        Ok(body) => println!("Health: {}", body),
+        Err(e) => println!("Health: unreachable at {} ({})", options.addr, e),
+    }
+    Ok(())
+}
+
+/// 请求控制面 `/health`，返回响应正文
+async fn health(addr: &str) -> anyhow::Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let request = format!(
+        "GET /health HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        addr
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .unwrap_or(0);
+    if status != 200 {
+        anyhow::bail!("daemon returned HTTP {}", status);
+    }
+    Ok(body.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directives(unit: &str) -> Vec<&str> {
+        unit.lines().filter(|line| line.contains('=')).collect()
+    }
+
+    #[test]
+    fn test_systemd_unit_for_user_and_system_scope() {
+        let bin = Path::new("/opt/neuroloom/neuroloom-daemon");
+        let workdir = Path::new("/var/lib/neuroloom");
+        for (system, target) in [(false, "default.target"), (true, "multi-user.target")] {
+            let unit = systemd_unit(bin, workdir, system);
+            let lines = directives(&unit);
+            let wanted_by = format!("WantedBy={}", target);
+            for expected in [
+                "Type=notify",
+                "Delegate=yes",
+                "ExecStart=/opt/neuroloom/neuroloom-daemon --service",
+                "WorkingDirectory=/var/lib/neuroloom",
+                "Restart=on-failure",
+                "WatchdogSec=30",
+                wanted_by.as_str(),
+            ] {
+                assert!(lines.contains(&expected), "system={} missing {}:\n{}", system, expected, unit);
+            }
+            // 各节齐全，且指令都在所属的节中
+            let sections: Vec<&str> = unit.lines().filter(|line| line.starts_with('[')).collect();
+            assert_eq!(sections, ["[Unit]", "[Service]", "[Install]"]);
+            assert!(unit.find("WantedBy=").unwrap() > unit.find("[Install]").unwrap());
+            assert!(unit.find("Delegate=").unwrap() > unit.find("[Service]").unwrap());
+        }
+
+        assert_eq!(
+            unit_path(true).unwrap(),
+            PathBuf::from("/etc/systemd/system/neuroloom-daemon.service")
+        );
+    }
+
+    #[test]
+    fn test_options_parse() {
+        let options = Options::parse(&["--system", "--addr", "127.0.0.1:9000", "--bin", "/x/d"]).unwrap();
+        assert!(options.system);
+        assert_eq!(options.addr, "127.0.0.1:9000");
+        assert_eq!(options.bin, Some(PathBuf::from("/x/d")));
+        assert!(Options::parse(&["--bin"]).is_err());
+        assert!(Options::parse(&["--unknown"]).is_err());
+    }
+}
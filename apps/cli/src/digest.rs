@@ -0,0 +1,45 @@
+//! `nl digest [daily|weekly]` - 活动摘要
+//!
+//! 向控制面请求截至当前时刻的日报 / 周报并打印 Markdown；
+//! 守护进程每天 / 每周自动生成的摘要位于工作区数据目录的 `digests/` 下。
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+const USAGE: &str = "Usage: digest [daily|weekly] [--workspace <name|path>] [--addr <host:port>]";
+
+/// 执行 `digest` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let mut period = "daily";
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut workspace = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "daily" | "weekly" => period = *arg,
+            "--addr" => addr = value()?,
+            "--workspace" => workspace = Some(value()?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            other => anyhow::bail!("unknown option: {}\n{}", other, USAGE),
+        }
+    }
+
+    let mut path = format!("/digest?period={}", period);
+    if let Some(workspace) = crate::workspace::selector(workspace.as_deref()) {
+        path.push_str(&format!("&workspace={}", crate::workspace::encode_query(&workspace)));
+    }
+    let (status, response) = crate::workspace::request(&addr, "GET", &path, None).await?;
+    if status != 200 {
+        anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to fetch digest"));
+    }
+    print!("{}", response["markdown"].as_str().unwrap_or_default());
+    Ok(())
+}
@@ -0,0 +1,273 @@
+//! `nl events tail` - 实时跟踪守护进程事件
+//!
+//! 通过控制面 WebSocket 订阅事件，断线后携带最后一个续传令牌重连，不遗漏事件。
+//!
+//! `nl events shards|rotate|export|query` - 管理事件库的月份分片：查看分片、把旧月份移出热表、
+//! 把冷分片转为 Parquet 归档，以及按月份（或直接指定归档文件）读回事件。
+
+use std::path::Path;
+use std::time::Duration;
+
+use futures::StreamExt;
+use nl_durable::{EventStore, MasterKey, ShardFormat, ShardPolicy};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+/// 默认事件库路径（与守护进程一致）
+const DEFAULT_DB: &str = "neuroloom.db";
+
+/// 订阅参数
+#[derive(Debug, Default)]
+struct TailOptions {
+    addr: Option<String>,
+    workspace: Option<String>,
+    kinds: Vec<String>,
+    entity: Option<String>,
+    correlation: Option<String>,
+}
+
+impl TailOptions {
+    fn parse(args: &[&str]) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .map(|v| v.to_string())
+                    .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+            };
+            match *arg {
+                "--addr" => options.addr = Some(value()?),
+                "--workspace" => options.workspace = Some(value()?),
+                "--kind" => options.kinds.push(value()?),
+                "--entity" => options.entity = Some(value()?),
+                "--correlation" => options.correlation = Some(value()?),
+                other => anyhow::bail!("unknown option: {}", other),
+            }
+        }
+        Ok(options)
+    }
+
+    fn url(&self, resume: Option<&str>) -> String {
+        let mut params = Vec::new();
+        if let Some(workspace) = crate::workspace::selector(self.workspace.as_deref()) {
+            params.push(format!("workspace={}", crate::workspace::encode_query(&workspace)));
+        }
+        if !self.kinds.is_empty() {
+            params.push(format!("kinds={}", self.kinds.join(",")));
+        }
+        if let Some(entity) = &self.entity {
+            params.push(format!("entity={}", entity));
+        }
+        if let Some(correlation) = &self.correlation {
+            params.push(format!("correlation={}", correlation));
+        }
+        if let Some(token) = resume {
+            params.push(format!("resume={}", token));
+        }
+        crate::workspace::push_api_key(&mut params);
+        format!(
+            "ws://{}/events?{}",
+            self.addr.as_deref().unwrap_or(DEFAULT_ADDR),
+            params.join("&")
+        )
+    }
+}
+
+/// 执行 `events` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    match args.first() {
+        Some(&"tail") => {
+            let options = TailOptions::parse(&args[1..])?;
+            tokio::select! {
+                result = tail(&options) => result,
+                _ = tokio::signal::ctrl_c() => Ok(()),
+            }
+        }
+        Some(&"shards") => shards(&args[1..]).await,
+        Some(&"rotate") => rotate(&args[1..]).await,
+        Some(&"export") => export(&args[1..]).await,
+        Some(&"query") => query(&args[1..]).await,
+        _ => {
+            println!("Usage: events tail [--kind <kind>]... [--entity <id>] [--correlation <id>] [--workspace <name|path>] [--addr <host:port>]");
+            println!("       events shards [--db <path>]");
+            println!("       events rotate [--hot-months <n>] [--db <path>]");
+            println!("       events export <YYYY-MM>|--all [--db <path>]");
+            println!("       events query <YYYY-MM>|<file.parquet> [--kind <kind>] [--entity <id>] [--db <path>]");
+            Ok(())
+        }
+    }
+}
+
+/// 分片命令的参数：位置参数与选项
+#[derive(Debug, Default)]
+struct ShardOptions {
+    target: Option<String>,
+    db: Option<String>,
+    hot_months: Option<u32>,
+    all: bool,
+    kind: Option<String>,
+    entity: Option<String>,
+}
+
+impl ShardOptions {
+    fn parse(args: &[&str]) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .map(|v| v.to_string())
+                    .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+            };
+            match *arg {
+                "--db" => options.db = Some(value()?),
+                "--hot-months" => options.hot_months = Some(value()?.parse()?),
+                "--all" => options.all = true,
+                "--kind" => options.kind = Some(value()?),
+                "--entity" => options.entity = Some(value()?),
+                other if !other.starts_with("--") && options.target.is_none() => {
+                    options.target = Some(other.to_string())
+                }
+                other => anyhow::bail!("unknown option: {}", other),
+            }
+        }
+        Ok(options)
+    }
+
+    /// 打开事件库；与守护进程一致，设置 NEUROLOOM_DB_KEY 时解密事件正文
+    async fn open(&self) -> anyhow::Result<EventStore> {
+        let db = Path::new(self.db.as_deref().unwrap_or(DEFAULT_DB));
+        let mut store = EventStore::open(db).await?;
+        let dir = db.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        if let Some(key) = MasterKey::from_env(dir)? {
+            store = store.with_cipher(key.cipher("events")?);
+        }
+        Ok(store)
+    }
+}
+
+/// 列出月份分片
+async fn shards(args: &[&str]) -> anyhow::Result<()> {
+    let store = ShardOptions::parse(args)?.open().await?;
+    let shards = store.shards()?;
+    for shard in &shards {
+        println!(
+            "{}  {:<8} {:>10} KiB  {}",
+            shard.month,
+            shard.format.as_str(),
+            shard.bytes.div_ceil(1024),
+            shard.path.display()
+        );
+    }
+    println!("{} shards, {} events in the hot table", shards.len(), store.hot_count().await?);
+    Ok(())
+}
+
+/// 把早于保留期的月份移出热表
+async fn rotate(args: &[&str]) -> anyhow::Result<()> {
+    let options = ShardOptions::parse(args)?;
+    let mut policy = ShardPolicy::default();
+    if let Some(hot_months) = options.hot_months {
+        policy.hot_months = hot_months;
+    }
+    let mut store = options.open().await?;
+    let rotated = store.rotate_shards(&policy).await?;
+    for shard in &rotated {
+        println!("Rotated {} into {}", shard.month, shard.path.display());
+    }
+    if rotated.is_empty() {
+        println!("Nothing to rotate: all events are within the last {} month(s)", policy.hot_months);
+    }
+    Ok(())
+}
+
+/// 把 SQLite 分片转为 Parquet 归档
+async fn export(args: &[&str]) -> anyhow::Result<()> {
+    let options = ShardOptions::parse(args)?;
+    let store = options.open().await?;
+    let months: Vec<String> = match (&options.target, options.all) {
+        (Some(month), false) => vec![month.clone()],
+        (None, true) => store
+            .shards()?
+            .into_iter()
+            .filter(|s| s.format == ShardFormat::Sqlite)
+            .map(|s| s.month)
+            .collect(),
+        _ => anyhow::bail!("Usage: events export <YYYY-MM>|--all [--db <path>]"),
+    };
+    for month in months {
+        let archive = store.export_shard(&month).await?;
+        println!("Exported {} to {} ({} KiB)", month, archive.path.display(), archive.bytes.div_ceil(1024));
+    }
+    Ok(())
+}
+
+/// 读回某月分片或指定归档文件中的事件
+async fn query(args: &[&str]) -> anyhow::Result<()> {
+    let options = ShardOptions::parse(args)?;
+    let Some(target) = &options.target else {
+        anyhow::bail!("Usage: events query <YYYY-MM>|<file.parquet> [--kind <kind>] [--entity <id>] [--db <path>]");
+    };
+    let store = options.open().await?;
+    let events = if target.ends_with(".parquet") {
+        store.read_archive(Path::new(target))?
+    } else {
+        store.shard_events(target).await?
+    };
+
+    let mut shown = 0;
+    for event in &events {
+        if options.kind.as_deref().is_some_and(|kind| kind != event.kind.as_str())
+            || options.entity.as_deref().is_some_and(|entity| entity != event.entity_id.to_string())
+        {
+            continue;
+        }
+        print_event(&serde_json::to_value(event)?);
+        shown += 1;
+    }
+    println!("{} of {} events shown", shown, events.len());
+    Ok(())
+}
+
+/// 持续输出事件，断线自动重连
+async fn tail(options: &TailOptions) -> anyhow::Result<()> {
+    let mut last_token: Option<String> = None;
+
+    loop {
+        let url = options.url(last_token.as_deref());
+        match connect_async(url.as_str()).await {
+            Ok((mut stream, _)) => {
+                while let Some(msg) = stream.next().await {
+                    let Ok(Message::Text(text)) = msg else {
+                        if msg.is_err() {
+                            break;
+                        }
+                        continue;
+                    };
+                    let envelope: serde_json::Value = serde_json::from_str(&text)?;
+                    if let Some(token) = envelope["token"].as_str() {
+                        last_token = Some(token.to_string());
+                    }
+                    print_event(&envelope["event"]);
+                }
+                eprintln!("Connection closed, reconnecting...");
+            }
+            Err(e) => eprintln!("Failed to connect to {}: {}", url, e),
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+fn print_event(event: &serde_json::Value) {
+    println!(
+        "{}  {:<24} entity={} correlation={}  {}",
+        event["timestamp"].as_str().unwrap_or("-"),
+        event["kind"].to_string().trim_matches('"'),
+        event["entity_id"].as_str().unwrap_or("-"),
+        event["correlation_id"].as_str().unwrap_or("-"),
+        event["payload"],
+    );
+}
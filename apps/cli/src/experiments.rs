@@ -0,0 +1,87 @@
+//! `nl experiments report [<name>]` - A/B 实验报告
+//!
+//! 从事件库汇总实验结果（`ExperimentOutcome` 事件），按变体列出样本数、通过率、得分、费用与耗时，
+//! 并给出各变体相对对照组的近似 p 值；未指定实验名时报告全部实验。
+
+use nl_durable::experiments::{experiment_names, DEFAULT_ALPHA};
+use nl_durable::{EventStore, ExperimentReport};
+
+/// 默认事件库路径（与守护进程一致）
+const DEFAULT_DB: &str = "neuroloom.db";
+
+/// 工作区内的事件库路径
+const WORKSPACE_DB: &str = ".neuroloom/neuroloom.db";
+
+const USAGE: &str =
+    "Usage: experiments report [<name>] [--alpha <p>] [--json] [--db <path> | --workspace <name|path>]";
+
+/// 执行 `experiments` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    match args.first() {
+        Some(&"report") => report(&args[1..]).await,
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+async fn report(args: &[&str]) -> anyhow::Result<()> {
+    let mut name = None;
+    let mut alpha = DEFAULT_ALPHA;
+    let mut json = false;
+    let mut db = None;
+    let mut workspace = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--alpha" => alpha = value()?.parse()?,
+            "--json" => json = true,
+            "--db" => db = Some(value()?),
+            "--workspace" => workspace = Some(value()?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            other if !other.starts_with("--") && name.is_none() => name = Some(other.to_string()),
+            other => anyhow::bail!("unknown option: {}\n{}", other, USAGE),
+        }
+    }
+
+    // 未指定 --db 时按工作区路径定位事件库
+    let db = db.unwrap_or_else(|| {
+        crate::workspace::selector(workspace.as_deref())
+            .map(|root| std::path::Path::new(&root).join(WORKSPACE_DB))
+            .filter(|path| path.exists())
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|| DEFAULT_DB.to_string())
+    });
+
+    let store = EventStore::open(&db).await?;
+    let names = match name {
+        Some(name) => vec![name],
+        None => experiment_names(&store).await?,
+    };
+    let mut reports = Vec::new();
+    for name in names {
+        reports.push(ExperimentReport::load(&store, &name).await?);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else if reports.iter().all(ExperimentReport::is_empty) {
+        println!("No experiment outcomes found");
+    } else {
+        for report in reports.iter().filter(|r| !r.is_empty()) {
+            print!("{}", report.render(alpha));
+            println!();
+        }
+        println!("* significant at alpha = {}", alpha);
+    }
+    Ok(())
+}
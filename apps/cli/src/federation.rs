@@ -0,0 +1,82 @@
+//! `nl federation peers` - 联邦对端
+//!
+//! 列出守护进程静态配置与 mDNS 发现的 HAP 对端及其能力清单；
+//! 对端握手后显示其发送的完整清单，否则显示广播中的精简清单。
+
+use nl_hap::{CapabilityManifest, Peer};
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+const USAGE: &str = "Usage: federation peers [--json] [--addr <host:port>]";
+
+/// 执行 `federation` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let mut positional = Vec::new();
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut json = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match *arg {
+            "--addr" => {
+                addr = iter
+                    .next()
+                    .map(|v| v.to_string())
+                    .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))?
+            }
+            "--json" => json = true,
+            other => positional.push(other),
+        }
+    }
+
+    match positional.as_slice() {
+        ["peers"] => {
+            let (status, response) = crate::workspace::request(&addr, "GET", "/federation/peers", None).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to list peers"));
+            }
+            if json {
+                println!("{}", serde_json::to_string_pretty(&response["peers"])?);
+                return Ok(());
+            }
+            let peers: Vec<Peer> = serde_json::from_value(response["peers"].clone())?;
+            if peers.is_empty() {
+                println!("No federation peers discovered.");
+            }
+            for peer in peers {
+                println!(
+                    "{}  {}  {:?}  agent {}  seen {}",
+                    peer.name,
+                    peer.url(),
+                    peer.source,
+                    peer.agent_id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string()),
+                    peer.last_seen.format("%Y-%m-%d %H:%M:%S"),
+                );
+                match &peer.manifest {
+                    Some(manifest) => println!("    {}", describe(manifest)),
+                    None => println!("    (capabilities unknown until handshake)"),
+                }
+            }
+        }
+        _ => println!("{}", USAGE),
+    }
+    Ok(())
+}
+
+/// 能力清单单行摘要
+fn describe(manifest: &CapabilityManifest) -> String {
+    let list = |items: &[String]| if items.is_empty() { "-".to_string() } else { items.join(", ") };
+    let mut line = format!(
+        "languages: {} | tools: {} | models: {}",
+        list(&manifest.languages),
+        list(&manifest.tools),
+        list(&manifest.models)
+    );
+    if let Some(context) = manifest.max_context {
+        line.push_str(&format!(" | context: {}", context));
+    }
+    if manifest.gpu {
+        line.push_str(" | gpu");
+    }
+    line
+}
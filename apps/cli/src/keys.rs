@@ -0,0 +1,108 @@
+//! `nl keys list|create|revoke` - 管理守护进程控制面的 API Key
+//!
+//! 登记了 API Key 后控制面要求鉴权：CLI 从环境变量 `NEUROLOOM_API_KEY` 读取 Key 并随请求发送。
+//! 角色 `read_only` 只能读取，`operator` 可执行操作，`admin` 另可管理工作区与 Key；第一个 Key 必须是 admin。
+//! `--budget` 限制累计 LLM token，`--rate` 限制每分钟请求数。Key 明文只在创建时打印一次。
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+const USAGE: &str = "Usage: keys list [--addr <host:port>]\n       keys create <name> --role read_only|operator|admin [--budget <tokens>] [--rate <requests/min>] [--addr <host:port>]\n       keys revoke <name|id> [--addr <host:port>]";
+
+/// 执行 `keys` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Some((command, rest)) = args.split_first() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut name = None;
+    let mut role = None;
+    let mut budget = None;
+    let mut rate = None;
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--addr" => addr = value()?,
+            "--role" => role = Some(value()?.replace('-', "_")),
+            "--budget" => budget = Some(value()?.parse::<u64>()?),
+            "--rate" => rate = Some(value()?.parse::<u32>()?),
+            other if other.starts_with("--") => anyhow::bail!("unknown option: {}", other),
+            other => name = Some(other.to_string()),
+        }
+    }
+
+    match *command {
+        "list" => list(&addr).await,
+        "create" => {
+            let (Some(name), Some(role)) = (name, role) else {
+                println!("{}", USAGE);
+                return Ok(());
+            };
+            let body = serde_json::json!({
+                "name": name,
+                "role": role,
+                "token_budget": budget,
+                "rate_limit_per_minute": rate,
+            });
+            let (status, response) = crate::workspace::request(&addr, "POST", "/keys", Some(&body)).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to create API key"));
+            }
+            println!("API key {} ({}) created:", name, role);
+            println!("  {}", response["secret"].as_str().unwrap_or_default());
+            println!("Store it now (e.g. export NEUROLOOM_API_KEY=...); it will not be shown again.");
+            Ok(())
+        }
+        "revoke" => {
+            let Some(name) = name else {
+                println!("{}", USAGE);
+                return Ok(());
+            };
+            let path = format!("/keys/{}", crate::workspace::encode_query(&name));
+            let (status, response) = crate::workspace::request(&addr, "DELETE", &path, None).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to revoke API key"));
+            }
+            println!("API key {} revoked", response["revoked"]["name"].as_str().unwrap_or(&name));
+            Ok(())
+        }
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+/// 打印 Key 列表
+async fn list(addr: &str) -> anyhow::Result<()> {
+    let (status, response) = crate::workspace::request(addr, "GET", "/keys", None).await?;
+    if status != 200 {
+        anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to list API keys"));
+    }
+
+    println!(
+        "{:<20} {:<10} {:>20} {:>10} {:<26} ID",
+        "NAME", "ROLE", "TOKENS USED/BUDGET", "RATE/MIN", "LAST USED"
+    );
+    for key in response["keys"].as_array().into_iter().flatten() {
+        let budget = key["token_budget"].as_u64().map_or("-".to_string(), |b| b.to_string());
+        let rate = key["rate_limit_per_minute"].as_u64().map_or("-".to_string(), |r| r.to_string());
+        println!(
+            "{:<20} {:<10} {:>20} {:>10} {:<26} {}",
+            key["name"].as_str().unwrap_or_default(),
+            key["role"].as_str().unwrap_or_default(),
+            format!("{}/{}", key["tokens_used"].as_u64().unwrap_or(0), budget),
+            rate,
+            key["last_used_at"].as_str().unwrap_or("never"),
+            key["id"].as_str().unwrap_or_default()
+        );
+    }
+    Ok(())
+}
@@ -1,5 +1,25 @@
 //! NeuroLoom CLI - 命令行交互接口
 
+mod audit;
+mod auth;
+mod daemon;
+mod digest;
+mod events;
+mod experiments;
+mod federation;
+mod keys;
+mod memory;
+mod providers;
+mod proxies;
+mod routes;
+mod schedule;
+mod sop;
+mod task;
+mod top;
+mod trace;
+mod trust;
+mod workspace;
+
 use std::io::{self, BufRead, Write};
 
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -15,6 +35,34 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // 带参数时直接执行子命令，例如 `nl events tail --kind task_completed`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        return match args[0] {
+            "events" => events::run(&args[1..]).await,
+            "trace" => trace::run(&args[1..]).await,
+            "audit" => audit::run(&args[1..]).await,
+            "auth" => auth::run(&args[1..]).await,
+            "trust" => trust::run(&args[1..]).await,
+            "federation" => federation::run(&args[1..]).await,
+            "keys" => keys::run(&args[1..]).await,
+            "task" => task::run(&args[1..]).await,
+            "routes" => routes::run(&args[1..]).await,
+            "sop" => sop::run(&args[1..]).await,
+            "schedule" => schedule::run(&args[1..]).await,
+            "daemon" => daemon::run(&args[1..]).await,
+            "digest" => digest::run(&args[1..]).await,
+            "top" => top::run(&args[1..]).await,
+            "experiments" => experiments::run(&args[1..]).await,
+            "workspace" => workspace::run(&args[1..]).await,
+            "memory" => memory::run(&args[1..]).await,
+            "providers" => providers::run(&args[1..]).await,
+            "proxies" => proxies::run(&args[1..]).await,
+            other => anyhow::bail!("unknown command: {}", other),
+        };
+    }
+
     println!("NeuroLoom CLI v0.1.0");
     println!("Type 'help' for available commands, 'quit' to exit.");
     println!();
@@ -45,6 +93,28 @@ async fn main() -> anyhow::Result<()> {
                 println!("  nodes         - List workspace nodes");
                 println!("  actors        - List active actors");
                 println!("  memory        - Show memory statistics");
+                println!("  memory import <dir> - Import Markdown/Obsidian/JSONL notes and PDF/DOCX documents into memory (resumable)");
+                println!("  memory search <query> - Search memory, e.g. tag:rust after:2024-06 near:\"token bucket\" limit:20");
+                println!("  events tail   - Stream daemon events (Ctrl+C to stop)");
+                println!("  events shards|rotate|export|query - Manage monthly event shards and Parquet archives");
+                println!("  trace <id>    - Show the causal event tree of a task");
+                println!("  top           - Live dashboard: actors, token bucket, in-flight LLM calls, recent events");
+                println!("  audit show    - Show the God Mode audit log (--since 1h)");
+                println!("  trust         - Manage trusted HAP agent keys");
+                println!("  federation peers - List HAP peers from static config and mDNS with their capabilities");
+                println!("  keys list|create|revoke - Manage control API keys (roles, token budgets, rate limits)");
+                println!("  task cancel <id> - Cancel a running task");
+                println!("  task feedback <id> --rating N [--comment <text>] - Rate a task's result");
+                println!("  routes test <task> - Show which model routing rule a task hits");
+                println!("  auth login|status|logout - Log in to LLM providers (antigravity, gemini-cli, iflow, vertex)");
+                println!("  providers transcript tail - Show recorded LLM provider requests/responses (--provider, --follow)");
+                println!("  proxies list  - Show effective black-magic proxy specs (built-in merged with the user catalog)");
+                println!("  sop watch|stats - Render live SOP DAGs or show per-workflow success statistics");
+                println!("  schedule add|list|remove - Manage cron schedules for SOP workflows or goals");
+                println!("  digest [daily|weekly] - Summarize agent activity (goals, verdicts, tokens, failures, new SOPs)");
+                println!("  experiments report [name] - Compare A/B experiment variants (score, pass rate, cost, latency)");
+                println!("  daemon <cmd>  - Install/start/stop/status the daemon as a system service");
+                println!("  workspace     - List, add, export, import or rewind daemon workspaces (select with --workspace)");
                 println!("  clear         - Clear the screen");
                 println!("  quit / exit   - Exit the CLI");
             }
@@ -60,11 +130,106 @@ async fn main() -> anyhow::Result<()> {
             "actors" => {
                 println!("Active Actors: (none)");
             }
+            "memory" if parts.len() > 1 => {
+                if let Err(e) = memory::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
             "memory" => {
                 println!("Memory Statistics:");
                 println!("  Total entries: 0");
                 println!("  Cache size: 0 bytes");
             }
+            "events" => {
+                if let Err(e) = events::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "trace" => {
+                if let Err(e) = trace::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "top" => {
+                if let Err(e) = top::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "audit" => {
+                if let Err(e) = audit::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "auth" => {
+                if let Err(e) = auth::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "trust" => {
+                if let Err(e) = trust::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "task" => {
+                if let Err(e) = task::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "routes" => {
+                if let Err(e) = routes::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "providers" => {
+                if let Err(e) = providers::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "proxies" => {
+                if let Err(e) = proxies::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "sop" => {
+                if let Err(e) = sop::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "schedule" => {
+                if let Err(e) = schedule::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "daemon" => {
+                if let Err(e) = daemon::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "federation" => {
+                if let Err(e) = federation::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "keys" => {
+                if let Err(e) = keys::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "digest" => {
+                if let Err(e) = digest::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "experiments" => {
+                if let Err(e) = experiments::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
+            "workspace" => {
+                if let Err(e) = workspace::run(&parts[1..]).await {
+                    println!("Error: {}", e);
+                }
+            }
             "clear" => {
                 print!("\x1B[2J\x1B[1;1H");
             }
@@ -0,0 +1,105 @@
+//! `nl memory import|search` - 导入笔记知识库、检索记忆
+//!
+//! `import <dir>`：守护进程递归读取目录下的 Markdown（含 Obsidian 库）与 JSONL 文件，分块写入记忆并把维基链接连成 GraphRAG 边。
+//! PDF 与 DOCX 文档提取文本后同样分块，记忆记录页码与标题，页面图片存入工作区产物库供视觉查询使用。
+//! 导入进度按文件记录在工作区数据目录的检查点中，大型语料中断后重新执行同一命令即可从断点继续。
+//!
+//! `search <查询>`：按记忆查询语言检索，例如 `nl memory search tag:rust after:2024-06 near:"token bucket" limit:20`。
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+const USAGE: &str = "Usage: memory import <dir> [--workspace <name|path>] [--addr <host:port>]\n       memory search <query> [--workspace <name|path>] [--addr <host:port>]";
+
+/// 执行 `memory` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Some((command, rest)) = args.split_first() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut workspace = None;
+    let mut terms = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--addr" => addr = value()?,
+            "--workspace" => workspace = Some(value()?),
+            other => terms.push(other),
+        }
+    }
+
+    match *command {
+        "import" if terms.len() == 1 => {
+            let path = terms[0];
+            let body = serde_json::json!({
+                "workspace": crate::workspace::selector(workspace.as_deref()),
+                "path": std::fs::canonicalize(path)?,
+            });
+            let (status, response) = crate::workspace::request(&addr, "POST", "/memory/import", Some(&body)).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("knowledge import failed"));
+            }
+            let stats = &response["stats"];
+            println!(
+                "Imported {} into workspace {}",
+                path,
+                response["workspace"].as_str().unwrap_or_default()
+            );
+            println!(
+                "  Files: {} scanned, {} imported, {} unchanged, {} failed",
+                stats["files"], stats["imported"], stats["unchanged"], stats["failed"]
+            );
+            println!("  Notes: {} ({} memory chunks)", stats["notes"], stats["chunks"]);
+            println!("  Links: {} resolved, {} unresolved", stats["links"], stats["unresolved_links"]);
+            for error in stats["errors"].as_array().into_iter().flatten() {
+                println!("  ! {}", error.as_str().unwrap_or_default());
+            }
+            Ok(())
+        }
+        "search" if !terms.is_empty() => {
+            let query: Vec<String> = terms.iter().map(|t| quote(t)).collect();
+            let mut route = format!("/memory/search?q={}", crate::workspace::encode_query(&query.join(" ")));
+            if let Some(workspace) = crate::workspace::selector(workspace.as_deref()) {
+                route.push_str(&format!("&workspace={}", crate::workspace::encode_query(&workspace)));
+            }
+            let (status, response) = crate::workspace::request(&addr, "GET", &route, None).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("memory search failed"));
+            }
+            println!("{:<10} {:>5}  {:<24} SUMMARY", "CREATED", "SCORE", "TAG");
+            for hit in response["hits"].as_array().into_iter().flatten() {
+                let created = hit["created_at"].as_str().unwrap_or_default();
+                println!(
+                    "{:<10} {:>5.2}  {:<24} {}",
+                    created.get(..10).unwrap_or(created),
+                    hit["score"].as_f64().unwrap_or_default(),
+                    hit["tag"].as_str().unwrap_or_default(),
+                    hit["summary"].as_str().unwrap_or_default()
+                );
+            }
+            Ok(())
+        }
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+/// shell 已去掉引号，值中含空白时重新加上（`near:token bucket` -> `near:"token bucket"`）
+fn quote(arg: &str) -> String {
+    if !arg.contains(char::is_whitespace) || arg.contains('"') {
+        return arg.to_string();
+    }
+    match arg.split_once(':') {
+        Some((key, value)) => format!("{}:\"{}\"", key, value),
+        None => format!("\"{}\"", arg),
+    }
+}
@@ -0,0 +1,120 @@
+//! `nl providers transcript tail` - 查看 Provider 通信记录
+//!
+//! 读取 Gateway 通信记录目录（`NEUROLOOM_LLM_TRANSCRIPTS` 或 `--dir`）中已脱敏的请求/响应，
+//! 按时间打印最近的若干条；`--follow` 持续输出新记录。
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use nl_llm_new::transcript::{self, TRANSCRIPTS_ENV};
+use nl_llm_new::TranscriptEntry;
+
+/// 默认显示条数
+const DEFAULT_LIMIT: usize = 20;
+
+/// 正文预览长度
+const PREVIEW_CHARS: usize = 200;
+
+const USAGE: &str =
+    "Usage: providers transcript tail [--provider <id>] [-n <count>] [--follow] [--dir <path>] [--full]";
+
+/// 执行 `providers` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    if args.get(..2) != Some(&["transcript", "tail"][..]) {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let mut provider = None;
+    let mut limit = DEFAULT_LIMIT;
+    let mut follow = false;
+    let mut full = false;
+    let mut dir = std::env::var(TRANSCRIPTS_ENV).ok().filter(|d| !d.is_empty());
+    let mut iter = args[2..].iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--provider" => provider = Some(value()?.to_string()),
+            "-n" | "--limit" => limit = value()?.parse()?,
+            "--follow" | "-f" => follow = true,
+            "--full" => full = true,
+            "--dir" => dir = Some(value()?.to_string()),
+            _ => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+        }
+    }
+    let Some(dir) = dir.map(PathBuf::from) else {
+        anyhow::bail!("transcripts are disabled; set {} to the transcript directory or pass --dir", TRANSCRIPTS_ENV);
+    };
+
+    let entries = transcript::tail(&dir, provider.as_deref(), limit)?;
+    if entries.is_empty() && !follow {
+        println!("No transcripts in {}", dir.display());
+    }
+    let mut last = entries.last().map(|e| e.timestamp);
+    for entry in &entries {
+        print_entry(entry, full);
+    }
+
+    if !follow {
+        return Ok(());
+    }
+    loop {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let seen = last;
+        let entries = transcript::tail(&dir, provider.as_deref(), usize::MAX)?;
+        for entry in entries.iter().filter(|e| seen.is_none_or(|seen| e.timestamp > seen)) {
+            print_entry(entry, full);
+            last = Some(entry.timestamp);
+        }
+    }
+}
+
+fn print_entry(entry: &TranscriptEntry, full: bool) {
+    let outcome = match (entry.status, &entry.error) {
+        (Some(status), _) => status.to_string(),
+        (None, Some(_)) => "ERR".to_string(),
+        (None, None) => "-".to_string(),
+    };
+    println!(
+        "{} {:<12} {} {} {} ({}ms{})",
+        entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        entry.provider,
+        entry.method,
+        entry.url,
+        outcome,
+        entry.duration_ms,
+        if entry.stream { ", stream" } else { "" }
+    );
+    if full {
+        for (name, value) in &entry.request_headers {
+            println!("  > {}: {}", name, value);
+        }
+    }
+    println!("  request:  {}", preview(&entry.request_body.to_string(), full));
+    if full {
+        for (name, value) in &entry.response_headers {
+            println!("  < {}: {}", name, value);
+        }
+    }
+    if let Some(body) = &entry.response_body {
+        println!("  response: {}", preview(body, full));
+    }
+    if let Some(error) = &entry.error {
+        println!("  error:    {}", error);
+    }
+}
+
+fn preview(text: &str, full: bool) -> String {
+    if full || text.chars().count() <= PREVIEW_CHARS {
+        return text.to_string();
+    }
+    let head: String = text.chars().take(PREVIEW_CHARS).collect();
+    format!("{}...", head)
+}
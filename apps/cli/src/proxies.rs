@@ -0,0 +1,83 @@
+//! `nl proxies list` - 查看生效的黑魔法代理规格
+//!
+//! 内置规格合并用户目录（`NEUROLOOM_PROXY_CATALOG`，默认 `<配置目录>/neuroloom/proxies`，或 `--catalog` 指定的目录 / 文件）
+//! 中的 JSON 规格后打印：来源为 `builtin` 或覆盖 / 新增它的文件。规格无效时报错并指出文件。
+
+use std::path::PathBuf;
+
+use nl_llm_new::black_magic_proxy::{BlackMagicProxyCatalog, CatalogEntry, ProxyExposureKind};
+
+const USAGE: &str = "Usage: proxies list [--catalog <dir|file>] [--verbose]";
+
+/// 执行 `proxies` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    if args.first() != Some(&"list") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let mut catalog_path = None;
+    let mut verbose = false;
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match *arg {
+            "--catalog" => {
+                let path = iter.next().ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))?;
+                catalog_path = Some(PathBuf::from(path));
+            }
+            "--verbose" | "-v" => verbose = true,
+            _ => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+        }
+    }
+
+    let catalog = match &catalog_path {
+        Some(path) => BlackMagicProxyCatalog::load_from(path)?,
+        None => BlackMagicProxyCatalog::load()?,
+    };
+    if let Some(dir) = catalog_path.or_else(BlackMagicProxyCatalog::default_dir) {
+        println!("Catalog: {}", dir.display());
+    }
+
+    println!("{:<18} {:<44} {:<24} SOURCE", "TARGET", "BASE URL", "EXPOSURES");
+    for entry in catalog.entries() {
+        print_entry(entry, verbose);
+    }
+    Ok(())
+}
+
+fn print_entry(entry: &CatalogEntry, verbose: bool) {
+    let spec = &entry.spec;
+    let exposures: Vec<&str> = spec.exposures.iter().map(|e| kind_name(e.kind)).collect();
+    let source = entry
+        .source
+        .as_ref()
+        .map_or("builtin".to_string(), |path| path.display().to_string());
+    let base_url = if spec.default_base_url.is_empty() { "-" } else { &spec.default_base_url };
+    println!("{:<18} {:<44} {:<24} {}", spec.target, base_url, exposures.join(","), source);
+    if !verbose {
+        return;
+    }
+    for exposure in &spec.exposures {
+        let endpoint = match exposure.kind {
+            ProxyExposureKind::Cli => {
+                let command = exposure.cli_command.as_deref().unwrap_or_default();
+                format!("{} {}", command, exposure.cli_args.join(" "))
+            }
+            _ => format!("{} {}", exposure.method, exposure.path),
+        };
+        let auth = exposure.auth_header.as_deref().map_or(String::new(), |h| format!(" [{}]", h));
+        println!("    {:<10} {}{}  {}", kind_name(exposure.kind), endpoint.trim(), auth, exposure.notes);
+    }
+}
+
+fn kind_name(kind: ProxyExposureKind) -> &'static str {
+    match kind {
+        ProxyExposureKind::Api => "api",
+        ProxyExposureKind::Auth => "auth",
+        ProxyExposureKind::WebSocket => "ws",
+        ProxyExposureKind::Cli => "cli",
+    }
+}
@@ -0,0 +1,84 @@
+//! `nl routes test "<task>"` - 调试模型路由规则
+//!
+//! 加载路由规则文件，按给定任务文本构造请求特征，逐条打印规则的命中情况与最终路由目标。
+
+use nl_llm_new::scheduler::Priority;
+use nl_llm_new::{PrimitiveRequest, RouteContext, RoutingRules};
+
+/// 默认规则文件
+const DEFAULT_RULES: &str = "routing_rules.json";
+
+const USAGE: &str =
+    "Usage: routes test \"<task>\" [--type <task-type>] [--priority high|normal|low] [--hour <0-23>] [--rules <path>]";
+
+/// 执行 `routes` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    if args.first() != Some(&"test") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let mut task = Vec::new();
+    let mut task_type = None;
+    let mut priority = Priority::Normal;
+    let mut hour = None;
+    let mut rules_path =
+        std::env::var("NEUROLOOM_ROUTING_RULES").unwrap_or_else(|_| DEFAULT_RULES.to_string());
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--type" => task_type = Some(value()?.to_string()),
+            "--priority" => {
+                priority = serde_json::from_value(serde_json::Value::String(value()?.to_lowercase()))
+                    .map_err(|_| anyhow::anyhow!("priority must be high, normal or low"))?
+            }
+            "--hour" => hour = Some(value()?.parse::<u32>()?),
+            "--rules" => rules_path = value()?.to_string(),
+            word => task.push(word),
+        }
+    }
+    if task.is_empty() {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let rules = RoutingRules::load(&rules_path)
+        .map_err(|e| anyhow::anyhow!("failed to load routing rules {}: {}", rules_path, e))?;
+
+    let mut request = PrimitiveRequest::single_user_message(task.join(" "));
+    if let Some(task_type) = task_type {
+        request.metadata = request.metadata.with_task_type(task_type);
+    }
+    let mut ctx = RouteContext::from_request(&request, priority);
+    if let Some(hour) = hour {
+        ctx.hour = hour;
+    }
+
+    println!(
+        "Task type: {}  Prompt: {} chars  Priority: {:?}  Hour: {}",
+        ctx.task_type.as_deref().unwrap_or("-"),
+        ctx.prompt_chars,
+        ctx.priority,
+        ctx.hour
+    );
+    for evaluation in rules.explain(&ctx) {
+        match evaluation.mismatch {
+            Some(reason) => println!("  ✗ {:<20} {}", evaluation.rule, reason),
+            None => println!("  ✓ {:<20} matched", evaluation.rule),
+        }
+    }
+    match rules.resolve(&ctx) {
+        Some(target) => println!(
+            "Route: {} / {}",
+            target.provider,
+            target.model.as_deref().unwrap_or("(request model)")
+        ),
+        None => println!("Route: no rule matched and no default; provider order applies"),
+    }
+    Ok(())
+}
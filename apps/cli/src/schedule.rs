@@ -0,0 +1,131 @@
+//! `nl schedule add|list|remove` - 管理守护进程的定时调度
+//!
+//! 调度把 cron 表达式（`分 时 日 月 周` 或 `@nightly` 等别名，按守护进程本地时间）绑定到
+//! SOP 工作流（`--workflow`）或编排目标（`--goal`）；`--catch-up skip|once|all`（默认 `once`）
+//! 决定守护进程停机期间错过的执行如何补跑。上一次执行未结束时，新的触发会被跳过。
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+const USAGE: &str = "Usage: schedule add <name> --cron <expr> (--workflow <name> | --goal <text>) [--catch-up skip|once|all] [--workspace <name|path>] [--addr <host:port>]\n       schedule list [--workspace <name|path>] [--addr <host:port>]\n       schedule remove <name|id> [--workspace <name|path>] [--addr <host:port>]";
+
+/// 执行 `schedule` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Some((command, rest)) = args.split_first() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut workspace = None;
+    let mut cron = None;
+    let mut workflow = None;
+    let mut goal = None;
+    let mut catch_up = "once".to_string();
+    let mut name = None;
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--addr" => addr = value()?,
+            "--workspace" => workspace = Some(value()?),
+            "--cron" => cron = Some(value()?),
+            "--workflow" => workflow = Some(value()?),
+            "--goal" => goal = Some(value()?),
+            "--catch-up" => catch_up = value()?,
+            other if other.starts_with("--") => anyhow::bail!("unknown option: {}", other),
+            other => name = Some(other.to_string()),
+        }
+    }
+    let workspace = crate::workspace::selector(workspace.as_deref());
+
+    match *command {
+        "list" => list(&addr, workspace.as_deref()).await,
+        "add" => {
+            let (Some(name), Some(cron)) = (name, cron) else {
+                println!("{}", USAGE);
+                return Ok(());
+            };
+            if workflow.is_some() == goal.is_some() {
+                anyhow::bail!("specify exactly one of --workflow or --goal");
+            }
+            cron.parse::<nl_durable::CronExpr>()?;
+            catch_up.parse::<nl_durable::CatchUpPolicy>()?;
+            let body = serde_json::json!({
+                "workspace": workspace,
+                "name": name,
+                "cron": cron,
+                "workflow": workflow,
+                "goal": goal,
+                "catch_up": catch_up,
+            });
+            let (status, response) = crate::workspace::request(&addr, "POST", "/schedules", Some(&body)).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to add schedule"));
+            }
+            let schedule = &response["schedule"];
+            println!(
+                "Schedule {} added to workspace {} (next run: {})",
+                name,
+                response["workspace"].as_str().unwrap_or_default(),
+                schedule["next_run_at"].as_str().unwrap_or("never")
+            );
+            Ok(())
+        }
+        "remove" => {
+            let Some(name) = name else {
+                println!("{}", USAGE);
+                return Ok(());
+            };
+            let mut path = format!("/schedules/{}", crate::workspace::encode_query(&name));
+            if let Some(workspace) = &workspace {
+                path.push_str(&format!("?workspace={}", crate::workspace::encode_query(workspace)));
+            }
+            let (status, response) = crate::workspace::request(&addr, "DELETE", &path, None).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to remove schedule"));
+            }
+            println!("Schedule {} removed", response["removed"]["name"].as_str().unwrap_or(&name));
+            Ok(())
+        }
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+/// 打印调度列表
+async fn list(addr: &str, workspace: Option<&str>) -> anyhow::Result<()> {
+    let mut path = "/schedules".to_string();
+    if let Some(workspace) = workspace {
+        path.push_str(&format!("?workspace={}", crate::workspace::encode_query(workspace)));
+    }
+    let (status, response) = crate::workspace::request(addr, "GET", &path, None).await?;
+    if status != 200 {
+        anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to list schedules"));
+    }
+
+    println!(
+        "{:<20} {:<16} {:<8} {:<26} {:<26} TARGET",
+        "NAME", "CRON", "CATCH-UP", "NEXT RUN", "LAST RUN"
+    );
+    for schedule in response["schedules"].as_array().into_iter().flatten() {
+        let target = &schedule["target"];
+        println!(
+            "{:<20} {:<16} {:<8} {:<26} {:<26} {}: {}",
+            schedule["name"].as_str().unwrap_or_default(),
+            schedule["cron"].as_str().unwrap_or_default(),
+            schedule["catch_up"].as_str().unwrap_or_default(),
+            schedule["next_run_at"].as_str().unwrap_or("-"),
+            schedule["last_run_at"].as_str().unwrap_or("-"),
+            target["type"].as_str().unwrap_or_default(),
+            target["value"].as_str().unwrap_or_default()
+        );
+    }
+    Ok(())
+}
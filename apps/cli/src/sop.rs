@@ -0,0 +1,164 @@
+//! `nl sop watch|stats` - SOP 执行的实时视图与统计
+//!
+//! `watch` 订阅控制面的 SOP 执行事件，每次节点状态变化时重绘所有进行中的执行；
+//! `stats` 列出各工作流的成功率、平均耗时、最近失败原因与退役状态。
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use futures::StreamExt;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use uuid::Uuid;
+
+use nl_core::sop_view::{SopRunView, SOP_EVENT_KINDS};
+use nl_core::Event;
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+/// 同时保留的执行数量（超出时丢弃最早结束的执行）
+const MAX_RUNS: usize = 8;
+
+const USAGE: &str = "Usage: sop watch [--workflow <name>] [--workspace <name|path>] [--addr <host:port>]\n       sop stats [--workspace <name|path>] [--addr <host:port>]";
+
+/// 执行 `sop` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Some(command @ ("watch" | "stats")) = args.first().copied() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut workspace = None;
+    let mut workflow = None;
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--addr" => addr = value()?,
+            "--workspace" => workspace = Some(value()?),
+            "--workflow" => workflow = Some(value()?),
+            other => anyhow::bail!("unknown option: {}", other),
+        }
+    }
+
+    if command == "stats" {
+        return stats(&addr, workspace.as_deref()).await;
+    }
+
+    let mut params = vec![format!("kinds={}", SOP_EVENT_KINDS.join(","))];
+    if let Some(workspace) = crate::workspace::selector(workspace.as_deref()) {
+        params.push(format!("workspace={}", crate::workspace::encode_query(&workspace)));
+    }
+    crate::workspace::push_api_key(&mut params);
+    let url = format!("ws://{}/events?{}", addr, params.join("&"));
+
+    tokio::select! {
+        result = watch(&url, workflow.as_deref()) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    }
+}
+
+/// 打印工作流执行统计
+async fn stats(addr: &str, workspace: Option<&str>) -> anyhow::Result<()> {
+    let mut path = "/sops/stats".to_string();
+    if let Some(workspace) = crate::workspace::selector(workspace) {
+        path.push_str(&format!("?workspace={}", crate::workspace::encode_query(&workspace)));
+    }
+    let (status, response) = crate::workspace::request(addr, "GET", &path, None).await?;
+    if status != 200 {
+        anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to fetch SOP stats"));
+    }
+
+    println!(
+        "{:<24} {:>6} {:>8} {:>8} {:>9}  {:<8} LAST FAILURE",
+        "WORKFLOW", "RUNS", "SUCCESS", "ROLLING", "AVG MS", "STATUS"
+    );
+    for workflow in response["workflows"].as_array().into_iter().flatten() {
+        let percent = |key: &str| format!("{:.0}%", workflow[key].as_f64().unwrap_or(0.0) * 100.0);
+        let status = if workflow["retired"].as_bool().unwrap_or(false) {
+            "retired"
+        } else {
+            "active"
+        };
+        println!(
+            "{:<24} {:>6} {:>8} {:>8} {:>9}  {:<8} {}",
+            workflow["workflow"].as_str().unwrap_or_default(),
+            workflow["runs"],
+            percent("success_rate"),
+            percent("rolling_success_rate"),
+            workflow["average_elapsed_ms"],
+            status,
+            workflow["last_failure"].as_str().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
+
+/// 持续接收事件并重绘，断线自动重连（只关心实时状态，不续传）
+async fn watch(url: &str, workflow: Option<&str>) -> anyhow::Result<()> {
+    let mut runs: BTreeMap<Uuid, SopRunView> = BTreeMap::new();
+    println!("Waiting for SOP runs...");
+
+    loop {
+        match connect_async(url).await {
+            Ok((mut stream, _)) => {
+                while let Some(msg) = stream.next().await {
+                    let Ok(Message::Text(text)) = msg else {
+                        if msg.is_err() {
+                            break;
+                        }
+                        continue;
+                    };
+                    let envelope: serde_json::Value = serde_json::from_str(&text)?;
+                    let Ok(event) = serde_json::from_value::<Event>(envelope["event"].clone()) else {
+                        continue;
+                    };
+                    if apply(&mut runs, &event, workflow) {
+                        render(&runs);
+                    }
+                }
+                eprintln!("Connection closed, reconnecting...");
+            }
+            Err(e) => eprintln!("Failed to connect to {}: {}", url, e),
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// 应用事件，视图发生变化时返回 `true`
+fn apply(runs: &mut BTreeMap<Uuid, SopRunView>, event: &Event, workflow: Option<&str>) -> bool {
+    if let Some(view) = SopRunView::from_event(event) {
+        if workflow.is_some_and(|w| w != view.workflow) {
+            return false;
+        }
+        if runs.len() >= MAX_RUNS {
+            let finished = runs.iter().find(|(_, v)| v.is_finished()).map(|(id, _)| *id);
+            if let Some(id) = finished.or_else(|| runs.keys().next().copied()) {
+                runs.remove(&id);
+            }
+        }
+        runs.insert(view.run, view);
+        return true;
+    }
+    event
+        .correlation_id
+        .and_then(|run| runs.get_mut(&run))
+        .is_some_and(|view| view.apply(event))
+}
+
+fn render(runs: &BTreeMap<Uuid, SopRunView>) {
+    // 清屏并回到左上角
+    print!("\x1b[2J\x1b[H");
+    for view in runs.values() {
+        for line in view.render() {
+            println!("{}", line);
+        }
+        println!();
+    }
+    println!("[ ] pending  [>] running  [+] completed  [x] failed    (Ctrl+C to stop)");
+}
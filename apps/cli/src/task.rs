@@ -0,0 +1,105 @@
+//! `nl task cancel <id>` - 取消守护进程中运行的任务
+//! `nl task feedback <id> --rating N [--comment "..."]` - 对任务结果评分
+//!
+//! 向控制面发送 `POST /tasks/<id>/cancel`，守护进程触发该任务的取消令牌并发布 `TaskCancelled` 事件；
+//! `POST /tasks/<id>/feedback` 把评分（1-5）与评语记为 `TaskFeedback` 事件，计入满意度统计。
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+const USAGE: &str = "Usage: task cancel <task-id> [--addr <host:port>]\n       \
+    task feedback <task-id> --rating <1-5> [--comment <text>] [--workspace <name|path>] [--addr <host:port>]";
+
+/// 执行 `task` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Some(&command @ ("cancel" | "feedback")) = args.first() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+    let mut task_id = None;
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut rating = None;
+    let mut comment = None;
+    let mut workspace = None;
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--addr" => addr = value()?,
+            "--rating" => rating = Some(value()?.parse::<u8>()?),
+            "--comment" => comment = Some(value()?),
+            "--workspace" => workspace = Some(value()?),
+            id => task_id = Some(id.parse::<uuid::Uuid>()?),
+        }
+    }
+    let Some(task_id) = task_id else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+    match command {
+        "cancel" => cancel(&addr, task_id).await,
+        _ => {
+            let Some(rating) = rating else {
+                println!("{}", USAGE);
+                return Ok(());
+            };
+            let body = serde_json::json!({ "rating": rating, "comment": comment, "workspace": workspace });
+            feedback(&addr, task_id, body).await
+        }
+    }
+}
+
+/// 发送取消请求
+async fn cancel(addr: &str, task_id: uuid::Uuid) -> anyhow::Result<()> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let request = format!(
+        "POST /tasks/{}/cancel HTTP/1.1\r\nHost: {}\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+        task_id,
+        addr,
+        crate::workspace::auth_header()
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .unwrap_or(0);
+    match status {
+        200 => println!("Task {} cancelled", task_id),
+        404 => anyhow::bail!("task {} is not running", task_id),
+        other => anyhow::bail!("daemon returned HTTP {}", other),
+    }
+    Ok(())
+}
+
+/// 发送反馈
+async fn feedback(addr: &str, task_id: uuid::Uuid, body: serde_json::Value) -> anyhow::Result<()> {
+    let path = format!("/tasks/{}/feedback", task_id);
+    let (status, response) = crate::workspace::request(addr, "POST", &path, Some(&body)).await?;
+    match status {
+        200 => {
+            println!("Recorded {}/5 for task {}", body["rating"], task_id);
+            match response["experiments"].as_u64() {
+                Some(0) | None => {}
+                Some(n) => println!("  also recorded in {} experiment(s)", n),
+            }
+        }
+        404 => anyhow::bail!("task {} not found", task_id),
+        other => anyhow::bail!(
+            "daemon returned HTTP {}: {}",
+            other,
+            response["error"].as_str().unwrap_or_default()
+        ),
+    }
+    Ok(())
+}
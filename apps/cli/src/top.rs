@@ -0,0 +1,478 @@
+//! `nl top` - 终端监控面板
+//!
+//! 订阅守护进程事件流，实时显示 Actor 列表、全局令牌桶水位、进行中的 LLM 调用及其耗时与最近事件；
+//! 记忆条目、图谱规模与运行中任务数每隔几秒从控制面 `GET /stats` 拉取。按 `q` / `Esc` 退出。
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use crossterm::event::{Event as TermEvent, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use futures::StreamExt;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, ListItem, Paragraph, Row, Table};
+use ratatui::{Frame, Terminal};
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+/// 保留的最近事件数
+const RECENT_EVENTS: usize = 200;
+
+/// 重绘间隔（刷新进行中调用的耗时）
+const TICK: Duration = Duration::from_millis(500);
+
+/// 拉取统计的间隔
+const STATS_INTERVAL: Duration = Duration::from_secs(3);
+
+const USAGE: &str = "Usage: top [--workspace <name|path>] [--addr <host:port>]";
+
+/// 执行 `top` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut workspace = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--addr" => addr = value()?,
+            "--workspace" => workspace = Some(value()?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            other => anyhow::bail!("unknown option: {}\n{}", other, USAGE),
+        }
+    }
+    let workspace = crate::workspace::selector(workspace.as_deref());
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    let result = dashboard(&mut terminal, &addr, workspace).await;
+    // 无论面板是否出错都恢复终端
+    disable_raw_mode()?;
+    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+/// 进行中的 LLM 调用
+#[derive(Debug, Clone)]
+struct InFlightCall {
+    model: String,
+    actor: String,
+    started: DateTime<Utc>,
+}
+
+/// 面板状态（由事件流驱动）
+#[derive(Debug, Default)]
+struct Dashboard {
+    workspace: Option<String>,
+    connected: bool,
+    /// Actor ID -> (状态, 最近变化时间)
+    actors: BTreeMap<String, (String, DateTime<Utc>)>,
+    /// 最近一次请求开始时的全局令牌桶水位 (可用, 容量)
+    bucket: Option<(u64, u64)>,
+    /// 调用 ID -> 调用
+    in_flight: HashMap<String, InFlightCall>,
+    completed_calls: u64,
+    failed_calls: u64,
+    total_tokens: u64,
+    recent: VecDeque<String>,
+    /// `GET /stats` 的最近结果
+    stats: Option<serde_json::Value>,
+}
+
+impl Dashboard {
+    fn apply(&mut self, event: &serde_json::Value) {
+        let kind = event["kind"].to_string().trim_matches('"').to_string();
+        let entity = event["entity_id"].as_str().unwrap_or_default().to_string();
+        let payload = &event["payload"];
+        let timestamp = event["timestamp"]
+            .as_str()
+            .and_then(|t| t.parse::<DateTime<Utc>>().ok())
+            .unwrap_or_else(Utc::now);
+        let call_id = payload["call_id"].as_str().map(String::from);
+
+        match kind.as_str() {
+            "ActorSpawned" | "ActorResumed" | "ActorSuspended" => {
+                let state = payload["state"].as_str().unwrap_or("Running").to_string();
+                self.actors.insert(entity.clone(), (state, timestamp));
+            }
+            "ActorTerminated" => {
+                self.actors.remove(&entity);
+            }
+            "LlmRequestStarted" => {
+                let bucket = &payload["bucket"];
+                if let (Some(available), Some(capacity)) = (bucket["available"].as_u64(), bucket["capacity"].as_u64()) {
+                    self.bucket = Some((available, capacity));
+                }
+                if let Some(call_id) = call_id {
+                    self.in_flight.insert(
+                        call_id,
+                        InFlightCall {
+                            model: payload["model"].as_str().unwrap_or("-").to_string(),
+                            actor: entity.clone(),
+                            started: timestamp,
+                        },
+                    );
+                }
+            }
+            "LlmResponseCompleted" => {
+                self.completed_calls += 1;
+                self.total_tokens += payload["usage"]["total_tokens"].as_u64().unwrap_or(0);
+                if let Some(call_id) = call_id {
+                    self.in_flight.remove(&call_id);
+                }
+            }
+            "LlmError" => {
+                self.failed_calls += 1;
+                if let Some(call_id) = call_id {
+                    self.in_flight.remove(&call_id);
+                }
+            }
+            _ => {}
+        }
+
+        self.recent.push_front(format!(
+            "{}  {:<24} {}  {}",
+            timestamp.format("%H:%M:%S"),
+            kind,
+            short_id(&entity),
+            summarize(payload)
+        ));
+        self.recent.truncate(RECENT_EVENTS);
+    }
+
+    /// 令牌桶水位条的比例与标签
+    fn bucket_gauge(&self) -> (f64, String) {
+        match self.bucket {
+            Some((available, capacity)) if capacity > 0 => (
+                (available as f64 / capacity as f64).clamp(0.0, 1.0),
+                format!("{}/{} tokens available", available, capacity),
+            ),
+            _ => (0.0, "no LLM requests yet".to_string()),
+        }
+    }
+}
+
+/// 面板主循环：事件到达、按键与定时器都会触发重绘
+async fn dashboard(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    addr: &str,
+    workspace: Option<String>,
+) -> anyhow::Result<()> {
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let follower = tokio::spawn(follow_events(addr.to_string(), workspace.clone(), events_tx));
+    let mut keys = EventStream::new();
+    let mut ticks = tokio::time::interval(TICK);
+    let mut stats_ticks = tokio::time::interval(STATS_INTERVAL);
+    let mut state = Dashboard {
+        workspace: workspace.clone(),
+        ..Default::default()
+    };
+
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| draw(frame, &state)) {
+            break Err(e.into());
+        }
+        tokio::select! {
+            update = events.recv() => match update {
+                Some(StreamUpdate::Event(event)) => state.apply(&event),
+                Some(StreamUpdate::Connected(connected)) => state.connected = connected,
+                None => break Ok(()),
+            },
+            key = keys.next() => match key {
+                Some(Ok(TermEvent::Key(key))) if key.kind == KeyEventKind::Press => {
+                    let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || ctrl_c {
+                        break Ok(());
+                    }
+                }
+                Some(Err(e)) => break Err(e.into()),
+                None => break Ok(()),
+                _ => {}
+            },
+            _ = stats_ticks.tick() => {
+                let mut path = "/stats".to_string();
+                if let Some(workspace) = &workspace {
+                    path.push_str(&format!("?workspace={}", crate::workspace::encode_query(workspace)));
+                }
+                state.stats = match crate::workspace::request(addr, "GET", &path, None).await {
+                    Ok((200, stats)) => Some(stats),
+                    _ => None,
+                };
+            }
+            _ = ticks.tick() => {}
+        }
+    };
+    follower.abort();
+    result
+}
+
+/// 事件流推送给面板的更新
+enum StreamUpdate {
+    Event(serde_json::Value),
+    Connected(bool),
+}
+
+/// 持续订阅事件，断线后携带续传令牌重连
+async fn follow_events(addr: String, workspace: Option<String>, updates: mpsc::UnboundedSender<StreamUpdate>) {
+    let mut last_token: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(workspace) = &workspace {
+            params.push(format!("workspace={}", crate::workspace::encode_query(workspace)));
+        }
+        if let Some(token) = &last_token {
+            params.push(format!("resume={}", token));
+        }
+        crate::workspace::push_api_key(&mut params);
+        let url = format!("ws://{}/events?{}", addr, params.join("&"));
+        if let Ok((mut stream, _)) = connect_async(url.as_str()).await {
+            if updates.send(StreamUpdate::Connected(true)).is_err() {
+                return;
+            }
+            while let Some(Ok(msg)) = stream.next().await {
+                let Message::Text(text) = msg else {
+                    continue;
+                };
+                let Ok(envelope) = serde_json::from_str::<serde_json::Value>(&text) else {
+                    continue;
+                };
+                if let Some(token) = envelope["token"].as_str() {
+                    last_token = Some(token.to_string());
+                }
+                if updates.send(StreamUpdate::Event(envelope["event"].clone())).is_err() {
+                    return;
+                }
+            }
+        }
+        if updates.send(StreamUpdate::Connected(false)).is_err() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+fn draw(frame: &mut Frame, state: &Dashboard) {
+    let [header, gauge, middle, stats, recent] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(3),
+        Constraint::Percentage(40),
+        Constraint::Length(3),
+        Constraint::Min(5),
+    ])
+    .areas(frame.area());
+
+    let status = if state.connected {
+        Span::styled("connected", Style::default().fg(Color::Green))
+    } else {
+        Span::styled("disconnected", Style::default().fg(Color::Red))
+    };
+    let title = Line::from(vec![
+        Span::raw(format!(
+            "NeuroLoom top - workspace {} - ",
+            state.workspace.as_deref().unwrap_or("default")
+        )),
+        status,
+        Span::raw(format!(
+            " - {} calls ok, {} failed, {} tokens  (q to quit)",
+            state.completed_calls, state.failed_calls, state.total_tokens
+        )),
+    ]);
+    frame.render_widget(Paragraph::new(title), header);
+
+    let (ratio, label) = state.bucket_gauge();
+    let color = if ratio < 0.2 { Color::Red } else { Color::Cyan };
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title("Token bucket"))
+            .gauge_style(Style::default().fg(color))
+            .ratio(ratio)
+            .label(label),
+        gauge,
+    );
+
+    let [actors_area, calls_area] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(middle);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let actors = state.actors.iter().map(|(id, (actor_state, since))| {
+        Row::new(vec![short_id(id), actor_state.clone(), since.format("%H:%M:%S").to_string()])
+    });
+    frame.render_widget(
+        Table::new(actors, [Constraint::Length(10), Constraint::Length(12), Constraint::Min(8)])
+            .header(Row::new(vec!["ACTOR", "STATE", "SINCE"]).style(bold))
+            .block(Block::bordered().title(format!("Actors ({})", state.actors.len()))),
+        actors_area,
+    );
+
+    let now = Utc::now();
+    let mut calls: Vec<_> = state.in_flight.iter().collect();
+    calls.sort_by_key(|(_, call)| call.started);
+    let calls = calls.into_iter().map(|(id, call)| {
+        let elapsed = (now - call.started).num_milliseconds().max(0) as f64 / 1000.0;
+        Row::new(vec![short_id(id), call.model.clone(), short_id(&call.actor), format!("{:.1}s", elapsed)])
+    });
+    frame.render_widget(
+        Table::new(
+            calls,
+            [Constraint::Length(10), Constraint::Min(16), Constraint::Length(10), Constraint::Length(8)],
+        )
+        .header(Row::new(vec!["CALL", "MODEL", "ACTOR", "ELAPSED"]).style(bold))
+        .block(Block::bordered().title(format!("In-flight LLM calls ({})", state.in_flight.len()))),
+        calls_area,
+    );
+
+    let stats_line = match &state.stats {
+        Some(stats) => format!(
+            "memory entries {}  graph {} nodes / {} edges  running tasks {}  events published {} (lagged {})",
+            stats["memory_entries"],
+            stats["graph_nodes"],
+            stats["graph_edges"],
+            stats["running_tasks"],
+            stats["events"]["published"],
+            stats["events"]["lagged"]
+        ),
+        None => "stats unavailable".to_string(),
+    };
+    frame.render_widget(
+        Paragraph::new(stats_line).block(Block::bordered().title("Memory / graph")),
+        stats,
+    );
+
+    let items: Vec<ListItem> = state.recent.iter().map(|line| ListItem::new(line.as_str())).collect();
+    frame.render_widget(List::new(items).block(Block::bordered().title("Recent events")), recent);
+}
+
+/// UUID 前 8 位
+fn short_id(id: &str) -> String {
+    id.chars().take(8).collect()
+}
+
+/// 载荷的单行摘要
+fn summarize(payload: &serde_json::Value) -> String {
+    let text = payload.to_string();
+    if text.chars().count() > 80 {
+        format!("{}…", text.chars().take(80).collect::<String>())
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(kind: &str, entity: &str, payload: serde_json::Value) -> serde_json::Value {
+        json!({
+            "kind": kind,
+            "entity_id": entity,
+            "timestamp": "2026-01-01T12:00:00Z",
+            "payload": payload,
+        })
+    }
+
+    #[test]
+    fn test_actor_list_follows_lifecycle_events() {
+        let mut state = Dashboard::default();
+        state.apply(&event("ActorSpawned", "actor-a", json!({})));
+        state.apply(&event("ActorSpawned", "actor-b", json!({ "state": "Idle" })));
+        assert_eq!(state.actors["actor-a"].0, "Running");
+        assert_eq!(state.actors["actor-b"].0, "Idle");
+
+        state.apply(&event("ActorSuspended", "actor-a", json!({ "state": "Suspended" })));
+        assert_eq!(state.actors["actor-a"].0, "Suspended");
+        state.apply(&event("ActorResumed", "actor-a", json!({ "state": "Running" })));
+        assert_eq!(state.actors["actor-a"].0, "Running");
+        assert_eq!(state.actors["actor-a"].1, "2026-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap());
+
+        state.apply(&event("ActorTerminated", "actor-b", json!({})));
+        assert_eq!(state.actors.keys().collect::<Vec<_>>(), ["actor-a"]);
+        // 未知 Actor 终止不影响列表
+        state.apply(&event("ActorTerminated", "actor-x", json!({})));
+        assert_eq!(state.actors.len(), 1);
+    }
+
+    #[test]
+    fn test_in_flight_calls_close_on_completion_or_error() {
+        let mut state = Dashboard::default();
+        for call in ["c1", "c2", "c3"] {
+            let payload = json!({ "call_id": call, "model": "gpt-4o" });
+            state.apply(&event("LlmRequestStarted", "actor-a", payload));
+        }
+        assert_eq!(state.in_flight.len(), 3);
+        assert_eq!(state.in_flight["c1"].model, "gpt-4o");
+        assert_eq!(state.in_flight["c1"].actor, "actor-a");
+
+        let usage = json!({ "call_id": "c1", "usage": { "total_tokens": 120 } });
+        state.apply(&event("LlmResponseCompleted", "actor-a", usage));
+        state.apply(&event("LlmError", "actor-a", json!({ "call_id": "c2", "error": "timeout" })));
+        assert_eq!(state.in_flight.keys().collect::<Vec<_>>(), ["c3"]);
+        assert_eq!((state.completed_calls, state.failed_calls, state.total_tokens), (1, 1, 120));
+
+        // 缺少 call_id 的完成事件只计数，不误删进行中的调用
+        state.apply(&event("LlmResponseCompleted", "actor-a", json!({ "usage": { "total_tokens": 5 } })));
+        assert_eq!(state.in_flight.len(), 1);
+        assert_eq!((state.completed_calls, state.total_tokens), (2, 125));
+        // 没有 call_id 的开始事件不进入列表
+        state.apply(&event("LlmRequestStarted", "actor-a", json!({ "model": "gpt-4o" })));
+        assert_eq!(state.in_flight.len(), 1);
+    }
+
+    #[test]
+    fn test_bucket_gauge_tracks_latest_request() {
+        let mut state = Dashboard::default();
+        assert_eq!(state.bucket_gauge(), (0.0, "no LLM requests yet".to_string()));
+
+        let started = |available: u64, capacity: u64| {
+            event(
+                "LlmRequestStarted",
+                "actor-a",
+                json!({ "call_id": "c", "bucket": { "available": available, "capacity": capacity } }),
+            )
+        };
+        state.apply(&started(750, 1000));
+        assert_eq!(state.bucket_gauge(), (0.75, "750/1000 tokens available".to_string()));
+        state.apply(&started(100, 1000));
+        assert_eq!(state.bucket_gauge().0, 0.1);
+
+        // 缺少水位的请求保留上一次的读数；超出容量与零容量都不会让比例越界
+        state.apply(&event("LlmRequestStarted", "actor-a", json!({ "call_id": "d" })));
+        assert_eq!(state.bucket, Some((100, 1000)));
+        state.apply(&started(1500, 1000));
+        assert_eq!(state.bucket_gauge().0, 1.0);
+        state.apply(&started(0, 0));
+        assert_eq!(state.bucket_gauge().0, 0.0);
+    }
+
+    #[test]
+    fn test_recent_events_are_newest_first_and_bounded() {
+        let mut state = Dashboard::default();
+        for i in 0..RECENT_EVENTS + 10 {
+            state.apply(&event("GoalCreated", &format!("{:08}-goal", i), json!({ "title": "x".repeat(200) })));
+        }
+        assert_eq!(state.recent.len(), RECENT_EVENTS);
+        let newest = format!("{:08}", RECENT_EVENTS + 9);
+        assert!(state.recent[0].starts_with("12:00:00  GoalCreated"), "{}", state.recent[0]);
+        assert!(state.recent[0].contains(&newest));
+        // 载荷摘要截断到 80 个字符
+        assert!(state.recent[0].ends_with('…'));
+        // 未识别的事件不改变其他状态
+        assert!(state.actors.is_empty() && state.in_flight.is_empty() && state.bucket.is_none());
+    }
+}
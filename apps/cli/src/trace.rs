@@ -0,0 +1,59 @@
+//! `nl trace <task-id>` - 渲染任务的因果事件树
+
+use nl_durable::{EventStore, Timeline};
+
+/// 默认事件库路径（与守护进程一致）
+const DEFAULT_DB: &str = "neuroloom.db";
+
+/// 工作区内的事件库路径
+const WORKSPACE_DB: &str = ".neuroloom/neuroloom.db";
+
+/// 执行 `trace` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let mut task_id = None;
+    let mut db = None;
+    let mut workspace = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match *arg {
+            "--db" => {
+                db = Some(
+                    iter.next()
+                        .ok_or_else(|| anyhow::anyhow!("missing value for --db"))?
+                        .to_string(),
+                );
+            }
+            "--workspace" => {
+                workspace = Some(
+                    *iter
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("missing value for --workspace"))?,
+                );
+            }
+            id => task_id = Some(id.parse::<uuid::Uuid>()?),
+        }
+    }
+    let Some(task_id) = task_id else {
+        println!("Usage: trace <task-id> [--db <path> | --workspace <path>]");
+        return Ok(());
+    };
+
+    // 未指定 --db 时按工作区路径定位事件库
+    let db = db.unwrap_or_else(|| {
+        crate::workspace::selector(workspace)
+            .map(|root| std::path::Path::new(&root).join(WORKSPACE_DB))
+            .filter(|path| path.exists())
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|| DEFAULT_DB.to_string())
+    });
+
+    let store = EventStore::open(&db).await?;
+    let timeline = Timeline::load(&store, task_id).await?;
+    if timeline.is_empty() {
+        println!("No events found for {}", task_id);
+    } else {
+        print!("{}", timeline.render());
+        println!("{} events", timeline.len());
+    }
+    Ok(())
+}
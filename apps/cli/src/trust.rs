@@ -0,0 +1,82 @@
+//! `nl trust` - 管理 HAP 信任库与本地 Agent 身份
+//!
+//! 身份密钥与信任库位于守护进程的数据目录（`--data-dir`，默认当前目录，与守护进程的默认工作目录一致）。
+//! 运行中的守护进程在校验下一条 HAP 消息前发现信任库文件的变化并重新加载，增删无需重启。
+
+use std::path::PathBuf;
+
+use nl_hap::{AgentIdentity, TrustStore, IDENTITY_FILE, TRUST_FILE};
+
+const USAGE: &str = "Usage:
+  trust list [--data-dir <dir>] [--store <path>]
+  trust add <agent-id> <public-key> [--label <text>] [--data-dir <dir>] [--store <path>]
+  trust remove <agent-id> [--data-dir <dir>] [--store <path>]
+  trust identity [--data-dir <dir>] [--identity <path>]";
+
+/// 执行 `trust` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let mut positional = Vec::new();
+    let mut data_dir = PathBuf::from(".");
+    let mut store_path = None;
+    let mut identity_path = None;
+    let mut label = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--data-dir" => data_dir = PathBuf::from(value()?),
+            "--store" => store_path = Some(PathBuf::from(value()?)),
+            "--identity" => identity_path = Some(PathBuf::from(value()?)),
+            "--label" => label = Some(value()?.to_string()),
+            other => positional.push(other),
+        }
+    }
+    let store_path = store_path.unwrap_or_else(|| data_dir.join(TRUST_FILE));
+    let identity_path = identity_path.unwrap_or_else(|| data_dir.join(IDENTITY_FILE));
+
+    match positional.as_slice() {
+        ["list"] => {
+            let store = TrustStore::load(&store_path)?;
+            let agents = store.list();
+            if agents.is_empty() {
+                println!("No trusted agents.");
+            }
+            for agent in agents {
+                println!(
+                    "{}  {}  {}  {}",
+                    agent.agent_id,
+                    agent.public_key,
+                    agent.added_at.format("%Y-%m-%d %H:%M:%S"),
+                    agent.label.as_deref().unwrap_or("-"),
+                );
+            }
+        }
+        ["add", agent_id, public_key] => {
+            let mut store = TrustStore::load(&store_path)?;
+            store.add(agent_id.parse()?, public_key, label)?;
+            store.save()?;
+            println!("Trusted agent {}", agent_id);
+        }
+        ["remove", agent_id] => {
+            let mut store = TrustStore::load(&store_path)?;
+            match store.remove(&agent_id.parse()?) {
+                Some(_) => {
+                    store.save()?;
+                    println!("Removed agent {}", agent_id);
+                }
+                None => println!("Agent {} is not trusted", agent_id),
+            }
+        }
+        ["identity"] => {
+            let identity = AgentIdentity::load_or_generate(&identity_path)?;
+            println!("Agent ID:   {}", identity.agent_id());
+            println!("Public key: {}", identity.public_key());
+        }
+        _ => println!("{}", USAGE),
+    }
+    Ok(())
+}
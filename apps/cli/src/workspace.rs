@@ -0,0 +1,327 @@
+//! `nl workspace list|add|export|import|rewind` - 管理守护进程托管的工作区
+//!
+//! 工作区可按名称或路径选择：命令行 `--workspace <name|path>` 优先，其次环境变量
+//! `NEUROLOOM_WORKSPACE`；都未设置时使用守护进程的默认工作区。
+//!
+//! `export <file>` 把事件、记忆、图谱与 SOP 写成压缩包；`import <file>` 按
+//! `--policy skip|overwrite|merge`（默认 `skip`）处理已存在的条目。
+//!
+//! `rewind --to <event-id|timestamp>` 重放事件，显示工作区在该时刻的实体状态（只读）；
+//! 加 `--restore` 时预演恢复所需的补偿事件，再加 `--yes` 才写入。恢复只追加事件，不删除历史。
+
+use std::path::Path;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// 默认控制面地址
+const DEFAULT_ADDR: &str = "127.0.0.1:8766";
+
+/// 选择工作区的环境变量
+const WORKSPACE_ENV: &str = "NEUROLOOM_WORKSPACE";
+
+/// 控制面 API Key 的环境变量
+const API_KEY_ENV: &str = "NEUROLOOM_API_KEY";
+
+const USAGE: &str = "Usage: workspace list [--addr <host:port>]\n       workspace add <path> [--name <name>] [--addr <host:port>]\n       workspace export <file> [--workspace <name|path>] [--addr <host:port>]\n       workspace import <file> [--policy skip|overwrite|merge] [--workspace <name|path>] [--addr <host:port>]\n       workspace rewind --to <event-id|timestamp> [--entity <id>] [--restore [--yes]] [--workspace <name|path>] [--addr <host:port>]";
+
+/// 解析工作区选择：已存在的路径转为绝对路径（守护进程与 CLI 工作目录不同），否则视为名称
+pub fn selector(explicit: Option<&str>) -> Option<String> {
+    let value = match explicit {
+        Some(value) => value.to_string(),
+        None => std::env::var(WORKSPACE_ENV).ok().filter(|v| !v.is_empty())?,
+    };
+    Some(match std::fs::canonicalize(Path::new(&value)) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_) => value,
+    })
+}
+
+/// 控制面 API Key（`NEUROLOOM_API_KEY`）
+pub fn api_key() -> Option<String> {
+    std::env::var(API_KEY_ENV).ok().filter(|v| !v.is_empty())
+}
+
+/// 携带 API Key 的请求头行（未设置时为空）
+pub fn auth_header() -> String {
+    api_key()
+        .map(|key| format!("Authorization: Bearer {}\r\n", key))
+        .unwrap_or_default()
+}
+
+/// WebSocket 订阅携带 API Key 的查询参数
+pub fn push_api_key(params: &mut Vec<String>) {
+    if let Some(key) = api_key() {
+        params.push(format!("api_key={}", encode_query(&key)));
+    }
+}
+
+/// 对查询参数值做百分号编码
+pub fn encode_query(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// 执行 `workspace` 子命令
+pub async fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Some((command, rest)) = args.split_first() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut name = None;
+    let mut workspace = None;
+    let mut policy = "skip".to_string();
+    let mut path = None;
+    let mut to = None;
+    let mut entity = None;
+    let mut restore = false;
+    let mut confirm = false;
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("missing value for {}", arg))
+        };
+        match *arg {
+            "--addr" => addr = value()?.to_string(),
+            "--name" => name = Some(value()?.to_string()),
+            "--workspace" => workspace = Some(value()?.to_string()),
+            "--policy" => policy = value()?.to_string(),
+            "--to" => to = Some(value()?.to_string()),
+            "--entity" => entity = Some(value()?.parse::<uuid::Uuid>()?),
+            "--restore" => restore = true,
+            "--yes" => confirm = true,
+            other => path = Some(other),
+        }
+    }
+
+    match *command {
+        "list" => list(&addr).await,
+        "add" => {
+            let Some(path) = path else {
+                println!("{}", USAGE);
+                return Ok(());
+            };
+            let path = std::fs::canonicalize(path)?;
+            let body = serde_json::json!({ "name": name, "path": path });
+            let (status, response) = request(&addr, "POST", "/workspaces", Some(&body)).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("failed to add workspace"));
+            }
+            println!(
+                "Workspace {} added at {}",
+                response["name"].as_str().unwrap_or_default(),
+                response["path"].as_str().unwrap_or_default()
+            );
+            Ok(())
+        }
+        "export" | "import" => {
+            let Some(path) = path else {
+                println!("{}", USAGE);
+                return Ok(());
+            };
+            policy.parse::<nl_durable::ConflictPolicy>()?;
+            let body = serde_json::json!({
+                "workspace": selector(workspace.as_deref()),
+                "path": absolute(path)?,
+                "policy": policy,
+            });
+            let route = format!("/workspaces/{}", command);
+            let (status, response) = request(&addr, "POST", &route, Some(&body)).await?;
+            if status != 200 {
+                anyhow::bail!("{}", response["error"].as_str().unwrap_or("bundle transfer failed"));
+            }
+            if *command == "export" {
+                println!(
+                    "Exported workspace {} ({} events) to {}",
+                    response["workspace"].as_str().unwrap_or_default(),
+                    response["events"],
+                    path
+                );
+            } else {
+                println!(
+                    "Imported {} into workspace {} (policy: {})",
+                    response["source"].as_str().unwrap_or_default(),
+                    response["workspace"].as_str().unwrap_or_default(),
+                    policy
+                );
+                println!("{:<10} {:>6} {:>8} {:>11} {:>7}", "KIND", "ADDED", "SKIPPED", "OVERWRITTEN", "MERGED");
+                for kind in ["events", "memories", "graph", "sops"] {
+                    let stats = &response["report"][kind];
+                    println!(
+                        "{:<10} {:>6} {:>8} {:>11} {:>7}",
+                        kind, stats["added"], stats["skipped"], stats["overwritten"], stats["merged"]
+                    );
+                }
+            }
+            Ok(())
+        }
+        "rewind" => {
+            let Some(to) = to else {
+                println!("{}", USAGE);
+                return Ok(());
+            };
+            to.parse::<nl_durable::RewindPoint>()?;
+            let workspace = selector(workspace.as_deref());
+            if restore {
+                restore_to(&addr, workspace, &to, entity, confirm).await
+            } else {
+                rewind(&addr, workspace, &to, entity).await
+            }
+        }
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+/// 打印工作区在回溯点的只读视图
+async fn rewind(addr: &str, workspace: Option<String>, to: &str, entity: Option<uuid::Uuid>) -> anyhow::Result<()> {
+    let mut params = vec![format!("to={}", encode_query(to))];
+    if let Some(workspace) = workspace {
+        params.push(format!("workspace={}", encode_query(&workspace)));
+    }
+    if let Some(entity) = entity {
+        params.push(format!("entity={}", entity));
+    }
+    let route = format!("/workspaces/rewind?{}", params.join("&"));
+    let (status, response) = request(addr, "GET", &route, None).await?;
+    if status != 200 {
+        anyhow::bail!("{}", response["error"].as_str().unwrap_or("rewind failed"));
+    }
+    let view = &response["view"];
+    println!(
+        "Workspace {} as of {} ({} events, read-only)",
+        response["workspace"].as_str().unwrap_or_default(),
+        to,
+        view["events"]
+    );
+    let entities = view["entities"].as_object().into_iter().flat_map(|e| e.values());
+    if entity.is_some() {
+        for state in entities {
+            println!("{}", serde_json::to_string_pretty(state)?);
+        }
+        return Ok(());
+    }
+    println!("{:<36} {:>6} {:<28} {:<26} STATUS", "ENTITY", "EVENTS", "LAST KIND", "UPDATED");
+    for state in entities {
+        println!(
+            "{:<36} {:>6} {:<28} {:<26} {}",
+            state["entity_id"].as_str().unwrap_or_default(),
+            state["events"],
+            state["last_kind"].as_str().unwrap_or_default(),
+            state["updated_at"].as_str().unwrap_or_default(),
+            if state["deleted"].as_bool().unwrap_or(false) { "deleted" } else { "live" }
+        );
+    }
+    Ok(())
+}
+
+/// 恢复到回溯点：未加 `--yes` 时只预演
+async fn restore_to(
+    addr: &str,
+    workspace: Option<String>,
+    to: &str,
+    entity: Option<uuid::Uuid>,
+    confirm: bool,
+) -> anyhow::Result<()> {
+    let body = serde_json::json!({ "workspace": workspace, "to": to, "entity": entity, "confirm": confirm });
+    let (status, response) = request(addr, "POST", "/workspaces/restore", Some(&body)).await?;
+    if status != 200 {
+        anyhow::bail!("{}", response["error"].as_str().unwrap_or("restore failed"));
+    }
+    let events = response["events"].as_array().cloned().unwrap_or_default();
+    if events.is_empty() {
+        println!("Nothing to restore: state already matches {}", to);
+        return Ok(());
+    }
+    for event in &events {
+        let action = if event["payload"]["deleted"].as_bool().unwrap_or(false) { "delete" } else { "restore" };
+        println!("  {:<8} {}", action, event["entity_id"].as_str().unwrap_or_default());
+    }
+    if confirm {
+        println!("Appended {} compensating event(s); history is preserved", events.len());
+    } else {
+        println!("Dry run: {} compensating event(s) would be appended; re-run with --yes to apply", events.len());
+    }
+    Ok(())
+}
+
+/// 包文件路径转为绝对路径（守护进程工作目录与 CLI 不同；导出目标文件可能尚不存在）
+fn absolute(path: &str) -> anyhow::Result<std::path::PathBuf> {
+    let path = Path::new(path);
+    Ok(if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    })
+}
+
+async fn list(addr: &str) -> anyhow::Result<()> {
+    let (status, response) = request(addr, "GET", "/workspaces", None).await?;
+    if status != 200 {
+        anyhow::bail!("daemon returned HTTP {}", status);
+    }
+    let selected = selector(None);
+    println!("{:<2}{:<20} {:>9} {:>8}  PATH", "", "NAME", "WORKFLOWS", "RUNNING");
+    for workspace in response.as_array().into_iter().flatten() {
+        let name = workspace["name"].as_str().unwrap_or_default();
+        let path = workspace["path"].as_str().unwrap_or_default();
+        let marker = match &selected {
+            Some(s) if s == name || s == path => "*",
+            _ => "",
+        };
+        println!(
+            "{:<2}{:<20} {:>9} {:>8}  {}",
+            marker,
+            name,
+            workspace["sop_workflows"].to_string(),
+            workspace["running_tasks"].to_string(),
+            path
+        );
+    }
+    Ok(())
+}
+
+/// 向控制面发送 JSON 请求
+pub async fn request(
+    addr: &str,
+    method: &str,
+    path: &str,
+    body: Option<&serde_json::Value>,
+) -> anyhow::Result<(u16, serde_json::Value)> {
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let mut stream = TcpStream::connect(addr).await?;
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        addr,
+        auth_header(),
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .unwrap_or(0);
+    let body = serde_json::from_str(body.trim()).unwrap_or(serde_json::Value::Null);
+    Ok((status, body))
+}
@@ -0,0 +1,382 @@
+//! 控制面 API Key
+//!
+//! 多人共用一个守护进程时按 Key 区分调用方：
+//! - 角色：`read_only` 只能读取（GET），`operator` 还可以执行操作（取消任务、导入记忆、管理调度、MCP 调用等），
+//!   `admin` 另可登记 / 导出 / 导入工作区与管理 API Key
+//! - 每个 Key 可设置 token 预算（累计 LLM token，用尽后拒绝请求）与每分钟请求数上限
+//! - 以 Key ID 作为 Actor 发起的 LLM 调用（`LlmResponseCompleted` 事件的实体）计入该 Key 的用量，
+//!   用量先累计在内存中，每隔 `USAGE_FLUSH_INTERVAL` 与退出时写回登记文件
+//! - Key 明文只在创建时返回一次，登记文件只保存 SHA-256 摘要
+//! - 没有任何 Key 时不做鉴权（单人本机使用），创建的第一个 Key 必须是 admin
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use nl_core::event::EventKind;
+use nl_durable::EventBus;
+
+/// API Key 登记文件（位于守护进程工作目录）
+pub const API_KEYS_FILE: &str = "api_keys.json";
+
+/// Key 明文前缀
+const KEY_PREFIX: &str = "nlk_";
+
+/// 请求数限流窗口
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 用量写回登记文件的间隔
+const USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 计量去重的最近事件数（工作区落库后会以同一 ID 再次发布用量事件）
+const METER_DEDUP_WINDOW: usize = 1024;
+
+/// Key 角色（按权限从低到高排列）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyRole {
+    ReadOnly,
+    Operator,
+    Admin,
+}
+
+/// 一个 API Key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub role: ApiKeyRole,
+    /// Key 明文的 SHA-256（十六进制）
+    key_hash: String,
+    /// 累计 LLM token 预算（`None` 不限制）
+    #[serde(default)]
+    pub token_budget: Option<u64>,
+    /// 已用 token
+    #[serde(default)]
+    pub tokens_used: u64,
+    /// 每分钟请求数上限（`None` 不限制）
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<u32>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// 对外展示的信息（不含摘要）
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "role": self.role,
+            "token_budget": self.token_budget,
+            "tokens_used": self.tokens_used,
+            "rate_limit_per_minute": self.rate_limit_per_minute,
+            "created_at": self.created_at,
+            "last_used_at": self.last_used_at,
+        })
+    }
+
+    fn budget_exhausted(&self) -> bool {
+        self.token_budget.is_some_and(|budget| self.tokens_used >= budget)
+    }
+}
+
+/// 创建 Key 的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub role: ApiKeyRole,
+    #[serde(default)]
+    pub token_budget: Option<u64>,
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<u32>,
+}
+
+/// 鉴权失败原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// 缺少或未知的 Key
+    Unauthorized,
+    /// 角色权限不足
+    Forbidden { role: ApiKeyRole, required: ApiKeyRole },
+    /// 超过每分钟请求数上限
+    RateLimited { retry_after: Duration },
+    /// token 预算已用尽
+    BudgetExhausted { budget: u64 },
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::Unauthorized => f.write_str("missing or invalid API key"),
+            AuthError::Forbidden { role, required } => {
+                write!(f, "API key role {:?} cannot perform {:?} requests", role, required)
+            }
+            AuthError::RateLimited { retry_after } => {
+                write!(f, "rate limit exceeded, retry in {}s", retry_after.as_secs().max(1))
+            }
+            AuthError::BudgetExhausted { budget } => write!(f, "token budget of {} exhausted", budget),
+        }
+    }
+}
+
+/// API Key 登记表
+pub struct ApiKeyStore {
+    /// 登记文件（`None` 时只保存在内存中）
+    path: Option<PathBuf>,
+    keys: RwLock<Vec<ApiKey>>,
+    /// 各 Key 最近一个窗口内的请求时间
+    requests: Mutex<HashMap<Uuid, VecDeque<Instant>>>,
+    /// 内存中的用量尚未写回登记文件
+    usage_dirty: AtomicBool,
+}
+
+impl ApiKeyStore {
+    /// 只保存在内存中的空登记表
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            keys: RwLock::new(Vec::new()),
+            requests: Mutex::new(HashMap::new()),
+            usage_dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// 加载登记文件（不存在时为空）
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let keys = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            keys: RwLock::new(keys),
+            requests: Mutex::new(HashMap::new()),
+            usage_dirty: AtomicBool::new(false),
+        })
+    }
+
+    fn save(&self, keys: &[ApiKey]) -> anyhow::Result<()> {
+        self.usage_dirty.store(false, Ordering::Release);
+        if let Some(path) = &self.path {
+            std::fs::write(path, serde_json::to_string_pretty(keys)?)?;
+        }
+        Ok(())
+    }
+
+    /// 是否启用鉴权（至少登记了一个 Key）
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.read().unwrap().is_empty()
+    }
+
+    /// 全部 Key
+    pub fn list(&self) -> Vec<ApiKey> {
+        self.keys.read().unwrap().clone()
+    }
+
+    /// 创建 Key，返回登记信息与明文（明文不会再次出现）
+    pub fn create(&self, request: NewApiKey) -> anyhow::Result<(ApiKey, String)> {
+        let name = request.name.trim().to_string();
+        if name.is_empty() {
+            anyhow::bail!("API key name must not be empty");
+        }
+        let mut keys = self.keys.write().unwrap();
+        if keys.is_empty() && request.role != ApiKeyRole::Admin {
+            anyhow::bail!("the first API key must have the admin role");
+        }
+        if keys.iter().any(|k| k.name == name) {
+            anyhow::bail!("API key {} already exists", name);
+        }
+
+        let mut secret = [0u8; 24];
+        SystemRandom::new()
+            .fill(&mut secret)
+            .map_err(|_| anyhow::anyhow!("failed to generate API key"))?;
+        let secret = format!("{}{}", KEY_PREFIX, hex(&secret));
+        let key = ApiKey {
+            id: Uuid::new_v4(),
+            name,
+            role: request.role,
+            key_hash: hash(&secret),
+            token_budget: request.token_budget,
+            tokens_used: 0,
+            rate_limit_per_minute: request.rate_limit_per_minute,
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+        keys.push(key.clone());
+        self.save(&keys)?;
+        Ok((key, secret))
+    }
+
+    /// 按 ID 或名称吊销 Key；其他 Key 仍在时不能吊销最后一个 admin
+    pub fn revoke(&self, selector: &str) -> anyhow::Result<Option<ApiKey>> {
+        let mut keys = self.keys.write().unwrap();
+        let Some(index) = keys.iter().position(|k| k.id.to_string() == selector || k.name == selector) else {
+            return Ok(None);
+        };
+        let admins = keys.iter().filter(|k| k.role == ApiKeyRole::Admin).count();
+        if keys[index].role == ApiKeyRole::Admin && admins == 1 && keys.len() > 1 {
+            anyhow::bail!("cannot revoke the last admin key while other keys exist");
+        }
+        let removed = keys.remove(index);
+        self.save(&keys)?;
+        self.requests.lock().unwrap().remove(&removed.id);
+        Ok(Some(removed))
+    }
+
+    /// 校验 Key 明文：角色、请求数上限与 token 预算
+    pub fn authenticate(&self, secret: &str, required: ApiKeyRole) -> Result<ApiKey, AuthError> {
+        let digest = hash(secret);
+        let mut keys = self.keys.write().unwrap();
+        let key = keys
+            .iter_mut()
+            .find(|k| k.key_hash == digest)
+            .ok_or(AuthError::Unauthorized)?;
+        if key.role < required {
+            return Err(AuthError::Forbidden { role: key.role, required });
+        }
+        if key.budget_exhausted() {
+            return Err(AuthError::BudgetExhausted {
+                budget: key.token_budget.unwrap_or_default(),
+            });
+        }
+        if let Some(limit) = key.rate_limit_per_minute {
+            let now = Instant::now();
+            let mut requests = self.requests.lock().unwrap();
+            let window = requests.entry(key.id).or_default();
+            while window.front().is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW) {
+                window.pop_front();
+            }
+            if window.len() >= limit as usize {
+                let retry_after = window
+                    .front()
+                    .map_or(RATE_WINDOW, |t| RATE_WINDOW.saturating_sub(now.duration_since(*t)));
+                return Err(AuthError::RateLimited { retry_after });
+            }
+            window.push_back(now);
+        }
+        key.last_used_at = Some(Utc::now());
+        Ok(key.clone())
+    }
+
+    /// 计入 Key 的 token 用量（ID 不是 Key 时忽略），由 `flush_usage` 写回登记文件
+    pub fn record_tokens(&self, id: Uuid, tokens: u64) {
+        let mut keys = self.keys.write().unwrap();
+        let Some(key) = keys.iter_mut().find(|k| k.id == id) else {
+            return;
+        };
+        key.tokens_used += tokens;
+        self.usage_dirty.store(true, Ordering::Release);
+    }
+
+    /// 把累计用量写回登记文件（没有变化时不写）
+    pub fn flush_usage(&self) {
+        if !self.usage_dirty.load(Ordering::Acquire) {
+            return;
+        }
+        let keys = self.keys.read().unwrap();
+        if let Err(e) = self.save(&keys) {
+            self.usage_dirty.store(true, Ordering::Release);
+            tracing::warn!("Failed to save API key usage: {}", e);
+        }
+    }
+
+    /// 定期写回用量
+    pub fn spawn_usage_flusher(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(USAGE_FLUSH_INTERVAL);
+            loop {
+                ticker.tick().await;
+                store.flush_usage();
+            }
+        })
+    }
+
+    /// 订阅工作区事件总线，把以 Key ID 为 Actor 的 LLM 用量计入对应 Key
+    pub fn meter(self: &Arc<Self>, bus: &EventBus) {
+        let mut usage = bus.subscribe(EventKind::LlmResponseCompleted);
+        let store = self.clone();
+        tokio::spawn(async move {
+            let (mut seen, mut order) = (HashSet::new(), VecDeque::new());
+            while let Some(event) = usage.recv().await {
+                if !seen.insert(event.id) {
+                    continue;
+                }
+                order.push_back(event.id);
+                if order.len() > METER_DEDUP_WINDOW {
+                    seen.remove(&order.pop_front().unwrap());
+                }
+                if let Some(tokens) = event.payload["usage"]["total_tokens"].as_u64() {
+                    store.record_tokens(event.entity_id, tokens);
+                }
+            }
+        });
+    }
+}
+
+fn hash(secret: &str) -> String {
+    hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_is_written_back_only_on_flush() {
+        let path = std::env::temp_dir().join(format!("nl_api_keys_{}.json", Uuid::new_v4()));
+        let store = ApiKeyStore::load(&path).unwrap();
+        let (key, _) = store
+            .create(NewApiKey {
+                name: "root".to_string(),
+                role: ApiKeyRole::Admin,
+                token_budget: Some(1_000),
+                rate_limit_per_minute: None,
+            })
+            .unwrap();
+        let saved = || ApiKeyStore::load(&path).unwrap().list()[0].tokens_used;
+
+        // 每个响应只更新内存，不重写登记文件
+        store.record_tokens(key.id, 300);
+        store.record_tokens(key.id, 200);
+        store.record_tokens(Uuid::new_v4(), 999);
+        assert_eq!(store.list()[0].tokens_used, 500);
+        assert_eq!(saved(), 0);
+
+        store.flush_usage();
+        assert_eq!(saved(), 500);
+        // 没有新用量时不写
+        std::fs::remove_file(&path).unwrap();
+        store.flush_usage();
+        assert!(!path.exists());
+
+        // 创建 / 吊销会连同累计用量一起写回
+        store.record_tokens(key.id, 100);
+        store
+            .create(NewApiKey {
+                name: "reader".to_string(),
+                role: ApiKeyRole::ReadOnly,
+                token_budget: None,
+                rate_limit_per_minute: None,
+            })
+            .unwrap();
+        assert_eq!(saved(), 600);
+        let _ = std::fs::remove_file(&path);
+    }
+}
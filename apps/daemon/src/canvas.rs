@@ -0,0 +1,124 @@
+//! 画布增量推送
+//!
+//! 每个工作区维护一份画布投影：打开时重放最近一段时间的事件，之后持续订阅事件总线，
+//! 产生的增量广播给所有 `GET /canvas` 连接。连接建立时先发送快照；
+//! 客户端落后于广播缓冲时断开连接，由客户端重连取新快照。
+//! 客户端发送的 `node_moved` 增量更新共享布局并广播给其他连接。
+
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use tokio::sync::{broadcast, Mutex};
+
+use nl_core::canvas::{CanvasDelta, CanvasMessage, CanvasProjector, CANVAS_EVENT_KINDS};
+use nl_durable::{EventBus, EventStore};
+
+/// 打开工作区时重放的事件时间范围（小时）
+const REPLAY_HOURS: i64 = 24;
+
+/// 增量广播缓冲
+const UPDATE_CAPACITY: usize = 1024;
+
+/// 工作区画布投影与增量广播
+pub struct CanvasFeed {
+    projector: Mutex<CanvasProjector>,
+    updates: broadcast::Sender<CanvasMessage>,
+}
+
+impl CanvasFeed {
+    /// 重放最近的事件并开始跟随事件总线
+    pub async fn follow(bus: &EventBus, store: &Mutex<EventStore>) -> Arc<Self> {
+        // 先订阅再重放，重放期间产生的事件不会丢失（重复的创建事件被投影忽略）
+        let mut events = bus.subscribe_all();
+        let mut projector = CanvasProjector::new();
+        let now = chrono::Utc::now();
+        match store
+            .lock()
+            .await
+            .get_events_by_time(now - chrono::Duration::hours(REPLAY_HOURS), now)
+            .await
+        {
+            Ok(history) => {
+                for event in history.iter().filter(|e| CANVAS_EVENT_KINDS.contains(&e.kind.as_str())) {
+                    projector.project(event);
+                }
+            }
+            Err(e) => tracing::warn!("Canvas replay skipped: {}", e),
+        }
+
+        let feed = Arc::new(Self {
+            projector: Mutex::new(projector),
+            updates: broadcast::channel(UPDATE_CAPACITY).0,
+        });
+        let follower = feed.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if !CANVAS_EVENT_KINDS.contains(&event.kind.as_str()) {
+                    continue;
+                }
+                let mut projector = follower.projector.lock().await;
+                for message in projector.project(&event) {
+                    let _ = follower.updates.send(message);
+                }
+            }
+        });
+        feed
+    }
+
+    /// 当前快照与后续增量的订阅（在同一把锁内取得，两者之间不会漏掉增量）
+    pub async fn subscribe(&self) -> (CanvasMessage, broadcast::Receiver<CanvasMessage>) {
+        let projector = self.projector.lock().await;
+        (projector.state().snapshot(), self.updates.subscribe())
+    }
+
+    /// 应用客户端回传的增量（目前只接受节点移动）
+    pub async fn apply_client_delta(&self, delta: CanvasDelta) {
+        let CanvasDelta::NodeMoved { id, position } = delta else {
+            tracing::debug!("Ignoring canvas delta from client: {:?}", delta);
+            return;
+        };
+        if let Some(message) = self.projector.lock().await.move_node(id, position) {
+            let _ = self.updates.send(message);
+        }
+    }
+}
+
+/// 向一个 WebSocket 连接推送画布增量
+pub async fn stream(mut socket: WebSocket, feed: Arc<CanvasFeed>) {
+    let (snapshot, mut updates) = feed.subscribe().await;
+    if send(&mut socket, &snapshot).await.is_err() {
+        return;
+    }
+    loop {
+        tokio::select! {
+            update = updates.recv() => {
+                let message = match update {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Canvas client lagged by {} deltas, closing for resync", skipped);
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if send(&mut socket, &message).await.is_err() {
+                    break;
+                }
+            }
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<CanvasDelta>(&text) {
+                        Ok(delta) => feed.apply_client_delta(delta).await,
+                        Err(e) => tracing::debug!("Invalid canvas delta from client: {}", e),
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &CanvasMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    socket.send(Message::Text(text)).await
+}
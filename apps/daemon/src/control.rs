@@ -0,0 +1,1427 @@
+//! 守护进程控制面
+//!
+//! 通过 WebSocket 向 CLI (`nl events tail`) 与桌面端推送事件：
+//! - `GET /events?workspace=<name|path>&kinds=a,b&entity=<id>&correlation=<id>&resume=<token>`
+//!   （省略 `workspace` 时订阅默认工作区）
+//! - 每条消息为 `{"token": <事件 ID>, "event": <事件>}`
+//! - 重连时携带最后收到的 `token`，先补发断线期间的事件再转入实时推送
+//! - `GET /canvas?workspace=<name|path>` WebSocket 推送画布增量（先发快照），客户端可回传 `node_moved`
+//! - 配置 MCP 服务器后在 `POST /mcp` 提供 MCP HTTP 传输
+//! - `POST /tasks/<id>/cancel` 取消运行中的任务（`nl task cancel`）
+//! - `POST /tasks/<id>/feedback` 记录人工评分与评语，并补记到任务参与的 A/B 实验（`nl task feedback`）
+//! - `GET /health` 健康检查（服务启动探测与 `nl daemon status`）
+//! - `GET /stats?workspace=<name|path>` 记忆条目、图谱规模、运行中任务与事件总线计数（`nl top`）
+//! - `GET /workspaces` 列出工作区，`POST /workspaces` 登记新工作区（`nl workspace`）
+//! - `POST /workspaces/export`、`POST /workspaces/import` 在本机路径读写工作区包
+//! - `GET /workspaces/rewind?to=<事件 ID|时间>&entity=<id>&workspace=<name|path>` 重放事件得到历史时刻的
+//!   只读视图，`POST /workspaces/restore` 追加补偿事件恢复到该时刻（未确认时只返回预演）（`nl workspace rewind`）
+//! - `POST /memory/import` 把本机目录下的 Markdown / JSONL 知识库导入记忆与 GraphRAG（`nl memory import`）
+//! - `GET /memory/search?q=<查询>&workspace=<name|path>` 按记忆查询语言检索记忆（`nl memory search`）
+//! - `POST /context` 为查询与目标模型组装上下文，返回装入预算的文本与逐条的收录 / 排除原因
+//! - `GET /sops/stats?workspace=<name|path>` SOP 工作流执行统计与退役状态（`nl sop stats`）
+//! - `GET /digest?period=daily|weekly&workspace=<name|path>` 截至当前时刻的活动摘要（`nl digest`）
+//! - `GET /federation/peers` 静态配置与 mDNS 发现的联邦对端及其能力清单（`nl federation peers`）
+//! - `GET /artifacts?workspace=<name|path>&task=<id>` 列出任务产物元数据，
+//!   `GET /artifacts/<hash>` 下载产物内容，`GET /artifacts/<hash>/meta` 查询单个产物的元数据
+//! - `GET /schedules?workspace=<name|path>` 列出定时调度，`POST /schedules` 添加，
+//!   `DELETE /schedules/<name|id>?workspace=<name|path>` 删除（`nl schedule`）
+//! - 配置 LLM 网关后 `POST /llm/complete` 执行一个原语请求（携带 API Key 时以 Key ID 为 Actor，用量计入其预算）
+//! - `GET /keys` 列出 API Key，`POST /keys` 创建（明文只返回一次），`DELETE /keys/<id|name>` 吊销（`nl keys`）
+//! - 登记了 API Key 后，除 `GET /health` 外的请求都要携带 `Authorization: Bearer <key>`（或 `X-Api-Key` 头，
+//!   WebSocket 可用 `api_key` 查询参数）：GET 需要 read_only，其他方法需要 operator，
+//!   Key 管理、工作区登记 / 导出 / 导入 / 恢复与知识库导入（读取任意本机路径）需要 admin；超过每分钟请求数返回 429，token 预算用尽返回 403
+//! - POST 请求可携带 `Idempotency-Key` 头：同一个键的重试直接返回首次成功的响应
+//!   （带 `Idempotent-Replayed: true`），不会重复执行；登记了 API Key 时键按 Key 隔离
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, Request, State,
+    },
+    http::{header, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use nl_cognitive::{ContextRequest, DigestPeriod};
+use nl_core::{Event, EventFilter};
+use nl_durable::{
+    CatchUpPolicy, CommandRecord, ConflictPolicy, CronExpr, IdempotencyStore, RewindPoint, Schedule,
+    ScheduleTarget, TaskFeedback, WorkspaceBundle, WorkspaceView,
+};
+use nl_llm_new::scheduler::Priority;
+use nl_llm_new::{Format, Gateway, PrimitiveRequest};
+use nl_memory::MemoryQuery;
+
+use crate::api_keys::{ApiKey, ApiKeyRole, ApiKeyStore, AuthError, NewApiKey};
+use crate::workspace::{Workspace, WorkspaceRegistry};
+
+/// 控制面配置
+#[derive(Debug, Clone)]
+pub struct ControlConfig {
+    /// 监听地址
+    pub addr: SocketAddr,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:8766".parse().unwrap(),
+        }
+    }
+}
+
+/// 幂等键请求头
+const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+/// 标记响应为重放结果的响应头
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// API Key 请求头（`Authorization: Bearer` 之外的写法）
+const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Clone)]
+struct ControlState {
+    workspaces: Arc<WorkspaceRegistry>,
+    idempotency: Arc<IdempotencyStore>,
+    peers: Arc<nl_hap::PeerDirectory>,
+    api_keys: Arc<ApiKeyStore>,
+    llm: Option<Arc<Gateway>>,
+}
+
+/// 订阅查询参数
+#[derive(Debug, Default, Deserialize)]
+struct SubscribeQuery {
+    /// 工作区名称或路径
+    workspace: Option<String>,
+    /// 逗号分隔的事件类型名称
+    kinds: Option<String>,
+    entity: Option<Uuid>,
+    correlation: Option<Uuid>,
+    /// 续传令牌（上次收到的最后一个事件 ID）
+    resume: Option<Uuid>,
+}
+
+impl SubscribeQuery {
+    fn filter(&self) -> EventFilter {
+        EventFilter {
+            kinds: self
+                .kinds
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(String::from)
+                .collect(),
+            entity_id: self.entity,
+            correlation_id: self.correlation,
+        }
+    }
+}
+
+/// 控制面服务器
+pub struct ControlServer {
+    config: ControlConfig,
+    state: ControlState,
+    mcp: Option<Arc<nl_hap::McpServer>>,
+}
+
+impl ControlServer {
+    /// 创建控制面服务器
+    pub fn new(config: ControlConfig, workspaces: Arc<WorkspaceRegistry>) -> Self {
+        Self {
+            config,
+            state: ControlState {
+                workspaces,
+                idempotency: Arc::new(IdempotencyStore::new()),
+                peers: Arc::new(nl_hap::PeerDirectory::new()),
+                api_keys: Arc::new(ApiKeyStore::in_memory()),
+                llm: None,
+            },
+            mcp: None,
+        }
+    }
+
+    /// 使用持久化的去重表（默认仅在内存中去重）
+    pub fn with_idempotency(mut self, idempotency: Arc<IdempotencyStore>) -> Self {
+        self.state.idempotency = idempotency;
+        self
+    }
+
+    /// 设置联邦对端目录
+    pub fn with_peers(mut self, peers: Arc<nl_hap::PeerDirectory>) -> Self {
+        self.state.peers = peers;
+        self
+    }
+
+    /// 使用持久化的 API Key 登记表（默认没有 Key，不做鉴权）
+    pub fn with_api_keys(mut self, api_keys: Arc<ApiKeyStore>) -> Self {
+        self.state.api_keys = api_keys;
+        self
+    }
+
+    /// 提供 `POST /llm/complete`
+    pub fn with_llm(mut self, llm: Arc<Gateway>) -> Self {
+        self.state.llm = Some(llm);
+        self
+    }
+
+    /// 挂载 MCP 服务器
+    pub fn with_mcp(mut self, mcp: Arc<nl_hap::McpServer>) -> Self {
+        self.mcp = Some(mcp);
+        self
+    }
+
+    /// 构建 Axum 路由
+    pub fn build_router(&self) -> Router {
+        let router = Router::new()
+            .route("/events", get(subscribe_events))
+            .route("/canvas", get(subscribe_canvas))
+            .route("/tasks/:id/cancel", post(cancel_task))
+            .route("/tasks/:id/feedback", post(task_feedback))
+            .route("/health", get(health))
+            .route("/stats", get(stats))
+            .route("/workspaces", get(list_workspaces).post(add_workspace))
+            .route("/workspaces/export", post(export_workspace))
+            .route("/workspaces/import", post(import_workspace))
+            .route("/workspaces/rewind", get(rewind_workspace))
+            .route("/workspaces/restore", post(restore_workspace))
+            .route("/memory/import", post(import_memory))
+            .route("/memory/search", get(search_memory))
+            .route("/context", post(assemble_context))
+            .route("/sops/stats", get(sop_stats))
+            .route("/digest", get(digest))
+            .route("/federation/peers", get(federation_peers))
+            .route("/artifacts", get(list_artifacts))
+            .route("/artifacts/:hash", get(get_artifact))
+            .route("/artifacts/:hash/meta", get(artifact_meta))
+            .route("/schedules", get(list_schedules).post(add_schedule))
+            .route("/schedules/:name", delete(remove_schedule))
+            .route("/keys", get(list_keys).post(create_key))
+            .route("/keys/:key", delete(revoke_key))
+            .route("/llm/complete", post(complete_llm))
+            .layer(middleware::from_fn_with_state(self.state.clone(), idempotent))
+            .with_state(self.state.clone());
+        let router = match &self.mcp {
+            Some(mcp) => router.merge(mcp.clone().build_router()),
+            None => router,
+        };
+        // 鉴权在最外层，MCP 路由同样受保护，幂等重放也要先通过鉴权
+        router.layer(middleware::from_fn_with_state(self.state.clone(), authorize))
+    }
+
+    /// 启动服务器
+    pub async fn start(&self) -> nl_core::Result<()> {
+        let listener = tokio::net::TcpListener::bind(&self.config.addr)
+            .await
+            .map_err(|e| nl_core::NeuroLoomError::Protocol(e.to_string()))?;
+
+        axum::serve(listener, self.build_router())
+            .await
+            .map_err(|e| nl_core::NeuroLoomError::Protocol(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> &ControlConfig {
+        &self.config
+    }
+}
+
+/// 事件订阅接口
+async fn subscribe_events(
+    ws: WebSocketUpgrade,
+    State(state): State<ControlState>,
+    Query(query): Query<SubscribeQuery>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    ws.on_upgrade(move |socket| stream_events(socket, workspace, query))
+}
+
+/// 画布增量订阅接口
+async fn subscribe_canvas(
+    ws: WebSocketUpgrade,
+    State(state): State<ControlState>,
+    Query(query): Query<WorkspaceQuery>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    let feed = workspace.canvas.clone();
+    ws.on_upgrade(move |socket| crate::canvas::stream(socket, feed))
+}
+
+fn workspace_not_found(selector: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": format!("unknown workspace: {}", selector) })),
+    )
+        .into_response()
+}
+
+/// 健康检查接口
+async fn health(State(state): State<ControlState>) -> Json<serde_json::Value> {
+    let workspaces = state.workspaces.list().await;
+    let running: usize = workspaces.iter().map(|w| w.cancellation.running().len()).sum();
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "workspaces": workspaces.len(),
+        "running_tasks": running,
+    }))
+}
+
+/// 运行状态统计接口
+async fn stats(State(state): State<ControlState>, Query(query): Query<WorkspaceQuery>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    let graph = workspace.graph_rag.read().await;
+    let bus = workspace.event_bus.metrics();
+    Json(serde_json::json!({
+        "workspace": workspace.name,
+        "memory_entries": workspace.memory_index.count(),
+        "graph_nodes": graph.node_count(),
+        "graph_edges": graph.edge_count(),
+        "running_tasks": workspace.cancellation.running().len(),
+        "events": {
+            "published": bus.published,
+            "delivered": bus.delivered,
+            "lagged": bus.lagged,
+        },
+    }))
+    .into_response()
+}
+
+/// 工作区列表接口
+async fn list_workspaces(State(state): State<ControlState>) -> Json<serde_json::Value> {
+    let mut workspaces = Vec::new();
+    for w in state.workspaces.list().await {
+        let workflows = w.orchestrator.lock().await.sop_engine().count();
+        workspaces.push(serde_json::json!({
+            "name": w.name,
+            "path": w.root,
+            "sop_workflows": workflows,
+            "running_tasks": w.cancellation.running().len(),
+        }));
+    }
+    Json(serde_json::json!(workspaces))
+}
+
+/// 工作区选择查询参数
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceQuery {
+    workspace: Option<String>,
+}
+
+/// SOP 执行统计接口（包含尚未执行过的工作流）
+async fn sop_stats(State(state): State<ControlState>, Query(query): Query<WorkspaceQuery>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    let mut orchestrator = workspace.orchestrator.lock().await;
+    let sop = orchestrator.sop_engine();
+    let stats = sop.stats();
+    let mut names: Vec<String> = sop.workflows().iter().map(|w| w.name.clone()).collect();
+    names.extend(stats.iter().map(|s| s.workflow.clone()));
+    names.sort();
+    names.dedup();
+
+    let workflows: Vec<_> = names
+        .into_iter()
+        .map(|name| {
+            let s = stats.iter().find(|s| s.workflow == name).cloned().unwrap_or_default();
+            serde_json::json!({
+                "workflow": name,
+                "runs": s.runs,
+                "success_rate": s.success_rate(),
+                "rolling_success_rate": s.rolling_success_rate(),
+                "average_elapsed_ms": s.average_elapsed_ms(),
+                "last_failure": s.last_failure,
+                "last_run_at": s.last_run_at,
+                "retired": s.retired,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "workspace": workspace.name, "workflows": workflows })).into_response()
+}
+
+/// 摘要查询参数
+#[derive(Debug, Default, Deserialize)]
+struct DigestQuery {
+    workspace: Option<String>,
+    period: Option<String>,
+}
+
+/// 活动摘要接口（不写入记忆、不推送 Webhook）
+async fn digest(State(state): State<ControlState>, Query(query): Query<DigestQuery>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    let period = match query.period.as_deref().unwrap_or("daily").parse::<DigestPeriod>() {
+        Ok(period) => period,
+        Err(e) => return bad_request(e),
+    };
+    match nl_cognitive::digest::generate(&workspace.event_store, period, chrono::Utc::now()).await {
+        Ok(digest) => Json(serde_json::json!({
+            "workspace": workspace.name,
+            "markdown": digest.render_markdown(),
+            "digest": digest,
+        }))
+        .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() })))
+            .into_response(),
+    }
+}
+
+/// 联邦对端接口
+async fn federation_peers(State(state): State<ControlState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "peers": state.peers.list() }))
+}
+
+/// 登记工作区请求
+#[derive(Debug, Deserialize)]
+struct AddWorkspaceRequest {
+    name: Option<String>,
+    path: String,
+}
+
+/// 登记工作区接口（新工作区的 LLM 用量同样计入 API Key）
+async fn add_workspace(
+    State(state): State<ControlState>,
+    Json(request): Json<AddWorkspaceRequest>,
+) -> Response {
+    match state.workspaces.add(request.name.as_deref(), &request.path).await {
+        Ok(workspace) => {
+            state.api_keys.meter(&workspace.event_bus);
+            Json(serde_json::json!(workspace.entry())).into_response()
+        }
+        Err(e) => bad_request(e),
+    }
+}
+
+/// 工作区包请求
+#[derive(Debug, Deserialize)]
+struct BundleRequest {
+    /// 工作区名称或路径
+    workspace: Option<String>,
+    /// 包文件路径
+    path: String,
+    /// 导入冲突策略
+    #[serde(default)]
+    policy: ConflictPolicy,
+}
+
+fn bad_request(error: impl std::fmt::Display) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": error.to_string() })),
+    )
+        .into_response()
+}
+
+/// 工作区导出接口
+async fn export_workspace(
+    State(state): State<ControlState>,
+    Json(request): Json<BundleRequest>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(request.workspace.as_deref()).await else {
+        return workspace_not_found(request.workspace.as_deref().unwrap_or_default());
+    };
+    let bundle = match workspace.export_bundle().await {
+        Ok(bundle) => bundle,
+        Err(e) => return bad_request(e),
+    };
+    if let Err(e) = bundle.write(&request.path) {
+        return bad_request(e);
+    }
+    Json(serde_json::json!({
+        "workspace": workspace.name,
+        "path": request.path,
+        "version": bundle.version,
+        "events": bundle.events.len(),
+        "sections": bundle.sections.keys().collect::<Vec<_>>(),
+    }))
+    .into_response()
+}
+
+/// 工作区导入接口
+async fn import_workspace(
+    State(state): State<ControlState>,
+    Json(request): Json<BundleRequest>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(request.workspace.as_deref()).await else {
+        return workspace_not_found(request.workspace.as_deref().unwrap_or_default());
+    };
+    let bundle = match WorkspaceBundle::read(&request.path) {
+        Ok(bundle) => bundle,
+        Err(e) => return bad_request(e),
+    };
+    let source = bundle.workspace.clone();
+    match workspace.import_bundle(bundle, request.policy).await {
+        Ok(report) => Json(serde_json::json!({
+            "workspace": workspace.name,
+            "source": source,
+            "policy": request.policy,
+            "report": report,
+        }))
+        .into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// 时间回溯请求
+#[derive(Debug, Deserialize)]
+struct RewindRequest {
+    /// 工作区名称或路径
+    workspace: Option<String>,
+    /// 回溯点：事件 ID 或时间
+    to: String,
+    /// 只查看 / 恢复单个实体
+    entity: Option<Uuid>,
+    /// 恢复须显式确认，否则只返回将写入的补偿事件
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// 工作区历史视图接口（只读）
+async fn rewind_workspace(State(state): State<ControlState>, Query(request): Query<RewindRequest>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(request.workspace.as_deref()).await else {
+        return workspace_not_found(request.workspace.as_deref().unwrap_or_default());
+    };
+    let point = match request.to.parse::<RewindPoint>() {
+        Ok(point) => point,
+        Err(e) => return bad_request(e),
+    };
+    let mut view = match WorkspaceView::at(&*workspace.event_store.lock().await, point).await {
+        Ok(view) => view,
+        Err(e) => return bad_request(e),
+    };
+    if let Some(entity) = request.entity {
+        view.entities.retain(|id, _| *id == entity);
+    }
+    Json(serde_json::json!({ "workspace": workspace.name, "view": view })).into_response()
+}
+
+/// 工作区恢复接口：追加补偿事件，不删除历史
+async fn restore_workspace(State(state): State<ControlState>, Json(request): Json<RewindRequest>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(request.workspace.as_deref()).await else {
+        return workspace_not_found(request.workspace.as_deref().unwrap_or_default());
+    };
+    let point = match request.to.parse::<RewindPoint>() {
+        Ok(point) => point,
+        Err(e) => return bad_request(e),
+    };
+    let mut store = workspace.event_store.lock().await;
+    match nl_durable::rewind::restore(&mut store, point, request.entity, request.confirm).await {
+        Ok(events) => Json(serde_json::json!({
+            "workspace": workspace.name,
+            "applied": request.confirm,
+            "events": events,
+        }))
+        .into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// 知识库导入请求
+#[derive(Debug, Deserialize)]
+struct MemoryImportRequest {
+    /// 工作区名称或路径
+    workspace: Option<String>,
+    /// 知识库目录或文件路径
+    path: String,
+}
+
+/// 知识库导入接口
+async fn import_memory(
+    State(state): State<ControlState>,
+    Json(request): Json<MemoryImportRequest>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(request.workspace.as_deref()).await else {
+        return workspace_not_found(request.workspace.as_deref().unwrap_or_default());
+    };
+    match workspace.import_knowledge(std::path::Path::new(&request.path)).await {
+        Ok(stats) => Json(serde_json::json!({
+            "workspace": workspace.name,
+            "path": request.path,
+            "stats": stats,
+        }))
+        .into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// 记忆检索参数
+#[derive(Debug, Default, Deserialize)]
+struct MemorySearchQuery {
+    workspace: Option<String>,
+    /// 查询语句
+    #[serde(default)]
+    q: String,
+}
+
+/// 记忆检索接口
+async fn search_memory(State(state): State<ControlState>, Query(query): Query<MemorySearchQuery>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    let memory_query = match query.q.parse::<MemoryQuery>() {
+        Ok(memory_query) => memory_query,
+        Err(e) => return bad_request(e),
+    };
+    let hits = match memory_query.execute(&workspace.memory_index, None).await {
+        Ok(hits) => hits,
+        Err(e) => return internal_error(e),
+    };
+    let hits: Vec<serde_json::Value> = hits
+        .into_iter()
+        .map(|hit| {
+            serde_json::json!({
+                "id": hit.entry.id,
+                "tag": hit.entry.tag,
+                "summary": hit.entry.summary,
+                "created_at": hit.entry.created_at,
+                "metadata": hit.entry.metadata,
+                "score": hit.score,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "workspace": workspace.name, "hits": hits })).into_response()
+}
+
+/// 上下文组装请求
+#[derive(Debug, Deserialize)]
+struct ContextBody {
+    /// 工作区名称或路径
+    workspace: Option<String>,
+    query: String,
+    /// 目标模型（决定预算与分词器）
+    model: String,
+    /// 纳入候选的源码文件（按工作区根目录解析，不能位于根目录之外）
+    #[serde(default)]
+    files: Vec<std::path::PathBuf>,
+    /// 限定记忆候选的记忆查询语句
+    memory: Option<String>,
+}
+
+/// 上下文组装接口
+async fn assemble_context(State(state): State<ControlState>, Json(body): Json<ContextBody>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(body.workspace.as_deref()).await else {
+        return workspace_not_found(body.workspace.as_deref().unwrap_or_default());
+    };
+    let files = match confine(&workspace.root, &body.files) {
+        Ok(files) => files,
+        Err(e) => return bad_request(e),
+    };
+    let mut request = ContextRequest::new(body.query, body.model).with_files(files);
+    if let Some(memory) = &body.memory {
+        match memory.parse::<MemoryQuery>() {
+            Ok(query) => request = request.with_memory_query(query),
+            Err(e) => return bad_request(e),
+        }
+    }
+    match workspace.context.assemble(&request).await {
+        Ok(context) => Json(context).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// 把请求中的文件解析到工作区根目录下（规范化后比较，`..`、绝对路径与指向外部的符号链接都被拒绝）
+fn confine(root: &std::path::Path, files: &[std::path::PathBuf]) -> Result<Vec<std::path::PathBuf>, String> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("workspace root {}: {}", root.display(), e))?;
+    files
+        .iter()
+        .map(|file| {
+            let path = root.join(file).canonicalize().map_err(|e| format!("{}: {}", file.display(), e))?;
+            if path.starts_with(&root) {
+                Ok(path)
+            } else {
+                Err(format!("{} is outside the workspace", file.display()))
+            }
+        })
+        .collect()
+}
+
+/// 产物查询参数
+#[derive(Debug, Default, Deserialize)]
+struct ArtifactQuery {
+    workspace: Option<String>,
+    task: Option<Uuid>,
+}
+
+/// 产物列表接口
+async fn list_artifacts(State(state): State<ControlState>, Query(query): Query<ArtifactQuery>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    match workspace.artifacts.list(query.task).await {
+        Ok(artifacts) => {
+            Json(serde_json::json!({ "workspace": workspace.name, "artifacts": artifacts })).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+/// 产物下载接口（按元数据中的 MIME 类型返回原始内容）
+async fn get_artifact(
+    State(state): State<ControlState>,
+    Path(hash): Path<String>,
+    Query(query): Query<WorkspaceQuery>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    let meta = match workspace.artifacts.get_meta(&hash).await {
+        Ok(Some(meta)) => meta,
+        Ok(None) => return artifact_not_found(&hash),
+        Err(e) => return internal_error(e),
+    };
+    match workspace.artifacts.get(&hash).await {
+        Ok(Some(content)) => ([(header::CONTENT_TYPE, meta.media_type)], content).into_response(),
+        Ok(None) => artifact_not_found(&hash),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// 产物元数据接口
+async fn artifact_meta(
+    State(state): State<ControlState>,
+    Path(hash): Path<String>,
+    Query(query): Query<WorkspaceQuery>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    match workspace.artifacts.get_meta(&hash).await {
+        Ok(Some(meta)) => Json(serde_json::json!({ "workspace": workspace.name, "artifact": meta })).into_response(),
+        Ok(None) => artifact_not_found(&hash),
+        Err(e) => internal_error(e),
+    }
+}
+
+fn artifact_not_found(hash: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": format!("unknown artifact: {}", hash) })),
+    )
+        .into_response()
+}
+
+fn internal_error(error: impl std::fmt::Display) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": error.to_string() })),
+    )
+        .into_response()
+}
+
+/// 调度列表接口
+async fn list_schedules(State(state): State<ControlState>, Query(query): Query<WorkspaceQuery>) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    let schedules = workspace.schedules.list().await;
+    Json(serde_json::json!({ "workspace": workspace.name, "schedules": schedules })).into_response()
+}
+
+/// 添加调度请求（`workflow` 与 `goal` 二选一）
+#[derive(Debug, Deserialize)]
+struct AddScheduleRequest {
+    workspace: Option<String>,
+    name: String,
+    cron: String,
+    workflow: Option<String>,
+    goal: Option<String>,
+    #[serde(default)]
+    catch_up: CatchUpPolicy,
+}
+
+/// 添加调度接口
+async fn add_schedule(
+    State(state): State<ControlState>,
+    Json(request): Json<AddScheduleRequest>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(request.workspace.as_deref()).await else {
+        return workspace_not_found(request.workspace.as_deref().unwrap_or_default());
+    };
+    let cron = match CronExpr::parse(&request.cron) {
+        Ok(cron) => cron,
+        Err(e) => return bad_request(e),
+    };
+    let target = match (request.workflow, request.goal) {
+        (Some(workflow), None) => ScheduleTarget::Workflow(workflow),
+        (None, Some(goal)) => ScheduleTarget::Goal(goal),
+        _ => return bad_request("exactly one of workflow or goal is required"),
+    };
+    let schedule = Schedule::new(request.name, cron, target).with_catch_up(request.catch_up);
+    match workspace.schedules.add(schedule.clone()).await {
+        Ok(()) => Json(serde_json::json!({ "workspace": workspace.name, "schedule": schedule })).into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// 删除调度接口
+async fn remove_schedule(
+    State(state): State<ControlState>,
+    Path(name): Path<String>,
+    Query(query): Query<WorkspaceQuery>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(query.workspace.as_deref()).await else {
+        return workspace_not_found(query.workspace.as_deref().unwrap_or_default());
+    };
+    match workspace.schedules.remove(&name).await {
+        Ok(Some(schedule)) => {
+            Json(serde_json::json!({ "workspace": workspace.name, "removed": schedule })).into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("unknown schedule: {}", name) })),
+        )
+            .into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// 幂等中间件：携带 `Idempotency-Key` 的 POST 请求按键去重（键与方法、路径绑定，
+/// 鉴权后按 API Key 隔离，不同 Key 的同名键互不可见），只记录成功响应，失败的请求可以用同一个键重试
+async fn idempotent(State(state): State<ControlState>, request: Request, next: Next) -> Response {
+    let header = match request.headers().get(IDEMPOTENCY_HEADER) {
+        Some(value) if request.method() == Method::POST => match value.to_str() {
+            Ok(key) if !key.trim().is_empty() => key.trim().to_string(),
+            _ => return bad_request("invalid Idempotency-Key header"),
+        },
+        _ => return next.run(request).await,
+    };
+    let key = match request.extensions().get::<ApiKey>() {
+        Some(api_key) => format!("{}:{}", api_key.id, header),
+        None => header.clone(),
+    };
+    let command = format!("{} {}", request.method(), request.uri().path());
+    match state.idempotency.get(&key).await {
+        Ok(Some(record)) if record.command == command => return replay(&record),
+        Ok(Some(record)) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({
+                    "error": format!("idempotency key {} was already used for {}", header, record.command)
+                })),
+            )
+                .into_response()
+        }
+        Ok(None) => {}
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() })))
+                .into_response()
+        }
+    }
+    if let Err(e) = state.idempotency.begin(&key) {
+        return (StatusCode::CONFLICT, Json(serde_json::json!({ "error": e.to_string() }))).into_response();
+    }
+
+    let (parts, body) = next.run(request).await.into_parts();
+    let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap_or_default();
+    if parts.status.is_success() {
+        let result = serde_json::json!({
+            "status": parts.status.as_u16(),
+            "body": serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or(serde_json::Value::Null),
+        });
+        if let Err(e) = state.idempotency.record(&key, &command, &result).await {
+            tracing::warn!("Failed to record idempotency key {}: {}", key, e);
+        }
+    }
+    state.idempotency.finish(&key);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// 请求所需的最低角色（按本机路径读写文件的接口需要 admin）
+fn required_role(method: &Method, path: &str) -> ApiKeyRole {
+    if path.starts_with("/keys")
+        || (path.starts_with("/workspaces") && method != Method::GET)
+        || path == "/memory/import"
+    {
+        ApiKeyRole::Admin
+    } else if method == Method::GET {
+        ApiKeyRole::ReadOnly
+    } else {
+        ApiKeyRole::Operator
+    }
+}
+
+/// 请求携带的 API Key：`Authorization: Bearer`、`X-Api-Key` 头或 `api_key` 查询参数
+fn presented_key(request: &Request) -> Option<String> {
+    let headers = request.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let header = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok());
+    let query = request
+        .uri()
+        .query()
+        .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("api_key=")));
+    bearer.or(header).or(query).map(|key| key.trim().to_string())
+}
+
+/// API Key 鉴权中间件（没有登记任何 Key 时放行），通过后把 Key 放入请求扩展供处理函数记账
+async fn authorize(State(state): State<ControlState>, mut request: Request, next: Next) -> Response {
+    if !state.api_keys.is_enabled() || request.uri().path() == "/health" {
+        return next.run(request).await;
+    }
+    let required = required_role(request.method(), request.uri().path());
+    let result = match presented_key(&request) {
+        Some(key) => state.api_keys.authenticate(&key, required),
+        None => Err(AuthError::Unauthorized),
+    };
+    let error = match result {
+        Ok(key) => {
+            request.extensions_mut().insert(key);
+            return next.run(request).await;
+        }
+        Err(error) => error,
+    };
+    let status = match &error {
+        AuthError::Unauthorized => StatusCode::UNAUTHORIZED,
+        AuthError::Forbidden { .. } | AuthError::BudgetExhausted { .. } => StatusCode::FORBIDDEN,
+        AuthError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+    };
+    let mut response = (status, Json(serde_json::json!({ "error": error.to_string() }))).into_response();
+    if let AuthError::RateLimited { retry_after } = error {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, retry_after.as_secs().max(1).into());
+    }
+    response
+}
+
+/// API Key 列表接口
+async fn list_keys(State(state): State<ControlState>) -> Json<serde_json::Value> {
+    let keys: Vec<_> = state.api_keys.list().iter().map(|k| k.summary()).collect();
+    Json(serde_json::json!({ "keys": keys }))
+}
+
+/// 创建 API Key 接口
+async fn create_key(State(state): State<ControlState>, Json(request): Json<NewApiKey>) -> Response {
+    match state.api_keys.create(request) {
+        Ok((key, secret)) => Json(serde_json::json!({ "key": key.summary(), "secret": secret })).into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// 吊销 API Key 接口
+async fn revoke_key(State(state): State<ControlState>, Path(key): Path<String>) -> Response {
+    match state.api_keys.revoke(&key) {
+        Ok(Some(key)) => Json(serde_json::json!({ "revoked": key.summary() })).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("unknown API key: {}", key) })),
+        )
+            .into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// LLM 补全接口：携带 API Key 时以 Key ID 为 Actor 调用，用量计入该 Key 的 token 预算
+async fn complete_llm(
+    State(state): State<ControlState>,
+    key: Option<Extension<ApiKey>>,
+    Json(request): Json<PrimitiveRequest>,
+) -> Response {
+    let Some(llm) = &state.llm else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "no LLM gateway configured" })),
+        )
+            .into_response();
+    };
+    let result = match key {
+        Some(Extension(key)) => llm.complete_for_actor(key.id, &request, Format::default(), Priority::Normal).await,
+        None => llm.complete_with_priority(&request, Format::default(), Priority::Normal).await,
+    };
+    match result {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// 返回首次执行记录的响应
+fn replay(record: &CommandRecord) -> Response {
+    let status = record.result["status"]
+        .as_u64()
+        .and_then(|s| StatusCode::from_u16(s as u16).ok())
+        .unwrap_or(StatusCode::OK);
+    (status, [(REPLAYED_HEADER, "true")], Json(record.result["body"].clone())).into_response()
+}
+
+/// 任务取消接口（任务 ID 全局唯一，在所有工作区中查找）
+async fn cancel_task(State(state): State<ControlState>, Path(id): Path<Uuid>) -> Response {
+    for workspace in state.workspaces.list().await {
+        if workspace.cancellation.cancel(id, "requested via control API") {
+            return Json(serde_json::json!({ "task": id, "workspace": workspace.name, "cancelled": true }))
+                .into_response();
+        }
+    }
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "task": id, "cancelled": false, "error": "task is not running" })),
+    )
+        .into_response()
+}
+
+/// 任务反馈请求
+#[derive(Debug, Deserialize)]
+struct FeedbackRequest {
+    workspace: Option<String>,
+    rating: u8,
+    #[serde(default)]
+    comment: Option<String>,
+}
+
+/// 任务反馈接口（任务必须在工作区事件库中出现过）
+async fn task_feedback(
+    State(state): State<ControlState>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<FeedbackRequest>,
+) -> Response {
+    let Some(workspace) = state.workspaces.resolve(request.workspace.as_deref()).await else {
+        return workspace_not_found(request.workspace.as_deref().unwrap_or_default());
+    };
+    let mut feedback = match TaskFeedback::new(id, request.rating) {
+        Ok(feedback) => feedback,
+        Err(e) => return bad_request(e),
+    };
+    if let Some(comment) = request.comment {
+        feedback = feedback.with_comment(comment);
+    }
+
+    let mut store = workspace.event_store.lock().await;
+    match store.get_events(id).await {
+        Ok(events) if events.is_empty() => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "task": id, "error": "unknown task" })),
+            )
+                .into_response();
+        }
+        Ok(_) => {}
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() })))
+                .into_response()
+        }
+    }
+    match feedback.record(&mut store).await {
+        Ok(experiments) => Json(serde_json::json!({
+            "workspace": workspace.name,
+            "feedback": feedback,
+            "experiments": experiments,
+        }))
+        .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() })))
+            .into_response(),
+    }
+}
+
+/// 推送事件直到客户端断开
+async fn stream_events(mut socket: WebSocket, workspace: Arc<Workspace>, query: SubscribeQuery) {
+    let filter = query.filter();
+    // 先订阅再补发，避免补发期间产生的事件丢失
+    let mut live = workspace.event_bus.subscribe_all();
+
+    let mut replayed = HashSet::new();
+    if let Some(token) = query.resume {
+        let missed = match workspace.event_store.lock().await.get_events_after(token).await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::warn!("failed to replay events after {}: {}", token, e);
+                Vec::new()
+            }
+        };
+        for event in missed.iter().filter(|e| filter.matches(e)) {
+            if send_event(&mut socket, event).await.is_err() {
+                return;
+            }
+            replayed.insert(event.id);
+        }
+    }
+
+    loop {
+        tokio::select! {
+            event = live.recv() => {
+                let Some(event) = event else { break };
+                if !filter.matches(&event) || replayed.remove(&event.id) {
+                    continue;
+                }
+                if send_event(&mut socket, &event).await.is_err() {
+                    break;
+                }
+            }
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+async fn send_event(socket: &mut WebSocket, event: &Event) -> Result<(), axum::Error> {
+    let envelope = serde_json::json!({ "token": event.id, "event": event });
+    socket.send(Message::Text(envelope.to_string())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderMap;
+    use tower::ServiceExt;
+
+    use nl_llm_new::provider::mock::MockProvider;
+    use nl_llm_new::provider::Usage;
+
+    use super::*;
+
+    async fn workspaces() -> Arc<WorkspaceRegistry> {
+        let base = std::env::temp_dir().join(format!("nl_control_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&base).unwrap();
+        Arc::new(WorkspaceRegistry::open(&base).await.unwrap())
+    }
+
+    async fn router() -> Router {
+        ControlServer::new(ControlConfig::default(), workspaces().await).build_router()
+    }
+
+    /// 创建 Key，返回 (ID, 明文)
+    async fn create_key(
+        router: &Router,
+        auth: &[(&str, &str)],
+        body: serde_json::Value,
+    ) -> (String, String) {
+        let (status, _, created) = call(router, Method::POST, "/keys", auth, Some(body)).await;
+        assert_eq!(status, StatusCode::OK, "{}", created);
+        (
+            created["key"]["id"].as_str().unwrap().to_string(),
+            created["secret"].as_str().unwrap().to_string(),
+        )
+    }
+
+    async fn call(
+        router: &Router,
+        method: Method,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, HeaderMap, serde_json::Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let body = match body {
+            Some(body) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        let response = router.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+        (parts.status, parts.headers, json)
+    }
+
+    fn schedule(name: &str, cron: &str) -> Option<serde_json::Value> {
+        Some(serde_json::json!({ "name": name, "cron": cron, "goal": "tidy up" }))
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_replays_success_and_reports_error_shapes() {
+        let router = router().await;
+        let key = [(IDEMPOTENCY_HEADER, "add-nightly")];
+
+        // 成功：首次执行并记录，重试返回同一响应且不再执行
+        let (status, headers, first) =
+            call(&router, Method::POST, "/schedules", &key, schedule("nightly", "30 2 * * *")).await;
+        assert_eq!(status, StatusCode::OK, "{}", first);
+        assert!(headers.get(REPLAYED_HEADER).is_none());
+        let (status, headers, replayed) =
+            call(&router, Method::POST, "/schedules", &key, schedule("nightly", "30 2 * * *")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[REPLAYED_HEADER], "true");
+        assert_eq!(replayed, first);
+        let (_, _, list) = call(&router, Method::GET, "/schedules", &[], None).await;
+        assert_eq!(list["schedules"].as_array().unwrap().len(), 1);
+
+        // 失败的请求不记录，同一个键可以修正后重试
+        let retry = [(IDEMPOTENCY_HEADER, "add-hourly")];
+        let (status, _, error) = call(&router, Method::POST, "/schedules", &retry, schedule("hourly", "bad")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error["error"].is_string());
+        let (status, headers, _) =
+            call(&router, Method::POST, "/schedules", &retry, schedule("hourly", "0 * * * *")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get(REPLAYED_HEADER).is_none());
+
+        // 键已用于其他接口
+        let (status, _, error) =
+            call(&router, Method::POST, "/workspaces", &key, Some(serde_json::json!({ "path": "." }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(error["error"].as_str().unwrap().contains("POST /schedules"), "{}", error);
+
+        // 空键
+        let (status, _, _) =
+            call(&router, Method::POST, "/schedules", &[(IDEMPOTENCY_HEADER, " ")], schedule("x", "0 * * * *")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // GET 不参与去重
+        let (status, headers, _) = call(&router, Method::GET, "/schedules", &key, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get(REPLAYED_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_keys_are_scoped_to_the_api_key() {
+        let router = router().await;
+        let (_, admin) = create_key(&router, &[], serde_json::json!({ "name": "root", "role": "admin" })).await;
+        let admin = [(API_KEY_HEADER, admin.as_str())];
+        let (_, alice) = create_key(&router, &admin, serde_json::json!({ "name": "a", "role": "operator" })).await;
+        let (_, bob) = create_key(&router, &admin, serde_json::json!({ "name": "b", "role": "operator" })).await;
+        let alice = [(API_KEY_HEADER, alice.as_str()), (IDEMPOTENCY_HEADER, "nightly")];
+        let bob = [(API_KEY_HEADER, bob.as_str()), (IDEMPOTENCY_HEADER, "nightly")];
+
+        let (status, _, first) = call(&router, Method::POST, "/schedules", &alice, schedule("a", "0 * * * *")).await;
+        assert_eq!(status, StatusCode::OK, "{}", first);
+        // 另一个 Key 使用同名键时照常执行，不会拿到前者的响应
+        let (status, headers, second) =
+            call(&router, Method::POST, "/schedules", &bob, schedule("b", "0 * * * *")).await;
+        assert_eq!(status, StatusCode::OK, "{}", second);
+        assert!(headers.get(REPLAYED_HEADER).is_none());
+        assert_ne!(second, first);
+
+        // 同一个 Key 的重试仍然重放
+        let (_, headers, replayed) =
+            call(&router, Method::POST, "/schedules", &alice, schedule("a", "0 * * * *")).await;
+        assert_eq!(headers[REPLAYED_HEADER], "true");
+        assert_eq!(replayed, first);
+        let (_, _, list) = call(&router, Method::GET, "/schedules", &admin, None).await;
+        assert_eq!(list["schedules"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_context_is_counted_with_tokenizers_from_the_data_dir() {
+        let base = std::env::temp_dir().join(format!("nl_control_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(base.join("tokenizers")).unwrap();
+        // 只含 "hello" 的词表：其余字节各算一个 token
+        std::fs::write(base.join("tokenizers/gpt-test.tiktoken"), "aGVsbG8= 0\n").unwrap();
+        let workspaces = Arc::new(WorkspaceRegistry::open(&base).await.unwrap());
+        let default = workspaces.default_workspace().await;
+        default.memory_index.store(nl_memory::hamt::MemoryEntry::new("hello", "hello"));
+        let router = ControlServer::new(ControlConfig::default(), workspaces.clone()).build_router();
+
+        let body = serde_json::json!({ "query": "hello", "model": "gpt-test-1" });
+        let (status, _, context) = call(&router, Method::POST, "/context", &[], Some(body)).await;
+        assert_eq!(status, StatusCode::OK, "{}", context);
+        assert_eq!(context["tokenizer"], "gpt-test");
+        assert_eq!(context["included"][0]["label"], "hello");
+        let counted = context["included"][0]["tokens"].as_u64().unwrap();
+        assert_eq!(workspaces.tokenizers().count("gpt-test", "hello"), 1);
+
+        let body = serde_json::json!({ "query": "hello", "model": "claude-sonnet-4", "memory": "tag:" });
+        let (status, _, context) = call(&router, Method::POST, "/context", &[], Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", context);
+        let body = serde_json::json!({ "query": "hello", "model": "claude-sonnet-4" });
+        let (_, _, context) = call(&router, Method::POST, "/context", &[], Some(body)).await;
+        assert_eq!(context["tokenizer"], "heuristic");
+        // 词表外的字节各算一个 token，多于按 4 个字符一个的估算
+        assert!(counted > context["included"][0]["tokens"].as_u64().unwrap());
+        std::fs::remove_dir_all(base).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_context_files_are_confined_to_the_workspace() {
+        let workspaces = workspaces().await;
+        let root = workspaces.default_workspace().await.root.clone();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub fn hello() {}\n").unwrap();
+        let outside = root.parent().unwrap().join(format!("nl_secret_{}", Uuid::new_v4()));
+        std::fs::write(&outside, "top secret").unwrap();
+        let router = ControlServer::new(ControlConfig::default(), workspaces).build_router();
+        let request = |file: &str| Some(serde_json::json!({ "query": "hello", "model": "gpt-4o", "files": [file] }));
+
+        let (status, _, context) = call(&router, Method::POST, "/context", &[], request("src/../src/lib.rs")).await;
+        assert_eq!(status, StatusCode::OK, "{}", context);
+        let escape = format!("../{}", outside.file_name().unwrap().to_str().unwrap());
+        for file in [escape.as_str(), outside.to_str().unwrap(), "/etc/passwd", "missing.rs"] {
+            let (status, _, error) = call(&router, Method::POST, "/context", &[], request(file)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", file);
+            assert!(!error.to_string().contains("top secret"));
+        }
+        std::fs::remove_file(outside).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_api_key_roles_gate_requests() {
+        let router = router().await;
+        // 没有 Key 时不鉴权；第一个 Key 必须是 admin
+        let (status, _, _) = call(&router, Method::GET, "/schedules", &[], None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) =
+            call(&router, Method::POST, "/keys", &[], Some(serde_json::json!({ "name": "ro", "role": "read_only" })))
+                .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (_, admin) = create_key(&router, &[], serde_json::json!({ "name": "root", "role": "admin" })).await;
+        let admin = format!("Bearer {}", admin);
+        let admin = [("authorization", admin.as_str())];
+        let (_, reader) = create_key(&router, &admin, serde_json::json!({ "name": "ro", "role": "read_only" })).await;
+        let (_, operator) =
+            create_key(&router, &admin, serde_json::json!({ "name": "op", "role": "operator" })).await;
+
+        // 缺少或未知的 Key
+        let (status, _, _) = call(&router, Method::GET, "/schedules", &[], None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _, _) = call(&router, Method::GET, "/schedules", &[(API_KEY_HEADER, "nlk_bogus")], None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        // 健康检查不需要 Key
+        let (status, _, _) = call(&router, Method::GET, "/health", &[], None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // read_only 只能 GET（三种携带方式都可用）
+        let (status, _, _) = call(&router, Method::GET, "/schedules", &[(API_KEY_HEADER, &reader)], None).await;
+        assert_eq!(status, StatusCode::OK);
+        let uri = format!("/schedules?api_key={}", reader);
+        let (status, _, _) = call(&router, Method::GET, &uri, &[], None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, error) =
+            call(&router, Method::POST, "/schedules", &[(API_KEY_HEADER, &reader)], schedule("a", "0 * * * *")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(error["error"].as_str().unwrap().contains("ReadOnly"), "{}", error);
+
+        // operator 可以操作，但不能管理 Key 或登记工作区
+        let (status, _, _) =
+            call(&router, Method::POST, "/schedules", &[(API_KEY_HEADER, &operator)], schedule("a", "0 * * * *"))
+                .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) = call(&router, Method::GET, "/keys", &[(API_KEY_HEADER, &operator)], None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let body = Some(serde_json::json!({ "path": "." }));
+        let (status, _, _) = call(&router, Method::POST, "/workspaces", &[(API_KEY_HEADER, &operator)], body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let body = Some(serde_json::json!({ "path": "/etc" }));
+        let (status, _, _) = call(&router, Method::POST, "/memory/import", &[(API_KEY_HEADER, &operator)], body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // admin 可以吊销，吊销后的 Key 失效
+        let (status, _, _) = call(&router, Method::DELETE, "/keys/op", &admin, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) = call(&router, Method::GET, "/schedules", &[(API_KEY_HEADER, &operator)], None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_rate_limit_returns_retry_after() {
+        let router = router().await;
+        let (_, admin) = create_key(&router, &[], serde_json::json!({ "name": "root", "role": "admin" })).await;
+        let body = serde_json::json!({ "name": "slow", "role": "read_only", "rate_limit_per_minute": 2 });
+        let (_, slow) = create_key(&router, &[(API_KEY_HEADER, &admin)], body).await;
+
+        for _ in 0..2 {
+            let (status, _, _) = call(&router, Method::GET, "/schedules", &[(API_KEY_HEADER, &slow)], None).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (status, headers, error) =
+            call(&router, Method::GET, "/schedules", &[(API_KEY_HEADER, &slow)], None).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = headers[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((1..=60).contains(&retry_after), "{}", retry_after);
+        assert!(error["error"].as_str().unwrap().contains("rate limit"));
+        // 限流按 Key 计算，其他 Key 不受影响
+        let (status, _, _) = call(&router, Method::GET, "/schedules", &[(API_KEY_HEADER, &admin)], None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_llm_calls_are_charged_to_the_api_key_until_the_budget_runs_out() {
+        let workspaces = workspaces().await;
+        let default = workspaces.default_workspace().await;
+        let provider = MockProvider::new("mock").with_usage(Usage {
+            input_tokens: 40,
+            output_tokens: 20,
+            ..Default::default()
+        });
+        let gateway = Gateway::new(nl_llm_new::GatewayConfig::default()).with_event_bus(default.event_bus.clone());
+        gateway.register_provider(Arc::new(provider)).await;
+        let api_keys = Arc::new(ApiKeyStore::in_memory());
+        api_keys.meter(&default.event_bus);
+        let router = ControlServer::new(ControlConfig::default(), workspaces.clone())
+            .with_api_keys(api_keys.clone())
+            .with_llm(Arc::new(gateway))
+            .build_router();
+
+        let (admin_id, admin) = create_key(&router, &[], serde_json::json!({ "name": "root", "role": "admin" })).await;
+        let body = serde_json::json!({ "name": "team", "role": "operator", "token_budget": 100 });
+        let (id, team) = create_key(&router, &[(API_KEY_HEADER, &admin)], body).await;
+        let team = [(API_KEY_HEADER, team.as_str())];
+        let tokens_used = |api_keys: &ApiKeyStore, id: &str| {
+            api_keys.list().iter().find(|k| k.id.to_string() == id).map(|k| k.tokens_used).unwrap()
+        };
+        let request = serde_json::to_value(PrimitiveRequest::single_user_message("hello")).unwrap();
+
+        // 用量事件以 Key ID 为实体，由计量任务异步计入
+        for expected in [60, 120] {
+            let (status, _, response) =
+                call(&router, Method::POST, "/llm/complete", &team, Some(request.clone())).await;
+            assert_eq!(status, StatusCode::OK, "{}", response);
+            assert_eq!(response["content"], "ok");
+            for _ in 0..100 {
+                if tokens_used(&api_keys, &id) == expected {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            assert_eq!(tokens_used(&api_keys, &id), expected);
+        }
+
+        // 预算用尽后拒绝请求，其他 Key 不受影响
+        let (status, _, error) = call(&router, Method::POST, "/llm/complete", &team, Some(request.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(error["error"].as_str().unwrap().contains("budget of 100"), "{}", error);
+        let (status, _, _) = call(&router, Method::GET, "/schedules", &team, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(tokens_used(&api_keys, &admin_id), 0);
+
+        // 控制面登记的新工作区同样计量
+        let root = std::env::temp_dir().join(format!("nl_control_ws_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let body = Some(serde_json::json!({ "name": "later", "path": root }));
+        let (status, _, _) = call(&router, Method::POST, "/workspaces", &[(API_KEY_HEADER, &admin)], body).await;
+        assert_eq!(status, StatusCode::OK);
+        let later = workspaces.resolve(Some("later")).await.unwrap();
+        let usage = serde_json::json!({ "usage": { "total_tokens": 5 } });
+        later.event_bus.publish(&Event::new(
+            nl_core::event::EventKind::LlmResponseCompleted,
+            id.parse().unwrap(),
+            usage,
+        ));
+        for _ in 0..100 {
+            if tokens_used(&api_keys, &id) == 125 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(tokens_used(&api_keys, &id), 125);
+    }
+}
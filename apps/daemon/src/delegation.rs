@@ -0,0 +1,100 @@
+//! 远程委托监控
+//!
+//! 把 HAP 执行方上报的进度、心跳与结果交给 `DelegationTracker`，状态变化写入默认工作区的事件库
+//! （经事件总线推送给控制面订阅者与桌面端）；每隔 `CHECK_INTERVAL` 检查停滞的委托，
+//! 转派给其他满足需求的已握手 Agent。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::{broadcast, Mutex};
+
+use nl_core::event::Event;
+use nl_hap::delegation::{DelegationConfig, DelegationTracker};
+use nl_hap::{HapServer, TaskRequirements};
+
+use crate::workspace::Workspace;
+
+/// 停滞检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 清理已结束委托的间隔
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 委托监控
+pub struct DelegationMonitor {
+    server: Arc<HapServer>,
+    tracker: Mutex<DelegationTracker>,
+    workspace: Arc<Workspace>,
+}
+
+impl DelegationMonitor {
+    /// 创建监控（状态变化记入 `workspace`）
+    pub fn new(server: Arc<HapServer>, workspace: Arc<Workspace>) -> Self {
+        let tracker = DelegationTracker::new(server.config().agent_id, DelegationConfig::default());
+        Self {
+            server,
+            tracker: Mutex::new(tracker),
+            workspace,
+        }
+    }
+
+    /// 持续运行（由调用方 spawn）
+    pub async fn run(self) {
+        let mut messages = self.server.subscribe();
+        let mut checks = tokio::time::interval(CHECK_INTERVAL);
+        let mut prunes = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            tokio::select! {
+                msg = messages.recv() => {
+                    let msg = match msg {
+                        Ok(msg) => msg,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Delegation monitor skipped {} HAP messages", skipped);
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    let event = self.tracker.lock().await.observe(&msg);
+                    if let Some(event) = event {
+                        self.record(event).await;
+                    }
+                }
+                _ = checks.tick() => self.check_stalled().await,
+                _ = prunes.tick() => {
+                    self.tracker.lock().await.prune_finished();
+                }
+            }
+        }
+    }
+
+    /// 转派停滞的委托
+    async fn check_stalled(&self) {
+        let stalled = self.tracker.lock().await.stalled(Utc::now());
+        for task_id in stalled {
+            let requirements = match self.tracker.lock().await.get(&task_id) {
+                Some(delegation) => TaskRequirements::parse(&delegation.requirements),
+                None => continue,
+            };
+            let candidates = self.server.eligible_agents(&requirements).await;
+            let Some(outcome) = self.tracker.lock().await.redelegate(task_id, &candidates) else {
+                continue;
+            };
+            match &outcome.reassignment {
+                Some(assign) => {
+                    tracing::warn!("Delegated task {} stalled, reassigning to {:?}", task_id, assign.receiver);
+                    self.server.publish(assign.clone());
+                }
+                None => tracing::warn!("Delegated task {} stalled with no agent left to take over", task_id),
+            }
+            self.record(outcome.event).await;
+        }
+    }
+
+    async fn record(&self, event: Event) {
+        if let Err(e) = self.workspace.event_store.lock().await.append(event).await {
+            tracing::warn!("Failed to record delegation event: {}", e);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! 活动摘要任务
+//!
+//! 每隔 `TICK` 检查所有工作区，为刚结束的周期生成摘要：
+//! - 每天零点（UTC）之后生成前一天的日报，每周一生成上一周的周报
+//! - Markdown 写入数据目录的 `digests/<周期>-<结束日期>.md`，已存在时跳过（重启不重复生成）
+//! - 同时作为记忆条目存入工作区的记忆索引，全文路径指向该文件
+//! - 设置 `NEUROLOOM_DIGEST_WEBHOOK` 后把摘要以 JSON POST 到该地址
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Utc, Weekday};
+
+use nl_cognitive::digest::{self, Digest, DigestPeriod};
+
+use crate::workspace::{Workspace, WorkspaceRegistry};
+
+/// 检查间隔
+const TICK: Duration = Duration::from_secs(3600);
+
+/// 摘要目录名（位于工作区数据目录下）
+const DIGEST_DIR: &str = "digests";
+
+/// 摘要任务
+pub struct DigestJob {
+    workspaces: Arc<WorkspaceRegistry>,
+    webhook: Option<String>,
+    client: reqwest::Client,
+}
+
+impl DigestJob {
+    /// 创建摘要任务（从环境变量读取 Webhook 地址）
+    pub fn new(workspaces: Arc<WorkspaceRegistry>) -> Self {
+        Self {
+            workspaces,
+            webhook: std::env::var("NEUROLOOM_DIGEST_WEBHOOK").ok().filter(|url| !url.is_empty()),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// 持续运行（由调用方 spawn）
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(TICK);
+        loop {
+            ticker.tick().await;
+            self.tick().await;
+        }
+    }
+
+    /// 检查一轮
+    async fn tick(&self) {
+        let midnight = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is a valid time")
+            .and_utc();
+        let mut periods = vec![DigestPeriod::Daily];
+        if midnight.weekday() == Weekday::Mon {
+            periods.push(DigestPeriod::Weekly);
+        }
+        for workspace in self.workspaces.list().await {
+            for period in &periods {
+                if let Err(e) = self.publish(&workspace, *period, midnight).await {
+                    tracing::warn!("Failed to generate {} digest for {}: {}", period, workspace.name, e);
+                }
+            }
+        }
+    }
+
+    /// 生成、保存并发送一份摘要（已生成过则跳过）
+    async fn publish(&self, workspace: &Workspace, period: DigestPeriod, end: DateTime<Utc>) -> anyhow::Result<()> {
+        let path = digest_path(workspace, period, end);
+        if path.exists() {
+            return Ok(());
+        }
+        let digest = digest::generate(&workspace.event_store, period, end).await?;
+        let markdown = digest.render_markdown();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, &markdown)?;
+        workspace
+            .memory_index
+            .store(digest.to_memory_entry(Some(path.to_string_lossy().to_string())));
+        tracing::info!("{} ({}): {}", digest.title(), workspace.name, digest.headline());
+
+        if let Some(url) = &self.webhook {
+            self.send(url, &workspace.name, &digest, &markdown).await?;
+        }
+        Ok(())
+    }
+
+    /// 发送到 Webhook（`text` 字段兼容 Slack 等只读取纯文本的接收端）
+    async fn send(&self, url: &str, workspace: &str, digest: &Digest, markdown: &str) -> anyhow::Result<()> {
+        let body = serde_json::json!({
+            "workspace": workspace,
+            "period": digest.period,
+            "start": digest.start,
+            "end": digest.end,
+            "markdown": markdown,
+            "text": format!("{} ({})\n{}", digest.title(), workspace, digest.headline()),
+            "digest": digest,
+        });
+        self.client
+            .post(url)
+            .json(&body)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// 摘要文件路径
+fn digest_path(workspace: &Workspace, period: DigestPeriod, end: DateTime<Utc>) -> PathBuf {
+    workspace
+        .data_dir
+        .join(DIGEST_DIR)
+        .join(format!("{}-{}.md", period, end.format("%Y-%m-%d")))
+}
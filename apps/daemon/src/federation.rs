@@ -0,0 +1,113 @@
+//! 联邦服务发现
+//!
+//! 读取守护进程工作目录下的 `federation.json`（不存在时使用默认配置）：
+//! - `advertise`（默认 false）：启动 HAP 监听并在局域网 mDNS 广播
+//! - `discover`（默认 true）：发现局域网内其他 NeuroLoom HAP 服务器
+//! - `peers`：静态对端列表（`addr`，可选 `name` / `agent_id` / `tls`），用于 mDNS 到达不了的网段
+//! - `manifest`：本端能力清单（握手时回传，广播时写入 TXT 记录）
+//!
+//! 对端握手时发送的完整能力清单写回对端目录，`GET /federation/peers`（`nl federation peers`）列出全部对端。
+
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+use nl_hap::protocol::HapMessageType;
+use nl_hap::{CapabilityManifest, HapServer, MdnsDiscovery, PeerDirectory, StaticPeer};
+
+/// 联邦配置文件名
+pub const FEDERATION_FILE: &str = "federation.json";
+
+/// 联邦配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct FederationConfig {
+    #[serde(default)]
+    pub advertise: bool,
+    #[serde(default = "default_discover")]
+    pub discover: bool,
+    #[serde(default)]
+    pub peers: Vec<StaticPeer>,
+    #[serde(default)]
+    pub manifest: CapabilityManifest,
+}
+
+fn default_discover() -> bool {
+    true
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            advertise: false,
+            discover: default_discover(),
+            peers: Vec::new(),
+            manifest: CapabilityManifest::default(),
+        }
+    }
+}
+
+impl FederationConfig {
+    /// 读取配置（文件不存在时返回默认配置）
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// 启动广播与发现，返回需要在退出时关闭的 mDNS 守护
+pub fn start(config: &FederationConfig, server: Arc<HapServer>, directory: Arc<PeerDirectory>) -> Option<MdnsDiscovery> {
+    // 握手得到的完整能力清单写回目录
+    let mut messages = server.subscribe();
+    let handshakes = directory.clone();
+    tokio::spawn(async move {
+        loop {
+            match messages.recv().await {
+                Ok(msg) if msg.msg_type == HapMessageType::Handshake => {
+                    if let Some(manifest) = msg.manifest() {
+                        handshakes.record_handshake(msg.sender, None, manifest);
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    if config.advertise {
+        let listener = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = listener.start().await {
+                tracing::error!("HAP server stopped: {}", e);
+            }
+        });
+    }
+    if !config.advertise && !config.discover {
+        return None;
+    }
+
+    let hap = server.config();
+    let discovery = match MdnsDiscovery::new(hap.agent_id, directory) {
+        Ok(discovery) => discovery,
+        Err(e) => {
+            tracing::warn!("mDNS discovery unavailable: {}", e);
+            return None;
+        }
+    };
+    if config.advertise {
+        match discovery.advertise(hap.addr.port(), hap.tls.is_some(), &hap.manifest) {
+            Ok(()) => tracing::info!("Advertising HAP server on port {} via mDNS", hap.addr.port()),
+            Err(e) => tracing::warn!("mDNS advertisement failed: {}", e),
+        }
+    }
+    if config.discover {
+        if let Err(e) = discovery.browse() {
+            tracing::warn!("mDNS browsing failed: {}", e);
+        }
+    }
+    Some(discovery)
+}
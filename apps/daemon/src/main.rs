@@ -1,68 +1,354 @@
 //! NeuroLoom Daemon - Headless 后台守护进程
 
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // 初始化日志
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "neuroloom_daemon=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+mod api_keys;
+mod canvas;
+mod control;
+mod delegation;
+mod digest;
+mod federation;
+mod mcp_tools;
+mod notifications;
+mod scheduler;
+mod service;
+mod triggers;
+mod webhooks;
+mod workspace;
+
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+fn main() -> anyhow::Result<()> {
+    let options = service::ServiceOptions::from_args()?;
+    options.enter_workdir()?;
+    // 初始化日志（guard 持有到进程退出）
+    let _log_guard = service::init_logging(&options)?;
+
+    // Windows 服务由服务分发器驱动
+    #[cfg(windows)]
+    if options.service {
+        return service::windows::run_dispatcher();
+    }
+
+    tokio::runtime::Runtime::new()?.block_on(run(service::shutdown_signal(), service::notify_ready))
+}
 
+/// 初始化全部组件并运行到 `shutdown` 完成；控制面健康检查通过后调用 `ready`
+async fn run(shutdown: impl Future<Output = ()>, ready: impl FnOnce()) -> anyhow::Result<()> {
     tracing::info!("NeuroLoom Daemon starting...");
 
     // 初始化核心组件
     tracing::info!("Initializing core components...");
 
-    // 初始化事件存储
-    let event_store = nl_durable::EventStore::open("neuroloom.db").await?;
-    tracing::info!("Event store initialized");
+    // 打开工作区：每个工作区拥有独立的事件库、事件总线、记忆索引、GraphRAG 与 SOP 注册表
+    // （未完成的任务在守护进程就绪后于后台恢复）
+    let workspaces = Arc::new(workspace::WorkspaceRegistry::open(".").await?);
+    if std::env::var_os(nl_durable::encryption::MASTER_KEY_ENV).is_some_and(|key| !key.is_empty()) {
+        tracing::info!("Event store encryption enabled");
+    }
+    let default_workspace = workspaces.default_workspace().await;
+    let event_bus = default_workspace.event_bus.clone();
+    let event_store = default_workspace.event_store.clone();
+    tracing::info!("{} workspaces opened", workspaces.list().await.len());
+
+    // 订阅任务完成事件（供铁匠等后台组件响应）
+    for workspace in workspaces.list().await {
+        let mut completed = workspace.event_bus.subscribe(nl_core::event::EventKind::TaskCompleted);
+        tokio::spawn(async move {
+            while let Some(event) = completed.recv().await {
+                tracing::debug!("Task completed in {}: {}", workspace.name, event.entity_id);
+            }
+        });
+    }
+
+    // 外发通知（Slack / Discord / Webhook）
+    match notifications::Notifier::load(std::path::Path::new(notifications::NOTIFICATIONS_FILE)) {
+        Ok(Some(notifier)) => {
+            let notifier = Arc::new(notifier);
+            for workspace in workspaces.list().await {
+                notifier.watch(workspace);
+            }
+            tracing::info!("Notifications enabled ({} rules)", notifier.rule_count());
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Notifications disabled: {}", e),
+    }
+
+    // 定时调度（首次检查时按补跑策略处理停机期间错过的执行）
+    tokio::spawn(scheduler::Scheduler::new(workspaces.clone()).run());
+
+    // 日报 / 周报（设置 NEUROLOOM_DIGEST_WEBHOOK 后同时推送）
+    tokio::spawn(digest::DigestJob::new(workspaces.clone()).run());
 
     // 初始化 Actor Mesh
-    let actor_mesh = nl_durable::ActorMesh::new();
+    let actor_mesh = Arc::new(nl_durable::ActorMesh::new().with_event_bus(event_bus.clone()));
     tracing::info!("Actor mesh initialized with {} actors", actor_mesh.count().await);
 
+    // 初始化 Actor 资源配额（超额即暂停并告警）
+    let quotas = Arc::new(
+        nl_durable::QuotaManager::new(
+            nl_durable::ResourceQuota::unlimited()
+                .with_llm_tokens_per_hour(2_000_000)
+                .with_sandbox_wall_time(std::time::Duration::from_secs(6 * 3600))
+                .with_bytes_written(1 << 30),
+        )
+        .with_mesh(actor_mesh.clone())
+        .with_event_bus(event_bus.clone()),
+    );
+
     // 初始化 LLM 网关能力
     tracing::info!("LLM gateway module loaded");
 
-    // 初始化 SOP 引擎
-    let sop_engine = nl_cognitive::SopEngine::new();
-    tracing::info!("SOP engine initialized with {} workflows", sop_engine.count());
-
-    // 初始化记忆索引
-    let memory_index = nl_memory::HamtIndex::new();
-    tracing::info!("Memory index initialized");
-
-    // 初始化 GraphRAG
-    let graph_rag = nl_memory::GraphRAG::new();
-    tracing::info!("GraphRAG initialized");
-
     // 初始化认知引擎
     let mcts_engine = nl_cognitive::MctsEngine::default_engine();
     tracing::info!("MCTS engine initialized");
 
     // 初始化法庭
-    let courtroom = nl_cognitive::Courtroom::default_courtroom();
+    // 裁决快照与事件共用同一 SQLite，按需加载
+    let mut snapshot_manager = nl_durable::SnapshotManager::new(nl_cognitive::Courtroom::snapshot_strategy());
+    if let Some(pool) = event_store.lock().await.pool().cloned() {
+        snapshot_manager = snapshot_manager.with_pool(pool).await?;
+    }
+    let verdict_snapshots = Arc::new(Mutex::new(snapshot_manager));
+
+    // 定期清理旧快照，压缩已被快照覆盖的事件，并把早于保留期的月份移入分片库
+    let (prune_snapshots, prune_store) = (verdict_snapshots.clone(), event_store.clone());
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(3600));
+        loop {
+            ticker.tick().await;
+            let mut store = prune_store.lock().await;
+            match prune_snapshots.lock().await.prune_and_compact(&mut store, 3).await {
+                Ok(removed) if removed > 0 => tracing::info!("Compacted {} events covered by snapshots", removed),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Snapshot pruning failed: {}", e),
+            }
+            match store.rotate_shards(&nl_durable::ShardPolicy::default()).await {
+                Ok(rotated) => {
+                    for shard in rotated {
+                        tracing::info!("Rotated events of {} into {}", shard.month, shard.path.display());
+                    }
+                }
+                Err(e) => tracing::warn!("Event shard rotation failed: {}", e),
+            }
+        }
+    });
+    // 评分校准样本（测试结果、人工批准）随事件持久化，启动时据此恢复校准曲线
+    let calibrator = Arc::new(nl_cognitive::Calibrator::from_store(&event_store).await?);
+    let courtroom = nl_cognitive::Courtroom::default_courtroom()
+        .with_event_store(event_store.clone())
+        .with_snapshots(verdict_snapshots)
+        .with_artifacts(default_workspace.artifacts.clone())
+        .with_calibrator(calibrator);
     tracing::info!("Courtroom initialized");
 
-    // 初始化沙箱
-    let sandbox = nl_sandbox::SandboxExecutor::new();
+    // 初始化沙箱（God Mode 操作写入防篡改审计链；带任务 ID 的操作在默认工作区的任务副本中执行；
+    // 命令资源上限取自 NEUROLOOM_SANDBOX_* 环境变量，HTTP 域名白名单取自 NEUROLOOM_HTTP_ALLOWED_DOMAINS，
+    // 抓取的页面缓存到默认工作区的记忆中；浏览器在首次使用时启动，截图保存在默认工作区的数据目录）
+    let audit_log = Arc::new(nl_sandbox::AuditLog::open(event_store.clone()).await?);
+    let scratch = nl_sandbox::ScratchManager::new(
+        default_workspace.root.clone(),
+        default_workspace.data_dir.join("scratch"),
+    );
+    // 上次退出时未完成的提升：默认续做，NEUROLOOM_SCRATCH_RECOVERY=rollback 时一律回滚
+    let recovery = match std::env::var("NEUROLOOM_SCRATCH_RECOVERY").as_deref() {
+        Ok("rollback") => nl_sandbox::RecoveryPolicy::Rollback,
+        _ => nl_sandbox::RecoveryPolicy::Resume,
+    };
+    match scratch.recover(recovery).await {
+        Ok(recovered) if !recovered.is_empty() => {
+            tracing::warn!("Recovered {} interrupted scratch promotions", recovered.len())
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Scratch promotion recovery failed: {}", e),
+    }
+    // cgroup v2 的父组不能有进程：启动时先把守护进程移入叶子组
+    let mut resource_policy = nl_sandbox::SandboxPolicy::from_env();
+    if let Err(e) = resource_policy.delegate_cgroup() {
+        tracing::warn!("Sandbox resource limits unavailable: {}", e);
+    }
+    let sandbox = Arc::new(
+        nl_sandbox::SandboxExecutor::new()
+            .with_audit(audit_log)
+            .with_quotas(quotas.clone())
+            .with_scratch(Arc::new(scratch))
+            .with_resource_policy(resource_policy)
+            .with_http_policy(nl_sandbox::HttpPolicy::from_env())
+            .with_http_cache(Arc::new(nl_sandbox::HttpCache::new(
+                default_workspace.memory_index.clone(),
+                default_workspace.data_dir.join("web_cache"),
+            )))
+            .with_browser(Arc::new(nl_sandbox::BrowserSession::new(
+                nl_sandbox::BrowserConfig::from_env()
+                    .with_screenshot_dir(default_workspace.data_dir.join("screenshots")),
+            ))),
+    );
     tracing::info!("Sandbox executor initialized");
 
-    // 初始化 HAP 服务器
-    let hap_server = nl_hap::HapServer::default_server();
+    // 以 MCP 暴露沙箱、记忆检索与 GraphRAG 查询（默认工作区）
+    let mcp_server = Arc::new(
+        nl_hap::McpServer::new("neuroloom", env!("CARGO_PKG_VERSION"))
+            .with_tool(Arc::new(mcp_tools::SandboxActionTool(sandbox.clone())))
+            .with_tool(Arc::new(mcp_tools::RunCodeTool(sandbox.clone())))
+            .with_tool(Arc::new(mcp_tools::MemorySearchTool(default_workspace.memory_index.clone())))
+            .with_tool(Arc::new(mcp_tools::GraphQueryTool(default_workspace.graph_rag.clone()))),
+    );
+
+    // 控制面请求去重表与默认工作区事件共用同一 SQLite，守护进程重启后重试仍被去重
+    let mut idempotency = nl_durable::IdempotencyStore::new();
+    if let Some(pool) = event_store.lock().await.pool().cloned() {
+        idempotency = idempotency.with_pool(pool).await?;
+    }
+
+    let idempotency = Arc::new(idempotency);
+
+    // 联邦对端目录（静态对端 + mDNS 发现）
+    let federation_config = federation::FederationConfig::load(std::path::Path::new(federation::FEDERATION_FILE))
+        .unwrap_or_else(|e| {
+            tracing::warn!("Invalid {}, using defaults: {}", federation::FEDERATION_FILE, e);
+            federation::FederationConfig::default()
+        });
+    let peers = Arc::new(nl_hap::PeerDirectory::new().with_static(&federation_config.peers));
+
+    // 控制面 API Key（登记了 Key 后才启用鉴权；以 Key ID 为 Actor 的 LLM 用量计入其 token 预算，
+    // 控制面登记的新工作区在登记时开始计量）
+    let api_keys = Arc::new(api_keys::ApiKeyStore::load(std::path::Path::new(api_keys::API_KEYS_FILE))?);
+    for workspace in workspaces.list().await {
+        api_keys.meter(&workspace.event_bus);
+    }
+    api_keys.spawn_usage_flusher();
+    if api_keys.is_enabled() {
+        tracing::info!("Control API authentication enabled ({} API keys)", api_keys.list().len());
+    }
+
+    // 控制面 LLM 网关：复用 NEUROLOOM_PROXY_CONFIG 的上游，用量事件发布到默认工作区；
+    // 发送前的上下文溢出检查与工作区的上下文组装共用 `tokenizers/` 下的词表
+    let proxy_config = match std::env::var("NEUROLOOM_PROXY_CONFIG") {
+        Ok(path) => Some(nl_llm_new::black_magic_proxy::ProxyServerConfig::load(&path)?),
+        Err(_) => None,
+    };
+    let llm = match &proxy_config {
+        Some(config) => {
+            let models = nl_llm_new::ModelRegistry::with_builtin().with_tokenizers(workspaces.tokenizers());
+            let gateway = nl_llm_new::Gateway::new(nl_llm_new::GatewayConfig::default())
+                .with_event_bus(default_workspace.event_bus.clone())
+                .with_quotas(quotas.clone())
+                .with_model_registry(Arc::new(models));
+            let http = reqwest::Client::new();
+            for route in &config.routes {
+                match route.upstream.build(http.clone()) {
+                    Ok(provider) => gateway.register_provider(provider).await,
+                    Err(e) => tracing::warn!("Skipping LLM upstream {}: {}", route.name, e),
+                }
+            }
+            gateway.start_refill_timer();
+            Some(Arc::new(gateway))
+        }
+        None => None,
+    };
+
+    // 启动控制面（事件订阅、任务取消、工作区管理）
+    let mut control = control::ControlServer::new(control::ControlConfig::default(), workspaces.clone())
+        .with_idempotency(idempotency.clone())
+        .with_peers(peers.clone())
+        .with_api_keys(api_keys.clone())
+        .with_mcp(mcp_server);
+    if let Some(llm) = llm {
+        control = control.with_llm(llm);
+    }
+    let control_addr = control.config().addr;
+    tracing::info!("Control API listening on {} (MCP at /mcp)", control_addr);
+    tokio::spawn(async move {
+        if let Err(e) = control.start().await {
+            tracing::error!("Control API stopped: {}", e);
+        }
+    });
+
+    // 入站 Webhook（GitHub 等外部事件映射为任务，投递去重与控制面共用去重表）
+    match webhooks::WebhookServer::load(
+        std::path::Path::new(webhooks::WEBHOOKS_FILE),
+        workspaces.clone(),
+        idempotency.clone(),
+    ) {
+        Ok(Some(server)) => {
+            tracing::info!("Webhook listener on {} ({} hooks)", server.addr(), server.hook_count());
+            tokio::spawn(async move {
+                if let Err(e) = server.start().await {
+                    tracing::error!("Webhook listener stopped: {}", e);
+                }
+            });
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Inbound webhooks disabled: {}", e),
+    }
+
+    // 自托管反代：设置 NEUROLOOM_PROXY_CONFIG 后把上游重新暴露为 OpenAI 兼容接口
+    if let Some(config) = &proxy_config {
+        let proxy = nl_llm_new::black_magic_proxy::ProxyServer::from_config(config);
+        tracing::info!(
+            "OpenAI-compatible proxy listening on {} ({} routes)",
+            proxy.addr(),
+            proxy.route_count()
+        );
+        tokio::spawn(async move {
+            if let Err(e) = proxy.start().await {
+                tracing::error!("Proxy server stopped: {}", e);
+            }
+        });
+    }
+
+    // 初始化 HAP 服务器（签名校验；设置证书环境变量后启用 TLS / mTLS）
+    // 身份密钥与信任库位于数据目录，与启动时的当前目录无关
+    let identity_path = default_workspace.data_dir.join(nl_hap::IDENTITY_FILE);
+    let hap_identity = nl_hap::AgentIdentity::load_or_generate(&identity_path)?;
+    let hap_trust = nl_hap::TrustStore::load(default_workspace.data_dir.join(nl_hap::TRUST_FILE))?;
+    tracing::info!(
+        "HAP identity {} from {} ({} trusted agents)",
+        hap_identity.agent_id(),
+        identity_path.display(),
+        hap_trust.list().len()
+    );
+    let hap_tls = match (std::env::var("NEUROLOOM_HAP_TLS_CERT"), std::env::var("NEUROLOOM_HAP_TLS_KEY")) {
+        (Ok(cert), Ok(key)) => {
+            let tls = nl_hap::TlsConfig::new(cert, key);
+            Some(match std::env::var("NEUROLOOM_HAP_CLIENT_CA") {
+                Ok(ca) => tls.with_client_ca(ca),
+                Err(_) => tls,
+            })
+        }
+        _ => None,
+    };
+    let hap_server = Arc::new(
+        nl_hap::HapServer::new(nl_hap::server::HapServerConfig {
+            tls: hap_tls,
+            manifest: federation_config.manifest.clone(),
+            ..Default::default()
+        })
+        .with_identity(Arc::new(hap_identity))
+        .with_verifier(Arc::new(nl_hap::MessageVerifier::new(hap_trust))),
+    );
     tracing::info!("HAP server configured on {}", hap_server.config().addr);
+    let discovery = federation::start(&federation_config, hap_server.clone(), peers);
+
+    // 跟踪远程委托的进度，停滞时转派（进度事件记入默认工作区）
+    tokio::spawn(delegation::DelegationMonitor::new(hap_server.clone(), default_workspace.clone()).run());
 
+    // 控制面可用后才向服务管理器报告就绪
+    service::probe_health(control_addr, 25).await?;
+    ready();
+    workspaces.resume_unfinished().await;
     tracing::info!("NeuroLoom Daemon is ready!");
     tracing::info!("Press Ctrl+C to shutdown...");
 
     // 等待关闭信号
-    tokio::signal::ctrl_c().await?;
+    shutdown.await;
+    service::notify_stopping();
     tracing::info!("Shutting down...");
+    api_keys.flush_usage();
+    if let Some(discovery) = discovery {
+        discovery.shutdown();
+    }
 
     Ok(())
 }
@@ -0,0 +1,183 @@
+//! 以 MCP 工具形式暴露的 NeuroLoom 能力：沙箱操作、记忆检索、GraphRAG 查询
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+
+use nl_core::{NeuroLoomError, Result};
+use nl_hap::mcp::McpTool;
+use nl_memory::{GraphRAG, HamtIndex};
+use nl_sandbox::god_mode::GodModeAction;
+use nl_sandbox::SandboxExecutor;
+
+fn string_arg<'a>(arguments: &'a Value, key: &str) -> Result<&'a str> {
+    arguments[key]
+        .as_str()
+        .ok_or_else(|| NeuroLoomError::Protocol(format!("missing string argument: {}", key)))
+}
+
+/// God Mode 沙箱操作
+pub struct SandboxActionTool(pub Arc<SandboxExecutor>);
+
+#[async_trait]
+impl McpTool for SandboxActionTool {
+    fn name(&self) -> &str {
+        "sandbox_action"
+    }
+
+    fn description(&self) -> &str {
+        "Run a God Mode sandbox action, e.g. {\"ReadFile\": {\"path\": \"src/main.rs\"}}; \
+         with task_id it runs in that task's scratch copy of the workspace"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "object" },
+                "task_id": { "type": "string", "format": "uuid" },
+            },
+            "required": ["action"],
+        })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let action: GodModeAction = serde_json::from_value(arguments["action"].clone())?;
+        let result = match arguments["task_id"].as_str() {
+            Some(task_id) => {
+                let task_id = uuid::Uuid::parse_str(task_id)
+                    .map_err(|e| NeuroLoomError::Protocol(format!("invalid task_id: {}", e)))?;
+                self.0.execute_god_mode_in_scratch(task_id, action).await?
+            }
+            None => self.0.execute_god_mode(action).await?,
+        };
+        match result.error {
+            Some(error) if !result.success => Err(NeuroLoomError::Sandbox(error)),
+            _ => Ok(result.output),
+        }
+    }
+}
+
+/// 在隔离环境中执行代码
+pub struct RunCodeTool(pub Arc<SandboxExecutor>);
+
+#[async_trait]
+impl McpTool for RunCodeTool {
+    fn name(&self) -> &str {
+        "sandbox_run_code"
+    }
+
+    fn description(&self) -> &str {
+        "Execute a code snippet in an isolated micro VM"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "code": { "type": "string" },
+                "language": { "type": "string" },
+            },
+            "required": ["code", "language"],
+        })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let result = self
+            .0
+            .execute_isolated(string_arg(&arguments, "code")?, string_arg(&arguments, "language")?)
+            .await?;
+        Ok(serde_json::to_string(&json!({
+            "success": result.success,
+            "exit_code": result.exit_code,
+            "stdout": result.stdout,
+            "stderr": result.stderr,
+        }))?)
+    }
+}
+
+/// HAMT 记忆检索
+pub struct MemorySearchTool(pub Arc<HamtIndex>);
+
+#[async_trait]
+impl McpTool for MemorySearchTool {
+    fn name(&self) -> &str {
+        "memory_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search long-term memory tags and summaries"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "query": { "type": "string" } },
+            "required": ["query"],
+        })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let query = string_arg(&arguments, "query")?;
+        let index = &self.0;
+        let mut hits = index.search_tags(query);
+        for entry in index.search_summaries(query) {
+            if !hits.iter().any(|hit| hit.id == entry.id) {
+                hits.push(entry);
+            }
+        }
+        let hits: Vec<Value> = hits
+            .into_iter()
+            .map(|entry| json!({ "id": entry.id, "tag": entry.tag, "summary": entry.summary }))
+            .collect();
+        Ok(serde_json::to_string(&hits)?)
+    }
+}
+
+/// GraphRAG 代码拓扑查询
+pub struct GraphQueryTool(pub Arc<RwLock<GraphRAG>>);
+
+#[async_trait]
+impl McpTool for GraphQueryTool {
+    fn name(&self) -> &str {
+        "graph_query"
+    }
+
+    fn description(&self) -> &str {
+        "Look up a symbol or file in the code graph with its callers and callees"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "path": { "type": "string" },
+            },
+        })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let graph = self.0.read().await;
+        let node = match (arguments["name"].as_str(), arguments["path"].as_str()) {
+            (Some(name), _) => graph.find_by_name(name),
+            (None, Some(path)) => graph.find_by_path(path),
+            (None, None) => {
+                return Err(NeuroLoomError::Protocol("either name or path is required".to_string()))
+            }
+        };
+        let Some(node) = node else {
+            return Ok("null".to_string());
+        };
+        let names = |nodes: Vec<&nl_memory::graph_rag::GraphNode>| -> Vec<String> {
+            nodes.into_iter().map(|n| n.name.clone()).collect()
+        };
+        Ok(serde_json::to_string(&json!({
+            "node": node,
+            "callers": names(graph.find_callers(&node.id)),
+            "callees": names(graph.find_callees(&node.id)),
+        }))?)
+    }
+}
@@ -0,0 +1,517 @@
+//! 外发通知
+//!
+//! 按守护进程工作目录下 `notifications.json` 中的规则，把关心的事件格式化后 POST 到
+//! Slack / Discord Incoming Webhook 或通用 HTTP 地址：
+//! - `task_completed`：计划完成（含成功与否）
+//! - `verdict_failed`：法庭驳回
+//! - `budget_exceeded`：Actor 超出资源配额被暂停
+//! - `approval_needed`：任务等待人工审批
+//!
+//! 每条规则包含 `name`、`on`（上述触发条件列表）、`kind`（`slack` / `discord` / `webhook`，默认
+//! `webhook`）、`url`、可选的 `workspace`（只匹配该工作区）与 `max_per_minute`（默认 10）。
+//! 超出速率的通知直接丢弃并告警；网络错误、429 与 5xx 按指数退避重试 `max_retries` 次（默认 3），
+//! 单次等待不超过 60 秒，429 响应带 `Retry-After` 时按其等待。
+//! Webhook 地址本身就是凭据，日志中只出现规则名与主机名。
+//! 只监听守护进程启动时打开的工作区。
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use nl_core::event::{Event, EventKind};
+use nl_llm_new::token_bucket::TokenBucket;
+
+use crate::workspace::Workspace;
+
+/// 通知配置文件名
+pub const NOTIFICATIONS_FILE: &str = "notifications.json";
+
+/// 首次重试前的等待时长（之后每次翻倍）
+const RETRY_BASE: Duration = Duration::from_secs(1);
+
+/// 单次重试的最长等待
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// 单次请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Discord 消息长度上限
+const DISCORD_MAX_CHARS: usize = 2000;
+
+/// 触发条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyTrigger {
+    TaskCompleted,
+    VerdictFailed,
+    BudgetExceeded,
+    ApprovalNeeded,
+}
+
+impl NotifyTrigger {
+    /// 从事件识别触发条件
+    pub fn from_event(event: &Event) -> Option<Self> {
+        let payload = &event.payload;
+        match event.kind {
+            // 子任务完成事件不带目标，只通知计划级完成
+            EventKind::TaskCompleted if payload.get("goal").is_some() => Some(NotifyTrigger::TaskCompleted),
+            EventKind::VerdictIssued if payload["passed"] == false => Some(NotifyTrigger::VerdictFailed),
+            EventKind::ActorSuspended if payload["reason"] == "quota_exceeded" => Some(NotifyTrigger::BudgetExceeded),
+            EventKind::ApprovalRequested => Some(NotifyTrigger::ApprovalNeeded),
+            _ => None,
+        }
+    }
+}
+
+/// 通知渠道
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Slack,
+    Discord,
+    #[default]
+    Webhook,
+}
+
+/// 单条通知规则
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationRule {
+    pub name: String,
+    pub on: Vec<NotifyTrigger>,
+    #[serde(default)]
+    pub kind: ChannelKind,
+    pub url: String,
+    /// 只匹配该工作区（名称）
+    pub workspace: Option<String>,
+    /// 每分钟最多发送的通知数
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: u32,
+}
+
+fn default_max_per_minute() -> u32 {
+    10
+}
+
+/// 通知配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationConfig {
+    #[serde(default)]
+    pub rules: Vec<NotificationRule>,
+    /// 失败后的最大重试次数
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+/// 格式化后的通知
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub trigger: NotifyTrigger,
+    pub workspace: String,
+    pub title: String,
+    pub text: String,
+    pub event: Event,
+}
+
+impl Notification {
+    /// 按触发条件格式化事件
+    pub fn new(trigger: NotifyTrigger, workspace: &str, event: &Event) -> Self {
+        let payload = &event.payload;
+        let (title, text) = match trigger {
+            NotifyTrigger::TaskCompleted => {
+                let goal = payload["goal"].as_str().unwrap_or_default();
+                if payload["success"].as_bool().unwrap_or(false) {
+                    (format!("Task completed: {}", goal), format!("Plan {} finished successfully.", event.entity_id))
+                } else {
+                    (format!("Task failed: {}", goal), format!("Plan {} finished with failures.", event.entity_id))
+                }
+            }
+            NotifyTrigger::VerdictFailed => {
+                let mut text = payload["reasoning"].as_str().unwrap_or_default().to_string();
+                for suggestion in payload["suggestions"].as_array().into_iter().flatten().take(3) {
+                    text.push_str(&format!("\n• {}", suggestion.as_str().unwrap_or_default()));
+                }
+                (
+                    format!("Verdict rejected (score {:.2})", payload["score"].as_f64().unwrap_or(0.0)),
+                    text,
+                )
+            }
+            NotifyTrigger::BudgetExceeded => {
+                let resource = payload["resource"].as_str().unwrap_or("resource");
+                (
+                    format!("Budget exceeded: {}", resource),
+                    format!("Actor {} was suspended after exceeding its {} quota.", event.entity_id, resource),
+                )
+            }
+            NotifyTrigger::ApprovalNeeded => {
+                let text = ["reason", "description", "goal"]
+                    .iter()
+                    .find_map(|key| payload[*key].as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| payload.to_string());
+                (format!("Approval needed for task {}", event.entity_id), text)
+            }
+        };
+        Self {
+            trigger,
+            workspace: workspace.to_string(),
+            title,
+            text,
+            event: event.clone(),
+        }
+    }
+
+    /// 按渠道生成请求体
+    pub fn payload(&self, kind: ChannelKind) -> serde_json::Value {
+        match kind {
+            ChannelKind::Slack => serde_json::json!({
+                "text": format!("*{}* ({})\n{}", self.title, self.workspace, self.text),
+            }),
+            ChannelKind::Discord => {
+                let content = format!("**{}** ({})\n{}", self.title, self.workspace, self.text);
+                serde_json::json!({ "content": content.chars().take(DISCORD_MAX_CHARS).collect::<String>() })
+            }
+            ChannelKind::Webhook => serde_json::json!(self),
+        }
+    }
+}
+
+/// 已编译的规则（附带速率限制）
+struct Rule {
+    config: NotificationRule,
+    bucket: TokenBucket,
+}
+
+/// 通知分发器
+pub struct Notifier {
+    rules: Vec<Rule>,
+    max_retries: u32,
+    client: reqwest::Client,
+}
+
+impl Notifier {
+    /// 读取配置（文件不存在或没有规则时返回 `None`）
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::from_config(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 按配置创建（没有规则时返回 `None`）
+    pub fn from_config(config: NotificationConfig) -> anyhow::Result<Option<Self>> {
+        if config.rules.is_empty() {
+            return Ok(None);
+        }
+        let rules = config
+            .rules
+            .into_iter()
+            .map(|rule| {
+                if rule.on.is_empty() {
+                    anyhow::bail!("notification rule {}: at least one trigger is required", rule.name);
+                }
+                Ok(Rule {
+                    bucket: TokenBucket::new(rule.max_per_minute.max(1), Duration::from_secs(60)),
+                    config: rule,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Some(Self {
+            rules,
+            max_retries: config.max_retries,
+            client: reqwest::Client::new(),
+        }))
+    }
+
+    /// 规则数量
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// 监听工作区事件总线
+    pub fn watch(self: &Arc<Self>, workspace: Arc<Workspace>) {
+        let notifier = self.clone();
+        let mut events = workspace.event_bus.subscribe_all();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                notifier.dispatch(&workspace.name, &event);
+            }
+        });
+    }
+
+    /// 把事件发送到所有匹配的规则（每次发送在后台进行，不阻塞事件接收）
+    fn dispatch(self: &Arc<Self>, workspace: &str, event: &Event) {
+        let Some(trigger) = NotifyTrigger::from_event(event) else {
+            return;
+        };
+        let notification = Notification::new(trigger, workspace, event);
+        for index in self.select(workspace, &notification) {
+            let notifier = self.clone();
+            let body = notification.payload(self.rules[index].config.kind);
+            tokio::spawn(async move {
+                let rule = &notifier.rules[index].config;
+                if let Err(e) = notifier.deliver(&rule.url, &body).await {
+                    tracing::warn!("Notification rule {} ({}) failed: {}", rule.name, host(&rule.url), e);
+                }
+            });
+        }
+    }
+
+    /// 匹配触发条件与工作区、且未超出速率的规则下标；超出速率的规则丢弃本次通知
+    fn select(&self, workspace: &str, notification: &Notification) -> Vec<usize> {
+        let mut selected = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let config = &rule.config;
+            let other_workspace = config.workspace.as_deref().is_some_and(|w| w != workspace);
+            if !config.on.contains(&notification.trigger) || other_workspace {
+                continue;
+            }
+            if !rule.bucket.try_acquire() {
+                tracing::warn!("Notification rule {} rate limited, dropping: {}", config.name, notification.title);
+                continue;
+            }
+            selected.push(index);
+        }
+        selected
+    }
+
+    /// 发送请求，网络错误、429 与 5xx 按指数退避重试
+    ///
+    /// 错误信息只包含主机名，不包含完整地址。
+    async fn deliver(&self, url: &str, body: &serde_json::Value) -> anyhow::Result<()> {
+        let host = host(url);
+        let mut attempt = 0;
+        loop {
+            let (error, retry_after) = match self.client.post(url).json(body).timeout(REQUEST_TIMEOUT).send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    if !(status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                        anyhow::bail!("{} responded with {}", host, status);
+                    }
+                    let retry_after = (status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+                        .then(|| response.headers().get(reqwest::header::RETRY_AFTER))
+                        .flatten()
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| parse_retry_after(value, chrono::Utc::now()));
+                    (anyhow::anyhow!("{} responded with {}", host, status), retry_after)
+                }
+                Err(e) => (e.without_url().into(), None),
+            };
+            if attempt >= self.max_retries {
+                return Err(error);
+            }
+            tokio::time::sleep(retry_after.unwrap_or_else(|| backoff(attempt)).min(MAX_RETRY_DELAY)).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// 第 `attempt` 次失败后的退避时长（从 0 开始计数，不超过 `MAX_RETRY_DELAY`）
+fn backoff(attempt: u32) -> Duration {
+    RETRY_BASE.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_RETRY_DELAY)
+}
+
+/// 解析 `Retry-After`：秒数或 HTTP 日期
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - now).to_std().unwrap_or_default())
+}
+
+/// 日志中使用的主机名（Webhook 路径与查询参数含密钥）
+fn host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "<invalid url>".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn event(kind: EventKind, payload: serde_json::Value) -> Event {
+        Event::new(kind, Uuid::new_v4(), payload)
+    }
+
+    fn rule(name: &str, on: &[NotifyTrigger], workspace: Option<&str>, max_per_minute: u32) -> NotificationRule {
+        NotificationRule {
+            name: name.to_string(),
+            on: on.to_vec(),
+            kind: ChannelKind::Webhook,
+            url: "https://hooks.example.com/services/T000/B000/secret".to_string(),
+            workspace: workspace.map(str::to_string),
+            max_per_minute,
+        }
+    }
+
+    fn notifier(rules: Vec<NotificationRule>, max_retries: u32) -> Notifier {
+        Notifier::from_config(NotificationConfig { rules, max_retries }).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_triggers_are_recognised_from_events() {
+        let cases = [
+            (EventKind::TaskCompleted, json!({ "goal": "ship", "success": true }), Some(NotifyTrigger::TaskCompleted)),
+            (EventKind::TaskCompleted, json!({ "subtask": "step" }), None),
+            (EventKind::VerdictIssued, json!({ "passed": false }), Some(NotifyTrigger::VerdictFailed)),
+            (EventKind::VerdictIssued, json!({ "passed": true }), None),
+            (EventKind::ActorSuspended, json!({ "reason": "quota_exceeded" }), Some(NotifyTrigger::BudgetExceeded)),
+            (EventKind::ActorSuspended, json!({ "reason": "manual" }), None),
+            (EventKind::ApprovalRequested, json!({}), Some(NotifyTrigger::ApprovalNeeded)),
+            (EventKind::NodeCreated, json!({}), None),
+        ];
+        for (kind, payload, expected) in cases {
+            let recognised = NotifyTrigger::from_event(&event(kind.clone(), payload.clone()));
+            assert_eq!(recognised, expected, "{:?} {}", kind, payload);
+        }
+        assert!(Notifier::from_config(NotificationConfig { rules: Vec::new(), max_retries: 3 }).unwrap().is_none());
+        assert!(Notifier::from_config(NotificationConfig { rules: vec![rule("none", &[], None, 1)], max_retries: 3 })
+            .is_err());
+    }
+
+    #[test]
+    fn test_payload_formatting_per_channel() {
+        let verdict = event(
+            EventKind::VerdictIssued,
+            json!({
+                "passed": false,
+                "score": 0.25,
+                "reasoning": "Missing tests",
+                "suggestions": ["a", "b", "c", "d"],
+            }),
+        );
+        let notification = Notification::new(NotifyTrigger::VerdictFailed, "alpha", &verdict);
+        assert_eq!(notification.title, "Verdict rejected (score 0.25)");
+        assert_eq!(notification.text, "Missing tests\n• a\n• b\n• c");
+        assert_eq!(
+            notification.payload(ChannelKind::Slack),
+            json!({ "text": "*Verdict rejected (score 0.25)* (alpha)\nMissing tests\n• a\n• b\n• c" })
+        );
+        let webhook = notification.payload(ChannelKind::Webhook);
+        assert_eq!(webhook["trigger"], "verdict_failed");
+        assert_eq!(webhook["workspace"], "alpha");
+        assert_eq!(webhook["event"]["id"], json!(verdict.id));
+
+        let failed = event(EventKind::TaskCompleted, json!({ "goal": "ship it", "success": false }));
+        let notification = Notification::new(NotifyTrigger::TaskCompleted, "alpha", &failed);
+        assert_eq!(notification.title, "Task failed: ship it");
+
+        // Discord 消息截断到长度上限（按字符）
+        let approval = event(EventKind::ApprovalRequested, json!({ "reason": "删".repeat(3000) }));
+        let notification = Notification::new(NotifyTrigger::ApprovalNeeded, "alpha", &approval);
+        let content = notification.payload(ChannelKind::Discord)["content"].as_str().unwrap().to_string();
+        assert_eq!(content.chars().count(), DISCORD_MAX_CHARS);
+        assert!(content.starts_with(&format!("**Approval needed for task {}** (alpha)\n", approval.entity_id)));
+
+        // 缺少说明字段时退回完整载荷
+        let bare = event(EventKind::ApprovalRequested, json!({ "tool": "rm" }));
+        assert_eq!(Notification::new(NotifyTrigger::ApprovalNeeded, "alpha", &bare).text, r#"{"tool":"rm"}"#);
+    }
+
+    #[test]
+    fn test_rules_match_trigger_and_workspace_and_are_rate_limited() {
+        let notifier = notifier(
+            vec![
+                rule("all", &[NotifyTrigger::TaskCompleted, NotifyTrigger::VerdictFailed], None, 2),
+                rule("alpha-only", &[NotifyTrigger::TaskCompleted], Some("alpha"), 10),
+                rule("budget", &[NotifyTrigger::BudgetExceeded], None, 10),
+            ],
+            0,
+        );
+        let completed = event(EventKind::TaskCompleted, json!({ "goal": "g", "success": true }));
+        let notification = |workspace: &str| Notification::new(NotifyTrigger::TaskCompleted, workspace, &completed);
+
+        assert_eq!(notifier.select("alpha", &notification("alpha")), vec![0, 1]);
+        assert_eq!(notifier.select("beta", &notification("beta")), vec![0]);
+        // 第三次超出 `all` 的每分钟上限，只丢弃该规则的通知
+        assert_eq!(notifier.select("alpha", &notification("alpha")), vec![1]);
+
+        let suspended = event(EventKind::ActorSuspended, json!({ "reason": "quota_exceeded" }));
+        let budget = Notification::new(NotifyTrigger::BudgetExceeded, "beta", &suspended);
+        assert_eq!(notifier.select("beta", &budget), vec![2]);
+    }
+
+    #[test]
+    fn test_backoff_is_capped_and_retry_after_is_parsed() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(6), MAX_RETRY_DELAY);
+        assert_eq!(backoff(40), MAX_RETRY_DELAY);
+        assert_eq!(backoff(u32::MAX), MAX_RETRY_DELAY);
+
+        let now = chrono::DateTime::parse_from_rfc3339("2026-10-16T07:28:00Z").unwrap().to_utc();
+        assert_eq!(parse_retry_after(" 7 ", now), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("Fri, 16 Oct 2026 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Fri, 16 Oct 2026 07:27:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+
+        assert_eq!(host("https://hooks.slack.com/services/T000/B000/secret"), "hooks.slack.com");
+        assert_eq!(host("not a url"), "<invalid url>");
+    }
+
+    /// 依次返回给定状态码的本地 Webhook，返回地址与请求计数
+    async fn webhook(responses: Vec<(StatusCode, Option<&'static str>)>) -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = axum::Router::new().route(
+            "/hook/secret-token",
+            post(move || async move {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let (status, retry_after) = responses[n.min(responses.len() - 1)];
+                let mut headers = HeaderMap::new();
+                if let Some(value) = retry_after {
+                    headers.insert("retry-after", value.parse().unwrap());
+                }
+                (status, headers)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook/secret-token", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, calls)
+    }
+
+    #[tokio::test]
+    async fn test_deliver_honours_retry_after_and_keeps_url_out_of_errors() {
+        let retrying = notifier(vec![rule("r", &[NotifyTrigger::TaskCompleted], None, 10)], 2);
+
+        // 429 带 `Retry-After: 0` 时立即重试，而不是按退避等待
+        let (url, calls) = webhook(vec![(StatusCode::TOO_MANY_REQUESTS, Some("0")), (StatusCode::OK, None)]).await;
+        let started = std::time::Instant::now();
+        retrying.deliver(&url, &json!({})).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() < RETRY_BASE);
+
+        // 4xx 不重试，错误中只有主机名
+        let (url, calls) = webhook(vec![(StatusCode::NOT_FOUND, None)]).await;
+        let error = retrying.deliver(&url, &json!({})).await.unwrap_err().to_string();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(error, "127.0.0.1 responded with 404 Not Found");
+
+        // 连接失败的错误同样不含地址
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = format!("http://{}/hook/secret-token", listener.local_addr().unwrap());
+        drop(listener);
+        let quick = notifier(vec![rule("r", &[NotifyTrigger::TaskCompleted], None, 10)], 0);
+        let error = quick.deliver(&closed, &json!({})).await.unwrap_err().to_string();
+        assert!(!error.contains("secret-token"), "{}", error);
+    }
+}
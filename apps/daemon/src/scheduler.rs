@@ -0,0 +1,153 @@
+//! 定时调度器
+//!
+//! 每隔 `TICK` 检查所有工作区的调度，到期时把目标交给该工作区的编排器执行：
+//! - SOP 工作流：构造只含一个子任务的计划并绑定该工作流（工作流退役时由 System 2 接管）
+//! - 编排目标：配置了规划器时分解执行，否则作为单个子任务交给 System 2
+//! - 同一调度的上一次执行尚未结束时跳过本次触发并发布 `ScheduleSkipped`，避免重叠执行
+//! - 启动后的首次检查即按各调度的补跑策略处理停机期间错过的执行
+//! - `run_target` 也供文件触发器与入站 Webhook 复用，执行期间不占用工作区编排器的锁
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use nl_cognitive::orchestrator::SubTask;
+use nl_cognitive::{OrchestrationResult, Orchestrator, TaskPlan};
+use nl_core::event::{Event, EventKind};
+use nl_durable::{Schedule, ScheduleTarget};
+
+use crate::workspace::{Workspace, WorkspaceRegistry};
+
+/// 检查间隔
+const TICK: Duration = Duration::from_secs(30);
+
+/// 定时调度器
+pub struct Scheduler {
+    workspaces: Arc<WorkspaceRegistry>,
+    /// 正在执行的调度
+    running: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+impl Scheduler {
+    /// 创建调度器
+    pub fn new(workspaces: Arc<WorkspaceRegistry>) -> Self {
+        Self {
+            workspaces,
+            running: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// 持续运行（由调用方 spawn）
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(TICK);
+        loop {
+            ticker.tick().await;
+            self.tick().await;
+        }
+    }
+
+    /// 检查一轮到期调度
+    async fn tick(&self) {
+        let now = Utc::now();
+        for workspace in self.workspaces.list().await {
+            for mut schedule in workspace.schedules.list().await {
+                let previous = schedule.next_run_at;
+                let runs = schedule.take_due(now);
+                if schedule.next_run_at != previous {
+                    if let Err(e) = workspace.schedules.update(&schedule).await {
+                        tracing::warn!("Failed to update schedule {}: {}", schedule.name, e);
+                    }
+                }
+                if runs == 0 {
+                    continue;
+                }
+
+                if !self.running.lock().unwrap().insert(schedule.id) {
+                    tracing::info!("Schedule {} skipped: previous run still in progress", schedule.name);
+                    publish(
+                        &workspace,
+                        EventKind::ScheduleSkipped,
+                        &schedule,
+                        serde_json::json!({ "reason": "previous run still in progress" }),
+                    )
+                    .await;
+                    continue;
+                }
+                let running = self.running.clone();
+                let workspace = workspace.clone();
+                tokio::spawn(async move {
+                    execute(&workspace, &schedule, runs).await;
+                    running.lock().unwrap().remove(&schedule.id);
+                });
+            }
+        }
+    }
+}
+
+/// 执行调度（补跑多次时依次执行）
+async fn execute(workspace: &Workspace, schedule: &Schedule, runs: usize) {
+    for run in 1..=runs {
+        publish(
+            workspace,
+            EventKind::ScheduleTriggered,
+            schedule,
+            serde_json::json!({ "run": run, "runs": runs }),
+        )
+        .await;
+        match run_target(&workspace.orchestrator, &schedule.target).await {
+            Ok(result) => tracing::info!(
+                "Schedule {} ({}) finished in {}: success={}",
+                schedule.name,
+                schedule.target,
+                workspace.name,
+                result.success
+            ),
+            Err(e) => tracing::warn!("Schedule {} failed in {}: {}", schedule.name, workspace.name, e),
+        }
+    }
+}
+
+/// 交给编排器执行调度目标（调度器、文件触发器与入站 Webhook 共用）
+///
+/// 只在 fork 时持有工作区编排器的锁，同一工作区的多个目标并发执行。
+pub async fn run_target(
+    orchestrator: &tokio::sync::Mutex<Orchestrator>,
+    target: &ScheduleTarget,
+) -> nl_core::Result<OrchestrationResult> {
+    let mut orchestrator = orchestrator.lock().await.fork();
+    match target {
+        ScheduleTarget::Goal(goal) if orchestrator.has_planner() => orchestrator.run(goal).await,
+        target => orchestrator.execute_plan(plan_for(target)).await,
+    }
+}
+
+/// 为调度目标构造单子任务计划
+fn plan_for(target: &ScheduleTarget) -> TaskPlan {
+    match target {
+        ScheduleTarget::Workflow(workflow) => {
+            let mut plan = TaskPlan::new(format!("Scheduled run of {}", workflow));
+            plan.add(
+                SubTask::new(workflow.clone(), format!("Run SOP workflow {}", workflow))
+                    .with_workflow(workflow.clone()),
+            );
+            plan
+        }
+        ScheduleTarget::Goal(goal) => {
+            let mut plan = TaskPlan::new(goal.clone());
+            plan.add(SubTask::new(goal.clone(), goal.clone()));
+            plan
+        }
+    }
+}
+
+async fn publish(workspace: &Workspace, kind: EventKind, schedule: &Schedule, mut payload: serde_json::Value) {
+    payload["schedule"] = serde_json::json!(schedule.name);
+    payload["target"] = serde_json::json!(schedule.target);
+    let event = Event::new(kind, schedule.id, payload);
+    if let Err(e) = workspace.event_store.lock().await.append(event).await {
+        tracing::warn!("Failed to record schedule event: {}", e);
+    }
+}
@@ -0,0 +1,319 @@
+//! 系统服务模式
+//!
+//! 守护进程可由 systemd 或 Windows 服务管理器托管（`nl daemon install` 负责注册）：
+//! - 命令行：`--service`（服务模式）、`--log-dir <dir>`、`--workdir <dir>`
+//! - 日志按天轮转写入文件（服务模式默认 `logs/`，也可用 `NEUROLOOM_LOG_DIR` 指定）
+//! - 日志写出前按工作目录 `redaction.json` 的规则脱敏（密钥、Cookie、邮箱等）
+//! - 启动后探测控制面 `/health`，通过后才向服务管理器报告就绪
+//! - systemd：`Type=notify` 的 READY/STOPPING 通知与看门狗心跳；SIGTERM 触发优雅关闭
+//! - Windows：通过服务分发器运行，响应 Stop/Shutdown 控制
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Rotation, RollingFileAppender};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use nl_durable::redaction::{RedactionConfig, REDACTION_FILE};
+use nl_durable::{RedactingWriter, Redactor};
+
+/// 服务名称（systemd unit、Windows 服务与日志文件前缀共用）
+pub const SERVICE_NAME: &str = "neuroloom-daemon";
+
+/// 保留的日志文件数
+const MAX_LOG_FILES: usize = 14;
+
+/// 守护进程启动选项
+#[derive(Debug, Clone, Default)]
+pub struct ServiceOptions {
+    /// 是否由服务管理器托管
+    pub service: bool,
+    /// 日志目录（为空时只输出到标准输出）
+    pub log_dir: Option<PathBuf>,
+    /// 工作目录（数据库与身份文件相对于此目录）
+    pub workdir: Option<PathBuf>,
+}
+
+impl ServiceOptions {
+    /// 解析命令行参数
+    pub fn from_args() -> anyhow::Result<Self> {
+        let mut options = Self {
+            log_dir: std::env::var_os("NEUROLOOM_LOG_DIR").map(PathBuf::from),
+            ..Self::default()
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--service" => options.service = true,
+                "--log-dir" => options.log_dir = Some(next_path(&mut args, "--log-dir")?),
+                "--workdir" => options.workdir = Some(next_path(&mut args, "--workdir")?),
+                other => anyhow::bail!("unknown argument: {}", other),
+            }
+        }
+        if options.service && options.log_dir.is_none() {
+            options.log_dir = Some(PathBuf::from("logs"));
+        }
+        Ok(options)
+    }
+
+    /// 切换到工作目录
+    pub fn enter_workdir(&self) -> anyhow::Result<()> {
+        if let Some(dir) = &self.workdir {
+            std::env::set_current_dir(dir)?;
+        }
+        Ok(())
+    }
+}
+
+fn next_path(args: &mut impl Iterator<Item = String>, flag: &str) -> anyhow::Result<PathBuf> {
+    args.next()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("missing value for {}", flag))
+}
+
+/// 初始化日志；配置日志目录时额外写入按天轮转的日志文件
+///
+/// 返回的 guard 需持有到进程退出，否则缓冲中的日志会丢失。
+pub fn init_logging(options: &ServiceOptions) -> anyhow::Result<Option<WorkerGuard>> {
+    let redactor = Redactor::from_config(&RedactionConfig::load(Path::new(REDACTION_FILE))?)?;
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "neuroloom_daemon=debug".into());
+    let stdout_redactor = redactor.clone();
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(move || {
+            RedactingWriter::new(stdout_redactor.clone(), std::io::stdout())
+        }));
+
+    let Some(dir) = &options.log_dir else {
+        registry.init();
+        return Ok(None);
+    };
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(SERVICE_NAME)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    registry
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || RedactingWriter::new(redactor.clone(), writer.clone())),
+        )
+        .init();
+    Ok(Some(guard))
+}
+
+/// 探测控制面健康检查，直到返回 200 或超过重试次数
+pub async fn probe_health(addr: SocketAddr, attempts: u32) -> anyhow::Result<()> {
+    let mut last_error = None;
+    for _ in 0..attempts {
+        match get_health(addr).await {
+            Ok(200) => return Ok(()),
+            Ok(status) => last_error = Some(anyhow::anyhow!("health check returned HTTP {}", status)),
+            Err(e) => last_error = Some(e),
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("health check not attempted")))
+}
+
+async fn get_health(addr: SocketAddr) -> anyhow::Result<u16> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let request = format!(
+        "GET /health HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        addr
+    );
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("malformed health response"))
+}
+
+/// 向服务管理器报告就绪，并在启用看门狗时定期发送心跳
+#[cfg(unix)]
+pub fn notify_ready() {
+    if let Err(e) = sd_notify::notify(false, &[sd_notify::NotifyState::Ready]) {
+        tracing::warn!("systemd ready notification failed: {}", e);
+    }
+    let mut usec = 0;
+    if sd_notify::watchdog_enabled(false, &mut usec) {
+        let interval = Duration::from_micros(usec / 2);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Watchdog]);
+            }
+        });
+    }
+}
+
+/// 向服务管理器报告正在停止
+#[cfg(unix)]
+pub fn notify_stopping() {
+    let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Stopping]);
+}
+
+/// 向服务管理器报告就绪（非 systemd 平台无需通知）
+#[cfg(not(unix))]
+pub fn notify_ready() {}
+
+/// 向服务管理器报告正在停止
+#[cfg(not(unix))]
+pub fn notify_stopping() {}
+
+/// 等待关闭信号（Ctrl+C 或 SIGTERM）
+#[cfg(unix)]
+pub async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            tracing::warn!("failed to listen for SIGTERM: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+/// 等待关闭信号（Ctrl+C）
+#[cfg(not(unix))]
+pub async fn shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Windows 服务入口
+#[cfg(windows)]
+pub mod windows {
+    use std::ffi::OsString;
+    use std::time::Duration;
+
+    use windows_service::service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    };
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+    use windows_service::{define_windows_service, service_dispatcher};
+
+    use super::SERVICE_NAME;
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    /// 交给服务分发器运行，阻塞直到服务停止
+    pub fn run_dispatcher() -> anyhow::Result<()> {
+        service_dispatcher::start(SERVICE_NAME, ffi_service_main)?;
+        Ok(())
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        if let Err(e) = run_service() {
+            tracing::error!("Service failed: {}", e);
+        }
+    }
+
+    fn status(state: ServiceState, accepted: ServiceControlAccept, exit_code: u32) -> ServiceStatus {
+        ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted: accepted,
+            exit_code: ServiceExitCode::Win32(exit_code),
+            checkpoint: 0,
+            wait_hint: Duration::from_secs(60),
+            process_id: None,
+        }
+    }
+
+    fn run_service() -> anyhow::Result<()> {
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+        let mut shutdown_tx = Some(shutdown_tx);
+        let handle = service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                if let Some(tx) = shutdown_tx.take() {
+                    let _ = tx.send(());
+                }
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })?;
+        handle.set_service_status(status(ServiceState::StartPending, ServiceControlAccept::empty(), 0))?;
+
+        let runtime = tokio::runtime::Runtime::new()?;
+        let result = runtime.block_on(crate::run(
+            async {
+                let _ = shutdown_rx.await;
+            },
+            move || {
+                let running = status(
+                    ServiceState::Running,
+                    ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+                    0,
+                );
+                if let Err(e) = handle.set_service_status(running) {
+                    tracing::warn!("failed to report service running: {}", e);
+                }
+            },
+        ));
+
+        let exit_code = if result.is_ok() { 0 } else { 1 };
+        handle.set_service_status(status(ServiceState::Stopped, ServiceControlAccept::empty(), exit_code))?;
+        result
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_notify_ready_sends_ready_then_watchdog_heartbeats() {
+        let path = std::env::temp_dir().join(format!("nl_notify_{}.sock", uuid::Uuid::new_v4()));
+        let socket = tokio::net::UnixDatagram::bind(&path).unwrap();
+        std::env::set_var("NOTIFY_SOCKET", &path);
+        std::env::set_var("WATCHDOG_USEC", "100000");
+        std::env::set_var("WATCHDOG_PID", std::process::id().to_string());
+
+        let recv = || async {
+            let mut buf = [0u8; 256];
+            let n = tokio::time::timeout(Duration::from_secs(5), socket.recv(&mut buf))
+                .await
+                .expect("no notification")
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).trim().to_string()
+        };
+
+        notify_ready();
+        assert_eq!(recv().await, "READY=1");
+        // 心跳间隔为 WATCHDOG_USEC 的一半，首个心跳立即发出
+        assert_eq!(recv().await, "WATCHDOG=1");
+        assert_eq!(recv().await, "WATCHDOG=1");
+
+        notify_stopping();
+        let mut messages = Vec::new();
+        while messages.last().map(String::as_str) != Some("STOPPING=1") {
+            messages.push(recv().await);
+        }
+        assert!(messages.iter().all(|m| m == "WATCHDOG=1" || m == "STOPPING=1"), "{:?}", messages);
+
+        for key in ["NOTIFY_SOCKET", "WATCHDOG_USEC", "WATCHDOG_PID"] {
+            std::env::remove_var(key);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+}
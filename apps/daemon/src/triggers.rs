@@ -0,0 +1,387 @@
+//! 文件触发器
+//!
+//! 监听工作区目录，匹配的文件变化在防抖后启动绑定的 SOP 工作流或编排目标
+//! （如“src/ 下任意 *.rs 变化时重跑 lint 工作流”）：
+//! - 绑定写在工作区数据目录的 `triggers.json`，每项包含 `name`、`patterns`、可选的 `exclude`、
+//!   `workflow` 与 `goal` 二选一，以及 `debounce_ms`（默认 500）
+//! - 路径按相对工作区根目录匹配 glob；数据目录 `.neuroloom/` 与 `.git/` 始终忽略
+//! - 防回环：任一触发器执行期间及结束后的静默期内，工作区的文件变化对所有触发器一律丢弃，
+//!   因此工作流写入的文件既不会再次触发自己，也不会经由其他触发器互相触发
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::{RecursiveMode, Watcher};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use nl_core::event::{Event, EventKind};
+use nl_durable::{EventStore, ScheduleTarget};
+
+use crate::scheduler::run_target;
+
+/// 触发器配置文件名
+pub const TRIGGERS_FILE: &str = "triggers.json";
+
+/// 始终忽略的路径
+const IGNORED: [&str; 2] = [".neuroloom/**", ".git/**"];
+
+/// 执行结束后的最短静默期
+const MIN_QUIET: Duration = Duration::from_secs(2);
+
+/// 单条触发器配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct TriggerConfig {
+    pub name: String,
+    /// 匹配的 glob（相对工作区根目录）
+    pub patterns: Vec<String>,
+    /// 排除的 glob
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub workflow: Option<String>,
+    pub goal: Option<String>,
+    /// 防抖时长（毫秒）
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+fn default_debounce_ms() -> u64 {
+    500
+}
+
+/// 编译后的触发器
+#[derive(Debug)]
+pub struct Trigger {
+    pub name: String,
+    pub target: ScheduleTarget,
+    pub debounce: Duration,
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+impl Trigger {
+    /// 编译配置
+    pub fn compile(config: TriggerConfig) -> anyhow::Result<Self> {
+        let target = match (config.workflow, config.goal) {
+            (Some(workflow), None) => ScheduleTarget::Workflow(workflow),
+            (None, Some(goal)) => ScheduleTarget::Goal(goal),
+            _ => anyhow::bail!("trigger {}: exactly one of workflow or goal is required", config.name),
+        };
+        if config.patterns.is_empty() {
+            anyhow::bail!("trigger {}: at least one pattern is required", config.name);
+        }
+        let ignored = IGNORED.iter().map(|p| p.to_string());
+        Ok(Self {
+            name: config.name,
+            target,
+            debounce: Duration::from_millis(config.debounce_ms),
+            include: glob_set(config.patterns)?,
+            exclude: glob_set(config.exclude.into_iter().chain(ignored))?,
+        })
+    }
+
+    /// 相对路径是否匹配
+    pub fn matches(&self, path: &Path) -> bool {
+        self.include.is_match(path) && !self.exclude.is_match(path)
+    }
+
+    /// 执行结束后的静默期
+    fn quiet_period(&self) -> Duration {
+        self.debounce.max(MIN_QUIET)
+    }
+}
+
+fn glob_set(patterns: impl IntoIterator<Item = String>) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(&pattern)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// 读取触发器配置（文件不存在时为空）
+pub fn load(path: &Path) -> anyhow::Result<Vec<Trigger>> {
+    let configs: Vec<TriggerConfig> = match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    configs.into_iter().map(Trigger::compile).collect()
+}
+
+/// 触发器运行状态
+#[derive(Default)]
+struct TriggerState {
+    /// 防抖截止时间与累计的变化路径
+    pending: Option<(Instant, Vec<PathBuf>)>,
+    running: bool,
+    /// 静默期截止时间
+    quiet_until: Option<Instant>,
+}
+
+impl TriggerState {
+    fn suppressed(&self, now: Instant) -> bool {
+        self.running || self.quiet_until.is_some_and(|t| now < t)
+    }
+}
+
+/// 工作区全部触发器的运行状态（按触发器下标）
+#[derive(Default)]
+struct TriggerStates {
+    states: HashMap<usize, TriggerState>,
+}
+
+impl TriggerStates {
+    /// 是否有触发器正在执行或处于静默期
+    ///
+    /// 此时的变化可能来自守护进程自己的工作流，对所有触发器都不计入，避免触发器之间互相触发。
+    fn suppressed(&self, now: Instant) -> bool {
+        self.states.values().any(|s| s.suppressed(now))
+    }
+
+    /// 记录一个相对路径的变化，匹配的触发器重新开始防抖
+    fn observe(&mut self, triggers: &[Trigger], relative: &Path, now: Instant) {
+        let matching: Vec<usize> = (0..triggers.len()).filter(|&i| triggers[i].matches(relative)).collect();
+        if matching.is_empty() {
+            return;
+        }
+        if self.suppressed(now) {
+            tracing::debug!("Ignored change to {} while a trigger is active (loop prevention)", relative.display());
+            return;
+        }
+        for index in matching {
+            let state = self.states.entry(index).or_default();
+            let (deadline, changed) = state.pending.get_or_insert_with(|| (now, Vec::new()));
+            *deadline = now + triggers[index].debounce;
+            if !changed.iter().any(|p| p == relative) {
+                changed.push(relative.to_path_buf());
+            }
+        }
+    }
+
+    /// 最早的防抖截止时间
+    fn next_deadline(&self) -> Option<Instant> {
+        self.states.values().filter_map(|s| s.pending.as_ref().map(|(t, _)| *t)).min()
+    }
+
+    /// 取出防抖已到期的触发器并标记为执行中
+    fn take_due(&mut self, now: Instant) -> Vec<(usize, Vec<PathBuf>)> {
+        let mut due: Vec<_> = self
+            .states
+            .iter_mut()
+            .filter(|(_, s)| s.pending.as_ref().is_some_and(|(t, _)| *t <= now))
+            .map(|(index, s)| {
+                s.running = true;
+                (*index, s.pending.take().map(|(_, paths)| paths).unwrap_or_default())
+            })
+            .collect();
+        due.sort_by_key(|(index, _)| *index);
+        due
+    }
+
+    /// 触发器执行结束，进入静默期
+    fn finish(&mut self, index: usize, quiet: Duration, now: Instant) {
+        let state = self.states.entry(index).or_default();
+        state.running = false;
+        state.quiet_until = Some(now + quiet);
+    }
+}
+
+/// 监听工作区根目录；没有配置触发器时不启动监听
+pub fn watch(
+    workspace: &str,
+    root: &Path,
+    triggers: Vec<Trigger>,
+    orchestrator: Arc<tokio::sync::Mutex<nl_cognitive::Orchestrator>>,
+    event_store: Arc<tokio::sync::Mutex<EventStore>>,
+) -> anyhow::Result<()> {
+    if triggers.is_empty() {
+        return Ok(());
+    }
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+        Ok(event) if !matches!(event.kind, notify::EventKind::Access(_)) => {
+            let _ = tx.send(event.paths);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("File watcher error: {}", e),
+    })?;
+    watcher.watch(root, RecursiveMode::Recursive)?;
+    tracing::info!("Watching {} with {} triggers ({})", root.display(), triggers.len(), workspace);
+
+    let root = root.to_path_buf();
+    let workspace = workspace.to_string();
+    let triggers: Arc<Vec<Trigger>> = Arc::new(triggers);
+    let states: Arc<Mutex<TriggerStates>> = Arc::default();
+    tokio::spawn(async move {
+        // 监听器随任务存活
+        let _watcher = watcher;
+        loop {
+            let deadline = states.lock().unwrap().next_deadline();
+            tokio::select! {
+                paths = rx.recv() => {
+                    let Some(paths) = paths else { break };
+                    let now = Instant::now();
+                    let mut states = states.lock().unwrap();
+                    for path in paths {
+                        if let Ok(relative) = path.strip_prefix(&root) {
+                            states.observe(&triggers, relative, now);
+                        }
+                    }
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let due = states.lock().unwrap().take_due(Instant::now());
+                    for (index, changed) in due {
+                        let (triggers, states) = (triggers.clone(), states.clone());
+                        let (orchestrator, event_store) = (orchestrator.clone(), event_store.clone());
+                        let workspace = workspace.clone();
+                        tokio::spawn(async move {
+                            let trigger = &triggers[index];
+                            fire(&workspace, trigger, changed, &orchestrator, &event_store).await;
+                            states.lock().unwrap().finish(index, trigger.quiet_period(), Instant::now());
+                        });
+                    }
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// 执行触发器绑定的目标
+async fn fire(
+    workspace: &str,
+    trigger: &Trigger,
+    changed: Vec<PathBuf>,
+    orchestrator: &tokio::sync::Mutex<nl_cognitive::Orchestrator>,
+    event_store: &tokio::sync::Mutex<EventStore>,
+) {
+    tracing::info!("Trigger {} fired in {} ({} changed files)", trigger.name, workspace, changed.len());
+    let event = Event::new(
+        EventKind::WatchTriggered,
+        uuid::Uuid::new_v4(),
+        serde_json::json!({ "trigger": trigger.name, "target": trigger.target, "changed": changed }),
+    );
+    if let Err(e) = event_store.lock().await.append(event).await {
+        tracing::warn!("Failed to record trigger event: {}", e);
+    }
+    match run_target(orchestrator, &trigger.target).await {
+        Ok(result) => tracing::info!("Trigger {} finished: success={}", trigger.name, result.success),
+        Err(e) => tracing::warn!("Trigger {} failed: {}", trigger.name, e),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str, patterns: &[&str]) -> TriggerConfig {
+        TriggerConfig {
+            name: name.to_string(),
+            patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            exclude: Vec::new(),
+            workflow: Some(format!("{}-workflow", name)),
+            goal: None,
+            debounce_ms: 100,
+        }
+    }
+
+    #[test]
+    fn test_compile_requires_one_target_and_a_pattern() {
+        let both = TriggerConfig { goal: Some("lint".to_string()), ..config("both", &["**/*.rs"]) };
+        assert!(Trigger::compile(both).unwrap_err().to_string().contains("exactly one of workflow or goal"));
+        let neither = TriggerConfig { workflow: None, ..config("neither", &["**/*.rs"]) };
+        assert!(Trigger::compile(neither).is_err());
+        assert!(Trigger::compile(config("empty", &[])).unwrap_err().to_string().contains("at least one pattern"));
+        assert!(Trigger::compile(config("bad", &["src/[*.rs"])).is_err());
+
+        let goal = TriggerConfig { workflow: None, goal: Some("lint".to_string()), ..config("goal", &["*.md"]) };
+        let trigger = Trigger::compile(goal).unwrap();
+        assert!(matches!(trigger.target, ScheduleTarget::Goal(ref g) if g == "lint"));
+        assert_eq!(trigger.debounce, Duration::from_millis(100));
+        assert_eq!(trigger.quiet_period(), MIN_QUIET);
+    }
+
+    #[test]
+    fn test_matches_excludes_and_always_ignores_data_and_git_dirs() {
+        let mut all = config("all", &["**/*.rs", "**"]);
+        all.exclude = vec!["target/**".to_string()];
+        let trigger = Trigger::compile(all).unwrap();
+        assert!(trigger.matches(Path::new("src/main.rs")));
+        assert!(trigger.matches(Path::new("README.md")));
+        assert!(!trigger.matches(Path::new("target/debug/main.rs")));
+        assert!(!trigger.matches(Path::new(".neuroloom/neuroloom.db")));
+        assert!(!trigger.matches(Path::new(".neuroloom/triggers.json")));
+        assert!(!trigger.matches(Path::new(".git/index")));
+        assert!(!trigger.matches(Path::new(".git/refs/heads/main")));
+
+        let rust = Trigger::compile(config("rust", &["src/**/*.rs"])).unwrap();
+        assert!(rust.matches(Path::new("src/a/b.rs")));
+        assert!(!rust.matches(Path::new("tests/a.rs")));
+    }
+
+    #[test]
+    fn test_trigger_state_suppressed_while_running_and_during_quiet_period() {
+        let now = Instant::now();
+        assert!(!TriggerState::default().suppressed(now));
+        let running = TriggerState { running: true, ..Default::default() };
+        assert!(running.suppressed(now));
+        let quiet = TriggerState { quiet_until: Some(now + Duration::from_secs(1)), ..Default::default() };
+        assert!(quiet.suppressed(now));
+        assert!(!quiet.suppressed(now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_changes_debounce_and_coalesce_per_trigger() {
+        let triggers = vec![Trigger::compile(config("rust", &["**/*.rs"])).unwrap()];
+        let mut states = TriggerStates::default();
+        let start = Instant::now();
+        states.observe(&triggers, Path::new("src/a.rs"), start);
+        states.observe(&triggers, Path::new("notes.txt"), start);
+        states.observe(&triggers, Path::new("src/b.rs"), start + Duration::from_millis(50));
+        states.observe(&triggers, Path::new("src/a.rs"), start + Duration::from_millis(60));
+
+        // 每次变化都推迟截止时间
+        assert_eq!(states.next_deadline(), Some(start + Duration::from_millis(160)));
+        assert!(states.take_due(start + Duration::from_millis(150)).is_empty());
+        let due = states.take_due(start + Duration::from_millis(160));
+        assert_eq!(due, vec![(0, vec![PathBuf::from("src/a.rs"), PathBuf::from("src/b.rs")])]);
+        assert_eq!(states.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_triggers_do_not_fire_each_other() {
+        // A 在 src/ 变化时生成 gen/，B 在 gen/ 变化时改写 src/
+        let triggers = vec![
+            Trigger::compile(config("a", &["src/**"])).unwrap(),
+            Trigger::compile(config("b", &["gen/**"])).unwrap(),
+        ];
+        let mut states = TriggerStates::default();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        states.observe(&triggers, Path::new("src/lib.rs"), at(0));
+        assert_eq!(states.take_due(at(100)).len(), 1);
+
+        // A 执行期间写入的 gen/ 不会触发 B，A 自己的 src/ 写入也不会再触发 A
+        states.observe(&triggers, Path::new("gen/out.rs"), at(200));
+        states.observe(&triggers, Path::new("src/lib.rs"), at(210));
+        assert_eq!(states.next_deadline(), None);
+
+        // 结束后的静默期内同样丢弃
+        states.finish(0, triggers[0].quiet_period(), at(300));
+        states.observe(&triggers, Path::new("gen/late.rs"), at(300) + MIN_QUIET - Duration::from_millis(1));
+        assert_eq!(states.next_deadline(), None);
+
+        // 静默期过后的用户修改照常触发
+        let after = at(300) + MIN_QUIET;
+        states.observe(&triggers, Path::new("gen/user.rs"), after);
+        let due = states.take_due(after + Duration::from_millis(100));
+        assert_eq!(due, vec![(1, vec![PathBuf::from("gen/user.rs")])]);
+    }
+}
@@ -0,0 +1,545 @@
+//! 入站 Webhook
+//!
+//! 把外部系统的 Webhook（如 GitHub 的 issue 打开、PR 请求评审）映射为编排器任务：
+//! - 配置写在守护进程工作目录的 `webhooks.json`：`addr`（监听地址，默认 `127.0.0.1:8767`）与 `hooks`
+//! - 每个 hook 在 `POST /hooks/<name>` 接收请求，包含 `secret` 或 `secret_env`（必填）、可选的 `workspace`，
+//!   以及事件类型、投递 ID 与签名所在的请求头（默认即 GitHub 的 `X-GitHub-Event`、`X-GitHub-Delivery`、
+//!   `X-Hub-Signature-256`）
+//! - 签名为请求体的 HMAC-SHA256（`sha256=<hex>`），校验失败返回 401
+//! - `rules` 按顺序匹配：`event` 为事件类型（省略时匹配任意类型），`match` 要求载荷中给定路径的值相等，
+//!   `goal` 与 `workflow` 二选一；`goal` 是模板，`{{issue.title}}` 之类的占位符按点分路径取载荷中的值
+//! - 同一投递 ID（没有时取请求体摘要）只处理一次：记录写入控制面共用的去重表，重投递直接返回 200
+//! - 命中后立即返回 202，任务在后台执行，并记录 `WebhookTriggered` 事件
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use ring::hmac;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use nl_core::event::{Event, EventKind};
+use nl_durable::{IdempotencyStore, ScheduleTarget};
+
+use crate::scheduler::run_target;
+use crate::workspace::WorkspaceRegistry;
+
+/// Webhook 配置文件名
+pub const WEBHOOKS_FILE: &str = "webhooks.json";
+
+/// 渲染后目标的最大字符数（避免超长的 issue 正文撑爆上下文）
+const MAX_GOAL_CHARS: usize = 4000;
+
+/// Webhook 配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default = "default_addr")]
+    pub addr: SocketAddr,
+    #[serde(default)]
+    pub hooks: Vec<HookConfig>,
+}
+
+fn default_addr() -> SocketAddr {
+    "127.0.0.1:8767".parse().unwrap()
+}
+
+/// 单个 hook 配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookConfig {
+    pub name: String,
+    /// 签名密钥
+    pub secret: Option<String>,
+    /// 从该环境变量读取签名密钥
+    pub secret_env: Option<String>,
+    /// 目标工作区（省略时为默认工作区）
+    pub workspace: Option<String>,
+    #[serde(default = "default_event_header")]
+    pub event_header: String,
+    #[serde(default = "default_delivery_header")]
+    pub delivery_header: String,
+    #[serde(default = "default_signature_header")]
+    pub signature_header: String,
+    pub rules: Vec<RuleConfig>,
+}
+
+fn default_event_header() -> String {
+    "x-github-event".to_string()
+}
+
+fn default_delivery_header() -> String {
+    "x-github-delivery".to_string()
+}
+
+fn default_signature_header() -> String {
+    "x-hub-signature-256".to_string()
+}
+
+/// 载荷到任务的映射规则
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleConfig {
+    /// 事件类型
+    pub event: Option<String>,
+    /// 载荷路径 → 期望值
+    #[serde(default, rename = "match")]
+    pub matches: BTreeMap<String, String>,
+    pub goal: Option<String>,
+    pub workflow: Option<String>,
+}
+
+impl RuleConfig {
+    /// 是否匹配事件
+    fn matches(&self, event: &str, payload: &serde_json::Value) -> bool {
+        self.event.iter().all(|e| e == event)
+            && self
+                .matches
+                .iter()
+                .all(|(path, expected)| lookup(payload, path).is_some_and(|v| &display(v) == expected))
+    }
+
+    /// 为载荷生成执行目标
+    fn target(&self, payload: &serde_json::Value) -> ScheduleTarget {
+        match (&self.workflow, &self.goal) {
+            (Some(workflow), _) => ScheduleTarget::Workflow(workflow.clone()),
+            (None, Some(goal)) => ScheduleTarget::Goal(render(goal, payload).chars().take(MAX_GOAL_CHARS).collect()),
+            (None, None) => unreachable!("validated when loading"),
+        }
+    }
+}
+
+/// 按点分路径取值（数组下标同样以数字表示）
+fn lookup<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        serde_json::Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => value.get(key),
+    })
+}
+
+/// 字符串按原文，其余按 JSON 文本
+fn display(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// 渲染模板：`{{path}}` 替换为载荷中的值，缺失的路径替换为空
+fn render(template: &str, payload: &serde_json::Value) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let path = rest[start + 2..start + end].trim();
+        output.push_str(&lookup(payload, path).map(display).unwrap_or_default());
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// 已校验的 hook
+struct Hook {
+    config: HookConfig,
+    key: hmac::Key,
+}
+
+impl Hook {
+    fn compile(config: HookConfig) -> anyhow::Result<Self> {
+        let secret = match (&config.secret, &config.secret_env) {
+            (Some(secret), _) => secret.clone(),
+            (None, Some(var)) => std::env::var(var)
+                .map_err(|_| anyhow::anyhow!("hook {}: environment variable {} is not set", config.name, var))?,
+            (None, None) => anyhow::bail!("hook {}: a secret or secret_env is required", config.name),
+        };
+        if secret.is_empty() {
+            anyhow::bail!("hook {}: the secret is empty", config.name);
+        }
+        for rule in &config.rules {
+            if rule.goal.is_some() == rule.workflow.is_some() {
+                anyhow::bail!("hook {}: exactly one of goal or workflow is required per rule", config.name);
+            }
+        }
+        Ok(Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+            config,
+        })
+    }
+
+    /// 校验 `sha256=<hex>` 签名（常数时间比较）
+    fn verify(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        let Some(signature) = header(headers, &self.config.signature_header) else {
+            return false;
+        };
+        let Some(tag) = signature.strip_prefix("sha256=").and_then(decode_hex) else {
+            return false;
+        };
+        hmac::verify(&self.key, body, &tag).is_ok()
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// 解码十六进制（只接受 `0-9a-fA-F`，不接受符号或空白）
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let digit = |b: u8| (b as char).to_digit(16);
+    let bytes = hex.as_bytes();
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    bytes.chunks(2).map(|pair| Some((digit(pair[0])? << 4 | digit(pair[1])?) as u8)).collect()
+}
+
+#[derive(Clone)]
+struct WebhookState {
+    hooks: Arc<Vec<Hook>>,
+    workspaces: Arc<WorkspaceRegistry>,
+    dedup: Arc<IdempotencyStore>,
+}
+
+/// 入站 Webhook 服务器
+pub struct WebhookServer {
+    addr: SocketAddr,
+    state: WebhookState,
+}
+
+impl WebhookServer {
+    /// 读取配置（文件不存在或没有 hook 时返回 `None`）
+    pub fn load(
+        path: &std::path::Path,
+        workspaces: Arc<WorkspaceRegistry>,
+        dedup: Arc<IdempotencyStore>,
+    ) -> anyhow::Result<Option<Self>> {
+        let config: WebhookConfig = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if config.hooks.is_empty() {
+            return Ok(None);
+        }
+        let hooks = config.hooks.into_iter().map(Hook::compile).collect::<anyhow::Result<_>>()?;
+        Ok(Some(Self {
+            addr: config.addr,
+            state: WebhookState {
+                hooks: Arc::new(hooks),
+                workspaces,
+                dedup,
+            },
+        }))
+    }
+
+    /// 监听地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// hook 数量
+    pub fn hook_count(&self) -> usize {
+        self.state.hooks.len()
+    }
+
+    /// 启动服务器
+    pub async fn start(self) -> nl_core::Result<()> {
+        let listener = tokio::net::TcpListener::bind(&self.addr)
+            .await
+            .map_err(|e| nl_core::NeuroLoomError::Protocol(e.to_string()))?;
+        axum::serve(listener, router(self.state))
+            .await
+            .map_err(|e| nl_core::NeuroLoomError::Protocol(e.to_string()))?;
+        Ok(())
+    }
+}
+
+fn router(state: WebhookState) -> Router {
+    Router::new().route("/hooks/:name", post(receive)).with_state(state)
+}
+
+fn reply(status: StatusCode, body: serde_json::Value) -> Response {
+    (status, Json(body)).into_response()
+}
+
+/// 接收 Webhook
+async fn receive(
+    State(state): State<WebhookState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(hook) = state.hooks.iter().find(|h| h.config.name == name) else {
+        return reply(StatusCode::NOT_FOUND, serde_json::json!({ "error": format!("unknown hook: {}", name) }));
+    };
+    if !hook.verify(&headers, &body) {
+        tracing::warn!("Rejected webhook {}: invalid signature", name);
+        return reply(StatusCode::UNAUTHORIZED, serde_json::json!({ "error": "invalid signature" }));
+    }
+    let payload: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => return reply(StatusCode::BAD_REQUEST, serde_json::json!({ "error": e.to_string() })),
+    };
+    let event = header(&headers, &hook.config.event_header).unwrap_or_default().to_string();
+    let delivery = match header(&headers, &hook.config.delivery_header) {
+        Some(id) => id.to_string(),
+        None => Sha256::digest(&body).iter().map(|b| format!("{:02x}", b)).collect(),
+    };
+
+    // 重投递：已处理过或正在处理
+    let key = format!("webhook:{}:{}", name, delivery);
+    match state.dedup.get(&key).await {
+        Ok(Some(record)) => {
+            return reply(StatusCode::OK, serde_json::json!({ "duplicate": true, "result": record.result }));
+        }
+        Ok(None) => {}
+        Err(e) => return reply(StatusCode::INTERNAL_SERVER_ERROR, serde_json::json!({ "error": e.to_string() })),
+    }
+    if state.dedup.begin(&key).is_err() {
+        return reply(StatusCode::OK, serde_json::json!({ "duplicate": true }));
+    }
+    let response = dispatch(&state, hook, &event, &delivery, &payload).await;
+    state.dedup.finish(&key);
+    let (status, result) = match response {
+        Ok(result) => result,
+        Err(response) => return response,
+    };
+    if let Err(e) = state.dedup.record(&key, &format!("webhook {} {}", name, event), &result).await {
+        tracing::warn!("Failed to record webhook delivery {}: {}", delivery, e);
+    }
+    reply(status, result)
+}
+
+/// 匹配规则并在后台启动任务
+async fn dispatch(
+    state: &WebhookState,
+    hook: &Hook,
+    event: &str,
+    delivery: &str,
+    payload: &serde_json::Value,
+) -> Result<(StatusCode, serde_json::Value), Response> {
+    let Some(workspace) = state.workspaces.resolve(hook.config.workspace.as_deref()).await else {
+        let selector = hook.config.workspace.as_deref().unwrap_or_default();
+        return Err(reply(
+            StatusCode::NOT_FOUND,
+            serde_json::json!({ "error": format!("unknown workspace: {}", selector) }),
+        ));
+    };
+    let targets: Vec<ScheduleTarget> = hook
+        .config
+        .rules
+        .iter()
+        .filter(|rule| rule.matches(event, payload))
+        .map(|rule| rule.target(payload))
+        .collect();
+    if targets.is_empty() {
+        tracing::debug!("Webhook {} event {} matched no rule", hook.config.name, event);
+        return Ok((StatusCode::OK, serde_json::json!({ "matched": 0 })));
+    }
+
+    for target in &targets {
+        let record = Event::new(
+            EventKind::WebhookTriggered,
+            uuid::Uuid::new_v4(),
+            serde_json::json!({ "hook": hook.config.name, "event": event, "delivery": delivery, "target": target }),
+        );
+        if let Err(e) = workspace.event_store.lock().await.append(record).await {
+            tracing::warn!("Failed to record webhook event: {}", e);
+        }
+        let (workspace, target, name) = (workspace.clone(), target.clone(), hook.config.name.clone());
+        tokio::spawn(async move {
+            tracing::info!("Webhook {} started {} in {}", name, target, workspace.name);
+            match run_target(&workspace.orchestrator, &target).await {
+                Ok(result) => tracing::info!("Webhook {} task finished: success={}", name, result.success),
+                Err(e) => tracing::warn!("Webhook {} task failed: {}", name, e),
+            }
+        });
+    }
+    Ok((
+        StatusCode::ACCEPTED,
+        serde_json::json!({ "matched": targets.len(), "workspace": workspace.name, "targets": targets }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const SECRET: &str = "It's a Secret to Everybody";
+
+    fn hook_config(rules: Vec<RuleConfig>) -> HookConfig {
+        serde_json::from_value(json!({ "name": "github", "secret": SECRET, "rules": [] }))
+            .map(|config: HookConfig| HookConfig { rules, ..config })
+            .unwrap()
+    }
+
+    fn rule(event: Option<&str>, matches: &[(&str, &str)], goal: &str) -> RuleConfig {
+        RuleConfig {
+            event: event.map(str::to_string),
+            matches: matches.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            goal: Some(goal.to_string()),
+            workflow: None,
+        }
+    }
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()), body);
+        format!("sha256={}", tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect::<String>())
+    }
+
+    fn signed(signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-hub-signature-256", signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_verify_accepts_only_a_valid_sha256_signature() {
+        let hook = Hook::compile(hook_config(Vec::new())).unwrap();
+        let body = b"Hello, World!";
+        let valid = sign(SECRET, body);
+        // GitHub 文档中的示例签名
+        assert_eq!(valid, "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17");
+        assert!(hook.verify(&signed(&valid), body));
+        assert!(hook.verify(&signed(&valid.to_uppercase().replace("SHA256=", "sha256=")), body));
+
+        // 签名与正文不符
+        assert!(!hook.verify(&signed(&sign("another secret", body)), body));
+        assert!(!hook.verify(&signed(&valid), b"Hello, World?"));
+        // 十六进制无效
+        assert!(!hook.verify(&signed("sha256=zz"), body));
+        assert!(!hook.verify(&signed(&valid[..valid.len() - 1]), body));
+        assert!(!hook.verify(&signed(&valid[..valid.len() - 2]), body));
+        // 缺少签名头
+        assert!(!hook.verify(&HeaderMap::new(), body));
+        // 前缀错误
+        assert!(!hook.verify(&signed(&valid.replace("sha256=", "sha1=")), body));
+        assert!(!hook.verify(&signed(&valid.replace("sha256=", "SHA256=")), body));
+        assert!(!hook.verify(&signed(valid.trim_start_matches("sha256=")), body));
+    }
+
+    #[test]
+    fn test_compile_rejects_missing_secret_and_ambiguous_rules() {
+        let mut config = hook_config(Vec::new());
+        config.secret = None;
+        assert!(Hook::compile(config.clone()).is_err());
+        config.secret = Some(String::new());
+        assert!(Hook::compile(config).is_err());
+
+        let both = RuleConfig { workflow: Some("triage".to_string()), ..rule(None, &[], "goal") };
+        assert!(Hook::compile(hook_config(vec![both])).is_err());
+        let neither = RuleConfig { goal: None, ..rule(None, &[], "goal") };
+        assert!(Hook::compile(hook_config(vec![neither])).is_err());
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("00ff7F"), Some(vec![0x00, 0xff, 0x7f]));
+        assert_eq!(decode_hex(""), Some(Vec::new()));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("0g"), None);
+        assert_eq!(decode_hex("+1"), None);
+        assert_eq!(decode_hex("éé"), None);
+    }
+
+    #[test]
+    fn test_render_and_match_with_missing_fields() {
+        let payload = json!({
+            "action": "opened",
+            "issue": { "number": 7, "title": "Crash on start", "labels": [{ "name": "bug" }], "body": null },
+        });
+        assert_eq!(
+            render("#{{issue.number}} {{ issue.title }} [{{issue.labels.0.name}}]", &payload),
+            "#7 Crash on start [bug]"
+        );
+        // 缺失路径、null、越界下标与非数字下标都替换为空
+        assert_eq!(
+            render("by {{issue.user.login}}: {{issue.body}}|{{issue.labels.5.name}}|{{issue.labels.x}}", &payload),
+            "by : ||"
+        );
+        // 未闭合的占位符原样保留
+        assert_eq!(render("{{issue.title}} {{oops", &payload), "Crash on start {{oops");
+
+        let triage = rule(Some("issues"), &[("action", "opened"), ("issue.number", "7")], "Triage {{issue.title}}");
+        assert!(triage.matches("issues", &payload));
+        assert!(!triage.matches("pull_request", &payload));
+        assert!(!triage.matches("issues", &json!({ "action": "opened" })));
+        assert!(rule(None, &[], "x").matches("anything", &payload));
+
+        let long = json!({ "issue": { "title": "x".repeat(MAX_GOAL_CHARS * 2) } });
+        let ScheduleTarget::Goal(goal) = triage.target(&long) else { panic!() };
+        assert_eq!(goal.chars().count(), MAX_GOAL_CHARS);
+    }
+
+    #[tokio::test]
+    async fn test_redelivered_delivery_is_ignored() {
+        let base = std::env::temp_dir().join(format!("nl_webhooks_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base).unwrap();
+        let workspaces = Arc::new(WorkspaceRegistry::open(&base).await.unwrap());
+        let hook = Hook::compile(hook_config(vec![rule(Some("issues"), &[], "Triage {{issue.title}}")])).unwrap();
+        let app = router(WebhookState {
+            hooks: Arc::new(vec![hook]),
+            workspaces: workspaces.clone(),
+            dedup: Arc::new(IdempotencyStore::new()),
+        });
+
+        let body = json!({ "issue": { "title": "Crash" } }).to_string();
+        let post = |delivery: Option<&str>, signature: String| {
+            let mut request = Request::post("/hooks/github")
+                .header("x-github-event", "issues")
+                .header("x-hub-signature-256", signature);
+            if let Some(delivery) = delivery {
+                request = request.header("x-github-delivery", delivery);
+            }
+            let app = app.clone();
+            let request = request.body(Body::from(body.clone())).unwrap();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap())
+            }
+        };
+        let signature = sign(SECRET, body.as_bytes());
+
+        let (status, first) = post(Some("d-1"), signature.clone()).await;
+        assert_eq!(status, StatusCode::ACCEPTED, "{}", first);
+        assert_eq!(first["targets"], json!([{ "type": "goal", "value": "Triage Crash" }]));
+        let (status, again) = post(Some("d-1"), signature.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(again["duplicate"], true);
+        assert_eq!(again["result"], first);
+
+        // 新的投递 ID 照常处理；没有投递 ID 时按请求体摘要去重
+        assert_eq!(post(Some("d-2"), signature.clone()).await.0, StatusCode::ACCEPTED);
+        assert_eq!(post(None, signature.clone()).await.0, StatusCode::ACCEPTED);
+        assert_eq!(post(None, signature.clone()).await.1["duplicate"], true);
+
+        // 签名无效的请求不会占用投递 ID
+        assert_eq!(post(Some("d-3"), sign("wrong", body.as_bytes())).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(post(Some("d-3"), signature).await.0, StatusCode::ACCEPTED);
+
+        let workspace = workspaces.default_workspace().await;
+        let store = workspace.event_store.lock().await;
+        let triggered = store.get_events_by_kind(&EventKind::WebhookTriggered).await.unwrap();
+        let mut deliveries: Vec<_> = triggered.iter().map(|e| e.payload["delivery"].as_str().unwrap()).collect();
+        deliveries.sort_unstable();
+        assert_eq!(deliveries.len(), 4);
+        assert_eq!(deliveries.iter().filter(|d| **d == "d-1").count(), 1);
+    }
+}
@@ -0,0 +1,623 @@
+//! 多工作区
+//!
+//! 每个工作区（项目）拥有独立的事件库、事件总线、取消登记表、记忆索引、GraphRAG 与 SOP 注册表
+//! （随编排器持有），工作区之间互不可见。
+//! - 工作区数据位于 `<root>/.neuroloom/`，登记在守护进程工作目录的 `workspaces.json`
+//! - 守护进程工作目录本身是 `default` 工作区，数据库仍为 `neuroloom.db`（兼容单工作区布局）
+//! - CLI/桌面端按名称或路径选择工作区；路径可以是工作区根目录下的任意子目录
+//! - 每个工作区在空闲时后台整理自己的记忆索引
+//! - 工作区可导出为可移植包（事件、记忆、图谱、SOP），在另一台机器上按冲突策略导入
+//! - 定时调度存于工作区事件库的 `schedules` 表，由守护进程的调度器统一触发
+//! - 数据目录下的 `triggers.json` 配置文件变化触发器，打开工作区时开始监听
+//! - 只发布到总线的 SOP 注册 / 执行结束与 LLM 用量事件同时落入事件库，供活动摘要统计
+//! - 数据目录下的 `redaction.json` 追加或替换事件载荷的脱敏规则（默认工作区的规则同时作用于日志）
+//! - 任务产物按内容哈希存放在数据目录的 `artifacts/` 下，每小时回收不再被事件或记忆引用的产物
+//!   （导入 PDF / DOCX 时页面图片存入产物库，由文档分块记忆引用）
+//! - 每个工作区持有一份画布投影，桌面端经 `GET /canvas` 实时镜像
+//! - 数据目录下的 `pii.json` 启用记忆 PII 脱敏：导入与整理时在生成摘要前替换为令牌，
+//!   原文与令牌映射只保存在 `pii/` 下的加密归档中（需要设置 `NEUROLOOM_DB_KEY`）
+//! - 设置 `NEUROLOOM_DB_KEY` 后事件正文与 `archives/` 下的冷记忆归档加密落盘。主密钥由该口令拉伸，
+//!   而不是从凭据库派生：守护进程的凭据（API Key、Provider 令牌）本身就存放在数据目录里，
+//!   用它们派生的密钥会和密文一起被拷走，口令则不落盘
+//! - 守护进程工作目录下的 `tokenizers/` 存放词表，全部工作区的上下文组装共用（没有词表时按字符估算）
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+
+use nl_cognitive::system1::SopWorkflow;
+use nl_cognitive::ContextAssembler;
+use nl_core::event::EventKind;
+use nl_core::tokenizer::{TokenizerRegistry, TOKENIZER_DIR};
+use nl_durable::{
+    ArtifactStore, CancellationRegistry, ConflictPolicy, EventBus, EventStore, ImportStats, MasterKey, PiiConfig,
+    PiiScrubber, RedactionConfig, Redactor, ScheduleStore, WorkspaceBundle,
+};
+use nl_durable::encryption::MASTER_KEY_ENV;
+use nl_durable::pii::PII_FILE;
+use nl_durable::redaction::REDACTION_FILE;
+use nl_memory::hamt::MemoryEntry;
+use nl_memory::archival::ArchivalStrategy;
+use nl_memory::knowledge::{referenced_images, KNOWLEDGE_CHECKPOINT_FILE};
+use nl_memory::{
+    ArchivalManager, ConsolidationConfig, GraphRAG, GraphSnapshot, HamtIndex, KnowledgeImportConfig, KnowledgeImportStats,
+    KnowledgeImporter, MemoryConsolidator, PiiVault,
+};
+
+use crate::canvas::CanvasFeed;
+
+/// 默认工作区名称
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// 工作区数据目录名
+const DATA_DIR: &str = ".neuroloom";
+
+/// 工作区登记文件
+const REGISTRY_FILE: &str = "workspaces.json";
+
+/// 只发布到总线、需要同时落库的事件
+const PERSISTED_BUS_EVENTS: &[EventKind] = &[
+    EventKind::SopRegistered,
+    EventKind::SopRunFinished,
+    EventKind::LlmResponseCompleted,
+];
+
+/// 产物目录名
+const ARTIFACTS_DIR: &str = "artifacts";
+
+/// PII 加密归档目录名
+const PII_ARCHIVE_DIR: &str = "pii";
+
+/// 冷记忆归档目录名
+const ARCHIVE_DIR: &str = "archives";
+
+/// 产物回收间隔
+const ARTIFACT_GC_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 刚写入的产物在此期间内不回收（引用它的事件可能尚未落库）
+const ARTIFACT_GC_GRACE_SECS: i64 = 3600;
+
+/// 包分区：记忆条目
+const MEMORIES_SECTION: &str = "memories";
+
+/// 包分区：GraphRAG
+const GRAPH_SECTION: &str = "graph";
+
+/// 包分区：SOP 工作流
+const SOPS_SECTION: &str = "sops";
+
+/// 各类状态的导入统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BundleImportReport {
+    pub events: ImportStats,
+    pub memories: ImportStats,
+    pub graph: ImportStats,
+    pub sops: ImportStats,
+}
+
+/// 登记的工作区
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceEntry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// 工作区运行时
+pub struct Workspace {
+    /// 名称
+    pub name: String,
+    /// 项目根目录
+    pub root: PathBuf,
+    /// 数据目录（事件库所在目录）
+    pub data_dir: PathBuf,
+    /// 事件总线
+    pub event_bus: Arc<EventBus>,
+    /// 事件库
+    pub event_store: Arc<Mutex<EventStore>>,
+    /// 运行中任务的取消令牌
+    pub cancellation: Arc<CancellationRegistry>,
+    /// 记忆索引
+    pub memory_index: Arc<HamtIndex>,
+    /// GraphRAG
+    pub graph_rag: Arc<RwLock<GraphRAG>>,
+    /// 编排器（持有本工作区的 SOP 注册表）
+    pub orchestrator: Arc<Mutex<nl_cognitive::Orchestrator>>,
+    /// 定时调度
+    pub schedules: Arc<ScheduleStore>,
+    /// 任务产物
+    pub artifacts: Arc<ArtifactStore>,
+    /// 画布投影
+    pub canvas: Arc<CanvasFeed>,
+    /// 记忆 PII 脱敏（`pii.json` 未启用时为 `None`）
+    pub pii: Option<Arc<PiiVault>>,
+    /// 上下文组装（记忆、图谱与近期事件，按目标模型的分词器计数）
+    pub context: Arc<ContextAssembler>,
+}
+
+impl Workspace {
+    /// 打开工作区（未完成的任务由 `resume_unfinished` 在后台恢复）
+    async fn open(
+        name: &str,
+        root: &Path,
+        db_path: &Path,
+        tokenizers: Arc<TokenizerRegistry>,
+    ) -> anyhow::Result<Self> {
+        let event_bus = Arc::new(EventBus::default());
+        let mut store = EventStore::open(db_path)
+            .await?
+            .with_event_bus(event_bus.clone());
+        let redaction = RedactionConfig::load(&db_path.with_file_name(REDACTION_FILE))?;
+        store = store.with_redactor(Redactor::from_config(&redaction)?);
+        // 设置 NEUROLOOM_DB_KEY 后事件正文加密落盘（口令按数据目录中 `db_key.json` 的盐拉伸）
+        let master_key = MasterKey::from_env(db_path.parent().unwrap_or(Path::new(".")))?;
+        if let Some(key) = &master_key {
+            store = store.with_cipher(key.cipher("events")?);
+        }
+        let mut schedules = ScheduleStore::new();
+        if let Some(pool) = store.pool().cloned() {
+            schedules = schedules.with_pool(pool).await?;
+        }
+        let event_store = Arc::new(Mutex::new(store));
+        persist_bus_events(&event_bus, event_store.clone());
+        let canvas = CanvasFeed::follow(&event_bus, &event_store).await;
+        let cancellation = Arc::new(CancellationRegistry::new().with_event_bus(event_bus.clone()));
+
+        let sop = nl_cognitive::SopEngine::new().with_event_bus(event_bus.clone());
+        let orchestrator = nl_cognitive::Orchestrator::new(sop)
+            .with_event_store(event_store.clone())
+            .with_cancellation(cancellation.clone());
+        tracing::info!("Workspace {} opened at {}", name, root.display());
+
+        // 空闲时整理记忆（合并重复、刷新摘要、归档冷数据）
+        let memory_index = Arc::new(HamtIndex::new());
+        let pii = open_pii_vault(db_path, master_key.as_ref())?;
+        let mut consolidator = MemoryConsolidator::new(memory_index.clone(), ConsolidationConfig::default())
+            .with_archival(memory_archival(db_path, master_key.as_ref())?)
+            .with_event_bus(event_bus.clone());
+        if let Some(vault) = &pii {
+            consolidator = consolidator.with_pii_vault(vault.clone());
+        }
+        tokio::spawn(consolidator.run());
+
+        let artifacts = Arc::new(ArtifactStore::open(db_path.with_file_name(ARTIFACTS_DIR)).await?);
+        collect_artifacts(name.to_string(), artifacts.clone(), event_store.clone(), memory_index.clone());
+
+        let orchestrator = Arc::new(Mutex::new(orchestrator));
+        let triggers = db_path.with_file_name(crate::triggers::TRIGGERS_FILE);
+        if let Err(e) = crate::triggers::load(&triggers)
+            .and_then(|t| crate::triggers::watch(name, root, t, orchestrator.clone(), event_store.clone()))
+        {
+            tracing::warn!("File triggers disabled for workspace {}: {}", name, e);
+        }
+
+        let graph_rag = Arc::new(RwLock::new(GraphRAG::new()));
+        let context = ContextAssembler::new()
+            .with_memory(memory_index.clone())
+            .with_graph(graph_rag.clone())
+            .with_event_store(event_store.clone())
+            .with_tokenizers(tokenizers);
+
+        Ok(Self {
+            name: name.to_string(),
+            root: root.to_path_buf(),
+            data_dir: db_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            event_bus,
+            event_store,
+            cancellation,
+            memory_index,
+            graph_rag,
+            orchestrator,
+            schedules: Arc::new(schedules),
+            artifacts,
+            canvas,
+            pii,
+            context: Arc::new(context),
+        })
+    }
+
+    /// 在后台恢复未完成的计划，失败只记录日志
+    pub fn resume_unfinished(self: &Arc<Self>) {
+        let workspace = self.clone();
+        tokio::spawn(async move {
+            let mut orchestrator = workspace.orchestrator.lock().await.fork();
+            match orchestrator.resume_unfinished().await {
+                Ok(resumed) if !resumed.is_empty() => {
+                    tracing::info!("Resumed {} unfinished plans in workspace {}", resumed.len(), workspace.name)
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to resume unfinished plans in workspace {}: {}", workspace.name, e),
+            }
+        });
+    }
+
+    /// 导出为可移植包
+    pub async fn export_bundle(&self) -> anyhow::Result<WorkspaceBundle> {
+        let memories: Vec<MemoryEntry> = self.memory_index.all_entries();
+        let graph = self.graph_rag.read().await.snapshot();
+        let sops: Vec<SopWorkflow> = self
+            .orchestrator
+            .lock()
+            .await
+            .sop_engine()
+            .workflows()
+            .into_iter()
+            .cloned()
+            .collect();
+
+        let bundle = WorkspaceBundle::new(&self.name)
+            .with_events_from(&*self.event_store.lock().await)
+            .await?
+            .with_section(MEMORIES_SECTION, &memories)?
+            .with_section(GRAPH_SECTION, &graph)?
+            .with_section(SOPS_SECTION, &sops)?;
+        Ok(bundle)
+    }
+
+    /// 按冲突策略导入包
+    pub async fn import_bundle(
+        &self,
+        bundle: WorkspaceBundle,
+        policy: ConflictPolicy,
+    ) -> anyhow::Result<BundleImportReport> {
+        let memories: Vec<MemoryEntry> = bundle.section(MEMORIES_SECTION)?.unwrap_or_default();
+        let graph: GraphSnapshot = bundle.section(GRAPH_SECTION)?.unwrap_or_default();
+        let sops: Vec<SopWorkflow> = bundle.section(SOPS_SECTION)?.unwrap_or_default();
+
+        let report = BundleImportReport {
+            events: self.event_store.lock().await.import_events(bundle.events, policy).await?,
+            memories: self.memory_index.import(memories, policy),
+            graph: self.graph_rag.write().await.import(graph, policy),
+            sops: self.orchestrator.lock().await.sop_engine().import(sops, policy),
+        };
+        tracing::info!(
+            "Imported bundle from workspace {} into {}: {:?}",
+            bundle.workspace,
+            self.name,
+            report
+        );
+        Ok(report)
+    }
+
+    /// 从本机目录导入 Markdown / JSONL 知识库与 PDF / DOCX 文档（按数据目录中的检查点续跑）
+    pub async fn import_knowledge(&self, path: &Path) -> anyhow::Result<KnowledgeImportStats> {
+        let mut importer = KnowledgeImporter::new(self.memory_index.clone(), KnowledgeImportConfig::default())
+            .with_graph(self.graph_rag.clone())
+            .with_artifacts(self.artifacts.clone())
+            .with_checkpoint(self.data_dir.join(KNOWLEDGE_CHECKPOINT_FILE));
+        if let Some(vault) = &self.pii {
+            importer = importer.with_pii_vault(vault.clone());
+        }
+        Ok(importer.import(path).await?)
+    }
+
+    /// 登记信息
+    pub fn entry(&self) -> WorkspaceEntry {
+        WorkspaceEntry {
+            name: self.name.clone(),
+            path: self.root.clone(),
+        }
+    }
+}
+
+/// 加载词表目录；目录不存在或词表无效时按字符估算
+fn load_tokenizers(dir: &Path) -> TokenizerRegistry {
+    if !dir.is_dir() {
+        return TokenizerRegistry::new();
+    }
+    TokenizerRegistry::load_dir(dir).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring tokenizers in {}: {}", dir.display(), e);
+        TokenizerRegistry::new()
+    })
+}
+
+/// 按 `pii.json` 打开 PII 保管库；启用脱敏但未设置主密钥时拒绝打开工作区，避免原文以明文留存
+/// 冷记忆归档（设置了主密钥时加密）
+fn memory_archival(db_path: &Path, master_key: Option<&MasterKey>) -> anyhow::Result<ArchivalManager> {
+    let archival =
+        ArchivalManager::new(ArchivalStrategy::ByAge(30), db_path.with_file_name(ARCHIVE_DIR).to_string_lossy());
+    Ok(match master_key {
+        Some(key) => archival.with_cipher(key.cipher("archives")?),
+        None => archival,
+    })
+}
+
+fn open_pii_vault(db_path: &Path, master_key: Option<&MasterKey>) -> anyhow::Result<Option<Arc<PiiVault>>> {
+    let config = PiiConfig::load(&db_path.with_file_name(PII_FILE))?;
+    if !config.enabled {
+        return Ok(None);
+    }
+    let Some(key) = master_key else {
+        anyhow::bail!("{} enables PII scrubbing but {} is not set", PII_FILE, MASTER_KEY_ENV);
+    };
+    let cipher = key.cipher("pii")?;
+    let archival = ArchivalManager::new(
+        ArchivalStrategy::ByAge(30),
+        db_path.with_file_name(PII_ARCHIVE_DIR).to_string_lossy(),
+    );
+    let vault = PiiVault::new(PiiScrubber::from_config(&config)?, archival, cipher);
+    Ok(Some(Arc::new(vault)))
+}
+
+/// 把只发布到总线的事件写入事件库
+///
+/// 事件库追加时会把事件再次发布到同一总线，用刚写入的 ID 过滤这次回显。
+fn persist_bus_events(bus: &EventBus, store: Arc<Mutex<EventStore>>) {
+    let mut events = bus.subscribe_all();
+    tokio::spawn(async move {
+        let mut echoes = HashSet::new();
+        while let Some(event) = events.recv().await {
+            if !PERSISTED_BUS_EVENTS.contains(&event.kind) || echoes.remove(&event.id) {
+                continue;
+            }
+            echoes.insert(event.id);
+            if let Err(e) = store.lock().await.append(event).await {
+                tracing::warn!("Failed to persist bus event: {}", e);
+            }
+        }
+    });
+}
+
+/// 定期回收不再被事件或记忆引用的产物
+fn collect_artifacts(
+    workspace: String,
+    artifacts: Arc<ArtifactStore>,
+    store: Arc<Mutex<EventStore>>,
+    memory_index: Arc<HamtIndex>,
+) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(ARTIFACT_GC_INTERVAL);
+        loop {
+            ticks.tick().await;
+            let events = match store.lock().await.all_events().await {
+                Ok(events) => events,
+                Err(e) => {
+                    tracing::warn!("Artifact GC skipped for workspace {}: {}", workspace, e);
+                    continue;
+                }
+            };
+            let mut referenced = nl_durable::artifact_store::referenced_by(&events);
+            referenced.extend(referenced_images(&memory_index));
+            let grace = chrono::Duration::seconds(ARTIFACT_GC_GRACE_SECS);
+            match artifacts.gc(&referenced, grace, chrono::Utc::now()).await {
+                Ok(stats) if stats.removed > 0 => tracing::info!(
+                    "Collected {} unreferenced artifacts ({} bytes) in workspace {}",
+                    stats.removed,
+                    stats.freed_bytes,
+                    workspace
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Artifact GC failed for workspace {}: {}", workspace, e),
+            }
+        }
+    });
+}
+
+/// 工作区登记表
+pub struct WorkspaceRegistry {
+    /// 登记文件路径
+    path: PathBuf,
+    workspaces: RwLock<HashMap<String, Arc<Workspace>>>,
+    /// 按模型选择的分词器（上下文组装与 LLM 网关的溢出检查共用）
+    tokenizers: Arc<TokenizerRegistry>,
+}
+
+impl WorkspaceRegistry {
+    /// 打开默认工作区与 `workspaces.json` 中登记的全部工作区
+    pub async fn open(base: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let base = std::fs::canonicalize(base)?;
+        let path = base.join(REGISTRY_FILE);
+        let entries: Vec<WorkspaceEntry> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let tokenizers = Arc::new(load_tokenizers(&base.join(TOKENIZER_DIR)));
+        let mut workspaces = HashMap::new();
+        let default = Workspace::open(DEFAULT_WORKSPACE, &base, &base.join("neuroloom.db"), tokenizers.clone()).await?;
+        workspaces.insert(DEFAULT_WORKSPACE.to_string(), Arc::new(default));
+        for entry in entries {
+            match Self::open_entry(&entry, tokenizers.clone()).await {
+                Ok(workspace) => {
+                    workspaces.insert(entry.name.clone(), Arc::new(workspace));
+                }
+                Err(e) => tracing::warn!("Skipping workspace {}: {}", entry.name, e),
+            }
+        }
+
+        Ok(Self {
+            path,
+            workspaces: RwLock::new(workspaces),
+            tokenizers,
+        })
+    }
+
+    async fn open_entry(entry: &WorkspaceEntry, tokenizers: Arc<TokenizerRegistry>) -> anyhow::Result<Workspace> {
+        let data = entry.path.join(DATA_DIR);
+        std::fs::create_dir_all(&data)?;
+        Workspace::open(&entry.name, &entry.path, &data.join("neuroloom.db"), tokenizers).await
+    }
+
+    /// 按模型选择的分词器
+    pub fn tokenizers(&self) -> Arc<TokenizerRegistry> {
+        self.tokenizers.clone()
+    }
+
+    /// 在后台恢复全部工作区未完成的计划（守护进程就绪后调用）
+    pub async fn resume_unfinished(&self) {
+        for workspace in self.list().await {
+            workspace.resume_unfinished();
+        }
+    }
+
+    /// 默认工作区
+    pub async fn default_workspace(&self) -> Arc<Workspace> {
+        self.workspaces.read().await[DEFAULT_WORKSPACE].clone()
+    }
+
+    /// 登记并打开新工作区（名称为空时取目录名）
+    pub async fn add(&self, name: Option<&str>, root: impl AsRef<Path>) -> anyhow::Result<Arc<Workspace>> {
+        let root = std::fs::canonicalize(root)?;
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => root
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .ok_or_else(|| anyhow::anyhow!("cannot derive a name from {}", root.display()))?,
+        };
+        {
+            let workspaces = self.workspaces.read().await;
+            if let Some(existing) = workspaces.get(&name) {
+                anyhow::bail!("workspace {} already exists at {}", name, existing.root.display());
+            }
+            if let Some(existing) = workspaces.values().find(|w| w.root == root) {
+                anyhow::bail!("{} is already workspace {}", root.display(), existing.name);
+            }
+        }
+
+        let entry = WorkspaceEntry { name: name.clone(), path: root };
+        let workspace = Arc::new(Self::open_entry(&entry, self.tokenizers.clone()).await?);
+        let mut workspaces = self.workspaces.write().await;
+        workspaces.insert(name, workspace.clone());
+        self.save(&workspaces)?;
+        workspace.resume_unfinished();
+        Ok(workspace)
+    }
+
+    /// 持久化登记表（默认工作区不写入）
+    fn save(&self, workspaces: &HashMap<String, Arc<Workspace>>) -> anyhow::Result<()> {
+        let mut entries: Vec<_> = workspaces
+            .values()
+            .filter(|w| w.name != DEFAULT_WORKSPACE)
+            .map(|w| w.entry())
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        std::fs::write(&self.path, serde_json::to_string_pretty(&entries)?)?;
+        Ok(())
+    }
+
+    /// 按名称或路径查找工作区，`None` 返回默认工作区
+    ///
+    /// 路径匹配包含该路径的最深工作区根目录。
+    pub async fn resolve(&self, selector: Option<&str>) -> Option<Arc<Workspace>> {
+        let workspaces = self.workspaces.read().await;
+        let Some(selector) = selector.filter(|s| !s.is_empty()) else {
+            return workspaces.get(DEFAULT_WORKSPACE).cloned();
+        };
+        if let Some(workspace) = workspaces.get(selector) {
+            return Some(workspace.clone());
+        }
+        let path = std::fs::canonicalize(selector).ok()?;
+        workspaces
+            .values()
+            .filter(|w| path.starts_with(&w.root))
+            .max_by_key(|w| w.root.components().count())
+            .cloned()
+    }
+
+    /// 全部工作区（按名称排序）
+    pub async fn list(&self) -> Vec<Arc<Workspace>> {
+        let mut list: Vec<_> = self.workspaces.read().await.values().cloned().collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nl_core::event::Event;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_workspaces_in_one_daemon_are_isolated() {
+        let base = std::env::temp_dir().join(format!("nl_workspaces_{}", Uuid::new_v4()));
+        let alpha_root = base.join("alpha");
+        let beta_root = alpha_root.join("nested/beta");
+        std::fs::create_dir_all(beta_root.join("src")).unwrap();
+
+        let registry = WorkspaceRegistry::open(&base).await.unwrap();
+        let alpha = registry.add(Some("alpha"), &alpha_root).await.unwrap();
+        let beta = registry.add(None, &beta_root).await.unwrap();
+        assert_eq!(beta.name, "beta");
+        assert!(registry.add(Some("alpha"), &base).await.is_err());
+        assert_ne!(alpha.data_dir, beta.data_dir);
+
+        // 事件
+        let event = Event::new(EventKind::NodeCreated, Uuid::new_v4(), serde_json::json!({ "in": "alpha" }));
+        alpha.event_store.lock().await.append(event.clone()).await.unwrap();
+        let alpha_events = alpha.event_store.lock().await.all_events().await.unwrap();
+        assert!(alpha_events.iter().any(|e| e.id == event.id));
+        for other in [&beta, &registry.default_workspace().await] {
+            let events = other.event_store.lock().await.all_events().await.unwrap();
+            assert!(events.iter().all(|e| e.id != event.id), "{} sees alpha's event", other.name);
+        }
+
+        // 记忆
+        let memory = MemoryEntry::new("alpha-only", "remembered in alpha");
+        alpha.memory_index.store(memory.clone());
+        assert!(alpha.memory_index.get(&memory.id).is_some());
+        assert!(beta.memory_index.get(&memory.id).is_none());
+        assert!(beta.memory_index.search_tags("alpha-only").is_empty());
+
+        // SOP
+        beta.orchestrator.lock().await.sop_engine().register(SopWorkflow::new("beta-sop"));
+        assert!(beta.orchestrator.lock().await.sop_engine().find("beta-sop").is_some());
+        assert!(alpha.orchestrator.lock().await.sop_engine().find("beta-sop").is_none());
+
+        // 按名称或路径选择：路径匹配最深的工作区根目录
+        let resolve = |selector: Option<String>| {
+            let registry = &registry;
+            async move { registry.resolve(selector.as_deref()).await.map(|w| w.name.clone()) }
+        };
+        let path = |p: &Path| Some(p.to_string_lossy().to_string());
+        assert_eq!(resolve(Some("alpha".into())).await.as_deref(), Some("alpha"));
+        assert_eq!(resolve(Some("beta".into())).await.as_deref(), Some("beta"));
+        assert_eq!(resolve(path(&beta_root.join("src"))).await.as_deref(), Some("beta"));
+        assert_eq!(resolve(path(&alpha_root.join("nested"))).await.as_deref(), Some("alpha"));
+        assert_eq!(resolve(path(&base)).await.as_deref(), Some(DEFAULT_WORKSPACE));
+        assert_eq!(resolve(None).await.as_deref(), Some(DEFAULT_WORKSPACE));
+        assert_eq!(resolve(Some("missing".into())).await, None);
+
+        // 登记表持久化后重新打开，事件仍只属于原工作区
+        alpha.event_store.lock().await.flush().await.unwrap();
+        drop(registry);
+        let reopened = WorkspaceRegistry::open(&base).await.unwrap();
+        let names: Vec<_> = reopened.list().await.iter().map(|w| w.name.clone()).collect();
+        assert_eq!(names, ["alpha", "beta", DEFAULT_WORKSPACE]);
+        let alpha = reopened.resolve(Some("alpha")).await.unwrap();
+        assert!(alpha.event_store.lock().await.all_events().await.unwrap().iter().any(|e| e.id == event.id));
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[tokio::test]
+    async fn test_memory_archives_are_encrypted_under_the_master_key() {
+        let dir = std::env::temp_dir().join(format!("nl_workspaces_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("neuroloom.db");
+        let key = MasterKey::from_passphrase(b"correct horse", &dir).unwrap();
+        let source = Uuid::new_v4();
+        let secret = b"cold memory that mentions the launch codes";
+
+        let entry = memory_archival(&db_path, Some(&key)).unwrap().archive(source, secret).await.unwrap();
+        let stored = std::fs::read(&entry.compressed_path).unwrap();
+        assert!(std::str::from_utf8(&stored).is_ok_and(nl_durable::EventCipher::is_encrypted));
+        let plain = memory_archival(&db_path, None).unwrap();
+        assert!(plain.restore(&source).await.is_err());
+        let restored = memory_archival(&db_path, Some(&key)).unwrap().restore(&source).await.unwrap();
+        assert_eq!(restored, secret);
+
+        // 未设置主密钥时仍只压缩
+        let other = Uuid::new_v4();
+        let entry = memory_archival(&db_path, None).unwrap().archive(other, secret).await.unwrap();
+        let stored = std::fs::read(&entry.compressed_path).unwrap();
+        assert!(!std::str::from_utf8(&stored).is_ok_and(nl_durable::EventCipher::is_encrypted));
+        assert_eq!(plain.restore(&other).await.unwrap(), secret);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
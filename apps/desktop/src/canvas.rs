@@ -0,0 +1,168 @@
+//! 画布 SOP 执行视图与画布镜像
+//!
+//! 订阅守护进程的 SOP 执行事件，维护每次执行的 DAG 与节点状态，供画布点亮节点；
+//! 同一连接上的远程委托事件交给委托看板。
+//!
+//! `follow_canvas` 经 `GET /canvas` 镜像守护进程的画布状态（节点、边与流式内容），
+//! 每条增量转发为 `CANVAS_DELTA_EVENT` Tauri 事件；用户拖动节点时把新位置回传守护进程。
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::sync::{mpsc, RwLock};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use uuid::Uuid;
+
+use nl_core::canvas::{CanvasDelta, CanvasMessage, CanvasState, Point};
+use nl_core::sop_view::{SopRunView, SOP_EVENT_KINDS};
+use nl_core::Event;
+
+use crate::delegations::{DelegationBoard, DELEGATION_EVENT_KINDS};
+
+/// 画布保留的执行数量
+const MAX_RUNS: usize = 16;
+
+/// 推送给前端画布的 Tauri 事件名
+pub const CANVAS_DELTA_EVENT: &str = "canvas://delta";
+
+/// 画布上的 SOP 执行
+#[derive(Debug, Default)]
+pub struct SopCanvas {
+    runs: BTreeMap<Uuid, SopRunView>,
+    /// 执行开始顺序，用于淘汰最早的执行
+    order: Vec<Uuid>,
+}
+
+impl SopCanvas {
+    /// 应用事件，返回状态发生变化的执行
+    pub fn apply(&mut self, event: &Event) -> Option<&SopRunView> {
+        if let Some(view) = SopRunView::from_event(event) {
+            let run = view.run;
+            if self.order.len() >= MAX_RUNS {
+                let oldest = self.order.remove(0);
+                self.runs.remove(&oldest);
+            }
+            self.order.push(run);
+            self.runs.insert(run, view);
+            return self.runs.get(&run);
+        }
+        let run = event.correlation_id?;
+        let view = self.runs.get_mut(&run)?;
+        view.apply(event).then_some(&*view)
+    }
+
+    /// 当前保留的执行（按开始顺序）
+    pub fn runs(&self) -> Vec<&SopRunView> {
+        self.order.iter().filter_map(|id| self.runs.get(id)).collect()
+    }
+}
+
+/// 守护进程启用 API Key 鉴权时，从 `NEUROLOOM_API_KEY` 读取 Key 作为查询参数
+fn push_api_key(url: &mut String) {
+    if let Ok(key) = std::env::var("NEUROLOOM_API_KEY") {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        url.push_str(&format!("{}api_key={}", separator, key));
+    }
+}
+
+/// 持续订阅 SOP 执行与远程委托事件并更新画布与委托看板，断线自动重连
+pub async fn follow(
+    addr: String,
+    workspace: Option<String>,
+    canvas: Arc<RwLock<SopCanvas>>,
+    delegations: Arc<RwLock<DelegationBoard>>,
+) {
+    let kinds: Vec<&str> = SOP_EVENT_KINDS.iter().chain(&DELEGATION_EVENT_KINDS).copied().collect();
+    let mut url = format!("ws://{}/events?kinds={}", addr, kinds.join(","));
+    if let Some(workspace) = workspace {
+        url.push_str(&format!("&workspace={}", workspace));
+    }
+    push_api_key(&mut url);
+
+    loop {
+        match connect_async(url.as_str()).await {
+            Ok((mut stream, _)) => {
+                while let Some(Ok(msg)) = stream.next().await {
+                    let Message::Text(text) = msg else {
+                        continue;
+                    };
+                    let Ok(envelope) = serde_json::from_str::<serde_json::Value>(&text) else {
+                        continue;
+                    };
+                    let Ok(event) = serde_json::from_value::<Event>(envelope["event"].clone()) else {
+                        continue;
+                    };
+                    if let Some(card) = delegations.write().await.apply(&event) {
+                        // TODO: 通过 Tauri 事件推送给前端委托看板
+                        tracing::debug!("Delegation {} updated: {}", card.task_id, card.render());
+                        continue;
+                    }
+                    let mut canvas = canvas.write().await;
+                    if let Some(view) = canvas.apply(&event) {
+                        // TODO: 通过 Tauri 事件推送给前端画布
+                        tracing::debug!("SOP run {} updated:\n{}", view.run, view.render().join("\n"));
+                    }
+                }
+                tracing::warn!("Event stream closed, reconnecting...");
+            }
+            Err(e) => tracing::warn!("Failed to connect to {}: {}", url, e),
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// 镜像守护进程的画布状态，断线或序号不连续时重连取快照
+///
+/// `moves` 接收前端拖动节点后的新位置，回传给守护进程。
+pub async fn follow_canvas(
+    addr: String,
+    workspace: Option<String>,
+    state: Arc<RwLock<CanvasState>>,
+    mut moves: mpsc::UnboundedReceiver<(Uuid, Point)>,
+) {
+    let mut url = format!("ws://{}/canvas", addr);
+    if let Some(workspace) = workspace {
+        url.push_str(&format!("?workspace={}", workspace));
+    }
+    push_api_key(&mut url);
+
+    loop {
+        match connect_async(url.as_str()).await {
+            Ok((stream, _)) => {
+                let (mut sink, mut stream) = stream.split();
+                loop {
+                    tokio::select! {
+                        msg = stream.next() => {
+                            let Some(Ok(msg)) = msg else { break };
+                            let Message::Text(text) = msg else {
+                                continue;
+                            };
+                            let Ok(message) = serde_json::from_str::<CanvasMessage>(&text) else {
+                                continue;
+                            };
+                            if !state.write().await.apply(&message) {
+                                tracing::warn!("Canvas delta {} out of sequence, resyncing...", message.seq);
+                                break;
+                            }
+                            // TODO: 通过 Tauri 事件 CANVAS_DELTA_EVENT 推送给前端画布
+                            tracing::debug!("{} #{}: {:?}", CANVAS_DELTA_EVENT, message.seq, message.delta);
+                        }
+                        moved = moves.recv() => {
+                            let Some((id, position)) = moved else { return };
+                            let delta = CanvasDelta::NodeMoved { id, position };
+                            let text = serde_json::to_string(&delta).unwrap_or_default();
+                            if sink.send(Message::Text(text)).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                tracing::warn!("Canvas stream closed, reconnecting...");
+            }
+            Err(e) => tracing::warn!("Failed to connect to {}: {}", url, e),
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,132 @@
+//! 远程委托看板
+//!
+//! 由守护进程推送的委托事件维护每个远程任务的执行方、完成度、当前步骤与部分交付物，
+//! 停滞转派、放弃与结果驳回同样反映在看板上。
+
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+use nl_core::{Event, EventKind};
+use nl_hap::ArtifactRef;
+
+/// 看板订阅的事件类型
+pub const DELEGATION_EVENT_KINDS: [&str; 5] = [
+    "task_delegated",
+    "delegation_progress",
+    "delegation_stalled",
+    "delegation_completed",
+    "delegation_disputed",
+];
+
+/// 委托状态
+#[derive(Debug, Clone, PartialEq)]
+pub enum DelegationState {
+    Running,
+    /// 停滞后已转派（等待新执行方的首个进度）
+    Reassigned,
+    Completed,
+    Abandoned,
+    /// 结果被驳回，等待执行方补救
+    Remediating,
+    /// 补救后仍被驳回，已收回重新竞拍
+    Rejected,
+}
+
+/// 看板上的远程任务
+#[derive(Debug, Clone)]
+pub struct DelegationCard {
+    pub task_id: Uuid,
+    pub task: String,
+    pub agent: Option<Uuid>,
+    pub attempt: u64,
+    pub percent: f32,
+    pub step: String,
+    pub artifacts: Vec<ArtifactRef>,
+    pub state: DelegationState,
+}
+
+impl DelegationCard {
+    /// 单行展示
+    pub fn render(&self) -> String {
+        let agent = self.agent.map(|a| a.to_string()).unwrap_or_else(|| "-".to_string());
+        let mut line = format!(
+            "{} [{:?}] {:>5.1}% {} (agent {}, attempt {})",
+            self.task, self.state, self.percent, self.step, agent, self.attempt
+        );
+        if !self.artifacts.is_empty() {
+            line.push_str(&format!(", {} partial artifacts", self.artifacts.len()));
+        }
+        line
+    }
+}
+
+/// 远程委托看板
+#[derive(Debug, Default)]
+pub struct DelegationBoard {
+    cards: BTreeMap<Uuid, DelegationCard>,
+}
+
+impl DelegationBoard {
+    /// 应用事件，返回发生变化的任务
+    pub fn apply(&mut self, event: &Event) -> Option<&DelegationCard> {
+        let payload = &event.payload;
+        if !DELEGATION_EVENT_KINDS.contains(&event.kind.as_str()) {
+            return None;
+        }
+        let card = self.cards.entry(event.entity_id).or_insert_with(|| DelegationCard {
+            task_id: event.entity_id,
+            task: String::new(),
+            agent: None,
+            attempt: 1,
+            percent: 0.0,
+            step: String::new(),
+            artifacts: Vec::new(),
+            state: DelegationState::Running,
+        });
+        card.agent = payload["agent"].as_str().and_then(|a| a.parse().ok()).or(card.agent);
+        card.attempt = payload["attempt"].as_u64().unwrap_or(card.attempt);
+        if let Ok(artifacts) = serde_json::from_value::<Vec<ArtifactRef>>(payload["artifacts"].clone()) {
+            card.artifacts = artifacts;
+        }
+
+        match event.kind {
+            EventKind::TaskDelegated => {
+                card.task = payload["task"].as_str().unwrap_or_default().to_string();
+                card.state = DelegationState::Running;
+            }
+            EventKind::DelegationProgress => {
+                card.percent = payload["percent"].as_f64().unwrap_or_default() as f32;
+                card.step = payload["step"].as_str().unwrap_or_default().to_string();
+                card.state = DelegationState::Running;
+            }
+            EventKind::DelegationStalled => match payload["redelegated_to"].as_str() {
+                Some(next) => {
+                    card.agent = next.parse().ok();
+                    card.attempt += 1;
+                    card.state = DelegationState::Reassigned;
+                }
+                None => card.state = DelegationState::Abandoned,
+            },
+            EventKind::DelegationCompleted => {
+                card.percent = 100.0;
+                card.state = DelegationState::Completed;
+            }
+            EventKind::DelegationDisputed => {
+                card.step = payload["evidence"]["reasoning"].as_str().unwrap_or_default().to_string();
+                card.state = if payload["remediation"].as_bool().unwrap_or_default() {
+                    DelegationState::Remediating
+                } else {
+                    DelegationState::Rejected
+                };
+            }
+            _ => {}
+        }
+        Some(card)
+    }
+
+    /// 全部任务
+    pub fn cards(&self) -> Vec<&DelegationCard> {
+        self.cards.values().collect()
+    }
+}
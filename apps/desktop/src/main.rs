@@ -1,5 +1,11 @@
 //! NeuroLoom Desktop - 空间流式画布前端
 
+mod canvas;
+mod delegations;
+
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -15,6 +21,30 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("NeuroLoom Desktop starting...");
 
+    // 选择工作区（名称或路径）：`--workspace` 优先，其次 NEUROLOOM_WORKSPACE，默认守护进程的默认工作区
+    let mut args = std::env::args().skip(1);
+    let mut workspace = std::env::var("NEUROLOOM_WORKSPACE").ok();
+    let mut addr = "127.0.0.1:8766".to_string();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--workspace" => workspace = args.next(),
+            "--addr" => addr = args.next().unwrap_or(addr),
+            _ => {}
+        }
+    }
+    tracing::info!("Workspace: {}", workspace.as_deref().unwrap_or("default"));
+
+    // 订阅 SOP 执行事件，画布据此点亮 DAG 节点；远程委托的进度显示在委托看板
+    let sop_canvas = Arc::new(RwLock::new(canvas::SopCanvas::default()));
+    let delegation_board = Arc::new(RwLock::new(delegations::DelegationBoard::default()));
+    tokio::spawn(canvas::follow(addr.clone(), workspace.clone(), sop_canvas.clone(), delegation_board.clone()));
+
+    // 镜像守护进程的画布（任务、SOP、记忆节点与数据流边）
+    let canvas_state = Arc::new(RwLock::new(nl_core::canvas::CanvasState::default()));
+    // TODO: Tauri 前端拖动节点时经 _canvas_moves 回传新位置
+    let (_canvas_moves, moves) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(canvas::follow_canvas(addr, workspace, canvas_state.clone(), moves));
+
     // TODO: 初始化 Tauri 前端
     // 这里是骨架实现，后续需要集成 Tauri
 
@@ -22,7 +52,12 @@ async fn main() -> anyhow::Result<()> {
 
     // 保持运行
     tokio::signal::ctrl_c().await?;
-    tracing::info!("Shutting down...");
+    tracing::info!(
+        "Shutting down ({} canvas nodes, {} SOP runs on canvas, {} delegated tasks)...",
+        canvas_state.read().await.nodes().count(),
+        sop_canvas.read().await.runs().len(),
+        delegation_board.read().await.cards().len()
+    );
 
     Ok(())
 }
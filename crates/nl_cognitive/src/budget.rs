@@ -0,0 +1,291 @@
+//! MCTS 搜索预算
+//!
+//! 一次搜索可能触发上千次 LLM 调用，`BudgetController` 为单次搜索分配 token / 美元预算：
+//! - 推演过程中通过 `MctsEngine::record_usage` 上报每次调用的用量，按模型单价折算费用
+//! - 以最近若干轮迭代的平均用量估算下一轮的开销
+//! - 剩余预算低于阈值时先收窄搜索（只在平均奖励最高的少数子节点中选择），
+//!   再切换到更便宜的推演模型；剩余预算不够下一轮时停止
+//! - 搜索结果附带 `BudgetReport`，记录消耗、估算值与是否因预算停止
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+/// 模型单价（美元 / 百万 token）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPricing {
+    pub fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+        }
+    }
+
+    /// 一次调用的费用
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_million + output_tokens as f64 * self.output_per_million) / 1_000_000.0
+    }
+}
+
+/// 单次搜索的预算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchBudget {
+    /// token 上限（输入 + 输出）
+    pub max_tokens: Option<u64>,
+    /// 费用上限（美元）
+    pub max_cost_usd: Option<f64>,
+    /// 剩余比例低于此值时收窄搜索
+    pub narrow_below: f64,
+    /// 剩余比例低于此值时切换到便宜的推演模型
+    pub cheap_rollout_below: f64,
+    /// 估算单轮开销时参考的最近迭代数
+    pub estimate_window: usize,
+}
+
+impl Default for SearchBudget {
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            max_cost_usd: None,
+            narrow_below: 0.5,
+            cheap_rollout_below: 0.25,
+            estimate_window: 10,
+        }
+    }
+}
+
+impl SearchBudget {
+    /// 按 token 数限制
+    pub fn tokens(max_tokens: u64) -> Self {
+        Self {
+            max_tokens: Some(max_tokens),
+            ..Default::default()
+        }
+    }
+
+    /// 按费用限制
+    pub fn dollars(max_cost_usd: f64) -> Self {
+        Self {
+            max_cost_usd: Some(max_cost_usd),
+            ..Default::default()
+        }
+    }
+
+    /// 同时限制 token 数
+    pub fn with_max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// 同时限制费用
+    pub fn with_max_cost_usd(mut self, max_cost_usd: f64) -> Self {
+        self.max_cost_usd = Some(max_cost_usd);
+        self
+    }
+}
+
+/// 根据剩余预算对下一轮迭代的决策（按严重程度递增）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetDecision {
+    /// 正常搜索
+    #[default]
+    Continue,
+    /// 收窄分支
+    Narrow,
+    /// 收窄分支并使用便宜的推演模型
+    CheapRollout,
+    /// 预算不足以完成下一轮，停止搜索
+    Stop,
+}
+
+/// 预算消耗报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetReport {
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+    pub spent_tokens: u64,
+    pub spent_cost_usd: f64,
+    /// 已完成的迭代数
+    pub iterations: u32,
+    /// 估算的单轮 token 数
+    pub estimated_iteration_tokens: u64,
+    /// 估算的单轮费用
+    pub estimated_iteration_cost_usd: f64,
+    /// 搜索结束时的决策
+    pub final_decision: BudgetDecision,
+    /// 是否因预算耗尽提前停止
+    pub stopped_for_budget: bool,
+}
+
+/// 一轮迭代的开销
+#[derive(Debug, Clone, Copy, Default)]
+struct IterationCost {
+    tokens: u64,
+    cost_usd: f64,
+}
+
+/// 单次搜索的预算控制器
+#[derive(Debug, Clone)]
+pub struct BudgetController {
+    budget: SearchBudget,
+    pricing: HashMap<String, ModelPricing>,
+    spent: IterationCost,
+    current: IterationCost,
+    recent: VecDeque<IterationCost>,
+    iterations: u32,
+    stopped_for_budget: bool,
+}
+
+impl BudgetController {
+    pub fn new(budget: SearchBudget) -> Self {
+        Self {
+            budget,
+            pricing: HashMap::new(),
+            spent: IterationCost::default(),
+            current: IterationCost::default(),
+            recent: VecDeque::new(),
+            iterations: 0,
+            stopped_for_budget: false,
+        }
+    }
+
+    /// 登记模型单价（未登记的模型只计 token，不计费用）
+    pub fn with_pricing(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.pricing.insert(model.into(), pricing);
+        self
+    }
+
+    /// 记录一次 LLM 调用的用量
+    pub fn record(&mut self, model: &str, input_tokens: u64, output_tokens: u64) {
+        let cost = self
+            .pricing
+            .get(model)
+            .map_or(0.0, |p| p.cost(input_tokens, output_tokens));
+        for total in [&mut self.spent, &mut self.current] {
+            total.tokens += input_tokens + output_tokens;
+            total.cost_usd += cost;
+        }
+    }
+
+    /// 结束一轮迭代，计入单轮开销估算
+    pub fn end_iteration(&mut self) {
+        self.iterations += 1;
+        self.recent.push_back(std::mem::take(&mut self.current));
+        while self.recent.len() > self.budget.estimate_window.max(1) {
+            self.recent.pop_front();
+        }
+    }
+
+    /// 最近迭代的平均单轮开销
+    fn estimated_iteration(&self) -> IterationCost {
+        if self.recent.is_empty() {
+            return IterationCost::default();
+        }
+        let n = self.recent.len() as f64;
+        let tokens: u64 = self.recent.iter().map(|c| c.tokens).sum();
+        let cost: f64 = self.recent.iter().map(|c| c.cost_usd).sum();
+        IterationCost {
+            tokens: (tokens as f64 / n).ceil() as u64,
+            cost_usd: cost / n,
+        }
+    }
+
+    /// 剩余预算比例（取 token 与费用中较紧的一项；无限制时为 1）
+    pub fn remaining_fraction(&self) -> f64 {
+        let tokens = self
+            .budget
+            .max_tokens
+            .map(|max| 1.0 - self.spent.tokens as f64 / max.max(1) as f64);
+        let cost = self
+            .budget
+            .max_cost_usd
+            .map(|max| if max > 0.0 { 1.0 - self.spent.cost_usd / max } else { 0.0 });
+        tokens.into_iter().chain(cost).fold(1.0_f64, f64::min).max(0.0)
+    }
+
+    /// 下一轮迭代的决策
+    pub fn decide(&mut self) -> BudgetDecision {
+        let next = self.estimated_iteration();
+        let exhausted = self
+            .budget
+            .max_tokens
+            .is_some_and(|max| self.spent.tokens + next.tokens > max || self.spent.tokens >= max)
+            || self
+                .budget
+                .max_cost_usd
+                .is_some_and(|max| self.spent.cost_usd + next.cost_usd > max || self.spent.cost_usd >= max);
+        if exhausted {
+            self.stopped_for_budget = true;
+            return BudgetDecision::Stop;
+        }
+        match self.remaining_fraction() {
+            r if r < self.budget.cheap_rollout_below => BudgetDecision::CheapRollout,
+            r if r < self.budget.narrow_below => BudgetDecision::Narrow,
+            _ => BudgetDecision::Continue,
+        }
+    }
+
+    /// 生成消耗报告
+    pub fn report(&self, final_decision: BudgetDecision) -> BudgetReport {
+        let estimate = self.estimated_iteration();
+        BudgetReport {
+            max_tokens: self.budget.max_tokens,
+            max_cost_usd: self.budget.max_cost_usd,
+            spent_tokens: self.spent.tokens,
+            spent_cost_usd: self.spent.cost_usd,
+            iterations: self.iterations,
+            estimated_iteration_tokens: estimate.tokens,
+            estimated_iteration_cost_usd: estimate.cost_usd,
+            final_decision,
+            stopped_for_budget: self.stopped_for_budget,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decisions_escalate_as_budget_depletes() {
+        let mut budget = BudgetController::new(SearchBudget::dollars(1.0).with_max_tokens(100_000))
+            .with_pricing("strong", ModelPricing::new(3.0, 15.0));
+        assert_eq!(budget.decide(), BudgetDecision::Continue);
+
+        // 每轮约 $0.18：10k 输入 + 10k 输出
+        budget.record("strong", 10_000, 10_000);
+        budget.end_iteration();
+        assert_eq!(budget.decide(), BudgetDecision::Continue);
+        budget.record("strong", 10_000, 10_000);
+        budget.end_iteration();
+        budget.record("strong", 10_000, 10_000);
+        budget.end_iteration();
+        // 已花 $0.54，token 剩余 40%
+        assert_eq!(budget.decide(), BudgetDecision::Narrow);
+        budget.record("strong", 10_000, 10_000);
+        budget.end_iteration();
+        assert_eq!(budget.decide(), BudgetDecision::CheapRollout);
+        budget.record("strong", 10_000, 10_000);
+        budget.end_iteration();
+        // token 用尽
+        assert_eq!(budget.decide(), BudgetDecision::Stop);
+
+        let report = budget.report(BudgetDecision::Stop);
+        assert_eq!((report.iterations, report.spent_tokens), (5, 100_000));
+        assert!((report.spent_cost_usd - 0.9).abs() < 1e-9);
+        assert!(report.stopped_for_budget);
+
+        // 未登记单价的模型只计 token
+        let mut tokens_only = BudgetController::new(SearchBudget::tokens(1_000));
+        tokens_only.record("cheap", 600, 0);
+        tokens_only.end_iteration();
+        assert_eq!(tokens_only.decide(), BudgetDecision::Stop);
+        assert_eq!(tokens_only.report(BudgetDecision::Stop).spent_cost_usd, 0.0);
+    }
+}
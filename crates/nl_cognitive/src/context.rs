@@ -0,0 +1,605 @@
+//! 上下文组装 - 决定“哪些内容进入提示词”
+//!
+//! `ContextAssembler` 从多个来源收集候选片段并打分：
+//! - 代码：请求附带的文件按语言感知分块（`CodeChunker`）
+//! - 记忆：HAMT 条目的标签与摘要；请求带记忆查询（`tag:rust after:2024-06 ...`）时只考虑查询命中的条目，
+//!   `near` 相似度与查询词相关度取较高者
+//! - 图谱：与查询相关的 GraphRAG 节点及其一跳邻域
+//! - 近期事件与视觉摘要：按相关度与新近程度综合打分
+//!
+//! 候选按得分从高到低装入目标模型的 token 预算，任何时候都不超出预算；token 数用目标模型的分词器（`with_tokenizers`）计算，
+//! 没有词表的模型按字符估算；
+//! 每个候选都记录被收录或被排除的原因，便于解释提示词的构成。
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+
+use nl_core::tokenizer::{Tokenizer, TokenizerRegistry};
+use nl_core::Result;
+use nl_durable::EventStore;
+use nl_memory::consolidation::Embedder;
+use nl_memory::{CodeChunker, GraphRAG, HamtIndex, MemoryQuery};
+
+/// 未单独配置的模型使用的预算
+const DEFAULT_BUDGET: usize = 8_000;
+
+/// 参与打分的近期事件时间窗口
+const EVENT_WINDOW_HOURS: i64 = 24;
+
+/// 最多考虑的近期事件数
+const MAX_EVENTS: usize = 50;
+
+/// 最多考虑的视觉摘要数
+const MAX_VISION: usize = 10;
+
+/// 低于此分数的候选不收录
+const MIN_SCORE: f64 = 0.05;
+
+/// 上下文来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextSource {
+    Code,
+    Memory,
+    Graph,
+    Event,
+    Vision,
+}
+
+impl ContextSource {
+    /// 来源权重
+    fn weight(&self) -> f64 {
+        match self {
+            ContextSource::Code => 1.0,
+            ContextSource::Memory => 0.9,
+            ContextSource::Graph => 0.8,
+            ContextSource::Event => 0.6,
+            ContextSource::Vision => 0.6,
+        }
+    }
+}
+
+impl fmt::Display for ContextSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ContextSource::Code => "code",
+            ContextSource::Memory => "memory",
+            ContextSource::Graph => "graph",
+            ContextSource::Event => "event",
+            ContextSource::Vision => "vision",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 视觉摘要（如屏幕变化的文字描述）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisionSummary {
+    pub captured_at: DateTime<Utc>,
+    pub text: String,
+}
+
+/// 视觉摘要来源
+#[async_trait]
+pub trait VisionSource: Send + Sync {
+    /// 最近的视觉摘要（新的在后）
+    async fn recent_summaries(&self, limit: usize) -> Result<Vec<VisionSummary>>;
+}
+
+/// 组装请求
+#[derive(Debug, Clone)]
+pub struct ContextRequest {
+    pub query: String,
+    /// 目标模型（决定预算）
+    pub model: String,
+    /// 需要纳入候选的源码文件
+    pub files: Vec<PathBuf>,
+    /// 限定记忆候选的查询
+    pub memory_query: Option<MemoryQuery>,
+}
+
+impl ContextRequest {
+    /// 创建请求
+    pub fn new(query: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            model: model.into(),
+            files: Vec::new(),
+            memory_query: None,
+        }
+    }
+
+    /// 附带源码文件
+    pub fn with_files(mut self, files: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.files.extend(files);
+        self
+    }
+
+    /// 用记忆查询限定记忆候选
+    pub fn with_memory_query(mut self, query: MemoryQuery) -> Self {
+        self.memory_query = Some(query);
+        self
+    }
+}
+
+/// 单个候选的取舍结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextDecision {
+    pub source: ContextSource,
+    pub label: String,
+    pub tokens: usize,
+    pub score: f64,
+    /// 被排除的原因（收录时为空）
+    pub reason: Option<String>,
+}
+
+/// 组装结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssembledContext {
+    pub text: String,
+    pub model: String,
+    pub budget: usize,
+    pub used_tokens: usize,
+    /// 计数所用的分词器（`heuristic` 为按字符估算）
+    pub tokenizer: String,
+    pub included: Vec<ContextDecision>,
+    pub excluded: Vec<ContextDecision>,
+}
+
+impl AssembledContext {
+    /// 逐条说明收录与排除的内容
+    pub fn explain(&self) -> String {
+        let mut lines = vec![format!(
+            "Context for {}: {}/{} tokens ({}), {} included, {} excluded",
+            self.model,
+            self.used_tokens,
+            self.budget,
+            self.tokenizer,
+            self.included.len(),
+            self.excluded.len()
+        )];
+        for decision in &self.included {
+            lines.push(format!(
+                "  + [{}] {} ({} tokens, score {:.2})",
+                decision.source, decision.label, decision.tokens, decision.score
+            ));
+        }
+        for decision in &self.excluded {
+            lines.push(format!(
+                "  - [{}] {} ({} tokens, score {:.2}): {}",
+                decision.source,
+                decision.label,
+                decision.tokens,
+                decision.score,
+                decision.reason.as_deref().unwrap_or_default()
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// 候选片段
+struct Candidate {
+    source: ContextSource,
+    label: String,
+    text: String,
+    score: f64,
+}
+
+/// 上下文组装器
+pub struct ContextAssembler {
+    memory: Option<Arc<HamtIndex>>,
+    embedder: Option<Arc<dyn Embedder>>,
+    graph: Option<Arc<RwLock<GraphRAG>>>,
+    events: Option<Arc<Mutex<EventStore>>>,
+    vision: Option<Arc<dyn VisionSource>>,
+    chunker: CodeChunker,
+    /// 模型名（或前缀）到 token 预算
+    budgets: HashMap<String, usize>,
+    default_budget: usize,
+    tokenizers: Arc<TokenizerRegistry>,
+}
+
+impl ContextAssembler {
+    /// 创建不带任何来源的组装器
+    pub fn new() -> Self {
+        Self {
+            memory: None,
+            embedder: None,
+            graph: None,
+            events: None,
+            vision: None,
+            chunker: CodeChunker::default(),
+            budgets: HashMap::new(),
+            default_budget: DEFAULT_BUDGET,
+            tokenizers: Arc::new(TokenizerRegistry::new()),
+        }
+    }
+
+    /// 设置记忆来源
+    pub fn with_memory(mut self, index: Arc<HamtIndex>) -> Self {
+        self.memory = Some(index);
+        self
+    }
+
+    /// 设置记忆查询 `near` 使用的嵌入器
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// 设置图谱来源
+    pub fn with_graph(mut self, graph: Arc<RwLock<GraphRAG>>) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
+    /// 设置近期事件来源
+    pub fn with_event_store(mut self, store: Arc<Mutex<EventStore>>) -> Self {
+        self.events = Some(store);
+        self
+    }
+
+    /// 设置视觉摘要来源
+    pub fn with_vision(mut self, vision: Arc<dyn VisionSource>) -> Self {
+        self.vision = Some(vision);
+        self
+    }
+
+    /// 设置代码分块器
+    pub fn with_chunker(mut self, chunker: CodeChunker) -> Self {
+        self.chunker = chunker;
+        self
+    }
+
+    /// 设置模型（或模型名前缀，如 `claude-`）的上下文预算
+    pub fn with_budget(mut self, model: impl Into<String>, tokens: usize) -> Self {
+        self.budgets.insert(model.into(), tokens);
+        self
+    }
+
+    /// 设置默认预算
+    pub fn with_default_budget(mut self, tokens: usize) -> Self {
+        self.default_budget = tokens;
+        self
+    }
+
+    /// 设置按模型计数的分词器
+    pub fn with_tokenizers(mut self, tokenizers: Arc<TokenizerRegistry>) -> Self {
+        self.tokenizers = tokenizers;
+        self
+    }
+
+    /// 模型的预算：精确匹配优先，其次取最长的前缀匹配
+    pub fn budget_for(&self, model: &str) -> usize {
+        if let Some(budget) = self.budgets.get(model) {
+            return *budget;
+        }
+        self.budgets
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, budget)| *budget)
+            .unwrap_or(self.default_budget)
+    }
+
+    /// 收集候选并装入预算
+    pub async fn assemble(&self, request: &ContextRequest) -> Result<AssembledContext> {
+        let query = terms(&request.query);
+        let mut candidates = Vec::new();
+        self.code_candidates(request, &query, &mut candidates)
+            .await?;
+        self.memory_candidates(request, &query, &mut candidates).await?;
+        self.graph_candidates(&query, &mut candidates).await;
+        self.event_candidates(&query, &mut candidates).await?;
+        self.vision_candidates(&query, &mut candidates).await?;
+        Ok(pack(
+            candidates,
+            &request.model,
+            self.budget_for(&request.model),
+            self.tokenizers.for_model(&request.model).as_ref(),
+        ))
+    }
+
+    async fn code_candidates(
+        &self,
+        request: &ContextRequest,
+        query: &HashSet<String>,
+        out: &mut Vec<Candidate>,
+    ) -> Result<()> {
+        for path in &request.files {
+            for chunk in self.chunker.chunk_file(path).await? {
+                let name = chunk.name.as_deref().map(terms).unwrap_or_default();
+                // 定义名命中额外加分
+                let score = relevance(query, &terms(&chunk.text)) + 0.5 * relevance(query, &name);
+                out.push(Candidate {
+                    source: ContextSource::Code,
+                    label: chunk.header(),
+                    text: chunk.text,
+                    score: score.min(1.0) * ContextSource::Code.weight(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn memory_candidates(
+        &self,
+        request: &ContextRequest,
+        query: &HashSet<String>,
+        out: &mut Vec<Candidate>,
+    ) -> Result<()> {
+        let Some(memory) = &self.memory else {
+            return Ok(());
+        };
+        let entries = match &request.memory_query {
+            Some(memory_query) => memory_query
+                .execute(memory, self.embedder.as_deref())
+                .await?
+                .into_iter()
+                .map(|hit| (hit.entry, hit.score))
+                .collect::<Vec<_>>(),
+            None => memory.all_entries().into_iter().map(|entry| (entry, 0.0)).collect::<Vec<_>>(),
+        };
+        for (entry, similarity) in entries {
+            let text = format!("{}: {}", entry.tag, entry.summary);
+            let score = relevance(query, &terms(&text)).max(similarity);
+            out.push(Candidate {
+                source: ContextSource::Memory,
+                label: entry.tag.clone(),
+                score: score * ContextSource::Memory.weight(),
+                text,
+            });
+        }
+        Ok(())
+    }
+
+    async fn graph_candidates(&self, query: &HashSet<String>, out: &mut Vec<Candidate>) {
+        let Some(graph) = &self.graph else {
+            return;
+        };
+        let graph = graph.read().await;
+        for node in graph.nodes() {
+            let score = relevance(query, &terms(&node.name));
+            if score == 0.0 {
+                continue;
+            }
+            let mut text = format!("{:?} {}", node.node_type, node.name);
+            if let (Some(path), Some(location)) = (
+                node.path.as_ref().or(node.metadata.get("path")),
+                &node.location,
+            ) {
+                text.push_str(&format!(
+                    " at {}:{}-{}",
+                    path, location.start_line, location.end_line
+                ));
+            }
+            for (edge, neighbor) in graph.neighborhood(&node.id) {
+                let direction = if edge.source == node.id { "->" } else { "<-" };
+                text.push_str(&format!(
+                    "\n  {} {:?} {:?} {}",
+                    direction, edge.edge_type, neighbor.node_type, neighbor.name
+                ));
+            }
+            out.push(Candidate {
+                source: ContextSource::Graph,
+                label: node.name.clone(),
+                text,
+                score: score * ContextSource::Graph.weight(),
+            });
+        }
+    }
+
+    async fn event_candidates(
+        &self,
+        query: &HashSet<String>,
+        out: &mut Vec<Candidate>,
+    ) -> Result<()> {
+        let Some(store) = &self.events else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        let events = store
+            .lock()
+            .await
+            .get_events_by_time(now - chrono::Duration::hours(EVENT_WINDOW_HOURS), now)
+            .await?;
+        let skip = events.len().saturating_sub(MAX_EVENTS);
+        for event in events.into_iter().skip(skip) {
+            let text = format!(
+                "{} {} {}",
+                event.timestamp.to_rfc3339(),
+                event.kind.as_str(),
+                event.payload
+            );
+            out.push(Candidate {
+                source: ContextSource::Event,
+                label: format!("{} {}", event.kind.as_str(), event.id),
+                score: timely(query, &text, event.timestamp, now) * ContextSource::Event.weight(),
+                text,
+            });
+        }
+        Ok(())
+    }
+
+    async fn vision_candidates(
+        &self,
+        query: &HashSet<String>,
+        out: &mut Vec<Candidate>,
+    ) -> Result<()> {
+        let Some(vision) = &self.vision else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        for summary in vision.recent_summaries(MAX_VISION).await? {
+            out.push(Candidate {
+                source: ContextSource::Vision,
+                label: format!("frame {}", summary.captured_at.to_rfc3339()),
+                score: timely(query, &summary.text, summary.captured_at, now)
+                    * ContextSource::Vision.weight(),
+                text: summary.text,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for ContextAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 按得分装入预算（每个片段带一行来源标题，计入预算）
+fn pack(mut candidates: Vec<Candidate>, model: &str, budget: usize, tokenizer: &dyn Tokenizer) -> AssembledContext {
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut seen = HashSet::new();
+    let mut used = 0;
+    let mut sections = Vec::new();
+    let mut included = Vec::new();
+    let mut excluded = Vec::new();
+
+    for candidate in candidates {
+        let section = format!(
+            "[{}] {}\n{}",
+            candidate.source, candidate.label, candidate.text
+        );
+        // 片段之间的空行也算一个 token
+        let tokens = tokenizer.count(&section) + 1;
+        let mut decision = ContextDecision {
+            source: candidate.source,
+            label: candidate.label,
+            tokens,
+            score: candidate.score,
+            reason: None,
+        };
+        decision.reason = if candidate.score < MIN_SCORE {
+            Some("not relevant to the query".to_string())
+        } else if !seen.insert(candidate.text) {
+            Some("duplicate of an included snippet".to_string())
+        } else if used + tokens > budget {
+            Some(format!("over budget ({} tokens left)", budget - used))
+        } else {
+            None
+        };
+        match decision.reason {
+            Some(_) => excluded.push(decision),
+            None => {
+                used += tokens;
+                sections.push(section);
+                included.push(decision);
+            }
+        }
+    }
+
+    AssembledContext {
+        text: sections.join("\n\n"),
+        model: model.to_string(),
+        budget,
+        used_tokens: used,
+        tokenizer: tokenizer.name().to_string(),
+        included,
+        excluded,
+    }
+}
+
+/// 查询词在候选中的覆盖率
+fn relevance(query: &HashSet<String>, words: &HashSet<String>) -> f64 {
+    if query.is_empty() {
+        return 0.0;
+    }
+    query.iter().filter(|t| words.contains(*t)).count() as f64 / query.len() as f64
+}
+
+/// 相关度与新近程度的综合分（一小时前的内容新近分减半）
+fn timely(query: &HashSet<String>, text: &str, at: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let age_hours = (now - at).num_seconds().max(0) as f64 / 3600.0;
+    let recency = 1.0 / (1.0 + age_hours);
+    0.6 * relevance(query, &terms(text)) + 0.4 * recency
+}
+
+/// 小写词集（拆分 snake_case 与 CamelCase，忽略过短的词）
+fn terms(text: &str) -> HashSet<String> {
+    let mut terms = HashSet::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let mut current = String::new();
+        for c in word.chars() {
+            if c.is_uppercase() && !current.is_empty() {
+                terms.insert(std::mem::take(&mut current));
+            }
+            current.extend(c.to_lowercase());
+        }
+        terms.insert(current);
+    }
+    terms.retain(|t| t.len() > 2);
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nl_memory::hamt::MemoryEntry;
+
+    #[tokio::test]
+    async fn test_assemble_within_budget_with_explanation() {
+        let index = HamtIndex::new();
+        index.store(MemoryEntry::new(
+            "retry policy",
+            "Token refresh uses exponential backoff retries",
+        ));
+        index.store(MemoryEntry::new(
+            "ui theme",
+            "Dark mode colors for the desktop app",
+        ));
+        index.store(MemoryEntry::new(
+            "refresh notes",
+            "Token refresh failures were traced to clock skew; ".repeat(20),
+        ));
+
+        let assembler = ContextAssembler::new()
+            .with_memory(Arc::new(index))
+            .with_budget("claude-", 40)
+            .with_budget("claude-small", 20);
+        assert_eq!(assembler.budget_for("claude-small"), 20);
+        assert_eq!(assembler.budget_for("claude-large"), 40);
+        assert_eq!(assembler.budget_for("gpt"), DEFAULT_BUDGET);
+
+        let context = assembler
+            .assemble(&ContextRequest::new(
+                "why does token refresh retry",
+                "claude-large",
+            ))
+            .await
+            .unwrap();
+        assert!(context.used_tokens <= context.budget);
+        assert_eq!(context.tokenizer, "heuristic");
+        assert_eq!(context.included.len(), 1);
+        assert_eq!(context.included[0].label, "retry policy");
+        assert!(context.text.contains("exponential backoff"));
+
+        let reasons: Vec<&str> = context
+            .excluded
+            .iter()
+            .filter_map(|d| d.reason.as_deref())
+            .collect();
+        assert!(reasons.iter().any(|r| r.starts_with("over budget")));
+        assert!(reasons.contains(&"not relevant to the query"));
+        assert!(context.explain().contains("- [memory] ui theme"));
+
+        let scoped = assembler
+            .assemble(
+                &ContextRequest::new("token refresh", "claude-large")
+                    .with_memory_query("tag:notes".parse().unwrap()),
+            )
+            .await
+            .unwrap();
+        assert!(scoped.included.iter().chain(&scoped.excluded).all(|d| d.label == "refresh notes"));
+    }
+}
@@ -0,0 +1,387 @@
+//! 静态分析证据
+//!
+//! 除了 LLM 的判断，Worker 的产出还可以交给 lint 工具检查。`StaticAnalyzer` 通过沙箱
+//! （`SandboxExecutor::execute_god_mode_for`，受 Worker 画像的 `exec` 授权约束并写入审计）在项目目录中运行
+//! clippy / ruff / eslint，把 JSON 诊断解析为结构化的 `Finding`：
+//! - clippy：`cargo clippy --message-format=json` 的 `compiler-message` 行，取主 span 的位置
+//! - ruff：`ruff check --output-format=json` 的诊断数组
+//! - eslint：`eslint --format json` 的文件数组，`severity` 2 为错误、1 为警告
+//!
+//! `AnalysisReport::prompt_section` 把发现注入 Critic 提示词，`Verdict::with_analysis` 把报告作为裁决证据。
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::Result;
+use nl_durable::actor_mesh::ActorId;
+use nl_sandbox::god_mode::GodModeAction;
+use nl_sandbox::SandboxExecutor;
+
+/// 提示词中最多列出的发现数
+const MAX_PROMPT_FINDINGS: usize = 30;
+
+/// lint 工具
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Linter {
+    Clippy,
+    Ruff,
+    Eslint,
+}
+
+impl Linter {
+    /// 按项目文件推断可用的 lint 工具（多语言项目可能有多个）
+    pub fn detect(root: &Path) -> Vec<Self> {
+        let mut linters = Vec::new();
+        if root.join("Cargo.toml").is_file() {
+            linters.push(Linter::Clippy);
+        }
+        if ["pyproject.toml", "ruff.toml", ".ruff.toml", "setup.py"]
+            .iter()
+            .any(|f| root.join(f).is_file())
+        {
+            linters.push(Linter::Ruff);
+        }
+        if root.join("package.json").is_file() {
+            linters.push(Linter::Eslint);
+        }
+        linters
+    }
+
+    /// 工具名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Linter::Clippy => "clippy",
+            Linter::Ruff => "ruff",
+            Linter::Eslint => "eslint",
+        }
+    }
+
+    /// 程序与参数（输出 JSON 诊断）
+    fn command(&self) -> (&'static str, Vec<&'static str>) {
+        match self {
+            Linter::Clippy => ("cargo", vec!["clippy", "--message-format=json", "--quiet"]),
+            Linter::Ruff => ("ruff", vec!["check", "--output-format=json", "--exit-zero", "."]),
+            Linter::Eslint => ("npx", vec!["--no-install", "eslint", "--format", "json", "."]),
+        }
+    }
+
+    /// 解析 JSON 诊断
+    pub fn parse(&self, output: &str) -> Vec<Finding> {
+        match self {
+            Linter::Clippy => parse_clippy(output),
+            Linter::Ruff => parse_ruff(output),
+            Linter::Eslint => parse_eslint(output),
+        }
+    }
+}
+
+impl fmt::Display for Linter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// 一条静态分析发现
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    pub linter: Linter,
+    pub severity: Severity,
+    /// 规则（如 `clippy::needless_return`、`F401`、`no-unused-vars`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<u64>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file)?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+                if let Some(column) = self.column {
+                    write!(f, ":{}", column)?;
+                }
+            }
+            f.write_str(" ")?;
+        }
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        write!(f, "{} ", severity)?;
+        match &self.rule {
+            Some(rule) => write!(f, "[{}/{}] {}", self.linter, rule, self.message),
+            None => write!(f, "[{}] {}", self.linter, self.message),
+        }
+    }
+}
+
+/// 静态分析报告
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisReport {
+    /// 运行的工具
+    pub linters: Vec<Linter>,
+    /// 发现（错误在前）
+    pub findings: Vec<Finding>,
+    /// 未能运行或输出无法解析的工具及原因
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+}
+
+impl AnalysisReport {
+    /// 指定严重程度的发现数
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|f| f.severity == severity).count()
+    }
+
+    /// 错误级别的发现
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Error)
+    }
+
+    /// 没有任何发现且所有工具都运行成功
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty() && self.failures.is_empty()
+    }
+
+    /// 一行摘要
+    pub fn summary(&self) -> String {
+        let linters: Vec<&str> = self.linters.iter().map(Linter::as_str).collect();
+        let mut summary = format!(
+            "{} error(s), {} warning(s) from {}",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            linters.join(", ")
+        );
+        if !self.failures.is_empty() {
+            summary.push_str(&format!(" ({} linter(s) failed to run)", self.failures.len()));
+        }
+        summary
+    }
+
+    /// 注入 Critic 提示词的段落
+    pub fn prompt_section(&self) -> String {
+        let mut section = format!("## Static analysis\n{}\n", self.summary());
+        for finding in self.findings.iter().take(MAX_PROMPT_FINDINGS) {
+            section.push_str(&format!("- {}\n", finding));
+        }
+        if self.findings.len() > MAX_PROMPT_FINDINGS {
+            section.push_str(&format!("- ... {} more\n", self.findings.len() - MAX_PROMPT_FINDINGS));
+        }
+        for failure in &self.failures {
+            section.push_str(&format!("- (not run) {}\n", failure));
+        }
+        section
+    }
+
+    fn push(&mut self, linter: Linter, findings: Vec<Finding>) {
+        self.linters.push(linter);
+        self.findings.extend(findings);
+        self.findings.sort_by_key(|f| f.severity);
+    }
+}
+
+/// 静态分析器：通过沙箱运行 lint 工具
+pub struct StaticAnalyzer {
+    executor: Arc<SandboxExecutor>,
+    root: PathBuf,
+    linters: Vec<Linter>,
+    /// 代表哪个 Actor 执行（按其画像检查 `exec` 授权）
+    actor: ActorId,
+}
+
+impl StaticAnalyzer {
+    /// 创建分析器，按项目文件推断 lint 工具
+    pub fn new(executor: Arc<SandboxExecutor>, root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            linters: Linter::detect(&root),
+            executor,
+            root,
+            actor: Uuid::nil(),
+        }
+    }
+
+    /// 指定 lint 工具（替换推断结果）
+    pub fn with_linters(mut self, linters: impl IntoIterator<Item = Linter>) -> Self {
+        self.linters = linters.into_iter().collect();
+        self
+    }
+
+    /// 代表指定 Actor 执行
+    pub fn with_actor(mut self, actor: ActorId) -> Self {
+        self.actor = actor;
+        self
+    }
+
+    /// 使用的 lint 工具
+    pub fn linters(&self) -> &[Linter] {
+        &self.linters
+    }
+
+    /// 运行全部 lint 工具；单个工具失败记入 `failures`，不影响其他工具
+    pub async fn run(&self) -> Result<AnalysisReport> {
+        let mut report = AnalysisReport::default();
+        for linter in &self.linters {
+            let (command, args) = linter.command();
+            let action = GodModeAction::Execute {
+                command: command.to_string(),
+                args: args.into_iter().map(String::from).collect(),
+                cwd: Some(self.root.clone()),
+            };
+            let result = self.executor.execute_god_mode_for(self.actor, action).await?;
+            let findings = linter.parse(&result.output);
+            if findings.is_empty() && !result.success {
+                let error = result.error.unwrap_or_default();
+                let reason = error.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("exited with an error");
+                report.failures.push(format!("{}: {}", linter, reason.trim()));
+                continue;
+            }
+            report.push(*linter, findings);
+        }
+        tracing::info!("Static analysis of {}: {}", self.root.display(), report.summary());
+        Ok(report)
+    }
+}
+
+fn parse_clippy(output: &str) -> Vec<Finding> {
+    let mut seen = HashSet::new();
+    let mut findings = Vec::new();
+    for line in output.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if value["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &value["message"];
+        let severity = match message["level"].as_str() {
+            Some("error") => Severity::Error,
+            Some("warning") => Severity::Warning,
+            _ => continue,
+        };
+        // 没有 span 的是 “N warnings emitted” 一类汇总
+        let spans = message["spans"].as_array().cloned().unwrap_or_default();
+        let Some(span) = spans.iter().find(|s| s["is_primary"] == true).or(spans.first()) else {
+            continue;
+        };
+        let finding = Finding {
+            linter: Linter::Clippy,
+            severity,
+            rule: message["code"]["code"].as_str().map(String::from),
+            message: message["message"].as_str().unwrap_or_default().to_string(),
+            file: span["file_name"].as_str().map(String::from),
+            line: span["line_start"].as_u64(),
+            column: span["column_start"].as_u64(),
+        };
+        // 同一文件被多个目标编译时诊断会重复
+        if seen.insert(finding.to_string()) {
+            findings.push(finding);
+        }
+    }
+    findings
+}
+
+fn parse_ruff(output: &str) -> Vec<Finding> {
+    let Ok(serde_json::Value::Array(items)) = serde_json::from_str(output.trim()) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .map(|item| {
+            let rule = item["code"].as_str().map(String::from);
+            Finding {
+                linter: Linter::Ruff,
+                // 没有规则码的是语法错误
+                severity: if rule.is_some() { Severity::Warning } else { Severity::Error },
+                rule,
+                message: item["message"].as_str().unwrap_or_default().to_string(),
+                file: item["filename"].as_str().map(String::from),
+                line: item["location"]["row"].as_u64(),
+                column: item["location"]["column"].as_u64(),
+            }
+        })
+        .collect()
+}
+
+fn parse_eslint(output: &str) -> Vec<Finding> {
+    let Ok(serde_json::Value::Array(files)) = serde_json::from_str(output.trim()) else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    for file in &files {
+        for message in file["messages"].as_array().into_iter().flatten() {
+            findings.push(Finding {
+                linter: Linter::Eslint,
+                severity: match message["severity"].as_u64() {
+                    Some(2) => Severity::Error,
+                    Some(1) => Severity::Warning,
+                    _ => Severity::Info,
+                },
+                rule: message["ruleId"].as_str().map(String::from),
+                message: message["message"].as_str().unwrap_or_default().to_string(),
+                file: file["filePath"].as_str().map(String::from),
+                line: message["line"].as_u64(),
+                column: message["column"].as_u64(),
+            });
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_linter_output() {
+        let clippy = r#"{"reason":"compiler-artifact","target":{}}
+{"reason":"compiler-message","message":{"level":"warning","message":"unneeded `return` statement","code":{"code":"clippy::needless_return"},"spans":[{"file_name":"src/lib.rs","line_start":3,"column_start":5,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"level":"warning","message":"unneeded `return` statement","code":{"code":"clippy::needless_return"},"spans":[{"file_name":"src/lib.rs","line_start":3,"column_start":5,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"level":"warning","message":"1 warning emitted","code":null,"spans":[]}}"#;
+        let findings = Linter::Clippy.parse(clippy);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].to_string(),
+            "src/lib.rs:3:5 warning [clippy/clippy::needless_return] unneeded `return` statement"
+        );
+
+        let ruff = r#"[{"code":"F401","message":"`os` imported but unused","filename":"app.py","location":{"row":1,"column":8}},
+{"code":null,"message":"SyntaxError: Expected an expression","filename":"bad.py","location":{"row":2,"column":1}}]"#;
+        let eslint = r#"[{"filePath":"/p/index.js","messages":[{"ruleId":"no-undef","severity":2,"message":"'x' is not defined.","line":4,"column":1},{"ruleId":"semi","severity":1,"message":"Missing semicolon.","line":5,"column":9}]}]"#;
+
+        let mut report = AnalysisReport::default();
+        report.push(Linter::Ruff, Linter::Ruff.parse(ruff));
+        report.push(Linter::Eslint, Linter::Eslint.parse(eslint));
+        report.failures.push("clippy: could not compile".to_string());
+        assert_eq!((report.count(Severity::Error), report.count(Severity::Warning)), (2, 2));
+        assert_eq!(report.errors().next().unwrap().linter, Linter::Ruff);
+        let section = report.prompt_section();
+        assert!(section.starts_with("## Static analysis\n2 error(s), 2 warning(s) from ruff, eslint"), "{}", section);
+        assert!(section.contains("/p/index.js:4:1 error [eslint/no-undef]"));
+        assert!(section.contains("(not run) clippy: could not compile"));
+        assert!(Linter::Eslint.parse("not json").is_empty());
+    }
+}
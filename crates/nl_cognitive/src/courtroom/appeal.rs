@@ -0,0 +1,196 @@
+//! 上诉机制 - 由更强的上诉法官复核被反复驳回的草稿
+//!
+//! Critic 偶尔会误判好的结果。同一任务中实质相同的草稿被驳回达到阈值后，
+//! 法庭把草稿与历次驳回理由提交给单独配置的上诉模型，其裁决覆盖 Critic 的结论，
+//! 并以 `AppealRecord` 记录在裁决链中。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+use nl_llm::{LlmClient, PrimitiveRequest};
+
+use super::Verdict;
+
+/// 上诉策略
+#[derive(Debug, Clone)]
+pub struct AppealPolicy {
+    /// 实质相同的草稿被驳回多少次后上诉
+    pub max_rejections: u32,
+    /// 判定为实质相同的最低词集相似度 (Jaccard)
+    pub similarity: f64,
+}
+
+impl AppealPolicy {
+    /// 创建上诉策略
+    pub fn new(max_rejections: u32) -> Self {
+        Self {
+            max_rejections,
+            ..Self::default()
+        }
+    }
+
+    /// 设置实质相同的相似度阈值
+    pub fn with_similarity(mut self, similarity: f64) -> Self {
+        self.similarity = similarity;
+        self
+    }
+
+    /// 两份草稿是否实质相同（忽略大小写、空白与标点）
+    pub fn is_same_draft(&self, a: &str, b: &str) -> bool {
+        let (a, b) = (word_set(a), word_set(b));
+        if a.is_empty() && b.is_empty() {
+            return true;
+        }
+        let union = a.union(&b).count();
+        let intersection = a.intersection(&b).count();
+        intersection as f64 / union as f64 >= self.similarity
+    }
+}
+
+impl Default for AppealPolicy {
+    fn default() -> Self {
+        Self {
+            max_rejections: 3,
+            similarity: 0.9,
+        }
+    }
+}
+
+pub(crate) fn word_set(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// 上诉记录（附在上诉裁决上）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppealRecord {
+    /// 上诉法官模型
+    pub judge_model: String,
+    /// 被覆盖的 Critic 裁决 ID
+    pub overruled: Vec<Uuid>,
+}
+
+/// 上诉法官
+#[async_trait]
+pub trait AppellateJudge: Send + Sync {
+    /// 法官模型名称
+    fn model(&self) -> &str;
+
+    /// 复核草稿，`rejections` 为此前被覆盖的驳回裁决
+    async fn hear(&self, task: &str, draft: &str, rejections: &[Verdict]) -> Result<Verdict>;
+}
+
+/// LLM 返回的裁决
+#[derive(Debug, Deserialize)]
+struct JudgedVerdict {
+    passed: bool,
+    #[serde(default)]
+    score: f64,
+    #[serde(default)]
+    reasoning: String,
+    #[serde(default)]
+    suggestions: Vec<String>,
+}
+
+/// 基于 LLM 的上诉法官
+pub struct LlmAppellateJudge {
+    /// LLM 客户端
+    client: Arc<LlmClient>,
+    /// 上诉模型（通常强于 Critic 使用的模型）
+    model: String,
+}
+
+impl LlmAppellateJudge {
+    /// 复核提示词
+    const SYSTEM_PROMPT: &'static str =
+        "You are an appellate judge reviewing work that lower critics rejected several times. \
+Judge the draft on its merits against the task; the critics may be wrong. \
+Reply with a JSON object only: {\"passed\": bool, \"score\": number 0-1, \
+\"reasoning\": string, \"suggestions\": [string]}.";
+
+    /// 创建上诉法官
+    pub fn new(client: Arc<LlmClient>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+        }
+    }
+
+    /// 解析 LLM 输出为裁决
+    pub fn parse_verdict(content: &str) -> Result<Verdict> {
+        // 容忍 ```json 代码块包裹
+        let json = match (content.find('{'), content.rfind('}')) {
+            (Some(s), Some(e)) if s < e => &content[s..=e],
+            _ => {
+                return Err(NeuroLoomError::LlmProvider(
+                    "Appellate response contains no JSON object".to_string(),
+                ))
+            }
+        };
+        let judged: JudgedVerdict = serde_json::from_str(json)?;
+        let score = judged.score.clamp(0.0, 1.0);
+        Ok(if judged.passed {
+            Verdict::approved(Uuid::nil(), score, judged.reasoning)
+        } else {
+            Verdict::rejected(Uuid::nil(), score, judged.reasoning, judged.suggestions)
+        })
+    }
+}
+
+#[async_trait]
+impl AppellateJudge for LlmAppellateJudge {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn hear(&self, task: &str, draft: &str, rejections: &[Verdict]) -> Result<Verdict> {
+        let mut prompt = format!("## Task\n{}\n\n## Draft\n{}\n\n## Critic rejections\n", task, draft);
+        for (i, verdict) in rejections.iter().enumerate() {
+            prompt.push_str(&format!("{}. {}\n", i + 1, verdict.reasoning));
+            for suggestion in &verdict.suggestions {
+                prompt.push_str(&format!("   - {}\n", suggestion));
+            }
+        }
+        if let Some(report) = rejections.iter().rev().find_map(|v| v.analysis.as_ref()) {
+            prompt.push('\n');
+            prompt.push_str(&report.prompt_section());
+        }
+
+        let mut req = PrimitiveRequest::single_user_message(prompt).with_model(self.model.clone());
+        req.system = Some(Self::SYSTEM_PROMPT.to_string());
+        let response = self
+            .client
+            .complete(&req)
+            .await
+            .map_err(|e| NeuroLoomError::LlmProvider(e.to_string()))?;
+
+        Self::parse_verdict(&response.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_draft_and_parse_verdict() {
+        let policy = AppealPolicy::new(2);
+        assert!(policy.is_same_draft("Fix the bug in parser.rs", "fix the  bug in parser.rs!"));
+        assert!(!policy.is_same_draft("Fix the bug in parser.rs", "Rewrite the lexer entirely"));
+
+        let verdict = LlmAppellateJudge::parse_verdict(
+            "```json\n{\"passed\": true, \"score\": 1.4, \"reasoning\": \"critics were too strict\"}\n```",
+        )
+        .unwrap();
+        assert!(verdict.passed);
+        assert_eq!(verdict.score, 1.0);
+        assert!(LlmAppellateJudge::parse_verdict("no json").is_err());
+    }
+}
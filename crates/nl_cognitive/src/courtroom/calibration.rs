@@ -0,0 +1,258 @@
+//! 置信度校准 - 把 Critic 评分映射为经验通过概率
+//!
+//! Critic 评分只是未经校准的浮点数。每当裁决得到真实结果（测试门控的测试结果、人工批准或驳回），
+//! 法庭把当时的评分与结果作为 `VerdictOutcome` 事件记入事件存储；校准器据此按保序回归（PAV）
+//! 拟合分段线性的校准曲线，裁决的 `confidence` 即评分在曲线上的通过概率。
+//! 样本不足 `min_samples` 时不给出置信度，自动批准应据此回退到人工审核。
+
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use nl_core::{Event, EventKind, Result};
+use nl_durable::EventStore;
+
+/// 拟合所需的最少样本数
+const DEFAULT_MIN_SAMPLES: usize = 20;
+
+/// 参与拟合的最近样本数上限
+const MAX_SAMPLES: usize = 5000;
+
+/// 真实结果来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeSource {
+    /// 测试门控的测试结果
+    Tests,
+    /// 人工批准或驳回
+    Human,
+}
+
+/// 一条校准样本：裁决评分与真实结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationSample {
+    /// 对应的裁决
+    pub verdict_id: Uuid,
+    /// Critic 评分（测试门控前）
+    pub score: f64,
+    /// 真实结果是否通过
+    pub passed: bool,
+    pub source: OutcomeSource,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl CalibrationSample {
+    /// 创建样本
+    pub fn new(verdict_id: Uuid, score: f64, passed: bool, source: OutcomeSource) -> Self {
+        Self {
+            verdict_id,
+            score,
+            passed,
+            source,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    /// 转为 `VerdictOutcome` 事件（实体为任务）
+    pub fn to_event(&self, task_id: Uuid) -> Result<Event> {
+        Ok(Event::new(EventKind::VerdictOutcome, task_id, serde_json::to_value(self)?))
+    }
+}
+
+/// 校准曲线：按评分升序的 (评分, 通过概率) 节点，节点之间线性插值，两端取端点值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationCurve {
+    pub points: Vec<(f64, f64)>,
+    /// 拟合所用样本数
+    pub samples: usize,
+}
+
+impl CalibrationCurve {
+    /// 保序回归拟合；每段的概率做拉普拉斯平滑，避免少量样本给出 0 或 1
+    pub fn fit(samples: &[CalibrationSample]) -> Option<Self> {
+        let mut sorted: Vec<(f64, bool)> = samples
+            .iter()
+            .filter(|s| s.score.is_finite())
+            .map(|s| (s.score, s.passed))
+            .collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // 每个块：(评分和, 通过数, 样本数)；通过率相同的相邻块也合并
+        let mut blocks: Vec<(f64, f64, f64)> = Vec::new();
+        for (score, passed) in sorted {
+            blocks.push((score, if passed { 1.0 } else { 0.0 }, 1.0));
+            while blocks.len() >= 2 {
+                let (_, p2, n2) = blocks[blocks.len() - 1];
+                let (_, p1, n1) = blocks[blocks.len() - 2];
+                if p1 / n1 < p2 / n2 {
+                    break;
+                }
+                let (s2, p2, n2) = blocks.pop().expect("two blocks");
+                let last = blocks.last_mut().expect("two blocks");
+                *last = (last.0 + s2, last.1 + p2, last.2 + n2);
+            }
+        }
+        // 平滑可能打破小块之间的单调性，取累计最大值恢复
+        let mut floor = 0.0f64;
+        let points = blocks
+            .iter()
+            .map(|(sum, passed, count)| {
+                floor = floor.max((passed + 1.0) / (count + 2.0));
+                (sum / count, floor)
+            })
+            .collect();
+        Some(Self {
+            points,
+            samples: samples.len(),
+        })
+    }
+
+    /// 评分对应的通过概率
+    pub fn probability(&self, score: f64) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if score <= first.0 {
+            return first.1;
+        }
+        if score >= last.0 {
+            return last.1;
+        }
+        let upper = self.points.iter().position(|(s, _)| *s >= score).unwrap_or(self.points.len() - 1);
+        let ((s0, p0), (s1, p1)) = (self.points[upper - 1], self.points[upper]);
+        if s1 <= s0 {
+            return p1;
+        }
+        p0 + (p1 - p0) * (score - s0) / (s1 - s0)
+    }
+
+    /// 通过概率达到 `target` 的最低评分，供自动批准设置阈值；曲线达不到时为空
+    pub fn threshold_for(&self, target: f64) -> Option<f64> {
+        let mut previous: Option<(f64, f64)> = None;
+        for &(score, probability) in &self.points {
+            if probability >= target {
+                return Some(match previous {
+                    Some((s0, p0)) if probability > p0 && target > p0 => {
+                        s0 + (score - s0) * (target - p0) / (probability - p0)
+                    }
+                    _ => score,
+                });
+            }
+            previous = Some((score, probability));
+        }
+        None
+    }
+}
+
+/// 校准器：累积样本并维护当前曲线
+pub struct Calibrator {
+    min_samples: usize,
+    state: RwLock<(Vec<CalibrationSample>, Option<CalibrationCurve>)>,
+}
+
+impl Calibrator {
+    /// 创建空校准器
+    pub fn new() -> Self {
+        Self {
+            min_samples: DEFAULT_MIN_SAMPLES,
+            state: RwLock::new((Vec::new(), None)),
+        }
+    }
+
+    /// 设置拟合所需的最少样本数
+    pub fn with_min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples.max(1);
+        self
+    }
+
+    /// 从事件存储中的 `VerdictOutcome` 事件恢复样本
+    pub async fn from_store(store: &Mutex<EventStore>) -> Result<Self> {
+        let events = store.lock().await.get_events_by_kind(&EventKind::VerdictOutcome).await?;
+        let calibrator = Self::new();
+        calibrator.extend(events.into_iter().filter_map(|e| serde_json::from_value(e.payload).ok()));
+        Ok(calibrator)
+    }
+
+    /// 加入样本并重新拟合
+    pub fn observe(&self, sample: CalibrationSample) {
+        self.extend(std::iter::once(sample));
+    }
+
+    fn extend(&self, samples: impl IntoIterator<Item = CalibrationSample>) {
+        let mut state = self.state.write().unwrap();
+        state.0.extend(samples);
+        let excess = state.0.len().saturating_sub(MAX_SAMPLES);
+        state.0.drain(..excess);
+        state.1 = if state.0.len() >= self.min_samples {
+            CalibrationCurve::fit(&state.0)
+        } else {
+            None
+        };
+    }
+
+    /// 评分对应的通过概率（样本不足时为空）
+    pub fn calibrate(&self, score: f64) -> Option<f64> {
+        self.state.read().unwrap().1.as_ref().map(|curve| curve.probability(score))
+    }
+
+    /// 当前曲线
+    pub fn curve(&self) -> Option<CalibrationCurve> {
+        self.state.read().unwrap().1.clone()
+    }
+
+    /// 已有样本数
+    pub fn sample_count(&self) -> usize {
+        self.state.read().unwrap().0.len()
+    }
+}
+
+impl Default for Calibrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_is_monotone_and_gives_thresholds() {
+        let calibrator = Calibrator::new().with_min_samples(10);
+        // 高分并不总是通过：0.9 分只有一半通过，0.6 分以下全部失败
+        let outcomes = [
+            (0.3, false),
+            (0.4, false),
+            (0.5, false),
+            (0.6, false),
+            (0.6, true),
+            (0.7, true),
+            (0.8, false),
+            (0.8, true),
+            (0.9, true),
+            (0.9, false),
+            (0.95, true),
+            (0.99, true),
+        ];
+        for (i, (score, passed)) in outcomes.iter().enumerate() {
+            if i == 9 {
+                assert!(calibrator.calibrate(0.9).is_none());
+            }
+            calibrator.observe(CalibrationSample::new(Uuid::new_v4(), *score, *passed, OutcomeSource::Tests));
+        }
+
+        let curve = calibrator.curve().unwrap();
+        assert!(curve.points.windows(2).all(|w| w[0].0 <= w[1].0 && w[0].1 <= w[1].1));
+        let low = calibrator.calibrate(0.3).unwrap();
+        let high = calibrator.calibrate(0.99).unwrap();
+        assert!(low < 0.25 && high > 0.6 && high < 1.0, "{} {}", low, high);
+
+        let threshold = curve.threshold_for(0.6).unwrap();
+        assert!(curve.probability(threshold) >= 0.6 - 1e-9);
+        assert!(curve.threshold_for(0.99).is_none());
+    }
+}
@@ -2,6 +2,10 @@
 
 use uuid::Uuid;
 
+use nl_sandbox::TestReport;
+
+use super::analysis::AnalysisReport;
+
 /// Critic Agent - 审查和质疑
 pub struct Critic {
     /// Critic ID
@@ -28,6 +32,41 @@ impl Critic {
             suggestions: Vec::new(),
         })
     }
+
+    /// 结合测试报告审查：测试未通过时一律驳回，失败的测试逐条列为问题
+    pub async fn review_with_tests(&self, work: &str, report: &TestReport) -> nl_core::Result<ReviewResult> {
+        let mut result = self.review(work).await?;
+        if !report.success() {
+            result.approved = false;
+            result.issues.push(format!("Tests did not pass: {}", report.summary()));
+            result
+                .issues
+                .extend(report.failing.iter().map(|name| format!("Failing test: {}", name)));
+            result.suggestions.push("Fix the failing tests before resubmitting".to_string());
+        }
+        Ok(result)
+    }
+
+    /// 审查提示词：任务、草稿与静态分析发现
+    pub fn review_prompt(&self, task: &str, draft: &str, analysis: Option<&AnalysisReport>) -> String {
+        let mut prompt = format!("## Task\n{}\n\n## Draft\n{}\n", task, draft);
+        if let Some(report) = analysis {
+            prompt.push('\n');
+            prompt.push_str(&report.prompt_section());
+        }
+        prompt
+    }
+
+    /// 结合静态分析审查：错误级别的发现一律驳回并逐条列为问题，警告只列为问题
+    pub async fn review_with_analysis(&self, work: &str, report: &AnalysisReport) -> nl_core::Result<ReviewResult> {
+        let mut result = self.review(work).await?;
+        result.issues.extend(report.findings.iter().map(|finding| finding.to_string()));
+        if report.errors().next().is_some() {
+            result.approved = false;
+            result.suggestions.push("Fix the static analysis errors before resubmitting".to_string());
+        }
+        Ok(result)
+    }
 }
 
 impl Default for Critic {
@@ -3,10 +3,29 @@
 pub mod worker;
 pub mod critic;
 pub mod parliament;
+pub mod appeal;
+pub mod calibration;
+pub mod analysis;
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 use uuid::Uuid;
 
+use nl_core::{ArtifactKind, Event, EventKind};
+use nl_durable::{ArtifactStore, EventStore, SnapshotManager, SnapshotStrategy};
+use nl_sandbox::{TestReport, TestRunner};
+
+pub use appeal::{AppealPolicy, AppealRecord, AppellateJudge, LlmAppellateJudge};
+pub use calibration::{CalibrationCurve, CalibrationSample, Calibrator, OutcomeSource};
+pub use analysis::{AnalysisReport, Finding, Linter, Severity, StaticAnalyzer};
+
+use crate::templates::PromptTemplate;
+
 /// 裁决结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Verdict {
@@ -18,10 +37,50 @@ pub struct Verdict {
     pub passed: bool,
     /// 评分
     pub score: f64,
+    /// 校准后的通过概率（设置了校准器且样本充足时带有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
     /// 理由
     pub reasoning: String,
     /// 修改建议
     pub suggestions: Vec<String>,
+    /// 上诉记录（仅上诉法官的裁决带有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appeal: Option<AppealRecord>,
+    /// 测试报告（测试门控模式下带有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tests: Option<TestReport>,
+    /// 静态分析报告（配置了静态分析器时带有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<AnalysisReport>,
+    /// 引用的产物（被审议的草稿、测试输出），`artifact://<hash>`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+    /// 生成草稿所用的 Worker 模板（`name@version`），供提示词自动改进按类别统计
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// 截至本裁决的审议轮次（按轮次顺序），供客户端绘制进度时间线
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rounds: Vec<DeliberationRound>,
+}
+
+/// 一轮审议的进度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliberationRound {
+    /// 轮次（从 1 开始）
+    pub round: u32,
+    /// 草稿摘要（SHA-256 前 16 位）
+    pub draft_hash: String,
+    /// 本轮评分
+    pub score: f64,
+    /// 截至本轮的最高评分
+    pub best_score: f64,
+    /// 本轮是否通过
+    pub passed: bool,
+    /// 剩余轮数
+    pub remaining_rounds: u32,
+    /// 本轮结束时间
+    pub finished_at: DateTime<Utc>,
 }
 
 impl Verdict {
@@ -32,8 +91,15 @@ impl Verdict {
             task_id,
             passed: true,
             score,
+            confidence: None,
             reasoning: reasoning.into(),
             suggestions: Vec::new(),
+            appeal: None,
+            tests: None,
+            analysis: None,
+            artifacts: Vec::new(),
+            template: None,
+            rounds: Vec::new(),
         }
     }
 
@@ -44,8 +110,71 @@ impl Verdict {
             task_id,
             passed: false,
             score,
+            confidence: None,
             reasoning: reasoning.into(),
             suggestions,
+            appeal: None,
+            tests: None,
+            analysis: None,
+            artifacts: Vec::new(),
+            template: None,
+            rounds: Vec::new(),
+        }
+    }
+
+    /// 记录生成草稿所用的模板版本
+    pub fn with_template(mut self, template: &PromptTemplate) -> Self {
+        self.template = Some(template.id());
+        self
+    }
+
+    /// 是否可自动批准：通过且校准置信度不低于阈值（未校准时一律交人工）
+    pub fn auto_approvable(&self, min_confidence: f64) -> bool {
+        self.passed && self.confidence.is_some_and(|c| c >= min_confidence)
+    }
+
+    /// 附加静态分析证据：错误级别的发现列为修改建议，不改变通过与否（由 Critic 结合提示词中的发现判断）
+    pub fn with_analysis(mut self, report: AnalysisReport) -> Self {
+        let fixes: Vec<String> = report.errors().map(|finding| format!("Fix {}", finding)).collect();
+        self.suggestions = fixes.into_iter().chain(self.suggestions).collect();
+        self.analysis = Some(report);
+        self
+    }
+
+    /// 按测试报告门控：测试未通过时裁决改为驳回，评分不高于通过率
+    pub fn gated(mut self, report: TestReport) -> Self {
+        if !report.success() {
+            self.passed = false;
+            self.score = self.score.min(report.pass_rate());
+            self.reasoning = format!("Tests failed ({}). {}", report.summary(), self.reasoning);
+            let fixes = report.failing.iter().map(|name| format!("Fix failing test {}", name));
+            self.suggestions = fixes.chain(self.suggestions).collect();
+        }
+        self.tests = Some(report);
+        self
+    }
+}
+
+/// 驳回委托结果时发给远端执行方的证据
+impl From<&Verdict> for nl_hap::RejectionEvidence {
+    fn from(verdict: &Verdict) -> Self {
+        Self {
+            verdict_id: verdict.id,
+            score: verdict.score,
+            reasoning: verdict.reasoning.clone(),
+            suggestions: verdict.suggestions.clone(),
+            failing_tests: verdict.tests.as_ref().map(|t| t.failing.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// 提升任务临时工作区变更所需的裁决
+impl From<&Verdict> for nl_sandbox::PromotionApproval {
+    fn from(verdict: &Verdict) -> Self {
+        Self {
+            task_id: verdict.task_id,
+            verdict_id: verdict.id,
+            passed: verdict.passed,
         }
     }
 }
@@ -54,12 +183,99 @@ impl Verdict {
 pub struct Courtroom {
     /// 最大审议轮数
     max_rounds: u32,
+    /// 事件存储 (设置后裁决会持久化，重启后不重复审议)
+    store: Option<Arc<Mutex<EventStore>>>,
+    /// 裁决快照 (按管理器策略决定是否快照)
+    snapshots: Option<Arc<Mutex<SnapshotManager>>>,
+    /// 已发出的裁决数，作为快照版本
+    verdicts_issued: AtomicU64,
+    /// 上诉法官与上诉策略
+    appellate: Option<(Arc<dyn AppellateJudge>, AppealPolicy)>,
+    /// 各任务尚未被上诉推翻的驳回（草稿, 裁决）
+    rejections: Mutex<HashMap<Uuid, Vec<(String, Verdict)>>>,
+    /// 测试门控：设置后每份草稿先跑测试，裁决以测试报告为准
+    test_runner: Option<Arc<TestRunner>>,
+    /// 产物库：设置后草稿与测试输出存为产物，裁决只保留引用
+    artifacts: Option<Arc<ArtifactStore>>,
+    /// 各任务进行中的审议轮次
+    rounds: Mutex<HashMap<Uuid, Vec<DeliberationRound>>>,
+    /// 评分校准器
+    calibrator: Option<Arc<Calibrator>>,
+    /// 静态分析器：设置后每份草稿先跑 lint，发现作为裁决证据
+    analyzer: Option<Arc<StaticAnalyzer>>,
 }
 
 impl Courtroom {
     /// 创建新法庭
     pub fn new(max_rounds: u32) -> Self {
-        Self { max_rounds }
+        Self {
+            max_rounds,
+            store: None,
+            snapshots: None,
+            verdicts_issued: AtomicU64::new(0),
+            appellate: None,
+            rejections: Mutex::new(HashMap::new()),
+            test_runner: None,
+            artifacts: None,
+            rounds: Mutex::new(HashMap::new()),
+            calibrator: None,
+            analyzer: None,
+        }
+    }
+
+    /// 设置事件存储
+    pub fn with_event_store(mut self, store: Arc<Mutex<EventStore>>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// 设置快照管理器
+    pub fn with_snapshots(mut self, manager: Arc<Mutex<SnapshotManager>>) -> Self {
+        self.snapshots = Some(manager);
+        self
+    }
+
+    /// 设置上诉法官（模型与 Critic 分开配置）
+    pub fn with_appellate_judge(mut self, judge: Arc<dyn AppellateJudge>, policy: AppealPolicy) -> Self {
+        self.appellate = Some((judge, policy));
+        self
+    }
+
+    /// 启用测试门控模式
+    pub fn with_test_runner(mut self, runner: Arc<TestRunner>) -> Self {
+        self.test_runner = Some(runner);
+        self
+    }
+
+    /// 设置产物库
+    pub fn with_artifacts(mut self, artifacts: Arc<ArtifactStore>) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    /// 设置评分校准器（测试结果与人工批准作为样本记入事件存储）
+    pub fn with_calibrator(mut self, calibrator: Arc<Calibrator>) -> Self {
+        self.calibrator = Some(calibrator);
+        self
+    }
+
+    /// 设置静态分析器
+    pub fn with_static_analyzer(mut self, analyzer: Arc<StaticAnalyzer>) -> Self {
+        self.analyzer = Some(analyzer);
+        self
+    }
+
+    /// 运行静态分析（未设置分析器时为 `None`），结果可经 `AnalysisReport::prompt_section` 注入 Critic 提示词
+    pub async fn analyze(&self) -> nl_core::Result<Option<AnalysisReport>> {
+        match &self.analyzer {
+            Some(analyzer) => Ok(Some(analyzer.run().await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 每次裁决后都快照的策略
+    pub fn snapshot_strategy() -> SnapshotStrategy {
+        SnapshotStrategy::always()
     }
 
     /// 创建默认法庭
@@ -72,6 +288,206 @@ impl Courtroom {
         // TODO: 实现实际的审议逻辑
         Ok(Verdict::approved(Uuid::new_v4(), 0.8, "Default approval"))
     }
+
+    /// 对指定任务执行审议
+    ///
+    /// 若事件存储中已存在该任务的裁决，直接返回已有裁决而不重复审议。
+    pub async fn deliberate_task(&self, task_id: Uuid, task: &str) -> nl_core::Result<Verdict> {
+        if let Some(verdict) = self.recorded_verdict(task_id).await? {
+            return Ok(verdict);
+        }
+
+        let mut verdict = self.deliberate(task).await?;
+        verdict.task_id = task_id;
+        self.calibrate(&mut verdict);
+        self.issue(&verdict).await?;
+        Ok(verdict)
+    }
+
+    /// 登记 Critic 对草稿的裁决，必要时上诉
+    ///
+    /// 同一任务中实质相同的草稿被驳回次数达到上诉策略阈值时，交由上诉法官复核；
+    /// 上诉裁决覆盖 Critic 的结论，两者都按顺序记入裁决链。
+    /// 测试门控模式下先运行测试：测试未通过的草稿直接驳回，且不可上诉；门控前的评分与测试结果记为校准样本。
+    /// 设置了静态分析器且 Critic 裁决尚未带有分析报告时，运行 lint 并把报告附为证据。
+    /// 每份草稿算作一轮，裁决前先发布 `VerdictProgress` 事件，裁决带有截至本轮的轮次记录；
+    /// 通过、轮数用尽或上诉后该任务的轮次重新计数。
+    pub async fn review_draft(
+        &self,
+        task_id: Uuid,
+        task: &str,
+        draft: &str,
+        mut critic_verdict: Verdict,
+    ) -> nl_core::Result<Verdict> {
+        critic_verdict.task_id = task_id;
+        if let Some(runner) = &self.test_runner {
+            let report = runner.run().await?;
+            let (id, score) = (critic_verdict.id, critic_verdict.score);
+            let sample = CalibrationSample::new(id, score, report.success(), OutcomeSource::Tests);
+            self.record_sample(task_id, sample).await?;
+            critic_verdict = critic_verdict.gated(report);
+        }
+        if critic_verdict.analysis.is_none() {
+            if let Some(report) = self.analyze().await? {
+                critic_verdict = critic_verdict.with_analysis(report);
+            }
+        }
+        self.calibrate(&mut critic_verdict);
+        self.attach_artifacts(&mut critic_verdict, draft).await?;
+        self.record_round(&mut critic_verdict, draft).await?;
+        self.issue(&critic_verdict).await?;
+        if critic_verdict.tests.as_ref().is_some_and(|report| !report.success()) {
+            return Ok(critic_verdict);
+        }
+        if critic_verdict.passed {
+            self.rejections.lock().await.remove(&task_id);
+            return Ok(critic_verdict);
+        }
+        let Some((judge, policy)) = &self.appellate else {
+            return Ok(critic_verdict);
+        };
+
+        let overruled: Vec<Verdict> = {
+            let mut rejections = self.rejections.lock().await;
+            let history = rejections.entry(task_id).or_default();
+            history.push((draft.to_string(), critic_verdict.clone()));
+            let same: Vec<Verdict> = history
+                .iter()
+                .filter(|(previous, _)| policy.is_same_draft(previous, draft))
+                .map(|(_, verdict)| verdict.clone())
+                .collect();
+            if (same.len() as u32) < policy.max_rejections.max(1) {
+                return Ok(critic_verdict);
+            }
+            rejections.remove(&task_id);
+            same
+        };
+
+        tracing::info!(
+            "Task {} rejected {} times for the same draft, appealing to {}",
+            task_id,
+            overruled.len(),
+            judge.model()
+        );
+        let mut verdict = judge.hear(task, draft, &overruled).await?;
+        verdict.task_id = task_id;
+        verdict.appeal = Some(AppealRecord {
+            judge_model: judge.model().to_string(),
+            overruled: overruled.iter().map(|v| v.id).collect(),
+        });
+        self.rounds.lock().await.remove(&task_id);
+        verdict.rounds = critic_verdict.rounds.clone();
+        self.calibrate(&mut verdict);
+        self.issue(&verdict).await?;
+        Ok(verdict)
+    }
+
+    /// 记录人工对裁决的批准或驳回，作为校准样本
+    pub async fn record_human_outcome(&self, verdict: &Verdict, approved: bool) -> nl_core::Result<()> {
+        let sample = CalibrationSample::new(verdict.id, verdict.score, approved, OutcomeSource::Human);
+        self.record_sample(verdict.task_id, sample).await
+    }
+
+    async fn record_sample(&self, task_id: Uuid, sample: CalibrationSample) -> nl_core::Result<()> {
+        if let Some(store) = &self.store {
+            store.lock().await.append(sample.to_event(task_id)?).await?;
+        }
+        if let Some(calibrator) = &self.calibrator {
+            calibrator.observe(sample);
+        }
+        Ok(())
+    }
+
+    fn calibrate(&self, verdict: &mut Verdict) {
+        verdict.confidence = self.calibrator.as_ref().and_then(|c| c.calibrate(verdict.score));
+    }
+
+    /// 记录本轮进度并发布 `VerdictProgress` 事件，裁决带上截至本轮的轮次
+    async fn record_round(&self, verdict: &mut Verdict, draft: &str) -> nl_core::Result<()> {
+        let task_id = verdict.task_id;
+        let mut rounds = self.rounds.lock().await;
+        let history = rounds.entry(task_id).or_default();
+        let round = history.len() as u32 + 1;
+        let progress = DeliberationRound {
+            round,
+            draft_hash: nl_durable::artifact_store::content_hash(draft.as_bytes())[..16].to_string(),
+            score: verdict.score,
+            best_score: history.iter().map(|r| r.best_score).fold(verdict.score, f64::max),
+            passed: verdict.passed,
+            remaining_rounds: self.max_rounds.saturating_sub(round),
+            finished_at: Utc::now(),
+        };
+        history.push(progress.clone());
+        verdict.rounds = history.clone();
+        if progress.passed || progress.remaining_rounds == 0 {
+            rounds.remove(&task_id);
+        }
+        drop(rounds);
+
+        if let Some(store) = &self.store {
+            let event = Event::new(EventKind::VerdictProgress, task_id, serde_json::to_value(&progress)?);
+            store.lock().await.append(event).await?;
+        }
+        Ok(())
+    }
+
+    /// 草稿与测试输出存入产物库，裁决改为引用
+    async fn attach_artifacts(&self, verdict: &mut Verdict, draft: &str) -> nl_core::Result<()> {
+        let Some(store) = &self.artifacts else {
+            return Ok(());
+        };
+        let task_id = Some(verdict.task_id);
+        let draft = store.put(draft.as_bytes(), ArtifactKind::Report, "text/plain", task_id).await?;
+        verdict.artifacts.push(draft.uri());
+        if let Some(report) = verdict.tests.as_mut().filter(|r| !r.output.is_empty()) {
+            let output = store.put(report.output.as_bytes(), ArtifactKind::Log, "text/plain", task_id).await?;
+            report.output = output.uri();
+            verdict.artifacts.push(output.uri());
+        }
+        Ok(())
+    }
+
+    /// 持久化并快照一份裁决
+    async fn issue(&self, verdict: &Verdict) -> nl_core::Result<()> {
+        let task_id = verdict.task_id;
+        if let Some(store) = &self.store {
+            let event = Event::new(
+                EventKind::VerdictIssued,
+                task_id,
+                serde_json::to_value(verdict)?,
+            );
+            store.lock().await.append_batch(vec![event]).await?;
+        }
+
+        let version = self.verdicts_issued.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(snapshots) = &self.snapshots {
+            let mut manager = snapshots.lock().await;
+            if manager.should_snapshot(version) {
+                manager
+                    .create_snapshot(task_id, version, serde_json::to_value(verdict)?)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 查询已持久化的裁决
+    pub async fn recorded_verdict(&self, task_id: Uuid) -> nl_core::Result<Option<Verdict>> {
+        Ok(self.verdict_chain(task_id).await?.pop())
+    }
+
+    /// 查询任务的完整裁决链（按发出顺序，上诉裁决位于被覆盖的驳回之后）
+    pub async fn verdict_chain(&self, task_id: Uuid) -> nl_core::Result<Vec<Verdict>> {
+        let Some(store) = &self.store else {
+            return Ok(Vec::new());
+        };
+        let events = store.lock().await.get_events(task_id).await?;
+        events
+            .into_iter()
+            .filter(|e| e.kind == EventKind::VerdictIssued)
+            .map(|e| serde_json::from_value(e.payload).map_err(Into::into))
+            .collect()
+    }
 }
 
 impl Default for Courtroom {
@@ -79,3 +495,111 @@ impl Default for Courtroom {
         Self::default_courtroom()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_snapshot_after_each_verdict() {
+        let snapshots = Arc::new(Mutex::new(SnapshotManager::new(Courtroom::snapshot_strategy())));
+        let courtroom = Courtroom::default_courtroom().with_snapshots(snapshots.clone());
+
+        let task_a = Uuid::new_v4();
+        courtroom.deliberate_task(task_a, "a").await.unwrap();
+        courtroom.deliberate_task(Uuid::new_v4(), "b").await.unwrap();
+
+        let manager = snapshots.lock().await;
+        assert_eq!(manager.count(), 2);
+        assert_eq!(manager.get_latest_snapshot(task_a).unwrap().state["task_id"], task_a.to_string());
+    }
+
+    struct LenientJudge;
+
+    #[async_trait::async_trait]
+    impl AppellateJudge for LenientJudge {
+        fn model(&self) -> &str {
+            "appellate-large"
+        }
+
+        async fn hear(&self, _task: &str, _draft: &str, rejections: &[Verdict]) -> nl_core::Result<Verdict> {
+            Ok(Verdict::approved(Uuid::nil(), 0.9, format!("overruling {} rejections", rejections.len())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_appeal_after_repeated_rejections() {
+        let store = Arc::new(Mutex::new(EventStore::new(nl_durable::event_store::EventStoreConfig::default())));
+        let courtroom = Courtroom::default_courtroom()
+            .with_event_store(store)
+            .with_appellate_judge(Arc::new(LenientJudge), AppealPolicy::new(2));
+        let task = Uuid::new_v4();
+        let reject = || Verdict::rejected(Uuid::nil(), 0.2, "too terse", vec!["add detail".to_string()]);
+
+        let first = courtroom.review_draft(task, "t", "final answer: 42", reject()).await.unwrap();
+        assert!(!first.passed);
+        // 内容不同的草稿不计入同一草稿的驳回次数
+        let other = courtroom.review_draft(task, "t", "a completely different draft", reject()).await.unwrap();
+        assert!(other.appeal.is_none());
+
+        let appealed = courtroom.review_draft(task, "t", "Final answer: 42.", reject()).await.unwrap();
+        assert!(appealed.passed);
+        let record = appealed.appeal.clone().unwrap();
+        assert_eq!(record.judge_model, "appellate-large");
+        assert_eq!(record.overruled, vec![first.id, courtroom.verdict_chain(task).await.unwrap()[2].id]);
+
+        let chain = courtroom.verdict_chain(task).await.unwrap();
+        assert_eq!(chain.len(), 4);
+        assert_eq!(courtroom.recorded_verdict(task).await.unwrap().unwrap().id, appealed.id);
+    }
+
+    #[tokio::test]
+    async fn test_rounds_emit_progress_until_final_verdict() {
+        let store = Arc::new(Mutex::new(EventStore::new(nl_durable::event_store::EventStoreConfig::default())));
+        let courtroom = Courtroom::new(3).with_event_store(store.clone());
+        let task = Uuid::new_v4();
+
+        let first = Verdict::rejected(Uuid::nil(), 0.4, "missing tests", Vec::new());
+        let first = courtroom.review_draft(task, "t", "draft one", first).await.unwrap();
+        assert_eq!(first.rounds.len(), 1);
+        assert_eq!((first.rounds[0].round, first.rounds[0].remaining_rounds), (1, 2));
+
+        let second = Verdict::approved(Uuid::nil(), 0.3, "fine");
+        let second = courtroom.review_draft(task, "t", "draft two", second).await.unwrap();
+        assert_eq!(second.rounds.len(), 2);
+        assert_eq!((second.rounds[1].score, second.rounds[1].best_score), (0.3, 0.4));
+        assert_ne!(second.rounds[0].draft_hash, second.rounds[1].draft_hash);
+
+        let events = store.lock().await.get_events(task).await.unwrap();
+        let kinds: Vec<&str> = events.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, ["verdict_progress", "verdict_issued", "verdict_progress", "verdict_issued"]);
+        assert_eq!(events[2].payload["remaining_rounds"], 1);
+
+        // 通过后重新计数
+        let next = courtroom.review_draft(task, "t", "draft three", Verdict::approved(Uuid::nil(), 0.9, "ok"));
+        assert_eq!(next.await.unwrap().rounds[0].round, 1);
+    }
+
+    #[test]
+    fn test_verdict_gated_on_failing_tests() {
+        let report = TestReport {
+            framework: nl_sandbox::TestFramework::Cargo,
+            passed: 3,
+            failed: 1,
+            skipped: 0,
+            failing: vec!["parser::handles_unicode".to_string()],
+            exit_code: Some(101),
+            duration_ms: 1200,
+            timed_out: false,
+            output: String::new(),
+        };
+        let verdict = Verdict::approved(Uuid::nil(), 0.9, "looks good").gated(report.clone());
+        assert!(!verdict.passed);
+        assert_eq!(verdict.score, 0.75);
+        assert_eq!(verdict.suggestions, vec!["Fix failing test parser::handles_unicode"]);
+
+        let passing = TestReport { failed: 0, failing: Vec::new(), exit_code: Some(0), ..report };
+        let verdict = Verdict::approved(Uuid::nil(), 0.9, "looks good").gated(passing);
+        assert!(verdict.passed && verdict.tests.is_some());
+    }
+}
@@ -1,8 +1,16 @@
 //! Worker Agent
 
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use nl_hap::mcp::{McpClient, McpTool};
+use nl_sandbox::{SandboxExecutor, WorkerProfile};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::templates::PromptTemplate;
+use crate::tool_output::{ExpandArtifactTool, ToolOutputTruncator, EXPAND_ARTIFACT_TOOL};
+
 /// Worker Agent - 执行任务
 pub struct Worker {
     /// Worker ID
@@ -11,6 +19,14 @@ pub struct Worker {
     pub expertise: Vec<String>,
     /// 当前任务
     pub current_task: Option<String>,
+    /// 可用工具（本地工具与外部 MCP 服务器工具）
+    tools: BTreeMap<String, Arc<dyn McpTool>>,
+    /// 人设与任务提示词模板
+    pub template: Option<PromptTemplate>,
+    /// 沙箱能力画像（默认授予全部能力）
+    pub profile: WorkerProfile,
+    /// 工具结果截断器（未设置时工具结果原样返回）
+    truncator: Option<Arc<ToolOutputTruncator>>,
 }
 
 impl Worker {
@@ -20,6 +36,10 @@ impl Worker {
             id: Uuid::new_v4(),
             expertise: Vec::new(),
             current_task: None,
+            tools: BTreeMap::new(),
+            template: None,
+            profile: WorkerProfile::default(),
+            truncator: None,
         }
     }
 
@@ -28,6 +48,69 @@ impl Worker {
         self.expertise.push(area.into());
     }
 
+    /// 注册工具，同名工具会被覆盖
+    pub fn add_tool(&mut self, tool: Arc<dyn McpTool>) {
+        self.tools.insert(tool.name().to_string(), tool);
+    }
+
+    /// 将外部 MCP 服务器的全部工具注册为工具提供者，返回新增工具数
+    pub async fn add_mcp_server(&mut self, client: &Arc<McpClient>) -> nl_core::Result<usize> {
+        let tools = client.remote_tools().await?;
+        let count = tools.len();
+        for tool in tools {
+            self.add_tool(tool);
+        }
+        Ok(count)
+    }
+
+    /// 可用工具名称
+    pub fn tool_names(&self) -> Vec<&str> {
+        self.tools.keys().map(String::as_str).collect()
+    }
+
+    /// 截断过长的工具结果，并注册本地展开工具 `expand_artifact`
+    pub fn set_truncator(&mut self, truncator: Arc<ToolOutputTruncator>) {
+        self.add_tool(Arc::new(ExpandArtifactTool::new(truncator.clone())));
+        self.truncator = Some(truncator);
+    }
+
+    /// 调用工具；设置了截断器时过长的结果只保留首尾并附上产物引用
+    pub async fn call_tool(&self, name: &str, arguments: serde_json::Value) -> nl_core::Result<String> {
+        let tool = self
+            .tools
+            .get(name)
+            .ok_or_else(|| nl_core::NeuroLoomError::Actor(format!("worker has no tool named {}", name)))?;
+        let output = tool.call(arguments).await?;
+        match &self.truncator {
+            Some(truncator) if name != EXPAND_ARTIFACT_TOOL => truncator.truncate(output, None).await,
+            _ => Ok(output),
+        }
+    }
+
+    /// 使用提示词模板（通常取自 `TemplateRegistry::active`）
+    pub fn set_template(&mut self, template: PromptTemplate) {
+        self.template = Some(template);
+    }
+
+    /// 设置沙箱能力画像
+    pub fn set_profile(&mut self, profile: WorkerProfile) {
+        self.profile = profile;
+    }
+
+    /// 在沙箱执行器中登记本 Worker 的能力画像，之后以 Worker ID 发起的 `*_for` 操作按画像检查
+    pub fn grant_sandbox(&self, executor: &SandboxExecutor) {
+        executor.assign_profile(self.id, self.profile.clone());
+    }
+
+    /// 当前任务的（人设, 提示词）；未设置模板时提示词即任务描述
+    pub fn prompt(&self) -> Option<(Option<&str>, String)> {
+        let task = self.current_task.as_deref()?;
+        Some(match &self.template {
+            Some(template) => (Some(template.persona.as_str()), template.render(task)),
+            None => (None, task.to_string()),
+        })
+    }
+
     /// 接受任务
     pub fn accept_task(&mut self, task: impl Into<String>) {
         self.current_task = Some(task.into());
@@ -0,0 +1,431 @@
+//! 活动摘要 - “这周我的 Agent 做了什么”
+//!
+//! 从一段时间内的事件汇总出人类可读的日报 / 周报：
+//! - 完成的目标（计划级 `TaskCompleted`）与完成的子任务数
+//! - 裁决结果（通过 / 驳回 / 上诉改判、平均分）
+//! - 按模型统计的 token 用量（`LlmResponseCompleted`）
+//! - 值得注意的失败（执行失败、取消、LLM 错误、失败的 SOP 执行）
+//! - 新注册的 SOP 工作流
+//! - 人工反馈的满意度（`TaskFeedback`，按 Worker 模板与模型归类）
+//!
+//! 摘要渲染为 Markdown，并可转为 HAMT 记忆条目（全文落盘为 Level 3 数据）。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use nl_core::{Event, EventKind, NeuroLoomError, Result};
+use nl_durable::{EventStore, FeedbackReport};
+use nl_memory::hamt::MemoryEntry;
+
+use crate::courtroom::Verdict;
+use crate::sop_stats::SopRunOutcome;
+
+/// 最多列出的失败条数
+const MAX_FAILURES: usize = 10;
+
+/// 失败信息的最大字符数
+const FAILURE_CHARS: usize = 160;
+
+/// 反馈评语的最大字符数
+const COMMENT_CHARS: usize = 160;
+
+/// 摘要周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestPeriod {
+    Daily,
+    Weekly,
+}
+
+impl DigestPeriod {
+    /// 周期长度
+    pub fn duration(&self) -> Duration {
+        match self {
+            DigestPeriod::Daily => Duration::days(1),
+            DigestPeriod::Weekly => Duration::weeks(1),
+        }
+    }
+}
+
+impl fmt::Display for DigestPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigestPeriod::Daily => f.write_str("daily"),
+            DigestPeriod::Weekly => f.write_str("weekly"),
+        }
+    }
+}
+
+impl FromStr for DigestPeriod {
+    type Err = NeuroLoomError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "daily" | "day" => Ok(DigestPeriod::Daily),
+            "weekly" | "week" => Ok(DigestPeriod::Weekly),
+            other => Err(NeuroLoomError::Unknown(format!(
+                "unknown digest period: {} (expected daily or weekly)",
+                other
+            ))),
+        }
+    }
+}
+
+/// 完成的目标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalOutcome {
+    pub goal: String,
+    pub success: bool,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// 裁决统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerdictStats {
+    pub issued: usize,
+    pub passed: usize,
+    pub rejected: usize,
+    /// 上诉改判的裁决数
+    pub appealed: usize,
+    pub average_score: f64,
+}
+
+/// 值得注意的失败
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Failure {
+    pub at: DateTime<Utc>,
+    pub kind: String,
+    pub message: String,
+}
+
+/// 活动摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Digest {
+    pub period: DigestPeriod,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub goals: Vec<GoalOutcome>,
+    pub subtasks_completed: usize,
+    pub verdicts: VerdictStats,
+    /// 各模型的 token 用量
+    pub tokens_by_model: BTreeMap<String, u64>,
+    pub failures: Vec<Failure>,
+    /// 失败总数（`failures` 只保留最近的若干条）
+    pub failure_count: usize,
+    pub new_sops: Vec<String>,
+    /// 周期内的人工反馈
+    pub feedback: FeedbackReport,
+}
+
+impl Digest {
+    /// 汇总 `end` 之前一个周期内的事件
+    pub fn from_events<'a>(period: DigestPeriod, end: DateTime<Utc>, events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let start = end - period.duration();
+        let mut digest = Self {
+            period,
+            start,
+            end,
+            goals: Vec::new(),
+            subtasks_completed: 0,
+            verdicts: VerdictStats::default(),
+            tokens_by_model: BTreeMap::new(),
+            failures: Vec::new(),
+            failure_count: 0,
+            new_sops: Vec::new(),
+            feedback: FeedbackReport::default(),
+        };
+        let events: Vec<&Event> = events
+            .into_iter()
+            .filter(|e| e.timestamp >= start && e.timestamp < end)
+            .collect();
+        digest.feedback = FeedbackReport::from_events(events.iter().copied());
+        let mut score_sum = 0.0;
+        for event in events {
+            let payload = &event.payload;
+            match &event.kind {
+                EventKind::TaskCompleted => match payload.get("goal").and_then(|g| g.as_str()) {
+                    // 计划级完成事件携带目标，子任务完成事件携带输出
+                    Some(goal) => digest.goals.push(GoalOutcome {
+                        goal: goal.to_string(),
+                        success: payload["success"].as_bool().unwrap_or(false),
+                        completed_at: event.timestamp,
+                    }),
+                    None => digest.subtasks_completed += 1,
+                },
+                EventKind::VerdictIssued => {
+                    let Ok(verdict) = serde_json::from_value::<Verdict>(payload.clone()) else {
+                        continue;
+                    };
+                    digest.verdicts.issued += 1;
+                    score_sum += verdict.score;
+                    if verdict.passed {
+                        digest.verdicts.passed += 1;
+                    } else {
+                        digest.verdicts.rejected += 1;
+                    }
+                    if verdict.appeal.is_some() {
+                        digest.verdicts.appealed += 1;
+                    }
+                }
+                EventKind::LlmResponseCompleted => {
+                    let model = payload["model"].as_str().unwrap_or("unknown").to_string();
+                    let tokens = payload["usage"]["total_tokens"].as_u64().unwrap_or(0);
+                    *digest.tokens_by_model.entry(model).or_default() += tokens;
+                }
+                EventKind::SopRegistered => {
+                    if let Some(name) = payload["workflow"].as_str() {
+                        digest.new_sops.push(name.to_string());
+                    }
+                }
+                EventKind::SopRunFinished => {
+                    if let Ok(outcome) = serde_json::from_value::<SopRunOutcome>(payload.clone()) {
+                        if !outcome.success {
+                            let reason = outcome.failure.unwrap_or_else(|| "failed".to_string());
+                            digest.fail(event, format!("SOP {}: {}", outcome.workflow, reason));
+                        }
+                    }
+                }
+                EventKind::ExecutionFailed | EventKind::LlmError => {
+                    let message = payload["error"].as_str().map(str::to_string).unwrap_or_else(|| payload.to_string());
+                    digest.fail(event, message);
+                }
+                EventKind::TaskCancelled => {
+                    let goal = payload["goal"].as_str().unwrap_or("task");
+                    digest.fail(event, format!("{} was cancelled", goal));
+                }
+                _ => {}
+            }
+        }
+        if digest.verdicts.issued > 0 {
+            digest.verdicts.average_score = score_sum / digest.verdicts.issued as f64;
+        }
+        digest
+    }
+
+    fn fail(&mut self, event: &Event, message: String) {
+        self.failure_count += 1;
+        self.failures.push(Failure {
+            at: event.timestamp,
+            kind: event.kind.as_str().to_string(),
+            message: message.chars().take(FAILURE_CHARS).collect(),
+        });
+        if self.failures.len() > MAX_FAILURES {
+            self.failures.remove(0);
+        }
+    }
+
+    /// token 总量
+    pub fn total_tokens(&self) -> u64 {
+        self.tokens_by_model.values().sum()
+    }
+
+    /// 一行概要
+    pub fn headline(&self) -> String {
+        let succeeded = self.goals.iter().filter(|g| g.success).count();
+        let mut headline = format!(
+            "{} goals completed ({} succeeded), {} verdicts ({} passed), {} tokens, {} failures, {} new SOPs",
+            self.goals.len(),
+            succeeded,
+            self.verdicts.issued,
+            self.verdicts.passed,
+            self.total_tokens(),
+            self.failure_count,
+            self.new_sops.len()
+        );
+        if let Some(average) = self.feedback.overall.average() {
+            headline.push_str(&format!(", satisfaction {:.1}/5", average));
+        }
+        headline
+    }
+
+    /// 标题
+    pub fn title(&self) -> String {
+        let label = match self.period {
+            DigestPeriod::Daily => "Daily",
+            DigestPeriod::Weekly => "Weekly",
+        };
+        format!(
+            "{} digest: {} – {}",
+            label,
+            self.start.format("%Y-%m-%d"),
+            self.end.format("%Y-%m-%d")
+        )
+    }
+
+    /// 渲染为 Markdown
+    pub fn render_markdown(&self) -> String {
+        let mut md = format!("# {}\n\n{}\n", self.title(), self.headline());
+
+        md.push_str("\n## Completed goals\n\n");
+        if self.goals.is_empty() {
+            md.push_str("_None._\n");
+        }
+        for goal in &self.goals {
+            let mark = if goal.success { "✅" } else { "⚠️" };
+            md.push_str(&format!("- {} {} ({})\n", mark, goal.goal, goal.completed_at.format("%a %H:%M")));
+        }
+        md.push_str(&format!("\n{} subtasks completed.\n", self.subtasks_completed));
+
+        md.push_str("\n## Verdicts\n\n");
+        if self.verdicts.issued == 0 {
+            md.push_str("_No verdicts issued._\n");
+        } else {
+            md.push_str(&format!(
+                "| Issued | Passed | Rejected | Overturned on appeal | Avg. score |\n|---|---|---|---|---|\n| {} | {} | {} | {} | {:.2} |\n",
+                self.verdicts.issued,
+                self.verdicts.passed,
+                self.verdicts.rejected,
+                self.verdicts.appealed,
+                self.verdicts.average_score
+            ));
+        }
+
+        md.push_str("\n## Token usage\n\n");
+        if self.tokens_by_model.is_empty() {
+            md.push_str("_No LLM usage recorded._\n");
+        } else {
+            md.push_str("| Model | Tokens |\n|---|---|\n");
+            for (model, tokens) in &self.tokens_by_model {
+                md.push_str(&format!("| {} | {} |\n", model, tokens));
+            }
+            md.push_str(&format!("| **Total** | **{}** |\n", self.total_tokens()));
+        }
+
+        md.push_str("\n## Notable failures\n\n");
+        if self.failures.is_empty() {
+            md.push_str("_None._\n");
+        }
+        for failure in &self.failures {
+            md.push_str(&format!(
+                "- {} `{}` {}\n",
+                failure.at.format("%a %H:%M"),
+                failure.kind,
+                failure.message
+            ));
+        }
+        if self.failure_count > self.failures.len() {
+            md.push_str(&format!("- … and {} earlier failures\n", self.failure_count - self.failures.len()));
+        }
+
+        md.push_str("\n## Satisfaction\n\n");
+        if self.feedback.is_empty() {
+            md.push_str("_No feedback received._\n");
+        } else {
+            md.push_str("| Group | Ratings | Avg. rating | Satisfied |\n|---|---|---|---|\n");
+            let overall = [("**Overall**".to_string(), &self.feedback.overall)];
+            let workers = self.feedback.by_worker.iter().map(|(k, v)| (format!("worker `{}`", k), v));
+            let models = self.feedback.by_model.iter().map(|(k, v)| (format!("model `{}`", k), v));
+            for (group, satisfaction) in overall.into_iter().chain(workers).chain(models) {
+                md.push_str(&format!(
+                    "| {} | {} | {:.2} | {:.0}% |\n",
+                    group,
+                    satisfaction.count,
+                    satisfaction.average().unwrap_or_default(),
+                    satisfaction.satisfaction_rate().unwrap_or_default() * 100.0
+                ));
+            }
+            if !self.feedback.comments.is_empty() {
+                md.push('\n');
+            }
+            for (_, rating, comment) in &self.feedback.comments {
+                md.push_str(&format!("- {}/5: {}\n", rating, comment.chars().take(COMMENT_CHARS).collect::<String>()));
+            }
+        }
+
+        md.push_str("\n## New SOPs\n\n");
+        if self.new_sops.is_empty() {
+            md.push_str("_None._\n");
+        }
+        for sop in &self.new_sops {
+            md.push_str(&format!("- {}\n", sop));
+        }
+        md
+    }
+
+    /// 转为记忆条目（标签为周期与结束日期，摘要为一行概要，全文路径由调用方写入后提供）
+    pub fn to_memory_entry(&self, full_data_path: Option<String>) -> MemoryEntry {
+        let mut entry = MemoryEntry::new(
+            format!("{} digest {}", self.period, self.end.format("%Y-%m-%d")),
+            self.headline(),
+        );
+        entry.full_data_path = full_data_path;
+        entry.metadata.insert("kind".to_string(), "digest".to_string());
+        entry.metadata.insert("period".to_string(), self.period.to_string());
+        entry.metadata.insert("start".to_string(), self.start.to_rfc3339());
+        entry.metadata.insert("end".to_string(), self.end.to_rfc3339());
+        entry
+    }
+}
+
+/// 从事件存储生成 `end` 之前一个周期的摘要
+pub async fn generate(store: &Mutex<EventStore>, period: DigestPeriod, end: DateTime<Utc>) -> Result<Digest> {
+    let events = store
+        .lock()
+        .await
+        .get_events_by_time(end - period.duration(), end)
+        .await?;
+    Ok(Digest::from_events(period, end, &events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_weekly_digest_aggregates_events() {
+        let end = Utc.with_ymd_and_hms(2026, 10, 16, 0, 0, 0).unwrap();
+        let at = |days: i64, mut event: Event| {
+            event.timestamp = end - Duration::days(days);
+            event
+        };
+        let plan = Uuid::new_v4();
+        let events = vec![
+            at(2, Event::new(EventKind::TaskCompleted, Uuid::new_v4(), serde_json::json!({ "output": "done" }))),
+            at(2, Event::new(EventKind::TaskCompleted, plan, serde_json::json!({ "goal": "ship release", "success": true }))),
+            at(3, Event::new(
+                EventKind::VerdictIssued,
+                plan,
+                serde_json::to_value(Verdict::approved(plan, 0.8, "good")).unwrap(),
+            )),
+            at(3, Event::new(
+                EventKind::VerdictIssued,
+                plan,
+                serde_json::to_value(Verdict::rejected(plan, 0.4, "bad", Vec::new())).unwrap(),
+            )),
+            at(1, Event::new(
+                EventKind::LlmResponseCompleted,
+                Uuid::nil(),
+                serde_json::json!({ "model": "claude", "usage": { "total_tokens": 1200 } }),
+            )),
+            at(1, Event::new(EventKind::ExecutionFailed, plan, serde_json::json!({ "error": "tests failed" }))),
+            at(1, nl_durable::TaskFeedback::new(plan, 4).unwrap().with_comment("nice").to_event().unwrap()),
+            at(4, Event::new(EventKind::SopRegistered, Uuid::new_v4(), serde_json::json!({ "workflow": "lint" }))),
+            // 周期之外
+            at(9, Event::new(EventKind::ExecutionFailed, plan, serde_json::json!({ "error": "old" }))),
+        ];
+
+        let digest = Digest::from_events(DigestPeriod::Weekly, end, &events);
+        assert_eq!(digest.goals.len(), 1);
+        assert_eq!(digest.subtasks_completed, 1);
+        assert_eq!((digest.verdicts.passed, digest.verdicts.rejected), (1, 1));
+        assert!((digest.verdicts.average_score - 0.6).abs() < 1e-9);
+        assert_eq!(digest.total_tokens(), 1200);
+        assert_eq!(digest.failure_count, 1);
+        assert_eq!(digest.new_sops, vec!["lint"]);
+        assert_eq!(digest.feedback.overall.average(), Some(4.0));
+        assert!(digest.headline().ends_with("satisfaction 4.0/5"));
+
+        let md = digest.render_markdown();
+        assert!(md.starts_with("# Weekly digest: 2026-10-09 – 2026-10-16"));
+        assert!(md.contains("✅ ship release") && md.contains("| claude | 1200 |") && md.contains("tests failed"));
+        assert!(md.contains("| **Overall** | 1 | 4.00 | 100% |") && md.contains("- 4/5: nice"));
+        assert_eq!(digest.to_memory_entry(None).tag, "weekly digest 2026-10-16");
+    }
+}
@@ -4,11 +4,30 @@
 //! 包含 Worker、Critic、MoA 议会的裁判逻辑。
 
 pub mod system1;
+pub mod sop_stats;
 pub mod system2;
+pub mod budget;
 pub mod courtroom;
 pub mod blacksmith;
+pub mod orchestrator;
+pub mod context;
+pub mod digest;
+pub mod templates;
+pub mod refinement;
+pub mod tool_output;
 
 pub use system1::SopEngine;
-pub use system2::MctsEngine;
-pub use courtroom::{Courtroom, Verdict};
+pub use sop_stats::{RetirementPolicy, SopStats, WorkflowStats};
+pub use system2::{MctsEngine, MctsSearchResult};
+pub use budget::{BudgetController, BudgetDecision, BudgetReport, ModelPricing, SearchBudget};
+pub use courtroom::{AnalysisReport, Calibrator, Courtroom, DeliberationRound, StaticAnalyzer, Verdict};
 pub use blacksmith::Blacksmith;
+pub use context::{AssembledContext, ContextAssembler, ContextRequest, ContextSource, VisionSource};
+pub use digest::{Digest, DigestPeriod};
+pub use templates::{PromptTemplate, TemplateRegistry};
+pub use refinement::{LlmTemplateRefiner, PromptEvaluator, RefinementConfig, RefinementJob, RefinementReport, TemplateRefiner};
+pub use tool_output::{ExpandArtifactTool, ToolOutputTruncator, TruncationPolicy, EXPAND_ARTIFACT_TOOL};
+pub use orchestrator::{
+    DuplicateTask, FailurePolicy, FanOutConfig, GoalPlanner, LlmEmbedder, LlmPlanner, OrchestrationResult, Orchestrator,
+    TaskPlan,
+};
@@ -0,0 +1,1400 @@
+//! 任务编排器 - 目标分解与调度
+//!
+//! 将用户目标交给 LLM 分解为带依赖关系的子任务 DAG，
+//! 按拓扑顺序派发到 System 1 (SOP) 或 System 2 (MCTS)，
+//! 通过事件追踪完成情况并汇总最终结果。
+//!
+//! 依赖已全部完成的子任务互相独立，按批并行派发（`FanOutConfig::max_parallel` 限制并发，
+//! 配置了 Actor 配额时每个子任务派发前检查配额）；批内结果按拓扑顺序合并，与完成先后无关。
+//! 子任务失败时按 `FailurePolicy` 继续其他分支或立即中止整个计划。
+//! 每个子任务在带计划 ID、子任务 ID 与批次号的 tracing span 中执行，`TaskAssigned` 事件同样记录批次号。
+//!
+//! 配置了向量嵌入后，规划前先比对历史目标：与已完成的目标足够相似时直接复用其结果，
+//! 与未完成的目标相似时续跑那个计划，避免换个说法重复提交的任务再消耗一遍 token。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use nl_core::{Event, EventKind, NeuroLoomError, Result};
+use nl_durable::actor_mesh::ActorId;
+use nl_durable::{CancellationRegistry, CancellationToken, EventStore, QuotaManager};
+use nl_llm::{LlmClient, PrimitiveRequest};
+use nl_memory::consolidation::Embedder;
+
+use crate::system1::SopEngine;
+use crate::system2::{MctsConfig, MctsEngine};
+
+/// 子任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubTaskStatus {
+    /// 等待依赖完成
+    Pending,
+    /// 执行中
+    Running,
+    /// 已完成
+    Completed,
+    /// 执行失败
+    Failed,
+    /// 已取消
+    Cancelled,
+}
+
+/// 派发路线
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DispatchRoute {
+    /// System 1: 已固化的 SOP 工作流
+    Sop { workflow: String },
+    /// System 2: MCTS 自适应推演
+    Mcts,
+}
+
+/// 子任务失败时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FailurePolicy {
+    /// 只跳过依赖失败子任务的分支，其他分支继续执行
+    #[default]
+    ContinueOthers,
+    /// 首个失败即取消同批仍在执行的子任务，不再派发新的子任务
+    FailFast,
+}
+
+/// 并行派发配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanOutConfig {
+    /// 同时执行的子任务上限
+    pub max_parallel: usize,
+    /// 失败处理方式
+    pub failure_policy: FailurePolicy,
+}
+
+impl Default for FanOutConfig {
+    fn default() -> Self {
+        Self {
+            max_parallel: 4,
+            failure_policy: FailurePolicy::ContinueOthers,
+        }
+    }
+}
+
+/// 子任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubTask {
+    /// 子任务 ID
+    pub id: Uuid,
+    /// 子任务名称
+    pub name: String,
+    /// 描述
+    pub description: String,
+    /// 建议使用的 SOP 工作流名称
+    pub workflow: Option<String>,
+    /// 前置子任务
+    pub depends_on: Vec<Uuid>,
+    /// 状态
+    pub status: SubTaskStatus,
+    /// 实际派发路线
+    pub route: Option<DispatchRoute>,
+    /// 执行结果
+    pub result: Option<String>,
+}
+
+impl SubTask {
+    /// 创建新子任务
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            description: description.into(),
+            workflow: None,
+            depends_on: Vec::new(),
+            status: SubTaskStatus::Pending,
+            route: None,
+            result: None,
+        }
+    }
+
+    /// 设置建议的 SOP 工作流
+    pub fn with_workflow(mut self, workflow: impl Into<String>) -> Self {
+        self.workflow = Some(workflow.into());
+        self
+    }
+
+    /// 添加前置依赖
+    pub fn depends_on(mut self, id: Uuid) -> Self {
+        self.depends_on.push(id);
+        self
+    }
+}
+
+/// 任务计划 (子任务 DAG)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskPlan {
+    /// 计划 ID (同时作为事件关联 ID)
+    pub id: Uuid,
+    /// 原始目标
+    pub goal: String,
+    /// 子任务列表
+    pub subtasks: Vec<SubTask>,
+}
+
+impl TaskPlan {
+    /// 创建新计划
+    pub fn new(goal: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            goal: goal.into(),
+            subtasks: Vec::new(),
+        }
+    }
+
+    /// 添加子任务
+    pub fn add(&mut self, subtask: SubTask) -> Uuid {
+        let id = subtask.id;
+        self.subtasks.push(subtask);
+        id
+    }
+
+    /// 获取子任务
+    pub fn get(&self, id: &Uuid) -> Option<&SubTask> {
+        self.subtasks.iter().find(|t| t.id == *id)
+    }
+
+    /// 按拓扑顺序返回子任务 ID (依赖缺失或存在环时报错)
+    pub fn topological_order(&self) -> Result<Vec<Uuid>> {
+        let ids: HashSet<Uuid> = self.subtasks.iter().map(|t| t.id).collect();
+        let mut in_degree: HashMap<Uuid, usize> = HashMap::new();
+        let mut dependents: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+
+        for task in &self.subtasks {
+            for dep in &task.depends_on {
+                if !ids.contains(dep) {
+                    return Err(NeuroLoomError::Unknown(format!(
+                        "Subtask {} depends on unknown subtask {}",
+                        task.id, dep
+                    )));
+                }
+                dependents.entry(*dep).or_default().push(task.id);
+            }
+            in_degree.insert(task.id, task.depends_on.len());
+        }
+
+        // 保持声明顺序，保证调度结果稳定
+        let mut ready: Vec<Uuid> = self
+            .subtasks
+            .iter()
+            .filter(|t| in_degree[&t.id] == 0)
+            .map(|t| t.id)
+            .collect();
+        let mut order = Vec::with_capacity(self.subtasks.len());
+
+        while !ready.is_empty() {
+            let id = ready.remove(0);
+            order.push(id);
+            for next in dependents.get(&id).into_iter().flatten() {
+                let degree = in_degree.get_mut(next).expect("known subtask");
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(*next);
+                }
+            }
+        }
+
+        if order.len() != self.subtasks.len() {
+            return Err(NeuroLoomError::Unknown(format!(
+                "Task plan {} contains a dependency cycle",
+                self.id
+            )));
+        }
+
+        Ok(order)
+    }
+}
+
+/// 目标规划器 - 将目标分解为子任务 DAG
+#[async_trait]
+pub trait GoalPlanner: Send + Sync {
+    /// 分解目标
+    async fn decompose(&self, goal: &str) -> Result<TaskPlan>;
+}
+
+/// LLM 返回的子任务描述
+#[derive(Debug, Deserialize)]
+struct PlannedSubTask {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    workflow: Option<String>,
+    #[serde(default)]
+    depends_on: Vec<String>,
+}
+
+/// 基于 LLM 的目标规划器
+pub struct LlmPlanner {
+    /// LLM 客户端
+    client: Arc<LlmClient>,
+    /// 使用的模型
+    model: String,
+}
+
+impl LlmPlanner {
+    /// 分解提示词
+    const SYSTEM_PROMPT: &'static str =
+        "You are a task planner. Decompose the user's goal into subtasks. \
+Reply with a JSON array only. Each item: {\"name\": string, \"description\": string, \
+\"workflow\": string|null, \"depends_on\": [names of earlier subtasks]}.";
+
+    /// 创建新规划器
+    pub fn new(client: Arc<LlmClient>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+        }
+    }
+
+    /// 解析 LLM 输出为任务计划
+    pub fn parse_plan(goal: &str, content: &str) -> Result<TaskPlan> {
+        // 容忍 ```json 代码块包裹
+        let start = content.find('[');
+        let end = content.rfind(']');
+        let json = match (start, end) {
+            (Some(s), Some(e)) if s < e => &content[s..=e],
+            _ => {
+                return Err(NeuroLoomError::LlmProvider(
+                    "Planner response contains no JSON array".to_string(),
+                ))
+            }
+        };
+        let planned: Vec<PlannedSubTask> = serde_json::from_str(json)?;
+
+        let mut plan = TaskPlan::new(goal);
+        let mut by_name: HashMap<String, Uuid> = HashMap::new();
+        for item in planned {
+            let mut subtask = SubTask::new(item.name.clone(), item.description);
+            subtask.workflow = item.workflow.filter(|w| !w.is_empty());
+            for dep in &item.depends_on {
+                let dep_id = by_name.get(dep).ok_or_else(|| {
+                    NeuroLoomError::LlmProvider(format!(
+                        "Subtask '{}' depends on unknown subtask '{}'",
+                        item.name, dep
+                    ))
+                })?;
+                subtask.depends_on.push(*dep_id);
+            }
+            by_name.insert(item.name, subtask.id);
+            plan.add(subtask);
+        }
+
+        Ok(plan)
+    }
+}
+
+#[async_trait]
+impl GoalPlanner for LlmPlanner {
+    async fn decompose(&self, goal: &str) -> Result<TaskPlan> {
+        let mut req = PrimitiveRequest::single_user_message(goal).with_model(self.model.clone());
+        req.system = Some(Self::SYSTEM_PROMPT.to_string());
+
+        let response = self
+            .client
+            .complete(&req)
+            .await
+            .map_err(|e| NeuroLoomError::LlmProvider(e.to_string()))?;
+
+        Self::parse_plan(goal, &response.content)
+    }
+}
+
+/// 基于 LLM 嵌入接口的向量化
+pub struct LlmEmbedder {
+    /// LLM 客户端
+    client: Arc<LlmClient>,
+    /// 嵌入模型
+    model: String,
+}
+
+impl LlmEmbedder {
+    /// 创建新嵌入器
+    pub fn new(client: Arc<LlmClient>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for LlmEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let data = self
+            .client
+            .embed(&self.model, &[text.to_string()])
+            .await
+            .map_err(|e| NeuroLoomError::LlmProvider(e.to_string()))?;
+        data.into_iter()
+            .next()
+            .map(|d| d.embedding)
+            .ok_or_else(|| NeuroLoomError::LlmProvider("Embedding response is empty".to_string()))
+    }
+}
+
+/// 历史任务的状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PreviousTaskStatus {
+    /// 已成功完成
+    Completed { output: String },
+    /// 已结束但有失败的子任务
+    Failed,
+    /// 尚未结束（可续跑）
+    Unfinished,
+}
+
+/// 与新目标相似的历史任务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateTask {
+    /// 历史计划 ID
+    pub plan_id: Uuid,
+    /// 历史目标
+    pub goal: String,
+    /// 余弦相似度
+    pub similarity: f32,
+    /// 历史任务状态
+    #[serde(flatten)]
+    pub status: PreviousTaskStatus,
+}
+
+/// 编排结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrchestrationResult {
+    /// 执行后的计划
+    pub plan: TaskPlan,
+    /// 汇总输出
+    pub output: String,
+    /// 是否全部成功
+    pub success: bool,
+    /// 复用或续跑的相似历史任务
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<DuplicateTask>,
+}
+
+/// 子任务幂等键：同一计划内的同一子任务只会完成一次
+pub fn idempotency_key(plan_id: Uuid, subtask_id: Uuid) -> String {
+    format!("{}:{}", plan_id, subtask_id)
+}
+
+/// 从事件流重建尚未完成的计划
+///
+/// `TaskPlanned` 事件恢复计划结构，携带幂等键的 `TaskCompleted` 事件恢复子任务结果，
+/// 以计划 ID 为实体的 `TaskCompleted` 或 `TaskCancelled` 事件表示计划已结束。
+pub fn recover_plans(events: &[Event]) -> Result<Vec<TaskPlan>> {
+    let mut plans: Vec<TaskPlan> = Vec::new();
+    for event in events.iter().filter(|e| e.kind == EventKind::TaskPlanned) {
+        let plan: TaskPlan = serde_json::from_value(event.payload["plan"].clone())?;
+        if !plans.iter().any(|p| p.id == plan.id) {
+            plans.push(plan);
+        }
+    }
+
+    // 已取消的计划不再恢复
+    let mut finished: HashSet<Uuid> = events
+        .iter()
+        .filter(|e| e.kind == EventKind::TaskCancelled)
+        .map(|e| e.entity_id)
+        .collect();
+    for event in events.iter().filter(|e| e.kind == EventKind::TaskCompleted) {
+        let Some(plan_id) = event.correlation_id else {
+            continue;
+        };
+        if event.entity_id == plan_id {
+            finished.insert(plan_id);
+            continue;
+        }
+        let Some(plan) = plans.iter_mut().find(|p| p.id == plan_id) else {
+            continue;
+        };
+        let key = idempotency_key(plan_id, event.entity_id);
+        if event.payload["idempotency_key"].as_str() != Some(key.as_str()) {
+            continue;
+        }
+        if let Some(subtask) = plan.subtasks.iter_mut().find(|t| t.id == event.entity_id) {
+            subtask.status = SubTaskStatus::Completed;
+            subtask.result = event.payload["output"].as_str().map(str::to_string);
+        }
+    }
+
+    plans.retain(|p| !finished.contains(&p.id));
+    Ok(plans)
+}
+
+/// 余弦相似度（维度不同或存在零向量时为 0）
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
+
+/// 在历史事件中查找与目标向量最相似的计划
+///
+/// 只比对 `TaskPlanned` 事件中记录了 `embedding` 的计划；已取消的计划不参与比对。
+pub fn find_similar_plan(
+    events: &[Event],
+    embedding: &[f32],
+    threshold: f32,
+) -> Result<Option<DuplicateTask>> {
+    let mut best: Option<(f32, &Event)> = None;
+    for event in events.iter().filter(|e| e.kind == EventKind::TaskPlanned) {
+        let Some(previous) = event.payload.get("embedding").and_then(|v| v.as_array()) else {
+            continue;
+        };
+        let previous: Vec<f32> = previous
+            .iter()
+            .filter_map(|x| x.as_f64())
+            .map(|x| x as f32)
+            .collect();
+        let similarity = cosine_similarity(embedding, &previous);
+        let cancelled = events
+            .iter()
+            .any(|e| e.kind == EventKind::TaskCancelled && e.entity_id == event.entity_id);
+        if similarity >= threshold && !cancelled && best.is_none_or(|(s, _)| similarity > s) {
+            best = Some((similarity, event));
+        }
+    }
+    let Some((similarity, planned)) = best else {
+        return Ok(None);
+    };
+
+    let plan: TaskPlan = serde_json::from_value(planned.payload["plan"].clone())?;
+    let finished = events.iter().find(|e| {
+        e.kind == EventKind::TaskCompleted
+            && e.entity_id == plan.id
+            && e.correlation_id == Some(plan.id)
+    });
+    let status = match finished {
+        None => PreviousTaskStatus::Unfinished,
+        Some(event) if event.payload["success"].as_bool().unwrap_or(false) => {
+            PreviousTaskStatus::Completed {
+                output: event.payload["output"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            }
+        }
+        Some(_) => PreviousTaskStatus::Failed,
+    };
+    Ok(Some(DuplicateTask {
+        plan_id: plan.id,
+        goal: plan.goal,
+        similarity,
+        status,
+    }))
+}
+
+/// 任务编排器
+pub struct Orchestrator {
+    /// 目标规划器
+    planner: Option<Arc<dyn GoalPlanner>>,
+    /// SOP 引擎
+    sop: SopEngine,
+    /// MCTS 配置
+    mcts_config: MctsConfig,
+    /// 编排事件
+    events: Vec<Event>,
+    /// 事件存储 (设置后每次状态变迁都会落盘)
+    store: Option<Arc<Mutex<EventStore>>>,
+    /// 已落盘的事件数量
+    persisted: usize,
+    /// 取消令牌登记表 (按计划 ID 登记)
+    cancellation: Option<Arc<CancellationRegistry>>,
+    /// 目标向量化 (设置后启用重复任务检测)
+    embedder: Option<Arc<dyn Embedder>>,
+    /// 视为重复任务的相似度阈值
+    duplicate_threshold: f32,
+    /// 并行派发配置
+    fan_out: FanOutConfig,
+    /// 派发前检查的 Actor 配额
+    quota: Option<(Arc<QuotaManager>, ActorId)>,
+}
+
+impl Orchestrator {
+    /// 创建新编排器
+    pub fn new(sop: SopEngine) -> Self {
+        Self {
+            planner: None,
+            sop,
+            mcts_config: MctsConfig::default(),
+            events: Vec::new(),
+            store: None,
+            persisted: 0,
+            cancellation: None,
+            embedder: None,
+            duplicate_threshold: Self::DEFAULT_DUPLICATE_THRESHOLD,
+            fan_out: FanOutConfig::default(),
+            quota: None,
+        }
+    }
+
+    /// 默认的重复任务相似度阈值
+    pub const DEFAULT_DUPLICATE_THRESHOLD: f32 = 0.92;
+
+    /// 设置目标规划器
+    pub fn with_planner(mut self, planner: Arc<dyn GoalPlanner>) -> Self {
+        self.planner = Some(planner);
+        self
+    }
+
+    /// 是否配置了目标规划器
+    pub fn has_planner(&self) -> bool {
+        self.planner.is_some()
+    }
+
+    /// 设置事件存储
+    pub fn with_event_store(mut self, store: Arc<Mutex<EventStore>>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// 设置取消登记表，执行中的计划可通过 `CancellationRegistry::cancel(plan_id)` 中止
+    pub fn with_cancellation(mut self, registry: Arc<CancellationRegistry>) -> Self {
+        self.cancellation = Some(registry);
+        self
+    }
+
+    /// 启用重复任务检测：目标向量与历史目标的余弦相似度不低于 `threshold` 时视为重复
+    pub fn with_duplicate_detection(mut self, embedder: Arc<dyn Embedder>, threshold: f32) -> Self {
+        self.embedder = Some(embedder);
+        self.duplicate_threshold = threshold;
+        self
+    }
+
+    /// 设置 MCTS 配置
+    pub fn with_mcts_config(mut self, config: MctsConfig) -> Self {
+        self.mcts_config = config;
+        self
+    }
+
+    /// 设置并行派发配置
+    pub fn with_fan_out(mut self, config: FanOutConfig) -> Self {
+        self.fan_out = config;
+        self
+    }
+
+    /// 每个子任务派发前检查 `actor` 的配额，超额的子任务直接失败
+    pub fn with_quota(mut self, quotas: Arc<QuotaManager>, actor: ActorId) -> Self {
+        self.quota = Some((quotas, actor));
+        self
+    }
+
+    /// 以相同配置与当前注册的工作流创建新编排器（编排事件从空开始）
+    ///
+    /// 执行计划需要 `&mut self`；共享的编排器只在 fork 时短暂加锁，各次执行互不阻塞。
+    pub fn fork(&self) -> Self {
+        Self {
+            planner: self.planner.clone(),
+            sop: self.sop.fork(),
+            mcts_config: self.mcts_config.clone(),
+            events: Vec::new(),
+            store: self.store.clone(),
+            persisted: 0,
+            cancellation: self.cancellation.clone(),
+            embedder: self.embedder.clone(),
+            duplicate_threshold: self.duplicate_threshold,
+            fan_out: self.fan_out.clone(),
+            quota: self.quota.clone(),
+        }
+    }
+
+    /// 获取 SOP 引擎
+    pub fn sop_engine(&mut self) -> &mut SopEngine {
+        &mut self.sop
+    }
+
+    /// 获取已产生的事件
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// 取出并清空已产生的事件
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        self.persisted = 0;
+        std::mem::take(&mut self.events)
+    }
+
+    /// 分解并执行目标
+    ///
+    /// 启用重复任务检测时，相似的历史任务已成功完成则直接返回其结果，尚未结束则续跑该计划；
+    /// 需要强制重新执行时使用 [`Orchestrator::run_fresh`]。
+    pub async fn run(&mut self, goal: &str) -> Result<OrchestrationResult> {
+        let embedding = self.embed_goal(goal).await;
+        if let Some(embedding) = &embedding {
+            if let Some(result) = self.reuse_duplicate(embedding).await? {
+                return Ok(result);
+            }
+        }
+        self.plan_and_execute(goal, embedding).await
+    }
+
+    /// 分解并执行目标，不复用相似的历史任务
+    pub async fn run_fresh(&mut self, goal: &str) -> Result<OrchestrationResult> {
+        let embedding = self.embed_goal(goal).await;
+        self.plan_and_execute(goal, embedding).await
+    }
+
+    /// 查找与目标相似的历史任务（未启用重复任务检测时返回 `None`）
+    pub async fn find_duplicate(&self, goal: &str) -> Result<Option<DuplicateTask>> {
+        match self.embed_goal(goal).await {
+            Some(embedding) => self.similar_plan(&embedding).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn plan_and_execute(
+        &mut self,
+        goal: &str,
+        embedding: Option<Vec<f32>>,
+    ) -> Result<OrchestrationResult> {
+        let planner = self
+            .planner
+            .clone()
+            .ok_or_else(|| NeuroLoomError::Unknown("No goal planner configured".to_string()))?;
+        let plan = planner.decompose(goal).await?;
+        let mut payload = serde_json::json!({ "plan": plan });
+        if let Some(embedding) = embedding {
+            payload["embedding"] = serde_json::json!(embedding);
+        }
+        self.emit(EventKind::TaskPlanned, plan.id, plan.id, None, payload);
+        self.persist().await?;
+        self.execute_plan(plan).await
+    }
+
+    /// 目标向量化（失败时只告警，不影响执行）
+    async fn embed_goal(&self, goal: &str) -> Option<Vec<f32>> {
+        let embedder = self.embedder.as_ref()?;
+        match embedder.embed(goal).await {
+            Ok(embedding) => Some(embedding),
+            Err(e) => {
+                tracing::warn!(
+                    "Duplicate task detection skipped, failed to embed goal: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// 编排历史：事件存储中的计划与结束事件（未配置事件存储时为内存事件）
+    async fn history(&self) -> Result<Vec<Event>> {
+        let Some(store) = &self.store else {
+            return Ok(self.events.clone());
+        };
+        let store = store.lock().await;
+        let mut events = store.get_events_by_kind(&EventKind::TaskPlanned).await?;
+        events.extend(store.get_events_by_kind(&EventKind::TaskCompleted).await?);
+        events.extend(store.get_events_by_kind(&EventKind::TaskCancelled).await?);
+        Ok(events)
+    }
+
+    async fn similar_plan(&self, embedding: &[f32]) -> Result<Option<DuplicateTask>> {
+        find_similar_plan(&self.history().await?, embedding, self.duplicate_threshold)
+    }
+
+    /// 复用已完成的相似任务或续跑未完成的相似任务；没有可复用的任务时返回 `None`
+    async fn reuse_duplicate(&mut self, embedding: &[f32]) -> Result<Option<OrchestrationResult>> {
+        let events = self.history().await?;
+        let Some(duplicate) = find_similar_plan(&events, embedding, self.duplicate_threshold)?
+        else {
+            return Ok(None);
+        };
+
+        match &duplicate.status {
+            PreviousTaskStatus::Completed { output } => {
+                tracing::info!(
+                    "Goal matches completed plan {} ({:.2}), reusing its result",
+                    duplicate.plan_id,
+                    duplicate.similarity
+                );
+                let planned = events
+                    .iter()
+                    .find(|e| e.kind == EventKind::TaskPlanned && e.entity_id == duplicate.plan_id)
+                    .expect("duplicate found among planned events");
+                let plan: TaskPlan = serde_json::from_value(planned.payload["plan"].clone())?;
+                Ok(Some(OrchestrationResult {
+                    plan,
+                    output: output.clone(),
+                    success: true,
+                    duplicate_of: Some(duplicate),
+                }))
+            }
+            PreviousTaskStatus::Unfinished => {
+                let Some(plan) = recover_plans(&events)?
+                    .into_iter()
+                    .find(|p| p.id == duplicate.plan_id)
+                else {
+                    return Ok(None);
+                };
+                tracing::info!(
+                    "Goal matches unfinished plan {} ({:.2}), resuming it",
+                    plan.id,
+                    duplicate.similarity
+                );
+                let mut result = self.execute_plan(plan).await?;
+                result.duplicate_of = Some(duplicate);
+                Ok(Some(result))
+            }
+            // 上次失败的任务重新规划
+            PreviousTaskStatus::Failed => Ok(None),
+        }
+    }
+
+    /// 恢复事件存储中未完成的计划，已完成的子任务按幂等键跳过
+    pub async fn resume_unfinished(&mut self) -> Result<Vec<OrchestrationResult>> {
+        let Some(store) = self.store.clone() else {
+            return Ok(Vec::new());
+        };
+        let events = {
+            let store = store.lock().await;
+            let mut events = store.get_events_by_kind(&EventKind::TaskPlanned).await?;
+            events.extend(store.get_events_by_kind(&EventKind::TaskCompleted).await?);
+            events.extend(store.get_events_by_kind(&EventKind::TaskCancelled).await?);
+            events
+        };
+
+        let mut results = Vec::new();
+        for plan in recover_plans(&events)? {
+            tracing::info!("Resuming task plan {} ({})", plan.id, plan.goal);
+            results.push(self.execute_plan(plan).await?);
+        }
+        Ok(results)
+    }
+
+    /// 执行已有计划
+    pub async fn execute_plan(&mut self, plan: TaskPlan) -> Result<OrchestrationResult> {
+        let plan_id = plan.id;
+        let cancel = match &self.cancellation {
+            Some(registry) => registry.register(plan_id),
+            None => CancellationToken::new(),
+        };
+        let span = tracing::info_span!("plan", plan = %plan_id);
+        let result = self.execute_plan_with(plan, &cancel).instrument(span).await;
+        if let Some(registry) = &self.cancellation {
+            registry.remove(plan_id);
+        }
+        result
+    }
+
+    async fn execute_plan_with(
+        &mut self,
+        mut plan: TaskPlan,
+        cancel: &CancellationToken,
+    ) -> Result<OrchestrationResult> {
+        let order = plan.topological_order()?;
+        let mut completed: HashSet<Uuid> = plan
+            .subtasks
+            .iter()
+            .filter(|t| t.status == SubTaskStatus::Completed)
+            .map(|t| t.id)
+            .collect();
+
+        let mut attempted: HashSet<Uuid> = HashSet::new();
+        // FailFast 时首个失败触发，取消同批仍在执行的子任务
+        let abort = cancel.child_token();
+        let mut wave = 0;
+
+        while !abort.is_cancelled() {
+            // 依赖全部完成且本次尚未派发的子任务互相独立，作为一批并行派发
+            let ready: Vec<usize> = order
+                .iter()
+                .filter(|id| !completed.contains(id) && !attempted.contains(id))
+                .map(|id| {
+                    plan.subtasks
+                        .iter()
+                        .position(|t| t.id == *id)
+                        .expect("id from topological order")
+                })
+                .filter(|&index| plan.subtasks[index].depends_on.iter().all(|d| completed.contains(d)))
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+            wave += 1;
+
+            let mut batch = Vec::with_capacity(ready.len());
+            for index in ready {
+                let id = plan.subtasks[index].id;
+                attempted.insert(id);
+                let route = self.route_for(&plan.subtasks[index]);
+                plan.subtasks[index].status = SubTaskStatus::Running;
+                plan.subtasks[index].route = Some(route.clone());
+                let assigned = self.emit(
+                    EventKind::TaskAssigned,
+                    id,
+                    plan.id,
+                    None,
+                    serde_json::json!({
+                        "name": plan.subtasks[index].name,
+                        "route": route,
+                        "wave": wave,
+                    }),
+                );
+                let context = self.dependency_context(&plan, &plan.subtasks[index]);
+                batch.push((index, assigned, route, context));
+            }
+
+            let outcomes = self.dispatch_batch(&plan, &batch, wave, &abort).await;
+            for ((index, assigned, _, _), outcome) in batch.into_iter().zip(outcomes) {
+                let id = plan.subtasks[index].id;
+                match outcome {
+                    Ok(output) => {
+                        plan.subtasks[index].status = SubTaskStatus::Completed;
+                        plan.subtasks[index].result = Some(output.clone());
+                        completed.insert(id);
+                        self.emit(
+                            EventKind::TaskCompleted,
+                            id,
+                            plan.id,
+                            Some(assigned),
+                            serde_json::json!({
+                                "output": output,
+                                "idempotency_key": idempotency_key(plan.id, id),
+                            }),
+                        );
+                    }
+                    Err(NeuroLoomError::Cancelled(reason)) => {
+                        tracing::info!("Subtask {} cancelled: {}", plan.subtasks[index].name, reason);
+                        plan.subtasks[index].status = SubTaskStatus::Cancelled;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Subtask {} failed: {}", plan.subtasks[index].name, e);
+                        plan.subtasks[index].status = SubTaskStatus::Failed;
+                        self.emit(
+                            EventKind::ExecutionFailed,
+                            id,
+                            plan.id,
+                            Some(assigned),
+                            serde_json::json!({ "error": e.to_string() }),
+                        );
+                    }
+                }
+            }
+            self.persist().await?;
+        }
+
+        if cancel.is_cancelled() {
+            for subtask in plan.subtasks.iter_mut() {
+                if matches!(subtask.status, SubTaskStatus::Pending | SubTaskStatus::Running) {
+                    subtask.status = SubTaskStatus::Cancelled;
+                }
+            }
+            self.emit(
+                EventKind::TaskCancelled,
+                plan.id,
+                plan.id,
+                None,
+                serde_json::json!({ "goal": plan.goal, "completed": completed.len() }),
+            );
+            self.persist().await?;
+            return Ok(OrchestrationResult {
+                output: Self::assemble(&plan),
+                plan,
+                success: false,
+                duplicate_of: None,
+            });
+        }
+
+        let success = completed.len() == plan.subtasks.len();
+        let output = Self::assemble(&plan);
+        self.emit(
+            EventKind::TaskCompleted,
+            plan.id,
+            plan.id,
+            None,
+            serde_json::json!({ "goal": plan.goal, "success": success, "output": output }),
+        );
+        self.persist().await?;
+
+        Ok(OrchestrationResult {
+            plan,
+            output,
+            success,
+            duplicate_of: None,
+        })
+    }
+
+    /// 选择派发路线：存在匹配且未退役的 SOP 工作流则走 System 1，否则走 System 2
+    fn route_for(&self, subtask: &SubTask) -> DispatchRoute {
+        let candidates = subtask
+            .workflow
+            .iter()
+            .chain(std::iter::once(&subtask.name));
+        for name in candidates {
+            if self.sop.find_active(name).is_some() {
+                return DispatchRoute::Sop {
+                    workflow: name.clone(),
+                };
+            }
+        }
+        DispatchRoute::Mcts
+    }
+
+    /// 并行派发一批互相独立的子任务，结果按批内顺序返回
+    async fn dispatch_batch(
+        &self,
+        plan: &TaskPlan,
+        batch: &[(usize, Uuid, DispatchRoute, String)],
+        wave: usize,
+        abort: &CancellationToken,
+    ) -> Vec<Result<String>> {
+        let fail_fast = self.fan_out.failure_policy == FailurePolicy::FailFast;
+        // 先收集为具体类型的 future：直接在流上 `map` 时闭包需对任意生命周期成立，执行计划的 future 不再是 `Send`
+        let tasks: Vec<_> = batch
+            .iter()
+            .enumerate()
+            .map(|(slot, (index, _, route, context))| {
+                let subtask = &plan.subtasks[*index];
+                let span = tracing::info_span!(
+                    "subtask",
+                    plan = %plan.id,
+                    subtask = %subtask.id,
+                    name = %subtask.name,
+                    wave
+                );
+                async move {
+                    if abort.is_cancelled() {
+                        let reason = format!("plan {} stopped before dispatch", plan.id);
+                        return (slot, Err(NeuroLoomError::Cancelled(reason)));
+                    }
+                    let outcome = match &self.quota {
+                        Some((quotas, actor)) => quotas.check(*actor).await,
+                        None => Ok(()),
+                    };
+                    let outcome = match outcome {
+                        Ok(()) => self.dispatch(subtask, route, context, abort).await,
+                        Err(e) => Err(e),
+                    };
+                    if fail_fast && matches!(&outcome, Err(e) if !matches!(e, NeuroLoomError::Cancelled(_))) {
+                        abort.cancel();
+                    }
+                    (slot, outcome)
+                }
+                .instrument(span)
+            })
+            .collect();
+        let mut outcomes: Vec<(usize, Result<String>)> = stream::iter(tasks)
+            .buffer_unordered(self.fan_out.max_parallel.max(1))
+            .collect()
+            .await;
+        outcomes.sort_by_key(|(slot, _)| *slot);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+
+    /// 派发单个子任务
+    async fn dispatch(
+        &self,
+        subtask: &SubTask,
+        route: &DispatchRoute,
+        context: &str,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        match route {
+            DispatchRoute::Sop { workflow } => {
+                let workflow_id = self.sop.find(workflow).map(|w| w.id).ok_or_else(|| {
+                    NeuroLoomError::Unknown(format!("Workflow not found: {}", workflow))
+                })?;
+                let ctx = self.sop.execute_cancellable(&workflow_id, cancel).await?;
+                Ok(ctx
+                    .history
+                    .last()
+                    .and_then(|id| ctx.results.get(id))
+                    .cloned()
+                    .unwrap_or_default())
+            }
+            DispatchRoute::Mcts => {
+                let mut engine = MctsEngine::new(self.mcts_config.clone());
+                let state = if context.is_empty() {
+                    subtask.description.clone()
+                } else {
+                    format!("{}\n\n{}", subtask.description, context)
+                };
+                engine.set_root(state);
+                Ok(engine
+                    .search_cancellable(cancel)
+                    .await?
+                    .unwrap_or_else(|| subtask.description.clone()))
+            }
+        }
+    }
+
+    /// 收集前置子任务的结果作为上下文
+    fn dependency_context(&self, plan: &TaskPlan, subtask: &SubTask) -> String {
+        subtask
+            .depends_on
+            .iter()
+            .filter_map(|id| plan.get(id))
+            .filter_map(|t| t.result.as_ref().map(|r| format!("[{}] {}", t.name, r)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 汇总所有子任务结果
+    fn assemble(plan: &TaskPlan) -> String {
+        plan.subtasks
+            .iter()
+            .map(|t| match (&t.status, &t.result) {
+                (SubTaskStatus::Completed, Some(r)) => format!("## {}\n{}", t.name, r),
+                (status, _) => format!("## {}\n({:?})", t.name, status),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// 将待写事件落盘 (未配置事件存储时保留在内存中)
+    async fn persist(&mut self) -> Result<()> {
+        if let Some(store) = &self.store {
+            let pending = self.events[self.persisted..].to_vec();
+            store.lock().await.append_batch(pending).await?;
+            self.persisted = self.events.len();
+        }
+        Ok(())
+    }
+
+    /// 记录编排事件，返回事件 ID
+    fn emit(
+        &mut self,
+        kind: EventKind,
+        entity_id: Uuid,
+        correlation_id: Uuid,
+        causation_id: Option<Uuid>,
+        payload: serde_json::Value,
+    ) -> Uuid {
+        let mut event = Event::new(kind, entity_id, payload).with_correlation(correlation_id);
+        if let Some(cause) = causation_id {
+            event = event.with_causation(cause);
+        }
+        let id = event.id;
+        self.events.push(event);
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topological_order_respects_dependencies() {
+        let mut plan = TaskPlan::new("goal");
+        let a = plan.add(SubTask::new("a", "first"));
+        let b = plan.add(SubTask::new("b", "second").depends_on(a));
+        let c = plan.add(SubTask::new("c", "third").depends_on(a).depends_on(b));
+
+        assert_eq!(plan.topological_order().unwrap(), vec![a, b, c]);
+    }
+
+    #[test]
+    fn test_topological_order_detects_cycle() {
+        let mut plan = TaskPlan::new("goal");
+        let mut a = SubTask::new("a", "first");
+        let b = SubTask::new("b", "second").depends_on(a.id);
+        a.depends_on.push(b.id);
+        plan.add(a);
+        plan.add(b);
+
+        assert!(plan.topological_order().is_err());
+    }
+
+    #[test]
+    fn test_parse_plan_resolves_names() {
+        let content = r#"```json
+[{"name": "fetch", "description": "get data"},
+ {"name": "report", "description": "write", "workflow": "daily_report", "depends_on": ["fetch"]}]
+```"#;
+        let plan = LlmPlanner::parse_plan("goal", content).unwrap();
+
+        assert_eq!(plan.subtasks.len(), 2);
+        assert_eq!(plan.subtasks[1].depends_on, vec![plan.subtasks[0].id]);
+        assert_eq!(plan.subtasks[1].workflow.as_deref(), Some("daily_report"));
+    }
+
+    #[test]
+    fn test_recover_plans_skips_completed_subtasks() {
+        let mut plan = TaskPlan::new("goal");
+        let a = plan.add(SubTask::new("a", "first"));
+        let b = plan.add(SubTask::new("b", "second").depends_on(a));
+        let planned = Event::new(
+            EventKind::TaskPlanned,
+            plan.id,
+            serde_json::json!({ "plan": plan }),
+        )
+        .with_correlation(plan.id);
+        let done_a = Event::new(
+            EventKind::TaskCompleted,
+            a,
+            serde_json::json!({ "output": "ok", "idempotency_key": idempotency_key(plan.id, a) }),
+        )
+        .with_correlation(plan.id);
+
+        let recovered = recover_plans(&[planned.clone(), done_a.clone()]).unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].get(&a).unwrap().status, SubTaskStatus::Completed);
+        assert_eq!(recovered[0].get(&b).unwrap().status, SubTaskStatus::Pending);
+
+        let finished = Event::new(EventKind::TaskCompleted, plan.id, serde_json::json!({}))
+            .with_correlation(plan.id);
+        assert!(recover_plans(&[planned, done_a, finished]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_similar_plan_reports_previous_status() {
+        let planned = |goal: &str, embedding: Vec<f32>| {
+            let plan = TaskPlan::new(goal);
+            let event = Event::new(
+                EventKind::TaskPlanned,
+                plan.id,
+                serde_json::json!({ "plan": plan, "embedding": embedding }),
+            )
+            .with_correlation(plan.id);
+            (plan.id, event)
+        };
+        let (done, done_planned) = planned("deploy the web app", vec![1.0, 0.0, 0.1]);
+        let (open, open_planned) = planned("write release notes", vec![0.0, 1.0, 0.0]);
+        let finished = Event::new(
+            EventKind::TaskCompleted,
+            done,
+            serde_json::json!({ "goal": "deploy the web app", "success": true, "output": "deployed" }),
+        )
+        .with_correlation(done);
+        let events = vec![done_planned, open_planned, finished];
+
+        let duplicate = find_similar_plan(&events, &[0.9, 0.0, 0.1], 0.9)
+            .unwrap()
+            .unwrap();
+        assert_eq!(duplicate.plan_id, done);
+        assert_eq!(
+            duplicate.status,
+            PreviousTaskStatus::Completed {
+                output: "deployed".to_string()
+            }
+        );
+
+        let duplicate = find_similar_plan(&events, &[0.1, 1.0, 0.0], 0.9)
+            .unwrap()
+            .unwrap();
+        assert_eq!(duplicate.plan_id, open);
+        assert_eq!(duplicate.status, PreviousTaskStatus::Unfinished);
+
+        assert!(find_similar_plan(&events, &[0.0, 0.0, 1.0], 0.9)
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_running_plan() {
+        use crate::system1::{SopAction, SopNode, SopWorkflow};
+
+        let mut workflow = SopWorkflow::new("slow");
+        let step = SopNode {
+            id: Uuid::new_v4(),
+            name: "wait".to_string(),
+            action: SopAction::Wait { seconds: 30 },
+            next: Vec::new(),
+            on_failure: None,
+        };
+        workflow.set_entry(step.id);
+        workflow.add_node(step);
+        let mut sop = SopEngine::new();
+        sop.register(workflow);
+
+        let registry = Arc::new(CancellationRegistry::new());
+        let mut orchestrator = Orchestrator::new(sop).with_cancellation(registry.clone());
+        let mut plan = TaskPlan::new("goal");
+        let a = plan.add(SubTask::new("slow", "wait a long time"));
+        let b = plan.add(SubTask::new("after", "never runs").depends_on(a));
+        let plan_id = plan.id;
+
+        tokio::spawn(async move {
+            while !registry.cancel(plan_id, "test") {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        });
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            orchestrator.execute_plan(plan),
+        )
+        .await
+        .expect("cancellation should interrupt the wait")
+        .unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.plan.get(&a).unwrap().status, SubTaskStatus::Cancelled);
+        assert_eq!(result.plan.get(&b).unwrap().status, SubTaskStatus::Cancelled);
+        let cancelled = orchestrator
+            .events()
+            .iter()
+            .find(|e| e.kind == EventKind::TaskCancelled)
+            .unwrap();
+        assert_eq!(cancelled.entity_id, plan_id);
+    }
+
+    #[tokio::test]
+    async fn test_independent_subtasks_fan_out_in_parallel() {
+        use crate::system1::{SopAction, SopNode, SopWorkflow};
+        use nl_durable::ResourceQuota;
+
+        let workflow = |name: &str, action: SopAction| {
+            let mut workflow = SopWorkflow::new(name);
+            let step = SopNode {
+                id: Uuid::new_v4(),
+                name: name.to_string(),
+                action,
+                next: Vec::new(),
+                on_failure: None,
+            };
+            workflow.set_entry(step.id);
+            workflow.add_node(step);
+            workflow
+        };
+        let mut sop = SopEngine::new();
+        sop.register(workflow("slow", SopAction::Wait { seconds: 1 }));
+        sop.register(workflow("join", SopAction::CallLLM { prompt: "merge".to_string(), model: "m".to_string() }));
+        let mut plan = TaskPlan::new("goal");
+        let branches: Vec<Uuid> = (0..3)
+            .map(|i| plan.add(SubTask::new(format!("branch{}", i), "wait").with_workflow("slow")))
+            .collect();
+        let join = plan.add(branches.iter().fold(SubTask::new("join", "merge"), |t, id| t.depends_on(*id)));
+
+        let fan_out = FanOutConfig { max_parallel: 3, ..FanOutConfig::default() };
+        let mut orchestrator = Orchestrator::new(sop).with_fan_out(fan_out);
+        let started = std::time::Instant::now();
+        let result = orchestrator.execute_plan(plan.clone()).await.unwrap();
+        assert!(result.success);
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        let waves: Vec<u64> = orchestrator
+            .events()
+            .iter()
+            .filter(|e| e.kind == EventKind::TaskAssigned)
+            .map(|e| e.payload["wave"].as_u64().unwrap())
+            .collect();
+        assert_eq!(waves, [1, 1, 1, 2]);
+        assert!(result.output.starts_with("## branch0\nWaited 1 seconds\n\n## branch1"));
+
+        // 配额用尽：首个失败即中止，同批其余子任务取消，汇合子任务不再派发
+        let quotas = Arc::new(QuotaManager::new(ResourceQuota::unlimited().with_llm_tokens_per_hour(0)));
+        let fan_out = FanOutConfig { max_parallel: 1, failure_policy: FailurePolicy::FailFast };
+        let mut orchestrator =
+            Orchestrator::new(SopEngine::new()).with_fan_out(fan_out).with_quota(quotas, Uuid::new_v4());
+        let result = orchestrator.execute_plan(plan).await.unwrap();
+        assert!(!result.success);
+        let statuses: Vec<SubTaskStatus> = result.plan.subtasks.iter().map(|t| t.status).collect();
+        assert_eq!(
+            statuses,
+            [SubTaskStatus::Failed, SubTaskStatus::Cancelled, SubTaskStatus::Cancelled, SubTaskStatus::Pending]
+        );
+        assert_eq!(result.plan.get(&join).unwrap().status, SubTaskStatus::Pending);
+    }
+
+    #[test]
+    fn test_execute_plan_future_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let mut orchestrator = Orchestrator::new(SopEngine::new());
+        // 守护进程在 `tokio::spawn` 中执行计划
+        assert_send(&orchestrator.execute_plan(TaskPlan::new("goal")));
+    }
+
+    #[tokio::test]
+    async fn test_forked_orchestrators_run_concurrently_and_share_sop_stats() {
+        use crate::system1::{SopAction, SopNode, SopWorkflow};
+
+        let mut workflow = SopWorkflow::new("slow");
+        let step = SopNode {
+            id: Uuid::new_v4(),
+            name: "wait".to_string(),
+            action: SopAction::Wait { seconds: 1 },
+            next: Vec::new(),
+            on_failure: None,
+        };
+        workflow.set_entry(step.id);
+        workflow.add_node(step);
+        let mut sop = SopEngine::new();
+        sop.register(workflow);
+        let shared = Arc::new(Mutex::new(Orchestrator::new(sop)));
+
+        let started = std::time::Instant::now();
+        let runs = (0..2).map(|_| {
+            let shared = shared.clone();
+            async move {
+                let mut runner = shared.lock().await.fork();
+                let mut plan = TaskPlan::new("goal");
+                plan.add(SubTask::new("wait", "wait").with_workflow("slow"));
+                runner.execute_plan(plan).await.unwrap()
+            }
+        });
+        let results = futures::future::join_all(runs).await;
+        assert!(results.iter().all(|r| r.success));
+        assert!(started.elapsed() < std::time::Duration::from_millis(1900));
+        let stats = shared.lock().await.sop_engine().stats();
+        assert_eq!((stats[0].workflow.as_str(), stats[0].runs), ("slow", 2));
+    }
+
+    #[tokio::test]
+    async fn test_sop_execution_publishes_node_events() {
+        use crate::system1::{SopAction, SopNode, SopWorkflow};
+        use nl_core::sop_view::{SopNodeStatus, SopRunView};
+        use nl_durable::EventBus;
+
+        let mut workflow = SopWorkflow::new("deploy");
+        let second = SopNode {
+            id: Uuid::new_v4(),
+            name: "notify".to_string(),
+            action: SopAction::CallLLM { prompt: "summarize".to_string(), model: "m".to_string() },
+            next: Vec::new(),
+            on_failure: None,
+        };
+        let first = SopNode {
+            id: Uuid::new_v4(),
+            name: "build".to_string(),
+            action: SopAction::ExecuteCommand { command: "cargo".to_string(), args: vec!["build".to_string()] },
+            next: vec![second.id],
+            on_failure: None,
+        };
+        let (first_id, second_id, workflow_id) = (first.id, second.id, workflow.id);
+        workflow.set_entry(first.id);
+        workflow.add_node(first);
+        workflow.add_node(second);
+
+        let bus = Arc::new(EventBus::default());
+        let mut sop = SopEngine::new().with_event_bus(bus.clone());
+        sop.register(workflow);
+        // 注册时发布的 `SopRegistered` 不属于执行事件
+        let mut events = bus.subscribe_all();
+        sop.execute(&workflow_id).await.unwrap();
+
+        let started = events.recv().await.unwrap();
+        let mut view = SopRunView::from_event(&started).unwrap();
+        assert_eq!(view.status(first_id), Some(SopNodeStatus::Pending));
+        for _ in 0..4 {
+            let event = events.recv().await.unwrap();
+            assert_eq!(event.correlation_id, Some(view.run));
+            assert!(view.apply(&event));
+        }
+        assert_eq!(view.status(first_id), Some(SopNodeStatus::Completed));
+        assert_eq!(view.status(second_id), Some(SopNodeStatus::Completed));
+        assert!(view.is_finished());
+        let lines = view.render();
+        assert!(lines[1].contains("[+] build (ExecuteCommand)"));
+        assert!(lines[2].starts_with("    [+] notify"));
+    }
+}
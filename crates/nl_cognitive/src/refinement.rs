@@ -0,0 +1,408 @@
+//! 基于裁决的提示词自动改进
+//!
+//! 同一类任务反复因相同原因被驳回时，改进任务调整该类 Worker 的提示词模板：
+//! 1. 从裁决历史中取出使用该模板的驳回，按理由与建议的词集相似度聚类
+//! 2. 成员数达到阈值的聚类连同当前模板交给 LLM，生成修改后的人设与模板
+//! 3. 在留出的任务上对新旧模板做 A/B 测试（由调用方提供的 `PromptEvaluator` 执行并裁决）
+//! 4. 新模板通过率更高（或通过率相同且平均分高出阈值）时登记并设为生效版本
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use nl_core::{EventKind, NeuroLoomError, Result};
+use nl_durable::EventStore;
+use nl_llm::{LlmClient, PrimitiveRequest};
+
+use crate::courtroom::appeal::word_set;
+use crate::courtroom::Verdict;
+use crate::templates::{template_name, PromptTemplate, TemplateRegistry};
+
+/// 改进配置
+#[derive(Debug, Clone)]
+pub struct RefinementConfig {
+    /// 同一聚类的驳回数达到多少才触发改进
+    pub min_cluster_size: usize,
+    /// 归入同一聚类的最低词集相似度 (Jaccard)
+    pub similarity: f64,
+    /// 提交给 LLM 的聚类数上限（按成员数降序）
+    pub max_clusters: usize,
+    /// 通过率相同时，新模板平均分至少高出多少才晋升
+    pub min_score_gain: f64,
+}
+
+impl Default for RefinementConfig {
+    fn default() -> Self {
+        Self {
+            min_cluster_size: 3,
+            similarity: 0.35,
+            max_clusters: 5,
+            min_score_gain: 0.05,
+        }
+    }
+}
+
+/// 一类相似的驳回原因
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectionCluster {
+    /// 代表性的理由（聚类中第一条）
+    pub reason: String,
+    /// 聚类内出现过的建议（去重）
+    pub suggestions: Vec<String>,
+    /// 驳回裁决 ID
+    pub verdicts: Vec<uuid::Uuid>,
+}
+
+impl RejectionCluster {
+    pub fn size(&self) -> usize {
+        self.verdicts.len()
+    }
+}
+
+/// 按理由与建议的词集相似度聚类驳回裁决，按成员数降序返回
+pub fn cluster_rejections<'a>(verdicts: impl IntoIterator<Item = &'a Verdict>, similarity: f64) -> Vec<RejectionCluster> {
+    let mut clusters: Vec<(std::collections::HashSet<String>, RejectionCluster)> = Vec::new();
+    for verdict in verdicts.into_iter().filter(|v| !v.passed) {
+        let text = std::iter::once(verdict.reasoning.as_str())
+            .chain(verdict.suggestions.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let words = word_set(&text);
+        let found = clusters.iter().position(|(centroid, _)| {
+            let union = centroid.union(&words).count();
+            union > 0 && centroid.intersection(&words).count() as f64 / union as f64 >= similarity
+        });
+        let index = found.unwrap_or_else(|| {
+            clusters.push((
+                words,
+                RejectionCluster {
+                    reason: verdict.reasoning.clone(),
+                    suggestions: Vec::new(),
+                    verdicts: Vec::new(),
+                },
+            ));
+            clusters.len() - 1
+        });
+        let cluster = &mut clusters[index].1;
+        cluster.verdicts.push(verdict.id);
+        for suggestion in &verdict.suggestions {
+            if !cluster.suggestions.contains(suggestion) {
+                cluster.suggestions.push(suggestion.clone());
+            }
+        }
+    }
+    let mut clusters: Vec<RejectionCluster> = clusters.into_iter().map(|(_, c)| c).collect();
+    clusters.sort_by(|a, b| b.size().cmp(&a.size()));
+    clusters
+}
+
+/// 从事件存储读取 `since` 之后使用指定模板（任意版本）的裁决
+pub async fn verdicts_for_template(
+    store: &Mutex<EventStore>,
+    name: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<Verdict>> {
+    let events = store.lock().await.get_events_by_time(since, Utc::now()).await?;
+    Ok(events
+        .into_iter()
+        .filter(|e| e.kind == EventKind::VerdictIssued)
+        .filter_map(|e| serde_json::from_value::<Verdict>(e.payload).ok())
+        .filter(|v| v.template.as_deref().is_some_and(|id| template_name(id) == name))
+        .collect())
+}
+
+/// 根据驳回聚类生成修改后的模板
+#[async_trait]
+pub trait TemplateRefiner: Send + Sync {
+    /// 返回修改后的模板（名称不变，版本由登记表分配）
+    async fn propose(&self, current: &PromptTemplate, clusters: &[RejectionCluster]) -> Result<PromptTemplate>;
+}
+
+/// 在留出任务上执行模板并裁决（通常为 Worker 执行 + Critic 审查）
+#[async_trait]
+pub trait PromptEvaluator: Send + Sync {
+    async fn evaluate(&self, template: &PromptTemplate, task: &str) -> Result<Verdict>;
+}
+
+/// LLM 返回的修改方案
+#[derive(Debug, Deserialize)]
+struct ProposedTemplate {
+    persona: String,
+    template: String,
+    #[serde(default)]
+    rationale: String,
+}
+
+/// 基于 LLM 的模板改写器
+pub struct LlmTemplateRefiner {
+    client: Arc<LlmClient>,
+    model: String,
+}
+
+impl LlmTemplateRefiner {
+    /// 改写提示词
+    const SYSTEM_PROMPT: &'static str =
+        "You improve prompts for an AI worker whose output keeps getting rejected by reviewers. \
+Given the current persona and task template and clusters of recurring rejection reasons, rewrite them \
+so the worker avoids those failures. Keep the {task} placeholder in the template. \
+Reply with a JSON object only: {\"persona\": string, \"template\": string, \"rationale\": string}.";
+
+    pub fn new(client: Arc<LlmClient>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+        }
+    }
+
+    /// 解析 LLM 输出为新模板
+    pub fn parse_proposal(current: &PromptTemplate, content: &str) -> Result<PromptTemplate> {
+        let json = match (content.find('{'), content.rfind('}')) {
+            (Some(s), Some(e)) if s < e => &content[s..=e],
+            _ => {
+                return Err(NeuroLoomError::LlmProvider(
+                    "Refinement response contains no JSON object".to_string(),
+                ))
+            }
+        };
+        let proposed: ProposedTemplate = serde_json::from_str(json)?;
+        Ok(PromptTemplate::new(current.name.clone(), proposed.persona, proposed.template)
+            .with_rationale(proposed.rationale))
+    }
+}
+
+#[async_trait]
+impl TemplateRefiner for LlmTemplateRefiner {
+    async fn propose(&self, current: &PromptTemplate, clusters: &[RejectionCluster]) -> Result<PromptTemplate> {
+        let mut prompt = format!(
+            "## Current persona\n{}\n\n## Current template\n{}\n\n## Recurring rejections\n",
+            current.persona, current.template
+        );
+        for (i, cluster) in clusters.iter().enumerate() {
+            prompt.push_str(&format!("{}. ({} rejections) {}\n", i + 1, cluster.size(), cluster.reason));
+            for suggestion in &cluster.suggestions {
+                prompt.push_str(&format!("   - {}\n", suggestion));
+            }
+        }
+
+        let mut req = PrimitiveRequest::single_user_message(prompt).with_model(self.model.clone());
+        req.system = Some(Self::SYSTEM_PROMPT.to_string());
+        let response = self
+            .client
+            .complete(&req)
+            .await
+            .map_err(|e| NeuroLoomError::LlmProvider(e.to_string()))?;
+        Self::parse_proposal(current, &response.content)
+    }
+}
+
+/// A/B 测试中一侧的成绩
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AbScore {
+    pub passed: usize,
+    pub total: usize,
+    pub average_score: f64,
+}
+
+impl AbScore {
+    fn from_verdicts(verdicts: &[Verdict]) -> Self {
+        let total = verdicts.len();
+        Self {
+            passed: verdicts.iter().filter(|v| v.passed).count(),
+            total,
+            average_score: if total == 0 {
+                0.0
+            } else {
+                verdicts.iter().map(|v| v.score).sum::<f64>() / total as f64
+            },
+        }
+    }
+
+    pub fn pass_rate(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.passed as f64 / self.total as f64
+        }
+    }
+}
+
+/// 一次改进的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefinementReport {
+    pub template: String,
+    /// 达到阈值的驳回聚类
+    pub clusters: Vec<RejectionCluster>,
+    /// LLM 生成的候选模板（已登记时带版本号）
+    pub candidate: Option<PromptTemplate>,
+    pub baseline: AbScore,
+    pub challenger: AbScore,
+    /// 候选模板是否已晋升为生效版本
+    pub promoted: bool,
+}
+
+/// 提示词改进任务
+pub struct RefinementJob {
+    registry: Arc<TemplateRegistry>,
+    refiner: Arc<dyn TemplateRefiner>,
+    evaluator: Arc<dyn PromptEvaluator>,
+    config: RefinementConfig,
+}
+
+impl RefinementJob {
+    pub fn new(
+        registry: Arc<TemplateRegistry>,
+        refiner: Arc<dyn TemplateRefiner>,
+        evaluator: Arc<dyn PromptEvaluator>,
+    ) -> Self {
+        Self {
+            registry,
+            refiner,
+            evaluator,
+            config: RefinementConfig::default(),
+        }
+    }
+
+    /// 设置改进配置
+    pub fn with_config(mut self, config: RefinementConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 对一个模板执行改进：`verdicts` 为该类任务的裁决历史，`held_out` 为 A/B 测试用的留出任务
+    pub async fn run(&self, name: &str, verdicts: &[Verdict], held_out: &[String]) -> Result<RefinementReport> {
+        let current = self
+            .registry
+            .active(name)
+            .ok_or_else(|| NeuroLoomError::Unknown(format!("template {} not registered", name)))?;
+        let mut report = RefinementReport {
+            template: name.to_string(),
+            clusters: Vec::new(),
+            candidate: None,
+            baseline: AbScore::default(),
+            challenger: AbScore::default(),
+            promoted: false,
+        };
+
+        // 只统计当前生效版本产生的驳回，已被修正的旧问题不再重复改进
+        let current_id = current.id();
+        report.clusters = cluster_rejections(
+            verdicts.iter().filter(|v| v.template.as_deref() == Some(current_id.as_str())),
+            self.config.similarity,
+        )
+        .into_iter()
+        .filter(|c| c.size() >= self.config.min_cluster_size)
+        .take(self.config.max_clusters)
+        .collect();
+        if report.clusters.is_empty() || held_out.is_empty() {
+            return Ok(report);
+        }
+
+        let candidate = self.refiner.propose(&current, &report.clusters).await?;
+        let (mut baseline, mut challenger) = (Vec::new(), Vec::new());
+        for task in held_out {
+            baseline.push(self.evaluator.evaluate(&current, task).await?);
+            challenger.push(self.evaluator.evaluate(&candidate, task).await?);
+        }
+        report.baseline = AbScore::from_verdicts(&baseline);
+        report.challenger = AbScore::from_verdicts(&challenger);
+
+        let candidate = self.registry.register(candidate)?;
+        if self.wins(&report.challenger, &report.baseline) {
+            self.registry.promote(name, candidate.version)?;
+            report.promoted = true;
+            tracing::info!(
+                "Promoted template {} (pass rate {:.2} -> {:.2})",
+                candidate.id(),
+                report.baseline.pass_rate(),
+                report.challenger.pass_rate()
+            );
+        }
+        report.candidate = Some(candidate);
+        Ok(report)
+    }
+
+    fn wins(&self, challenger: &AbScore, baseline: &AbScore) -> bool {
+        match challenger.passed.cmp(&baseline.passed) {
+            std::cmp::Ordering::Greater => true,
+            std::cmp::Ordering::Equal => {
+                challenger.average_score >= baseline.average_score + self.config.min_score_gain
+            }
+            std::cmp::Ordering::Less => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    struct AddExamples;
+
+    #[async_trait]
+    impl TemplateRefiner for AddExamples {
+        async fn propose(&self, current: &PromptTemplate, _clusters: &[RejectionCluster]) -> Result<PromptTemplate> {
+            LlmTemplateRefiner::parse_proposal(
+                current,
+                r#"```json
+{"persona": "You are thorough.", "template": "{task}\nInclude worked examples.", "rationale": "add examples"}
+```"#,
+            )
+        }
+    }
+
+    /// 模板要求给出示例时通过
+    struct ExampleCritic;
+
+    #[async_trait]
+    impl PromptEvaluator for ExampleCritic {
+        async fn evaluate(&self, template: &PromptTemplate, task: &str) -> Result<Verdict> {
+            let verdict = if template.render(task).contains("examples") {
+                Verdict::approved(Uuid::nil(), 0.9, "good")
+            } else {
+                Verdict::rejected(Uuid::nil(), 0.3, "missing examples", Vec::new())
+            };
+            Ok(verdict.with_template(template))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refinement_promotes_winning_template() {
+        let registry = Arc::new(TemplateRegistry::in_memory());
+        let current = registry
+            .register(PromptTemplate::new("docs", "You write docs.", "Document: {task}"))
+            .unwrap();
+        let rejected = |reason: &str| {
+            Verdict::rejected(Uuid::new_v4(), 0.2, reason, vec!["Add usage examples".to_string()])
+                .with_template(&current)
+        };
+        let verdicts = vec![
+            rejected("No examples of usage"),
+            rejected("Missing usage examples"),
+            rejected("no usage examples given"),
+            rejected("Wrong function signature documented"),
+        ];
+
+        let clusters = cluster_rejections(&verdicts, 0.35);
+        assert_eq!(clusters.iter().map(RejectionCluster::size).collect::<Vec<_>>(), vec![3, 1]);
+
+        let job = RefinementJob::new(registry.clone(), Arc::new(AddExamples), Arc::new(ExampleCritic));
+        let report = job
+            .run("docs", &verdicts, &["parse()".to_string(), "render()".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(report.clusters.len(), 1);
+        assert_eq!((report.baseline.passed, report.challenger.passed), (0, 2));
+        assert!(report.promoted);
+        let active = registry.active("docs").unwrap();
+        assert_eq!(active.version, 2);
+        assert_eq!(active.rationale.as_deref(), Some("add examples"));
+
+        // 新版本尚无驳回，不再改进
+        let again = job.run("docs", &verdicts, &["parse()".to_string()]).await.unwrap();
+        assert!(again.clusters.is_empty() && again.candidate.is_none());
+    }
+}
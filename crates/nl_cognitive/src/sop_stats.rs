@@ -0,0 +1,240 @@
+//! SOP 执行统计与自动退役
+//!
+//! 由 `SopRunFinished` 事件累计每个工作流的执行统计（成功率、平均耗时、最近一次失败原因）。
+//! 最近若干次执行的滚动成功率低于阈值时，工作流被退役：编排器不再把子任务派发给它，
+//! 改由 System 2 (MCTS) 处理，直到工作流被重新注册或手动恢复。
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::{Event, EventKind};
+
+/// `SopRunFinished` 载荷
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SopRunOutcome {
+    /// 执行 ID
+    pub run: Uuid,
+    /// 工作流名称
+    pub workflow: String,
+    /// 是否成功
+    pub success: bool,
+    /// 总耗时（毫秒）
+    pub elapsed_ms: u64,
+    /// 失败模式（失败节点与错误摘要）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+/// 退役策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetirementPolicy {
+    /// 滚动窗口大小（最近执行次数）
+    pub window: usize,
+    /// 窗口内至少执行多少次才评估
+    pub min_runs: usize,
+    /// 滚动成功率低于此值时退役
+    pub min_success_rate: f64,
+}
+
+impl Default for RetirementPolicy {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            min_runs: 5,
+            min_success_rate: 0.6,
+        }
+    }
+}
+
+/// 单个工作流的执行统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowStats {
+    /// 工作流名称
+    pub workflow: String,
+    /// 总执行次数
+    pub runs: u64,
+    /// 成功次数
+    pub successes: u64,
+    /// 累计耗时（毫秒）
+    pub total_elapsed_ms: u64,
+    /// 最近的执行结果（滚动窗口）
+    pub recent: VecDeque<bool>,
+    /// 最近一次失败原因
+    pub last_failure: Option<String>,
+    /// 最近一次执行时间
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// 是否已退役
+    pub retired: bool,
+}
+
+impl WorkflowStats {
+    /// 总成功率
+    pub fn success_rate(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.successes as f64 / self.runs as f64
+        }
+    }
+
+    /// 滚动窗口成功率
+    pub fn rolling_success_rate(&self) -> f64 {
+        if self.recent.is_empty() {
+            0.0
+        } else {
+            self.recent.iter().filter(|s| **s).count() as f64 / self.recent.len() as f64
+        }
+    }
+
+    /// 平均耗时（毫秒）
+    pub fn average_elapsed_ms(&self) -> u64 {
+        self.total_elapsed_ms.checked_div(self.runs).unwrap_or(0)
+    }
+}
+
+/// 工作流统计表
+#[derive(Debug, Clone, Default)]
+pub struct SopStats {
+    policy: RetirementPolicy,
+    workflows: HashMap<String, WorkflowStats>,
+}
+
+impl SopStats {
+    /// 创建统计表
+    pub fn new(policy: RetirementPolicy) -> Self {
+        Self {
+            policy,
+            workflows: HashMap::new(),
+        }
+    }
+
+    /// 退役策略
+    pub fn policy(&self) -> &RetirementPolicy {
+        &self.policy
+    }
+
+    /// 应用 `SopRunFinished` 事件，本次导致工作流退役时返回 `true`
+    pub fn apply(&mut self, event: &Event) -> bool {
+        if event.kind != EventKind::SopRunFinished {
+            return false;
+        }
+        match serde_json::from_value::<SopRunOutcome>(event.payload.clone()) {
+            Ok(outcome) => self.record(&outcome, event.timestamp),
+            Err(_) => false,
+        }
+    }
+
+    /// 从事件流重建统计
+    pub fn replay<'a>(&mut self, events: impl IntoIterator<Item = &'a Event>) {
+        for event in events {
+            self.apply(event);
+        }
+    }
+
+    /// 记录一次执行结果，本次导致工作流退役时返回 `true`
+    pub fn record(&mut self, outcome: &SopRunOutcome, at: DateTime<Utc>) -> bool {
+        let stats = self
+            .workflows
+            .entry(outcome.workflow.clone())
+            .or_insert_with(|| WorkflowStats {
+                workflow: outcome.workflow.clone(),
+                ..Default::default()
+            });
+        stats.runs += 1;
+        stats.total_elapsed_ms += outcome.elapsed_ms;
+        stats.last_run_at = Some(at);
+        if outcome.success {
+            stats.successes += 1;
+        } else {
+            stats.last_failure = outcome.failure.clone();
+        }
+        stats.recent.push_back(outcome.success);
+        while stats.recent.len() > self.policy.window.max(1) {
+            stats.recent.pop_front();
+        }
+
+        if stats.retired
+            || stats.recent.len() < self.policy.min_runs
+            || stats.rolling_success_rate() >= self.policy.min_success_rate
+        {
+            return false;
+        }
+        stats.retired = true;
+        tracing::warn!(
+            "SOP workflow {} retired: rolling success rate {:.0}% over {} runs",
+            stats.workflow,
+            stats.rolling_success_rate() * 100.0,
+            stats.recent.len()
+        );
+        true
+    }
+
+    /// 工作流是否已退役
+    pub fn is_retired(&self, workflow: &str) -> bool {
+        self.workflows.get(workflow).is_some_and(|s| s.retired)
+    }
+
+    /// 恢复工作流并清空滚动窗口（重新注册时调用）
+    pub fn reinstate(&mut self, workflow: &str) {
+        if let Some(stats) = self.workflows.get_mut(workflow) {
+            stats.retired = false;
+            stats.recent.clear();
+        }
+    }
+
+    /// 单个工作流的统计
+    pub fn get(&self, workflow: &str) -> Option<&WorkflowStats> {
+        self.workflows.get(workflow)
+    }
+
+    /// 全部统计（按名称排序）
+    pub fn all(&self) -> Vec<&WorkflowStats> {
+        let mut all: Vec<_> = self.workflows.values().collect();
+        all.sort_by(|a, b| a.workflow.cmp(&b.workflow));
+        all
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(success: bool) -> SopRunOutcome {
+        SopRunOutcome {
+            run: Uuid::new_v4(),
+            workflow: "deploy".to_string(),
+            success,
+            elapsed_ms: 100,
+            failure: (!success).then(|| "build: exit 1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_rolling_failures_retire_workflow() {
+        let mut stats = SopStats::new(RetirementPolicy {
+            window: 4,
+            min_runs: 4,
+            min_success_rate: 0.5,
+        });
+        for success in [true, true, false] {
+            assert!(!stats.record(&outcome(success), Utc::now()));
+        }
+        // 4 次中 2 次成功，恰好达到阈值
+        assert!(!stats.record(&outcome(false), Utc::now()));
+        assert!(stats.record(&outcome(false), Utc::now()));
+        assert!(stats.is_retired("deploy"));
+
+        let deploy = stats.get("deploy").unwrap();
+        assert_eq!(deploy.runs, 5);
+        assert_eq!(deploy.average_elapsed_ms(), 100);
+        assert_eq!(deploy.last_failure.as_deref(), Some("build: exit 1"));
+        assert!((deploy.success_rate() - 0.4).abs() < 1e-9);
+
+        stats.reinstate("deploy");
+        assert!(!stats.is_retired("deploy"));
+        assert!(stats.get("deploy").unwrap().recent.is_empty());
+    }
+}
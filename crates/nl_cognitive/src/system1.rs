@@ -3,11 +3,17 @@
 //! System 1: 高频任务 DAG 工作流固化引擎。
 
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use nl_core::Result;
+use nl_core::sop_view::{snippet, SopNodeUpdate, SopPlanNode, SopRunPlan};
+use nl_core::{Event, EventKind, NeuroLoomError, Result};
+use nl_durable::{CancellationToken, ConflictPolicy, EventBus, ImportStats};
+
+use crate::sop_stats::{RetirementPolicy, SopRunOutcome, SopStats, WorkflowStats};
 
 /// SOP 节点
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +47,20 @@ pub enum SopAction {
     Script { language: String, code: String },
 }
 
+impl SopAction {
+    /// 动作类型名称
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SopAction::ExecuteCommand { .. } => "ExecuteCommand",
+            SopAction::CallLLM { .. } => "CallLLM",
+            SopAction::Condition { .. } => "Condition",
+            SopAction::Parallel { .. } => "Parallel",
+            SopAction::Wait { .. } => "Wait",
+            SopAction::Script { .. } => "Script",
+        }
+    }
+}
+
 /// SOP 工作流
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SopWorkflow {
@@ -85,6 +105,28 @@ impl SopWorkflow {
     pub fn get_node(&self, id: &Uuid) -> Option<&SopNode> {
         self.nodes.get(id)
     }
+
+    /// 执行计划（`SopRunStarted` 载荷）
+    pub fn run_plan(&self, run: Uuid) -> SopRunPlan {
+        let mut nodes: Vec<SopPlanNode> = self
+            .nodes
+            .values()
+            .map(|node| SopPlanNode {
+                id: node.id,
+                name: node.name.clone(),
+                action: node.action.kind().to_string(),
+                next: node.next.clone(),
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+        SopRunPlan {
+            run,
+            workflow_id: self.id,
+            workflow: self.name.clone(),
+            entry: self.entry,
+            nodes,
+        }
+    }
 }
 
 /// SOP 执行上下文
@@ -106,6 +148,10 @@ pub struct SopEngine {
     workflows: HashMap<Uuid, SopWorkflow>,
     /// 名称索引
     name_index: HashMap<String, Uuid>,
+    /// 执行事件总线
+    bus: Option<Arc<EventBus>>,
+    /// 执行统计（由 `SopRunFinished` 事件累计，`fork` 出的引擎共享同一份）
+    stats: Arc<Mutex<SopStats>>,
 }
 
 impl SopEngine {
@@ -114,11 +160,42 @@ impl SopEngine {
         Self {
             workflows: HashMap::new(),
             name_index: HashMap::new(),
+            bus: None,
+            stats: Arc::new(Mutex::new(SopStats::default())),
         }
     }
 
-    /// 注册工作流
+    /// 执行时发布 `SopRunStarted`、逐节点的 `SopNode*` 与 `SopRunFinished` 事件
+    pub fn with_event_bus(mut self, bus: Arc<EventBus>) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// 设置自动退役策略
+    pub fn with_retirement_policy(mut self, policy: RetirementPolicy) -> Self {
+        self.stats = Arc::new(Mutex::new(SopStats::new(policy)));
+        self
+    }
+
+    /// 复制当前注册的工作流，执行统计与退役状态与原引擎共享
+    pub fn fork(&self) -> Self {
+        Self {
+            workflows: self.workflows.clone(),
+            name_index: self.name_index.clone(),
+            bus: self.bus.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// 注册工作流（重新注册已退役的工作流会恢复它；新名称发布 `SopRegistered`）
     pub fn register(&mut self, workflow: SopWorkflow) {
+        if !self.name_index.contains_key(&workflow.name) {
+            if let Some(bus) = &self.bus {
+                let payload = serde_json::json!({ "workflow": workflow.name, "nodes": workflow.nodes.len() });
+                bus.publish(&Event::new(EventKind::SopRegistered, workflow.id, payload));
+            }
+        }
+        self.stats.lock().unwrap().reinstate(&workflow.name);
         self.name_index.insert(workflow.name.clone(), workflow.id);
         self.workflows.insert(workflow.id, workflow);
     }
@@ -130,8 +207,37 @@ impl SopEngine {
             .and_then(|id| self.workflows.get(id))
     }
 
+    /// 通过名称查找未退役的工作流
+    pub fn find_active(&self, name: &str) -> Option<&SopWorkflow> {
+        self.find(name).filter(|w| !self.is_retired(&w.name))
+    }
+
+    /// 工作流是否已退役
+    pub fn is_retired(&self, name: &str) -> bool {
+        self.stats.lock().unwrap().is_retired(name)
+    }
+
+    /// 手动恢复已退役的工作流
+    pub fn reinstate(&self, name: &str) {
+        self.stats.lock().unwrap().reinstate(name);
+    }
+
+    /// 全部工作流的执行统计（按名称排序）
+    pub fn stats(&self) -> Vec<WorkflowStats> {
+        self.stats.lock().unwrap().all().into_iter().cloned().collect()
+    }
+
     /// 执行工作流
     pub async fn execute(&self, workflow_id: &Uuid) -> Result<SopContext> {
+        self.execute_cancellable(workflow_id, &CancellationToken::new()).await
+    }
+
+    /// 可取消地执行工作流：取消时中断当前动作，不再进入后续节点
+    pub async fn execute_cancellable(
+        &self,
+        workflow_id: &Uuid,
+        cancel: &CancellationToken,
+    ) -> Result<SopContext> {
         let workflow = self.workflows.get(workflow_id).ok_or_else(|| {
             nl_core::NeuroLoomError::Unknown(format!("Workflow not found: {}", workflow_id))
         })?;
@@ -142,13 +248,85 @@ impl SopEngine {
             history: Vec::new(),
             results: HashMap::new(),
         };
+        let run = Uuid::new_v4();
+        self.publish(
+            EventKind::SopRunStarted,
+            workflow.id,
+            run,
+            serde_json::to_value(workflow.run_plan(run))?,
+        );
 
+        let started = Instant::now();
+        let result = self.run_nodes(workflow, run, &mut ctx, cancel).await;
+        let outcome = SopRunOutcome {
+            run,
+            workflow: workflow.name.clone(),
+            success: result.is_ok(),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            failure: result.as_ref().err().map(|e| {
+                let node = ctx
+                    .history
+                    .last()
+                    .and_then(|id| workflow.get_node(id))
+                    .map(|n| n.name.as_str())
+                    .unwrap_or("-");
+                snippet(&format!("{}: {}", node, e))
+            }),
+        };
+        let event = Event::new(EventKind::SopRunFinished, workflow.id, serde_json::to_value(&outcome)?)
+            .with_correlation(run);
+        self.stats.lock().unwrap().apply(&event);
+        if let Some(bus) = &self.bus {
+            bus.publish(&event);
+        }
+
+        result.map(|_| ctx)
+    }
+
+    /// 依次执行节点，节点结果写入 `ctx`
+    async fn run_nodes(
+        &self,
+        workflow: &SopWorkflow,
+        run: Uuid,
+        ctx: &mut SopContext,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
         // 执行工作流
         while let Some(node) = workflow.get_node(&ctx.current_node) {
             ctx.history.push(ctx.current_node);
+            let update = |elapsed_ms: Option<u64>, detail: Option<String>| SopNodeUpdate {
+                run,
+                node: node.id,
+                name: node.name.clone(),
+                elapsed_ms,
+                snippet: detail,
+            };
+            self.publish(EventKind::SopNodeStarted, workflow.id, run, serde_json::to_value(update(None, None))?);
+            let started = Instant::now();
 
             // 执行动作
-            let result = self.execute_action(&node.action, &ctx).await?;
+            let result = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => Err(NeuroLoomError::Cancelled(format!(
+                    "workflow {} stopped at step {}",
+                    workflow.name,
+                    ctx.history.len()
+                ))),
+                result = self.execute_action(&node.action, &ctx) => result,
+            };
+            let elapsed_ms = Some(started.elapsed().as_millis() as u64);
+            let result = match result {
+                Ok(result) => {
+                    let payload = update(elapsed_ms, Some(snippet(&result)));
+                    self.publish(EventKind::SopNodeCompleted, workflow.id, run, serde_json::to_value(payload)?);
+                    result
+                }
+                Err(e) => {
+                    let payload = update(elapsed_ms, Some(snippet(&e.to_string())));
+                    self.publish(EventKind::SopNodeFailed, workflow.id, run, serde_json::to_value(payload)?);
+                    return Err(e);
+                }
+            };
             ctx.results.insert(ctx.current_node, result);
 
             // 移动到下一个节点
@@ -158,7 +336,14 @@ impl SopEngine {
             ctx.current_node = node.next[0];
         }
 
-        Ok(ctx)
+        Ok(())
+    }
+
+    /// 发布执行事件（未配置事件总线时忽略）
+    fn publish(&self, kind: EventKind, workflow_id: Uuid, run: Uuid, payload: serde_json::Value) {
+        if let Some(bus) = &self.bus {
+            bus.publish(&Event::new(kind, workflow_id, payload).with_correlation(run));
+        }
     }
 
     /// 执行单个动作
@@ -184,6 +369,44 @@ impl SopEngine {
     pub fn count(&self) -> usize {
         self.workflows.len()
     }
+
+    /// 全部工作流
+    pub fn workflows(&self) -> Vec<&SopWorkflow> {
+        self.workflows.values().collect()
+    }
+
+    /// 导入工作流（工作区包）
+    ///
+    /// 按名称判定冲突，`Merge` 保留本地工作流并补齐缺失的变量与描述。
+    pub fn import(&mut self, workflows: Vec<SopWorkflow>, policy: ConflictPolicy) -> ImportStats {
+        let mut stats = ImportStats::default();
+        for workflow in workflows {
+            let Some(local_id) = self.name_index.get(&workflow.name).copied() else {
+                stats.added += 1;
+                self.register(workflow);
+                continue;
+            };
+            stats.record_conflict(policy);
+            match policy {
+                ConflictPolicy::Skip => {}
+                ConflictPolicy::Overwrite => {
+                    self.workflows.remove(&local_id);
+                    self.register(workflow);
+                }
+                ConflictPolicy::Merge => {
+                    if let Some(local) = self.workflows.get_mut(&local_id) {
+                        for (key, value) in workflow.variables {
+                            local.variables.entry(key).or_insert(value);
+                        }
+                        if local.description.is_empty() {
+                            local.description = workflow.description;
+                        }
+                    }
+                }
+            }
+        }
+        stats
+    }
 }
 
 impl Default for SopEngine {
@@ -1,16 +1,27 @@
 //! 认知引擎 - MCTS 引擎
 //!
 //! System 2: 蒙特卡洛树搜索，自适应算力推演。
+//!
+//! 搜索树可通过 `export_tree()` 导出为 JSON / Graphviz DOT 用于调试，
+//! `explain_best_path()` 经网关生成最佳路径的自然语言解释。
+//!
+//! 配置 `with_budget()` 后，搜索按 `BudgetController` 的决策收窄分支、切换便宜的推演模型或提前停止，
+//! `search_with_report()` 返回的结果附带预算消耗报告。
 
 use std::collections::HashMap;
+use std::fmt::Write as _;
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use nl_core::Result;
+use nl_core::{NeuroLoomError, Result};
+use nl_durable::CancellationToken;
+use nl_llm::{LlmClient, PrimitiveRequest};
+
+use crate::budget::{BudgetController, BudgetDecision, BudgetReport};
 
 /// MCTS 节点
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MctsNode {
     /// 节点 ID
     pub id: Uuid,
@@ -62,6 +73,62 @@ impl MctsNode {
     }
 }
 
+/// 导出的搜索树
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MctsTree {
+    /// 根节点
+    pub root: Option<Uuid>,
+    /// 全部节点（广度优先顺序）
+    pub nodes: Vec<MctsNode>,
+    /// 最佳路径（从根节点起，每层取访问次数最多的子节点）
+    pub best_path: Vec<Uuid>,
+}
+
+impl MctsTree {
+    /// 状态在 DOT 标签中的最大字符数
+    const DOT_LABEL_CHARS: usize = 40;
+
+    /// 查找节点
+    pub fn node(&self, id: &Uuid) -> Option<&MctsNode> {
+        self.nodes.iter().find(|n| n.id == *id)
+    }
+
+    /// 导出为 JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// 导出为 Graphviz DOT，最佳路径上的节点与边加粗
+    pub fn to_dot(&self) -> String {
+        let on_path = |id: &Uuid| self.best_path.contains(id);
+        let mut dot = String::from("digraph mcts {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for node in &self.nodes {
+            let mut state: String = node.state.chars().take(Self::DOT_LABEL_CHARS).collect();
+            if node.state.chars().count() > Self::DOT_LABEL_CHARS {
+                state.push('…');
+            }
+            let style = if on_path(&node.id) { ", style=bold, color=blue" } else { "" };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\\nvisits={} avg={:.3}\"{}];",
+                node.id,
+                state.replace('\\', "\\\\").replace('"', "\\\""),
+                node.visits,
+                node.average_reward(),
+                style
+            );
+        }
+        for node in &self.nodes {
+            for child in &node.children {
+                let style = if on_path(&node.id) && on_path(child) { " [style=bold, color=blue]" } else { "" };
+                let _ = writeln!(dot, "    \"{}\" -> \"{}\"{};", node.id, child, style);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
 /// MCTS 配置
 #[derive(Debug, Clone)]
 pub struct MctsConfig {
@@ -75,6 +142,12 @@ pub struct MctsConfig {
     pub early_stop_threshold: f64,
     /// 挫败指数阈值 (连续失败多少次触发熔断)
     pub frustration_threshold: u32,
+    /// 推演模型
+    pub rollout_model: Option<String>,
+    /// 预算紧张时改用的便宜推演模型
+    pub cheap_rollout_model: Option<String>,
+    /// 收窄搜索时每层参与选择的子节点数（按平均奖励取前若干个）
+    pub narrowed_branching: usize,
 }
 
 impl Default for MctsConfig {
@@ -85,6 +158,9 @@ impl Default for MctsConfig {
             max_depth: 10,
             early_stop_threshold: 0.95,
             frustration_threshold: 50,
+            rollout_model: None,
+            cheap_rollout_model: None,
+            narrowed_branching: 2,
         }
     }
 }
@@ -99,6 +175,21 @@ pub struct MctsEngine {
     root: Option<Uuid>,
     /// 挫败计数器
     frustration_count: u32,
+    /// 预算控制器（未设置时不限制）
+    budget: Option<BudgetController>,
+    /// 当前的预算决策
+    budget_decision: BudgetDecision,
+}
+
+/// 搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MctsSearchResult {
+    /// 最佳动作
+    pub best_action: Option<String>,
+    /// 完成的迭代数
+    pub iterations: u32,
+    /// 预算消耗（未设置预算时为空）
+    pub budget: Option<BudgetReport>,
 }
 
 impl MctsEngine {
@@ -109,9 +200,33 @@ impl MctsEngine {
             nodes: HashMap::new(),
             root: None,
             frustration_count: 0,
+            budget: None,
+            budget_decision: BudgetDecision::Continue,
         }
     }
 
+    /// 为搜索设置预算
+    pub fn with_budget(mut self, budget: BudgetController) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// 记录推演过程中一次 LLM 调用的用量
+    pub fn record_usage(&mut self, model: &str, input_tokens: u64, output_tokens: u64) {
+        if let Some(budget) = &mut self.budget {
+            budget.record(model, input_tokens, output_tokens);
+        }
+    }
+
+    /// 当前应使用的推演模型：预算紧张时切换到便宜模型
+    pub fn rollout_model(&self) -> Option<&str> {
+        let cheap = self.budget_decision >= BudgetDecision::CheapRollout;
+        cheap
+            .then_some(self.config.cheap_rollout_model.as_deref())
+            .flatten()
+            .or(self.config.rollout_model.as_deref())
+    }
+
     /// 创建默认配置的引擎
     pub fn default_engine() -> Self {
         Self::new(MctsConfig::default())
@@ -127,11 +242,40 @@ impl MctsEngine {
 
     /// 执行 MCTS 搜索
     pub async fn search(&mut self) -> Result<Option<String>> {
+        self.search_cancellable(&CancellationToken::new()).await
+    }
+
+    /// 可取消的 MCTS 搜索：每轮迭代前检查令牌，取消后不再推演
+    pub async fn search_cancellable(&mut self, cancel: &CancellationToken) -> Result<Option<String>> {
+        Ok(self.search_with_report(cancel).await?.best_action)
+    }
+
+    /// 执行搜索并返回迭代数与预算消耗
+    pub async fn search_with_report(&mut self, cancel: &CancellationToken) -> Result<MctsSearchResult> {
         let root_id = self.root.ok_or_else(|| {
             nl_core::NeuroLoomError::Unknown("Root not set".to_string())
         })?;
 
-        for iteration in 0..self.config.max_iterations {
+        let mut iterations = 0;
+        while iterations < self.config.max_iterations {
+            if cancel.is_cancelled() {
+                return Err(NeuroLoomError::Cancelled(format!(
+                    "MCTS search stopped after {} iterations",
+                    iterations
+                )));
+            }
+
+            if let Some(budget) = &mut self.budget {
+                let decision = budget.decide();
+                if decision != self.budget_decision {
+                    tracing::debug!("MCTS budget decision: {:?} after {} iterations", decision, iterations);
+                }
+                self.budget_decision = decision;
+                if decision == BudgetDecision::Stop {
+                    break;
+                }
+            }
+
             // 选择
             let selected = self.select(root_id)?;
 
@@ -143,11 +287,15 @@ impl MctsEngine {
 
             // 回溯
             self.backpropagate(expanded, reward);
+            iterations += 1;
+            if let Some(budget) = &mut self.budget {
+                budget.end_iteration();
+            }
 
             // 早停检查
             if let Some(node) = self.nodes.get(&root_id) {
                 if node.average_reward() >= self.config.early_stop_threshold {
-                    return Ok(self.best_action());
+                    break;
                 }
             }
 
@@ -157,7 +305,11 @@ impl MctsEngine {
             }
         }
 
-        Ok(self.best_action())
+        Ok(MctsSearchResult {
+            best_action: self.best_action(),
+            iterations,
+            budget: self.budget.as_ref().map(|b| b.report(self.budget_decision)),
+        })
     }
 
     /// 选择阶段
@@ -173,11 +325,20 @@ impl MctsEngine {
                 return Ok(current);
             }
 
-            // 选择 UCB1 最大的子节点
-            let best_child = node
+            // 预算紧张时只在平均奖励最高的少数子节点中选择
+            let mut candidates: Vec<(&Uuid, &MctsNode)> = node
                 .children
                 .iter()
                 .filter_map(|id| self.nodes.get(id).map(|n| (id, n)))
+                .collect();
+            if self.budget_decision >= BudgetDecision::Narrow {
+                candidates.sort_by(|(_, a), (_, b)| b.average_reward().total_cmp(&a.average_reward()));
+                candidates.truncate(self.config.narrowed_branching.max(1));
+            }
+
+            // 选择 UCB1 最大的子节点
+            let best_child = candidates
+                .into_iter()
                 .max_by(|(_, a), (_, b)| {
                     a.ucb1(self.config.exploration_constant, node.visits)
                         .partial_cmp(&b.ucb1(self.config.exploration_constant, node.visits))
@@ -229,10 +390,166 @@ impl MctsEngine {
         })
     }
 
+    /// 在指定节点下添加子状态，返回子节点 ID
+    pub fn add_child(&mut self, parent: Uuid, state: impl Into<String>) -> Result<Uuid> {
+        let mut child = MctsNode::new(state);
+        child.parent = Some(parent);
+        let id = child.id;
+        self.nodes
+            .get_mut(&parent)
+            .ok_or_else(|| NeuroLoomError::Unknown(format!("Node not found: {}", parent)))?
+            .children
+            .push(id);
+        self.nodes.insert(id, child);
+        Ok(id)
+    }
+
+    /// 最佳路径：从根节点起，每层取访问次数最多的子节点
+    pub fn best_path(&self) -> Vec<Uuid> {
+        let mut path = Vec::new();
+        let mut current = self.root.and_then(|id| self.nodes.get(&id));
+        while let Some(node) = current {
+            path.push(node.id);
+            current = node
+                .children
+                .iter()
+                .filter_map(|id| self.nodes.get(id))
+                .max_by(|a, b| a.visits.cmp(&b.visits));
+        }
+        path
+    }
+
+    /// 导出搜索树
+    pub fn export_tree(&self) -> MctsTree {
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        let mut queue: std::collections::VecDeque<Uuid> = self.root.into_iter().collect();
+        while let Some(id) = queue.pop_front() {
+            if let Some(node) = self.nodes.get(&id) {
+                queue.extend(node.children.iter().copied());
+                nodes.push(node.clone());
+            }
+        }
+        MctsTree {
+            root: self.root,
+            nodes,
+            best_path: self.best_path(),
+        }
+    }
+
+    /// 经网关生成最佳路径的自然语言解释
+    ///
+    /// 提示词包含路径上每一步的访问次数、平均奖励以及被放弃的同层备选。
+    pub async fn explain_best_path(&self, client: &LlmClient, model: &str) -> Result<String> {
+        let path = self.best_path();
+        if path.is_empty() {
+            return Err(NeuroLoomError::Unknown("Root not set".to_string()));
+        }
+
+        let mut prompt = String::from("MCTS best path (root first):\n");
+        for (depth, id) in path.iter().enumerate() {
+            let node = &self.nodes[id];
+            let _ = writeln!(
+                prompt,
+                "{}. {} (visits={}, avg_reward={:.3})",
+                depth,
+                node.state,
+                node.visits,
+                node.average_reward()
+            );
+            let Some(parent) = node.parent.and_then(|p| self.nodes.get(&p)) else {
+                continue;
+            };
+            for sibling in parent.children.iter().filter(|c| *c != id).filter_map(|c| self.nodes.get(c)) {
+                let _ = writeln!(
+                    prompt,
+                    "   rejected alternative: {} (visits={}, avg_reward={:.3})",
+                    sibling.state,
+                    sibling.visits,
+                    sibling.average_reward()
+                );
+            }
+        }
+
+        let mut req = PrimitiveRequest::single_user_message(prompt).with_model(model);
+        req.system = Some(Self::EXPLAIN_PROMPT.to_string());
+        let response = client
+            .complete(&req)
+            .await
+            .map_err(|e| NeuroLoomError::LlmProvider(e.to_string()))?;
+        Ok(response.content)
+    }
+
+    /// 解释提示词
+    const EXPLAIN_PROMPT: &'static str =
+        "You explain Monte Carlo tree search decisions. Given the chosen path with visit counts \
+and average rewards, and the rejected alternatives at each step, explain concisely why this path \
+was preferred and how confident the search is.";
+
     /// 重置引擎
     pub fn reset(&mut self) {
         self.nodes.clear();
         self.root = None;
         self.frustration_count = 0;
+        self.budget_decision = BudgetDecision::Continue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_export_tree_follows_most_visited_path() {
+        let mut engine = MctsEngine::new(MctsConfig {
+            max_iterations: 10,
+            ..Default::default()
+        });
+        engine.set_root("root");
+        let root = Uuid::nil();
+        let a = engine.add_child(root, "refactor \"parser\"").unwrap();
+        let b = engine.add_child(root, "rewrite").unwrap();
+        let a1 = engine.add_child(a, "add tests").unwrap();
+        engine.backpropagate(a1, 1.0);
+        engine.backpropagate(a1, 0.5);
+        engine.backpropagate(b, 0.2);
+
+        let tree = engine.export_tree();
+        assert_eq!(tree.best_path, vec![root, a, a1]);
+        assert_eq!(tree.nodes.len(), 4);
+        assert_eq!(tree.node(&a).unwrap().visits, 2);
+
+        let json: MctsTree = serde_json::from_str(&tree.to_json().unwrap()).unwrap();
+        assert_eq!(json.best_path, tree.best_path);
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph mcts {"));
+        assert!(dot.contains("refactor \\\"parser\\\""));
+        assert!(dot.contains(&format!("\"{}\" -> \"{}\" [style=bold, color=blue];", a, a1)));
+        assert!(dot.contains(&format!("\"{}\" -> \"{}\";", root, b)));
+    }
+
+    #[tokio::test]
+    async fn test_budget_switches_rollout_model_then_stops() {
+        use crate::budget::SearchBudget;
+
+        let mut engine = MctsEngine::new(MctsConfig {
+            max_iterations: 10,
+            rollout_model: Some("strong".to_string()),
+            cheap_rollout_model: Some("cheap".to_string()),
+            ..Default::default()
+        })
+        .with_budget(BudgetController::new(SearchBudget::tokens(1_000)));
+        engine.set_root("root");
+        assert_eq!(engine.rollout_model(), Some("strong"));
+
+        engine.record_usage("strong", 700, 100);
+        let result = engine.search_with_report(&CancellationToken::new()).await.unwrap();
+        // 剩余 20%：改用便宜模型推演一轮，之后按估算的单轮开销停止
+        assert_eq!(engine.rollout_model(), Some("cheap"));
+        assert_eq!(result.iterations, 1);
+        let report = result.budget.unwrap();
+        assert_eq!(report.spent_tokens, 800);
+        assert!(report.stopped_for_budget);
+        assert_eq!(report.final_decision, BudgetDecision::Stop);
     }
 }
@@ -0,0 +1,169 @@
+//! Worker 提示词模板登记表
+//!
+//! 每个模板（按名称区分任务类别）保留全部版本，只有一个版本处于生效状态：
+//! - `register` 追加新版本（首个版本自动生效），`promote` 切换生效版本
+//! - 裁决记录生成草稿所用的模板版本（`Verdict::with_template`），自动改进任务据此按类别统计驳回原因
+//! - 指定文件路径时，每次变更写回 JSON 文件
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use nl_core::{NeuroLoomError, Result};
+
+/// 模板正文中任务描述的占位符
+pub const TASK_PLACEHOLDER: &str = "{task}";
+
+/// Worker 提示词模板
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    /// 模板名称（任务类别）
+    pub name: String,
+    /// 版本号，由登记表分配
+    #[serde(default)]
+    pub version: u32,
+    /// Worker 人设（系统提示词）
+    pub persona: String,
+    /// 任务提示词模板，`{task}` 处填入任务描述
+    pub template: String,
+    /// 修改说明（自动改进生成的版本带有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PromptTemplate {
+    /// 创建模板
+    pub fn new(name: impl Into<String>, persona: impl Into<String>, template: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: 0,
+            persona: persona.into(),
+            template: template.into(),
+            rationale: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 附上修改说明
+    pub fn with_rationale(mut self, rationale: impl Into<String>) -> Self {
+        self.rationale = Some(rationale.into());
+        self
+    }
+
+    /// `name@version`，记录在裁决上
+    pub fn id(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+
+    /// 填入任务描述（模板没有占位符时追加在末尾）
+    pub fn render(&self, task: &str) -> String {
+        if self.template.contains(TASK_PLACEHOLDER) {
+            self.template.replace(TASK_PLACEHOLDER, task)
+        } else {
+            format!("{}\n\n{}", self.template, task)
+        }
+    }
+}
+
+/// 从 `name@version` 中取出模板名称
+pub fn template_name(id: &str) -> &str {
+    id.rsplit_once('@').map_or(id, |(name, _)| name)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryState {
+    /// 名称 -> 全部版本（按版本号递增）
+    versions: BTreeMap<String, Vec<PromptTemplate>>,
+    /// 名称 -> 生效版本
+    active: BTreeMap<String, u32>,
+}
+
+/// 提示词模板登记表
+#[derive(Debug, Default)]
+pub struct TemplateRegistry {
+    path: Option<PathBuf>,
+    state: RwLock<RegistryState>,
+}
+
+impl TemplateRegistry {
+    /// 仅保存在内存中
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// 从文件加载（文件不存在时为空）
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let state = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)?
+        } else {
+            RegistryState::default()
+        };
+        Ok(Self {
+            path: Some(path),
+            state: RwLock::new(state),
+        })
+    }
+
+    /// 持久化文件路径
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 登记新版本并返回带版本号的模板；该名称的首个版本自动生效
+    pub fn register(&self, mut template: PromptTemplate) -> Result<PromptTemplate> {
+        let mut state = self.state.write().unwrap();
+        let versions = state.versions.entry(template.name.clone()).or_default();
+        template.version = versions.last().map_or(1, |t| t.version + 1);
+        versions.push(template.clone());
+        state.active.entry(template.name.clone()).or_insert(template.version);
+        self.save(&state)?;
+        Ok(template)
+    }
+
+    /// 切换生效版本
+    pub fn promote(&self, name: &str, version: u32) -> Result<()> {
+        let mut state = self.state.write().unwrap();
+        let exists = state
+            .versions
+            .get(name)
+            .is_some_and(|versions| versions.iter().any(|t| t.version == version));
+        if !exists {
+            return Err(NeuroLoomError::Unknown(format!("template {}@{} not found", name, version)));
+        }
+        state.active.insert(name.to_string(), version);
+        self.save(&state)
+    }
+
+    /// 当前生效的版本
+    pub fn active(&self, name: &str) -> Option<PromptTemplate> {
+        let state = self.state.read().unwrap();
+        let version = *state.active.get(name)?;
+        state.versions.get(name)?.iter().find(|t| t.version == version).cloned()
+    }
+
+    /// 全部版本
+    pub fn history(&self, name: &str) -> Vec<PromptTemplate> {
+        self.state.read().unwrap().versions.get(name).cloned().unwrap_or_default()
+    }
+
+    /// 已登记的模板名称
+    pub fn names(&self) -> Vec<String> {
+        self.state.read().unwrap().versions.keys().cloned().collect()
+    }
+
+    fn save(&self, state: &RegistryState) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(state)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+//! 工具结果截断与按需展开
+//!
+//! 文件内容、测试日志等工具输出动辄撑爆提示词。超过 `TruncationPolicy` 限制的输出完整存入产物库，
+//! 提示词中只保留开头与结尾若干行，中间替换为一行标记，注明省略的行数与产物引用（`artifact://<hash>`）。
+//! Worker 需要被省略的部分时调用本地工具 `expand_artifact` 按行号区间取回，
+//! 在本地读产物库完成，不需要再经过一轮 LLM 调用。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use nl_core::artifact::{is_artifact_hash, parse_artifact_uri};
+use nl_core::{ArtifactKind, NeuroLoomError, Result};
+use nl_durable::ArtifactStore;
+use nl_hap::mcp::McpTool;
+
+/// 展开工具名
+pub const EXPAND_ARTIFACT_TOOL: &str = "expand_artifact";
+
+/// 截断策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TruncationPolicy {
+    /// 不截断的最大行数
+    pub max_lines: usize,
+    /// 不截断的最大字节数
+    pub max_bytes: usize,
+    /// 截断时保留的开头行数
+    pub head_lines: usize,
+    /// 截断时保留的结尾行数
+    pub tail_lines: usize,
+    /// 保留行的最大字符数（超长的行截到此长度）
+    pub max_line_chars: usize,
+    /// 单次展开的最大行数
+    pub max_expand_lines: usize,
+}
+
+impl Default for TruncationPolicy {
+    fn default() -> Self {
+        Self {
+            max_lines: 200,
+            max_bytes: 16 * 1024,
+            head_lines: 80,
+            tail_lines: 40,
+            max_line_chars: 400,
+            max_expand_lines: 200,
+        }
+    }
+}
+
+/// 工具结果截断器
+pub struct ToolOutputTruncator {
+    store: Arc<ArtifactStore>,
+    policy: TruncationPolicy,
+}
+
+impl ToolOutputTruncator {
+    /// 创建截断器，完整输出存入 `store`
+    pub fn new(store: Arc<ArtifactStore>, policy: TruncationPolicy) -> Self {
+        Self { store, policy }
+    }
+
+    /// 截断策略
+    pub fn policy(&self) -> &TruncationPolicy {
+        &self.policy
+    }
+
+    /// 超限时存入产物库并返回保留首尾的文本，否则原样返回
+    pub async fn truncate(&self, output: String, task_id: Option<Uuid>) -> Result<String> {
+        let lines: Vec<&str> = output.lines().collect();
+        if lines.len() <= self.policy.max_lines && output.len() <= self.policy.max_bytes {
+            return Ok(output);
+        }
+        let artifact = self.store.put(output.as_bytes(), ArtifactKind::Log, "text/plain", task_id).await?;
+
+        let head = self.policy.head_lines.min(lines.len());
+        let tail = self.policy.tail_lines.min(lines.len() - head);
+        let omitted = lines.len() - head - tail;
+        let clip = |line: &&str| match line.char_indices().nth(self.policy.max_line_chars) {
+            Some((end, _)) => format!("{}…", &line[..end]),
+            None => line.to_string(),
+        };
+        let mut kept: Vec<String> = lines[..head].iter().map(clip).collect();
+        kept.push(format!(
+            "[... {} of {} lines omitted, full output in {} (call {} with start_line/end_line to view) ...]",
+            omitted,
+            lines.len(),
+            artifact.uri(),
+            EXPAND_ARTIFACT_TOOL
+        ));
+        kept.extend(lines[lines.len() - tail..].iter().map(clip));
+        Ok(kept.join("\n"))
+    }
+
+    /// 展开产物的第 `start_line` 到 `end_line` 行（从 1 开始，含两端，最多 `max_expand_lines` 行）
+    pub async fn expand(&self, artifact: &str, start_line: usize, end_line: usize) -> Result<String> {
+        let hash = parse_artifact_uri(artifact)
+            .or(Some(artifact).filter(|h| is_artifact_hash(h)))
+            .ok_or_else(|| NeuroLoomError::Actor(format!("not an artifact reference: {}", artifact)))?;
+        let content = self
+            .store
+            .get(hash)
+            .await?
+            .ok_or_else(|| NeuroLoomError::Actor(format!("artifact {} not found", artifact)))?;
+        let content = String::from_utf8_lossy(&content);
+        let lines: Vec<&str> = content.lines().collect();
+
+        let start = start_line.max(1);
+        let end = end_line.min(lines.len()).min(start.saturating_add(self.policy.max_expand_lines.max(1) - 1));
+        if start > end {
+            return Err(NeuroLoomError::Actor(format!(
+                "lines {}-{} out of range ({} has {} lines)",
+                start_line,
+                end_line,
+                artifact,
+                lines.len()
+            )));
+        }
+        let mut out = vec![format!("[{} lines {}-{} of {}]", artifact, start, end, lines.len())];
+        out.extend(lines[start - 1..end].iter().map(|line| line.to_string()));
+        Ok(out.join("\n"))
+    }
+}
+
+/// 本地展开工具：`{"artifact": "artifact://<hash>", "start_line": 100, "end_line": 200}`
+pub struct ExpandArtifactTool {
+    truncator: Arc<ToolOutputTruncator>,
+}
+
+impl ExpandArtifactTool {
+    /// 创建展开工具
+    pub fn new(truncator: Arc<ToolOutputTruncator>) -> Self {
+        Self { truncator }
+    }
+}
+
+#[async_trait]
+impl McpTool for ExpandArtifactTool {
+    fn name(&self) -> &str {
+        EXPAND_ARTIFACT_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Show a line range of a tool output that was truncated into an artifact"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "artifact": {
+                    "type": "string",
+                    "description": "artifact://<hash> reference from the truncated output"
+                },
+                "start_line": { "type": "integer", "minimum": 1 },
+                "end_line": { "type": "integer", "minimum": 1 }
+            },
+            "required": ["artifact", "start_line", "end_line"]
+        })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let line = |key: &str| {
+            arguments[key]
+                .as_u64()
+                .map(|n| n as usize)
+                .ok_or_else(|| NeuroLoomError::Actor(format!("{} requires integer {}", EXPAND_ARTIFACT_TOOL, key)))
+        };
+        let artifact = arguments["artifact"]
+            .as_str()
+            .ok_or_else(|| NeuroLoomError::Actor(format!("{} requires artifact", EXPAND_ARTIFACT_TOOL)))?;
+        self.truncator.expand(artifact, line("start_line")?, line("end_line")?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_truncate_keeps_head_tail_and_expands_locally() {
+        let dir = std::env::temp_dir().join(format!("nl-tool-output-{}", Uuid::new_v4()));
+        let store = Arc::new(ArtifactStore::open(&dir).await.unwrap());
+        let policy = TruncationPolicy {
+            max_lines: 10,
+            head_lines: 3,
+            tail_lines: 2,
+            max_expand_lines: 5,
+            ..TruncationPolicy::default()
+        };
+        let truncator = Arc::new(ToolOutputTruncator::new(store, policy));
+
+        assert_eq!(truncator.truncate("short".to_string(), None).await.unwrap(), "short");
+        let log: String = (1..=50).map(|i| format!("line {}\n", i)).collect();
+        let truncated = truncator.truncate(log, None).await.unwrap();
+        let lines: Vec<&str> = truncated.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!((lines[0], lines[2], lines[4], lines[5]), ("line 1", "line 3", "line 49", "line 50"));
+        assert!(lines[3].starts_with("[... 45 of 50 lines omitted"));
+
+        let uri = lines[3].split_whitespace().find(|w| w.starts_with("artifact://")).unwrap();
+        let tool = ExpandArtifactTool::new(truncator.clone());
+        let expanded = tool.call(json!({ "artifact": uri, "start_line": 20, "end_line": 40 })).await.unwrap();
+        let expanded: Vec<&str> = expanded.lines().collect();
+        assert_eq!(expanded[0], format!("[{} lines 20-24 of 50]", uri));
+        assert_eq!((expanded[1], expanded[5]), ("line 20", "line 24"));
+        assert!(tool.call(json!({ "artifact": uri, "start_line": 60, "end_line": 70 })).await.is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+//! 任务产物
+//!
+//! 补丁、报告、截图等任务产物按内容哈希（SHA-256）存放在工作区的产物库中，
+//! 事件载荷与裁决只携带 `artifact://<hash>` 引用而不内联大块内容；
+//! 产物库回收时扫描事件中的这些引用，不再被任何事件引用的产物被删除。
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 产物引用的 URI 前缀
+pub const ARTIFACT_SCHEME: &str = "artifact://";
+
+/// 产物类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// 代码补丁
+    Patch,
+    /// 报告（测试报告、审议记录等）
+    Report,
+    /// 图片（截图等）
+    Image,
+    /// 日志
+    Log,
+    /// 其他
+    Other,
+}
+
+/// 产物元数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// 内容的 SHA-256（小写十六进制）
+    pub hash: String,
+    /// 产生该产物的任务
+    pub task_id: Option<Uuid>,
+    /// 类型
+    pub kind: ArtifactKind,
+    /// MIME 类型
+    pub media_type: String,
+    /// 字节数
+    pub size: u64,
+    /// 首次写入时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl Artifact {
+    /// 在事件与裁决中引用该产物的 URI
+    pub fn uri(&self) -> String {
+        artifact_uri(&self.hash)
+    }
+}
+
+/// 由内容哈希构造引用 URI
+pub fn artifact_uri(hash: &str) -> String {
+    format!("{}{}", ARTIFACT_SCHEME, hash)
+}
+
+/// 解析引用 URI，返回内容哈希
+pub fn parse_artifact_uri(uri: &str) -> Option<&str> {
+    uri.strip_prefix(ARTIFACT_SCHEME).filter(|hash| is_artifact_hash(hash))
+}
+
+/// 是否为合法的内容哈希（64 位小写十六进制）
+pub fn is_artifact_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// 收集 JSON 值中出现的全部产物引用（内容哈希）
+pub fn collect_artifact_refs(value: &serde_json::Value, refs: &mut HashSet<String>) {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Some(hash) = parse_artifact_uri(s) {
+                refs.insert(hash.to_string());
+            }
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(|v| collect_artifact_refs(v, refs)),
+        serde_json::Value::Object(fields) => fields.values().for_each(|v| collect_artifact_refs(v, refs)),
+        _ => {}
+    }
+}
@@ -0,0 +1,576 @@
+//! 空间画布数据模型与增量协议
+//!
+//! 守护进程与桌面端共用的画布模型：
+//! - 画布节点绑定到任务（计划与子任务）、SOP 执行及其步骤、记忆条目；边表示数据流向
+//!   （计划 → 子任务、前置子任务 → 后续子任务、SOP 步骤顺序、记忆 → 引用它的任务）
+//! - 守护进程的 `CanvasProjector` 把事件投影为增量，并为新节点自动分层布局；
+//!   桌面端拖动节点后回传 `NodeMoved`，此后该节点保持用户指定的位置
+//! - 增量带递增序号经 WebSocket（`GET /canvas`）推送，桌面端再转发为 Tauri 事件；
+//!   连接建立时先推送全量快照，`CanvasState::apply` 发现序号不连续时应重连取快照
+//! - `LlmResponseChunk` 事件（`entity_id` 为任务 ID，载荷 `{"text": ...}`）作为
+//!   `ContentStreaming` 追加到对应节点的内容
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::event::{Event, EventKind};
+use crate::sop_view::{snippet, SopNodeUpdate, SopRunView};
+
+/// 画布投影关心的事件类型名称（用于订阅过滤）
+pub const CANVAS_EVENT_KINDS: [&str; 12] = [
+    "task_planned",
+    "task_assigned",
+    "task_completed",
+    "task_cancelled",
+    "execution_failed",
+    "sop_run_started",
+    "sop_node_started",
+    "sop_node_completed",
+    "sop_node_failed",
+    "sop_run_finished",
+    "memory_stored",
+    "llm_response_chunk",
+];
+
+/// 自动布局的列宽
+pub const COLUMN_WIDTH: f64 = 280.0;
+
+/// 自动布局的行高
+pub const ROW_HEIGHT: f64 = 120.0;
+
+/// 节点内容保留的最大字符数（流式内容超出时丢弃最早的部分）
+pub const MAX_CONTENT_CHARS: usize = 4_000;
+
+/// 画布坐标
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+/// 节点绑定的守护进程实体
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CanvasBinding {
+    /// 任务计划或子任务
+    Task { id: Uuid },
+    /// 一次 SOP 执行
+    SopRun { run: Uuid },
+    /// SOP 执行中的一个步骤
+    SopStep { run: Uuid, node: Uuid },
+    /// 记忆条目
+    Memory { id: Uuid },
+}
+
+impl CanvasBinding {
+    /// 画布节点 ID（由绑定确定，重连、重放后保持不变）
+    pub fn node_id(&self) -> Uuid {
+        match self {
+            CanvasBinding::Task { id } | CanvasBinding::Memory { id } => *id,
+            CanvasBinding::SopRun { run } => *run,
+            CanvasBinding::SopStep { run, node } => Uuid::from_u128(run.as_u128().rotate_left(64) ^ node.as_u128()),
+        }
+    }
+}
+
+/// 节点状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanvasNodeStatus {
+    #[default]
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// 画布节点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanvasNode {
+    pub id: Uuid,
+    pub binding: CanvasBinding,
+    pub title: String,
+    pub position: Point,
+    pub status: CanvasNodeStatus,
+    /// 节点内容（结果、错误或流式输出）
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub content: String,
+}
+
+impl CanvasNode {
+    pub fn new(binding: CanvasBinding, title: impl Into<String>, position: Point) -> Self {
+        Self {
+            id: binding.node_id(),
+            binding,
+            title: title.into(),
+            position,
+            status: CanvasNodeStatus::Pending,
+            content: String::new(),
+        }
+    }
+}
+
+/// 数据流边
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanvasEdge {
+    pub id: Uuid,
+    pub source: Uuid,
+    pub target: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl CanvasEdge {
+    /// 创建边（ID 由两端确定）
+    pub fn new(source: Uuid, target: Uuid) -> Self {
+        Self {
+            id: Uuid::from_u128(source.as_u128().rotate_left(1) ^ target.as_u128()),
+            source,
+            target,
+            label: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+}
+
+/// 画布增量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CanvasDelta {
+    /// 全量快照（替换客户端的全部状态）
+    Snapshot { nodes: Vec<CanvasNode>, edges: Vec<CanvasEdge> },
+    NodeCreated { node: CanvasNode },
+    /// 节点被移动（桌面端回传时也使用此增量）
+    NodeMoved { id: Uuid, position: Point },
+    /// 状态或内容变化（`content` 替换原内容）
+    NodeUpdated {
+        id: Uuid,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<CanvasNodeStatus>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+    },
+    /// 追加流式内容
+    ContentStreaming { id: Uuid, chunk: String },
+    EdgeCreated { edge: CanvasEdge },
+}
+
+/// 带序号的增量消息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanvasMessage {
+    /// 递增序号（快照的序号为生成快照时的最后一个序号）
+    pub seq: u64,
+    pub delta: CanvasDelta,
+}
+
+/// 画布状态（守护进程与桌面端各持一份）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CanvasState {
+    nodes: BTreeMap<Uuid, CanvasNode>,
+    edges: BTreeMap<Uuid, CanvasEdge>,
+    seq: u64,
+}
+
+impl CanvasState {
+    /// 最后应用的序号
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn node(&self, id: Uuid) -> Option<&CanvasNode> {
+        self.nodes.get(&id)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &CanvasNode> {
+        self.nodes.values()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = &CanvasEdge> {
+        self.edges.values()
+    }
+
+    /// 应用消息；快照总是接受，其余消息的序号必须紧接当前序号，否则返回 `false`（应重新取快照）
+    pub fn apply(&mut self, message: &CanvasMessage) -> bool {
+        if !matches!(message.delta, CanvasDelta::Snapshot { .. }) && message.seq != self.seq + 1 {
+            return false;
+        }
+        self.apply_delta(&message.delta);
+        self.seq = message.seq;
+        true
+    }
+
+    /// 应用增量（不检查序号；指向未知节点的增量被忽略）
+    pub fn apply_delta(&mut self, delta: &CanvasDelta) {
+        match delta {
+            CanvasDelta::Snapshot { nodes, edges } => {
+                self.nodes = nodes.iter().map(|n| (n.id, n.clone())).collect();
+                self.edges = edges.iter().map(|e| (e.id, e.clone())).collect();
+            }
+            CanvasDelta::NodeCreated { node } => {
+                self.nodes.insert(node.id, node.clone());
+            }
+            CanvasDelta::NodeMoved { id, position } => {
+                if let Some(node) = self.nodes.get_mut(id) {
+                    node.position = *position;
+                }
+            }
+            CanvasDelta::NodeUpdated { id, status, content } => {
+                if let Some(node) = self.nodes.get_mut(id) {
+                    if let Some(status) = status {
+                        node.status = *status;
+                    }
+                    if let Some(content) = content {
+                        node.content = content.clone();
+                        truncate_front(&mut node.content);
+                    }
+                }
+            }
+            CanvasDelta::ContentStreaming { id, chunk } => {
+                if let Some(node) = self.nodes.get_mut(id) {
+                    node.content.push_str(chunk);
+                    truncate_front(&mut node.content);
+                }
+            }
+            CanvasDelta::EdgeCreated { edge } => {
+                self.edges.insert(edge.id, edge.clone());
+            }
+        }
+    }
+
+    /// 当前状态的全量快照
+    pub fn snapshot(&self) -> CanvasMessage {
+        CanvasMessage {
+            seq: self.seq,
+            delta: CanvasDelta::Snapshot {
+                nodes: self.nodes.values().cloned().collect(),
+                edges: self.edges.values().cloned().collect(),
+            },
+        }
+    }
+}
+
+/// 超出上限时丢弃最早的内容
+fn truncate_front(content: &mut String) {
+    let count = content.chars().count();
+    if count > MAX_CONTENT_CHARS {
+        *content = content.chars().skip(count - MAX_CONTENT_CHARS).collect();
+    }
+}
+
+/// 事件 -> 画布增量投影（守护进程侧）
+///
+/// 每个新的计划、SOP 执行或记忆占据一条横向泳道，泳道内按数据流深度分列。
+#[derive(Debug, Default)]
+pub struct CanvasProjector {
+    state: CanvasState,
+    /// 下一条泳道的纵坐标
+    next_lane: f64,
+}
+
+impl CanvasProjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 投影后的画布状态
+    pub fn state(&self) -> &CanvasState {
+        &self.state
+    }
+
+    /// 投影事件，返回产生的增量消息（已应用到自身状态）
+    pub fn project(&mut self, event: &Event) -> Vec<CanvasMessage> {
+        let deltas = self.deltas(event);
+        self.commit(deltas)
+    }
+
+    /// 移动节点（桌面端拖动），节点不存在时返回 `None`
+    pub fn move_node(&mut self, id: Uuid, position: Point) -> Option<CanvasMessage> {
+        self.state.node(id)?;
+        self.commit(vec![CanvasDelta::NodeMoved { id, position }]).pop()
+    }
+
+    fn commit(&mut self, deltas: Vec<CanvasDelta>) -> Vec<CanvasMessage> {
+        deltas
+            .into_iter()
+            .map(|delta| {
+                let message = CanvasMessage {
+                    seq: self.state.seq + 1,
+                    delta,
+                };
+                self.state.apply(&message);
+                message
+            })
+            .collect()
+    }
+
+    fn deltas(&mut self, event: &Event) -> Vec<CanvasDelta> {
+        let payload = &event.payload;
+        match event.kind {
+            EventKind::TaskPlanned => self.plan_deltas(event),
+            EventKind::TaskAssigned => self.update(event.entity_id, Some(CanvasNodeStatus::Running), None),
+            EventKind::TaskCompleted => {
+                let status = if payload["success"].as_bool() == Some(false) {
+                    CanvasNodeStatus::Failed
+                } else {
+                    CanvasNodeStatus::Completed
+                };
+                let output = payload["output"].as_str().map(String::from);
+                self.update(event.entity_id, Some(status), output)
+            }
+            EventKind::ExecutionFailed => {
+                let error = payload["error"].as_str().map(String::from);
+                self.update(event.entity_id, Some(CanvasNodeStatus::Failed), error)
+            }
+            EventKind::TaskCancelled => self.update(event.entity_id, Some(CanvasNodeStatus::Cancelled), None),
+            EventKind::SopRunStarted => self.sop_run_deltas(event),
+            EventKind::SopNodeStarted | EventKind::SopNodeCompleted | EventKind::SopNodeFailed => {
+                let Ok(update) = serde_json::from_value::<SopNodeUpdate>(payload.clone()) else {
+                    return Vec::new();
+                };
+                let status = match event.kind {
+                    EventKind::SopNodeStarted => CanvasNodeStatus::Running,
+                    EventKind::SopNodeCompleted => CanvasNodeStatus::Completed,
+                    _ => CanvasNodeStatus::Failed,
+                };
+                let id = CanvasBinding::SopStep {
+                    run: update.run,
+                    node: update.node,
+                }
+                .node_id();
+                self.update(id, Some(status), update.snippet)
+            }
+            EventKind::SopRunFinished => {
+                let Some(run) = payload["run"].as_str().and_then(|r| r.parse().ok()) else {
+                    return Vec::new();
+                };
+                let status = if payload["success"].as_bool() == Some(true) {
+                    CanvasNodeStatus::Completed
+                } else {
+                    CanvasNodeStatus::Failed
+                };
+                let failure = payload["failure"].as_str().map(String::from);
+                self.update(CanvasBinding::SopRun { run }.node_id(), Some(status), failure)
+            }
+            EventKind::MemoryStored => self.memory_deltas(event),
+            EventKind::LlmResponseChunk => match payload["text"].as_str() {
+                Some(chunk) if self.state.node(event.entity_id).is_some() => vec![CanvasDelta::ContentStreaming {
+                    id: event.entity_id,
+                    chunk: chunk.to_string(),
+                }],
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    fn update(&self, id: Uuid, status: Option<CanvasNodeStatus>, content: Option<String>) -> Vec<CanvasDelta> {
+        if self.state.node(id).is_none() {
+            return Vec::new();
+        }
+        vec![CanvasDelta::NodeUpdated { id, status, content }]
+    }
+
+    /// 计划节点与子任务节点，子任务按依赖深度分列
+    fn plan_deltas(&mut self, event: &Event) -> Vec<CanvasDelta> {
+        let plan = &event.payload["plan"];
+        let plan_id = plan["id"].as_str().and_then(|id| id.parse().ok()).unwrap_or(event.entity_id);
+        if self.state.node(plan_id).is_some() {
+            return Vec::new();
+        }
+        let mut preds = vec![(plan_id, Vec::new())];
+        let mut titles = HashMap::from([(plan_id, plan["goal"].as_str().unwrap_or("plan").to_string())]);
+        let mut edges = Vec::new();
+        for subtask in plan["subtasks"].as_array().into_iter().flatten() {
+            let Some(id) = subtask["id"].as_str().and_then(|id| id.parse::<Uuid>().ok()) else {
+                continue;
+            };
+            let deps: Vec<Uuid> = subtask["depends_on"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|d| d.as_str()?.parse().ok())
+                .collect();
+            if deps.is_empty() {
+                edges.push(CanvasEdge::new(plan_id, id));
+            }
+            edges.extend(deps.iter().map(|dep| CanvasEdge::new(*dep, id).with_label("depends_on")));
+            titles.insert(id, subtask["name"].as_str().unwrap_or("subtask").to_string());
+            preds.push((id, if deps.is_empty() { vec![plan_id] } else { deps }));
+        }
+        let positions = self.layout(&preds);
+        let mut deltas: Vec<CanvasDelta> = preds
+            .iter()
+            .map(|(id, _)| {
+                let title = titles.remove(id).unwrap_or_default();
+                CanvasDelta::NodeCreated {
+                    node: CanvasNode::new(CanvasBinding::Task { id: *id }, title, positions[id]),
+                }
+            })
+            .collect();
+        deltas.extend(edges.into_iter().map(|edge| CanvasDelta::EdgeCreated { edge }));
+        deltas
+    }
+
+    /// SOP 执行节点与步骤节点，步骤按 DAG 深度分列
+    fn sop_run_deltas(&mut self, event: &Event) -> Vec<CanvasDelta> {
+        let Some(view) = SopRunView::from_event(event) else {
+            return Vec::new();
+        };
+        let run = view.run;
+        let run_id = CanvasBinding::SopRun { run }.node_id();
+        if self.state.node(run_id).is_some() {
+            return Vec::new();
+        }
+        let step_id = |node: Uuid| CanvasBinding::SopStep { run, node }.node_id();
+        let mut preds: Vec<(Uuid, Vec<Uuid>)> = vec![(run_id, Vec::new())];
+        preds.extend(view.nodes.iter().map(|n| (step_id(n.node.id), Vec::new())));
+        let mut edges = vec![CanvasEdge::new(run_id, step_id(view.entry))];
+        for step in &view.nodes {
+            for next in &step.node.next {
+                edges.push(CanvasEdge::new(step_id(step.node.id), step_id(*next)));
+            }
+        }
+        for edge in &edges {
+            if let Some((_, p)) = preds.iter_mut().find(|(id, _)| *id == edge.target) {
+                p.push(edge.source);
+            }
+        }
+        let positions = self.layout(&preds);
+        let mut deltas = vec![CanvasDelta::NodeCreated {
+            node: CanvasNode::new(CanvasBinding::SopRun { run }, view.workflow.clone(), positions[&run_id]),
+        }];
+        deltas.extend(view.nodes.iter().map(|step| {
+            let binding = CanvasBinding::SopStep { run, node: step.node.id };
+            CanvasDelta::NodeCreated {
+                node: CanvasNode::new(binding, step.node.name.clone(), positions[&binding.node_id()]),
+            }
+        }));
+        deltas.extend(edges.into_iter().map(|edge| CanvasDelta::EdgeCreated { edge }));
+        deltas
+    }
+
+    /// 记忆节点；载荷带 `task` 时连到对应任务
+    fn memory_deltas(&mut self, event: &Event) -> Vec<CanvasDelta> {
+        let id = event.entity_id;
+        if self.state.node(id).is_some() {
+            return Vec::new();
+        }
+        let payload = &event.payload;
+        let content = payload["content"].as_str().unwrap_or_default();
+        let title = payload["title"]
+            .as_str()
+            .or(payload["summary"].as_str())
+            .map_or_else(|| snippet(content), String::from);
+        let position = self.layout(&[(id, Vec::new())])[&id];
+        let mut node = CanvasNode::new(CanvasBinding::Memory { id }, title, position);
+        node.status = CanvasNodeStatus::Completed;
+        node.content = content.to_string();
+        let mut deltas = vec![CanvasDelta::NodeCreated { node }];
+        if let Some(task) = payload["task"].as_str().and_then(|t| t.parse::<Uuid>().ok()) {
+            if self.state.node(task).is_some() {
+                deltas.push(CanvasDelta::EdgeCreated {
+                    edge: CanvasEdge::new(id, task).with_label("context"),
+                });
+            }
+        }
+        deltas
+    }
+
+    /// 在新泳道内按最长前驱路径分列布局（`preds` 中不属于本组的前驱被忽略）
+    fn layout(&mut self, preds: &[(Uuid, Vec<Uuid>)]) -> HashMap<Uuid, Point> {
+        let mut depth: HashMap<Uuid, usize> = preds.iter().map(|(id, _)| (*id, 0)).collect();
+        // 最多迭代节点数轮，环路不会导致死循环
+        for _ in 0..preds.len() {
+            let mut changed = false;
+            for (id, ps) in preds {
+                let d = ps.iter().filter_map(|p| depth.get(p)).map(|d| d + 1).max().unwrap_or(0);
+                if d > depth[id] && d < preds.len() {
+                    depth.insert(*id, d);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut rows: HashMap<usize, usize> = HashMap::new();
+        let lane = self.next_lane;
+        let positions = preds
+            .iter()
+            .map(|(id, _)| {
+                let column = depth[id];
+                let row = rows.entry(column).or_default();
+                let position = Point::new(column as f64 * COLUMN_WIDTH, lane + *row as f64 * ROW_HEIGHT);
+                *row += 1;
+                (*id, position)
+            })
+            .collect();
+        let height = rows.values().copied().max().unwrap_or(1);
+        self.next_lane = lane + (height + 1) as f64 * ROW_HEIGHT;
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_projected_deltas_mirror_state() {
+        let (plan, a, b) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let planned = Event::new(
+            EventKind::TaskPlanned,
+            plan,
+            serde_json::json!({ "plan": {
+                "id": plan,
+                "goal": "ship release",
+                "subtasks": [
+                    { "id": a, "name": "build", "depends_on": [] },
+                    { "id": b, "name": "publish", "depends_on": [a] },
+                ],
+            }}),
+        );
+        let mut projector = CanvasProjector::new();
+        let mut mirror = CanvasState::default();
+        let mut messages = projector.project(&planned);
+        assert_eq!(messages.len(), 5);
+        assert!(projector.project(&planned).is_empty());
+
+        messages.extend(projector.project(&Event::new(EventKind::TaskAssigned, a, serde_json::json!({}))));
+        messages.extend(projector.project(&Event::new(EventKind::LlmResponseChunk, a, serde_json::json!({ "text": "com" }))));
+        messages.extend(projector.project(&Event::new(EventKind::LlmResponseChunk, a, serde_json::json!({ "text": "piling" }))));
+        messages.extend(projector.move_node(b, Point::new(10.0, 20.0)));
+        assert!(projector.move_node(Uuid::new_v4(), Point::default()).is_none());
+
+        // 序号不连续时拒绝，快照后恢复
+        assert!(!mirror.apply(&messages[1]));
+        for message in &messages {
+            let json = serde_json::to_string(message).unwrap();
+            assert!(mirror.apply(&serde_json::from_str(&json).unwrap()));
+        }
+        let build = mirror.node(a).unwrap();
+        assert_eq!((build.status, build.content.as_str()), (CanvasNodeStatus::Running, "compiling"));
+        assert_eq!(mirror.node(b).unwrap().position, Point::new(10.0, 20.0));
+        assert_eq!(mirror.node(a).unwrap().position, Point::new(COLUMN_WIDTH, 0.0));
+        assert_eq!(mirror.edges().count(), 2);
+
+        let mut fresh = CanvasState::default();
+        assert!(fresh.apply(&projector.state().snapshot()));
+        assert_eq!(fresh.seq(), mirror.seq());
+        assert_eq!(fresh.nodes().count(), 3);
+    }
+}
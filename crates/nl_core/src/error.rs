@@ -23,6 +23,9 @@ pub enum NeuroLoomError {
     #[error("Sandbox execution error: {0}")]
     Sandbox(String),
 
+    #[error("Vision error: {0}")]
+    Vision(String),
+
     #[error("Protocol error: {0}")]
     Protocol(String),
 
@@ -35,6 +38,13 @@ pub enum NeuroLoomError {
     #[error("Database error: {0}")]
     Database(String),
 
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    /// 沙箱命令超出资源限制（内存按字节、CPU 时间按毫秒、进程按个数）
+    #[error("Resource limit exceeded: {resource} reached {used} (limit {limit})")]
+    ResourceExceeded { resource: String, limit: u64, used: u64 },
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
@@ -53,18 +53,36 @@ impl Event {
 }
 
 /// 事件类型枚举
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum EventKind {
     // 工作区事件
     NodeCreated,
     NodeUpdated,
     NodeDeleted,
+    /// 补偿事件：把实体状态恢复到历史时刻（`nl workspace rewind --restore`），不删除原有历史
+    StateRestored,
 
     // LLM 事件
     LlmRequestStarted,
     LlmResponseChunk,
     LlmResponseCompleted,
     LlmError,
+    /// 发出的请求中检测到凭证（被拦截或已替换）
+    SecretBlocked,
+    /// Provider 的 p95 延迟持续超过 SLO，已在降级顺序中后移
+    ProviderDemoted,
+    /// 降级的 Provider 探测恢复，回到原有顺序
+    ProviderRestored,
+
+    // 对话事件
+    /// 对话在某一轮处分叉出新分支（含主分支的创建）
+    ConversationBranched,
+    /// 分支追加一轮消息
+    ConversationTurn,
+    /// 分支合并回父分支
+    ConversationBranchMerged,
+    /// 分支被丢弃
+    ConversationBranchDiscarded,
 
     // 执行事件
     CodeExecuted,
@@ -72,9 +90,35 @@ pub enum EventKind {
     ExecutionSuccess,
 
     // 认知事件
+    TaskPlanned,
     TaskAssigned,
     TaskCompleted,
+    TaskCancelled,
+    /// 人工对任务结果的评分与评语
+    TaskFeedback,
     VerdictIssued,
+    /// 一轮审议结束（草稿摘要、当前评分与剩余轮数），最终裁决前的进度
+    VerdictProgress,
+    /// 裁决得到真实结果（测试结果或人工批准），用于评分校准
+    VerdictOutcome,
+    /// 任务需要人工审批后才能继续
+    ApprovalRequested,
+
+    // SOP 执行事件（桌面画布/`nl sop watch` 实时点亮节点）
+    SopRunStarted,
+    SopNodeStarted,
+    SopNodeCompleted,
+    SopNodeFailed,
+    SopRunFinished,
+    /// 新的 SOP 工作流注册到引擎
+    SopRegistered,
+
+    // 调度与文件触发事件
+    ScheduleTriggered,
+    ScheduleSkipped,
+    WatchTriggered,
+    /// 入站 Webhook 命中规则并启动任务
+    WebhookTriggered,
 
     // Actor 事件
     ActorSpawned,
@@ -86,12 +130,25 @@ pub enum EventKind {
     MemoryStored,
     MemoryRetrieved,
     MemoryArchived,
+    MemoryConsolidationProgress,
+    MemoryConsolidated,
 
     // 网络事件
     AgentConnected,
     AgentDisconnected,
     BidReceived,
     TaskDelegated,
+    DelegationProgress,
+    DelegationStalled,
+    DelegationCompleted,
+    DelegationDisputed,
+
+    // 审计事件
+    GodModeAction,
+
+    // 实验事件
+    ExperimentAssigned,
+    ExperimentOutcome,
 
     // 自定义事件
     Custom(String),
@@ -104,16 +161,40 @@ impl EventKind {
             EventKind::NodeCreated => "node_created",
             EventKind::NodeUpdated => "node_updated",
             EventKind::NodeDeleted => "node_deleted",
+            EventKind::StateRestored => "state_restored",
             EventKind::LlmRequestStarted => "llm_request_started",
             EventKind::LlmResponseChunk => "llm_response_chunk",
             EventKind::LlmResponseCompleted => "llm_response_completed",
             EventKind::LlmError => "llm_error",
+            EventKind::SecretBlocked => "secret_blocked",
+            EventKind::ProviderDemoted => "provider_demoted",
+            EventKind::ProviderRestored => "provider_restored",
+            EventKind::ConversationBranched => "conversation_branched",
+            EventKind::ConversationTurn => "conversation_turn",
+            EventKind::ConversationBranchMerged => "conversation_branch_merged",
+            EventKind::ConversationBranchDiscarded => "conversation_branch_discarded",
             EventKind::CodeExecuted => "code_executed",
             EventKind::ExecutionFailed => "execution_failed",
             EventKind::ExecutionSuccess => "execution_success",
+            EventKind::TaskPlanned => "task_planned",
             EventKind::TaskAssigned => "task_assigned",
             EventKind::TaskCompleted => "task_completed",
+            EventKind::TaskCancelled => "task_cancelled",
+            EventKind::TaskFeedback => "task_feedback",
             EventKind::VerdictIssued => "verdict_issued",
+            EventKind::VerdictProgress => "verdict_progress",
+            EventKind::VerdictOutcome => "verdict_outcome",
+            EventKind::ApprovalRequested => "approval_requested",
+            EventKind::SopRunStarted => "sop_run_started",
+            EventKind::SopNodeStarted => "sop_node_started",
+            EventKind::SopNodeCompleted => "sop_node_completed",
+            EventKind::SopNodeFailed => "sop_node_failed",
+            EventKind::SopRunFinished => "sop_run_finished",
+            EventKind::SopRegistered => "sop_registered",
+            EventKind::ScheduleTriggered => "schedule_triggered",
+            EventKind::ScheduleSkipped => "schedule_skipped",
+            EventKind::WatchTriggered => "watch_triggered",
+            EventKind::WebhookTriggered => "webhook_triggered",
             EventKind::ActorSpawned => "actor_spawned",
             EventKind::ActorSuspended => "actor_suspended",
             EventKind::ActorResumed => "actor_resumed",
@@ -121,11 +202,64 @@ impl EventKind {
             EventKind::MemoryStored => "memory_stored",
             EventKind::MemoryRetrieved => "memory_retrieved",
             EventKind::MemoryArchived => "memory_archived",
+            EventKind::MemoryConsolidationProgress => "memory_consolidation_progress",
+            EventKind::MemoryConsolidated => "memory_consolidated",
             EventKind::AgentConnected => "agent_connected",
             EventKind::AgentDisconnected => "agent_disconnected",
             EventKind::BidReceived => "bid_received",
             EventKind::TaskDelegated => "task_delegated",
+            EventKind::DelegationProgress => "delegation_progress",
+            EventKind::DelegationStalled => "delegation_stalled",
+            EventKind::DelegationCompleted => "delegation_completed",
+            EventKind::DelegationDisputed => "delegation_disputed",
+            EventKind::GodModeAction => "god_mode_action",
+            EventKind::ExperimentAssigned => "experiment_assigned",
+            EventKind::ExperimentOutcome => "experiment_outcome",
             EventKind::Custom(name) => name,
         }
     }
 }
+
+/// 事件订阅过滤条件
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EventFilter {
+    /// 事件类型名称（`EventKind::as_str`），为空表示全部类型
+    #[serde(default)]
+    pub kinds: Vec<String>,
+    /// 关联实体 ID
+    pub entity_id: Option<EntityId>,
+    /// 关联 ID
+    pub correlation_id: Option<Uuid>,
+}
+
+impl EventFilter {
+    /// 创建匹配全部事件的过滤条件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加事件类型
+    pub fn with_kind(mut self, kind: impl Into<String>) -> Self {
+        self.kinds.push(kind.into());
+        self
+    }
+
+    /// 限定关联实体
+    pub fn with_entity(mut self, entity_id: EntityId) -> Self {
+        self.entity_id = Some(entity_id);
+        self
+    }
+
+    /// 限定关联 ID
+    pub fn with_correlation(mut self, correlation_id: Uuid) -> Self {
+        self.correlation_id = Some(correlation_id);
+        self
+    }
+
+    /// 判断事件是否满足过滤条件
+    pub fn matches(&self, event: &Event) -> bool {
+        (self.kinds.is_empty() || self.kinds.iter().any(|k| k == event.kind.as_str()))
+            && self.entity_id.is_none_or(|id| id == event.entity_id)
+            && self.correlation_id.is_none_or(|id| event.correlation_id == Some(id))
+    }
+}
@@ -3,10 +3,17 @@
 //! 核心原语层，定义事件溯源事件枚举、UUID、全局错误处理机制。
 //! 此 crate 是整个项目的基础依赖，不依赖其他业务 crate。
 
+pub mod artifact;
+pub mod canvas;
 pub mod error;
 pub mod event;
 pub mod entity;
+pub mod sop_view;
+pub mod tokenizer;
 
+pub use artifact::{Artifact, ArtifactKind};
+pub use canvas::{CanvasDelta, CanvasMessage, CanvasNode, CanvasProjector, CanvasState};
 pub use error::{NeuroLoomError, Result};
-pub use event::{Event, EventKind};
+pub use event::{Event, EventFilter, EventKind};
 pub use entity::{Entity, EntityId};
+pub use tokenizer::{BpeTokenizer, HeuristicTokenizer, SentencePieceTokenizer, Tokenizer, TokenizerRegistry};
@@ -0,0 +1,211 @@
+//! SOP 执行视图
+//!
+//! SOP 引擎执行时发布 `SopRunStarted`（携带 DAG 结构）、逐节点的
+//! `SopNodeStarted` / `SopNodeCompleted` / `SopNodeFailed` 事件，以及结束时的 `SopRunFinished`：
+//! - 事件的 `entity_id` 为工作流 ID，`correlation_id` 为本次执行 ID
+//! - `SopRunView` 把这些事件投影为带实时状态的 DAG，供桌面画布与 `nl sop watch` 渲染
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::event::{Event, EventKind};
+
+/// 结果摘要的最大字符数
+pub const SNIPPET_CHARS: usize = 120;
+
+/// SOP 执行事件类型名称（用于订阅过滤）
+pub const SOP_EVENT_KINDS: [&str; 5] = [
+    "sop_run_started",
+    "sop_node_started",
+    "sop_node_completed",
+    "sop_node_failed",
+    "sop_run_finished",
+];
+
+/// `SopRunStarted` 载荷
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SopRunPlan {
+    /// 执行 ID
+    pub run: Uuid,
+    /// 工作流 ID
+    pub workflow_id: Uuid,
+    /// 工作流名称
+    pub workflow: String,
+    /// 入口节点
+    pub entry: Uuid,
+    /// 全部节点
+    pub nodes: Vec<SopPlanNode>,
+}
+
+/// DAG 节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SopPlanNode {
+    pub id: Uuid,
+    pub name: String,
+    /// 动作类型（如 `ExecuteCommand`）
+    pub action: String,
+    /// 下游节点
+    pub next: Vec<Uuid>,
+}
+
+/// 节点级事件载荷
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SopNodeUpdate {
+    /// 执行 ID
+    pub run: Uuid,
+    /// 节点 ID
+    pub node: Uuid,
+    /// 节点名称
+    pub name: String,
+    /// 耗时（毫秒），仅完成/失败事件携带
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    /// 结果摘要或错误信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// 截取结果摘要
+pub fn snippet(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() > SNIPPET_CHARS || line.len() < text.trim_end().len() {
+        format!("{}…", line.chars().take(SNIPPET_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// 节点状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SopNodeStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl SopNodeStatus {
+    /// 文本渲染标记
+    pub fn marker(&self) -> &'static str {
+        match self {
+            SopNodeStatus::Pending => "[ ]",
+            SopNodeStatus::Running => "[>]",
+            SopNodeStatus::Completed => "[+]",
+            SopNodeStatus::Failed => "[x]",
+        }
+    }
+}
+
+/// 带状态的节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SopNodeView {
+    pub node: SopPlanNode,
+    pub status: SopNodeStatus,
+    pub elapsed_ms: Option<u64>,
+    pub snippet: Option<String>,
+}
+
+/// 一次 SOP 执行的实时视图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SopRunView {
+    pub run: Uuid,
+    pub workflow: String,
+    pub entry: Uuid,
+    pub nodes: Vec<SopNodeView>,
+}
+
+impl SopRunView {
+    /// 从 `SopRunStarted` 事件创建视图，其他事件返回 `None`
+    pub fn from_event(event: &Event) -> Option<Self> {
+        if event.kind != EventKind::SopRunStarted {
+            return None;
+        }
+        let plan: SopRunPlan = serde_json::from_value(event.payload.clone()).ok()?;
+        Some(Self {
+            run: plan.run,
+            workflow: plan.workflow,
+            entry: plan.entry,
+            nodes: plan
+                .nodes
+                .into_iter()
+                .map(|node| SopNodeView {
+                    node,
+                    status: SopNodeStatus::Pending,
+                    elapsed_ms: None,
+                    snippet: None,
+                })
+                .collect(),
+        })
+    }
+
+    /// 应用节点事件，属于本次执行且状态发生变化时返回 `true`
+    pub fn apply(&mut self, event: &Event) -> bool {
+        let status = match event.kind {
+            EventKind::SopNodeStarted => SopNodeStatus::Running,
+            EventKind::SopNodeCompleted => SopNodeStatus::Completed,
+            EventKind::SopNodeFailed => SopNodeStatus::Failed,
+            _ => return false,
+        };
+        let Ok(update) = serde_json::from_value::<SopNodeUpdate>(event.payload.clone()) else {
+            return false;
+        };
+        if update.run != self.run {
+            return false;
+        }
+        let Some(view) = self.nodes.iter_mut().find(|n| n.node.id == update.node) else {
+            return false;
+        };
+        view.status = status;
+        view.elapsed_ms = update.elapsed_ms;
+        view.snippet = update.snippet;
+        true
+    }
+
+    /// 节点状态
+    pub fn status(&self, node: Uuid) -> Option<SopNodeStatus> {
+        self.nodes.iter().find(|n| n.node.id == node).map(|n| n.status)
+    }
+
+    /// 是否已结束（有节点失败，或没有运行中的节点且至少一个节点已完成）
+    pub fn is_finished(&self) -> bool {
+        let any = |status| self.nodes.iter().any(|n| n.status == status);
+        any(SopNodeStatus::Failed) || (any(SopNodeStatus::Completed) && !any(SopNodeStatus::Running))
+    }
+
+    /// 渲染为缩进文本：从入口深度优先展开，已展开的节点只引用名称
+    pub fn render(&self) -> Vec<String> {
+        let mut lines = vec![format!("{} (run {})", self.workflow, self.run)];
+        let mut seen = HashSet::new();
+        self.render_node(self.entry, 1, &mut seen, &mut lines);
+        for view in &self.nodes {
+            if !seen.contains(&view.node.id) {
+                self.render_node(view.node.id, 1, &mut seen, &mut lines);
+            }
+        }
+        lines
+    }
+
+    fn render_node(&self, id: Uuid, depth: usize, seen: &mut HashSet<Uuid>, lines: &mut Vec<String>) {
+        let Some(view) = self.nodes.iter().find(|n| n.node.id == id) else {
+            return;
+        };
+        let indent = "  ".repeat(depth);
+        if !seen.insert(id) {
+            lines.push(format!("{}-> {}", indent, view.node.name));
+            return;
+        }
+        let mut line = format!("{}{} {} ({})", indent, view.status.marker(), view.node.name, view.node.action);
+        if let Some(ms) = view.elapsed_ms {
+            line.push_str(&format!(" {}ms", ms));
+        }
+        if let Some(snippet) = &view.snippet {
+            line.push_str(&format!("  {}", snippet));
+        }
+        lines.push(line);
+        for next in &view.node.next {
+            self.render_node(*next, depth + 1, seen, lines);
+        }
+    }
+}
@@ -0,0 +1,749 @@
+//! 分词器 - 按模型家族计算 token 数
+//!
+//! 上下文装箱、提示词统计与发送前的溢出检查都要知道文本占多少 token，`Tokenizer` 统一计数接口：
+//! - `BpeTokenizer`：tiktoken 格式的字节级 BPE 词表（OpenAI `cl100k_base`、`o200k_base`），
+//!   预切分按词表大小选择 cl100k 的规则（英文缩写、字母串、1-3 位数字、标点串、空白）
+//!   或 o200k 的规则（字母串按大小写切开并带上缩写）
+//! - `SentencePieceTokenizer`：SentencePiece `.model` 文件（Gemma / Gemini、Llama 等），支持 Unigram 与 BPE 模型
+//! - `HeuristicTokenizer`：没有词表时的回退估算（ASCII 约 4 字符 / token，其余字符各算 1 个）
+//!
+//! `TokenizerRegistry` 按模型名选择分词器（精确匹配优先，其次最长前缀），未登记的模型使用回退估算。
+//! 词表文件从目录加载：`tokenizers.json` 记录模型前缀到文件名的映射（如 `{"gpt-4o": "o200k_base.tiktoken"}`），
+//! 没有映射文件时以文件名（去掉扩展名）作为前缀。守护进程从数据目录的 `tokenizers/` 加载。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::{NeuroLoomError, Result};
+
+/// 数据目录中存放词表的子目录
+pub const TOKENIZER_DIR: &str = "tokenizers";
+
+/// 词表目录中的映射文件
+pub const TOKENIZER_MAPPING_FILE: &str = "tokenizers.json";
+
+/// 词表达到此大小时按 o200k 的规则预切分（`cl100k_base` 约 10 万个 token，`o200k_base` 约 20 万个）
+const O200K_MIN_VOCAB: usize = 150_000;
+
+/// 超过此字节数的预切分片段分段合并，避免超长的无空白文本（如 base64）让合并退化为平方复杂度
+const MAX_PIECE_BYTES: usize = 256;
+
+/// 回退估算时每个 token 对应的 ASCII 字符数
+const ASCII_CHARS_PER_TOKEN: usize = 4;
+
+/// SentencePiece 的空格替代符
+const SPACE_MARK: char = '\u{2581}';
+
+/// 分词器
+pub trait Tokenizer: Send + Sync {
+    /// 名称（如 `cl100k_base`），用于统计与日志
+    fn name(&self) -> &str;
+
+    /// 文本的 token 数
+    fn count(&self, text: &str) -> usize;
+
+    /// 计数是否只是估算
+    fn is_estimate(&self) -> bool {
+        false
+    }
+}
+
+/// 回退估算
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenizer;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        let ascii = text.bytes().filter(u8::is_ascii).count();
+        let other = text.chars().filter(|c| !c.is_ascii()).count();
+        ascii.div_ceil(ASCII_CHARS_PER_TOKEN) + other
+    }
+
+    fn is_estimate(&self) -> bool {
+        true
+    }
+}
+
+/// BPE 预切分规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pretokenizer {
+    Cl100k,
+    O200k,
+}
+
+/// tiktoken 格式的字节级 BPE 分词器
+pub struct BpeTokenizer {
+    name: String,
+    ranks: HashMap<Vec<u8>, u32>,
+    pretokenizer: Pretokenizer,
+}
+
+impl BpeTokenizer {
+    /// 解析 tiktoken 词表（每行 `<base64 字节串> <rank>`）
+    pub fn from_tiktoken(name: impl Into<String>, content: &str) -> Result<Self> {
+        let name = name.into();
+        let mut ranks = HashMap::new();
+        for (number, line) in content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let invalid = || invalid_vocab(&name, format!("line {}", number + 1));
+            let (token, rank) = line.trim().split_once(' ').ok_or_else(invalid)?;
+            let token = decode_base64(token).ok_or_else(invalid)?;
+            let rank = rank.trim().parse().map_err(|_| invalid())?;
+            ranks.insert(token, rank);
+        }
+        if ranks.is_empty() {
+            return Err(invalid_vocab(&name, "empty vocabulary"));
+        }
+        let pretokenizer = if ranks.len() >= O200K_MIN_VOCAB { Pretokenizer::O200k } else { Pretokenizer::Cl100k };
+        Ok(Self { name, ranks, pretokenizer })
+    }
+
+    /// 从文件加载，名称取文件名（去掉扩展名）
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_tiktoken(file_stem(path), &std::fs::read_to_string(path)?)
+    }
+
+    /// 词表大小
+    pub fn vocab_size(&self) -> usize {
+        self.ranks.len()
+    }
+
+    /// 一个预切分片段按 rank 从低到高合并相邻字节对后的 token 数
+    fn merge(&self, piece: &[u8]) -> usize {
+        if piece.len() <= 1 || self.ranks.contains_key(piece) {
+            return 1;
+        }
+        // 每个部分为 piece 中的起始偏移，最后一个元素为结尾哨兵
+        let mut bounds: Vec<usize> = (0..=piece.len()).collect();
+        loop {
+            let best = (0..bounds.len() - 2)
+                .filter_map(|i| self.ranks.get(&piece[bounds[i]..bounds[i + 2]]).map(|rank| (*rank, i)))
+                .min();
+            match best {
+                Some((_, i)) => {
+                    bounds.remove(i + 1);
+                }
+                None => break,
+            }
+            if bounds.len() == 2 {
+                break;
+            }
+        }
+        bounds.len() - 1
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn count(&self, text: &str) -> usize {
+        pretokenize(text, self.pretokenizer)
+            .into_iter()
+            .flat_map(|piece| piece.as_bytes().chunks(MAX_PIECE_BYTES))
+            .map(|piece| self.merge(piece))
+            .sum()
+    }
+}
+
+/// 按 cl100k 的规则预切分：
+/// `'s|'t|'re|'ve|'m|'ll|'d`、`[^\r\n\p{L}\p{N}]?\p{L}+`、`\p{N}{1,3}`、` ?[^\s\p{L}\p{N}]+[\r\n]*`、
+/// `\s*[\r\n]+`、`\s+(?!\S)`、`\s+`；
+/// o200k 没有单独的缩写分支，字母串为 `[^\r\n\p{L}\p{N}]?` 加 `cased_word`，标点串后还可跟 `/`
+fn pretokenize(text: &str, rules: Pretokenizer) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let offset = |i: usize| chars.get(i).map_or(text.len(), |(o, _)| *o);
+    let is_punct = |c: char| !c.is_whitespace() && !c.is_alphabetic() && !c.is_numeric();
+    let run = |from: usize, pred: &dyn Fn(char) -> bool| {
+        from + chars[from..].iter().take_while(|(_, c)| pred(*c)).count()
+    };
+
+    let mut pieces = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        let word = if c.is_alphabetic() {
+            Some(i)
+        } else if c != '\r' && c != '\n' && !c.is_numeric() && next.is_some_and(char::is_alphabetic) {
+            Some(i + 1)
+        } else {
+            None
+        };
+        let end = if let Some(len) = contraction(&chars[i..]).filter(|_| rules == Pretokenizer::Cl100k) {
+            i + len
+        } else if let Some(start) = word {
+            match rules {
+                Pretokenizer::Cl100k => run(start, &|c| c.is_alphabetic()),
+                Pretokenizer::O200k => cased_word(&chars, start),
+            }
+        } else if c.is_numeric() {
+            (i + 3).min(run(i, &|c| c.is_numeric()))
+        } else if is_punct(c) || (c == ' ' && next.is_some_and(is_punct)) {
+            let start = if c == ' ' { i + 1 } else { i };
+            let punct = run(start, &is_punct);
+            let slash = rules == Pretokenizer::O200k;
+            run(punct, &|c| c == '\r' || c == '\n' || (slash && c == '/'))
+        } else {
+            let whitespace = run(i, &char::is_whitespace);
+            match chars[i..whitespace].iter().rposition(|(_, c)| *c == '\r' || *c == '\n') {
+                Some(last) => i + last + 1,
+                None if whitespace == chars.len() || whitespace - i == 1 => whitespace,
+                // 末尾留一个空白给后面的单词或标点
+                None => whitespace - 1,
+            }
+        };
+        pieces.push(&text[offset(i)..offset(end)]);
+        i = end;
+    }
+    pieces
+}
+
+/// o200k 的字母串结尾：`[大写]*[小写]+` 优先，其次 `[大写]+[小写]*`，之后可跟英文缩写；
+/// 没有大小写之分的字母（如汉字、修饰字母 `ʰ`）同时属于两类，标题大小写字母（如 `ǅ`）只属于大写类
+fn cased_word(chars: &[(usize, char)], start: usize) -> usize {
+    // 按有无大小写映射近似 Unicode 的 Ll 与 Lu / Lt
+    let small = |c: char| c.is_lowercase() && !c.to_uppercase().eq([c]);
+    let capital = |c: char| c.is_uppercase() || (!c.is_lowercase() && !c.to_lowercase().eq([c]));
+    let upper = |c: char| c.is_alphabetic() && !small(c);
+    let lower = |c: char| c.is_alphabetic() && !capital(c);
+    let run = |from: usize, pred: &dyn Fn(char) -> bool| {
+        from + chars[from..].iter().take_while(|(_, c)| pred(*c)).count()
+    };
+    let uppers = run(start, &upper);
+    let lowers = run(uppers, &lower);
+    let end = if lowers > uppers {
+        lowers
+    } else {
+        // 正则回溯：大写部分让出最后一个也属于小写类的字母
+        match chars[start..uppers].iter().rposition(|(_, c)| lower(*c)) {
+            Some(last) => run(start + last, &lower),
+            None => uppers,
+        }
+    };
+    end + contraction(&chars[end..]).unwrap_or(0)
+}
+
+/// 英文缩写（`'s`、`'re` 等，不区分大小写）的字符数
+fn contraction(chars: &[(usize, char)]) -> Option<usize> {
+    if chars.first()?.1 != '\'' {
+        return None;
+    }
+    let lower = |i: usize| chars.get(i).map(|(_, c)| c.to_ascii_lowercase());
+    match (lower(1)?, lower(2)) {
+        ('r', Some('e')) | ('v', Some('e')) | ('l', Some('l')) => Some(3),
+        ('s' | 't' | 'm' | 'd', _) => Some(2),
+        _ => None,
+    }
+}
+
+/// SentencePiece 模型类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PieceModel {
+    Unigram,
+    Bpe,
+}
+
+/// SentencePiece 分词器
+pub struct SentencePieceTokenizer {
+    name: String,
+    model: PieceModel,
+    /// 可输出的词片及其得分
+    pieces: HashMap<String, f32>,
+    /// 最长词片的字符数
+    max_piece_chars: usize,
+    /// 未登录字符按 UTF-8 字节拆分（否则记为一个未知 token）
+    byte_fallback: bool,
+    add_dummy_prefix: bool,
+    remove_extra_whitespaces: bool,
+    /// 未知字符在 Viterbi 中的得分
+    unknown_score: f32,
+}
+
+impl SentencePieceTokenizer {
+    /// 解析 SentencePiece `.model`（protobuf `ModelProto`）
+    pub fn from_model(name: impl Into<String>, bytes: &[u8]) -> Result<Self> {
+        let name = name.into();
+        let invalid = |reason: &str| invalid_vocab(&name, reason);
+        let mut tokenizer = Self {
+            model: PieceModel::Unigram,
+            pieces: HashMap::new(),
+            max_piece_chars: 1,
+            byte_fallback: false,
+            add_dummy_prefix: true,
+            remove_extra_whitespaces: true,
+            unknown_score: 0.0,
+            name: name.clone(),
+        };
+        let mut min_score = 0.0f32;
+        for field in ProtoFields::new(bytes) {
+            match field.map_err(|_| invalid("truncated protobuf"))? {
+                (1, ProtoValue::Bytes(piece)) => {
+                    let (mut text, mut score, mut kind) = (String::new(), 0.0f32, 1);
+                    for field in ProtoFields::new(piece) {
+                        match field.map_err(|_| invalid("truncated piece"))? {
+                            (1, ProtoValue::Bytes(b)) => text = String::from_utf8_lossy(b).into_owned(),
+                            (2, ProtoValue::Fixed32(bits)) => score = f32::from_bits(bits),
+                            (3, ProtoValue::Varint(v)) => kind = v,
+                            _ => {}
+                        }
+                    }
+                    min_score = min_score.min(score);
+                    // 1 = NORMAL，4 = USER_DEFINED；控制符、未知符与字节词片不参与匹配
+                    if kind == 1 || kind == 4 {
+                        tokenizer.max_piece_chars = tokenizer.max_piece_chars.max(text.chars().count());
+                        tokenizer.pieces.insert(text, score);
+                    }
+                }
+                (2, ProtoValue::Bytes(trainer)) => {
+                    for field in ProtoFields::new(trainer) {
+                        match field.map_err(|_| invalid("truncated trainer spec"))? {
+                            (3, ProtoValue::Varint(2)) => tokenizer.model = PieceModel::Bpe,
+                            (3, ProtoValue::Varint(kind)) if kind != 1 => {
+                                return Err(invalid(&format!("unsupported model type {}", kind)));
+                            }
+                            (35, ProtoValue::Varint(v)) => tokenizer.byte_fallback = v != 0,
+                            _ => {}
+                        }
+                    }
+                }
+                (3, ProtoValue::Bytes(normalizer)) => {
+                    for field in ProtoFields::new(normalizer) {
+                        match field.map_err(|_| invalid("truncated normalizer spec"))? {
+                            (3, ProtoValue::Varint(v)) => tokenizer.add_dummy_prefix = v != 0,
+                            (4, ProtoValue::Varint(v)) => tokenizer.remove_extra_whitespaces = v != 0,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if tokenizer.pieces.is_empty() {
+            return Err(invalid("no pieces"));
+        }
+        tokenizer.unknown_score = min_score - 10.0;
+        Ok(tokenizer)
+    }
+
+    /// 从文件加载，名称取文件名（去掉扩展名）
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_model(file_stem(path), &std::fs::read(path)?)
+    }
+
+    /// 词表大小（不含控制符与字节词片）
+    pub fn vocab_size(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// 归一化：（可选）合并多余空格、加前导空格，空格替换为 `▁`
+    fn normalize(&self, text: &str) -> String {
+        let text = if self.remove_extra_whitespaces {
+            text.split(' ').filter(|w| !w.is_empty()).collect::<Vec<_>>().join(" ")
+        } else {
+            text.to_string()
+        };
+        let prefix = if self.add_dummy_prefix && !text.is_empty() { " " } else { "" };
+        format!("{}{}", prefix, text).replace(' ', &SPACE_MARK.to_string())
+    }
+
+    /// 未登录字符的 token 数
+    fn unknown(&self, c: char) -> usize {
+        if self.byte_fallback {
+            c.len_utf8()
+        } else {
+            1
+        }
+    }
+
+    /// Unigram：Viterbi 求得分最高的切分
+    fn viterbi(&self, word: &[char]) -> usize {
+        // best[i] = 前 i 个字符的（最高得分, token 数）
+        let mut best: Vec<(f32, usize)> = vec![(f32::NEG_INFINITY, 0); word.len() + 1];
+        best[0] = (0.0, 0);
+        let mut piece = String::new();
+        for start in 0..word.len() {
+            let (score, tokens) = best[start];
+            if score == f32::NEG_INFINITY {
+                continue;
+            }
+            piece.clear();
+            for end in start + 1..=word.len().min(start + self.max_piece_chars) {
+                piece.push(word[end - 1]);
+                if let Some(piece_score) = self.pieces.get(&piece) {
+                    if score + piece_score > best[end].0 {
+                        best[end] = (score + piece_score, tokens + 1);
+                    }
+                }
+            }
+            if score + self.unknown_score > best[start + 1].0 {
+                best[start + 1] = (score + self.unknown_score, tokens + self.unknown(word[start]));
+            }
+        }
+        best[word.len()].1
+    }
+
+    /// BPE：反复合并得分最高的相邻词片
+    fn bpe(&self, word: &[char]) -> usize {
+        let mut symbols: Vec<String> = word.iter().map(char::to_string).collect();
+        loop {
+            let best = symbols
+                .windows(2)
+                .enumerate()
+                .filter_map(|(i, pair)| self.pieces.get(&format!("{}{}", pair[0], pair[1])).map(|s| (*s, i)))
+                .max_by(|a, b| a.0.total_cmp(&b.0).then(b.1.cmp(&a.1)));
+            let Some((_, i)) = best else { break };
+            let right = symbols.remove(i + 1);
+            symbols[i].push_str(&right);
+        }
+        symbols
+            .iter()
+            .map(|s| match self.pieces.contains_key(s) {
+                true => 1,
+                false => s.chars().map(|c| self.unknown(c)).sum(),
+            })
+            .sum()
+    }
+}
+
+impl Tokenizer for SentencePieceTokenizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn count(&self, text: &str) -> usize {
+        let normalized: Vec<char> = self.normalize(text).chars().collect();
+        // 按 `▁` 切成单词分别处理（SentencePiece 默认不跨空白合并）
+        let mut words = Vec::new();
+        let mut start = 0;
+        for i in 1..=normalized.len() {
+            if i == normalized.len() || normalized[i] == SPACE_MARK || i - start >= MAX_PIECE_BYTES {
+                words.push(&normalized[start..i]);
+                start = i;
+            }
+        }
+        words
+            .into_iter()
+            .filter(|w| !w.is_empty())
+            .map(|word| match self.model {
+                PieceModel::Unigram => self.viterbi(word),
+                PieceModel::Bpe => self.bpe(word),
+            })
+            .sum()
+    }
+}
+
+/// protobuf 字段值
+enum ProtoValue<'a> {
+    Varint(u64),
+    Fixed32(u32),
+    Bytes(&'a [u8]),
+    Fixed64,
+}
+
+/// 逐个读取 protobuf 消息的字段（字段号, 值）
+struct ProtoFields<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ProtoFields<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.bytes.get(self.pos)?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(slice)
+    }
+}
+
+impl<'a> Iterator for ProtoFields<'a> {
+    type Item = std::result::Result<(u64, ProtoValue<'a>), ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.bytes.len() {
+            return None;
+        }
+        let field = (|| {
+            let key = self.varint()?;
+            let value = match key & 7 {
+                0 => ProtoValue::Varint(self.varint()?),
+                1 => self.take(8).map(|_| ProtoValue::Fixed64)?,
+                2 => {
+                    let len = usize::try_from(self.varint()?).ok()?;
+                    ProtoValue::Bytes(self.take(len)?)
+                }
+                5 => ProtoValue::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().ok()?)),
+                _ => return None,
+            };
+            Some((key >> 3, value))
+        })();
+        if field.is_none() {
+            // 出错后停止读取
+            self.pos = self.bytes.len();
+        }
+        Some(field.ok_or(()))
+    }
+}
+
+/// 按模型选择分词器
+pub struct TokenizerRegistry {
+    /// 模型名（或前缀）到分词器
+    tokenizers: HashMap<String, Arc<dyn Tokenizer>>,
+    fallback: Arc<dyn Tokenizer>,
+}
+
+impl TokenizerRegistry {
+    /// 创建只有回退估算的登记表
+    pub fn new() -> Self {
+        Self {
+            tokenizers: HashMap::new(),
+            fallback: Arc::new(HeuristicTokenizer),
+        }
+    }
+
+    /// 为模型（或模型名前缀，如 `gpt-4o`）设置分词器
+    pub fn with_tokenizer(mut self, model: impl Into<String>, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizers.insert(model.into(), tokenizer);
+        self
+    }
+
+    /// 设置未登记模型使用的分词器
+    pub fn with_fallback(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.fallback = tokenizer;
+        self
+    }
+
+    /// 从目录加载词表（`.tiktoken` 与 `.model`），同一文件只加载一次
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let mapping_path = dir.join(TOKENIZER_MAPPING_FILE);
+        let mapping: HashMap<String, String> = if mapping_path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&mapping_path)?)?
+        } else {
+            let mut mapping = HashMap::new();
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if matches!(path.extension().and_then(|e| e.to_str()), Some("tiktoken" | "model")) {
+                    let file = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                    mapping.insert(file_stem(&path), file);
+                }
+            }
+            mapping
+        };
+
+        let mut loaded: HashMap<String, Arc<dyn Tokenizer>> = HashMap::new();
+        let mut registry = Self::new();
+        for (model, file) in mapping {
+            let tokenizer = match loaded.get(&file) {
+                Some(tokenizer) => tokenizer.clone(),
+                None => {
+                    let tokenizer = load_file(&dir.join(&file))?;
+                    loaded.insert(file, tokenizer.clone());
+                    tokenizer
+                }
+            };
+            registry.tokenizers.insert(model, tokenizer);
+        }
+        tracing::info!(
+            "Loaded {} tokenizers for {} models from {}",
+            loaded.len(),
+            registry.tokenizers.len(),
+            dir.display()
+        );
+        Ok(registry)
+    }
+
+    /// 模型的分词器：精确匹配优先，其次取最长的前缀匹配，都没有时使用回退估算
+    pub fn for_model(&self, model: &str) -> Arc<dyn Tokenizer> {
+        if let Some(tokenizer) = self.tokenizers.get(model) {
+            return tokenizer.clone();
+        }
+        self.tokenizers
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, tokenizer)| tokenizer.clone())
+            .unwrap_or_else(|| self.fallback.clone())
+    }
+
+    /// 用模型的分词器计数
+    pub fn count(&self, model: &str, text: &str) -> usize {
+        self.for_model(model).count(text)
+    }
+}
+
+impl Default for TokenizerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TokenizerRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut models: Vec<(&str, &str)> = self.tokenizers.iter().map(|(m, t)| (m.as_str(), t.name())).collect();
+        models.sort();
+        f.debug_struct("TokenizerRegistry")
+            .field("tokenizers", &models)
+            .field("fallback", &self.fallback.name())
+            .finish()
+    }
+}
+
+/// 按扩展名加载词表文件
+fn load_file(path: &Path) -> Result<Arc<dyn Tokenizer>> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("tiktoken") => Ok(Arc::new(BpeTokenizer::load(path)?)),
+        Some("model") => Ok(Arc::new(SentencePieceTokenizer::load(path)?)),
+        _ => Err(invalid_vocab(&path.display().to_string(), "unsupported tokenizer file")),
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn invalid_vocab(name: &str, reason: impl fmt::Display) -> NeuroLoomError {
+    NeuroLoomError::LlmProvider(format!("invalid tokenizer {}: {}", name, reason))
+}
+
+/// 标准 base64 解码（允许省略填充）
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.trim_end_matches('=').bytes() {
+        buffer = (buffer << 6) | u32::from(value(c)?);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        bytes
+            .chunks(3)
+            .flat_map(|chunk| {
+                let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+                (0..=chunk.len()).map(move |i| ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char)
+            })
+            .collect()
+    }
+
+    /// protobuf 长度前缀字段
+    fn proto(field: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![field << 3 | 2, payload.len() as u8];
+        out.extend_from_slice(payload);
+        out
+    }
+
+    #[test]
+    fn test_tokenizers_count_with_vocabularies() {
+        assert_eq!(
+            pretokenize("Hello world's 12345!!\n\n  x", Pretokenizer::Cl100k),
+            ["Hello", " world", "'s", " ", "123", "45", "!!\n\n", " ", " x"]
+        );
+        assert_eq!(
+            pretokenize("HelloWorld's CASEWord ABC 中文A a/b//\n", Pretokenizer::O200k),
+            ["Hello", "World's", " CASEWord", " ABC", " 中文", "A", " a", "/b", "//\n"]
+        );
+
+        // 256 个单字节 + 合并出的 "he"、"ll"、"hell"、"hello"、" w"
+        let merges: [&[u8]; 5] = [b"he", b"ll", b"hell", b"hello", b" w"];
+        let vocab: String = (0..=255u8)
+            .map(|b| vec![b])
+            .chain(merges.iter().map(|m| m.to_vec()))
+            .enumerate()
+            .map(|(rank, token)| format!("{} {}\n", b64(&token), rank))
+            .collect();
+        let bpe = BpeTokenizer::from_tiktoken("tiny", &vocab).unwrap();
+        assert_eq!(bpe.vocab_size(), 261);
+        // "hello" 整词命中，" world" = " w" + o r l d
+        assert_eq!(bpe.count("hello world"), 6);
+        assert!(BpeTokenizer::from_tiktoken("bad", "not-base64!").is_err());
+
+        let piece = |text: &str, score: f32| {
+            let mut fields = proto(1, text.as_bytes());
+            fields.push(2 << 3 | 5);
+            fields.extend_from_slice(&score.to_le_bytes());
+            proto(1, &fields)
+        };
+        let mut model: Vec<u8> = [("▁hello", -1.0), ("▁he", -2.0), ("llo", -2.0), ("▁", -3.0), ("w", -4.0)]
+            .iter()
+            .flat_map(|(text, score)| piece(text, *score))
+            .collect();
+        model.extend(proto(2, &[3 << 3, 1, 0x98, 0x02, 1]));
+        let unigram = SentencePieceTokenizer::from_model("tiny", &model).unwrap();
+        // "▁hello" 一个词片；"▁w" = "▁" + "w"；"ö" 按字节回退为 2 个
+        assert_eq!(unigram.count("hello  w"), 3);
+        assert_eq!(unigram.count("hello ö"), 4);
+
+        let registry = TokenizerRegistry::new()
+            .with_tokenizer("gpt-4", Arc::new(bpe))
+            .with_tokenizer("gemma", Arc::new(unigram));
+        assert_eq!(registry.for_model("gpt-4o-mini").name(), "tiny");
+        assert!(!registry.for_model("gemma-3").is_estimate());
+        let fallback = registry.for_model("claude-sonnet-4");
+        assert!(fallback.is_estimate());
+        assert_eq!(fallback.count("abcdefgh你好"), 4);
+    }
+
+    /// 与 tiktoken 的计数对照。词表较大不随仓库分发：把 `cl100k_base.tiktoken` 与 `o200k_base.tiktoken`
+    /// 放入 `NEUROLOOM_TEST_TOKENIZERS` 指向的目录后运行
+    #[test]
+    fn test_counts_match_tiktoken() {
+        let Some(dir) = std::env::var_os("NEUROLOOM_TEST_TOKENIZERS") else {
+            eprintln!("skipping: NEUROLOOM_TEST_TOKENIZERS is not set");
+            return;
+        };
+        let registry = TokenizerRegistry::load_dir(Path::new(&dir)).unwrap();
+        // (文本, cl100k_base, o200k_base)，取自 tiktoken 的输出
+        let cases = [
+            ("hello world", 2, 2),
+            ("tiktoken is great!", 6, 6),
+            ("2 + 2 = 4", 7, 7),
+            ("antidisestablishmentarianism", 6, 6),
+            ("お誕生日おめでとう", 9, 8),
+        ];
+        for (text, cl100k, o200k) in cases {
+            assert_eq!(registry.count("cl100k_base", text), cl100k, "cl100k_base {:?}", text);
+            assert_eq!(registry.count("o200k_base", text), o200k, "o200k_base {:?}", text);
+        }
+        assert!(!registry.for_model("o200k_base").is_estimate());
+    }
+}
@@ -1,4 +1,8 @@
 //! Actor Mesh - Actor 生命周期管理
+//!
+//! 设置事件总线后，注册、注销与状态切换发布 `ActorSpawned` / `ActorTerminated` /
+//! `ActorSuspended` / `ActorResumed` 事件（`nl top` 据此显示 Actor 列表）。
+//! 设置仿真后，注册与注销记入 `Simulation`，`send` 把消息交给它按种子调度投递（见 `simulation`）。
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -8,10 +12,12 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::{mpsc, RwLock};
 use uuid::Uuid;
 
-use nl_core::event::Event;
-use nl_core::entity::EntityId;
+use nl_core::event::{Event, EventKind};
 use nl_core::Result;
 
+use crate::event_bus::EventBus;
+use crate::simulation::Simulation;
+
 /// Actor ID
 pub type ActorId = Uuid;
 
@@ -50,6 +56,34 @@ pub enum ActorMessage {
     Terminate,
     /// 自定义消息
     Custom(String, serde_json::Value),
+    /// 带幂等键的命令（重投递时由 `IdempotencyStore::dispatch` 去重）
+    Command {
+        key: String,
+        message: Box<ActorMessage>,
+    },
+}
+
+impl ActorMessage {
+    /// 包装为带幂等键的命令
+    pub fn command(key: impl Into<String>, message: ActorMessage) -> Self {
+        ActorMessage::Command {
+            key: key.into(),
+            message: Box::new(message),
+        }
+    }
+
+    /// 命令描述（用于校验同一个幂等键只对应同一个命令）
+    pub fn describe(&self) -> String {
+        match self {
+            ActorMessage::ProcessEvent(event) => format!("process_event:{}", event.id),
+            ActorMessage::Suspend => "suspend".to_string(),
+            ActorMessage::Resume => "resume".to_string(),
+            ActorMessage::Hibernate => "hibernate".to_string(),
+            ActorMessage::Terminate => "terminate".to_string(),
+            ActorMessage::Custom(name, _) => format!("custom:{}", name),
+            ActorMessage::Command { message, .. } => message.describe(),
+        }
+    }
 }
 
 /// Actor 特征
@@ -79,6 +113,10 @@ pub struct ActorContext {
 pub struct ActorMesh {
     actors: Arc<RwLock<HashMap<ActorId, ActorAddress>>>,
     states: Arc<RwLock<HashMap<ActorId, ActorState>>>,
+    /// 生命周期事件总线
+    bus: Option<Arc<EventBus>>,
+    /// 确定性仿真（测试用）
+    simulation: Option<Arc<Simulation>>,
 }
 
 impl ActorMesh {
@@ -87,9 +125,23 @@ impl ActorMesh {
         Self {
             actors: Arc::new(RwLock::new(HashMap::new())),
             states: Arc::new(RwLock::new(HashMap::new())),
+            bus: None,
+            simulation: None,
         }
     }
 
+    /// 发布 Actor 生命周期事件
+    pub fn with_event_bus(mut self, bus: Arc<EventBus>) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// 注册、注销记入仿真，消息改由仿真调度投递
+    pub fn with_simulation(mut self, simulation: Arc<Simulation>) -> Self {
+        self.simulation = Some(simulation);
+        self
+    }
+
     /// 生成 Actor ID
     pub fn generate_id() -> ActorId {
         Uuid::new_v4()
@@ -99,24 +151,37 @@ impl ActorMesh {
     pub async fn register(&self, id: ActorId, address: ActorAddress) {
         let mut actors = self.actors.write().await;
         actors.insert(id, address);
+        if let Some(simulation) = &self.simulation {
+            simulation.register(id);
+        }
 
         let mut states = self.states.write().await;
         states.insert(id, ActorState::Running);
+        self.publish(EventKind::ActorSpawned, id, ActorState::Running);
     }
 
     /// 注销 Actor
     pub async fn unregister(&self, id: &ActorId) {
         let mut actors = self.actors.write().await;
         actors.remove(id);
+        if let Some(simulation) = &self.simulation {
+            simulation.unregister(id);
+        }
 
         let mut states = self.states.write().await;
-        states.remove(id);
+        if states.remove(id).is_some() {
+            self.publish(EventKind::ActorTerminated, *id, ActorState::Terminated);
+        }
     }
 
     /// 发送消息到 Actor
     pub async fn send(&self, id: &ActorId, msg: ActorMessage) -> Result<()> {
         let actors = self.actors.read().await;
         if let Some(addr) = actors.get(id) {
+            if let Some(simulation) = &self.simulation {
+                simulation.enqueue(*id, addr.sender.clone(), msg);
+                return Ok(());
+            }
             addr.sender
                 .send(msg)
                 .await
@@ -125,6 +190,11 @@ impl ActorMesh {
         Ok(())
     }
 
+    /// 发送带幂等键的命令（接收方通过 `IdempotencyStore::dispatch` 处理）
+    pub async fn send_command(&self, id: &ActorId, key: impl Into<String>, msg: ActorMessage) -> Result<()> {
+        self.send(id, ActorMessage::command(key, msg)).await
+    }
+
     /// 获取 Actor 状态
     pub async fn get_state(&self, id: &ActorId) -> Option<ActorState> {
         let states = self.states.read().await;
@@ -134,7 +204,15 @@ impl ActorMesh {
     /// 更新 Actor 状态
     pub async fn set_state(&self, id: &ActorId, state: ActorState) {
         let mut states = self.states.write().await;
-        states.insert(*id, state);
+        let previous = states.insert(*id, state);
+        let kind = match state {
+            ActorState::Suspended | ActorState::Hibernated => EventKind::ActorSuspended,
+            ActorState::Running => EventKind::ActorResumed,
+            ActorState::Terminated => EventKind::ActorTerminated,
+        };
+        if previous != Some(state) {
+            self.publish(kind, *id, state);
+        }
     }
 
     /// 获取所有 Actor ID
@@ -148,6 +226,12 @@ impl ActorMesh {
         let actors = self.actors.read().await;
         actors.len()
     }
+
+    fn publish(&self, kind: EventKind, id: ActorId, state: ActorState) {
+        if let Some(bus) = &self.bus {
+            bus.publish(&Event::new(kind, id, serde_json::json!({ "state": state })));
+        }
+    }
 }
 
 impl Default for ActorMesh {
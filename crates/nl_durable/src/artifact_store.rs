@@ -0,0 +1,228 @@
+//! 内容寻址产物库
+//!
+//! 产物按内容的 SHA-256 存放：`<dir>/<哈希前两位>/<哈希>` 为内容，同名 `.json` 为元数据。
+//! 相同内容只存一份（保留首次写入的元数据）；写入先落临时文件再改名，读到的产物总是完整的。
+//! 回收时由调用方给出仍被引用的哈希（通常由 `referenced_by` 扫描事件得到），
+//! 未被引用且超过宽限期的产物被删除；宽限期保护刚写入、引用事件尚未落库的产物。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use ring::digest::{digest, SHA256};
+use serde::Serialize;
+use uuid::Uuid;
+
+use nl_core::artifact::{collect_artifact_refs, is_artifact_hash, Artifact, ArtifactKind};
+use nl_core::event::Event;
+use nl_core::Result;
+
+/// 回收统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GcStats {
+    /// 删除的产物数
+    pub removed: usize,
+    /// 释放的字节数
+    pub freed_bytes: u64,
+    /// 保留的产物数
+    pub retained: usize,
+}
+
+/// 产物库
+pub struct ArtifactStore {
+    dir: PathBuf,
+}
+
+impl ArtifactStore {
+    /// 打开（必要时创建）产物目录
+    pub async fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&dir).await?;
+        Ok(Self { dir })
+    }
+
+    /// 写入产物，返回元数据（内容已存在时返回已有的元数据）
+    pub async fn put(
+        &self,
+        content: &[u8],
+        kind: ArtifactKind,
+        media_type: &str,
+        task_id: Option<Uuid>,
+    ) -> Result<Artifact> {
+        let hash = content_hash(content);
+        if let Some(existing) = self.get_meta(&hash).await? {
+            return Ok(existing);
+        }
+        let artifact = Artifact {
+            hash: hash.clone(),
+            task_id,
+            kind,
+            media_type: media_type.to_string(),
+            size: content.len() as u64,
+            created_at: Utc::now(),
+        };
+        let blob = self.blob_path(&hash);
+        if let Some(parent) = blob.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        write_atomic(&blob, content).await?;
+        write_atomic(&blob.with_extension("json"), &serde_json::to_vec(&artifact)?).await?;
+        Ok(artifact)
+    }
+
+    /// JSON 值序列化后超过 `threshold` 字节时存入产物库并替换为引用 URI，否则原样返回
+    pub async fn inline_or_store(
+        &self,
+        value: serde_json::Value,
+        threshold: usize,
+        kind: ArtifactKind,
+        task_id: Option<Uuid>,
+    ) -> Result<serde_json::Value> {
+        let content = serde_json::to_vec(&value)?;
+        if content.len() <= threshold {
+            return Ok(value);
+        }
+        let artifact = self.put(&content, kind, "application/json", task_id).await?;
+        Ok(serde_json::Value::String(artifact.uri()))
+    }
+
+    /// 读取产物内容
+    pub async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        if !is_artifact_hash(hash) {
+            return Ok(None);
+        }
+        match tokio::fs::read(self.blob_path(hash)).await {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 读取产物元数据
+    pub async fn get_meta(&self, hash: &str) -> Result<Option<Artifact>> {
+        if !is_artifact_hash(hash) {
+            return Ok(None);
+        }
+        match tokio::fs::read(self.blob_path(hash).with_extension("json")).await {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 全部产物（按写入时间排序），`task_id` 非空时只列出该任务的产物
+    pub async fn list(&self, task_id: Option<Uuid>) -> Result<Vec<Artifact>> {
+        let mut artifacts = Vec::new();
+        let mut shards = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(shard) = shards.next_entry().await? {
+            if !shard.file_type().await?.is_dir() {
+                continue;
+            }
+            let mut entries = tokio::fs::read_dir(shard.path()).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                match serde_json::from_slice::<Artifact>(&tokio::fs::read(&path).await?) {
+                    Ok(artifact) if task_id.is_none() || artifact.task_id == task_id => artifacts.push(artifact),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Skipping unreadable artifact metadata {}: {}", path.display(), e),
+                }
+            }
+        }
+        artifacts.sort_by_key(|a| a.created_at);
+        Ok(artifacts)
+    }
+
+    /// 删除未被引用且早于 `now - grace` 写入的产物
+    pub async fn gc(
+        &self,
+        referenced: &HashSet<String>,
+        grace: chrono::Duration,
+        now: DateTime<Utc>,
+    ) -> Result<GcStats> {
+        let mut stats = GcStats::default();
+        for artifact in self.list(None).await? {
+            if referenced.contains(&artifact.hash) || artifact.created_at > now - grace {
+                stats.retained += 1;
+                continue;
+            }
+            let blob = self.blob_path(&artifact.hash);
+            remove_if_exists(&blob).await?;
+            remove_if_exists(&blob.with_extension("json")).await?;
+            stats.removed += 1;
+            stats.freed_bytes += artifact.size;
+        }
+        Ok(stats)
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(&hash[..2]).join(hash)
+    }
+}
+
+/// 事件中引用的全部产物哈希
+pub fn referenced_by(events: &[Event]) -> HashSet<String> {
+    let mut refs = HashSet::new();
+    for event in events {
+        collect_artifact_refs(&event.payload, &mut refs);
+    }
+    refs
+}
+
+/// 内容的 SHA-256（小写十六进制）
+pub fn content_hash(content: &[u8]) -> String {
+    digest(&SHA256, content).as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let tmp = path.with_extension(format!("tmp-{}", Uuid::new_v4().simple()));
+    tokio::fs::write(&tmp, content).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+async fn remove_if_exists(path: &Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nl_core::EventKind;
+
+    #[tokio::test]
+    async fn test_artifacts_dedupe_and_unreferenced_ones_are_collected() {
+        let dir = std::env::temp_dir().join(format!("nl-artifacts-{}", Uuid::new_v4()));
+        let store = ArtifactStore::open(&dir).await.unwrap();
+        let task = Uuid::new_v4();
+        let patch = store.put(b"--- a\n+++ b\n", ArtifactKind::Patch, "text/x-diff", Some(task)).await.unwrap();
+        let again = store.put(b"--- a\n+++ b\n", ArtifactKind::Report, "text/plain", None).await.unwrap();
+        assert_eq!(again, patch);
+        assert_eq!(store.get(&patch.hash).await.unwrap().unwrap(), b"--- a\n+++ b\n");
+        assert!(store.get("../../etc/passwd").await.unwrap().is_none());
+
+        let small = serde_json::json!({ "ok": true });
+        assert_eq!(store.inline_or_store(small.clone(), 1024, ArtifactKind::Report, None).await.unwrap(), small);
+        let large = serde_json::json!({ "log": "x".repeat(2048) });
+        let stored = store.inline_or_store(large, 1024, ArtifactKind::Log, Some(task)).await.unwrap();
+        let log_hash = nl_core::artifact::parse_artifact_uri(stored.as_str().unwrap()).unwrap().to_string();
+        assert_eq!(store.list(Some(task)).await.unwrap().len(), 2);
+
+        let events = vec![Event::new(EventKind::TaskCompleted, task, serde_json::json!({ "patch": patch.uri() }))];
+        let referenced = referenced_by(&events);
+        let now = Utc::now();
+        let kept = store.gc(&referenced, chrono::Duration::hours(1), now).await.unwrap();
+        assert_eq!(kept.removed, 0);
+        let stats = store.gc(&referenced, chrono::Duration::zero(), now + chrono::Duration::seconds(1)).await.unwrap();
+        assert_eq!((stats.removed, stats.retained), (1, 1));
+        assert!(store.get(&log_hash).await.unwrap().is_none());
+        assert!(store.get_meta(&patch.hash).await.unwrap().is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! 可移植工作区包
+//!
+//! 把工作区状态打包为单个 gzip 压缩的 JSON 文件，用于在机器之间迁移训练好的工作区：
+//! - 带格式版本号，读取时拒绝更高版本的包
+//! - 事件直接取自 `EventStore`；记忆、图谱、SOP 等状态以命名分区存放，由各自的 crate 序列化
+//! - 导入时按 `ConflictPolicy` 处理已存在的条目（跳过 / 覆盖 / 合并）
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use nl_core::{Event, NeuroLoomError, Result};
+
+use crate::event_store::EventStore;
+
+/// 当前包格式版本
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// 导入冲突处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// 保留本地条目
+    #[default]
+    Skip,
+    /// 用包内条目替换本地条目
+    Overwrite,
+    /// 合并两者（具体规则由各类状态定义）
+    Merge,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = NeuroLoomError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "merge" => Ok(Self::Merge),
+            other => Err(NeuroLoomError::Unknown(format!(
+                "unknown conflict policy: {} (expected skip, overwrite or merge)",
+                other
+            ))),
+        }
+    }
+}
+
+/// 导入统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportStats {
+    /// 新增条目
+    pub added: usize,
+    /// 因冲突跳过的条目
+    pub skipped: usize,
+    /// 覆盖的条目
+    pub overwritten: usize,
+    /// 合并的条目
+    pub merged: usize,
+}
+
+impl ImportStats {
+    /// 按策略记录一次冲突
+    pub fn record_conflict(&mut self, policy: ConflictPolicy) {
+        match policy {
+            ConflictPolicy::Skip => self.skipped += 1,
+            ConflictPolicy::Overwrite => self.overwritten += 1,
+            ConflictPolicy::Merge => self.merged += 1,
+        }
+    }
+}
+
+/// 工作区包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceBundle {
+    /// 格式版本
+    pub version: u32,
+    /// 来源工作区名称
+    pub workspace: String,
+    /// 打包时间
+    pub created_at: DateTime<Utc>,
+    /// 事件
+    #[serde(default)]
+    pub events: Vec<Event>,
+    /// 命名分区（记忆、图谱、SOP 等）
+    #[serde(default)]
+    pub sections: BTreeMap<String, serde_json::Value>,
+}
+
+impl WorkspaceBundle {
+    /// 创建空包
+    pub fn new(workspace: impl Into<String>) -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            workspace: workspace.into(),
+            created_at: Utc::now(),
+            events: Vec::new(),
+            sections: BTreeMap::new(),
+        }
+    }
+
+    /// 收录事件库中的全部事件
+    pub async fn with_events_from(mut self, store: &EventStore) -> Result<Self> {
+        self.events = store.all_events().await?;
+        Ok(self)
+    }
+
+    /// 写入命名分区
+    pub fn with_section(mut self, name: impl Into<String>, value: &impl Serialize) -> Result<Self> {
+        self.sections.insert(name.into(), serde_json::to_value(value)?);
+        Ok(self)
+    }
+
+    /// 读取命名分区，不存在时返回 `None`
+    pub fn section<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>> {
+        self.sections
+            .get(name)
+            .map(|value| serde_json::from_value(value.clone()))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// 编码为压缩字节
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// 从压缩字节解码，并校验格式版本
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut json = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut json)?;
+        let bundle: Self = serde_json::from_slice(&json)?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(NeuroLoomError::Unknown(format!(
+                "bundle version {} is newer than supported version {}",
+                bundle.version, BUNDLE_VERSION
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// 写入文件
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// 读取文件
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nl_core::EventKind;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_bundle_roundtrip_and_event_import() {
+        let mut source = EventStore::new(Default::default());
+        let entity = Uuid::new_v4();
+        for i in 0..3 {
+            source
+                .append(Event::new(EventKind::NodeCreated, entity, serde_json::json!({ "i": i })))
+                .await
+                .unwrap();
+        }
+
+        let bundle = WorkspaceBundle::new("proj")
+            .with_events_from(&source)
+            .await
+            .unwrap()
+            .with_section("notes", &vec!["a", "b"])
+            .unwrap();
+        let decoded = WorkspaceBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.events.len(), 3);
+        assert_eq!(decoded.section::<Vec<String>>("notes").unwrap().unwrap(), vec!["a", "b"]);
+        assert!(decoded.section::<Vec<String>>("missing").unwrap().is_none());
+
+        let mut target = EventStore::new(Default::default());
+        target.append(decoded.events[0].clone()).await.unwrap();
+        let stats = target
+            .import_events(decoded.events.clone(), ConflictPolicy::Skip)
+            .await
+            .unwrap();
+        assert_eq!((stats.added, stats.skipped), (2, 1));
+        assert_eq!(target.count().await.unwrap(), 3);
+
+        let stats = target
+            .import_events(decoded.events, ConflictPolicy::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(stats.overwritten, 3);
+        assert_eq!(target.count().await.unwrap(), 3);
+
+        let mut newer = bundle.clone();
+        newer.version = BUNDLE_VERSION + 1;
+        assert!(WorkspaceBundle::from_bytes(&newer.to_bytes().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! 任务取消
+//!
+//! 每个运行中的任务在登记表中持有一个取消令牌；编排器把令牌（或其子令牌）继续传给
+//! Gateway、MCTS、SOP 与沙箱。`cancel` 触发令牌后，各层在下一个检查点停止并释放资源
+//! （中断 HTTP 请求、终止子进程），同时发布 `TaskCancelled` 事件。
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use uuid::Uuid;
+
+use nl_core::event::{Event, EventKind};
+
+use crate::event_bus::EventBus;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// 取消令牌登记表
+#[derive(Default)]
+pub struct CancellationRegistry {
+    tokens: RwLock<HashMap<Uuid, CancellationToken>>,
+    bus: Option<Arc<EventBus>>,
+}
+
+impl CancellationRegistry {
+    /// 创建登记表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 取消时发布 `TaskCancelled` 事件
+    pub fn with_event_bus(mut self, bus: Arc<EventBus>) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// 登记任务并返回其取消令牌（已登记时返回同一令牌）
+    pub fn register(&self, task_id: Uuid) -> CancellationToken {
+        self.tokens
+            .write()
+            .unwrap()
+            .entry(task_id)
+            .or_default()
+            .clone()
+    }
+
+    /// 任务结束后移除令牌
+    pub fn remove(&self, task_id: Uuid) {
+        self.tokens.write().unwrap().remove(&task_id);
+    }
+
+    /// 取消任务，任务未登记时返回 `false`
+    pub fn cancel(&self, task_id: Uuid, reason: &str) -> bool {
+        let Some(token) = self.tokens.read().unwrap().get(&task_id).cloned() else {
+            return false;
+        };
+        if token.is_cancelled() {
+            return true;
+        }
+        token.cancel();
+        tracing::info!("Task {} cancelled: {}", task_id, reason);
+        if let Some(bus) = &self.bus {
+            bus.publish(&Event::new(
+                EventKind::TaskCancelled,
+                task_id,
+                serde_json::json!({ "reason": reason }),
+            ));
+        }
+        true
+    }
+
+    /// 任务是否已被取消
+    pub fn is_cancelled(&self, task_id: Uuid) -> bool {
+        self.tokens
+            .read()
+            .unwrap()
+            .get(&task_id)
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// 当前登记的任务
+    pub fn running(&self) -> Vec<Uuid> {
+        self.tokens.read().unwrap().keys().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::EventBusConfig;
+
+    #[tokio::test]
+    async fn test_cancel_triggers_token_and_publishes_event() {
+        let bus = Arc::new(EventBus::new(EventBusConfig::default()));
+        let mut events = bus.subscribe(EventKind::TaskCancelled);
+        let registry = CancellationRegistry::new().with_event_bus(bus);
+
+        let task = Uuid::new_v4();
+        let token = registry.register(task);
+        let child = token.child_token();
+        assert!(!registry.cancel(Uuid::new_v4(), "unknown"));
+
+        assert!(registry.cancel(task, "user request"));
+        assert!(child.is_cancelled());
+        assert!(registry.is_cancelled(task));
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.entity_id, task);
+        assert_eq!(event.payload["reason"], "user request");
+
+        registry.remove(task);
+        assert!(registry.running().is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! 事件存储静态加密
+//!
+//! 事件正文（`data` 列，含提示词、代码差异、API 流量）以 AES-256-GCM 加密后落盘；
+//! 类型、实体 ID、关联 ID 等索引列保持明文以支持查询。
+//!
+//! 主密钥来自环境变量中的口令，经 PBKDF2-HMAC-SHA256 拉伸（迭代次数与随机盐保存在数据库旁的
+//! `db_key.json`，每个数据库各自的盐），再经 HKDF-SHA256 按用途派生加密密钥；口令与主密钥本身不被保存。
+//! 参数文件同时保存主密钥的校验值，口令错误时打开即报错，而不是在解密时才发现。未加密的历史行仍可读取。
+
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hkdf::{Salt, HKDF_SHA256};
+use ring::pbkdf2::PBKDF2_HMAC_SHA256;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use nl_core::{NeuroLoomError, Result};
+
+/// 密文前缀（带版本号，便于日后轮换算法）
+const CIPHERTEXT_PREFIX: &str = "enc:v1:";
+
+/// HKDF 盐
+const KDF_SALT: &[u8] = b"neuroloom-event-store";
+
+/// 主密钥环境变量
+pub const MASTER_KEY_ENV: &str = "NEUROLOOM_DB_KEY";
+
+/// 口令拉伸参数文件（位于数据库所在目录）
+pub const KEY_PARAMS_FILE: &str = "db_key.json";
+
+/// 新数据库的 PBKDF2 迭代次数
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// 主密钥长度
+const MASTER_KEY_LEN: usize = 32;
+
+/// 校验值的 HKDF 用途
+const CHECK_CONTEXT: &str = "key-check";
+
+/// 校验值长度
+const CHECK_LEN: usize = 16;
+
+struct CheckLen;
+
+impl ring::hkdf::KeyType for CheckLen {
+    fn len(&self) -> usize {
+        CHECK_LEN
+    }
+}
+
+/// 口令拉伸参数
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyParams {
+    kdf: String,
+    iterations: u32,
+    /// 随机盐（base64）
+    salt: String,
+    /// 主密钥校验值（base64）
+    check: String,
+}
+
+/// 由口令拉伸得到的主密钥
+pub struct MasterKey([u8; MASTER_KEY_LEN]);
+
+impl MasterKey {
+    /// 从环境变量读取口令并按 `dir` 下的参数文件拉伸，未设置时返回 `None`
+    pub fn from_env(dir: &Path) -> Result<Option<Self>> {
+        match std::env::var(MASTER_KEY_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => Self::from_passphrase(passphrase.as_bytes(), dir).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// 拉伸口令；`dir` 下还没有参数文件时生成随机盐并写入
+    pub fn from_passphrase(passphrase: &[u8], dir: &Path) -> Result<Self> {
+        Self::load_or_create(passphrase, &dir.join(KEY_PARAMS_FILE), PBKDF2_ITERATIONS)
+    }
+
+    fn load_or_create(passphrase: &[u8], path: &Path, iterations: u32) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(NeuroLoomError::EventStore("empty master key".to_string()));
+        }
+        let params: Option<KeyParams> = match std::fs::read_to_string(path) {
+            Ok(content) => Some(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let Some(params) = params else {
+            let mut salt = [0u8; 16];
+            SystemRandom::new()
+                .fill(&mut salt)
+                .map_err(|_| NeuroLoomError::EventStore("failed to generate salt".to_string()))?;
+            let key = Self::stretch(passphrase, &salt, iterations)?;
+            let params = KeyParams {
+                kdf: "pbkdf2-sha256".to_string(),
+                iterations,
+                salt: STANDARD.encode(salt),
+                check: key.check()?,
+            };
+            std::fs::write(path, serde_json::to_string_pretty(&params)?)?;
+            return Ok(key);
+        };
+
+        if params.kdf != "pbkdf2-sha256" {
+            return Err(NeuroLoomError::EventStore(format!("unsupported key derivation: {}", params.kdf)));
+        }
+        let salt = STANDARD
+            .decode(&params.salt)
+            .map_err(|e| NeuroLoomError::EventStore(e.to_string()))?;
+        let key = Self::stretch(passphrase, &salt, params.iterations)?;
+        if key.check()? != params.check {
+            return Err(NeuroLoomError::EventStore(format!(
+                "{} does not match the key this database was encrypted with ({})",
+                MASTER_KEY_ENV,
+                path.display()
+            )));
+        }
+        Ok(key)
+    }
+
+    fn stretch(passphrase: &[u8], salt: &[u8], iterations: u32) -> Result<Self> {
+        let iterations = NonZeroU32::new(iterations)
+            .ok_or_else(|| NeuroLoomError::EventStore("invalid PBKDF2 iteration count".to_string()))?;
+        let mut key = [0u8; MASTER_KEY_LEN];
+        ring::pbkdf2::derive(PBKDF2_HMAC_SHA256, iterations, salt, passphrase, &mut key);
+        Ok(Self(key))
+    }
+
+    /// 校验值：由主密钥经 HKDF 派生，与各用途的加密密钥互相独立
+    fn check(&self) -> Result<String> {
+        let mut check = [0u8; CHECK_LEN];
+        Salt::new(HKDF_SHA256, KDF_SALT)
+            .extract(&self.0)
+            .expand(&[CHECK_CONTEXT.as_bytes()], CheckLen)
+            .and_then(|okm| okm.fill(&mut check))
+            .map_err(|_| NeuroLoomError::EventStore("key derivation failed".to_string()))?;
+        Ok(STANDARD.encode(check))
+    }
+
+    /// 按用途派生加密器
+    pub fn cipher(&self, context: &str) -> Result<EventCipher> {
+        EventCipher::derive(&self.0, context)
+    }
+}
+
+/// 事件加密器
+pub struct EventCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl EventCipher {
+    /// 由主密钥派生加密密钥，`context` 区分不同用途（如 "events"、"archives"）
+    ///
+    /// `master_key` 须是高熵密钥；口令先经 [`MasterKey`] 拉伸。
+    pub fn derive(master_key: &[u8], context: &str) -> Result<Self> {
+        if master_key.is_empty() {
+            return Err(NeuroLoomError::EventStore("empty master key".to_string()));
+        }
+        let prk = Salt::new(HKDF_SHA256, KDF_SALT).extract(master_key);
+        let info = [context.as_bytes()];
+        let okm = prk
+            .expand(&info, &AES_256_GCM)
+            .map_err(|_| NeuroLoomError::EventStore("key derivation failed".to_string()))?;
+        Ok(Self {
+            key: LessSafeKey::new(UnboundKey::from(okm)),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// 加密，`aad` 为绑定的附加数据（如事件 ID，防止密文被挪用到其他行）
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| NeuroLoomError::EventStore("failed to generate nonce".to_string()))?;
+
+        let mut in_out = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(aad), &mut in_out)
+            .map_err(|_| NeuroLoomError::EventStore("encryption failed".to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&in_out);
+        Ok(format!("{}{}", CIPHERTEXT_PREFIX, STANDARD.encode(sealed)))
+    }
+
+    /// 解密；不带密文前缀的数据视为明文原样返回
+    pub fn decrypt(&self, data: &str, aad: &[u8]) -> Result<Vec<u8>> {
+        let Some(encoded) = data.strip_prefix(CIPHERTEXT_PREFIX) else {
+            return Ok(data.as_bytes().to_vec());
+        };
+        let mut sealed = STANDARD
+            .decode(encoded)
+            .map_err(|e| NeuroLoomError::EventStore(e.to_string()))?;
+        if sealed.len() < NONCE_LEN {
+            return Err(NeuroLoomError::EventStore("ciphertext too short".to_string()));
+        }
+
+        let mut in_out = sealed.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&sealed)
+            .map_err(|_| NeuroLoomError::EventStore("invalid nonce".to_string()))?;
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::from(aad), &mut in_out)
+            .map_err(|_| NeuroLoomError::EventStore("decryption failed: wrong key or corrupted data".to_string()))?;
+        Ok(plaintext.to_vec())
+    }
+
+    /// 判断数据是否为密文
+    pub fn is_encrypted(data: &str) -> bool {
+        data.starts_with(CIPHERTEXT_PREFIX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_and_wrong_key() {
+        let cipher = EventCipher::derive(b"master secret", "events").unwrap();
+        let sealed = cipher.encrypt(b"{\"prompt\":\"hi\"}", b"event-1").unwrap();
+
+        assert!(EventCipher::is_encrypted(&sealed));
+        assert!(!sealed.contains("prompt"));
+        assert_eq!(cipher.decrypt(&sealed, b"event-1").unwrap(), b"{\"prompt\":\"hi\"}");
+        // 绑定的事件 ID 不符时解密失败
+        assert!(cipher.decrypt(&sealed, b"event-2").is_err());
+
+        let other = EventCipher::derive(b"other secret", "events").unwrap();
+        assert!(other.decrypt(&sealed, b"event-1").is_err());
+
+        // 历史明文直接返回
+        assert_eq!(cipher.decrypt("{}", b"event-1").unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_passphrase_is_stretched_with_per_database_salt() {
+        let dir = std::env::temp_dir().join(format!("nl_key_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        let open = |db: &str, passphrase: &[u8]| {
+            MasterKey::load_or_create(passphrase, &dir.join(db).join(KEY_PARAMS_FILE), 1_000)
+        };
+
+        let sealed = open("a", b"hunter2").unwrap().cipher("events").unwrap().encrypt(b"secret", b"e").unwrap();
+        // 重新打开使用同一份盐
+        let reopened = open("a", b"hunter2").unwrap().cipher("events").unwrap();
+        assert_eq!(reopened.decrypt(&sealed, b"e").unwrap(), b"secret");
+        // 同一口令在另一个数据库得到不同的密钥
+        assert!(open("b", b"hunter2").unwrap().cipher("events").unwrap().decrypt(&sealed, b"e").is_err());
+        // 口令错误时打开即失败
+        assert!(open("a", b"hunter3").is_err());
+
+        let params: KeyParams =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("a").join(KEY_PARAMS_FILE)).unwrap()).unwrap();
+        assert_eq!((params.kdf.as_str(), params.iterations), ("pbkdf2-sha256", 1_000));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,193 @@
+//! 进程内事件总线
+//!
+//! 各子系统通过按 `EventKind` 划分的广播通道订阅事件，无需轮询 EventStore。
+//! 通道容量有限：慢订阅者落后超过容量时丢弃最旧的事件，并记录滞后计数。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use nl_core::event::{Event, EventKind};
+
+/// 事件总线配置
+#[derive(Debug, Clone)]
+pub struct EventBusConfig {
+    /// 每个通道的缓冲容量
+    pub capacity: usize,
+}
+
+impl Default for EventBusConfig {
+    fn default() -> Self {
+        Self { capacity: 1024 }
+    }
+}
+
+/// 事件总线指标
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventBusMetrics {
+    /// 已发布事件数
+    pub published: u64,
+    /// 已投递给订阅者的事件数
+    pub delivered: u64,
+    /// 因订阅者落后而丢弃的事件数
+    pub lagged: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    published: AtomicU64,
+    delivered: AtomicU64,
+    lagged: AtomicU64,
+}
+
+/// 事件总线
+pub struct EventBus {
+    config: EventBusConfig,
+    /// 按事件类型划分的通道
+    channels: RwLock<HashMap<EventKind, broadcast::Sender<Event>>>,
+    /// 接收全部事件的通道
+    all: broadcast::Sender<Event>,
+    counters: Arc<Counters>,
+}
+
+impl EventBus {
+    /// 创建新的事件总线
+    pub fn new(config: EventBusConfig) -> Self {
+        let (all, _) = broadcast::channel(config.capacity.max(1));
+        Self {
+            config,
+            channels: RwLock::new(HashMap::new()),
+            all,
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// 发布事件，返回收到事件的订阅者数量
+    pub fn publish(&self, event: &Event) -> usize {
+        self.counters.published.fetch_add(1, Ordering::Relaxed);
+        let mut receivers = self.all.send(event.clone()).unwrap_or(0);
+        if let Some(sender) = self.channels.read().unwrap().get(&event.kind) {
+            receivers += sender.send(event.clone()).unwrap_or(0);
+        }
+        receivers
+    }
+
+    /// 订阅指定类型的事件
+    pub fn subscribe(&self, kind: EventKind) -> EventSubscription {
+        let mut channels = self.channels.write().unwrap();
+        let sender = channels
+            .entry(kind)
+            .or_insert_with(|| broadcast::channel(self.config.capacity.max(1)).0);
+        EventSubscription::new(sender.subscribe(), self.counters.clone())
+    }
+
+    /// 订阅全部事件
+    pub fn subscribe_all(&self) -> EventSubscription {
+        EventSubscription::new(self.all.subscribe(), self.counters.clone())
+    }
+
+    /// 当前订阅者数量
+    pub fn subscriber_count(&self) -> usize {
+        let typed: usize = self
+            .channels
+            .read()
+            .unwrap()
+            .values()
+            .map(|s| s.receiver_count())
+            .sum();
+        typed + self.all.receiver_count()
+    }
+
+    /// 获取总线指标
+    pub fn metrics(&self) -> EventBusMetrics {
+        EventBusMetrics {
+            published: self.counters.published.load(Ordering::Relaxed),
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            lagged: self.counters.lagged.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(EventBusConfig::default())
+    }
+}
+
+/// 事件订阅
+pub struct EventSubscription {
+    receiver: broadcast::Receiver<Event>,
+    counters: Arc<Counters>,
+    /// 本订阅丢失的事件数
+    lagged: u64,
+}
+
+impl EventSubscription {
+    fn new(receiver: broadcast::Receiver<Event>, counters: Arc<Counters>) -> Self {
+        Self {
+            receiver,
+            counters,
+            lagged: 0,
+        }
+    }
+
+    /// 接收下一个事件，总线关闭时返回 `None`
+    ///
+    /// 落后过多时跳过丢失的事件并记录滞后计数。
+    pub async fn recv(&mut self) -> Option<Event> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => {
+                    self.counters.delivered.fetch_add(1, Ordering::Relaxed);
+                    return Some(event);
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    self.lagged += skipped;
+                    self.counters.lagged.fetch_add(skipped, Ordering::Relaxed);
+                    tracing::warn!("event subscriber lagged, skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// 本订阅累计丢失的事件数
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_typed_subscription_filters_kind() {
+        let bus = EventBus::default();
+        let mut completed = bus.subscribe(EventKind::TaskCompleted);
+        let mut all = bus.subscribe_all();
+
+        bus.publish(&Event::new(EventKind::NodeUpdated, uuid::Uuid::new_v4(), serde_json::json!({})));
+        bus.publish(&Event::new(EventKind::TaskCompleted, uuid::Uuid::new_v4(), serde_json::json!({})));
+
+        assert_eq!(completed.recv().await.unwrap().kind, EventKind::TaskCompleted);
+        assert_eq!(all.recv().await.unwrap().kind, EventKind::NodeUpdated);
+        assert_eq!(bus.metrics().published, 2);
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_records_lag() {
+        let bus = EventBus::new(EventBusConfig { capacity: 2 });
+        let mut sub = bus.subscribe(EventKind::NodeUpdated);
+
+        for _ in 0..5 {
+            bus.publish(&Event::new(EventKind::NodeUpdated, uuid::Uuid::new_v4(), serde_json::json!({})));
+        }
+
+        assert!(sub.recv().await.is_some());
+        assert_eq!(sub.lagged(), 3);
+        assert_eq!(bus.metrics().lagged, 3);
+    }
+}
@@ -1,14 +1,90 @@
 //! 事件存储引擎
 
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
+use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::Connection;
 use uuid::Uuid;
 
 use nl_core::event::{Event, EventKind};
 use nl_core::entity::EntityId;
-use nl_core::Result;
+use nl_core::{NeuroLoomError, Result};
+
+use crate::bundle::{ConflictPolicy, ImportStats};
+use crate::encryption::EventCipher;
+use crate::event_bus::EventBus;
+use crate::parquet::{self, ArchivedEvent};
+use crate::redaction::Redactor;
+use crate::shard::{self, EventShard, ShardFormat, ShardPolicy};
+
+/// 事件表结构，`schema` 为空时建在主库，否则建在挂载的分片库
+fn events_table_sql(schema: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {}events (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            id TEXT NOT NULL UNIQUE,
+            kind TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            correlation_id TEXT,
+            data TEXT NOT NULL
+        )",
+        schema
+    )
+}
+
+/// 事件表索引（索引列与时间范围查询用的 `timestamp`），`schema` 规则同 `events_table_sql`
+fn events_indexes_sql(schema: &str) -> Vec<String> {
+    IndexedColumn::ALL
+        .iter()
+        .map(|column| (column.index_name(), column.name()))
+        .chain([("idx_events_timestamp", "timestamp")])
+        .map(|(index, column)| format!("CREATE INDEX IF NOT EXISTS {}{} ON events({})", schema, index, column))
+        .collect()
+}
+
+/// 分片库挂载名
+const SHARD_SCHEMA: &str = "shard";
+
+/// 建有索引、可按值查询的列
+#[derive(Debug, Clone, Copy)]
+enum IndexedColumn {
+    Entity,
+    Kind,
+    Correlation,
+}
+
+impl IndexedColumn {
+    const ALL: [IndexedColumn; 3] = [Self::Entity, Self::Kind, Self::Correlation];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Entity => "entity_id",
+            Self::Kind => "kind",
+            Self::Correlation => "correlation_id",
+        }
+    }
+
+    fn index_name(self) -> &'static str {
+        match self {
+            Self::Entity => "idx_events_entity",
+            Self::Kind => "idx_events_kind",
+            Self::Correlation => "idx_events_correlation",
+        }
+    }
+
+    /// 归档行中该列的值
+    fn value(self, row: &ArchivedEvent) -> Option<&str> {
+        match self {
+            Self::Entity => Some(&row.entity_id),
+            Self::Kind => Some(&row.kind),
+            Self::Correlation => row.correlation_id.as_deref(),
+        }
+    }
+}
 
 /// 事件存储配置
 #[derive(Debug, Clone)]
@@ -36,14 +112,29 @@ pub struct EventStore {
     config: EventStoreConfig,
     /// 事件缓冲区
     buffer: Vec<Event>,
+    /// SQLite 连接池 (仅内存模式时为空)
+    pool: Option<SqlitePool>,
+    /// 内存模式下已刷新的事件
+    persisted: Vec<Event>,
+    /// 追加事件时同步发布的事件总线
+    bus: Option<Arc<EventBus>>,
+    /// 事件正文加密器 (未设置时明文落盘)
+    cipher: Option<EventCipher>,
+    /// 载荷脱敏规则
+    redactor: Redactor,
 }
 
 impl EventStore {
-    /// 创建新的事件存储
+    /// 创建新的事件存储 (纯内存，不落盘)
     pub fn new(config: EventStoreConfig) -> Self {
         Self {
             config,
             buffer: Vec::new(),
+            pool: None,
+            persisted: Vec::new(),
+            bus: None,
+            cipher: None,
+            redactor: Redactor::default(),
         }
     }
 
@@ -53,11 +144,79 @@ impl EventStore {
             database_path: path.as_ref().to_string_lossy().to_string(),
             ..Default::default()
         };
-        Ok(Self::new(config))
+        Self::connect(config).await
+    }
+
+    /// 按配置连接 SQLite 并初始化表结构
+    pub async fn connect(config: EventStoreConfig) -> Result<Self> {
+        let journal_mode = if config.enable_wal {
+            SqliteJournalMode::Wal
+        } else {
+            SqliteJournalMode::Delete
+        };
+        let options = SqliteConnectOptions::new()
+            .filename(&config.database_path)
+            .create_if_missing(true)
+            .journal_mode(journal_mode);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .map_err(db_error)?;
+
+        sqlx::query(&events_table_sql(""))
+            .execute(&pool)
+            .await
+            .map_err(db_error)?;
+        for index in events_indexes_sql("") {
+            sqlx::query(&index).execute(&pool).await.map_err(db_error)?;
+        }
+
+        Ok(Self {
+            config,
+            buffer: Vec::new(),
+            pool: Some(pool),
+            persisted: Vec::new(),
+            bus: None,
+            cipher: None,
+            redactor: Redactor::default(),
+        })
+    }
+
+    /// 挂接事件总线，之后追加的事件都会发布给订阅者
+    pub fn with_event_bus(mut self, bus: Arc<EventBus>) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// 启用静态加密，之后写入的事件正文以密文落盘
+    pub fn with_cipher(mut self, cipher: EventCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// 替换载荷脱敏规则
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// 写入前使用的脱敏规则
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
+    /// SQLite 连接池 (仅内存模式时为空)，供快照等同库表复用
+    pub fn pool(&self) -> Option<&SqlitePool> {
+        self.pool.as_ref()
     }
 
     /// 追加事件
-    pub async fn append(&mut self, event: Event) -> Result<()> {
+    pub async fn append(&mut self, mut event: Event) -> Result<()> {
+        self.redactor.redact(&mut event.payload);
+        if let Some(bus) = &self.bus {
+            bus.publish(&event);
+        }
         self.buffer.push(event);
 
         if self.buffer.len() >= self.config.batch_size {
@@ -68,7 +227,15 @@ impl EventStore {
     }
 
     /// 批量追加
-    pub async fn append_batch(&mut self, events: Vec<Event>) -> Result<()> {
+    pub async fn append_batch(&mut self, mut events: Vec<Event>) -> Result<()> {
+        for event in &mut events {
+            self.redactor.redact(&mut event.payload);
+        }
+        if let Some(bus) = &self.bus {
+            for event in &events {
+                bus.publish(event);
+            }
+        }
         self.buffer.extend(events);
         self.flush().await
     }
@@ -79,34 +246,492 @@ impl EventStore {
             return Ok(());
         }
 
-        // TODO: 实现实际的 SQLite 写入
-        // 这里是骨架实现
+        let Some(pool) = &self.pool else {
+            self.persisted.append(&mut self.buffer);
+            return Ok(());
+        };
+
+        let mut tx = pool.begin().await.map_err(db_error)?;
+        for event in &self.buffer {
+            sqlx::query(
+                "INSERT OR IGNORE INTO events (id, kind, timestamp, entity_id, correlation_id, data)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(event.id.to_string())
+            .bind(event.kind.as_str())
+            .bind(event.timestamp.to_rfc3339())
+            .bind(event.entity_id.to_string())
+            .bind(event.correlation_id.map(|id| id.to_string()))
+            .bind(self.encode(event)?)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)?;
+
         self.buffer.clear();
         Ok(())
     }
 
-    /// 查询实体的事件流
+    /// 查询实体的事件流（包括已移出热表的分片）
     pub async fn get_events(&self, entity_id: EntityId) -> Result<Vec<Event>> {
-        // TODO: 实现 SQLite 查询
-        Ok(vec![])
+        self.query_indexed(IndexedColumn::Entity, entity_id.to_string(), |e| {
+            e.entity_id == entity_id
+        })
+        .await
+    }
+
+    /// 查询同一关联 ID 下的事件流（包括已移出热表的分片）
+    pub async fn get_events_by_correlation(&self, correlation_id: Uuid) -> Result<Vec<Event>> {
+        self.query_indexed(IndexedColumn::Correlation, correlation_id.to_string(), |e| {
+            e.correlation_id == Some(correlation_id)
+        })
+        .await
+    }
+
+    /// 查询指定类型的事件（包括已移出热表的分片）
+    pub async fn get_events_by_kind(&self, kind: &EventKind) -> Result<Vec<Event>> {
+        self.query_indexed(IndexedColumn::Kind, kind.as_str().to_string(), |e| {
+            &e.kind == kind
+        })
+        .await
     }
 
     /// 查询时间范围内的事件
+    ///
+    /// 热表按 `timestamp` 列在 SQL 中过滤（与写入时相同的 RFC 3339 编码，字符串序即时间序）；
+    /// 范围覆盖到已移出热表的月份时，自动读取这些月份的分片。
     pub async fn get_events_by_time(
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<Vec<Event>> {
-        // TODO: 实现 SQLite 查询
-        Ok(vec![])
+        let (first, last) = (shard::month_of(start), shard::month_of(end));
+        let cold = self.cold_events(|month| month >= first.as_str() && month <= last.as_str()).await?;
+        let in_range = |e: &Event| e.timestamp >= start && e.timestamp <= end;
+        let hot = self
+            .query(
+                "WHERE timestamp >= ? AND timestamp <= ?",
+                vec![start.to_rfc3339(), end.to_rfc3339()],
+                in_range,
+            )
+            .await?;
+        Ok(merge_cold(cold, hot).into_iter().filter(in_range).collect())
+    }
+
+    /// 获取指定事件之后写入的全部事件 (按写入顺序)
+    ///
+    /// 用作订阅的续传令牌；令牌对应的事件不存在时只返回尚未落盘的缓冲事件。
+    pub async fn get_events_after(&self, event_id: Uuid) -> Result<Vec<Event>> {
+        if let Some(pos) = self.buffer.iter().position(|e| e.id == event_id) {
+            return Ok(self.buffer[pos + 1..].to_vec());
+        }
+
+        let mut events = match &self.pool {
+            Some(pool) => {
+                let rows = sqlx::query_as::<_, (String, String)>(
+                    "SELECT id, data FROM events WHERE seq > (SELECT seq FROM events WHERE id = ?) ORDER BY seq",
+                )
+                .bind(event_id.to_string())
+                .fetch_all(pool)
+                .await
+                .map_err(db_error)?;
+                self.decode_rows(rows)?
+            }
+            None => self
+                .persisted
+                .iter()
+                .position(|e| e.id == event_id)
+                .map(|pos| self.persisted[pos + 1..].to_vec())
+                .unwrap_or_default(),
+        };
+        events.extend(self.buffer.iter().cloned());
+        Ok(events)
+    }
+
+    /// 获取全部事件 (按写入顺序)，包括已移出热表的分片
+    pub async fn all_events(&self) -> Result<Vec<Event>> {
+        let cold = self.cold_events(|_| true).await?;
+        Ok(merge_cold(cold, self.query("", Vec::new(), |_| true).await?))
+    }
+
+    /// 压缩事件库：删除指定实体在 `before` 之前的已落盘事件，返回删除数量
+    ///
+    /// 仅应在该时间点之后存在快照时调用（见 `SnapshotManager::prune_and_compact`）。
+    pub async fn compact_entity(&mut self, entity_id: EntityId, before: DateTime<Utc>) -> Result<u64> {
+        match &self.pool {
+            Some(pool) => {
+                let result = sqlx::query("DELETE FROM events WHERE entity_id = ? AND timestamp < ?")
+                    .bind(entity_id.to_string())
+                    .bind(before.to_rfc3339())
+                    .execute(pool)
+                    .await
+                    .map_err(db_error)?;
+                Ok(result.rows_affected())
+            }
+            None => {
+                let len = self.persisted.len();
+                self.persisted
+                    .retain(|e| e.entity_id != entity_id || e.timestamp >= before);
+                Ok((len - self.persisted.len()) as u64)
+            }
+        }
+    }
+
+    /// 导入事件（不经过事件总线广播）
+    ///
+    /// 事件是不可变事实：`Skip` 与 `Merge` 只补充本地缺失的事件，`Overwrite` 替换同 ID 的本地事件。
+    pub async fn import_events(&mut self, events: Vec<Event>, policy: ConflictPolicy) -> Result<ImportStats> {
+        let existing: HashSet<Uuid> = self.all_events().await?.iter().map(|e| e.id).collect();
+        let mut stats = ImportStats::default();
+        for mut event in events {
+            if existing.contains(&event.id) {
+                stats.record_conflict(policy);
+                if policy != ConflictPolicy::Overwrite {
+                    continue;
+                }
+                self.buffer.retain(|e| e.id != event.id);
+                match &self.pool {
+                    Some(pool) => {
+                        sqlx::query("DELETE FROM events WHERE id = ?")
+                            .bind(event.id.to_string())
+                            .execute(pool)
+                            .await
+                            .map_err(db_error)?;
+                    }
+                    None => self.persisted.retain(|e| e.id != event.id),
+                }
+            } else {
+                stats.added += 1;
+            }
+            self.redactor.redact(&mut event.payload);
+            self.buffer.push(event);
+        }
+        self.flush().await?;
+        Ok(stats)
+    }
+
+    /// 事件库旁的月份分片（纯内存模式下为空）
+    pub fn shards(&self) -> Result<Vec<EventShard>> {
+        match self.database_path() {
+            Some(database) => shard::list_shards(database),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// 把热表中早于保留期的事件按月份移入分片库，返回本次写入的分片
+    ///
+    /// `pinned_kinds` 中的事件类型留在热表。分片库建有与热表相同的索引，之后的查询与 `count` 仍包含分片中的事件；
+    /// `get_events_after` 的续传只覆盖热表。
+    pub async fn rotate_shards(&mut self, policy: &ShardPolicy) -> Result<Vec<EventShard>> {
+        self.flush().await?;
+        let (Some(pool), Some(database)) = (&self.pool, self.database_path()) else {
+            return Ok(Vec::new());
+        };
+        let pinned = if policy.pinned_kinds.is_empty() {
+            String::new()
+        } else {
+            format!(" AND kind NOT IN ({})", vec!["?"; policy.pinned_kinds.len()].join(", "))
+        };
+        let month_filter = format!("substr(timestamp, 1, 7) = ?{}", pinned);
+
+        let cutoff = shard::cutoff_month(Utc::now(), policy.hot_months);
+        let sql = format!(
+            "SELECT DISTINCT substr(timestamp, 1, 7) FROM events WHERE substr(timestamp, 1, 7) < ?{} ORDER BY 1",
+            pinned
+        );
+        let mut query = sqlx::query_as::<_, (String,)>(&sql).bind(cutoff);
+        for kind in &policy.pinned_kinds {
+            query = query.bind(kind);
+        }
+        let months = query.fetch_all(pool).await.map_err(db_error)?;
+
+        let mut conn = pool.acquire().await.map_err(db_error)?;
+        for (month,) in &months {
+            let path = shard::shard_path(database, month, ShardFormat::Sqlite);
+            sqlx::query(&format!("ATTACH DATABASE ? AS {}", SHARD_SCHEMA))
+                .bind(path.to_string_lossy().to_string())
+                .execute(&mut *conn)
+                .await
+                .map_err(db_error)?;
+            let moved = async {
+                let schema = format!("{}.", SHARD_SCHEMA);
+                sqlx::query(&events_table_sql(&schema)).execute(&mut *conn).await?;
+                for index in events_indexes_sql(&schema) {
+                    sqlx::query(&index).execute(&mut *conn).await?;
+                }
+                let mut tx = conn.begin().await?;
+                let insert = format!(
+                    "INSERT OR IGNORE INTO {}.events SELECT * FROM main.events WHERE {}",
+                    SHARD_SCHEMA, month_filter
+                );
+                let delete = format!("DELETE FROM main.events WHERE {}", month_filter);
+                let mut moved = 0;
+                for sql in [insert, delete] {
+                    let mut query = sqlx::query(&sql).bind(month);
+                    for kind in &policy.pinned_kinds {
+                        query = query.bind(kind);
+                    }
+                    moved = query.execute(&mut *tx).await?.rows_affected();
+                }
+                tx.commit().await?;
+                Ok::<u64, sqlx::Error>(moved)
+            }
+            .await;
+            // 无论成败都卸载分片库
+            let detached = sqlx::query(&format!("DETACH DATABASE {}", SHARD_SCHEMA))
+                .execute(&mut *conn)
+                .await;
+            let moved = moved.map_err(db_error)?;
+            detached.map_err(db_error)?;
+            tracing::info!("moved {} events of {} into {}", moved, month, path.display());
+        }
+        drop(conn);
+
+        let rotated: HashSet<&str> = months.iter().map(|(month,)| month.as_str()).collect();
+        Ok(self
+            .shards()?
+            .into_iter()
+            .filter(|s| s.format == ShardFormat::Sqlite && rotated.contains(s.month.as_str()))
+            .collect())
+    }
+
+    /// 把某月的 SQLite 分片转为 Parquet 归档（已有归档时合并），成功后删除 SQLite 分片
+    pub async fn export_shard(&self, month: &str) -> Result<EventShard> {
+        let month = shard::parse_month(month)?;
+        let database = self
+            .database_path()
+            .ok_or_else(|| NeuroLoomError::EventStore("in-memory event store has no shards".to_string()))?;
+        let source = shard::shard_path(database, &month, ShardFormat::Sqlite);
+        if !source.exists() {
+            return Err(NeuroLoomError::EventStore(format!("no SQLite shard for {}", month)));
+        }
+        let target = shard::shard_path(database, &month, ShardFormat::Parquet);
+
+        let mut rows = self.read_sqlite_shard(&source, None).await?;
+        if target.exists() {
+            rows.extend(parquet::read_events(&target)?);
+        }
+        let rows = dedup_rows(rows);
+        parquet::write_events(&target, &rows)?;
+        std::fs::remove_file(&source)?;
+        tracing::info!("exported {} events of {} to {}", rows.len(), month, target.display());
+
+        Ok(EventShard {
+            month,
+            format: ShardFormat::Parquet,
+            bytes: std::fs::metadata(&target)?.len(),
+            path: target,
+        })
+    }
+
+    /// 读取某月分片中的全部事件（SQLite 分片临时挂载，Parquet 归档直接读取），按写入顺序
+    pub async fn shard_events(&self, month: &str) -> Result<Vec<Event>> {
+        let month = shard::parse_month(month)?;
+        let mut rows = Vec::new();
+        for shard in self.shards()?.into_iter().filter(|s| s.month == month) {
+            match shard.format {
+                ShardFormat::Sqlite => rows.extend(self.read_sqlite_shard(&shard.path, None).await?),
+                ShardFormat::Parquet => rows.extend(parquet::read_events(&shard.path)?),
+            }
+        }
+        self.decode_rows(dedup_rows(rows).into_iter().map(|row| (row.id, row.data)).collect())
+    }
+
+    /// 读取任意位置的 Parquet 事件归档（如拷贝到别处的归档文件）
+    pub fn read_archive(&self, path: &Path) -> Result<Vec<Event>> {
+        let rows = parquet::read_events(path)?;
+        self.decode_rows(rows.into_iter().map(|row| (row.id, row.data)).collect())
     }
 
-    /// 获取事件计数
+    /// 获取事件计数（包括分片中的事件）
     pub async fn count(&self) -> Result<u64> {
-        Ok(0)
+        let cold = self.cold_rows(None).await?;
+        let cold = dedup_rows(cold).len() as u64;
+        Ok(cold + self.hot_count().await?)
+    }
+
+    /// 获取热表中的事件计数
+    pub async fn hot_count(&self) -> Result<u64> {
+        let stored = match &self.pool {
+            Some(pool) => {
+                let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM events")
+                    .fetch_one(pool)
+                    .await
+                    .map_err(db_error)?;
+                count as u64
+            }
+            None => self.persisted.len() as u64,
+        };
+        Ok(stored + self.buffer.len() as u64)
+    }
+
+    /// 已移出热表的分片中的事件（按月份过滤），按月份顺序
+    async fn cold_events(&self, include: impl Fn(&str) -> bool) -> Result<Vec<Event>> {
+        let months: BTreeSet<String> = self.shards()?.into_iter().map(|s| s.month).filter(|m| include(m)).collect();
+        let mut events = Vec::new();
+        for month in months {
+            events.extend(self.shard_events(&month).await?);
+        }
+        Ok(events)
+    }
+
+    /// 全部分片中的行（可按列值过滤：SQLite 分片走索引，Parquet 归档逐行过滤），按写入顺序去重
+    async fn cold_rows(&self, filter: Option<(IndexedColumn, &str)>) -> Result<Vec<ArchivedEvent>> {
+        let mut rows = Vec::new();
+        for shard in self.shards()? {
+            match shard.format {
+                ShardFormat::Sqlite => rows.extend(self.read_sqlite_shard(&shard.path, filter).await?),
+                ShardFormat::Parquet => rows.extend(
+                    parquet::read_events(&shard.path)?
+                        .into_iter()
+                        .filter(|row| filter.is_none_or(|(column, value)| column.value(row) == Some(value))),
+                ),
+            }
+        }
+        Ok(dedup_rows(rows))
+    }
+
+    /// 按索引列查询热表与分片
+    async fn query_indexed(
+        &self,
+        column: IndexedColumn,
+        value: String,
+        matches: impl Fn(&Event) -> bool,
+    ) -> Result<Vec<Event>> {
+        let rows = self.cold_rows(Some((column, &value))).await?;
+        let cold = self.decode_rows(rows.into_iter().map(|row| (row.id, row.data)).collect())?;
+        let filter = format!("WHERE {} = ?", column.name());
+        Ok(merge_cold(cold, self.query(&filter, vec![value], matches).await?))
+    }
+
+    /// 落盘模式下的数据库路径
+    fn database_path(&self) -> Option<&Path> {
+        self.pool.as_ref().map(|_| Path::new(&self.config.database_path))
+    }
+
+    /// 挂载 SQLite 分片库读取全部行（或指定列等于某值的行），读完即卸载
+    async fn read_sqlite_shard(
+        &self,
+        path: &Path,
+        filter: Option<(IndexedColumn, &str)>,
+    ) -> Result<Vec<ArchivedEvent>> {
+        let pool = self
+            .pool
+            .as_ref()
+            .ok_or_else(|| NeuroLoomError::EventStore("in-memory event store has no shards".to_string()))?;
+        let mut conn = pool.acquire().await.map_err(db_error)?;
+        sqlx::query(&format!("ATTACH DATABASE ? AS {}", SHARD_SCHEMA))
+            .bind(path.to_string_lossy().to_string())
+            .execute(&mut *conn)
+            .await
+            .map_err(db_error)?;
+        let sql = format!(
+            "SELECT seq, id, kind, timestamp, entity_id, correlation_id, data FROM {}.events {} ORDER BY seq",
+            SHARD_SCHEMA,
+            filter.map(|(column, _)| format!("WHERE {} = ?", column.name())).unwrap_or_default()
+        );
+        let mut query = sqlx::query_as::<_, (i64, String, String, String, String, Option<String>, String)>(&sql);
+        if let Some((_, value)) = filter {
+            query = query.bind(value);
+        }
+        let rows = query.fetch_all(&mut *conn).await;
+        let detached = sqlx::query(&format!("DETACH DATABASE {}", SHARD_SCHEMA))
+            .execute(&mut *conn)
+            .await;
+        let rows = rows.map_err(db_error)?;
+        detached.map_err(db_error)?;
+        Ok(rows
+            .into_iter()
+            .map(|(seq, id, kind, timestamp, entity_id, correlation_id, data)| ArchivedEvent {
+                seq,
+                id,
+                kind,
+                timestamp,
+                entity_id,
+                correlation_id,
+                data,
+            })
+            .collect())
+    }
+
+    /// 序列化事件正文，启用加密时以事件 ID 作为附加数据加密
+    fn encode(&self, event: &Event) -> Result<String> {
+        let json = serde_json::to_string(event)?;
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(json.as_bytes(), event.id.to_string().as_bytes()),
+            None => Ok(json),
+        }
+    }
+
+    /// 解析 (id, data) 行，兼容未加密的历史数据
+    fn decode_rows(&self, rows: Vec<(String, String)>) -> Result<Vec<Event>> {
+        rows.into_iter()
+            .map(|(id, data)| {
+                if !EventCipher::is_encrypted(&data) {
+                    return Ok(serde_json::from_str(&data)?);
+                }
+                let cipher = self.cipher.as_ref().ok_or_else(|| {
+                    NeuroLoomError::EventStore(format!("event {} is encrypted but no key is configured", id))
+                })?;
+                Ok(serde_json::from_slice(&cipher.decrypt(&data, id.as_bytes())?)?)
+            })
+            .collect()
+    }
+
+    /// 执行查询，并合并尚未刷新的缓冲事件
+    async fn query(
+        &self,
+        filter: &str,
+        params: Vec<String>,
+        matches: impl Fn(&Event) -> bool,
+    ) -> Result<Vec<Event>> {
+        let mut events = match &self.pool {
+            Some(pool) => {
+                let sql = format!("SELECT id, data FROM events {} ORDER BY seq", filter);
+                let mut query = sqlx::query_as::<_, (String, String)>(&sql);
+                for param in params {
+                    query = query.bind(param);
+                }
+                let rows = query.fetch_all(pool).await.map_err(db_error)?;
+                self.decode_rows(rows)?
+            }
+            None => self.persisted.iter().filter(|e| matches(e)).cloned().collect(),
+        };
+        events.extend(self.buffer.iter().filter(|e| matches(e)).cloned());
+        Ok(events)
     }
 }
 
+/// 合并分片与热表事件：有分片事件时按时间戳稳定排序（热表中固定保留的旧事件归位），
+/// 同一事件同时出现在热表（如按 `Overwrite` 重新导入）时以热表为准
+fn merge_cold(mut cold: Vec<Event>, hot: Vec<Event>) -> Vec<Event> {
+    if cold.is_empty() {
+        return hot;
+    }
+    let hot_ids: HashSet<Uuid> = hot.iter().map(|e| e.id).collect();
+    cold.retain(|e| !hot_ids.contains(&e.id));
+    cold.extend(hot);
+    cold.sort_by_key(|e| e.timestamp);
+    cold
+}
+
+/// 按事件 ID 去重并按写入顺序排列
+fn dedup_rows(rows: Vec<ArchivedEvent>) -> Vec<ArchivedEvent> {
+    let mut unique: HashMap<String, ArchivedEvent> = HashMap::new();
+    for row in rows {
+        unique.entry(row.id.clone()).or_insert(row);
+    }
+    let mut rows: Vec<ArchivedEvent> = unique.into_values().collect();
+    rows.sort_by_key(|row| row.seq);
+    rows
+}
+
+/// 将 sqlx 错误转换为统一错误
+pub(crate) fn db_error(e: sqlx::Error) -> NeuroLoomError {
+    NeuroLoomError::Database(e.to_string())
+}
+
 /// 事件流迭代器
 pub struct EventStream {
     events: Vec<Event>,
@@ -118,6 +743,7 @@ impl EventStream {
         Self { events, position: 0 }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<&Event> {
         if self.position < self.events.len() {
             let event = &self.events[self.position];
@@ -132,3 +758,197 @@ impl EventStream {
         self.position
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_events_after_resume_token() {
+        let mut store = EventStore::new(EventStoreConfig::default());
+        let events: Vec<Event> = (0..3)
+            .map(|_| Event::new(EventKind::NodeUpdated, Uuid::new_v4(), serde_json::json!({})))
+            .collect();
+        store.append_batch(events[..2].to_vec()).await.unwrap();
+        store.append(events[2].clone()).await.unwrap();
+
+        let after: Vec<Uuid> = store
+            .get_events_after(events[0].id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(after, vec![events[1].id, events[2].id]);
+        assert!(store.get_events_after(events[2].id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_redacts_and_roundtrips() {
+        let path = std::env::temp_dir().join(format!("nl_events_{}.db", Uuid::new_v4()));
+        let cipher = || EventCipher::derive(b"vault secret", "events").unwrap();
+
+        let mut store = EventStore::open(&path).await.unwrap().with_cipher(cipher());
+        let event = Event::new(
+            EventKind::LlmRequestStarted,
+            Uuid::new_v4(),
+            serde_json::json!({ "prompt": "refactor parser", "headers": { "Authorization": "Bearer sk-1" } }),
+        );
+        store.append(event.clone()).await.unwrap();
+        store.flush().await.unwrap();
+
+        let pool = store.pool.clone().unwrap();
+        let (data,): (String,) = sqlx::query_as("SELECT data FROM events").fetch_one(&pool).await.unwrap();
+        assert!(EventCipher::is_encrypted(&data));
+        assert!(!data.contains("refactor parser"));
+
+        let reopened = EventStore::open(&path).await.unwrap().with_cipher(cipher());
+        let events = reopened.all_events().await.unwrap();
+        assert_eq!(events[0].payload["prompt"], "refactor parser");
+        assert_eq!(events[0].payload["headers"]["Authorization"], crate::redaction::REDACTED);
+
+        let without_key = EventStore::open(&path).await.unwrap();
+        assert!(without_key.all_events().await.is_err());
+
+        drop((store, reopened, without_key));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_time_range_is_filtered_in_sql() {
+        let path = std::env::temp_dir().join(format!("nl_events_{}.db", Uuid::new_v4()));
+        let mut store = EventStore::open(&path).await.unwrap();
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let times = [
+            "2024-03-01T09:59:59.999Z",
+            "2024-03-01T10:00:00Z",
+            "2024-03-01T10:00:00.5Z",
+            "2024-03-01T11:00:00Z",
+        ];
+        let events: Vec<Event> = times
+            .iter()
+            .map(|t| {
+                let mut event = Event::new(EventKind::NodeUpdated, Uuid::new_v4(), serde_json::json!({}));
+                event.timestamp = at(t);
+                event
+            })
+            .collect();
+        store.append_batch(events.clone()).await.unwrap();
+        store.flush().await.unwrap();
+
+        // 边界包含在内，亚秒精度按时间而不是按字符串长度比较
+        let range = store.get_events_by_time(at(times[1]), at(times[3])).await.unwrap();
+        let ids: Vec<Uuid> = range.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![events[1].id, events[2].id, events[3].id]);
+        let range = store.get_events_by_time(at("2024-03-01T10:00:00.1Z"), at("2024-03-01T10:59:00Z")).await.unwrap();
+        assert_eq!(range.iter().map(|e| e.id).collect::<Vec<_>>(), vec![events[2].id]);
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_export_and_query_cold_months() {
+        let dir = std::env::temp_dir().join(format!("nl_shards_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut store = EventStore::open(dir.join("events.db")).await.unwrap();
+        let at = |month: u32| format!("2020-{:02}-15T12:00:00Z", month).parse::<DateTime<Utc>>().unwrap();
+        let event = |kind: EventKind, month: Option<u32>| {
+            let mut event = Event::new(kind, Uuid::new_v4(), serde_json::json!({ "month": month }));
+            if let Some(month) = month {
+                event.timestamp = at(month);
+            }
+            event
+        };
+        store
+            .append_batch(vec![
+                event(EventKind::NodeUpdated, Some(1)),
+                event(EventKind::GodModeAction, Some(1)),
+                event(EventKind::NodeUpdated, Some(2)),
+                event(EventKind::NodeUpdated, None),
+            ])
+            .await
+            .unwrap();
+
+        let rotated = store.rotate_shards(&ShardPolicy::default()).await.unwrap();
+        let months: Vec<&str> = rotated.iter().map(|s| s.month.as_str()).collect();
+        assert_eq!(months, vec!["2020-01", "2020-02"]);
+        // 审计事件留在热表
+        assert_eq!((store.hot_count().await.unwrap(), store.count().await.unwrap()), (2, 4));
+        assert_eq!(store.all_events().await.unwrap().len(), 4);
+        assert_eq!(store.get_events_by_time(at(1), at(2)).await.unwrap().len(), 3);
+
+        let archive = store.export_shard("2020-01").await.unwrap();
+        assert_eq!(archive.format, ShardFormat::Parquet);
+        let formats: Vec<ShardFormat> = store.shards().unwrap().iter().map(|s| s.format).collect();
+        assert_eq!(formats, vec![ShardFormat::Parquet, ShardFormat::Sqlite]);
+        let cold = store.shard_events("2020-01").await.unwrap();
+        assert_eq!((cold.len(), &cold[0].payload["month"]), (1, &serde_json::json!(1)));
+        assert_eq!(store.read_archive(&archive.path).unwrap()[0].id, cold[0].id);
+        assert_eq!(store.get_events_by_time(at(1), at(2)).await.unwrap().len(), 3);
+
+        drop(store);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rotated_conversation_and_experiment_read_back() {
+        use crate::experiments::{experiment_names, ExperimentOutcome, ExperimentReport};
+
+        let dir = std::env::temp_dir().join(format!("nl_shards_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut store = EventStore::open(dir.join("events.db")).await.unwrap();
+        let at = |month: u32| format!("2020-{:02}-15T12:00:00Z", month).parse::<DateTime<Utc>>().unwrap();
+
+        // 对话横跨两个旧月份，最后一轮仍在热表
+        let conversation = Uuid::new_v4();
+        let turns: Vec<Event> = [Some(1), Some(2), None]
+            .into_iter()
+            .enumerate()
+            .map(|(turn, month)| {
+                let payload = serde_json::json!({ "turn": turn });
+                let mut event =
+                    Event::new(EventKind::ConversationTurn, conversation, payload).with_correlation(conversation);
+                if let Some(month) = month {
+                    event.timestamp = at(month);
+                }
+                event
+            })
+            .collect();
+        let mut outcomes: Vec<Event> = ["a", "b"]
+            .into_iter()
+            .map(|variant| {
+                let outcome = ExperimentOutcome::new("planner-prompt", variant, "task-1").with_verdict(0.8, true);
+                let mut event = outcome.to_event().unwrap();
+                event.timestamp = at(1);
+                event
+            })
+            .collect();
+        outcomes.push(ExperimentOutcome::new("planner-prompt", "a", "task-2").to_event().unwrap());
+        store.append_batch(turns.iter().chain(&outcomes).cloned().collect()).await.unwrap();
+        store.rotate_shards(&ShardPolicy::default()).await.unwrap();
+        assert_eq!((store.hot_count().await.unwrap(), store.count().await.unwrap()), (2, 6));
+
+        let ids = |events: Vec<Event>| events.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        let turn_ids = ids(turns.clone());
+        for archived in [false, true] {
+            if archived {
+                store.export_shard("2020-01").await.unwrap();
+            }
+            assert_eq!(ids(store.get_events(conversation).await.unwrap()), turn_ids);
+            assert_eq!(ids(store.get_events_by_correlation(conversation).await.unwrap()), turn_ids);
+            assert_eq!(ids(store.get_events_by_kind(&EventKind::ConversationTurn).await.unwrap()), turn_ids);
+            let recorded = store.get_events_by_kind(&EventKind::ExperimentOutcome).await.unwrap();
+            assert_eq!(ids(recorded), ids(outcomes.clone()));
+            assert_eq!(store.count().await.unwrap(), 6);
+
+            let report = ExperimentReport::load(&store, "planner-prompt").await.unwrap();
+            let samples: Vec<(&str, u64)> = report.variants.iter().map(|v| (v.variant.as_str(), v.samples)).collect();
+            assert_eq!(samples, vec![("a", 2), ("b", 1)]);
+            assert_eq!(experiment_names(&store).await.unwrap(), vec!["planner-prompt".to_string()]);
+        }
+
+        drop(store);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,561 @@
+//! A/B 实验
+//!
+//! 对提示词版本、模型或路由规则做对照实验：
+//! - `Experiment` 定义若干带权重的变体，`assign` 按「实验名 + 任务 ID」的 SHA-256 确定性分桶，
+//!   同一任务重放、重试时总是落在同一变体
+//! - 分配与结果（裁决得分、是否通过、费用、耗时、人工评分）作为 `ExperimentAssigned` / `ExperimentOutcome`
+//!   事件写入事件库，实体 ID 由实验名派生
+//! - `ExperimentReport` 从结果事件汇总各变体的指标，以最先出现的变体为对照组，
+//!   得分用 Welch t 检验、通过率用双比例 z 检验给出近似 p 值（正态近似，样本少时仅供参考）
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::event::{Event, EventKind};
+use nl_core::{NeuroLoomError, Result};
+
+use crate::event_store::EventStore;
+
+/// 默认显著性水平
+pub const DEFAULT_ALPHA: f64 = 0.05;
+
+/// 实验变体
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    /// 变体名称
+    pub name: String,
+    /// 分桶权重
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// 提示词模板版本（`name@version`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+    /// 模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 路由规则名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_rule: Option<String>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl Variant {
+    /// 创建权重为 1 的变体
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            weight: default_weight(),
+            prompt_version: None,
+            model: None,
+            routing_rule: None,
+        }
+    }
+
+    /// 设置分桶权重
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// 使用指定的提示词模板版本
+    pub fn with_prompt_version(mut self, prompt_version: impl Into<String>) -> Self {
+        self.prompt_version = Some(prompt_version.into());
+        self
+    }
+
+    /// 使用指定的模型
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// 使用指定的路由规则
+    pub fn with_routing_rule(mut self, routing_rule: impl Into<String>) -> Self {
+        self.routing_rule = Some(routing_rule.into());
+        self
+    }
+}
+
+/// 实验定义（第一个变体通常作为对照组）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Experiment {
+    pub name: String,
+    pub variants: Vec<Variant>,
+}
+
+impl Experiment {
+    /// 创建实验
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            variants: Vec::new(),
+        }
+    }
+
+    /// 添加变体
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    /// 实验事件的实体 ID
+    pub fn entity_id(&self) -> Uuid {
+        entity_id(&self.name)
+    }
+
+    /// 为任务确定性地分配变体（没有权重为正的变体时为空）
+    pub fn assign(&self, task_id: &str) -> Option<&Variant> {
+        let total: u64 = self.variants.iter().map(|v| v.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let hash = digest(&SHA256, format!("{}\0{}", self.name, task_id).as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&hash.as_ref()[..8]);
+        let mut bucket = u64::from_be_bytes(bytes) % total;
+        self.variants.iter().find(|v| {
+            if bucket < v.weight as u64 {
+                return true;
+            }
+            bucket -= v.weight as u64;
+            false
+        })
+    }
+
+    /// 分配变体并写入 `ExperimentAssigned` 事件
+    pub async fn record_assignment(&self, store: &mut EventStore, task_id: &str) -> Result<Variant> {
+        let variant = self
+            .assign(task_id)
+            .cloned()
+            .ok_or_else(|| NeuroLoomError::Unknown(format!("experiment {} has no variants", self.name)))?;
+        let payload = serde_json::json!({
+            "experiment": self.name,
+            "task_id": task_id,
+            "variant": variant,
+        });
+        store
+            .append(Event::new(EventKind::ExperimentAssigned, self.entity_id(), payload))
+            .await?;
+        Ok(variant)
+    }
+}
+
+/// 实验名派生的实体 ID
+fn entity_id(experiment: &str) -> Uuid {
+    let hash = digest(&SHA256, format!("experiment:{}", experiment).as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash.as_ref()[..16]);
+    Uuid::from_bytes(bytes)
+}
+
+/// 一次任务在某变体下的结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentOutcome {
+    pub experiment: String,
+    pub variant: String,
+    pub task_id: String,
+    /// 裁决得分
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verdict_score: Option<f64>,
+    /// 裁决是否通过
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passed: Option<bool>,
+    /// 费用（美元）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    /// 耗时（毫秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// 人工评分（1-5）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl ExperimentOutcome {
+    /// 创建空结果
+    pub fn new(experiment: impl Into<String>, variant: impl Into<String>, task_id: impl Into<String>) -> Self {
+        Self {
+            experiment: experiment.into(),
+            variant: variant.into(),
+            task_id: task_id.into(),
+            verdict_score: None,
+            passed: None,
+            cost_usd: None,
+            latency_ms: None,
+            rating: None,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    /// 记录裁决
+    pub fn with_verdict(mut self, score: f64, passed: bool) -> Self {
+        self.verdict_score = Some(score);
+        self.passed = Some(passed);
+        self
+    }
+
+    /// 记录费用
+    pub fn with_cost_usd(mut self, cost_usd: f64) -> Self {
+        self.cost_usd = Some(cost_usd);
+        self
+    }
+
+    /// 记录耗时
+    pub fn with_latency_ms(mut self, latency_ms: u64) -> Self {
+        self.latency_ms = Some(latency_ms);
+        self
+    }
+
+    /// 记录人工评分
+    pub fn with_rating(mut self, rating: u8) -> Self {
+        self.rating = Some(rating);
+        self
+    }
+
+    /// 只有人工评分的补记结果
+    pub fn is_rating_only(&self) -> bool {
+        self.rating.is_some()
+            && self.verdict_score.is_none()
+            && self.passed.is_none()
+            && self.cost_usd.is_none()
+            && self.latency_ms.is_none()
+    }
+
+    /// 转为 `ExperimentOutcome` 事件
+    pub fn to_event(&self) -> Result<Event> {
+        Ok(Event::new(
+            EventKind::ExperimentOutcome,
+            entity_id(&self.experiment),
+            serde_json::to_value(self)?,
+        ))
+    }
+
+    /// 写入事件库
+    pub async fn record(&self, store: &mut EventStore) -> Result<()> {
+        store.append(self.to_event()?).await
+    }
+}
+
+/// 样本均值与标准差（Welford 累加）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricSummary {
+    pub count: u64,
+    pub mean: f64,
+    m2: f64,
+}
+
+impl MetricSummary {
+    fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// 样本方差
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// 样本标准差
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+}
+
+/// 单个变体的汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VariantStats {
+    pub variant: String,
+    /// 结果数
+    pub samples: u64,
+    /// 有裁决的结果数
+    pub judged: u64,
+    /// 通过数
+    pub passed: u64,
+    pub score: MetricSummary,
+    pub cost_usd: MetricSummary,
+    pub latency_ms: MetricSummary,
+    pub rating: MetricSummary,
+}
+
+impl VariantStats {
+    /// 通过率（没有裁决时为空）
+    pub fn pass_rate(&self) -> Option<f64> {
+        (self.judged > 0).then(|| self.passed as f64 / self.judged as f64)
+    }
+}
+
+/// 变体与对照组的比较
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariantComparison {
+    pub variant: String,
+    pub control: String,
+    /// 平均得分差（变体 - 对照）
+    pub score_delta: Option<f64>,
+    /// 得分差异的 p 值（Welch t 检验）
+    pub score_p_value: Option<f64>,
+    /// 通过率差（变体 - 对照）
+    pub pass_rate_delta: Option<f64>,
+    /// 通过率差异的 p 值（双比例 z 检验）
+    pub pass_rate_p_value: Option<f64>,
+}
+
+impl VariantComparison {
+    /// 任一指标的差异在给定显著性水平下显著
+    pub fn is_significant(&self, alpha: f64) -> bool {
+        [self.score_p_value, self.pass_rate_p_value]
+            .into_iter()
+            .flatten()
+            .any(|p| p < alpha)
+    }
+}
+
+/// 实验报告
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentReport {
+    pub experiment: String,
+    /// 各变体汇总，第一个为对照组
+    pub variants: Vec<VariantStats>,
+    pub comparisons: Vec<VariantComparison>,
+}
+
+impl ExperimentReport {
+    /// 从事件汇总（忽略其他实验与其他类型的事件）
+    pub fn from_events(experiment: &str, events: &[Event]) -> Self {
+        let mut variants: Vec<VariantStats> = Vec::new();
+        for outcome in outcomes(events).filter(|o| o.experiment == experiment) {
+            let index = match variants.iter().position(|v| v.variant == outcome.variant) {
+                Some(index) => index,
+                None => {
+                    variants.push(VariantStats {
+                        variant: outcome.variant.clone(),
+                        ..Default::default()
+                    });
+                    variants.len() - 1
+                }
+            };
+            let stats = &mut variants[index];
+            // 人工评分在任务结束后单独补记，不重复计入结果数
+            if !outcome.is_rating_only() {
+                stats.samples += 1;
+            }
+            if let Some(passed) = outcome.passed {
+                stats.judged += 1;
+                stats.passed += passed as u64;
+            }
+            if let Some(score) = outcome.verdict_score {
+                stats.score.push(score);
+            }
+            if let Some(cost) = outcome.cost_usd {
+                stats.cost_usd.push(cost);
+            }
+            if let Some(latency) = outcome.latency_ms {
+                stats.latency_ms.push(latency as f64);
+            }
+            if let Some(rating) = outcome.rating {
+                stats.rating.push(rating as f64);
+            }
+        }
+
+        let comparisons = match variants.split_first() {
+            Some((control, rest)) => rest.iter().map(|v| compare(control, v)).collect(),
+            None => Vec::new(),
+        };
+        Self {
+            experiment: experiment.to_string(),
+            variants,
+            comparisons,
+        }
+    }
+
+    /// 从事件库加载
+    pub async fn load(store: &EventStore, experiment: &str) -> Result<Self> {
+        let events = store.get_events(entity_id(experiment)).await?;
+        Ok(Self::from_events(experiment, &events))
+    }
+
+    /// 是否没有任何结果
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// 渲染为文本表格，显著的差异标 `*`
+    pub fn render(&self, alpha: f64) -> String {
+        let mut out = format!("Experiment {}\n", self.experiment);
+        out.push_str(&format!(
+            "  {:<20} {:>7} {:>9} {:>15} {:>10} {:>12} {:>7}\n",
+            "VARIANT", "N", "PASS", "SCORE", "COST", "LATENCY", "RATING"
+        ));
+        for stats in &self.variants {
+            let pass = stats
+                .pass_rate()
+                .map_or("-".to_string(), |r| format!("{:.1}%", r * 100.0));
+            let score = if stats.score.count > 0 {
+                format!("{:.3}±{:.3}", stats.score.mean, stats.score.std_dev())
+            } else {
+                "-".to_string()
+            };
+            let cost = if stats.cost_usd.count > 0 {
+                format!("${:.4}", stats.cost_usd.mean)
+            } else {
+                "-".to_string()
+            };
+            let latency = if stats.latency_ms.count > 0 {
+                format!("{:.0}ms", stats.latency_ms.mean)
+            } else {
+                "-".to_string()
+            };
+            let rating = if stats.rating.count > 0 {
+                format!("{:.2}", stats.rating.mean)
+            } else {
+                "-".to_string()
+            };
+            out.push_str(&format!(
+                "  {:<20} {:>7} {:>9} {:>15} {:>10} {:>12} {:>7}\n",
+                stats.variant, stats.samples, pass, score, cost, latency, rating
+            ));
+        }
+        for cmp in &self.comparisons {
+            let describe = |delta: Option<f64>, p: Option<f64>, scale: f64| match (delta, p) {
+                (Some(delta), Some(p)) => {
+                    format!("{:+.3} (p={:.3}{})", delta * scale, p, if p < alpha { "*" } else { "" })
+                }
+                (Some(delta), None) => format!("{:+.3} (n too small)", delta * scale),
+                _ => "-".to_string(),
+            };
+            out.push_str(&format!(
+                "  {} vs {}: score {}, pass rate {}\n",
+                cmp.variant,
+                cmp.control,
+                describe(cmp.score_delta, cmp.score_p_value, 1.0),
+                describe(cmp.pass_rate_delta, cmp.pass_rate_p_value, 100.0),
+            ));
+        }
+        out
+    }
+}
+
+/// 事件库中出现过的实验名称
+pub async fn experiment_names(store: &EventStore) -> Result<Vec<String>> {
+    let events = store.get_events_by_kind(&EventKind::ExperimentOutcome).await?;
+    Ok(outcomes(&events)
+        .map(|o| o.experiment)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect())
+}
+
+fn outcomes(events: &[Event]) -> impl Iterator<Item = ExperimentOutcome> + '_ {
+    events
+        .iter()
+        .filter(|e| e.kind == EventKind::ExperimentOutcome)
+        .filter_map(|e| serde_json::from_value(e.payload.clone()).ok())
+}
+
+fn compare(control: &VariantStats, variant: &VariantStats) -> VariantComparison {
+    let (a, b) = (&control.score, &variant.score);
+    let score_delta = (a.count > 0 && b.count > 0).then_some(b.mean - a.mean);
+    let score_p_value = (a.count >= 2 && b.count >= 2).then(|| {
+        let se = (a.variance() / a.count as f64 + b.variance() / b.count as f64).sqrt();
+        if se > 0.0 {
+            two_sided_p((b.mean - a.mean) / se)
+        } else if a.mean == b.mean {
+            1.0
+        } else {
+            0.0
+        }
+    });
+
+    let (pa, pb) = (control.pass_rate(), variant.pass_rate());
+    let pass_rate_delta = pa.zip(pb).map(|(pa, pb)| pb - pa);
+    let pass_rate_p_value = pa.zip(pb).and_then(|(pa, pb)| {
+        let (na, nb) = (control.judged as f64, variant.judged as f64);
+        let pooled = (control.passed + variant.passed) as f64 / (na + nb);
+        let se = (pooled * (1.0 - pooled) * (1.0 / na + 1.0 / nb)).sqrt();
+        (se > 0.0).then(|| two_sided_p((pb - pa) / se))
+    });
+
+    VariantComparison {
+        variant: variant.variant.clone(),
+        control: control.variant.clone(),
+        score_delta,
+        score_p_value,
+        pass_rate_delta,
+        pass_rate_p_value,
+    }
+}
+
+/// 标准正态分布下统计量 z 的双侧 p 值
+fn two_sided_p(z: f64) -> f64 {
+    (1.0 - erf(z.abs() / std::f64::consts::SQRT_2)).clamp(0.0, 1.0)
+}
+
+/// 误差函数（Abramowitz & Stegun 7.1.26，误差小于 1.5e-7）
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t
+        * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x >= 0.0 {
+        y
+    } else {
+        -y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assignment_is_deterministic_and_report_flags_significance() {
+        let experiment = Experiment::new("planner-prompt")
+            .with_variant(Variant::new("control").with_prompt_version("planner@1"))
+            .with_variant(Variant::new("candidate").with_prompt_version("planner@2").with_weight(3));
+        let first = experiment.assign("task-1").unwrap().name.clone();
+        assert_eq!(experiment.assign("task-1").unwrap().name, first);
+        let candidates = (0..1_000)
+            .filter(|i| experiment.assign(&format!("task-{}", i)).unwrap().name == "candidate")
+            .count();
+        assert!((650..850).contains(&candidates), "{}", candidates);
+        assert!(Experiment::new("empty").assign("task-1").is_none());
+
+        let mut events = Vec::new();
+        for i in 0..40 {
+            let control = ExperimentOutcome::new("planner-prompt", "control", format!("c{}", i))
+                .with_verdict(0.5 + (i % 5) as f64 * 0.01, i % 2 == 0)
+                .with_cost_usd(0.02)
+                .with_latency_ms(1_000);
+            let candidate = ExperimentOutcome::new("planner-prompt", "candidate", format!("v{}", i))
+                .with_verdict(0.8 + (i % 5) as f64 * 0.01, i % 10 != 0)
+                .with_cost_usd(0.03);
+            events.push(control.to_event().unwrap());
+            events.push(candidate.to_event().unwrap());
+        }
+        events.push(ExperimentOutcome::new("other", "control", "x").to_event().unwrap());
+
+        let report = ExperimentReport::from_events("planner-prompt", &events);
+        assert_eq!(report.variants.len(), 2);
+        assert_eq!((report.variants[0].variant.as_str(), report.variants[0].samples), ("control", 40));
+        assert_eq!(report.variants[1].pass_rate(), Some(0.9));
+        let cmp = &report.comparisons[0];
+        assert!((cmp.score_delta.unwrap() - 0.3).abs() < 1e-9);
+        assert!(cmp.score_p_value.unwrap() < 0.001);
+        assert!(cmp.pass_rate_p_value.unwrap() < DEFAULT_ALPHA);
+        assert!(cmp.is_significant(DEFAULT_ALPHA));
+        assert!(report.render(DEFAULT_ALPHA).contains("candidate vs control"));
+    }
+}
@@ -0,0 +1,274 @@
+//! 人工反馈
+//!
+//! 用户对任务结果的评分（1-5）与评语作为 `TaskFeedback` 事件写入事件库，实体为任务；
+//! 同一任务多次评分时以最后一次为准。`FeedbackReport` 把评分按任务的上下文归类：
+//! - 执行者画像：任务裁决记录的 Worker 模板（`name@version`）
+//! - 模型路由：任务在 A/B 实验中分配到的变体模型
+//! - 远程 Agent：任务委派事件中的执行方
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::event::{Event, EventKind};
+use nl_core::{NeuroLoomError, Result};
+
+use crate::event_store::EventStore;
+use crate::experiments::ExperimentOutcome;
+
+/// 最低评分
+pub const MIN_RATING: u8 = 1;
+
+/// 最高评分
+pub const MAX_RATING: u8 = 5;
+
+/// 评分达到此值视为满意
+const SATISFIED_RATING: u8 = 4;
+
+/// 一条人工反馈
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskFeedback {
+    pub task_id: Uuid,
+    /// 评分（1-5）
+    pub rating: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+}
+
+impl TaskFeedback {
+    /// 创建反馈（评分超出 1-5 时报错）
+    pub fn new(task_id: Uuid, rating: u8) -> Result<Self> {
+        if !(MIN_RATING..=MAX_RATING).contains(&rating) {
+            return Err(NeuroLoomError::Unknown(format!(
+                "rating must be between {} and {}, got {}",
+                MIN_RATING, MAX_RATING, rating
+            )));
+        }
+        Ok(Self {
+            task_id,
+            rating,
+            comment: None,
+            submitted_at: Utc::now(),
+        })
+    }
+
+    /// 附加评语（空白评语忽略）
+    pub fn with_comment(mut self, comment: impl Into<String>) -> Self {
+        let comment = comment.into();
+        self.comment = (!comment.trim().is_empty()).then_some(comment);
+        self
+    }
+
+    /// 转为 `TaskFeedback` 事件
+    pub fn to_event(&self) -> Result<Event> {
+        Ok(Event::new(EventKind::TaskFeedback, self.task_id, serde_json::to_value(self)?))
+    }
+
+    /// 写入事件库，并为任务参与的每个 A/B 实验补记评分结果；返回补记的实验数
+    pub async fn record(&self, store: &mut EventStore) -> Result<usize> {
+        store.append(self.to_event()?).await?;
+        let task_id = self.task_id.to_string();
+        let assignments = store.get_events_by_kind(&EventKind::ExperimentAssigned).await?;
+        let mut recorded = 0;
+        for payload in assignments.iter().map(|e| &e.payload) {
+            if payload["task_id"].as_str() != Some(task_id.as_str()) {
+                continue;
+            }
+            let experiment = payload["experiment"].as_str();
+            let (Some(experiment), Some(variant)) = (experiment, payload["variant"]["name"].as_str()) else {
+                continue;
+            };
+            ExperimentOutcome::new(experiment, variant, task_id.clone())
+                .with_rating(self.rating)
+                .record(store)
+                .await?;
+            recorded += 1;
+        }
+        Ok(recorded)
+    }
+}
+
+/// 一组评分的满意度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Satisfaction {
+    /// 评分数
+    pub count: u64,
+    /// 评分总和
+    pub total: u64,
+    /// 满意（4 分及以上）的评分数
+    pub satisfied: u64,
+}
+
+impl Satisfaction {
+    fn push(&mut self, rating: u8) {
+        self.count += 1;
+        self.total += rating as u64;
+        self.satisfied += (rating >= SATISFIED_RATING) as u64;
+    }
+
+    /// 平均评分（没有评分时为空）
+    pub fn average(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total as f64 / self.count as f64)
+    }
+
+    /// 满意率（没有评分时为空）
+    pub fn satisfaction_rate(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.satisfied as f64 / self.count as f64)
+    }
+}
+
+/// 任务的执行上下文
+#[derive(Debug, Default)]
+struct TaskContext {
+    worker: Option<String>,
+    models: Vec<String>,
+    agent: Option<String>,
+}
+
+/// 反馈汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeedbackReport {
+    pub overall: Satisfaction,
+    /// 按 Worker 模板
+    pub by_worker: BTreeMap<String, Satisfaction>,
+    /// 按实验分配的模型
+    pub by_model: BTreeMap<String, Satisfaction>,
+    /// 按远程 Agent
+    pub by_agent: BTreeMap<String, Satisfaction>,
+    /// 评语（任务, 评分, 评语），按提交时间升序
+    pub comments: Vec<(Uuid, u8, String)>,
+}
+
+impl FeedbackReport {
+    /// 从事件汇总；没有反馈的任务只贡献上下文
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let mut contexts: HashMap<Uuid, TaskContext> = HashMap::new();
+        let mut latest: HashMap<Uuid, TaskFeedback> = HashMap::new();
+        for event in events {
+            let payload = &event.payload;
+            match &event.kind {
+                EventKind::TaskFeedback => {
+                    let Ok(feedback) = serde_json::from_value::<TaskFeedback>(payload.clone()) else {
+                        continue;
+                    };
+                    match latest.get(&feedback.task_id) {
+                        Some(previous) if previous.submitted_at > feedback.submitted_at => {}
+                        _ => {
+                            latest.insert(feedback.task_id, feedback);
+                        }
+                    }
+                }
+                EventKind::VerdictIssued => {
+                    if let Some(template) = payload["template"].as_str() {
+                        contexts.entry(event.entity_id).or_default().worker = Some(template.to_string());
+                    }
+                }
+                EventKind::TaskDelegated | EventKind::DelegationCompleted => {
+                    if let Some(agent) = payload["agent"].as_str() {
+                        contexts.entry(event.entity_id).or_default().agent = Some(agent.to_string());
+                    }
+                }
+                EventKind::ExperimentAssigned => {
+                    let task = payload["task_id"].as_str().and_then(|id| Uuid::parse_str(id).ok());
+                    if let (Some(task), Some(model)) = (task, payload["variant"]["model"].as_str()) {
+                        let models = &mut contexts.entry(task).or_default().models;
+                        if !models.iter().any(|m| m == model) {
+                            models.push(model.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut feedback: Vec<TaskFeedback> = latest.into_values().collect();
+        feedback.sort_by_key(|f| f.submitted_at);
+        let mut report = Self::default();
+        for feedback in feedback {
+            report.overall.push(feedback.rating);
+            if let Some(context) = contexts.get(&feedback.task_id) {
+                if let Some(worker) = &context.worker {
+                    report.by_worker.entry(worker.clone()).or_default().push(feedback.rating);
+                }
+                for model in &context.models {
+                    report.by_model.entry(model.clone()).or_default().push(feedback.rating);
+                }
+                if let Some(agent) = &context.agent {
+                    report.by_agent.entry(agent.clone()).or_default().push(feedback.rating);
+                }
+            }
+            if let Some(comment) = feedback.comment {
+                report.comments.push((feedback.task_id, feedback.rating, comment));
+            }
+        }
+        report
+    }
+
+    /// 从事件库加载全部反馈及相关任务的上下文
+    pub async fn load(store: &EventStore) -> Result<Self> {
+        let mut events = store.get_events_by_kind(&EventKind::TaskFeedback).await?;
+        let mut tasks: Vec<Uuid> = events.iter().map(|e| e.entity_id).collect();
+        tasks.sort();
+        tasks.dedup();
+        for task in tasks {
+            events.extend(
+                store
+                    .get_events(task)
+                    .await?
+                    .into_iter()
+                    .filter(|e| e.kind != EventKind::TaskFeedback),
+            );
+        }
+        events.extend(store.get_events_by_kind(&EventKind::ExperimentAssigned).await?);
+        Ok(Self::from_events(&events))
+    }
+
+    /// 是否没有任何反馈
+    pub fn is_empty(&self) -> bool {
+        self.overall.count == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::experiments::{Experiment, Variant};
+
+    #[test]
+    fn test_report_groups_latest_rating_by_context() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let experiment = Experiment::new("model")
+            .with_variant(Variant::new("sonnet").with_model("claude-sonnet"));
+        let events = vec![
+            Event::new(EventKind::VerdictIssued, first, serde_json::json!({ "template": "coder@2" })),
+            Event::new(EventKind::VerdictIssued, second, serde_json::json!({ "template": "coder@2" })),
+            Event::new(
+                EventKind::ExperimentAssigned,
+                experiment.entity_id(),
+                serde_json::json!({
+                    "experiment": "model",
+                    "task_id": first.to_string(),
+                    "variant": experiment.variants[0],
+                }),
+            ),
+            Event::new(EventKind::TaskDelegated, second, serde_json::json!({ "agent": "peer-a" })),
+            TaskFeedback::new(first, 2).unwrap().to_event().unwrap(),
+            // 同一任务再次评分覆盖之前的评分
+            TaskFeedback::new(first, 5).unwrap().with_comment("great fix").to_event().unwrap(),
+            TaskFeedback::new(second, 3).unwrap().with_comment("  ").to_event().unwrap(),
+        ];
+
+        let report = FeedbackReport::from_events(&events);
+        assert_eq!(report.overall.count, 2);
+        assert_eq!(report.overall.average(), Some(4.0));
+        assert_eq!(report.overall.satisfaction_rate(), Some(0.5));
+        assert_eq!(report.by_worker["coder@2"].count, 2);
+        assert_eq!(report.by_model["claude-sonnet"].average(), Some(5.0));
+        assert_eq!(report.by_agent["peer-a"].average(), Some(3.0));
+        assert_eq!(report.comments, vec![(first, 5, "great fix".to_string())]);
+        assert!(TaskFeedback::new(first, 0).is_err() && TaskFeedback::new(first, 6).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! 命令幂等去重
+//!
+//! 重投递的 Actor 消息或重试的控制面调用不应重复产生副作用（沙箱写入、LLM 开销）：
+//! - 命令层以 `ActorMessage::Command` 携带幂等键，控制面以 `Idempotency-Key` 请求头携带
+//! - 首次执行后把键、命令描述与结果写入去重表 `command_dedup`（与事件库共用同一 SQLite，
+//!   无连接池时退化为内存表），重放时直接返回记录的结果，不再执行、不产生新事件
+//! - 同一个键正在执行时，并发的重复命令被拒绝
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
+
+use nl_core::event::Event;
+use nl_core::{NeuroLoomError, Result};
+
+use crate::actor_mesh::{Actor, ActorMessage};
+use crate::event_store::db_error;
+
+/// 去重记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandRecord {
+    /// 幂等键
+    pub key: String,
+    /// 命令描述（同一个键只能对应同一个命令）
+    pub command: String,
+    /// 首次执行的结果
+    pub result: serde_json::Value,
+    /// 记录时间
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// 幂等去重表
+pub struct IdempotencyStore {
+    /// SQLite 连接池 (仅内存模式时为空)
+    pool: Option<SqlitePool>,
+    /// 内存模式下的记录
+    memory: RwLock<HashMap<String, CommandRecord>>,
+    /// 正在执行的键
+    in_flight: std::sync::Mutex<HashSet<String>>,
+}
+
+impl IdempotencyStore {
+    /// 创建内存去重表
+    pub fn new() -> Self {
+        Self {
+            pool: None,
+            memory: RwLock::new(HashMap::new()),
+            in_flight: std::sync::Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// 将去重表持久化到 SQLite（通常与事件库共用同一连接池）
+    pub async fn with_pool(mut self, pool: SqlitePool) -> Result<Self> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS command_dedup (
+                key TEXT PRIMARY KEY,
+                command TEXT NOT NULL,
+                result TEXT NOT NULL,
+                recorded_at TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .map_err(db_error)?;
+
+        self.pool = Some(pool);
+        Ok(self)
+    }
+
+    /// 查询去重记录
+    pub async fn get(&self, key: &str) -> Result<Option<CommandRecord>> {
+        let Some(pool) = &self.pool else {
+            return Ok(self.memory.read().await.get(key).cloned());
+        };
+        let row: Option<(String, String, String)> =
+            sqlx::query_as("SELECT command, result, recorded_at FROM command_dedup WHERE key = ?")
+                .bind(key)
+                .fetch_optional(pool)
+                .await
+                .map_err(db_error)?;
+        let Some((command, result, recorded_at)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(CommandRecord {
+            key: key.to_string(),
+            command,
+            result: serde_json::from_str(&result)?,
+            recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+        }))
+    }
+
+    /// 记录命令结果，键已存在时保留首次记录并返回 `false`
+    pub async fn record(&self, key: &str, command: &str, result: &serde_json::Value) -> Result<bool> {
+        let record = CommandRecord {
+            key: key.to_string(),
+            command: command.to_string(),
+            result: result.clone(),
+            recorded_at: Utc::now(),
+        };
+        let Some(pool) = &self.pool else {
+            let mut memory = self.memory.write().await;
+            if memory.contains_key(key) {
+                return Ok(false);
+            }
+            memory.insert(record.key.clone(), record);
+            return Ok(true);
+        };
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO command_dedup (key, command, result, recorded_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&record.key)
+        .bind(&record.command)
+        .bind(serde_json::to_string(&record.result)?)
+        .bind(record.recorded_at.to_rfc3339())
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+        Ok(inserted.rows_affected() > 0)
+    }
+
+    /// 标记键开始执行，同一个键已在执行中时返回错误
+    pub fn begin(&self, key: &str) -> Result<()> {
+        if self.in_flight.lock().unwrap().insert(key.to_string()) {
+            Ok(())
+        } else {
+            Err(NeuroLoomError::Actor(format!("command {} is already in progress", key)))
+        }
+    }
+
+    /// 结束执行标记（无论成功与否都应调用）
+    pub fn finish(&self, key: &str) {
+        self.in_flight.lock().unwrap().remove(key);
+    }
+
+    /// 清理早于指定时间的记录，返回删除数量
+    pub async fn prune(&self, before: DateTime<Utc>) -> Result<u64> {
+        let Some(pool) = &self.pool else {
+            let mut memory = self.memory.write().await;
+            let count = memory.len();
+            memory.retain(|_, r| r.recorded_at >= before);
+            return Ok((count - memory.len()) as u64);
+        };
+        // RFC 3339 (UTC) 字符串按字典序即时间序
+        let result = sqlx::query("DELETE FROM command_dedup WHERE recorded_at < ?")
+            .bind(before.to_rfc3339())
+            .execute(pool)
+            .await
+            .map_err(db_error)?;
+        Ok(result.rows_affected())
+    }
+
+    /// 把消息交给 Actor 处理：`Command` 消息按幂等键去重，重放时返回 `None`（不产生新事件），
+    /// 其他消息直接处理
+    pub async fn dispatch<A: Actor + ?Sized>(&self, actor: &mut A, msg: ActorMessage) -> Result<Option<Event>> {
+        let (key, message) = match msg {
+            ActorMessage::Command { key, message } => (key, message),
+            other => return actor.handle(other).await,
+        };
+        let command = message.describe();
+        if let Some(record) = self.get(&key).await? {
+            if record.command != command {
+                return Err(NeuroLoomError::Actor(format!(
+                    "idempotency key {} was already used for {}",
+                    key, record.command
+                )));
+            }
+            tracing::debug!("Skipping replayed command {} ({})", key, command);
+            return Ok(None);
+        }
+
+        self.begin(&key)?;
+        let outcome = actor.handle(*message).await;
+        let recorded = match &outcome {
+            Ok(event) => match serde_json::to_value(event) {
+                Ok(result) => self.record(&key, &command, &result).await.map(|_| ()),
+                Err(e) => Err(e.into()),
+            },
+            Err(_) => Ok(()),
+        };
+        self.finish(&key);
+        recorded?;
+        outcome
+    }
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_mesh::ActorState;
+    use crate::event_store::EventStore;
+    use nl_core::event::EventKind;
+    use uuid::Uuid;
+
+    /// 每条自定义消息产生一个事件（模拟沙箱写入），`fail_next` 时下一次处理失败
+    struct Writer {
+        id: Uuid,
+        writes: usize,
+        fail_next: bool,
+    }
+
+    impl Writer {
+        fn new() -> Self {
+            Self { id: Uuid::new_v4(), writes: 0, fail_next: false }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Actor for Writer {
+        fn type_name(&self) -> &'static str {
+            "writer"
+        }
+
+        async fn handle(&mut self, msg: ActorMessage) -> Result<Option<Event>> {
+            let ActorMessage::Custom(_, payload) = msg else {
+                return Ok(None);
+            };
+            if std::mem::take(&mut self.fail_next) {
+                return Err(NeuroLoomError::Actor("sandbox write failed".to_string()));
+            }
+            self.writes += 1;
+            Ok(Some(Event::new(EventKind::NodeUpdated, self.id, payload)))
+        }
+
+        async fn recover(&mut self, _events: Vec<Event>) -> Result<()> {
+            Ok(())
+        }
+
+        fn state(&self) -> ActorState {
+            ActorState::Running
+        }
+    }
+
+    async fn deliver(dedup: &IdempotencyStore, actor: &mut Writer, store: &mut EventStore, msg: ActorMessage) {
+        if let Some(event) = dedup.dispatch(actor, msg).await.unwrap() {
+            store.append(event).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replayed_command_produces_no_new_events() {
+        let path = std::env::temp_dir().join(format!("nl_dedup_{}.db", Uuid::new_v4()));
+        let mut store = EventStore::open(&path).await.unwrap();
+        let pool = store.pool().cloned().unwrap();
+        let mut actor = Writer::new();
+        let command = ActorMessage::command(
+            "write-1",
+            ActorMessage::Custom("write".to_string(), serde_json::json!({ "file": "a.txt" })),
+        );
+
+        let dedup = IdempotencyStore::new().with_pool(pool.clone()).await.unwrap();
+        deliver(&dedup, &mut actor, &mut store, command.clone()).await;
+        deliver(&dedup, &mut actor, &mut store, command.clone()).await;
+
+        // 重启后（新的去重表实例）重放同一命令
+        let reopened = IdempotencyStore::new().with_pool(pool).await.unwrap();
+        deliver(&reopened, &mut actor, &mut store, command).await;
+        store.flush().await.unwrap();
+
+        assert_eq!(actor.writes, 1);
+        assert_eq!(store.count().await.unwrap(), 1);
+        let record = reopened.get("write-1").await.unwrap().unwrap();
+        assert_eq!(record.command, "custom:write");
+
+        // 不同命令复用同一个键被拒绝
+        let other = ActorMessage::command("write-1", ActorMessage::Custom("delete".to_string(), serde_json::json!({})));
+        assert!(reopened.dispatch(&mut actor, other).await.is_err());
+
+        // 未携带幂等键的消息照常处理
+        let plain = ActorMessage::Custom("write".to_string(), serde_json::json!({ "file": "b.txt" }));
+        deliver(&reopened, &mut actor, &mut store, plain).await;
+        store.flush().await.unwrap();
+        assert_eq!(store.count().await.unwrap(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// 两种存储后端：内存表与 SQLite 表，契约用例在两者上都要成立
+    async fn backends() -> Vec<(&'static str, IdempotencyStore, std::path::PathBuf)> {
+        let path = std::env::temp_dir().join(format!("nl_dedup_{}.db", Uuid::new_v4()));
+        let store = EventStore::open(&path).await.unwrap();
+        let sqlite = IdempotencyStore::new().with_pool(store.pool().cloned().unwrap()).await.unwrap();
+        vec![("memory", IdempotencyStore::new(), path.clone()), ("sqlite", sqlite, path)]
+    }
+
+    fn write(file: &str) -> ActorMessage {
+        ActorMessage::Custom("write".to_string(), serde_json::json!({ "file": file }))
+    }
+
+    #[tokio::test]
+    async fn test_contract_success_is_recorded_and_replayed_on_each_backend() {
+        for (backend, dedup, path) in backends().await {
+            let mut actor = Writer::new();
+            let command = ActorMessage::command("k1", write("a.txt"));
+
+            let event = dedup.dispatch(&mut actor, command.clone()).await.unwrap().unwrap();
+            let record = dedup.get("k1").await.unwrap().expect(backend);
+            assert_eq!(record.command, "custom:write", "{}", backend);
+            assert_eq!(record.result, serde_json::to_value(Some(&event)).unwrap(), "{}", backend);
+
+            for _ in 0..3 {
+                assert!(dedup.dispatch(&mut actor, command.clone()).await.unwrap().is_none(), "{}", backend);
+            }
+            assert_eq!(actor.writes, 1, "{}", backend);
+
+            // 重复记录保留首次结果
+            assert!(!dedup.record("k1", "custom:write", &serde_json::json!("later")).await.unwrap());
+            assert_eq!(dedup.get("k1").await.unwrap().unwrap().result, record.result, "{}", backend);
+
+            // 未携带幂等键的消息不经过去重表
+            dedup.dispatch(&mut actor, write("a.txt")).await.unwrap().unwrap();
+            dedup.dispatch(&mut actor, write("a.txt")).await.unwrap().unwrap();
+            assert_eq!(actor.writes, 3, "{}", backend);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_contract_error_shapes_on_each_backend() {
+        for (backend, dedup, path) in backends().await {
+            let mut actor = Writer::new();
+
+            // 处理失败：错误原样返回，不记录、不占用键，重试会再次执行
+            actor.fail_next = true;
+            let err = dedup.dispatch(&mut actor, ActorMessage::command("k2", write("b.txt"))).await;
+            assert!(err.unwrap_err().to_string().contains("sandbox write failed"), "{}", backend);
+            assert!(dedup.get("k2").await.unwrap().is_none(), "{}", backend);
+            assert!(dedup.dispatch(&mut actor, ActorMessage::command("k2", write("b.txt"))).await.unwrap().is_some());
+            assert_eq!(actor.writes, 1, "{}", backend);
+
+            // 同一个键用于不同命令：拒绝且不执行
+            let other = ActorMessage::command("k2", ActorMessage::Custom("delete".to_string(), serde_json::json!({})));
+            let err = dedup.dispatch(&mut actor, other).await.unwrap_err().to_string();
+            assert!(err.contains("already used for custom:write"), "{}: {}", backend, err);
+
+            // 同一个键正在执行：并发的重复命令被拒绝，结束后可以执行
+            dedup.begin("k3").unwrap();
+            let err = dedup.dispatch(&mut actor, ActorMessage::command("k3", write("c.txt"))).await.unwrap_err();
+            assert!(err.to_string().contains("already in progress"), "{}", backend);
+            dedup.finish("k3");
+            assert!(dedup.dispatch(&mut actor, ActorMessage::command("k3", write("c.txt"))).await.unwrap().is_some());
+            assert_eq!(actor.writes, 2, "{}", backend);
+
+            // 清理过期记录后同一个键可以再次执行
+            assert_eq!(dedup.prune(Utc::now() + chrono::Duration::seconds(1)).await.unwrap(), 2, "{}", backend);
+            assert!(dedup.dispatch(&mut actor, ActorMessage::command("k3", write("c.txt"))).await.unwrap().is_some());
+            assert_eq!(actor.writes, 3, "{}", backend);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
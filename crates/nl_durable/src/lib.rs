@@ -1,11 +1,45 @@
 //! # nl_durable - NeuroLoom Durable Execution
 //!
-//! 持久化执行底座，实现 SQLite 事件溯源重放、Actor 休眠/唤醒机制。
+//! 持久化执行底座，实现 SQLite 事件溯源重放、Actor 休眠/唤醒机制与内容寻址的任务产物库。
 
 pub mod event_store;
+pub mod event_bus;
 pub mod snapshot;
 pub mod actor_mesh;
+pub mod timeline;
+pub mod encryption;
+pub mod redaction;
+pub mod quota;
+pub mod cancellation;
+pub mod bundle;
+pub mod idempotency;
+pub mod schedule;
+pub mod artifact_store;
+pub mod experiments;
+pub mod feedback;
+pub mod rewind;
+pub mod pii;
+pub mod shard;
+pub mod parquet;
+pub mod simulation;
 
 pub use event_store::EventStore;
-pub use snapshot::SnapshotManager;
+pub use event_bus::{EventBus, EventBusConfig, EventBusMetrics, EventSubscription};
+pub use snapshot::{SnapshotManager, SnapshotScheduler, SnapshotSource, SnapshotStrategy};
 pub use actor_mesh::ActorMesh;
+pub use timeline::{Timeline, TimelineNode};
+pub use encryption::{EventCipher, MasterKey};
+pub use redaction::{RedactingWriter, RedactionConfig, Redactor};
+pub use quota::{QuotaManager, QuotaResource, QuotaUsage, ResourceQuota};
+pub use cancellation::{CancellationRegistry, CancellationToken};
+pub use bundle::{ConflictPolicy, ImportStats, WorkspaceBundle, BUNDLE_VERSION};
+pub use idempotency::{CommandRecord, IdempotencyStore};
+pub use schedule::{CatchUpPolicy, CronExpr, Schedule, ScheduleStore, ScheduleTarget};
+pub use artifact_store::{ArtifactStore, GcStats};
+pub use experiments::{Experiment, ExperimentOutcome, ExperimentReport, Variant, VariantComparison, VariantStats};
+pub use feedback::{FeedbackReport, Satisfaction, TaskFeedback};
+pub use rewind::{EntityState, RewindPoint, WorkspaceView};
+pub use pii::{EntityRecognizer, PiiConfig, PiiKind, PiiScrubber, PiiTokens};
+pub use shard::{EventShard, ShardFormat, ShardPolicy};
+pub use crate::parquet::ArchivedEvent;
+pub use simulation::{SimConfig, SimEvent, SimEventKind, Simulation};
@@ -0,0 +1,300 @@
+//! 事件归档的 Parquet 读写
+//!
+//! 冷分片导出为 Parquet 供分析工具（DuckDB、pandas 等）直接读取，需要时再由 `EventStore` 读回事件。
+//! 读写都经由 `parquet` crate：写出扁平 schema（`seq` 为 INT64，其余为 UTF8 字符串，只有 `correlation_id` 可空）、
+//! GZIP 压缩、每个行组至多 `ROW_GROUP_ROWS` 行；读取按列名取值、按文件自身的 schema 判断可空，
+//! 列顺序、字典编码、压缩方式与多出的列都不影响读取（如被分析工具重写过的归档）。
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use ::parquet::basic::{Compression, GzipLevel};
+use ::parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use ::parquet::errors::ParquetError;
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::reader::{FileReader, SerializedFileReader};
+use ::parquet::file::writer::SerializedFileWriter;
+use ::parquet::record::Field;
+use ::parquet::schema::parser::parse_message_type;
+
+use nl_core::{NeuroLoomError, Result};
+
+/// 每个行组的最大行数
+const ROW_GROUP_ROWS: usize = 10_000;
+
+/// 归档的 schema，列与 SQLite `events` 表一致
+const SCHEMA: &str = "message events {
+    REQUIRED INT64 seq;
+    REQUIRED BYTE_ARRAY id (UTF8);
+    REQUIRED BYTE_ARRAY kind (UTF8);
+    REQUIRED BYTE_ARRAY timestamp (UTF8);
+    REQUIRED BYTE_ARRAY entity_id (UTF8);
+    OPTIONAL BYTE_ARRAY correlation_id (UTF8);
+    REQUIRED BYTE_ARRAY data (UTF8);
+}";
+
+/// 事件表的一行（与 SQLite `events` 表的列一致，`data` 保持落盘形式，加密时为密文）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedEvent {
+    pub seq: i64,
+    pub id: String,
+    pub kind: String,
+    pub timestamp: String,
+    pub entity_id: String,
+    pub correlation_id: Option<String>,
+    pub data: String,
+}
+
+impl ArchivedEvent {
+    /// 按 schema 中的列序号取字符串列（`seq` 除外）
+    fn text(&self, column: usize) -> Option<&str> {
+        match column {
+            1 => Some(&self.id),
+            2 => Some(&self.kind),
+            3 => Some(&self.timestamp),
+            4 => Some(&self.entity_id),
+            5 => self.correlation_id.as_deref(),
+            _ => Some(&self.data),
+        }
+    }
+}
+
+/// 把事件行写成 Parquet 文件（先写临时文件再改名，避免留下半个文件）
+pub fn write_events(path: &Path, rows: &[ArchivedEvent]) -> Result<()> {
+    let tmp = path.with_extension("parquet.tmp");
+    let write = || -> std::result::Result<(), ParquetError> {
+        let schema = Arc::new(parse_message_type(SCHEMA)?);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::GZIP(GzipLevel::default()))
+            .set_max_row_group_size(ROW_GROUP_ROWS)
+            .set_created_by("neuroloom".to_string())
+            .build();
+        let mut writer = SerializedFileWriter::new(File::create(&tmp)?, schema, Arc::new(properties))?;
+        for chunk in rows.chunks(ROW_GROUP_ROWS) {
+            let mut group = writer.next_row_group()?;
+            let mut index = 0;
+            while let Some(mut column) = group.next_column()? {
+                if index == 0 {
+                    let seqs: Vec<i64> = chunk.iter().map(|row| row.seq).collect();
+                    column.typed::<Int64Type>().write_batch(&seqs, None, None)?;
+                } else {
+                    let values: Vec<Option<&str>> = chunk.iter().map(|row| row.text(index)).collect();
+                    let present: Vec<ByteArray> = values.iter().flatten().map(|v| ByteArray::from(*v)).collect();
+                    let levels: Vec<i16> = values.iter().map(|v| i16::from(v.is_some())).collect();
+                    let column = column.typed::<ByteArrayType>();
+                    let optional = column.get_descriptor().max_def_level() > 0;
+                    column.write_batch(&present, optional.then_some(&levels[..]), None)?;
+                }
+                column.close()?;
+                index += 1;
+            }
+            group.close()?;
+        }
+        writer.close()?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(archive_error(path, e));
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 读取 Parquet 事件归档
+pub fn read_events(path: &Path) -> Result<Vec<ArchivedEvent>> {
+    let reader = SerializedFileReader::new(File::open(path)?).map_err(|e| archive_error(path, e))?;
+    let invalid = |reason: String| NeuroLoomError::EventStore(format!("{}: {}", path.display(), reason));
+    let mut rows = Vec::new();
+    for row in reader.get_row_iter(None).map_err(|e| archive_error(path, e))? {
+        let row = row.map_err(|e| archive_error(path, e))?;
+        let (mut seq, mut correlation_id) = (None, None);
+        let (mut id, mut kind, mut timestamp, mut entity_id, mut data) = (None, None, None, None, None);
+        for (name, field) in row.get_column_iter() {
+            let slot = match name.as_str() {
+                "seq" => {
+                    seq = match field {
+                        Field::Long(n) => Some(*n),
+                        Field::Int(n) => Some(i64::from(*n)),
+                        Field::Null => None,
+                        other => return Err(invalid(format!("unexpected seq {}", other))),
+                    };
+                    continue;
+                }
+                "id" => &mut id,
+                "kind" => &mut kind,
+                "timestamp" => &mut timestamp,
+                "entity_id" => &mut entity_id,
+                "correlation_id" => &mut correlation_id,
+                "data" => &mut data,
+                _ => continue,
+            };
+            *slot = match field {
+                Field::Null => None,
+                Field::Str(s) => Some(s.clone()),
+                Field::Bytes(b) => Some(String::from_utf8_lossy(b.data()).into_owned()),
+                other => return Err(invalid(format!("unexpected value {} in column {}", other, name))),
+            };
+        }
+        let required = |value: Option<String>, column: &str| {
+            value.ok_or_else(|| invalid(format!("row {} has no {}", rows.len(), column)))
+        };
+        rows.push(ArchivedEvent {
+            seq: seq.ok_or_else(|| invalid(format!("row {} has no seq", rows.len())))?,
+            id: required(id, "id")?,
+            kind: required(kind, "kind")?,
+            timestamp: required(timestamp, "timestamp")?,
+            entity_id: required(entity_id, "entity_id")?,
+            correlation_id,
+            data: required(data, "data")?,
+        });
+    }
+    Ok(rows)
+}
+
+fn archive_error(path: &Path, e: ParquetError) -> NeuroLoomError {
+    NeuroLoomError::EventStore(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use ::parquet::basic::{ConvertedType, Repetition, Type as PhysicalType};
+    use ::parquet::file::reader::FileReader;
+
+    use super::*;
+
+    fn rows(count: i64) -> Vec<ArchivedEvent> {
+        (0..count)
+            .map(|i| ArchivedEvent {
+                seq: 1_000 + i,
+                id: format!("id-{}", i),
+                kind: "node_updated".to_string(),
+                timestamp: format!("2026-01-{:02}T00:00:00+00:00", i % 28 + 1),
+                entity_id: "entity".to_string(),
+                correlation_id: (i % 3 == 0).then(|| format!("corr-{}", i)),
+                data: format!("{{\"n\":{}}}", i),
+            })
+            .collect()
+    }
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("nl_archive_{}.parquet", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_events_roundtrip_through_parquet() {
+        let rows = rows(ROW_GROUP_ROWS as i64 + 25);
+        let path = temp_path();
+        write_events(&path, &rows).unwrap();
+        assert_eq!(read_events(&path).unwrap(), rows);
+
+        // 通用读取端看到的是标准的 schema：INT64 与 UTF8 字符串列，只有 correlation_id 可空
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.num_row_groups(), 2);
+        assert_eq!(metadata.file_metadata().num_rows(), rows.len() as i64);
+        let schema = metadata.file_metadata().schema_descr();
+        let columns: Vec<(&str, PhysicalType, ConvertedType, Repetition)> = schema
+            .columns()
+            .iter()
+            .map(|c| {
+                let info = c.self_type().get_basic_info();
+                (c.name(), c.physical_type(), c.converted_type(), info.repetition())
+            })
+            .collect();
+        assert_eq!(columns[0], ("seq", PhysicalType::INT64, ConvertedType::NONE, Repetition::REQUIRED));
+        assert_eq!(
+            columns[5],
+            ("correlation_id", PhysicalType::BYTE_ARRAY, ConvertedType::UTF8, Repetition::OPTIONAL)
+        );
+        assert_eq!(columns[6], ("data", PhysicalType::BYTE_ARRAY, ConvertedType::UTF8, Repetition::REQUIRED));
+        let gzip = Compression::GZIP(GzipLevel::default());
+        assert!(metadata.row_group(0).columns().iter().all(|c| c.compression() == gzip));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reads_archives_rewritten_by_other_tools() {
+        // 分析工具重写的归档：列顺序不同、字典编码、不压缩、seq 为 INT32、多出两列、correlation_id 必填
+        let schema = "message rewritten {
+            REQUIRED BYTE_ARRAY data (UTF8);
+            REQUIRED BYTE_ARRAY correlation_id (UTF8);
+            REQUIRED INT32 seq;
+            REQUIRED BYTE_ARRAY id (UTF8);
+            REQUIRED BYTE_ARRAY kind (UTF8);
+            REQUIRED BYTE_ARRAY timestamp (UTF8);
+            REQUIRED BYTE_ARRAY entity_id (UTF8);
+            OPTIONAL BYTE_ARRAY note (UTF8);
+            REQUIRED INT64 year;
+        }";
+        let expected = rows(6)
+            .into_iter()
+            .map(|mut row| {
+                row.correlation_id = Some(row.correlation_id.unwrap_or_else(|| "none".to_string()));
+                row
+            })
+            .collect::<Vec<_>>();
+        let path = temp_path();
+        let properties = WriterProperties::builder().set_dictionary_enabled(true).build();
+        let mut writer = SerializedFileWriter::new(
+            File::create(&path).unwrap(),
+            Arc::new(parse_message_type(schema).unwrap()),
+            Arc::new(properties),
+        )
+        .unwrap();
+        let mut group = writer.next_row_group().unwrap();
+        let mut index = 0;
+        while let Some(mut column) = group.next_column().unwrap() {
+            let text = |f: fn(&ArchivedEvent) -> &str| -> Vec<ByteArray> {
+                expected.iter().map(|row| ByteArray::from(f(row))).collect()
+            };
+            match index {
+                2 => {
+                    let seqs: Vec<i32> = expected.iter().map(|row| row.seq as i32).collect();
+                    column.typed::<::parquet::data_type::Int32Type>().write_batch(&seqs, None, None).unwrap();
+                }
+                7 => {
+                    let levels = vec![0; expected.len()];
+                    column.typed::<ByteArrayType>().write_batch(&[], Some(&levels), None).unwrap();
+                }
+                8 => {
+                    column.typed::<Int64Type>().write_batch(&vec![2026; expected.len()], None, None).unwrap();
+                }
+                _ => {
+                    let values = match index {
+                        0 => text(|row| &row.data),
+                        1 => text(|row| row.correlation_id.as_deref().unwrap()),
+                        3 => text(|row| &row.id),
+                        4 => text(|row| &row.kind),
+                        5 => text(|row| &row.timestamp),
+                        _ => text(|row| &row.entity_id),
+                    };
+                    column.typed::<ByteArrayType>().write_batch(&values, None, None).unwrap();
+                }
+            }
+            column.close().unwrap();
+            index += 1;
+        }
+        group.close().unwrap();
+        writer.close().unwrap();
+
+        assert_eq!(read_events(&path).unwrap(), expected);
+
+        // 缺少必需列时报错而不是补空值
+        let schema = Arc::new(parse_message_type("message partial { REQUIRED INT64 seq; }").unwrap());
+        let mut writer = SerializedFileWriter::new(File::create(&path).unwrap(), schema, Default::default()).unwrap();
+        let mut group = writer.next_row_group().unwrap();
+        let mut column = group.next_column().unwrap().unwrap();
+        column.typed::<Int64Type>().write_batch(&[1], None, None).unwrap();
+        column.close().unwrap();
+        group.close().unwrap();
+        writer.close().unwrap();
+        let error = read_events(&path).unwrap_err().to_string();
+        assert!(error.contains("row 0 has no id"), "{}", error);
+
+        std::fs::write(&path, b"PAR1 not really parquet PAR1").unwrap();
+        assert!(read_events(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,362 @@
+//! PII 脱敏与可逆令牌化
+//!
+//! `PiiScrubber` 把文本中的个人信息替换为 `[PII:<kind>:<n>]` 令牌：
+//! - 邮箱、电话号码（国际格式、`(415) 555-0132` 一类北美格式、中国大陆手机号）
+//! - 人名：带称谓的英文姓名（`Dr. Jane Doe`）、`张先生` 一类中文称呼、配置的已知人名与正则，
+//!   以及可选的 `EntityRecognizer`（接入 NER 模型）
+//!
+//! 同一原文在同一个 `PiiTokens` 中始终对应同一令牌，因此标签、元数据与正文脱敏后仍可互相对照。
+//! `PiiTokens` 记录令牌到原文的映射，可用 `restore` 还原；映射本身含原始 PII，只应加密保存。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use nl_core::{NeuroLoomError, Result};
+
+/// 工作区 PII 脱敏配置文件名
+pub const PII_FILE: &str = "pii.json";
+
+const EMAIL_PATTERN: &str = r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b";
+
+const PHONE_PATTERN: &str = concat!(
+    r"\+\d{1,3}[\s.-]?(?:\(\d{1,4}\)[\s.-]?)?\d{2,4}(?:[\s.-]?\d{2,4}){1,3}\b",
+    r"|(?:\(\d{3}\)\s?|\b\d{3}[\s.-])\d{3}[\s.-]\d{4}\b",
+    r"|\b1[3-9]\d{9}\b",
+);
+
+/// 带称谓的人名：只替换 `name` 分组，称谓保留
+const TITLED_NAME_PATTERN: &str = concat!(
+    r"\b(?:Mr|Mrs|Ms|Miss|Dr|Prof)\.?\s+(?P<name>[A-Z][a-z]+(?:\s+[A-Z][a-z]+)?)",
+    r"|(?P<surname>\p{Han})(?:先生|女士|小姐)",
+);
+
+const TOKEN_PATTERN: &str = r"\[PII:[a-z]+:\d+\]";
+
+fn builtin(pattern: &'static str, cell: &'static OnceLock<Regex>) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+fn email() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    builtin(EMAIL_PATTERN, &REGEX)
+}
+
+fn phone() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    builtin(PHONE_PATTERN, &REGEX)
+}
+
+fn titled_name() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    builtin(TITLED_NAME_PATTERN, &REGEX)
+}
+
+fn token() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    builtin(TOKEN_PATTERN, &REGEX)
+}
+
+/// PII 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    Name,
+}
+
+impl PiiKind {
+    /// 类型名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Phone => "phone",
+            PiiKind::Name => "name",
+        }
+    }
+}
+
+impl fmt::Display for PiiKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 脱敏配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PiiConfig {
+    /// 是否启用（默认关闭，企业部署打开）
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub emails: bool,
+    #[serde(default = "default_true")]
+    pub phones: bool,
+    /// 识别带称谓的人名
+    #[serde(default = "default_true")]
+    pub titled_names: bool,
+    /// 已知人名（如组织成员名单），英文名不区分大小写
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// 追加的人名正则（带 `name` 命名分组时只替换该分组）
+    #[serde(default)]
+    pub name_patterns: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for PiiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            emails: true,
+            phones: true,
+            titled_names: true,
+            names: Vec::new(),
+            name_patterns: Vec::new(),
+        }
+    }
+}
+
+impl PiiConfig {
+    /// 读取配置（文件不存在时返回默认配置）
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// 实体识别器（如 NER 模型），补充正则识别不到的人名等
+pub trait EntityRecognizer: Send + Sync {
+    /// 返回识别出的（字节区间, 类型）
+    fn recognize(&self, text: &str) -> Vec<(Range<usize>, PiiKind)>;
+}
+
+/// 令牌到原文的映射
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PiiTokens {
+    tokens: BTreeMap<String, String>,
+}
+
+impl PiiTokens {
+    /// 令牌数
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// 令牌对应的原文
+    pub fn get(&self, token: &str) -> Option<&str> {
+        self.tokens.get(token).map(String::as_str)
+    }
+
+    /// 原文对应的令牌（已有则复用）
+    fn token_for(&mut self, kind: PiiKind, value: &str) -> String {
+        if let Some((token, _)) = self.tokens.iter().find(|(_, v)| v.as_str() == value) {
+            return token.clone();
+        }
+        let prefix = format!("[PII:{}:", kind);
+        let n = self.tokens.keys().filter(|t| t.starts_with(&prefix)).count() + 1;
+        let token = format!("{}{}]", prefix, n);
+        self.tokens.insert(token.clone(), value.to_string());
+        token
+    }
+
+    /// 把文本中的令牌还原为原文（未知令牌保持不变）
+    pub fn restore(&self, text: &str) -> String {
+        token()
+            .replace_all(text, |caps: &regex::Captures| {
+                let token = &caps[0];
+                self.get(token).unwrap_or(token).to_string()
+            })
+            .into_owned()
+    }
+}
+
+/// PII 脱敏器
+#[derive(Clone)]
+pub struct PiiScrubber {
+    emails: bool,
+    phones: bool,
+    titled_names: bool,
+    names: Option<Regex>,
+    name_patterns: Vec<Regex>,
+    recognizer: Option<Arc<dyn EntityRecognizer>>,
+}
+
+impl Default for PiiScrubber {
+    fn default() -> Self {
+        Self {
+            emails: true,
+            phones: true,
+            titled_names: true,
+            names: None,
+            name_patterns: Vec::new(),
+            recognizer: None,
+        }
+    }
+}
+
+impl fmt::Debug for PiiScrubber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PiiScrubber")
+            .field("emails", &self.emails)
+            .field("phones", &self.phones)
+            .field("titled_names", &self.titled_names)
+            .field("names", &self.names.is_some())
+            .field("name_patterns", &self.name_patterns.len())
+            .field("recognizer", &self.recognizer.is_some())
+            .finish()
+    }
+}
+
+impl PiiScrubber {
+    /// 由配置创建
+    pub fn from_config(config: &PiiConfig) -> Result<Self> {
+        let mut scrubber = Self {
+            emails: config.emails,
+            phones: config.phones,
+            titled_names: config.titled_names,
+            ..Self::default()
+        };
+        scrubber = scrubber.with_names(config.names.iter().map(String::as_str))?;
+        for pattern in &config.name_patterns {
+            scrubber = scrubber.with_name_pattern(pattern)?;
+        }
+        Ok(scrubber)
+    }
+
+    /// 设置已知人名（替换之前的名单）
+    pub fn with_names<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Result<Self> {
+        let mut names: Vec<&str> = names.into_iter().map(str::trim).filter(|n| !n.is_empty()).collect();
+        if names.is_empty() {
+            return Ok(self);
+        }
+        // 长名优先，避免 "Ann" 先于 "Anna" 命中
+        names.sort_by_key(|n| std::cmp::Reverse(n.len()));
+        let alternatives: Vec<String> = names
+            .iter()
+            .map(|n| match n.is_ascii() {
+                true => format!(r"\b{}\b", regex::escape(n)),
+                false => regex::escape(n),
+            })
+            .collect();
+        let pattern = format!("(?i){}", alternatives.join("|"));
+        self.names = Some(
+            Regex::new(&pattern).map_err(|e| NeuroLoomError::EventStore(format!("invalid pii name list: {}", e)))?,
+        );
+        Ok(self)
+    }
+
+    /// 追加人名正则
+    pub fn with_name_pattern(mut self, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| NeuroLoomError::EventStore(format!("invalid pii pattern {}: {}", pattern, e)))?;
+        self.name_patterns.push(regex);
+        Ok(self)
+    }
+
+    /// 设置实体识别器
+    pub fn with_recognizer(mut self, recognizer: Arc<dyn EntityRecognizer>) -> Self {
+        self.recognizer = Some(recognizer);
+        self
+    }
+
+    /// 识别文本中的 PII，返回互不重叠、按位置排序的（字节区间, 类型）
+    pub fn find(&self, text: &str) -> Vec<(Range<usize>, PiiKind)> {
+        let mut found: Vec<(Range<usize>, PiiKind)> = Vec::new();
+        let mut push = |range: Range<usize>, kind: PiiKind| {
+            let overlaps = found.iter().any(|(r, _)| r.start < range.end && range.start < r.end);
+            // 已有令牌不再识别（重复脱敏时保持不变）
+            let tokenized = token().find_iter(text).any(|t| t.start() < range.end && range.start < t.end());
+            if !range.is_empty() && !overlaps && !tokenized {
+                found.push((range, kind));
+            }
+        };
+        if self.emails {
+            email().find_iter(text).for_each(|m| push(m.range(), PiiKind::Email));
+        }
+        if self.phones {
+            phone().find_iter(text).for_each(|m| push(m.range(), PiiKind::Phone));
+        }
+        let mut name_regexes: Vec<&Regex> = self.names.iter().chain(&self.name_patterns).collect();
+        if self.titled_names {
+            name_regexes.push(titled_name());
+        }
+        for regex in name_regexes {
+            for caps in regex.captures_iter(text) {
+                let m = caps.name("name").or_else(|| caps.name("surname")).or_else(|| caps.get(0));
+                if let Some(m) = m {
+                    push(m.range(), PiiKind::Name);
+                }
+            }
+        }
+        if let Some(recognizer) = &self.recognizer {
+            for (range, kind) in recognizer.recognize(text) {
+                if text.get(range.clone()).is_some() {
+                    push(range, kind);
+                }
+            }
+        }
+        found.sort_by_key(|(range, _)| range.start);
+        found
+    }
+
+    /// 把 PII 替换为令牌，映射记入 `tokens`
+    pub fn scrub(&self, text: &str, tokens: &mut PiiTokens) -> String {
+        let mut scrubbed = String::with_capacity(text.len());
+        let mut last = 0;
+        for (range, kind) in self.find(text) {
+            scrubbed.push_str(&text[last..range.start]);
+            scrubbed.push_str(&tokens.token_for(kind, &text[range.clone()]));
+            last = range.end;
+        }
+        scrubbed.push_str(&text[last..]);
+        scrubbed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_and_restore() {
+        let scrubber = PiiScrubber::from_config(&PiiConfig {
+            names: vec!["Alice Wong".to_string(), "李雷".to_string()],
+            ..PiiConfig::default()
+        })
+        .unwrap();
+        let text = "Ping alice wong (alice@corp.example, +1 (415) 555-0132) or Dr. Jane Doe; \
+                    李雷 13800138000，张先生 cc alice@corp.example. Build 2024-06-01 took 1234 ms.";
+        let mut tokens = PiiTokens::default();
+        let scrubbed = scrubber.scrub(text, &mut tokens);
+        assert_eq!(
+            scrubbed,
+            "Ping [PII:name:1] ([PII:email:1], [PII:phone:1]) or Dr. [PII:name:2]; \
+             [PII:name:3] [PII:phone:2]，[PII:name:4]先生 cc [PII:email:1]. Build 2024-06-01 took 1234 ms."
+        );
+        assert_eq!(tokens.len(), 7);
+        assert_eq!(tokens.get("[PII:name:2]"), Some("Jane Doe"));
+
+        // 再次脱敏不改变令牌，还原得到原文
+        assert_eq!(scrubber.scrub(&scrubbed, &mut tokens), scrubbed);
+        assert_eq!(tokens.restore(&scrubbed), text);
+        assert_eq!(tokens.restore("[PII:email:9]"), "[PII:email:9]");
+    }
+}
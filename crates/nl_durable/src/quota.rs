@@ -0,0 +1,349 @@
+//! Actor 资源配额
+//!
+//! 按 Actor 限制每小时 LLM token、沙箱累计运行时长与累计写入字节数。
+//! 网关与沙箱在入口处调用 `check_*` 拒绝已超额的 Actor，执行后调用 `record_*` 记账；
+//! 一旦超额即暂停该 Actor 并发布 `ActorSuspended` 告警事件，直到 `reset` 解除。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use nl_core::event::{Event, EventKind};
+use nl_core::{NeuroLoomError, Result};
+
+use crate::actor_mesh::{ActorId, ActorMesh, ActorMessage, ActorState};
+use crate::event_bus::EventBus;
+
+/// LLM token 统计窗口
+const TOKEN_WINDOW: Duration = Duration::from_secs(3600);
+
+/// 资源配额 (`None` 表示不限制)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceQuota {
+    /// 每小时最大 LLM token 数
+    pub max_llm_tokens_per_hour: Option<u64>,
+    /// 沙箱最长累计运行时长
+    pub max_sandbox_wall_time: Option<Duration>,
+    /// 最大累计写入字节数
+    pub max_bytes_written: Option<u64>,
+}
+
+impl ResourceQuota {
+    /// 不限制任何资源
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// 设置每小时 LLM token 上限
+    pub fn with_llm_tokens_per_hour(mut self, tokens: u64) -> Self {
+        self.max_llm_tokens_per_hour = Some(tokens);
+        self
+    }
+
+    /// 设置沙箱累计运行时长上限
+    pub fn with_sandbox_wall_time(mut self, limit: Duration) -> Self {
+        self.max_sandbox_wall_time = Some(limit);
+        self
+    }
+
+    /// 设置累计写入字节上限
+    pub fn with_bytes_written(mut self, bytes: u64) -> Self {
+        self.max_bytes_written = Some(bytes);
+        self
+    }
+}
+
+/// 受配额约束的资源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaResource {
+    /// LLM token
+    LlmTokens,
+    /// 沙箱运行时长
+    SandboxWallTime,
+    /// 写入字节
+    BytesWritten,
+}
+
+impl QuotaResource {
+    /// 资源名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaResource::LlmTokens => "llm_tokens",
+            QuotaResource::SandboxWallTime => "sandbox_wall_time",
+            QuotaResource::BytesWritten => "bytes_written",
+        }
+    }
+}
+
+/// 资源使用量快照
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct QuotaUsage {
+    /// 最近一小时 LLM token 数
+    pub llm_tokens_last_hour: u64,
+    /// 沙箱累计运行时长
+    pub sandbox_wall_time: Duration,
+    /// 累计写入字节数
+    pub bytes_written: u64,
+    /// 是否因超额被暂停
+    pub suspended: bool,
+}
+
+#[derive(Debug, Default)]
+struct ActorUsage {
+    llm_tokens: VecDeque<(Instant, u64)>,
+    sandbox_wall_time: Duration,
+    bytes_written: u64,
+    suspended: bool,
+}
+
+impl ActorUsage {
+    fn tokens_last_hour(&mut self, now: Instant) -> u64 {
+        while self
+            .llm_tokens
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) >= TOKEN_WINDOW)
+        {
+            self.llm_tokens.pop_front();
+        }
+        self.llm_tokens.iter().map(|(_, tokens)| tokens).sum()
+    }
+
+    /// 返回第一个超额的资源
+    fn exceeded(&mut self, quota: &ResourceQuota, now: Instant) -> Option<QuotaResource> {
+        if quota
+            .max_llm_tokens_per_hour
+            .is_some_and(|max| self.tokens_last_hour(now) >= max)
+        {
+            return Some(QuotaResource::LlmTokens);
+        }
+        if quota
+            .max_sandbox_wall_time
+            .is_some_and(|max| self.sandbox_wall_time >= max)
+        {
+            return Some(QuotaResource::SandboxWallTime);
+        }
+        if quota.max_bytes_written.is_some_and(|max| self.bytes_written >= max) {
+            return Some(QuotaResource::BytesWritten);
+        }
+        None
+    }
+}
+
+/// 配额管理器
+pub struct QuotaManager {
+    default_quota: ResourceQuota,
+    quotas: RwLock<HashMap<ActorId, ResourceQuota>>,
+    usage: RwLock<HashMap<ActorId, ActorUsage>>,
+    mesh: Option<Arc<ActorMesh>>,
+    bus: Option<Arc<EventBus>>,
+}
+
+impl QuotaManager {
+    /// 创建配额管理器，未单独配置的 Actor 使用 `default_quota`
+    pub fn new(default_quota: ResourceQuota) -> Self {
+        Self {
+            default_quota,
+            quotas: RwLock::new(HashMap::new()),
+            usage: RwLock::new(HashMap::new()),
+            mesh: None,
+            bus: None,
+        }
+    }
+
+    /// 超额时通过 Actor Mesh 暂停 Actor
+    pub fn with_mesh(mut self, mesh: Arc<ActorMesh>) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+
+    /// 超额时向事件总线发布告警
+    pub fn with_event_bus(mut self, bus: Arc<EventBus>) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// 为指定 Actor 设置配额
+    pub async fn set_quota(&self, actor: ActorId, quota: ResourceQuota) {
+        self.quotas.write().await.insert(actor, quota);
+    }
+
+    /// 获取 Actor 的生效配额
+    pub async fn quota(&self, actor: ActorId) -> ResourceQuota {
+        self.quotas
+            .read()
+            .await
+            .get(&actor)
+            .cloned()
+            .unwrap_or_else(|| self.default_quota.clone())
+    }
+
+    /// 获取 Actor 的使用量
+    pub async fn usage(&self, actor: ActorId) -> QuotaUsage {
+        let mut usage = self.usage.write().await;
+        let entry = usage.entry(actor).or_default();
+        QuotaUsage {
+            llm_tokens_last_hour: entry.tokens_last_hour(Instant::now()),
+            sandbox_wall_time: entry.sandbox_wall_time,
+            bytes_written: entry.bytes_written,
+            suspended: entry.suspended,
+        }
+    }
+
+    /// 入口检查：Actor 已被暂停或任一配额已用尽时返回错误
+    pub async fn check(&self, actor: ActorId) -> Result<()> {
+        let quota = self.quota(actor).await;
+        let exceeded = {
+            let mut usage = self.usage.write().await;
+            let entry = usage.entry(actor).or_default();
+            if entry.suspended {
+                return Err(Self::suspended_error(actor));
+            }
+            entry.exceeded(&quota, Instant::now())
+        };
+        match exceeded {
+            Some(resource) => self.suspend(actor, resource).await,
+            None => Ok(()),
+        }
+    }
+
+    /// 记录 LLM token 用量
+    pub async fn record_llm_tokens(&self, actor: ActorId, tokens: u64) -> Result<()> {
+        self.record(actor, |usage| usage.llm_tokens.push_back((Instant::now(), tokens)))
+            .await
+    }
+
+    /// 记录沙箱运行时长
+    pub async fn record_sandbox_time(&self, actor: ActorId, elapsed: Duration) -> Result<()> {
+        self.record(actor, |usage| usage.sandbox_wall_time += elapsed).await
+    }
+
+    /// 记录写入字节数
+    pub async fn record_bytes_written(&self, actor: ActorId, bytes: u64) -> Result<()> {
+        self.record(actor, |usage| usage.bytes_written += bytes).await
+    }
+
+    /// 清零使用量并恢复 Actor
+    pub async fn reset(&self, actor: ActorId) {
+        self.usage.write().await.remove(&actor);
+        if let Some(mesh) = &self.mesh {
+            if mesh.get_state(&actor).await == Some(ActorState::Suspended) {
+                mesh.set_state(&actor, ActorState::Running).await;
+                if let Err(e) = mesh.send(&actor, ActorMessage::Resume).await {
+                    tracing::warn!("failed to resume actor {}: {}", actor, e);
+                }
+            }
+        }
+    }
+
+    async fn record(&self, actor: ActorId, apply: impl FnOnce(&mut ActorUsage)) -> Result<()> {
+        let quota = self.quota(actor).await;
+        let exceeded = {
+            let mut usage = self.usage.write().await;
+            let entry = usage.entry(actor).or_default();
+            apply(entry);
+            if entry.suspended {
+                None
+            } else {
+                entry.exceeded(&quota, Instant::now())
+            }
+        };
+        match exceeded {
+            Some(resource) => self.suspend(actor, resource).await,
+            None => Ok(()),
+        }
+    }
+
+    /// 暂停 Actor 并告警，返回配额错误
+    async fn suspend(&self, actor: ActorId, resource: QuotaResource) -> Result<()> {
+        let usage = {
+            let mut usage = self.usage.write().await;
+            let entry = usage.entry(actor).or_default();
+            if entry.suspended {
+                return Err(Self::suspended_error(actor));
+            }
+            entry.suspended = true;
+            QuotaUsage {
+                llm_tokens_last_hour: entry.tokens_last_hour(Instant::now()),
+                sandbox_wall_time: entry.sandbox_wall_time,
+                bytes_written: entry.bytes_written,
+                suspended: true,
+            }
+        };
+        tracing::warn!("Actor {} exceeded its {} quota, suspending", actor, resource.as_str());
+
+        if let Some(mesh) = &self.mesh {
+            mesh.set_state(&actor, ActorState::Suspended).await;
+            if let Err(e) = mesh.send(&actor, ActorMessage::Suspend).await {
+                tracing::warn!("failed to suspend actor {}: {}", actor, e);
+            }
+        }
+        if let Some(bus) = &self.bus {
+            let event = Event::new(
+                EventKind::ActorSuspended,
+                actor,
+                serde_json::json!({
+                    "reason": "quota_exceeded",
+                    "resource": resource,
+                    "usage": usage,
+                }),
+            );
+            bus.publish(&event);
+        }
+
+        Err(NeuroLoomError::Actor(format!(
+            "actor {} exceeded its {} quota",
+            actor,
+            resource.as_str()
+        )))
+    }
+
+    fn suspended_error(actor: ActorId) -> NeuroLoomError {
+        NeuroLoomError::Actor(format!("actor {} is suspended: quota exceeded", actor))
+    }
+}
+
+impl Default for QuotaManager {
+    fn default() -> Self {
+        Self::new(ResourceQuota::unlimited())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_exceeding_quota_suspends_and_alerts() {
+        let bus = Arc::new(EventBus::default());
+        let mut alerts = bus.subscribe(EventKind::ActorSuspended);
+        let quotas = QuotaManager::new(ResourceQuota::unlimited().with_llm_tokens_per_hour(100))
+            .with_event_bus(bus.clone());
+        let actor = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        quotas
+            .set_quota(other, ResourceQuota::unlimited().with_bytes_written(10))
+            .await;
+
+        quotas.check(actor).await.unwrap();
+        quotas.record_llm_tokens(actor, 60).await.unwrap();
+        assert!(quotas.record_llm_tokens(actor, 60).await.is_err());
+        assert!(quotas.check(actor).await.is_err());
+        assert!(quotas.usage(actor).await.suspended);
+
+        let alert = alerts.recv().await.unwrap();
+        assert_eq!(alert.entity_id, actor);
+        assert_eq!(alert.payload["resource"], "llm_tokens");
+
+        // 其他 Actor 不受影响，且按各自配额计算
+        quotas.record_llm_tokens(other, 1_000).await.unwrap();
+        assert!(quotas.record_bytes_written(other, 10).await.is_err());
+
+        quotas.reset(actor).await;
+        quotas.check(actor).await.unwrap();
+    }
+}
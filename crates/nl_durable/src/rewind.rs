@@ -0,0 +1,270 @@
+//! 工作区时间回溯
+//!
+//! 事件库是工作区状态的唯一来源，因此任意历史时刻的状态都可以通过重放得到：
+//! `WorkspaceView::at` 把截至某个事件（含）或某个时间点的事件按写入顺序折叠为各实体的状态，
+//! 这是只读视图（`nl workspace rewind --to <event-id|timestamp>`）。
+//!
+//! 实体状态的折叠规则：载荷为对象时按字段合并，否则整体替换；`NodeDeleted` 标记实体已删除；
+//! `StateRestored` 整体替换状态与删除标记。
+//!
+//! 恢复（`restore`）不删除也不改写历史：对回溯点之后状态发生变化的每个实体追加一条
+//! `StateRestored` 补偿事件，重放后实体回到回溯点的状态；回溯点之后才出现的实体标记为已删除。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::event::{Event, EventKind};
+use nl_core::{EntityId, NeuroLoomError, Result};
+
+use crate::event_store::EventStore;
+
+/// 回溯点：事件 ID（含该事件）或时间点（含该时刻）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RewindPoint {
+    Event(Uuid),
+    Time(DateTime<Utc>),
+}
+
+impl fmt::Display for RewindPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewindPoint::Event(id) => write!(f, "event {}", id),
+            RewindPoint::Time(time) => write!(f, "{}", time.to_rfc3339()),
+        }
+    }
+}
+
+impl FromStr for RewindPoint {
+    type Err = NeuroLoomError;
+
+    /// 解析事件 ID、RFC 3339 时间或日期（`YYYY-MM-DD`，视为当天 00:00 UTC）
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Ok(id) = s.parse::<Uuid>() {
+            return Ok(RewindPoint::Event(id));
+        }
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(RewindPoint::Time(time.with_timezone(&Utc)));
+        }
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|time| RewindPoint::Time(time.and_utc()))
+            .ok_or_else(|| {
+                NeuroLoomError::EventStore(format!("expected an event id or RFC 3339 timestamp, got {}", s))
+            })
+    }
+}
+
+/// 实体在回溯点的状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityState {
+    pub entity_id: EntityId,
+    /// 折叠后的状态
+    pub state: serde_json::Value,
+    /// 是否已删除
+    pub deleted: bool,
+    /// 截至回溯点的事件数
+    pub events: usize,
+    /// 最后一个事件的类型
+    pub last_kind: String,
+    pub last_event_id: Uuid,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl EntityState {
+    fn new(event: &Event) -> Self {
+        Self {
+            entity_id: event.entity_id,
+            state: serde_json::Value::Null,
+            deleted: false,
+            events: 0,
+            last_kind: String::new(),
+            last_event_id: event.id,
+            updated_at: event.timestamp,
+        }
+    }
+
+    /// 折叠一个事件
+    fn apply(&mut self, event: &Event) {
+        match &event.kind {
+            EventKind::StateRestored => {
+                self.state = event.payload["state"].clone();
+                self.deleted = event.payload["deleted"].as_bool().unwrap_or(false);
+            }
+            EventKind::NodeDeleted => self.deleted = true,
+            _ => {
+                match (&mut self.state, &event.payload) {
+                    (serde_json::Value::Object(state), serde_json::Value::Object(payload)) => {
+                        state.extend(payload.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    }
+                    (state, payload) => *state = payload.clone(),
+                }
+                if event.kind == EventKind::NodeCreated {
+                    self.deleted = false;
+                }
+            }
+        }
+        self.events += 1;
+        self.last_kind = event.kind.as_str().to_string();
+        self.last_event_id = event.id;
+        self.updated_at = event.timestamp;
+    }
+
+    /// 与另一状态是否一致（只比较状态与删除标记）
+    fn same_as(&self, other: &EntityState) -> bool {
+        self.state == other.state && self.deleted == other.deleted
+    }
+}
+
+/// 工作区在某一时刻的只读视图
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceView {
+    /// 回溯点（当前状态为 `None`）
+    pub point: Option<RewindPoint>,
+    /// 折叠的事件数
+    pub events: usize,
+    /// 折叠的最后一个事件
+    pub last_event_id: Option<Uuid>,
+    pub entities: BTreeMap<EntityId, EntityState>,
+}
+
+impl WorkspaceView {
+    /// 按写入顺序折叠事件；`point` 为 `None` 时折叠全部事件
+    pub fn from_events(events: &[Event], point: Option<RewindPoint>) -> Result<Self> {
+        let end = match point {
+            None => events.len(),
+            Some(RewindPoint::Event(id)) => {
+                events.iter().position(|e| e.id == id).map(|pos| pos + 1).ok_or_else(|| {
+                    NeuroLoomError::EventStore(format!("event {} not found", id))
+                })?
+            }
+            Some(RewindPoint::Time(time)) => events.iter().take_while(|e| e.timestamp <= time).count(),
+        };
+        let mut view = Self {
+            point,
+            ..Default::default()
+        };
+        for event in &events[..end] {
+            view.entities
+                .entry(event.entity_id)
+                .or_insert_with(|| EntityState::new(event))
+                .apply(event);
+            view.events += 1;
+            view.last_event_id = Some(event.id);
+        }
+        Ok(view)
+    }
+
+    /// 从事件库重建回溯点的视图
+    pub async fn at(store: &EventStore, point: RewindPoint) -> Result<Self> {
+        Self::from_events(&store.all_events().await?, Some(point))
+    }
+
+    /// 从事件库重建当前视图
+    pub async fn current(store: &EventStore) -> Result<Self> {
+        Self::from_events(&store.all_events().await?, None)
+    }
+
+    /// 实体在该视图中的状态
+    pub fn entity(&self, entity_id: EntityId) -> Option<&EntityState> {
+        self.entities.get(&entity_id)
+    }
+
+    /// 把 `current` 恢复到本视图所需的补偿事件；`entity` 限定只恢复单个实体
+    pub fn compensate(&self, current: &WorkspaceView, entity: Option<EntityId>) -> Vec<Event> {
+        let point = self.point.map(|p| p.to_string());
+        current
+            .entities
+            .values()
+            .filter(|now| entity.is_none_or(|id| id == now.entity_id))
+            .filter_map(|now| {
+                let (state, deleted) = match self.entities.get(&now.entity_id) {
+                    Some(then) if then.same_as(now) => return None,
+                    Some(then) => (then.state.clone(), then.deleted),
+                    // 回溯点之后才出现的实体
+                    None if now.deleted => return None,
+                    None => (serde_json::Value::Null, true),
+                };
+                let payload = serde_json::json!({
+                    "state": state,
+                    "deleted": deleted,
+                    "restored_to": point,
+                    "restored_event_id": self.last_event_id,
+                    "replaced_event_id": now.last_event_id,
+                });
+                let mut event = Event::new(EventKind::StateRestored, now.entity_id, payload);
+                event.causation_id = Some(now.last_event_id);
+                Some(event)
+            })
+            .collect()
+    }
+}
+
+/// 把工作区（或单个实体）恢复到回溯点：`apply` 为 `false` 时只返回将写入的补偿事件
+pub async fn restore(
+    store: &mut EventStore,
+    point: RewindPoint,
+    entity: Option<EntityId>,
+    apply: bool,
+) -> Result<Vec<Event>> {
+    let events = store.all_events().await?;
+    let then = WorkspaceView::from_events(&events, Some(point))?;
+    let now = WorkspaceView::from_events(&events, None)?;
+    let compensation = then.compensate(&now, entity);
+    if apply && !compensation.is_empty() {
+        store.append_batch(compensation.clone()).await?;
+    }
+    Ok(compensation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_store::EventStoreConfig;
+    use chrono::Duration;
+
+    fn event(kind: EventKind, entity: EntityId, payload: serde_json::Value, minutes: i64) -> Event {
+        let mut event = Event::new(kind, entity, payload);
+        event.timestamp = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap().with_timezone(&Utc)
+            + Duration::minutes(minutes);
+        event
+    }
+
+    #[tokio::test]
+    async fn test_rewind_view_and_compensating_restore() {
+        let (doc, note) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut store = EventStore::new(EventStoreConfig::default());
+        let created = event(EventKind::NodeCreated, doc, serde_json::json!({ "title": "draft", "tags": 1 }), 0);
+        let checkpoint = created.id;
+        store.append(created).await.unwrap();
+        store.append(event(EventKind::NodeUpdated, doc, serde_json::json!({ "title": "final" }), 10)).await.unwrap();
+        store.append(event(EventKind::NodeCreated, note, serde_json::json!({ "text": "later" }), 20)).await.unwrap();
+
+        let then = WorkspaceView::at(&store, RewindPoint::Event(checkpoint)).await.unwrap();
+        assert_eq!(then.events, 1);
+        assert_eq!(then.entity(doc).unwrap().state["title"], "draft");
+        assert!(then.entity(note).is_none());
+        let by_time = WorkspaceView::at(&store, "2026-01-01T00:15:00Z".parse().unwrap()).await.unwrap();
+        assert_eq!(by_time.entity(doc).unwrap().state, serde_json::json!({ "title": "final", "tags": 1 }));
+        assert!("yesterday".parse::<RewindPoint>().is_err());
+
+        // 预演不写入；恢复只追加补偿事件，历史保持不变
+        let plan = restore(&mut store, RewindPoint::Event(checkpoint), None, false).await.unwrap();
+        assert_eq!(plan.len(), 2);
+        assert_eq!(store.count().await.unwrap(), 3);
+        restore(&mut store, RewindPoint::Event(checkpoint), None, true).await.unwrap();
+        assert_eq!(store.count().await.unwrap(), 5);
+
+        let now = WorkspaceView::current(&store).await.unwrap();
+        assert_eq!(now.entity(doc).unwrap().state["title"], "draft");
+        assert!(now.entity(note).unwrap().deleted);
+        assert!(restore(&mut store, RewindPoint::Event(checkpoint), None, false).await.unwrap().is_empty());
+    }
+}
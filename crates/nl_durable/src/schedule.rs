@@ -0,0 +1,480 @@
+//! 定时调度
+//!
+//! 类 cron 表达式驱动的周期任务（如每晚“更新依赖并跑测试”）：
+//! - 表达式为 5 个字段 `分 时 日 月 周`，支持 `*`、`a-b`、`a,b`、`*/n`、`a-b/n`，
+//!   以及 `@hourly`、`@daily`、`@nightly`、`@weekly`、`@monthly` 别名；按本地时间计算
+//! - 调度绑定 SOP 工作流或编排目标，存于事件库所在 SQLite 的 `schedules` 表（无连接池时仅在内存）
+//! - 守护进程停机期间错过的执行按补跑策略处理：全部跳过、只补一次或逐次补跑（最多 `MAX_CATCH_UP` 次）
+
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+
+use crate::event_store::db_error;
+
+/// 逐次补跑的最大次数
+pub const MAX_CATCH_UP: usize = 10;
+
+/// 计划时间之后多久内触发仍视为准时（超过即视为错过）
+pub const ON_TIME_GRACE_SECS: i64 = 120;
+
+/// cron 表达式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    source: String,
+    minutes: BTreeSet<u32>,
+    hours: BTreeSet<u32>,
+    days: BTreeSet<u32>,
+    months: BTreeSet<u32>,
+    weekdays: BTreeSet<u32>,
+    /// 日字段是否受限（与周字段同时受限时两者满足其一即可）
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronExpr {
+    /// 解析表达式
+    pub fn parse(expr: &str) -> Result<Self> {
+        let source = expr.trim().to_string();
+        let expanded = match source.as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@nightly" => "0 2 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid(&source, "expected 5 fields: minute hour day month weekday"));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7).map_err(|e| invalid(&source, &e))?;
+        // 7 与 0 都表示周日
+        if weekdays.remove(&7) {
+            weekdays.insert(0);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59).map_err(|e| invalid(&source, &e))?,
+            hours: parse_field(hour, 0, 23).map_err(|e| invalid(&source, &e))?,
+            days: parse_field(day, 1, 31).map_err(|e| invalid(&source, &e))?,
+            months: parse_field(month, 1, 12).map_err(|e| invalid(&source, &e))?,
+            weekdays,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+            source,
+        })
+    }
+
+    /// 原始表达式
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days.contains(&date.day());
+        let weekday = self.weekdays.contains(&date.weekday().num_days_from_sunday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// 严格晚于 `after` 的下一个触发时间（不考虑时区）
+    pub fn next_after_naive(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        // 最多向后搜索 5 年（覆盖 2 月 29 日等稀疏表达式）
+        let limit = t + Duration::days(5 * 366);
+        while t <= limit {
+            if !self.months.contains(&t.month()) {
+                let (year, month) = if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_day(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.hours.contains(&t.hour()) {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+            if !self.minutes.contains(&t.minute()) {
+                t += Duration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+        None
+    }
+
+    /// 严格晚于 `after` 的下一个触发时间（按本地时间计算）
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut local = after.with_timezone(&Local).naive_local();
+        loop {
+            local = self.next_after_naive(local)?;
+            // 夏令时跳过的时刻不存在，继续向后找
+            if let Some(t) = Local.from_local_datetime(&local).earliest() {
+                let t = t.with_timezone(&Utc);
+                if t > after {
+                    return Some(t);
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for CronExpr {
+    type Err = NeuroLoomError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Serialize for CronExpr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for CronExpr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Self::parse(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+fn invalid(expr: &str, reason: &str) -> NeuroLoomError {
+    NeuroLoomError::Unknown(format!("invalid cron expression '{}': {}", expr, reason))
+}
+
+/// 解析单个字段
+fn parse_field(field: &str, min: u32, max: u32) -> std::result::Result<BTreeSet<u32>, String> {
+    let mut values = BTreeSet::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("invalid step in '{}'", part))?,
+            ),
+            None => (part, 1),
+        };
+        let number = |s: &str| {
+            s.parse::<u32>()
+                .ok()
+                .filter(|v| (min..=max).contains(v))
+                .ok_or_else(|| format!("'{}' is out of range {}-{}", s, min, max))
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (number(a)?, number(b)?),
+                // `a/n` 表示从 a 开始到上限
+                None if step > 1 => (number(range)?, max),
+                None => {
+                    let v = number(range)?;
+                    (v, v)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("empty range '{}'", part));
+        }
+        values.extend((start..=end).step_by(step as usize));
+    }
+    Ok(values)
+}
+
+/// 调度目标
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ScheduleTarget {
+    /// 执行指定名称的 SOP 工作流
+    Workflow(String),
+    /// 交给编排器分解执行的目标
+    Goal(String),
+}
+
+impl std::fmt::Display for ScheduleTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleTarget::Workflow(name) => write!(f, "workflow {}", name),
+            ScheduleTarget::Goal(goal) => write!(f, "goal \"{}\"", goal),
+        }
+    }
+}
+
+/// 错过执行的补跑策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// 跳过所有错过的执行
+    Skip,
+    /// 无论错过多少次只补跑一次
+    #[default]
+    Once,
+    /// 逐次补跑（最多 `MAX_CATCH_UP` 次）
+    All,
+}
+
+impl FromStr for CatchUpPolicy {
+    type Err = NeuroLoomError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(CatchUpPolicy::Skip),
+            "once" => Ok(CatchUpPolicy::Once),
+            "all" => Ok(CatchUpPolicy::All),
+            other => Err(NeuroLoomError::Unknown(format!(
+                "unknown catch-up policy: {} (expected skip, once or all)",
+                other
+            ))),
+        }
+    }
+}
+
+/// 调度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    /// 调度 ID
+    pub id: Uuid,
+    /// 名称（工作区内唯一）
+    pub name: String,
+    /// cron 表达式
+    pub cron: CronExpr,
+    /// 调度目标
+    pub target: ScheduleTarget,
+    /// 补跑策略
+    #[serde(default)]
+    pub catch_up: CatchUpPolicy,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 下一次尚未处理的触发时间
+    pub next_run_at: Option<DateTime<Utc>>,
+    /// 最近一次触发时间
+    pub last_run_at: Option<DateTime<Utc>>,
+}
+
+impl Schedule {
+    /// 创建调度，从当前时间起算下一次触发
+    pub fn new(name: impl Into<String>, cron: CronExpr, target: ScheduleTarget) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            next_run_at: cron.next_after(now),
+            cron,
+            target,
+            catch_up: CatchUpPolicy::default(),
+            created_at: now,
+            last_run_at: None,
+        }
+    }
+
+    /// 设置补跑策略
+    pub fn with_catch_up(mut self, catch_up: CatchUpPolicy) -> Self {
+        self.catch_up = catch_up;
+        self
+    }
+
+    /// 计算 `now` 时应触发的次数并推进下一次触发时间
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> usize {
+        let mut due = Vec::new();
+        let mut next = self.next_run_at;
+        while let Some(t) = next.filter(|t| *t <= now) {
+            due.push(t);
+            next = self.cron.next_after(t);
+            if due.len() > MAX_CATCH_UP {
+                // 错过太多次时不再逐个枚举
+                next = self.cron.next_after(now);
+                break;
+            }
+        }
+        self.next_run_at = next;
+        if due.is_empty() {
+            return 0;
+        }
+
+        let grace = Duration::seconds(ON_TIME_GRACE_SECS);
+        let on_time = due.iter().filter(|t| now - **t <= grace).count().min(1);
+        let runs = match self.catch_up {
+            CatchUpPolicy::Skip => on_time,
+            CatchUpPolicy::Once => 1,
+            CatchUpPolicy::All => due.len().min(MAX_CATCH_UP),
+        };
+        if runs > 0 {
+            self.last_run_at = Some(now);
+        }
+        runs
+    }
+}
+
+/// 调度存储
+pub struct ScheduleStore {
+    /// SQLite 连接池 (仅内存模式时为空)
+    pool: Option<SqlitePool>,
+    /// 全部调度（启动时从 SQLite 加载）
+    schedules: RwLock<HashMap<Uuid, Schedule>>,
+}
+
+impl ScheduleStore {
+    /// 创建内存调度存储
+    pub fn new() -> Self {
+        Self {
+            pool: None,
+            schedules: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 将调度持久化到 SQLite（通常与事件库共用同一连接池）并加载已有调度
+    pub async fn with_pool(mut self, pool: SqlitePool) -> Result<Self> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS schedules (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                data TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .map_err(db_error)?;
+
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT data FROM schedules")
+            .fetch_all(&pool)
+            .await
+            .map_err(db_error)?;
+        let mut schedules = HashMap::new();
+        for (data,) in rows {
+            match serde_json::from_str::<Schedule>(&data) {
+                Ok(schedule) => {
+                    schedules.insert(schedule.id, schedule);
+                }
+                Err(e) => tracing::warn!("Skipping unreadable schedule: {}", e),
+            }
+        }
+        self.schedules = RwLock::new(schedules);
+        self.pool = Some(pool);
+        Ok(self)
+    }
+
+    /// 添加调度（名称重复时报错）
+    pub async fn add(&self, schedule: Schedule) -> Result<()> {
+        let mut schedules = self.schedules.write().await;
+        if schedules.values().any(|s| s.name == schedule.name) {
+            return Err(NeuroLoomError::Unknown(format!("schedule {} already exists", schedule.name)));
+        }
+        self.save(&schedule).await?;
+        schedules.insert(schedule.id, schedule);
+        Ok(())
+    }
+
+    /// 更新调度状态
+    pub async fn update(&self, schedule: &Schedule) -> Result<()> {
+        let mut schedules = self.schedules.write().await;
+        if !schedules.contains_key(&schedule.id) {
+            return Ok(());
+        }
+        self.save(schedule).await?;
+        schedules.insert(schedule.id, schedule.clone());
+        Ok(())
+    }
+
+    /// 按名称或 ID 删除调度
+    pub async fn remove(&self, name_or_id: &str) -> Result<Option<Schedule>> {
+        let mut schedules = self.schedules.write().await;
+        let Some(id) = schedules
+            .values()
+            .find(|s| s.name == name_or_id || s.id.to_string() == name_or_id)
+            .map(|s| s.id)
+        else {
+            return Ok(None);
+        };
+        if let Some(pool) = &self.pool {
+            sqlx::query("DELETE FROM schedules WHERE id = ?")
+                .bind(id.to_string())
+                .execute(pool)
+                .await
+                .map_err(db_error)?;
+        }
+        Ok(schedules.remove(&id))
+    }
+
+    /// 全部调度（按名称排序）
+    pub async fn list(&self) -> Vec<Schedule> {
+        let mut all: Vec<Schedule> = self.schedules.read().await.values().cloned().collect();
+        all.sort_by(|a, b| a.name.cmp(&b.name));
+        all
+    }
+
+    async fn save(&self, schedule: &Schedule) -> Result<()> {
+        let Some(pool) = &self.pool else {
+            return Ok(());
+        };
+        sqlx::query("INSERT OR REPLACE INTO schedules (id, name, data) VALUES (?, ?, ?)")
+            .bind(schedule.id.to_string())
+            .bind(&schedule.name)
+            .bind(serde_json::to_string(schedule)?)
+            .execute(pool)
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+}
+
+impl Default for ScheduleStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_and_catch_up() {
+        let nightly = CronExpr::parse("30 2 * * 1-5").unwrap();
+        // 2026-10-16 是周五，下一次是周一凌晨
+        assert_eq!(nightly.next_after_naive(at("2026-10-16 03:00")), Some(at("2026-10-19 02:30")));
+        assert_eq!(
+            CronExpr::parse("*/15 * * * *").unwrap().next_after_naive(at("2026-10-16 10:07")),
+            Some(at("2026-10-16 10:15"))
+        );
+        assert_eq!(
+            CronExpr::parse("@monthly").unwrap().next_after_naive(at("2026-12-31 23:59")),
+            Some(at("2027-01-01 00:00"))
+        );
+        assert!(CronExpr::parse("61 * * * *").is_err());
+        assert!(CronExpr::parse("* * *").is_err());
+
+        let hourly = CronExpr::parse("@hourly").unwrap();
+        // 远离任何时区的整点/半点/刻钟，避免恰好落在准时窗口内
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 10, 37, 0).unwrap();
+        let mut schedule = Schedule::new("deps", hourly, ScheduleTarget::Workflow("update-deps".to_string()));
+        assert_eq!(schedule.take_due(now), 0);
+
+        // 停机 5 小时：按策略补跑
+        schedule.next_run_at = Some(now - Duration::hours(5));
+        let mut all = schedule.clone().with_catch_up(CatchUpPolicy::All);
+        let mut skip = schedule.clone().with_catch_up(CatchUpPolicy::Skip);
+        assert_eq!(schedule.take_due(now), 1);
+        assert!(schedule.next_run_at.unwrap() > now);
+        assert!((5..=6).contains(&all.take_due(now)));
+        assert_eq!(skip.take_due(now), 0);
+        assert!(skip.next_run_at.unwrap() > now);
+    }
+}
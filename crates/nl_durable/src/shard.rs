@@ -0,0 +1,139 @@
+//! 事件库按月分片
+//!
+//! 单个 SQLite 文件用久了会变得很大、很慢。`EventStore::rotate_shards` 把早于保留期的事件按月份
+//! 移到旁边的分片库 `<库名>.events-YYYY-MM.db`，热表只保留最近几个月；需要完整历史的事件类型
+//! （审计哈希链、评分校准样本）固定留在热表。读取全部事件或按时间范围查询时自动挂载覆盖到的分片，查完即卸载。
+//! 冷分片可由 `EventStore::export_shard` 转为 `<库名>.events-YYYY-MM.parquet` 供分析，仍可按月读回。
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::Serialize;
+
+use nl_core::event::EventKind;
+use nl_core::{NeuroLoomError, Result};
+
+/// 分片策略
+#[derive(Debug, Clone)]
+pub struct ShardPolicy {
+    /// 热表保留的完整月份数（不含当月）
+    pub hot_months: u32,
+    /// 始终留在热表的事件类型（`EventKind::as_str`）
+    pub pinned_kinds: Vec<String>,
+}
+
+impl Default for ShardPolicy {
+    fn default() -> Self {
+        Self {
+            hot_months: 1,
+            pinned_kinds: vec![
+                EventKind::GodModeAction.as_str().to_string(),
+                EventKind::VerdictOutcome.as_str().to_string(),
+            ],
+        }
+    }
+}
+
+/// 分片存储格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardFormat {
+    /// SQLite 分片库，查询时临时挂载
+    Sqlite,
+    /// 导出的 Parquet 归档
+    Parquet,
+}
+
+impl ShardFormat {
+    /// 格式名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShardFormat::Sqlite => "sqlite",
+            ShardFormat::Parquet => "parquet",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ShardFormat::Sqlite => "db",
+            ShardFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// 一个月份分片
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EventShard {
+    /// 月份（`YYYY-MM`）
+    pub month: String,
+    pub format: ShardFormat,
+    pub path: PathBuf,
+    /// 文件大小（字节）
+    pub bytes: u64,
+}
+
+/// 时间戳所在月份（`YYYY-MM`）
+pub fn month_of(timestamp: DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m").to_string()
+}
+
+/// 校验月份参数（`YYYY-MM`）
+pub fn parse_month(month: &str) -> Result<String> {
+    let valid = month.len() == 7
+        && month.as_bytes()[4] == b'-'
+        && month[..4].bytes().all(|b| b.is_ascii_digit())
+        && matches!(month[5..].parse::<u32>(), Ok(1..=12));
+    if !valid {
+        return Err(NeuroLoomError::EventStore(format!("invalid month {:?}, expected YYYY-MM", month)));
+    }
+    Ok(month.to_string())
+}
+
+/// 热表保留期的起始月份：早于该月份的事件移入分片
+pub(crate) fn cutoff_month(now: DateTime<Utc>, hot_months: u32) -> String {
+    let index = now.year() * 12 + now.month0() as i32 - hot_months as i32;
+    format!("{:04}-{:02}", index.div_euclid(12), index.rem_euclid(12) + 1)
+}
+
+/// 分片文件路径
+pub(crate) fn shard_path(database: &Path, month: &str, format: ShardFormat) -> PathBuf {
+    let stem = database.file_stem().unwrap_or_default().to_string_lossy();
+    database.with_file_name(format!("{}.events-{}.{}", stem, month, format.extension()))
+}
+
+/// 列出事件库旁的分片文件（按月份、格式排序）
+pub(crate) fn list_shards(database: &Path) -> Result<Vec<EventShard>> {
+    let stem = database.file_stem().unwrap_or_default().to_string_lossy();
+    let prefix = format!("{}.events-", stem);
+    let dir = match database.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut shards = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(rest) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let (Some(month), Some(b'.')) = (rest.get(..7), rest.as_bytes().get(7)) else {
+            continue;
+        };
+        let format = match &rest[8..] {
+            "db" => ShardFormat::Sqlite,
+            "parquet" => ShardFormat::Parquet,
+            _ => continue,
+        };
+        let Ok(month) = parse_month(month) else {
+            continue;
+        };
+        shards.push(EventShard {
+            month,
+            format,
+            path: entry.path(),
+            bytes: entry.metadata()?.len(),
+        });
+    }
+    shards.sort_by(|a, b| (&a.month, a.format.extension()).cmp(&(&b.month, b.format.extension())));
+    Ok(shards)
+}
@@ -0,0 +1,583 @@
+//! Actor Mesh 确定性仿真
+//!
+//! 为 `ActorMesh` 设置仿真（`ActorMesh::with_simulation`）后，注册与注销记入 `Simulation`，
+//! `send` 不再直接写入邮箱，而是交给 `Simulation` 排队：
+//! - 每次注册是该 Actor 的新一代（`generation`），消息投递给发送时已注册的那一代，注销后仍在途的消息照常送达旧邮箱
+//! - 投递顺序由种子决定：每一步在有就绪消息的 Actor 中按种子选一个，投递其最早的就绪消息（同一 Actor 内保持先后顺序）
+//! - 虚拟时钟：延迟投递的消息在时钟到达其投递时刻后才就绪；没有就绪消息时时钟直接跳到最近的投递时刻
+//! - 故障注入：按概率丢弃消息或延迟投递（延迟的消息可被同一 Actor 的后续消息超越）
+//!
+//! 相同种子与相同操作序列得到相同的 `trace`，用于复现注册、注销与发送之间的竞争。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::actor_mesh::{ActorId, ActorMessage};
+
+/// 仿真配置
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    /// 随机种子
+    pub seed: u64,
+    /// 消息被丢弃的概率
+    pub drop_rate: f64,
+    /// 消息被延迟投递的概率
+    pub delay_rate: f64,
+    /// 最大投递延迟
+    pub max_delay: Duration,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            drop_rate: 0.0,
+            delay_rate: 0.0,
+            max_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+impl SimConfig {
+    /// 使用指定种子
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 按概率丢弃消息
+    pub fn with_drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// 按概率延迟投递，延迟在 `(0, max_delay]` 内均匀分布
+    pub fn with_delay(mut self, rate: f64, max_delay: Duration) -> Self {
+        self.delay_rate = rate;
+        self.max_delay = max_delay;
+        self
+    }
+}
+
+/// 仿真事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimEventKind {
+    /// Actor 注册为新的一代
+    Registered,
+    /// Actor 注销
+    Unregistered,
+    /// 注入故障：丢弃
+    Dropped,
+    /// 注入故障：延迟到 `until_ms`
+    Delayed { until_ms: u64 },
+    /// 已写入邮箱
+    Delivered,
+    /// 邮箱已满，1 ms 后重试（该邮箱的其他消息随之等待）
+    Backpressure,
+    /// 接收端已关闭，消息丢失
+    Closed,
+}
+
+/// 仿真轨迹中的一条记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SimEvent {
+    /// 虚拟时钟（毫秒）
+    pub at_ms: u64,
+    /// 操作序号（按注册、注销与发送的顺序从 0 开始）
+    pub seq: u64,
+    pub target: ActorId,
+    /// 目标 Actor 的代数（从 0 开始）
+    pub generation: u64,
+    /// 消息描述（`ActorMessage::describe`），注册与注销为空
+    pub message: String,
+    #[serde(flatten)]
+    pub kind: SimEventKind,
+}
+
+/// 一代 Actor 的邮箱
+type Mailbox = (ActorId, u64);
+
+struct Pending {
+    seq: u64,
+    deliver_at: u64,
+    mailbox: Mailbox,
+    sender: mpsc::Sender<ActorMessage>,
+    message: ActorMessage,
+}
+
+struct SimState {
+    rng: u64,
+    now_ms: u64,
+    next_seq: u64,
+    pending: Vec<Pending>,
+    /// Actor -> 下一次注册的代数
+    generations: HashMap<ActorId, u64>,
+    /// 已注册的 Actor -> 当前代数
+    registered: HashMap<ActorId, u64>,
+    /// 已满的邮箱 -> 下次重试时刻
+    blocked: HashMap<Mailbox, u64>,
+    trace: Vec<SimEvent>,
+}
+
+impl SimState {
+    /// SplitMix64
+    fn next_u64(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// `[0, 1)` 内的均匀分布
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    /// 消息可投递的时刻：不早于其投递时刻与邮箱的重试时刻
+    fn ready_at(&self, pending: &Pending) -> u64 {
+        pending.deliver_at.max(self.blocked.get(&pending.mailbox).copied().unwrap_or(0))
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        self.next_seq += 1;
+        self.next_seq - 1
+    }
+
+    fn record(&mut self, seq: u64, (target, generation): Mailbox, message: String, kind: SimEventKind) -> SimEvent {
+        let event = SimEvent {
+            at_ms: self.now_ms,
+            seq,
+            target,
+            generation,
+            message,
+            kind,
+        };
+        self.trace.push(event.clone());
+        event
+    }
+}
+
+/// 确定性仿真调度器
+pub struct Simulation {
+    config: SimConfig,
+    state: Mutex<SimState>,
+}
+
+impl Simulation {
+    /// 创建仿真
+    pub fn new(config: SimConfig) -> Self {
+        let state = SimState {
+            rng: config.seed,
+            now_ms: 0,
+            next_seq: 0,
+            pending: Vec::new(),
+            generations: HashMap::new(),
+            registered: HashMap::new(),
+            blocked: HashMap::new(),
+            trace: Vec::new(),
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// 配置
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// 虚拟时钟
+    pub fn now(&self) -> Duration {
+        Duration::from_millis(self.lock().now_ms)
+    }
+
+    /// 推进虚拟时钟
+    pub fn advance(&self, duration: Duration) {
+        self.lock().now_ms += duration.as_millis() as u64;
+    }
+
+    /// 按种子在 `[0, n)` 中选一个（供测试驱动操作交错）
+    pub fn choose(&self, n: usize) -> usize {
+        self.lock().below(n)
+    }
+
+    /// 排队等待投递的消息数
+    pub fn pending(&self) -> usize {
+        self.lock().pending.len()
+    }
+
+    /// 已注册 Actor 的当前代数
+    pub fn generation(&self, id: &ActorId) -> Option<u64> {
+        self.lock().registered.get(id).copied()
+    }
+
+    /// 至今的仿真轨迹
+    pub fn trace(&self) -> Vec<SimEvent> {
+        self.lock().trace.clone()
+    }
+
+    /// 记录 `ActorMesh::register`：Actor 成为新的一代（重复注册同样替换为新的一代），返回其代数
+    pub(crate) fn register(&self, id: ActorId) -> u64 {
+        let mut state = self.lock();
+        let seq = state.next_seq();
+        let generation = *state.generations.entry(id).and_modify(|g| *g += 1).or_insert(0);
+        state.registered.insert(id, generation);
+        state.record(seq, (id, generation), String::new(), SimEventKind::Registered);
+        generation
+    }
+
+    /// 记录 `ActorMesh::unregister`；已在途的消息不受影响
+    pub(crate) fn unregister(&self, id: &ActorId) {
+        let mut state = self.lock();
+        if let Some(generation) = state.registered.remove(id) {
+            let seq = state.next_seq();
+            state.record(seq, (*id, generation), String::new(), SimEventKind::Unregistered);
+        }
+    }
+
+    /// 接收 `ActorMesh::send` 的消息，按配置注入故障后排队；目标未经仿真注册时忽略
+    pub(crate) fn enqueue(&self, target: ActorId, sender: mpsc::Sender<ActorMessage>, message: ActorMessage) {
+        let mut state = self.lock();
+        let Some(&generation) = state.registered.get(&target) else {
+            return;
+        };
+        let (seq, mailbox) = (state.next_seq(), (target, generation));
+        if state.next_f64() < self.config.drop_rate {
+            state.record(seq, mailbox, message.describe(), SimEventKind::Dropped);
+            return;
+        }
+        let mut deliver_at = state.now_ms;
+        if state.next_f64() < self.config.delay_rate {
+            let max_delay = (self.config.max_delay.as_millis() as u64).max(1);
+            deliver_at += 1 + state.next_u64() % max_delay;
+            state.record(seq, mailbox, message.describe(), SimEventKind::Delayed { until_ms: deliver_at });
+        }
+        state.pending.push(Pending {
+            seq,
+            deliver_at,
+            mailbox,
+            sender,
+            message,
+        });
+    }
+
+    /// 投递一条消息；没有待投递消息时返回 `None`
+    pub fn step(&self) -> Option<SimEvent> {
+        let mut state = self.lock();
+        let earliest = state.pending.iter().map(|p| state.ready_at(p)).min()?;
+        state.now_ms = state.now_ms.max(earliest);
+
+        let now = state.now_ms;
+        let mut mailboxes: Vec<Mailbox> = Vec::new();
+        for pending in state.pending.iter().filter(|p| state.ready_at(p) <= now) {
+            if !mailboxes.contains(&pending.mailbox) {
+                mailboxes.push(pending.mailbox);
+            }
+        }
+        let mailbox = mailboxes[state.below(mailboxes.len())];
+        let index = state
+            .pending
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.mailbox == mailbox && p.deliver_at <= now)
+            .min_by_key(|(_, p)| p.seq)
+            .map(|(index, _)| index)?;
+
+        let pending = state.pending.remove(index);
+        let (seq, description) = (pending.seq, pending.message.describe());
+        let kind = match pending.sender.try_send(pending.message) {
+            Ok(()) => SimEventKind::Delivered,
+            Err(TrySendError::Full(message)) => {
+                // 与阻塞发送一致：邮箱腾出空间前后续消息不能越过这条消息
+                state.blocked.insert(mailbox, now + 1);
+                state.pending.push(Pending { message, ..pending });
+                SimEventKind::Backpressure
+            }
+            Err(TrySendError::Closed(_)) => SimEventKind::Closed,
+        };
+        Some(state.record(seq, mailbox, description, kind))
+    }
+
+    /// 连续投递直到没有待投递消息或达到 `max_steps`，返回执行的步数
+    pub fn run_until_idle(&self, max_steps: usize) -> usize {
+        let mut steps = 0;
+        while steps < max_steps && self.step().is_some() {
+            steps += 1;
+        }
+        steps
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SimState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::actor_mesh::{ActorAddress, ActorMesh};
+
+    fn payload(message: ActorMessage) -> u64 {
+        match message {
+            ActorMessage::Custom(_, value) => value.as_u64().unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    fn message(value: u64) -> ActorMessage {
+        ActorMessage::Custom("m".into(), value.into())
+    }
+
+    async fn register(mesh: &ActorMesh, id: ActorId, capacity: usize) -> mpsc::Receiver<ActorMessage> {
+        let (sender, receiver) = mpsc::channel(capacity);
+        mesh.register(id, ActorAddress { id, sender }).await;
+        receiver
+    }
+
+    fn drain(mailbox: &mut mpsc::Receiver<ActorMessage>) -> Vec<u64> {
+        std::iter::from_fn(|| mailbox.try_recv().ok()).map(payload).collect()
+    }
+
+    /// 轨迹中的 (序号, 代数, 类型)
+    fn summary(sim: &Simulation) -> Vec<(u64, u64, SimEventKind)> {
+        sim.trace().iter().map(|e| (e.seq, e.generation, e.kind)).collect()
+    }
+
+    /// 按种子交错注册、注销、发送与投递，并与顺序模型比较：
+    /// 消息只投递给发送时已注册的那一代 Actor，不重复；无故障时全部按序送达
+    async fn run_scenario(config: SimConfig) -> Vec<SimEvent> {
+        let faulty = config.drop_rate > 0.0 || config.delay_rate > 0.0;
+        let sim = Arc::new(Simulation::new(config));
+        let mesh = ActorMesh::new().with_simulation(sim.clone());
+        let actors: Vec<ActorId> = (1..=3).map(Uuid::from_u128).collect();
+
+        // 每次注册是一代新邮箱
+        let mut mailboxes: Vec<mpsc::Receiver<ActorMessage>> = Vec::new();
+        let (mut expected, mut received): (Vec<Vec<u64>>, Vec<Vec<u64>>) = (Vec::new(), Vec::new());
+        let mut model: HashMap<ActorId, usize> = HashMap::new();
+        let mut next = 0u64;
+
+        for _ in 0..300 {
+            let actor = actors[sim.choose(actors.len())];
+            match sim.choose(6) {
+                0 => {
+                    let (sender, receiver) = mpsc::channel(4);
+                    mesh.register(actor, ActorAddress { id: actor, sender }).await;
+                    mailboxes.push(receiver);
+                    expected.push(Vec::new());
+                    received.push(Vec::new());
+                    model.insert(actor, mailboxes.len() - 1);
+                }
+                1 => {
+                    mesh.unregister(&actor).await;
+                    model.remove(&actor);
+                }
+                2 | 3 => {
+                    mesh.send(&actor, ActorMessage::Custom("m".into(), next.into())).await.unwrap();
+                    if let Some(&generation) = model.get(&actor) {
+                        expected[generation].push(next);
+                    }
+                    next += 1;
+                }
+                4 => {
+                    sim.step();
+                }
+                _ if !mailboxes.is_empty() => {
+                    let generation = sim.choose(mailboxes.len());
+                    while let Ok(message) = mailboxes[generation].try_recv() {
+                        received[generation].push(payload(message));
+                    }
+                }
+                _ => {}
+            }
+            assert_eq!(mesh.count().await, model.len());
+            for id in &actors {
+                assert_eq!(mesh.get_state(id).await.is_some(), model.contains_key(id));
+                assert_eq!(sim.generation(id).is_some(), model.contains_key(id));
+            }
+        }
+
+        loop {
+            for (generation, mailbox) in mailboxes.iter_mut().enumerate() {
+                while let Ok(message) = mailbox.try_recv() {
+                    received[generation].push(payload(message));
+                }
+            }
+            if sim.pending() == 0 {
+                break;
+            }
+            sim.run_until_idle(16);
+        }
+
+        let trace = sim.trace();
+        let count = |kind: fn(&SimEventKind) -> bool| trace.iter().filter(|e| kind(&e.kind)).count();
+        let sent: usize = expected.iter().map(Vec::len).sum();
+        let delivered: usize = received.iter().map(Vec::len).sum();
+        assert_eq!(count(|k| *k == SimEventKind::Delivered), delivered);
+        assert_eq!(delivered + count(|k| *k == SimEventKind::Dropped), sent);
+        assert_eq!(count(|k| *k == SimEventKind::Closed), 0);
+        for (expected, received) in expected.iter().zip(&received) {
+            if faulty {
+                let mut sorted = received.clone();
+                sorted.sort_unstable();
+                sorted.dedup();
+                assert_eq!(sorted.len(), received.len());
+                assert!(sorted.iter().all(|n| expected.contains(n)));
+            } else {
+                assert_eq!(received, expected);
+            }
+        }
+        assert!(sent > 20 && mailboxes.len() > 5);
+        trace
+    }
+
+    #[tokio::test]
+    async fn test_register_unregister_send_races_match_sequential_model() {
+        for seed in 0..40 {
+            run_scenario(SimConfig::default().with_seed(seed)).await;
+            let faulty = SimConfig::default()
+                .with_seed(seed)
+                .with_drop_rate(0.1)
+                .with_delay(0.3, Duration::from_millis(50));
+            run_scenario(faulty).await;
+        }
+
+        // 同一种子完整复现交错与故障
+        let config = SimConfig::default()
+            .with_seed(7)
+            .with_drop_rate(0.2)
+            .with_delay(0.3, Duration::from_millis(50));
+        let trace = run_scenario(config.clone()).await;
+        assert_eq!(trace, run_scenario(config.clone()).await);
+        assert_ne!(trace, run_scenario(config.with_seed(8)).await);
+        assert!(trace.iter().any(|e| matches!(e.kind, SimEventKind::Delayed { .. })));
+        assert!(trace.iter().any(|e| e.kind == SimEventKind::Backpressure));
+    }
+
+    #[tokio::test]
+    async fn test_unregister_racing_an_in_flight_send() {
+        let sim = Arc::new(Simulation::new(SimConfig::default()));
+        let mesh = ActorMesh::new().with_simulation(sim.clone());
+        let actor = Uuid::from_u128(1);
+        let mut mailbox = register(&mesh, actor, 4).await;
+
+        // 注销前发出的消息仍送达旧邮箱，注销后发出的消息不再排队
+        mesh.send(&actor, message(0)).await.unwrap();
+        mesh.unregister(&actor).await;
+        mesh.send(&actor, message(1)).await.unwrap();
+        assert_eq!(sim.pending(), 1);
+        assert_eq!(sim.generation(&actor), None);
+        sim.run_until_idle(8);
+        assert_eq!(drain(&mut mailbox), vec![0]);
+
+        // 旧邮箱已关闭时在途消息记为 Closed
+        let mut mailbox = register(&mesh, actor, 4).await;
+        mesh.send(&actor, message(2)).await.unwrap();
+        mesh.unregister(&actor).await;
+        mailbox.close();
+        sim.run_until_idle(8);
+        assert!(drain(&mut mailbox).is_empty());
+
+        use SimEventKind::*;
+        assert_eq!(
+            summary(&sim),
+            vec![
+                (0, 0, Registered),
+                (2, 0, Unregistered),
+                (1, 0, Delivered),
+                (3, 1, Registered),
+                (5, 1, Unregistered),
+                (4, 1, Closed),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_re_register_gets_a_new_generation() {
+        let sim = Arc::new(Simulation::new(SimConfig::default()));
+        let mesh = ActorMesh::new().with_simulation(sim.clone());
+        let actor = Uuid::from_u128(1);
+
+        let mut first = register(&mesh, actor, 4).await;
+        mesh.send(&actor, message(0)).await.unwrap();
+        mesh.unregister(&actor).await;
+        let mut second = register(&mesh, actor, 4).await;
+        mesh.send(&actor, message(1)).await.unwrap();
+        assert_eq!(sim.generation(&actor), Some(1));
+
+        // 未注销直接重新注册同样是新的一代
+        let mut third = register(&mesh, actor, 4).await;
+        mesh.send(&actor, message(2)).await.unwrap();
+        assert_eq!(sim.generation(&actor), Some(2));
+
+        sim.run_until_idle(8);
+        assert_eq!((drain(&mut first), drain(&mut second), drain(&mut third)), (vec![0], vec![1], vec![2]));
+        // 三代邮箱互不相干，投递先后由种子决定
+        let mut delivered: Vec<(u64, String)> = sim
+            .trace()
+            .into_iter()
+            .filter(|e| e.kind == SimEventKind::Delivered)
+            .map(|e| (e.generation, e.message))
+            .collect();
+        delivered.sort();
+        let expected: Vec<(u64, String)> = (0..3).map(|g| (g, "custom:m".to_string())).collect();
+        assert_eq!(delivered, expected);
+    }
+
+    #[tokio::test]
+    async fn test_backpressure_keeps_mailbox_order() {
+        let sim = Arc::new(Simulation::new(SimConfig::default()));
+        let mesh = ActorMesh::new().with_simulation(sim.clone());
+        let (full, other) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let mut full_mailbox = register(&mesh, full, 1).await;
+        let mut other_mailbox = register(&mesh, other, 4).await;
+        for value in 0..3 {
+            mesh.send(&full, message(value)).await.unwrap();
+        }
+
+        // 第一条占满邮箱后，后续消息等待重试且不能相互越过
+        while sim.pending() > 2 {
+            sim.step();
+        }
+        assert_eq!(sim.step().map(|e| (e.seq, e.kind)), Some((3, SimEventKind::Backpressure)));
+        let blocked_at = sim.now();
+
+        // 满邮箱不阻塞其他 Actor
+        mesh.send(&other, message(10)).await.unwrap();
+        assert_eq!(sim.step().map(|e| (e.target, e.kind)), Some((other, SimEventKind::Delivered)));
+        assert_eq!((drain(&mut other_mailbox), sim.now()), (vec![10], blocked_at));
+
+        let mut received = drain(&mut full_mailbox);
+        while sim.pending() > 0 {
+            let event = sim.step().unwrap();
+            if event.kind == SimEventKind::Delivered {
+                assert!(event.at_ms > blocked_at.as_millis() as u64);
+            }
+            received.extend(drain(&mut full_mailbox));
+        }
+        assert_eq!(received, vec![0, 1, 2]);
+        let order: Vec<u64> = sim
+            .trace()
+            .iter()
+            .filter(|e| e.target == full && e.kind == SimEventKind::Delivered)
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(order, vec![2, 3, 4]);
+    }
+}
@@ -1,12 +1,24 @@
 //! 快照管理器
+//!
+//! 快照可写入事件库所在的 SQLite（`snapshots` 表，按 entity_id + event_version 索引），
+//! 内存中只缓存按需加载过的快照；清理旧快照时可同步压缩事件库中已被快照覆盖的事件。
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 use nl_core::entity::EntityId;
 use nl_core::Result;
 
+use crate::event_store::{db_error, EventStore};
+
 /// 快照
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Snapshot {
@@ -36,14 +48,36 @@ impl Snapshot {
 }
 
 /// 快照策略
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum SnapshotStrategy {
     /// 每 N 个事件创建快照
     EveryNEvents(u64),
-    /// 时间间隔
+    /// 时间间隔（期间没有新事件时不重复快照）
     TimeInterval(chrono::Duration),
     /// 自定义条件
-    Custom(Box<dyn Fn(u64) -> bool + Send + Sync>),
+    Custom(Arc<dyn Fn(u64) -> bool + Send + Sync>),
+}
+
+impl SnapshotStrategy {
+    /// 以闭包构造自定义策略
+    pub fn custom(predicate: impl Fn(u64) -> bool + Send + Sync + 'static) -> Self {
+        SnapshotStrategy::Custom(Arc::new(predicate))
+    }
+
+    /// 每次检查都创建快照（例如法庭在每次裁决后检查）
+    pub fn always() -> Self {
+        Self::custom(|_| true)
+    }
+}
+
+impl std::fmt::Debug for SnapshotStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotStrategy::EveryNEvents(n) => f.debug_tuple("EveryNEvents").field(n).finish(),
+            SnapshotStrategy::TimeInterval(d) => f.debug_tuple("TimeInterval").field(d).finish(),
+            SnapshotStrategy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
 }
 
 /// 快照管理器
@@ -52,8 +86,12 @@ pub struct SnapshotManager {
     strategy: SnapshotStrategy,
     /// 内存缓存
     cache: Vec<Snapshot>,
+    /// SQLite 连接池 (仅内存模式时为空)
+    pool: Option<SqlitePool>,
     /// 上次快照版本
     last_snapshot_version: u64,
+    /// 上次快照时间
+    last_snapshot_at: DateTime<Utc>,
 }
 
 impl SnapshotManager {
@@ -62,7 +100,9 @@ impl SnapshotManager {
         Self {
             strategy,
             cache: Vec::new(),
+            pool: None,
             last_snapshot_version: 0,
+            last_snapshot_at: Utc::now(),
         }
     }
 
@@ -71,15 +111,40 @@ impl SnapshotManager {
         Self::new(SnapshotStrategy::EveryNEvents(100))
     }
 
+    /// 将快照持久化到 SQLite（通常与事件库共用同一连接池）
+    pub async fn with_pool(mut self, pool: SqlitePool) -> Result<Self> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS snapshots (
+                id TEXT PRIMARY KEY,
+                entity_id TEXT NOT NULL,
+                event_version INTEGER NOT NULL,
+                timestamp TEXT NOT NULL,
+                data TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .map_err(db_error)?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_snapshots_entity_version ON snapshots(entity_id, event_version)",
+        )
+        .execute(&pool)
+        .await
+        .map_err(db_error)?;
+
+        self.pool = Some(pool);
+        Ok(self)
+    }
+
     /// 检查是否需要创建快照
     pub fn should_snapshot(&self, current_version: u64) -> bool {
         match &self.strategy {
             SnapshotStrategy::EveryNEvents(n) => {
                 current_version - self.last_snapshot_version >= *n
             }
-            SnapshotStrategy::TimeInterval(_) => {
-                // TODO: 实现时间间隔检查
-                false
+            SnapshotStrategy::TimeInterval(interval) => {
+                current_version > self.last_snapshot_version
+                    && Utc::now() - self.last_snapshot_at >= *interval
             }
             SnapshotStrategy::Custom(predicate) => predicate(current_version),
         }
@@ -93,12 +158,61 @@ impl SnapshotManager {
         state: serde_json::Value,
     ) -> Result<Snapshot> {
         let snapshot = Snapshot::new(entity_id, event_version, state);
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "INSERT INTO snapshots (id, entity_id, event_version, timestamp, data) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(snapshot.id.to_string())
+            .bind(entity_id.to_string())
+            .bind(event_version as i64)
+            .bind(snapshot.timestamp.to_rfc3339())
+            .bind(serde_json::to_string(&snapshot)?)
+            .execute(pool)
+            .await
+            .map_err(db_error)?;
+        }
         self.cache.push(snapshot.clone());
         self.last_snapshot_version = event_version;
+        self.last_snapshot_at = snapshot.timestamp;
         Ok(snapshot)
     }
 
-    /// 获取实体的最新快照
+    /// 加载实体的最新快照（缓存未命中时查询 SQLite 并缓存结果）
+    pub async fn load_latest(&mut self, entity_id: EntityId) -> Result<Option<Snapshot>> {
+        self.load_at_version(entity_id, u64::MAX).await
+    }
+
+    /// 加载不晚于指定版本的最新快照
+    pub async fn load_at_version(&mut self, entity_id: EntityId, version: u64) -> Result<Option<Snapshot>> {
+        let cached = self.get_snapshot_at_version(entity_id, version).cloned();
+        let Some(pool) = &self.pool else {
+            return Ok(cached);
+        };
+
+        let row: Option<(String,)> = sqlx::query_as(
+            "SELECT data FROM snapshots WHERE entity_id = ? AND event_version <= ?
+             ORDER BY event_version DESC LIMIT 1",
+        )
+        .bind(entity_id.to_string())
+        .bind(version.min(i64::MAX as u64) as i64)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+
+        let Some((data,)) = row else {
+            return Ok(cached);
+        };
+        let snapshot: Snapshot = serde_json::from_str(&data)?;
+        if cached.as_ref().is_some_and(|c| c.event_version >= snapshot.event_version) {
+            return Ok(cached);
+        }
+        if !self.cache.iter().any(|s| s.id == snapshot.id) {
+            self.cache.push(snapshot.clone());
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// 获取实体的最新快照（仅查询内存缓存）
     pub fn get_latest_snapshot(&self, entity_id: EntityId) -> Option<&Snapshot> {
         self.cache
             .iter()
@@ -106,7 +220,7 @@ impl SnapshotManager {
             .max_by_key(|s| s.event_version)
     }
 
-    /// 获取指定版本的快照
+    /// 获取指定版本的快照（仅查询内存缓存）
     pub fn get_snapshot_at_version(&self, entity_id: EntityId, version: u64) -> Option<&Snapshot> {
         self.cache
             .iter()
@@ -114,17 +228,258 @@ impl SnapshotManager {
             .max_by_key(|s| s.event_version)
     }
 
-    /// 清理旧快照
+    /// 清理旧快照：每个实体只保留最新的 `keep_last` 个
     pub async fn prune_old_snapshots(&mut self, keep_last: usize) -> Result<()> {
-        // TODO: 实现基于时间的清理
-        if self.cache.len() > keep_last {
-            self.cache.drain(0..self.cache.len() - keep_last);
+        let keep_last = keep_last.max(1);
+
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "DELETE FROM snapshots WHERE id IN (
+                    SELECT id FROM (
+                        SELECT id, ROW_NUMBER() OVER (
+                            PARTITION BY entity_id ORDER BY event_version DESC
+                        ) AS rank FROM snapshots
+                    ) WHERE rank > ?
+                )",
+            )
+            .bind(keep_last as i64)
+            .execute(pool)
+            .await
+            .map_err(db_error)?;
         }
+
+        self.cache.sort_by_key(|s| std::cmp::Reverse(s.event_version));
+        let mut kept: HashMap<EntityId, usize> = HashMap::new();
+        self.cache.retain(|s| {
+            let count = kept.entry(s.entity_id).or_default();
+            *count += 1;
+            *count <= keep_last
+        });
         Ok(())
     }
 
-    /// 获取快照数量
+    /// 清理旧快照并压缩事件库
+    ///
+    /// 每个实体保留的最旧快照之前的事件已无需回放，从事件库中删除。返回删除的事件数。
+    pub async fn prune_and_compact(&mut self, store: &mut EventStore, keep_last: usize) -> Result<u64> {
+        self.prune_old_snapshots(keep_last).await?;
+
+        let floors: Vec<(EntityId, DateTime<Utc>)> = match &self.pool {
+            Some(pool) => {
+                let rows: Vec<(String, String)> = sqlx::query_as(
+                    "SELECT entity_id, MIN(timestamp) FROM snapshots GROUP BY entity_id",
+                )
+                .fetch_all(pool)
+                .await
+                .map_err(db_error)?;
+                rows.into_iter()
+                    .filter_map(|(entity, ts)| {
+                        let entity = entity.parse().ok()?;
+                        let ts = DateTime::parse_from_rfc3339(&ts).ok()?.with_timezone(&Utc);
+                        Some((entity, ts))
+                    })
+                    .collect()
+            }
+            None => {
+                let mut floors: HashMap<EntityId, DateTime<Utc>> = HashMap::new();
+                for snapshot in &self.cache {
+                    let floor = floors.entry(snapshot.entity_id).or_insert(snapshot.timestamp);
+                    *floor = (*floor).min(snapshot.timestamp);
+                }
+                floors.into_iter().collect()
+            }
+        };
+
+        let mut removed = 0;
+        for (entity_id, before) in floors {
+            removed += store.compact_entity(entity_id, before).await?;
+        }
+        Ok(removed)
+    }
+
+    /// 获取已缓存的快照数量
     pub fn count(&self) -> usize {
         self.cache.len()
     }
+
+    /// 获取已持久化的快照数量（内存模式下等于缓存数量）
+    pub async fn stored_count(&self) -> Result<u64> {
+        match &self.pool {
+            Some(pool) => {
+                let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM snapshots")
+                    .fetch_one(pool)
+                    .await
+                    .map_err(db_error)?;
+                Ok(count as u64)
+            }
+            None => Ok(self.cache.len() as u64),
+        }
+    }
+}
+
+/// 快照数据源（一类实体）
+#[async_trait]
+pub trait SnapshotSource: Send + Sync {
+    /// 实体类别名称
+    fn entity_class(&self) -> &str;
+
+    /// 采集当前状态：(实体 ID, 事件版本, 状态)
+    async fn capture(&self) -> Result<Vec<(EntityId, u64, serde_json::Value)>>;
+}
+
+/// 定时快照调度器（每个实体类别一个后台任务）
+pub struct SnapshotScheduler {
+    manager: Arc<Mutex<SnapshotManager>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SnapshotScheduler {
+    /// 创建调度器
+    pub fn new(manager: Arc<Mutex<SnapshotManager>>) -> Self {
+        Self {
+            manager,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// 注册实体类别，按墙钟间隔定时快照
+    pub fn register(&mut self, source: Arc<dyn SnapshotSource>, interval: std::time::Duration) {
+        let manager = self.manager.clone();
+        self.tasks.push(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = Self::run_once(&manager, source.as_ref()).await {
+                    tracing::warn!("snapshot of {} failed: {}", source.entity_class(), e);
+                }
+            }
+        }));
+    }
+
+    /// 对一个实体类别执行一轮快照，返回新建的快照数
+    ///
+    /// 自上次快照以来版本未变化的实体会被跳过（防抖）。
+    pub async fn run_once(manager: &Mutex<SnapshotManager>, source: &dyn SnapshotSource) -> Result<usize> {
+        let states = source.capture().await?;
+        let mut manager = manager.lock().await;
+        let mut created = 0;
+        for (entity_id, version, state) in states {
+            let latest = manager.load_latest(entity_id).await?.map(|s| s.event_version);
+            if latest.is_some_and(|v| v >= version) {
+                continue;
+            }
+            manager.create_snapshot(entity_id, version, state).await?;
+            created += 1;
+        }
+        Ok(created)
+    }
+
+    /// 后台任务数量
+    pub fn task_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// 停止全部后台任务
+    pub fn shutdown(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for SnapshotScheduler {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter {
+        id: EntityId,
+        version: std::sync::atomic::AtomicU64,
+    }
+
+    #[async_trait]
+    impl SnapshotSource for Counter {
+        fn entity_class(&self) -> &str {
+            "counter"
+        }
+
+        async fn capture(&self) -> Result<Vec<(EntityId, u64, serde_json::Value)>> {
+            let version = self.version.load(std::sync::atomic::Ordering::SeqCst);
+            Ok(vec![(self.id, version, serde_json::json!({ "value": version }))])
+        }
+    }
+
+    #[test]
+    fn test_time_interval_debounces_without_events() {
+        let mut manager = SnapshotManager::new(SnapshotStrategy::TimeInterval(chrono::Duration::zero()));
+        assert!(manager.should_snapshot(1));
+
+        manager.last_snapshot_version = 1;
+        assert!(!manager.should_snapshot(1));
+
+        manager.strategy = SnapshotStrategy::TimeInterval(chrono::Duration::hours(1));
+        assert!(!manager.should_snapshot(2));
+    }
+
+    #[tokio::test]
+    async fn test_run_once_skips_unchanged_entities() {
+        let manager = Mutex::new(SnapshotManager::default_manager());
+        let source = Counter {
+            id: Uuid::new_v4(),
+            version: std::sync::atomic::AtomicU64::new(3),
+        };
+
+        assert_eq!(SnapshotScheduler::run_once(&manager, &source).await.unwrap(), 1);
+        assert_eq!(SnapshotScheduler::run_once(&manager, &source).await.unwrap(), 0);
+
+        source.version.store(4, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(SnapshotScheduler::run_once(&manager, &source).await.unwrap(), 1);
+        assert_eq!(manager.lock().await.count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_persisted_snapshots_lazy_load_and_compact() {
+        use nl_core::event::{Event, EventKind};
+
+        let path = std::env::temp_dir().join(format!("nl_snapshots_{}.db", Uuid::new_v4()));
+        let mut store = EventStore::open(&path).await.unwrap();
+        let pool = store.pool().cloned().unwrap();
+        let entity = Uuid::new_v4();
+
+        let mut manager = SnapshotManager::default_manager().with_pool(pool.clone()).await.unwrap();
+        for version in 1..=3u64 {
+            store
+                .append(Event::new(EventKind::TaskPlanned, entity, serde_json::json!({ "v": version })))
+                .await
+                .unwrap();
+            store.flush().await.unwrap();
+            manager
+                .create_snapshot(entity, version, serde_json::json!({ "v": version }))
+                .await
+                .unwrap();
+        }
+        let later = Event::new(EventKind::TaskCompleted, entity, serde_json::json!({}));
+        store.append(later.clone()).await.unwrap();
+        store.flush().await.unwrap();
+
+        // 新管理器只在需要时从数据库加载
+        let mut reopened = SnapshotManager::default_manager().with_pool(pool).await.unwrap();
+        assert_eq!(reopened.count(), 0);
+        assert_eq!(reopened.load_latest(entity).await.unwrap().unwrap().event_version, 3);
+        assert_eq!(reopened.load_at_version(entity, 2).await.unwrap().unwrap().event_version, 2);
+        assert_eq!(reopened.count(), 2);
+
+        let removed = reopened.prune_and_compact(&mut store, 1).await.unwrap();
+        assert_eq!(reopened.stored_count().await.unwrap(), 1);
+        assert_eq!(removed, 3);
+        assert_eq!(store.get_events(entity).await.unwrap()[0].id, later.id);
+
+        let _ = std::fs::remove_file(&path);
+    }
 }
@@ -0,0 +1,156 @@
+//! 任务时间线重建
+//!
+//! 以关联 ID 收集一次任务产生的全部事件（LLM 调用、沙箱执行、裁决等），
+//! 再按因果 ID 组装为有序的因果树。
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use nl_core::event::Event;
+use nl_core::Result;
+
+use crate::event_store::EventStore;
+
+/// 时间线节点
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineNode {
+    /// 事件
+    pub event: Event,
+    /// 由该事件直接引发的后续事件
+    pub children: Vec<TimelineNode>,
+}
+
+/// 任务时间线
+#[derive(Debug, Clone, Serialize)]
+pub struct Timeline {
+    /// 关联 ID
+    pub correlation_id: Uuid,
+    /// 没有（已知）前置事件的根节点，按时间排序
+    pub roots: Vec<TimelineNode>,
+}
+
+impl Timeline {
+    /// 从事件存储加载指定关联 ID 的时间线
+    pub async fn load(store: &EventStore, correlation_id: Uuid) -> Result<Self> {
+        let events = store.get_events_by_correlation(correlation_id).await?;
+        Ok(Self::build(correlation_id, events))
+    }
+
+    /// 由事件列表组装因果树
+    ///
+    /// 前置事件不在列表中的事件视为根节点；同级节点按时间戳排序（时间相同时保持写入顺序）。
+    pub fn build(correlation_id: Uuid, mut events: Vec<Event>) -> Self {
+        events.sort_by_key(|e| e.timestamp);
+        let ids: HashSet<Uuid> = events.iter().map(|e| e.id).collect();
+
+        let (roots, rest): (Vec<Event>, Vec<Event>) = events
+            .into_iter()
+            .partition(|e| e.causation_id.is_none_or(|cause| !ids.contains(&cause)));
+
+        let mut children: HashMap<Uuid, Vec<Event>> = HashMap::new();
+        for event in rest {
+            if let Some(cause) = event.causation_id {
+                children.entry(cause).or_default().push(event);
+            }
+        }
+
+        Self {
+            correlation_id,
+            roots: roots
+                .into_iter()
+                .map(|event| Self::attach(event, &mut children))
+                .collect(),
+        }
+    }
+
+    fn attach(event: Event, children: &mut HashMap<Uuid, Vec<Event>>) -> TimelineNode {
+        let kids = children.remove(&event.id).unwrap_or_default();
+        TimelineNode {
+            event,
+            children: kids
+                .into_iter()
+                .map(|child| Self::attach(child, children))
+                .collect(),
+        }
+    }
+
+    /// 事件总数
+    pub fn len(&self) -> usize {
+        fn count(node: &TimelineNode) -> usize {
+            1 + node.children.iter().map(count).sum::<usize>()
+        }
+        self.roots.iter().map(count).sum()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// 渲染为文本树
+    pub fn render(&self) -> String {
+        let mut out = format!("trace {}\n", self.correlation_id);
+        let count = self.roots.len();
+        for (i, node) in self.roots.iter().enumerate() {
+            Self::render_node(node, "", i + 1 == count, &mut out);
+        }
+        out
+    }
+
+    fn render_node(node: &TimelineNode, prefix: &str, last: bool, out: &mut String) {
+        let event = &node.event;
+        out.push_str(&format!(
+            "{}{}{} {} [{}]\n",
+            prefix,
+            if last { "└── " } else { "├── " },
+            event.timestamp.format("%H:%M:%S%.3f"),
+            event.kind.as_str(),
+            event.entity_id,
+        ));
+
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        let count = node.children.len();
+        for (i, child) in node.children.iter().enumerate() {
+            Self::render_node(child, &child_prefix, i + 1 == count, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nl_core::event::EventKind;
+
+    #[test]
+    fn test_build_causal_tree() {
+        let task = Uuid::new_v4();
+        let planned = Event::new(EventKind::TaskPlanned, task, serde_json::json!({})).with_correlation(task);
+        let llm = Event::new(EventKind::LlmRequestStarted, task, serde_json::json!({}))
+            .with_correlation(task)
+            .with_causation(planned.id);
+        let exec = Event::new(EventKind::CodeExecuted, task, serde_json::json!({}))
+            .with_correlation(task)
+            .with_causation(llm.id);
+        let verdict = Event::new(EventKind::VerdictIssued, task, serde_json::json!({}))
+            .with_correlation(task)
+            .with_causation(Uuid::new_v4());
+
+        let timeline = Timeline::build(task, vec![exec, verdict, llm, planned]);
+
+        assert_eq!(timeline.len(), 4);
+        assert_eq!(timeline.roots.len(), 2);
+        let root = timeline
+            .roots
+            .iter()
+            .find(|n| n.event.kind == EventKind::TaskPlanned)
+            .unwrap();
+        assert_eq!(root.children[0].event.kind, EventKind::LlmRequestStarted);
+        assert_eq!(root.children[0].children[0].event.kind, EventKind::CodeExecuted);
+
+        let rendered = timeline.render();
+        assert_eq!(rendered.lines().count(), 5);
+        assert!(rendered.contains("code_executed"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Agent 能力清单与任务需求匹配
+//!
+//! Agent 在 HAP 握手时交换 `CapabilityManifest`；任务的 `requirements` 解析为
+//! `TaskRequirements`，只有能力满足需求的 Agent 才会收到该任务的广播。
+//!
+//! 需求字符串格式：`language:rust`、`tool:docker`、`model:claude-3-opus`、
+//! `context>=200000`、`gpu`；其他文本按名称匹配任一能力（不区分大小写）。
+
+use serde::{Deserialize, Serialize};
+
+/// Agent 能力清单
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityManifest {
+    /// 支持的编程语言
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// 可用工具
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// 可用模型
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// 最大上下文长度 (token)
+    #[serde(default)]
+    pub max_context: Option<u64>,
+    /// 是否有 GPU
+    #[serde(default)]
+    pub gpu: bool,
+}
+
+impl CapabilityManifest {
+    /// 创建空清单
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加编程语言
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.languages.push(language.into());
+        self
+    }
+
+    /// 添加工具
+    pub fn with_tool(mut self, tool: impl Into<String>) -> Self {
+        self.tools.push(tool.into());
+        self
+    }
+
+    /// 添加模型
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    /// 设置最大上下文长度
+    pub fn with_max_context(mut self, tokens: u64) -> Self {
+        self.max_context = Some(tokens);
+        self
+    }
+
+    /// 声明有 GPU
+    pub fn with_gpu(mut self) -> Self {
+        self.gpu = true;
+        self
+    }
+
+    /// 是否满足任务需求
+    pub fn satisfies(&self, requirements: &TaskRequirements) -> bool {
+        let has = |list: &[String], name: &str| list.iter().any(|item| item.eq_ignore_ascii_case(name));
+
+        requirements.languages.iter().all(|l| has(&self.languages, l))
+            && requirements.tools.iter().all(|t| has(&self.tools, t))
+            && requirements.models.iter().all(|m| has(&self.models, m))
+            && requirements
+                .min_context
+                .is_none_or(|min| self.max_context.is_some_and(|max| max >= min))
+            && (!requirements.gpu || self.gpu)
+            && requirements.other.iter().all(|term| {
+                has(&self.languages, term) || has(&self.tools, term) || has(&self.models, term)
+            })
+    }
+}
+
+/// 结构化任务需求
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskRequirements {
+    /// 需要的编程语言
+    pub languages: Vec<String>,
+    /// 需要的工具
+    pub tools: Vec<String>,
+    /// 需要的模型
+    pub models: Vec<String>,
+    /// 最小上下文长度
+    pub min_context: Option<u64>,
+    /// 是否需要 GPU
+    pub gpu: bool,
+    /// 未分类的需求，匹配任一能力名称
+    pub other: Vec<String>,
+}
+
+impl TaskRequirements {
+    /// 解析需求字符串列表
+    pub fn parse<S: AsRef<str>>(requirements: &[S]) -> Self {
+        let mut parsed = Self::default();
+        for raw in requirements {
+            let term = raw.as_ref().trim();
+            if term.is_empty() {
+                continue;
+            }
+            if term.eq_ignore_ascii_case("gpu") {
+                parsed.gpu = true;
+            } else if let Some(min) = term
+                .strip_prefix("context>=")
+                .and_then(|v| v.trim().parse().ok())
+            {
+                parsed.min_context = Some(min);
+            } else if let Some((kind, value)) = term.split_once(':') {
+                let value = value.trim().to_string();
+                match kind.trim().to_ascii_lowercase().as_str() {
+                    "language" | "lang" => parsed.languages.push(value),
+                    "tool" => parsed.tools.push(value),
+                    "model" => parsed.models.push(value),
+                    _ => parsed.other.push(term.to_string()),
+                }
+            } else {
+                parsed.other.push(term.to_string());
+            }
+        }
+        parsed
+    }
+
+    /// 是否没有任何需求
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirement_matching() {
+        let gpu_box = CapabilityManifest::new()
+            .with_language("Rust")
+            .with_language("python")
+            .with_tool("docker")
+            .with_model("claude-3-opus")
+            .with_max_context(200_000)
+            .with_gpu();
+        let laptop = CapabilityManifest::new().with_language("rust").with_max_context(32_000);
+
+        let requirements = TaskRequirements::parse(&["language:rust", "context>=100000", "gpu", "docker"]);
+        assert!(!requirements.is_empty());
+        assert!(gpu_box.satisfies(&requirements));
+        assert!(!laptop.satisfies(&requirements));
+
+        let simple = TaskRequirements::parse(&["lang:RUST"]);
+        assert!(laptop.satisfies(&simple));
+        assert!(laptop.satisfies(&TaskRequirements::parse::<&str>(&[])));
+        assert!(!laptop.satisfies(&TaskRequirements::parse(&["model:gpt-4o"])));
+    }
+}
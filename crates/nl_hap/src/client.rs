@@ -1,11 +1,14 @@
 //! HAP 客户端
 
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
-use crate::protocol::{HapMessage, HapMessageType};
+use crate::capability::CapabilityManifest;
+use crate::protocol::{HapMessage, HapProtocol, TaskProgress};
+use crate::security::AgentIdentity;
 
 /// HAP 客户端配置
 #[derive(Debug, Clone)]
@@ -35,6 +38,10 @@ pub struct HapClient {
     message_tx: mpsc::Sender<HapMessage>,
     /// 是否已连接
     connected: bool,
+    /// 签名身份 (设置后所有发出的消息都会签名)
+    identity: Option<Arc<AgentIdentity>>,
+    /// 本端能力清单 (握手时发送)
+    manifest: CapabilityManifest,
 }
 
 impl HapClient {
@@ -45,9 +52,24 @@ impl HapClient {
             config,
             message_tx,
             connected: false,
+            identity: None,
+            manifest: CapabilityManifest::default(),
         }
     }
 
+    /// 设置本端能力清单
+    pub fn with_manifest(mut self, manifest: CapabilityManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// 使用身份密钥签名发出的消息（Agent ID 取自身份）
+    pub fn with_identity(mut self, identity: Arc<AgentIdentity>) -> Self {
+        self.config.agent_id = identity.agent_id();
+        self.identity = Some(identity);
+        self
+    }
+
     /// 创建默认客户端
     pub fn default_client() -> Self {
         Self::new(HapClientConfig::default())
@@ -70,12 +92,22 @@ impl HapClient {
         if !self.connected {
             return Err(nl_core::NeuroLoomError::Protocol("Not connected".to_string()));
         }
+        let msg = match &self.identity {
+            Some(identity) => identity.sign(msg)?,
+            None => msg,
+        };
         self.message_tx
             .send(msg)
             .await
             .map_err(|e| nl_core::NeuroLoomError::Protocol(e.to_string()))
     }
 
+    /// 发送握手（携带能力清单）
+    pub async fn handshake(&self) -> nl_core::Result<()> {
+        let msg = HapProtocol::handshake(self.config.agent_id, &self.manifest);
+        self.send(msg).await
+    }
+
     /// 广播任务
     pub async fn broadcast_task(&self, task: &str, requirements: Vec<String>) -> nl_core::Result<()> {
         let msg = HapProtocol::task_broadcast(self.config.agent_id, task, requirements);
@@ -88,6 +120,28 @@ impl HapClient {
         self.send(msg).await
     }
 
+    /// 按任务广播中的建议报价区间竞标（区间缺失时按成本报价），返回报价
+    pub async fn bid_for(&self, broadcast: &HapMessage, cost: f64, eta_secs: u64) -> nl_core::Result<f64> {
+        let task_id = broadcast.payload["task_id"]
+            .as_str()
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| nl_core::NeuroLoomError::Protocol("Task broadcast without task_id".to_string()))?;
+        let price = broadcast.price_band().map(|band| band.quote(cost)).unwrap_or(cost);
+        self.submit_bid(task_id, price, eta_secs).await?;
+        Ok(price)
+    }
+
+    /// 发送心跳（执行任务期间至少每 `heartbeat_interval` 秒一次，否则委托方视为停滞）
+    pub async fn heartbeat(&self) -> nl_core::Result<()> {
+        self.send(HapProtocol::heartbeat(self.config.agent_id)).await
+    }
+
+    /// 上报任务进度
+    pub async fn report_progress(&self, progress: &TaskProgress) -> nl_core::Result<()> {
+        let msg = HapProtocol::task_progress(self.config.agent_id, progress);
+        self.send(msg).await
+    }
+
     /// 提交任务结果
     pub async fn submit_result(&self, task_id: Uuid, result: &str) -> nl_core::Result<()> {
         let msg = HapProtocol::task_result(self.config.agent_id, task_id, result);
@@ -99,6 +153,11 @@ impl HapClient {
         self.connected
     }
 
+    /// 获取能力清单
+    pub fn manifest(&self) -> &CapabilityManifest {
+        &self.manifest
+    }
+
     /// 获取配置
     pub fn config(&self) -> &HapClientConfig {
         &self.config
@@ -0,0 +1,388 @@
+//! 远程委托跟踪
+//!
+//! 记录已分配给远端 Agent 的任务：执行方上报的 `TaskProgress` 更新完成度与部分交付物，
+//! `Heartbeat` 刷新该 Agent 名下所有任务的活跃时间。超过 `stall_timeout` 没有任何消息的任务视为停滞，
+//! 转派给其他满足需求的 Agent（新分配携带已产出的部分交付物），转派次数用尽后放弃。
+//! 结果未通过本地审议时按 `dispute` 模块的流程驳回：先给执行方补救机会，仍不合格则收回任务重新竞拍。
+//! 每次状态变化都转换为事件供本地编排器与桌面端展示。
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::{Event, EventKind};
+
+use crate::dispute::{DisputeConfig, DisputeOutcome, Escalation, RejectionEvidence, TaskRejection};
+use crate::protocol::{ArtifactRef, HapMessage, HapMessageType, HapProtocol, TaskProgress};
+
+/// 跟踪配置
+#[derive(Debug, Clone)]
+pub struct DelegationConfig {
+    /// 多久没有消息视为停滞
+    pub stall_timeout: Duration,
+    /// 最多分配次数（含首次）
+    pub max_attempts: u32,
+}
+
+impl Default for DelegationConfig {
+    fn default() -> Self {
+        Self {
+            // 客户端默认 30 秒一次心跳，容忍丢失两次
+            stall_timeout: Duration::from_secs(90),
+            max_attempts: 3,
+        }
+    }
+}
+
+/// 委托状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DelegationStatus {
+    /// 执行中
+    Running,
+    /// 已完成
+    Completed { result: String },
+    /// 转派次数用尽
+    Abandoned,
+    /// 补救后仍被驳回，已收回重新竞拍
+    Rejected,
+}
+
+/// 已委托的任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delegation {
+    /// 任务 ID
+    pub task_id: Uuid,
+    /// 任务描述
+    pub task: String,
+    /// 任务需求
+    pub requirements: Vec<String>,
+    /// 当前执行方
+    pub agent_id: Uuid,
+    /// 分配次数
+    pub attempt: u32,
+    /// 曾经停滞的执行方（转派时排除）
+    pub stalled_agents: Vec<Uuid>,
+    /// 当前执行方的分配时间
+    pub assigned_at: DateTime<Utc>,
+    /// 最近一次收到执行方消息的时间
+    pub last_seen: DateTime<Utc>,
+    /// 最近的进度
+    pub progress: Option<TaskProgress>,
+    /// 累计的部分交付物（跨执行方保留）
+    pub artifacts: Vec<ArtifactRef>,
+    /// 当前执行方收到的驳回
+    #[serde(default)]
+    pub rejections: Vec<RejectionEvidence>,
+    /// 状态
+    pub status: DelegationStatus,
+}
+
+impl Delegation {
+    /// 给当前执行方的分配消息
+    fn assign_message(&self, sender: Uuid) -> HapMessage {
+        HapProtocol::task_assign(sender, self.task_id, &self.task, &self.requirements, &self.artifacts)
+            .to(self.agent_id)
+    }
+
+    fn is_running(&self) -> bool {
+        self.status == DelegationStatus::Running
+    }
+
+    fn event(&self, kind: EventKind, mut payload: serde_json::Value) -> Event {
+        payload["agent"] = serde_json::json!(self.agent_id);
+        payload["attempt"] = serde_json::json!(self.attempt);
+        Event::new(kind, self.task_id, payload)
+    }
+}
+
+/// 停滞处理结果
+#[derive(Debug, Clone)]
+pub struct StallOutcome {
+    /// 停滞事件
+    pub event: Event,
+    /// 转派给新执行方的分配消息（放弃时为 `None`）
+    pub reassignment: Option<HapMessage>,
+}
+
+/// 委托跟踪器
+pub struct DelegationTracker {
+    /// 本端 Agent ID（分配消息的发送者）
+    agent_id: Uuid,
+    config: DelegationConfig,
+    disputes: DisputeConfig,
+    delegations: HashMap<Uuid, Delegation>,
+}
+
+impl DelegationTracker {
+    /// 创建跟踪器
+    pub fn new(agent_id: Uuid, config: DelegationConfig) -> Self {
+        Self {
+            agent_id,
+            config,
+            disputes: DisputeConfig::default(),
+            delegations: HashMap::new(),
+        }
+    }
+
+    /// 设置争议配置
+    pub fn with_disputes(mut self, disputes: DisputeConfig) -> Self {
+        self.disputes = disputes;
+        self
+    }
+
+    /// 委托任务，返回待发送的分配消息与 `TaskDelegated` 事件
+    pub fn delegate(
+        &mut self,
+        task_id: Uuid,
+        task: impl Into<String>,
+        requirements: Vec<String>,
+        agent_id: Uuid,
+    ) -> (HapMessage, Event) {
+        let now = Utc::now();
+        let delegation = Delegation {
+            task_id,
+            task: task.into(),
+            requirements,
+            agent_id,
+            attempt: 1,
+            stalled_agents: Vec::new(),
+            assigned_at: now,
+            last_seen: now,
+            progress: None,
+            artifacts: Vec::new(),
+            rejections: Vec::new(),
+            status: DelegationStatus::Running,
+        };
+        let message = delegation.assign_message(self.agent_id);
+        let event = delegation.event(EventKind::TaskDelegated, serde_json::json!({ "task": delegation.task }));
+        self.delegations.insert(task_id, delegation);
+        (message, event)
+    }
+
+    /// 处理执行方发来的消息，返回需要发布的事件
+    ///
+    /// 只接受当前执行方的消息：已被转派的执行方迟到的进度与结果不再生效。
+    pub fn observe(&mut self, msg: &HapMessage) -> Option<Event> {
+        let now = Utc::now();
+        match msg.msg_type {
+            HapMessageType::Heartbeat => {
+                for delegation in self.delegations.values_mut() {
+                    if delegation.agent_id == msg.sender && delegation.is_running() {
+                        delegation.last_seen = now;
+                    }
+                }
+                None
+            }
+            HapMessageType::TaskProgress => {
+                let progress = msg.progress()?;
+                let delegation = self.current(progress.task_id, msg.sender)?;
+                delegation.last_seen = now;
+                for artifact in &progress.artifacts {
+                    if !delegation.artifacts.iter().any(|a| a.uri == artifact.uri) {
+                        delegation.artifacts.push(artifact.clone());
+                    }
+                }
+                let event = delegation.event(
+                    EventKind::DelegationProgress,
+                    serde_json::json!({
+                        "percent": progress.percent,
+                        "step": progress.step,
+                        "artifacts": delegation.artifacts,
+                    }),
+                );
+                delegation.progress = Some(progress);
+                Some(event)
+            }
+            HapMessageType::TaskResult => {
+                let task_id = msg.payload["task_id"].as_str()?.parse().ok()?;
+                let result = msg.payload["result"].as_str().unwrap_or_default().to_string();
+                let delegation = self.current(task_id, msg.sender)?;
+                delegation.last_seen = now;
+                delegation.status = DelegationStatus::Completed { result: result.clone() };
+                Some(delegation.event(
+                    EventKind::DelegationCompleted,
+                    serde_json::json!({
+                        "success": true,
+                        "result": result,
+                        "artifacts": delegation.artifacts,
+                    }),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// 由当前执行方负责且仍在执行的委托
+    fn current(&mut self, task_id: Uuid, sender: Uuid) -> Option<&mut Delegation> {
+        self.delegations
+            .get_mut(&task_id)
+            .filter(|d| d.agent_id == sender && d.is_running())
+    }
+
+    /// 已停滞的任务
+    pub fn stalled(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let timeout = chrono::Duration::from_std(self.config.stall_timeout).unwrap_or(chrono::Duration::MAX);
+        self.delegations
+            .values()
+            .filter(|d| d.is_running() && now - d.last_seen > timeout)
+            .map(|d| d.task_id)
+            .collect()
+    }
+
+    /// 转派停滞的任务
+    ///
+    /// `candidates` 为当前满足任务需求的 Agent，曾停滞的执行方被排除；没有可用 Agent 或分配次数用尽时放弃。
+    pub fn redelegate(&mut self, task_id: Uuid, candidates: &[Uuid]) -> Option<StallOutcome> {
+        let sender = self.agent_id;
+        let max_attempts = self.config.max_attempts;
+        let delegation = self.delegations.get_mut(&task_id).filter(|d| d.is_running())?;
+        let now = Utc::now();
+        let silent_secs = (now - delegation.last_seen).num_seconds();
+        let stalled = delegation.agent_id;
+        delegation.stalled_agents.push(stalled);
+
+        let next = candidates
+            .iter()
+            .find(|id| **id != sender && !delegation.stalled_agents.contains(id))
+            .filter(|_| delegation.attempt < max_attempts);
+        let Some(next) = next else {
+            delegation.status = DelegationStatus::Abandoned;
+            let event = delegation.event(
+                EventKind::DelegationStalled,
+                serde_json::json!({ "silent_secs": silent_secs, "redelegated_to": null }),
+            );
+            return Some(StallOutcome {
+                event,
+                reassignment: None,
+            });
+        };
+
+        let event = delegation.event(
+            EventKind::DelegationStalled,
+            serde_json::json!({ "silent_secs": silent_secs, "redelegated_to": next }),
+        );
+        delegation.agent_id = *next;
+        delegation.attempt += 1;
+        delegation.rejections.clear();
+        delegation.assigned_at = now;
+        delegation.last_seen = now;
+        Some(StallOutcome {
+            event,
+            reassignment: Some(delegation.assign_message(sender)),
+        })
+    }
+
+    /// 驳回已完成委托的结果
+    ///
+    /// 补救次数未用尽时委托回到执行中，等待同一执行方提交新结果；否则委托收回，
+    /// 返回的升级信息交给 `AgentMarket::reauction` 在本地重新竞拍。
+    pub fn reject(&mut self, task_id: Uuid, evidence: RejectionEvidence) -> Option<DisputeOutcome> {
+        let sender = self.agent_id;
+        let delegation = self
+            .delegations
+            .get_mut(&task_id)
+            .filter(|d| matches!(d.status, DelegationStatus::Completed { .. }))?;
+        delegation.rejections.push(evidence.clone());
+        let round = delegation.rejections.len() as u32;
+        let remediation = round <= self.disputes.remediation_rounds;
+
+        let rejection = TaskRejection {
+            task_id,
+            round,
+            remediation,
+            evidence,
+        };
+        let message = HapProtocol::task_rejection(sender, &rejection).to(delegation.agent_id);
+        let event = delegation.event(
+            EventKind::DelegationDisputed,
+            serde_json::json!({
+                "round": round,
+                "remediation": remediation,
+                "evidence": rejection.evidence,
+            }),
+        );
+
+        let escalation = if remediation {
+            delegation.status = DelegationStatus::Running;
+            delegation.last_seen = Utc::now();
+            None
+        } else {
+            delegation.status = DelegationStatus::Rejected;
+            Some(Escalation {
+                task_id,
+                task: delegation.task.clone(),
+                requirements: delegation.requirements.clone(),
+                failed_agent: delegation.agent_id,
+                penalty: self.disputes.reputation_penalty,
+                artifacts: delegation.artifacts.clone(),
+            })
+        };
+        Some(DisputeOutcome {
+            message,
+            event,
+            escalation,
+        })
+    }
+
+    /// 获取委托
+    pub fn get(&self, task_id: &Uuid) -> Option<&Delegation> {
+        self.delegations.get(task_id)
+    }
+
+    /// 仍在执行的委托
+    pub fn running(&self) -> Vec<&Delegation> {
+        self.delegations.values().filter(|d| d.is_running()).collect()
+    }
+
+    /// 清理已结束的委托
+    pub fn prune_finished(&mut self) -> usize {
+        let before = self.delegations.len();
+        self.delegations.retain(|_, d| d.is_running());
+        before - self.delegations.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_delegation_is_reassigned_with_partial_artifacts() {
+        let local = Uuid::new_v4();
+        let (slow, backup) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut tracker = DelegationTracker::new(
+            local,
+            DelegationConfig {
+                stall_timeout: Duration::from_secs(60),
+                max_attempts: 2,
+            },
+        );
+        let task = Uuid::new_v4();
+        let (assign, _) = tracker.delegate(task, "port parser", vec!["language:rust".to_string()], slow);
+        assert_eq!(assign.receiver, Some(slow));
+
+        let progress =
+            TaskProgress::new(task, 40.0, "lexer done").with_artifact(ArtifactRef::new("lexer", "file:///lexer.rs"));
+        let event = tracker.observe(&HapProtocol::task_progress(slow, &progress)).unwrap();
+        assert_eq!(event.kind, EventKind::DelegationProgress);
+        assert!(tracker.stalled(Utc::now()).is_empty());
+        assert_eq!(tracker.stalled(Utc::now() + chrono::Duration::seconds(61)), vec![task]);
+
+        let outcome = tracker.redelegate(task, &[slow, backup]).unwrap();
+        assert_eq!(outcome.event.payload["redelegated_to"], serde_json::json!(backup));
+        let reassignment = outcome.reassignment.unwrap();
+        assert_eq!(reassignment.receiver, Some(backup));
+        assert_eq!(reassignment.payload["partial_artifacts"][0]["uri"], "file:///lexer.rs");
+
+        // 被转派的执行方迟到的结果不生效
+        assert!(tracker.observe(&HapProtocol::task_result(slow, task, "late")).is_none());
+        // 分配次数用尽后放弃
+        let outcome = tracker.redelegate(task, &[slow, backup]).unwrap();
+        assert!(outcome.reassignment.is_none());
+        assert_eq!(tracker.get(&task).unwrap().status, DelegationStatus::Abandoned);
+    }
+}
@@ -0,0 +1,352 @@
+//! 联邦服务发现
+//!
+//! 局域网内的 HAP 服务器通过 mDNS（`_neuroloom-hap._tcp.local.`）广播自身：
+//! TXT 记录携带 Agent ID、是否启用 TLS 与精简的能力清单（`languages` / `tools` / `models`
+//! 为逗号分隔列表，`max_context`、`gpu`）。跨网段的服务器在静态对端列表中配置。
+//! 发现的对端汇总在 `PeerDirectory`，握手后拿到的完整能力清单覆盖 TXT 中的精简版本。
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+
+use crate::capability::CapabilityManifest;
+
+/// mDNS 服务类型
+pub const SERVICE_TYPE: &str = "_neuroloom-hap._tcp.local.";
+
+/// 单条 TXT 记录（`key=value`）的长度上限
+const TXT_MAX_LEN: usize = 255;
+
+/// 对端来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerSource {
+    /// mDNS 发现
+    Mdns,
+    /// 静态配置
+    Static,
+}
+
+/// 静态配置的对端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticPeer {
+    /// 地址（`host:port`）
+    pub addr: String,
+    /// 显示名称（默认取地址）
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 已知的 Agent ID
+    #[serde(default)]
+    pub agent_id: Option<Uuid>,
+    /// 是否使用 TLS
+    #[serde(default)]
+    pub tls: bool,
+}
+
+/// 联邦对端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Peer {
+    /// 名称（mDNS 实例名或静态配置名称）
+    pub name: String,
+    /// Agent ID（静态对端握手前可能未知）
+    pub agent_id: Option<Uuid>,
+    /// 地址（`host:port`）
+    pub addr: String,
+    /// 是否使用 TLS
+    pub tls: bool,
+    /// 来源
+    pub source: PeerSource,
+    /// 能力清单
+    pub manifest: Option<CapabilityManifest>,
+    /// 最近一次发现或握手的时间
+    pub last_seen: DateTime<Utc>,
+}
+
+impl Peer {
+    /// WebSocket 地址
+    pub fn url(&self) -> String {
+        let scheme = if self.tls { "wss" } else { "ws" };
+        format!("{}://{}/ws", scheme, self.addr)
+    }
+}
+
+/// 对端目录
+#[derive(Debug, Default)]
+pub struct PeerDirectory {
+    /// 按 mDNS 全名或 `static:<addr>` 索引
+    peers: RwLock<BTreeMap<String, Peer>>,
+}
+
+impl PeerDirectory {
+    /// 创建空目录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入静态对端
+    pub fn with_static(self, peers: &[StaticPeer]) -> Self {
+        {
+            let mut entries = self.peers.write().unwrap();
+            for peer in peers {
+                entries.insert(
+                    format!("static:{}", peer.addr),
+                    Peer {
+                        name: peer.name.clone().unwrap_or_else(|| peer.addr.clone()),
+                        agent_id: peer.agent_id,
+                        addr: peer.addr.clone(),
+                        tls: peer.tls,
+                        source: PeerSource::Static,
+                        manifest: None,
+                        last_seen: Utc::now(),
+                    },
+                );
+            }
+        }
+        self
+    }
+
+    /// 新增或更新对端（保留握手得到的能力清单）
+    pub fn upsert(&self, key: impl Into<String>, mut peer: Peer) {
+        let mut peers = self.peers.write().unwrap();
+        let key = key.into();
+        if let Some(previous) = peers.get(&key) {
+            if previous.agent_id == peer.agent_id && previous.source == peer.source {
+                peer.manifest = peer.manifest.or(previous.manifest.clone());
+            }
+        }
+        peers.insert(key, peer);
+    }
+
+    /// 移除对端（静态对端不会被移除）
+    pub fn remove(&self, key: &str) -> Option<Peer> {
+        let mut peers = self.peers.write().unwrap();
+        if peers.get(key)?.source == PeerSource::Static {
+            return None;
+        }
+        peers.remove(key)
+    }
+
+    /// 记录握手得到的能力清单
+    ///
+    /// 按 Agent ID 匹配；尚不知道 Agent ID 的静态对端按地址匹配并补全 ID。
+    pub fn record_handshake(&self, agent_id: Uuid, addr: Option<SocketAddr>, manifest: CapabilityManifest) -> bool {
+        let mut peers = self.peers.write().unwrap();
+        let peer = peers.values_mut().find(|p| {
+            p.agent_id == Some(agent_id)
+                || (p.agent_id.is_none() && addr.is_some_and(|a| p.addr == a.to_string()))
+        });
+        match peer {
+            Some(peer) => {
+                peer.agent_id = Some(agent_id);
+                peer.manifest = Some(manifest);
+                peer.last_seen = Utc::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 全部对端（按名称排序）
+    pub fn list(&self) -> Vec<Peer> {
+        let mut peers: Vec<Peer> = self.peers.read().unwrap().values().cloned().collect();
+        peers.sort_by(|a, b| a.name.cmp(&b.name));
+        peers
+    }
+
+    /// 按 Agent ID 查找
+    pub fn find(&self, agent_id: &Uuid) -> Option<Peer> {
+        self.peers
+            .read()
+            .unwrap()
+            .values()
+            .find(|p| p.agent_id.as_ref() == Some(agent_id))
+            .cloned()
+    }
+}
+
+/// 能力清单编码为 TXT 记录（超长的列表被截断）
+pub fn manifest_txt(manifest: &CapabilityManifest) -> Vec<(String, String)> {
+    let mut records = Vec::new();
+    for (key, items) in [
+        ("languages", &manifest.languages),
+        ("tools", &manifest.tools),
+        ("models", &manifest.models),
+    ] {
+        let mut value = String::new();
+        for item in items {
+            if key.len() + 1 + value.len() + 1 + item.len() > TXT_MAX_LEN {
+                break;
+            }
+            if !value.is_empty() {
+                value.push(',');
+            }
+            value.push_str(item);
+        }
+        if !value.is_empty() {
+            records.push((key.to_string(), value));
+        }
+    }
+    if let Some(max_context) = manifest.max_context {
+        records.push(("max_context".to_string(), max_context.to_string()));
+    }
+    if manifest.gpu {
+        records.push(("gpu".to_string(), "1".to_string()));
+    }
+    records
+}
+
+/// 由 TXT 记录还原能力清单
+pub fn manifest_from_txt(get: impl Fn(&str) -> Option<String>) -> CapabilityManifest {
+    let list = |key: &str| -> Vec<String> {
+        get(key)
+            .map(|v| v.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect())
+            .unwrap_or_default()
+    };
+    CapabilityManifest {
+        languages: list("languages"),
+        tools: list("tools"),
+        models: list("models"),
+        max_context: get("max_context").and_then(|v| v.parse().ok()),
+        gpu: get("gpu").as_deref() == Some("1"),
+    }
+}
+
+/// mDNS 广播与发现
+pub struct MdnsDiscovery {
+    daemon: ServiceDaemon,
+    directory: Arc<PeerDirectory>,
+    /// 本端 Agent ID（发现时跳过自身）
+    agent_id: Uuid,
+}
+
+impl MdnsDiscovery {
+    /// 启动 mDNS 守护线程
+    pub fn new(agent_id: Uuid, directory: Arc<PeerDirectory>) -> Result<Self> {
+        let daemon = ServiceDaemon::new().map_err(|e| NeuroLoomError::Protocol(format!("mDNS: {}", e)))?;
+        Ok(Self {
+            daemon,
+            directory,
+            agent_id,
+        })
+    }
+
+    /// 广播本端 HAP 服务器
+    pub fn advertise(&self, port: u16, tls: bool, manifest: &CapabilityManifest) -> Result<()> {
+        let id = self.agent_id.simple().to_string();
+        let instance = format!("neuroloom-{}", id);
+        let host = format!("nl-{}.local.", &id[..12]);
+        let mut properties = vec![
+            ("agent_id".to_string(), self.agent_id.to_string()),
+            ("tls".to_string(), if tls { "1" } else { "0" }.to_string()),
+            ("version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+        ];
+        properties.extend(manifest_txt(manifest));
+
+        let info = ServiceInfo::new(SERVICE_TYPE, &instance, &host, "", port, &properties[..])
+            .map_err(|e| NeuroLoomError::Protocol(format!("mDNS: {}", e)))?
+            .enable_addr_auto();
+        self.daemon
+            .register(info)
+            .map_err(|e| NeuroLoomError::Protocol(format!("mDNS: {}", e)))
+    }
+
+    /// 持续发现对端并更新目录（在后台线程中运行）
+    pub fn browse(&self) -> Result<()> {
+        let receiver = self
+            .daemon
+            .browse(SERVICE_TYPE)
+            .map_err(|e| NeuroLoomError::Protocol(format!("mDNS: {}", e)))?;
+        let directory = self.directory.clone();
+        let local = self.agent_id;
+        std::thread::Builder::new()
+            .name("hap-mdns-browse".to_string())
+            .spawn(move || {
+                while let Ok(event) = receiver.recv() {
+                    match event {
+                        ServiceEvent::ServiceResolved(info) => {
+                            let Some(peer) = peer_from_service(&info) else {
+                                continue;
+                            };
+                            if peer.agent_id == Some(local) {
+                                continue;
+                            }
+                            tracing::debug!("Discovered HAP peer {} at {}", peer.name, peer.addr);
+                            directory.upsert(info.get_fullname(), peer);
+                        }
+                        ServiceEvent::ServiceRemoved(_, fullname) => {
+                            if let Some(peer) = directory.remove(&fullname) {
+                                tracing::debug!("HAP peer {} went away", peer.name);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    /// 停止广播与发现
+    pub fn shutdown(&self) {
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// 由解析出的 mDNS 服务构造对端（优先使用 IPv4 地址）
+fn peer_from_service(info: &ServiceInfo) -> Option<Peer> {
+    let mut addresses: Vec<_> = info.get_addresses().iter().copied().collect();
+    addresses.sort_by_key(|ip| !ip.is_ipv4());
+    let addr = SocketAddr::new(*addresses.first()?, info.get_port());
+    let property = |key: &str| info.get_property_val_str(key).map(String::from);
+    let name = info
+        .get_fullname()
+        .strip_suffix(&format!(".{}", SERVICE_TYPE))
+        .unwrap_or(info.get_fullname())
+        .to_string();
+    Some(Peer {
+        name,
+        agent_id: property("agent_id").and_then(|id| id.parse().ok()),
+        addr: addr.to_string(),
+        tls: property("tls").as_deref() == Some("1"),
+        source: PeerSource::Mdns,
+        manifest: Some(manifest_from_txt(property)),
+        last_seen: Utc::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_round_trips_through_txt_and_handshake_fills_static_peer() {
+        let manifest = CapabilityManifest::new()
+            .with_language("rust")
+            .with_language("python")
+            .with_tool("docker")
+            .with_max_context(200_000)
+            .with_gpu();
+        let records = manifest_txt(&manifest);
+        let decoded = manifest_from_txt(|key| records.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone()));
+        assert_eq!(decoded, manifest);
+
+        let directory = PeerDirectory::new().with_static(&[StaticPeer {
+            addr: "10.0.0.5:8765".to_string(),
+            name: Some("build-box".to_string()),
+            agent_id: None,
+            tls: true,
+        }]);
+        let agent = Uuid::new_v4();
+        assert!(directory.record_handshake(agent, Some("10.0.0.5:8765".parse().unwrap()), manifest.clone()));
+        let peer = directory.find(&agent).unwrap();
+        assert_eq!(peer.url(), "wss://10.0.0.5:8765/ws");
+        assert_eq!(peer.manifest, Some(manifest));
+        assert!(directory.remove("static:10.0.0.5:8765").is_none());
+    }
+}
@@ -0,0 +1,140 @@
+//! 委托争议
+//!
+//! 远端交付的结果未通过本地法庭审议时，委托方向执行方发送带裁决证据的结构化驳回：
+//! 前 `remediation_rounds` 次驳回给执行方补救机会（委托回到执行中，等待新的结果），
+//! 之后的驳回升级为本地重新竞拍，失败执行方的市场评分按 `reputation_penalty` 扣减。
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::Event;
+
+use crate::protocol::{ArtifactRef, HapMessage};
+
+/// 争议配置
+#[derive(Debug, Clone)]
+pub struct DisputeConfig {
+    /// 允许执行方补救的次数
+    pub remediation_rounds: u32,
+    /// 升级时失败执行方评分的扣减比例 (0-1)
+    pub reputation_penalty: f64,
+}
+
+impl Default for DisputeConfig {
+    fn default() -> Self {
+        Self {
+            remediation_rounds: 1,
+            reputation_penalty: 0.5,
+        }
+    }
+}
+
+/// 驳回证据（本地法庭的裁决摘要）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectionEvidence {
+    /// 裁决 ID
+    pub verdict_id: Uuid,
+    /// 评分
+    pub score: f64,
+    /// 理由
+    pub reasoning: String,
+    /// 修改建议
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+    /// 未通过的测试
+    #[serde(default)]
+    pub failing_tests: Vec<String>,
+}
+
+/// 发给执行方的驳回通知
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskRejection {
+    /// 任务 ID
+    pub task_id: Uuid,
+    /// 第几次驳回（从 1 开始）
+    pub round: u32,
+    /// 是否允许补救（为 false 时任务已收回重新竞拍）
+    pub remediation: bool,
+    /// 证据
+    pub evidence: RejectionEvidence,
+}
+
+/// 升级为本地重新竞拍所需的信息
+#[derive(Debug, Clone)]
+pub struct Escalation {
+    /// 任务 ID
+    pub task_id: Uuid,
+    /// 任务描述
+    pub task: String,
+    /// 任务需求
+    pub requirements: Vec<String>,
+    /// 失败的执行方
+    pub failed_agent: Uuid,
+    /// 评分扣减比例
+    pub penalty: f64,
+    /// 已产出的部分交付物
+    pub artifacts: Vec<ArtifactRef>,
+}
+
+/// 驳回处理结果
+#[derive(Debug, Clone)]
+pub struct DisputeOutcome {
+    /// 发给执行方的驳回消息
+    pub message: HapMessage,
+    /// `DelegationDisputed` 事件
+    pub event: Event,
+    /// 补救次数用尽时的升级信息
+    pub escalation: Option<Escalation>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delegation::{DelegationConfig, DelegationStatus, DelegationTracker};
+    use crate::market::{AgentMarket, Bid};
+    use crate::protocol::HapProtocol;
+
+    fn evidence(reasoning: &str) -> RejectionEvidence {
+        RejectionEvidence {
+            verdict_id: Uuid::new_v4(),
+            score: 0.3,
+            reasoning: reasoning.to_string(),
+            suggestions: vec!["Handle empty input".to_string()],
+            failing_tests: vec!["parser::empty".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_rejected_result_gets_one_remediation_then_reauctions() {
+        let local = Uuid::new_v4();
+        let (remote, rival) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut tracker = DelegationTracker::new(local, DelegationConfig::default());
+        let task = Uuid::new_v4();
+        tracker.delegate(task, "port parser", vec![], remote);
+        // 执行中的委托不能驳回
+        assert!(tracker.reject(task, evidence("too early")).is_none());
+
+        tracker.observe(&HapProtocol::task_result(remote, task, "v1")).unwrap();
+        let outcome = tracker.reject(task, evidence("panics on empty input")).unwrap();
+        let rejection = outcome.message.rejection().unwrap();
+        assert_eq!(outcome.message.receiver, Some(remote));
+        assert!(rejection.remediation);
+        assert_eq!(rejection.evidence.failing_tests, vec!["parser::empty".to_string()]);
+        assert!(outcome.escalation.is_none());
+        assert_eq!(tracker.get(&task).unwrap().status, DelegationStatus::Running);
+
+        tracker.observe(&HapProtocol::task_result(remote, task, "v2")).unwrap();
+        let outcome = tracker.reject(task, evidence("still panics")).unwrap();
+        assert!(!outcome.message.rejection().unwrap().remediation);
+        assert_eq!(outcome.event.payload["round"], 2);
+        assert_eq!(tracker.get(&task).unwrap().status, DelegationStatus::Rejected);
+
+        let escalation = outcome.escalation.unwrap();
+        let mut market = AgentMarket::new();
+        market.reauction(&escalation);
+        assert_eq!(market.get_agent_score(&remote), 0.5);
+        assert!(market.submit_bid(Bid::new(remote, task, 6.0, 60)));
+        assert!(market.submit_bid(Bid::new(rival, task, 10.0, 60)));
+        assert_eq!(market.select_best_bid(&task).unwrap().agent_id, rival);
+    }
+}
@@ -1,13 +1,29 @@
 //! # nl_hap - NeuroLoom Hyper-Agent Protocol
 //!
-//! 星际联邦协议：HAP 跨网竞标、WebSocket 通信、Agent 互操作。
+//! 星际联邦协议：HAP 跨网竞标与市场定价、WebSocket 通信、委托进度跟踪与争议、mDNS 服务发现、Agent 互操作、MCP 桥接。
 
+pub mod capability;
 pub mod protocol;
 pub mod server;
 pub mod client;
+pub mod delegation;
+pub mod discovery;
+pub mod dispute;
 pub mod market;
+pub mod mcp;
+pub mod pricing;
+pub mod security;
 
-pub use protocol::{HapMessage, HapProtocol};
+pub use capability::{CapabilityManifest, TaskRequirements};
+pub use protocol::{ArtifactRef, HapMessage, HapProtocol, TaskProgress};
 pub use server::HapServer;
 pub use client::HapClient;
+pub use delegation::{DelegationConfig, DelegationTracker};
+pub use discovery::{MdnsDiscovery, Peer, PeerDirectory, StaticPeer};
+pub use dispute::{DisputeConfig, RejectionEvidence, TaskRejection};
 pub use market::AgentMarket;
+pub use mcp::{McpClient, McpServer, McpTool};
+pub use pricing::{BidAssessment, PriceBand, PricingEngine};
+pub use security::{
+    AgentIdentity, MessageVerifier, ReplayGuard, TlsConfig, TrustStore, TrustedAgent, IDENTITY_FILE, TRUST_FILE,
+};
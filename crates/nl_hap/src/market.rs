@@ -1,10 +1,18 @@
 //! Agent 市场
+//!
+//! 配置定价引擎后，任务分配时按任务类别记录成交价，广播任务时附带建议报价区间，
+//! 明显低于成本底价或历史成交价的竞标被拒绝。
 
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::capability::{CapabilityManifest, TaskRequirements};
+use crate::dispute::Escalation;
+use crate::pricing::{category_of, PriceBand, PricingEngine};
+use crate::protocol::{HapMessage, HapProtocol};
+
 /// 竞标
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bid {
@@ -52,6 +60,18 @@ pub struct Task {
     pub assigned_to: Option<Uuid>,
 }
 
+impl Task {
+    /// 结构化需求
+    pub fn parsed_requirements(&self) -> TaskRequirements {
+        TaskRequirements::parse(&self.requirements)
+    }
+
+    /// 定价类别
+    pub fn category(&self) -> String {
+        category_of(&self.parsed_requirements())
+    }
+}
+
 /// 任务状态
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TaskStatus {
@@ -63,6 +83,9 @@ pub enum TaskStatus {
     Cancelled,
 }
 
+/// Agent 评分下限（避免折算时除以零）
+pub const MIN_AGENT_SCORE: f64 = 0.05;
+
 /// Agent 市场
 pub struct AgentMarket {
     /// 开放任务
@@ -71,6 +94,10 @@ pub struct AgentMarket {
     bids: HashMap<Uuid, Vec<Bid>>,
     /// Agent 评分
     agent_scores: HashMap<Uuid, f64>,
+    /// Agent 能力清单
+    manifests: HashMap<Uuid, CapabilityManifest>,
+    /// 定价引擎
+    pricing: PricingEngine,
 }
 
 impl AgentMarket {
@@ -80,43 +107,121 @@ impl AgentMarket {
             open_tasks: HashMap::new(),
             bids: HashMap::new(),
             agent_scores: HashMap::new(),
+            manifests: HashMap::new(),
+            pricing: PricingEngine::default(),
         }
     }
 
+    /// 设置定价引擎
+    pub fn with_pricing(mut self, pricing: PricingEngine) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// 获取定价引擎
+    pub fn pricing(&self) -> &PricingEngine {
+        &self.pricing
+    }
+
     /// 发布任务
     pub fn publish_task(&mut self, task: Task) {
         self.open_tasks.insert(task.id, task);
     }
 
-    /// 提交竞标
-    pub fn submit_bid(&mut self, bid: Bid) {
+    /// 登记 Agent 能力清单（握手时交换）
+    pub fn register_agent(&mut self, agent_id: Uuid, manifest: CapabilityManifest) {
+        self.manifests.insert(agent_id, manifest);
+    }
+
+    /// Agent 能否承接任务（未登记清单的 Agent 只能承接无需求的任务）
+    pub fn is_eligible(&self, agent_id: &Uuid, task: &Task) -> bool {
+        let requirements = task.parsed_requirements();
+        match self.manifests.get(agent_id) {
+            Some(manifest) => manifest.satisfies(&requirements),
+            None => requirements.is_empty(),
+        }
+    }
+
+    /// 能承接任务的已登记 Agent
+    pub fn eligible_agents(&self, task_id: &Uuid) -> Vec<Uuid> {
+        let Some(task) = self.open_tasks.get(task_id) else {
+            return Vec::new();
+        };
+        self.manifests
+            .keys()
+            .filter(|agent_id| self.is_eligible(agent_id, task))
+            .copied()
+            .collect()
+    }
+
+    /// 提交竞标，能力不满足任务需求或价格异常的竞标被拒绝
+    pub fn submit_bid(&mut self, bid: Bid) -> bool {
+        if let Some(task) = self.open_tasks.get(&bid.task_id) {
+            if !self.is_eligible(&bid.agent_id, task) {
+                return false;
+            }
+            let assessment = self.pricing.assess(&task.category(), bid.price);
+            if assessment.is_anomalous() {
+                tracing::warn!(
+                    "Rejected bid {} from agent {} on task {} at {}: {:?}",
+                    bid.id,
+                    bid.agent_id,
+                    bid.task_id,
+                    bid.price,
+                    assessment
+                );
+                return false;
+            }
+        }
         self.bids
             .entry(bid.task_id)
             .or_default()
             .push(bid);
+        true
     }
 
-    /// 选择最佳竞标
+    /// 选择最佳竞标（价格与时效按 Agent 评分折算，评分越低越吃亏）
     pub fn select_best_bid(&self, task_id: &Uuid) -> Option<&Bid> {
         self.bids
             .get(task_id)?
             .iter()
             .min_by(|a, b| {
-                let score_a = a.price + (a.eta_secs as f64 * 0.01);
-                let score_b = b.price + (b.eta_secs as f64 * 0.01);
+                let score_a = (a.price + (a.eta_secs as f64 * 0.01)) / self.get_agent_score(&a.agent_id);
+                let score_b = (b.price + (b.eta_secs as f64 * 0.01)) / self.get_agent_score(&b.agent_id);
                 score_a.partial_cmp(&score_b).unwrap()
             })
     }
 
-    /// 分配任务
+    /// 分配任务（该 Agent 的竞标价记为成交价）
     pub fn assign_task(&mut self, task_id: &Uuid, agent_id: Uuid) -> Option<&Task> {
-        if let Some(task) = self.open_tasks.get_mut(task_id) {
-            task.status = TaskStatus::Assigned;
-            task.assigned_to = Some(agent_id);
-            Some(task)
-        } else {
-            None
+        let task = self.open_tasks.get_mut(task_id)?;
+        task.status = TaskStatus::Assigned;
+        task.assigned_to = Some(agent_id);
+        let winning = self
+            .bids
+            .get(task_id)
+            .and_then(|bids| bids.iter().find(|b| b.agent_id == agent_id));
+        if let Some(bid) = winning {
+            self.pricing.record_clearing(&task.category(), bid);
         }
+        Some(task)
+    }
+
+    /// 任务的建议报价区间
+    pub fn suggested_band(&self, task_id: &Uuid) -> Option<PriceBand> {
+        self.pricing.suggest(&self.open_tasks.get(task_id)?.category())
+    }
+
+    /// 任务广播消息（携带任务 ID、预算与建议报价区间）
+    pub fn broadcast(&self, sender: Uuid, task_id: &Uuid) -> Option<HapMessage> {
+        let task = self.open_tasks.get(task_id)?;
+        let mut msg = HapProtocol::task_broadcast(sender, &task.description, task.requirements.clone());
+        msg.payload["task_id"] = serde_json::json!(task.id);
+        msg.payload["budget"] = serde_json::json!(task.budget);
+        if let Some(band) = self.suggested_band(task_id) {
+            msg.payload["price_band"] = serde_json::json!(band);
+        }
+        Some(msg)
     }
 
     /// 获取开放任务
@@ -127,6 +232,14 @@ impl AgentMarket {
             .collect()
     }
 
+    /// 获取指定 Agent 可见的开放任务
+    pub fn open_tasks_for(&self, agent_id: &Uuid) -> Vec<&Task> {
+        self.open_tasks()
+            .into_iter()
+            .filter(|t| self.is_eligible(agent_id, t))
+            .collect()
+    }
+
     /// 更新 Agent 评分
     pub fn update_agent_score(&mut self, agent_id: Uuid, score: f64) {
         self.agent_scores.insert(agent_id, score);
@@ -136,6 +249,32 @@ impl AgentMarket {
     pub fn get_agent_score(&self, agent_id: &Uuid) -> f64 {
         self.agent_scores.get(agent_id).copied().unwrap_or(1.0)
     }
+
+    /// 按比例扣减 Agent 评分（不低于 `MIN_AGENT_SCORE`）
+    pub fn penalize_agent(&mut self, agent_id: Uuid, penalty: f64) -> f64 {
+        let score = (self.get_agent_score(&agent_id) * (1.0 - penalty.clamp(0.0, 1.0))).max(MIN_AGENT_SCORE);
+        self.update_agent_score(agent_id, score);
+        score
+    }
+
+    /// 争议升级后在本地重新竞拍：扣减失败执行方的评分，任务以开放状态重新发布，旧竞标作废
+    pub fn reauction(&mut self, escalation: &Escalation) -> &Task {
+        self.penalize_agent(escalation.failed_agent, escalation.penalty);
+        self.bids.remove(&escalation.task_id);
+        let budget = self.open_tasks.get(&escalation.task_id).and_then(|t| t.budget);
+        self.open_tasks.insert(
+            escalation.task_id,
+            Task {
+                id: escalation.task_id,
+                description: escalation.task.clone(),
+                requirements: escalation.requirements.clone(),
+                budget,
+                status: TaskStatus::Open,
+                assigned_to: None,
+            },
+        );
+        &self.open_tasks[&escalation.task_id]
+    }
 }
 
 impl Default for AgentMarket {
@@ -143,3 +282,34 @@ impl Default for AgentMarket {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_capable_agents_see_and_bid() {
+        let mut market = AgentMarket::new();
+        let task = Task {
+            id: Uuid::new_v4(),
+            description: "port parser to rust".to_string(),
+            requirements: vec!["language:rust".to_string(), "context>=100000".to_string()],
+            budget: None,
+            status: TaskStatus::Open,
+            assigned_to: None,
+        };
+        market.publish_task(task.clone());
+
+        let capable = Uuid::new_v4();
+        let limited = Uuid::new_v4();
+        market.register_agent(capable, CapabilityManifest::new().with_language("rust").with_max_context(200_000));
+        market.register_agent(limited, CapabilityManifest::new().with_language("rust").with_max_context(8_000));
+
+        assert_eq!(market.eligible_agents(&task.id), vec![capable]);
+        assert_eq!(market.open_tasks_for(&capable).len(), 1);
+        assert!(market.open_tasks_for(&limited).is_empty());
+        assert!(market.submit_bid(Bid::new(capable, task.id, 1.0, 60)));
+        assert!(!market.submit_bid(Bid::new(limited, task.id, 0.5, 60)));
+        assert_eq!(market.select_best_bid(&task.id).unwrap().agent_id, capable);
+    }
+}
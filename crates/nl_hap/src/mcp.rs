@@ -0,0 +1,436 @@
+//! MCP (Model Context Protocol) 桥接
+//!
+//! - `McpServer`：把 NeuroLoom 工具（沙箱操作、记忆检索、GraphRAG 查询等）以 MCP 暴露，
+//!   支持 stdio（逐行 JSON-RPC）与 HTTP（`POST /mcp`）两种传输
+//! - `McpClient`：连接外部 MCP 服务器，把其工具包装为 `McpTool` 供 Worker 调用
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+
+use nl_core::{NeuroLoomError, Result};
+
+/// 支持的 MCP 协议版本
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+fn protocol_error(message: impl Into<String>) -> NeuroLoomError {
+    NeuroLoomError::Protocol(message.into())
+}
+
+/// MCP 工具
+#[async_trait]
+pub trait McpTool: Send + Sync {
+    /// 工具名称
+    fn name(&self) -> &str;
+
+    /// 工具说明
+    fn description(&self) -> &str;
+
+    /// 参数 JSON Schema
+    fn input_schema(&self) -> Value;
+
+    /// 调用工具，返回文本结果
+    async fn call(&self, arguments: Value) -> Result<String>;
+}
+
+/// 工具描述（`tools/list` 返回项）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolInfo {
+    /// 工具名称
+    pub name: String,
+    /// 工具说明
+    #[serde(default)]
+    pub description: String,
+    /// 参数 JSON Schema
+    #[serde(default)]
+    pub input_schema: Value,
+}
+
+/// MCP 服务器
+pub struct McpServer {
+    name: String,
+    version: String,
+    tools: BTreeMap<String, Arc<dyn McpTool>>,
+}
+
+impl McpServer {
+    /// 创建 MCP 服务器
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            tools: BTreeMap::new(),
+        }
+    }
+
+    /// 注册工具
+    pub fn with_tool(mut self, tool: Arc<dyn McpTool>) -> Self {
+        self.tools.insert(tool.name().to_string(), tool);
+        self
+    }
+
+    /// 已注册的工具
+    pub fn tools(&self) -> Vec<McpToolInfo> {
+        self.tools
+            .values()
+            .map(|tool| McpToolInfo {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                input_schema: tool.input_schema(),
+            })
+            .collect()
+    }
+
+    /// 处理一条 JSON-RPC 消息；通知（无 `id`）不返回响应
+    pub async fn handle(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": self.name, "version": self.version },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": self.tools() })),
+            "tools/call" => self.call_tool(params).await,
+            other => Err((METHOD_NOT_FOUND, format!("method not found: {}", other))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    async fn call_tool(&self, params: Value) -> std::result::Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "missing tool name".to_string()))?;
+        let tool = self
+            .tools
+            .get(name)
+            .ok_or_else(|| (INVALID_PARAMS, format!("unknown tool: {}", name)))?;
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+        // 工具执行失败以 isError 结果返回，便于模型自行纠正
+        let (text, is_error) = match tool.call(arguments).await {
+            Ok(text) => (text, false),
+            Err(e) => (e.to_string(), true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        }))
+    }
+
+    /// 通过标准输入输出提供服务，直到输入结束
+    pub async fn serve_stdio(&self) -> Result<()> {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut stdout = tokio::io::stdout();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle(message).await,
+                Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+            };
+            if let Some(response) = response {
+                stdout.write_all(format!("{}\n", response).as_bytes()).await?;
+                stdout.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// HTTP 传输路由（`POST /mcp`）
+    pub fn build_router(self: Arc<Self>) -> Router {
+        Router::new().route("/mcp", post(handle_http)).with_state(self)
+    }
+}
+
+async fn handle_http(State(server): State<Arc<McpServer>>, Json(message): Json<Value>) -> impl IntoResponse {
+    match server.handle(message).await {
+        Some(response) => (StatusCode::OK, Json(response)).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// MCP 传输层
+#[async_trait]
+pub trait McpTransport: Send + Sync {
+    /// 发送请求并等待对应 `id` 的响应
+    async fn request(&self, message: Value) -> Result<Value>;
+
+    /// 发送通知
+    async fn notify(&self, message: Value) -> Result<()>;
+}
+
+/// 进程内传输（直连本地 `McpServer`）
+pub struct InProcessTransport(pub Arc<McpServer>);
+
+#[async_trait]
+impl McpTransport for InProcessTransport {
+    async fn request(&self, message: Value) -> Result<Value> {
+        self.0
+            .handle(message)
+            .await
+            .ok_or_else(|| protocol_error("request produced no response"))
+    }
+
+    async fn notify(&self, message: Value) -> Result<()> {
+        self.0.handle(message).await;
+        Ok(())
+    }
+}
+
+/// 子进程 stdio 传输
+pub struct StdioTransport {
+    _child: Child,
+    io: Mutex<(ChildStdin, Lines<BufReader<ChildStdout>>)>,
+}
+
+impl StdioTransport {
+    /// 启动 MCP 服务器进程
+    pub fn spawn(command: &str, args: &[String]) -> Result<Self> {
+        let mut child = tokio::process::Command::new(command)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| protocol_error("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| protocol_error("no stdout"))?;
+        Ok(Self {
+            _child: child,
+            io: Mutex::new((stdin, BufReader::new(stdout).lines())),
+        })
+    }
+}
+
+#[async_trait]
+impl McpTransport for StdioTransport {
+    async fn request(&self, message: Value) -> Result<Value> {
+        let id = message.get("id").cloned();
+        let mut io = self.io.lock().await;
+        io.0.write_all(format!("{}\n", message).as_bytes()).await?;
+        io.0.flush().await?;
+
+        // 跳过服务器主动发来的通知
+        while let Some(line) = io.1.next_line().await? {
+            let Ok(response) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if response.get("id") == id.as_ref() {
+                return Ok(response);
+            }
+        }
+        Err(protocol_error("MCP server closed the connection"))
+    }
+
+    async fn notify(&self, message: Value) -> Result<()> {
+        let mut io = self.io.lock().await;
+        io.0.write_all(format!("{}\n", message).as_bytes()).await?;
+        io.0.flush().await?;
+        Ok(())
+    }
+}
+
+/// MCP 客户端
+pub struct McpClient {
+    transport: Arc<dyn McpTransport>,
+    next_id: AtomicU64,
+}
+
+impl McpClient {
+    /// 基于传输层创建客户端
+    pub fn new(transport: Arc<dyn McpTransport>) -> Self {
+        Self {
+            transport,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// 启动外部 MCP 服务器进程并完成初始化
+    pub async fn spawn(command: &str, args: &[String]) -> Result<Self> {
+        let client = Self::new(Arc::new(StdioTransport::spawn(command, args)?));
+        client.initialize().await?;
+        Ok(client)
+    }
+
+    /// 初始化握手，返回服务器信息
+    pub async fn initialize(&self) -> Result<Value> {
+        let result = self
+            .call(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "neuroloom", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            )
+            .await?;
+        self.transport
+            .notify(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await?;
+        Ok(result)
+    }
+
+    /// 列出服务器工具
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>> {
+        let result = self.call("tools/list", json!({})).await?;
+        Ok(serde_json::from_value(result["tools"].clone())?)
+    }
+
+    /// 调用工具，返回拼接后的文本内容
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<String> {
+        let result = self
+            .call("tools/call", json!({ "name": name, "arguments": arguments }))
+            .await?;
+        let text = result["content"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        if result["isError"].as_bool().unwrap_or(false) {
+            return Err(protocol_error(format!("tool {} failed: {}", name, text)));
+        }
+        Ok(text)
+    }
+
+    /// 把服务器的全部工具包装为本地 `McpTool`
+    pub async fn remote_tools(self: &Arc<Self>) -> Result<Vec<Arc<dyn McpTool>>> {
+        Ok(self
+            .list_tools()
+            .await?
+            .into_iter()
+            .map(|info| {
+                Arc::new(RemoteTool {
+                    client: self.clone(),
+                    info,
+                }) as Arc<dyn McpTool>
+            })
+            .collect())
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let response = self
+            .transport
+            .request(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await?;
+        if let Some(error) = response.get("error") {
+            return Err(protocol_error(format!(
+                "MCP error {}: {}",
+                error["code"],
+                error["message"].as_str().unwrap_or_default()
+            )));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+}
+
+/// 外部 MCP 服务器提供的工具
+struct RemoteTool {
+    client: Arc<McpClient>,
+    info: McpToolInfo,
+}
+
+#[async_trait]
+impl McpTool for RemoteTool {
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn description(&self) -> &str {
+        &self.info.description
+    }
+
+    fn input_schema(&self) -> Value {
+        self.info.input_schema.clone()
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        self.client.call_tool(&self.info.name, arguments).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    #[async_trait]
+    impl McpTool for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echo the input text"
+        }
+
+        fn input_schema(&self) -> Value {
+            json!({ "type": "object", "properties": { "text": { "type": "string" } }, "required": ["text"] })
+        }
+
+        async fn call(&self, arguments: Value) -> Result<String> {
+            arguments["text"]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| protocol_error("missing text"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_consumes_server_tools() {
+        let server = Arc::new(McpServer::new("neuroloom", "0.1.0").with_tool(Arc::new(Echo)));
+        let client = Arc::new(McpClient::new(Arc::new(InProcessTransport(server.clone()))));
+
+        let info = client.initialize().await.unwrap();
+        assert_eq!(info["serverInfo"]["name"], "neuroloom");
+
+        let tools = client.remote_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name(), "echo");
+        assert_eq!(tools[0].call(json!({ "text": "hi" })).await.unwrap(), "hi");
+        assert!(tools[0].call(json!({})).await.is_err());
+
+        let unknown = server
+            .handle(json!({ "jsonrpc": "2.0", "id": 9, "method": "resources/list" }))
+            .await
+            .unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        assert!(server
+            .handle(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await
+            .is_none());
+    }
+}
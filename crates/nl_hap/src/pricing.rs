@@ -0,0 +1,270 @@
+//! 市场定价
+//!
+//! 按任务类别记录最近的成交价（中标价），给出建议报价区间（四分位数）随任务广播下发给竞标方，
+//! 并识别异常竞标：价格非正、低于该类别的成本底价，或在样本充足时远低于历史中位数
+//! （常见于先低价抢单再不交付的欺诈）。
+//!
+//! 任务类别由需求中的编程语言与 GPU 需求决定（如 `python+rust+gpu`），没有这些需求的任务归入 `general`。
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::capability::TaskRequirements;
+use crate::market::Bid;
+
+/// 没有语言与 GPU 需求的任务类别
+pub const GENERAL_CATEGORY: &str = "general";
+
+/// 任务类别
+pub fn category_of(requirements: &TaskRequirements) -> String {
+    let mut parts: Vec<String> = requirements.languages.iter().map(|l| l.to_ascii_lowercase()).collect();
+    parts.sort();
+    parts.dedup();
+    if requirements.gpu {
+        parts.push("gpu".to_string());
+    }
+    if parts.is_empty() {
+        GENERAL_CATEGORY.to_string()
+    } else {
+        parts.join("+")
+    }
+}
+
+/// 定价配置
+#[derive(Debug, Clone)]
+pub struct PricingConfig {
+    /// 每个类别保留的成交记录数
+    pub window: usize,
+    /// 给出建议区间与中位数比较所需的最少样本数
+    pub min_samples: usize,
+    /// 低于历史中位数的该比例视为异常
+    pub anomaly_ratio: f64,
+    /// 各类别的成本底价
+    pub cost_floors: HashMap<String, f64>,
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            window: 200,
+            min_samples: 5,
+            anomaly_ratio: 0.3,
+            cost_floors: HashMap::new(),
+        }
+    }
+}
+
+/// 成交记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClearingRecord {
+    pub task_id: Uuid,
+    pub agent_id: Uuid,
+    pub price: f64,
+    pub eta_secs: u64,
+    pub cleared_at: DateTime<Utc>,
+}
+
+/// 成交价统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceStats {
+    pub samples: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub p25: f64,
+    pub p75: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// 建议报价区间
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceBand {
+    pub low: f64,
+    pub target: f64,
+    pub high: f64,
+}
+
+impl PriceBand {
+    /// 按自身成本报价：取建议价，但不低于成本
+    pub fn quote(&self, cost: f64) -> f64 {
+        self.target.max(cost)
+    }
+}
+
+/// 竞标评估
+#[derive(Debug, Clone, PartialEq)]
+pub enum BidAssessment {
+    /// 正常
+    Normal,
+    /// 价格不是正数
+    Invalid,
+    /// 低于成本底价
+    BelowFloor { floor: f64 },
+    /// 远低于历史中位数
+    Outlier { median: f64 },
+}
+
+impl BidAssessment {
+    /// 是否异常
+    pub fn is_anomalous(&self) -> bool {
+        *self != BidAssessment::Normal
+    }
+}
+
+/// 定价引擎
+#[derive(Debug, Default)]
+pub struct PricingEngine {
+    config: PricingConfig,
+    history: HashMap<String, VecDeque<ClearingRecord>>,
+}
+
+impl PricingEngine {
+    /// 创建定价引擎
+    pub fn new(config: PricingConfig) -> Self {
+        Self {
+            config,
+            history: HashMap::new(),
+        }
+    }
+
+    /// 设置类别的成本底价
+    pub fn with_cost_floor(mut self, category: impl Into<String>, floor: f64) -> Self {
+        self.config.cost_floors.insert(category.into(), floor);
+        self
+    }
+
+    /// 记录成交
+    pub fn record_clearing(&mut self, category: &str, bid: &Bid) {
+        let records = self.history.entry(category.to_string()).or_default();
+        records.push_back(ClearingRecord {
+            task_id: bid.task_id,
+            agent_id: bid.agent_id,
+            price: bid.price,
+            eta_secs: bid.eta_secs,
+            cleared_at: Utc::now(),
+        });
+        while records.len() > self.config.window {
+            records.pop_front();
+        }
+    }
+
+    /// 类别的成交价统计
+    pub fn stats(&self, category: &str) -> Option<PriceStats> {
+        let mut prices: Vec<f64> = self.history.get(category)?.iter().map(|r| r.price).collect();
+        if prices.is_empty() {
+            return None;
+        }
+        prices.sort_by(f64::total_cmp);
+        Some(PriceStats {
+            samples: prices.len(),
+            mean: prices.iter().sum::<f64>() / prices.len() as f64,
+            median: percentile(&prices, 0.5),
+            p25: percentile(&prices, 0.25),
+            p75: percentile(&prices, 0.75),
+            min: prices[0],
+            max: prices[prices.len() - 1],
+        })
+    }
+
+    /// 建议报价区间（样本不足时为 `None`；区间下限不低于成本底价）
+    pub fn suggest(&self, category: &str) -> Option<PriceBand> {
+        let stats = self.stats(category).filter(|s| s.samples >= self.config.min_samples)?;
+        let floor = self.cost_floor(category).unwrap_or(0.0);
+        Some(PriceBand {
+            low: stats.p25.max(floor),
+            target: stats.median.max(floor),
+            high: stats.p75.max(floor),
+        })
+    }
+
+    /// 评估竞标价格
+    pub fn assess(&self, category: &str, price: f64) -> BidAssessment {
+        if !price.is_finite() || price <= 0.0 {
+            return BidAssessment::Invalid;
+        }
+        if let Some(floor) = self.cost_floor(category).filter(|floor| price < *floor) {
+            return BidAssessment::BelowFloor { floor };
+        }
+        match self.stats(category) {
+            Some(stats)
+                if stats.samples >= self.config.min_samples && price < stats.median * self.config.anomaly_ratio =>
+            {
+                BidAssessment::Outlier { median: stats.median }
+            }
+            _ => BidAssessment::Normal,
+        }
+    }
+
+    /// 类别的成本底价
+    pub fn cost_floor(&self, category: &str) -> Option<f64> {
+        self.config.cost_floors.get(category).copied()
+    }
+
+    /// 类别的成交记录
+    pub fn history(&self, category: &str) -> Vec<&ClearingRecord> {
+        self.history.get(category).map(|r| r.iter().collect()).unwrap_or_default()
+    }
+}
+
+/// 线性插值分位数（`sorted` 非空且已排序）
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::CapabilityManifest;
+    use crate::market::{AgentMarket, Task, TaskStatus};
+
+    #[test]
+    fn test_clearing_prices_produce_band_and_flag_dumping_bids() {
+        let mut market = AgentMarket::new().with_pricing(PricingEngine::default().with_cost_floor("rust", 0.5));
+        let agent = Uuid::new_v4();
+        market.register_agent(agent, CapabilityManifest::new().with_language("rust"));
+        for price in [8.0, 10.0, 9.0, 12.0, 11.0] {
+            let task = Task {
+                id: Uuid::new_v4(),
+                description: "port parser to rust".to_string(),
+                requirements: vec!["language:rust".to_string()],
+                budget: None,
+                status: TaskStatus::Open,
+                assigned_to: None,
+            };
+            market.publish_task(task.clone());
+            assert!(market.submit_bid(Bid::new(agent, task.id, price, 60)));
+            market.assign_task(&task.id, agent);
+        }
+
+        let stats = market.pricing().stats("rust").unwrap();
+        assert_eq!((stats.samples, stats.median, stats.min, stats.max), (5, 10.0, 8.0, 12.0));
+        let band = market.pricing().suggest("rust").unwrap();
+        assert_eq!(band, PriceBand { low: 9.0, target: 10.0, high: 11.0 });
+        assert_eq!(band.quote(14.0), 14.0);
+
+        let engine = market.pricing();
+        assert_eq!(engine.assess("rust", 9.5), BidAssessment::Normal);
+        assert_eq!(engine.assess("rust", 0.2), BidAssessment::BelowFloor { floor: 0.5 });
+        assert_eq!(engine.assess("rust", 1.0), BidAssessment::Outlier { median: 10.0 });
+        assert_eq!(engine.assess("rust", -1.0), BidAssessment::Invalid);
+        assert!(engine.suggest(GENERAL_CATEGORY).is_none());
+
+        let task = Task {
+            id: Uuid::new_v4(),
+            description: "optimise hot loop".to_string(),
+            requirements: vec!["language:Rust".to_string()],
+            budget: Some(20.0),
+            status: TaskStatus::Open,
+            assigned_to: None,
+        };
+        market.publish_task(task.clone());
+        assert!(!market.submit_bid(Bid::new(agent, task.id, 1.0, 60)));
+        let broadcast = market.broadcast(Uuid::new_v4(), &task.id).unwrap();
+        assert_eq!(broadcast.price_band(), Some(band));
+    }
+}
@@ -3,7 +3,13 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::capability::{CapabilityManifest, TaskRequirements};
+use crate::dispute::TaskRejection;
+use crate::pricing::PriceBand;
+
 /// HAP 消息
+///
+/// 消息 ID 同时作为防重放的一次性随机数；`signature` 覆盖除自身外的全部字段。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HapMessage {
     /// 消息 ID
@@ -18,6 +24,9 @@ pub struct HapMessage {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     /// 载荷
     pub payload: serde_json::Value,
+    /// 发送者的 Ed25519 签名 (base64)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 impl HapMessage {
@@ -30,6 +39,7 @@ impl HapMessage {
             receiver: None,
             timestamp: chrono::Utc::now(),
             payload,
+            signature: None,
         }
     }
 
@@ -39,14 +49,59 @@ impl HapMessage {
         self
     }
 
+    /// 待签名的字节（不含签名字段）
+    pub fn signing_bytes(&self) -> nl_core::Result<Vec<u8>> {
+        let unsigned = HapMessage {
+            signature: None,
+            ..self.clone()
+        };
+        Ok(serde_json::to_vec(&unsigned)?)
+    }
+
+    /// 握手消息中携带的能力清单
+    pub fn manifest(&self) -> Option<CapabilityManifest> {
+        serde_json::from_value(self.payload.get("manifest")?.clone()).ok()
+    }
+
+    /// 任务广播中的结构化需求
+    pub fn requirements(&self) -> TaskRequirements {
+        let terms: Vec<String> = self
+            .payload
+            .get("requirements")
+            .and_then(|r| serde_json::from_value(r.clone()).ok())
+            .unwrap_or_default();
+        TaskRequirements::parse(&terms)
+    }
+
+    /// 任务广播中的建议报价区间（市场有足够的历史成交时附带）
+    pub fn price_band(&self) -> Option<PriceBand> {
+        serde_json::from_value(self.payload.get("price_band")?.clone()).ok()
+    }
+
+    /// 任务进度消息中的进度
+    pub fn progress(&self) -> Option<TaskProgress> {
+        if self.msg_type != HapMessageType::TaskProgress {
+            return None;
+        }
+        serde_json::from_value(self.payload.clone()).ok()
+    }
+
+    /// 驳回消息中的驳回通知
+    pub fn rejection(&self) -> Option<TaskRejection> {
+        if self.msg_type != HapMessageType::TaskRejected {
+            return None;
+        }
+        serde_json::from_value(self.payload.clone()).ok()
+    }
+
     /// 序列化为 JSON
     pub fn to_json(&self) -> nl_core::Result<String> {
-        serde_json::to_string(self).map_err(|e| nl_core::NeuroLoomError::Serialization(e))
+        serde_json::to_string(self).map_err(nl_core::NeuroLoomError::Serialization)
     }
 
     /// 从 JSON 反序列化
     pub fn from_json(json: &str) -> nl_core::Result<Self> {
-        serde_json::from_str(json).map_err(|e| nl_core::NeuroLoomError::Serialization(e))
+        serde_json::from_str(json).map_err(nl_core::NeuroLoomError::Serialization)
     }
 }
 
@@ -69,27 +124,100 @@ pub enum HapMessageType {
     BidAck,
     /// 任务分配
     TaskAssign,
+    /// 任务进度（执行方定期上报，兼作该任务的心跳）
+    TaskProgress,
     /// 任务结果
     TaskResult,
+    /// 任务结果被委托方驳回（附裁决证据）
+    TaskRejected,
     /// 错误
     Error,
     /// 自定义
     Custom(String),
 }
 
+/// 部分交付物引用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactRef {
+    /// 名称
+    pub name: String,
+    /// 获取地址（URL 或执行方本地路径）
+    pub uri: String,
+    /// MIME 类型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+}
+
+impl ArtifactRef {
+    /// 创建交付物引用
+    pub fn new(name: impl Into<String>, uri: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            uri: uri.into(),
+            media_type: None,
+        }
+    }
+}
+
+/// 任务进度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskProgress {
+    /// 任务 ID
+    pub task_id: Uuid,
+    /// 完成百分比 (0-100)
+    pub percent: f32,
+    /// 当前步骤
+    pub step: String,
+    /// 已产出的部分交付物
+    #[serde(default)]
+    pub artifacts: Vec<ArtifactRef>,
+}
+
+impl TaskProgress {
+    /// 创建进度（百分比截断到 0-100）
+    pub fn new(task_id: Uuid, percent: f32, step: impl Into<String>) -> Self {
+        Self {
+            task_id,
+            percent: percent.clamp(0.0, 100.0),
+            step: step.into(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// 附带部分交付物
+    pub fn with_artifact(mut self, artifact: ArtifactRef) -> Self {
+        self.artifacts.push(artifact);
+        self
+    }
+}
+
 /// HAP 协议处理器
 pub struct HapProtocol;
 
 impl HapProtocol {
     /// 创建握手消息
-    pub fn handshake(agent_id: Uuid, capabilities: Vec<String>) -> HapMessage {
+    pub fn handshake(agent_id: Uuid, manifest: &CapabilityManifest) -> HapMessage {
         HapMessage::new(
             HapMessageType::Handshake,
             agent_id,
-            serde_json::json!({ "capabilities": capabilities }),
+            serde_json::json!({ "manifest": manifest }),
         )
     }
 
+    /// 创建握手响应（回传本端能力清单）
+    pub fn handshake_ack(agent_id: Uuid, manifest: &CapabilityManifest) -> HapMessage {
+        HapMessage::new(
+            HapMessageType::HandshakeAck,
+            agent_id,
+            serde_json::json!({ "manifest": manifest }),
+        )
+    }
+
+    /// 创建心跳
+    pub fn heartbeat(agent_id: Uuid) -> HapMessage {
+        HapMessage::new(HapMessageType::Heartbeat, agent_id, serde_json::json!({}))
+    }
+
     /// 创建任务广播
     pub fn task_broadcast(agent_id: Uuid, task: &str, requirements: Vec<String>) -> HapMessage {
         HapMessage::new(
@@ -115,6 +243,36 @@ impl HapProtocol {
         )
     }
 
+    /// 创建任务分配（转派时携带上一执行方已产出的部分交付物）
+    pub fn task_assign(
+        agent_id: Uuid,
+        task_id: Uuid,
+        task: &str,
+        requirements: &[String],
+        partial: &[ArtifactRef],
+    ) -> HapMessage {
+        HapMessage::new(
+            HapMessageType::TaskAssign,
+            agent_id,
+            serde_json::json!({
+                "task_id": task_id,
+                "task": task,
+                "requirements": requirements,
+                "partial_artifacts": partial,
+            }),
+        )
+    }
+
+    /// 创建任务进度
+    pub fn task_progress(agent_id: Uuid, progress: &TaskProgress) -> HapMessage {
+        HapMessage::new(HapMessageType::TaskProgress, agent_id, serde_json::json!(progress))
+    }
+
+    /// 创建驳回通知
+    pub fn task_rejection(agent_id: Uuid, rejection: &TaskRejection) -> HapMessage {
+        HapMessage::new(HapMessageType::TaskRejected, agent_id, serde_json::json!(rejection))
+    }
+
     /// 创建任务结果
     pub fn task_result(agent_id: Uuid, task_id: Uuid, result: &str) -> HapMessage {
         HapMessage::new(
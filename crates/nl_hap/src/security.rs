@@ -0,0 +1,552 @@
+//! HAP 安全层
+//!
+//! - 消息签名：每个 Agent 持有 Ed25519 身份密钥，对消息（不含签名字段）签名
+//! - 信任库：已知 Agent 的公钥，只接受来自信任库中 Agent 的签名消息；
+//!   文件在运行中被修改（`nl trust add/remove`）后，下一条消息校验前重新加载，吊销立即生效
+//! - 防重放：消息 ID 作为一次性随机数，超出时间窗口或重复出现的消息被拒绝
+//! - TLS / mTLS：WebSocket 服务器可选启用 TLS，配置客户端 CA 时要求客户端证书
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+
+use crate::protocol::HapMessage;
+
+fn protocol_error(message: impl Into<String>) -> NeuroLoomError {
+    NeuroLoomError::Protocol(message.into())
+}
+
+/// 身份密钥文件名（位于守护进程数据目录）
+pub const IDENTITY_FILE: &str = "hap_identity.json";
+
+/// 信任库文件名（位于守护进程数据目录）
+pub const TRUST_FILE: &str = "hap_trust.json";
+
+/// 身份密钥文件内容
+#[derive(Serialize, Deserialize)]
+struct IdentityFile {
+    agent_id: Uuid,
+    /// PKCS#8 编码的私钥 (base64)
+    private_key: String,
+}
+
+/// Agent 身份（Ed25519 密钥对）
+pub struct AgentIdentity {
+    agent_id: Uuid,
+    key_pair: Ed25519KeyPair,
+    pkcs8: Vec<u8>,
+}
+
+impl AgentIdentity {
+    /// 生成新身份
+    pub fn generate(agent_id: Uuid) -> Result<Self> {
+        let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| protocol_error("failed to generate identity key"))?;
+        Self::from_pkcs8(agent_id, document.as_ref())
+    }
+
+    /// 从 PKCS#8 私钥恢复身份
+    pub fn from_pkcs8(agent_id: Uuid, pkcs8: &[u8]) -> Result<Self> {
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|e| protocol_error(e.to_string()))?;
+        Ok(Self {
+            agent_id,
+            key_pair,
+            pkcs8: pkcs8.to_vec(),
+        })
+    }
+
+    /// 读取身份文件，不存在时生成并保存（Unix 上仅属主可读写）
+    pub fn load_or_generate(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            restrict_permissions(path)?;
+            let file: IdentityFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            let pkcs8 = STANDARD
+                .decode(file.private_key)
+                .map_err(|e| protocol_error(e.to_string()))?;
+            return Self::from_pkcs8(file.agent_id, &pkcs8);
+        }
+
+        let identity = Self::generate(Uuid::new_v4())?;
+        let file = IdentityFile {
+            agent_id: identity.agent_id,
+            private_key: STANDARD.encode(&identity.pkcs8),
+        };
+        write_private(path, serde_json::to_string_pretty(&file)?.as_bytes())?;
+        Ok(identity)
+    }
+
+    /// Agent ID
+    pub fn agent_id(&self) -> Uuid {
+        self.agent_id
+    }
+
+    /// 公钥 (base64)
+    pub fn public_key(&self) -> String {
+        STANDARD.encode(self.key_pair.public_key().as_ref())
+    }
+
+    /// 以本身份签名消息（发送者设为本 Agent）
+    pub fn sign(&self, mut msg: HapMessage) -> Result<HapMessage> {
+        msg.sender = self.agent_id;
+        msg.signature = None;
+        let signature = self.key_pair.sign(&msg.signing_bytes()?);
+        msg.signature = Some(STANDARD.encode(signature.as_ref()));
+        Ok(msg)
+    }
+}
+
+/// 创建仅属主可读写的文件（私钥从创建起就不会以默认权限落盘）
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// 收紧已有身份文件的权限（早期版本以默认权限写入）
+fn restrict_permissions(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            tracing::warn!("Identity key {} was readable by others, restricting to 0600", path.display());
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// 信任库中的 Agent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedAgent {
+    /// Agent ID
+    pub agent_id: Uuid,
+    /// 公钥 (base64)
+    pub public_key: String,
+    /// 备注
+    pub label: Option<String>,
+    /// 加入时间
+    pub added_at: DateTime<Utc>,
+}
+
+/// 已知 Agent 公钥信任库
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    path: Option<PathBuf>,
+    agents: HashMap<Uuid, TrustedAgent>,
+    /// 加载或保存时的文件内容（修改时间的精度不足以区分连续的两次写入，按内容比较）
+    contents: Option<Vec<u8>>,
+}
+
+impl TrustStore {
+    /// 创建空的内存信任库
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从文件加载信任库（文件不存在时为空，`save` 时创建）
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let contents = match std::fs::read(&path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let agents = match &contents {
+            Some(contents) => {
+                let list: Vec<TrustedAgent> = serde_json::from_slice(contents)?;
+                list.into_iter().map(|a| (a.agent_id, a)).collect()
+            }
+            None => HashMap::new(),
+        };
+        Ok(Self {
+            path: Some(path),
+            agents,
+            contents,
+        })
+    }
+
+    /// 保存到加载时的文件（先写临时文件再改名，运行中的守护进程不会读到写了一半的内容）
+    pub fn save(&mut self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut list: Vec<&TrustedAgent> = self.agents.values().collect();
+        list.sort_by_key(|a| a.added_at);
+        let contents = serde_json::to_vec_pretty(&list)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, &contents)?;
+        std::fs::rename(&tmp, path)?;
+        self.contents = Some(contents);
+        Ok(())
+    }
+
+    /// 文件自加载或保存后是否被其他进程修改（或删除）
+    pub fn is_stale(&self) -> bool {
+        self.path.as_deref().is_some_and(|path| std::fs::read(path).ok() != self.contents)
+    }
+
+    /// 文件被修改时重新加载，返回是否重新加载；加载失败时保留原内容并返回错误
+    pub fn reload_if_changed(&mut self) -> Result<bool> {
+        match &self.path {
+            Some(path) if self.is_stale() => {
+                *self = Self::load(path.clone())?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// 信任 Agent（已存在时替换公钥）
+    pub fn add(&mut self, agent_id: Uuid, public_key: &str, label: Option<String>) -> Result<()> {
+        let decoded = STANDARD
+            .decode(public_key)
+            .map_err(|e| protocol_error(format!("invalid public key: {}", e)))?;
+        if decoded.len() != 32 {
+            return Err(protocol_error("invalid public key: expected 32 bytes"));
+        }
+        self.agents.insert(
+            agent_id,
+            TrustedAgent {
+                agent_id,
+                public_key: public_key.to_string(),
+                label,
+                added_at: Utc::now(),
+            },
+        );
+        Ok(())
+    }
+
+    /// 移除 Agent
+    pub fn remove(&mut self, agent_id: &Uuid) -> Option<TrustedAgent> {
+        self.agents.remove(agent_id)
+    }
+
+    /// 获取 Agent
+    pub fn get(&self, agent_id: &Uuid) -> Option<&TrustedAgent> {
+        self.agents.get(agent_id)
+    }
+
+    /// 列出全部 Agent
+    pub fn list(&self) -> Vec<&TrustedAgent> {
+        let mut list: Vec<&TrustedAgent> = self.agents.values().collect();
+        list.sort_by_key(|a| a.added_at);
+        list
+    }
+
+    /// 验证消息签名
+    pub fn verify_signature(&self, msg: &HapMessage) -> Result<()> {
+        let agent = self
+            .get(&msg.sender)
+            .ok_or_else(|| protocol_error(format!("untrusted agent {}", msg.sender)))?;
+        let signature = msg
+            .signature
+            .as_deref()
+            .ok_or_else(|| protocol_error("message is not signed"))?;
+        let signature = STANDARD
+            .decode(signature)
+            .map_err(|e| protocol_error(e.to_string()))?;
+        let public_key = STANDARD
+            .decode(&agent.public_key)
+            .map_err(|e| protocol_error(e.to_string()))?;
+
+        UnparsedPublicKey::new(&signature::ED25519, public_key)
+            .verify(&msg.signing_bytes()?, &signature)
+            .map_err(|_| protocol_error(format!("invalid signature from agent {}", msg.sender)))
+    }
+}
+
+/// 防重放检查
+pub struct ReplayGuard {
+    /// 允许的时钟偏差
+    window: Duration,
+    /// 窗口内见过的消息 ID
+    seen: HashMap<Uuid, DateTime<Utc>>,
+}
+
+impl ReplayGuard {
+    /// 创建防重放检查，`window` 之外的消息视为过期
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// 检查消息是否为重放
+    pub fn check(&mut self, msg: &HapMessage) -> Result<()> {
+        let now = Utc::now();
+        if (now - msg.timestamp).abs() > self.window {
+            return Err(protocol_error(format!("message {} is outside the replay window", msg.id)));
+        }
+
+        let window = self.window;
+        self.seen.retain(|_, at| now - *at <= window);
+        if self.seen.insert(msg.id, msg.timestamp).is_some() {
+            return Err(protocol_error(format!("replayed message {}", msg.id)));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ReplayGuard {
+    fn default() -> Self {
+        Self::new(Duration::minutes(5))
+    }
+}
+
+/// 入站消息校验器（签名 + 防重放）
+pub struct MessageVerifier {
+    trust: Arc<std::sync::RwLock<TrustStore>>,
+    replay: Mutex<ReplayGuard>,
+}
+
+impl MessageVerifier {
+    /// 创建校验器
+    pub fn new(trust: TrustStore) -> Self {
+        Self {
+            trust: Arc::new(std::sync::RwLock::new(trust)),
+            replay: Mutex::new(ReplayGuard::default()),
+        }
+    }
+
+    /// 设置防重放窗口
+    pub fn with_replay_window(mut self, window: Duration) -> Self {
+        self.replay = Mutex::new(ReplayGuard::new(window));
+        self
+    }
+
+    /// 共享的信任库（运行时增删 Agent）
+    pub fn trust_store(&self) -> Arc<std::sync::RwLock<TrustStore>> {
+        self.trust.clone()
+    }
+
+    /// 校验消息：签名有效且未被重放（信任库文件被修改时先重新加载）
+    pub fn verify(&self, msg: &HapMessage) -> Result<()> {
+        fn poisoned<T>(_: T) -> NeuroLoomError {
+            protocol_error("trust store lock poisoned")
+        }
+        if self.trust.read().map_err(poisoned)?.is_stale() {
+            self.trust.write().map_err(poisoned)?.reload_if_changed()?;
+        }
+        self.trust.read().map_err(poisoned)?.verify_signature(msg)?;
+        self.replay
+            .lock()
+            .map_err(|_| protocol_error("replay guard lock poisoned"))?
+            .check(msg)
+    }
+}
+
+/// TLS 配置
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// 服务器证书链 (PEM)
+    pub cert_path: PathBuf,
+    /// 服务器私钥 (PEM)
+    pub key_path: PathBuf,
+    /// 客户端 CA 证书 (PEM)，设置后启用 mTLS
+    pub client_ca_path: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// 创建 TLS 配置
+    pub fn new(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            client_ca_path: None,
+        }
+    }
+
+    /// 要求客户端出示由该 CA 签发的证书
+    pub fn with_client_ca(mut self, ca_path: impl Into<PathBuf>) -> Self {
+        self.client_ca_path = Some(ca_path.into());
+        self
+    }
+
+    /// 构建 rustls 服务器配置
+    pub fn server_config(&self) -> Result<rustls::ServerConfig> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| protocol_error(e.to_string()))?;
+
+        let builder = match &self.client_ca_path {
+            Some(ca_path) => {
+                let mut roots = rustls::RootCertStore::empty();
+                for cert in read_certs(ca_path)? {
+                    roots.add(cert).map_err(|e| protocol_error(e.to_string()))?;
+                }
+                let verifier =
+                    rustls::server::WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .map_err(|e| protocol_error(e.to_string()))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let key = rustls_pemfile::private_key(&mut std::io::BufReader::new(std::fs::File::open(&self.key_path)?))?
+            .ok_or_else(|| protocol_error(format!("no private key in {}", self.key_path.display())))?;
+        let mut config = builder
+            .with_single_cert(read_certs(&self.cert_path)?, key)
+            .map_err(|e| protocol_error(e.to_string()))?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(config)
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<rustls::pki_types::CertificateDer<'static>>> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader).collect::<std::io::Result<Vec<_>>>()?;
+    if certs.is_empty() {
+        return Err(protocol_error(format!("no certificates in {}", path.display())));
+    }
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::HapProtocol;
+
+    #[test]
+    fn test_identity_file_is_private_and_stable() {
+        let dir = std::env::temp_dir().join(format!("nl_identity_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(IDENTITY_FILE);
+
+        let identity = AgentIdentity::load_or_generate(&path).unwrap();
+        let reloaded = AgentIdentity::load_or_generate(&path).unwrap();
+        assert_eq!(
+            (identity.agent_id(), identity.public_key()),
+            (reloaded.agent_id(), reloaded.public_key())
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&path), 0o600);
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            AgentIdentity::load_or_generate(&path).unwrap();
+            assert_eq!(mode(&path), 0o600);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_signed_message_verification_and_replay() {
+        let identity = AgentIdentity::generate(Uuid::new_v4()).unwrap();
+        let mut trust = TrustStore::new();
+        trust
+            .add(identity.agent_id(), &identity.public_key(), Some("peer".to_string()))
+            .unwrap();
+        let verifier = MessageVerifier::new(trust);
+
+        let msg = identity
+            .sign(HapProtocol::bid(Uuid::nil(), Uuid::new_v4(), 1.5, 60))
+            .unwrap();
+        assert_eq!(msg.sender, identity.agent_id());
+        verifier.verify(&msg).unwrap();
+        // 同一消息再次出现视为重放
+        assert!(verifier.verify(&msg).is_err());
+
+        // 篡改载荷后签名失效
+        let mut tampered = identity
+            .sign(HapProtocol::bid(Uuid::nil(), Uuid::new_v4(), 1.5, 60))
+            .unwrap();
+        tampered.payload["price"] = serde_json::json!(0.1);
+        assert!(verifier.verify(&tampered).is_err());
+
+        // 不在信任库中的 Agent
+        let stranger = AgentIdentity::generate(Uuid::new_v4()).unwrap();
+        let msg = stranger.sign(HapProtocol::handshake(Uuid::nil(), &Default::default())).unwrap();
+        assert!(verifier.verify(&msg).is_err());
+
+        // 过期消息
+        let mut stale = HapProtocol::handshake(Uuid::nil(), &Default::default());
+        stale.timestamp = Utc::now() - Duration::hours(1);
+        let stale = identity.sign(stale).unwrap();
+        assert!(verifier.verify(&stale).is_err());
+    }
+
+    #[test]
+    fn test_identity_and_trust_store_persistence() {
+        let dir = std::env::temp_dir().join(format!("nl_hap_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let identity = AgentIdentity::load_or_generate(dir.join("identity.json")).unwrap();
+        let reloaded = AgentIdentity::load_or_generate(dir.join("identity.json")).unwrap();
+        assert_eq!(identity.agent_id(), reloaded.agent_id());
+        assert_eq!(identity.public_key(), reloaded.public_key());
+
+        let mut trust = TrustStore::load(dir.join("trust.json")).unwrap();
+        trust.add(identity.agent_id(), &identity.public_key(), None).unwrap();
+        assert!(trust.add(Uuid::new_v4(), "not-a-key", None).is_err());
+        trust.save().unwrap();
+
+        let trust = TrustStore::load(dir.join("trust.json")).unwrap();
+        assert_eq!(trust.list().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_verifier_reloads_the_trust_file_after_it_changes() {
+        let dir = std::env::temp_dir().join(format!("nl_hap_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(TRUST_FILE);
+        let identity = || AgentIdentity::generate(Uuid::new_v4()).unwrap();
+        let (peer, newcomer) = (identity(), identity());
+        let mut trust = TrustStore::load(&path).unwrap();
+        trust.add(peer.agent_id(), &peer.public_key(), None).unwrap();
+        trust.save().unwrap();
+        let verifier = MessageVerifier::new(TrustStore::load(&path).unwrap());
+        let message = |identity: &AgentIdentity| {
+            identity.sign(HapProtocol::handshake(identity.agent_id(), &Default::default())).unwrap()
+        };
+        assert!(verifier.verify(&message(&peer)).is_ok());
+        assert!(verifier.verify(&message(&newcomer)).is_err());
+
+        // 另一个进程（`nl trust`）吊销一个、信任另一个，守护进程不重启即生效
+        let mut cli = TrustStore::load(&path).unwrap();
+        cli.remove(&peer.agent_id());
+        cli.add(newcomer.agent_id(), &newcomer.public_key(), None).unwrap();
+        cli.save().unwrap();
+        assert!(verifier.verify(&message(&peer)).is_err());
+        assert!(verifier.verify(&message(&newcomer)).is_ok());
+
+        // 损坏的文件不会让已吊销的 Agent 恢复信任，修复后重新加载
+        std::fs::write(&path, "[{").unwrap();
+        assert!(verifier.verify(&message(&peer)).is_err());
+        assert!(verifier.verify(&message(&newcomer)).is_err());
+        cli.save().unwrap();
+        assert!(verifier.verify(&message(&newcomer)).is_ok());
+
+        // 删除文件即清空信任库
+        std::fs::remove_file(&path).unwrap();
+        assert!(verifier.verify(&message(&newcomer)).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,17 +1,27 @@
 //! HAP 服务器
+//!
+//! 配置 `TlsConfig` 时以 TLS（或 mTLS）提供 WebSocket；配置 `MessageVerifier` 时
+//! 丢弃未签名、签名无效、来自未知 Agent 或被重放的消息。
+//!
+//! 握手时记录对方的能力清单；任务广播只送达能力满足任务需求的 Agent。
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 use axum::{
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
-    response::Response,
     routing::get,
     Router,
 };
-use tokio::sync::broadcast;
+use futures::stream::SplitSink;
+use futures::{SinkExt, StreamExt};
+use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
 
-use crate::protocol::{HapMessage, HapProtocol};
+use crate::capability::{CapabilityManifest, TaskRequirements};
+use crate::protocol::{HapMessage, HapMessageType, HapProtocol};
+use crate::security::{AgentIdentity, MessageVerifier, TlsConfig};
 
 /// HAP 服务器配置
 #[derive(Debug, Clone)]
@@ -20,6 +30,10 @@ pub struct HapServerConfig {
     pub addr: SocketAddr,
     /// Agent ID
     pub agent_id: Uuid,
+    /// TLS 配置 (None 表示明文)
+    pub tls: Option<TlsConfig>,
+    /// 本端能力清单 (握手时回传)
+    pub manifest: CapabilityManifest,
 }
 
 impl Default for HapServerConfig {
@@ -27,6 +41,8 @@ impl Default for HapServerConfig {
         Self {
             addr: "0.0.0.0:8765".parse().unwrap(),
             agent_id: Uuid::new_v4(),
+            tls: None,
+            manifest: CapabilityManifest::default(),
         }
     }
 }
@@ -36,13 +52,57 @@ pub struct HapServer {
     config: HapServerConfig,
     /// 消息广播通道
     message_tx: broadcast::Sender<HapMessage>,
+    /// 会话共享状态
+    session: Session,
+}
+
+/// 各连接共享的状态
+#[derive(Clone)]
+struct Session {
+    agent_id: Uuid,
+    manifest: CapabilityManifest,
+    message_tx: broadcast::Sender<HapMessage>,
+    /// 发往已连接 Agent 的消息
+    outbound_tx: broadcast::Sender<HapMessage>,
+    /// 已握手 Agent 的能力清单
+    peers: Arc<RwLock<HashMap<Uuid, CapabilityManifest>>>,
+    verifier: Option<Arc<MessageVerifier>>,
+    identity: Option<Arc<AgentIdentity>>,
 }
 
 impl HapServer {
     /// 创建新服务器
     pub fn new(config: HapServerConfig) -> Self {
         let (message_tx, _) = broadcast::channel(1024);
-        Self { config, message_tx }
+        let (outbound_tx, _) = broadcast::channel(1024);
+        let session = Session {
+            agent_id: config.agent_id,
+            manifest: config.manifest.clone(),
+            message_tx: message_tx.clone(),
+            outbound_tx,
+            peers: Arc::new(RwLock::new(HashMap::new())),
+            verifier: None,
+            identity: None,
+        };
+        Self {
+            config,
+            message_tx,
+            session,
+        }
+    }
+
+    /// 只接受通过签名与防重放校验的消息
+    pub fn with_verifier(mut self, verifier: Arc<MessageVerifier>) -> Self {
+        self.session.verifier = Some(verifier);
+        self
+    }
+
+    /// 使用身份密钥签名发出的消息
+    pub fn with_identity(mut self, identity: Arc<AgentIdentity>) -> Self {
+        self.config.agent_id = identity.agent_id();
+        self.session.agent_id = identity.agent_id();
+        self.session.identity = Some(identity);
+        self
     }
 
     /// 创建默认服务器
@@ -52,16 +112,25 @@ impl HapServer {
 
     /// 构建 Axum 路由
     pub fn build_router(&self) -> Router {
-        let message_tx = self.message_tx.clone();
+        let session = self.session.clone();
         Router::new()
             .route("/ws", get(|ws: WebSocketUpgrade| async move {
-                ws.on_upgrade(|socket| handle_socket(socket, message_tx))
+                ws.on_upgrade(|socket| handle_socket(socket, session))
             }))
     }
 
     /// 启动服务器
     pub async fn start(&self) -> nl_core::Result<()> {
         let app = self.build_router();
+
+        if let Some(tls) = &self.config.tls {
+            let rustls = axum_server::tls_rustls::RustlsConfig::from_config(Arc::new(tls.server_config()?));
+            axum_server::bind_rustls(self.config.addr, rustls)
+                .serve(app.into_make_service())
+                .await?;
+            return Ok(());
+        }
+
         let listener = tokio::net::TcpListener::bind(&self.config.addr)
             .await
             .map_err(|e| nl_core::NeuroLoomError::Protocol(e.to_string()))?;
@@ -73,6 +142,31 @@ impl HapServer {
         Ok(())
     }
 
+    /// 向已连接的 Agent 发送消息
+    ///
+    /// 任务广播只送达能力满足任务需求的 Agent；指定接收者的消息只送达该 Agent。
+    /// 返回当前连接数。
+    pub fn publish(&self, msg: HapMessage) -> usize {
+        self.session.outbound_tx.send(msg).unwrap_or(0)
+    }
+
+    /// 能力满足需求的已握手 Agent
+    pub async fn eligible_agents(&self, requirements: &TaskRequirements) -> Vec<Uuid> {
+        self.session
+            .peers
+            .read()
+            .await
+            .iter()
+            .filter(|(_, manifest)| manifest.satisfies(requirements))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// 获取 Agent 的能力清单
+    pub async fn peer_manifest(&self, agent_id: &Uuid) -> Option<CapabilityManifest> {
+        self.session.peers.read().await.get(agent_id).cloned()
+    }
+
     /// 获取消息接收器
     pub fn subscribe(&self) -> broadcast::Receiver<HapMessage> {
         self.message_tx.subscribe()
@@ -84,19 +178,99 @@ impl HapServer {
     }
 }
 
+impl Session {
+    /// 入站消息是否应投递给指定 Agent
+    fn should_deliver(&self, msg: &HapMessage, peer: Option<(Uuid, &CapabilityManifest)>) -> bool {
+        let Some((peer_id, manifest)) = peer else {
+            // 尚未握手的连接不接收任何消息
+            return false;
+        };
+        if msg.receiver.is_some_and(|receiver| receiver != peer_id) {
+            return false;
+        }
+        msg.msg_type != HapMessageType::TaskBroadcast || manifest.satisfies(&msg.requirements())
+    }
+
+    async fn send(&self, sender: &mut SplitSink<WebSocket, Message>, msg: HapMessage) -> nl_core::Result<()> {
+        let msg = match &self.identity {
+            Some(identity) => identity.sign(msg)?,
+            None => msg,
+        };
+        sender
+            .send(Message::Text(msg.to_json()?))
+            .await
+            .map_err(|e| nl_core::NeuroLoomError::Protocol(e.to_string()))
+    }
+}
+
 /// 处理 WebSocket 连接
-async fn handle_socket(socket: WebSocket, message_tx: broadcast::Sender<HapMessage>) {
+async fn handle_socket(socket: WebSocket, session: Session) {
     let (mut sender, mut receiver) = socket.split();
+    let mut outbound = session.outbound_tx.subscribe();
+    let mut peer: Option<(Uuid, CapabilityManifest)> = None;
 
-    while let Some(msg) = receiver.recv().await {
-        if let Ok(msg) = msg {
-            if let Message::Text(text) = msg {
-                if let Ok(hap_msg) = HapMessage::from_json(&text) {
-                    let _ = message_tx.send(hap_msg);
+    loop {
+        tokio::select! {
+            msg = receiver.next() => {
+                let Some(Ok(msg)) = msg else { break };
+                let Message::Text(text) = msg else { continue };
+                let Ok(hap_msg) = HapMessage::from_json(&text) else { continue };
+                if let Some(verifier) = &session.verifier {
+                    if let Err(e) = verifier.verify(&hap_msg) {
+                        tracing::warn!("Rejected HAP message {}: {}", hap_msg.id, e);
+                        continue;
+                    }
+                }
+
+                if hap_msg.msg_type == HapMessageType::Handshake {
+                    let manifest = hap_msg.manifest().unwrap_or_default();
+                    session.peers.write().await.insert(hap_msg.sender, manifest.clone());
+                    peer = Some((hap_msg.sender, manifest));
+                    let ack = HapProtocol::handshake_ack(session.agent_id, &session.manifest).to(hap_msg.sender);
+                    if session.send(&mut sender, ack).await.is_err() {
+                        break;
+                    }
+                }
+                let _ = session.message_tx.send(hap_msg);
+            }
+            msg = outbound.recv() => {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if session.should_deliver(&msg, peer.as_ref().map(|(id, m)| (*id, m)))
+                    && session.send(&mut sender, msg).await.is_err()
+                {
+                    break;
                 }
             }
-        } else {
-            break;
         }
     }
+
+    if let Some((peer_id, _)) = peer {
+        session.peers.write().await.remove(&peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_broadcast_only_reaches_capable_agents() {
+        let server = HapServer::default_server();
+        let gpu_agent = Uuid::new_v4();
+        let gpu = CapabilityManifest::new().with_language("rust").with_gpu();
+        let cpu = CapabilityManifest::new().with_language("rust");
+
+        let task = HapProtocol::task_broadcast(Uuid::new_v4(), "train model", vec!["gpu".to_string()]);
+        assert!(server.session.should_deliver(&task, Some((gpu_agent, &gpu))));
+        assert!(!server.session.should_deliver(&task, Some((Uuid::new_v4(), &cpu))));
+        assert!(!server.session.should_deliver(&task, None));
+
+        let direct = HapProtocol::task_result(Uuid::new_v4(), Uuid::new_v4(), "done").to(gpu_agent);
+        assert!(server.session.should_deliver(&direct, Some((gpu_agent, &cpu))));
+        assert!(!server.session.should_deliver(&direct, Some((Uuid::new_v4(), &cpu))));
+    }
 }
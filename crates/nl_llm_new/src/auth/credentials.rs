@@ -0,0 +1,185 @@
+//! 本地凭证登记
+//!
+//! `nl auth login|status|logout` 管理的凭证文件与各 Provider 配置的默认 Token 路径一致，
+//! 登录完成后以默认配置构建的 Provider 直接可用：
+//! - antigravity / gemini-cli：OAuth Token（含 refresh_token，过期后自动刷新）
+//! - iflow：Cookie 换得的 API Key
+//! - vertex：Service Account JSON 原文（access token 按需签发，不落盘）
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::providers::antigravity::AntigravityOAuth;
+use super::providers::gemini_cli::GeminiCliOAuth;
+use super::providers::iflow::IFlowAuth;
+use super::{AuthError, TokenStatus, TokenStorage, TokenStorageManager};
+use crate::provider::antigravity::AntigravityConfig;
+use crate::provider::gemini_cli::GeminiCliConfig;
+use crate::provider::iflow::config::IFlowConfig;
+
+/// 支持 CLI 登录的 Provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialProvider {
+    Antigravity,
+    GeminiCli,
+    IFlow,
+    Vertex,
+}
+
+impl CredentialProvider {
+    /// 全部 Provider（`nl auth status` 的列出顺序）
+    pub const ALL: [CredentialProvider; 4] = [
+        CredentialProvider::Antigravity,
+        CredentialProvider::GeminiCli,
+        CredentialProvider::IFlow,
+        CredentialProvider::Vertex,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialProvider::Antigravity => "antigravity",
+            CredentialProvider::GeminiCli => "gemini-cli",
+            CredentialProvider::IFlow => "iflow",
+            CredentialProvider::Vertex => "vertex",
+        }
+    }
+
+    /// 凭证文件路径（与 Provider 默认配置一致）
+    pub fn credential_path(&self) -> PathBuf {
+        match self {
+            CredentialProvider::Antigravity => AntigravityConfig::default().token_path,
+            CredentialProvider::GeminiCli => GeminiCliConfig::default().token_path,
+            CredentialProvider::IFlow => IFlowConfig::with_default_path(String::new(), String::new()).token_path,
+            CredentialProvider::Vertex => nl_llm_home().join("vertex_sa.json"),
+        }
+    }
+}
+
+impl fmt::Display for CredentialProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for CredentialProvider {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CredentialProvider::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s.to_ascii_lowercase())
+            .ok_or_else(|| {
+                AuthError::InvalidCredentials(format!(
+                    "unknown provider '{}', expected antigravity, gemini-cli, iflow or vertex",
+                    s
+                ))
+            })
+    }
+}
+
+/// 单个 Provider 的凭证状态
+#[derive(Debug, Clone)]
+pub struct CredentialStatus {
+    pub provider: CredentialProvider,
+    pub path: PathBuf,
+    /// 未登录时为空
+    pub status: Option<TokenStatus>,
+    /// 过期时间（Service Account 无过期时间）
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 账号（邮箱、SA client_email 或 project_id）
+    pub account: Option<String>,
+    /// 过期后能否无交互续期
+    pub refreshable: bool,
+}
+
+/// 读取凭证状态
+pub fn credential_status(provider: CredentialProvider) -> CredentialStatus {
+    let path = provider.credential_path();
+    let (status, token) = match provider {
+        CredentialProvider::Antigravity => AntigravityOAuth::from_file(&path)
+            .ok()
+            .map(|auth| (auth.token_status(), auth.token))
+            .unwrap_or((TokenStatus::Expired, None)),
+        CredentialProvider::GeminiCli => GeminiCliOAuth::from_file(&path)
+            .ok()
+            .map(|auth| (auth.token_status(), auth.token))
+            .unwrap_or((TokenStatus::Expired, None)),
+        CredentialProvider::IFlow => IFlowAuth::from_file(&path)
+            .ok()
+            .map(|auth| (auth.token_status(), auth.token))
+            .unwrap_or((TokenStatus::Expired, None)),
+        CredentialProvider::Vertex => return service_account_status(path),
+    };
+
+    CredentialStatus {
+        provider,
+        status: token.as_ref().map(|_| status),
+        expires_at: token.as_ref().and_then(|t| t.expires_at),
+        account: token.as_ref().and_then(account_of),
+        // iFlow 凭 Cookie 重新换取 API Key
+        refreshable: token.as_ref().is_some_and(|t| t.refresh_token.is_some() || t.extra.contains_key("cookie")),
+        path,
+    }
+}
+
+/// 删除凭证文件，返回是否存在过
+pub fn logout(provider: CredentialProvider) -> Result<bool, AuthError> {
+    let path = provider.credential_path();
+    let existed = TokenStorageManager::exists(&path);
+    TokenStorageManager::delete(&path)?;
+    Ok(existed)
+}
+
+fn account_of(token: &TokenStorage) -> Option<String> {
+    token
+        .email
+        .clone()
+        .or_else(|| token.extra.get("project_id")?.as_str().map(|s| s.to_string()))
+}
+
+fn service_account_status(path: PathBuf) -> CredentialStatus {
+    #[derive(Deserialize)]
+    struct ServiceAccount {
+        client_email: String,
+    }
+
+    let account = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<ServiceAccount>(&content).ok())
+        .map(|sa| sa.client_email);
+    CredentialStatus {
+        provider: CredentialProvider::Vertex,
+        status: account.as_ref().map(|_| TokenStatus::Valid),
+        expires_at: None,
+        refreshable: account.is_some(),
+        account,
+        path,
+    }
+}
+
+/// `~/.nl_llm`（与 OAuth Provider 的默认 Token 目录相同）
+fn nl_llm_home() -> PathBuf {
+    let home = std::env::var("USERPROFILE")
+        .or_else(|_| std::env::var("HOME"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".nl_llm")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_names_round_trip() {
+        for provider in CredentialProvider::ALL {
+            assert_eq!(provider.as_str().parse::<CredentialProvider>().unwrap(), provider);
+        }
+        assert_eq!("Gemini-CLI".parse::<CredentialProvider>().unwrap(), CredentialProvider::GeminiCli);
+        assert!("claude".parse::<CredentialProvider>().is_err());
+        assert!(CredentialProvider::Vertex.credential_path().ends_with(".nl_llm/vertex_sa.json"));
+    }
+}
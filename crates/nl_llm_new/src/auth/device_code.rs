@@ -0,0 +1,241 @@
+//! OAuth 2.0 设备码流程（RFC 8628）
+//!
+//! 无需在本机监听回调端口：CLI 打印验证网址与用户码，用户在任意设备的浏览器中完成授权，
+//! CLI 按服务端要求的间隔轮询 token 端点直到授权完成、被拒绝或用户码过期。
+//! OAuth 客户端类型不支持设备码时 `request` 返回 `None`，调用方回退到本地端口回调流程。
+//!
+//! `LoginMethod::Auto` 在检测不到图形界面（SSH 会话，或 Linux 下没有 X11/Wayland 显示）时选择设备码流程，
+//! 桌面环境仍使用浏览器 + 本地回调。
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::auth::AuthError;
+
+/// Google 设备码端点
+pub const GOOGLE_DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+
+/// 设备码授权类型
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// 服务端未给出轮询间隔时的默认值（秒）
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+
+/// 登录方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoginMethod {
+    /// 有图形界面时用本地回调，否则用设备码
+    #[default]
+    Auto,
+    /// 设备码流程
+    DeviceCode,
+    /// 浏览器 + 本地端口回调
+    Loopback,
+}
+
+impl LoginMethod {
+    /// 把 `Auto` 解析为具体方式
+    pub fn resolve(self) -> LoginMethod {
+        match self {
+            LoginMethod::Auto if is_headless() => LoginMethod::DeviceCode,
+            LoginMethod::Auto => LoginMethod::Loopback,
+            method => method,
+        }
+    }
+}
+
+/// 当前会话是否无法在本机打开浏览器
+pub fn is_headless() -> bool {
+    detect_headless(
+        |name| std::env::var_os(name).is_some_and(|v| !v.is_empty()),
+        cfg!(any(target_os = "windows", target_os = "macos")),
+    )
+}
+
+/// SSH 会话一律视为无界面；Windows/macOS 本地会话总有桌面，其余平台看 `DISPLAY` / `WAYLAND_DISPLAY`
+fn detect_headless(env_set: impl Fn(&str) -> bool, desktop_os: bool) -> bool {
+    if env_set("SSH_CONNECTION") || env_set("SSH_TTY") {
+        return true;
+    }
+    !desktop_os && !env_set("DISPLAY") && !env_set("WAYLAND_DISPLAY")
+}
+
+/// 设备码流程使用的 OAuth 客户端
+#[derive(Debug, Clone)]
+pub struct DeviceCodeClient<'a> {
+    pub client_id: &'a str,
+    pub client_secret: &'a str,
+    pub device_code_url: &'a str,
+    pub token_url: &'a str,
+    pub scopes: &'a [&'a str],
+}
+
+/// 设备码端点返回的授权信息
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    /// 用户在验证页面输入的代码
+    pub user_code: String,
+    /// 验证页面（Google 使用 `verification_url`，RFC 8628 为 `verification_uri`）
+    #[serde(alias = "verification_uri")]
+    pub verification_url: String,
+    /// 用户码有效期（秒）
+    pub expires_in: u64,
+    /// 轮询间隔（秒）
+    #[serde(default = "default_poll_interval")]
+    pub interval: u64,
+}
+
+/// 授权完成后换得的 Token
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceToken {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_in: i64,
+}
+
+/// 一次轮询的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PollOutcome {
+    /// 用户尚未完成授权
+    Pending,
+    /// 轮询过快，需要加大间隔
+    SlowDown,
+    /// 终止（拒绝、过期或其他错误）
+    Failed(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+impl DeviceCodeClient<'_> {
+    /// 申请设备码；客户端不支持设备码流程时返回 `None`
+    pub async fn request(&self, http: &reqwest::Client) -> Result<Option<DeviceAuthorization>, AuthError> {
+        let scope = self.scopes.join(" ");
+        let params = [("client_id", self.client_id), ("scope", scope.as_str())];
+        let res = http
+            .post(self.device_code_url)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| AuthError::OAuthFailed(format!("Device code request failed: {}", e)))?;
+
+        if !res.status().is_success() {
+            let body = res.text().await.unwrap_or_default();
+            return match serde_json::from_str::<OAuthErrorResponse>(&body) {
+                Ok(err) if matches!(err.error.as_str(), "invalid_client" | "unauthorized_client" | "invalid_scope") => {
+                    tracing::debug!("Device code flow unsupported for this client: {}", body);
+                    Ok(None)
+                }
+                _ => Err(AuthError::OAuthFailed(format!("Device code request failed: {}", body))),
+            };
+        }
+
+        res.json::<DeviceAuthorization>()
+            .await
+            .map(Some)
+            .map_err(|e| AuthError::Http(e.to_string()))
+    }
+
+    /// 轮询 token 端点直到用户完成授权
+    pub async fn poll(
+        &self,
+        http: &reqwest::Client,
+        authorization: &DeviceAuthorization,
+    ) -> Result<DeviceToken, AuthError> {
+        let params = [
+            ("client_id", self.client_id),
+            ("client_secret", self.client_secret),
+            ("device_code", authorization.device_code.as_str()),
+            ("grant_type", DEVICE_CODE_GRANT_TYPE),
+        ];
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(authorization.expires_in);
+        let mut interval = authorization.interval.max(1);
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+            if tokio::time::Instant::now() >= deadline {
+                return Err(AuthError::OAuthFailed("Device code expired before authorization".to_string()));
+            }
+
+            let res = http
+                .post(self.token_url)
+                .form(&params)
+                .send()
+                .await
+                .map_err(|e| AuthError::OAuthFailed(format!("Device token poll failed: {}", e)))?;
+            if res.status().is_success() {
+                return res.json::<DeviceToken>().await.map_err(|e| AuthError::Http(e.to_string()));
+            }
+
+            match poll_outcome(&res.text().await.unwrap_or_default()) {
+                PollOutcome::Pending => {}
+                PollOutcome::SlowDown => interval += 5,
+                PollOutcome::Failed(reason) => return Err(AuthError::OAuthFailed(reason)),
+            }
+        }
+    }
+}
+
+fn default_poll_interval() -> u64 {
+    DEFAULT_POLL_INTERVAL_SECS
+}
+
+/// 解析轮询失败时的错误响应
+fn poll_outcome(body: &str) -> PollOutcome {
+    match serde_json::from_str::<OAuthErrorResponse>(body) {
+        Ok(err) => match err.error.as_str() {
+            "authorization_pending" => PollOutcome::Pending,
+            "slow_down" => PollOutcome::SlowDown,
+            "access_denied" => PollOutcome::Failed("Authorization was denied".to_string()),
+            "expired_token" => PollOutcome::Failed("Device code expired before authorization".to_string()),
+            other => PollOutcome::Failed(format!(
+                "Device token poll failed: {} {}",
+                other,
+                err.error_description.unwrap_or_default()
+            )),
+        },
+        Err(_) => PollOutcome::Failed(format!("Device token poll failed: {}", body)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_headless() {
+        let env = |vars: &'static [&'static str]| move |name: &str| vars.iter().any(|v| *v == name);
+        assert!(detect_headless(env(&["SSH_CONNECTION", "DISPLAY"]), false));
+        assert!(detect_headless(env(&["SSH_TTY"]), true));
+        assert!(detect_headless(env(&[]), false));
+        assert!(!detect_headless(env(&["WAYLAND_DISPLAY"]), false));
+        assert!(!detect_headless(env(&[]), true));
+        assert_eq!(LoginMethod::DeviceCode.resolve(), LoginMethod::DeviceCode);
+        assert_eq!(LoginMethod::Loopback.resolve(), LoginMethod::Loopback);
+    }
+
+    #[test]
+    fn test_poll_outcome_and_google_authorization() {
+        assert_eq!(poll_outcome(r#"{"error":"authorization_pending"}"#), PollOutcome::Pending);
+        assert_eq!(poll_outcome(r#"{"error":"slow_down"}"#), PollOutcome::SlowDown);
+        assert!(matches!(poll_outcome(r#"{"error":"access_denied"}"#), PollOutcome::Failed(_)));
+        assert!(matches!(poll_outcome("<html>"), PollOutcome::Failed(_)));
+
+        let google: DeviceAuthorization = serde_json::from_str(
+            r#"{"device_code":"d","user_code":"ABC-DEF","verification_url":"https://www.google.com/device","expires_in":1800}"#,
+        )
+        .unwrap();
+        assert_eq!(google.interval, DEFAULT_POLL_INTERVAL_SECS);
+        let rfc: DeviceAuthorization = serde_json::from_str(
+            r#"{"device_code":"d","user_code":"X","verification_uri":"https://example.com/device","expires_in":600,"interval":2}"#,
+        )
+        .unwrap();
+        assert_eq!(rfc.verification_url, "https://example.com/device");
+    }
+}
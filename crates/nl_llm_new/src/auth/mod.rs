@@ -4,10 +4,14 @@
 //! - OAuth：需要浏览器登录，Token 会过期
 //! - API Key：直接使用，不过期
 //! - Service Account：JSON 凭据，JWT 认证
+//!
+//! `credentials` 登记 CLI 登录（`nl auth login`）写入的凭证文件，`device_code` 实现 OAuth 设备码流程。
 
 mod types;
 mod storage;
 
+pub mod credentials;
+pub mod device_code;
 pub mod providers;
 
 pub use types::*;
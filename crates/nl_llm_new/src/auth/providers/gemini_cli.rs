@@ -33,6 +33,7 @@ pub struct GeminiCliOAuthConfig {
     pub scopes: &'static [&'static str],
 }
 
+use crate::auth::device_code::{DeviceCodeClient, LoginMethod, GOOGLE_DEVICE_CODE_URL};
 use crate::auth::{AuthError, TokenStatus, TokenStorage};
 
 /// OAuth Token 响应
@@ -104,8 +105,8 @@ impl GeminiCliOAuth {
     /// 确保已认证（自动刷新或登录）
     pub async fn ensure_authenticated(&mut self) -> Result<(), AuthError> {
         if self.token.is_none() {
-            // 需要登录
-            let token = self.login().await?;
+            // 需要登录（无图形界面时走设备码流程）
+            let token = self.login_with(LoginMethod::Auto).await?;
             self.token = Some(token.clone());
             if let Some(ref path) = self.path {
                 let _ = Self::save_token_to_path_static(&token, path);
@@ -258,11 +259,57 @@ impl GeminiCliOAuth {
         };
 
         let token_resp = Self::exchange_code_static(&self.http, &code, &redirect_uri).await?;
-        let expires_at = chrono::Utc::now() + chrono::Duration::seconds(token_resp.expires_in);
-        let refresh_token = token_resp.refresh_token.ok_or_else(|| {
+        self.build_token(token_resp.access_token, token_resp.refresh_token, token_resp.expires_in).await
+    }
+
+    /// 交互式登录并保存 Token（`nl auth login`）
+    pub async fn interactive_login(&mut self, method: LoginMethod) -> Result<(), AuthError> {
+        let token = self.login_with(method).await?;
+        if let Some(ref path) = self.path {
+            Self::save_token_to_path_static(&token, path)?;
+        }
+        self.token = Some(token);
+        Ok(())
+    }
+
+    /// 按登录方式获取 Token
+    ///
+    /// 无图形界面（如 SSH 会话）时 `Auto` 使用设备码流程；OAuth 客户端不支持设备码时回退到本地端口回调。
+    async fn login_with(&mut self, method: LoginMethod) -> Result<TokenStorage, AuthError> {
+        if method.resolve() == LoginMethod::DeviceCode {
+            let device = DeviceCodeClient {
+                client_id: GEMINI_CLI_OAUTH_CONFIG.client_id,
+                client_secret: GEMINI_CLI_OAUTH_CONFIG.client_secret,
+                device_code_url: GOOGLE_DEVICE_CODE_URL,
+                token_url: GEMINI_CLI_OAUTH_CONFIG.token_url,
+                scopes: GEMINI_CLI_OAUTH_CONFIG.scopes,
+            };
+            if let Some(authorization) = device.request(&self.http).await? {
+                println!("=== Gemini CLI Device Login ===");
+                println!(
+                    "On any device, open {} and enter the code: {}\n",
+                    authorization.verification_url, authorization.user_code
+                );
+                let resp = device.poll(&self.http, &authorization).await?;
+                return self.build_token(resp.access_token, resp.refresh_token, resp.expires_in).await;
+            }
+            println!("Device code login is not available for this client, falling back to the browser callback.");
+        }
+        self.login().await
+    }
+
+    /// 由授权结果组装 TokenStorage（并尝试获取 project_id）
+    async fn build_token(
+        &self,
+        access_token: String,
+        refresh_token: Option<String>,
+        expires_in: i64,
+    ) -> Result<TokenStorage, AuthError> {
+        let expires_at = chrono::Utc::now() + chrono::Duration::seconds(expires_in);
+        let refresh_token = refresh_token.ok_or_else(|| {
             AuthError::OAuthFailed("No refresh_token returned".to_string())
         })?;
-        let project_id = Self::fetch_project_id_static(&self.http, &token_resp.access_token).await.ok();
+        let project_id = Self::fetch_project_id_static(&self.http, &access_token).await.ok();
 
         let mut extra = std::collections::HashMap::new();
         if let Some(pid) = project_id {
@@ -270,7 +317,7 @@ impl GeminiCliOAuth {
         }
 
         Ok(TokenStorage {
-            access_token: token_resp.access_token,
+            access_token,
             refresh_token: Some(refresh_token),
             expires_at: Some(expires_at),
             email: None,
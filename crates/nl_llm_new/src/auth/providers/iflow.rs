@@ -4,6 +4,7 @@
 //! - 无需浏览器登录，Cookie 由用户提供
 //! - API Key 通过两步获取：GET 获取信息 → POST 刷新获取完整 Key
 //! - API Key 有过期时间，支持持久化缓存复用
+//! - 剩余有效期不足 2 天时刷新（由 Gateway 后台任务提前完成，请求路径上仅作兜底）
 
 use crate::auth::{AuthError, TokenStatus, TokenStorage};
 use reqwest::header::{HeaderMap, HeaderValue};
@@ -11,6 +12,12 @@ use serde::Deserialize;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+/// API Key 有效期（接口不返回过期时间，按 7 天计）
+const API_KEY_TTL_DAYS: i64 = 7;
+
+/// 提前刷新窗口
+pub const API_KEY_REFRESH_LEAD_SECS: i64 = 2 * 24 * 3600;
+
 /// iFlow 认证客户端
 ///
 /// 负责 Cookie → API Key 的获取和持久化缓存
@@ -139,8 +146,7 @@ impl IFlowAuth {
 
             if let Some(ref mut token) = self.token {
                 token.access_token = api_key.clone();
-                // 更新过期时间（假设 7 天有效）
-                token.expires_at = Some(chrono::Utc::now() + chrono::Duration::days(7));
+                token.expires_at = Some(chrono::Utc::now() + chrono::Duration::days(API_KEY_TTL_DAYS));
 
                 if let Some(ref path) = self.path {
                     let _ = Self::save_token_to_path_static(token, path);
@@ -158,8 +164,7 @@ impl IFlowAuth {
             if t.access_token.is_empty() {
                 return TokenStatus::Expired;
             }
-            // 使用 7 天的提前量检查过期
-            t.status(7 * 24 * 3600)
+            t.status(API_KEY_REFRESH_LEAD_SECS)
         })
     }
 
@@ -184,7 +189,7 @@ impl IFlowAuth {
 
         if let Some(ref mut token) = self.token {
             token.access_token = api_key.clone();
-            token.expires_at = Some(chrono::Utc::now() + chrono::Duration::days(7));
+            token.expires_at = Some(chrono::Utc::now() + chrono::Duration::days(API_KEY_TTL_DAYS));
 
             if let Some(ref path) = self.path {
                 let _ = Self::save_token_to_path_static(token, path);
@@ -375,6 +380,19 @@ mod tests {
         assert!(!auth.has_cache());
     }
 
+    #[test]
+    fn test_refresh_window() {
+        let mut auth = IFlowAuth::from_cookie("BXAuth=test123").unwrap();
+        let token = auth.token.as_mut().unwrap();
+        token.access_token = "sk-iflow".to_string();
+        token.expires_at = Some(chrono::Utc::now() + chrono::Duration::days(3));
+        assert!(!auth.needs_refresh());
+
+        auth.token.as_mut().unwrap().expires_at = Some(chrono::Utc::now() + chrono::Duration::hours(47));
+        assert_eq!(auth.token_status(), TokenStatus::ExpiringSoon);
+        assert_eq!(auth.api_key(), Some("sk-iflow"));
+    }
+
     #[test]
     fn test_from_cookie() {
         let auth = IFlowAuth::from_cookie("BXAuth=test123; other=value").unwrap();
@@ -1,11 +1,138 @@
 //! 代理目录（用于文档、配置 UI、诊断）
+//!
+//! 内置规格之外，可从用户目录加载更多规格：目录（`NEUROLOOM_PROXY_CATALOG`，默认 `<配置目录>/neuroloom/proxies`）
+//! 中的每个 `*.json` 文件包含一个规格或规格数组，按文件名顺序合并。与内置规格同名的条目覆盖内置规格，
+//! 其他名称登记为自定义代理（`BlackMagicProxyTarget::Custom`）。加载时逐条校验，任一条目无效即报错并指出文件。
 
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::client::ProxyError;
 use super::types::*;
 
+/// 用户目录位置的环境变量
+pub const CATALOG_ENV: &str = "NEUROLOOM_PROXY_CATALOG";
+
+/// 目录中的一个条目
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    pub spec: BlackMagicProxySpec,
+    /// 来源文件（内置规格为空）
+    pub source: Option<PathBuf>,
+}
+
+/// 规格文件内容：单个规格或规格数组
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CatalogFile {
+    One(BlackMagicProxySpec),
+    Many(Vec<BlackMagicProxySpec>),
+}
+
 /// 黑魔法代理目录
-pub struct BlackMagicProxyCatalog;
+#[derive(Debug, Clone)]
+pub struct BlackMagicProxyCatalog {
+    entries: Vec<CatalogEntry>,
+}
 
 impl BlackMagicProxyCatalog {
+    /// 只含内置规格的目录
+    pub fn builtin() -> Self {
+        Self {
+            entries: Self::all_specs()
+                .into_iter()
+                .map(|spec| CatalogEntry { spec, source: None })
+                .collect(),
+        }
+    }
+
+    /// 用户目录位置（`NEUROLOOM_PROXY_CATALOG`，默认 `<配置目录>/neuroloom/proxies`）
+    pub fn default_dir() -> Option<PathBuf> {
+        match std::env::var_os(CATALOG_ENV).filter(|dir| !dir.is_empty()) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => dirs::config_dir().map(|dir| dir.join("neuroloom").join("proxies")),
+        }
+    }
+
+    /// 内置规格合并用户目录（目录不存在时只含内置规格）
+    pub fn load() -> Result<Self, ProxyError> {
+        let mut catalog = Self::builtin();
+        if let Some(dir) = Self::default_dir().filter(|dir| dir.exists()) {
+            catalog.merge_path(&dir)?;
+        }
+        Ok(catalog)
+    }
+
+    /// 内置规格合并指定的目录或规格文件
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, ProxyError> {
+        let mut catalog = Self::builtin();
+        catalog.merge_path(path.as_ref())?;
+        Ok(catalog)
+    }
+
+    /// 合并目录（其中的 `*.json`，按文件名顺序）或单个规格文件，返回合并的规格数
+    pub fn merge_path(&mut self, path: &Path) -> Result<usize, ProxyError> {
+        let files = if path.is_dir() {
+            let entries = std::fs::read_dir(path).map_err(|e| ProxyError {
+                message: format!("read proxy catalog {} failed: {e}", path.display()),
+            })?;
+            let mut files: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| file.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        let mut merged = 0;
+        for file in files {
+            let content = std::fs::read_to_string(&file).map_err(|e| ProxyError {
+                message: format!("read proxy spec {} failed: {e}", file.display()),
+            })?;
+            let specs = match serde_json::from_str(&content) {
+                Ok(CatalogFile::One(spec)) => vec![spec],
+                Ok(CatalogFile::Many(specs)) => specs,
+                Err(e) => {
+                    return Err(ProxyError {
+                        message: format!("invalid proxy spec {}: {e}", file.display()),
+                    })
+                }
+            };
+            for spec in specs {
+                self.merge(spec, Some(file.clone())).map_err(|e| ProxyError {
+                    message: format!("{}: {}", file.display(), e.message),
+                })?;
+                merged += 1;
+            }
+        }
+        Ok(merged)
+    }
+
+    /// 校验并合并一个规格，同名规格被覆盖
+    pub fn merge(&mut self, spec: BlackMagicProxySpec, source: Option<PathBuf>) -> Result<(), ProxyError> {
+        validate(&spec)?;
+        let entry = CatalogEntry { spec, source };
+        match self.entries.iter_mut().find(|it| it.spec.target == entry.spec.target) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+        Ok(())
+    }
+
+    /// 全部条目
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    /// 按目标查找规格
+    pub fn get(&self, target: &BlackMagicProxyTarget) -> Option<&BlackMagicProxySpec> {
+        self.entries.iter().map(|it| &it.spec).find(|spec| &spec.target == target)
+    }
+
+    /// 内置规格
     pub fn all_specs() -> Vec<BlackMagicProxySpec> {
         vec![
             BlackMagicProxySpec {
@@ -251,11 +378,59 @@ impl BlackMagicProxyCatalog {
         ]
     }
 
+    /// 按目标查找内置规格
     pub fn by_target(target: BlackMagicProxyTarget) -> Option<BlackMagicProxySpec> {
         Self::all_specs().into_iter().find(|it| it.target == target)
     }
 }
 
+impl Default for BlackMagicProxyCatalog {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// 校验规格：名称、基础 URL 与各暴露方式的必填字段
+fn validate(spec: &BlackMagicProxySpec) -> Result<(), ProxyError> {
+    let invalid = |reason: String| ProxyError {
+        message: format!("proxy spec {}: {}", spec.target, reason),
+    };
+    let name = spec.target.name();
+    if name.trim().is_empty() || name.chars().any(char::is_whitespace) {
+        return Err(invalid("target name must be non-empty without whitespace".to_string()));
+    }
+    if !spec.default_base_url.is_empty() {
+        match url::Url::parse(&spec.default_base_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => return Err(invalid(format!("invalid default_base_url {}", spec.default_base_url))),
+        }
+    }
+    if spec.exposures.is_empty() {
+        return Err(invalid("at least one exposure is required".to_string()));
+    }
+    for exposure in &spec.exposures {
+        match exposure.kind {
+            ProxyExposureKind::Cli => {
+                if exposure.cli_command.as_deref().is_none_or(|c| c.trim().is_empty()) {
+                    return Err(invalid("cli exposure requires cli_command".to_string()));
+                }
+            }
+            kind => {
+                if exposure.path.trim().is_empty() {
+                    return Err(invalid(format!("{:?} exposure requires a path", kind)));
+                }
+                if kind != ProxyExposureKind::WebSocket && exposure.method.trim().is_empty() {
+                    return Err(invalid(format!("{:?} exposure requires a method", kind)));
+                }
+            }
+        }
+        if exposure.auth_prefix.is_some() && exposure.auth_header.is_none() {
+            return Err(invalid("auth_prefix requires auth_header".to_string()));
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,4 +454,38 @@ mod tests {
             .iter()
             .any(|it| it.kind == ProxyExposureKind::WebSocket));
     }
+
+    #[test]
+    fn test_load_merges_user_specs() {
+        let dir = std::env::temp_dir().join(format!("nl-proxy-catalog-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("10-local.json"),
+            r#"[
+                {"target": "NewApi", "default_base_url": "http://10.0.0.2:3000",
+                 "exposures": [{"kind": "Api", "path": "/v1/chat/completions", "method": "POST"}]},
+                {"target": "my-relay", "default_base_url": "https://relay.example.com",
+                 "exposures": [{"kind": "Api", "path": "/chat", "method": "POST",
+                                "auth_header": "Authorization", "auth_prefix": "Bearer "}]}
+            ]"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let catalog = BlackMagicProxyCatalog::load_from(&dir).unwrap();
+        assert_eq!(catalog.entries().len(), 11);
+        let newapi = catalog.get(&BlackMagicProxyTarget::NewApi).unwrap();
+        assert_eq!(newapi.default_base_url, "http://10.0.0.2:3000");
+        let relay = BlackMagicProxyTarget::Custom("my-relay".to_string());
+        assert!(catalog.entries().iter().any(|it| it.spec.target == relay && it.source.is_some()));
+
+        std::fs::write(
+            dir.join("20-broken.json"),
+            r#"{"target": "broken", "exposures": [{"kind": "Cli"}]}"#,
+        )
+        .unwrap();
+        let err = BlackMagicProxyCatalog::load_from(&dir).unwrap_err();
+        assert!(err.message.contains("20-broken.json") && err.message.contains("cli_command"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,312 @@
+//! CLI 代理执行
+//!
+//! 执行 `ProxyPreparedCliCall`：启动子进程并注入环境变量，把请求载荷写入 stdin 后关闭，
+//! 边读 stdout 边输出增量文本。整次调用受超时限制（超时终止子进程），退出码映射为 Provider 错误：
+//! - 命令不存在或不可执行（启动失败、126、127）：不可重试，降级到其他 Provider
+//! - `EX_TEMPFAIL`（75）或被信号终止：可重试
+//! - `EX_NOPERM`（77）：认证错误
+//! - 其他非零退出码：不可重试
+//!
+//! `CliProxyProvider` 把 Cli 形态的代理目标包装为 `LlmProvider`，可注册到 Gateway 或作为反代上游。
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::client::BlackMagicProxyClient;
+use super::types::*;
+use crate::auth::{ApiKeyConfig, ApiKeyProvider, Auth};
+use crate::primitive::PrimitiveRequest;
+use crate::provider::local::backend::take_utf8;
+use crate::provider::local::template::message_text;
+use crate::provider::{BoxStream, ChunkDelta, LlmChunk, LlmProvider, LlmResponse, ProviderError, StopReason, Usage};
+
+/// 默认调用超时
+pub const DEFAULT_CLI_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// 启动 CLI 调用，返回 stdout 的增量文本流；进程以非零退出码结束或超时时流的最后一项为错误
+pub fn spawn_cli_call(
+    call: &ProxyPreparedCliCall,
+    timeout: Duration,
+) -> crate::Result<BoxStream<'static, crate::Result<String>>> {
+    let mut child = tokio::process::Command::new(&call.command)
+        .args(&call.args)
+        .envs(&call.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| unavailable(format!("failed to start {}: {}", call.command, e)))?;
+    let mut stdin = child.stdin.take().ok_or_else(|| unavailable("no stdin".into()))?;
+    let mut stdout = child.stdout.take().ok_or_else(|| unavailable("no stdout".into()))?;
+    let mut stderr = child.stderr.take().ok_or_else(|| unavailable("no stderr".into()))?;
+
+    // 单独写入 stdin，避免载荷较大时与读取 stdout 互相阻塞；写完后关闭管道
+    let payload = call.input_payload.clone();
+    tokio::spawn(async move {
+        let _ = stdin.write_all(payload.as_bytes()).await;
+    });
+    let stderr_task = tokio::spawn(async move {
+        let mut log = String::new();
+        let _ = stderr.read_to_string(&mut log).await;
+        log
+    });
+    let command = call.command.clone();
+    let deadline = tokio::time::Instant::now() + timeout;
+
+    let stream = async_stream::stream! {
+        let mut buf = [0u8; 4096];
+        let mut pending = Vec::new();
+        loop {
+            match tokio::time::timeout_at(deadline, stdout.read(&mut buf)).await {
+                Err(_) => {
+                    let _ = child.kill().await;
+                    yield Err(timed_out(&command, timeout));
+                    return;
+                }
+                Ok(Ok(0)) => break,
+                Ok(Ok(n)) => {
+                    pending.extend_from_slice(&buf[..n]);
+                    let text = take_utf8(&mut pending);
+                    if !text.is_empty() {
+                        yield Ok(text);
+                    }
+                }
+                Ok(Err(e)) => {
+                    yield Err(crate::Error::Io(e));
+                    return;
+                }
+            }
+        }
+        if !pending.is_empty() {
+            yield Ok(String::from_utf8_lossy(&pending).into_owned());
+        }
+
+        let status = match tokio::time::timeout_at(deadline, child.wait()).await {
+            Ok(status) => status,
+            Err(_) => {
+                let _ = child.kill().await;
+                yield Err(timed_out(&command, timeout));
+                return;
+            }
+        };
+        let log = stderr_task.await.unwrap_or_default();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => yield Err(exit_error(&command, status.code(), &log)),
+            Err(e) => yield Err(crate::Error::Io(e)),
+        }
+    };
+    Ok(Box::pin(stream))
+}
+
+/// 执行 CLI 调用并收集全部输出
+pub async fn run_cli_call(call: &ProxyPreparedCliCall, timeout: Duration) -> crate::Result<String> {
+    let mut chunks = spawn_cli_call(call, timeout)?;
+    let mut output = String::new();
+    while let Some(chunk) = chunks.next().await {
+        output.push_str(&chunk?);
+    }
+    Ok(output)
+}
+
+/// 命令不可用：不可重试，但应降级到其他 Provider
+fn unavailable(message: String) -> crate::Error {
+    crate::Error::Provider(ProviderError {
+        message,
+        retryable: false,
+        should_fallback: true,
+        retry_after_ms: None,
+    })
+}
+
+fn timed_out(command: &str, timeout: Duration) -> crate::Error {
+    crate::Error::Provider(ProviderError::retryable(
+        format!("{} timed out after {}s", command, timeout.as_secs()),
+        true,
+        None,
+    ))
+}
+
+/// 退出码映射为错误
+fn exit_error(command: &str, code: Option<i32>, log: &str) -> crate::Error {
+    let tail: String = log.lines().rev().take(5).collect::<Vec<_>>().join(" | ");
+    let status = code.map_or("a signal".to_string(), |code| format!("code {}", code));
+    let message = format!("{} exited with {}: {}", command, status, tail);
+    match code {
+        Some(126) | Some(127) => unavailable(message),
+        Some(75) | None => crate::Error::Provider(ProviderError::retryable(message, true, None)),
+        Some(77) => crate::Error::Auth(message),
+        Some(_) => crate::Error::Provider(ProviderError::fail(message)),
+    }
+}
+
+/// CLI 代理 Provider
+///
+/// 请求编译为 `ProxyChatRequest`，经 `BlackMagicProxyClient` 准备为 CLI 调用后执行；
+/// stdout 即回复内容，token 用量按约 4 字符/token 估算。
+pub struct CliProxyProvider {
+    id: String,
+    auth: Auth,
+    client: BlackMagicProxyClient,
+    models: &'static [&'static str],
+    timeout: Duration,
+}
+
+impl CliProxyProvider {
+    /// 创建 Provider，`client` 的目标须提供 Cli 形态
+    pub fn new(id: impl Into<String>, client: BlackMagicProxyClient, models: Vec<String>) -> Self {
+        // 模型名只在创建时泄漏一次，用于满足 `supported_models` 的 'static 约束
+        let models: Vec<&'static str> = models.into_iter().map(|m| &*Box::leak(m.into_boxed_str())).collect();
+        Self {
+            id: id.into(),
+            auth: Auth::ApiKey(ApiKeyConfig::new("cli", ApiKeyProvider::OpenAI)),
+            client,
+            models: Box::leak(models.into_boxed_slice()),
+            timeout: DEFAULT_CLI_TIMEOUT,
+        }
+    }
+
+    /// 设置单次调用超时（默认 300 秒）
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn prepare(&self, body: serde_json::Value, stream: bool) -> crate::Result<ProxyPreparedCliCall> {
+        let mut request: ProxyChatRequest = serde_json::from_value(body)?;
+        request.stream = Some(stream);
+        match self.client.prepare_call(ProxyExposureKind::Cli, &request) {
+            Ok(ProxyPreparedCall::Cli(call)) => Ok(call),
+            Ok(_) => Err(unavailable(format!("{} did not prepare a cli call", self.id))),
+            Err(e) => Err(unavailable(e.message)),
+        }
+    }
+}
+
+fn estimate_usage(input: &str, output: &str) -> Usage {
+    Usage {
+        input_tokens: (input.chars().count() as u64).div_ceil(4),
+        output_tokens: (output.chars().count() as u64).div_ceil(4),
+        ..Default::default()
+    }
+}
+
+#[async_trait]
+impl LlmProvider for CliProxyProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn auth(&self) -> &Auth {
+        &self.auth
+    }
+
+    fn supported_models(&self) -> &[&str] {
+        self.models
+    }
+
+    fn compile(&self, primitive: &PrimitiveRequest) -> serde_json::Value {
+        let mut messages = Vec::new();
+        if let Some(system) = primitive.system.as_deref().filter(|s| !s.is_empty()) {
+            messages.push(ProxyMessage {
+                role: "system".to_string(),
+                content: system.to_string(),
+            });
+        }
+        messages.extend(primitive.messages.iter().map(|m| ProxyMessage {
+            role: m.role.to_string(),
+            content: message_text(m),
+        }));
+        let request = ProxyChatRequest {
+            model: primitive.model.clone(),
+            messages,
+            temperature: primitive.parameters.temperature,
+            stream: None,
+        };
+        serde_json::to_value(request).unwrap_or_default()
+    }
+
+    async fn complete(&self, body: serde_json::Value) -> crate::Result<LlmResponse> {
+        let call = self.prepare(body, false)?;
+        let content = run_cli_call(&call, self.timeout).await?;
+        Ok(LlmResponse {
+            usage: estimate_usage(&call.input_payload, &content),
+            content,
+            tool_calls: Vec::new(),
+            stop_reason: StopReason::EndTurn,
+        })
+    }
+
+    async fn stream(
+        &self,
+        body: serde_json::Value,
+    ) -> crate::Result<BoxStream<'_, crate::Result<LlmChunk>>> {
+        let call = self.prepare(body, true)?;
+        let mut chunks = spawn_cli_call(&call, self.timeout)?;
+        let input = call.input_payload;
+
+        let stream = async_stream::stream! {
+            let mut content = String::new();
+            while let Some(chunk) = chunks.next().await {
+                match chunk {
+                    Ok(text) => {
+                        content.push_str(&text);
+                        yield Ok(LlmChunk { delta: ChunkDelta::Text(text), usage: None });
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+            yield Ok(LlmChunk {
+                delta: ChunkDelta::Text(String::new()),
+                usage: Some(estimate_usage(&input, &content)),
+            });
+        };
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn sh(script: &str) -> ProxyPreparedCliCall {
+        ProxyPreparedCliCall {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: BTreeMap::from([("NEUROLOOM_PROXY_TOKEN".to_string(), "secret".to_string())]),
+            input_payload: "ping".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cli_call_env_stdin_timeout_and_exit_codes() {
+        let output = run_cli_call(&sh("printf '%s:' \"$NEUROLOOM_PROXY_TOKEN\"; cat"), DEFAULT_CLI_TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(output, "secret:ping");
+
+        let err = run_cli_call(&sh("sleep 5"), Duration::from_millis(100)).await.unwrap_err();
+        assert!(matches!(err, crate::Error::Provider(ProviderError { retryable: true, .. })), "{}", err);
+
+        let err = run_cli_call(&sh("echo denied >&2; exit 77"), DEFAULT_CLI_TIMEOUT).await.unwrap_err();
+        assert!(matches!(&err, crate::Error::Auth(m) if m.contains("denied")), "{}", err);
+        let err = run_cli_call(&sh("exit 3"), DEFAULT_CLI_TIMEOUT).await.unwrap_err();
+        assert!(matches!(err, crate::Error::Provider(ProviderError { retryable: false, .. })));
+
+        let missing = ProxyPreparedCliCall {
+            command: "nl-missing-proxy-cli".to_string(),
+            ..sh("")
+        };
+        let err = run_cli_call(&missing, DEFAULT_CLI_TIMEOUT).await.unwrap_err();
+        assert!(matches!(err, crate::Error::Provider(ProviderError { should_fallback: true, .. })));
+    }
+}
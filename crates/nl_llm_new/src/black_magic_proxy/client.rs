@@ -1,6 +1,7 @@
 //! 统一代理客户端
 
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 use super::types::*;
 use super::catalog::BlackMagicProxyCatalog;
@@ -24,6 +25,8 @@ pub struct BlackMagicProxyClient {
     target: BlackMagicProxyTarget,
     base_url: String,
     credential: String,
+    /// 查找规格的目录（默认只含内置规格）
+    catalog: Arc<BlackMagicProxyCatalog>,
 }
 
 impl BlackMagicProxyClient {
@@ -36,6 +39,7 @@ impl BlackMagicProxyClient {
             target,
             base_url: base_url.into(),
             credential: credential.into(),
+            catalog: Arc::new(BlackMagicProxyCatalog::builtin()),
         }
     }
 
@@ -43,22 +47,39 @@ impl BlackMagicProxyClient {
         target: BlackMagicProxyTarget,
         credential: impl Into<String>,
     ) -> Result<Self, ProxyError> {
-        let spec = BlackMagicProxyCatalog::by_target(target).ok_or_else(|| {
-            ProxyError {
-                message: "proxy target spec not found".to_string(),
-            }
-        })?;
+        Self::from_catalog(Arc::new(BlackMagicProxyCatalog::builtin()), target, credential)
+    }
 
-        Ok(Self::new(target, spec.default_base_url, credential))
+    /// 按目录（如 `BlackMagicProxyCatalog::load()` 的结果）中的规格创建客户端
+    pub fn from_catalog(
+        catalog: Arc<BlackMagicProxyCatalog>,
+        target: BlackMagicProxyTarget,
+        credential: impl Into<String>,
+    ) -> Result<Self, ProxyError> {
+        let base_url = catalog
+            .get(&target)
+            .map(|spec| spec.default_base_url.clone())
+            .ok_or_else(|| ProxyError {
+                message: format!("proxy target spec not found: {}", target),
+            })?;
+
+        Ok(Self::new(target, base_url, credential).with_catalog(catalog))
+    }
+
+    /// 使用指定目录查找规格
+    pub fn with_catalog(mut self, catalog: Arc<BlackMagicProxyCatalog>) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    fn spec(&self) -> Result<&BlackMagicProxySpec, ProxyError> {
+        self.catalog.get(&self.target).ok_or_else(|| ProxyError {
+            message: format!("proxy target spec not found: {}", self.target),
+        })
     }
 
     pub fn list_supported_exposures(&self) -> Result<Vec<ProxyExposure>, ProxyError> {
-        let spec = BlackMagicProxyCatalog::by_target(self.target).ok_or_else(|| {
-            ProxyError {
-                message: "proxy target spec not found".to_string(),
-            }
-        })?;
-        Ok(spec.exposures)
+        Ok(self.spec()?.exposures.clone())
     }
 
     /// 按指定形态准备调用参数
@@ -67,11 +88,7 @@ impl BlackMagicProxyClient {
         exposure_kind: ProxyExposureKind,
         request: &ProxyChatRequest,
     ) -> Result<ProxyPreparedCall, ProxyError> {
-        let spec = BlackMagicProxyCatalog::by_target(self.target).ok_or_else(|| {
-            ProxyError {
-                message: "proxy target spec not found".to_string(),
-            }
-        })?;
+        let spec = self.spec()?;
 
         let exposure = spec
             .exposures
@@ -80,7 +97,7 @@ impl BlackMagicProxyClient {
             .ok_or_else(|| {
                 ProxyError {
                     message: format!(
-                        "exposure kind {:?} not supported for target {:?}",
+                        "exposure kind {:?} not supported for target {}",
                         exposure_kind, self.target
                     ),
                 }
@@ -178,7 +195,7 @@ impl BlackMagicProxyClient {
         env.insert("NEUROLOOM_PROXY_TOKEN".to_string(), self.credential.clone());
         env.insert(
             "NEUROLOOM_PROXY_TARGET".to_string(),
-            format!("{:?}", self.target),
+            self.target.to_string(),
         );
 
         Ok(ProxyPreparedCliCall {
@@ -6,12 +6,22 @@
 //! - WebSocket（实时双工）
 //! - CLI（本地命令行代理）
 //!
+//! 规格目录（`BlackMagicProxyCatalog`）由内置规格与用户目录中的 JSON 规格合并而成，新增代理目标无需改代码。
+//!
+//! Cli 形态由 `CliProxyProvider` 实际执行（子进程、stdin 载荷、超时与退出码映射），可注册为 Gateway 的 Provider。
+//!
+//! 另提供反向模式（`ProxyServer`）：在本地端口把上游重新暴露为 OpenAI 兼容接口。
+//!
 //! 对应上游项目：CLIProxyAPI / newapi / ccswitch / Claude Code Router。
 
 mod types;
 mod catalog;
+mod cli;
 mod client;
+mod server;
 
 pub use types::*;
 pub use catalog::*;
+pub use cli::*;
 pub use client::*;
+pub use server::*;
@@ -0,0 +1,608 @@
+//! 自托管反代模式
+//!
+//! 在本地端口监听，把已配置的上游（Antigravity、Vertex、IFlow 等任意 `LlmProvider`）
+//! 重新暴露为 OpenAI 兼容接口：
+//! - `GET /v1/models`
+//! - `POST /v1/chat/completions`（支持 `stream: true` SSE）
+//!
+//! 每条路由有独立的访问密钥（`Authorization: Bearer <key>`）。
+//! 模型可写作 `<路由名>/<模型>` 显式指定路由，否则在该密钥可访问的路由中按模型匹配。
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::catalog::BlackMagicProxyCatalog;
+use super::cli::CliProxyProvider;
+use super::client::BlackMagicProxyClient;
+use super::types::BlackMagicProxyTarget;
+use crate::primitive::{PrimitiveContent, PrimitiveMessage, PrimitiveRequest, Role};
+use crate::provider::antigravity::{AntigravityConfig, AntigravityProvider};
+use crate::provider::iflow::config::IFlowConfig;
+use crate::provider::iflow::provider::IFlowProvider;
+use crate::provider::vertex::VertexProvider;
+use crate::provider::{ChunkDelta, LlmProvider, StopReason, Usage};
+
+/// 上游配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UpstreamConfig {
+    /// Antigravity（OAuth token 文件，缺省为 `~/.nl_llm/antigravity_token.json`）
+    Antigravity {
+        model: String,
+        #[serde(default)]
+        token_path: Option<PathBuf>,
+    },
+    /// Vertex AI（Service Account JSON 文件）
+    Vertex {
+        model: String,
+        credentials_path: PathBuf,
+        #[serde(default)]
+        location: Option<String>,
+    },
+    /// iFlow（Cookie 换取 API Key）
+    #[serde(rename = "iflow")]
+    IFlow {
+        model: String,
+        cookie: String,
+        #[serde(default)]
+        token_path: Option<PathBuf>,
+    },
+    /// 代理目录中提供 Cli 形态的目标（本地命令行工具）
+    Cli {
+        target: BlackMagicProxyTarget,
+        model: String,
+        /// 以 `NEUROLOOM_PROXY_TOKEN` 注入子进程的凭据
+        #[serde(default)]
+        credential: String,
+        /// 单次调用超时（秒，缺省 300）
+        #[serde(default)]
+        timeout_secs: Option<u64>,
+    },
+}
+
+impl UpstreamConfig {
+    /// 构建上游 Provider
+    pub fn build(&self, http: reqwest::Client) -> crate::Result<Arc<dyn LlmProvider>> {
+        Ok(match self {
+            UpstreamConfig::Antigravity { model, token_path } => {
+                let config = match token_path {
+                    Some(path) => AntigravityConfig::new(path.clone(), model.clone()),
+                    None => AntigravityConfig::with_default_path(model.clone()),
+                };
+                if !config.token_path.exists() {
+                    return Err(crate::Error::Auth(format!(
+                        "antigravity token not found: {}",
+                        config.token_path.display()
+                    )));
+                }
+                Arc::new(AntigravityProvider::new(config, http))
+            }
+            UpstreamConfig::Vertex {
+                model,
+                credentials_path,
+                location,
+            } => Arc::new(VertexProvider::from_file(
+                credentials_path,
+                model.clone(),
+                location.clone(),
+                http,
+            )?),
+            UpstreamConfig::IFlow {
+                model,
+                cookie,
+                token_path,
+            } => {
+                let config = match token_path {
+                    Some(path) => IFlowConfig::new(cookie.clone(), model.clone(), path.clone()),
+                    None => IFlowConfig::with_default_path(cookie.clone(), model.clone()),
+                };
+                Arc::new(IFlowProvider::new(config, http))
+            }
+            UpstreamConfig::Cli {
+                target,
+                model,
+                credential,
+                timeout_secs,
+            } => {
+                let catalog = BlackMagicProxyCatalog::load().map_err(|e| crate::Error::Unknown(e.message))?;
+                let client = BlackMagicProxyClient::from_catalog(Arc::new(catalog), target.clone(), credential.clone())
+                    .map_err(|e| crate::Error::Unknown(e.message))?;
+                let provider = CliProxyProvider::new(format!("cli:{}", target), client, vec![model.clone()]);
+                Arc::new(match timeout_secs {
+                    Some(secs) => provider.with_timeout(std::time::Duration::from_secs(*secs)),
+                    None => provider,
+                })
+            }
+        })
+    }
+}
+
+/// 路由配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyRouteConfig {
+    /// 路由名（可作为模型前缀）
+    pub name: String,
+    /// 上游
+    pub upstream: UpstreamConfig,
+    /// 允许访问该路由的密钥
+    pub keys: Vec<String>,
+    /// 对外暴露的模型（为空时使用上游支持的模型）
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+/// 反代服务配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyServerConfig {
+    /// 监听地址
+    #[serde(default = "default_proxy_addr")]
+    pub addr: SocketAddr,
+    /// 路由
+    #[serde(default)]
+    pub routes: Vec<ProxyRouteConfig>,
+}
+
+fn default_proxy_addr() -> SocketAddr {
+    "127.0.0.1:8317".parse().unwrap()
+}
+
+impl Default for ProxyServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: default_proxy_addr(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl ProxyServerConfig {
+    /// 从 JSON 文件加载
+    pub fn load(path: impl AsRef<Path>) -> crate::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// 已就绪的路由
+#[derive(Clone)]
+pub struct ProxyRoute {
+    /// 路由名
+    pub name: String,
+    /// 上游 Provider
+    pub provider: Arc<dyn LlmProvider>,
+    /// 访问密钥
+    pub keys: Vec<String>,
+    /// 对外暴露的模型
+    pub models: Vec<String>,
+}
+
+impl ProxyRoute {
+    /// 创建路由
+    pub fn new(name: impl Into<String>, provider: Arc<dyn LlmProvider>) -> Self {
+        Self {
+            name: name.into(),
+            provider,
+            keys: Vec::new(),
+            models: Vec::new(),
+        }
+    }
+
+    /// 添加访问密钥
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+
+    /// 添加对外暴露的模型
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    fn models(&self) -> Vec<String> {
+        if self.models.is_empty() {
+            self.provider
+                .supported_models()
+                .iter()
+                .map(|m| m.to_string())
+                .collect()
+        } else {
+            self.models.clone()
+        }
+    }
+
+    fn accepts(&self, key: &str) -> bool {
+        self.keys.iter().any(|k| k == key)
+    }
+}
+
+/// 自托管反代服务
+pub struct ProxyServer {
+    addr: SocketAddr,
+    routes: Vec<ProxyRoute>,
+}
+
+impl ProxyServer {
+    /// 创建反代服务
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            routes: Vec::new(),
+        }
+    }
+
+    /// 按配置构建全部上游，构建失败的路由会被跳过并记录警告
+    pub fn from_config(config: &ProxyServerConfig) -> Self {
+        let http = reqwest::Client::new();
+        let routes = config
+            .routes
+            .iter()
+            .filter_map(|route| match route.upstream.build(http.clone()) {
+                Ok(provider) => Some(ProxyRoute {
+                    name: route.name.clone(),
+                    provider,
+                    keys: route.keys.clone(),
+                    models: route.models.clone(),
+                }),
+                Err(e) => {
+                    tracing::warn!("proxy route {} disabled: {}", route.name, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            addr: config.addr,
+            routes,
+        }
+    }
+
+    /// 添加路由
+    pub fn with_route(mut self, route: ProxyRoute) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// 监听地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 路由数量
+    pub fn route_count(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// 构建 Axum 路由
+    pub fn build_router(&self) -> Router {
+        Router::new()
+            .route("/v1/models", get(list_models))
+            .route("/v1/chat/completions", post(chat_completions))
+            .with_state(Arc::new(self.routes.clone()))
+    }
+
+    /// 启动服务
+    pub async fn start(&self) -> crate::Result<()> {
+        let listener = tokio::net::TcpListener::bind(self.addr).await?;
+        axum::serve(listener, self.build_router()).await?;
+        Ok(())
+    }
+}
+
+type Routes = Arc<Vec<ProxyRoute>>;
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    let kind = match status {
+        StatusCode::UNAUTHORIZED => "invalid_api_key",
+        StatusCode::NOT_FOUND => "model_not_found",
+        StatusCode::BAD_REQUEST => "invalid_request_error",
+        _ => "upstream_error",
+    };
+    (
+        status,
+        Json(json!({ "error": { "message": message.into(), "type": kind } })),
+    )
+        .into_response()
+}
+
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get("authorization")?.to_str().ok()?;
+    value.strip_prefix("Bearer ").map(str::trim)
+}
+
+/// 按密钥与模型选择路由，返回路由与上游模型名
+fn select_route<'a>(
+    routes: &'a [ProxyRoute],
+    key: &str,
+    model: &str,
+) -> Result<(&'a ProxyRoute, String), (StatusCode, String)> {
+    let allowed: Vec<&ProxyRoute> = routes.iter().filter(|r| r.accepts(key)).collect();
+    if allowed.is_empty() {
+        return Err((StatusCode::UNAUTHORIZED, "invalid API key".to_string()));
+    }
+    if let Some((name, upstream_model)) = model.split_once('/') {
+        if let Some(route) = allowed.iter().find(|r| r.name == name) {
+            return Ok((route, upstream_model.to_string()));
+        }
+    }
+    if let Some(route) = allowed.iter().find(|r| r.models().iter().any(|m| m == model)) {
+        return Ok((route, model.to_string()));
+    }
+    match allowed.as_slice() {
+        [only] => Ok((only, model.to_string())),
+        _ => Err((
+            StatusCode::NOT_FOUND,
+            format!("model {} is not served by any route for this key", model),
+        )),
+    }
+}
+
+async fn list_models(State(routes): State<Routes>, headers: HeaderMap) -> Response {
+    let Some(key) = bearer(&headers) else {
+        return error_response(StatusCode::UNAUTHORIZED, "missing API key");
+    };
+    let data: Vec<Value> = routes
+        .iter()
+        .filter(|r| r.accepts(key))
+        .flat_map(|route| {
+            route.models().into_iter().map(move |model| {
+                json!({
+                    "id": format!("{}/{}", route.name, model),
+                    "object": "model",
+                    "owned_by": route.provider.id(),
+                })
+            })
+        })
+        .collect();
+    Json(json!({ "object": "list", "data": data })).into_response()
+}
+
+/// OpenAI Chat Completions 请求转为原语
+fn to_primitive(body: &Value, model: String) -> Result<PrimitiveRequest, String> {
+    let messages = body["messages"]
+        .as_array()
+        .ok_or("messages must be an array")?;
+    let mut request = PrimitiveRequest::new(model);
+    for message in messages {
+        let text = match &message["content"] {
+            Value::String(text) => text.clone(),
+            Value::Array(parts) => parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        };
+        let role = match message["role"].as_str() {
+            Some("system") | Some("developer") => {
+                request.system = Some(match request.system.take() {
+                    Some(existing) => format!("{}\n\n{}", existing, text),
+                    None => text,
+                });
+                continue;
+            }
+            Some("assistant") => Role::Assistant,
+            _ => Role::User,
+        };
+        request.messages.push(PrimitiveMessage {
+            role,
+            content: vec![PrimitiveContent::text(text)],
+        });
+    }
+
+    let params = &mut request.parameters;
+    params.max_tokens = body["max_completion_tokens"]
+        .as_u64()
+        .or_else(|| body["max_tokens"].as_u64());
+    params.temperature = body["temperature"].as_f64().map(|v| v as f32);
+    params.top_p = body["top_p"].as_f64().map(|v| v as f32);
+    params.stop_sequences = match &body["stop"] {
+        Value::String(stop) => Some(vec![stop.clone()]),
+        Value::Array(stops) => Some(stops.iter().filter_map(|s| s.as_str().map(String::from)).collect()),
+        _ => None,
+    };
+    Ok(request)
+}
+
+fn finish_reason(reason: StopReason) -> &'static str {
+    match reason {
+        StopReason::EndTurn | StopReason::StopSequence => "stop",
+        StopReason::ToolUse => "tool_calls",
+        StopReason::MaxTokens => "length",
+    }
+}
+
+fn usage_json(usage: &Usage) -> Value {
+    json!({
+        "prompt_tokens": usage.input_tokens,
+        "completion_tokens": usage.output_tokens + usage.thinking_tokens.unwrap_or(0),
+        "total_tokens": usage.total_tokens(),
+    })
+}
+
+async fn chat_completions(
+    State(routes): State<Routes>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let Some(key) = bearer(&headers) else {
+        return error_response(StatusCode::UNAUTHORIZED, "missing API key");
+    };
+    let requested_model = body["model"].as_str().unwrap_or_default().to_string();
+    let (route, model) = match select_route(&routes, key, &requested_model) {
+        Ok(selected) => selected,
+        Err((status, message)) => return error_response(status, message),
+    };
+    let primitive = match to_primitive(&body, model) {
+        Ok(primitive) => primitive,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    let provider = route.provider.clone();
+    let compiled = provider.compile(&primitive);
+    let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+    let created = chrono::Utc::now().timestamp();
+
+    if !body["stream"].as_bool().unwrap_or(false) {
+        return match provider.complete(compiled).await {
+            Ok(response) => {
+                let tool_calls: Vec<Value> = response
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": { "name": call.name, "arguments": call.arguments.to_string() },
+                        })
+                    })
+                    .collect();
+                let mut message = json!({ "role": "assistant", "content": response.content });
+                if !tool_calls.is_empty() {
+                    message["tool_calls"] = json!(tool_calls);
+                }
+                Json(json!({
+                    "id": id,
+                    "object": "chat.completion",
+                    "created": created,
+                    "model": requested_model,
+                    "choices": [{
+                        "index": 0,
+                        "message": message,
+                        "finish_reason": finish_reason(response.stop_reason),
+                    }],
+                    "usage": usage_json(&response.usage),
+                }))
+                .into_response()
+            }
+            Err(e) => error_response(StatusCode::BAD_GATEWAY, e.to_string()),
+        };
+    }
+
+    let chunk = move |delta: Value, finish: Option<&str>, usage: Option<Value>| {
+        let mut chunk = json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": requested_model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish }],
+        });
+        if let Some(usage) = usage {
+            chunk["usage"] = usage;
+        }
+        Event::default().data(chunk.to_string())
+    };
+    let events = async_stream::stream! {
+        let mut upstream = match provider.stream(compiled).await {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                yield Ok::<_, Infallible>(Event::default().data(
+                    json!({ "error": { "message": e.to_string(), "type": "upstream_error" } }).to_string(),
+                ));
+                return;
+            }
+        };
+        yield Ok(chunk(json!({ "role": "assistant" }), None, None));
+        let mut usage = None;
+        while let Some(item) = upstream.next().await {
+            match item {
+                Ok(item) => {
+                    if let Some(u) = &item.usage {
+                        usage = Some(usage_json(u));
+                    }
+                    if let ChunkDelta::Text(text) = item.delta {
+                        if !text.is_empty() {
+                            yield Ok(chunk(json!({ "content": text }), None, None));
+                        }
+                    }
+                }
+                Err(e) => {
+                    yield Ok(Event::default().data(
+                        json!({ "error": { "message": e.to_string(), "type": "upstream_error" } }).to_string(),
+                    ));
+                    return;
+                }
+            }
+        }
+        yield Ok(chunk(json!({}), Some("stop"), usage));
+        yield Ok(Event::default().data("[DONE]"));
+    };
+    Sse::new(events).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockProvider;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn call(router: &Router, key: &str, body: Value) -> (StatusCode, String) {
+        let request = Request::post("/v1/chat/completions")
+            .header("authorization", format!("Bearer {}", key))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_routing_and_keys() {
+        let server = ProxyServer::new(default_proxy_addr())
+            .with_route(
+                ProxyRoute::new("vertex", Arc::new(MockProvider::new("vertex").with_response("from vertex")))
+                    .with_key("key-a")
+                    .with_model("gemini-2.5-pro"),
+            )
+            .with_route(
+                ProxyRoute::new("iflow", Arc::new(MockProvider::new("iflow").with_response("from iflow")))
+                    .with_key("key-a")
+                    .with_key("key-b")
+                    .with_model("qwen3-max"),
+            );
+        let router = server.build_router();
+        let messages = json!([{ "role": "system", "content": "be brief" }, { "role": "user", "content": "hi" }]);
+
+        let (status, body) = call(&router, "key-a", json!({ "model": "gemini-2.5-pro", "messages": messages })).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "from vertex");
+        assert_eq!(body["model"], "gemini-2.5-pro");
+
+        let (_, body) = call(&router, "key-a", json!({ "model": "iflow/anything", "messages": messages })).await;
+        assert!(body.contains("from iflow"));
+
+        // key-b 只能访问 iflow
+        let (status, _) = call(&router, "key-b", json!({ "model": "vertex/gemini-2.5-pro", "messages": messages })).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&router, "nope", json!({ "model": "qwen3-max", "messages": messages })).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = call(
+            &router,
+            "key-b",
+            json!({ "model": "qwen3-max", "messages": messages, "stream": true }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("\"content\":\"from iflow\""));
+        assert!(body.trim_end().ends_with("data: [DONE]"));
+    }
+}
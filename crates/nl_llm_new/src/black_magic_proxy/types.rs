@@ -31,8 +31,9 @@ impl ProxyMessage {
     }
 }
 
-/// 目标代理类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// 目标代理类型（序列化为名称，非内置名称视为自定义代理）
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum BlackMagicProxyTarget {
     CliProxyApi,
     NewApi,
@@ -46,6 +47,67 @@ pub enum BlackMagicProxyTarget {
     GoogleAIStudio,
     /// Vertex Compat - 第三方转发站代理（如 zenmux.ai）
     VertexCompat,
+    /// 用户目录中登记的自定义代理
+    Custom(String),
+}
+
+impl BlackMagicProxyTarget {
+    /// 内置代理类型
+    pub const BUILTIN: [BlackMagicProxyTarget; 10] = [
+        BlackMagicProxyTarget::CliProxyApi,
+        BlackMagicProxyTarget::NewApi,
+        BlackMagicProxyTarget::CcSwitch,
+        BlackMagicProxyTarget::ClaudeCodeRouter,
+        BlackMagicProxyTarget::IFlow,
+        BlackMagicProxyTarget::Antigravity,
+        BlackMagicProxyTarget::GeminiCli,
+        BlackMagicProxyTarget::Vertex,
+        BlackMagicProxyTarget::GoogleAIStudio,
+        BlackMagicProxyTarget::VertexCompat,
+    ];
+
+    /// 名称
+    pub fn name(&self) -> &str {
+        match self {
+            BlackMagicProxyTarget::CliProxyApi => "CliProxyApi",
+            BlackMagicProxyTarget::NewApi => "NewApi",
+            BlackMagicProxyTarget::CcSwitch => "CcSwitch",
+            BlackMagicProxyTarget::ClaudeCodeRouter => "ClaudeCodeRouter",
+            BlackMagicProxyTarget::IFlow => "IFlow",
+            BlackMagicProxyTarget::Antigravity => "Antigravity",
+            BlackMagicProxyTarget::GeminiCli => "GeminiCli",
+            BlackMagicProxyTarget::Vertex => "Vertex",
+            BlackMagicProxyTarget::GoogleAIStudio => "GoogleAIStudio",
+            BlackMagicProxyTarget::VertexCompat => "VertexCompat",
+            BlackMagicProxyTarget::Custom(name) => name,
+        }
+    }
+
+    /// 是否为自定义代理
+    pub fn is_custom(&self) -> bool {
+        matches!(self, BlackMagicProxyTarget::Custom(_))
+    }
+}
+
+impl From<String> for BlackMagicProxyTarget {
+    fn from(name: String) -> Self {
+        Self::BUILTIN
+            .into_iter()
+            .find(|target| target.name() == name)
+            .unwrap_or(BlackMagicProxyTarget::Custom(name))
+    }
+}
+
+impl From<BlackMagicProxyTarget> for String {
+    fn from(target: BlackMagicProxyTarget) -> Self {
+        target.name().to_string()
+    }
+}
+
+impl std::fmt::Display for BlackMagicProxyTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
 }
 
 /// 反代接口形态
@@ -66,18 +128,25 @@ pub enum ProxyExposureKind {
 pub struct ProxyExposure {
     pub kind: ProxyExposureKind,
     /// endpoint path 或 ws path；CLI 下可留空
+    #[serde(default)]
     pub path: String,
     /// method（HTTP 使用），WS/CLI 可忽略
+    #[serde(default)]
     pub method: String,
     /// 鉴权头（如 Authorization / x-api-key）
+    #[serde(default)]
     pub auth_header: Option<String>,
     /// 鉴权前缀（如 Bearer ）
+    #[serde(default)]
     pub auth_prefix: Option<String>,
     /// CLI 命令（仅 CLI 模式）
+    #[serde(default)]
     pub cli_command: Option<String>,
     /// CLI 参数（仅 CLI 模式）
+    #[serde(default)]
     pub cli_args: Vec<String>,
     /// 备注
+    #[serde(default)]
     pub notes: String,
 }
 
@@ -85,8 +154,10 @@ pub struct ProxyExposure {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlackMagicProxySpec {
     pub target: BlackMagicProxyTarget,
+    #[serde(default)]
     pub default_base_url: String,
     pub exposures: Vec<ProxyExposure>,
+    #[serde(default)]
     pub notes: String,
 }
 
@@ -0,0 +1,142 @@
+//! 单 Provider 并发上限
+//!
+//! 部分 Provider 不论 token 多少都只允许少量并发请求（如同时 2 个流），超出即报限流错误。
+//! 按 Provider 配置并发上限（`GatewayConfig::max_concurrent`），Gateway 在全局令牌桶与 Provider QPS 桶之外，
+//! 再用信号量限制同时进行的请求；未配置上限的 Provider 不受限。
+//! 等待许可的请求数与等待时长计入 `ConcurrencyStats`，用于判断上限是否过紧。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 单个 Provider 的并发统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConcurrencyStats {
+    pub provider: String,
+    /// 并发上限
+    pub limit: usize,
+    /// 进行中的请求数
+    pub in_flight: usize,
+    /// 正在排队等待许可的请求数
+    pub waiting: u64,
+    /// 累计获得许可的请求数
+    pub acquired: u64,
+    /// 累计等待时长（毫秒）
+    pub total_wait_ms: u64,
+    /// 最长单次等待（毫秒）
+    pub max_wait_ms: u64,
+}
+
+struct ProviderSlots {
+    limit: usize,
+    semaphore: Arc<Semaphore>,
+    waiting: AtomicU64,
+    acquired: AtomicU64,
+    total_wait_ms: AtomicU64,
+    max_wait_ms: AtomicU64,
+}
+
+/// 按 Provider 的并发限制器
+#[derive(Default)]
+pub struct ConcurrencyLimiter {
+    slots: RwLock<HashMap<String, Arc<ProviderSlots>>>,
+}
+
+impl ConcurrencyLimiter {
+    /// 按 Provider ID 到并发上限的映射创建
+    pub fn new(limits: &HashMap<String, usize>) -> Self {
+        let limiter = Self::default();
+        for (provider, limit) in limits {
+            limiter.set_limit(provider, *limit);
+        }
+        limiter
+    }
+
+    /// 设置（或替换）Provider 的并发上限；替换前已获得的许可不受影响，统计重新开始
+    pub fn set_limit(&self, provider: &str, limit: usize) {
+        let limit = limit.max(1);
+        let slots = ProviderSlots {
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit)),
+            waiting: AtomicU64::new(0),
+            acquired: AtomicU64::new(0),
+            total_wait_ms: AtomicU64::new(0),
+            max_wait_ms: AtomicU64::new(0),
+        };
+        self.slots
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(provider.to_string(), Arc::new(slots));
+    }
+
+    /// 等待 Provider 的并发许可，许可在返回值释放时归还；未配置上限时立即返回 `None`
+    pub async fn acquire(&self, provider: &str) -> Option<OwnedSemaphorePermit> {
+        let slots = self.slots.read().unwrap_or_else(|e| e.into_inner()).get(provider).cloned()?;
+        let started = Instant::now();
+        slots.waiting.fetch_add(1, Ordering::Relaxed);
+        let permit = slots.semaphore.clone().acquire_owned().await;
+        slots.waiting.fetch_sub(1, Ordering::Relaxed);
+
+        let waited = started.elapsed().as_millis() as u64;
+        slots.acquired.fetch_add(1, Ordering::Relaxed);
+        slots.total_wait_ms.fetch_add(waited, Ordering::Relaxed);
+        slots.max_wait_ms.fetch_max(waited, Ordering::Relaxed);
+        if waited > 0 {
+            tracing::debug!("waited {} ms for a concurrency slot on {}", waited, provider);
+        }
+        // 信号量从不关闭
+        permit.ok()
+    }
+
+    /// 各 Provider 的统计（按 Provider 排序）
+    pub fn stats(&self) -> Vec<ConcurrencyStats> {
+        let slots = self.slots.read().unwrap_or_else(|e| e.into_inner());
+        let mut stats: Vec<ConcurrencyStats> = slots
+            .iter()
+            .map(|(provider, slots)| ConcurrencyStats {
+                provider: provider.clone(),
+                limit: slots.limit,
+                in_flight: slots.limit - slots.semaphore.available_permits(),
+                waiting: slots.waiting.load(Ordering::Relaxed),
+                acquired: slots.acquired.load(Ordering::Relaxed),
+                total_wait_ms: slots.total_wait_ms.load(Ordering::Relaxed),
+                max_wait_ms: slots.max_wait_ms.load(Ordering::Relaxed),
+            })
+            .collect();
+        stats.sort_by(|a, b| a.provider.cmp(&b.provider));
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_limit_queues_requests_and_records_wait() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(&HashMap::from([("slow".to_string(), 2)])));
+        assert!(limiter.acquire("other").await.is_none());
+
+        let first = limiter.acquire("slow").await.unwrap();
+        let _second = limiter.acquire("slow").await.unwrap();
+        let queued = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire("slow").await.is_some() }
+        });
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let stats = limiter.stats();
+        assert_eq!((stats[0].in_flight, stats[0].waiting, stats[0].acquired), (2, 1, 2));
+
+        drop(first);
+        assert!(queued.await.unwrap());
+        let stats = &limiter.stats()[0];
+        assert_eq!((stats.provider.as_str(), stats.limit, stats.waiting, stats.acquired), ("slow", 2, 0, 3));
+        assert!(stats.max_wait_ms >= 20);
+        assert_eq!(stats.in_flight, 1);
+    }
+}
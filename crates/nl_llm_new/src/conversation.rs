@@ -0,0 +1,463 @@
+//! 对话分支
+//!
+//! 在某一轮分叉出新分支，用不同的指令探索另一种回答：
+//! - 分支记录父分支与分叉位置，共享父分支在分叉点之前的历史；系统提示词属于整个对话，
+//!   各分支的静态前缀相同，因此共享前缀缓存
+//! - 每个分支使用独立的会话句柄（主分支即对话 ID，其他分支为 `<对话>/<分支>`），服务端上下文互不干扰
+//! - 合并：父分支在分叉点之后的历史被替换为该分支的消息，该分支的子分支改挂到父分支；
+//!   父分支在分叉点之后还有其他未结束的分支时拒绝合并
+//! - 丢弃：分支不再接受新消息，其子分支须先处理
+//!
+//! 创建、追加、合并与丢弃都作为事件（实体为对话 ID）写入事件库，`SessionManager` 按事件重放恢复对话。
+//!
+//! 对话可以引用多语言系统提示词模板（`start_with_prompt`）：每次发送时检测用户消息的语言区域
+//! （检测不出时沿用对话上一次的区域），取模板的对应语言版本作为系统提示词，区域记在消息事件上。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use nl_core::event::{Event, EventKind};
+use nl_durable::EventStore;
+
+use crate::gateway::Gateway;
+use crate::locale::{Locale, LocalizedPrompts};
+use crate::primitive::{PrimitiveMessage, PrimitiveRequest};
+use crate::provider::local::template::message_text;
+use crate::provider::LlmResponse;
+use crate::scheduler::Priority;
+
+/// 主分支名
+pub const MAIN_BRANCH: &str = "main";
+
+/// 分支状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BranchStatus {
+    Active,
+    /// 已合并到父分支
+    Merged { into: String },
+    Discarded,
+}
+
+/// 对话分支
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationBranch {
+    pub name: String,
+    /// 父分支（主分支为空）
+    pub parent: Option<String>,
+    /// 分叉位置：共享父分支历史的前若干条消息
+    pub fork_turn: usize,
+    /// 分叉后本分支自己的消息
+    pub turns: Vec<PrimitiveMessage>,
+    pub status: BranchStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 一个对话及其全部分支
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub id: Uuid,
+    /// 系统提示词（所有分支共享）
+    pub system: Option<String>,
+    /// 多语言系统提示词模板名，发送时取对应语言版本替代 `system`
+    pub prompt: Option<String>,
+    /// 最近一条消息的语言区域
+    pub locale: Option<Locale>,
+    pub branches: BTreeMap<String, ConversationBranch>,
+}
+
+impl Conversation {
+    /// 从事件重放（没有创建事件时为空）
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a Event>) -> Option<Self> {
+        let mut conversation: Option<Self> = None;
+        for event in events {
+            match (&mut conversation, &event.kind) {
+                (None, EventKind::ConversationBranched) => {
+                    let mut created = Self {
+                        id: event.entity_id,
+                        system: event.payload["system"].as_str().map(String::from),
+                        prompt: event.payload["prompt"].as_str().map(String::from),
+                        locale: None,
+                        branches: BTreeMap::new(),
+                    };
+                    created.apply(event);
+                    conversation = Some(created);
+                }
+                (Some(conversation), _) => conversation.apply(event),
+                _ => {}
+            }
+        }
+        conversation
+    }
+
+    fn apply(&mut self, event: &Event) {
+        let payload = &event.payload;
+        let Some(name) = payload["branch"].as_str() else {
+            return;
+        };
+        match event.kind {
+            EventKind::ConversationBranched => {
+                let branch = ConversationBranch {
+                    name: name.to_string(),
+                    parent: payload["parent"].as_str().map(String::from),
+                    fork_turn: payload["fork_turn"].as_u64().unwrap_or(0) as usize,
+                    turns: Vec::new(),
+                    status: BranchStatus::Active,
+                    created_at: event.timestamp,
+                };
+                self.branches.insert(name.to_string(), branch);
+            }
+            EventKind::ConversationTurn => {
+                if let Ok(locale) = serde_json::from_value(payload["locale"].clone()) {
+                    self.locale = Some(locale);
+                }
+                let message = serde_json::from_value(payload["message"].clone());
+                if let (Some(branch), Ok(message)) = (self.branches.get_mut(name), message) {
+                    branch.turns.push(message);
+                }
+            }
+            EventKind::ConversationBranchMerged => self.apply_merge(name),
+            EventKind::ConversationBranchDiscarded => {
+                if let Some(branch) = self.branches.get_mut(name) {
+                    branch.status = BranchStatus::Discarded;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_merge(&mut self, name: &str) {
+        let Some(branch) = self.branches.get(name).cloned() else {
+            return;
+        };
+        let Some(parent_name) = branch.parent.clone() else {
+            return;
+        };
+        let Some(parent) = self.branches.get_mut(&parent_name) else {
+            return;
+        };
+        if branch.fork_turn >= parent.fork_turn {
+            parent.turns.truncate(branch.fork_turn - parent.fork_turn);
+            parent.turns.extend(branch.turns.iter().cloned());
+        } else {
+            parent.fork_turn = branch.fork_turn;
+            parent.turns = branch.turns.clone();
+        }
+        // 合并后父分支的完整历史与该分支相同，子分支的分叉位置不变
+        for child in self.branches.values_mut().filter(|b| b.parent.as_deref() == Some(name)) {
+            child.parent = Some(parent_name.clone());
+        }
+        if let Some(merged) = self.branches.get_mut(name) {
+            merged.status = BranchStatus::Merged { into: parent_name };
+        }
+    }
+
+    /// 查找分支
+    pub fn branch(&self, name: &str) -> crate::Result<&ConversationBranch> {
+        self.branches
+            .get(name)
+            .ok_or_else(|| crate::Error::Unknown(format!("conversation {} has no branch {}", self.id, name)))
+    }
+
+    fn active_branch(&self, name: &str) -> crate::Result<&ConversationBranch> {
+        let branch = self.branch(name)?;
+        match &branch.status {
+            BranchStatus::Active => Ok(branch),
+            status => Err(crate::Error::Unknown(format!("branch {} is no longer active ({:?})", name, status))),
+        }
+    }
+
+    /// 分支的完整历史：父分支分叉点之前的消息 + 本分支的消息
+    pub fn history(&self, name: &str) -> crate::Result<Vec<PrimitiveMessage>> {
+        let branch = self.branch(name)?;
+        let mut history = match &branch.parent {
+            Some(parent) => {
+                let mut inherited = self.history(parent)?;
+                inherited.truncate(branch.fork_turn);
+                inherited
+            }
+            None => Vec::new(),
+        };
+        history.extend(branch.turns.iter().cloned());
+        Ok(history)
+    }
+
+    /// 分支的血缘：从该分支到主分支
+    pub fn lineage(&self, name: &str) -> Vec<&str> {
+        let mut lineage = Vec::new();
+        let mut current = self.branches.get(name);
+        while let Some(branch) = current {
+            lineage.push(branch.name.as_str());
+            current = branch.parent.as_deref().and_then(|parent| self.branches.get(parent));
+        }
+        lineage
+    }
+
+    /// 分支的会话句柄键（供 `SessionStore` / `Gateway::complete_in_conversation` 使用）
+    pub fn session_key(&self, name: &str) -> String {
+        if name == MAIN_BRANCH {
+            self.id.to_string()
+        } else {
+            format!("{}/{}", self.id, name)
+        }
+    }
+
+    /// 以分支历史构造请求
+    pub fn request(&self, name: &str, model: impl Into<String>) -> crate::Result<PrimitiveRequest> {
+        let mut request = PrimitiveRequest::new(model);
+        request.system = self.system.clone();
+        request.messages = self.history(name)?;
+        Ok(request)
+    }
+}
+
+/// 对话会话管理：分支的创建、追加、分叉、合并与丢弃，全部以事件持久化
+pub struct SessionManager {
+    store: Arc<Mutex<EventStore>>,
+    conversations: Mutex<HashMap<Uuid, Conversation>>,
+    prompts: LocalizedPrompts,
+}
+
+impl SessionManager {
+    /// 使用事件库
+    pub fn new(store: Arc<Mutex<EventStore>>) -> Self {
+        Self {
+            store,
+            conversations: Mutex::new(HashMap::new()),
+            prompts: LocalizedPrompts::default(),
+        }
+    }
+
+    /// 设置多语言系统提示词模板
+    pub fn with_prompts(mut self, prompts: LocalizedPrompts) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// 开始新对话（只有主分支）
+    pub async fn start(&self, system: Option<String>) -> crate::Result<Uuid> {
+        self.create(system, None).await
+    }
+
+    /// 开始使用多语言系统提示词模板的新对话
+    pub async fn start_with_prompt(&self, prompt: &str) -> crate::Result<Uuid> {
+        if self.prompts.get(prompt, None).is_none() {
+            return Err(crate::Error::Unknown(format!("prompt template {} not found", prompt)));
+        }
+        self.create(None, Some(prompt)).await
+    }
+
+    async fn create(&self, system: Option<String>, prompt: Option<&str>) -> crate::Result<Uuid> {
+        let id = Uuid::new_v4();
+        let payload = serde_json::json!({
+            "branch": MAIN_BRANCH,
+            "parent": null,
+            "fork_turn": 0,
+            "system": system,
+            "prompt": prompt,
+        });
+        self.record(id, EventKind::ConversationBranched, payload).await?;
+        Ok(id)
+    }
+
+    /// 获取对话（首次访问时从事件库重放）
+    pub async fn conversation(&self, id: Uuid) -> crate::Result<Conversation> {
+        if let Some(conversation) = self.conversations.lock().await.get(&id) {
+            return Ok(conversation.clone());
+        }
+        let events = self.store.lock().await.get_events(id).await.map_err(store_error)?;
+        let conversation = Conversation::from_events(&events)
+            .ok_or_else(|| crate::Error::Unknown(format!("conversation {} not found", id)))?;
+        self.conversations.lock().await.insert(id, conversation.clone());
+        Ok(conversation)
+    }
+
+    /// 向分支追加一条消息
+    pub async fn append(&self, id: Uuid, branch: &str, message: PrimitiveMessage) -> crate::Result<()> {
+        self.append_turn(id, branch, message, None).await
+    }
+
+    async fn append_turn(
+        &self,
+        id: Uuid,
+        branch: &str,
+        message: PrimitiveMessage,
+        locale: Option<Locale>,
+    ) -> crate::Result<()> {
+        self.conversation(id).await?.active_branch(branch)?;
+        let mut payload = serde_json::json!({ "branch": branch, "message": message });
+        if let Some(locale) = locale {
+            payload["locale"] = serde_json::json!(locale);
+        }
+        self.record(id, EventKind::ConversationTurn, payload).await
+    }
+
+    /// 在分支的前 `at_turn` 条消息处分叉出新分支
+    pub async fn fork(&self, id: Uuid, from: &str, at_turn: usize, name: &str) -> crate::Result<()> {
+        let conversation = self.conversation(id).await?;
+        conversation.branch(from)?;
+        if name.trim().is_empty() || name.contains('/') {
+            return Err(crate::Error::Unknown(format!("invalid branch name: {:?}", name)));
+        }
+        if conversation.branches.contains_key(name) {
+            return Err(crate::Error::Unknown(format!("branch {} already exists", name)));
+        }
+        let length = conversation.history(from)?.len();
+        if at_turn > length {
+            return Err(crate::Error::Unknown(format!(
+                "branch {} has only {} messages, cannot fork at {}",
+                from, length, at_turn
+            )));
+        }
+        let payload = serde_json::json!({ "branch": name, "parent": from, "fork_turn": at_turn });
+        self.record(id, EventKind::ConversationBranched, payload).await
+    }
+
+    /// 把分支合并回父分支
+    pub async fn merge(&self, id: Uuid, branch: &str) -> crate::Result<()> {
+        let conversation = self.conversation(id).await?;
+        let merged = conversation.active_branch(branch)?;
+        let Some(parent) = &merged.parent else {
+            return Err(crate::Error::Unknown(format!("branch {} has no parent to merge into", branch)));
+        };
+        conversation.active_branch(parent)?;
+        let blocking = conversation.branches.values().find(|b| {
+            b.name != branch
+                && b.parent.as_deref() == Some(parent.as_str())
+                && b.fork_turn > merged.fork_turn
+                && b.status == BranchStatus::Active
+        });
+        if let Some(blocking) = blocking {
+            return Err(crate::Error::Unknown(format!(
+                "branch {} forks from {} after turn {}; merge or discard it first",
+                blocking.name, parent, merged.fork_turn
+            )));
+        }
+        let payload = serde_json::json!({ "branch": branch, "into": parent });
+        self.record(id, EventKind::ConversationBranchMerged, payload).await
+    }
+
+    /// 丢弃分支（主分支不能丢弃）
+    pub async fn discard(&self, id: Uuid, branch: &str) -> crate::Result<()> {
+        let conversation = self.conversation(id).await?;
+        conversation.active_branch(branch)?;
+        if branch == MAIN_BRANCH {
+            return Err(crate::Error::Unknown("the main branch cannot be discarded".to_string()));
+        }
+        let children = conversation.branches.values().find(|b| {
+            b.parent.as_deref() == Some(branch) && b.status == BranchStatus::Active
+        });
+        if let Some(child) = children {
+            return Err(crate::Error::Unknown(format!(
+                "branch {} still has active child branch {}",
+                branch, child.name
+            )));
+        }
+        let payload = serde_json::json!({ "branch": branch });
+        self.record(id, EventKind::ConversationBranchDiscarded, payload).await
+    }
+
+    /// 在分支上发送一条用户消息：以分支历史请求 Gateway，回复追加到分支；消息与回复都记录语言区域
+    pub async fn send(
+        &self,
+        gateway: &Gateway,
+        id: Uuid,
+        branch: &str,
+        message: PrimitiveMessage,
+        model: &str,
+        priority: Priority,
+    ) -> crate::Result<LlmResponse> {
+        let locale = Locale::detect(&message_text(&message)).or(self.conversation(id).await?.locale);
+        self.append_turn(id, branch, message, locale).await?;
+        let conversation = self.conversation(id).await?;
+        let mut request = conversation.request(branch, model)?;
+        if let Some(system) = conversation.prompt.as_deref().and_then(|p| self.prompts.get(p, locale)) {
+            request.system = Some(system.to_string());
+        }
+        request.metadata.locale = locale;
+        let response = gateway
+            .complete_in_conversation(&conversation.session_key(branch), &request, priority)
+            .await?;
+        self.append_turn(id, branch, PrimitiveMessage::assistant(response.content.clone()), locale)
+            .await?;
+        Ok(response)
+    }
+
+    /// 写入事件并更新缓存中的对话
+    async fn record(&self, id: Uuid, kind: EventKind, payload: serde_json::Value) -> crate::Result<()> {
+        let event = Event::new(kind, id, payload);
+        self.store.lock().await.append(event.clone()).await.map_err(store_error)?;
+        let mut conversations = self.conversations.lock().await;
+        match conversations.get_mut(&id) {
+            Some(conversation) => conversation.apply(&event),
+            None => {
+                if let Some(conversation) = Conversation::from_events([&event]) {
+                    conversations.insert(id, conversation);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn store_error(e: nl_core::NeuroLoomError) -> crate::Error {
+    crate::Error::Unknown(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::PrimitiveContent;
+
+    fn texts(messages: &[PrimitiveMessage]) -> Vec<String> {
+        messages
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|c| match c {
+                PrimitiveContent::Text { text } => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_fork_merge_and_replay() {
+        let store = Arc::new(Mutex::new(EventStore::new(Default::default())));
+        let manager = SessionManager::new(store.clone());
+        let id = manager.start(Some("be terse".to_string())).await.unwrap();
+        for (i, text) in ["q1", "a1", "q2", "a2"].into_iter().enumerate() {
+            let message = if i % 2 == 0 {
+                PrimitiveMessage::user(text)
+            } else {
+                PrimitiveMessage::assistant(text)
+            };
+            manager.append(id, MAIN_BRANCH, message).await.unwrap();
+        }
+
+        manager.fork(id, MAIN_BRANCH, 2, "alt").await.unwrap();
+        manager.append(id, "alt", PrimitiveMessage::user("q2 but shorter")).await.unwrap();
+        manager.fork(id, "alt", 3, "deeper").await.unwrap();
+        let conversation = manager.conversation(id).await.unwrap();
+        assert_eq!(texts(&conversation.history("alt").unwrap()), ["q1", "a1", "q2 but shorter"]);
+        assert_eq!(conversation.lineage("deeper"), ["deeper", "alt", "main"]);
+        assert_eq!(conversation.session_key("alt"), format!("{}/alt", id));
+        assert!(manager.fork(id, MAIN_BRANCH, 9, "too-far").await.is_err());
+
+        manager.merge(id, "alt").await.unwrap();
+        assert!(manager.append(id, "alt", PrimitiveMessage::user("late")).await.is_err());
+        assert!(manager.discard(id, MAIN_BRANCH).await.is_err());
+        manager.discard(id, "deeper").await.unwrap();
+
+        // 新的管理器从事件库重放得到相同的对话
+        let replayed = SessionManager::new(store).conversation(id).await.unwrap();
+        assert_eq!(texts(&replayed.history(MAIN_BRANCH).unwrap()), ["q1", "a1", "q2 but shorter"]);
+        assert_eq!(replayed.branches["deeper"].parent.as_deref(), Some(MAIN_BRANCH));
+        assert_eq!(replayed.branches["deeper"].status, BranchStatus::Discarded);
+        assert_eq!(replayed.request("main", "m").unwrap().system.as_deref(), Some("be terse"));
+    }
+}
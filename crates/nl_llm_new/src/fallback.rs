@@ -1,6 +1,22 @@
 //! 降级路由
+//!
+//! 除顺序降级链外，支持交互场景的 `race` 竞速策略：
+//! 同一请求同时发给快速模型与强模型，先流式输出快速模型的结果；
+//! 强模型输出足够长度后比较两者，差异过大则切换到强模型的回答，
+//! 输掉的一方立即取消以节省 token。
+//! 延迟持续超标的 Provider 由 SLO 跟踪降级（`demote`），在降级链与分发顺序中排到末尾，恢复后回到原位。
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use futures::StreamExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::primitive::PrimitiveRequest;
+use crate::provider::{BoxStream, ChunkDelta, LlmChunk, LlmProvider, Usage};
 
 /// 降级配置
 #[derive(Debug, Clone)]
@@ -11,6 +27,8 @@ pub struct FallbackConfig {
     pub provider_chains: HashMap<String, Vec<String>>,
     /// 是否在降级时记录日志
     pub log_fallback: bool,
+    /// 竞速策略（交互请求）
+    pub race: Option<RacePolicy>,
 }
 
 impl Default for FallbackConfig {
@@ -23,6 +41,7 @@ impl Default for FallbackConfig {
             ],
             provider_chains: HashMap::new(),
             log_fallback: true,
+            race: None,
         }
     }
 }
@@ -30,28 +49,61 @@ impl Default for FallbackConfig {
 /// 降级路由器
 pub struct FallbackRouter {
     config: FallbackConfig,
+    demoted: RwLock<HashSet<String>>,
 }
 
 impl FallbackRouter {
     /// 创建新的降级路由器
     pub fn new(config: FallbackConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            demoted: RwLock::new(HashSet::new()),
+        }
     }
 
-    /// 获取指定 Provider 的降级链
+    /// 获取指定 Provider 的降级链（已降级的 Provider 排在末尾）
     pub fn get_fallback_chain(&self, provider_id: &str) -> Vec<String> {
         // 先查找定制降级链
         if let Some(chain) = self.config.provider_chains.get(provider_id) {
-            return chain.clone();
+            return self.order(chain.clone());
         }
 
         // 返回默认降级链（排除当前 Provider）
-        self.config
-            .default_chain
-            .iter()
-            .filter(|id| *id != provider_id)
-            .cloned()
-            .collect()
+        self.order(
+            self.config
+                .default_chain
+                .iter()
+                .filter(|id| *id != provider_id)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// 把已降级的 Provider 移到末尾，其余保持原有相对顺序
+    pub fn order(&self, providers: Vec<String>) -> Vec<String> {
+        let demoted = self.demoted.read().unwrap();
+        if demoted.is_empty() {
+            return providers;
+        }
+        let (slow, mut ordered): (Vec<String>, Vec<String>) =
+            providers.into_iter().partition(|id| demoted.contains(id));
+        ordered.extend(slow);
+        ordered
+    }
+
+    /// 降级 Provider，返回此前是否未降级
+    pub fn demote(&self, provider_id: &str) -> bool {
+        self.demoted.write().unwrap().insert(provider_id.to_string())
+    }
+
+    /// 恢复 Provider，返回此前是否已降级
+    pub fn restore(&self, provider_id: &str) -> bool {
+        self.demoted.write().unwrap().remove(provider_id)
+    }
+
+    /// 是否已降级
+    pub fn is_demoted(&self, provider_id: &str) -> bool {
+        self.demoted.read().unwrap().contains(provider_id)
     }
 
     /// 设置默认降级链
@@ -68,6 +120,278 @@ impl FallbackRouter {
     pub fn should_log(&self) -> bool {
         self.config.log_fallback
     }
+
+    /// 设置竞速策略
+    pub fn set_race_policy(&mut self, policy: RacePolicy) {
+        self.config.race = Some(policy);
+    }
+
+    /// 获取竞速策略
+    pub fn race_policy(&self) -> Option<&RacePolicy> {
+        self.config.race.as_ref()
+    }
+}
+
+/// 竞速策略
+#[derive(Debug, Clone)]
+pub struct RacePolicy {
+    /// 快速（廉价）Provider
+    pub fast_provider: String,
+    /// 强（慢速）Provider
+    pub strong_provider: String,
+    /// 快速 Provider 使用的模型（默认沿用请求中的模型）
+    pub fast_model: Option<String>,
+    /// 强 Provider 使用的模型（默认沿用请求中的模型）
+    pub strong_model: Option<String>,
+    /// 比较前缀的字符数
+    pub compare_chars: usize,
+    /// 允许的最大差异 (0.0 ~ 1.0)，超过则切换到强模型
+    pub max_divergence: f64,
+    /// 快速模型结束后等待强模型给出可比较输出的最长时间
+    pub decision_timeout: Duration,
+}
+
+impl RacePolicy {
+    /// 创建竞速策略
+    pub fn new(fast_provider: impl Into<String>, strong_provider: impl Into<String>) -> Self {
+        Self {
+            fast_provider: fast_provider.into(),
+            strong_provider: strong_provider.into(),
+            fast_model: None,
+            strong_model: None,
+            compare_chars: 200,
+            max_divergence: 0.5,
+            decision_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// 分别指定两侧模型
+    pub fn with_models(mut self, fast: impl Into<String>, strong: impl Into<String>) -> Self {
+        self.fast_model = Some(fast.into());
+        self.strong_model = Some(strong.into());
+        self
+    }
+
+    /// 设置比较前缀的字符数
+    pub fn with_compare_chars(mut self, chars: usize) -> Self {
+        self.compare_chars = chars;
+        self
+    }
+
+    /// 设置允许的最大差异
+    pub fn with_max_divergence(mut self, divergence: f64) -> Self {
+        self.max_divergence = divergence;
+        self
+    }
+
+    /// 设置等待强模型的最长时间
+    pub fn with_decision_timeout(mut self, timeout: Duration) -> Self {
+        self.decision_timeout = timeout;
+        self
+    }
+
+    /// 发起竞速，返回面向调用方的事件流
+    ///
+    /// 丢弃事件流会同时取消两侧仍在进行的请求。
+    pub fn run(
+        &self,
+        fast: Arc<dyn LlmProvider>,
+        strong: Arc<dyn LlmProvider>,
+        primitive: &PrimitiveRequest,
+    ) -> BoxStream<'static, crate::Result<RaceEvent>> {
+        let leg_request = |model: &Option<String>| {
+            let mut request = primitive.clone();
+            if let Some(model) = model {
+                request.model = model.clone();
+            }
+            request
+        };
+        let (mut fast_rx, fast_task) = spawn_leg(fast, leg_request(&self.fast_model));
+        let (mut strong_rx, strong_task) = spawn_leg(strong, leg_request(&self.strong_model));
+        let policy = self.clone();
+
+        let stream = async_stream::stream! {
+            let (fast_task, strong_task) = (AbortOnDrop(fast_task), AbortOnDrop(strong_task));
+            let (mut fast_text, mut strong_text) = (String::new(), String::new());
+            let (mut fast_usage, mut strong_usage) = (None, None);
+            let (mut fast_done, mut strong_done) = (false, false);
+            let (mut fast_error, mut strong_error) = (None, None);
+            let mut deadline: Option<Instant> = None;
+
+            // 阶段一：输出快速模型，同时收集强模型的前缀直到能做出判断
+            let strong_wins = loop {
+                let next = tokio::select! {
+                    chunk = fast_rx.recv(), if !fast_done => Leg::Fast(chunk),
+                    chunk = strong_rx.recv(), if !strong_done => Leg::Strong(chunk),
+                    _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => Leg::Timeout,
+                    else => Leg::Timeout,
+                };
+                match next {
+                    Leg::Fast(Some(Ok(chunk))) => {
+                        if let Some(text) = absorb(chunk, &mut fast_text, &mut fast_usage) {
+                            yield Ok(RaceEvent::Delta(text));
+                        }
+                    }
+                    Leg::Fast(Some(Err(e))) => {
+                        fast_done = true;
+                        fast_error = Some(e);
+                    }
+                    Leg::Fast(None) => {
+                        fast_done = true;
+                        deadline = Some(Instant::now() + policy.decision_timeout);
+                    }
+                    Leg::Strong(Some(Ok(chunk))) => {
+                        absorb(chunk, &mut strong_text, &mut strong_usage);
+                    }
+                    Leg::Strong(Some(Err(e))) => {
+                        strong_done = true;
+                        strong_error = Some(e);
+                    }
+                    Leg::Strong(None) => strong_done = true,
+                    Leg::Timeout => break false,
+                }
+
+                if let (Some(_), Some(_)) = (&fast_error, &strong_error) {
+                    yield Err(fast_error.take().unwrap());
+                    return;
+                }
+                if let Some(e) = &fast_error {
+                    tracing::warn!("race: {} failed, switching to {}: {}", policy.fast_provider, policy.strong_provider, e);
+                    break true;
+                }
+                if let Some(e) = &strong_error {
+                    tracing::warn!("race: {} failed, keeping {}: {}", policy.strong_provider, policy.fast_provider, e);
+                    break false;
+                }
+
+                let fast_ready = fast_done || fast_text.chars().count() >= policy.compare_chars;
+                let strong_ready = strong_done || strong_text.chars().count() >= policy.compare_chars;
+                if fast_ready && strong_ready {
+                    let score = divergence(
+                        &prefix(&fast_text, policy.compare_chars),
+                        &prefix(&strong_text, policy.compare_chars),
+                    );
+                    break score > policy.max_divergence;
+                }
+            };
+
+            // 阶段二：取消输家，继续输出赢家
+            let (winner, mut rx, mut usage) = if strong_wins {
+                drop(fast_task);
+                yield Ok(RaceEvent::Switch {
+                    provider: policy.strong_provider.clone(),
+                    text: strong_text.clone(),
+                });
+                (policy.strong_provider.clone(), strong_rx, strong_usage)
+            } else {
+                drop(strong_task);
+                (policy.fast_provider.clone(), fast_rx, fast_usage)
+            };
+            let winner_done = if strong_wins { strong_done } else { fast_done };
+            if !winner_done {
+                let mut text = String::new();
+                while let Some(chunk) = rx.recv().await {
+                    match chunk {
+                        Ok(chunk) => {
+                            if let Some(delta) = absorb(chunk, &mut text, &mut usage) {
+                                yield Ok(RaceEvent::Delta(delta));
+                            }
+                        }
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                }
+            }
+            yield Ok(RaceEvent::Done { winner, usage });
+        };
+        Box::pin(stream)
+    }
+}
+
+/// 竞速事件
+#[derive(Debug, Clone)]
+pub enum RaceEvent {
+    /// 追加文本
+    Delta(String),
+    /// 切换到强模型：以 `text` 替换此前输出的全部内容
+    Switch { provider: String, text: String },
+    /// 结束，`usage` 为胜出方的用量
+    Done { winner: String, usage: Option<Usage> },
+}
+
+enum Leg {
+    Fast(Option<crate::Result<LlmChunk>>),
+    Strong(Option<crate::Result<LlmChunk>>),
+    Timeout,
+}
+
+/// 丢弃时中止任务（即取消对应 Provider 的请求）
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// 在后台任务中流式执行一侧请求
+fn spawn_leg(
+    provider: Arc<dyn LlmProvider>,
+    request: PrimitiveRequest,
+) -> (mpsc::UnboundedReceiver<crate::Result<LlmChunk>>, JoinHandle<()>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let task = tokio::spawn(async move {
+        let body = provider.compile(&request);
+        match provider.stream(body).await {
+            Ok(mut chunks) => {
+                while let Some(chunk) = chunks.next().await {
+                    if tx.send(chunk).is_err() {
+                        break;
+                    }
+                }
+            }
+            Err(e) => {
+                let _ = tx.send(Err(e));
+            }
+        }
+    });
+    (rx, task)
+}
+
+/// 累积文本与用量，返回非空的文本增量
+fn absorb(chunk: LlmChunk, text: &mut String, usage: &mut Option<Usage>) -> Option<String> {
+    if chunk.usage.is_some() {
+        *usage = chunk.usage;
+    }
+    match chunk.delta {
+        ChunkDelta::Text(delta) if !delta.is_empty() => {
+            text.push_str(&delta);
+            Some(delta)
+        }
+        _ => None,
+    }
+}
+
+fn prefix(text: &str, chars: usize) -> String {
+    text.chars().take(chars).collect()
+}
+
+/// 两段文本的词集合差异 (1 - Jaccard 相似度)
+fn divergence(a: &str, b: &str) -> f64 {
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    1.0 - a.intersection(&b).count() as f64 / union as f64
 }
 
 #[cfg(test)]
@@ -101,4 +425,62 @@ mod tests {
         // 应该返回定制链
         assert_eq!(chain, vec!["gemini", "openai"]);
     }
+
+    #[tokio::test]
+    async fn test_race_switches_on_divergence_and_cancels_loser() {
+        use crate::provider::mock::MockProvider;
+
+        async fn collect(stream: BoxStream<'static, crate::Result<RaceEvent>>) -> (String, String) {
+            let events: Vec<_> = stream.map(|e| e.unwrap()).collect().await;
+            let mut shown = String::new();
+            let mut winner = String::new();
+            for event in events {
+                match event {
+                    RaceEvent::Delta(t) => shown.push_str(&t),
+                    RaceEvent::Switch { text, .. } => shown = text,
+                    RaceEvent::Done { winner: w, .. } => winner = w,
+                }
+            }
+            (shown, winner)
+        }
+
+        let request = PrimitiveRequest::new("any");
+        let policy = RacePolicy::new("fast", "strong").with_compare_chars(50);
+
+        // 回答一致：保留快速模型
+        let fast = Arc::new(MockProvider::new("fast").with_response("The answer is 42"));
+        let strong = Arc::new(
+            MockProvider::new("strong")
+                .with_response("the answer is 42")
+                .with_latency(Duration::from_millis(20)),
+        );
+        let (shown, winner) = collect(policy.run(fast, strong, &request)).await;
+        assert_eq!((shown.as_str(), winner.as_str()), ("The answer is 42", "fast"));
+
+        // 回答分歧：切换到强模型
+        let fast = Arc::new(MockProvider::new("fast").with_response("Paris is in Italy"));
+        let strong = Arc::new(
+            MockProvider::new("strong")
+                .with_response("Rome, not Paris, is the capital of Italy")
+                .with_latency(Duration::from_millis(20)),
+        );
+        let (shown, winner) = collect(policy.run(fast, strong, &request)).await;
+        assert_eq!(winner, "strong");
+        assert_eq!(shown, "Rome, not Paris, is the capital of Italy");
+
+        // 强模型超时：取消强模型，保留快速模型
+        let fast = Arc::new(MockProvider::new("fast").with_response("quick"));
+        let strong = Arc::new(MockProvider::new("strong").with_latency(Duration::from_secs(60)));
+        let started = Instant::now();
+        let policy = policy.with_decision_timeout(Duration::from_millis(50));
+        let (shown, winner) = collect(policy.run(fast, strong, &request)).await;
+        assert_eq!((shown.as_str(), winner.as_str()), ("quick", "fast"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // 快速模型失败：改用强模型
+        let fast = Arc::new(MockProvider::new("fast").with_errors([500]));
+        let strong = Arc::new(MockProvider::new("strong").with_response("solid"));
+        let (shown, winner) = collect(policy.run(fast, strong, &request)).await;
+        assert_eq!((shown.as_str(), winner.as_str()), ("solid", "strong"));
+    }
 }
@@ -5,18 +5,54 @@
 //! - 通用错误重试（429/5xx）
 //! - 跨 Provider 降级
 //! - 请求超时控制
+//! - 批量请求（按模型分组提交到 Provider 批量接口）
+//! - 录制/回放（测试时离线、确定性地复现 LLM 响应）
+//! - 按 Actor 的 token 配额
+//! - 交互请求的快/强双模型竞速
+//! - 响应缓存（TTL + LRU，可按请求跳过）
+//! - 用量事件（每次成功响应发布 `LlmResponseCompleted`，供计量订阅；
+//!   单个请求开始时发布带调用 ID 与全局令牌桶水位的 `LlmRequestStarted`，失败时发布 `LlmError`）
+//! - 任务取消（取消令牌触发时中断进行中的 HTTP 请求）
+//! - 模型路由规则（按任务类型、提示词长度、优先级与时段选择 Provider/模型）
+//! - 通信记录（可选，按 Provider 落盘脱敏后的请求/响应，用于调试）
+//! - 后台认证刷新（定期检查各 Provider，临近过期的凭证在请求路径之外续期）
+//! - 上下文溢出保护（按模型登记的上下文窗口拒绝或裁剪超长请求）
+//! - 对话会话（按对话 ID 分配并持久化会话句柄，多轮请求携带同一句柄）
+//! - 请求/响应中间件链（内置计量与重试，可追加请求头、改写请求体、后处理响应）
+//! - 延迟 SLO（按 Provider/模型统计 p50/p95，持续超标的 Provider 降到分发顺序末尾，探测恢复后复位）
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use nl_core::event::{Event, EventKind};
+use nl_durable::actor_mesh::ActorId;
+use nl_durable::{CancellationToken, EventBus, QuotaManager};
 
 use crate::primitive::PrimitiveRequest;
-use crate::provider::{LlmProvider, LlmResponse, ProviderError};
+use crate::provider::{BoxStream, LlmProvider, LlmResponse, ProviderError};
 use crate::translator::Format;
 use crate::token_bucket::TokenBucket;
-use crate::fallback::{FallbackRouter, FallbackConfig};
+use crate::fallback::{FallbackRouter, FallbackConfig, RaceEvent};
+use crate::scheduler::{Priority, RateLimitScheduler, SchedulerConfig};
+use crate::concurrency::{ConcurrencyLimiter, ConcurrencyStats};
+use crate::latency::{LatencySloConfig, LatencyStats, LatencyTracker, SloTransition};
+use crate::prefix_cache::{static_prefix_hash, PrefixCache};
+use crate::recording::{request_hash, RecordMode, ResponseRecorder};
+use crate::response_cache::{cache_key, ResponseCache};
+use crate::routing::{RouteContext, RoutingRules};
+use crate::transcript::TranscriptRecorder;
+use crate::model_registry::{ContextOverflow, ModelRegistry, OverflowPolicy};
+use crate::session::SessionStore;
+use crate::middleware::{
+    ErrorAction, MeteringMiddleware, Middleware, MiddlewareChain, MiddlewareConfig, RequestContext, RetryMiddleware,
+};
+use crate::secret_guard::{SecretAction, SecretFinding, SecretGuard};
+use crate::locale::{Locale, LocaleTracker, ResponseLanguage};
 
 /// Gateway 配置
 #[derive(Debug, Clone)]
@@ -25,6 +61,8 @@ pub struct GatewayConfig {
     pub global_qps: u32,
     /// 单 Provider QPS 限制
     pub per_provider_qps: u32,
+    /// 单 Provider 并发请求上限（Provider ID -> 上限），未列出的 Provider 不限
+    pub max_concurrent: HashMap<String, usize>,
     /// 请求超时（秒）
     pub timeout_secs: u64,
     /// 最大重试次数
@@ -33,6 +71,8 @@ pub struct GatewayConfig {
     pub retry_base_delay_ms: u64,
     /// 是否启用降级
     pub enable_fallback: bool,
+    /// 限流调度配置
+    pub scheduler: SchedulerConfig,
 }
 
 impl Default for GatewayConfig {
@@ -40,10 +80,12 @@ impl Default for GatewayConfig {
         Self {
             global_qps: 100,
             per_provider_qps: 30,
+            max_concurrent: HashMap::new(),
             timeout_secs: 120,
             max_retries: 3,
             retry_base_delay_ms: 500,
             enable_fallback: true,
+            scheduler: SchedulerConfig::default(),
         }
     }
 }
@@ -53,32 +95,249 @@ pub struct Gateway {
     config: GatewayConfig,
     providers: Arc<RwLock<HashMap<String, Arc<dyn LlmProvider>>>>,
     provider_order: Arc<RwLock<Vec<String>>>,
-    global_bucket: TokenBucket,
+    global_bucket: Arc<TokenBucket>,
+    scheduler: RateLimitScheduler,
+    prefix_cache: PrefixCache,
+    recorder: Option<Arc<ResponseRecorder>>,
+    response_cache: Option<Arc<ResponseCache>>,
+    quotas: Option<Arc<QuotaManager>>,
+    event_bus: Option<Arc<EventBus>>,
+    routing: Option<Arc<RoutingRules>>,
+    transcripts: Option<Arc<TranscriptRecorder>>,
+    models: Option<Arc<ModelRegistry>>,
+    overflow_policy: OverflowPolicy,
+    sessions: Arc<SessionStore>,
+    locales: Arc<LocaleTracker>,
+    response_language: Option<ResponseLanguage>,
+    metering: Arc<MeteringMiddleware>,
+    middleware: MiddlewareChain,
+    secret_guard: Option<SecretGuard>,
     provider_buckets: Arc<RwLock<HashMap<String, TokenBucket>>>,
-    #[allow(dead_code)]
-    fallback_router: FallbackRouter,
+    concurrency: ConcurrencyLimiter,
+    latency: Option<Arc<LatencyTracker>>,
+    fallback_router: Arc<FallbackRouter>,
 }
 
 impl Gateway {
     /// 创建新的 Gateway
     pub fn new(config: GatewayConfig) -> Self {
-        let global_bucket = TokenBucket::new(config.global_qps, Duration::from_secs(1));
-        let fallback_router = FallbackRouter::new(FallbackConfig::default());
+        let global_bucket = Arc::new(TokenBucket::new(config.global_qps, Duration::from_secs(1)));
+        let scheduler = RateLimitScheduler::new(global_bucket.clone(), config.scheduler.clone());
+        let fallback_router = Arc::new(FallbackRouter::new(FallbackConfig::default()));
+        let metering = Arc::new(MeteringMiddleware::new());
+        let concurrency = ConcurrencyLimiter::new(&config.max_concurrent);
+        let middleware = MiddlewareChain::new()
+            .with(metering.clone())
+            .with(Arc::new(RetryMiddleware::new(config.max_retries, config.retry_base_delay_ms)));
 
         Self {
             config,
             providers: Arc::new(RwLock::new(HashMap::new())),
             provider_order: Arc::new(RwLock::new(Vec::new())),
             global_bucket,
+            scheduler,
+            prefix_cache: PrefixCache::new(),
+            recorder: None,
+            response_cache: None,
+            quotas: None,
+            event_bus: None,
+            routing: None,
+            transcripts: None,
+            models: None,
+            overflow_policy: OverflowPolicy::default(),
+            sessions: Arc::new(SessionStore::in_memory()),
+            locales: Arc::new(LocaleTracker::new()),
+            response_language: None,
+            metering,
+            middleware,
+            secret_guard: None,
             provider_buckets: Arc::new(RwLock::new(HashMap::new())),
+            concurrency,
+            latency: None,
             fallback_router,
         }
     }
 
+    /// 启用录制/回放
+    pub fn with_recorder(mut self, recorder: Arc<ResponseRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// 启用 Actor 配额（仅作用于 `complete_for_actor`）
+    pub fn with_quotas(mut self, quotas: Arc<QuotaManager>) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
+    /// 发布 `LlmRequestStarted` / `LlmResponseCompleted` / `LlmError` 事件
+    pub fn with_event_bus(mut self, bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(bus);
+        self
+    }
+
+    /// 启用模型路由规则
+    pub fn with_routing_rules(mut self, rules: Arc<RoutingRules>) -> Self {
+        self.routing = Some(rules);
+        self
+    }
+
+    /// 启用通信记录（作用于此后注册的 Provider）
+    pub fn with_transcripts(mut self, recorder: Arc<TranscriptRecorder>) -> Self {
+        self.transcripts = Some(recorder);
+        self
+    }
+
+    /// 启用上下文窗口检查（未登记的模型不检查）
+    pub fn with_model_registry(mut self, models: Arc<ModelRegistry>) -> Self {
+        self.models = Some(models);
+        self
+    }
+
+    /// 设置超出上下文窗口时的处理方式（默认拒绝）
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// 获取模型元数据登记表
+    pub fn model_registry(&self) -> Option<&Arc<ModelRegistry>> {
+        self.models.as_ref()
+    }
+
+    /// 使用指定的会话登记表（如 `SessionStore::open` 持久化到文件），默认仅保存在内存中
+    pub fn with_sessions(mut self, sessions: Arc<SessionStore>) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// 获取会话登记表（对话结束时可 `forget`）
+    pub fn sessions(&self) -> &Arc<SessionStore> {
+        &self.sessions
+    }
+
+    /// 获取按对话记录的语言区域
+    pub fn locales(&self) -> &Arc<LocaleTracker> {
+        &self.locales
+    }
+
+    /// 设置回答语言：跟随用户消息的语言或强制指定语言（在系统提示词末尾追加指令）
+    pub fn with_response_language(mut self, language: ResponseLanguage) -> Self {
+        self.response_language = Some(language);
+        self
+    }
+
+    /// 在中间件链末尾追加中间件（内置的计量与重试在最外层）
+    pub fn with_middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// 按配置文件追加中间件
+    pub fn with_middleware_config(mut self, config: &MiddlewareConfig) -> crate::Result<Self> {
+        for middleware in config.build()? {
+            self.middleware.push(middleware);
+        }
+        Ok(self)
+    }
+
+    /// 获取中间件链
+    pub fn middleware(&self) -> &MiddlewareChain {
+        &self.middleware
+    }
+
+    /// 获取按 Provider/模型的计量
+    pub fn metering(&self) -> &Arc<MeteringMiddleware> {
+        &self.metering
+    }
+
+    /// 设置 Provider 的并发请求上限（覆盖 `GatewayConfig::max_concurrent` 中的配置）
+    pub fn with_provider_concurrency(self, provider_id: &str, limit: usize) -> Self {
+        self.concurrency.set_limit(provider_id, limit);
+        self
+    }
+
+    /// 获取按 Provider 的并发占用与排队等待统计
+    pub fn concurrency_stats(&self) -> Vec<ConcurrencyStats> {
+        self.concurrency.stats()
+    }
+
+    /// 启用发送前的凭证检测（在全部中间件之后检查最终发出的请求体）
+    pub fn with_secret_guard(mut self, guard: SecretGuard) -> Self {
+        self.secret_guard = Some(guard);
+        self
+    }
+
+    /// 启用响应缓存
+    pub fn with_response_cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// 获取响应缓存（用于显式失效）
+    pub fn response_cache(&self) -> Option<&Arc<ResponseCache>> {
+        self.response_cache.as_ref()
+    }
+
+    /// 设置降级与竞速配置
+    pub fn with_fallback(mut self, config: FallbackConfig) -> Self {
+        self.fallback_router = Arc::new(FallbackRouter::new(config));
+        self
+    }
+
+    /// 启用延迟 SLO 跟踪：p95 持续超标的 Provider 自动降级（需配合 `start_slo_probes` 恢复）
+    pub fn with_latency_slo(mut self, config: LatencySloConfig) -> Self {
+        self.latency = Some(Arc::new(LatencyTracker::new(config)));
+        self
+    }
+
+    /// 获取按 Provider/模型的延迟统计（未启用 SLO 跟踪时为空）
+    pub fn latency_stats(&self) -> Vec<LatencyStats> {
+        self.latency.as_ref().map(|tracker| tracker.stats()).unwrap_or_default()
+    }
+
+    /// 启动降级 Provider 的恢复探测任务：按 `probe_interval` 向每个降级的 Provider 发送 1 token 的探测请求，
+    /// 连续在 SLO 内完成足够次数后恢复；未启用 SLO 跟踪时返回 `None`
+    pub fn start_slo_probes(&self) -> Option<JoinHandle<()>> {
+        let tracker = self.latency.clone()?;
+        let providers = self.providers.clone();
+        let router = self.fallback_router.clone();
+        let bus = self.event_bus.clone();
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(tracker.config().probe_interval);
+            loop {
+                ticker.tick().await;
+                for provider_id in tracker.demoted() {
+                    let provider = providers.read().await.get(&provider_id).cloned();
+                    let (Some(provider), Some(probe)) = (provider, tracker.probe_request(&provider_id)) else {
+                        continue;
+                    };
+                    let started = Instant::now();
+                    let request = provider.complete(provider.compile(&probe));
+                    let latency = match tokio::time::timeout(timeout, request).await {
+                        Ok(Ok(_)) => Some(started.elapsed()),
+                        Ok(Err(e)) => {
+                            tracing::debug!("latency probe to {} failed: {}", provider_id, e);
+                            None
+                        }
+                        Err(_) => None,
+                    };
+                    if let Some(transition) = tracker.record_probe(&provider_id, latency) {
+                        apply_slo_transition(&router, bus.as_deref(), transition);
+                    }
+                }
+            }
+        }))
+    }
+
     /// 注册 Provider
     pub async fn register_provider(&self, provider: Arc<dyn LlmProvider>) {
         let id = provider.id().to_string();
         let bucket = TokenBucket::new(self.config.per_provider_qps, Duration::from_secs(1));
+        if let Some(recorder) = &self.transcripts {
+            provider.attach_transcript(recorder.clone());
+        }
 
         {
             let mut providers = self.providers.write().await;
@@ -104,23 +363,310 @@ impl Gateway {
         *provider_order = order;
     }
 
+    /// 启动令牌桶定时补充任务（全局桶与各 Provider 桶）
+    pub fn start_refill_timer(&self) -> JoinHandle<()> {
+        let global_bucket = self.global_bucket.clone();
+        let provider_buckets = self.provider_buckets.clone();
+        let interval = self.config.scheduler.tick_interval;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                global_bucket.refill();
+                for bucket in provider_buckets.read().await.values() {
+                    bucket.refill();
+                }
+            }
+        })
+    }
+
+    /// 启动后台认证刷新任务：每隔 `interval` 检查已注册的 Provider，为需要刷新的 Provider 续期凭证
+    pub fn start_auth_refresh(&self, interval: Duration) -> JoinHandle<()> {
+        let providers = self.providers.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let due: Vec<Arc<dyn LlmProvider>> = providers
+                    .read()
+                    .await
+                    .values()
+                    .filter(|p| p.needs_refresh())
+                    .cloned()
+                    .collect();
+                for provider in due {
+                    match provider.refresh_auth_shared().await {
+                        Ok(()) => tracing::info!("Refreshed credentials for provider {}", provider.id()),
+                        Err(e) => tracing::warn!("Background auth refresh for {} failed: {}", provider.id(), e),
+                    }
+                }
+            }
+        })
+    }
+
+    /// 从已注册 Provider 的模型目录刷新上下文窗口等元数据，返回更新的模型数
+    pub async fn refresh_model_registry(&self) -> usize {
+        let Some(models) = &self.models else {
+            return 0;
+        };
+        let providers: Vec<Arc<dyn LlmProvider>> = self.providers.read().await.values().cloned().collect();
+        let mut updated = 0;
+        for provider in providers {
+            match models.refresh_from(provider.as_ref()).await {
+                Ok(count) => updated += count,
+                Err(e) => tracing::warn!("Failed to list models of provider {}: {}", provider.id(), e),
+            }
+        }
+        updated
+    }
+
+    /// 获取限流调度器
+    pub fn scheduler(&self) -> &RateLimitScheduler {
+        &self.scheduler
+    }
+
+    /// 获取静态前缀缓存表
+    pub fn prefix_cache(&self) -> &PrefixCache {
+        &self.prefix_cache
+    }
+
     /// 执行请求
     pub async fn complete(
         &self,
         primitive: &PrimitiveRequest,
+        target_format: Format,
+    ) -> Result<LlmResponse, GatewayError> {
+        self.complete_with_priority(primitive, target_format, Priority::Normal)
+            .await
+    }
+
+    /// 按优先级执行请求
+    ///
+    /// 低优先级请求在全局预算不足时会延后到补充窗口，超过最长延后时间才返回限流错误。
+    pub async fn complete_with_priority(
+        &self,
+        primitive: &PrimitiveRequest,
+        _target_format: Format,
+        priority: Priority,
+    ) -> Result<LlmResponse, GatewayError> {
+        self.execute(primitive, priority, None).await
+    }
+
+    /// 执行请求：回放 → 分发 → 录制，并发布用量事件
+    async fn execute(
+        &self,
+        primitive: &PrimitiveRequest,
+        priority: Priority,
+        actor: Option<ActorId>,
+    ) -> Result<LlmResponse, GatewayError> {
+        let (primitive, preferred) = self.route(primitive, priority);
+        let primitive = self.localize(primitive);
+        let primitive = self.fit_context(primitive)?;
+        let primitive = primitive.as_ref();
+
+        let hash = self.recorder.as_ref().map(|_| request_hash(primitive));
+        if let (Some(recorder), Some(hash)) = (&self.recorder, &hash) {
+            if recorder.mode() == RecordMode::Replay {
+                return recorder
+                    .lookup(hash)
+                    .ok_or_else(|| GatewayError::ReplayMissing(hash.clone()));
+            }
+        }
+
+        let call_id = self.publish_started(actor, primitive);
+        let (provider_id, response) = match self.dispatch(primitive, priority, preferred.as_deref()).await {
+            Ok(dispatched) => dispatched,
+            Err(e) => {
+                self.publish_error(actor, call_id, primitive, &e);
+                return Err(e);
+            }
+        };
+        self.publish_usage(actor, call_id, &provider_id, primitive, &response);
+
+        if let (Some(recorder), Some(hash)) = (&self.recorder, &hash) {
+            if let Err(e) = recorder.store(hash, &response) {
+                tracing::warn!("failed to record response {}: {}", hash, e);
+            }
+        }
+        Ok(response)
+    }
+
+    /// 在指定对话中执行请求
+    ///
+    /// 同一 `conversation` 的各轮请求携带相同的会话句柄，使 CloudCode 等 Provider 续接服务端上下文；
+    /// 请求已显式设置 `session_id` 时保持不变。
+    pub async fn complete_in_conversation(
+        &self,
+        conversation: &str,
+        primitive: &PrimitiveRequest,
+        priority: Priority,
+    ) -> Result<LlmResponse, GatewayError> {
+        let mut primitive = primitive.clone();
+        if primitive.metadata.session_id.is_none() {
+            primitive.metadata.session_id = Some(self.sessions.get_or_create(conversation).as_str().to_string());
+        }
+        // 本轮检测不出语言时沿用该对话上一次的结果
+        primitive.metadata.locale = self.locales.observe(conversation, &primitive);
+        self.execute(&primitive, priority, None).await
+    }
+
+    /// 代表指定 Actor 执行请求
+    ///
+    /// 请求前检查该 Actor 的配额，成功后按响应用量记账；超额的 Actor 会被暂停，后续请求直接拒绝。
+    pub async fn complete_for_actor(
+        &self,
+        actor: ActorId,
+        primitive: &PrimitiveRequest,
         _target_format: Format,
+        priority: Priority,
+    ) -> Result<LlmResponse, GatewayError> {
+        if let Some(quotas) = &self.quotas {
+            quotas
+                .check(actor)
+                .await
+                .map_err(|e| GatewayError::QuotaExceeded(e.to_string()))?;
+        }
+
+        let response = self.execute(primitive, priority, Some(actor)).await?;
+
+        if let Some(quotas) = &self.quotas {
+            // 本次响应已产生，超额只影响后续请求
+            if let Err(e) = quotas.record_llm_tokens(actor, response.usage.total_tokens()).await {
+                tracing::warn!("{}", e);
+            }
+        }
+        Ok(response)
+    }
+
+    /// 可取消地执行请求
+    ///
+    /// 令牌触发时丢弃进行中的请求 future，底层 HTTP 连接随之中断，返回 `GatewayError::Cancelled`。
+    pub async fn complete_cancellable(
+        &self,
+        primitive: &PrimitiveRequest,
+        target_format: Format,
+        priority: Priority,
+        cancel: &CancellationToken,
     ) -> Result<LlmResponse, GatewayError> {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(GatewayError::Cancelled),
+            result = self.complete_with_priority(primitive, target_format, priority) => result,
+        }
+    }
+
+    /// 按竞速策略同时请求快速与强模型，流式返回竞速事件
+    ///
+    /// 两侧请求各消耗一个高优先级令牌；输掉的一侧在判定后立即取消。
+    pub async fn race(
+        &self,
+        primitive: &PrimitiveRequest,
+    ) -> Result<BoxStream<'static, crate::Result<RaceEvent>>, GatewayError> {
+        let policy = self
+            .fallback_router
+            .race_policy()
+            .ok_or(GatewayError::NoProviderAvailable)?;
+        let (fast, strong) = {
+            let providers = self.providers.read().await;
+            let get = |id: &str| {
+                providers
+                    .get(id)
+                    .cloned()
+                    .ok_or_else(|| GatewayError::ProviderNotFound(id.to_string()))
+            };
+            (get(&policy.fast_provider)?, get(&policy.strong_provider)?)
+        };
+        self.scheduler.acquire(Priority::High, 2).await?;
+        Ok(policy.run(fast, strong, primitive))
+    }
+
+    /// 按路由规则改写请求模型，并返回首选 Provider
+    fn route<'a>(
+        &self,
+        primitive: &'a PrimitiveRequest,
+        priority: Priority,
+    ) -> (Cow<'a, PrimitiveRequest>, Option<String>) {
+        let Some(rules) = &self.routing else {
+            return (Cow::Borrowed(primitive), None);
+        };
+        let ctx = RouteContext::from_request(primitive, priority);
+        let Some(target) = rules.resolve(&ctx) else {
+            return (Cow::Borrowed(primitive), None);
+        };
+        tracing::debug!("routed {:?} request to {:?}", ctx.task_type, target);
+
+        let primitive = match &target.model {
+            Some(model) if *model != primitive.model => {
+                let mut routed = primitive.clone();
+                routed.model = model.clone();
+                Cow::Owned(routed)
+            }
+            _ => Cow::Borrowed(primitive),
+        };
+        (primitive, Some(target.provider.clone()))
+    }
+
+    /// 确定请求的语言区域，并按回答语言选项在系统提示词末尾追加指令
+    fn localize<'a>(&self, primitive: Cow<'a, PrimitiveRequest>) -> Cow<'a, PrimitiveRequest> {
+        let locale = primitive.metadata.locale.or_else(|| Locale::detect_request(&primitive));
+        let respond_in = self.response_language.and_then(|language| language.resolve(locale));
+        if locale == primitive.metadata.locale && respond_in.is_none() {
+            return primitive;
+        }
+        let mut localized = primitive.into_owned();
+        localized.metadata.locale = locale;
+        if let Some(respond_in) = respond_in {
+            let instruction = respond_in.response_instruction();
+            localized.system = Some(match localized.system.take().filter(|s| !s.trim().is_empty()) {
+                Some(system) => format!("{}\n\n{}", system, instruction),
+                None => instruction.to_string(),
+            });
+        }
+        Cow::Owned(localized)
+    }
+
+    /// 检查请求是否放得进模型上下文窗口，按溢出策略拒绝或裁剪
+    fn fit_context<'a>(
+        &self,
+        primitive: Cow<'a, PrimitiveRequest>,
+    ) -> Result<Cow<'a, PrimitiveRequest>, GatewayError> {
+        match &self.models {
+            Some(models) => models
+                .fit(primitive, self.overflow_policy)
+                .map_err(GatewayError::ContextOverflow),
+            None => Ok(primitive),
+        }
+    }
+
+    /// 按 Provider 顺序执行请求，可降级时尝试下一个
+    ///
+    /// `preferred` 为路由规则选中的 Provider，排在顺序最前。
+    async fn dispatch(
+        &self,
+        primitive: &PrimitiveRequest,
+        priority: Priority,
+        preferred: Option<&str>,
+    ) -> Result<(String, LlmResponse), GatewayError> {
         // 获取 Provider 顺序
-        let provider_ids = {
+        let mut provider_ids = {
             let order = self.provider_order.read().await;
-            order.clone()
+            self.fallback_router.order(order.clone())
         };
+        if let Some(preferred) = preferred {
+            match provider_ids.iter().position(|id| id == preferred) {
+                Some(index) => {
+                    let id = provider_ids.remove(index);
+                    provider_ids.insert(0, id);
+                }
+                None => tracing::warn!("routing target provider {} is not registered", preferred),
+            }
+        }
 
         let mut last_error: Option<GatewayError> = None;
 
         for provider_id in provider_ids {
-            match self.try_provider(&provider_id, primitive).await {
-                Ok(response) => return Ok(response),
+            match self.try_provider(&provider_id, primitive, priority).await {
+                Ok(response) => return Ok((provider_id, response)),
                 Err(e) => {
                     // 检查是否应该降级
                     let fallback = match &e {
@@ -140,14 +686,179 @@ impl Gateway {
         Err(last_error.unwrap_or(GatewayError::NoProviderAvailable))
     }
 
+    /// 批量执行请求，按输入顺序返回逐条结果
+    ///
+    /// 适合 MCTS 推演打分等大量廉价请求：按模型分组交给首选 Provider 的批量接口，
+    /// 每条请求消耗一个全局令牌（超过桶容量时分块预留）；
+    /// 整批失败或单条失败可降级时，逐条回退到 `complete_with_priority`。
+    pub async fn complete_batch(
+        &self,
+        requests: &[PrimitiveRequest],
+        priority: Priority,
+    ) -> Vec<Result<LlmResponse, GatewayError>> {
+        let mut results: Vec<Option<Result<LlmResponse, GatewayError>>> =
+            (0..requests.len()).map(|_| None).collect();
+
+        let primary = {
+            let order = self.provider_order.read().await;
+            let providers = self.providers.read().await;
+            order
+                .first()
+                .and_then(|id| providers.get(id).cloned().map(|p| (id.clone(), p)))
+        };
+        let Some((provider_id, provider)) = primary else {
+            return requests
+                .iter()
+                .map(|_| Err(GatewayError::NoProviderAvailable))
+                .collect();
+        };
+
+        // 超出上下文窗口的请求直接返回错误，不进入批量提交
+        let fitted: Vec<Option<Cow<PrimitiveRequest>>> = requests
+            .iter()
+            .enumerate()
+            .map(|(index, request)| match self.fit_context(Cow::Borrowed(request)) {
+                Ok(request) => Some(request),
+                Err(e) => {
+                    results[index] = Some(Err(e));
+                    None
+                }
+            })
+            .collect();
+
+        // 按模型分组（保持首次出现顺序）
+        let mut groups: Vec<(&str, Vec<usize>)> = Vec::new();
+        for (index, request) in fitted.iter().enumerate() {
+            let Some(request) = request else {
+                continue;
+            };
+            match groups.iter_mut().find(|(model, _)| *model == request.model) {
+                Some((_, indices)) => indices.push(index),
+                None => groups.push((request.model.as_str(), vec![index])),
+            }
+        }
+
+        let chunk_size = self.global_bucket.capacity().max(1) as usize;
+        let mut retry = Vec::new();
+
+        // 录制/回放模式下逐条执行，保证每条请求都按哈希录制
+        if self.recorder.is_some() {
+            retry.extend(groups.drain(..).flat_map(|(_, indices)| indices));
+        }
+
+        for (_, indices) in &groups {
+            for chunk in indices.chunks(chunk_size) {
+                if let Err(e) = self.scheduler.acquire(priority, chunk.len() as u64).await {
+                    for &index in chunk {
+                        results[index] = Some(Err(e.clone()));
+                    }
+                    continue;
+                }
+
+                // 一次批量提交只计一次 Provider 请求，也只占一个并发名额
+                {
+                    let buckets = self.provider_buckets.read().await;
+                    if let Some(bucket) = buckets.get(&provider_id) {
+                        bucket.acquire().await;
+                    }
+                }
+                let _slot = self.concurrency.acquire(&provider_id).await;
+
+                let mut submitted = Vec::with_capacity(chunk.len());
+                let mut contexts = Vec::with_capacity(chunk.len());
+                for &index in chunk {
+                    let Some(request) = fitted[index].as_deref() else {
+                        continue;
+                    };
+                    let mut ctx = RequestContext::new(&provider_id, &request.model, provider.compile(request));
+                    let prepared = self.middleware.before_request(&mut ctx).await;
+                    match prepared.and_then(|_| self.guard_secrets(&mut ctx)) {
+                        Ok(()) => {
+                            submitted.push(index);
+                            contexts.push(ctx);
+                        }
+                        Err(e) => results[index] = Some(Err(e)),
+                    }
+                }
+                let bodies = contexts.iter().map(RequestContext::outgoing_body).collect();
+                let items = match provider.complete_batch(bodies).await {
+                    Ok(items) => items,
+                    Err(e) => {
+                        tracing::warn!("batch request to {} failed, retrying individually: {}", provider_id, e);
+                        retry.extend_from_slice(&submitted);
+                        continue;
+                    }
+                };
+
+                let mut items = items.into_iter();
+                for (&index, ctx) in submitted.iter().zip(&contexts) {
+                    match items.next() {
+                        Some(Ok(mut response)) => {
+                            if let Err(e) = self.middleware.after_response(ctx, &mut response).await {
+                                results[index] = Some(Err(e));
+                                continue;
+                            }
+                            self.prefix_cache.record_usage(&response.usage);
+                            self.publish_usage(None, None, &provider_id, &requests[index], &response);
+                            results[index] = Some(Ok(response));
+                        }
+                        Some(Err(e)) => {
+                            let provider_error = match e {
+                                crate::Error::Provider(pe) => pe,
+                                other => ProviderError::fail(other.to_string()),
+                            };
+                            // 批量中的失败由逐条回退处理，这里只通知中间件
+                            self.middleware.on_error(ctx, &provider_error).await;
+                            if provider_error.retryable || provider_error.should_fallback {
+                                retry.push(index);
+                            } else {
+                                results[index] = Some(Err(GatewayError::ProviderError {
+                                    provider_id: provider_id.clone(),
+                                    message: provider_error.message,
+                                    should_fallback: false,
+                                }));
+                            }
+                        }
+                        None => retry.push(index),
+                    }
+                }
+            }
+        }
+
+        for index in retry {
+            results[index] = Some(
+                self.complete_with_priority(&requests[index], Format::default(), priority)
+                    .await,
+            );
+        }
+
+        results
+            .into_iter()
+            .map(|r| r.unwrap_or(Err(GatewayError::NoProviderAvailable)))
+            .collect()
+    }
+
     /// 尝试使用指定 Provider 执行请求
     async fn try_provider(
         &self,
         provider_id: &str,
         primitive: &PrimitiveRequest,
+        priority: Priority,
     ) -> Result<LlmResponse, GatewayError> {
-        // 全局限流
-        self.global_bucket.acquire().await;
+        // 响应缓存：命中时不占用限流预算
+        let cached = self
+            .response_cache
+            .as_ref()
+            .filter(|_| !primitive.metadata.no_cache)
+            .map(|cache| (cache, cache_key(provider_id, primitive)));
+        if let Some((cache, key)) = &cached {
+            if let Some(response) = cache.get(key) {
+                return Ok(response);
+            }
+        }
+
+        // 全局限流（按优先级调度）
+        self.scheduler.acquire(priority, 1).await?;
 
         // Provider 限流
         {
@@ -157,6 +868,9 @@ impl Gateway {
             }
         }
 
+        // Provider 并发上限：名额持有到本次请求返回
+        let _slot = self.concurrency.acquire(provider_id).await;
+
         // 获取 Provider
         let provider = {
             let providers = self.providers.read().await;
@@ -167,13 +881,51 @@ impl Gateway {
         };
 
         // 编译请求
-        let body = provider.compile(primitive);
+        let mut body = provider.compile(primitive);
 
-        // 执行请求（带重试）
-        let mut retries = 0;
+        // 静态前缀缓存：命中时由 Provider 改写为服务端缓存引用
+        if let Some(hash) = static_prefix_hash(primitive) {
+            let mut hint = self.prefix_cache.lookup(provider_id, &primitive.model, &hash);
+            match provider.prepare_prefix_cache(primitive, &hash).await {
+                Ok(Some(cache_ref)) => {
+                    if hint.cache_ref.as_deref() != Some(cache_ref.as_str()) {
+                        self.prefix_cache
+                            .set_cache_ref(provider_id, &primitive.model, &hash, cache_ref.clone());
+                    }
+                    hint.cache_ref = Some(cache_ref);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    // 缓存不可用时退回完整请求
+                    tracing::warn!("prefix cache unavailable for {}: {}", provider_id, e);
+                    hint.cache_ref = None;
+                }
+            }
+            body = provider.apply_prefix_cache(body, &hint);
+        }
+
+        // 经中间件链执行请求，重试与否由中间件决定
+        let mut attempt = 1;
         loop {
-            match provider.complete(body.clone()).await {
-                Ok(response) => return Ok(response),
+            let mut ctx = RequestContext::new(provider_id, &primitive.model, body.clone()).with_attempt(attempt);
+            self.middleware.before_request(&mut ctx).await?;
+            self.guard_secrets(&mut ctx)?;
+            ctx.started_at = Instant::now();
+            match provider.complete(ctx.outgoing_body()).await {
+                Ok(mut response) => {
+                    if let Some(tracker) = &self.latency {
+                        let latency = ctx.started_at.elapsed();
+                        if let Some(transition) = tracker.record(provider_id, &primitive.model, latency) {
+                            apply_slo_transition(&self.fallback_router, self.event_bus.as_deref(), transition);
+                        }
+                    }
+                    self.middleware.after_response(&ctx, &mut response).await?;
+                    self.prefix_cache.record_usage(&response.usage);
+                    if let Some((cache, key)) = cached {
+                        cache.insert(key, provider_id, &primitive.model, response.clone());
+                    }
+                    return Ok(response);
+                }
                 Err(e) => {
                     // 解析内部真正的 ProviderError 信号
                     let provider_error = match e {
@@ -181,10 +933,8 @@ impl Gateway {
                         _ => ProviderError::fail(e.to_string()),
                     };
 
-                    // 检查是否可重试
-                    if provider_error.retryable && retries < self.config.max_retries {
-                        retries += 1;
-                        let delay = self.calculate_retry_delay(retries, &provider_error);
+                    if let ErrorAction::Retry(delay) = self.middleware.on_error(&ctx, &provider_error).await {
+                        attempt += 1;
                         tokio::time::sleep(delay).await;
                         continue;
                     }
@@ -200,15 +950,122 @@ impl Gateway {
         }
     }
 
-    /// 计算重试延迟
-    fn calculate_retry_delay(&self, attempt: u32, error: &ProviderError) -> Duration {
-        if let Some(retry_after) = error.retry_after_ms {
-            Duration::from_millis(retry_after)
-        } else {
-            let base = self.config.retry_base_delay_ms;
-            let delay = base * (1 << (attempt - 1)); // 指数退避
-            Duration::from_millis(delay.min(30_000)) // 最大 30 秒
+    /// 发送前检测凭证：按策略拦截或替换命中片段，并发布 `SecretBlocked` 事件
+    fn guard_secrets(&self, ctx: &mut RequestContext) -> Result<(), GatewayError> {
+        let Some(guard) = &self.secret_guard else {
+            return Ok(());
+        };
+        let action = guard.policy().action;
+        let findings = match action {
+            SecretAction::Block => guard.scan(&ctx.body),
+            SecretAction::Mask => guard.mask(&mut ctx.body),
+        };
+        if findings.is_empty() {
+            return Ok(());
+        }
+        tracing::warn!(
+            "{} secret(s) detected in request to {} ({:?})",
+            findings.len(),
+            ctx.provider_id,
+            action
+        );
+        // 重试时请求体相同，只在首次尝试时发布
+        if let Some(bus) = self.event_bus.as_ref().filter(|_| ctx.attempt == 1) {
+            let event = Event::new(
+                EventKind::SecretBlocked,
+                Default::default(),
+                serde_json::json!({
+                    "provider": ctx.provider_id,
+                    "model": ctx.model,
+                    "action": action,
+                    "findings": findings,
+                }),
+            );
+            bus.publish(&event);
         }
+        match action {
+            SecretAction::Block => Err(GatewayError::SecretBlocked(findings)),
+            SecretAction::Mask => Ok(()),
+        }
+    }
+
+    /// 发布 `LlmRequestStarted` 事件，载荷包含调用 ID、模型、语言区域与全局令牌桶水位，返回调用 ID
+    fn publish_started(&self, actor: Option<ActorId>, primitive: &PrimitiveRequest) -> Option<uuid::Uuid> {
+        let bus = self.event_bus.as_ref()?;
+        let call_id = uuid::Uuid::new_v4();
+        let event = Event::new(
+            EventKind::LlmRequestStarted,
+            actor.unwrap_or_default(),
+            serde_json::json!({
+                "call_id": call_id,
+                "model": primitive.model,
+                "locale": primitive.metadata.locale,
+                "bucket": {
+                    "available": self.global_bucket.available(),
+                    "capacity": self.global_bucket.capacity(),
+                },
+            }),
+        );
+        bus.publish(&event);
+        Some(call_id)
+    }
+
+    /// 发布 `LlmError` 事件
+    fn publish_error(
+        &self,
+        actor: Option<ActorId>,
+        call_id: Option<uuid::Uuid>,
+        primitive: &PrimitiveRequest,
+        error: &GatewayError,
+    ) {
+        let Some(bus) = &self.event_bus else {
+            return;
+        };
+        let event = Event::new(
+            EventKind::LlmError,
+            actor.unwrap_or_default(),
+            serde_json::json!({
+                "call_id": call_id,
+                "model": primitive.model,
+                "locale": primitive.metadata.locale,
+                "error": error.to_string(),
+            }),
+        );
+        bus.publish(&event);
+    }
+
+    /// 发布 `LlmResponseCompleted` 事件，载荷包含 Provider、模型、语言区域与 token 用量
+    fn publish_usage(
+        &self,
+        actor: Option<ActorId>,
+        call_id: Option<uuid::Uuid>,
+        provider_id: &str,
+        primitive: &PrimitiveRequest,
+        response: &LlmResponse,
+    ) {
+        let Some(bus) = &self.event_bus else {
+            return;
+        };
+        let usage = &response.usage;
+        let event = Event::new(
+            EventKind::LlmResponseCompleted,
+            actor.unwrap_or_default(),
+            serde_json::json!({
+                "call_id": call_id,
+                "provider": provider_id,
+                "model": primitive.model,
+                "locale": primitive.metadata.locale,
+                "usage": {
+                    "input_tokens": usage.input_tokens,
+                    "output_tokens": usage.output_tokens,
+                    "thinking_tokens": usage.thinking_tokens,
+                    "cached_tokens": usage.cached_tokens,
+                    "total_tokens": usage.total_tokens(),
+                },
+                "stop_reason": response.stop_reason,
+            }),
+        );
+        bus.publish(&event);
     }
 
     /// 获取所有已注册的 Provider ID
@@ -218,6 +1075,26 @@ impl Gateway {
     }
 }
 
+/// 把 SLO 状态变化应用到降级路由，并发布 `ProviderDemoted`/`ProviderRestored` 告警
+fn apply_slo_transition(router: &FallbackRouter, bus: Option<&EventBus>, transition: SloTransition) {
+    let kind = match &transition {
+        SloTransition::Demoted { provider, p95_ms, slo_ms } => {
+            tracing::warn!("demoting provider {}: p95 {} ms exceeds SLO {} ms", provider, p95_ms, slo_ms);
+            router.demote(provider);
+            EventKind::ProviderDemoted
+        }
+        SloTransition::Restored { provider, probes } => {
+            tracing::info!("restoring provider {} after {} successful probes", provider, probes);
+            router.restore(provider);
+            EventKind::ProviderRestored
+        }
+    };
+    if let Some(bus) = bus {
+        let payload = serde_json::to_value(&transition).unwrap_or_default();
+        bus.publish(&Event::new(kind, Default::default(), payload));
+    }
+}
+
 /// Gateway 错误
 #[derive(Debug, Clone)]
 pub enum GatewayError {
@@ -235,6 +1112,18 @@ pub enum GatewayError {
     Timeout,
     /// 限流
     RateLimited,
+    /// 回放模式下请求未被录制
+    ReplayMissing(String),
+    /// Actor 配额已用尽
+    QuotaExceeded(String),
+    /// 任务已取消
+    Cancelled,
+    /// 提示词超出模型上下文窗口
+    ContextOverflow(ContextOverflow),
+    /// 中间件拒绝请求或处理响应失败
+    Middleware { middleware: String, message: String },
+    /// 请求中检测到凭证，按策略拦截
+    SecretBlocked(Vec<SecretFinding>),
 }
 
 impl std::fmt::Display for GatewayError {
@@ -247,8 +1136,439 @@ impl std::fmt::Display for GatewayError {
             }
             GatewayError::Timeout => write!(f, "Request timeout"),
             GatewayError::RateLimited => write!(f, "Rate limited"),
+            GatewayError::ReplayMissing(hash) => write!(f, "No recorded response for request {}", hash),
+            GatewayError::QuotaExceeded(msg) => write!(f, "Quota exceeded: {}", msg),
+            GatewayError::Cancelled => write!(f, "Request cancelled"),
+            GatewayError::ContextOverflow(overflow) => write!(f, "Context overflow: {}", overflow),
+            GatewayError::Middleware { middleware, message } => {
+                write!(f, "Middleware {} failed: {}", middleware, message)
+            }
+            GatewayError::SecretBlocked(findings) => {
+                let found: Vec<String> = findings.iter().map(|f| format!("{} at {}", f.kind, f.path)).collect();
+                write!(f, "Request blocked: {} secret(s) detected ({})", findings.len(), found.join(", "))
+            }
         }
     }
 }
 
 impl std::error::Error for GatewayError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{ApiKeyConfig, ApiKeyProvider, Auth};
+    use crate::primitive::{PrimitiveContent, PrimitiveMessage};
+    use crate::provider::{BoxStream, LlmChunk, StopReason, Usage};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 按 prompt 内容返回结果的测试 Provider："fail" 返回不可降级错误，"flaky" 首次批量失败
+    struct EchoProvider {
+        auth: Auth,
+        batch_calls: AtomicUsize,
+        single_calls: AtomicUsize,
+    }
+
+    impl EchoProvider {
+        fn new() -> Self {
+            Self {
+                auth: Auth::ApiKey(ApiKeyConfig::new("test", ApiKeyProvider::OpenAI)),
+                batch_calls: AtomicUsize::new(0),
+                single_calls: AtomicUsize::new(0),
+            }
+        }
+
+        fn respond(body: &serde_json::Value) -> crate::Result<LlmResponse> {
+            let text = body["text"].as_str().unwrap_or_default().to_string();
+            if text == "fail" {
+                return Err(crate::Error::Provider(ProviderError::fail("bad request")));
+            }
+            Ok(LlmResponse {
+                content: format!("echo:{}", text),
+                tool_calls: Vec::new(),
+                usage: Usage::default(),
+                stop_reason: StopReason::EndTurn,
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for EchoProvider {
+        fn id(&self) -> &str {
+            "echo"
+        }
+
+        fn auth(&self) -> &Auth {
+            &self.auth
+        }
+
+        fn supported_models(&self) -> &[&str] {
+            &[]
+        }
+
+        fn compile(&self, primitive: &PrimitiveRequest) -> serde_json::Value {
+            let text = match primitive.messages.last().and_then(|m| m.content.first()) {
+                Some(PrimitiveContent::Text { text }) => text.clone(),
+                _ => String::new(),
+            };
+            serde_json::json!({ "text": text })
+        }
+
+        async fn complete(&self, body: serde_json::Value) -> crate::Result<LlmResponse> {
+            self.single_calls.fetch_add(1, Ordering::SeqCst);
+            Self::respond(&body)
+        }
+
+        async fn stream(
+            &self,
+            _body: serde_json::Value,
+        ) -> crate::Result<BoxStream<'_, crate::Result<LlmChunk>>> {
+            unimplemented!()
+        }
+
+        async fn complete_batch(
+            &self,
+            bodies: Vec<serde_json::Value>,
+        ) -> crate::Result<Vec<crate::Result<LlmResponse>>> {
+            self.batch_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(bodies
+                .iter()
+                .map(|body| match body["text"].as_str() {
+                    Some("flaky") => Err(crate::Error::Provider(ProviderError::from_http_status(503, "busy"))),
+                    _ => Self::respond(body),
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_batch_returns_results_in_order() {
+        let gateway = Gateway::new(GatewayConfig {
+            retry_base_delay_ms: 1,
+            ..Default::default()
+        });
+        let provider = Arc::new(EchoProvider::new());
+        gateway.register_provider(provider.clone()).await;
+
+        let mut requests: Vec<PrimitiveRequest> = ["a", "fail", "flaky", "b"]
+            .iter()
+            .map(|t| PrimitiveRequest::single_user_message(*t))
+            .collect();
+        requests[3].model = "other".to_string();
+
+        let results = gateway.complete_batch(&requests, Priority::Low).await;
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap().content, "echo:a");
+        assert!(matches!(
+            results[1],
+            Err(GatewayError::ProviderError { should_fallback: false, .. })
+        ));
+        // 批量中的可重试错误逐条重发成功
+        assert_eq!(results[2].as_ref().unwrap().content, "echo:flaky");
+        assert_eq!(results[3].as_ref().unwrap().content, "echo:b");
+        // 两个模型各一次批量提交
+        assert_eq!(provider.batch_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(provider.single_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_offline() {
+        let path = std::env::temp_dir().join(format!("nl_gateway_replay_{}.json", uuid::Uuid::new_v4()));
+        let request = PrimitiveRequest::single_user_message("verdict?");
+
+        let recording = Gateway::new(GatewayConfig::default())
+            .with_recorder(Arc::new(ResponseRecorder::record(&path).unwrap()));
+        recording.register_provider(Arc::new(EchoProvider::new())).await;
+        let recorded = recording.complete(&request, Format::default()).await.unwrap();
+
+        // 回放时不注册任何 Provider
+        let replay = Gateway::new(GatewayConfig::default())
+            .with_recorder(Arc::new(ResponseRecorder::replay(&path).unwrap()));
+        let replayed = replay.complete(&request, Format::default()).await.unwrap();
+        assert_eq!(replayed.content, recorded.content);
+
+        let unknown = replay
+            .complete(&PrimitiveRequest::single_user_message("new"), Format::default())
+            .await;
+        assert!(matches!(unknown, Err(GatewayError::ReplayMissing(_))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fallback_after_injected_failures() {
+        use crate::provider::mock::MockProvider;
+
+        let gateway = Gateway::new(GatewayConfig {
+            retry_base_delay_ms: 1,
+            ..Default::default()
+        });
+        let primary = Arc::new(MockProvider::new("claude").with_errors([429, 503, 503, 503]));
+        let backup = Arc::new(MockProvider::new("openai").with_response("from backup"));
+        gateway.register_provider(primary.clone()).await;
+        gateway.register_provider(backup.clone()).await;
+
+        let router = FallbackRouter::new(FallbackConfig::default());
+        let mut order = vec!["claude".to_string()];
+        order.extend(router.get_fallback_chain("claude"));
+        gateway.set_provider_order(order).await;
+
+        let response = gateway
+            .complete(&PrimitiveRequest::single_user_message("hi"), Format::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "from backup");
+        // 首次调用 + 3 次重试后降级
+        assert_eq!(primary.call_count(), 4);
+        assert_eq!(backup.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_middleware_post_processes_and_meters_retries() {
+        use crate::provider::mock::MockProvider;
+
+        struct Shout;
+
+        #[async_trait::async_trait]
+        impl Middleware for Shout {
+            fn name(&self) -> &str {
+                "shout"
+            }
+
+            async fn before_request(&self, ctx: &mut RequestContext) -> crate::Result<()> {
+                if ctx.model == "blocked" {
+                    return Err(crate::Error::Unknown("model not allowed".to_string()));
+                }
+                Ok(())
+            }
+
+            async fn after_response(&self, _ctx: &RequestContext, response: &mut LlmResponse) -> crate::Result<()> {
+                response.content = response.content.to_uppercase();
+                Ok(())
+            }
+        }
+
+        let gateway = Gateway::new(GatewayConfig {
+            retry_base_delay_ms: 1,
+            ..Default::default()
+        })
+        .with_middleware(Arc::new(Shout));
+        let mock = Arc::new(MockProvider::new("mock").with_errors([503]).with_response("done"));
+        gateway.register_provider(mock.clone()).await;
+
+        let mut request = PrimitiveRequest::single_user_message("hi");
+        let response = gateway.complete(&request, Format::default()).await.unwrap();
+        assert_eq!(response.content, "DONE");
+        assert_eq!(gateway.middleware().names(), ["metering", "retry", "shout"]);
+        let readings = gateway.metering().snapshot();
+        assert_eq!((readings.len(), readings[0].requests, readings[0].failures), (1, 2, 1));
+
+        request.model = "blocked".to_string();
+        let rejected = gateway.complete(&request, Format::default()).await;
+        assert!(matches!(rejected, Err(GatewayError::Middleware { ref middleware, .. }) if middleware == "shout"));
+        assert_eq!(mock.call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_hit_and_opt_out() {
+        use crate::provider::mock::MockProvider;
+        use crate::response_cache::ResponseCache;
+
+        let cache = Arc::new(ResponseCache::default());
+        let gateway = Gateway::new(GatewayConfig::default()).with_response_cache(cache.clone());
+        let mock = Arc::new(MockProvider::new("mock").with_response("verdict"));
+        gateway.register_provider(mock.clone()).await;
+
+        let request = PrimitiveRequest::single_user_message("evaluate");
+        for _ in 0..3 {
+            let response = gateway.complete(&request, Format::default()).await.unwrap();
+            assert_eq!(response.content, "verdict");
+        }
+        assert_eq!(mock.call_count(), 1);
+        assert_eq!(cache.stats().hits, 2);
+
+        let mut uncached = request.clone();
+        uncached.metadata = uncached.metadata.with_no_cache();
+        gateway.complete(&uncached, Format::default()).await.unwrap();
+        assert_eq!(mock.call_count(), 2);
+
+        assert!(cache.invalidate("mock", &request));
+        gateway.complete(&request, Format::default()).await.unwrap();
+        assert_eq!(mock.call_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_routing_rules_select_provider_and_model() {
+        use crate::provider::mock::MockProvider;
+        use crate::routing::RoutingRule;
+
+        let rules = RoutingRules::new()
+            .with_rule(RoutingRule::new("verdicts", "strong").with_task_type("verdict").with_model("big-model"));
+        let gateway = Gateway::new(GatewayConfig::default()).with_routing_rules(Arc::new(rules));
+        let cheap = Arc::new(MockProvider::new("cheap").with_response("cheap"));
+        let strong = Arc::new(MockProvider::new("strong").with_response("strong"));
+        gateway.register_provider(cheap.clone()).await;
+        gateway.register_provider(strong.clone()).await;
+        gateway.set_provider_order(vec!["cheap".to_string(), "strong".to_string()]).await;
+
+        let mut request = PrimitiveRequest::single_user_message("summarize this");
+        assert_eq!(gateway.complete(&request, Format::default()).await.unwrap().content, "cheap");
+
+        request.metadata = request.metadata.with_task_type("verdict");
+        let (routed, preferred) = gateway.route(&request, Priority::Normal);
+        assert_eq!(routed.model, "big-model");
+        assert_eq!(preferred.as_deref(), Some("strong"));
+        assert_eq!(gateway.complete(&request, Format::default()).await.unwrap().content, "strong");
+        assert_eq!((cheap.call_count(), strong.call_count()), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_context_overflow_rejected_before_dispatch() {
+        use crate::model_registry::ModelInfo;
+        use crate::provider::mock::MockProvider;
+
+        let models = Arc::new(ModelRegistry::new());
+        models.upsert(ModelInfo::new("tiny", 100, 50));
+        let gateway = Gateway::new(GatewayConfig::default()).with_model_registry(models.clone());
+        let provider = Arc::new(MockProvider::new("mock").with_response("ok"));
+        gateway.register_provider(provider.clone()).await;
+
+        let mut request = PrimitiveRequest::single_user_message("x".repeat(1_000));
+        request.model = "tiny".to_string();
+        let result = gateway.complete(&request, Format::default()).await;
+        assert!(matches!(result, Err(GatewayError::ContextOverflow(ref o)) if o.context_window == 100));
+        let batch = gateway.complete_batch(std::slice::from_ref(&request), Priority::Low).await;
+        assert!(matches!(batch[0], Err(GatewayError::ContextOverflow(_))));
+        assert_eq!(provider.call_count(), 0);
+
+        // 裁剪掉较早的轮次后放得下
+        let gateway = Gateway::new(GatewayConfig::default())
+            .with_model_registry(models)
+            .with_overflow_policy(OverflowPolicy::Trim);
+        gateway.register_provider(provider.clone()).await;
+        request.messages.push(PrimitiveMessage::assistant("noted"));
+        request.messages.push(PrimitiveMessage::user("short question"));
+        assert_eq!(gateway.complete(&request, Format::default()).await.unwrap().content, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_actor_quota_blocks_requests() {
+        use crate::provider::mock::MockProvider;
+        use nl_durable::ResourceQuota;
+
+        let quotas = Arc::new(QuotaManager::new(
+            ResourceQuota::unlimited().with_llm_tokens_per_hour(100),
+        ));
+        let gateway = Gateway::new(GatewayConfig::default()).with_quotas(quotas.clone());
+        let provider = Arc::new(MockProvider::new("claude").with_response("ok"));
+        gateway.register_provider(provider.clone()).await;
+        gateway.set_provider_order(vec!["claude".to_string()]).await;
+
+        let runaway = uuid::Uuid::new_v4();
+        let other = uuid::Uuid::new_v4();
+        assert!(quotas.record_llm_tokens(runaway, 150).await.is_err());
+
+        let request = PrimitiveRequest::single_user_message("hi");
+        let blocked = gateway
+            .complete_for_actor(runaway, &request, Format::default(), Priority::Normal)
+            .await;
+        assert!(matches!(blocked, Err(GatewayError::QuotaExceeded(_))));
+        assert_eq!(provider.call_count(), 0);
+
+        gateway
+            .complete_for_actor(other, &request, Format::default(), Priority::Normal)
+            .await
+            .unwrap();
+        assert_eq!(provider.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_usage_event_published_for_actor() {
+        use crate::provider::mock::MockProvider;
+        use nl_durable::EventBusConfig;
+
+        let bus = Arc::new(EventBus::new(EventBusConfig::default()));
+        let mut events = bus.subscribe(EventKind::LlmResponseCompleted);
+        let gateway = Gateway::new(GatewayConfig::default()).with_event_bus(bus.clone());
+        gateway
+            .register_provider(Arc::new(MockProvider::new("gemini").with_response("ok")))
+            .await;
+
+        let actor = uuid::Uuid::new_v4();
+        let request = PrimitiveRequest::single_user_message("hi");
+        gateway
+            .complete_for_actor(actor, &request, Format::default(), Priority::Normal)
+            .await
+            .unwrap();
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.entity_id, actor);
+        assert_eq!(event.payload["provider"], "gemini");
+        assert_eq!(event.payload["model"], request.model.as_str());
+        assert_eq!(event.payload["locale"], "en");
+        assert_eq!(event.payload["usage"]["total_tokens"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_in_flight_request() {
+        use crate::provider::mock::MockProvider;
+
+        let gateway = Gateway::new(GatewayConfig::default());
+        gateway
+            .register_provider(Arc::new(
+                MockProvider::new("slow").with_latency(Duration::from_secs(30)),
+            ))
+            .await;
+
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            trigger.cancel();
+        });
+        let started = std::time::Instant::now();
+        let result = gateway
+            .complete_cancellable(
+                &PrimitiveRequest::single_user_message("hi"),
+                Format::default(),
+                Priority::Normal,
+                &cancel,
+            )
+            .await;
+        assert!(matches!(result, Err(GatewayError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_slow_provider_demoted_behind_fast_one() {
+        use crate::provider::mock::MockProvider;
+        use nl_durable::EventBusConfig;
+
+        let bus = Arc::new(EventBus::new(EventBusConfig::default()));
+        let mut alerts = bus.subscribe(EventKind::ProviderDemoted);
+        let slo = LatencySloConfig {
+            breach_duration: Duration::ZERO,
+            min_samples: 1,
+            ..LatencySloConfig::default()
+        }
+        .with_slo("slow", Duration::from_millis(10));
+        let gateway = Gateway::new(GatewayConfig::default())
+            .with_event_bus(bus.clone())
+            .with_latency_slo(slo);
+        let slow = Arc::new(MockProvider::new("slow").with_latency(Duration::from_millis(50)).with_response("slow"));
+        let fast = Arc::new(MockProvider::new("fast").with_response("fast"));
+        gateway.register_provider(slow.clone()).await;
+        gateway.register_provider(fast.clone()).await;
+
+        let request = PrimitiveRequest::single_user_message("hi");
+        gateway.complete(&request, Format::default()).await.unwrap();
+        let alert = alerts.recv().await.unwrap();
+        assert_eq!((alert.payload["provider"].as_str(), alert.payload["slo_ms"].as_u64()), (Some("slow"), Some(10)));
+
+        gateway.complete(&request, Format::default()).await.unwrap();
+        assert_eq!((slow.call_count(), fast.call_count()), (1, 1));
+        let stats = gateway.latency_stats();
+        assert!(stats.iter().any(|s| s.provider == "slow" && s.demoted && s.p95_ms >= 50));
+    }
+}
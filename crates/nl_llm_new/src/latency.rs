@@ -0,0 +1,297 @@
+//! 延迟 SLO 跟踪与慢 Provider 自动降级
+//!
+//! 按 Provider/模型记录成功请求的耗时，在滚动窗口内计算 p50/p95。
+//! Provider 的 p95（跨模型汇总）持续超过其 SLO 达 `breach_duration` 后，
+//! 在 `FallbackRouter` 的顺序中降到末尾，并发布 `ProviderDemoted` 告警事件；
+//! 降级期间由 `Gateway::start_slo_probes` 定时发送探测请求，
+//! 连续 `recovery_probes` 次在 SLO 内完成后恢复原顺序（`ProviderRestored`），并清空旧样本重新计算。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::primitive::PrimitiveRequest;
+
+/// 延迟 SLO 配置
+#[derive(Debug, Clone)]
+pub struct LatencySloConfig {
+    /// 按 Provider 的 p95 目标，未列出的 Provider 只统计不降级
+    pub p95_slo: HashMap<String, Duration>,
+    /// 滚动窗口长度
+    pub window: Duration,
+    /// 超标持续多久后降级
+    pub breach_duration: Duration,
+    /// 窗口内样本少于此数时不判定
+    pub min_samples: usize,
+    /// 恢复前需连续成功的探测次数
+    pub recovery_probes: u32,
+    /// 探测间隔
+    pub probe_interval: Duration,
+}
+
+impl Default for LatencySloConfig {
+    fn default() -> Self {
+        Self {
+            p95_slo: HashMap::new(),
+            window: Duration::from_secs(300),
+            breach_duration: Duration::from_secs(180),
+            min_samples: 20,
+            recovery_probes: 3,
+            probe_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl LatencySloConfig {
+    /// 设置 Provider 的 p95 目标
+    pub fn with_slo(mut self, provider: impl Into<String>, p95: Duration) -> Self {
+        self.p95_slo.insert(provider.into(), p95);
+        self
+    }
+}
+
+/// 单个 Provider/模型的延迟统计
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LatencyStats {
+    pub provider: String,
+    pub model: String,
+    /// 窗口内样本数
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    /// Provider 的 p95 目标
+    pub slo_ms: Option<u64>,
+    /// Provider 是否已降级
+    pub demoted: bool,
+}
+
+/// SLO 状态变化，由调用方应用到降级路由并发布告警
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "transition", rename_all = "snake_case")]
+pub enum SloTransition {
+    /// p95 持续超标，降级
+    Demoted { provider: String, p95_ms: u64, slo_ms: u64 },
+    /// 探测连续达标，恢复
+    Restored { provider: String, probes: u32 },
+}
+
+#[derive(Default)]
+struct ProviderState {
+    /// 模型 -> (完成时刻, 耗时毫秒)
+    samples: HashMap<String, VecDeque<(Instant, u64)>>,
+    last_model: Option<String>,
+    breach_since: Option<Instant>,
+    demoted: bool,
+    probe_successes: u32,
+}
+
+impl ProviderState {
+    fn prune(&mut self, now: Instant, window: Duration) {
+        for samples in self.samples.values_mut() {
+            while samples.front().is_some_and(|(at, _)| now.duration_since(*at) > window) {
+                samples.pop_front();
+            }
+        }
+        self.samples.retain(|_, samples| !samples.is_empty());
+    }
+}
+
+/// 延迟跟踪器
+pub struct LatencyTracker {
+    config: LatencySloConfig,
+    providers: Mutex<HashMap<String, ProviderState>>,
+}
+
+impl LatencyTracker {
+    /// 创建跟踪器
+    pub fn new(config: LatencySloConfig) -> Self {
+        Self {
+            config,
+            providers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 配置
+    pub fn config(&self) -> &LatencySloConfig {
+        &self.config
+    }
+
+    /// 记录一次成功请求的耗时，p95 持续超标时返回 `Demoted`
+    pub fn record(&self, provider: &str, model: &str, latency: Duration) -> Option<SloTransition> {
+        let now = Instant::now();
+        let mut providers = self.providers.lock().unwrap();
+        let state = providers.entry(provider.to_string()).or_default();
+        state
+            .samples
+            .entry(model.to_string())
+            .or_default()
+            .push_back((now, latency.as_millis() as u64));
+        state.last_model = Some(model.to_string());
+        state.prune(now, self.config.window);
+
+        let slo = self.config.p95_slo.get(provider)?;
+        if state.demoted {
+            return None;
+        }
+        let mut all: Vec<u64> = state.samples.values().flatten().map(|(_, ms)| *ms).collect();
+        if all.len() < self.config.min_samples.max(1) {
+            return None;
+        }
+        all.sort_unstable();
+        let p95 = percentile(&all, 0.95);
+        let slo_ms = slo.as_millis() as u64;
+        if p95 <= slo_ms {
+            state.breach_since = None;
+            return None;
+        }
+        let since = *state.breach_since.get_or_insert(now);
+        if now.duration_since(since) < self.config.breach_duration {
+            return None;
+        }
+        state.demoted = true;
+        state.breach_since = None;
+        state.probe_successes = 0;
+        Some(SloTransition::Demoted {
+            provider: provider.to_string(),
+            p95_ms: p95,
+            slo_ms,
+        })
+    }
+
+    /// 记录一次探测结果（`None` 表示失败），连续达标次数足够时返回 `Restored`
+    pub fn record_probe(&self, provider: &str, latency: Option<Duration>) -> Option<SloTransition> {
+        let mut providers = self.providers.lock().unwrap();
+        let state = providers.get_mut(provider).filter(|state| state.demoted)?;
+        let within = match (latency, self.config.p95_slo.get(provider)) {
+            (Some(latency), Some(slo)) => latency <= *slo,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if !within {
+            state.probe_successes = 0;
+            return None;
+        }
+        state.probe_successes += 1;
+        if state.probe_successes < self.config.recovery_probes.max(1) {
+            return None;
+        }
+        let probes = state.probe_successes;
+        state.demoted = false;
+        state.probe_successes = 0;
+        // 降级前的慢样本不再参与判定
+        state.samples.clear();
+        Some(SloTransition::Restored {
+            provider: provider.to_string(),
+            probes,
+        })
+    }
+
+    /// 已降级的 Provider（排序）
+    pub fn demoted(&self) -> Vec<String> {
+        let providers = self.providers.lock().unwrap();
+        let mut demoted: Vec<String> = providers
+            .iter()
+            .filter(|(_, state)| state.demoted)
+            .map(|(id, _)| id.clone())
+            .collect();
+        demoted.sort();
+        demoted
+    }
+
+    /// 探测请求：沿用该 Provider 最近一次请求的模型，只生成 1 个 token
+    pub fn probe_request(&self, provider: &str) -> Option<PrimitiveRequest> {
+        let providers = self.providers.lock().unwrap();
+        let model = providers.get(provider)?.last_model.clone()?;
+        let mut request = PrimitiveRequest::single_user_message("ping");
+        request.model = model;
+        request.parameters.max_tokens = Some(1);
+        request.metadata.no_cache = true;
+        Some(request)
+    }
+
+    /// 各 Provider/模型在当前窗口内的统计（按 Provider、模型排序）
+    pub fn stats(&self) -> Vec<LatencyStats> {
+        let now = Instant::now();
+        let mut providers = self.providers.lock().unwrap();
+        let mut stats = Vec::new();
+        for (provider, state) in providers.iter_mut() {
+            state.prune(now, self.config.window);
+            for (model, samples) in &state.samples {
+                let mut values: Vec<u64> = samples.iter().map(|(_, ms)| *ms).collect();
+                values.sort_unstable();
+                stats.push(LatencyStats {
+                    provider: provider.clone(),
+                    model: model.clone(),
+                    samples: values.len(),
+                    p50_ms: percentile(&values, 0.5),
+                    p95_ms: percentile(&values, 0.95),
+                    slo_ms: self.config.p95_slo.get(provider).map(|slo| slo.as_millis() as u64),
+                    demoted: state.demoted,
+                });
+            }
+        }
+        stats.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+        stats
+    }
+}
+
+/// 最近秩百分位（`sorted` 已升序）
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breach_demotes_and_probes_restore() {
+        let config = LatencySloConfig {
+            breach_duration: Duration::ZERO,
+            min_samples: 10,
+            recovery_probes: 2,
+            ..LatencySloConfig::default()
+        }
+        .with_slo("slow", Duration::from_millis(500));
+        let tracker = LatencyTracker::new(config);
+
+        for _ in 0..19 {
+            assert_eq!(tracker.record("slow", "m", Duration::from_millis(100)), None);
+        }
+        // 样本足够但 p95 仍在 SLO 内
+        assert_eq!(tracker.record("slow", "m", Duration::from_millis(900)), None);
+        assert_eq!(tracker.record("other", "m", Duration::from_secs(5)), None);
+        let demoted = tracker.record("slow", "m", Duration::from_millis(900));
+        assert_eq!(
+            demoted,
+            Some(SloTransition::Demoted {
+                provider: "slow".to_string(),
+                p95_ms: 900,
+                slo_ms: 500
+            })
+        );
+        assert_eq!(tracker.demoted(), vec!["slow".to_string()]);
+        let stats = &tracker.stats()[1];
+        assert_eq!((stats.samples, stats.p50_ms, stats.p95_ms, stats.demoted), (21, 100, 900, true));
+        assert_eq!(tracker.probe_request("slow").unwrap().model, "m");
+
+        assert_eq!(tracker.record_probe("slow", Some(Duration::from_millis(100))), None);
+        assert_eq!(tracker.record_probe("slow", None), None);
+        assert_eq!(tracker.record_probe("slow", Some(Duration::from_millis(100))), None);
+        assert_eq!(
+            tracker.record_probe("slow", Some(Duration::from_millis(200))),
+            Some(SloTransition::Restored {
+                provider: "slow".to_string(),
+                probes: 2
+            })
+        );
+        assert!(tracker.demoted().is_empty());
+        assert_eq!(tracker.stats().len(), 1);
+    }
+}
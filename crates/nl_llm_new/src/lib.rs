@@ -7,6 +7,10 @@
 //! - 认证管理
 //! - 黑魔法代理聚合
 //! - 分层容错机制
+//! - 对话分支（分叉、合并、丢弃，事件持久化）
+//! - 请求/响应中间件
+//! - 发送前的凭证检测
+//! - 按对话检测语言区域、多语言系统提示词与回答语言
 
 pub mod auth;
 pub mod primitive;
@@ -16,13 +20,42 @@ pub mod provider;
 pub mod gateway;
 pub mod fallback;
 pub mod token_bucket;
+pub mod scheduler;
+pub mod concurrency;
+pub mod latency;
+pub mod prefix_cache;
+pub mod recording;
+pub mod response_cache;
+pub mod routing;
+pub mod transcript;
+pub mod model_registry;
+pub mod session;
+pub mod conversation;
+pub mod middleware;
+pub mod secret_guard;
+pub mod locale;
 
 // 重导出常用类型
 pub use primitive::{PrimitiveRequest, PrimitiveMessage, PrimitiveContent, PrimitiveTool};
 pub use translator::{Format, WrapperKind, TranslatorPipeline, TranslateError};
 pub use auth::{Auth, ApiKeyConfig, OAuthProvider, SAProvider, TokenStorage, TokenStatus};
 pub use gateway::{Gateway, GatewayConfig, GatewayError};
+pub use fallback::{FallbackConfig, FallbackRouter, RaceEvent, RacePolicy};
 pub use provider::{LlmProvider, LlmResponse, ProviderError};
+pub use scheduler::{Priority, RateLimitScheduler, SchedulerConfig};
+pub use concurrency::{ConcurrencyLimiter, ConcurrencyStats};
+pub use latency::{LatencySloConfig, LatencyStats, LatencyTracker, SloTransition};
+pub use prefix_cache::{PrefixCache, PrefixCacheHint, PrefixCacheStats};
+pub use recording::{RecordMode, ResponseRecorder};
+pub use response_cache::{ResponseCache, ResponseCacheConfig, ResponseCacheStats};
+pub use routing::{RouteContext, RouteTarget, RoutingRule, RoutingRules};
+pub use transcript::{TranscriptEntry, TranscriptRecorder};
+pub use model_registry::{ContextOverflow, ModelInfo, ModelRegistry, OverflowPolicy};
+pub use session::{SessionHandle, SessionStore};
+pub use conversation::{BranchStatus, Conversation, ConversationBranch, SessionManager, MAIN_BRANCH};
+pub use middleware::{ErrorAction, MeterReading, Middleware, MiddlewareChain, MiddlewareConfig, RequestContext};
+pub use secret_guard::{SecretAction, SecretFinding, SecretGuard, SecretKind, SecretPolicy};
+pub use locale::{Locale, LocaleTracker, LocalizedPrompts, ResponseLanguage};
 
 /// 模块错误类型
 #[derive(Debug, thiserror::Error)]
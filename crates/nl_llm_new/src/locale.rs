@@ -0,0 +1,236 @@
+//! 语言区域
+//!
+//! 团队中英文混用，每个对话按用户消息检测语言区域（`Locale::detect`：比较汉字数与英文单词数，代码块不计），
+//! 检测不出（如消息只有代码或数字）时沿用该对话上一次的结果：
+//! - `LocaleTracker` 按对话记住最近的区域，`Gateway::complete_in_conversation` 据此补全请求的 `metadata.locale`；
+//!   `SessionManager` 把区域记在对话消息事件上，重放后仍可沿用
+//! - `LocalizedPrompts` 保存系统提示词模板的各语言版本，缺少对应版本时回退到英文
+//! - `ResponseLanguage` 是 Gateway 选项：跟随用户语言或强制指定语言，在系统提示词末尾追加回答语言的指令
+//!
+//! 请求的区域写入 `LlmRequestStarted` / `LlmResponseCompleted` / `LlmError` 事件，供用量分析按语言统计。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::primitive::{PrimitiveContent, PrimitiveRequest, Role};
+
+/// 语言区域
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Locale {
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en")]
+    En,
+}
+
+impl Locale {
+    /// BCP 47 标签
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+
+    /// 检测文本的语言区域：汉字数不少于英文单词数时为中文，两者都没有时为 `None`
+    pub fn detect(text: &str) -> Option<Self> {
+        let (mut han, mut words) = (0usize, 0usize);
+        let mut in_code = false;
+        for line in text.lines() {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                continue;
+            }
+            if in_code {
+                continue;
+            }
+            han += line.chars().filter(|c| is_han(*c)).count();
+            words += line
+                .split(|c: char| !c.is_ascii_alphabetic())
+                .filter(|w| w.len() >= 2)
+                .count();
+        }
+        match (han, words) {
+            (0, 0) => None,
+            (han, words) if han >= words => Some(Locale::ZhCn),
+            _ => Some(Locale::En),
+        }
+    }
+
+    /// 按最后一条用户消息检测请求的语言区域
+    pub fn detect_request(primitive: &PrimitiveRequest) -> Option<Self> {
+        let message = primitive.messages.iter().rev().find(|m| m.role == Role::User)?;
+        let text: Vec<&str> = message
+            .content
+            .iter()
+            .filter_map(|c| match c {
+                PrimitiveContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        Self::detect(&text.join("\n"))
+    }
+
+    /// 要求以该语言回答的指令
+    pub fn response_instruction(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "请使用简体中文回答（代码、命令与专有名词保持原文）。",
+            Locale::En => "Respond in English (keep code, commands and proper nouns as they are).",
+        }
+    }
+}
+
+fn is_han(c: char) -> bool {
+    matches!(c, '\u{4e00}'..='\u{9fff}' | '\u{3400}'..='\u{4dbf}' | '\u{f900}'..='\u{faff}')
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = crate::Error;
+
+    /// 解析 `zh`、`zh-CN`、`zh_Hans`、`en`、`en-US` 等标签
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let tag = s.trim().to_ascii_lowercase();
+        match tag.split(['-', '_']).next() {
+            Some("zh") => Ok(Locale::ZhCn),
+            Some("en") => Ok(Locale::En),
+            _ => Err(crate::Error::Unknown(format!("unsupported locale: {}", s))),
+        }
+    }
+}
+
+/// 回答语言（Gateway 选项）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseLanguage {
+    /// 与用户消息的语言一致
+    MatchUser,
+    /// 始终使用指定语言
+    Force(Locale),
+}
+
+impl ResponseLanguage {
+    /// 回答应使用的语言（跟随用户但检测不出时为 `None`，不追加指令）
+    pub fn resolve(&self, user: Option<Locale>) -> Option<Locale> {
+        match self {
+            ResponseLanguage::MatchUser => user,
+            ResponseLanguage::Force(locale) => Some(*locale),
+        }
+    }
+}
+
+/// 按对话记住最近检测到的语言区域
+#[derive(Debug, Default)]
+pub struct LocaleTracker {
+    locales: RwLock<HashMap<String, Locale>>,
+}
+
+impl LocaleTracker {
+    /// 创建空的记录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求的语言区域：显式指定或检测到时更新记录，否则沿用该对话上一次的结果
+    pub fn observe(&self, conversation: &str, primitive: &PrimitiveRequest) -> Option<Locale> {
+        match primitive.metadata.locale.or_else(|| Locale::detect_request(primitive)) {
+            Some(locale) => {
+                self.locales.write().unwrap().insert(conversation.to_string(), locale);
+                Some(locale)
+            }
+            None => self.get(conversation),
+        }
+    }
+
+    /// 对话最近的语言区域
+    pub fn get(&self, conversation: &str) -> Option<Locale> {
+        self.locales.read().unwrap().get(conversation).copied()
+    }
+
+    /// 忘记对话的记录
+    pub fn forget(&self, conversation: &str) -> Option<Locale> {
+        self.locales.write().unwrap().remove(conversation)
+    }
+}
+
+/// 系统提示词模板的各语言版本（JSON：`{ "<名称>": { "zh-CN": "...", "en": "..." } }`）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LocalizedPrompts {
+    prompts: BTreeMap<String, BTreeMap<Locale, String>>,
+}
+
+impl LocalizedPrompts {
+    /// 从文件加载（文件不存在时为空）
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 添加一个语言版本
+    pub fn with_prompt(mut self, name: impl Into<String>, locale: Locale, text: impl Into<String>) -> Self {
+        self.prompts.entry(name.into()).or_default().insert(locale, text.into());
+        self
+    }
+
+    /// 取模板的语言版本：优先指定语言，其次英文，再次任一版本
+    pub fn get(&self, name: &str, locale: Option<Locale>) -> Option<&str> {
+        let versions = self.prompts.get(name)?;
+        locale
+            .and_then(|l| versions.get(&l))
+            .or_else(|| versions.get(&Locale::En))
+            .or_else(|| versions.values().next())
+            .map(String::as_str)
+    }
+
+    /// 模板名称
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.prompts.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::PrimitiveMessage;
+
+    #[test]
+    fn test_detect_track_and_localize() {
+        assert_eq!(Locale::detect("帮我把 parser 重构一下"), Some(Locale::ZhCn));
+        assert_eq!(Locale::detect("Please fix the bug in 解析器"), Some(Locale::En));
+        assert_eq!(Locale::detect("修复：\n```rust\nfn main() { let value = parse(input); }\n```"), Some(Locale::ZhCn));
+        assert_eq!(Locale::detect("42 + 1"), None);
+        assert_eq!("zh_Hans".parse::<Locale>().unwrap(), Locale::ZhCn);
+        assert_eq!("en-US".parse::<Locale>().unwrap(), Locale::En);
+        assert!("fr".parse::<Locale>().is_err());
+
+        let tracker = LocaleTracker::new();
+        let mut request = PrimitiveRequest::new("m");
+        request.messages.push(PrimitiveMessage::user("这个测试为什么失败？"));
+        assert_eq!(tracker.observe("c1", &request), Some(Locale::ZhCn));
+        request.messages.push(PrimitiveMessage::user("```\ncargo test\n```"));
+        assert_eq!(tracker.observe("c1", &request), Some(Locale::ZhCn));
+        assert_eq!(tracker.observe("c2", &request), None);
+
+        let prompts: LocalizedPrompts =
+            serde_json::from_str(r#"{ "reviewer": { "en": "You review code.", "zh-CN": "你负责代码评审。" } }"#).unwrap();
+        assert_eq!(prompts.get("reviewer", Some(Locale::ZhCn)), Some("你负责代码评审。"));
+        let prompts = LocalizedPrompts::default().with_prompt("planner", Locale::En, "You plan tasks.");
+        assert_eq!(prompts.get("planner", Some(Locale::ZhCn)), Some("You plan tasks."));
+        assert_eq!(ResponseLanguage::Force(Locale::En).resolve(Some(Locale::ZhCn)), Some(Locale::En));
+        assert_eq!(ResponseLanguage::MatchUser.resolve(None), None);
+    }
+}
@@ -0,0 +1,556 @@
+//! Provider 请求/响应中间件
+//!
+//! 在不修改 Provider 的前提下插入自定义行为：附加请求头、改写请求体、对响应做后处理。
+//! `MiddlewareChain` 按注册顺序执行 `before_request`，按相反顺序执行 `after_response` 与 `on_error`
+//! （先注册的中间件在最外层）；每次失败都会通知全部中间件，第一个返回 `ErrorAction::Retry` 的决定重试。
+//!
+//! Gateway 内置两层：`MeteringMiddleware`（按 Provider/模型统计请求、失败、token 与耗时）
+//! 与 `RetryMiddleware`（429/5xx 指数退避重试）；其余中间件经 `Gateway::with_middleware` 注册，
+//! 或在 `MiddlewareConfig`（JSON 文件）中声明：
+//! - `headers`：附加请求头
+//! - `body`：合并请求体顶层字段
+//! - `redact`：用 `Redactor` 脱敏发出的请求体与/或返回的响应内容
+//!
+//! 请求头随请求体的 `_gw_headers` 字段传给 Provider，由 `GenericClient` 在发送前取出。
+//! 响应缓存命中与回放不经过中间件；批量请求逐条执行钩子，失败统一由逐条回退处理。
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use nl_durable::{RedactionConfig, Redactor};
+
+use crate::gateway::GatewayError;
+use crate::provider::{LlmResponse, ProviderError};
+
+/// 请求体中携带附加请求头的字段
+pub const HEADERS_FIELD: &str = "_gw_headers";
+
+/// 单次重试的最长等待
+const MAX_RETRY_DELAY_MS: u64 = 30_000;
+
+/// 一次发往 Provider 的请求
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// Provider ID
+    pub provider_id: String,
+    /// 模型
+    pub model: String,
+    /// 第几次尝试（从 1 开始）
+    pub attempt: u32,
+    /// Provider 编译后的请求体
+    pub body: Value,
+    /// 附加请求头
+    pub headers: BTreeMap<String, String>,
+    /// 开始发送的时间
+    pub started_at: Instant,
+}
+
+impl RequestContext {
+    /// 创建第一次尝试的请求
+    pub fn new(provider_id: impl Into<String>, model: impl Into<String>, body: Value) -> Self {
+        Self {
+            provider_id: provider_id.into(),
+            model: model.into(),
+            attempt: 1,
+            body,
+            headers: BTreeMap::new(),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// 设置尝试次数
+    pub fn with_attempt(mut self, attempt: u32) -> Self {
+        self.attempt = attempt;
+        self
+    }
+
+    /// 交给 Provider 的请求体（有附加请求头时写入 `_gw_headers`）
+    pub fn outgoing_body(&self) -> Value {
+        let mut body = self.body.clone();
+        if !self.headers.is_empty() {
+            if let Some(obj) = body.as_object_mut() {
+                obj.insert(HEADERS_FIELD.to_string(), serde_json::json!(self.headers));
+            }
+        }
+        body
+    }
+}
+
+/// 从请求体中取出附加请求头
+pub fn take_headers(body: &mut Value) -> Vec<(String, String)> {
+    let Some(Value::Object(headers)) = body.as_object_mut().and_then(|obj| obj.remove(HEADERS_FIELD)) else {
+        return Vec::new();
+    };
+    headers
+        .into_iter()
+        .filter_map(|(name, value)| value.as_str().map(|v| (name, v.to_string())))
+        .collect()
+}
+
+/// 失败后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// 交给上层（降级或返回错误）
+    Propagate,
+    /// 等待后在同一 Provider 重试
+    Retry(Duration),
+}
+
+/// 请求/响应中间件
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// 名称（出现在错误信息中）
+    fn name(&self) -> &str;
+
+    /// 发送前修改请求体或附加请求头，返回错误时放弃本次请求
+    async fn before_request(&self, _ctx: &mut RequestContext) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// 成功响应的后处理
+    async fn after_response(&self, _ctx: &RequestContext, _response: &mut LlmResponse) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// 请求失败时的通知，可要求重试
+    async fn on_error(&self, _ctx: &RequestContext, _error: &ProviderError) -> ErrorAction {
+        ErrorAction::Propagate
+    }
+}
+
+/// 有序的中间件链
+#[derive(Clone, Default)]
+pub struct MiddlewareChain {
+    layers: Vec<Arc<dyn Middleware>>,
+}
+
+impl MiddlewareChain {
+    /// 创建空链
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加中间件
+    pub fn with(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.layers.push(middleware);
+        self
+    }
+
+    /// 追加中间件
+    pub fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.layers.push(middleware);
+    }
+
+    /// 按执行顺序列出中间件名称
+    pub fn names(&self) -> Vec<&str> {
+        self.layers.iter().map(|m| m.name()).collect()
+    }
+
+    /// 按注册顺序执行 `before_request`
+    pub async fn before_request(&self, ctx: &mut RequestContext) -> Result<(), GatewayError> {
+        for layer in &self.layers {
+            layer.before_request(ctx).await.map_err(|e| middleware_error(layer.as_ref(), e))?;
+        }
+        Ok(())
+    }
+
+    /// 按相反顺序执行 `after_response`
+    pub async fn after_response(&self, ctx: &RequestContext, response: &mut LlmResponse) -> Result<(), GatewayError> {
+        for layer in self.layers.iter().rev() {
+            layer
+                .after_response(ctx, response)
+                .await
+                .map_err(|e| middleware_error(layer.as_ref(), e))?;
+        }
+        Ok(())
+    }
+
+    /// 按相反顺序通知 `on_error`，返回第一个重试决定
+    pub async fn on_error(&self, ctx: &RequestContext, error: &ProviderError) -> ErrorAction {
+        let mut action = ErrorAction::Propagate;
+        for layer in self.layers.iter().rev() {
+            let decision = layer.on_error(ctx, error).await;
+            if action == ErrorAction::Propagate {
+                action = decision;
+            }
+        }
+        action
+    }
+}
+
+fn middleware_error(layer: &dyn Middleware, error: crate::Error) -> GatewayError {
+    GatewayError::Middleware {
+        middleware: layer.name().to_string(),
+        message: error.to_string(),
+    }
+}
+
+/// 是否作用于指定 Provider（列表为空时作用于全部）
+fn applies_to(providers: &[String], provider_id: &str) -> bool {
+    providers.is_empty() || providers.iter().any(|p| p == provider_id)
+}
+
+/// 可重试错误（429/5xx）的指数退避重试
+#[derive(Debug, Clone)]
+pub struct RetryMiddleware {
+    max_retries: u32,
+    base_delay_ms: u64,
+}
+
+impl RetryMiddleware {
+    /// 最多重试 `max_retries` 次，第 n 次重试等待 `base_delay_ms * 2^(n-1)`（Provider 给出 retry-after 时以其为准）
+    pub fn new(max_retries: u32, base_delay_ms: u64) -> Self {
+        Self {
+            max_retries,
+            base_delay_ms,
+        }
+    }
+
+    /// 第 `retry` 次重试前的等待时间
+    pub fn delay(&self, retry: u32, error: &ProviderError) -> Duration {
+        if let Some(retry_after) = error.retry_after_ms {
+            return Duration::from_millis(retry_after);
+        }
+        let delay = self.base_delay_ms.saturating_mul(1 << retry.saturating_sub(1).min(16));
+        Duration::from_millis(delay.min(MAX_RETRY_DELAY_MS))
+    }
+}
+
+#[async_trait]
+impl Middleware for RetryMiddleware {
+    fn name(&self) -> &str {
+        "retry"
+    }
+
+    async fn on_error(&self, ctx: &RequestContext, error: &ProviderError) -> ErrorAction {
+        if error.retryable && ctx.attempt <= self.max_retries {
+            ErrorAction::Retry(self.delay(ctx.attempt, error))
+        } else {
+            ErrorAction::Propagate
+        }
+    }
+}
+
+/// 单个 Provider/模型的计量
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MeterReading {
+    /// Provider ID
+    pub provider: String,
+    /// 模型
+    pub model: String,
+    /// 完成的尝试数（含失败）
+    pub requests: u64,
+    /// 失败的尝试数
+    pub failures: u64,
+    /// 输入 token 数
+    pub input_tokens: u64,
+    /// 输出 token 数（含思考）
+    pub output_tokens: u64,
+    /// 累计耗时（毫秒）
+    pub latency_ms: u64,
+}
+
+/// 按 Provider/模型计量每次尝试
+#[derive(Debug, Default)]
+pub struct MeteringMiddleware {
+    readings: Mutex<HashMap<(String, String), MeterReading>>,
+}
+
+impl MeteringMiddleware {
+    /// 创建计量中间件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前计量（按 Provider、模型排序）
+    pub fn snapshot(&self) -> Vec<MeterReading> {
+        let mut readings: Vec<MeterReading> = self.readings.lock().unwrap().values().cloned().collect();
+        readings.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+        readings
+    }
+
+    fn record(&self, ctx: &RequestContext, update: impl FnOnce(&mut MeterReading)) {
+        let mut readings = self.readings.lock().unwrap();
+        let reading = readings
+            .entry((ctx.provider_id.clone(), ctx.model.clone()))
+            .or_insert_with(|| MeterReading {
+                provider: ctx.provider_id.clone(),
+                model: ctx.model.clone(),
+                ..Default::default()
+            });
+        reading.requests += 1;
+        reading.latency_ms += ctx.started_at.elapsed().as_millis() as u64;
+        update(reading);
+    }
+}
+
+#[async_trait]
+impl Middleware for MeteringMiddleware {
+    fn name(&self) -> &str {
+        "metering"
+    }
+
+    async fn after_response(&self, ctx: &RequestContext, response: &mut LlmResponse) -> crate::Result<()> {
+        let usage = &response.usage;
+        self.record(ctx, |reading| {
+            reading.input_tokens += usage.input_tokens;
+            reading.output_tokens += usage.output_tokens + usage.thinking_tokens.unwrap_or(0);
+        });
+        Ok(())
+    }
+
+    async fn on_error(&self, ctx: &RequestContext, _error: &ProviderError) -> ErrorAction {
+        self.record(ctx, |reading| reading.failures += 1);
+        ErrorAction::Propagate
+    }
+}
+
+/// 附加请求头
+#[derive(Debug, Clone)]
+pub struct HeadersMiddleware {
+    headers: BTreeMap<String, String>,
+    providers: Vec<String>,
+}
+
+impl HeadersMiddleware {
+    /// 对全部 Provider 附加请求头
+    pub fn new(headers: BTreeMap<String, String>) -> Self {
+        Self {
+            headers,
+            providers: Vec::new(),
+        }
+    }
+
+    /// 只作用于指定 Provider
+    pub fn for_providers(mut self, providers: Vec<String>) -> Self {
+        self.providers = providers;
+        self
+    }
+}
+
+#[async_trait]
+impl Middleware for HeadersMiddleware {
+    fn name(&self) -> &str {
+        "headers"
+    }
+
+    async fn before_request(&self, ctx: &mut RequestContext) -> crate::Result<()> {
+        if applies_to(&self.providers, &ctx.provider_id) {
+            ctx.headers.extend(self.headers.clone());
+        }
+        Ok(())
+    }
+}
+
+/// 合并请求体顶层字段
+#[derive(Debug, Clone)]
+pub struct BodyMiddleware {
+    fields: Map<String, Value>,
+    providers: Vec<String>,
+}
+
+impl BodyMiddleware {
+    /// 对全部 Provider 合并字段（覆盖同名字段）
+    pub fn new(fields: Map<String, Value>) -> Self {
+        Self {
+            fields,
+            providers: Vec::new(),
+        }
+    }
+
+    /// 只作用于指定 Provider
+    pub fn for_providers(mut self, providers: Vec<String>) -> Self {
+        self.providers = providers;
+        self
+    }
+}
+
+#[async_trait]
+impl Middleware for BodyMiddleware {
+    fn name(&self) -> &str {
+        "body"
+    }
+
+    async fn before_request(&self, ctx: &mut RequestContext) -> crate::Result<()> {
+        if !applies_to(&self.providers, &ctx.provider_id) {
+            return Ok(());
+        }
+        if let Some(obj) = ctx.body.as_object_mut() {
+            obj.extend(self.fields.clone());
+        }
+        Ok(())
+    }
+}
+
+/// 脱敏发出的请求体与/或返回的响应内容
+#[derive(Debug, Clone)]
+pub struct RedactionMiddleware {
+    redactor: Redactor,
+    request: bool,
+    response: bool,
+}
+
+impl RedactionMiddleware {
+    /// 同时脱敏请求与响应
+    pub fn new(redactor: Redactor) -> Self {
+        Self {
+            redactor,
+            request: true,
+            response: true,
+        }
+    }
+
+    /// 设置脱敏范围
+    pub fn with_scope(mut self, request: bool, response: bool) -> Self {
+        self.request = request;
+        self.response = response;
+        self
+    }
+}
+
+#[async_trait]
+impl Middleware for RedactionMiddleware {
+    fn name(&self) -> &str {
+        "redact"
+    }
+
+    async fn before_request(&self, ctx: &mut RequestContext) -> crate::Result<()> {
+        if self.request {
+            self.redactor.redact(&mut ctx.body);
+        }
+        Ok(())
+    }
+
+    async fn after_response(&self, _ctx: &RequestContext, response: &mut LlmResponse) -> crate::Result<()> {
+        if self.response {
+            if let std::borrow::Cow::Owned(redacted) = self.redactor.redact_str(&response.content) {
+                response.content = redacted;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 配置文件中的一个中间件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MiddlewareSpec {
+    /// 附加请求头
+    Headers {
+        headers: BTreeMap<String, String>,
+        /// 作用的 Provider（为空时作用于全部）
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        providers: Vec<String>,
+    },
+    /// 合并请求体字段
+    Body {
+        set: Map<String, Value>,
+        /// 作用的 Provider（为空时作用于全部）
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        providers: Vec<String>,
+    },
+    /// 脱敏
+    Redact {
+        #[serde(default = "default_true")]
+        request: bool,
+        #[serde(default = "default_true")]
+        response: bool,
+        /// 追加的脱敏规则
+        #[serde(default)]
+        rules: RedactionConfig,
+    },
+}
+
+impl MiddlewareSpec {
+    /// 创建对应的中间件
+    pub fn build(&self) -> crate::Result<Arc<dyn Middleware>> {
+        Ok(match self {
+            MiddlewareSpec::Headers { headers, providers } => {
+                Arc::new(HeadersMiddleware::new(headers.clone()).for_providers(providers.clone()))
+            }
+            MiddlewareSpec::Body { set, providers } => {
+                Arc::new(BodyMiddleware::new(set.clone()).for_providers(providers.clone()))
+            }
+            MiddlewareSpec::Redact { request, response, rules } => {
+                let redactor = Redactor::from_config(rules).map_err(|e| crate::Error::Unknown(e.to_string()))?;
+                Arc::new(RedactionMiddleware::new(redactor).with_scope(*request, *response))
+            }
+        })
+    }
+}
+
+/// 中间件配置（按顺序注册到内置中间件之后）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MiddlewareConfig {
+    #[serde(default)]
+    pub middleware: Vec<MiddlewareSpec>,
+}
+
+impl MiddlewareConfig {
+    /// 从 JSON 文件加载
+    pub fn load(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// 按顺序创建中间件
+    pub fn build(&self) -> crate::Result<Vec<Arc<dyn Middleware>>> {
+        self.middleware.iter().map(MiddlewareSpec::build).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{StopReason, Usage};
+
+    #[tokio::test]
+    async fn test_configured_chain_mutates_request_and_response() {
+        let config: MiddlewareConfig = serde_json::from_value(serde_json::json!({
+            "middleware": [
+                { "type": "headers", "headers": { "x-team": "loom" } },
+                { "type": "headers", "headers": { "x-beta": "tools" }, "providers": ["other"] },
+                { "type": "body", "set": { "user": "nl" } },
+                { "type": "redact", "request": false }
+            ]
+        }))
+        .unwrap();
+        let mut chain = MiddlewareChain::new().with(Arc::new(RetryMiddleware::new(1, 10)));
+        for middleware in config.build().unwrap() {
+            chain.push(middleware);
+        }
+        assert_eq!(chain.names(), ["retry", "headers", "headers", "body", "redact"]);
+
+        let mut ctx = RequestContext::new("claude", "m", serde_json::json!({ "prompt": "sk-ant-0123456789abcdefgh" }));
+        chain.before_request(&mut ctx).await.unwrap();
+        let mut body = ctx.outgoing_body();
+        assert_eq!(body["user"], "nl");
+        assert_eq!(body["prompt"], "sk-ant-0123456789abcdefgh");
+        assert_eq!(take_headers(&mut body), vec![("x-team".to_string(), "loom".to_string())]);
+        assert!(body.get(HEADERS_FIELD).is_none());
+
+        let mut response = LlmResponse {
+            content: "key is sk-ant-0123456789abcdefgh".to_string(),
+            tool_calls: Vec::new(),
+            usage: Usage::default(),
+            stop_reason: StopReason::EndTurn,
+        };
+        chain.after_response(&ctx, &mut response).await.unwrap();
+        assert_eq!(response.content, "key is [REDACTED]");
+
+        let busy = ProviderError::from_http_status(503, "busy");
+        assert_eq!(chain.on_error(&ctx, &busy).await, ErrorAction::Retry(Duration::from_millis(10)));
+        let ctx = ctx.with_attempt(2);
+        assert_eq!(chain.on_error(&ctx, &busy).await, ErrorAction::Propagate);
+    }
+}
@@ -0,0 +1,317 @@
+//! 模型元数据登记与上下文溢出保护
+//!
+//! 记录各模型的上下文窗口、最大输出与能力（工具调用、视觉），Gateway 发送前据此检查提示词是否放得下：
+//! - 内置常用模型的默认值，`refresh_from` 用 Provider 目录接口（如 Gemini `models.list`）返回的数据覆盖
+//! - 按模型名精确匹配，其次按最长前缀匹配（`gemini-2.5-flash-001` 命中 `gemini-2.5-flash`）；未登记的模型不检查
+//! - 提示词 token 数用模型对应的分词器（`with_tokenizers`）计算，没有词表的模型按字符估算；
+//!   加上 `max_tokens` 预留的输出超过窗口时，按 `OverflowPolicy` 拒绝（`ContextOverflow`）或丢弃最早的对话轮次直到放得下
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use nl_core::tokenizer::{HeuristicTokenizer, Tokenizer, TokenizerRegistry};
+use serde::{Deserialize, Serialize};
+
+use crate::primitive::{PrimitiveContent, PrimitiveMessage, PrimitiveRequest, Role};
+use crate::provider::LlmProvider;
+
+/// 每张图片按固定 token 数估算
+const IMAGE_TOKENS: u64 = 1_000;
+
+/// 模型元数据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// 模型标识
+    pub id: String,
+    /// 上下文窗口（输入 + 输出 token）
+    pub context_window: u64,
+    /// 单次最大输出 token
+    pub max_output_tokens: u64,
+    /// 是否支持工具调用
+    #[serde(default)]
+    pub supports_tools: bool,
+    /// 是否支持图片输入
+    #[serde(default)]
+    pub supports_vision: bool,
+}
+
+impl ModelInfo {
+    /// 创建模型元数据（能力默认关闭）
+    pub fn new(id: impl Into<String>, context_window: u64, max_output_tokens: u64) -> Self {
+        Self {
+            id: id.into(),
+            context_window,
+            max_output_tokens,
+            supports_tools: false,
+            supports_vision: false,
+        }
+    }
+
+    /// 声明支持工具调用
+    pub fn with_tools(mut self) -> Self {
+        self.supports_tools = true;
+        self
+    }
+
+    /// 声明支持图片输入
+    pub fn with_vision(mut self) -> Self {
+        self.supports_vision = true;
+        self
+    }
+}
+
+/// 超出上下文窗口时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// 直接拒绝
+    #[default]
+    Reject,
+    /// 丢弃最早的对话轮次（保留系统提示词与最后一条消息）
+    Trim,
+}
+
+/// 提示词超出模型上下文窗口
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextOverflow {
+    pub model: String,
+    /// 估算的提示词 token 数（裁剪后仍超出时为裁剪后的值）
+    pub prompt_tokens: u64,
+    /// 为输出预留的 token 数
+    pub reserved_output_tokens: u64,
+    pub context_window: u64,
+}
+
+impl std::fmt::Display for ContextOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "prompt of ~{} tokens plus {} reserved output tokens exceeds the {}-token context window of {}",
+            self.prompt_tokens, self.reserved_output_tokens, self.context_window, self.model
+        )
+    }
+}
+
+/// 模型元数据登记表
+#[derive(Debug, Default)]
+pub struct ModelRegistry {
+    models: RwLock<HashMap<String, ModelInfo>>,
+    tokenizers: Arc<TokenizerRegistry>,
+}
+
+impl ModelRegistry {
+    /// 创建空登记表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 预置常用模型
+    pub fn with_builtin() -> Self {
+        let registry = Self::new();
+        for info in builtin_models() {
+            registry.upsert(info);
+        }
+        registry
+    }
+
+    /// 设置计算提示词 token 数的分词器
+    pub fn with_tokenizers(mut self, tokenizers: Arc<TokenizerRegistry>) -> Self {
+        self.tokenizers = tokenizers;
+        self
+    }
+
+    /// 新增或覆盖模型
+    pub fn upsert(&self, info: ModelInfo) {
+        self.models
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(info.id.clone(), info);
+    }
+
+    /// 查询模型（精确匹配优先，其次最长前缀）
+    pub fn get(&self, model: &str) -> Option<ModelInfo> {
+        let models = self.models.read().unwrap_or_else(|e| e.into_inner());
+        models.get(model).cloned().or_else(|| {
+            models
+                .values()
+                .filter(|info| model.starts_with(&info.id))
+                .max_by_key(|info| info.id.len())
+                .cloned()
+        })
+    }
+
+    /// 已登记的模型数
+    pub fn len(&self) -> usize {
+        self.models.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 从 Provider 的模型目录刷新，返回更新的模型数
+    pub async fn refresh_from(&self, provider: &dyn LlmProvider) -> crate::Result<usize> {
+        let models = provider.list_models().await?;
+        let count = models.len();
+        for info in models {
+            self.upsert(info);
+        }
+        Ok(count)
+    }
+
+    /// 检查请求是否放得进模型窗口，`Trim` 时返回裁剪后的请求
+    pub fn fit<'a>(
+        &self,
+        request: Cow<'a, PrimitiveRequest>,
+        policy: OverflowPolicy,
+    ) -> Result<Cow<'a, PrimitiveRequest>, ContextOverflow> {
+        let Some(info) = self.get(&request.model) else {
+            return Ok(request);
+        };
+        let reserved = request
+            .parameters
+            .max_tokens
+            .unwrap_or(0)
+            .min(info.max_output_tokens);
+        let budget = info.context_window.saturating_sub(reserved);
+        let overflow = |prompt_tokens| ContextOverflow {
+            model: request.model.clone(),
+            prompt_tokens,
+            reserved_output_tokens: reserved,
+            context_window: info.context_window,
+        };
+
+        let tokenizer = self.tokenizers.for_model(&request.model);
+        let prompt_tokens = count_prompt_tokens(&request, tokenizer.as_ref());
+        if prompt_tokens <= budget {
+            return Ok(request);
+        }
+        if policy == OverflowPolicy::Reject {
+            return Err(overflow(prompt_tokens));
+        }
+
+        let mut trimmed = request.as_ref().clone();
+        while count_prompt_tokens(&trimmed, tokenizer.as_ref()) > budget && trimmed.messages.len() > 1 {
+            trimmed.messages.remove(0);
+            // 不以孤立的工具结果或助手回复开头
+            while trimmed.messages.len() > 1 && !starts_user_turn(&trimmed.messages[0]) {
+                trimmed.messages.remove(0);
+            }
+        }
+        let prompt_tokens = count_prompt_tokens(&trimmed, tokenizer.as_ref());
+        if prompt_tokens > budget {
+            return Err(overflow(prompt_tokens));
+        }
+        tracing::debug!(
+            "trimmed {} oldest messages to fit {} context window",
+            request.messages.len() - trimmed.messages.len(),
+            info.id
+        );
+        Ok(Cow::Owned(trimmed))
+    }
+}
+
+/// 估算请求的提示词 token 数（系统提示词、消息与工具定义）
+pub fn estimate_prompt_tokens(request: &PrimitiveRequest) -> u64 {
+    count_prompt_tokens(request, &HeuristicTokenizer)
+}
+
+/// 用分词器计算请求的提示词 token 数（图片按固定数计）
+pub fn count_prompt_tokens(request: &PrimitiveRequest, tokenizer: &dyn Tokenizer) -> u64 {
+    let mut tokens = request.system.as_deref().map_or(0, |s| tokenizer.count(s));
+    for message in &request.messages {
+        for content in &message.content {
+            tokens += match content {
+                PrimitiveContent::Text { text } | PrimitiveContent::Thinking { text } => tokenizer.count(text),
+                PrimitiveContent::ToolResult { content, .. } => tokenizer.count(content),
+                PrimitiveContent::ToolCall { name, arguments, .. } => {
+                    tokenizer.count(name) + tokenizer.count(&arguments.to_string())
+                }
+                PrimitiveContent::Image { .. } => IMAGE_TOKENS as usize,
+            };
+        }
+    }
+    for tool in &request.tools {
+        tokens += tokenizer.count(&tool.name)
+            + tool.description.as_deref().map_or(0, |d| tokenizer.count(d))
+            + tokenizer.count(&tool.input_schema.to_string());
+    }
+    tokens as u64
+}
+
+fn starts_user_turn(message: &PrimitiveMessage) -> bool {
+    message.role == Role::User
+        && !message.content.iter().any(|c| matches!(c, PrimitiveContent::ToolResult { .. }))
+}
+
+/// 内置的常用模型（Provider 目录可覆盖）
+fn builtin_models() -> Vec<ModelInfo> {
+    vec![
+        ModelInfo::new("gemini-2.5-pro", 1_048_576, 65_536).with_tools().with_vision(),
+        ModelInfo::new("gemini-2.5-flash", 1_048_576, 65_536).with_tools().with_vision(),
+        ModelInfo::new("gemini-2.0-flash", 1_048_576, 8_192).with_tools().with_vision(),
+        ModelInfo::new("gemini-1.5-pro", 2_097_152, 8_192).with_tools().with_vision(),
+        ModelInfo::new("gemini-1.5-flash", 1_048_576, 8_192).with_tools().with_vision(),
+        ModelInfo::new("claude-3-5-sonnet", 200_000, 8_192).with_tools().with_vision(),
+        ModelInfo::new("claude-3-5-haiku", 200_000, 8_192).with_tools(),
+        ModelInfo::new("claude-sonnet-4", 200_000, 64_000).with_tools().with_vision(),
+        ModelInfo::new("claude-opus-4", 200_000, 32_000).with_tools().with_vision(),
+        ModelInfo::new("gpt-4o", 128_000, 16_384).with_tools().with_vision(),
+        ModelInfo::new("gpt-4.1", 1_047_576, 32_768).with_tools().with_vision(),
+        ModelInfo::new("gpt-4-0613", 8_192, 8_192).with_tools(),
+        ModelInfo::new("gpt-4-turbo", 128_000, 4_096).with_tools().with_vision(),
+        ModelInfo::new("qwen3-max", 262_144, 32_768).with_tools(),
+        ModelInfo::new("deepseek-v3", 128_000, 8_192).with_tools(),
+        ModelInfo::new("deepseek-r1", 128_000, 32_768),
+        ModelInfo::new("glm-4-plus", 128_000, 4_096).with_tools(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_rejects_or_trims_oldest_turns() {
+        let registry = ModelRegistry::new();
+        registry.upsert(ModelInfo::new("small", 100, 50));
+        assert_eq!(registry.get("small-2024").unwrap().id, "small");
+        assert!(registry.get("unknown").is_none());
+
+        let filler = "x".repeat(200); // 约 50 token
+        let request = PrimitiveRequest::new("small")
+            .with_system("be brief")
+            .with_message(PrimitiveMessage::user(filler.clone()))
+            .with_message(PrimitiveMessage::assistant(filler.clone()))
+            .with_message(PrimitiveMessage::user("latest question"))
+            .with_max_tokens(20);
+
+        let err = registry.fit(Cow::Borrowed(&request), OverflowPolicy::Reject).unwrap_err();
+        assert_eq!((err.context_window, err.reserved_output_tokens), (100, 20));
+        assert!(err.prompt_tokens > 80);
+
+        let trimmed = registry.fit(Cow::Borrowed(&request), OverflowPolicy::Trim).unwrap();
+        assert_eq!(trimmed.messages.len(), 1);
+        assert_eq!(trimmed.system.as_deref(), Some("be brief"));
+
+        let huge = PrimitiveRequest::new("small").with_message(PrimitiveMessage::user("y".repeat(1_000)));
+        assert!(registry.fit(Cow::Borrowed(&huge), OverflowPolicy::Trim).is_err());
+
+        // 按模型分词器计数：按词计数时同一请求放得下
+        struct Words;
+        impl Tokenizer for Words {
+            fn name(&self) -> &str {
+                "words"
+            }
+            fn count(&self, text: &str) -> usize {
+                text.split_whitespace().count()
+            }
+        }
+        let tokenizers = TokenizerRegistry::new().with_tokenizer("small", Arc::new(Words));
+        let registry = ModelRegistry::new().with_tokenizers(Arc::new(tokenizers));
+        registry.upsert(ModelInfo::new("small", 100, 50));
+        assert!(registry.fit(Cow::Borrowed(&request), OverflowPolicy::Reject).is_ok());
+    }
+}
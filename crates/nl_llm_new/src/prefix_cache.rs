@@ -0,0 +1,173 @@
+//! 静态前缀缓存
+//!
+//! 系统提示词与工具定义构成请求的静态前缀。Gateway 按 (Provider, 模型, 前缀哈希)
+//! 记录每个前缀是否已发送过，命中时交由 Provider 复用服务端缓存：
+//! - Anthropic: 在静态块上标记 `cache_control`
+//! - Gemini: 以 `cachedContent` 引用替换 `systemInstruction`/`tools`，只发送动态片段
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use sha2::{Digest, Sha256};
+
+use crate::primitive::PrimitiveRequest;
+use crate::provider::Usage;
+
+/// 计算请求静态前缀（系统提示词 + 工具定义）的哈希
+///
+/// 没有静态前缀时返回 `None`。
+pub fn static_prefix_hash(primitive: &PrimitiveRequest) -> Option<String> {
+    if primitive.system.is_none() && primitive.tools.is_empty() {
+        return None;
+    }
+
+    let mut hasher = Sha256::new();
+    if let Some(system) = &primitive.system {
+        hasher.update(system.as_bytes());
+    }
+    hasher.update([0u8]);
+    for tool in &primitive.tools {
+        hasher.update(serde_json::to_vec(tool).unwrap_or_default());
+        hasher.update([0u8]);
+    }
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// 前缀缓存提示，传给 Provider 用于改写请求体
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixCacheHint {
+    /// 静态前缀哈希
+    pub hash: String,
+    /// 是否曾向同一 Provider/模型发送过该前缀
+    pub hit: bool,
+    /// 服务端缓存引用（如 Gemini `cachedContents/xxx`）
+    pub cache_ref: Option<String>,
+}
+
+/// 前缀缓存统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrefixCacheStats {
+    /// 带静态前缀的请求数
+    pub requests: u64,
+    /// 命中次数
+    pub hits: u64,
+    /// 服务端报告的缓存命中 token 数（即节省的输入 token）
+    pub cached_tokens: u64,
+}
+
+/// 前缀缓存表
+#[derive(Default)]
+pub struct PrefixCache {
+    /// (Provider, 模型, 前缀哈希) -> 服务端缓存引用
+    entries: RwLock<HashMap<(String, String, String), Option<String>>>,
+    requests: AtomicU64,
+    hits: AtomicU64,
+    cached_tokens: AtomicU64,
+}
+
+impl PrefixCache {
+    /// 创建空缓存表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 查询并登记一次前缀使用
+    pub fn lookup(&self, provider_id: &str, model: &str, hash: &str) -> PrefixCacheHint {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let key = (provider_id.to_string(), model.to_string(), hash.to_string());
+        let mut entries = self.entries.write().unwrap();
+
+        match entries.get(&key) {
+            Some(cache_ref) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                PrefixCacheHint {
+                    hash: hash.to_string(),
+                    hit: true,
+                    cache_ref: cache_ref.clone(),
+                }
+            }
+            None => {
+                entries.insert(key, None);
+                PrefixCacheHint {
+                    hash: hash.to_string(),
+                    hit: false,
+                    cache_ref: None,
+                }
+            }
+        }
+    }
+
+    /// 记录服务端缓存引用
+    pub fn set_cache_ref(&self, provider_id: &str, model: &str, hash: &str, cache_ref: impl Into<String>) {
+        let key = (provider_id.to_string(), model.to_string(), hash.to_string());
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key, Some(cache_ref.into()));
+    }
+
+    /// 移除服务端缓存引用（缓存过期或前缀变化时）
+    pub fn invalidate(&self, provider_id: &str, model: &str, hash: &str) {
+        let key = (provider_id.to_string(), model.to_string(), hash.to_string());
+        self.entries.write().unwrap().remove(&key);
+    }
+
+    /// 累计服务端报告的缓存 token
+    pub fn record_usage(&self, usage: &Usage) {
+        if let Some(cached) = usage.cached_tokens {
+            self.cached_tokens.fetch_add(cached, Ordering::Relaxed);
+        }
+    }
+
+    /// 获取统计信息
+    pub fn stats(&self) -> PrefixCacheStats {
+        PrefixCacheStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            cached_tokens: self.cached_tokens.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_prefix_hash_ignores_dynamic_messages() {
+        let a = PrimitiveRequest::with_system_and_user("rules", "question 1");
+        let b = PrimitiveRequest::with_system_and_user("rules", "question 2");
+        let c = PrimitiveRequest::with_system_and_user("other rules", "question 1");
+
+        assert_eq!(static_prefix_hash(&a), static_prefix_hash(&b));
+        assert_ne!(static_prefix_hash(&a), static_prefix_hash(&c));
+        assert_eq!(static_prefix_hash(&PrimitiveRequest::single_user_message("hi")), None);
+    }
+
+    #[test]
+    fn test_lookup_hits_per_provider_and_model() {
+        let cache = PrefixCache::new();
+
+        assert!(!cache.lookup("claude", "sonnet", "h").hit);
+        assert!(cache.lookup("claude", "sonnet", "h").hit);
+        assert!(!cache.lookup("gemini", "sonnet", "h").hit);
+
+        cache.set_cache_ref("gemini", "sonnet", "h", "cachedContents/abc");
+        let hint = cache.lookup("gemini", "sonnet", "h");
+        assert_eq!(hint.cache_ref.as_deref(), Some("cachedContents/abc"));
+
+        cache.record_usage(&Usage {
+            cached_tokens: Some(1200),
+            ..Default::default()
+        });
+        assert_eq!(
+            cache.stats(),
+            PrefixCacheStats {
+                requests: 4,
+                hits: 2,
+                cached_tokens: 1200
+            }
+        );
+    }
+}
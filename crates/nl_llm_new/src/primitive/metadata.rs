@@ -4,6 +4,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use crate::locale::Locale;
 use crate::translator::{Format, WrapperKind};
 
 /// 原语元数据
@@ -24,6 +25,22 @@ pub struct PrimitiveMetadata {
     /// 原始请求中的客户端特有字段（保留用于回填）
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub client_specific: HashMap<String, serde_json::Value>,
+
+    /// 跳过 Gateway 响应缓存（需要非确定性输出时）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_cache: bool,
+
+    /// 任务类型（如 `summarize`、`verdict`），供路由规则匹配
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_type: Option<String>,
+
+    /// 会话句柄（同一对话的各轮保持一致，供 CloudCode 等续接服务端上下文）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+
+    /// 用户消息的语言区域（显式指定或由 Gateway 检测）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Locale>,
 }
 
 impl PrimitiveMetadata {
@@ -56,10 +73,38 @@ impl PrimitiveMetadata {
         self
     }
 
+    /// 跳过响应缓存
+    pub fn with_no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    /// 设置任务类型
+    pub fn with_task_type(mut self, task_type: impl Into<String>) -> Self {
+        self.task_type = Some(task_type.into());
+        self
+    }
+
+    /// 设置会话句柄
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// 设置语言区域
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
     /// 是否为空
     pub fn is_empty(&self) -> bool {
         !self.was_unwrapped
             && self.wrapper_kind == WrapperKind::None
             && self.client_specific.is_empty()
+            && !self.no_cache
+            && self.task_type.is_none()
+            && self.session_id.is_none()
+            && self.locale.is_none()
     }
 }
@@ -8,6 +8,8 @@ mod message;
 mod tool;
 mod parameters;
 mod metadata;
+#[cfg(test)]
+pub(crate) mod arbitrary;
 
 pub use request::*;
 pub use message::*;
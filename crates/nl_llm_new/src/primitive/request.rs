@@ -31,7 +31,7 @@ pub struct PrimitiveRequest {
     pub parameters: PrimitiveParameters,
 
     /// 元数据（用于追踪和调试）
-    #[serde(skip_serializing_if = "PrimitiveMetadata::is_empty")]
+    #[serde(default, skip_serializing_if = "PrimitiveMetadata::is_empty")]
     pub metadata: PrimitiveMetadata,
 }
 
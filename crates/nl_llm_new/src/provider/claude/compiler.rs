@@ -71,4 +71,18 @@ impl ClaudeCompiler {
 
         body
     }
+
+    /// 在静态前缀（system 与 tools 的最后一个块）上标记 Anthropic 提示词缓存断点
+    pub fn mark_cache_control(&self, mut body: Value) -> Value {
+        for key in ["system", "tools"] {
+            if let Some(last) = body
+                .get_mut(key)
+                .and_then(|v| v.as_array_mut())
+                .and_then(|blocks| blocks.last_mut())
+            {
+                last["cache_control"] = json!({"type": "ephemeral"});
+            }
+        }
+        body
+    }
 }
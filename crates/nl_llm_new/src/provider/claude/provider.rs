@@ -2,6 +2,7 @@ use super::{config::{ClaudeConfig, ClaudeAuth}, compiler::ClaudeCompiler};
 use crate::auth::providers::claude::ClaudeOAuth;
 use crate::auth::Auth;
 use crate::provider::{LlmProvider, LlmResponse};
+use crate::prefix_cache::PrefixCacheHint;
 use crate::primitive::PrimitiveRequest;
 use async_trait::async_trait;
 use crate::provider::BoxStream;
@@ -68,6 +69,11 @@ impl LlmProvider for ClaudeProvider {
         self.compiler.compile(primitive)
     }
 
+    fn apply_prefix_cache(&self, body: serde_json::Value, _hint: &PrefixCacheHint) -> serde_json::Value {
+        // Anthropic 按内容匹配缓存：首次请求写入，之后的相同前缀读取
+        self.compiler.mark_cache_control(body)
+    }
+
     async fn complete(&self, _body: serde_json::Value) -> crate::Result<LlmResponse> {
         unimplemented!("TDD: implement real HTTP execution")
     }
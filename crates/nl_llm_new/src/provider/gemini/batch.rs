@@ -0,0 +1,119 @@
+//! Gemini `batchGenerateContent` 批量接口
+//!
+//! 批量任务以长时操作 (`batches/xxx`) 形式异步执行，价格约为在线调用的一半，
+//! 适合 MCTS 推演打分等大量小请求。提交后轮询操作状态直到完成。
+
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::provider::ProviderError;
+
+/// 批量接口配置
+#[derive(Debug, Clone)]
+pub struct GeminiBatchConfig {
+    /// 轮询间隔
+    pub poll_interval: Duration,
+    /// 最长等待时间
+    pub max_wait: Duration,
+    /// 达到该数量才走批量接口，否则逐条调用
+    pub min_batch_size: usize,
+}
+
+impl Default for GeminiBatchConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            max_wait: Duration::from_secs(30 * 60),
+            min_batch_size: 8,
+        }
+    }
+}
+
+/// 构造 batchGenerateContent 请求体（内联请求，以下标作为 key）
+pub fn build_batch_body(bodies: Vec<Value>) -> Value {
+    let requests: Vec<Value> = bodies
+        .into_iter()
+        .enumerate()
+        .map(|(i, request)| json!({ "request": request, "metadata": { "key": i.to_string() } }))
+        .collect();
+
+    json!({
+        "batch": {
+            "display_name": "neuroloom-batch",
+            "input_config": { "requests": { "requests": requests } }
+        }
+    })
+}
+
+/// 解析已完成的批量操作，按输入顺序返回每条 GenerateContentResponse 原文
+pub fn parse_batch_output(operation: &Value, count: usize) -> Vec<Result<String, ProviderError>> {
+    let mut results: Vec<Result<String, ProviderError>> = (0..count)
+        .map(|_| Err(ProviderError::fail("gemini: batch item missing from output")))
+        .collect();
+
+    if let Some(error) = operation.get("error") {
+        let message = format!("gemini: batch failed: {}", error);
+        return (0..count).map(|_| Err(ProviderError::fail(message.clone()))).collect();
+    }
+
+    let inlined = operation
+        .pointer("/response/inlinedResponses/inlinedResponses")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    for (position, item) in inlined.iter().enumerate() {
+        let index = item
+            .pointer("/metadata/key")
+            .and_then(|k| k.as_str())
+            .and_then(|k| k.parse::<usize>().ok())
+            .unwrap_or(position);
+        let Some(slot) = results.get_mut(index) else {
+            continue;
+        };
+        *slot = match (item.get("response"), item.get("error")) {
+            (Some(response), _) => Ok(response.to_string()),
+            (None, Some(error)) => {
+                let code = error.get("code").and_then(|c| c.as_u64()).unwrap_or(500) as u16;
+                Err(ProviderError::from_http_status(code, format!("gemini: batch item failed: {}", error)))
+            }
+            (None, None) => Err(ProviderError::fail("gemini: empty batch item")),
+        };
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_batch_body_keys_by_index() {
+        let body = build_batch_body(vec![json!({"contents": []}), json!({"contents": []})]);
+        let requests = body.pointer("/batch/input_config/requests/requests").unwrap();
+        assert_eq!(requests[1]["metadata"]["key"], "1");
+    }
+
+    #[test]
+    fn test_parse_batch_output_per_item_errors() {
+        let operation = json!({
+            "name": "batches/1",
+            "done": true,
+            "response": {
+                "inlinedResponses": {
+                    "inlinedResponses": [
+                        { "metadata": { "key": "1" }, "error": { "code": 429, "message": "quota" } },
+                        { "metadata": { "key": "0" }, "response": { "candidates": [] } }
+                    ]
+                }
+            }
+        });
+
+        let results = parse_batch_output(&operation, 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].as_ref().unwrap_err().retryable);
+        assert!(results[2].is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Gemini `cachedContents` 显式缓存
+//!
+//! 将请求的静态前缀（systemInstruction + tools）上传为服务端缓存，
+//! 之后的 generateContent 只需携带 `cachedContent` 引用与动态 contents。
+//!
+//! 生命周期：
+//! - 首次遇到某模型的静态前缀时创建缓存
+//! - 临近过期时通过 PATCH 续期 TTL
+//! - 同一模型的静态前缀哈希变化时删除旧缓存并重新创建
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use super::protocol::compile_request;
+use crate::primitive::PrimitiveRequest;
+use crate::provider::ProviderError;
+
+/// cachedContents 配置
+#[derive(Debug, Clone)]
+pub struct GeminiCacheConfig {
+    /// 缓存存活时间
+    pub ttl: Duration,
+    /// 提前多久续期
+    pub refresh_before: Duration,
+    /// 静态前缀最少字符数（过短的前缀不满足服务端最小 token 要求）
+    pub min_prefix_chars: usize,
+}
+
+impl Default for GeminiCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(3600),
+            refresh_before: Duration::from_secs(300),
+            min_prefix_chars: 4096,
+        }
+    }
+}
+
+/// 已创建的服务端缓存
+#[derive(Debug, Clone)]
+pub struct CachedContentEntry {
+    /// 资源名 (`cachedContents/xxx`)
+    pub name: String,
+    /// 静态前缀哈希
+    pub hash: String,
+    /// 过期时间
+    pub expire_at: DateTime<Utc>,
+}
+
+/// cachedContents 管理器（按模型维护当前有效的缓存）
+pub struct GeminiCacheManager {
+    config: GeminiCacheConfig,
+    base_url: String,
+    api_key: String,
+    entries: Mutex<HashMap<String, CachedContentEntry>>,
+}
+
+impl GeminiCacheManager {
+    /// 创建新的管理器
+    pub fn new(config: GeminiCacheConfig, base_url: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            config,
+            base_url: base_url.into(),
+            api_key: api_key.into(),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 构造创建缓存的请求体
+    pub fn create_body(primitive: &PrimitiveRequest, model: &str, ttl: Duration) -> Value {
+        let compiled = compile_request(primitive);
+        let mut body = json!({
+            "model": format!("models/{}", model),
+            "ttl": format!("{}s", ttl.as_secs()),
+        });
+        for key in ["systemInstruction", "tools"] {
+            if let Some(value) = compiled.get(key) {
+                body[key] = value.clone();
+            }
+        }
+        body
+    }
+
+    /// 确保当前静态前缀存在有效缓存，返回缓存资源名
+    ///
+    /// 前缀过短或模型未知时返回 `None`，调用方应直接发送完整请求。
+    pub async fn ensure(
+        &self,
+        http: &reqwest::Client,
+        primitive: &PrimitiveRequest,
+        hash: &str,
+    ) -> crate::Result<Option<String>> {
+        let model = primitive.model.as_str();
+        if model.is_empty() || Self::prefix_chars(primitive) < self.config.min_prefix_chars {
+            return Ok(None);
+        }
+
+        let mut entries = self.entries.lock().await;
+        let now = Utc::now();
+        let refresh_before = chrono::Duration::from_std(self.config.refresh_before).unwrap_or_default();
+
+        if let Some(entry) = entries.get(model).cloned() {
+            if entry.hash != hash {
+                // 静态前缀已变化：旧缓存作废
+                entries.remove(model);
+                if let Err(e) = self.delete(http, &entry.name).await {
+                    tracing::warn!("gemini: failed to delete stale cache {}: {}", entry.name, e);
+                }
+            } else if entry.expire_at > now + refresh_before {
+                return Ok(Some(entry.name));
+            } else if entry.expire_at > now {
+                let expire_at = self.refresh(http, &entry.name).await?;
+                let name = entry.name.clone();
+                entries.insert(model.to_string(), CachedContentEntry { expire_at, ..entry });
+                return Ok(Some(name));
+            } else {
+                entries.remove(model);
+            }
+        }
+
+        let entry = self.create(http, primitive, hash).await?;
+        let name = entry.name.clone();
+        entries.insert(model.to_string(), entry);
+        Ok(Some(name))
+    }
+
+    /// 删除全部已创建的缓存
+    pub async fn expire_all(&self, http: &reqwest::Client) -> crate::Result<()> {
+        let entries: Vec<CachedContentEntry> = self.entries.lock().await.drain().map(|(_, e)| e).collect();
+        for entry in entries {
+            self.delete(http, &entry.name).await?;
+        }
+        Ok(())
+    }
+
+    /// 创建缓存
+    async fn create(
+        &self,
+        http: &reqwest::Client,
+        primitive: &PrimitiveRequest,
+        hash: &str,
+    ) -> crate::Result<CachedContentEntry> {
+        let body = Self::create_body(primitive, &primitive.model, self.config.ttl);
+        let resp = http
+            .post(self.url("cachedContents"))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| crate::Error::Http(e.to_string()))?;
+        let json = Self::read_json(resp, "create").await?;
+
+        let name = json
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| crate::Error::Provider(ProviderError::fail("gemini: cachedContents response missing name")))?
+            .to_string();
+
+        Ok(CachedContentEntry {
+            name,
+            hash: hash.to_string(),
+            expire_at: self.parse_expire_time(&json),
+        })
+    }
+
+    /// 续期缓存 TTL
+    async fn refresh(&self, http: &reqwest::Client, name: &str) -> crate::Result<DateTime<Utc>> {
+        let resp = http
+            .patch(format!("{}?updateMask=ttl", self.url(name)))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&json!({ "ttl": format!("{}s", self.config.ttl.as_secs()) }))
+            .send()
+            .await
+            .map_err(|e| crate::Error::Http(e.to_string()))?;
+        let json = Self::read_json(resp, "refresh").await?;
+        Ok(self.parse_expire_time(&json))
+    }
+
+    /// 删除缓存
+    async fn delete(&self, http: &reqwest::Client, name: &str) -> crate::Result<()> {
+        let resp = http
+            .delete(self.url(name))
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| crate::Error::Http(e.to_string()))?;
+        Self::read_json(resp, "delete").await.map(|_| ())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/v1beta/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    async fn read_json(resp: reqwest::Response, action: &str) -> crate::Result<Value> {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(crate::Error::Provider(ProviderError::from_http_status(
+                status.as_u16(),
+                format!("gemini: cachedContents {} failed ({}): {}", action, status.as_u16(), text.trim()),
+            )));
+        }
+        if text.trim().is_empty() {
+            return Ok(json!({}));
+        }
+        serde_json::from_str(&text).map_err(crate::Error::Json)
+    }
+
+    /// 解析 `expireTime`，缺失时按配置 TTL 推算
+    fn parse_expire_time(&self, json: &Value) -> DateTime<Utc> {
+        json.get("expireTime")
+            .and_then(|v| v.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc::now() + chrono::Duration::from_std(self.config.ttl).unwrap_or_default())
+    }
+
+    fn prefix_chars(primitive: &PrimitiveRequest) -> usize {
+        let system = primitive.system.as_ref().map_or(0, |s| s.len());
+        let tools: usize = primitive
+            .tools
+            .iter()
+            .map(|t| {
+                t.name.len()
+                    + t.description.as_ref().map_or(0, |d| d.len())
+                    + t.input_schema.to_string().len()
+            })
+            .sum();
+        system + tools
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_body_contains_only_static_prefix() {
+        let primitive = PrimitiveRequest::with_system_and_user("static rules", "dynamic question");
+        let body = GeminiCacheManager::create_body(&primitive, "gemini-2.5-flash", Duration::from_secs(600));
+
+        assert_eq!(body["model"], "models/gemini-2.5-flash");
+        assert_eq!(body["ttl"], "600s");
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "static rules");
+        assert!(body.get("contents").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ensure_skips_short_prefix() {
+        let manager = GeminiCacheManager::new(GeminiCacheConfig::default(), "http://127.0.0.1:1", "key");
+        let mut primitive = PrimitiveRequest::with_system_and_user("short", "q");
+        primitive.model = "gemini-2.5-flash".to_string();
+
+        let result = manager.ensure(&reqwest::Client::new(), &primitive, "h").await.unwrap();
+        assert!(result.is_none());
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! 支持 API Key 认证（官方/转发站）
 
+use super::batch::GeminiBatchConfig;
+use super::cache::GeminiCacheConfig;
 use crate::auth::{ApiKeyConfig, ApiKeyProvider};
 use std::collections::HashMap;
 
@@ -20,6 +22,10 @@ pub struct GeminiConfig {
     pub model: String,
     /// 额外请求头
     pub extra_headers: HashMap<String, String>,
+    /// cachedContents 显式缓存（None 表示不启用）
+    pub cached_contents: Option<GeminiCacheConfig>,
+    /// batchGenerateContent 批量接口（None 表示批量请求逐条发送）
+    pub batch: Option<GeminiBatchConfig>,
 }
 
 impl GeminiConfig {
@@ -29,9 +35,23 @@ impl GeminiConfig {
             auth,
             model,
             extra_headers: HashMap::new(),
+            cached_contents: None,
+            batch: None,
         }
     }
 
+    /// 启用 batchGenerateContent 批量接口
+    pub fn with_batch(mut self, config: GeminiBatchConfig) -> Self {
+        self.batch = Some(config);
+        self
+    }
+
+    /// 启用 cachedContents 显式缓存
+    pub fn with_cached_contents(mut self, config: GeminiCacheConfig) -> Self {
+        self.cached_contents = Some(config);
+        self
+    }
+
     /// 使用 API Key 创建配置（官方端点）
     pub fn with_api_key(api_key: impl Into<String>, model: impl Into<String>) -> Self {
         Self::new(
@@ -10,9 +10,13 @@
 pub mod protocol;
 pub mod config;
 pub mod provider;
+pub mod cache;
+pub mod batch;
 
 // 重导出常用类型和函数
-pub use protocol::{compile_request, parse_response, parse_sse_stream, CloudCodeProtocol};
+pub use protocol::{apply_cached_content, compile_request, parse_response, parse_sse_stream, CloudCodeProtocol};
 pub use config::GeminiConfig;
+pub use cache::{GeminiCacheConfig, GeminiCacheManager};
+pub use batch::GeminiBatchConfig;
 pub use provider::{GeminiProvider, GoogleAIStudioProvider};
 
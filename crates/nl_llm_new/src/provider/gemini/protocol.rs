@@ -46,8 +46,11 @@
 //! | `GeminiCliProvider` | CloudCode Protocol |
 //! | `AntigravityProvider` | CloudCode Protocol |
 
+use crate::prefix_cache::PrefixCacheHint;
 use crate::primitive::{PrimitiveContent, PrimitiveRequest, Role};
+use crate::provider::sse::SseDecoder;
 use crate::provider::{BoxStream, LlmChunk, LlmResponse, StopReason, Usage};
+use crate::session::SessionHandle;
 use serde_json::{json, Value};
 
 // ================================================================================================
@@ -145,6 +148,21 @@ pub fn compile_request(primitive: &PrimitiveRequest) -> Value {
     body
 }
 
+/// 以 `cachedContent` 引用替换请求中的静态前缀
+///
+/// 缓存中已包含 systemInstruction 与 tools，请求只需携带动态的 contents。
+pub fn apply_cached_content(mut body: Value, hint: &PrefixCacheHint) -> Value {
+    let Some(cache_ref) = &hint.cache_ref else {
+        return body;
+    };
+    if let Some(obj) = body.as_object_mut() {
+        obj.remove("systemInstruction");
+        obj.remove("tools");
+        obj.insert("cachedContent".to_string(), json!(cache_ref));
+    }
+    body
+}
+
 /// 解析 Gemini 非流式响应：candidates[0].content.parts[0].text
 pub fn parse_response(raw: &str) -> crate::Result<LlmResponse> {
     let json: Value = serde_json::from_str(raw).map_err(|e| {
@@ -166,14 +184,7 @@ pub fn parse_response(raw: &str) -> crate::Result<LlmResponse> {
         .to_string();
 
     // 解析 usage
-    let usage = json
-        .get("usageMetadata")
-        .map(|u| Usage {
-            input_tokens: u.get("promptTokenCount").and_then(|v| v.as_u64()).unwrap_or(0),
-            output_tokens: u.get("candidatesTokenCount").and_then(|v| v.as_u64()).unwrap_or(0),
-            thinking_tokens: u.get("thoughtsTokenCount").and_then(|v| v.as_u64()),
-        })
-        .unwrap_or_default();
+    let usage = Usage::from_response(&json).unwrap_or_default();
 
     // 解析 stop_reason
     let finish_reason = json
@@ -207,22 +218,22 @@ pub fn parse_sse_stream(
 
     let stream = async_stream::stream! {
         let mut byte_stream = resp.bytes_stream();
-        let mut buffer = String::new();
+        let mut decoder = SseDecoder::new();
 
-        while let Some(chunk) = byte_stream.next().await {
-            let bytes = match chunk {
-                Ok(b) => b,
-                Err(e) => {
+        loop {
+            let (events, done) = match byte_stream.next().await {
+                Some(Ok(bytes)) => (decoder.push(&bytes), false),
+                Some(Err(e)) => {
                     yield Err(crate::Error::Http(e.to_string()));
                     continue;
                 }
+                None => (decoder.finish(), true),
             };
-            let s = String::from_utf8_lossy(&bytes);
-            buffer.push_str(&s);
 
             // Check if this might be a pure non-SSE JSON response returned by mistake
-            if buffer.starts_with('{') && buffer.ends_with('}') {
-                if let Ok(json) = serde_json::from_str::<Value>(&buffer) {
+            let buffered = decoder.buffered().trim();
+            if !done && events.is_empty() && buffered.starts_with('{') && buffered.ends_with('}') {
+                if let Ok(json) = serde_json::from_str::<Value>(buffered) {
                     if let Some(text) = json
                         .get("candidates")
                         .and_then(|c| c.get(0))
@@ -235,51 +246,56 @@ pub fn parse_sse_stream(
                         if !text.is_empty() {
                             yield Ok(LlmChunk {
                                 delta: crate::provider::ChunkDelta::Text(text.to_string()),
-                                usage: None,
+                                usage: Usage::from_response(&json),
                             });
                         }
-                        buffer.clear();
+                        decoder.clear();
                         return;
                     }
                 }
             }
 
-            // 按行处理
-            while let Some(pos) = buffer.find('\n') {
-                let line = buffer[..pos].trim().to_string();
-                buffer = buffer[pos + 1..].to_string();
-
-                if let Some(data) = line.strip_prefix("data: ") {
-                    let data = data.trim();
-                    if data == "[DONE]" || data.is_empty() {
-                        continue;
-                    }
-                    if let Ok(json) = serde_json::from_str::<Value>(data) {
-                        if let Some(text) = json
-                            .get("candidates")
-                            .and_then(|c| c.get(0))
-                            .and_then(|c| c.get("content"))
-                            .and_then(|c| c.get("parts"))
-                            .and_then(|p| p.get(0))
-                            .and_then(|p| p.get("text"))
-                            .and_then(|t| t.as_str())
-                        {
-                            if !text.is_empty() {
-                                yield Ok(LlmChunk {
-                                    delta: crate::provider::ChunkDelta::Text(text.to_string()),
-                                    usage: None,
-                                });
-                            }
-                        }
-                    }
+            for data in events {
+                if let Some(chunk) = stream_chunk(&data) {
+                    yield Ok(chunk);
                 }
             }
+            if done {
+                break;
+            }
         }
     };
 
     Box::pin(stream)
 }
 
+/// 解析一个 SSE 事件的 `data`
+fn stream_chunk(data: &str) -> Option<LlmChunk> {
+    let data = data.trim();
+    if data == "[DONE]" || data.is_empty() {
+        return None;
+    }
+    let json = serde_json::from_str::<Value>(data).ok()?;
+    let text = json
+        .get("candidates")
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("content"))
+        .and_then(|c| c.get("parts"))
+        .and_then(|p| p.get(0))
+        .and_then(|p| p.get("text"))
+        .and_then(|t| t.as_str())
+        .unwrap_or_default();
+    // usageMetadata 为累计值，随所在分片一并上报
+    let usage = Usage::from_response(&json);
+    if text.is_empty() && usage.is_none() {
+        return None;
+    }
+    Some(LlmChunk {
+        delta: crate::provider::ChunkDelta::Text(text.to_string()),
+        usage,
+    })
+}
+
 // ================================================================================================
 // CloudCode Protocol (Gemini 方言壳)
 // ================================================================================================
@@ -323,8 +339,9 @@ impl crate::provider::Protocol for CloudCodeProtocol {
 
         let inner_request = compile_request(&req);
 
+        // 同一对话各轮使用相同的 sessionId，服务端才能续接上下文
         let mut request_payload = serde_json::json!({
-            "sessionId": format!("-{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos() & 0x7FFFFFFFFFFFFFFF)
+            "sessionId": SessionHandle::for_request(&req).cloudcode_id()
         });
 
         if let Some(contents) = inner_request.get("contents") {
@@ -370,7 +387,7 @@ impl crate::provider::Protocol for CloudCodeProtocol {
         Ok(LlmResponse {
             content,
             tool_calls: Vec::new(),
-            usage: Usage::default(),
+            usage: Usage::from_response(&v).unwrap_or_default(),
             stop_reason: StopReason::EndTurn,
         })
     }
@@ -383,82 +400,53 @@ impl crate::provider::Protocol for CloudCodeProtocol {
 
         let stream = async_stream::stream! {
             let mut byte_stream = resp.bytes_stream();
-            let mut buffer = String::new();
+            let mut decoder = SseDecoder::new();
 
-            while let Some(chunk) = byte_stream.next().await {
-                let bytes = match chunk {
-                    Ok(b) => b,
-                    Err(e) => {
+            loop {
+                let (events, done) = match byte_stream.next().await {
+                    Some(Ok(bytes)) => (decoder.push(&bytes), false),
+                    Some(Err(e)) => {
                         yield Err(crate::Error::Http(e.to_string()));
                         continue;
                     }
+                    None => (decoder.finish(), true),
                 };
-                let s = String::from_utf8_lossy(&bytes);
-                buffer.push_str(&s);
 
                 // Check if this might be a pure non-SSE JSON response returned by mistake
-                if buffer.starts_with('{') && buffer.ends_with('}') {
-                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(&buffer) {
-                        let candidates = v
-                            .get("response")
-                            .and_then(|r| r.get("candidates"))
-                            .or_else(|| v.get("candidates"));
-
-                        if let Some(text) = candidates
-                            .and_then(|c| c.get(0).or_else(|| c.as_array().and_then(|a| a.get(0))))
-                            .and_then(|c| c.get("content"))
-                            .and_then(|c| c.get("parts"))
-                            .and_then(|p| p.get(0).or_else(|| p.as_array().and_then(|a| a.get(0))))
-                            .and_then(|p| p.get("text"))
-                            .and_then(|t| t.as_str())
-                        {
+                let buffered = decoder.buffered().trim();
+                if !done && events.is_empty() && buffered.starts_with('{') && buffered.ends_with('}') {
+                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(buffered) {
+                        if let Some(text) = cloud_code_text(&v) {
                             if !text.is_empty() {
                                 yield Ok(LlmChunk {
                                     delta: crate::provider::ChunkDelta::Text(text.to_string()),
-                                    usage: None,
+                                    usage: Usage::from_response(&v),
                                 });
                             }
-                            buffer.clear();
+                            decoder.clear();
                             return;
                         }
                     }
                 }
 
-                while let Some(pos) = buffer.find("\r\n\r\n").or_else(|| buffer.find("\n\n")) {
-                    let offset = if buffer[pos..].starts_with("\r\n\r\n") { 4 } else { 2 };
-                    let event = buffer[..pos].to_string();
-                    buffer = buffer[pos + offset..].to_string();
-
-                    for line in event.lines() {
-                        if let Some(data) = line.strip_prefix("data: ") {
-                            if data == "[DONE]" {
-                                return;
-                            }
-                            if let Ok(v) = serde_json::from_str::<serde_json::Value>(data) {
-                                let candidates = v
-                                    .get("response")
-                                    .and_then(|r| r.get("candidates"))
-                                    .or_else(|| v.get("candidates"));
-
-                                if let Some(text) = candidates
-                                    .and_then(|c| c.get(0).or_else(|| c.as_array().and_then(|a| a.get(0))))
-                                    .and_then(|c| c.get("content"))
-                                    .and_then(|c| c.get("parts"))
-                                    .and_then(|p| p.get(0).or_else(|| p.as_array().and_then(|a| a.get(0))))
-                                    .and_then(|p| p.get("text"))
-                                    .and_then(|t| t.as_str())
-                                {
-                                    if !text.is_empty() {
-                                        yield Ok(LlmChunk {
-                                            delta: crate::provider::ChunkDelta::Text(text.to_string()),
-                                            usage: None,
-                                        });
-                                    }
-                                }
-                            }
+                for data in events {
+                    if data == "[DONE]" {
+                        return;
+                    }
+                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(&data) {
+                        let text = cloud_code_text(&v).unwrap_or_default();
+                        let usage = Usage::from_response(&v);
+                        if !text.is_empty() || usage.is_some() {
+                            yield Ok(LlmChunk {
+                                delta: crate::provider::ChunkDelta::Text(text.to_string()),
+                                usage,
+                            });
                         }
                     }
                 }
+                if done {
+                    break;
+                }
             }
         };
 
@@ -466,6 +454,19 @@ impl crate::provider::Protocol for CloudCodeProtocol {
     }
 }
 
+/// CloudCode 响应（`response` 包装或裸响应）中第一个候选的文本
+fn cloud_code_text(v: &Value) -> Option<&str> {
+    v.get("response")
+        .and_then(|r| r.get("candidates"))
+        .or_else(|| v.get("candidates"))
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("content"))
+        .and_then(|c| c.get("parts"))
+        .and_then(|p| p.get(0))
+        .and_then(|p| p.get("text"))
+        .and_then(|t| t.as_str())
+}
+
 // ================================================================================================
 // 测试
 // ================================================================================================
@@ -553,6 +554,42 @@ mod tests {
         assert_eq!(result.usage.output_tokens, 5);
     }
 
+    #[test]
+    fn test_usage_from_gemini_cloud_code_and_openai() {
+        let wrapped = serde_json::json!({
+            "response": {
+                "usageMetadata": {
+                    "promptTokenCount": 12,
+                    "candidatesTokenCount": 4,
+                    "thoughtsTokenCount": 6,
+                    "cachedContentTokenCount": 8
+                }
+            }
+        });
+        let usage = Usage::from_response(&wrapped).unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 4));
+        assert_eq!(usage.thinking_tokens, Some(6));
+        assert_eq!(usage.cached_tokens, Some(8));
+        assert_eq!(usage.total_tokens(), 22);
+
+        // OpenAI / iFlow：completion_tokens 含推理 token
+        let openai = serde_json::json!({
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 20,
+                "completion_tokens": 30,
+                "completion_tokens_details": { "reasoning_tokens": 10 },
+                "prompt_tokens_details": { "cached_tokens": 5 }
+            }
+        });
+        let usage = Usage::from_response(&openai).unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (20, 20));
+        assert_eq!(usage.total_tokens(), 50);
+        assert_eq!(usage.cached_tokens, Some(5));
+
+        assert!(Usage::from_response(&serde_json::json!({ "usage": null })).is_none());
+    }
+
     #[test]
     fn test_parse_response_error() {
         let raw = r#"{"error": "something went wrong"}"#;
@@ -560,4 +597,103 @@ mod tests {
         // 应该返回空内容而不是错误
         assert_eq!(result.unwrap().content, "");
     }
+
+    #[test]
+    fn test_apply_cached_content_strips_static_prefix() {
+        let primitive = PrimitiveRequest::with_system_and_user("static rules", "dynamic question");
+        let body = compile_request(&primitive);
+        let hint = PrefixCacheHint {
+            hash: "h".to_string(),
+            hit: true,
+            cache_ref: Some("cachedContents/abc".to_string()),
+        };
+
+        let body = apply_cached_content(body, &hint);
+        assert!(body.get("systemInstruction").is_none());
+        assert_eq!(body["cachedContent"], "cachedContents/abc");
+        assert_eq!(body["contents"].as_array().unwrap().len(), 1);
+    }
+
+    /// 按给定分片返回的流式响应
+    fn chunked_response(chunks: Vec<Vec<u8>>) -> reqwest::Response {
+        let body = futures::stream::iter(chunks.into_iter().map(Ok::<_, std::io::Error>));
+        axum::http::Response::new(reqwest::Body::wrap_stream(body)).into()
+    }
+
+    #[tokio::test]
+    async fn test_sse_streams_reassemble_text_split_anywhere() {
+        use crate::primitive::arbitrary::Arbitrary;
+        use crate::provider::{ChunkDelta, Protocol};
+        use futures::StreamExt;
+        use rand::Rng;
+
+        let protocol = CloudCodeProtocol { default_model: "gemini-2.5-flash".to_string() };
+        for seed in 0..100 {
+            let mut arbitrary = Arbitrary::new(seed);
+            let texts: Vec<String> = (0..arbitrary.rng().gen_range(1..5)).map(|_| arbitrary.text()).collect();
+            let events = |wrap: bool| -> Vec<u8> {
+                texts
+                    .iter()
+                    .map(|text| {
+                        let candidates = json!({"candidates": [{"content": {"parts": [{"text": text}]}}]});
+                        let data = if wrap { json!({ "response": candidates }) } else { candidates };
+                        format!("data: {}\r\n\r\n", data)
+                    })
+                    .collect::<String>()
+                    .into_bytes()
+            };
+            let garbage: Vec<u8> = (0..200).map(|_| arbitrary.rng().gen()).collect();
+            // 随机切分（可能切在多字节字符中间），随机字节只验证不会 panic
+            let mut split = |bytes: Vec<u8>| -> Vec<Vec<u8>> {
+                let mut cuts: Vec<usize> = (0..4).map(|_| arbitrary.rng().gen_range(0..=bytes.len())).collect();
+                cuts.sort_unstable();
+                cuts.push(bytes.len());
+                let mut start = 0;
+                cuts.into_iter()
+                    .map(|cut| {
+                        let chunk = bytes[start..cut].to_vec();
+                        start = cut;
+                        chunk
+                    })
+                    .collect()
+            };
+            let native = split(events(false));
+            let wrapped = split(events(true));
+            let garbage = split(garbage);
+
+            let collect = |chunks: Vec<crate::Result<LlmChunk>>| -> String {
+                chunks
+                    .into_iter()
+                    .map(|chunk| match chunk.unwrap().delta {
+                        ChunkDelta::Text(text) => text,
+                        other => panic!("unexpected delta {:?}", other),
+                    })
+                    .collect()
+            };
+            let expected = texts.concat();
+            let streamed = parse_sse_stream(chunked_response(native)).collect().await;
+            assert_eq!(collect(streamed), expected, "seed {}", seed);
+            let streamed = protocol.parse_stream(chunked_response(wrapped)).unwrap().collect().await;
+            assert_eq!(collect(streamed), expected, "seed {}", seed);
+            let _ = parse_sse_stream(chunked_response(garbage)).collect::<Vec<_>>().await;
+        }
+    }
+
+    #[test]
+    fn test_cloud_code_session_id_follows_conversation() {
+        use crate::provider::Protocol;
+
+        let protocol = CloudCodeProtocol { default_model: "gemini-2.5-flash".to_string() };
+        let first = PrimitiveRequest::with_system_and_user("rules", "hello");
+        let second = first
+            .clone()
+            .with_message(PrimitiveMessage::assistant("hi"))
+            .with_message(PrimitiveMessage::user("continue"));
+        let session = |req: &PrimitiveRequest| protocol.compile(req)["request"]["sessionId"].clone();
+        assert_eq!(session(&first), session(&second));
+
+        let mut explicit = second.clone();
+        explicit.metadata = explicit.metadata.with_session_id("-42");
+        assert_eq!(session(&explicit), "-42");
+    }
 }
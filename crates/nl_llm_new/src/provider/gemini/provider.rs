@@ -8,11 +8,15 @@
 //!
 //! 认证方式: `x-goog-api-key` header
 
-use super::protocol::{compile_request, parse_response, parse_sse_stream};
+use super::protocol::{apply_cached_content, compile_request, parse_response, parse_sse_stream};
+use super::batch::{build_batch_body, parse_batch_output, GeminiBatchConfig};
+use super::cache::GeminiCacheManager;
 use super::config::GeminiConfig;
 use crate::auth::{Auth, ApiKeyConfig, ApiKeyProvider};
+use crate::model_registry::ModelInfo;
+use crate::prefix_cache::PrefixCacheHint;
 use crate::primitive::PrimitiveRequest;
-use crate::provider::{BoxStream, LlmChunk, LlmResponse, GenericClient, Endpoint, Protocol};
+use crate::provider::{BoxStream, LlmChunk, LlmResponse, GenericClient, Endpoint, Protocol, ProviderError};
 use crate::generic_client;
 use async_trait::async_trait;
 
@@ -21,6 +25,7 @@ const GOOGLE_AI_STUDIO_API_VERSION: &str = "v1beta";
 
 // ── Orthogonal Decomposition: Protocol & Endpoint ─────────────────────────────
 
+#[derive(Clone)]
 pub struct GeminiProtocol;
 
 impl Protocol for GeminiProtocol {
@@ -28,6 +33,10 @@ impl Protocol for GeminiProtocol {
         compile_request(primitive)
     }
 
+    fn apply_prefix_cache(&self, body: serde_json::Value, hint: &PrefixCacheHint) -> serde_json::Value {
+        apply_cached_content(body, hint)
+    }
+
     fn parse_response(&self, raw_text: &str) -> crate::Result<LlmResponse> {
         parse_response(raw_text)
     }
@@ -41,6 +50,8 @@ pub struct GeminiEndpoint {
     base_url: String,
     auth_key: String,
     extra_headers: std::collections::HashMap<String, String>,
+    cache_manager: Option<GeminiCacheManager>,
+    batch: Option<GeminiBatchConfig>,
 }
 
 #[async_trait]
@@ -67,6 +78,118 @@ impl Endpoint for GeminiEndpoint {
         }
         Ok(req)
     }
+
+    async fn prepare_prefix_cache(
+        &self,
+        http: &reqwest::Client,
+        primitive: &PrimitiveRequest,
+        hash: &str,
+    ) -> crate::Result<Option<String>> {
+        match &self.cache_manager {
+            Some(manager) => manager.ensure(http, primitive, hash).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn batch(
+        &self,
+        http: &reqwest::Client,
+        model: &str,
+        bodies: Vec<serde_json::Value>,
+    ) -> crate::Result<Option<Vec<Result<String, ProviderError>>>> {
+        let Some(config) = &self.batch else {
+            return Ok(None);
+        };
+        if bodies.len() < config.min_batch_size || model.is_empty() {
+            return Ok(None);
+        }
+
+        let count = bodies.len();
+        let base = format!("{}/{}", self.base_url.trim_end_matches('/'), GOOGLE_AI_STUDIO_API_VERSION);
+        let submit_url = format!("{}/models/{}:batchGenerateContent", base, model);
+        let mut operation = self
+            .send_json(http.post(&submit_url).json(&build_batch_body(bodies)), "batch submit")
+            .await?;
+        let name = operation
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| crate::Error::Provider(ProviderError::fail("gemini: batch response missing name")))?
+            .to_string();
+
+        let started = std::time::Instant::now();
+        while !operation.get("done").and_then(|v| v.as_bool()).unwrap_or(false) {
+            if started.elapsed() > config.max_wait {
+                return Err(crate::Error::Provider(ProviderError::fail(format!(
+                    "gemini: batch {} did not finish within {:?}",
+                    name, config.max_wait
+                ))));
+            }
+            tokio::time::sleep(config.poll_interval).await;
+            operation = self
+                .send_json(http.get(format!("{}/{}", base, name)), "batch poll")
+                .await?;
+        }
+
+        Ok(Some(parse_batch_output(&operation, count)))
+    }
+
+    async fn list_models(&self, http: &reqwest::Client) -> crate::Result<Vec<ModelInfo>> {
+        let url = format!("{}/{}/models", self.base_url.trim_end_matches('/'), GOOGLE_AI_STUDIO_API_VERSION);
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut req = http.get(&url).query(&[("pageSize", "1000")]);
+            if let Some(token) = &page_token {
+                req = req.query(&[("pageToken", token)]);
+            }
+            let page = self.send_json(req, "list models").await?;
+            models.extend(parse_model_list(&page));
+            page_token = page.get("nextPageToken").and_then(|v| v.as_str()).map(|s| s.to_string());
+            if page_token.is_none() {
+                return Ok(models);
+            }
+        }
+    }
+}
+
+/// 解析 `models.list` 响应中的上下文窗口（`inputTokenLimit` + `outputTokenLimit`）
+fn parse_model_list(page: &serde_json::Value) -> Vec<ModelInfo> {
+    let Some(models) = page.get("models").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    models
+        .iter()
+        .filter_map(|model| {
+            let id = model.get("name")?.as_str()?.trim_start_matches("models/");
+            let input = model.get("inputTokenLimit")?.as_u64()?;
+            let output = model.get("outputTokenLimit").and_then(|v| v.as_u64()).unwrap_or(0);
+            Some(ModelInfo::new(id, input + output, output).with_tools().with_vision())
+        })
+        .collect()
+}
+
+impl GeminiEndpoint {
+    /// 发送带认证的请求并解析 JSON 响应
+    async fn send_json(
+        &self,
+        req: reqwest::RequestBuilder,
+        action: &str,
+    ) -> crate::Result<serde_json::Value> {
+        let resp = self
+            .inject_auth(req)?
+            .send()
+            .await
+            .map_err(|e| crate::Error::Http(e.to_string()))?;
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(crate::Error::Provider(ProviderError::from_http_status(
+                status.as_u16(),
+                format!("gemini: {} failed ({}): {}", action, status.as_u16(), text.trim()),
+            )));
+        }
+        serde_json::from_str(&text).map_err(crate::Error::Json)
+    }
 }
 
 // ── GeminiProvider 本质是 GenericClient 装配好的别名 ──────────────────────
@@ -84,10 +207,17 @@ impl GeminiProvider {
             provider: ApiKeyProvider::GeminiAIStudio,
         });
 
+        let cache_manager = config
+            .cached_contents
+            .clone()
+            .map(|cache| GeminiCacheManager::new(cache, config.base_url(), config.auth.key.clone()));
+
         let endpoint = GeminiEndpoint {
             base_url: config.base_url(),
             auth_key: config.auth.key.clone(),
             extra_headers: config.extra_headers.clone(),
+            cache_manager,
+            batch: config.batch.clone(),
         };
 
         generic_client! {
@@ -199,4 +329,18 @@ mod tests {
             "https://zenmux.ai/api/v1beta/models/gemini-2.5-flash:generateContent"
         );
     }
+
+    #[test]
+    fn test_parse_model_list() {
+        let page = serde_json::json!({
+            "models": [
+                {"name": "models/gemini-2.5-flash", "inputTokenLimit": 1048576, "outputTokenLimit": 65536},
+                {"name": "models/embedding-001"}
+            ]
+        });
+        let models = parse_model_list(&page);
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, "gemini-2.5-flash");
+        assert_eq!((models[0].context_window, models[0].max_output_tokens), (1_114_112, 65_536));
+    }
 }
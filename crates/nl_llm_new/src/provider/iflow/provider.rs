@@ -1,16 +1,20 @@
 //! IFlow Provider 实现
 //!
 //! 使用 OpenAI 兼容协议，通过 Cookie 认证获取 API Key
+//!
+//! - 流式请求走同一 chat 端点（`stream: true` + SSE），思考模型的 `reasoning_content` 作为 Thinking 分片输出
+//! - API Key 临近过期（2 天窗口）时由 Gateway 后台任务（`Gateway::start_auth_refresh`）提前续期并写回 Token 文件
 
 use super::config::IFlowConfig;
 use crate::auth::providers::iflow::IFlowAuth;
 use crate::auth::Auth;
 use crate::primitive::PrimitiveRequest;
+use crate::provider::sse::SseDecoder;
 use crate::provider::{BoxStream, LlmChunk, LlmResponse, StopReason, Usage, GenericClient, Endpoint, Protocol};
 use crate::generic_client;
 use async_trait::async_trait;
 use tokio::sync::Mutex;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 /// iFlow 支持的模型常量
 pub mod models {
@@ -83,6 +87,12 @@ impl Protocol for IFlowProtocol {
         body
     }
 
+    fn prepare_stream(&self, mut body: serde_json::Value) -> serde_json::Value {
+        body["stream"] = serde_json::Value::Bool(true);
+        body["stream_options"] = serde_json::json!({ "include_usage": true });
+        body
+    }
+
     fn parse_response(&self, raw_text: &str) -> crate::Result<LlmResponse> {
         let json_resp: serde_json::Value =
             serde_json::from_str(&raw_text).map_err(|e| crate::Error::Json(e))?;
@@ -95,7 +105,7 @@ impl Protocol for IFlowProtocol {
         Ok(LlmResponse {
             content,
             tool_calls: Vec::new(),
-            usage: Usage::default(),
+            usage: Usage::from_response(&json_resp).unwrap_or_default(),
             stop_reason: StopReason::EndTurn,
         })
     }
@@ -108,59 +118,60 @@ impl Protocol for IFlowProtocol {
 
         let stream = async_stream::stream! {
             let mut byte_stream = resp.bytes_stream();
-            let mut buffer = String::new();
+            let mut decoder = SseDecoder::new();
 
-            while let Some(chunk_res) = byte_stream.next().await {
-                let bytes = match chunk_res {
-                    Ok(b) => b,
-                    Err(e) => {
+            loop {
+                let (events, done) = match byte_stream.next().await {
+                    Some(Ok(bytes)) => (decoder.push(&bytes), false),
+                    Some(Err(e)) => {
                         yield Err(crate::Error::Http(e.to_string()));
                         continue;
                     }
+                    None => (decoder.finish(), true),
                 };
-                let s = String::from_utf8_lossy(&bytes);
-                buffer.push_str(&s);
 
                 // Check if this might be a pure non-SSE JSON response returned by mistake
-                if buffer.starts_with('{') && buffer.ends_with('}') {
-                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(&buffer) {
+                let buffered = decoder.buffered().trim();
+                if !done && events.is_empty() && buffered.starts_with('{') && buffered.ends_with('}') {
+                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(buffered) {
                         if let Some(content) = v["choices"][0]["message"]["content"].as_str() {
                             yield Ok(LlmChunk {
                                 delta: crate::provider::ChunkDelta::Text(content.to_string()),
-                                usage: None,
+                                usage: Usage::from_response(&v),
                             });
-                            buffer.clear();
+                            decoder.clear();
                             return;
                         }
                     }
                 }
 
-                while let Some(pos) = buffer.find("\n\n").or_else(|| buffer.find("\r\n\r\n")) {
-                    let offset = if buffer[pos..].starts_with("\r\n\r\n") { 4 } else { 2 };
-                    let event = buffer[..pos].to_string();
-                    buffer = buffer[pos + offset..].to_string();
-
-                    for line in event.lines() {
-                        if let Some(raw_data) = line.strip_prefix("data:") {
-                            let data = raw_data.trim_start();
-                            if data == "[DONE]" {
-                                return;
-                            }
-                            if let Ok(v) = serde_json::from_str::<serde_json::Value>(data) {
-                                if let Some(content) = v["choices"][0]["delta"]["content"].as_str() {
-                                    if !content.is_empty() {
-                                        yield Ok(LlmChunk {
-                                            delta: crate::provider::ChunkDelta::Text(
-                                                content.to_string(),
-                                            ),
-                                            usage: None,
-                                        });
-                                    }
-                                }
-                            }
+                for data in events {
+                    let data = data.trim_start();
+                    if data == "[DONE]" {
+                        return;
+                    }
+                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(data) {
+                        let reasoning = v["choices"][0]["delta"]["reasoning_content"].as_str().unwrap_or_default();
+                        if !reasoning.is_empty() {
+                            yield Ok(LlmChunk {
+                                delta: crate::provider::ChunkDelta::Thinking(reasoning.to_string()),
+                                usage: None,
+                            });
+                        }
+                        let content = v["choices"][0]["delta"]["content"].as_str().unwrap_or_default();
+                        // 开启 include_usage 时最后一个分片携带 usage
+                        let usage = Usage::from_response(&v);
+                        if !content.is_empty() || usage.is_some() {
+                            yield Ok(LlmChunk {
+                                delta: crate::provider::ChunkDelta::Text(content.to_string()),
+                                usage,
+                            });
                         }
                     }
                 }
+                if done {
+                    break;
+                }
             }
         };
 
@@ -170,15 +181,26 @@ impl Protocol for IFlowProtocol {
 
 pub struct IFlowEndpoint {
     auth: Arc<Mutex<IFlowAuth>>,
+    /// 当前 API Key 副本，inject_auth 不必等待正在进行的刷新
+    api_key: RwLock<Option<String>>,
 }
 
-#[async_trait]
-impl Endpoint for IFlowEndpoint {
-    async fn pre_flight(&self) -> crate::Result<()> {
+impl IFlowEndpoint {
+    /// 刷新（如临近过期）并同步 API Key 副本
+    async fn ensure_api_key(&self) -> crate::Result<()> {
         let mut auth_guard = self.auth.lock().await;
         auth_guard.ensure_authenticated().await.map_err(|e| crate::Error::Auth(e.to_string()))?;
+        *self.api_key.write().unwrap_or_else(|e| e.into_inner()) = auth_guard.api_key().map(|s| s.to_string());
         Ok(())
     }
+}
+
+#[async_trait]
+impl Endpoint for IFlowEndpoint {
+    async fn pre_flight(&self) -> crate::Result<()> {
+        // 后台任务已提前续期时，这里不会再请求 iFlow 平台
+        self.ensure_api_key().await
+    }
 
     fn url(&self, _model: &str, _is_stream: bool) -> crate::Result<String> {
         Ok("https://apis.iflow.cn/v1/chat/completions".to_string())
@@ -193,11 +215,13 @@ impl Endpoint for IFlowEndpoint {
     }
 
     fn inject_auth(&self, req: reqwest::RequestBuilder) -> crate::Result<reqwest::RequestBuilder> {
-        // 获取预先解密的 token，一定能在 pre_flight() 后的缓存拿到
-        let api_key = {
-            let guard = self.auth.try_lock().map_err(|_| crate::Error::Auth("Auth Mutex Failed".to_string()))?;
-            guard.api_key().map(|s| s.to_string()).ok_or_else(|| crate::Error::Auth("No API key available".to_string()))?
-        };
+        // pre_flight() 之后副本中一定有 API Key
+        let api_key = self
+            .api_key
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| crate::Error::Auth("No API key available".to_string()))?;
 
         let req = req
             .header("Authorization", format!("Bearer {}", api_key))
@@ -217,9 +241,7 @@ impl Endpoint for IFlowEndpoint {
     }
 
     async fn refresh_auth(&self) -> crate::Result<()> {
-        let mut auth_guard = self.auth.lock().await;
-        auth_guard.ensure_authenticated().await.map_err(|e| crate::Error::Auth(e.to_string()))?;
-        Ok(())
+        self.ensure_api_key().await
     }
 }
 
@@ -241,11 +263,12 @@ impl IFlowProvider {
             token_path: config.token_path.clone(),
         };
 
+        let api_key = RwLock::new(auth.api_key().map(|s| s.to_string()));
         let shared_auth = Arc::new(Mutex::new(auth));
 
         generic_client! {
             id: "iflow".to_string(),
-            endpoint: IFlowEndpoint { auth: shared_auth.clone() },
+            endpoint: IFlowEndpoint { auth: shared_auth.clone(), api_key },
             protocol: IFlowProtocol { default_model: config.model.clone() },
             auth: auth_enum,
             supported_models: vec![
@@ -261,12 +284,30 @@ impl IFlowProvider {
     /// 获取 API Key（公开方法，供示例和调试使用）
     pub async fn fetch_api_key(&self) -> crate::Result<String> {
         let mut auth_guard = self.endpoint.auth.lock().await;
-        auth_guard.fetch_api_key().await.map_err(|e| crate::Error::Auth(e.to_string()))
+        let api_key = auth_guard.fetch_api_key().await.map_err(|e| crate::Error::Auth(e.to_string()))?;
+        *self.endpoint.api_key.write().unwrap_or_else(|e| e.into_inner()) = Some(api_key.clone());
+        Ok(api_key)
     }
 
     /// 清除 API Key 缓存
     pub async fn clear_cache(&self) {
         let mut auth_guard = self.endpoint.auth.lock().await;
         auth_guard.clear_cache();
+        *self.endpoint.api_key.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_stream_enables_sse_and_usage() {
+        let protocol = IFlowProtocol { default_model: models::DEFAULT_MODEL.to_string() };
+        let body = protocol.compile(&PrimitiveRequest::single_user_message("hi"));
+        let body = protocol.prepare_stream(body);
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["stream_options"]["include_usage"], true);
+        assert_eq!(body["model"], models::DEFAULT_MODEL);
     }
 }
@@ -0,0 +1,542 @@
+//! 本地推理后端
+//!
+//! `LocalBackend` 抽象了"加载 GGUF → 流式生成 → 计算嵌入"三件事；
+//! `LlamaCppBackend` 驱动本机常驻的 llama.cpp `llama-server`，只在本机回环地址通信，
+//! 全程不访问外部网络，适用于离线部署。
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::Mutex;
+
+use super::gguf::GgufModelInfo;
+use crate::provider::sse::SseDecoder;
+use crate::provider::{BoxStream, ProviderError, Usage};
+
+/// 服务端启动失败时保留的日志行数
+const LOG_TAIL_LINES: usize = 20;
+
+/// 生成参数
+#[derive(Debug, Clone, Default)]
+pub struct GenerationParams {
+    /// 最大生成 token 数
+    pub max_tokens: Option<u64>,
+    /// 温度
+    pub temperature: Option<f32>,
+    /// Top-P
+    pub top_p: Option<f32>,
+}
+
+/// 生成事件
+#[derive(Debug, Clone)]
+pub enum LocalEvent {
+    /// 增量文本
+    Token(String),
+    /// 生成结束，附带 token 用量
+    Done(Usage),
+}
+
+/// 本地推理后端
+#[async_trait]
+pub trait LocalBackend: Send + Sync {
+    /// 已加载模型的元数据
+    fn model(&self) -> &GgufModelInfo;
+
+    /// 流式生成，最后一个事件为 `LocalEvent::Done`
+    async fn generate(
+        &self,
+        prompt: &str,
+        params: &GenerationParams,
+    ) -> crate::Result<BoxStream<'static, crate::Result<LocalEvent>>>;
+
+    /// 计算文本嵌入
+    async fn embed(&self, text: &str) -> crate::Result<Vec<f32>>;
+}
+
+/// 运行中的 `llama-server`
+struct LlamaServer {
+    url: String,
+    /// 由本后端启动的进程；连接外部服务时为空
+    child: Option<tokio::process::Child>,
+    /// 最近的服务端日志，用于报告启动失败与意外退出
+    log: Arc<std::sync::Mutex<VecDeque<String>>>,
+}
+
+impl LlamaServer {
+    fn external(url: String) -> Self {
+        Self {
+            url,
+            child: None,
+            log: Arc::default(),
+        }
+    }
+
+    fn is_running(&mut self) -> bool {
+        match self.child.as_mut() {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => true,
+        }
+    }
+
+    fn log_tail(&self) -> String {
+        let log = self.log.lock().unwrap();
+        log.iter().rev().take(5).rev().cloned().collect::<Vec<_>>().join(" | ")
+    }
+}
+
+/// llama.cpp 后端
+///
+/// 首次使用时启动常驻的 `llama-server`，模型只加载一次；提示词放在 HTTP 请求体中，不受命令行长度限制，
+/// 也不会出现在进程列表里。token 用量取自服务端在流末尾返回的统计。嵌入需要以 `--embeddings`
+/// 启动的实例，首次计算嵌入时另起一个。进程意外退出后下次请求自动重启，后端析构时一并结束。
+pub struct LlamaCppBackend {
+    model: GgufModelInfo,
+    server_binary: PathBuf,
+    context_size: Option<u64>,
+    threads: Option<usize>,
+    gpu_layers: Option<u32>,
+    startup_timeout: Duration,
+    client: reqwest::Client,
+    generation: Mutex<Option<LlamaServer>>,
+    embedding: Mutex<Option<LlamaServer>>,
+}
+
+impl LlamaCppBackend {
+    /// 加载 GGUF 模型（只读取元数据，推理时由 llama.cpp 映射权重）
+    pub fn load(model_path: impl AsRef<Path>) -> crate::Result<Self> {
+        Ok(Self::new(GgufModelInfo::read(model_path)?))
+    }
+
+    fn new(model: GgufModelInfo) -> Self {
+        Self {
+            model,
+            server_binary: PathBuf::from("llama-server"),
+            context_size: None,
+            threads: None,
+            gpu_layers: None,
+            startup_timeout: Duration::from_secs(120),
+            client: reqwest::Client::new(),
+            generation: Mutex::new(None),
+            embedding: Mutex::new(None),
+        }
+    }
+
+    /// 设置服务端可执行文件路径（默认 `llama-server`）
+    pub fn with_server_binary(mut self, path: impl Into<PathBuf>) -> Self {
+        self.server_binary = path.into();
+        self
+    }
+
+    /// 连接已运行的 `llama-server`（如 `http://127.0.0.1:8080`），不再自行启动
+    ///
+    /// 生成与嵌入共用该服务，嵌入要求服务端以 `--embeddings` 启动。
+    pub fn with_server_url(self, url: impl Into<String>) -> Self {
+        let url = url.into().trim_end_matches('/').to_string();
+        Self {
+            generation: Mutex::new(Some(LlamaServer::external(url.clone()))),
+            embedding: Mutex::new(Some(LlamaServer::external(url))),
+            ..self
+        }
+    }
+
+    /// 设置等待服务端加载模型的超时（默认 120 秒）
+    pub fn with_startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// 设置上下文长度（默认使用模型训练长度）
+    pub fn with_context_size(mut self, tokens: u64) -> Self {
+        self.context_size = Some(tokens);
+        self
+    }
+
+    /// 设置推理线程数
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// 设置卸载到 GPU 的层数
+    pub fn with_gpu_layers(mut self, layers: u32) -> Self {
+        self.gpu_layers = Some(layers);
+        self
+    }
+
+    /// 返回可用服务的地址，未启动或已退出时（重新）启动
+    async fn server(&self, slot: &Mutex<Option<LlamaServer>>, embeddings: bool) -> crate::Result<String> {
+        let mut slot = slot.lock().await;
+        if let Some(server) = slot.as_mut() {
+            if server.is_running() {
+                return Ok(server.url.clone());
+            }
+            tracing::warn!("llama-server exited, restarting: {}", server.log_tail());
+        }
+        let server = self.spawn_server(embeddings).await?;
+        let url = server.url.clone();
+        *slot = Some(server);
+        Ok(url)
+    }
+
+    async fn spawn_server(&self, embeddings: bool) -> crate::Result<LlamaServer> {
+        let port = std::net::TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port();
+        let mut cmd = tokio::process::Command::new(&self.server_binary);
+        cmd.arg("-m").arg(&self.model.path);
+        cmd.args(["--host", "127.0.0.1", "--port", &port.to_string()]);
+        if let Some(ctx) = self.context_size.or(self.model.context_length()) {
+            cmd.arg("-c").arg(ctx.to_string());
+        }
+        if let Some(threads) = self.threads {
+            cmd.arg("-t").arg(threads.to_string());
+        }
+        if let Some(layers) = self.gpu_layers {
+            cmd.arg("-ngl").arg(layers.to_string());
+        }
+        if embeddings {
+            cmd.arg("--embeddings");
+        }
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| backend_error(format!("failed to start {}: {}", self.server_binary.display(), e)))?;
+        let log: Arc<std::sync::Mutex<VecDeque<String>>> = Arc::default();
+        if let Some(stderr) = child.stderr.take() {
+            let log = log.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    tracing::trace!(target: "llama_server", "{}", line);
+                    let mut log = log.lock().unwrap();
+                    if log.len() == LOG_TAIL_LINES {
+                        log.pop_front();
+                    }
+                    log.push_back(line);
+                }
+            });
+        }
+        let mut server = LlamaServer {
+            url: format!("http://127.0.0.1:{}", port),
+            child: Some(child),
+            log,
+        };
+
+        // 模型加载完成前 /health 返回 503
+        let deadline = tokio::time::Instant::now() + self.startup_timeout;
+        loop {
+            if let Some(status) = server.child.as_mut().and_then(|c| c.try_wait().ok().flatten()) {
+                return Err(backend_error(format!("llama-server exited with {}: {}", status, server.log_tail())));
+            }
+            let health = self.client.get(format!("{}/health", server.url)).send().await;
+            if health.is_ok_and(|r| r.status().is_success()) {
+                tracing::info!("llama-server for {} listening on {}", self.model.path.display(), server.url);
+                return Ok(server);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(backend_error(format!(
+                    "llama-server not ready after {:?}: {}",
+                    self.startup_timeout,
+                    server.log_tail()
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+
+    async fn post(&self, url: String, body: &Value) -> crate::Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| crate::Error::Http(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(backend_error(format!("llama-server returned {}: {}", status, text.trim())));
+        }
+        Ok(response)
+    }
+}
+
+fn backend_error(message: String) -> crate::Error {
+    crate::Error::Provider(ProviderError::fail(message))
+}
+
+#[async_trait]
+impl LocalBackend for LlamaCppBackend {
+    fn model(&self) -> &GgufModelInfo {
+        &self.model
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        params: &GenerationParams,
+    ) -> crate::Result<BoxStream<'static, crate::Result<LocalEvent>>> {
+        let url = self.server(&self.generation, false).await?;
+        let mut body = json!({
+            "prompt": prompt,
+            "n_predict": params.max_tokens.map_or(-1, |n| n as i64),
+            "stream": true,
+            "cache_prompt": true,
+        });
+        if let Some(temperature) = params.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        let mut bytes = self.post(format!("{}/completion", url), &body).await?.bytes_stream();
+
+        let stream = async_stream::stream! {
+            let mut decoder = SseDecoder::new();
+            loop {
+                let (events, finished) = match bytes.next().await {
+                    Some(Ok(chunk)) => (decoder.push(&chunk), false),
+                    Some(Err(e)) => {
+                        yield Err(crate::Error::Http(e.to_string()));
+                        return;
+                    }
+                    None => (decoder.finish(), true),
+                };
+                for data in events {
+                    match parse_completion_event(&data) {
+                        Ok((content, usage)) => {
+                            if !content.is_empty() {
+                                yield Ok(LocalEvent::Token(content));
+                            }
+                            if let Some(usage) = usage {
+                                yield Ok(LocalEvent::Done(usage));
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                }
+                if finished {
+                    break;
+                }
+            }
+            yield Err(backend_error("llama-server stream ended without a final stop event".into()));
+        };
+        Ok(Box::pin(stream))
+    }
+
+    async fn embed(&self, text: &str) -> crate::Result<Vec<f32>> {
+        let url = self.server(&self.embedding, true).await?;
+        // OpenAI 兼容接口返回按欧氏范数归一化的向量
+        let value: Value = self
+            .post(format!("{}/v1/embeddings", url), &json!({ "input": text }))
+            .await?
+            .json()
+            .await
+            .map_err(|e| crate::Error::Http(e.to_string()))?;
+        value["data"][0]["embedding"]
+            .as_array()
+            .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+            .ok_or_else(|| backend_error("embedding response has no data[0].embedding".into()))
+    }
+}
+
+/// 解析 `/completion` 流的一个事件，返回增量文本；`stop` 事件同时返回 token 用量
+fn parse_completion_event(data: &str) -> crate::Result<(String, Option<Usage>)> {
+    let value: Value = serde_json::from_str(data)?;
+    if let Some(error) = value.get("error") {
+        let message = error["message"].as_str().map_or_else(|| error.to_string(), str::to_string);
+        return Err(backend_error(format!("llama-server error: {}", message)));
+    }
+    let content = value["content"].as_str().unwrap_or_default().to_string();
+    if value["stop"].as_bool() != Some(true) {
+        return Ok((content, None));
+    }
+
+    let count = |field: &str, timing: &str| value[field].as_u64().or_else(|| value["timings"][timing].as_u64());
+    match (count("tokens_evaluated", "prompt_n"), count("tokens_predicted", "predicted_n")) {
+        (Some(input_tokens), Some(output_tokens)) => Ok((
+            content,
+            Some(Usage {
+                input_tokens,
+                output_tokens,
+                cached_tokens: value["tokens_cached"].as_u64(),
+                ..Default::default()
+            }),
+        )),
+        _ => Err(backend_error("llama-server stop event has no token counts".into())),
+    }
+}
+
+/// 取出缓冲区中完整的 UTF-8 前缀，保留被截断的多字节字符
+///
+/// 无效字节逐段替换为 U+FFFD（与 `String::from_utf8_lossy` 一致），结果与分片方式无关。
+pub(crate) fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    let mut start = 0;
+    while start < pending.len() {
+        match std::str::from_utf8(&pending[start..]) {
+            Ok(valid) => {
+                text.push_str(valid);
+                start = pending.len();
+            }
+            Err(e) => {
+                let valid_end = start + e.valid_up_to();
+                text.push_str(&String::from_utf8_lossy(&pending[start..valid_end]));
+                match e.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        start = valid_end + len;
+                    }
+                    None => {
+                        start = valid_end;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    pending.drain(..start);
+    text
+}
+
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::post;
+    use axum::{Json, Router};
+
+    use super::*;
+
+    /// 在回环地址上模拟 `llama-server`，记录收到的请求体
+    async fn fake_server(completion: &'static str) -> (String, Arc<std::sync::Mutex<Vec<Value>>>) {
+        let requests: Arc<std::sync::Mutex<Vec<Value>>> = Arc::default();
+        let recorded = requests.clone();
+        let app = Router::new()
+            .route(
+                "/completion",
+                post(move |Json(body): Json<Value>| async move {
+                    recorded.lock().unwrap().push(body);
+                    ([("content-type", "text/event-stream")], completion)
+                }),
+            )
+            .route(
+                "/v1/embeddings",
+                post(|Json(body): Json<Value>| async move {
+                    let len = body["input"].as_str().unwrap().len();
+                    Json(json!({ "data": [{ "index": 0, "embedding": [len as f64, 0.5] }] }))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
+
+    fn backend(url: &str) -> LlamaCppBackend {
+        let model = GgufModelInfo {
+            path: "tiny.gguf".into(),
+            version: 3,
+            tensor_count: 0,
+            metadata: Default::default(),
+        };
+        LlamaCppBackend::new(model).with_server_url(url)
+    }
+
+    async fn collect(backend: &LlamaCppBackend, prompt: &str) -> Vec<crate::Result<LocalEvent>> {
+        let params = GenerationParams {
+            max_tokens: Some(16),
+            temperature: Some(0.2),
+            ..Default::default()
+        };
+        backend.generate(prompt, &params).await.unwrap().collect().await
+    }
+
+    #[tokio::test]
+    async fn test_generation_streams_tokens_and_reports_server_usage() {
+        let (url, requests) = fake_server(
+            "data: {\"content\":\"Hel\",\"stop\":false}\n\n\
+             data: {\"content\":\"lo\",\"stop\":false}\n\n\
+             data: {\"content\":\"\",\"stop\":true,\"tokens_evaluated\":9,\"tokens_predicted\":2,\"tokens_cached\":4}\n\n",
+        )
+        .await;
+        let backend = backend(&url);
+
+        // 提示词在请求体中，远超命令行参数长度上限也能完整送达
+        let prompt = "问".repeat(300_000);
+        let events = collect(&backend, &prompt).await;
+        let text: String = events
+            .iter()
+            .filter_map(|e| match e {
+                Ok(LocalEvent::Token(t)) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hello");
+        let Some(Ok(LocalEvent::Done(usage))) = events.last() else { panic!("{:?}", events) };
+        assert_eq!((usage.input_tokens, usage.output_tokens, usage.cached_tokens), (9, 2, Some(4)));
+
+        let body = requests.lock().unwrap().pop().unwrap();
+        assert_eq!(body["prompt"].as_str().unwrap().len(), prompt.len());
+        assert_eq!((body["n_predict"].as_i64(), body["stream"].as_bool()), (Some(16), Some(true)));
+
+        assert_eq!(backend.embed("abc").await.unwrap(), vec![3.0, 0.5]);
+    }
+
+    #[tokio::test]
+    async fn test_generation_without_server_counts_is_an_error() {
+        // 流在 stop 事件前中断
+        let (url, _) = fake_server("data: {\"content\":\"Hi\",\"stop\":false}\n\n").await;
+        let events = collect(&backend(&url), "hi").await;
+        assert!(matches!(&events[0], Ok(LocalEvent::Token(t)) if t == "Hi"));
+        assert!(events[1].as_ref().unwrap_err().to_string().contains("without a final stop event"));
+
+        // stop 事件缺少统计时报错而不是估算
+        let (url, _) = fake_server("data: {\"content\":\"Hi\",\"stop\":true}\n\n").await;
+        let events = collect(&backend(&url), "hi").await;
+        assert!(events.last().unwrap().as_ref().unwrap_err().to_string().contains("no token counts"));
+
+        // 仅有 timings 时取其中的计数
+        let usage = parse_completion_event(r#"{"content":"","stop":true,"timings":{"prompt_n":3,"predicted_n":5}}"#)
+            .unwrap()
+            .1
+            .unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens, usage.cached_tokens), (3, 5, None));
+        assert!(parse_completion_event(r#"{"error":{"code":500,"message":"boom"}}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_that_exits_during_startup_is_reported() {
+        let model = GgufModelInfo {
+            path: "tiny.gguf".into(),
+            version: 3,
+            tensor_count: 0,
+            metadata: Default::default(),
+        };
+        let backend = LlamaCppBackend::new(model).with_server_binary("false");
+        let err = backend.generate("hi", &GenerationParams::default()).await.err().unwrap();
+        assert!(err.to_string().contains("llama-server exited"), "{}", err);
+    }
+
+    #[test]
+    fn test_utf8_chunks() {
+        let bytes = "你好".as_bytes();
+        let mut pending = bytes[..4].to_vec();
+        assert_eq!(take_utf8(&mut pending), "你");
+        pending.extend_from_slice(&bytes[4..]);
+        assert_eq!(take_utf8(&mut pending), "好");
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+//! GGUF 模型文件元数据解析
+//!
+//! 只读取文件头与 KV 元数据（架构、上下文长度、嵌入维度等），不加载张量。
+//! 大数组（如分词表）只记录元素类型与长度。
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::provider::ProviderError;
+
+/// GGUF 文件魔数 ("GGUF")
+const GGUF_MAGIC: [u8; 4] = *b"GGUF";
+
+/// 单个字符串元数据的长度上限，防止损坏文件导致巨量分配
+const MAX_STRING_LEN: u64 = 1 << 24;
+
+/// GGUF 元数据值
+#[derive(Debug, Clone, PartialEq)]
+pub enum GgufValue {
+    /// 无符号整数 (u8/u16/u32/u64)
+    Uint(u64),
+    /// 有符号整数 (i8/i16/i32/i64)
+    Int(i64),
+    /// 浮点数 (f32/f64)
+    Float(f64),
+    /// 布尔值
+    Bool(bool),
+    /// 字符串
+    String(String),
+    /// 数组（仅记录元素类型与长度）
+    Array { item_type: u32, len: u64 },
+}
+
+impl GgufValue {
+    /// 按整数读取
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            GgufValue::Uint(v) => Some(*v),
+            GgufValue::Int(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    /// 按字符串读取
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            GgufValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// GGUF 模型信息
+#[derive(Debug, Clone)]
+pub struct GgufModelInfo {
+    /// 模型文件路径
+    pub path: PathBuf,
+    /// GGUF 版本
+    pub version: u32,
+    /// 张量数量
+    pub tensor_count: u64,
+    /// 元数据
+    pub metadata: BTreeMap<String, GgufValue>,
+}
+
+impl GgufModelInfo {
+    /// 读取模型文件头
+    pub fn read(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != GGUF_MAGIC {
+            return Err(invalid(format!("{} is not a GGUF file", path.display())));
+        }
+        let version = read_u32(&mut reader)?;
+        if version < 2 {
+            return Err(invalid(format!("unsupported GGUF version {}", version)));
+        }
+        let tensor_count = read_u64(&mut reader)?;
+        let kv_count = read_u64(&mut reader)?;
+
+        let mut metadata = BTreeMap::new();
+        for _ in 0..kv_count {
+            let key = read_string(&mut reader)?;
+            let value_type = read_u32(&mut reader)?;
+            let value = read_value(&mut reader, value_type)?;
+            metadata.insert(key, value);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            version,
+            tensor_count,
+            metadata,
+        })
+    }
+
+    /// 模型架构 (`general.architecture`)
+    pub fn architecture(&self) -> Option<&str> {
+        self.metadata.get("general.architecture").and_then(GgufValue::as_str)
+    }
+
+    /// 模型名称 (`general.name`)，缺省时使用文件名
+    pub fn name(&self) -> String {
+        self.metadata
+            .get("general.name")
+            .and_then(GgufValue::as_str)
+            .map(String::from)
+            .or_else(|| {
+                self.path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "local".to_string())
+    }
+
+    /// 训练上下文长度 (`<arch>.context_length`)
+    pub fn context_length(&self) -> Option<u64> {
+        self.arch_value("context_length")
+    }
+
+    /// 嵌入维度 (`<arch>.embedding_length`)
+    pub fn embedding_length(&self) -> Option<u64> {
+        self.arch_value("embedding_length")
+    }
+
+    fn arch_value(&self, key: &str) -> Option<u64> {
+        let arch = self.architecture()?;
+        self.metadata
+            .get(&format!("{}.{}", arch, key))
+            .and_then(GgufValue::as_u64)
+    }
+}
+
+fn invalid(message: String) -> crate::Error {
+    crate::Error::Provider(ProviderError::fail(message))
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> crate::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u32(reader: &mut impl Read) -> crate::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_u64(reader: &mut impl Read) -> crate::Result<u64> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_string(reader: &mut impl Read) -> crate::Result<String> {
+    let len = read_u64(reader)?;
+    if len > MAX_STRING_LEN {
+        return Err(invalid(format!("GGUF string too long ({} bytes)", len)));
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// 标量类型的字节宽度；字符串与数组返回 `None`
+fn scalar_size(value_type: u32) -> Option<i64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+fn read_value<R: Read + Seek>(reader: &mut R, value_type: u32) -> crate::Result<GgufValue> {
+    Ok(match value_type {
+        0 => GgufValue::Uint(read_bytes::<1>(reader)?[0] as u64),
+        1 => GgufValue::Int(i8::from_le_bytes(read_bytes(reader)?) as i64),
+        2 => GgufValue::Uint(u16::from_le_bytes(read_bytes(reader)?) as u64),
+        3 => GgufValue::Int(i16::from_le_bytes(read_bytes(reader)?) as i64),
+        4 => GgufValue::Uint(read_u32(reader)? as u64),
+        5 => GgufValue::Int(i32::from_le_bytes(read_bytes(reader)?) as i64),
+        6 => GgufValue::Float(f32::from_le_bytes(read_bytes(reader)?) as f64),
+        7 => GgufValue::Bool(read_bytes::<1>(reader)?[0] != 0),
+        8 => GgufValue::String(read_string(reader)?),
+        9 => {
+            let item_type = read_u32(reader)?;
+            let len = read_u64(reader)?;
+            match scalar_size(item_type) {
+                Some(size) => {
+                    let bytes = i64::try_from(len)
+                        .ok()
+                        .and_then(|len| len.checked_mul(size))
+                        .ok_or_else(|| invalid(format!("GGUF array too long ({})", len)))?;
+                    reader.seek(SeekFrom::Current(bytes))?;
+                }
+                None => {
+                    for _ in 0..len {
+                        read_value(reader, item_type)?;
+                    }
+                }
+            }
+            GgufValue::Array { item_type, len }
+        }
+        10 => GgufValue::Uint(read_u64(reader)?),
+        11 => GgufValue::Int(i64::from_le_bytes(read_bytes(reader)?)),
+        12 => GgufValue::Float(f64::from_le_bytes(read_bytes(reader)?)),
+        other => return Err(invalid(format!("unknown GGUF value type {}", other))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend_from_slice(&(s.len() as u64).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    #[test]
+    fn test_read_metadata() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"GGUF");
+        buf.extend_from_slice(&3u32.to_le_bytes());
+        buf.extend_from_slice(&0u64.to_le_bytes());
+        buf.extend_from_slice(&4u64.to_le_bytes());
+
+        push_string(&mut buf, "general.architecture");
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        push_string(&mut buf, "llama");
+
+        push_string(&mut buf, "llama.context_length");
+        buf.extend_from_slice(&4u32.to_le_bytes());
+        buf.extend_from_slice(&4096u32.to_le_bytes());
+
+        push_string(&mut buf, "tokenizer.ggml.tokens");
+        buf.extend_from_slice(&9u32.to_le_bytes());
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        buf.extend_from_slice(&2u64.to_le_bytes());
+        push_string(&mut buf, "<s>");
+        push_string(&mut buf, "</s>");
+
+        push_string(&mut buf, "llama.embedding_length");
+        buf.extend_from_slice(&10u32.to_le_bytes());
+        buf.extend_from_slice(&2048u64.to_le_bytes());
+
+        let path = std::env::temp_dir().join(format!("nl_gguf_{}.gguf", uuid::Uuid::new_v4()));
+        std::fs::write(&path, &buf).unwrap();
+        let info = GgufModelInfo::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(info.version, 3);
+        assert_eq!(info.architecture(), Some("llama"));
+        assert_eq!(info.context_length(), Some(4096));
+        assert_eq!(info.embedding_length(), Some(2048));
+        assert_eq!(
+            info.metadata["tokenizer.ggml.tokens"],
+            GgufValue::Array { item_type: 8, len: 2 }
+        );
+        assert!(info.name().starts_with("nl_gguf_"));
+    }
+}
@@ -0,0 +1,17 @@
+//! 本地模型 Provider（离线推理）
+//!
+//! 加载 GGUF 模型并在本机推理，不访问外部网络：
+//! - `gguf`：读取模型元数据（架构、上下文长度、对话模板）
+//! - `backend`：推理后端抽象与 llama.cpp 实现（本机回环地址上的常驻 `llama-server`），支持流式生成与嵌入
+//! - `template`：把原语请求渲染为模型对话模板
+//! - `provider`：`LocalProvider`，按 `LlmProvider` 注册到 Gateway，自行统计 token 用量
+
+pub mod backend;
+pub mod gguf;
+pub mod provider;
+pub mod template;
+
+pub use backend::{GenerationParams, LlamaCppBackend, LocalBackend, LocalEvent};
+pub use gguf::{GgufModelInfo, GgufValue};
+pub use provider::LocalProvider;
+pub use template::PromptTemplate;
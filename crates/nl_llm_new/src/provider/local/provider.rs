@@ -0,0 +1,311 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use super::backend::{GenerationParams, LocalBackend, LocalEvent};
+use super::template::PromptTemplate;
+use crate::auth::{ApiKeyConfig, ApiKeyProvider, Auth};
+use crate::primitive::PrimitiveRequest;
+use crate::provider::{
+    BoxStream, ChunkDelta, LlmChunk, LlmProvider, LlmResponse, StopReason, Usage,
+};
+
+/// 累计 token 用量
+#[derive(Debug, Default)]
+struct UsageCounter {
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+}
+
+impl UsageCounter {
+    fn add(&self, usage: &Usage) {
+        self.input_tokens.fetch_add(usage.input_tokens, Ordering::Relaxed);
+        self.output_tokens.fetch_add(usage.output_tokens, Ordering::Relaxed);
+    }
+}
+
+/// 本地模型 Provider
+///
+/// 不依赖网络，按 `LlmProvider` 注册到 Gateway 后与远程 Provider 一样参与路由与降级。
+pub struct LocalProvider {
+    id: String,
+    auth: Auth,
+    backend: Arc<dyn LocalBackend>,
+    template: PromptTemplate,
+    models: &'static [&'static str],
+    usage: Arc<UsageCounter>,
+}
+
+impl LocalProvider {
+    /// 基于后端创建 Provider，提示词模板从模型元数据推断
+    pub fn new(id: impl Into<String>, backend: Arc<dyn LocalBackend>) -> Self {
+        let template = PromptTemplate::detect(backend.model());
+        // 模型名只在创建时泄漏一次，用于满足 `supported_models` 的 'static 约束
+        let name: &'static str = Box::leak(backend.model().name().into_boxed_str());
+        Self {
+            id: id.into(),
+            auth: Auth::ApiKey(ApiKeyConfig::new("local", ApiKeyProvider::OpenAI)),
+            backend,
+            template,
+            models: Box::leak(Box::new([name])),
+            usage: Arc::new(UsageCounter::default()),
+        }
+    }
+
+    /// 指定提示词模板
+    pub fn with_template(mut self, template: PromptTemplate) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// 自创建以来的累计 token 用量
+    pub fn total_usage(&self) -> Usage {
+        Usage {
+            input_tokens: self.usage.input_tokens.load(Ordering::Relaxed),
+            output_tokens: self.usage.output_tokens.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+
+    /// 计算一组文本的嵌入向量
+    pub async fn embed(&self, texts: &[String]) -> crate::Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            embeddings.push(self.backend.embed(text).await?);
+        }
+        Ok(embeddings)
+    }
+
+    fn params(body: &serde_json::Value) -> (GenerationParams, Vec<String>) {
+        let params = GenerationParams {
+            max_tokens: body["max_tokens"].as_u64(),
+            temperature: body["temperature"].as_f64().map(|v| v as f32),
+            top_p: body["top_p"].as_f64().map(|v| v as f32),
+        };
+        let stop = body["stop"]
+            .as_array()
+            .map(|items| items.iter().filter_map(|s| s.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        (params, stop)
+    }
+}
+
+/// 返回文本中最早出现的停止序列位置
+fn find_stop(text: &str, stop: &[String]) -> Option<usize> {
+    stop.iter()
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| text.find(s.as_str()))
+        .min()
+}
+
+#[async_trait]
+impl LlmProvider for LocalProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn auth(&self) -> &Auth {
+        &self.auth
+    }
+
+    fn supported_models(&self) -> &[&str] {
+        self.models
+    }
+
+    fn compile(&self, primitive: &PrimitiveRequest) -> serde_json::Value {
+        serde_json::json!({
+            "prompt": self.template.render(primitive),
+            "max_tokens": primitive.parameters.max_tokens,
+            "temperature": primitive.parameters.temperature,
+            "top_p": primitive.parameters.top_p,
+            "stop": primitive.parameters.stop_sequences.clone().unwrap_or_default(),
+        })
+    }
+
+    async fn complete(&self, body: serde_json::Value) -> crate::Result<LlmResponse> {
+        let (params, stop) = Self::params(&body);
+        let prompt = body["prompt"].as_str().unwrap_or_default();
+        let mut events = self.backend.generate(prompt, &params).await?;
+
+        let mut content = String::new();
+        let mut usage = Usage::default();
+        let mut stop_reason = StopReason::EndTurn;
+        while let Some(event) = events.next().await {
+            match event? {
+                LocalEvent::Token(text) => {
+                    content.push_str(&text);
+                    if let Some(pos) = find_stop(&content, &stop) {
+                        content.truncate(pos);
+                        stop_reason = StopReason::StopSequence;
+                        break;
+                    }
+                }
+                LocalEvent::Done(done) => usage = done,
+            }
+        }
+        if stop_reason == StopReason::StopSequence {
+            // 提前结束时后端不会上报用量，按约 4 字符/token 估算
+            usage.input_tokens = (prompt.chars().count() as u64).div_ceil(4);
+            usage.output_tokens = (content.chars().count() as u64).div_ceil(4);
+        } else if params.max_tokens.is_some_and(|max| usage.output_tokens >= max) {
+            stop_reason = StopReason::MaxTokens;
+        }
+        self.usage.add(&usage);
+
+        Ok(LlmResponse {
+            content,
+            tool_calls: Vec::new(),
+            usage,
+            stop_reason,
+        })
+    }
+
+    async fn stream(
+        &self,
+        body: serde_json::Value,
+    ) -> crate::Result<BoxStream<'_, crate::Result<LlmChunk>>> {
+        let (params, stop) = Self::params(&body);
+        let prompt = body["prompt"].as_str().unwrap_or_default().to_string();
+        let mut events = self.backend.generate(&prompt, &params).await?;
+        let counter = self.usage.clone();
+
+        let stream = async_stream::stream! {
+            let mut emitted = 0;
+            let mut content = String::new();
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(LocalEvent::Token(text)) => {
+                        content.push_str(&text);
+                        if let Some(pos) = find_stop(&content, &stop) {
+                            if pos > emitted {
+                                yield Ok(LlmChunk {
+                                    delta: ChunkDelta::Text(content[emitted..pos].to_string()),
+                                    usage: None,
+                                });
+                            }
+                            let usage = Usage {
+                                input_tokens: (prompt.chars().count() as u64).div_ceil(4),
+                                output_tokens: (content[..pos].chars().count() as u64).div_ceil(4),
+                                ..Default::default()
+                            };
+                            counter.add(&usage);
+                            yield Ok(LlmChunk { delta: ChunkDelta::Text(String::new()), usage: Some(usage) });
+                            // 丢弃后端流即终止推理进程
+                            return;
+                        }
+                        // 保留可能是停止序列前缀的尾部，等待后续文本确认
+                        let hold = stop.iter().map(|s| s.len().saturating_sub(1)).max().unwrap_or(0);
+                        let mut safe = content.len().saturating_sub(hold).max(emitted);
+                        while !content.is_char_boundary(safe) {
+                            safe -= 1;
+                        }
+                        if safe > emitted {
+                            yield Ok(LlmChunk {
+                                delta: ChunkDelta::Text(content[emitted..safe].to_string()),
+                                usage: None,
+                            });
+                            emitted = safe;
+                        }
+                    }
+                    Ok(LocalEvent::Done(usage)) => {
+                        counter.add(&usage);
+                        yield Ok(LlmChunk {
+                            delta: ChunkDelta::Text(content[emitted..].to_string()),
+                            usage: Some(usage),
+                        });
+                        return;
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+        };
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::PrimitiveMessage;
+    use crate::provider::local::gguf::GgufModelInfo;
+
+    struct ScriptedBackend {
+        model: GgufModelInfo,
+        tokens: Vec<&'static str>,
+    }
+
+    #[async_trait]
+    impl LocalBackend for ScriptedBackend {
+        fn model(&self) -> &GgufModelInfo {
+            &self.model
+        }
+
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _params: &GenerationParams,
+        ) -> crate::Result<BoxStream<'static, crate::Result<LocalEvent>>> {
+            let events: Vec<_> = self
+                .tokens
+                .iter()
+                .map(|t| Ok(LocalEvent::Token(t.to_string())))
+                .chain(std::iter::once(Ok(LocalEvent::Done(Usage {
+                    input_tokens: 7,
+                    output_tokens: self.tokens.len() as u64,
+                    ..Default::default()
+                }))))
+                .collect();
+            Ok(Box::pin(futures::stream::iter(events)))
+        }
+
+        async fn embed(&self, text: &str) -> crate::Result<Vec<f32>> {
+            Ok(vec![text.len() as f32])
+        }
+    }
+
+    fn provider(tokens: Vec<&'static str>) -> LocalProvider {
+        let model = GgufModelInfo {
+            path: "tiny.gguf".into(),
+            version: 3,
+            tensor_count: 0,
+            metadata: Default::default(),
+        };
+        LocalProvider::new("local", Arc::new(ScriptedBackend { model, tokens }))
+    }
+
+    #[tokio::test]
+    async fn test_local_generation_accounting_and_stop() {
+        let local = provider(vec!["Hel", "lo", " world"]);
+        assert_eq!(local.supported_models(), &["tiny"]);
+
+        let body = local.compile(&PrimitiveRequest::new("tiny").with_message(PrimitiveMessage::user("hi")));
+        let response = local.complete(body.clone()).await.unwrap();
+        assert_eq!(response.content, "Hello world");
+        assert_eq!(response.usage.input_tokens, 7);
+
+        let chunks: Vec<_> = local.stream(body).await.unwrap().collect().await;
+        let text: String = chunks
+            .iter()
+            .filter_map(|c| match &c.as_ref().unwrap().delta {
+                ChunkDelta::Text(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hello world");
+        assert_eq!(local.total_usage().input_tokens, 14);
+        assert_eq!(local.total_usage().output_tokens, 6);
+
+        let mut stopped = local.compile(&PrimitiveRequest::new("tiny").with_message(PrimitiveMessage::user("hi")));
+        stopped["stop"] = serde_json::json!(["lo w"]);
+        let response = local.complete(stopped).await.unwrap();
+        assert_eq!(response.content, "Hel");
+        assert_eq!(response.stop_reason, StopReason::StopSequence);
+
+        assert_eq!(local.embed(&["abc".to_string()]).await.unwrap(), vec![vec![3.0]]);
+    }
+}
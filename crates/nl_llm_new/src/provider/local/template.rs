@@ -0,0 +1,90 @@
+//! 对话提示词模板
+//!
+//! 消息内容中的 `<|` 会被插入不可见的 U+2060，避免其中的 `<|im_end|>` 等特殊标记被当作回合边界，
+//! 伪造出系统或助手回合。
+
+use super::gguf::{GgufModelInfo, GgufValue};
+use crate::primitive::{PrimitiveContent, PrimitiveMessage, PrimitiveRequest, Role};
+
+/// 对话提示词模板
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptTemplate {
+    /// ChatML (`<|im_start|>role ... <|im_end|>`)，Qwen 等模型使用
+    ChatMl,
+    /// Llama 3 (`<|start_header_id|>role<|end_header_id|>`)
+    Llama3,
+    /// 纯文本 `Role: content`
+    Plain,
+}
+
+impl PromptTemplate {
+    /// 根据 GGUF 中的 `tokenizer.chat_template` 推断模板
+    pub fn detect(model: &GgufModelInfo) -> Self {
+        match model.metadata.get("tokenizer.chat_template").and_then(GgufValue::as_str) {
+            Some(t) if t.contains("<|start_header_id|>") => PromptTemplate::Llama3,
+            Some(t) if t.contains("<|im_start|>") => PromptTemplate::ChatMl,
+            _ => PromptTemplate::Plain,
+        }
+    }
+
+    /// 渲染请求，末尾留出助手回合
+    pub fn render(&self, primitive: &PrimitiveRequest) -> String {
+        let mut turns: Vec<(Role, String)> = Vec::new();
+        if let Some(system) = primitive.system.as_deref().filter(|s| !s.is_empty()) {
+            turns.push((Role::System, system.to_string()));
+        }
+        turns.extend(primitive.messages.iter().map(|m| (m.role, message_text(m))));
+
+        let mut prompt = String::new();
+        for (role, text) in &turns {
+            let text = match self {
+                PromptTemplate::ChatMl | PromptTemplate::Llama3 => text.replace("<|", SPECIAL_TOKEN_BREAK),
+                PromptTemplate::Plain => text.clone(),
+            };
+            match self {
+                PromptTemplate::ChatMl => {
+                    prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", role, text))
+                }
+                PromptTemplate::Llama3 => prompt.push_str(&format!(
+                    "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                    role, text
+                )),
+                PromptTemplate::Plain => prompt.push_str(&format!("{}: {}\n", title(*role), text)),
+            }
+        }
+        prompt.push_str(match self {
+            PromptTemplate::ChatMl => "<|im_start|>assistant\n",
+            PromptTemplate::Llama3 => "<|start_header_id|>assistant<|end_header_id|>\n\n",
+            PromptTemplate::Plain => "Assistant:",
+        });
+        prompt
+    }
+}
+
+/// 替换内容中特殊标记的起始 `<|`
+const SPECIAL_TOKEN_BREAK: &str = "<\u{2060}|";
+
+fn title(role: Role) -> &'static str {
+    match role {
+        Role::User => "User",
+        Role::Assistant => "Assistant",
+        Role::System => "System",
+    }
+}
+
+/// 提取消息中的文本（图片不支持，工具结果按文本展开）
+pub(crate) fn message_text(message: &PrimitiveMessage) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            PrimitiveContent::Text { text } => Some(text.clone()),
+            PrimitiveContent::ToolResult { content, .. } => Some(content.clone()),
+            PrimitiveContent::ToolCall { name, arguments, .. } => {
+                Some(format!("[tool call] {} {}", name, arguments))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
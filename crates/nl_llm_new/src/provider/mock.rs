@@ -0,0 +1,224 @@
+//! Mock Provider 与故障注入
+//!
+//! 用于在不访问真实 API 的情况下验证 Gateway 的重试与降级路径：
+//! - 固定响应与固定延迟
+//! - 按顺序注入的错误序列（429、503、畸形 JSON）
+//! - 中途断开的流式响应
+//!
+//! 以任意 ID 注册到 Gateway 后，即可出现在 `FallbackRouter` 的降级链中。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::auth::{ApiKeyConfig, ApiKeyProvider, Auth};
+use crate::primitive::PrimitiveRequest;
+use crate::provider::{
+    BoxStream, ChunkDelta, LlmChunk, LlmProvider, LlmResponse, ProviderError, StopReason, Usage,
+};
+
+/// 一次调用的预设行为
+#[derive(Debug, Clone)]
+pub enum MockStep {
+    /// 返回文本响应
+    Respond(String),
+    /// 返回 HTTP 错误（按状态码判定重试/降级信号）
+    Status(u16),
+    /// 返回无法解析的响应体
+    Malformed,
+    /// 流式返回若干文本块后断开
+    PartialStream(Vec<String>),
+}
+
+/// Mock Provider
+pub struct MockProvider {
+    id: String,
+    auth: Auth,
+    latency: Duration,
+    default_response: String,
+    usage: Usage,
+    steps: Mutex<VecDeque<MockStep>>,
+    calls: AtomicUsize,
+}
+
+impl MockProvider {
+    /// 创建新的 Mock Provider（默认返回 "ok"）
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            auth: Auth::ApiKey(ApiKeyConfig::new("mock", ApiKeyProvider::OpenAI)),
+            latency: Duration::ZERO,
+            default_response: "ok".to_string(),
+            usage: Usage::default(),
+            steps: Mutex::new(VecDeque::new()),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// 设置预设步骤用尽后的默认响应
+    pub fn with_response(mut self, text: impl Into<String>) -> Self {
+        self.default_response = text.into();
+        self
+    }
+
+    /// 设置每次响应报告的用量（默认为零）
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// 设置每次调用的固定延迟
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// 追加一个预设步骤
+    pub fn then(self, step: MockStep) -> Self {
+        self.steps.lock().unwrap().push_back(step);
+        self
+    }
+
+    /// 追加一串 HTTP 错误
+    pub fn with_errors(self, statuses: impl IntoIterator<Item = u16>) -> Self {
+        self.steps
+            .lock()
+            .unwrap()
+            .extend(statuses.into_iter().map(MockStep::Status));
+        self
+    }
+
+    /// 累计调用次数（complete 与 stream）
+    pub fn call_count(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// 剩余未消费的预设步骤数
+    pub fn pending_steps(&self) -> usize {
+        self.steps.lock().unwrap().len()
+    }
+
+    async fn next_step(&self) -> MockStep {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        self.steps
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| MockStep::Respond(self.default_response.clone()))
+    }
+
+    fn status_error(&self, status: u16) -> crate::Error {
+        crate::Error::Provider(ProviderError::from_http_status(
+            status,
+            format!("{} injected failure ({})", self.id, status),
+        ))
+    }
+
+    fn malformed_error() -> crate::Error {
+        let err = serde_json::from_str::<serde_json::Value>("{\"candidates\": [").unwrap_err();
+        crate::Error::Json(err)
+    }
+
+    fn response(&self, text: String) -> LlmResponse {
+        LlmResponse {
+            content: text,
+            tool_calls: Vec::new(),
+            usage: self.usage.clone(),
+            stop_reason: StopReason::EndTurn,
+        }
+    }
+
+    fn text_chunk(text: String) -> crate::Result<LlmChunk> {
+        Ok(LlmChunk {
+            delta: ChunkDelta::Text(text),
+            usage: None,
+        })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn auth(&self) -> &Auth {
+        &self.auth
+    }
+
+    fn supported_models(&self) -> &[&str] {
+        &[]
+    }
+
+    fn compile(&self, primitive: &PrimitiveRequest) -> serde_json::Value {
+        serde_json::to_value(primitive).unwrap_or_default()
+    }
+
+    async fn complete(&self, _body: serde_json::Value) -> crate::Result<LlmResponse> {
+        match self.next_step().await {
+            MockStep::Respond(text) => Ok(self.response(text)),
+            MockStep::Status(status) => Err(self.status_error(status)),
+            MockStep::Malformed => Err(Self::malformed_error()),
+            MockStep::PartialStream(chunks) => Ok(self.response(chunks.concat())),
+        }
+    }
+
+    async fn stream(
+        &self,
+        _body: serde_json::Value,
+    ) -> crate::Result<BoxStream<'_, crate::Result<LlmChunk>>> {
+        let items: Vec<crate::Result<LlmChunk>> = match self.next_step().await {
+            MockStep::Respond(text) => vec![Self::text_chunk(text)],
+            MockStep::Status(status) => return Err(self.status_error(status)),
+            MockStep::Malformed => vec![Err(Self::malformed_error())],
+            MockStep::PartialStream(chunks) => chunks
+                .into_iter()
+                .map(Self::text_chunk)
+                .chain(std::iter::once(Err(crate::Error::Http(format!(
+                    "{} stream interrupted",
+                    self.id
+                )))))
+                .collect(),
+        };
+        Ok(Box::pin(futures::stream::iter(items)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_error_sequence_then_default() {
+        let mock = MockProvider::new("mock")
+            .with_errors([429])
+            .then(MockStep::Malformed)
+            .with_response("fine");
+
+        let err = mock.complete(serde_json::json!({})).await.unwrap_err();
+        assert!(matches!(err, crate::Error::Provider(ref e) if e.retryable && e.should_fallback));
+        assert!(matches!(
+            mock.complete(serde_json::json!({})).await,
+            Err(crate::Error::Json(_))
+        ));
+        assert_eq!(mock.complete(serde_json::json!({})).await.unwrap().content, "fine");
+        assert_eq!(mock.call_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_partial_stream_ends_with_error() {
+        let mock = MockProvider::new("mock").then(MockStep::PartialStream(vec!["a".into(), "b".into()]));
+
+        let items: Vec<_> = mock.stream(serde_json::json!({})).await.unwrap().collect().await;
+        assert_eq!(items.len(), 3);
+        assert!(items[0].is_ok());
+        assert!(items[2].is_err());
+    }
+}
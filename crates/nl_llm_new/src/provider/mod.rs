@@ -39,6 +39,9 @@
 //! └── CloudCode Protocol (方言壳)
 //!     ├── GeminiCliProvider
 //!     └── AntigravityProvider
+//!
+//! 本地推理（无网络）
+//! └── LocalProvider (GGUF + llama.cpp)
 //! ```
 //!
 //! # 目录结构说明
@@ -48,7 +51,7 @@
 //! 2. 认证/端点隔离：每个 Provider 有独立的认证配置
 //! 3. 扩展方向：Provider 数量持续增长，协议相对稳定
 //!
-//! 协议复用通过 `Protocol` trait 和模块依赖实现（如 `gemini/protocol.rs`）。
+//! 协议复用通过 `Protocol` trait 和模块依赖实现（如 `gemini/protocol.rs`），流式响应的 SSE 分帧统一由 `sse.rs` 处理。
 
 pub mod traits;
 pub mod gemini;
@@ -59,6 +62,9 @@ pub mod iflow;
 pub mod codex;
 pub mod gemini_cli;
 pub mod antigravity;
+pub mod mock;
+pub mod local;
+pub mod sse;
 
 // 重导出
 pub use traits::*;
@@ -0,0 +1,190 @@
+//! SSE 流解码
+//!
+//! 各 Provider 的流式响应共用的分帧逻辑，按 SSE 规范处理：
+//! - 网络分片可能截断多字节 UTF-8 字符，未完整的字节留到下一个分片再解码
+//! - 行尾可为 `\r\n`、`\n` 或 `\r`；空行结束一个事件，`:` 开头的注释行忽略
+//! - `data:` 后的单个空格可省略；同一事件的多行 `data` 以 `\n` 拼接
+//!
+//! 未结束的事件原样保留在缓冲区，供调用方识别误返回的非 SSE JSON 响应。
+
+use crate::provider::local::backend::take_utf8;
+
+/// SSE 解码器
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    /// 尚未解码的字节（被截断的多字节字符）
+    pending: Vec<u8>,
+    /// 未结束事件的原始文本
+    buffer: String,
+}
+
+impl SseDecoder {
+    /// 创建解码器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一个网络分片，返回其中已结束事件的 `data`
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let text = take_utf8(&mut self.pending);
+        self.buffer.push_str(&text);
+        self.drain(false)
+    }
+
+    /// 流结束：解码剩余字节，返回缺少结尾空行的最后一个事件
+    pub fn finish(&mut self) -> Vec<String> {
+        if !self.pending.is_empty() {
+            let rest = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned();
+            self.buffer.push_str(&rest);
+        }
+        self.drain(true)
+    }
+
+    /// 未结束事件的原始文本
+    pub fn buffered(&self) -> &str {
+        &self.buffer
+    }
+
+    /// 清空缓冲区
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.buffer.clear();
+    }
+
+    fn drain(&mut self, at_end: bool) -> Vec<String> {
+        let bytes = self.buffer.as_bytes();
+        let mut events = Vec::new();
+        let mut data: Vec<&str> = Vec::new();
+        let (mut start, mut consumed, mut i) = (0, 0, 0);
+        while i < bytes.len() {
+            if bytes[i] != b'\n' && bytes[i] != b'\r' {
+                i += 1;
+                continue;
+            }
+            // 末尾的 `\r` 可能与下一个分片的 `\n` 组成一个行尾
+            if bytes[i] == b'\r' && i + 1 == bytes.len() && !at_end {
+                break;
+            }
+            let line = &self.buffer[start..i];
+            let next = if bytes[i] == b'\r' && bytes.get(i + 1) == Some(&b'\n') { i + 2 } else { i + 1 };
+            if line.is_empty() {
+                if !data.is_empty() {
+                    events.push(data.join("\n"));
+                    data.clear();
+                }
+                consumed = next;
+            } else {
+                push_field(&mut data, line);
+            }
+            start = next;
+            i = next;
+        }
+        if at_end {
+            push_field(&mut data, &self.buffer[start..]);
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+            consumed = bytes.len();
+        }
+        self.buffer.drain(..consumed);
+        events
+    }
+}
+
+/// 解析一行字段，`data` 字段的值追加到 `data`
+fn push_field<'a>(data: &mut Vec<&'a str>, line: &'a str) {
+    let (field, value) = match line.find(':') {
+        Some(0) => return,
+        Some(colon) => {
+            let value = &line[colon + 1..];
+            (&line[..colon], value.strip_prefix(' ').unwrap_or(value))
+        }
+        None => (line, ""),
+    };
+    if field == "data" {
+        data.push(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::primitive::arbitrary::Arbitrary;
+
+    /// 在随机位置切分（可能切在多字节字符中间）
+    fn split<'a>(arbitrary: &mut Arbitrary, bytes: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut cuts: Vec<usize> = (0..arbitrary.rng().gen_range(0..8))
+            .map(|_| arbitrary.rng().gen_range(0..=bytes.len()))
+            .collect();
+        cuts.push(bytes.len());
+        cuts.sort_unstable();
+        let mut start = 0;
+        cuts.into_iter()
+            .map(|cut| {
+                let chunk = &bytes[start..cut];
+                start = cut;
+                chunk
+            })
+            .collect()
+    }
+
+    fn decode(chunks: &[&[u8]]) -> Vec<String> {
+        let mut decoder = SseDecoder::new();
+        let mut events: Vec<String> = chunks.iter().flat_map(|chunk| decoder.push(chunk)).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    #[test]
+    fn test_events_survive_any_split_and_line_ending() {
+        for seed in 0..300 {
+            let mut arbitrary = Arbitrary::new(seed);
+            let payloads: Vec<String> = (0..arbitrary.rng().gen_range(0..6))
+                .map(|_| arbitrary.text().replace('\r', ""))
+                .collect();
+
+            // 按规范编码：多行内容拆成多行 `data`，随机选用行尾与 `data:` 后的空格，插入注释与其他字段
+            let eol = ["\n", "\r\n", "\r"][arbitrary.rng().gen_range(0..3)];
+            let mut stream = String::new();
+            for payload in &payloads {
+                if arbitrary.rng().gen_bool(0.3) {
+                    stream.push_str(&format!(": keep-alive{eol}event: message{eol}"));
+                }
+                for line in payload.split('\n') {
+                    let space = line.starts_with(' ') || arbitrary.rng().gen_bool(0.5);
+                    stream.push_str(&format!("data:{}{line}{eol}", if space { " " } else { "" }));
+                }
+                stream.push_str(eol);
+            }
+            // 最后一个事件缺少结尾空行时由 finish 补齐
+            if !stream.is_empty() && arbitrary.rng().gen_bool(0.3) {
+                stream.truncate(stream.len() - eol.len());
+            }
+
+            let chunks = split(&mut arbitrary, stream.as_bytes());
+            assert_eq!(decode(&chunks), payloads, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_bytes_decode_independently_of_chunking() {
+        for seed in 0..500 {
+            let mut arbitrary = Arbitrary::new(seed);
+            let mut bytes = Vec::new();
+            for _ in 0..arbitrary.rng().gen_range(0..40) {
+                match arbitrary.rng().gen_range(0..4) {
+                    0 => bytes.extend_from_slice(arbitrary.text().as_bytes()),
+                    1 => bytes.extend_from_slice(b"data:"),
+                    2 => bytes.push([b'\n', b'\r', b':', b' '][arbitrary.rng().gen_range(0..4)]),
+                    _ => bytes.push(arbitrary.rng().gen()),
+                }
+            }
+            let whole = decode(&[&bytes]);
+            let chunks = split(&mut arbitrary, &bytes);
+            assert_eq!(decode(&chunks), whole, "seed {}", seed);
+        }
+    }
+}
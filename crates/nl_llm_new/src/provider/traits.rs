@@ -5,6 +5,10 @@ use futures::Stream;
 
 use crate::primitive::PrimitiveRequest;
 use crate::auth::Auth;
+use crate::model_registry::ModelInfo;
+use crate::middleware::take_headers;
+use crate::prefix_cache::PrefixCacheHint;
+use crate::transcript::{TranscriptEntry, TranscriptRecorder};
 
 /// LLM Provider 统一 Trait
 #[async_trait]
@@ -21,6 +25,24 @@ pub trait LlmProvider: Send + Sync {
     /// 将原语编译为请求体
     fn compile(&self, primitive: &PrimitiveRequest) -> serde_json::Value;
 
+    /// （可选）根据静态前缀缓存提示改写已编译的请求体
+    fn apply_prefix_cache(
+        &self,
+        body: serde_json::Value,
+        _hint: &PrefixCacheHint,
+    ) -> serde_json::Value {
+        body
+    }
+
+    /// （可选）为静态前缀准备服务端缓存，返回缓存引用
+    async fn prepare_prefix_cache(
+        &self,
+        _primitive: &PrimitiveRequest,
+        _hash: &str,
+    ) -> crate::Result<Option<String>> {
+        Ok(None)
+    }
+
     /// 执行请求
     async fn complete(&self, body: serde_json::Value) -> crate::Result<LlmResponse>;
 
@@ -30,6 +52,20 @@ pub trait LlmProvider: Send + Sync {
         body: serde_json::Value,
     ) -> crate::Result<BoxStream<'_, crate::Result<LlmChunk>>>;
 
+    /// 批量执行（同一模型），按输入顺序返回逐条结果
+    ///
+    /// 默认逐条调用 `complete`；支持批量接口的 Provider 可整体提交。
+    async fn complete_batch(
+        &self,
+        bodies: Vec<serde_json::Value>,
+    ) -> crate::Result<Vec<crate::Result<LlmResponse>>> {
+        let mut results = Vec::with_capacity(bodies.len());
+        for body in bodies {
+            results.push(self.complete(body).await);
+        }
+        Ok(results)
+    }
+
     /// 是否需要刷新认证
     fn needs_refresh(&self) -> bool {
         false
@@ -39,6 +75,19 @@ pub trait LlmProvider: Send + Sync {
     async fn refresh_auth(&mut self) -> crate::Result<()> {
         Ok(())
     }
+
+    /// （可选）通过共享引用刷新认证，供 Gateway 后台刷新任务调用
+    async fn refresh_auth_shared(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// （可选）挂载通信记录器，之后的请求 / 响应写入记录
+    fn attach_transcript(&self, _recorder: std::sync::Arc<TranscriptRecorder>) {}
+
+    /// （可选）从 Provider 模型目录获取上下文窗口等元数据，不支持时返回空列表
+    async fn list_models(&self) -> crate::Result<Vec<ModelInfo>> {
+        Ok(Vec::new())
+    }
 }
 
 // ================================================================================================
@@ -52,6 +101,20 @@ pub trait Protocol: Send + Sync {
     /// 把统一的抽象原语压缩为特定平台协议所需的 JSON 体
     fn compile(&self, primitive: &PrimitiveRequest) -> serde_json::Value;
     
+    /// （可选）复用服务端前缀缓存：按平台方式改写请求体
+    fn apply_prefix_cache(
+        &self,
+        body: serde_json::Value,
+        _hint: &PrefixCacheHint,
+    ) -> serde_json::Value {
+        body
+    }
+
+    /// （可选）流式请求发送前改写请求体（如 OpenAI 兼容协议的 `stream: true`）
+    fn prepare_stream(&self, body: serde_json::Value) -> serde_json::Value {
+        body
+    }
+
     /// 从特定平台的普通 HTTP 返回体中解包还原 LlmResponse
     fn parse_response(&self, raw_text: &str) -> crate::Result<LlmResponse>;
     
@@ -81,6 +144,28 @@ pub trait Endpoint: Send + Sync {
         body
     }
 
+    /// （可选）为静态前缀创建/续期服务端缓存 (如 Gemini cachedContents)，返回缓存引用
+    async fn prepare_prefix_cache(
+        &self,
+        _http: &reqwest::Client,
+        _primitive: &PrimitiveRequest,
+        _hash: &str,
+    ) -> crate::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// （可选）通过平台批量接口提交同一模型的多个请求体
+    ///
+    /// 返回 `None` 表示该端点不支持批量接口；否则按输入顺序返回每条原始响应文本或错误。
+    async fn batch(
+        &self,
+        _http: &reqwest::Client,
+        _model: &str,
+        _bodies: Vec<serde_json::Value>,
+    ) -> crate::Result<Option<Vec<std::result::Result<String, ProviderError>>>> {
+        Ok(None)
+    }
+
     /// （可选）指示该端点是否需要刷新底层 Auth 门票
     fn needs_refresh(&self) -> bool {
         false
@@ -90,6 +175,11 @@ pub trait Endpoint: Send + Sync {
     async fn refresh_auth(&self) -> crate::Result<()> {
         Ok(())
     }
+
+    /// （可选）查询平台模型目录
+    async fn list_models(&self, _http: &reqwest::Client) -> crate::Result<Vec<ModelInfo>> {
+        Ok(Vec::new())
+    }
 }
 
 /// 通用客户端 (Generic Client)
@@ -109,6 +199,28 @@ where
     pub http: reqwest::Client,
     /// 缓存的支持模型列表（避免重复转换，线程安全）
     pub(crate) supported_models_cache: std::sync::OnceLock<&'static [&'static str]>,
+    /// 通信记录器（未挂载时不记录）
+    pub(crate) transcript: std::sync::OnceLock<std::sync::Arc<TranscriptRecorder>>,
+}
+
+impl<E, P> Clone for GenericClient<E, P>
+where
+    E: Endpoint + Clone,
+    P: Protocol + Clone,
+{
+    /// 克隆共享端点内部状态（如认证缓存），模型列表缓存重新建立
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            endpoint: self.endpoint.clone(),
+            protocol: self.protocol.clone(),
+            auth: self.auth.clone(),
+            supported_models: self.supported_models.clone(),
+            http: self.http.clone(),
+            supported_models_cache: std::sync::OnceLock::new(),
+            transcript: self.transcript.clone(),
+        }
+    }
 }
 
 #[async_trait]
@@ -160,6 +272,22 @@ where
         body
     }
 
+    fn apply_prefix_cache(
+        &self,
+        body: serde_json::Value,
+        hint: &PrefixCacheHint,
+    ) -> serde_json::Value {
+        self.protocol.apply_prefix_cache(body, hint)
+    }
+
+    async fn prepare_prefix_cache(
+        &self,
+        primitive: &PrimitiveRequest,
+        hash: &str,
+    ) -> crate::Result<Option<String>> {
+        self.endpoint.prepare_prefix_cache(&self.http, primitive, hash).await
+    }
+
     async fn complete(&self, mut body: serde_json::Value) -> crate::Result<LlmResponse> {
         self.endpoint.pre_flight().await?;
         
@@ -168,13 +296,28 @@ where
             .and_then(|v| v.as_str().map(|s| s.to_string()))
             .unwrap_or_default();
 
+        let headers = take_headers(&mut body);
+
         let url = self.endpoint.url(&model, false)?;
         let mut req = self.http.post(&url).header("Content-Type", "application/json");
         req = self.endpoint.inject_auth(req)?;
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
         
-        let resp = req.json(&body).send().await.map_err(|e| crate::Error::Http(e.to_string()))?;
+        let request = req.json(&body).build().map_err(|e| crate::Error::Http(e.to_string()))?;
+        let transcript = self.transcript.get().map(|_| TranscriptEntry::request(&self.id, &request, &body, false));
+        let resp = match self.http.execute(request).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.record_transcript(transcript.map(|t| t.with_error(&e)));
+                return Err(crate::Error::Http(e.to_string()));
+            }
+        };
         let status = resp.status();
+        let transcript = transcript.map(|t| t.with_response(status.as_u16(), resp.headers()));
         let text = resp.text().await.unwrap_or_default();
+        self.record_transcript(transcript.map(|t| t.with_body(&text)));
 
         if !status.is_success() {
             let status_code = status.as_u16();
@@ -197,29 +340,89 @@ where
             .and_then(|v| v.as_str().map(|s| s.to_string()))
             .unwrap_or_default();
 
+        let headers = take_headers(&mut body);
+        let body = self.protocol.prepare_stream(body);
         let url = self.endpoint.url(&model, true)?;
         let mut req = self.http.post(&url)
             .header("Content-Type", "application/json")
             .header("Accept", "text/event-stream");
             
         req = self.endpoint.inject_auth(req)?;
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
         
-        let resp = req.json(&body).send().await.map_err(|e| crate::Error::Http(e.to_string()))?;
+        let request = req.json(&body).build().map_err(|e| crate::Error::Http(e.to_string()))?;
+        let transcript = self.transcript.get().map(|_| TranscriptEntry::request(&self.id, &request, &body, true));
+        let resp = match self.http.execute(request).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.record_transcript(transcript.map(|t| t.with_error(&e)));
+                return Err(crate::Error::Http(e.to_string()));
+            }
+        };
         let status = resp.status();
+        let transcript = transcript.map(|t| t.with_response(status.as_u16(), resp.headers()));
 
         if !status.is_success() {
             let status_code = status.as_u16();
             let text = resp.text().await.unwrap_or_default();
+            self.record_transcript(transcript.map(|t| t.with_body(&text)));
             return Err(crate::Error::Provider(ProviderError::from_http_status(
                 status_code,
                 format!("{} stream failed ({}): {}", self.id, status_code, text.trim()),
             )));
         }
 
+        // 流式正文不缓存，只记录状态与响应头
+        self.record_transcript(transcript);
+
         // 把 SSE 解包能力下放给负责方块的协议实现
         self.protocol.parse_stream(resp)
     }
 
+    async fn complete_batch(
+        &self,
+        mut bodies: Vec<serde_json::Value>,
+    ) -> crate::Result<Vec<crate::Result<LlmResponse>>> {
+        self.endpoint.pre_flight().await?;
+
+        let model = bodies
+            .iter_mut()
+            .filter_map(|body| body.as_object_mut().and_then(|obj| obj.remove("_gw_model")))
+            .find_map(|v| v.as_str().map(|s| s.to_string()))
+            .unwrap_or_default();
+
+        // 批量接口只有一个 HTTP 请求，逐条附加的请求头在此丢弃；逐条回退时仍然生效
+        let batch_bodies = bodies
+            .iter()
+            .cloned()
+            .map(|mut body| {
+                take_headers(&mut body);
+                body
+            })
+            .collect();
+        match self.endpoint.batch(&self.http, &model, batch_bodies).await? {
+            Some(raw_results) => Ok(raw_results
+                .into_iter()
+                .map(|raw| match raw {
+                    Ok(text) => self.protocol.parse_response(&text),
+                    Err(e) => Err(crate::Error::Provider(e)),
+                })
+                .collect()),
+            None => {
+                let mut results = Vec::with_capacity(bodies.len());
+                for mut body in bodies {
+                    if let Some(obj) = body.as_object_mut() {
+                        obj.insert("_gw_model".to_string(), serde_json::json!(&model));
+                    }
+                    results.push(self.complete(body).await);
+                }
+                Ok(results)
+            }
+        }
+    }
+
     fn needs_refresh(&self) -> bool {
         self.endpoint.needs_refresh()
     }
@@ -227,11 +430,35 @@ where
     async fn refresh_auth(&mut self) -> crate::Result<()> {
         self.endpoint.refresh_auth().await
     }
+
+    async fn refresh_auth_shared(&self) -> crate::Result<()> {
+        self.endpoint.refresh_auth().await
+    }
+
+    fn attach_transcript(&self, recorder: std::sync::Arc<TranscriptRecorder>) {
+        let _ = self.transcript.set(recorder);
+    }
+
+    async fn list_models(&self) -> crate::Result<Vec<ModelInfo>> {
+        self.endpoint.list_models(&self.http).await
+    }
+}
+
+impl<E, P> GenericClient<E, P>
+where
+    E: Endpoint,
+    P: Protocol,
+{
+    fn record_transcript(&self, entry: Option<TranscriptEntry>) {
+        if let (Some(recorder), Some(entry)) = (self.transcript.get(), entry) {
+            recorder.record(entry);
+        }
+    }
 }
 
 
 /// LLM 响应
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct LlmResponse {
     /// 响应内容
     pub content: String,
@@ -268,7 +495,7 @@ pub enum ChunkDelta {
 }
 
 /// 停止原因
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum StopReason {
     /// 正常结束
     EndTurn,
@@ -281,7 +508,7 @@ pub enum StopReason {
 }
 
 /// 工具调用
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ToolCall {
     /// 调用 ID
     pub id: String,
@@ -292,7 +519,7 @@ pub struct ToolCall {
 }
 
 /// 使用统计
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Usage {
     /// 输入 token 数
     pub input_tokens: u64,
@@ -300,6 +527,50 @@ pub struct Usage {
     pub output_tokens: u64,
     /// 思考 token 数（如果有）
     pub thinking_tokens: Option<u64>,
+    /// 命中服务端前缀缓存的输入 token 数（如果有）
+    pub cached_tokens: Option<u64>,
+}
+
+impl Usage {
+    /// 计费 token 总数（输入 + 输出 + 思考）
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.thinking_tokens.unwrap_or(0)
+    }
+
+    /// 解析 Gemini `usageMetadata`
+    pub fn from_gemini(meta: &serde_json::Value) -> Self {
+        Self {
+            input_tokens: meta["promptTokenCount"].as_u64().unwrap_or(0),
+            output_tokens: meta["candidatesTokenCount"].as_u64().unwrap_or(0),
+            thinking_tokens: meta["thoughtsTokenCount"].as_u64(),
+            cached_tokens: meta["cachedContentTokenCount"].as_u64(),
+        }
+    }
+
+    /// 解析 OpenAI 兼容格式（OpenAI / iFlow 等）的 `usage`
+    ///
+    /// `completion_tokens` 已包含推理 token，这里拆出到 `thinking_tokens`，避免总数重复计算。
+    pub fn from_openai(usage: &serde_json::Value) -> Self {
+        let completion = usage["completion_tokens"].as_u64().unwrap_or(0);
+        let reasoning = usage["completion_tokens_details"]["reasoning_tokens"].as_u64();
+        Self {
+            input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+            output_tokens: completion.saturating_sub(reasoning.unwrap_or(0)),
+            thinking_tokens: reasoning,
+            cached_tokens: usage["prompt_tokens_details"]["cached_tokens"].as_u64(),
+        }
+    }
+
+    /// 从任意响应体（含 Cloud Code 的 `response` 包裹）中找出用量块并解析
+    pub fn from_response(body: &serde_json::Value) -> Option<Self> {
+        let body = body.get("response").filter(|r| r.is_object()).unwrap_or(body);
+        if let Some(meta) = body.get("usageMetadata").filter(|m| m.is_object()) {
+            return Some(Self::from_gemini(meta));
+        }
+        body.get("usage")
+            .filter(|u| u.is_object())
+            .map(Self::from_openai)
+    }
 }
 
 /// Provider 执行错误，带有重试信号
@@ -380,6 +651,7 @@ macro_rules! generic_client {
             supported_models: $models,
             http: $http,
             supported_models_cache: std::sync::OnceLock::new(),
+            transcript: std::sync::OnceLock::new(),
         }
     };
 }
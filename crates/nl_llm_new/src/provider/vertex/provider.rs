@@ -4,12 +4,15 @@
 //!
 //! URL 格式: `https://{region}-aiplatform.googleapis.com/v1/projects/{proj}/locations/{region}/publishers/google/models/{model}:{action}`
 //! 认证方式: RS256 JWT → Bearer token (Authorization header)
+//!
+//! access token 连同过期时间缓存在 `TokenCache` 中，剩余有效期不足提前刷新窗口（默认 5 分钟）时才重新签发 JWT 换取；
+//! 并发请求同时发现过期时只有一个去请求 token 端点，其余等待其结果。Provider 的克隆共享同一缓存。
 
 use super::config::VertexConfig;
 use crate::auth::{Auth, SAProvider};
-// Unused import removed
-use tokio::sync::RwLock;
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use crate::provider::{Endpoint, GenericClient};
 use crate::provider::gemini::provider::GeminiProtocol;
 use crate::generic_client;
@@ -23,6 +26,10 @@ const VERTEX_API_VERSION: &str = "v1";
 const VERTEX_TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
 const VERTEX_CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
 const VERTEX_DEFAULT_LOCATION: &str = "us-central1";
+/// 默认提前刷新窗口
+const TOKEN_REFRESH_LEAD_SECS: u64 = 300;
+/// 刷新失败时，剩余有效期超过该值的旧 token 仍继续使用
+const TOKEN_MIN_REMAINING_SECS: u64 = 30;
 
 // ── 数据结构 ────────────────────────────────────────────────────────────────────
 
@@ -62,43 +69,107 @@ struct GoogleTokenResponse {
 // ── 凭证与状态管理 ─────────────────────────────────────────────────────────────
 
 /// SA 认证状态缓存
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 struct AuthState {
     access_token: String,
     expires_at: u64,
 }
 
 impl AuthState {
-    fn is_valid(&self) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        // 提前 5 分钟刷新
-        self.expires_at > now + 300
+    /// 剩余有效期超过 `lead` 秒
+    fn is_fresh(&self, lead: u64) -> bool {
+        !self.access_token.is_empty() && self.expires_at > now_secs() + lead
+    }
+
+    /// 仍可用于请求（即将过期但尚未过期）
+    fn is_usable(&self) -> bool {
+        self.is_fresh(TOKEN_MIN_REMAINING_SECS)
+    }
+}
+
+/// access token 缓存
+#[derive(Debug, Default)]
+struct TokenCache {
+    state: RwLock<AuthState>,
+    /// 串行化 token 端点请求，避免并发刷新
+    refresh: tokio::sync::Mutex<()>,
+}
+
+impl TokenCache {
+    fn current(&self) -> AuthState {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn store(&self, access_token: String, expires_in: u64) {
+        *self.state.write().unwrap_or_else(|e| e.into_inner()) = AuthState {
+            access_token,
+            expires_at: now_secs() + expires_in,
+        };
+    }
+
+    /// 返回剩余有效期超过 `lead` 秒的 token，否则调用 `fetch` 换取新 token
+    ///
+    /// 刷新失败但旧 token 仍未过期时继续使用旧 token。
+    async fn get_or_refresh<F, Fut>(&self, lead: u64, fetch: F) -> crate::Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = crate::Result<(String, u64)>>,
+    {
+        let state = self.current();
+        if state.is_fresh(lead) {
+            return Ok(state.access_token);
+        }
+
+        let _guard = self.refresh.lock().await;
+        // 等锁期间其他调用方可能已经刷新完成
+        let state = self.current();
+        if state.is_fresh(lead) {
+            return Ok(state.access_token);
+        }
+        match fetch().await {
+            Ok((token, expires_in)) => {
+                self.store(token.clone(), expires_in);
+                Ok(token)
+            }
+            Err(e) if state.is_usable() => {
+                tracing::warn!("vertex: token refresh failed, keep using cached token: {}", e);
+                Ok(state.access_token)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 无条件换取新 token
+    async fn refresh<F, Fut>(&self, fetch: F) -> crate::Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = crate::Result<(String, u64)>>,
+    {
+        let _guard = self.refresh.lock().await;
+        let (token, expires_in) = fetch().await?;
+        self.store(token, expires_in);
+        Ok(())
     }
 }
 
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[derive(Clone)]
 pub struct VertexEndpoint {
     config: VertexConfig,
-    auth_state: Arc<RwLock<AuthState>>,
+    /// 克隆间共享
+    tokens: Arc<TokenCache>,
+    /// 提前刷新窗口（秒）
+    refresh_lead: u64,
     http: reqwest::Client,
 }
 
 impl VertexEndpoint {
-    async fn refresh_auth_internal(&self) -> crate::Result<()> {
-        let (token, expires_in) = self.exchange_jwt_for_token(&self.config.credentials_json).await?;
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        
-        let mut state = self.auth_state.write().await;
-        state.access_token = token;
-        state.expires_at = now + expires_in;
-        Ok(())
-    }
-
     /// 解析 SA JSON，签发 JWT，向 Google token 端点换取 access_token
     async fn exchange_jwt_for_token(&self, sa_json: &str) -> crate::Result<(String, u64)> {
         // 1. 解析服务账号 JSON
@@ -198,11 +269,9 @@ impl VertexEndpoint {
 #[async_trait]
 impl Endpoint for VertexEndpoint {
     async fn pre_flight(&self) -> crate::Result<()> {
-        let state = self.auth_state.read().await;
-        if !state.is_valid() {
-            drop(state);
-            self.refresh_auth_internal().await?;
-        }
+        self.tokens
+            .get_or_refresh(self.refresh_lead, || self.exchange_jwt_for_token(&self.config.credentials_json))
+            .await?;
         Ok(())
     }
 
@@ -220,31 +289,24 @@ impl Endpoint for VertexEndpoint {
     }
 
     fn inject_auth(&self, req: reqwest::RequestBuilder) -> crate::Result<reqwest::RequestBuilder> {
-        // 由于预检过程(pre_flight)已确保 valid，这里安全 block 并提取
-        // 为了避免复杂的 async 嵌套（inject_auth 是同步签名），我们可以用 try_read 或者直接这里假定 pre_flight 做了保证。
-        // 由于 get_access_token() 是 async 的，但我们这里是同步的，所以我们读取当前状态即可
-        let token = {
-            let Ok(state) = self.auth_state.try_read() else {
-                return Err(crate::Error::Provider(crate::provider::ProviderError::fail("vertex: auth state locked")));
-            };
-            if !state.is_valid() {
-                return Err(crate::Error::Provider(crate::provider::ProviderError::fail("vertex: auth state invalid during inject_auth")));
-            }
-            state.access_token.clone()
-        };
-
-        Ok(req.header("Authorization", format!("Bearer {}", token)))
+        // pre_flight 已保证缓存中有可用 token
+        let state = self.tokens.current();
+        if !state.is_usable() {
+            return Err(crate::Error::Provider(crate::provider::ProviderError::fail(
+                "vertex: no valid access token during inject_auth",
+            )));
+        }
+        Ok(req.header("Authorization", format!("Bearer {}", state.access_token)))
     }
 
     fn needs_refresh(&self) -> bool {
-        let Ok(state) = self.auth_state.try_read() else {
-            return true;
-        };
-        !state.is_valid()
+        !self.tokens.current().is_fresh(self.refresh_lead)
     }
 
     async fn refresh_auth(&self) -> crate::Result<()> {
-        self.refresh_auth_internal().await
+        self.tokens
+            .refresh(|| self.exchange_jwt_for_token(&self.config.credentials_json))
+            .await
     }
 }
 
@@ -263,7 +325,8 @@ impl VertexProvider {
 
         let endpoint = VertexEndpoint {
             config,
-            auth_state: Arc::new(RwLock::new(AuthState::default())),
+            tokens: Arc::new(TokenCache::default()),
+            refresh_lead: TOKEN_REFRESH_LEAD_SECS,
             http: http.clone(),
         };
 
@@ -284,6 +347,12 @@ impl VertexProvider {
         }
     }
 
+    /// 设置 token 提前刷新窗口
+    pub fn with_refresh_lead(mut self, lead: Duration) -> Self {
+        self.endpoint.refresh_lead = lead.as_secs();
+        self
+    }
+
     /// 以 SA JSON 字符串构建，指定模型和区域
     pub fn from_service_account(
         credentials_json: impl Into<String>,
@@ -417,6 +486,43 @@ mod tests {
         assert_eq!(provider.endpoint.config.location, Some("us-west1".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_token_cache_single_flight_and_lead_window() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = TokenCache::default();
+        let calls = &AtomicUsize::new(0);
+        let fetch = move || async move {
+            let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok((format!("token-{}", n), 3600))
+        };
+        let tokens = futures::future::join_all((0..16).map(|_| cache.get_or_refresh(300, fetch))).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(tokens.iter().all(|t| t.as_deref().unwrap() == "token-1"));
+
+        // 剩余 100 秒：进入提前刷新窗口，刷新失败时仍沿用旧 token
+        cache.store("old".to_string(), 100);
+        let failing = || async { Err(crate::Error::Http("token endpoint down".to_string())) };
+        assert_eq!(cache.get_or_refresh(300, failing).await.unwrap(), "old");
+        assert_eq!(cache.get_or_refresh(300, fetch).await.unwrap(), "token-2");
+        assert_eq!(cache.get_or_refresh(60, fetch).await.unwrap(), "token-2");
+
+        cache.store("expired".to_string(), 0);
+        assert!(cache.get_or_refresh(300, failing).await.is_err());
+    }
+
+    #[test]
+    fn test_clones_share_token_cache() {
+        let sa_json = r#"{"project_id":"p","client_email":"e@x.iam.gserviceaccount.com","private_key":"","private_key_id":""}"#;
+        let provider = VertexProvider::from_service_account(sa_json, "gemini-2.5-flash", None, reqwest::Client::new());
+        let clone = provider.clone();
+        provider.endpoint.tokens.store("shared".to_string(), 3600);
+        assert!(!clone.endpoint.needs_refresh());
+        let req = clone.endpoint.inject_auth(reqwest::Client::new().post("https://example.com")).unwrap();
+        assert_eq!(req.build().unwrap().headers()["authorization"], "Bearer shared");
+    }
+
     #[test]
     fn test_build_url() {
         let config = VertexConfig {
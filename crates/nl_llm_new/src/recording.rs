@@ -0,0 +1,152 @@
+//! 录制/回放层
+//!
+//! 认知流程（Courtroom、MCTS）的集成测试直接调用真实 LLM 既不稳定又昂贵：
+//! - Record: 正常调用 Provider，并以请求哈希为键把响应写入录制文件
+//! - Replay: 只从录制文件返回响应，未录制的请求直接报错，不访问网络
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use sha2::{Digest, Sha256};
+
+use crate::primitive::PrimitiveRequest;
+use crate::provider::LlmResponse;
+
+/// 录制模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordMode {
+    /// 录制：真实调用并保存响应
+    Record,
+    /// 回放：只使用已录制的响应
+    Replay,
+}
+
+/// 计算请求哈希（基于完整原语请求，会话句柄除外）
+pub fn request_hash(primitive: &PrimitiveRequest) -> String {
+    // 会话句柄每次运行可能不同，不参与匹配
+    let primitive = match primitive.metadata.session_id {
+        Some(_) => {
+            let mut without_session = primitive.clone();
+            without_session.metadata.session_id = None;
+            Cow::Owned(without_session)
+        }
+        None => Cow::Borrowed(primitive),
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(primitive.as_ref()).unwrap_or_default());
+    format!("{:x}", hasher.finalize())
+}
+
+/// 响应录制器
+pub struct ResponseRecorder {
+    mode: RecordMode,
+    path: PathBuf,
+    entries: RwLock<BTreeMap<String, LlmResponse>>,
+}
+
+impl ResponseRecorder {
+    /// 以录制模式打开（已存在的录制会被保留并追加）
+    pub fn record(path: impl Into<PathBuf>) -> crate::Result<Self> {
+        Self::open(RecordMode::Record, path.into())
+    }
+
+    /// 以回放模式打开（录制文件必须存在）
+    pub fn replay(path: impl Into<PathBuf>) -> crate::Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            return Err(crate::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("recording not found: {}", path.display()),
+            )));
+        }
+        Self::open(RecordMode::Replay, path)
+    }
+
+    fn open(mode: RecordMode, path: PathBuf) -> crate::Result<Self> {
+        let entries = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            mode,
+            path,
+            entries: RwLock::new(entries),
+        })
+    }
+
+    /// 当前模式
+    pub fn mode(&self) -> RecordMode {
+        self.mode
+    }
+
+    /// 录制文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 已录制的条目数
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 查找已录制的响应
+    pub fn lookup(&self, hash: &str) -> Option<LlmResponse> {
+        self.entries.read().unwrap().get(hash).cloned()
+    }
+
+    /// 保存一条响应并写回录制文件（仅录制模式生效）
+    pub fn store(&self, hash: &str, response: &LlmResponse) -> crate::Result<()> {
+        if self.mode != RecordMode::Record {
+            return Ok(());
+        }
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(hash.to_string(), response.clone());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&*entries)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{StopReason, Usage};
+
+    #[test]
+    fn test_record_then_replay() {
+        let path = std::env::temp_dir().join(format!("nl_recording_{}.json", uuid::Uuid::new_v4()));
+        let request = PrimitiveRequest::single_user_message("judge this");
+        let hash = request_hash(&request);
+
+        let recorder = ResponseRecorder::record(&path).unwrap();
+        recorder
+            .store(
+                &hash,
+                &LlmResponse {
+                    content: "guilty".to_string(),
+                    tool_calls: Vec::new(),
+                    usage: Usage::default(),
+                    stop_reason: StopReason::EndTurn,
+                },
+            )
+            .unwrap();
+
+        let replay = ResponseRecorder::replay(&path).unwrap();
+        assert_eq!(replay.lookup(&hash).unwrap().content, "guilty");
+        assert!(replay
+            .lookup(&request_hash(&PrimitiveRequest::single_user_message("other")))
+            .is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+//! 响应缓存
+//!
+//! Critic 评估、摘要等请求经常完全相同。Gateway 以 (Provider, 模型, 提示词哈希, 生成参数)
+//! 为键缓存完整响应：
+//! - 条目超过 TTL 即失效
+//! - 超过容量时淘汰最久未使用的条目 (LRU)
+//! - 支持按键、按 Provider/模型或全部失效
+//! - 请求元数据设置 `no_cache` 时跳过缓存
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use crate::primitive::PrimitiveRequest;
+use crate::provider::{LlmResponse, Usage};
+
+/// 响应缓存配置
+#[derive(Debug, Clone)]
+pub struct ResponseCacheConfig {
+    /// 条目有效期
+    pub ttl: Duration,
+    /// 最大条目数
+    pub max_entries: usize,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(3600),
+            max_entries: 1024,
+        }
+    }
+}
+
+/// 响应缓存统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResponseCacheStats {
+    /// 命中次数
+    pub hits: u64,
+    /// 未命中次数
+    pub misses: u64,
+    /// LRU 淘汰次数
+    pub evictions: u64,
+    /// 当前条目数
+    pub entries: usize,
+}
+
+/// 计算缓存键：Provider、模型、提示词（系统提示词 + 消息 + 工具）与生成参数
+pub fn cache_key(provider_id: &str, primitive: &PrimitiveRequest) -> String {
+    let mut hasher = Sha256::new();
+    for part in [provider_id, primitive.model.as_str()] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    hasher.update(primitive.system.as_deref().unwrap_or_default().as_bytes());
+    hasher.update([0u8]);
+    hasher.update(serde_json::to_vec(&primitive.messages).unwrap_or_default());
+    hasher.update(serde_json::to_vec(&primitive.tools).unwrap_or_default());
+    hasher.update(serde_json::to_vec(&primitive.parameters).unwrap_or_default());
+    format!("{:x}", hasher.finalize())
+}
+
+struct CacheEntry {
+    provider_id: String,
+    model: String,
+    response: LlmResponse,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    /// 最近使用序号 -> 键，最小者最久未使用
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    stats: ResponseCacheStats,
+}
+
+impl CacheState {
+    fn remove(&mut self, key: &str) -> Option<CacheEntry> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_used);
+        Some(entry)
+    }
+
+    fn remove_where(&mut self, predicate: impl Fn(&CacheEntry) -> bool) -> usize {
+        let keys: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| predicate(entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            self.remove(key);
+        }
+        keys.len()
+    }
+}
+
+/// 响应缓存
+pub struct ResponseCache {
+    config: ResponseCacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl ResponseCache {
+    /// 创建响应缓存
+    pub fn new(config: ResponseCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// 查询缓存，命中时返回的响应用量为零（未产生新的 token 消耗）
+    pub fn get(&self, key: &str) -> Option<LlmResponse> {
+        let mut state = self.state.lock().unwrap();
+        let expired = match state.entries.get(key) {
+            Some(entry) => entry.inserted_at.elapsed() >= self.config.ttl,
+            None => {
+                state.stats.misses += 1;
+                return None;
+            }
+        };
+        if expired {
+            state.remove(key);
+            state.stats.misses += 1;
+            return None;
+        }
+
+        state.tick += 1;
+        let tick = state.tick;
+        let entry = state.entries.get_mut(key)?;
+        let previous = std::mem::replace(&mut entry.last_used, tick);
+        let mut response = entry.response.clone();
+        state.recency.remove(&previous);
+        state.recency.insert(tick, key.to_string());
+        state.stats.hits += 1;
+
+        response.usage = Usage::default();
+        Some(response)
+    }
+
+    /// 写入缓存，超出容量时淘汰最久未使用的条目
+    pub fn insert(&self, key: String, provider_id: &str, model: &str, response: LlmResponse) {
+        if self.config.max_entries == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.remove(&key);
+        while state.entries.len() >= self.config.max_entries {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+            state.stats.evictions += 1;
+        }
+
+        state.tick += 1;
+        let tick = state.tick;
+        state.recency.insert(tick, key.clone());
+        state.entries.insert(
+            key,
+            CacheEntry {
+                provider_id: provider_id.to_string(),
+                model: model.to_string(),
+                response,
+                inserted_at: Instant::now(),
+                last_used: tick,
+            },
+        );
+    }
+
+    /// 使单个请求的缓存失效
+    pub fn invalidate(&self, provider_id: &str, primitive: &PrimitiveRequest) -> bool {
+        self.invalidate_key(&cache_key(provider_id, primitive))
+    }
+
+    /// 按键失效
+    pub fn invalidate_key(&self, key: &str) -> bool {
+        self.state.lock().unwrap().remove(key).is_some()
+    }
+
+    /// 使某 Provider（可选限定模型）的全部缓存失效，返回移除条数
+    pub fn invalidate_provider(&self, provider_id: &str, model: Option<&str>) -> usize {
+        self.state.lock().unwrap().remove_where(|entry| {
+            entry.provider_id == provider_id && model.is_none_or(|m| entry.model == m)
+        })
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.recency.clear();
+    }
+
+    /// 移除所有过期条目，返回移除条数
+    pub fn purge_expired(&self) -> usize {
+        let ttl = self.config.ttl;
+        self.state
+            .lock()
+            .unwrap()
+            .remove_where(|entry| entry.inserted_at.elapsed() >= ttl)
+    }
+
+    /// 获取统计信息
+    pub fn stats(&self) -> ResponseCacheStats {
+        let state = self.state.lock().unwrap();
+        ResponseCacheStats {
+            entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(ResponseCacheConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::StopReason;
+
+    fn response(text: &str) -> LlmResponse {
+        LlmResponse {
+            content: text.to_string(),
+            tool_calls: Vec::new(),
+            usage: Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+                ..Default::default()
+            },
+            stop_reason: StopReason::EndTurn,
+        }
+    }
+
+    #[test]
+    fn test_lru_ttl_and_invalidation() {
+        let cache = ResponseCache::new(ResponseCacheConfig {
+            ttl: Duration::from_secs(60),
+            max_entries: 2,
+        });
+        let a = PrimitiveRequest::single_user_message("a");
+        let b = PrimitiveRequest::single_user_message("b");
+        let c = PrimitiveRequest::single_user_message("c");
+        let (ka, kb, kc) = (cache_key("p", &a), cache_key("p", &b), cache_key("p", &c));
+        assert_ne!(ka, cache_key("q", &a));
+        assert_ne!(ka, cache_key("p", &a.clone().with_temperature(0.9)));
+
+        cache.insert(ka.clone(), "p", &a.model, response("A"));
+        cache.insert(kb.clone(), "p", &b.model, response("B"));
+        let hit = cache.get(&ka).unwrap();
+        assert_eq!(hit.content, "A");
+        assert_eq!(hit.usage.total_tokens(), 0);
+
+        // a 刚被使用，容量满时淘汰 b
+        cache.insert(kc.clone(), "p", &c.model, response("C"));
+        assert!(cache.get(&kb).is_none());
+        assert!(cache.get(&kc).is_some());
+        assert_eq!(cache.stats().evictions, 1);
+
+        assert!(cache.invalidate("p", &a));
+        assert!(cache.get(&ka).is_none());
+        assert_eq!(cache.invalidate_provider("p", None), 1);
+        assert_eq!(cache.stats().entries, 0);
+
+        let expiring = ResponseCache::new(ResponseCacheConfig {
+            ttl: Duration::ZERO,
+            max_entries: 8,
+        });
+        expiring.insert(ka.clone(), "p", &a.model, response("A"));
+        assert!(expiring.get(&ka).is_none());
+    }
+}
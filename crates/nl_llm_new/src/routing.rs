@@ -0,0 +1,301 @@
+//! 模型路由规则
+//!
+//! 声明式规则按任务类型、提示词长度、优先级与时段把请求路由到指定 Provider/模型，
+//! 例如摘要走廉价模型、裁决走强模型，调整策略只需修改配置文件。
+//! 规则按顺序匹配，首条命中生效；全部未命中时使用 `default`（若有）。
+
+use std::path::Path;
+
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+
+use crate::primitive::{PrimitiveContent, PrimitiveRequest};
+use crate::scheduler::Priority;
+
+/// 路由目标
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteTarget {
+    /// Provider ID
+    pub provider: String,
+    /// 覆盖的模型（为空时保留请求中的模型）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// 时段（本地时间的小时，`start..end`，`start > end` 时跨越午夜）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HourRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl HourRange {
+    /// 是否包含指定小时
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&hour)
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+/// 单条路由规则（未设置的条件视为匹配任意值）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// 规则名称
+    pub name: String,
+    /// 匹配的任务类型
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub task_types: Vec<String>,
+    /// 提示词最少字符数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_prompt_chars: Option<usize>,
+    /// 提示词最多字符数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_chars: Option<usize>,
+    /// 匹配的优先级
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priorities: Vec<Priority>,
+    /// 生效时段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<HourRange>,
+    /// 路由目标
+    #[serde(flatten)]
+    pub target: RouteTarget,
+}
+
+impl RoutingRule {
+    /// 创建规则
+    pub fn new(name: impl Into<String>, provider: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            task_types: Vec::new(),
+            min_prompt_chars: None,
+            max_prompt_chars: None,
+            priorities: Vec::new(),
+            hours: None,
+            target: RouteTarget {
+                provider: provider.into(),
+                model: None,
+            },
+        }
+    }
+
+    /// 设置目标模型
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.target.model = Some(model.into());
+        self
+    }
+
+    /// 添加匹配的任务类型
+    pub fn with_task_type(mut self, task_type: impl Into<String>) -> Self {
+        self.task_types.push(task_type.into());
+        self
+    }
+
+    /// 设置提示词长度范围
+    pub fn with_prompt_chars(mut self, min: Option<usize>, max: Option<usize>) -> Self {
+        self.min_prompt_chars = min;
+        self.max_prompt_chars = max;
+        self
+    }
+
+    /// 添加匹配的优先级
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priorities.push(priority);
+        self
+    }
+
+    /// 设置生效时段
+    pub fn with_hours(mut self, start: u32, end: u32) -> Self {
+        self.hours = Some(HourRange { start, end });
+        self
+    }
+
+    /// 检查规则，未命中时返回原因
+    pub fn check(&self, ctx: &RouteContext) -> Result<(), String> {
+        if !self.task_types.is_empty() {
+            match &ctx.task_type {
+                Some(t) if self.task_types.iter().any(|x| x == t) => {}
+                Some(t) => return Err(format!("task type '{}' not in {:?}", t, self.task_types)),
+                None => return Err(format!("no task type, rule requires {:?}", self.task_types)),
+            }
+        }
+        if let Some(min) = self.min_prompt_chars {
+            if ctx.prompt_chars < min {
+                return Err(format!("prompt {} chars < min {}", ctx.prompt_chars, min));
+            }
+        }
+        if let Some(max) = self.max_prompt_chars {
+            if ctx.prompt_chars > max {
+                return Err(format!("prompt {} chars > max {}", ctx.prompt_chars, max));
+            }
+        }
+        if !self.priorities.is_empty() && !self.priorities.contains(&ctx.priority) {
+            return Err(format!("priority {:?} not in {:?}", ctx.priority, self.priorities));
+        }
+        if let Some(hours) = self.hours {
+            if !hours.contains(ctx.hour) {
+                return Err(format!("hour {} outside {}..{}", ctx.hour, hours.start, hours.end));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 路由判定所需的请求特征
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteContext {
+    /// 任务类型
+    pub task_type: Option<String>,
+    /// 提示词字符数（系统提示 + 全部文本内容）
+    pub prompt_chars: usize,
+    /// 优先级
+    pub priority: Priority,
+    /// 当前小时（本地时间）
+    pub hour: u32,
+}
+
+impl RouteContext {
+    /// 从请求提取特征，时段取当前本地时间
+    pub fn from_request(primitive: &PrimitiveRequest, priority: Priority) -> Self {
+        let mut prompt_chars = primitive.system.as_ref().map_or(0, |s| s.chars().count());
+        for message in &primitive.messages {
+            for content in &message.content {
+                prompt_chars += match content {
+                    PrimitiveContent::Text { text } | PrimitiveContent::Thinking { text } => {
+                        text.chars().count()
+                    }
+                    PrimitiveContent::ToolResult { content, .. } => content.chars().count(),
+                    _ => 0,
+                };
+            }
+        }
+        Self {
+            task_type: primitive.metadata.task_type.clone(),
+            prompt_chars,
+            priority,
+            hour: chrono::Local::now().hour(),
+        }
+    }
+}
+
+/// 单条规则的判定结果
+#[derive(Debug, Clone)]
+pub struct RuleEvaluation {
+    /// 规则名称
+    pub rule: String,
+    /// 未命中原因（命中时为 `None`）
+    pub mismatch: Option<String>,
+}
+
+/// 路由规则集
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingRules {
+    /// 按顺序匹配的规则
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+    /// 全部未命中时的目标
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<RouteTarget>,
+}
+
+impl RoutingRules {
+    /// 创建空规则集
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从 JSON 文件加载
+    pub fn load(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// 追加规则
+    pub fn with_rule(mut self, rule: RoutingRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// 设置默认目标
+    pub fn with_default(mut self, provider: impl Into<String>, model: Option<String>) -> Self {
+        self.default = Some(RouteTarget {
+            provider: provider.into(),
+            model,
+        });
+        self
+    }
+
+    /// 解析路由目标（首条命中的规则，否则默认目标）
+    pub fn resolve(&self, ctx: &RouteContext) -> Option<&RouteTarget> {
+        self.rules
+            .iter()
+            .find(|rule| rule.check(ctx).is_ok())
+            .map(|rule| &rule.target)
+            .or(self.default.as_ref())
+    }
+
+    /// 逐条判定规则（用于调试），遇到首条命中即停止
+    pub fn explain(&self, ctx: &RouteContext) -> Vec<RuleEvaluation> {
+        let mut evaluations = Vec::new();
+        for rule in &self.rules {
+            let mismatch = rule.check(ctx).err();
+            let matched = mismatch.is_none();
+            evaluations.push(RuleEvaluation {
+                rule: rule.name.clone(),
+                mismatch,
+            });
+            if matched {
+                break;
+            }
+        }
+        evaluations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(task_type: Option<&str>, prompt_chars: usize, priority: Priority, hour: u32) -> RouteContext {
+        RouteContext {
+            task_type: task_type.map(str::to_string),
+            prompt_chars,
+            priority,
+            hour,
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules: RoutingRules = serde_json::from_value(serde_json::json!({
+            "rules": [
+                { "name": "summaries", "task_types": ["summarize"], "provider": "gemini", "model": "gemini-flash" },
+                { "name": "verdicts", "task_types": ["verdict"], "priorities": ["high"], "provider": "claude", "model": "claude-opus" },
+                { "name": "night-long", "min_prompt_chars": 1000, "hours": { "start": 22, "end": 6 }, "provider": "batch" }
+            ],
+            "default": { "provider": "openai" }
+        }))
+        .unwrap();
+
+        let target = rules.resolve(&ctx(Some("summarize"), 10, Priority::Low, 12)).unwrap();
+        assert_eq!(target.model.as_deref(), Some("gemini-flash"));
+
+        let target = rules.resolve(&ctx(Some("verdict"), 10, Priority::High, 12)).unwrap();
+        assert_eq!(target.provider, "claude");
+        // 优先级不符则落到默认
+        let target = rules.resolve(&ctx(Some("verdict"), 10, Priority::Normal, 12)).unwrap();
+        assert_eq!(target.provider, "openai");
+
+        // 跨午夜时段
+        assert_eq!(rules.resolve(&ctx(None, 5000, Priority::Normal, 23)).unwrap().provider, "batch");
+        assert_eq!(rules.resolve(&ctx(None, 5000, Priority::Normal, 12)).unwrap().provider, "openai");
+
+        let explained = rules.explain(&ctx(Some("verdict"), 10, Priority::High, 12));
+        assert_eq!(explained.len(), 2);
+        assert!(explained[0].mismatch.as_deref().unwrap().contains("task type"));
+        assert!(explained[1].mismatch.is_none());
+    }
+}
@@ -0,0 +1,220 @@
+//! 限流感知调度器
+//!
+//! 负责：
+//! - 定时补充令牌桶
+//! - 预测未来窗口内的可用预算
+//! - 将低优先级任务（MCTS 推演、摘要等）延后到补充窗口执行，而非立即失败
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+
+use crate::gateway::GatewayError;
+use crate::token_bucket::TokenBucket;
+
+/// 任务优先级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// 高优先级：用户交互请求，始终等待令牌
+    High,
+    /// 普通优先级
+    #[default]
+    Normal,
+    /// 低优先级：后台推演/摘要，只在预算充裕时执行
+    Low,
+}
+
+/// 调度器配置
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// 定时补充间隔
+    pub tick_interval: Duration,
+    /// 预算预测窗口
+    pub forecast_horizon: Duration,
+    /// 低优先级任务需为高优先级保留的容量比例 (0.0 - 1.0)
+    pub low_priority_reserve: f64,
+    /// 低优先级任务最长延后时间，超时返回限流错误
+    pub max_defer: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            tick_interval: Duration::from_millis(100),
+            forecast_horizon: Duration::from_secs(60),
+            low_priority_reserve: 0.3,
+            max_defer: Duration::from_secs(60),
+        }
+    }
+}
+
+/// 调度决策
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleDecision {
+    /// 立即执行
+    Now,
+    /// 延后到指定时长之后再尝试
+    Defer(Duration),
+}
+
+/// 预算预测
+#[derive(Debug, Clone, Copy)]
+pub struct BudgetForecast {
+    /// 当前可用令牌
+    pub available: u64,
+    /// 每秒补充速率
+    pub refill_per_sec: f64,
+    /// 预测窗口
+    pub horizon: Duration,
+    /// 窗口内预计可用令牌总量
+    pub projected: u64,
+}
+
+/// 限流感知调度器
+pub struct RateLimitScheduler {
+    bucket: Arc<TokenBucket>,
+    config: SchedulerConfig,
+    /// 累计延后次数
+    deferred: AtomicU64,
+}
+
+impl RateLimitScheduler {
+    /// 创建新的调度器
+    pub fn new(bucket: Arc<TokenBucket>, config: SchedulerConfig) -> Self {
+        Self {
+            bucket,
+            config,
+            deferred: AtomicU64::new(0),
+        }
+    }
+
+    /// 启动定时补充任务
+    pub fn start_refill_timer(&self) -> JoinHandle<()> {
+        let bucket = self.bucket.clone();
+        let interval = self.config.tick_interval;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                bucket.refill();
+            }
+        })
+    }
+
+    /// 预测窗口内的可用预算
+    pub fn forecast(&self) -> BudgetForecast {
+        let horizon = self.config.forecast_horizon;
+        BudgetForecast {
+            available: self.bucket.available(),
+            refill_per_sec: self.bucket.refill_per_sec(),
+            horizon,
+            projected: self.bucket.forecast(horizon),
+        }
+    }
+
+    /// 根据优先级与消耗决定立即执行还是延后
+    pub fn decide(&self, priority: Priority, cost: u64) -> ScheduleDecision {
+        let required = match priority {
+            Priority::High | Priority::Normal => cost,
+            Priority::Low => cost + self.reserve(),
+        };
+        let wait = self.bucket.time_until_available(required);
+        if wait.is_zero() {
+            ScheduleDecision::Now
+        } else {
+            ScheduleDecision::Defer(wait)
+        }
+    }
+
+    /// 按优先级获取令牌
+    ///
+    /// 高/普通优先级一直等待；低优先级在超过 `max_defer` 后返回 `RateLimited`。
+    pub async fn acquire(&self, priority: Priority, cost: u64) -> Result<(), GatewayError> {
+        let started = Instant::now();
+        let mut counted = false;
+
+        loop {
+            match self.decide(priority, cost) {
+                ScheduleDecision::Now => {
+                    if self.bucket.try_acquire_n(cost) {
+                        return Ok(());
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                ScheduleDecision::Defer(wait) => {
+                    if priority == Priority::Low {
+                        if started.elapsed() + wait > self.config.max_defer {
+                            return Err(GatewayError::RateLimited);
+                        }
+                        if !counted {
+                            self.deferred.fetch_add(1, Ordering::Relaxed);
+                            counted = true;
+                        }
+                    }
+                    tokio::time::sleep(wait.max(Duration::from_millis(10))).await;
+                }
+            }
+        }
+    }
+
+    /// 累计被延后的低优先级任务数
+    pub fn deferred_count(&self) -> u64 {
+        self.deferred.load(Ordering::Relaxed)
+    }
+
+    /// 低优先级任务需保留的令牌数
+    fn reserve(&self) -> u64 {
+        let reserve = self.config.low_priority_reserve.clamp(0.0, 1.0);
+        (self.bucket.capacity() as f64 * reserve).ceil() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler(capacity: u32) -> RateLimitScheduler {
+        let bucket = Arc::new(TokenBucket::new(capacity, Duration::from_secs(1)));
+        RateLimitScheduler::new(bucket, SchedulerConfig::default())
+    }
+
+    #[test]
+    fn test_low_priority_respects_reserve() {
+        let scheduler = scheduler(10);
+        assert!(scheduler.bucket.try_acquire_n(7));
+
+        // 剩余 3 个令牌：普通请求可以立即执行，低优先级需为高优先级保留 30%
+        assert_eq!(scheduler.decide(Priority::Normal, 1), ScheduleDecision::Now);
+        assert!(matches!(
+            scheduler.decide(Priority::Low, 1),
+            ScheduleDecision::Defer(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_low_priority_fails_after_max_defer() {
+        let bucket = Arc::new(TokenBucket::new(10, Duration::from_secs(10)));
+        assert!(bucket.try_acquire_n(10));
+        let scheduler = RateLimitScheduler::new(
+            bucket,
+            SchedulerConfig {
+                max_defer: Duration::from_millis(50),
+                ..Default::default()
+            },
+        );
+
+        let result = scheduler.acquire(Priority::Low, 1).await;
+        assert!(matches!(result, Err(GatewayError::RateLimited)));
+    }
+
+    #[test]
+    fn test_forecast_includes_refill() {
+        let scheduler = scheduler(10);
+        let forecast = scheduler.forecast();
+        assert_eq!(forecast.available, 10);
+        assert!(forecast.projected >= 600);
+    }
+}
@@ -0,0 +1,199 @@
+//! 会话句柄
+//!
+//! CloudCode（Antigravity / Gemini CLI）按 `sessionId` 在服务端续接多轮对话的上下文，
+//! 同一对话的每一轮都必须带相同的句柄：
+//! - 调用方通过 `PrimitiveMetadata::with_session_id` 显式指定，或经 `Gateway::complete_in_conversation`
+//!   由 `SessionStore` 为每个对话分配并持久化句柄
+//! - 都没有时才从请求内容（系统提示词 + 首条用户消息）派生，同一对话的后续轮次派生结果不变
+//!
+//! CloudCode 要求 `sessionId` 为负整数字符串，任意句柄经 `SessionHandle::cloudcode_id` 映射到该格式。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::primitive::{PrimitiveContent, PrimitiveRequest, Role};
+
+/// 对话会话句柄
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionHandle(String);
+
+impl SessionHandle {
+    /// 随机生成新句柄（CloudCode 格式）
+    pub fn generate() -> Self {
+        Self(format!("-{}", rand::random::<u64>() & i64::MAX as u64))
+    }
+
+    /// 从请求内容派生句柄（系统提示词 + 首条用户消息，多轮对话中保持不变）
+    pub fn from_content(primitive: &PrimitiveRequest) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(primitive.system.as_deref().unwrap_or_default().as_bytes());
+        hasher.update([0u8]);
+        if let Some(first) = primitive.messages.iter().find(|m| m.role == Role::User) {
+            for content in &first.content {
+                if let PrimitiveContent::Text { text } = content {
+                    hasher.update(text.as_bytes());
+                }
+            }
+        }
+        Self(negative_id(&hasher.finalize()))
+    }
+
+    /// 请求的会话句柄：显式指定优先，否则从内容派生
+    pub fn for_request(primitive: &PrimitiveRequest) -> Self {
+        match &primitive.metadata.session_id {
+            Some(id) => Self(id.clone()),
+            None => Self::from_content(primitive),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// CloudCode 要求的负整数形式（已是该形式时原样返回）
+    pub fn cloudcode_id(&self) -> String {
+        match self.0.strip_prefix('-') {
+            Some(digits) if !digits.is_empty() && digits.parse::<i64>().is_ok() => self.0.clone(),
+            _ => negative_id(&Sha256::digest(self.0.as_bytes())),
+        }
+    }
+}
+
+impl From<String> for SessionHandle {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl std::fmt::Display for SessionHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// 取摘要前 8 字节作为非负 i64，格式化为 `-<digits>`
+fn negative_id(digest: &[u8]) -> String {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    format!("-{}", u64::from_be_bytes(bytes) & i64::MAX as u64)
+}
+
+/// 对话 ID -> 会话句柄的登记表
+///
+/// 指定文件路径时，新分配的句柄写回文件，进程重启后同一对话继续使用原句柄。
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    path: Option<PathBuf>,
+    sessions: RwLock<HashMap<String, SessionHandle>>,
+}
+
+impl SessionStore {
+    /// 仅保存在内存中
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// 从文件加载（文件不存在时为空）
+    pub fn open(path: impl Into<PathBuf>) -> crate::Result<Self> {
+        let path = path.into();
+        let sessions = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            path: Some(path),
+            sessions: RwLock::new(sessions),
+        })
+    }
+
+    /// 持久化文件路径
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 查询对话的句柄
+    pub fn get(&self, conversation: &str) -> Option<SessionHandle> {
+        self.sessions.read().unwrap().get(conversation).cloned()
+    }
+
+    /// 获取对话的句柄，首次出现时分配新句柄并持久化
+    ///
+    /// 写文件失败只记录警告，句柄在本进程内照常使用。
+    pub fn get_or_create(&self, conversation: &str) -> SessionHandle {
+        if let Some(handle) = self.get(conversation) {
+            return handle;
+        }
+        let mut sessions = self.sessions.write().unwrap();
+        if let Some(handle) = sessions.get(conversation) {
+            return handle.clone();
+        }
+        let handle = SessionHandle::generate();
+        sessions.insert(conversation.to_string(), handle.clone());
+        self.save(&sessions);
+        handle
+    }
+
+    /// 结束对话，删除其句柄（下次同 ID 的对话将分配新句柄）
+    pub fn forget(&self, conversation: &str) -> Option<SessionHandle> {
+        let mut sessions = self.sessions.write().unwrap();
+        let removed = sessions.remove(conversation);
+        if removed.is_some() {
+            self.save(&sessions);
+        }
+        removed
+    }
+
+    fn save(&self, sessions: &HashMap<String, SessionHandle>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(crate::Error::Io)
+            .and_then(|_| Ok(std::fs::write(path, serde_json::to_string_pretty(sessions)?)?));
+        if let Err(e) = result {
+            tracing::warn!("failed to persist sessions to {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::PrimitiveMessage;
+
+    #[test]
+    fn test_session_handle_stable_across_turns() {
+        let first_turn = PrimitiveRequest::with_system_and_user("be terse", "hello");
+        let second_turn = first_turn
+            .clone()
+            .with_message(PrimitiveMessage::assistant("hi"))
+            .with_message(PrimitiveMessage::user("what next?"));
+        let derived = SessionHandle::for_request(&first_turn);
+        assert_eq!(derived, SessionHandle::for_request(&second_turn));
+        assert_eq!(derived.cloudcode_id(), derived.as_str());
+
+        let mut explicit = second_turn.clone();
+        explicit.metadata = explicit.metadata.with_session_id("conv-42");
+        let handle = SessionHandle::for_request(&explicit);
+        assert_eq!(handle.as_str(), "conv-42");
+        let id = handle.cloudcode_id();
+        assert!(id.starts_with('-') && id[1..].parse::<i64>().is_ok());
+
+        let path = std::env::temp_dir().join(format!("nl_sessions_{}.json", uuid::Uuid::new_v4()));
+        let store = SessionStore::open(&path).unwrap();
+        let handle = store.get_or_create("chat-1");
+        assert_eq!(store.get_or_create("chat-1"), handle);
+        assert_eq!(SessionStore::open(&path).unwrap().get("chat-1"), Some(handle.clone()));
+        assert_eq!(store.forget("chat-1"), Some(handle));
+        assert!(SessionStore::open(&path).unwrap().get("chat-1").is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -79,8 +79,8 @@ impl TokenBucket {
         }
     }
 
-    /// 补充令牌
-    fn refill(&self) {
+    /// 补充令牌（获取令牌时会自动调用，也可由定时器周期调用）
+    pub fn refill(&self) {
         let mut last_refill = self.last_refill.lock().unwrap();
         let now = Instant::now();
         let elapsed = now.duration_since(*last_refill);
@@ -101,6 +101,35 @@ impl TokenBucket {
         self.refill();
         self.tokens.load(Ordering::Relaxed)
     }
+
+    /// 桶容量
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// 每秒补充的令牌数
+    pub fn refill_per_sec(&self) -> f64 {
+        self.refill_rate_nanos * 1_000_000_000.0
+    }
+
+    /// 预测未来一段时间内可用的令牌总量（当前余量 + 期间补充量）
+    pub fn forecast(&self, horizon: Duration) -> u64 {
+        let refilled = (horizon.as_nanos() as f64 * self.refill_rate_nanos) as u64;
+        self.available() + refilled
+    }
+
+    /// 距离桶内累积到 `n` 个令牌还需等待的时间
+    pub fn time_until_available(&self, n: u64) -> Duration {
+        let available = self.available();
+        if available >= n {
+            return Duration::ZERO;
+        }
+        if self.refill_rate_nanos <= 0.0 {
+            return Duration::MAX;
+        }
+        let missing = (n.min(self.capacity) - available.min(n)) as f64;
+        Duration::from_nanos((missing / self.refill_rate_nanos).ceil() as u64)
+    }
 }
 
 #[cfg(test)]
@@ -136,4 +165,17 @@ mod tests {
         // 应该有新令牌了
         assert!(bucket.try_acquire());
     }
+
+    #[test]
+    fn test_token_bucket_forecast() {
+        let bucket = TokenBucket::new(10, Duration::from_secs(1));
+        assert!(bucket.try_acquire_n(10));
+
+        // 空桶一分钟内可补充 600 个令牌
+        let forecast = bucket.forecast(Duration::from_secs(60));
+        assert!((599..=601).contains(&forecast));
+
+        let wait = bucket.time_until_available(5);
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+    }
 }
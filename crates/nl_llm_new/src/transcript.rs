@@ -0,0 +1,325 @@
+//! Provider 通信记录
+//!
+//! 调试行为异常的 Provider 时需要看到真实的请求与响应。开启后，`GenericClient` 发出的每个 HTTP 请求
+//! 连同响应按 Provider 追加到 `<dir>/<provider>.jsonl`（每行一条 `TranscriptEntry`）：
+//! - 请求头 / 响应头中的凭证（Authorization、x-api-key、Cookie 等）、URL 查询参数中的密钥，
+//!   以及正文里的令牌经 `Redactor` 脱敏后才落盘
+//! - 文件超过 `max_file_bytes` 时轮转为 `<provider>.1.jsonl`、`<provider>.2.jsonl`……，最多保留 `max_files` 个旧文件
+//! - 流式请求只记录状态码与响应头（失败时记录错误正文），不缓存事件流
+//!
+//! 默认关闭：设置 `NEUROLOOM_LLM_TRANSCRIPTS=<目录>` 后 `TranscriptRecorder::from_env` 返回记录器，
+//! 经 `Gateway::with_transcripts` 挂到注册的 Provider 上；`nl providers transcript tail` 查看最近的记录。
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use nl_durable::redaction::REDACTED;
+use nl_durable::Redactor;
+
+/// 记录目录的环境变量
+pub const TRANSCRIPTS_ENV: &str = "NEUROLOOM_LLM_TRANSCRIPTS";
+
+/// 默认单文件上限
+const DEFAULT_MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// 默认保留的旧文件数
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// 一次请求 / 响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    /// 发送时间
+    pub timestamp: DateTime<Utc>,
+    /// Provider 标识
+    pub provider: String,
+    /// HTTP 方法
+    pub method: String,
+    /// 请求 URL
+    pub url: String,
+    /// 是否为流式请求
+    pub stream: bool,
+    /// 请求头
+    pub request_headers: BTreeMap<String, String>,
+    /// 请求体
+    pub request_body: Value,
+    /// 响应状态码（传输失败时为空）
+    pub status: Option<u16>,
+    /// 响应头
+    #[serde(default)]
+    pub response_headers: BTreeMap<String, String>,
+    /// 响应正文（流式成功响应为空）
+    pub response_body: Option<String>,
+    /// 传输错误
+    pub error: Option<String>,
+    /// 从发送到记录的耗时
+    pub duration_ms: u64,
+}
+
+impl TranscriptEntry {
+    /// 由即将发送的请求创建
+    pub fn request(provider: &str, request: &reqwest::Request, body: &Value, stream: bool) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            provider: provider.to_string(),
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            stream,
+            request_headers: header_map(request.headers()),
+            request_body: body.clone(),
+            status: None,
+            response_headers: BTreeMap::new(),
+            response_body: None,
+            error: None,
+            duration_ms: 0,
+        }
+    }
+
+    /// 记录响应状态与响应头
+    pub fn with_response(mut self, status: u16, headers: &HeaderMap) -> Self {
+        self.status = Some(status);
+        self.response_headers = header_map(headers);
+        self
+    }
+
+    /// 记录响应正文
+    pub fn with_body(mut self, body: &str) -> Self {
+        self.response_body = Some(body.to_string());
+        self
+    }
+
+    /// 记录传输错误
+    pub fn with_error(mut self, error: impl std::fmt::Display) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+/// 通信记录器
+pub struct TranscriptRecorder {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    max_files: usize,
+    redactor: Redactor,
+    /// 串行化追加与轮转
+    lock: Mutex<()>,
+}
+
+impl TranscriptRecorder {
+    /// 记录到指定目录
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            max_files: DEFAULT_MAX_FILES,
+            redactor: Redactor::default(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 设置了 `NEUROLOOM_LLM_TRANSCRIPTS` 时记录到该目录
+    pub fn from_env() -> Option<Self> {
+        std::env::var(TRANSCRIPTS_ENV)
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(Self::new)
+    }
+
+    /// 设置单文件上限
+    pub fn with_max_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_file_bytes = bytes;
+        self
+    }
+
+    /// 设置保留的旧文件数
+    pub fn with_max_files(mut self, files: usize) -> Self {
+        self.max_files = files;
+        self
+    }
+
+    /// 替换脱敏规则
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// 记录目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 脱敏后追加一条记录（写入失败只记日志，不影响请求）
+    pub fn record(&self, mut entry: TranscriptEntry) {
+        entry.duration_ms = (Utc::now() - entry.timestamp).num_milliseconds().max(0) as u64;
+        self.sanitize(&mut entry);
+        if let Err(e) = self.append(&entry) {
+            tracing::warn!("Failed to write {} transcript: {}", entry.provider, e);
+        }
+    }
+
+    fn sanitize(&self, entry: &mut TranscriptEntry) {
+        for headers in [&mut entry.request_headers, &mut entry.response_headers] {
+            for (name, value) in headers.iter_mut() {
+                *value = if self.redactor.is_sensitive_key(name) {
+                    REDACTED.to_string()
+                } else {
+                    self.redactor.redact_str(value).into_owned()
+                };
+            }
+        }
+        entry.url = redact_url(&entry.url, &self.redactor);
+        self.redactor.redact(&mut entry.request_body);
+        for text in [&mut entry.response_body, &mut entry.error].into_iter().flatten() {
+            *text = self.redactor.redact_str(text).into_owned();
+        }
+    }
+
+    fn append(&self, entry: &TranscriptEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        std::fs::create_dir_all(&self.dir)?;
+        let path = transcript_path(&self.dir, &entry.provider, 0);
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_file_bytes {
+            self.rotate(&entry.provider)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// `<p>.jsonl` -> `<p>.1.jsonl` -> ... -> `<p>.<max_files>.jsonl`，最旧的被删除
+    fn rotate(&self, provider: &str) -> std::io::Result<()> {
+        let oldest = transcript_path(&self.dir, provider, self.max_files);
+        if oldest.exists() {
+            std::fs::remove_file(oldest)?;
+        }
+        for generation in (0..self.max_files).rev() {
+            let from = transcript_path(&self.dir, provider, generation);
+            if from.exists() {
+                std::fs::rename(from, transcript_path(&self.dir, provider, generation + 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 读取最近 `limit` 条记录（含已轮转的文件，按时间排序）；`provider` 为空时合并全部 Provider
+pub fn tail(dir: &Path, provider: Option<&str>, limit: usize) -> std::io::Result<Vec<TranscriptEntry>> {
+    let files = match std::fs::read_dir(dir) {
+        Ok(files) => files,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let wanted = provider.map(file_stem);
+    let mut entries = Vec::new();
+    for file in files {
+        let path = file?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(stem) = name.strip_suffix(".jsonl").and_then(|s| s.split('.').next()) else {
+            continue;
+        };
+        if wanted.as_deref().is_some_and(|wanted| wanted != stem) {
+            continue;
+        }
+        for line in BufReader::new(File::open(&path)?).lines() {
+            if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(&line?) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries.sort_by_key(|e| e.timestamp);
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+/// 第 `generation` 代记录文件（0 为当前文件）
+fn transcript_path(dir: &Path, provider: &str, generation: usize) -> PathBuf {
+    match generation {
+        0 => dir.join(format!("{}.jsonl", file_stem(provider))),
+        n => dir.join(format!("{}.{}.jsonl", file_stem(provider), n)),
+    }
+}
+
+/// Provider 标识转为文件名（只保留字母、数字、`-` 与 `_`）
+fn file_stem(provider: &str) -> String {
+    provider
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn header_map(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| (name.as_str().to_string(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+        .collect()
+}
+
+/// 替换 URL 查询参数中的密钥（如 Gemini 的 `?key=`）
+fn redact_url(url: &str, redactor: &Redactor) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if key == "key" || redactor.is_sensitive_key(key) => format!("{}={}", key, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{}?{}", base, query.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcripts_are_redacted_rotated_and_tailed() {
+        let dir = std::env::temp_dir().join(format!("nl-transcripts-{}", uuid::Uuid::new_v4()));
+        let recorder = TranscriptRecorder::new(&dir).with_max_file_bytes(1500).with_max_files(1);
+        let body = serde_json::json!({ "model": "gemini-pro", "api_key": "inline-secret", "prompt": "hi" });
+        for i in 0..6 {
+            let request = reqwest::Client::new()
+                .post("https://generativelanguage.googleapis.com/v1/models:generate?key=AIzaSecret&alt=json")
+                .header("x-goog-api-key", "AIzaSecret")
+                .header("Authorization", "Bearer sk-live-token")
+                .json(&body)
+                .build()
+                .unwrap();
+            let mut headers = HeaderMap::new();
+            headers.insert("set-cookie", "session=abc".parse().unwrap());
+            let entry = TranscriptEntry::request("gemini", &request, &body, false)
+                .with_response(200, &headers)
+                .with_body(&format!("{{\"text\": \"reply {}\"}}", i));
+            recorder.record(entry);
+        }
+
+        let current = std::fs::read_to_string(dir.join("gemini.jsonl")).unwrap();
+        assert!(!current.contains("AIzaSecret") && !current.contains("inline-secret") && !current.contains("sk-live"));
+        assert!(dir.join("gemini.1.jsonl").exists());
+        assert!(!dir.join("gemini.2.jsonl").exists());
+
+        let recent = tail(&dir, Some("gemini"), 2).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[1].response_body.as_deref(), Some("{\"text\": \"reply 5\"}"));
+        assert_eq!(recent[1].request_headers["authorization"], REDACTED);
+        assert_eq!(recent[1].response_headers["set-cookie"], REDACTED);
+        assert!(recent[1].url.ends_with("?key=[REDACTED]&alt=json"));
+        assert!(tail(&dir, Some("claude"), 10).unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
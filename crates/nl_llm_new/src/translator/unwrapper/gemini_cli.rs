@@ -11,6 +11,10 @@ pub fn unwrap(parsed: &Value, wrapper: WrapperKind) -> Result<PrimitiveRequest,
         wrapper_kind: wrapper,
         was_unwrapped: true,
         client_specific: Default::default(),
+        no_cache: false,
+        task_type: None,
+        session_id: None,
+        locale: None,
     };
 
     Ok(request)
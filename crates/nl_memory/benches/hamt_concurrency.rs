@@ -0,0 +1,44 @@
+//! HAMT 索引并发读吞吐
+//!
+//! 多个线程同时按标签检索同一个索引，观察吞吐随线程数的变化。
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use nl_memory::hamt::MemoryEntry;
+use nl_memory::HamtIndex;
+
+const ENTRIES: usize = 10_000;
+const LOOKUPS_PER_THREAD: usize = 10_000;
+
+fn concurrent_retrieve(c: &mut Criterion) {
+    let index = Arc::new(HamtIndex::new());
+    for i in 0..ENTRIES {
+        index.store(MemoryEntry::new(format!("tag-{}", i), format!("summary {}", i)));
+    }
+    let tags: Vec<String> = (0..ENTRIES).map(|i| format!("tag-{}", i)).collect();
+
+    let mut group = c.benchmark_group("hamt_retrieve_by_tag");
+    for threads in [1, 4, 8] {
+        group.throughput(Throughput::Elements((threads * LOOKUPS_PER_THREAD) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+            b.iter(|| {
+                std::thread::scope(|scope| {
+                    for t in 0..threads {
+                        let (index, tags) = (&index, &tags);
+                        scope.spawn(move || {
+                            for i in 0..LOOKUPS_PER_THREAD {
+                                let tag = &tags[(i * 7 + t * 131) % ENTRIES];
+                                assert!(index.retrieve_by_tag(tag).is_some());
+                            }
+                        });
+                    }
+                });
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_retrieve);
+criterion_main!(benches);
@@ -1,9 +1,22 @@
 //! 归档管理器
+//!
+//! 数据以 gzip 压缩写入归档目录下的 `<来源 ID>.gz`；恢复时优先按登记的条目定位，
+//! 进程重启后登记丢失也可按来源 ID 直接找到文件。
+//! 设置加密器后压缩数据再以 AES-256-GCM 加密（绑定来源 ID），用于保存含敏感原文的 Level 3 数据。
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use nl_core::NeuroLoomError;
+use nl_durable::EventCipher;
+
 /// 归档条目
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArchiveEntry {
@@ -40,6 +53,8 @@ pub struct ArchivalManager {
     archive_dir: String,
     /// 已归档条目
     archives: Vec<ArchiveEntry>,
+    /// 加密器（未设置时只压缩）
+    cipher: Option<EventCipher>,
 }
 
 impl ArchivalManager {
@@ -49,9 +64,16 @@ impl ArchivalManager {
             strategy,
             archive_dir: archive_dir.into(),
             archives: Vec::new(),
+            cipher: None,
         }
     }
 
+    /// 加密归档数据
+    pub fn with_cipher(mut self, cipher: EventCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
     /// 创建默认管理器
     pub fn default_manager() -> Self {
         Self::new(
@@ -60,46 +82,81 @@ impl ArchivalManager {
         )
     }
 
-    /// 归档数据
+    /// 归档数据（同一来源重复归档时覆盖）
     pub async fn archive(&mut self, source_id: Uuid, data: &[u8]) -> nl_core::Result<ArchiveEntry> {
-        let compressed = self.compress(data);
-        let compressed_path = format!("{}/{}.zst", self.archive_dir, source_id);
+        let mut compressed = Self::compress(data)?;
+        if let Some(cipher) = &self.cipher {
+            compressed = cipher.encrypt(&compressed, source_id.as_bytes())?.into_bytes();
+        }
+        let path = self.path_for(&source_id);
+        tokio::fs::create_dir_all(&self.archive_dir).await?;
+        tokio::fs::write(&path, &compressed).await?;
 
-        // TODO: 实现实际的文件写入
         let entry = ArchiveEntry {
             id: Uuid::new_v4(),
             source_id,
             archived_at: Utc::now(),
-            compressed_path,
+            compressed_path: path.to_string_lossy().to_string(),
             original_size: data.len() as u64,
             compressed_size: compressed.len() as u64,
         };
 
+        self.archives.retain(|a| a.source_id != source_id);
         self.archives.push(entry.clone());
         Ok(entry)
     }
 
     /// 恢复数据
     pub async fn restore(&self, source_id: &Uuid) -> nl_core::Result<Vec<u8>> {
-        let entry = self.archives
-            .iter()
-            .find(|a| &a.source_id == source_id)
-            .ok_or_else(|| nl_core::NeuroLoomError::Memory("Archive not found".to_string()))?;
+        let path = match self.archives.iter().find(|a| &a.source_id == source_id) {
+            Some(entry) => PathBuf::from(&entry.compressed_path),
+            None => self.path_for(source_id),
+        };
+        let compressed = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(NeuroLoomError::Memory("Archive not found".to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        match std::str::from_utf8(&compressed).ok().filter(|data| EventCipher::is_encrypted(data)) {
+            Some(sealed) => match &self.cipher {
+                Some(cipher) => Self::decompress(&cipher.decrypt(sealed, source_id.as_bytes())?),
+                None => Err(NeuroLoomError::Memory("Archive is encrypted".to_string())),
+            },
+            None => Self::decompress(&compressed),
+        }
+    }
+
+    /// 删除归档，返回是否存在
+    pub async fn remove(&mut self, source_id: &Uuid) -> nl_core::Result<bool> {
+        let path = match self.archives.iter().position(|a| &a.source_id == source_id) {
+            Some(index) => PathBuf::from(self.archives.remove(index).compressed_path),
+            None => self.path_for(source_id),
+        };
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
 
-        // TODO: 实现实际的文件读取和解压
-        Ok(vec![])
+    fn path_for(&self, source_id: &Uuid) -> PathBuf {
+        PathBuf::from(&self.archive_dir).join(format!("{}.gz", source_id))
     }
 
     /// 压缩数据
-    fn compress(&self, data: &[u8]) -> Vec<u8> {
-        // TODO: 实现 Zstandard 压缩
-        data.to_vec()
+    fn compress(data: &[u8]) -> nl_core::Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
     }
 
     /// 解压数据
-    fn decompress(&self, data: &[u8]) -> Vec<u8> {
-        // TODO: 实现 Zstandard 解压
-        data.to_vec()
+    fn decompress(data: &[u8]) -> nl_core::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut output)?;
+        Ok(output)
     }
 
     /// 获取归档数量
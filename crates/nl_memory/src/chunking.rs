@@ -0,0 +1,606 @@
+//! 语言感知的代码分块
+//!
+//! 整个文件塞进提示词会浪费大量 token。`CodeChunker` 用 tree-sitter 解析源码，
+//! 沿函数 / 结构体 / 类边界切分：
+//! - 定义连同其前导注释与属性（`///`、`#[...]`、装饰器）作为一个块
+//! - 超过 `max_lines` 的 impl / trait / class 拆成内部方法；其余超长定义按行窗口切分，相邻窗口重叠 `overlap_lines` 行
+//! - 定义之间的 `use`、常量等零散代码按连续区间合并为 `Other` 块
+//! - 不支持的语言或解析失败时退化为带重叠的行窗口
+//!
+//! `link` 把块挂到 GraphRAG 的文件 / 函数 / 结构体节点上；`select` 按查询相关度在 token 预算内挑选块，
+//! 供记忆摘要与提示词上下文使用。
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Node, Parser};
+use uuid::Uuid;
+
+use crate::graph_rag::{CodeLocation, EdgeType, GraphEdge, GraphNode, GraphRAG, NodeType};
+
+/// 节点元数据中记录块 ID 的键
+const CHUNK_ID_KEY: &str = "chunk_id";
+
+/// 定义节点元数据中记录所在文件的键（节点的 `path` 字段留给 File 节点，避免覆盖路径索引）
+const PATH_KEY: &str = "path";
+
+/// 估算 token 数时每个 token 的平均字节数
+pub(crate) const BYTES_PER_TOKEN: usize = 4;
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeLanguage {
+    Rust,
+    Python,
+    JavaScript,
+}
+
+impl CodeLanguage {
+    /// 按扩展名识别语言
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(CodeLanguage::Rust),
+            "py" | "pyi" => Some(CodeLanguage::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(CodeLanguage::JavaScript),
+            _ => None,
+        }
+    }
+
+    fn grammar(&self) -> tree_sitter::Language {
+        match self {
+            CodeLanguage::Rust => tree_sitter_rust::LANGUAGE.into(),
+            CodeLanguage::Python => tree_sitter_python::LANGUAGE.into(),
+            CodeLanguage::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+        }
+    }
+
+    /// 语法节点对应的块类型（非定义节点返回 `None`）
+    fn definition_kind(&self, kind: &str) -> Option<ChunkKind> {
+        let chunk = match (self, kind) {
+            (
+                CodeLanguage::Rust,
+                "function_item" | "function_signature_item" | "macro_definition",
+            ) => ChunkKind::Function,
+            (CodeLanguage::Rust, "struct_item" | "enum_item" | "union_item" | "type_item") => {
+                ChunkKind::Struct
+            }
+            (CodeLanguage::Rust, "impl_item" | "trait_item" | "mod_item") => ChunkKind::Module,
+            (CodeLanguage::Python, "function_definition") => ChunkKind::Function,
+            (CodeLanguage::Python, "class_definition") => ChunkKind::Module,
+            (
+                CodeLanguage::JavaScript,
+                "function_declaration" | "generator_function_declaration" | "method_definition",
+            ) => ChunkKind::Function,
+            (CodeLanguage::JavaScript, "class_declaration") => ChunkKind::Module,
+            _ => return None,
+        };
+        Some(chunk)
+    }
+
+    /// 前导的注释与属性节点
+    fn is_leading(&self, kind: &str) -> bool {
+        matches!(
+            kind,
+            "line_comment"
+                | "block_comment"
+                | "attribute_item"
+                | "inner_attribute_item"
+                | "comment"
+                | "decorator"
+        )
+    }
+}
+
+/// 块类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkKind {
+    Function,
+    Struct,
+    /// impl / trait / mod / class
+    Module,
+    /// 定义之间的零散代码
+    Other,
+    /// 行窗口（超长定义的片段或无法解析的文件）
+    Window,
+}
+
+/// 代码块
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeChunk {
+    pub id: Uuid,
+    pub path: String,
+    pub language: Option<CodeLanguage>,
+    pub kind: ChunkKind,
+    /// 定义名（方法为 `Type::method`）
+    pub name: Option<String>,
+    /// 起止行（从 1 开始，含两端）
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    /// 关联的 GraphRAG 节点
+    pub node_id: Option<Uuid>,
+}
+
+impl CodeChunk {
+    /// 估算 token 数
+    pub fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.text)
+    }
+
+    /// 带路径与行号的标题，用于提示词
+    pub fn header(&self) -> String {
+        match &self.name {
+            Some(name) => format!(
+                "{}:{}-{} ({})",
+                self.path, self.start_line, self.end_line, name
+            ),
+            None => format!("{}:{}-{}", self.path, self.start_line, self.end_line),
+        }
+    }
+
+    fn location(&self) -> CodeLocation {
+        CodeLocation {
+            start_line: self.start_line as u32,
+            start_column: 0,
+            end_line: self.end_line as u32,
+            end_column: 0,
+        }
+    }
+}
+
+/// 粗略估算文本的 token 数
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(BYTES_PER_TOKEN)
+}
+
+/// 分块配置
+#[derive(Debug, Clone)]
+pub struct ChunkerConfig {
+    /// 单块最大行数
+    pub max_lines: usize,
+    /// 窗口切分时相邻块的重叠行数
+    pub overlap_lines: usize,
+}
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        Self {
+            max_lines: 80,
+            overlap_lines: 8,
+        }
+    }
+}
+
+/// 代码分块器
+#[derive(Debug, Clone, Default)]
+pub struct CodeChunker {
+    config: ChunkerConfig,
+}
+
+/// 待输出的区间（0 起始行号，含两端）
+struct Span {
+    kind: ChunkKind,
+    name: Option<String>,
+    start: usize,
+    end: usize,
+}
+
+impl CodeChunker {
+    /// 创建分块器
+    pub fn new(config: ChunkerConfig) -> Self {
+        Self { config }
+    }
+
+    /// 切分源码
+    pub fn chunk(&self, path: &str, source: &str) -> Vec<CodeChunk> {
+        let lines: Vec<&str> = source.lines().collect();
+        if lines.is_empty() {
+            return Vec::new();
+        }
+        let language = CodeLanguage::from_path(Path::new(path));
+        let spans = language
+            .and_then(|language| self.syntax_spans(language, source, lines.len()))
+            .unwrap_or_else(|| self.windows(ChunkKind::Window, None, 0, lines.len() - 1));
+
+        spans
+            .into_iter()
+            .map(|span| CodeChunk {
+                id: Uuid::new_v4(),
+                path: path.to_string(),
+                language,
+                kind: span.kind,
+                name: span.name,
+                start_line: span.start + 1,
+                end_line: span.end + 1,
+                text: lines[span.start..=span.end].join("\n"),
+                node_id: None,
+            })
+            .collect()
+    }
+
+    /// 读取并切分文件
+    pub async fn chunk_file(&self, path: &Path) -> nl_core::Result<Vec<CodeChunk>> {
+        let source = tokio::fs::read_to_string(path).await?;
+        Ok(self.chunk(&path.to_string_lossy(), &source))
+    }
+
+    fn syntax_spans(
+        &self,
+        language: CodeLanguage,
+        source: &str,
+        line_count: usize,
+    ) -> Option<Vec<Span>> {
+        let mut parser = Parser::new();
+        parser.set_language(&language.grammar()).ok()?;
+        let tree = parser.parse(source, None)?;
+        let root = tree.root_node();
+        if root.has_error() && root.named_child_count() == 0 {
+            return None;
+        }
+
+        let mut spans = Vec::new();
+        self.collect(language, source, root, None, &mut spans);
+        spans.sort_by_key(|s| s.start);
+
+        // 定义之间的空隙合并为 Other 块
+        let mut filled = Vec::new();
+        let mut next = 0;
+        for span in spans {
+            self.gap(&mut filled, source, next, span.start);
+            next = next.max(span.end + 1);
+            filled.push(span);
+        }
+        self.gap(&mut filled, source, next, line_count);
+        Some(filled)
+    }
+
+    /// 收集容器内的定义区间
+    fn collect(
+        &self,
+        language: CodeLanguage,
+        source: &str,
+        container: Node,
+        owner: Option<&str>,
+        spans: &mut Vec<Span>,
+    ) {
+        let mut cursor = container.walk();
+        let children: Vec<Node> = container.named_children(&mut cursor).collect();
+        for (index, child) in children.iter().enumerate() {
+            let Some((definition, kind)) = definition(language, *child) else {
+                continue;
+            };
+            let name = definition_name(language, definition, source).map(|name| match owner {
+                Some(owner) => format!("{}::{}", owner, name),
+                None => name,
+            });
+
+            // 前导注释与属性并入定义
+            let mut start = child.start_position().row;
+            for previous in children[..index].iter().rev() {
+                if !language.is_leading(previous.kind()) || previous.end_position().row + 1 < start
+                {
+                    break;
+                }
+                start = previous.start_position().row;
+            }
+            let end = child.end_position().row;
+
+            if end - start < self.config.max_lines {
+                spans.push(Span {
+                    kind,
+                    name,
+                    start,
+                    end,
+                });
+                continue;
+            }
+            let body = definition
+                .child_by_field_name("body")
+                .filter(|_| kind == ChunkKind::Module);
+            match body {
+                Some(body) => {
+                    let before = spans.len();
+                    self.collect(language, source, body, name.as_deref(), spans);
+                    if spans.len() == before {
+                        spans.extend(self.windows(ChunkKind::Window, name, start, end));
+                    }
+                }
+                None => spans.extend(self.windows(ChunkKind::Window, name, start, end)),
+            }
+        }
+    }
+
+    /// 把 [from, to) 中的非空行加入为 Other 块
+    fn gap(&self, spans: &mut Vec<Span>, source: &str, from: usize, to: usize) {
+        let lines: Vec<&str> = source.lines().collect();
+        let to = to.min(lines.len());
+        let Some(start) = (from..to).find(|&i| !lines[i].trim().is_empty()) else {
+            return;
+        };
+        let end = (start..to)
+            .rev()
+            .find(|&i| !lines[i].trim().is_empty())
+            .unwrap_or(start);
+        spans.extend(self.windows(ChunkKind::Other, None, start, end));
+    }
+
+    /// 按行窗口切分 [start, end]，相邻窗口重叠
+    fn windows(
+        &self,
+        kind: ChunkKind,
+        name: Option<String>,
+        start: usize,
+        end: usize,
+    ) -> Vec<Span> {
+        let size = self.config.max_lines.max(1);
+        if end - start < size {
+            return vec![Span {
+                kind,
+                name,
+                start,
+                end,
+            }];
+        }
+        let step = size.saturating_sub(self.config.overlap_lines).max(1);
+        let mut spans = Vec::new();
+        let mut from = start;
+        loop {
+            let to = (from + size - 1).min(end);
+            spans.push(Span {
+                kind: ChunkKind::Window,
+                name: name.clone(),
+                start: from,
+                end: to,
+            });
+            if to == end {
+                break;
+            }
+            from += step;
+        }
+        spans
+    }
+
+    /// 把块关联到 GraphRAG：每个文件一个 File 节点，具名定义挂为其 `Defines` 子节点
+    pub fn link(&self, chunks: &mut [CodeChunk], graph: &mut GraphRAG) {
+        for chunk in chunks.iter_mut() {
+            let file_id = match graph.find_by_path(&chunk.path) {
+                Some(node) => node.id,
+                None => {
+                    let node =
+                        graph_node(&chunk.path, NodeType::File, Some(chunk.path.clone()), None);
+                    let id = node.id;
+                    graph.add_node(node);
+                    id
+                }
+            };
+            let (Some(name), Some(node_type)) = (chunk.name.clone(), node_type(chunk.kind)) else {
+                chunk.node_id = Some(file_id);
+                continue;
+            };
+
+            // 同一文件中的同名定义（如 struct 与其 impl）共用一个节点
+            let existing = graph
+                .find_by_name(&name)
+                .filter(|node| node.metadata.get(PATH_KEY) == Some(&chunk.path))
+                .map(|node| node.id);
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    let mut node = graph_node(&name, node_type, None, Some(chunk.location()));
+                    node.metadata
+                        .insert(PATH_KEY.to_string(), chunk.path.clone());
+                    node.metadata
+                        .insert(CHUNK_ID_KEY.to_string(), chunk.id.to_string());
+                    let id = node.id;
+                    graph.add_node(node);
+                    graph.add_edge(GraphEdge {
+                        source: file_id,
+                        target: id,
+                        edge_type: EdgeType::Defines,
+                    });
+                    id
+                }
+            };
+            chunk.node_id = Some(id);
+        }
+    }
+
+    /// 按与查询的词重合度挑选块，总量不超过 `token_budget`，结果按文件内顺序排列
+    pub fn select<'a>(
+        &self,
+        chunks: &'a [CodeChunk],
+        query: &str,
+        token_budget: usize,
+    ) -> Vec<&'a CodeChunk> {
+        let wanted = terms(query);
+        let mut ranked: Vec<(f64, &CodeChunk)> = chunks
+            .iter()
+            .map(|chunk| {
+                let text = terms(&chunk.text);
+                let name = chunk.name.as_deref().map(terms).unwrap_or_default();
+                let hits = wanted.iter().filter(|t| text.contains(*t)).count() as f64;
+                let name_hits = wanted.iter().filter(|t| name.contains(*t)).count() as f64;
+                let kind = if chunk.kind == ChunkKind::Other {
+                    0.5
+                } else {
+                    1.0
+                };
+                ((hits + 2.0 * name_hits) * kind, chunk)
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then(a.1.start_line.cmp(&b.1.start_line))
+        });
+
+        let mut used = 0;
+        let mut selected: Vec<&CodeChunk> = Vec::new();
+        for (_, chunk) in ranked {
+            let tokens = chunk.estimated_tokens();
+            if used + tokens <= token_budget {
+                used += tokens;
+                selected.push(chunk);
+            }
+        }
+        selected.sort_by(|a, b| a.path.cmp(&b.path).then(a.start_line.cmp(&b.start_line)));
+        selected
+    }
+
+    /// 把挑选的块渲染为提示词片段
+    pub fn render(chunks: &[&CodeChunk]) -> String {
+        chunks
+            .iter()
+            .map(|chunk| format!("// {}\n{}", chunk.header(), chunk.text))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// 节点本身或其包裹的定义（装饰器、`export` 语句）
+fn definition(language: CodeLanguage, node: Node) -> Option<(Node, ChunkKind)> {
+    if let Some(kind) = language.definition_kind(node.kind()) {
+        return Some((node, kind));
+    }
+    let inner = match node.kind() {
+        "decorated_definition" => node.child_by_field_name("definition")?,
+        "export_statement" => node.child_by_field_name("declaration")?,
+        _ => return None,
+    };
+    language
+        .definition_kind(inner.kind())
+        .map(|kind| (inner, kind))
+}
+
+fn definition_name(language: CodeLanguage, node: Node, source: &str) -> Option<String> {
+    let field = match (language, node.kind()) {
+        (CodeLanguage::Rust, "impl_item") => "type",
+        _ => "name",
+    };
+    let name = node
+        .child_by_field_name(field)?
+        .utf8_text(source.as_bytes())
+        .ok()?;
+    Some(name.to_string())
+}
+
+fn node_type(kind: ChunkKind) -> Option<NodeType> {
+    match kind {
+        ChunkKind::Function => Some(NodeType::Function),
+        ChunkKind::Struct => Some(NodeType::Struct),
+        ChunkKind::Module => Some(NodeType::Module),
+        ChunkKind::Window => Some(NodeType::Function),
+        ChunkKind::Other => None,
+    }
+}
+
+fn graph_node(
+    name: &str,
+    node_type: NodeType,
+    path: Option<String>,
+    location: Option<CodeLocation>,
+) -> GraphNode {
+    GraphNode {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        node_type,
+        path,
+        location,
+        metadata: Default::default(),
+    }
+}
+
+/// 标识符切分为小写词（含 snake_case 与 CamelCase 拆分）
+fn terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let mut current = String::new();
+        for c in word.chars() {
+            if c.is_uppercase() && !current.is_empty() {
+                terms.push(std::mem::take(&mut current));
+            }
+            current.extend(c.to_lowercase());
+        }
+        if !current.is_empty() {
+            terms.push(current);
+        }
+    }
+    terms.retain(|t| t.len() > 2);
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_rust_by_definitions_and_link() {
+        let source = "\
+use std::fmt;
+
+/// A point.
+#[derive(Debug)]
+struct Point {
+    x: i32,
+}
+
+impl Point {
+    fn norm(&self) -> i32 {
+        self.x.abs()
+    }
+}
+
+fn parse_point(text: &str) -> Point {
+    Point { x: text.len() as i32 }
+}
+";
+        let chunker = CodeChunker::default();
+        let mut chunks = chunker.chunk("src/point.rs", source);
+        let kinds: Vec<(ChunkKind, Option<&str>, usize)> = chunks
+            .iter()
+            .map(|c| (c.kind, c.name.as_deref(), c.start_line))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (ChunkKind::Other, None, 1),
+                (ChunkKind::Struct, Some("Point"), 3),
+                (ChunkKind::Module, Some("Point"), 9),
+                (ChunkKind::Function, Some("parse_point"), 15),
+            ]
+        );
+        assert!(chunks[1].text.starts_with("/// A point."));
+
+        let mut graph = GraphRAG::new();
+        chunker.link(&mut chunks, &mut graph);
+        let file = graph.find_by_path("src/point.rs").unwrap().id;
+        let function = graph.find_by_name("parse_point").unwrap();
+        assert_eq!(chunks[3].node_id, Some(function.id));
+        assert_eq!(chunks[2].node_id, chunks[1].node_id);
+        assert_eq!(graph.get_outgoing_edges(&file).len(), 2);
+
+        let selected = chunker.select(&chunks, "how is a point parsed from text", 20);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].name.as_deref(), Some("parse_point"));
+
+        // 超长定义按重叠窗口切分；未知语言退化为行窗口
+        let small = CodeChunker::new(ChunkerConfig {
+            max_lines: 4,
+            overlap_lines: 1,
+        });
+        let long = "fn long() {\n    a();\n    b();\n    c();\n    d();\n    e();\n}\n";
+        let windows = small.chunk("long.rs", long);
+        assert_eq!(
+            windows
+                .iter()
+                .map(|c| (c.start_line, c.end_line))
+                .collect::<Vec<_>>(),
+            vec![(1, 4), (4, 7)]
+        );
+        assert!(windows.iter().all(|c| c.name.as_deref() == Some("long")));
+        assert_eq!(small.chunk("notes.txt", long)[0].kind, ChunkKind::Window);
+    }
+}
@@ -0,0 +1,494 @@
+//! 记忆整理 ("睡眠周期")
+//!
+//! 在系统空闲时（事件总线一段时间没有外部事件）周期性整理 HAMT 记忆：
+//! 1. 聚类近期记忆，并在簇内合并重复条目
+//! 2. 为高频访问的 Level 3 数据重新生成摘要（超长源码按代码块挑选后再交给摘要器；配置 `PiiVault` 时先脱敏）
+//! 3. 归档冷数据并从索引移除
+//! 4. 为缺失或过期的条目重建向量嵌入
+//!
+//! 每个阶段发布 `MemoryConsolidationProgress` 事件，整轮结束发布 `MemoryConsolidated`。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use nl_core::{Event, EventKind, Result};
+use nl_durable::EventBus;
+
+use crate::archival::ArchivalManager;
+use crate::chunking::{estimate_tokens, CodeChunker, CodeLanguage};
+use crate::hamt::{HamtIndex, MemoryEntry};
+use crate::pii::PiiVault;
+
+/// 摘要刷新时间的元数据键
+const SUMMARY_REFRESHED_KEY: &str = "summary_refreshed_at";
+
+/// 被合并条目 ID 的元数据键
+const MERGED_FROM_KEY: &str = "merged_from";
+
+/// 聚类标签的元数据键
+const CLUSTER_KEY: &str = "cluster";
+
+/// 由 Level 3 全量数据生成摘要
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    async fn summarize(&self, entry: &MemoryEntry, full: &str) -> Result<String>;
+}
+
+/// 生成向量嵌入
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+}
+
+/// 整理配置
+#[derive(Debug, Clone)]
+pub struct ConsolidationConfig {
+    /// 空闲检测间隔
+    pub check_interval: Duration,
+    /// 无外部事件多久视为空闲
+    pub idle_after: Duration,
+    /// 两轮整理的最小间隔
+    pub min_interval: Duration,
+    /// 参与聚类的近期记忆范围
+    pub recent_window: chrono::Duration,
+    /// 归入同一簇的相似度阈值（标签与摘要词集的 Jaccard 相似度）
+    pub cluster_threshold: f64,
+    /// 视为重复的相似度阈值
+    pub duplicate_threshold: f64,
+    /// 刷新摘要所需的最小访问次数
+    pub promote_min_access: u64,
+    /// 交给摘要器的原文 token 上限（超出的源码文件按代码块挑选）
+    pub summary_input_tokens: usize,
+    /// 多少天未访问视为冷数据
+    pub cold_days: i64,
+}
+
+impl Default for ConsolidationConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(60),
+            idle_after: Duration::from_secs(300),
+            min_interval: Duration::from_secs(3600),
+            recent_window: chrono::Duration::days(1),
+            cluster_threshold: 0.3,
+            duplicate_threshold: 0.8,
+            promote_min_access: 10,
+            summary_input_tokens: 4000,
+            cold_days: 30,
+        }
+    }
+}
+
+/// 一轮整理的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsolidationReport {
+    pub clusters: usize,
+    pub merged: usize,
+    pub promoted: usize,
+    pub embedded: usize,
+    pub archived: usize,
+}
+
+/// 记忆整理器
+pub struct MemoryConsolidator {
+    config: ConsolidationConfig,
+    index: Arc<HamtIndex>,
+    archival: tokio::sync::Mutex<ArchivalManager>,
+    summarizer: Option<Arc<dyn Summarizer>>,
+    embedder: Option<Arc<dyn Embedder>>,
+    pii: Option<Arc<PiiVault>>,
+    bus: Option<Arc<EventBus>>,
+    chunker: CodeChunker,
+}
+
+impl MemoryConsolidator {
+    /// 创建整理器
+    pub fn new(index: Arc<HamtIndex>, config: ConsolidationConfig) -> Self {
+        Self {
+            config,
+            index,
+            archival: tokio::sync::Mutex::new(ArchivalManager::default_manager()),
+            summarizer: None,
+            embedder: None,
+            pii: None,
+            bus: None,
+            chunker: CodeChunker::default(),
+        }
+    }
+
+    /// 设置归档管理器
+    pub fn with_archival(mut self, archival: ArchivalManager) -> Self {
+        self.archival = tokio::sync::Mutex::new(archival);
+        self
+    }
+
+    /// 设置摘要生成器（未设置时跳过摘要刷新）
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn Summarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// 设置嵌入生成器（未设置时跳过嵌入重建）
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// 刷新摘要前脱敏 PII
+    pub fn with_pii_vault(mut self, vault: Arc<PiiVault>) -> Self {
+        self.pii = Some(vault);
+        self
+    }
+
+    /// 设置代码分块器
+    pub fn with_chunker(mut self, chunker: CodeChunker) -> Self {
+        self.chunker = chunker;
+        self
+    }
+
+    /// 设置事件总线：用于空闲检测与发布进度事件
+    pub fn with_event_bus(mut self, bus: Arc<EventBus>) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// 后台运行：空闲且距上轮超过最小间隔时执行一轮整理
+    pub async fn run(self) {
+        let mut events = self.bus.as_ref().map(|bus| bus.subscribe_all());
+        let mut ticker = tokio::time::interval(self.config.check_interval);
+        let mut last_activity = Instant::now();
+        let mut last_cycle: Option<Instant> = None;
+
+        loop {
+            tokio::select! {
+                event = async { events.as_mut()?.recv().await }, if events.is_some() => {
+                    match event {
+                        Some(event) if !Self::is_own_event(&event) => last_activity = Instant::now(),
+                        Some(_) => {}
+                        None => events = None,
+                    }
+                }
+                _ = ticker.tick() => {
+                    let idle = last_activity.elapsed() >= self.config.idle_after;
+                    let due = last_cycle.is_none_or(|t| t.elapsed() >= self.config.min_interval);
+                    if !(idle && due) {
+                        continue;
+                    }
+                    last_cycle = Some(Instant::now());
+                    match self.run_cycle().await {
+                        Ok(report) => tracing::info!("Memory consolidation finished: {:?}", report),
+                        Err(e) => tracing::warn!("Memory consolidation failed: {}", e),
+                    }
+                }
+            }
+        }
+    }
+
+    /// 整理自身产生的事件不打断空闲
+    fn is_own_event(event: &Event) -> bool {
+        matches!(
+            event.kind,
+            EventKind::MemoryConsolidationProgress | EventKind::MemoryConsolidated | EventKind::MemoryArchived
+        )
+    }
+
+    /// 执行一轮整理
+    pub async fn run_cycle(&self) -> Result<ConsolidationReport> {
+        let cycle = Uuid::new_v4();
+        let mut report = ConsolidationReport::default();
+
+        (report.clusters, report.merged) = self.cluster_and_merge().await;
+        self.progress(cycle, "merge", report.merged);
+
+        report.promoted = self.promote().await?;
+        self.progress(cycle, "promote", report.promoted);
+
+        report.archived = self.archive_cold(cycle).await?;
+        self.progress(cycle, "archive", report.archived);
+
+        report.embedded = self.embed().await?;
+        self.progress(cycle, "embed", report.embedded);
+
+        self.publish(Event::new(
+            EventKind::MemoryConsolidated,
+            cycle,
+            serde_json::to_value(&report)?,
+        ));
+        Ok(report)
+    }
+
+    /// 聚类近期记忆并合并簇内重复条目，返回 (簇数量, 合并数量)
+    ///
+    /// 贪心聚类：按访问次数从高到低，每个条目归入第一个与簇代表足够相似的簇。
+    async fn cluster_and_merge(&self) -> (usize, usize) {
+        let threshold = Utc::now() - self.config.recent_window;
+        let index = &self.index;
+        let mut recent: Vec<MemoryEntry> = index
+            .all_entries()
+            .into_iter()
+            .filter(|e| e.created_at >= threshold)
+            .collect();
+        recent.sort_by(|a, b| b.access_count.cmp(&a.access_count).then(a.created_at.cmp(&b.created_at)));
+
+        let mut clusters: Vec<Vec<MemoryEntry>> = Vec::new();
+        for entry in recent {
+            let words = tokens(&entry);
+            match clusters
+                .iter_mut()
+                .find(|c| jaccard(&words, &tokens(&c[0])) >= self.config.cluster_threshold)
+            {
+                Some(cluster) => cluster.push(entry),
+                None => clusters.push(vec![entry]),
+            }
+        }
+
+        let mut merged = 0;
+        for cluster in &clusters {
+            let label = cluster[0].tag.clone();
+            let mut survivors: Vec<MemoryEntry> = Vec::new();
+            for entry in cluster {
+                let words = tokens(entry);
+                match survivors
+                    .iter_mut()
+                    .find(|s| jaccard(&words, &tokens(s)) >= self.config.duplicate_threshold)
+                {
+                    Some(survivor) => {
+                        merge_into(survivor, entry);
+                        index.remove(&entry.id);
+                        merged += 1;
+                    }
+                    None => {
+                        let mut entry = entry.clone();
+                        entry.metadata.insert(CLUSTER_KEY.to_string(), label.clone());
+                        survivors.push(entry);
+                    }
+                }
+            }
+            for survivor in survivors {
+                index.store(survivor);
+            }
+        }
+        (clusters.len(), merged)
+    }
+
+    /// 为高频访问的 Level 3 数据重新生成摘要
+    async fn promote(&self) -> Result<usize> {
+        let Some(summarizer) = &self.summarizer else {
+            return Ok(0);
+        };
+        let candidates: Vec<MemoryEntry> = self
+            .index
+            .all_entries()
+            .into_iter()
+            .filter(|e| e.full_data_path.is_some() && e.access_count >= self.config.promote_min_access)
+            .filter(|e| {
+                // 上次刷新之后又被访问过才需要刷新
+                e.metadata
+                    .get(SUMMARY_REFRESHED_KEY)
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                    .is_none_or(|t| t < e.last_accessed)
+            })
+            .collect();
+
+        let mut promoted = 0;
+        for mut entry in candidates {
+            let Some(path) = entry.full_data_path.clone() else {
+                continue;
+            };
+            let full = match tokio::fs::read_to_string(&path).await {
+                Ok(full) => full,
+                Err(e) => {
+                    tracing::warn!("Skipping summary refresh for {}: {}", entry.id, e);
+                    continue;
+                }
+            };
+            let full = match &self.pii {
+                Some(vault) => vault.protect(&mut entry, &full).await?,
+                None => full,
+            };
+            let full = self.summary_input(&entry, &path, full);
+            entry.summary = summarizer.summarize(&entry, &full).await?;
+            entry.metadata.insert(SUMMARY_REFRESHED_KEY.to_string(), Utc::now().to_rfc3339());
+            entry.embedding = None;
+            self.replace(entry).await;
+            promoted += 1;
+        }
+        Ok(promoted)
+    }
+
+    /// 超出预算的源码只保留与标签、摘要最相关的代码块
+    fn summary_input(&self, entry: &MemoryEntry, path: &str, full: String) -> String {
+        let budget = self.config.summary_input_tokens;
+        if estimate_tokens(&full) <= budget || CodeLanguage::from_path(std::path::Path::new(path)).is_none() {
+            return full;
+        }
+        let chunks = self.chunker.chunk(path, &full);
+        let query = format!("{} {}", entry.tag, entry.summary);
+        CodeChunker::render(&self.chunker.select(&chunks, &query, budget))
+    }
+
+    /// 为缺失嵌入的条目重建嵌入
+    async fn embed(&self) -> Result<usize> {
+        let Some(embedder) = &self.embedder else {
+            return Ok(0);
+        };
+        let stale: Vec<MemoryEntry> = self
+            .index
+            .all_entries()
+            .into_iter()
+            .filter(|e| e.embedding.is_none())
+            .collect();
+
+        let mut embedded = 0;
+        for mut entry in stale {
+            entry.embedding = Some(embedder.embed(&format!("{}\n{}", entry.tag, entry.summary)).await?);
+            self.replace(entry).await;
+            embedded += 1;
+        }
+        Ok(embedded)
+    }
+
+    /// 归档冷数据并从索引移除
+    async fn archive_cold(&self, cycle: Uuid) -> Result<usize> {
+        let threshold = Utc::now() - chrono::Duration::days(self.config.cold_days);
+        let cold: Vec<MemoryEntry> = self
+            .index
+            .all_entries()
+            .into_iter()
+            .filter(|e| e.last_accessed < threshold)
+            .collect();
+
+        let mut archival = self.archival.lock().await;
+        for entry in &cold {
+            let archive = archival.archive(entry.id, &serde_json::to_vec(entry)?).await?;
+            self.index.remove(&entry.id);
+            self.publish(
+                Event::new(
+                    EventKind::MemoryArchived,
+                    entry.id,
+                    serde_json::json!({ "tag": entry.tag, "path": archive.compressed_path }),
+                )
+                .with_correlation(cycle),
+            );
+        }
+        Ok(cold.len())
+    }
+
+    /// 写回条目（整理期间被移除的条目不再写回）
+    async fn replace(&self, entry: MemoryEntry) {
+        self.index.replace(entry);
+    }
+
+    fn progress(&self, cycle: Uuid, phase: &str, processed: usize) {
+        self.publish(
+            Event::new(
+                EventKind::MemoryConsolidationProgress,
+                cycle,
+                serde_json::json!({ "phase": phase, "processed": processed }),
+            )
+            .with_correlation(cycle),
+        );
+    }
+
+    fn publish(&self, event: Event) {
+        if let Some(bus) = &self.bus {
+            bus.publish(&event);
+        }
+    }
+}
+
+/// 标签与摘要的小写词集
+fn tokens(entry: &MemoryEntry) -> HashSet<String> {
+    format!("{} {}", entry.tag, entry.summary)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Jaccard 相似度
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// 把重复条目合并进保留条目：累加访问次数，补齐元数据与全量数据路径
+fn merge_into(survivor: &mut MemoryEntry, duplicate: &MemoryEntry) {
+    survivor.access_count += duplicate.access_count;
+    survivor.created_at = survivor.created_at.min(duplicate.created_at);
+    survivor.last_accessed = survivor.last_accessed.max(duplicate.last_accessed);
+    if survivor.full_data_path.is_none() {
+        survivor.full_data_path = duplicate.full_data_path.clone();
+    }
+    let mut metadata: HashMap<String, String> = duplicate.metadata.clone();
+    metadata.extend(survivor.metadata.drain());
+    survivor.metadata = metadata;
+    let merged_from = survivor.metadata.entry(MERGED_FROM_KEY.to_string()).or_default();
+    if !merged_from.is_empty() {
+        merged_from.push(',');
+    }
+    merged_from.push_str(&duplicate.id.to_string());
+    survivor.embedding = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LengthEmbedder;
+
+    #[async_trait]
+    impl Embedder for LengthEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            Ok(vec![text.len() as f32])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cycle_merges_duplicates_embeds_and_archives_cold() {
+        let index = Arc::new(HamtIndex::new());
+        let mut original = MemoryEntry::new("rust build", "cargo build fails on missing feature flag");
+        original.access_count = 5;
+        let duplicate = MemoryEntry::new("rust build failure", "cargo build fails on missing feature flag");
+        let unrelated = MemoryEntry::new("lunch", "team lunch is on friday");
+        let mut cold = MemoryEntry::new("old", "stale note");
+        cold.last_accessed = Utc::now() - chrono::Duration::days(90);
+        let (original_id, duplicate_id, cold_id) = (original.id, duplicate.id, cold.id);
+        for entry in [original, duplicate, unrelated, cold] {
+            index.store(entry);
+        }
+
+        let bus = Arc::new(EventBus::default());
+        let mut events = bus.subscribe(EventKind::MemoryConsolidated);
+        let archives = std::env::temp_dir().join(format!("nl_archives_{}", Uuid::new_v4()));
+        let consolidator = MemoryConsolidator::new(index.clone(), ConsolidationConfig::default())
+            .with_embedder(Arc::new(LengthEmbedder))
+            .with_archival(ArchivalManager::new(
+                crate::archival::ArchivalStrategy::ByAge(30),
+                archives.to_string_lossy(),
+            ))
+            .with_event_bus(bus);
+        let report = consolidator.run_cycle().await.unwrap();
+
+        assert_eq!(report.merged, 1);
+        assert_eq!(report.embedded, 2);
+        assert_eq!(report.archived, 1);
+        assert!(index.get(&duplicate_id).is_none());
+        assert!(index.get(&cold_id).is_none());
+        let archived = consolidator.archival.lock().await.restore(&cold_id).await.unwrap();
+        assert_eq!(serde_json::from_slice::<MemoryEntry>(&archived).unwrap().tag, "old");
+        let survivor = index.get(&original_id).unwrap();
+        assert_eq!(survivor.metadata[MERGED_FROM_KEY], duplicate_id.to_string());
+        assert!(survivor.embedding.is_some());
+        assert_eq!(events.recv().await.unwrap().payload["merged"], 1);
+    }
+}
@@ -0,0 +1,625 @@
+//! DOCX 提取
+//!
+//! DOCX 是 ZIP 包：正文在 `word/document.xml`，样式在 `word/styles.xml`，图片经 `word/_rels/document.xml.rels`
+//! 中的关系指向 `word/media/`。正文只按需扫描标签，不做完整的 XML 解析：
+//! - 段落（`w:p`）之间空一行；样式名为 `Title` / `heading N`（或样式、段落带大纲级别）的段落输出为 Markdown 标题
+//! - 表格（`w:tbl`）按行输出为 Markdown 表格，第一行作为表头；单元格内的段落以空格连接，嵌套表格并入外层单元格
+//! - `w:lastRenderedPageBreak`（Word 保存时的分页位置）与手动分页符开始新的一页，空页不计；表格内的分页推迟到表格之后
+//! - `a:blip` / `v:imagedata` 引用的图片归入所在页面
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+
+use nl_core::Result;
+
+use super::{malformed, Document, DocumentImage, DocumentPage};
+
+/// 单个 ZIP 条目解压后的字节上限
+const MAX_ENTRY_BYTES: u64 = 256 << 20;
+
+/// 提取 DOCX
+pub fn extract(bytes: &[u8]) -> Result<Document> {
+    let archive = Archive::open(bytes)?;
+    let body = archive
+        .read("word/document.xml")?
+        .ok_or_else(|| malformed("DOCX", "word/document.xml not found"))?;
+    let styles = match archive.read("word/styles.xml")? {
+        Some(styles) => heading_styles(&String::from_utf8_lossy(&styles)),
+        None => HashMap::new(),
+    };
+    let relationships = match archive.read("word/_rels/document.xml.rels")? {
+        Some(rels) => relationships(&String::from_utf8_lossy(&rels)),
+        None => HashMap::new(),
+    };
+
+    let mut pages = Vec::new();
+    for (i, page) in paginate(&String::from_utf8_lossy(&body), &styles).into_iter().enumerate() {
+        let mut images = Vec::new();
+        for id in page.images {
+            let Some(target) = relationships.get(&id) else {
+                continue;
+            };
+            if let (Some(media_type), Some(data)) = (media_type(target), archive.read(target)?) {
+                images.push(DocumentImage {
+                    media_type: media_type.to_string(),
+                    data,
+                });
+            }
+        }
+        pages.push(DocumentPage {
+            number: i + 1,
+            text: page.text.trim_end().to_string(),
+            images,
+        });
+    }
+    Ok(Document { pages })
+}
+
+/// 扫描出的页面（图片为关系 ID）
+#[derive(Debug, Default)]
+struct Page {
+    text: String,
+    images: Vec<String>,
+}
+
+impl Page {
+    fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.images.is_empty()
+    }
+}
+
+/// 段落
+#[derive(Debug, Default)]
+struct Paragraph {
+    text: String,
+    heading: Option<usize>,
+    /// 段落中途分页：段落结束后开始新的一页
+    break_after: bool,
+}
+
+/// 表格
+#[derive(Debug, Default)]
+struct Table {
+    /// 嵌套深度（嵌套表格并入外层单元格）
+    depth: usize,
+    rows: usize,
+    row: Vec<String>,
+    cell: Option<String>,
+    /// 表格内出现分页：表格结束后开始新的一页
+    break_after: bool,
+}
+
+impl Table {
+    /// 输出一行，第一行之后加分隔行
+    fn finish_row(&mut self, page: &mut Page) {
+        let cells = std::mem::take(&mut self.row);
+        if cells.is_empty() {
+            return;
+        }
+        page.text.push_str(&format!("| {} |\n", cells.join(" | ")));
+        if self.rows == 0 {
+            page.text.push_str(&format!("|{}\n", " --- |".repeat(cells.len())));
+        }
+        self.rows += 1;
+    }
+}
+
+/// 按段落与分页扫描正文
+fn paginate(xml: &str, styles: &HashMap<String, usize>) -> Vec<Page> {
+    let mut pages = vec![Page::default()];
+    let mut paragraph: Option<Paragraph> = None;
+    let mut table: Option<Table> = None;
+    let mut in_text = false;
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        if in_text {
+            if let Some(p) = paragraph.as_mut() {
+                p.text.push_str(&unescape(&rest[..open]));
+            }
+        }
+        let Some(close) = rest[open..].find('>') else {
+            break;
+        };
+        let tag = &rest[open + 1..open + close];
+        rest = &rest[open + close + 1..];
+        let self_closing = tag.ends_with('/');
+        let name = tag.split(|c: char| c.is_whitespace() || c == '/').find(|s| !s.is_empty()).unwrap_or_default();
+        let closing = tag.starts_with('/');
+
+        match (name, closing) {
+            ("w:p", false) if !self_closing => paragraph = Some(Paragraph::default()),
+            ("w:p", true) => {
+                let cell = table.as_mut().and_then(|t| t.cell.as_mut());
+                if let (Some(p), Some(cell)) = (paragraph.as_ref(), cell) {
+                    let text = p.text.split_whitespace().collect::<Vec<_>>().join(" ").replace('|', "\\|");
+                    if !text.is_empty() {
+                        if !cell.is_empty() {
+                            cell.push(' ');
+                        }
+                        cell.push_str(&text);
+                    }
+                    paragraph = None;
+                } else if let Some(p) = paragraph.take() {
+                    let text = p.text.trim();
+                    if let Some(page) = pages.last_mut().filter(|_| !text.is_empty()) {
+                        match p.heading {
+                            Some(level) => page.text.push_str(&format!("{} {}", "#".repeat(level), text)),
+                            None => page.text.push_str(text),
+                        }
+                        page.text.push_str("\n\n");
+                    }
+                    if p.break_after {
+                        new_page(&mut pages);
+                    }
+                }
+            }
+            ("w:tbl", false) if !self_closing => table.get_or_insert_with(Table::default).depth += 1,
+            ("w:tbl", true) => {
+                if let Some(t) = table.as_mut() {
+                    t.depth -= 1;
+                }
+                if let Some(t) = table.take_if(|t| t.depth == 0) {
+                    if let Some(page) = pages.last_mut().filter(|_| t.rows > 0) {
+                        page.text.push('\n');
+                    }
+                    if t.break_after {
+                        new_page(&mut pages);
+                    }
+                }
+            }
+            ("w:tr", true) => {
+                if let (Some(t), Some(page)) = (table.as_mut().filter(|t| t.depth == 1), pages.last_mut()) {
+                    t.finish_row(page);
+                }
+            }
+            ("w:tc", false) if !self_closing => {
+                if let Some(t) = table.as_mut().filter(|t| t.depth == 1) {
+                    t.cell = Some(String::new());
+                }
+            }
+            ("w:tc", true) => {
+                if let Some(t) = table.as_mut().filter(|t| t.depth == 1) {
+                    let cell = t.cell.take().unwrap_or_default();
+                    t.row.push(cell);
+                }
+            }
+            ("w:pStyle", false) => {
+                if let (Some(p), Some(style)) = (paragraph.as_mut(), attribute(tag, "w:val")) {
+                    p.heading = p.heading.or_else(|| styles.get(style).copied()).or_else(|| heading_level(style));
+                }
+            }
+            ("w:outlineLvl", false) => {
+                let level = attribute(tag, "w:val").and_then(|v| v.parse::<usize>().ok()).filter(|l| *l < 6);
+                if let (Some(p), Some(level)) = (paragraph.as_mut(), level) {
+                    p.heading = Some(level + 1);
+                }
+            }
+            ("w:t", false) if !self_closing => in_text = true,
+            ("w:t", true) => in_text = false,
+            // 段落属性中的制表位定义带属性，文本中的制表符没有
+            ("w:tab", false) if tag == "w:tab/" => {
+                if let Some(p) = paragraph.as_mut() {
+                    p.text.push('\t');
+                }
+            }
+            ("w:br", false) if attribute(tag, "w:type") == Some("page") => {
+                page_break(paragraph.as_mut(), table.as_mut(), &mut pages)
+            }
+            ("w:br" | "w:cr", false) => {
+                if let Some(p) = paragraph.as_mut() {
+                    p.text.push('\n');
+                }
+            }
+            ("w:lastRenderedPageBreak", false) => page_break(paragraph.as_mut(), table.as_mut(), &mut pages),
+            ("a:blip", false) | ("v:imagedata", false) => {
+                let id = attribute(tag, "r:embed").or_else(|| attribute(tag, "r:id"));
+                if let (Some(page), Some(id)) = (pages.last_mut(), id) {
+                    page.images.push(id.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    if pages.len() > 1 && pages.last().is_some_and(Page::is_empty) {
+        pages.pop();
+    }
+    pages
+}
+
+/// 分页：表格内推迟到表格结束，段落已有文本时推迟到段落结束
+fn page_break(paragraph: Option<&mut Paragraph>, table: Option<&mut Table>, pages: &mut Vec<Page>) {
+    match (paragraph, table) {
+        (_, Some(table)) => table.break_after = true,
+        (Some(p), None) if !p.text.trim().is_empty() => p.break_after = true,
+        _ => new_page(pages),
+    }
+}
+
+/// 开始新的一页（当前页为空时沿用）
+fn new_page(pages: &mut Vec<Page>) {
+    if !pages.last().is_some_and(Page::is_empty) {
+        pages.push(Page::default());
+    }
+}
+
+/// 样式 ID 到标题级别：样式名为 `Title` / `heading N`，或样式带大纲级别
+fn heading_styles(xml: &str) -> HashMap<String, usize> {
+    let mut styles = HashMap::new();
+    for style in xml.split("<w:style ").skip(1) {
+        let style = style.split("</w:style>").next().unwrap_or_default();
+        let Some(id) = attribute(style, "w:styleId") else {
+            continue;
+        };
+        let name = style.find("<w:name ").and_then(|i| attribute(&style[i..], "w:val"));
+        let outline = style
+            .find("<w:outlineLvl ")
+            .and_then(|i| attribute(&style[i..], "w:val"))
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|l| *l < 6)
+            .map(|l| l + 1);
+        if let Some(level) = name.and_then(heading_level).or(outline) {
+            styles.insert(id.to_string(), level);
+        }
+    }
+    styles
+}
+
+/// `Title`、`heading 2`、`Heading2` 等样式名的标题级别
+fn heading_level(style: &str) -> Option<usize> {
+    let style = style.to_ascii_lowercase();
+    if style == "title" {
+        return Some(1);
+    }
+    let level = style.strip_prefix("heading")?.trim().parse::<usize>().ok()?;
+    (1..=6).contains(&level).then_some(level)
+}
+
+/// 关系 ID 到包内路径（跳过外部链接）
+fn relationships(xml: &str) -> HashMap<String, String> {
+    let mut relationships = HashMap::new();
+    for tag in xml.split("<Relationship ").skip(1) {
+        let tag = tag.split('>').next().unwrap_or_default();
+        if attribute(tag, "TargetMode") == Some("External") {
+            continue;
+        }
+        if let (Some(id), Some(target)) = (attribute(tag, "Id"), attribute(tag, "Target")) {
+            let path = match target.strip_prefix('/') {
+                Some(absolute) => absolute.to_string(),
+                None => format!("word/{}", target),
+            };
+            relationships.insert(id.to_string(), normalize(&path));
+        }
+    }
+    relationships
+}
+
+/// 处理路径中的 `..`
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn media_type(path: &str) -> Option<&'static str> {
+    let extension = path.rsplit('.').next()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "emf" => "image/emf",
+        "wmf" => "image/wmf",
+        _ => return None,
+    })
+}
+
+/// 标签中的属性值（不反转义）
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut from = 0;
+    while let Some(found) = tag[from..].find(name) {
+        let at = from + found;
+        from = at + name.len();
+        if tag[..at].chars().next_back().is_some_and(|c| !c.is_whitespace()) {
+            continue;
+        }
+        let Some(value) = tag[from..].trim_start().strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        if let Some(quote) = value.chars().next().filter(|q| *q == '"' || *q == '\'') {
+            return value[1..].split(quote).next();
+        }
+    }
+    None
+}
+
+/// XML 实体
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| char::from_u32(code.ok()?)),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// ZIP 中央目录条目
+struct Entry {
+    method: u16,
+    compressed: usize,
+    offset: usize,
+}
+
+/// 只读的 ZIP 包
+struct Archive<'a> {
+    data: &'a [u8],
+    entries: HashMap<String, Entry>,
+}
+
+impl<'a> Archive<'a> {
+    fn open(data: &'a [u8]) -> Result<Self> {
+        // 目录结尾记录在末尾的 22 字节（加最多 64KB 注释）之内
+        let floor = data.len().saturating_sub(22 + u16::MAX as usize);
+        let end = (floor..data.len().saturating_sub(21))
+            .rev()
+            .find(|i| data[*i..].starts_with(b"PK\x05\x06"))
+            .ok_or_else(|| malformed("DOCX", "not a ZIP archive"))?;
+        let count = u16_at(data, end + 10)? as usize;
+        let directory = u32_at(data, end + 16)?;
+        if directory == u32::MAX {
+            return Err(malformed("DOCX", "ZIP64 archives are not supported"));
+        }
+
+        let mut entries = HashMap::new();
+        let mut pos = directory as usize;
+        for _ in 0..count {
+            if !data.get(pos..).is_some_and(|d| d.starts_with(b"PK\x01\x02")) {
+                return Err(malformed("DOCX", "broken central directory"));
+            }
+            let name_len = u16_at(data, pos + 28)? as usize;
+            let extra_len = u16_at(data, pos + 30)? as usize;
+            let comment_len = u16_at(data, pos + 32)? as usize;
+            let name = data
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or_else(|| malformed("DOCX", "truncated entry name"))?;
+            entries.insert(
+                String::from_utf8_lossy(name).to_string(),
+                Entry {
+                    method: u16_at(data, pos + 10)?,
+                    compressed: u32_at(data, pos + 20)? as usize,
+                    offset: u32_at(data, pos + 42)? as usize,
+                },
+            );
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { data, entries })
+    }
+
+    /// 读取条目（不存在时为 `None`）
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.get(name) else {
+            return Ok(None);
+        };
+        let header = entry.offset;
+        if !self.data.get(header..).is_some_and(|d| d.starts_with(b"PK\x03\x04")) {
+            return Err(malformed("DOCX", format!("broken local header for {}", name)));
+        }
+        let start = header + 30 + u16_at(self.data, header + 26)? as usize + u16_at(self.data, header + 28)? as usize;
+        let raw = self
+            .data
+            .get(start..start + entry.compressed)
+            .ok_or_else(|| malformed("DOCX", format!("truncated entry {}", name)))?;
+        let mut out = Vec::new();
+        match entry.method {
+            0 => out.extend_from_slice(raw),
+            8 => {
+                DeflateDecoder::new(raw)
+                    .take(MAX_ENTRY_BYTES)
+                    .read_to_end(&mut out)
+                    .map_err(|e| malformed("DOCX", format!("{}: {}", name, e)))?;
+            }
+            method => return Err(malformed("DOCX", format!("unsupported compression method {} for {}", method, name))),
+        }
+        Ok(Some(out))
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Result<u16> {
+    let bytes = data.get(pos..pos + 2).ok_or_else(|| malformed("DOCX", "unexpected end of archive"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Result<u32> {
+    let bytes = data.get(pos..pos + 4).ok_or_else(|| malformed("DOCX", "unexpected end of archive"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    /// 以存储方式（不压缩）打包
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        pack(files, false)
+    }
+
+    /// 打包，`deflate` 时以 Word 默认的 Deflate 方式压缩
+    fn pack(files: &[(&str, &[u8])], deflate: bool) -> Vec<u8> {
+        let (mut out, mut directory) = (Vec::new(), Vec::new());
+        for (name, content) in files {
+            let offset = out.len() as u32;
+            let stored = if deflate {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                content.to_vec()
+            };
+            let mut header = Vec::new();
+            header.extend_from_slice(&[0, 0, if deflate { 8 } else { 0 }, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            header.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            header.extend_from_slice(&[0, 0]);
+            out.extend_from_slice(b"PK\x03\x04\x14\x00");
+            out.extend_from_slice(&header);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&stored);
+            directory.extend_from_slice(b"PK\x01\x02\x14\x00\x14\x00");
+            directory.extend_from_slice(&header);
+            directory.extend_from_slice(&[0; 10]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let start = out.len() as u32;
+        out.extend_from_slice(&directory);
+        out.extend_from_slice(b"PK\x05\x06\0\0\0\0");
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&start.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out
+    }
+
+    #[test]
+    fn test_extract_headings_pages_and_images() {
+        let styles = r#"<w:styles><w:style w:type="paragraph" w:styleId="1"><w:name w:val="heading 1"/></w:style>
+            <w:style w:type="paragraph" w:styleId="Quote"><w:name w:val="Quote"/></w:style></w:styles>"#;
+        let rels = r#"<Relationships><Relationship Id="rId4" Target="media/image1.png"/>
+            <Relationship Id="rId9" Target="https://example.com" TargetMode="External"/></Relationships>"#;
+        let body = r#"<w:document><w:body>
+            <w:p><w:pPr><w:pStyle w:val="1"/></w:pPr><w:r><w:t>安装</w:t></w:r></w:p>
+            <w:p><w:pPr><w:tabs><w:tab w:val="left" w:pos="720"/></w:tabs></w:pPr>
+              <w:r><w:t xml:space="preserve">Run </w:t></w:r>
+              <w:r><w:t>cargo &amp;&amp; make</w:t><w:tab/><w:t>now</w:t></w:r></w:p>
+            <w:p><w:r><w:drawing><a:blip r:embed="rId4"/></w:drawing></w:r></w:p>
+            <w:p><w:r><w:br w:type="page"/></w:r></w:p>
+            <w:p><w:r><w:lastRenderedPageBreak/><w:t>Usage</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Flags</w:t></w:r></w:p>
+        </w:body></w:document>"#;
+        let docx = zip(&[
+            ("word/document.xml", body.as_bytes()),
+            ("word/styles.xml", styles.as_bytes()),
+            ("word/_rels/document.xml.rels", rels.as_bytes()),
+            ("word/media/image1.png", b"\x89PNG"),
+        ]);
+
+        let document = extract(&docx).unwrap();
+        assert_eq!(document.pages.len(), 2);
+        assert_eq!(document.pages[0].text, "# 安装\n\nRun cargo && make\tnow");
+        assert_eq!(document.pages[0].images.len(), 1);
+        assert_eq!(document.pages[0].images[0].media_type, "image/png");
+        assert_eq!(document.pages[0].images[0].data, b"\x89PNG");
+        assert_eq!((document.pages[1].number, document.pages[1].text.as_str()), (2, "Usage\n\n## Flags"));
+        assert!(extract(b"plain text").is_err());
+    }
+
+    #[test]
+    fn test_extract_heading_levels_and_tables() {
+        let styles = r#"<w:styles>
+            <w:style w:type="paragraph" w:styleId="Title"><w:name w:val="Title"/></w:style>
+            <w:style w:type="paragraph" w:styleId="Custom"><w:name w:val="Chapter"/>
+              <w:pPr><w:outlineLvl w:val="1"/></w:pPr></w:style>
+            <w:style w:type="paragraph" w:styleId="Body"><w:name w:val="Body Text"/>
+              <w:pPr><w:outlineLvl w:val="9"/></w:pPr></w:style></w:styles>"#;
+        let cell = |text: &str| format!("<w:tc><w:tcPr/><w:p><w:r><w:t>{}</w:t></w:r></w:p></w:tc>", text);
+        let body = format!(
+            r#"<w:document><w:body>
+            <w:p><w:pPr><w:pStyle w:val="Title"/></w:pPr><w:r><w:t>Handbook</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="Custom"/></w:pPr><w:r><w:t>Limits</w:t></w:r></w:p>
+            <w:p><w:pPr><w:outlineLvl w:val="2"/></w:pPr><w:r><w:t>Memory</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="Body"/></w:pPr><w:r><w:t>Plain body.</w:t></w:r></w:p>
+            <w:tbl><w:tblPr/><w:tblGrid/>
+              <w:tr>{}{}</w:tr>
+              <w:tr>{}<w:tc><w:p><w:pPr><w:pStyle w:val="Title"/></w:pPr><w:r><w:t>64 MiB</w:t></w:r></w:p>
+                <w:p><w:r><w:t>a | b</w:t></w:r></w:p>
+                <w:tbl><w:tr><w:tc><w:p><w:r><w:t>nested</w:t></w:r></w:p></w:tc></w:tr></w:tbl></w:tc></w:tr>
+              <w:tr>{}<w:tc><w:p><w:r><w:lastRenderedPageBreak/><w:t>1</w:t></w:r></w:p></w:tc></w:tr>
+            </w:tbl>
+            <w:p><w:r><w:t>After the table.</w:t></w:r></w:p>
+            </w:body></w:document>"#,
+            cell("Limit"),
+            cell("Value"),
+            cell("memory"),
+            cell("cpu"),
+        );
+        // Word 保存的包用 Deflate 压缩
+        let docx = pack(&[("word/document.xml", body.as_bytes()), ("word/styles.xml", styles.as_bytes())], true);
+
+        let document = extract(&docx).unwrap();
+        assert_eq!(document.pages.len(), 2);
+        assert_eq!(
+            document.pages[0].text,
+            "# Handbook\n\n## Limits\n\n### Memory\n\nPlain body.\n\n\
+             | Limit | Value |\n| --- | --- |\n| memory | 64 MiB a \\| b nested |\n| cpu | 1 |"
+        );
+        // 表格内的分页推迟到表格之后，表格行不被拆开
+        assert_eq!(document.pages[1].text, "After the table.");
+    }
+
+    #[test]
+    fn test_truncated_or_corrupt_archives_fail_cleanly() {
+        let body = b"<w:document><w:body><w:p><w:r><w:t>Hello</w:t></w:r></w:p></w:body></w:document>";
+        let docx = pack(&[("word/document.xml", body)], true);
+        assert_eq!(extract(&docx).unwrap().pages[0].text, "Hello");
+        for len in 0..docx.len() {
+            assert!(extract(&docx[..len]).is_err(), "truncated to {} bytes", len);
+        }
+        assert!(extract(&pack(&[("word/other.xml", body)], false)).is_err());
+
+        // 条目数据损坏
+        let mut corrupt = docx.clone();
+        let data = 30 + "word/document.xml".len();
+        corrupt[data..data + 8].fill(0xff);
+        assert!(extract(&corrupt).is_err());
+    }
+}
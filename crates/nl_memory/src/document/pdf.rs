@@ -0,0 +1,1148 @@
+//! PDF 提取
+//!
+//! 顺序扫描 `N G obj` 得到全部对象（后出现的同号对象覆盖先出现的，对应增量更新），再展开压缩对象流；
+//! 不读取交叉引用表（也就不区分表与压缩的交叉引用流），截断的文件保留已扫描到的对象。
+//! 流的 `/Length` 为间接引用时先按 `endstream` 截取，扫描完成后再按解析出的长度重新截取。
+//! 页面文本来自内容流中的文本操作：`Tj` / `TJ` / `'` / `"` 输出字符串，`Td` / `TD` / `Tm` / `T*` 换行，
+//! 行距明显大于前一行时视为段落分隔；表单 XObject 递归解释。只解码 FlateDecode，其他编码的内容流跳过。
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use nl_core::Result;
+
+use super::{malformed, Document, DocumentImage, DocumentPage};
+
+/// 单个流解压后的字节上限
+const MAX_STREAM_BYTES: u64 = 64 << 20;
+
+/// 页树、引用链与表单 XObject 的嵌套深度上限
+const MAX_DEPTH: usize = 16;
+
+/// `TJ` 中超过该值（千分之一字号）的负间距视为空格
+const TJ_SPACE: f64 = 200.0;
+
+type Dict = BTreeMap<String, Object>;
+
+/// 页面字典与（可继承的）资源字典
+type PageRef<'a> = (&'a Dict, Option<&'a Dict>);
+
+/// PDF 对象（内容流中的操作符也作为对象出现）
+#[derive(Debug, Clone, PartialEq)]
+enum Object {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(Vec<u8>),
+    Name(String),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Stream(Dict, Vec<u8>),
+    Ref(u32),
+    Operator(String),
+}
+
+impl Object {
+    fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(dict) | Object::Stream(dict, _) => Some(dict),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// 提取 PDF
+pub fn extract(bytes: &[u8]) -> Result<Document> {
+    let head = &bytes[..bytes.len().min(1024)];
+    if find(head, b"%PDF-").is_none() {
+        return Err(malformed("PDF", "missing %PDF header"));
+    }
+    if find(bytes, b"/Encrypt").is_some() {
+        return Err(malformed("PDF", "encrypted documents are not supported"));
+    }
+    let pdf = Pdf::parse(bytes);
+    let pages = pdf.pages();
+    if pages.is_empty() {
+        return Err(malformed("PDF", "no pages found"));
+    }
+    let pages = pages
+        .into_iter()
+        .enumerate()
+        .map(|(i, (page, resources))| pdf.page(page, resources, i + 1))
+        .collect();
+    Ok(Document { pages })
+}
+
+struct Pdf {
+    objects: HashMap<u32, Object>,
+}
+
+impl Pdf {
+    fn parse(data: &[u8]) -> Self {
+        let mut objects = HashMap::new();
+        // 对象号 -> (长度对象号, 流数据位置)
+        let mut indirect_lengths: HashMap<u32, (u32, usize)> = HashMap::new();
+        let mut i = 0;
+        while let Some(found) = find(&data[i..], b"obj") {
+            let at = i + found;
+            i = at + 3;
+            if data.get(at + 3).is_some_and(|b| !is_whitespace(*b) && !is_delimiter(*b)) {
+                continue;
+            }
+            let Some(number) = object_number(data, at) else {
+                continue;
+            };
+            let mut lexer = Lexer::new(data, at + 3);
+            let Some(object) = lexer.next() else {
+                continue;
+            };
+            lexer.skip_whitespace();
+            indirect_lengths.remove(&number);
+            let object = match object {
+                Object::Dict(dict) if lexer.starts_with(b"stream") => {
+                    let (content, end) = stream_content(data, lexer.pos + 6, &dict);
+                    if let Some(Object::Ref(length)) = dict.get("Length") {
+                        indirect_lengths.insert(number, (*length, lexer.pos + 6));
+                    }
+                    i = end;
+                    Object::Stream(dict, content)
+                }
+                other => {
+                    i = i.max(lexer.pos);
+                    other
+                }
+            };
+            objects.insert(number, object);
+        }
+
+        // 流内容中可能出现 `endstream`，间接长度可用时以其为准
+        for (number, (length, pos)) in indirect_lengths {
+            let Some(length) = objects.get(&length).and_then(Object::as_number) else {
+                continue;
+            };
+            let Some(Object::Stream(dict, _)) = objects.get(&number) else {
+                continue;
+            };
+            let mut direct = dict.clone();
+            direct.insert("Length".to_string(), Object::Number(length));
+            let (content, _) = stream_content(data, pos, &direct);
+            objects.insert(number, Object::Stream(dict.clone(), content));
+        }
+
+        // 压缩对象流：头部为 N 对（对象号，相对 /First 的偏移）
+        let streams: Vec<(Vec<u8>, usize, usize)> = objects
+            .values()
+            .filter_map(|o| {
+                let Object::Stream(dict, content) = o else {
+                    return None;
+                };
+                if name(dict, "Type") != Some("ObjStm") {
+                    return None;
+                }
+                let count = dict.get("N").and_then(Object::as_number)? as usize;
+                let first = dict.get("First").and_then(Object::as_number)? as usize;
+                Some((decode(dict, content)?, count, first))
+            })
+            .collect();
+        for (data, count, first) in &streams {
+            let mut header = Lexer::new(data, 0);
+            for _ in 0..*count {
+                let (Some(Object::Number(number)), Some(Object::Number(offset))) = (header.next(), header.next()) else {
+                    break;
+                };
+                if let Some(object) = Lexer::new(data, first + offset as usize).next() {
+                    objects.entry(number as u32).or_insert(object);
+                }
+            }
+        }
+        Self { objects }
+    }
+
+    /// 解开引用
+    fn resolve<'a>(&'a self, object: &'a Object) -> Option<&'a Object> {
+        let mut object = object;
+        for _ in 0..MAX_DEPTH {
+            match object {
+                Object::Ref(number) => object = self.objects.get(number)?,
+                _ => return Some(object),
+            }
+        }
+        None
+    }
+
+    fn get<'a>(&'a self, dict: &'a Dict, key: &str) -> Option<&'a Object> {
+        self.resolve(dict.get(key)?)
+    }
+
+    fn get_dict<'a>(&'a self, dict: &'a Dict, key: &str) -> Option<&'a Dict> {
+        self.get(dict, key)?.as_dict()
+    }
+
+    /// 按页树顺序列出页面及其（可继承的）资源字典；找不到目录时按对象号排列全部页面
+    fn pages(&self) -> Vec<PageRef<'_>> {
+        let mut pages = Vec::new();
+        let catalog = self.objects.values().filter_map(Object::as_dict).find(|d| name(d, "Type") == Some("Catalog"));
+        if let Some(tree) = catalog.and_then(|c| self.get_dict(c, "Pages")) {
+            self.walk(tree, None, 0, &mut pages);
+        }
+        if pages.is_empty() {
+            let mut loose: Vec<(u32, &Dict)> = self
+                .objects
+                .iter()
+                .filter_map(|(n, o)| Some((*n, o.as_dict().filter(|d| name(d, "Type") == Some("Page"))?)))
+                .collect();
+            loose.sort_by_key(|(n, _)| *n);
+            pages.extend(loose.into_iter().map(|(_, page)| (page, self.get_dict(page, "Resources"))));
+        }
+        pages
+    }
+
+    fn walk<'a>(&'a self, node: &'a Dict, inherited: Option<&'a Dict>, depth: usize, pages: &mut Vec<PageRef<'a>>) {
+        let resources = self.get_dict(node, "Resources").or(inherited);
+        match self.get(node, "Kids") {
+            Some(Object::Array(kids)) if depth < MAX_DEPTH => {
+                for kid in kids.iter().filter_map(|k| self.resolve(k)?.as_dict()) {
+                    self.walk(kid, resources, depth + 1, pages);
+                }
+            }
+            Some(_) => {}
+            None => pages.push((node, resources)),
+        }
+    }
+
+    fn page(&self, page: &Dict, resources: Option<&Dict>, number: usize) -> DocumentPage {
+        let mut content = Vec::new();
+        let parts: Vec<&Object> = match self.get(page, "Contents") {
+            Some(Object::Array(parts)) => parts.iter().filter_map(|p| self.resolve(p)).collect(),
+            Some(stream) => vec![stream],
+            None => Vec::new(),
+        };
+        for part in parts {
+            if let Some(data) = stream_data(part) {
+                content.extend(data);
+                content.push(b'\n');
+            }
+        }
+        let mut output = Output::default();
+        self.run(&content, resources, 0, &mut output);
+        DocumentPage {
+            number,
+            text: output.finish(),
+            images: output.images,
+        }
+    }
+
+    /// 解释内容流
+    fn run(&self, content: &[u8], resources: Option<&Dict>, depth: usize, output: &mut Output) {
+        let fonts = resources.and_then(|r| self.get_dict(r, "Font"));
+        let xobjects = resources.and_then(|r| self.get_dict(r, "XObject"));
+        let mut cache: HashMap<String, Font> = HashMap::new();
+        let mut font: Option<String> = None;
+        // 当前行在文本空间中的纵坐标与上一行的纵坐标（跨 BT 保留，用于判断换行与分段）
+        let (mut line_y, mut last_y) = (0.0, None);
+        let mut operands: Vec<Object> = Vec::new();
+        let mut lexer = Lexer::new(content, 0);
+        while let Some(object) = lexer.next_token() {
+            let Object::Operator(operator) = object else {
+                operands.push(object);
+                continue;
+            };
+            let number = |i: usize| operands.get(i).and_then(Object::as_number);
+            let current = font.as_ref().and_then(|f| cache.get(f));
+            match operator.as_str() {
+                "Tf" => {
+                    if let Some(Object::Name(name)) = operands.first() {
+                        if !cache.contains_key(name) {
+                            cache.insert(name.clone(), self.font(fonts.and_then(|f| self.get_dict(f, name))));
+                        }
+                        font = Some(name.clone());
+                    }
+                }
+                "Tj" | "'" | "\"" => {
+                    if operator != "Tj" {
+                        output.newline();
+                    }
+                    if let Some(Object::String(s)) = operands.last() {
+                        output.push(&decode_text(current, s));
+                    }
+                }
+                "TJ" => {
+                    if let Some(Object::Array(items)) = operands.last() {
+                        for item in items {
+                            match item {
+                                Object::String(s) => output.push(&decode_text(current, s)),
+                                Object::Number(n) if *n < -TJ_SPACE => output.space(),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                "Td" | "TD" | "Tm" => {
+                    let y = match (operator.as_str(), number(0), number(1)) {
+                        ("Tm", _, _) => number(5),
+                        (_, _, Some(ty)) if ty != 0.0 => Some(line_y + ty),
+                        (_, Some(tx), _) if tx != 0.0 => {
+                            output.space();
+                            None
+                        }
+                        _ => None,
+                    };
+                    if let Some(y) = y {
+                        if let Some(last) = last_y.filter(|last| *last != y) {
+                            output.advance(y - last);
+                        }
+                        (line_y, last_y) = (y, Some(y));
+                    }
+                }
+                "T*" => output.newline(),
+                "BT" => line_y = 0.0,
+                "ID" => lexer.skip_inline_image(),
+                "Do" => {
+                    if let Some(Object::Name(name)) = operands.first() {
+                        self.xobject(xobjects, name, resources, depth, output);
+                    }
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+    }
+
+    /// 绘制 XObject：图片按原始编码收集（同一图片对象只收一次），表单递归解释
+    fn xobject(&self, xobjects: Option<&Dict>, key: &str, resources: Option<&Dict>, depth: usize, output: &mut Output) {
+        let Some(reference) = xobjects.and_then(|x| x.get(key)) else {
+            return;
+        };
+        let Some(Object::Stream(dict, content)) = self.resolve(reference) else {
+            return;
+        };
+        match name(dict, "Subtype") {
+            Some("Image") => {
+                if let Object::Ref(number) = reference {
+                    if !output.seen.insert(*number) {
+                        return;
+                    }
+                }
+                let filter = match dict.get("Filter") {
+                    Some(Object::Array(filters)) if filters.len() == 1 => filters[0].as_name(),
+                    Some(filter) => filter.as_name(),
+                    None => None,
+                };
+                let media_type = match filter {
+                    Some("DCTDecode") => "image/jpeg",
+                    Some("JPXDecode") => "image/jp2",
+                    // 其他编码是原始位图，需要重新编码才能查看
+                    _ => return,
+                };
+                output.images.push(DocumentImage {
+                    media_type: media_type.to_string(),
+                    data: content.clone(),
+                });
+            }
+            Some("Form") if depth < MAX_DEPTH => {
+                if let Some(data) = decode(dict, content) {
+                    let form_resources = self.get_dict(dict, "Resources").or(resources);
+                    self.run(&data, form_resources, depth + 1, output);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn font(&self, dict: Option<&Dict>) -> Font {
+        let Some(dict) = dict else {
+            return Font::default();
+        };
+        Font {
+            cmap: self.get(dict, "ToUnicode").and_then(stream_data).map(|data| CMap::parse(&data)),
+            composite: name(dict, "Subtype") == Some("Type0"),
+        }
+    }
+}
+
+/// 页面文本与图片
+#[derive(Default)]
+struct Output {
+    text: String,
+    images: Vec<DocumentImage>,
+    /// 已收集的图片对象
+    seen: HashSet<u32>,
+    /// 最近的行距
+    leading: f64,
+}
+
+impl Output {
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    fn space(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with([' ', '\n']) {
+            self.text.push(' ');
+        }
+    }
+
+    fn newline(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push('\n');
+        }
+    }
+
+    /// 换到新的一行：行距明显大于前一行时视为段落分隔
+    fn advance(&mut self, dy: f64) {
+        let dy = dy.abs();
+        if self.leading > 0.0 && dy > self.leading * 1.5 {
+            self.newline();
+            if !self.text.is_empty() && !self.text.ends_with("\n\n") {
+                self.text.push('\n');
+            }
+        } else {
+            self.newline();
+            self.leading = dy;
+        }
+    }
+
+    /// 去掉行尾空白与多余空行
+    fn finish(&self) -> String {
+        let mut text = String::new();
+        for line in self.text.lines().map(str::trim_end) {
+            if line.is_empty() && (text.is_empty() || text.ends_with("\n\n")) {
+                continue;
+            }
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.trim_end().to_string()
+    }
+}
+
+/// 字体的文本解码方式
+#[derive(Default)]
+struct Font {
+    cmap: Option<CMap>,
+    /// 复合字体（多字节编码），没有 ToUnicode 时无法还原文本
+    composite: bool,
+}
+
+fn decode_text(font: Option<&Font>, bytes: &[u8]) -> String {
+    if let Some(cmap) = font.and_then(|f| f.cmap.as_ref()) {
+        return cmap.decode(bytes);
+    }
+    if font.is_some_and(|f| f.composite) {
+        return String::new();
+    }
+    bytes.iter().filter_map(|b| single_byte(*b)).collect()
+}
+
+/// 单字节编码（按 WinAnsi 近似）
+fn single_byte(b: u8) -> Option<char> {
+    match b {
+        b'\t' => Some(' '),
+        0x20..=0x7e | 0xa0..=0xff => Some(b as char),
+        0x85 => Some('…'),
+        0x91 => Some('‘'),
+        0x92 => Some('’'),
+        0x93 => Some('“'),
+        0x94 => Some('”'),
+        0x95 => Some('•'),
+        0x96 => Some('–'),
+        0x97 => Some('—'),
+        _ => None,
+    }
+}
+
+/// ToUnicode CMap（`bfchar` 与 `bfrange`）
+#[derive(Debug, Default)]
+struct CMap {
+    chars: HashMap<(usize, u32), String>,
+    ranges: Vec<(usize, u32, u32, RangeTarget)>,
+    /// 编码长度（字节，从长到短）
+    lengths: Vec<usize>,
+}
+
+#[derive(Debug)]
+enum RangeTarget {
+    /// 起始码位，区间内依次递增末位
+    Base(Vec<u16>),
+    List(Vec<String>),
+}
+
+impl CMap {
+    fn parse(data: &[u8]) -> Self {
+        let mut cmap = CMap::default();
+        let mut operands = Vec::new();
+        let mut lexer = Lexer::new(data, 0);
+        while let Some(object) = lexer.next_token() {
+            match object {
+                Object::Operator(op) if op == "endbfchar" => {
+                    for pair in operands.chunks(2) {
+                        if let [Object::String(source), Object::String(target)] = pair {
+                            cmap.chars.insert((source.len(), code(source)), utf16(target));
+                        }
+                    }
+                    operands.clear();
+                }
+                Object::Operator(op) if op == "endbfrange" => {
+                    for triple in operands.chunks(3) {
+                        let [Object::String(low), Object::String(high), target] = triple else {
+                            continue;
+                        };
+                        let target = match target {
+                            Object::String(base) => RangeTarget::Base(units(base)),
+                            Object::Array(items) => RangeTarget::List(
+                                items
+                                    .iter()
+                                    .map(|i| match i {
+                                        Object::String(s) => utf16(s),
+                                        _ => String::new(),
+                                    })
+                                    .collect(),
+                            ),
+                            _ => continue,
+                        };
+                        cmap.ranges.push((low.len(), code(low), code(high), target));
+                    }
+                    operands.clear();
+                }
+                Object::Operator(_) => operands.clear(),
+                other => operands.push(other),
+            }
+        }
+        let mut lengths: Vec<usize> = cmap.chars.keys().map(|(len, _)| *len).collect();
+        lengths.extend(cmap.ranges.iter().map(|r| r.0));
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+        lengths.dedup();
+        cmap.lengths = if lengths.is_empty() { vec![1] } else { lengths };
+        cmap
+    }
+
+    fn lookup(&self, len: usize, code: u32) -> Option<String> {
+        if let Some(text) = self.chars.get(&(len, code)) {
+            return Some(text.clone());
+        }
+        let (_, low, _, target) =
+            self.ranges.iter().find(|(l, low, high, _)| *l == len && (*low..=*high).contains(&code))?;
+        let offset = code - low;
+        match target {
+            RangeTarget::Base(base) => {
+                let mut units = base.clone();
+                if let Some(last) = units.last_mut() {
+                    *last = last.wrapping_add(offset as u16);
+                }
+                Some(String::from_utf16_lossy(&units))
+            }
+            RangeTarget::List(items) => items.get(offset as usize).cloned(),
+        }
+    }
+
+    /// 按最长匹配解码；没有映射的编码按最长编码长度跳过
+    fn decode(&self, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let found = self.lengths.iter().find_map(|len| {
+                let source = bytes.get(i..i + len)?;
+                self.lookup(*len, code(source)).map(|t| (*len, t))
+            });
+            match found {
+                Some((len, t)) => {
+                    text.extend(t.chars().filter(|c| *c != '\0'));
+                    i += len;
+                }
+                None => i += self.lengths[0],
+            }
+        }
+        text
+    }
+}
+
+fn code(bytes: &[u8]) -> u32 {
+    bytes.iter().take(4).fold(0, |acc, b| acc << 8 | *b as u32)
+}
+
+fn units(bytes: &[u8]) -> Vec<u16> {
+    bytes.chunks(2).map(|p| (p[0] as u16) << 8 | p.get(1).copied().unwrap_or(0) as u16).collect()
+}
+
+fn utf16(bytes: &[u8]) -> String {
+    String::from_utf16_lossy(&units(bytes))
+}
+
+fn name<'a>(dict: &'a Dict, key: &str) -> Option<&'a str> {
+    dict.get(key)?.as_name()
+}
+
+/// 解码后的流数据
+fn stream_data(object: &Object) -> Option<Vec<u8>> {
+    match object {
+        Object::Stream(dict, content) => decode(dict, content),
+        _ => None,
+    }
+}
+
+/// 按过滤器解码（只支持 FlateDecode）
+fn decode(dict: &Dict, content: &[u8]) -> Option<Vec<u8>> {
+    let filters: Vec<&str> = match dict.get("Filter") {
+        None => Vec::new(),
+        Some(Object::Name(filter)) => vec![filter],
+        Some(Object::Array(filters)) => filters.iter().filter_map(Object::as_name).collect(),
+        Some(_) => return None,
+    };
+    let mut data = content.to_vec();
+    for filter in filters {
+        match filter {
+            "FlateDecode" | "Fl" => data = inflate(&data)?,
+            _ => return None,
+        }
+    }
+    Some(data)
+}
+
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    match ZlibDecoder::new(data).take(MAX_STREAM_BYTES).read_to_end(&mut out) {
+        Ok(_) => Some(out),
+        // 流末尾损坏时保留已解出的部分
+        Err(_) if !out.is_empty() => Some(out),
+        Err(_) => None,
+    }
+}
+
+/// 流内容与 `endstream` 之后的位置；`/Length` 不可用（如为间接引用）时查找 `endstream`
+fn stream_content(data: &[u8], pos: usize, dict: &Dict) -> (Vec<u8>, usize) {
+    let mut start = pos;
+    if data.get(start) == Some(&b'\r') {
+        start += 1;
+    }
+    if data.get(start) == Some(&b'\n') {
+        start += 1;
+    }
+    if let Some(length) = dict.get("Length").and_then(Object::as_number).filter(|l| *l >= 0.0) {
+        let end = start + length as usize;
+        let mut lexer = Lexer::new(data, end);
+        lexer.skip_whitespace();
+        if end <= data.len() && lexer.starts_with(b"endstream") {
+            return (data[start..end].to_vec(), lexer.pos + 9);
+        }
+    }
+    let Some(offset) = data.get(start..).and_then(|rest| find(rest, b"endstream")) else {
+        return (data.get(start..).unwrap_or_default().to_vec(), data.len());
+    };
+    let mut end = start + offset;
+    if end > start && data[end - 1] == b'\n' {
+        end -= 1;
+    }
+    if end > start && data[end - 1] == b'\r' {
+        end -= 1;
+    }
+    (data[start..end].to_vec(), start + offset + 9)
+}
+
+/// `obj` 关键字前的 `N G`，返回对象号
+fn object_number(data: &[u8], at: usize) -> Option<u32> {
+    let mut pos = at;
+    skip_back_whitespace(data, &mut pos)?;
+    digits_back(data, &mut pos)?;
+    skip_back_whitespace(data, &mut pos)?;
+    let number = digits_back(data, &mut pos)?;
+    if pos > 0 && !is_whitespace(data[pos - 1]) && !is_delimiter(data[pos - 1]) {
+        return None;
+    }
+    std::str::from_utf8(number).ok()?.parse().ok()
+}
+
+fn skip_back_whitespace(data: &[u8], pos: &mut usize) -> Option<()> {
+    let end = *pos;
+    while *pos > 0 && is_whitespace(data[*pos - 1]) {
+        *pos -= 1;
+    }
+    (*pos < end).then_some(())
+}
+
+fn digits_back<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    let end = *pos;
+    while *pos > 0 && data[*pos - 1].is_ascii_digit() {
+        *pos -= 1;
+    }
+    (*pos < end).then(|| &data[*pos..end])
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(b, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+/// 对象与内容流的词法分析
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.data.get(self.pos..).is_some_and(|rest| rest.starts_with(prefix))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek() {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while self.peek().is_some_and(|b| b != b'\n' && b != b'\r') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn word(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| !is_whitespace(b) && !is_delimiter(b)) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    /// 下一个对象，跳过不成对的 `]`、`>`
+    fn next_token(&mut self) -> Option<Object> {
+        loop {
+            match self.next() {
+                Some(object) => return Some(object),
+                None if self.pos < self.data.len() => self.pos += 1,
+                None => return None,
+            }
+        }
+    }
+
+    /// 下一个对象；到达末尾或遇到 `]`、`>` 时为 `None`
+    fn next(&mut self) -> Option<Object> {
+        self.skip_whitespace();
+        let b = self.peek()?;
+        match b {
+            b'/' => {
+                self.pos += 1;
+                Some(Object::Name(decode_name(self.word())))
+            }
+            b'(' => {
+                self.pos += 1;
+                Some(Object::String(self.literal()))
+            }
+            b'<' if self.starts_with(b"<<") => {
+                self.pos += 2;
+                Some(Object::Dict(self.dict()))
+            }
+            b'<' => {
+                self.pos += 1;
+                Some(Object::String(self.hex()))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while let Some(item) = self.next() {
+                    items.push(item);
+                }
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                }
+                Some(Object::Array(items))
+            }
+            b']' | b'>' => None,
+            b')' | b'{' | b'}' => {
+                self.pos += 1;
+                Some(Object::Operator((b as char).to_string()))
+            }
+            _ => {
+                let word = self.word();
+                let text = String::from_utf8_lossy(word);
+                match text.as_ref() {
+                    "true" => Some(Object::Bool(true)),
+                    "false" => Some(Object::Bool(false)),
+                    "null" => Some(Object::Null),
+                    _ if word[0].is_ascii_digit() || matches!(word[0], b'+' | b'-' | b'.') => {
+                        match text.parse::<f64>() {
+                            Ok(n) if word.iter().all(u8::is_ascii_digit) => {
+                                Some(self.reference(n).unwrap_or(Object::Number(n)))
+                            }
+                            Ok(n) => Some(Object::Number(n)),
+                            Err(_) => Some(Object::Operator(text.to_string())),
+                        }
+                    }
+                    _ => Some(Object::Operator(text.to_string())),
+                }
+            }
+        }
+    }
+
+    /// 整数之后是 `G R` 时为间接引用
+    fn reference(&mut self, number: f64) -> Option<Object> {
+        let save = self.pos;
+        self.skip_whitespace();
+        let generation = self.word();
+        if !generation.is_empty() && generation.iter().all(u8::is_ascii_digit) {
+            self.skip_whitespace();
+            if self.word() == b"R" {
+                return Some(Object::Ref(number as u32));
+            }
+        }
+        self.pos = save;
+        None
+    }
+
+    fn dict(&mut self) -> Dict {
+        let mut dict = Dict::new();
+        loop {
+            self.skip_whitespace();
+            if self.starts_with(b">>") {
+                self.pos += 2;
+                break;
+            }
+            match self.next() {
+                Some(Object::Name(key)) => {
+                    let value = self.next().unwrap_or(Object::Null);
+                    dict.insert(key, value);
+                }
+                Some(_) => {}
+                None if self.pos >= self.data.len() => break,
+                None => self.pos += 1,
+            }
+        }
+        dict
+    }
+
+    fn literal(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut depth = 0;
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(escaped) = self.peek() else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0c),
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        // 行尾续行
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(b),
+            }
+        }
+        out
+    }
+
+    fn hex(&mut self) -> Vec<u8> {
+        let mut digits = Vec::new();
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            if b == b'>' {
+                break;
+            }
+            if let Some(d) = (b as char).to_digit(16) {
+                digits.push(d as u8);
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        digits.chunks(2).map(|p| p[0] << 4 | p[1]).collect()
+    }
+
+    /// 跳过内联图片数据（`ID` 之后直到独立的 `EI`）
+    fn skip_inline_image(&mut self) {
+        let mut i = self.pos + 1;
+        while i + 2 <= self.data.len() {
+            if &self.data[i..i + 2] == b"EI"
+                && is_whitespace(self.data[i - 1])
+                && self.data.get(i + 2).is_none_or(|b| is_whitespace(*b))
+            {
+                self.pos = i + 2;
+                return;
+            }
+            i += 1;
+        }
+        self.pos = self.data.len();
+    }
+}
+
+/// 名称中的 `#xx` 转义
+fn decode_name(raw: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        let escaped = (raw[i] == b'#')
+            .then(|| raw.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(b) => {
+                bytes.push(b);
+                i += 3;
+            }
+            None => {
+                bytes.push(raw[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn object(number: u32, body: &str) -> Vec<u8> {
+        format!("{} 0 obj\n{}\nendobj\n", number, body).into_bytes()
+    }
+
+    fn stream(number: u32, dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut out = format!("{} 0 obj\n<< {} /Length {} >>\nstream\n", number, dict, data.len()).into_bytes();
+        out.extend_from_slice(data);
+        out.extend_from_slice(b"\nendstream\nendobj\n");
+        out
+    }
+
+    #[test]
+    fn test_extract_pages_text_and_images() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(b"BT /F1 12 Tf 72 700 Td (Hello, \\(PDF\\)) Tj 0 -14 Td [(Sec) -250 (ond)] TJ ET\n")
+            .unwrap();
+        encoder
+            .write_all(b"BT 72 646 Td (Next) Tj ET")
+            .unwrap();
+        let first = encoder.finish().unwrap();
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+        pdf.extend_from_slice(b"2 0 obj\n<< /Type /Pages /Kids [4 0 R 3 0 R] /Count 2\n");
+        pdf.extend_from_slice(b"/Resources << /Font << /F1 5 0 R >> >> >>\nendobj\n");
+        pdf.extend_from_slice(b"3 0 obj\n<< /Type /Page /Parent 2 0 R /Contents 8 0 R\n");
+        pdf.extend_from_slice(b"/Resources << /Font << /F2 6 0 R >> /XObject << /Im1 10 0 R >> >> >>\nendobj\n");
+        pdf.extend_from_slice(b"4 0 obj\n<< /Type /Page /Parent 2 0 R /Contents [7 0 R] >>\nendobj\n");
+        pdf.extend_from_slice(b"5 0 obj\n<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>\nendobj\n");
+        pdf.extend_from_slice(b"6 0 obj\n<< /Type /Font /Subtype /Type0 /ToUnicode 9 0 R >>\nendobj\n");
+        pdf.extend(stream(7, "/Filter /FlateDecode", &first));
+        pdf.extend(stream(8, "", b"BT /F2 10 Tf 1 0 0 1 72 700 Tm <00010002> Tj ET q /Im1 Do /Im1 Do Q"));
+        let cmap = "/CIDInit /ProcSet findresource begin 1 begincodespacerange <0000> <FFFF> endcodespacerange\n\
+                    1 beginbfchar <0001> <4E2D> endbfchar 1 beginbfrange <0002> <0003> <6587> endbfrange end";
+        pdf.extend(stream(9, "", cmap.as_bytes()));
+        pdf.extend(stream(10, "/Type /XObject /Subtype /Image /Filter /DCTDecode", b"\xff\xd8\xff\xd9"));
+        pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R >>\n%%EOF\n");
+
+        let document = extract(&pdf).unwrap();
+        assert_eq!(document.pages.len(), 2);
+        assert_eq!(document.pages[0].text, "Hello, (PDF)\nSec ond\n\nNext");
+        assert!(document.pages[0].images.is_empty());
+        assert_eq!((document.pages[1].number, document.pages[1].text.as_str()), (2, "中文"));
+        assert_eq!(document.pages[1].images.len(), 1);
+        assert_eq!(document.pages[1].images[0].media_type, "image/jpeg");
+
+        assert!(extract(b"not a pdf").is_err());
+    }
+
+    #[test]
+    fn test_compressed_xref_stream_and_object_stream() {
+        // PDF 1.5 起常见的布局：页树与字体都在压缩对象流中，交叉引用表是压缩的 XRef 流，没有 trailer
+        let packed = [
+            (1, "<< /Type /Catalog /Pages 2 0 R >>"),
+            (2, "<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+            (3, "<< /Type /Page /Parent 2 0 R /Contents 5 0 R /Resources << /Font << /F1 4 0 R >> >> >>"),
+            (4, "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>"),
+        ];
+        let (mut header, mut body) = (String::new(), String::new());
+        for (number, object) in packed {
+            header.push_str(&format!("{} {} ", number, body.len()));
+            body.push_str(object);
+            body.push('\n');
+        }
+        let objstm = deflate(format!("{}{}", header, body).as_bytes());
+        let dict = format!("/Type /ObjStm /N 4 /First {} /Filter /FlateDecode", header.len());
+
+        let mut pdf = b"%PDF-1.5\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let content_at = pdf.len();
+        pdf.extend(stream(5, "/Filter /FlateDecode", &deflate(b"BT /F1 12 Tf 72 700 Td (Packed objects) Tj ET")));
+        let objstm_at = pdf.len();
+        pdf.extend(stream(6, &dict, &objstm));
+        let xref_at = pdf.len();
+        let mut xref = vec![0, 0, 0, 255];
+        for index in 0..4u8 {
+            xref.extend_from_slice(&[2, 0, 6, index]);
+        }
+        for offset in [content_at, objstm_at, xref_at] {
+            xref.extend_from_slice(&[1, (offset >> 8) as u8, offset as u8, 0]);
+        }
+        pdf.extend(stream(7, "/Type /XRef /Size 8 /W [1 2 1] /Root 1 0 R /Filter /FlateDecode", &deflate(&xref)));
+        pdf.extend_from_slice(format!("startxref\n{}\n%%EOF\n", xref_at).as_bytes());
+
+        let document = extract(&pdf).unwrap();
+        assert_eq!(document.pages.len(), 1);
+        assert_eq!(document.pages[0].text, "Packed objects");
+
+        // 增量更新追加的同号对象覆盖原对象
+        pdf.extend(stream(5, "/Filter /FlateDecode", &deflate(b"BT /F1 12 Tf 72 700 Td (Revised) Tj ET")));
+        pdf.extend(stream(8, "/Type /XRef /Size 9 /W [1 2 1] /Root 1 0 R /Prev 0", &[1, 0, 0, 0]));
+        pdf.extend_from_slice(b"startxref\n0\n%%EOF\n");
+        assert_eq!(extract(&pdf).unwrap().pages[0].text, "Revised");
+    }
+
+    #[test]
+    fn test_indirect_stream_length() {
+        let first = b"BT /F1 12 Tf 72 700 Td (see endstream docs) Tj ET";
+        let second = deflate(b"BT /F1 12 Tf 72 700 Td (Fallback) Tj ET");
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        pdf.extend(object(1, "<< /Type /Catalog /Pages 2 0 R >>"));
+        pdf.extend(object(2, "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>"));
+        pdf.extend(object(3, "<< /Type /Page /Parent 2 0 R /Contents 5 0 R >>"));
+        pdf.extend(object(4, "<< /Type /Page /Parent 2 0 R /Contents 6 0 R >>"));
+        // 长度对象在流之后定义，流内容中出现 `endstream`
+        pdf.extend_from_slice(b"5 0 obj\n<< /Length 9 0 R >>\nstream\n");
+        pdf.extend_from_slice(first);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n");
+        // 长度对象不存在时按 `endstream` 截取
+        pdf.extend_from_slice(b"6 0 obj\n<< /Length 99 0 R /Filter /FlateDecode >>\nstream\r\n");
+        pdf.extend_from_slice(&second);
+        pdf.extend_from_slice(b"\r\nendstream\nendobj\n");
+        pdf.extend(object(9, &first.len().to_string()));
+        pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R >>\n%%EOF\n");
+
+        let document = extract(&pdf).unwrap();
+        assert_eq!(document.pages[0].text, "see endstream docs");
+        assert_eq!(document.pages[1].text, "Fallback");
+    }
+
+    #[test]
+    fn test_to_unicode_cmap() {
+        let cmap = CMap::parse(
+            b"/CIDInit /ProcSet findresource begin 12 dict begin begincmap\n\
+              2 begincodespacerange <00> <7F> <8000> <FFFF> endcodespacerange\n\
+              3 beginbfchar <01> <0041> <8001> <00660069> <8002> <D83DDE00> endbfchar\n\
+              2 beginbfrange <20> <22> <0061> <8010> <8011> [<4E2D> <6587>] endbfrange\n\
+              endcmap CMapName currentdict /CMap defineresource pop end end",
+        );
+        // 单字节与双字节编码混用、连字映射为多个字符、代理对、数组形式的区间
+        let bytes = [0x01, 0x80, 0x01, 0x20, 0x22, 0x80, 0x02, 0x80, 0x11, 0x80, 0x10];
+        assert_eq!(cmap.decode(&bytes), "Afiac😀文中");
+        // 没有映射的编码被跳过
+        assert_eq!(cmap.decode(&[0x7f, 0x7e, 0x01]), "A");
+        assert_eq!(cmap.decode(&[0x01, 0x7f]), "A");
+
+        let font = Font { cmap: Some(cmap), composite: true };
+        assert_eq!(decode_text(Some(&font), &[0x20, 0x21]), "ab");
+        // 没有 ToUnicode 的复合字体无法还原文本，单字节字体按 WinAnsi 解码
+        let composite = Font { cmap: None, composite: true };
+        assert_eq!(decode_text(Some(&composite), b"\x00\x21"), "");
+        assert_eq!(decode_text(None, b"\x93caf\xe9\x94\x01"), "“café”");
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let lines: String = (1..=40).map(|i| format!("0 -14 Td (Line {}) Tj\n", i)).collect();
+        let second = deflate(format!("BT 72 700 Td {} ET", lines).as_bytes());
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        pdf.extend(object(1, "<< /Type /Catalog /Pages 2 0 R >>"));
+        pdf.extend(object(2, "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>"));
+        pdf.extend(object(3, "<< /Type /Page /Parent 2 0 R /Contents 5 0 R >>"));
+        pdf.extend(object(4, "<< /Type /Page /Parent 2 0 R /Contents 6 0 R >>"));
+        pdf.extend(stream(5, "", b"BT 72 700 Td (First page) Tj ET"));
+        let second_at = pdf.len();
+        pdf.extend(stream(6, "/Filter /FlateDecode", &second));
+        pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R >>\n%%EOF\n");
+        let full = extract(&pdf).unwrap().pages[1].text.clone();
+        assert!(full.starts_with("Line 1\nLine 2\n") && full.ends_with("Line 40"), "{}", full);
+
+        // 任意位置截断都不会 panic；页树完整时保留已有的页面
+        for len in 0..pdf.len() {
+            let Ok(document) = extract(&pdf[..len]) else {
+                continue;
+            };
+            assert!(document.pages.iter().all(|page| full.starts_with(&page.text) || page.text == "First page"));
+        }
+
+        // 截断在第二页的压缩流中间：第一页完整，第二页保留已解压出的前几行
+        let document = extract(&pdf[..second_at + 60 + second.len() / 2]).unwrap();
+        assert_eq!(document.pages[0].text, "First page");
+        let partial = &document.pages[1].text;
+        assert!(partial.starts_with("Line 1") && partial.len() < full.len(), "{}", partial);
+        assert!(full.starts_with(partial.as_str()));
+
+        // 页树之前就截断时没有页面
+        assert!(extract(&pdf[..second_at / 3]).is_err());
+    }
+}
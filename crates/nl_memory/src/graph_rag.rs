@@ -7,6 +7,8 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use nl_durable::{ConflictPolicy, ImportStats};
+
 /// 图节点类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum NodeType {
@@ -20,6 +22,8 @@ pub enum NodeType {
     Module,
     /// 依赖
     Dependency,
+    /// 笔记（知识库导入）
+    Note,
 }
 
 /// 图节点
@@ -59,6 +63,8 @@ pub enum EdgeType {
     Defines,
     /// 依赖关系
     DependsOn,
+    /// 引用关系（笔记间的维基链接）
+    References,
 }
 
 /// 图边
@@ -72,6 +78,13 @@ pub struct GraphEdge {
     pub edge_type: EdgeType,
 }
 
+/// 图快照（工作区包中的 `graph` 分区）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
 /// GraphRAG 图数据库
 pub struct GraphRAG {
     /// 节点集合
@@ -109,6 +122,13 @@ impl GraphRAG {
         self.edges.push(edge);
     }
 
+    /// 移除节点的所有出边，返回移除的数量
+    pub fn remove_outgoing_edges(&mut self, node_id: &Uuid) -> usize {
+        let before = self.edges.len();
+        self.edges.retain(|e| &e.source != node_id);
+        before - self.edges.len()
+    }
+
     /// 通过名称查找节点
     pub fn find_by_name(&self, name: &str) -> Option<&GraphNode> {
         self.name_index.get(name).and_then(|id| self.nodes.get(id))
@@ -153,6 +173,28 @@ impl GraphRAG {
             .collect()
     }
 
+    /// 遍历所有节点
+    pub fn nodes(&self) -> impl Iterator<Item = &GraphNode> {
+        self.nodes.values()
+    }
+
+    /// 节点的一跳邻域（任意方向、任意边类型），附带连接的边
+    pub fn neighborhood(&self, node_id: &Uuid) -> Vec<(&GraphEdge, &GraphNode)> {
+        self.edges
+            .iter()
+            .filter_map(|e| {
+                let other = if &e.source == node_id {
+                    &e.target
+                } else if &e.target == node_id {
+                    &e.source
+                } else {
+                    return None;
+                };
+                self.nodes.get(other).map(|node| (e, node))
+            })
+            .collect()
+    }
+
     /// 获取节点数量
     pub fn node_count(&self) -> usize {
         self.nodes.len()
@@ -162,6 +204,67 @@ impl GraphRAG {
     pub fn edge_count(&self) -> usize {
         self.edges.len()
     }
+
+    /// 导出全部节点与边
+    pub fn snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot {
+            nodes: self.nodes.values().cloned().collect(),
+            edges: self.edges.clone(),
+        }
+    }
+
+    /// 导入快照
+    ///
+    /// 节点按 ID 处理冲突，`Merge` 保留本地节点并补齐缺失的元数据与位置；边按 (源, 目标, 类型) 去重。
+    pub fn import(&mut self, snapshot: GraphSnapshot, policy: ConflictPolicy) -> ImportStats {
+        let mut stats = ImportStats::default();
+        for node in snapshot.nodes {
+            let Some(local) = self.nodes.get_mut(&node.id) else {
+                stats.added += 1;
+                self.add_node(node);
+                continue;
+            };
+            stats.record_conflict(policy);
+            match policy {
+                ConflictPolicy::Skip => {}
+                ConflictPolicy::Overwrite => {
+                    let old = local.clone();
+                    if self.name_index.get(&old.name) == Some(&old.id) {
+                        self.name_index.remove(&old.name);
+                    }
+                    if let Some(path) = &old.path {
+                        if self.path_index.get(path) == Some(&old.id) {
+                            self.path_index.remove(path);
+                        }
+                    }
+                    self.add_node(node);
+                }
+                ConflictPolicy::Merge => {
+                    for (key, value) in node.metadata {
+                        local.metadata.entry(key).or_insert(value);
+                    }
+                    if local.location.is_none() {
+                        local.location = node.location;
+                    }
+                    if local.path.is_none() {
+                        if let Some(path) = node.path {
+                            self.path_index.insert(path.clone(), node.id);
+                            local.path = Some(path);
+                        }
+                    }
+                }
+            }
+        }
+        for edge in snapshot.edges {
+            let exists = self.edges.iter().any(|e| {
+                e.source == edge.source && e.target == edge.target && e.edge_type == edge.edge_type
+            });
+            if !exists {
+                self.edges.push(edge);
+            }
+        }
+        stats
+    }
 }
 
 impl Default for GraphRAG {
@@ -1,12 +1,18 @@
 //! HAMT - 分层抽象记忆树
 //!
-//! 实现 20 字标签 -> 200 字摘要 -> 全量提取的漏斗检索。
+//! 实现 20 字标签 -> 200 字摘要 -> 全量提取的漏斗检索。索引内部分片加锁，可在多个 Actor 间直接共享。
 
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::RwLock;
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use nl_durable::{ConflictPolicy, ImportStats};
+
 /// 记忆层级
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MemoryLevel {
@@ -37,6 +43,9 @@ pub struct MemoryEntry {
     pub access_count: u64,
     /// 元数据
     pub metadata: HashMap<String, String>,
+    /// 向量嵌入（`None` 表示尚未生成或内容变化后待重建）
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
 }
 
 impl MemoryEntry {
@@ -52,6 +61,7 @@ impl MemoryEntry {
             last_accessed: now,
             access_count: 0,
             metadata: HashMap::new(),
+            embedding: None,
         }
     }
 
@@ -62,90 +72,225 @@ impl MemoryEntry {
     }
 }
 
+/// 分片数（2 的幂）
+const SHARDS: usize = 16;
+
+/// 索引中的条目：访问统计用原子量维护，检索只需分片读锁
+struct Slot {
+    entry: MemoryEntry,
+    access_count: AtomicU64,
+    /// 最后访问时间（Unix 微秒）
+    last_accessed: AtomicI64,
+}
+
+impl Slot {
+    fn new(entry: MemoryEntry) -> Self {
+        Self {
+            access_count: AtomicU64::new(entry.access_count),
+            last_accessed: AtomicI64::new(entry.last_accessed.timestamp_micros()),
+            entry,
+        }
+    }
+
+    /// 记录一次访问
+    fn touch(&self) {
+        self.access_count.fetch_add(1, Ordering::Relaxed);
+        self.last_accessed.fetch_max(chrono::Utc::now().timestamp_micros(), Ordering::Relaxed);
+    }
+
+    fn last_accessed(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_micros(self.last_accessed.load(Ordering::Relaxed))
+            .unwrap_or(self.entry.last_accessed)
+    }
+
+    /// 带最新访问统计的条目副本
+    fn snapshot(&self) -> MemoryEntry {
+        let mut entry = self.entry.clone();
+        entry.access_count = self.access_count.load(Ordering::Relaxed);
+        entry.last_accessed = self.last_accessed();
+        entry
+    }
+}
+
 /// HAMT 索引
+///
+/// 条目与标签索引分别按 ID、标签哈希分片，每个分片一把读写锁；所有操作只需 `&self`，
+/// 检索时的访问计数通过原子量更新，多个 Actor 可以并发检索而无需外层全局锁。
+/// 读取返回条目副本（含最新访问统计）。
 pub struct HamtIndex {
     /// 标签索引 (Level 1)
-    tag_index: HashMap<String, Uuid>,
-    /// 摘要缓存 (Level 2)
-    summary_cache: HashMap<Uuid, String>,
+    tags: Vec<RwLock<HashMap<String, Uuid>>>,
     /// 所有条目
-    entries: HashMap<Uuid, MemoryEntry>,
+    entries: Vec<RwLock<HashMap<Uuid, Slot>>>,
 }
 
 impl HamtIndex {
     /// 创建新的 HAMT 索引
     pub fn new() -> Self {
         Self {
-            tag_index: HashMap::new(),
-            summary_cache: HashMap::new(),
-            entries: HashMap::new(),
+            tags: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            entries: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
         }
     }
 
-    /// 存储记忆
-    pub fn store(&mut self, entry: MemoryEntry) {
-        let id = entry.id;
-        self.tag_index.insert(entry.tag.clone(), id);
-        self.summary_cache.insert(id, entry.summary.clone());
-        self.entries.insert(id, entry);
+    fn entry_shard(&self, id: &Uuid) -> &RwLock<HashMap<Uuid, Slot>> {
+        &self.entries[id.as_u128() as usize & (SHARDS - 1)]
     }
 
-    /// 通过标签检索 (Level 1 -> Level 2 -> Level 3)
-    pub fn retrieve_by_tag(&mut self, tag: &str) -> Option<&MemoryEntry> {
-        if let Some(id) = self.tag_index.get(tag) {
-            if let Some(entry) = self.entries.get_mut(id) {
-                entry.touch();
-                return Some(entry);
-            }
+    fn tag_shard(&self, tag: &str) -> &RwLock<HashMap<String, Uuid>> {
+        let mut hasher = DefaultHasher::new();
+        tag.hash(&mut hasher);
+        &self.tags[hasher.finish() as usize & (SHARDS - 1)]
+    }
+
+    /// 存储记忆（同 ID 覆盖）
+    pub fn store(&self, entry: MemoryEntry) {
+        let (id, tag) = (entry.id, entry.tag.clone());
+        let previous = self.entry_shard(&id).write().unwrap().insert(id, Slot::new(entry));
+        if let Some(previous) = previous.filter(|p| p.entry.tag != tag) {
+            self.unlink_tag(&previous.entry.tag, &id);
         }
-        None
+        self.tag_shard(&tag).write().unwrap().insert(tag, id);
     }
 
-    /// 模糊搜索标签
-    pub fn search_tags(&self, query: &str) -> Vec<&MemoryEntry> {
+    /// 仅当条目仍在索引中时覆盖它，返回是否写入
+    pub fn replace(&self, entry: MemoryEntry) -> bool {
+        let (id, tag) = (entry.id, entry.tag.clone());
+        let previous = {
+            let mut shard = self.entry_shard(&id).write().unwrap();
+            let Some(slot) = shard.get_mut(&id) else {
+                return false;
+            };
+            std::mem::replace(slot, Slot::new(entry))
+        };
+        if previous.entry.tag != tag {
+            self.unlink_tag(&previous.entry.tag, &id);
+            self.tag_shard(&tag).write().unwrap().insert(tag, id);
+        }
+        true
+    }
+
+    /// 按 ID 获取条目（不记录访问）
+    pub fn get(&self, id: &Uuid) -> Option<MemoryEntry> {
+        self.entry_shard(id).read().unwrap().get(id).map(Slot::snapshot)
+    }
+
+    /// 移除条目
+    pub fn remove(&self, id: &Uuid) -> Option<MemoryEntry> {
+        let slot = self.entry_shard(id).write().unwrap().remove(id)?;
+        self.unlink_tag(&slot.entry.tag, id);
+        Some(slot.snapshot())
+    }
+
+    /// 标签仍指向该条目时移除标签索引
+    fn unlink_tag(&self, tag: &str, id: &Uuid) {
+        let mut tags = self.tag_shard(tag).write().unwrap();
+        if tags.get(tag) == Some(id) {
+            tags.remove(tag);
+        }
+    }
+
+    /// 通过标签检索 (Level 1 -> Level 2 -> Level 3)
+    pub fn retrieve_by_tag(&self, tag: &str) -> Option<MemoryEntry> {
+        let id = *self.tag_shard(tag).read().unwrap().get(tag)?;
+        let shard = self.entry_shard(&id).read().unwrap();
+        let slot = shard.get(&id)?;
+        slot.touch();
+        Some(slot.snapshot())
+    }
+
+    /// 按条件收集条目副本
+    fn collect(&self, filter: impl Fn(&MemoryEntry) -> bool) -> Vec<MemoryEntry> {
         self.entries
-            .values()
-            .filter(|e| e.tag.contains(query))
+            .iter()
+            .flat_map(|shard| {
+                shard
+                    .read()
+                    .unwrap()
+                    .values()
+                    .filter(|slot| filter(&slot.entry))
+                    .map(Slot::snapshot)
+                    .collect::<Vec<_>>()
+            })
             .collect()
     }
 
+    /// 模糊搜索标签
+    pub fn search_tags(&self, query: &str) -> Vec<MemoryEntry> {
+        self.collect(|e| e.tag.contains(query))
+    }
+
     /// 搜索摘要
-    pub fn search_summaries(&self, query: &str) -> Vec<&MemoryEntry> {
-        self.entries
-            .values()
-            .filter(|e| e.summary.contains(query))
-            .collect()
+    pub fn search_summaries(&self, query: &str) -> Vec<MemoryEntry> {
+        self.collect(|e| e.summary.contains(query))
     }
 
     /// 获取所有条目
-    pub fn all_entries(&self) -> Vec<&MemoryEntry> {
-        self.entries.values().collect()
+    pub fn all_entries(&self) -> Vec<MemoryEntry> {
+        self.collect(|_| true)
     }
 
     /// 获取条目数量
     pub fn count(&self) -> usize {
-        self.entries.len()
+        self.entries.iter().map(|shard| shard.read().unwrap().len()).sum()
     }
 
     /// 清理冷数据
-    pub fn prune_cold(&mut self, days: i64) -> usize {
+    pub fn prune_cold(&self, days: i64) -> usize {
         let threshold = chrono::Utc::now() - chrono::Duration::days(days);
-        let cold_ids: Vec<Uuid> = self
-            .entries
-            .iter()
-            .filter(|(_, e)| e.last_accessed < threshold)
-            .map(|(id, _)| *id)
-            .collect();
-
-        let removed = cold_ids.len();
-        for id in &cold_ids {
-            if let Some(entry) = self.entries.remove(id) {
-                self.tag_index.remove(&entry.tag);
-                self.summary_cache.remove(id);
+        let mut removed = 0;
+        for shard in &self.entries {
+            let cold: Vec<Slot> = {
+                let mut shard = shard.write().unwrap();
+                let ids: Vec<Uuid> = shard
+                    .iter()
+                    .filter(|(_, slot)| slot.last_accessed() < threshold)
+                    .map(|(id, _)| *id)
+                    .collect();
+                ids.iter().filter_map(|id| shard.remove(id)).collect()
+            };
+            for slot in &cold {
+                self.unlink_tag(&slot.entry.tag, &slot.entry.id);
             }
+            removed += cold.len();
         }
         removed
     }
+
+    /// 导入记忆条目（工作区包）
+    ///
+    /// 同 ID 冲突时：`Merge` 取最近访问一方的内容，访问次数取较大值，元数据以本地为准补齐。
+    pub fn import(&self, entries: Vec<MemoryEntry>, policy: ConflictPolicy) -> ImportStats {
+        let mut stats = ImportStats::default();
+        for incoming in entries {
+            let Some(local) = self.get(&incoming.id) else {
+                stats.added += 1;
+                self.store(incoming);
+                continue;
+            };
+            stats.record_conflict(policy);
+            let entry = match policy {
+                ConflictPolicy::Skip => continue,
+                ConflictPolicy::Overwrite => incoming,
+                ConflictPolicy::Merge => {
+                    let mut metadata = incoming.metadata.clone();
+                    metadata.extend(local.metadata.clone());
+                    let (mut newer, older) = if incoming.last_accessed > local.last_accessed {
+                        (incoming, local)
+                    } else {
+                        (local, incoming)
+                    };
+                    newer.created_at = newer.created_at.min(older.created_at);
+                    newer.access_count = newer.access_count.max(older.access_count);
+                    newer.full_data_path = newer.full_data_path.or(older.full_data_path);
+                    newer.metadata = metadata;
+                    newer
+                }
+            };
+            self.store(entry);
+        }
+        stats
+    }
 }
 
 impl Default for HamtIndex {
@@ -153,3 +298,41 @@ impl Default for HamtIndex {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_concurrent_retrieval_counts_every_access() {
+        let index = Arc::new(HamtIndex::new());
+        for i in 0..64 {
+            index.store(MemoryEntry::new(format!("tag-{}", i), format!("summary {}", i)));
+        }
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let index = index.clone();
+                std::thread::spawn(move || {
+                    for i in 0..1000 {
+                        assert!(index.retrieve_by_tag(&format!("tag-{}", i % 64)).is_some());
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let total: u64 = index.all_entries().iter().map(|e| e.access_count).sum();
+        assert_eq!(total, 8000);
+
+        let mut renamed = index.retrieve_by_tag("tag-0").unwrap();
+        renamed.tag = "renamed".to_string();
+        assert!(index.replace(renamed.clone()));
+        assert!(index.retrieve_by_tag("tag-0").is_none());
+        assert_eq!(index.retrieve_by_tag("renamed").unwrap().access_count, renamed.access_count + 1);
+        index.remove(&renamed.id);
+        assert!(!index.replace(renamed));
+        assert_eq!(index.count(), 63);
+    }
+}
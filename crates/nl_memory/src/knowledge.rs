@@ -0,0 +1,758 @@
+//! 知识库导入
+//!
+//! 把 Markdown 笔记（含 Obsidian 库）、JSONL 文件与 PDF / DOCX 文档批量导入 HAMT 记忆：
+//! - Markdown 去掉 front matter 后按标题切分，超过 `max_chunk_tokens` 的小节再按段落切分；
+//!   JSONL 每行一条记录（`title`、`text` / `content` / `body`，可选 `tags`）
+//! - PDF / DOCX 经 `document` 提取为按页的文本后同样切分（DOCX 的标题样式成为小节标题），
+//!   分块元数据记录起始页码；配置产物库时页面图片写入产物库，分块的 `images` 元数据列出所跨页面的图片 URI，
+//!   供之后的视觉查询读取（`referenced_images` 供产物回收保留这些图片）
+//! - 每个分块成为一条记忆：标签取自笔记名与小节标题，摘要与嵌入由配置的摘要器 / 嵌入器生成
+//!   （未配置摘要器时取正文开头）；配置 `PiiVault` 时先脱敏，记忆中只保留令牌
+//! - 每篇笔记在 GraphRAG 中对应一个 `Note` 节点，`[[笔记名]]` 维基链接成为 `References` 边
+//! - 每导入完一个文件就写入检查点（内容哈希与生成的记忆 ID）：重新运行时跳过内容未变且记忆仍在索引中的文件，
+//!   内容变化的文件先移除旧记忆再导入，大型语料中断后直接重新运行即可续跑
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use nl_core::artifact::{parse_artifact_uri, ArtifactKind};
+use nl_core::Result;
+use nl_durable::artifact_store::content_hash;
+use nl_durable::ArtifactStore;
+
+use crate::chunking::estimate_tokens;
+use crate::consolidation::{Embedder, Summarizer};
+use crate::document::DocumentFormat;
+use crate::graph_rag::{EdgeType, GraphEdge, GraphNode, GraphRAG, NodeType};
+use crate::hamt::{HamtIndex, MemoryEntry};
+use crate::pii::PiiVault;
+
+/// 工作区数据目录中的导入检查点文件名
+pub const KNOWLEDGE_CHECKPOINT_FILE: &str = "knowledge_import.json";
+
+/// 笔记节点元数据中记录维基链接目标的键（换行分隔）
+const LINKS_KEY: &str = "links";
+
+/// 文档分块元数据中记录起始页码的键
+pub const PAGE_KEY: &str = "page";
+
+/// 文档分块元数据中记录页面图片产物 URI 的键（逗号分隔）
+pub const IMAGES_KEY: &str = "images";
+
+/// 导入配置
+#[derive(Debug, Clone)]
+pub struct KnowledgeImportConfig {
+    /// 单个分块的 token 上限
+    pub max_chunk_tokens: usize,
+    /// 标签字数上限
+    pub tag_chars: usize,
+    /// 未配置摘要器时摘要的字数上限
+    pub summary_chars: usize,
+}
+
+impl Default for KnowledgeImportConfig {
+    fn default() -> Self {
+        Self {
+            max_chunk_tokens: 512,
+            tag_chars: 20,
+            summary_chars: 200,
+        }
+    }
+}
+
+/// 导入统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeImportStats {
+    /// 扫描到的文件数
+    pub files: usize,
+    /// 本次导入的文件数
+    pub imported: usize,
+    /// 内容未变而跳过的文件数
+    pub unchanged: usize,
+    /// 导入失败的文件数
+    pub failed: usize,
+    /// 本次导入的笔记数（Markdown 文件或 JSONL 记录）
+    pub notes: usize,
+    /// 本次生成的记忆条目数
+    pub chunks: usize,
+    /// 全部已导入笔记之间的引用边数
+    pub links: usize,
+    /// 找不到目标笔记的链接数
+    pub unresolved_links: usize,
+    /// 错误（文件路径与原因）
+    pub errors: Vec<String>,
+}
+
+impl KnowledgeImportStats {
+    fn fail(&mut self, source: &str, error: impl std::fmt::Display) {
+        self.failed += 1;
+        self.errors.push(format!("{}: {}", source, error));
+    }
+}
+
+/// 检查点中的已导入文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileRecord {
+    hash: String,
+    entries: Vec<Uuid>,
+}
+
+/// 笔记
+#[derive(Debug, Clone, PartialEq)]
+struct Note {
+    /// 名称（维基链接按名称指向笔记）
+    name: String,
+    /// 图节点路径（文件路径，JSONL 记录附加 `#行号`）
+    key: String,
+    /// 正文在文件中的起始行
+    line: usize,
+    /// 正文
+    text: String,
+    /// 标签
+    tags: Vec<String>,
+    /// 文档的页面（Markdown 与 JSONL 为空）
+    pages: Vec<Page>,
+}
+
+/// 文档页面
+#[derive(Debug, Clone, PartialEq)]
+struct Page {
+    /// 页码
+    number: usize,
+    /// 在笔记正文中的起始行
+    line: usize,
+    /// 图片产物 URI
+    images: Vec<String>,
+}
+
+/// 笔记分块
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    /// 所在小节标题
+    heading: Option<String>,
+    /// 在笔记正文中的起始行
+    line: usize,
+    /// 内容
+    text: String,
+}
+
+/// 知识库导入器
+pub struct KnowledgeImporter {
+    index: Arc<HamtIndex>,
+    config: KnowledgeImportConfig,
+    graph: Option<Arc<RwLock<GraphRAG>>>,
+    summarizer: Option<Arc<dyn Summarizer>>,
+    embedder: Option<Arc<dyn Embedder>>,
+    pii: Option<Arc<PiiVault>>,
+    artifacts: Option<Arc<ArtifactStore>>,
+    checkpoint: Option<PathBuf>,
+}
+
+impl KnowledgeImporter {
+    /// 创建导入器
+    pub fn new(index: Arc<HamtIndex>, config: KnowledgeImportConfig) -> Self {
+        Self {
+            index,
+            config,
+            graph: None,
+            summarizer: None,
+            embedder: None,
+            pii: None,
+            artifacts: None,
+            checkpoint: None,
+        }
+    }
+
+    /// 在 GraphRAG 中建立笔记节点与引用边
+    pub fn with_graph(mut self, graph: Arc<RwLock<GraphRAG>>) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
+    /// 设置摘要器
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn Summarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// 设置嵌入器
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// 生成摘要前脱敏 PII
+    pub fn with_pii_vault(mut self, vault: Arc<PiiVault>) -> Self {
+        self.pii = Some(vault);
+        self
+    }
+
+    /// 把文档的页面图片写入产物库（不设置时丢弃图片）
+    pub fn with_artifacts(mut self, artifacts: Arc<ArtifactStore>) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    /// 设置检查点文件（不设置时每次都全量导入）
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    /// 导入目录（或单个文件）下的全部 Markdown、JSONL、PDF 与 DOCX 文件
+    pub async fn import(&self, root: &Path) -> Result<KnowledgeImportStats> {
+        let files = collect_files(root).await?;
+        let mut checkpoint = self.load_checkpoint().await?;
+        let mut stats = KnowledgeImportStats {
+            files: files.len(),
+            ..Default::default()
+        };
+
+        for path in files {
+            let key = path.to_string_lossy().to_string();
+            let content = match tokio::fs::read(&path).await {
+                Ok(content) => content,
+                Err(e) => {
+                    stats.fail(&key, e);
+                    continue;
+                }
+            };
+            let hash = content_hash(&content);
+            if let Some(record) = checkpoint.get(&key) {
+                if record.hash == hash && record.entries.iter().all(|id| self.index.get(id).is_some()) {
+                    stats.unchanged += 1;
+                    continue;
+                }
+            }
+
+            let (notes, errors) = match DocumentFormat::from_path(&path) {
+                Some(format) => match self.parse_document(&path, &key, format, content).await {
+                    Ok(note) => (vec![note], Vec::new()),
+                    Err(e) => {
+                        stats.fail(&key, e);
+                        continue;
+                    }
+                },
+                None => parse_file(&path, &key, &String::from_utf8_lossy(&content)),
+            };
+            stats.errors.extend(errors);
+            // 先生成全部条目再写入索引，摘要器中途失败时不留下半个文件
+            let entries = match self.build_entries(&key, &notes).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    stats.fail(&key, e);
+                    continue;
+                }
+            };
+            if let Some(previous) = checkpoint.remove(&key) {
+                for id in &previous.entries {
+                    self.index.remove(id);
+                }
+            }
+            let ids = entries.iter().map(|e| e.id).collect();
+            stats.chunks += entries.len();
+            for entry in entries {
+                self.index.store(entry);
+            }
+            if let Some(graph) = &self.graph {
+                let mut graph = graph.write().await;
+                for note in &notes {
+                    upsert_note(&mut graph, note);
+                }
+            }
+            stats.notes += notes.len();
+            stats.imported += 1;
+            checkpoint.insert(key, FileRecord { hash, entries: ids });
+            self.save_checkpoint(&checkpoint).await?;
+        }
+
+        if let Some(graph) = &self.graph {
+            (stats.links, stats.unresolved_links) = link_notes(&mut *graph.write().await);
+        }
+        tracing::info!(
+            "Knowledge import from {}: {} imported, {} unchanged, {} failed, {} chunks",
+            root.display(),
+            stats.imported,
+            stats.unchanged,
+            stats.failed,
+            stats.chunks
+        );
+        Ok(stats)
+    }
+
+    /// 提取文档（在阻塞线程上进行）为一篇笔记，页面之间空一行；页面图片写入产物库
+    async fn parse_document(&self, path: &Path, key: &str, format: DocumentFormat, content: Vec<u8>) -> Result<Note> {
+        let document = tokio::task::spawn_blocking(move || format.extract(&content))
+            .await
+            .map_err(|e| nl_core::NeuroLoomError::Memory(format!("document extraction panicked: {}", e)))??;
+        let mut text = String::new();
+        let mut pages = Vec::new();
+        for page in document.pages {
+            let mut images = Vec::new();
+            if let Some(artifacts) = &self.artifacts {
+                for image in &page.images {
+                    images.push(artifacts.put(&image.data, ArtifactKind::Image, &image.media_type, None).await?.uri());
+                }
+            }
+            pages.push(Page {
+                number: page.number,
+                line: text.lines().count() + 1,
+                images,
+            });
+            text.push_str(&page.text);
+            text.push_str("\n\n");
+        }
+        Ok(Note {
+            name: path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+            key: key.to_string(),
+            line: 1,
+            text,
+            tags: Vec::new(),
+            pages,
+        })
+    }
+
+    /// 为笔记的每个分块生成记忆条目
+    async fn build_entries(&self, source: &str, notes: &[Note]) -> Result<Vec<MemoryEntry>> {
+        let mut entries = Vec::new();
+        for note in notes {
+            for chunk in split_chunks(&note.text, self.config.max_chunk_tokens) {
+                let label = match &chunk.heading {
+                    Some(heading) if *heading != note.name => format!("{} / {}", note.name, heading),
+                    _ => note.name.clone(),
+                };
+                let mut entry = MemoryEntry::new(truncate_chars(&label, self.config.tag_chars), "");
+                entry.full_data_path = Some(source.to_string());
+                entry.metadata.insert("kind".to_string(), "knowledge".to_string());
+                entry.metadata.insert("note".to_string(), note.name.clone());
+                entry.metadata.insert("line".to_string(), (note.line + chunk.line - 1).to_string());
+                if let Some(heading) = &chunk.heading {
+                    entry.metadata.insert("heading".to_string(), heading.clone());
+                }
+                if !note.tags.is_empty() {
+                    entry.metadata.insert("tags".to_string(), note.tags.join(","));
+                }
+                if let Some(first) = note.pages.iter().rposition(|p| p.line <= chunk.line) {
+                    let end = chunk.line + chunk.text.lines().count();
+                    let spanned = note.pages[first..].iter().take_while(|p| p.line < end);
+                    let images: Vec<&str> = spanned.flat_map(|p| p.images.iter().map(String::as_str)).collect();
+                    entry.metadata.insert(PAGE_KEY.to_string(), note.pages[first].number.to_string());
+                    if !images.is_empty() {
+                        entry.metadata.insert(IMAGES_KEY.to_string(), images.join(","));
+                    }
+                }
+                let text = match &self.pii {
+                    Some(vault) => vault.protect(&mut entry, &chunk.text).await?,
+                    None => chunk.text.clone(),
+                };
+                entry.summary = match &self.summarizer {
+                    Some(summarizer) => summarizer.summarize(&entry, &text).await?,
+                    None => truncate_chars(&collapse_whitespace(&text), self.config.summary_chars),
+                };
+                if let Some(embedder) = &self.embedder {
+                    entry.embedding = Some(embedder.embed(&format!("{}\n{}", entry.tag, entry.summary)).await?);
+                }
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn load_checkpoint(&self) -> Result<HashMap<String, FileRecord>> {
+        let Some(path) = &self.checkpoint else {
+            return Ok(HashMap::new());
+        };
+        match tokio::fs::read(path).await {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &HashMap<String, FileRecord>) -> Result<()> {
+        let Some(path) = &self.checkpoint else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(checkpoint)?).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+}
+
+/// 索引中文档分块引用的图片产物（内容哈希）
+pub fn referenced_images(index: &HamtIndex) -> HashSet<String> {
+    index
+        .all_entries()
+        .iter()
+        .filter_map(|entry| entry.metadata.get(IMAGES_KEY))
+        .flat_map(|images| images.split(',').filter_map(parse_artifact_uri).map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+/// 递归收集 Markdown、JSONL、PDF 与 DOCX 文件（跳过 `.obsidian`、`.git` 等隐藏目录）
+async fn collect_files(root: &Path) -> Result<Vec<PathBuf>> {
+    if tokio::fs::metadata(root).await?.is_file() {
+        return Ok(vec![root.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                dirs.push(path);
+            } else if matches!(path.extension().and_then(|e| e.to_str()), Some("md" | "markdown" | "jsonl"))
+                || DocumentFormat::from_path(&path).is_some()
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// 解析文件中的笔记，返回笔记与无法解析的 JSONL 行
+fn parse_file(path: &Path, key: &str, content: &str) -> (Vec<Note>, Vec<String>) {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+        let (text, line, tags) = split_front_matter(content);
+        let note = Note {
+            name: stem,
+            key: key.to_string(),
+            line,
+            text,
+            tags,
+            pages: Vec::new(),
+        };
+        return (vec![note], Vec::new());
+    }
+
+    let (mut notes, mut errors) = (Vec::new(), Vec::new());
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_record(line, &stem, key, i + 1) {
+            Ok(note) => notes.push(note),
+            Err(e) => errors.push(format!("{}:{}: {}", key, i + 1, e)),
+        }
+    }
+    (notes, errors)
+}
+
+/// 解析一条 JSONL 记录
+fn parse_record(line: &str, stem: &str, key: &str, number: usize) -> std::result::Result<Note, String> {
+    let value: serde_json::Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    let field = |names: &[&str]| names.iter().find_map(|n| value.get(*n).and_then(|v| v.as_str())).map(str::to_string);
+    let text = field(&["text", "content", "body"]).ok_or("record has no text, content or body field")?;
+    let tags: Vec<String> = value
+        .get("tags")
+        .and_then(|t| t.as_array())
+        .map(|t| t.iter().filter_map(|t| t.as_str()).map(str::to_string).collect())
+        .unwrap_or_default();
+    Ok(Note {
+        name: field(&["title", "name"]).unwrap_or_else(|| format!("{}:{}", stem, number)),
+        key: format!("{}#{}", key, number),
+        line: number,
+        text,
+        tags,
+        pages: Vec::new(),
+    })
+}
+
+/// 去掉 YAML front matter，返回正文、正文起始行与其中的 `tags`
+fn split_front_matter(content: &str) -> (String, usize, Vec<String>) {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.first().map(|l| l.trim_end()) == Some("---") {
+        if let Some(end) = lines.iter().skip(1).position(|l| l.trim_end() == "---").map(|p| p + 1) {
+            return (lines[end + 1..].join("\n"), end + 2, front_matter_tags(&lines[1..end]));
+        }
+    }
+    (content.to_string(), 1, Vec::new())
+}
+
+/// 读取 front matter 中的 `tags: [a, b]`、`tags: a, b` 或列表形式
+fn front_matter_tags(lines: &[&str]) -> Vec<String> {
+    let clean = |tag: &str| tag.trim().trim_matches(|c: char| c == '"' || c == '\'').trim_start_matches('#').to_string();
+    let mut tags = Vec::new();
+    let mut in_list = false;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("tags:") {
+            let value = value.trim().trim_start_matches('[').trim_end_matches(']');
+            tags.extend(value.split(',').map(clean));
+            in_list = value.is_empty();
+        } else if in_list {
+            match line.trim_start().strip_prefix("- ") {
+                Some(tag) => tags.push(clean(tag)),
+                None => in_list = false,
+            }
+        }
+    }
+    tags.retain(|t| !t.is_empty());
+    tags
+}
+
+/// 按标题切分正文（忽略代码块中的 `#`），超长小节再按段落切分
+fn split_chunks(text: &str, max_tokens: usize) -> Vec<Chunk> {
+    let mut sections = Vec::new();
+    let mut current = Chunk {
+        heading: None,
+        line: 1,
+        text: String::new(),
+    };
+    let mut in_fence = false;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if let Some(heading) = heading_of(line).filter(|_| !in_fence) {
+            let next = Chunk {
+                heading: Some(heading),
+                line: i + 2,
+                text: String::new(),
+            };
+            sections.push(std::mem::replace(&mut current, next));
+            continue;
+        }
+        current.text.push_str(line);
+        current.text.push('\n');
+    }
+    sections.push(current);
+    sections
+        .into_iter()
+        .filter(|s| !s.text.trim().is_empty())
+        .flat_map(|s| split_oversized(s, max_tokens))
+        .collect()
+}
+
+/// 超过 token 上限的小节按空行分段后重新装箱（单个超长段落保持完整）
+fn split_oversized(section: Chunk, max_tokens: usize) -> Vec<Chunk> {
+    if estimate_tokens(&section.text) <= max_tokens {
+        return vec![section];
+    }
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let (mut start, mut line) = (section.line, section.line);
+    for paragraph in section.text.split("\n\n") {
+        if !current.trim().is_empty() && estimate_tokens(&current) + estimate_tokens(paragraph) > max_tokens {
+            chunks.push(Chunk {
+                heading: section.heading.clone(),
+                line: start,
+                text: std::mem::take(&mut current),
+            });
+            start = line;
+        }
+        current.push_str(paragraph);
+        current.push_str("\n\n");
+        line += paragraph.matches('\n').count() + 2;
+    }
+    if !current.trim().is_empty() {
+        chunks.push(Chunk {
+            heading: section.heading,
+            line: start,
+            text: current,
+        });
+    }
+    chunks
+}
+
+/// ATX 标题（`# ` 到 `###### `）的文字
+fn heading_of(line: &str) -> Option<String> {
+    let level = line.bytes().take_while(|b| *b == b'#').count();
+    let rest = line[level..].strip_prefix(' ').filter(|_| (1..=6).contains(&level))?;
+    Some(rest.trim().trim_end_matches('#').trim().to_string()).filter(|h| !h.is_empty())
+}
+
+/// 正文中的维基链接目标（去掉别名、标题锚点、目录前缀与 `.md` 后缀，去重）
+fn wiki_links(text: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let inner = &rest[..end];
+        rest = &rest[end + 2..];
+        let target = inner.split(['|', '#']).next().unwrap_or_default().trim();
+        let target = target.rsplit('/').next().unwrap_or(target);
+        let target = target.strip_suffix(".md").unwrap_or(target);
+        if !target.is_empty() && !target.contains('\n') && !links.iter().any(|l| l == target) {
+            links.push(target.to_string());
+        }
+    }
+    links
+}
+
+/// 写入（或更新）笔记节点，链接目标记在元数据中，由 `link_notes` 统一连边
+fn upsert_note(graph: &mut GraphRAG, note: &Note) {
+    let id = graph.find_by_path(&note.key).map(|n| n.id).unwrap_or_else(Uuid::new_v4);
+    let mut metadata = HashMap::new();
+    metadata.insert(LINKS_KEY.to_string(), wiki_links(&note.text).join("\n"));
+    if !note.tags.is_empty() {
+        metadata.insert("tags".to_string(), note.tags.join(","));
+    }
+    graph.add_node(GraphNode {
+        id,
+        name: note.name.clone(),
+        node_type: NodeType::Note,
+        path: Some(note.key.clone()),
+        location: None,
+        metadata,
+    });
+}
+
+/// 按全部笔记节点记录的链接重建引用边，返回（边数，未解析的链接数）
+///
+/// 每次导入后对所有笔记重建，先导入的笔记指向后导入的笔记时也能连上。
+fn link_notes(graph: &mut GraphRAG) -> (usize, usize) {
+    let notes: Vec<(Uuid, Vec<String>)> = graph
+        .nodes()
+        .filter(|n| n.node_type == NodeType::Note)
+        .map(|n| {
+            let links = n.metadata.get(LINKS_KEY).map(|l| l.lines().map(str::to_string).collect());
+            (n.id, links.unwrap_or_default())
+        })
+        .collect();
+    let (mut linked, mut unresolved) = (0, 0);
+    for (source, targets) in notes {
+        graph.remove_outgoing_edges(&source);
+        for target in targets {
+            match graph.find_by_name(&target).filter(|n| n.node_type == NodeType::Note).map(|n| n.id) {
+                Some(target) if target == source => {}
+                Some(target) => {
+                    graph.add_edge(GraphEdge {
+                        source,
+                        target,
+                        edge_type: EdgeType::References,
+                    });
+                    linked += 1;
+                }
+                None => unresolved += 1,
+            }
+        }
+    }
+    (linked, unresolved)
+}
+
+/// 合并连续空白
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 按字符截断
+fn truncate_chars(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_import_chunks_notes_links_them_and_resumes() {
+        let dir = std::env::temp_dir().join(format!("nl-knowledge-{}", Uuid::new_v4()));
+        let vault = dir.join("vault");
+        std::fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        std::fs::write(vault.join(".obsidian/app.json"), "{}").unwrap();
+        std::fs::write(
+            vault.join("Ownership.md"),
+            "---\ntags: [rust, memory]\n---\nIntro about moves.\n\n## Borrowing\nSee [[Lifetimes|lifetimes]] and [[Missing]].\n```\n# not a heading\n```\n",
+        )
+        .unwrap();
+        std::fs::write(vault.join("Lifetimes.md"), "# Lifetimes\nBack to [[notes/Ownership.md#Borrowing]].\n").unwrap();
+        std::fs::write(
+            vault.join("snippets.jsonl"),
+            "{\"title\": \"Arc\", \"text\": \"Shared ownership, see [[Ownership]]\", \"tags\": [\"sync\"]}\nnot json\n",
+        )
+        .unwrap();
+
+        let index = Arc::new(HamtIndex::new());
+        let graph = Arc::new(RwLock::new(GraphRAG::new()));
+        let importer = KnowledgeImporter::new(index.clone(), KnowledgeImportConfig::default())
+            .with_graph(graph.clone())
+            .with_checkpoint(dir.join(KNOWLEDGE_CHECKPOINT_FILE));
+        let stats = importer.import(&vault).await.unwrap();
+        assert_eq!((stats.files, stats.imported, stats.notes, stats.chunks), (3, 3, 3, 4));
+        assert_eq!((stats.links, stats.unresolved_links), (3, 1));
+        assert_eq!(stats.errors.len(), 1);
+
+        let borrowing = index.search_tags("Ownership / ").pop().unwrap();
+        assert_eq!(borrowing.metadata["line"], "7");
+        assert_eq!(borrowing.metadata["tags"], "rust,memory");
+        assert!(borrowing.summary.starts_with("See [[Lifetimes|lifetimes]]"));
+        {
+            let graph = graph.read().await;
+            let ownership = graph.find_by_name("Ownership").unwrap();
+            assert_eq!(graph.get_incoming_edges(&ownership.id).len(), 2);
+        }
+
+        let stats = importer.import(&vault).await.unwrap();
+        assert_eq!((stats.imported, stats.unchanged), (0, 3));
+        assert_eq!(stats.links, 3);
+
+        std::fs::write(vault.join("Lifetimes.md"), "# Lifetimes\nNo links any more.\n").unwrap();
+        let stats = importer.import(&vault).await.unwrap();
+        assert_eq!((stats.imported, stats.unchanged, stats.links), (1, 2, 2));
+        assert_eq!(index.count(), 4);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pdf_page_images_go_to_artifact_store() {
+        let dir = std::env::temp_dir().join(format!("nl-knowledge-{}", Uuid::new_v4()));
+        let vault = dir.join("vault");
+        std::fs::create_dir_all(&vault).unwrap();
+        let jpeg = b"\xff\xd8\xff\xe0 diagram \xff\xd9";
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+        pdf.extend_from_slice(b"2 0 obj\n<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>\nendobj\n");
+        pdf.extend_from_slice(b"3 0 obj\n<< /Type /Page /Parent 2 0 R /Contents 5 0 R >>\nendobj\n");
+        pdf.extend_from_slice(b"4 0 obj\n<< /Type /Page /Parent 2 0 R /Contents 6 0 R\n");
+        pdf.extend_from_slice(b"/Resources << /XObject << /Im1 7 0 R >> >> >>\nendobj\n");
+        let stream = |number: u32, dict: &str, data: &[u8]| {
+            let mut object = format!("{} 0 obj\n<< {} /Length {} >>\nstream\n", number, dict, data.len()).into_bytes();
+            object.extend_from_slice(data);
+            object.extend_from_slice(b"\nendstream\nendobj\n");
+            object
+        };
+        pdf.extend(stream(5, "", b"BT 72 700 Td (Architecture) Tj ET"));
+        pdf.extend(stream(6, "", b"BT 72 700 Td (See diagram) Tj ET /Im1 Do"));
+        pdf.extend(stream(7, "/Subtype /Image /Filter /DCTDecode", jpeg));
+        pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R >>\n%%EOF\n");
+        std::fs::write(vault.join("Design.pdf"), &pdf).unwrap();
+
+        let index = Arc::new(HamtIndex::new());
+        let artifacts = Arc::new(ArtifactStore::open(dir.join("artifacts")).await.unwrap());
+        let importer = KnowledgeImporter::new(index.clone(), KnowledgeImportConfig::default())
+            .with_artifacts(artifacts.clone());
+        let stats = importer.import(&vault).await.unwrap();
+        assert_eq!((stats.files, stats.imported, stats.chunks), (1, 1, 1));
+
+        let entry = index.search_tags("Design").pop().unwrap();
+        assert_eq!(entry.metadata[PAGE_KEY], "1");
+        assert!(entry.summary.contains("Architecture") && entry.summary.contains("See diagram"));
+        let hash = parse_artifact_uri(&entry.metadata[IMAGES_KEY]).unwrap();
+        assert_eq!(artifacts.get(hash).await.unwrap().unwrap(), jpeg);
+        assert_eq!(artifacts.get_meta(hash).await.unwrap().unwrap().media_type, "image/jpeg");
+        assert_eq!(referenced_images(&index), HashSet::from([hash.to_string()]));
+
+        // 没有产物库时不记录图片
+        let index = Arc::new(HamtIndex::new());
+        KnowledgeImporter::new(index.clone(), KnowledgeImportConfig::default()).import(&vault).await.unwrap();
+        let entry = index.search_tags("Design").pop().unwrap();
+        assert!(!entry.metadata.contains_key(IMAGES_KEY));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
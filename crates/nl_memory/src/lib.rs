@@ -1,11 +1,25 @@
 //! # nl_memory - NeuroLoom Memory Foundation
 //!
-//! 记忆底座，实现 HAMT 漏斗检索、GraphRAG 空间拓扑、快照归档。
+//! 记忆底座，实现 HAMT 漏斗检索、GraphRAG 空间拓扑、快照归档、语言感知的代码分块、知识库导入（含 PDF / DOCX 文档）、记忆查询语言、PII 脱敏与长文档 map-reduce 摘要。
 
 pub mod hamt;
 pub mod graph_rag;
 pub mod archival;
+pub mod consolidation;
+pub mod chunking;
+pub mod knowledge;
+pub mod document;
+pub mod query;
+pub mod pii;
+pub mod map_reduce;
 
 pub use hamt::HamtIndex;
-pub use graph_rag::GraphRAG;
+pub use graph_rag::{GraphRAG, GraphSnapshot};
 pub use archival::ArchivalManager;
+pub use chunking::{ChunkKind, ChunkerConfig, CodeChunk, CodeChunker, CodeLanguage};
+pub use consolidation::{ConsolidationConfig, ConsolidationReport, MemoryConsolidator};
+pub use knowledge::{KnowledgeImportConfig, KnowledgeImportStats, KnowledgeImporter};
+pub use document::{Document, DocumentFormat, DocumentPage};
+pub use query::{MemoryQuery, QueryHit};
+pub use pii::PiiVault;
+pub use map_reduce::{MapReduceConfig, MapReduceSummarizer, SummaryTree};
@@ -0,0 +1,355 @@
+//! 长文档 map-reduce 摘要
+//!
+//! 数百页的文档（如 PDF 提取出的文本）放不进任何上下文窗口，按层摘要：
+//! 1. 切分：按行装箱为不超过 `chunk_tokens` 的分块，超长的单行按字符硬切
+//! 2. map：并发（`concurrency`）为每个分块生成摘要；送入摘要器的 token 受令牌桶（`tokens_per_minute`）限制，
+//!    桶空时等待补充而不是报错
+//! 3. reduce：相邻摘要按 `fan_in` 个（且合计不超过 `chunk_tokens`）一组合并为上一层摘要，直到只剩一个根摘要
+//!
+//! 结果树写入 HAMT，每个节点一条记忆：分块条目的 Level 3 数据指向原文档并记录起始行，
+//! 元数据记录文档、层级与序号，父子节点互相记录 ID（`parent`、`children`），从根摘要可逐层下钻到分块。
+//! 任一摘要失败时整棵树都不写入。
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use nl_core::Result;
+
+use crate::chunking::{estimate_tokens, BYTES_PER_TOKEN};
+use crate::consolidation::Summarizer;
+use crate::hamt::{HamtIndex, MemoryEntry};
+
+/// 文档的元数据键
+pub const DOCUMENT_KEY: &str = "document";
+
+/// 层级的元数据键（分块为 0）
+pub const LEVEL_KEY: &str = "level";
+
+/// 父节点 ID 的元数据键
+pub const PARENT_KEY: &str = "parent";
+
+/// 子节点 ID（逗号分隔）的元数据键
+pub const CHILDREN_KEY: &str = "children";
+
+/// map-reduce 配置
+#[derive(Debug, Clone)]
+pub struct MapReduceConfig {
+    /// 分块（以及每次合并的输入）的 token 上限
+    pub chunk_tokens: usize,
+    /// 每次合并的摘要数上限
+    pub fan_in: usize,
+    /// 并发摘要数
+    pub concurrency: usize,
+    /// 每分钟送入摘要器的 token 上限（`None` 不限）
+    pub tokens_per_minute: Option<usize>,
+    /// 标签的最大字符数
+    pub tag_chars: usize,
+}
+
+impl Default for MapReduceConfig {
+    fn default() -> Self {
+        Self {
+            chunk_tokens: 3000,
+            fan_in: 8,
+            concurrency: 4,
+            tokens_per_minute: None,
+            tag_chars: 60,
+        }
+    }
+}
+
+/// 写入 HAMT 的摘要树
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummaryTree {
+    pub document: String,
+    /// 根摘要条目
+    pub root: Uuid,
+    /// 根摘要
+    pub summary: String,
+    /// 各层条目 ID（第 0 层为分块，最后一层只有根）
+    pub levels: Vec<Vec<Uuid>>,
+    /// 送入摘要器的估算 token 总数
+    pub input_tokens: usize,
+}
+
+impl SummaryTree {
+    /// 分块数
+    pub fn chunks(&self) -> usize {
+        self.levels.first().map_or(0, Vec::len)
+    }
+}
+
+/// 令牌桶：容量为每分钟上限，按时间连续补充
+struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn per_minute(tokens: usize) -> Self {
+        let capacity = tokens.max(1) as f64;
+        Self {
+            capacity,
+            per_second: capacity / 60.0,
+            state: Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    /// 取出 `tokens` 个令牌（超过容量时按容量计），不足时等待补充
+    async fn acquire(&self, tokens: usize) {
+        let tokens = (tokens as f64).min(self.capacity);
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                let (available, refilled) = &mut *state;
+                let now = Instant::now();
+                let refill = now.duration_since(*refilled).as_secs_f64() * self.per_second;
+                *available = (*available + refill).min(self.capacity);
+                *refilled = now;
+                if *available >= tokens {
+                    *available -= tokens;
+                    return;
+                }
+                Duration::from_secs_f64((tokens - *available) / self.per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// 长文档 map-reduce 摘要管线
+pub struct MapReduceSummarizer {
+    index: Arc<HamtIndex>,
+    summarizer: Arc<dyn Summarizer>,
+    config: MapReduceConfig,
+    bucket: Option<TokenBucket>,
+}
+
+impl MapReduceSummarizer {
+    /// 创建管线
+    pub fn new(index: Arc<HamtIndex>, summarizer: Arc<dyn Summarizer>, config: MapReduceConfig) -> Self {
+        Self {
+            bucket: config.tokens_per_minute.map(TokenBucket::per_minute),
+            index,
+            summarizer,
+            config,
+        }
+    }
+
+    /// 摘要文档并把结果树写入 HAMT；`document` 为文档路径（或名称），记为分块的 Level 3 数据
+    pub async fn summarize(&self, document: &str, text: &str) -> Result<SummaryTree> {
+        let name = document.rsplit(['/', '\\']).next().unwrap_or(document);
+        let chunks = split_document(text, self.config.chunk_tokens.max(1));
+        let mut input_tokens = 0;
+
+        let mut level: Vec<(MemoryEntry, String)> = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, (line, chunk))| {
+                let mut entry = self.node(document, name, 0, index);
+                entry.full_data_path = Some(document.to_string());
+                entry.metadata.insert("line".to_string(), line.to_string());
+                (entry, chunk)
+            })
+            .collect();
+        let mut levels: Vec<Vec<MemoryEntry>> = Vec::new();
+        loop {
+            input_tokens += level.iter().map(|(_, input)| estimate_tokens(input)).sum::<usize>();
+            let summarized = self.map(level).await?;
+            if summarized.len() <= 1 {
+                levels.push(summarized);
+                break;
+            }
+            let depth = levels.len() + 1;
+            let groups = self.group(&summarized);
+            let mut parents = Vec::new();
+            for (index, range) in groups.into_iter().enumerate() {
+                let mut parent = self.node(document, name, depth, index);
+                let children = &summarized[range];
+                let ids: Vec<String> = children.iter().map(|c| c.id.to_string()).collect();
+                parent.metadata.insert(CHILDREN_KEY.to_string(), ids.join(","));
+                let input: Vec<&str> = children.iter().map(|c| c.summary.as_str()).collect();
+                parents.push((parent, input.join("\n\n")));
+            }
+            levels.push(summarized);
+            level = parents;
+        }
+
+        // 回填父节点，整棵树一次写入
+        for depth in 1..levels.len() {
+            let (lower, upper) = levels.split_at_mut(depth);
+            for parent in &upper[0] {
+                let children = parent.metadata.get(CHILDREN_KEY).cloned().unwrap_or_default();
+                for child in lower[depth - 1].iter_mut().filter(|c| children.contains(&c.id.to_string())) {
+                    child.metadata.insert(PARENT_KEY.to_string(), parent.id.to_string());
+                }
+            }
+        }
+        let root = levels.last().and_then(|l| l.first()).cloned().unwrap_or_else(|| self.node(document, name, 0, 0));
+        let tree = SummaryTree {
+            document: document.to_string(),
+            root: root.id,
+            summary: root.summary.clone(),
+            levels: levels.iter().map(|l| l.iter().map(|e| e.id).collect()).collect(),
+            input_tokens,
+        };
+        for entry in levels.into_iter().flatten() {
+            self.index.store(entry);
+        }
+        tracing::info!(
+            "Summarized {} into {} chunks and {} levels ({} input tokens)",
+            document,
+            tree.chunks(),
+            tree.levels.len(),
+            tree.input_tokens
+        );
+        Ok(tree)
+    }
+
+    /// 并发摘要一层，保持顺序
+    async fn map(&self, level: Vec<(MemoryEntry, String)>) -> Result<Vec<MemoryEntry>> {
+        stream::iter(level)
+            .map(|(mut entry, input)| async move {
+                if let Some(bucket) = &self.bucket {
+                    bucket.acquire(estimate_tokens(&input)).await;
+                }
+                entry.summary = self.summarizer.summarize(&entry, &input).await?;
+                Ok::<_, nl_core::NeuroLoomError>(entry)
+            })
+            .buffered(self.config.concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// 相邻摘要分组：每组不超过 `fan_in` 个，合计不超过 `chunk_tokens`（单个摘要超限时独占一组）
+    fn group(&self, entries: &[MemoryEntry]) -> Vec<std::ops::Range<usize>> {
+        let fan_in = self.config.fan_in.max(2);
+        let mut groups = Vec::new();
+        let (mut start, mut tokens) = (0, 0);
+        for (i, entry) in entries.iter().enumerate() {
+            let size = estimate_tokens(&entry.summary);
+            if i > start && (i - start >= fan_in || tokens + size > self.config.chunk_tokens) {
+                groups.push(start..i);
+                (start, tokens) = (i, 0);
+            }
+            tokens += size;
+        }
+        groups.push(start..entries.len());
+        // 每组都只有一个摘要时无法继续收敛，强制按 fan_in 合并
+        if groups.len() == entries.len() {
+            groups = (0..entries.len()).step_by(fan_in).map(|s| s..(s + fan_in).min(entries.len())).collect();
+        }
+        groups
+    }
+
+    fn node(&self, document: &str, name: &str, level: usize, index: usize) -> MemoryEntry {
+        let label = match level {
+            0 => format!("{} #{}", name, index + 1),
+            _ => format!("{} L{} #{}", name, level, index + 1),
+        };
+        let mut entry = MemoryEntry::new(label.chars().take(self.config.tag_chars).collect::<String>(), "");
+        let kind = if level == 0 { "document_chunk" } else { "document_summary" };
+        entry.metadata.insert("kind".to_string(), kind.to_string());
+        entry.metadata.insert(DOCUMENT_KEY.to_string(), document.to_string());
+        entry.metadata.insert(LEVEL_KEY.to_string(), level.to_string());
+        entry.metadata.insert("index".to_string(), index.to_string());
+        entry
+    }
+}
+
+/// 按行装箱为不超过 `max_tokens` 的分块，返回（起始行, 内容）；超长的单行按字符硬切
+fn split_document(text: &str, max_tokens: usize) -> Vec<(usize, String)> {
+    let max_bytes = max_tokens * BYTES_PER_TOKEN;
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut start = 1;
+    let mut flush = |current: &mut String, start: usize| {
+        if !current.trim().is_empty() {
+            chunks.push((start, std::mem::take(current)));
+        }
+        current.clear();
+    };
+    for (i, line) in text.lines().enumerate() {
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(line) > max_tokens {
+            flush(&mut current, start);
+        }
+        if current.is_empty() {
+            start = i + 1;
+        }
+        if line.len() <= max_bytes {
+            current.push_str(line);
+            current.push('\n');
+            continue;
+        }
+        for c in line.chars() {
+            if current.len() + c.len_utf8() > max_bytes {
+                flush(&mut current, start);
+            }
+            current.push(c);
+        }
+        current.push('\n');
+    }
+    flush(&mut current, start);
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 取每段输入的首个词作为摘要
+    #[derive(Default)]
+    struct FirstWords {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Summarizer for FirstWords {
+        async fn summarize(&self, _entry: &MemoryEntry, full: &str) -> Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let words: Vec<&str> = full.split("\n\n").filter_map(|p| p.split_whitespace().next()).collect();
+            Ok(words.join(" "))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce_builds_linked_tree() {
+        let text: String = (1..=9).map(|i| format!("page{} {}\n", i, "lorem ipsum ".repeat(8))).collect();
+        let index = Arc::new(HamtIndex::new());
+        let summarizer = Arc::new(FirstWords::default());
+        let config = MapReduceConfig {
+            chunk_tokens: 30,
+            fan_in: 3,
+            tokens_per_minute: Some(100_000),
+            ..MapReduceConfig::default()
+        };
+        let pipeline = MapReduceSummarizer::new(index.clone(), summarizer.clone(), config);
+        let tree = pipeline.summarize("docs/manual.pdf", &text).await.unwrap();
+
+        assert_eq!(tree.levels.iter().map(Vec::len).collect::<Vec<_>>(), [9, 3, 1]);
+        assert_eq!(summarizer.calls.load(Ordering::SeqCst), 13);
+        assert_eq!(index.all_entries().len(), 13);
+        assert_eq!(tree.summary, "page1 page4 page7");
+
+        let root = index.get(&tree.root).unwrap();
+        assert_eq!(root.metadata[LEVEL_KEY], "2");
+        let middle = index.get(&tree.levels[1][1]).unwrap();
+        assert_eq!(middle.summary, "page4 page5 page6");
+        assert_eq!(middle.metadata[PARENT_KEY], tree.root.to_string());
+        let chunk = index.get(&tree.levels[0][4]).unwrap();
+        assert_eq!(chunk.metadata[PARENT_KEY], middle.id.to_string());
+        assert_eq!((chunk.tag.as_str(), chunk.metadata["line"].as_str()), ("manual.pdf #5", "5"));
+        assert_eq!(chunk.full_data_path.as_deref(), Some("docs/manual.pdf"));
+
+        let long = "x".repeat(250);
+        assert_eq!(split_document(&long, 30).iter().map(|(_, c)| c.len()).collect::<Vec<_>>(), [120, 120, 11]);
+    }
+}
@@ -0,0 +1,136 @@
+//! 记忆 PII 脱敏
+//!
+//! 企业部署中记忆条目不得保留原始 PII。`PiiVault` 在生成摘要之前处理每条记忆：
+//! - 标签、元数据与送入摘要器的全文经 `PiiScrubber` 替换为 `[PII:<kind>:<n>]` 令牌，
+//!   摘要与嵌入只基于脱敏文本生成
+//! - 原始全文与令牌映射按条目 ID 写入加密的 Level 3 归档（`ArchivalManager::with_cipher`），
+//!   这是唯一保留原文的地方；持有主密钥时可用 `reveal` 还原
+//!
+//! 同一条目再次处理（如整理时刷新摘要）会沿用已有的令牌映射，令牌编号保持稳定。
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+use nl_durable::{EventCipher, PiiScrubber, PiiTokens};
+
+use crate::archival::ArchivalManager;
+use crate::hamt::MemoryEntry;
+
+/// 记录令牌数的元数据键
+pub const PII_TOKENS_KEY: &str = "pii_tokens";
+
+/// 加密归档中的 Level 3 数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SealedRecord {
+    /// 脱敏前的全文
+    full: String,
+    tokens: PiiTokens,
+}
+
+/// PII 脱敏与加密保管
+pub struct PiiVault {
+    scrubber: PiiScrubber,
+    archival: Mutex<ArchivalManager>,
+}
+
+impl PiiVault {
+    /// 创建保管库；归档数据一律用 `cipher` 加密
+    pub fn new(scrubber: PiiScrubber, archival: ArchivalManager, cipher: EventCipher) -> Self {
+        Self {
+            scrubber,
+            archival: Mutex::new(archival.with_cipher(cipher)),
+        }
+    }
+
+    /// 脱敏器
+    pub fn scrubber(&self) -> &PiiScrubber {
+        &self.scrubber
+    }
+
+    /// 脱敏条目的标签与元数据，返回脱敏后的全文（交给摘要器）；含 PII 时把原文与令牌映射写入加密归档
+    pub async fn protect(&self, entry: &mut MemoryEntry, full: &str) -> Result<String> {
+        let mut archival = self.archival.lock().await;
+        let mut record = Self::load(&archival, &entry.id).await?.unwrap_or_default();
+
+        let scrubbed = self.scrubber.scrub(full, &mut record.tokens);
+        entry.tag = self.scrubber.scrub(&entry.tag, &mut record.tokens);
+        for value in entry.metadata.values_mut() {
+            *value = self.scrubber.scrub(value, &mut record.tokens);
+        }
+        if !record.tokens.is_empty() {
+            record.full = full.to_string();
+            archival.archive(entry.id, &serde_json::to_vec(&record)?).await?;
+            entry.metadata.insert(PII_TOKENS_KEY.to_string(), record.tokens.len().to_string());
+        }
+        Ok(scrubbed)
+    }
+
+    /// 还原条目中的令牌（如摘要），条目没有加密归档时原样返回
+    pub async fn reveal(&self, entry_id: &Uuid, text: &str) -> Result<String> {
+        let archival = self.archival.lock().await;
+        Ok(match Self::load(&archival, entry_id).await? {
+            Some(record) => record.tokens.restore(text),
+            None => text.to_string(),
+        })
+    }
+
+    /// 读取条目脱敏前的全文
+    pub async fn original(&self, entry_id: &Uuid) -> Result<Option<String>> {
+        let archival = self.archival.lock().await;
+        Ok(Self::load(&archival, entry_id).await?.map(|record| record.full))
+    }
+
+    /// 删除条目的加密归档
+    pub async fn remove(&self, entry_id: &Uuid) -> Result<bool> {
+        self.archival.lock().await.remove(entry_id).await
+    }
+
+    async fn load(archival: &ArchivalManager, entry_id: &Uuid) -> Result<Option<SealedRecord>> {
+        match archival.restore(entry_id).await {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            // 归档不存在
+            Err(NeuroLoomError::Memory(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archival::ArchivalStrategy;
+    use nl_durable::PiiConfig;
+
+    #[tokio::test]
+    async fn test_protect_seals_original_and_reveals() {
+        let dir = std::env::temp_dir().join(format!("nl_pii_{}", Uuid::new_v4()));
+        let cipher = EventCipher::derive(b"master secret", "pii").unwrap();
+        let scrubber = PiiScrubber::from_config(&PiiConfig {
+            names: vec!["Alice Wong".to_string()],
+            ..PiiConfig::default()
+        })
+        .unwrap();
+        let archival = ArchivalManager::new(ArchivalStrategy::ByAge(30), dir.to_string_lossy());
+        let vault = PiiVault::new(scrubber, archival, cipher);
+
+        let mut entry = MemoryEntry::new("Alice Wong / onboarding", "");
+        let full = "Alice Wong starts Monday, reach her at alice@corp.example.";
+        let scrubbed = vault.protect(&mut entry, full).await.unwrap();
+        assert_eq!(scrubbed, "[PII:name:1] starts Monday, reach her at [PII:email:1].");
+        assert_eq!(entry.tag, "[PII:name:1] / onboarding");
+        assert_eq!(entry.metadata[PII_TOKENS_KEY], "2");
+
+        // 归档落盘为密文
+        let sealed = std::fs::read_to_string(dir.join(format!("{}.gz", entry.id))).unwrap();
+        assert!(EventCipher::is_encrypted(&sealed) && !sealed.contains("alice"));
+        assert_eq!(vault.original(&entry.id).await.unwrap().as_deref(), Some(full));
+        assert_eq!(vault.reveal(&entry.id, &scrubbed).await.unwrap(), full);
+
+        // 再次处理沿用原有令牌
+        assert_eq!(vault.protect(&mut entry, "ping Alice Wong").await.unwrap(), "ping [PII:name:1]");
+        assert_eq!(vault.reveal(&Uuid::new_v4(), "[PII:name:1]").await.unwrap(), "[PII:name:1]");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,302 @@
+//! 记忆查询语言
+//!
+//! 以空白分隔的若干项组成一条查询，值含空格时用双引号括起：
+//! `tag:rust after:2024-06 near:"token bucket" meta.kind:knowledge limit:20`
+//! - `tag:<文本>` 标签包含该文本（不区分大小写，可重复，需同时满足）
+//! - `after:<时间>` / `before:<时间>` 按创建时间过滤；时间可写 `2024`、`2024-06`、`2024-06-15`、
+//!   RFC 3339，或相对当前的 `12h`、`7d`、`2w`；`after` 含起点，`before` 不含
+//! - `near:<文本>` 按语义相似度排序：配置嵌入器且条目已有嵌入时用向量余弦相似度，否则退化为词重叠
+//! - `meta.<键>:<值>` 元数据精确匹配
+//! - `limit:<n>` 最多返回的条数（默认 20）
+//! - 其余项为全文词，标签或摘要包含该词（不区分大小写）
+//!
+//! 没有 `near` 时结果按创建时间从新到旧排列。
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+
+use nl_core::{NeuroLoomError, Result};
+
+use crate::consolidation::Embedder;
+use crate::hamt::{HamtIndex, MemoryEntry};
+
+/// 默认返回条数
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// 解析后的查询
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryQuery {
+    /// 全文词
+    pub text: Vec<String>,
+    /// 标签过滤
+    pub tags: Vec<String>,
+    /// 创建时间下限（含）
+    pub after: Option<DateTime<Utc>>,
+    /// 创建时间上限（不含）
+    pub before: Option<DateTime<Utc>>,
+    /// 语义相似度排序的参照文本
+    pub near: Option<String>,
+    /// 元数据过滤
+    pub metadata: Vec<(String, String)>,
+    /// 最多返回的条数
+    pub limit: usize,
+}
+
+impl Default for MemoryQuery {
+    fn default() -> Self {
+        Self {
+            text: Vec::new(),
+            tags: Vec::new(),
+            after: None,
+            before: None,
+            near: None,
+            metadata: Vec::new(),
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+/// 查询命中
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryHit {
+    /// 条目
+    pub entry: MemoryEntry,
+    /// 与 `near` 的相似度（0-1，没有 `near` 时为 0）
+    pub score: f64,
+}
+
+impl FromStr for MemoryQuery {
+    type Err = NeuroLoomError;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Self::parse_at(input, Utc::now())
+    }
+}
+
+impl MemoryQuery {
+    /// 解析查询（相对时间以 `now` 为基准）
+    pub fn parse_at(input: &str, now: DateTime<Utc>) -> Result<Self> {
+        let mut query = Self::default();
+        for (key, value) in tokenize(input)? {
+            let Some(key) = key else {
+                query.text.push(value.to_lowercase());
+                continue;
+            };
+            match key.as_str() {
+                "tag" => query.tags.push(value.to_lowercase()),
+                "after" => query.after = Some(parse_time(&value, now)?),
+                "before" => query.before = Some(parse_time(&value, now)?),
+                "near" => query.near = Some(value),
+                "limit" => {
+                    query.limit = value
+                        .parse()
+                        .map_err(|_| NeuroLoomError::Memory(format!("invalid limit: {}", value)))?
+                }
+                _ => match key.strip_prefix("meta.").filter(|k| !k.is_empty()) {
+                    Some(meta) => query.metadata.push((meta.to_string(), value)),
+                    None => {
+                        return Err(NeuroLoomError::Memory(format!(
+                            "unknown filter `{}:` (expected tag, after, before, near, meta.<key> or limit)",
+                            key
+                        )))
+                    }
+                },
+            }
+        }
+        Ok(query)
+    }
+
+    /// 条目是否满足全部过滤条件（不含 `near` 与 `limit`）
+    pub fn matches(&self, entry: &MemoryEntry) -> bool {
+        let tag = entry.tag.to_lowercase();
+        let summary = entry.summary.to_lowercase();
+        self.tags.iter().all(|t| tag.contains(t.as_str()))
+            && self.text.iter().all(|t| tag.contains(t.as_str()) || summary.contains(t.as_str()))
+            && self.after.is_none_or(|after| entry.created_at >= after)
+            && self.before.is_none_or(|before| entry.created_at < before)
+            && self.metadata.iter().all(|(k, v)| entry.metadata.get(k) == Some(v))
+    }
+
+    /// 在索引上执行查询；`near` 需要嵌入器才能按向量排序
+    pub async fn execute(&self, index: &HamtIndex, embedder: Option<&dyn Embedder>) -> Result<Vec<QueryHit>> {
+        let mut hits: Vec<QueryHit> = index
+            .all_entries()
+            .into_iter()
+            .filter(|e| self.matches(e))
+            .map(|entry| QueryHit { entry, score: 0.0 })
+            .collect();
+
+        if let Some(near) = &self.near {
+            let vector = match embedder {
+                Some(embedder) if hits.iter().any(|h| h.entry.embedding.is_some()) => {
+                    Some(embedder.embed(near).await?)
+                }
+                _ => None,
+            };
+            let reference = words(near);
+            for hit in &mut hits {
+                hit.score = match (&vector, &hit.entry.embedding) {
+                    (Some(a), Some(b)) => cosine(a, b).max(0.0),
+                    _ => overlap(&reference, &words(&format!("{} {}", hit.entry.tag, hit.entry.summary))),
+                };
+            }
+        }
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.entry.created_at.cmp(&a.entry.created_at))
+        });
+        hits.truncate(self.limit);
+        Ok(hits)
+    }
+}
+
+/// 拆分查询项：返回（键，值），键为空表示全文词；引号内的冒号与空白不作分隔
+fn tokenize(input: &str) -> Result<Vec<(Option<String>, String)>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(tokens);
+        }
+        let mut key = None;
+        let mut value = String::new();
+        let mut quoted = false;
+        for c in chars.by_ref() {
+            match c {
+                '"' => quoted = !quoted,
+                c if c.is_whitespace() && !quoted => break,
+                ':' if !quoted && key.is_none() && !value.is_empty() => key = Some(std::mem::take(&mut value)),
+                c => value.push(c),
+            }
+        }
+        if quoted {
+            return Err(NeuroLoomError::Memory(format!("unterminated quote in query: {}", input)));
+        }
+        if value.is_empty() {
+            if let Some(key) = key {
+                return Err(NeuroLoomError::Memory(format!("missing value for `{}:`", key)));
+            }
+            continue;
+        }
+        tokens.push((key.map(|k| k.to_lowercase()), value));
+    }
+}
+
+/// 解析时间：绝对日期（年 / 年-月 / 年-月-日 / RFC 3339）或相对当前的 `<n>h|d|w`
+fn parse_time(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let date = match value.len() {
+        4 => value.parse().ok().and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1)),
+        7 => NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d").ok(),
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok(),
+    };
+    if let Some(midnight) = date.and_then(|d| d.and_hms_opt(0, 0, 0)) {
+        return Ok(midnight.and_utc());
+    }
+    let relative = value.char_indices().last().and_then(|(i, unit)| {
+        let n: i64 = value[..i].parse().ok()?;
+        match unit {
+            'h' => Some(Duration::hours(n)),
+            'd' => Some(Duration::days(n)),
+            'w' => Some(Duration::weeks(n)),
+            _ => None,
+        }
+    });
+    relative
+        .map(|ago| now - ago)
+        .ok_or_else(|| NeuroLoomError::Memory(format!("invalid time: {}", value)))
+}
+
+/// 小写词集合
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// 参照文本的词被覆盖的比例
+fn overlap(query: &HashSet<String>, text: &HashSet<String>) -> f64 {
+    if query.is_empty() {
+        return 0.0;
+    }
+    query.intersection(text).count() as f64 / query.len() as f64
+}
+
+/// 余弦相似度（维度不同或零向量时为 0）
+fn cosine(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| *x as f64 * *y as f64).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// 按是否包含 "bucket" / "lock" 生成二维向量
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            Ok(vec![text.contains("bucket") as u8 as f32, text.contains("lock") as u8 as f32])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_filters_and_ranks_by_similarity() {
+        let now = DateTime::parse_from_rfc3339("2024-09-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let input = r#"tag:Rust after:2024-06 before:7d near:"token bucket" limit:2 meta.kind:note refill"#;
+        let query = MemoryQuery::parse_at(input, now).unwrap();
+        assert_eq!(query.tags, vec!["rust".to_string()]);
+        assert_eq!(query.after.unwrap().to_rfc3339(), "2024-06-01T00:00:00+00:00");
+        assert_eq!(query.before.unwrap().to_rfc3339(), "2024-08-25T00:00:00+00:00");
+        assert_eq!(query.near.as_deref(), Some("token bucket"));
+        assert_eq!(query.metadata, vec![("kind".to_string(), "note".to_string())]);
+        assert_eq!((query.text.clone(), query.limit), (vec!["refill".to_string()], 2));
+        assert!(MemoryQuery::parse_at("colour:red", now).is_err());
+        assert!(MemoryQuery::parse_at("near:\"open", now).is_err());
+        assert_eq!(MemoryQuery::parse_at("\"a:b\"", now).unwrap().text, vec!["a:b".to_string()]);
+
+        let index = HamtIndex::new();
+        let entry = |tag: &str, summary: &str, month: u32, embedding: Vec<f32>| {
+            let mut entry = MemoryEntry::new(tag, summary);
+            let created = NaiveDate::from_ymd_opt(2024, month, 10).unwrap().and_hms_opt(0, 0, 0).unwrap();
+            entry.created_at = created.and_utc();
+            entry.metadata.insert("kind".to_string(), "note".to_string());
+            entry.embedding = Some(embedding);
+            entry
+        };
+        index.store(entry("rust limiter", "token bucket refill rate", 7, vec![1.0, 0.0]));
+        index.store(entry("rust mutex", "lock refill order", 8, vec![0.0, 1.0]));
+        index.store(entry("rust old", "token bucket refill", 3, vec![1.0, 0.0]));
+        index.store(entry("go limiter", "token bucket refill", 7, vec![1.0, 0.0]));
+
+        let hits = query.execute(&index, Some(&KeywordEmbedder)).await.unwrap();
+        let tags: Vec<&str> = hits.iter().map(|h| h.entry.tag.as_str()).collect();
+        assert_eq!(tags, vec!["rust limiter", "rust mutex"]);
+        assert_eq!(hits[0].score, 1.0);
+
+        // 没有嵌入器时按词重叠排序
+        let hits = query.execute(&index, None).await.unwrap();
+        assert_eq!((hits[0].entry.tag.as_str(), hits[0].score), ("rust limiter", 1.0));
+        assert_eq!(hits[1].score, 0.0);
+    }
+}
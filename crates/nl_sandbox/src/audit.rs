@@ -0,0 +1,244 @@
+//! God Mode 审计日志
+//!
+//! 每个 God Mode 操作写入一条 `GodModeAction` 审计事件（执行者、操作、参数、结果摘要、策略决定）。
+//! 条目按序号以滚动哈希串联，任一条目被篡改、删除或重排都会导致校验失败。
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use nl_core::event::{Event, EventKind};
+use nl_core::Result;
+use nl_durable::EventStore;
+
+use crate::god_mode::{GodModeAction, GodModeResult};
+
+/// 链首条目的前置哈希
+pub const GENESIS_HASH: &str = "genesis";
+
+/// 审计流的实体 ID（所有审计事件归属同一条流）
+pub const AUDIT_STREAM_ID: Uuid = Uuid::nil();
+
+/// 策略决定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum PolicyDecision {
+    /// 允许执行
+    Allowed,
+    /// 拒绝执行
+    Denied { reason: String },
+    /// 经批准后执行（高风险操作）
+    Approved { by: String },
+}
+
+/// 审计条目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// 链内序号（从 1 开始）
+    pub seq: u64,
+    /// 记录时间
+    pub timestamp: DateTime<Utc>,
+    /// 执行者
+    pub actor: String,
+    /// 操作名称
+    pub action: String,
+    /// 操作参数（写入内容、环境变量值等以摘要代替）
+    pub arguments: serde_json::Value,
+    /// 策略决定
+    pub policy: PolicyDecision,
+    /// 是否执行成功
+    pub success: bool,
+    /// 结果摘要（未执行时为空）
+    pub result_hash: Option<String>,
+    /// 前一条目的哈希
+    pub prev_hash: String,
+    /// 本条目的哈希
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// 计算条目哈希（覆盖除 `hash` 外的全部字段）
+    pub fn compute_hash(&self) -> String {
+        let body = serde_json::json!([
+            self.seq,
+            self.timestamp,
+            self.actor,
+            self.action,
+            self.arguments,
+            self.policy,
+            self.success,
+            self.result_hash,
+        ]);
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(body.to_string().as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// 校验审计链，返回第一个断裂条目的序号
+pub fn verify_chain(entries: &[AuditEntry]) -> std::result::Result<(), u64> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.seq != i as u64 + 1 || entry.prev_hash != prev_hash || entry.hash != entry.compute_hash() {
+            return Err(i as u64 + 1);
+        }
+        prev_hash = entry.hash.clone();
+    }
+    Ok(())
+}
+
+/// 结果摘要
+fn result_hash(result: &GodModeResult) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(result).unwrap_or_default());
+    format!("{:x}", hasher.finalize())
+}
+
+/// 链尾状态
+struct ChainHead {
+    seq: u64,
+    hash: String,
+}
+
+/// 审计日志
+pub struct AuditLog {
+    store: Arc<Mutex<EventStore>>,
+    head: Mutex<ChainHead>,
+}
+
+impl AuditLog {
+    /// 打开审计日志，从事件存储中已有的条目接续链尾
+    pub async fn open(store: Arc<Mutex<EventStore>>) -> Result<Self> {
+        let entries = Self::load(&*store.lock().await).await?;
+        let head = match entries.last() {
+            Some(last) => ChainHead {
+                seq: last.seq,
+                hash: last.hash.clone(),
+            },
+            None => ChainHead {
+                seq: 0,
+                hash: GENESIS_HASH.to_string(),
+            },
+        };
+        Ok(Self {
+            store,
+            head: Mutex::new(head),
+        })
+    }
+
+    /// 读取全部审计条目（按序号排序）
+    pub async fn load(store: &EventStore) -> Result<Vec<AuditEntry>> {
+        let mut entries = Vec::new();
+        for event in store.get_events_by_kind(&EventKind::GodModeAction).await? {
+            entries.push(serde_json::from_value::<AuditEntry>(event.payload)?);
+        }
+        entries.sort_by_key(|e| e.seq);
+        Ok(entries)
+    }
+
+    /// 记录一次操作
+    pub async fn record(
+        &self,
+        actor: &str,
+        action: &GodModeAction,
+        policy: PolicyDecision,
+        result: Option<&GodModeResult>,
+    ) -> Result<AuditEntry> {
+        // 持锁直到写入完成，保证链顺序与落盘顺序一致
+        let mut head = self.head.lock().await;
+        let mut store = self.store.lock().await;
+
+        // 事件库写入时会脱敏，须在计算哈希前完成，否则落盘条目无法通过校验
+        let mut arguments = action.audit_arguments();
+        store.redactor().redact(&mut arguments);
+
+        let mut entry = AuditEntry {
+            seq: head.seq + 1,
+            timestamp: Utc::now(),
+            actor: actor.to_string(),
+            action: action.name().to_string(),
+            arguments,
+            policy,
+            success: result.is_some_and(|r| r.success),
+            result_hash: result.map(result_hash),
+            prev_hash: head.hash.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+
+        let event = Event::new(EventKind::GodModeAction, AUDIT_STREAM_ID, serde_json::to_value(&entry)?);
+        store.append_batch(vec![event]).await?;
+
+        head.seq = entry.seq;
+        head.hash = entry.hash.clone();
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn test_chain_detects_tampering() {
+        let store = Arc::new(Mutex::new(EventStore::new(Default::default())));
+        let log = AuditLog::open(store.clone()).await.unwrap();
+        let ok = GodModeResult {
+            success: true,
+            output: "done".to_string(),
+            error: None,
+        };
+
+        log.record(
+            "worker",
+            &GodModeAction::WriteFile {
+                path: PathBuf::from("a.txt"),
+                content: "secret".to_string(),
+            },
+            PolicyDecision::Allowed,
+            Some(&ok),
+        )
+        .await
+        .unwrap();
+        log.record(
+            "worker",
+            &GodModeAction::DeleteFile { path: PathBuf::from("a.txt") },
+            PolicyDecision::Denied {
+                reason: "disabled".to_string(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+        // 重新打开后接续链尾
+        let reopened = AuditLog::open(store.clone()).await.unwrap();
+        let execute = GodModeAction::Execute {
+            command: "curl".to_string(),
+            args: vec!["-H".to_string(), "Authorization: Bearer sk-1".to_string()],
+            cwd: None,
+        };
+        reopened
+            .record("worker", &execute, PolicyDecision::Allowed, Some(&ok))
+            .await
+            .unwrap();
+
+        let mut entries = AuditLog::load(&*store.lock().await).await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(!entries[0].arguments.to_string().contains("secret"));
+        assert!(!entries[2].arguments.to_string().contains("sk-1"));
+        assert_eq!(verify_chain(&entries), Ok(()));
+
+        entries[1].actor = "someone else".to_string();
+        assert_eq!(verify_chain(&entries), Err(2));
+
+        entries.remove(1);
+        assert_eq!(verify_chain(&entries), Err(2));
+    }
+}
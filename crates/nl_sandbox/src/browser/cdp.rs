@@ -0,0 +1,113 @@
+//! Chrome DevTools Protocol 客户端
+//!
+//! 最小实现：请求按 `id` 与响应配对，事件一律丢弃；连接断开时等待中的请求全部失败。
+//! 页面级命令经 `Target.attachToTarget`（`flatten`）得到的 `sessionId` 在同一连接上发送。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::stream::SplitSink;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use nl_core::{NeuroLoomError, Result};
+
+type Sink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<std::result::Result<Value, String>>>>>;
+
+/// 一条 DevTools WebSocket 连接
+pub(super) struct CdpClient {
+    sink: tokio::sync::Mutex<Sink>,
+    pending: Pending,
+    next_id: AtomicU64,
+    reader: JoinHandle<()>,
+    timeout: Duration,
+}
+
+impl CdpClient {
+    /// 连接到 `ws://.../devtools/browser/...`
+    pub(super) async fn connect(url: &str, timeout: Duration) -> Result<Self> {
+        let (stream, _) = tokio::time::timeout(timeout, tokio_tungstenite::connect_async(url))
+            .await
+            .map_err(|_| cdp_error(format!("connecting to {} timed out", url)))?
+            .map_err(|e| cdp_error(format!("connecting to {}: {}", url, e)))?;
+        let (sink, mut stream) = stream.split();
+        let pending: Pending = Arc::default();
+
+        let responses = pending.clone();
+        let reader = tokio::spawn(async move {
+            while let Some(Ok(message)) = stream.next().await {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let Ok(response) = serde_json::from_str::<Value>(&text) else {
+                    continue;
+                };
+                let Some(id) = response.get("id").and_then(Value::as_u64) else {
+                    continue;
+                };
+                let Some(sender) = responses.lock().unwrap().remove(&id) else {
+                    continue;
+                };
+                let result = match response.get("error") {
+                    Some(error) => Err(error["message"].as_str().unwrap_or("unknown error").to_string()),
+                    None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                let _ = sender.send(result);
+            }
+            // 丢弃发送端，等待中的请求随即失败
+            responses.lock().unwrap().clear();
+        });
+
+        Ok(Self {
+            sink: tokio::sync::Mutex::new(sink),
+            pending,
+            next_id: AtomicU64::new(1),
+            reader,
+            timeout,
+        })
+    }
+
+    /// 发送命令并等待结果（`session` 为空时发往浏览器本身）
+    pub(super) async fn call(&self, method: &str, params: Value, session: Option<&str>) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut request = serde_json::json!({ "id": id, "method": method, "params": params });
+        if let Some(session) = session {
+            request["sessionId"] = Value::String(session.to_string());
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+
+        let sent = self.sink.lock().await.send(Message::Text(request.to_string())).await;
+        if let Err(e) = sent {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(cdp_error(format!("{}: {}", method, e)));
+        }
+        match tokio::time::timeout(self.timeout, receiver).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(message))) => Err(cdp_error(format!("{}: {}", method, message))),
+            Ok(Err(_)) => Err(cdp_error(format!("{}: connection closed", method))),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(cdp_error(format!("{} timed out", method)))
+            }
+        }
+    }
+}
+
+impl Drop for CdpClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+fn cdp_error(message: String) -> NeuroLoomError {
+    NeuroLoomError::Sandbox(format!("CDP {}", message))
+}
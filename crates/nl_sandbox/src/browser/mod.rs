@@ -0,0 +1,617 @@
+//! 无头浏览器自动化
+//!
+//! 以 `GodModeAction::Browser` 形式经 Chrome DevTools Protocol 操作 Chromium 系浏览器：
+//! - 浏览器在首个操作时启动（无头模式、独立的临时用户目录），`Close` 或会话释放时结束
+//! - `Navigate` 与 HTTP 请求共用 `HttpPolicy` 的域名白名单，导航目标与最终地址都须在白名单内
+//! - `Snapshot` 读取无障碍树中的可交互元素（nl_vision `UiElement`，坐标为页面视口坐标），
+//!   或把 DOM 转为 Markdown
+//! - `Screenshot` 保存 PNG，设置了视觉流时同时送入 nl_vision 做帧差分
+//! - `Click` / `Type` 以 `#<序号>` 引用最近一次快照中的元素，与输入注入一样经 `InputPolicy`、
+//!   限流与批准者管控，再以 CDP 输入事件送达页面（不操作真实鼠标键盘）
+
+mod cdp;
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+use nl_vision::ui_elements::{BoundingBox, ElementRole, ElementSource, UiElement};
+use nl_vision::VisionStream;
+
+use crate::god_mode::GodModeResult;
+use crate::http::{HttpFetch, HttpPolicy};
+use crate::input::InputAction;
+
+use cdp::CdpClient;
+
+/// 浏览器可执行文件的环境变量
+pub const CHROME_ENV: &str = "NEUROLOOM_CHROME";
+
+/// 在 PATH 中查找的可执行文件名
+const EXECUTABLE_NAMES: &[&str] = &[
+    "google-chrome",
+    "google-chrome-stable",
+    "chromium",
+    "chromium-browser",
+    "chrome",
+    "msedge",
+];
+
+/// 常见安装位置
+const WELL_KNOWN_PATHS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+    r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+];
+
+/// 单次快照最多保留的元素数
+const MAX_ELEMENTS: usize = 200;
+
+/// 页面加载状态的轮询间隔
+const READY_POLL: Duration = Duration::from_millis(100);
+
+/// 快照内容
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotMode {
+    /// 无障碍树中的可交互元素
+    #[default]
+    Accessibility,
+    /// DOM 转 Markdown
+    Dom,
+}
+
+/// 浏览器操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BrowserAction {
+    /// 打开网址并等待加载完成
+    Navigate { url: String },
+    /// 读取当前页面
+    Snapshot {
+        #[serde(default)]
+        mode: SnapshotMode,
+    },
+    /// 截取视口
+    Screenshot,
+    /// 点击快照中的元素（高风险）
+    Click { element: usize },
+    /// 输入文本，指定元素时先点击使其获得焦点（高风险）
+    Type {
+        text: String,
+        #[serde(default)]
+        element: Option<usize>,
+    },
+    /// 结束浏览器
+    Close,
+}
+
+impl BrowserAction {
+    /// 操作名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrowserAction::Navigate { .. } => "browser_navigate",
+            BrowserAction::Snapshot { .. } => "browser_snapshot",
+            BrowserAction::Screenshot => "browser_screenshot",
+            BrowserAction::Click { .. } => "browser_click",
+            BrowserAction::Type { .. } => "browser_type",
+            BrowserAction::Close => "browser_close",
+        }
+    }
+
+    /// 是否属于高风险的输入类操作
+    pub fn is_input(&self) -> bool {
+        matches!(self, BrowserAction::Click { .. } | BrowserAction::Type { .. })
+    }
+
+    /// 审计用参数：文本只记录摘要与长度
+    pub fn audit_arguments(&self) -> serde_json::Value {
+        match self {
+            BrowserAction::Navigate { url } => serde_json::json!({ "url": url }),
+            BrowserAction::Snapshot { mode } => serde_json::json!({ "mode": mode }),
+            BrowserAction::Screenshot | BrowserAction::Close => serde_json::json!({}),
+            BrowserAction::Click { element } => serde_json::json!({ "element": element }),
+            BrowserAction::Type { text, element } => serde_json::json!({
+                "text": format!("sha256:{:x}", Sha256::digest(text.as_bytes())),
+                "chars": text.chars().count(),
+                "element": element,
+            }),
+        }
+    }
+}
+
+/// 浏览器启动配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserConfig {
+    /// 可执行文件（为空时按 `NEUROLOOM_CHROME`、PATH 与常见安装位置查找）
+    pub executable: Option<PathBuf>,
+    /// 是否无头
+    pub headless: bool,
+    /// 窗口宽度
+    pub window_width: u32,
+    /// 窗口高度
+    pub window_height: u32,
+    /// 截图保存目录
+    pub screenshot_dir: PathBuf,
+    /// 启动与单条命令的超时（秒）
+    pub timeout_secs: u64,
+}
+
+impl Default for BrowserConfig {
+    fn default() -> Self {
+        Self {
+            executable: None,
+            headless: true,
+            window_width: 1280,
+            window_height: 800,
+            screenshot_dir: std::env::temp_dir().join("neuroloom_screenshots"),
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl BrowserConfig {
+    /// 从 `NEUROLOOM_CHROME` 读取可执行文件
+    pub fn from_env() -> Self {
+        Self {
+            executable: std::env::var_os(CHROME_ENV).map(PathBuf::from),
+            ..Self::default()
+        }
+    }
+
+    /// 设置可执行文件
+    pub fn with_executable(mut self, executable: impl Into<PathBuf>) -> Self {
+        self.executable = Some(executable.into());
+        self
+    }
+
+    /// 设置截图保存目录
+    pub fn with_screenshot_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.screenshot_dir = dir.into();
+        self
+    }
+
+    /// 显示浏览器窗口（调试用）
+    pub fn headed(mut self) -> Self {
+        self.headless = false;
+        self
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// 运行中的浏览器与所附着的页面
+struct Browser {
+    process: Child,
+    profile: PathBuf,
+    client: CdpClient,
+    session_id: String,
+}
+
+impl Drop for Browser {
+    fn drop(&mut self) {
+        let _ = self.process.start_kill();
+        let _ = std::fs::remove_dir_all(&self.profile);
+    }
+}
+
+impl Browser {
+    async fn launch(config: &BrowserConfig) -> Result<Self> {
+        let executable = config
+            .executable
+            .clone()
+            .or_else(find_executable)
+            .ok_or_else(|| NeuroLoomError::Sandbox(format!("no Chromium-based browser found; set {}", CHROME_ENV)))?;
+        let profile = std::env::temp_dir().join(format!("nl-browser-{}", Uuid::new_v4().simple()));
+
+        let mut command = Command::new(&executable);
+        command
+            .arg("--remote-debugging-port=0")
+            .arg(format!("--user-data-dir={}", profile.display()))
+            .arg(format!("--window-size={},{}", config.window_width, config.window_height))
+            .args(["--no-first-run", "--no-default-browser-check", "--disable-extensions"]);
+        if config.headless {
+            command.arg("--headless=new");
+        }
+        command
+            .arg("about:blank")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut process = command
+            .spawn()
+            .map_err(|e| NeuroLoomError::Sandbox(format!("failed to start {}: {}", executable.display(), e)))?;
+        let stderr = process.stderr.take().expect("stderr is piped");
+        let url = tokio::time::timeout(config.timeout(), devtools_url(stderr))
+            .await
+            .map_err(|_| NeuroLoomError::Sandbox("browser did not expose DevTools in time".to_string()))??;
+
+        let client = CdpClient::connect(&url, config.timeout()).await?;
+        let target = client
+            .call("Target.createTarget", serde_json::json!({ "url": "about:blank" }), None)
+            .await?;
+        let attached = client
+            .call(
+                "Target.attachToTarget",
+                serde_json::json!({ "targetId": target["targetId"], "flatten": true }),
+                None,
+            )
+            .await?;
+        let session_id = attached["sessionId"]
+            .as_str()
+            .ok_or_else(|| NeuroLoomError::Sandbox("CDP Target.attachToTarget returned no session".to_string()))?
+            .to_string();
+        let browser = Self {
+            process,
+            profile,
+            client,
+            session_id,
+        };
+        for domain in ["Page.enable", "DOM.enable", "Accessibility.enable"] {
+            browser.call(domain, serde_json::json!({})).await?;
+        }
+        Ok(browser)
+    }
+
+    /// 发往页面的命令
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        self.client.call(method, params, Some(&self.session_id)).await
+    }
+
+    /// 在页面中求值（结果按值返回）
+    async fn evaluate(&self, expression: &str) -> Result<Value> {
+        let result = self
+            .call(
+                "Runtime.evaluate",
+                serde_json::json!({ "expression": expression, "returnByValue": true, "awaitPromise": true }),
+            )
+            .await?;
+        if let Some(exception) = result.get("exceptionDetails") {
+            return Err(NeuroLoomError::Sandbox(format!(
+                "page script failed: {}",
+                exception["text"].as_str().unwrap_or("exception")
+            )));
+        }
+        Ok(result["result"]["value"].clone())
+    }
+
+    async fn location(&self) -> Result<(String, String)> {
+        let page = self.evaluate("[location.href, document.title]").await?;
+        Ok((
+            page[0].as_str().unwrap_or_default().to_string(),
+            page[1].as_str().unwrap_or_default().to_string(),
+        ))
+    }
+
+    async fn navigate(&self, url: &str, timeout: Duration) -> Result<(String, String)> {
+        let navigated = self.call("Page.navigate", serde_json::json!({ "url": url })).await?;
+        if let Some(error) = navigated["errorText"].as_str() {
+            return Err(NeuroLoomError::Sandbox(format!("navigation to {} failed: {}", url, error)));
+        }
+        let started = Instant::now();
+        while self.evaluate("document.readyState").await?.as_str() != Some("complete") {
+            if started.elapsed() > timeout {
+                return Err(NeuroLoomError::Sandbox(format!("loading {} timed out", url)));
+            }
+            tokio::time::sleep(READY_POLL).await;
+        }
+        self.location().await
+    }
+
+    /// 无障碍树中的可交互元素，连同视口坐标
+    async fn elements(&self) -> Result<Vec<UiElement>> {
+        let tree = self.call("Accessibility.getFullAXTree", serde_json::json!({})).await?;
+        let mut elements = Vec::new();
+        for node in tree["nodes"].as_array().into_iter().flatten() {
+            if node["ignored"].as_bool() == Some(true) {
+                continue;
+            }
+            let Some(role) = node["role"]["value"].as_str().and_then(page_role) else {
+                continue;
+            };
+            let Some(backend_id) = node["backendDOMNodeId"].as_u64() else {
+                continue;
+            };
+            // 不可见的节点没有盒模型
+            let Ok(model) = self
+                .call("DOM.getBoxModel", serde_json::json!({ "backendNodeId": backend_id }))
+                .await
+            else {
+                continue;
+            };
+            let Some(bounds) = quad_bounds(&model["model"]["border"]) else {
+                continue;
+            };
+            let property = |name: &str| {
+                node["properties"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|p| p["name"] == name)
+                    .and_then(|p| p["value"]["value"].as_bool())
+                    .unwrap_or(false)
+            };
+            let value = match &node["value"]["value"] {
+                Value::String(value) => Some(value.clone()),
+                Value::Number(value) => Some(value.to_string()),
+                _ => None,
+            };
+            elements.push(UiElement {
+                index: elements.len(),
+                role,
+                name: node["name"]["value"].as_str().unwrap_or_default().trim().to_string(),
+                value,
+                bounds,
+                enabled: !property("disabled"),
+                focused: property("focused"),
+                source: ElementSource::Accessibility,
+                ocr_text: None,
+            });
+            if elements.len() >= MAX_ELEMENTS {
+                break;
+            }
+        }
+        Ok(elements)
+    }
+
+    async fn click(&self, (x, y): (i32, i32)) -> Result<()> {
+        for kind in ["mouseMoved", "mousePressed", "mouseReleased"] {
+            self.call(
+                "Input.dispatchMouseEvent",
+                serde_json::json!({ "type": kind, "x": x, "y": y, "button": "left", "clickCount": 1 }),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// 浏览器会话
+///
+/// 同一时刻只有一个页面；元素序号在下一次快照或导航前有效。
+pub struct BrowserSession {
+    config: BrowserConfig,
+    vision: Option<Arc<tokio::sync::Mutex<VisionStream>>>,
+    browser: tokio::sync::Mutex<Option<Browser>>,
+    elements: Mutex<Vec<UiElement>>,
+}
+
+impl BrowserSession {
+    /// 创建会话（浏览器在首个操作时启动）
+    pub fn new(config: BrowserConfig) -> Self {
+        Self {
+            config,
+            vision: None,
+            browser: tokio::sync::Mutex::new(None),
+            elements: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 截图同时送入视觉流
+    pub fn with_vision(mut self, vision: Arc<tokio::sync::Mutex<VisionStream>>) -> Self {
+        self.vision = Some(vision);
+        self
+    }
+
+    /// 最近一次快照中的元素
+    pub fn element(&self, index: usize) -> Option<UiElement> {
+        self.elements.lock().unwrap().get(index).cloned()
+    }
+
+    /// 输入类操作对应的 `InputAction`（供策略检查与批准），引用的元素不存在时返回原因
+    pub fn input_action(&self, action: &BrowserAction) -> std::result::Result<Option<InputAction>, String> {
+        let element = |index: usize| {
+            self.element(index)
+                .ok_or_else(|| format!("element #{} is not in the last snapshot", index))
+        };
+        Ok(match action {
+            BrowserAction::Click { element: index } => Some(InputAction::click_element(&element(*index)?)),
+            BrowserAction::Type { text, element: index } => Some(InputAction::TypeText {
+                text: text.clone(),
+                target: index.map(element).transpose()?,
+            }),
+            _ => None,
+        })
+    }
+
+    /// 执行操作（`policy` 用于校验导航后的最终地址）
+    pub async fn execute(&self, action: &BrowserAction, policy: &HttpPolicy) -> GodModeResult {
+        match self.run(action, policy).await {
+            Ok(output) => GodModeResult {
+                success: true,
+                output,
+                error: None,
+            },
+            Err(e) => GodModeResult {
+                success: false,
+                output: String::new(),
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    async fn run(&self, action: &BrowserAction, policy: &HttpPolicy) -> Result<String> {
+        let mut guard = self.browser.lock().await;
+        if let BrowserAction::Close = action {
+            self.elements.lock().unwrap().clear();
+            return Ok(match guard.take() {
+                Some(_) => "Browser closed".to_string(),
+                None => "Browser was not running".to_string(),
+            });
+        }
+        if guard.is_none() {
+            *guard = Some(Browser::launch(&self.config).await?);
+        }
+        let browser = guard.as_ref().expect("browser is running");
+
+        match action {
+            BrowserAction::Navigate { url } => {
+                self.elements.lock().unwrap().clear();
+                let (location, title) = browser.navigate(url, self.config.timeout()).await?;
+                // 重定向或脚本跳转可能离开白名单
+                if let Err(reason) = policy.check(&HttpFetch::get(location.clone())) {
+                    let _ = browser.call("Page.navigate", serde_json::json!({ "url": "about:blank" })).await;
+                    return Err(NeuroLoomError::Sandbox(format!("navigated to {}: {}", location, reason)));
+                }
+                Ok(serde_json::json!({ "url": location, "title": title }).to_string())
+            }
+            BrowserAction::Snapshot { mode: SnapshotMode::Accessibility } => {
+                let (location, title) = browser.location().await?;
+                let elements = browser.elements().await?;
+                let mut text = format!("Page \"{}\" ({})\n", title, location);
+                for element in &elements {
+                    text.push_str(&element.describe());
+                    text.push('\n');
+                }
+                *self.elements.lock().unwrap() = elements;
+                Ok(text)
+            }
+            BrowserAction::Snapshot { mode: SnapshotMode::Dom } => {
+                let html = browser.evaluate("document.documentElement.outerHTML").await?;
+                let html = html.as_str().unwrap_or_default().to_string();
+                tokio::task::spawn_blocking(move || crate::http::html_to_markdown(&html))
+                    .await
+                    .map_err(|e| NeuroLoomError::Sandbox(e.to_string()))
+            }
+            BrowserAction::Screenshot => {
+                let shot = browser.call("Page.captureScreenshot", serde_json::json!({ "format": "png" })).await?;
+                let png = base64::engine::general_purpose::STANDARD
+                    .decode(shot["data"].as_str().unwrap_or_default())
+                    .map_err(|e| NeuroLoomError::Sandbox(format!("invalid screenshot data: {}", e)))?;
+                tokio::fs::create_dir_all(&self.config.screenshot_dir).await?;
+                let path = self.config.screenshot_dir.join(format!(
+                    "{}-{}.png",
+                    chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+                    &Uuid::new_v4().simple().to_string()[..8]
+                ));
+                tokio::fs::write(&path, &png).await?;
+                let diff = match &self.vision {
+                    Some(vision) => Some(vision.lock().await.capture(&png).await?),
+                    None => None,
+                };
+                Ok(serde_json::json!({ "path": path, "bytes": png.len(), "diff": diff }).to_string())
+            }
+            BrowserAction::Click { element } => {
+                let target = self.resolve(*element)?;
+                browser.click(target.bounds.center()).await?;
+                Ok(format!("Clicked {}", target.describe()))
+            }
+            BrowserAction::Type { text, element } => {
+                let target = element.map(|index| self.resolve(index)).transpose()?;
+                if let Some(target) = &target {
+                    browser.click(target.bounds.center()).await?;
+                }
+                browser.call("Input.insertText", serde_json::json!({ "text": text })).await?;
+                Ok(match target {
+                    Some(target) => format!("Typed {} chars into {}", text.chars().count(), target.describe()),
+                    None => format!("Typed {} chars", text.chars().count()),
+                })
+            }
+            BrowserAction::Close => unreachable!("handled above"),
+        }
+    }
+
+    fn resolve(&self, index: usize) -> Result<UiElement> {
+        self.element(index)
+            .ok_or_else(|| NeuroLoomError::Sandbox(format!("element #{} is not in the last snapshot", index)))
+    }
+}
+
+/// 读取 stderr 直到 `DevTools listening on ws://...`，之后继续排空以免浏览器阻塞在写满的管道上
+async fn devtools_url(stderr: ChildStderr) -> Result<String> {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(url) = parse_devtools_line(&line) {
+            tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+            return Ok(url);
+        }
+    }
+    Err(NeuroLoomError::Sandbox("browser exited before DevTools was ready".to_string()))
+}
+
+fn parse_devtools_line(line: &str) -> Option<String> {
+    let url = line.trim().strip_prefix("DevTools listening on ")?;
+    url.starts_with("ws://").then(|| url.to_string())
+}
+
+fn find_executable() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let suffix = if cfg!(windows) { ".exe" } else { "" };
+    std::env::split_paths(&path)
+        .flat_map(|dir| EXECUTABLE_NAMES.iter().map(move |name| dir.join(format!("{}{}", name, suffix))))
+        .chain(WELL_KNOWN_PATHS.iter().map(PathBuf::from))
+        .find(|candidate| candidate.is_file())
+}
+
+/// 可交互的页面角色（ARIA / Chromium 无障碍角色名）
+fn page_role(role: &str) -> Option<ElementRole> {
+    Some(match role {
+        "button" => ElementRole::Button,
+        "textbox" | "searchbox" | "textField" | "searchBox" => ElementRole::TextField,
+        "checkbox" | "switch" | "checkBox" => ElementRole::CheckBox,
+        "radio" | "radioButton" => ElementRole::RadioButton,
+        "combobox" | "listbox" | "comboBoxSelect" | "comboBoxMenuButton" => ElementRole::ComboBox,
+        "menuitem" | "menuitemcheckbox" | "menuitemradio" | "menuItem" => ElementRole::MenuItem,
+        "link" => ElementRole::Link,
+        "tab" => ElementRole::Tab,
+        "option" | "listBoxOption" => ElementRole::ListItem,
+        "slider" | "spinbutton" | "spinButton" => ElementRole::Other(role.to_lowercase()),
+        _ => return None,
+    })
+}
+
+/// 盒模型四边形（`[x1, y1, ..., x4, y4]`）的外接矩形，零面积时为空
+fn quad_bounds(quad: &Value) -> Option<BoundingBox> {
+    let points: Vec<f64> = quad.as_array()?.iter().filter_map(Value::as_f64).collect();
+    if points.len() != 8 {
+        return None;
+    }
+    let xs = points.iter().step_by(2);
+    let ys = points.iter().skip(1).step_by(2);
+    let (left, right) = xs.fold((f64::MAX, f64::MIN), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+    let (top, bottom) = ys.fold((f64::MAX, f64::MIN), |(lo, hi), &y| (lo.min(y), hi.max(y)));
+    let (width, height) = ((right - left).round() as u32, (bottom - top).round() as u32);
+    (width > 0 && height > 0).then(|| BoundingBox::new(left.round() as i32, top.round() as i32, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_parsing_helpers() {
+        assert_eq!(
+            parse_devtools_line("DevTools listening on ws://127.0.0.1:39211/devtools/browser/ab-cd\n").as_deref(),
+            Some("ws://127.0.0.1:39211/devtools/browser/ab-cd")
+        );
+        assert!(parse_devtools_line("[0101/000000.000:ERROR:gpu_init.cc] failed").is_none());
+
+        assert_eq!(page_role("textbox"), Some(ElementRole::TextField));
+        assert_eq!(page_role("radio"), Some(ElementRole::RadioButton));
+        assert_eq!(page_role("StaticText"), None);
+
+        let quad = serde_json::json!([10.4, 20.0, 110.4, 20.0, 110.4, 50.0, 10.4, 50.0]);
+        let bounds = quad_bounds(&quad).unwrap();
+        assert_eq!((bounds.x, bounds.y, bounds.width, bounds.height), (10, 20, 100, 30));
+        assert!(quad_bounds(&serde_json::json!([0, 0, 0, 0, 0, 0, 0, 0])).is_none());
+
+        let session = BrowserSession::new(BrowserConfig::default());
+        assert!(session.input_action(&BrowserAction::Click { element: 0 }).is_err());
+        assert!(session.input_action(&BrowserAction::Screenshot).unwrap().is_none());
+    }
+}
@@ -0,0 +1,201 @@
+//! 沙箱能力授权
+//!
+//! 并非每个 Worker 都应拥有完整的 God Mode。`WorkerProfile` 携带一组能力授权
+//! （`fs-read`、`fs-write`、`exec`、`network`、`input-injection`），`SandboxExecutor` 在分发
+//! `*_for` 入口的操作前按 Actor 的画像检查：操作所需的能力未全部授予时拒绝执行，
+//! 拒绝作为 `Denied` 策略决定写入审计链。未登记画像的 Actor 使用执行器的默认画像（默认授予全部能力）。
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use nl_core::NeuroLoomError;
+
+use crate::browser::BrowserAction;
+use crate::git::GitAction;
+use crate::god_mode::GodModeAction;
+
+/// 沙箱能力
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    /// 读取文件、列出目录、查看仓库状态与环境变量
+    FsRead,
+    /// 写入 / 删除文件、应用补丁、提交
+    FsWrite,
+    /// 执行命令、修改环境变量
+    Exec,
+    /// HTTP 请求、数据库连接、浏览器与 Git 推送
+    Network,
+    /// 鼠标 / 键盘输入与浏览器中的点击、输入
+    InputInjection,
+}
+
+impl Capability {
+    /// 全部能力
+    pub const ALL: [Capability; 5] = [
+        Capability::FsRead,
+        Capability::FsWrite,
+        Capability::Exec,
+        Capability::Network,
+        Capability::InputInjection,
+    ];
+
+    /// 能力名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::FsRead => "fs-read",
+            Capability::FsWrite => "fs-write",
+            Capability::Exec => "exec",
+            Capability::Network => "network",
+            Capability::InputInjection => "input-injection",
+        }
+    }
+
+    /// 操作所需的能力
+    pub fn required_by(action: &GodModeAction) -> BTreeSet<Capability> {
+        use Capability::*;
+        let required: &[Capability] = match action {
+            GodModeAction::ReadFile { .. } | GodModeAction::ListDir { .. } | GodModeAction::GetEnv { .. } => {
+                &[FsRead]
+            }
+            GodModeAction::WriteFile { .. }
+            | GodModeAction::DeleteFile { .. }
+            | GodModeAction::CreateDir { .. }
+            | GodModeAction::ApplyPatch { .. } => &[FsWrite],
+            GodModeAction::Execute { .. } | GodModeAction::SetEnv { .. } => &[Exec],
+            GodModeAction::Git(GitAction::Status { .. } | GitAction::Diff { .. }) => &[FsRead],
+            GodModeAction::Git(GitAction::Push { .. }) => &[FsRead, Network],
+            GodModeAction::Git(_) => &[FsWrite],
+            GodModeAction::Input(_) => &[InputInjection],
+            GodModeAction::QueryDatabase(_) | GodModeAction::HttpFetch(_) => &[Network],
+            GodModeAction::Browser(BrowserAction::Click { .. } | BrowserAction::Type { .. }) => {
+                &[Network, InputInjection]
+            }
+            GodModeAction::Browser(_) => &[Network],
+        };
+        required.iter().copied().collect()
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Capability {
+    type Err = NeuroLoomError;
+
+    fn from_str(s: &str) -> nl_core::Result<Self> {
+        Capability::ALL
+            .into_iter()
+            .find(|c| c.as_str() == s.trim())
+            .ok_or_else(|| NeuroLoomError::Sandbox(format!("unknown sandbox capability: {}", s)))
+    }
+}
+
+/// Worker 画像：名称与沙箱能力授权
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerProfile {
+    pub name: String,
+    /// 授予的能力
+    #[serde(default)]
+    pub grants: BTreeSet<Capability>,
+}
+
+impl WorkerProfile {
+    /// 不授予任何能力的画像
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            grants: BTreeSet::new(),
+        }
+    }
+
+    /// 授予全部能力（完整的 God Mode）
+    pub fn god_mode(name: impl Into<String>) -> Self {
+        Self::new(name).with_grants(Capability::ALL)
+    }
+
+    /// 只读画像（读取文件与仓库状态）
+    pub fn read_only(name: impl Into<String>) -> Self {
+        Self::new(name).with_grant(Capability::FsRead)
+    }
+
+    /// 授予能力
+    pub fn with_grant(mut self, capability: Capability) -> Self {
+        self.grants.insert(capability);
+        self
+    }
+
+    /// 授予多项能力
+    pub fn with_grants(mut self, capabilities: impl IntoIterator<Item = Capability>) -> Self {
+        self.grants.extend(capabilities);
+        self
+    }
+
+    /// 是否授予了该能力
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.grants.contains(&capability)
+    }
+
+    /// 操作所需但未授予的能力
+    pub fn missing(&self, action: &GodModeAction) -> Vec<Capability> {
+        Capability::required_by(action)
+            .into_iter()
+            .filter(|c| !self.allows(*c))
+            .collect()
+    }
+
+    /// 检查操作，未授予时返回拒绝原因
+    pub fn check(&self, action: &GodModeAction) -> Result<(), String> {
+        let missing = self.missing(action);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let missing: Vec<&str> = missing.iter().map(Capability::as_str).collect();
+        Err(format!(
+            "worker profile {} is not granted {} (required by {})",
+            self.name,
+            missing.join(", "),
+            action.name()
+        ))
+    }
+}
+
+impl Default for WorkerProfile {
+    fn default() -> Self {
+        Self::god_mode("default")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_profile_checks_required_capabilities() {
+        let reviewer = WorkerProfile::read_only("reviewer");
+        let read = GodModeAction::ReadFile { path: PathBuf::from("src/lib.rs") };
+        let push = GodModeAction::Git(GitAction::Push {
+            repo: PathBuf::from("."),
+            remote: "origin".to_string(),
+            branch: "main".to_string(),
+        });
+        assert!(reviewer.check(&read).is_ok());
+        assert_eq!(reviewer.missing(&push), vec![Capability::Network]);
+        let reason = reviewer.check(&push).unwrap_err();
+        assert!(reason.contains("reviewer") && reason.contains("network"), "{}", reason);
+
+        let click = GodModeAction::Browser(BrowserAction::Click { element: 3 });
+        let browsing = WorkerProfile::new("researcher").with_grant(Capability::Network);
+        assert_eq!(browsing.missing(&click), vec![Capability::InputInjection]);
+        assert!(WorkerProfile::default().check(&click).is_ok());
+        assert_eq!("input-injection".parse::<Capability>().unwrap(), Capability::InputInjection);
+        assert!("root".parse::<Capability>().is_err());
+    }
+}
@@ -0,0 +1,498 @@
+//! 数据库查询
+//!
+//! 以 `GodModeAction::QueryDatabase` 形式执行的 SQL 查询（SQLite / Postgres / MySQL，按连接串前缀区分），
+//! 结果以结构化 JSON（列名、类型与逐行的值）返回，便于直接放进提示词：
+//! - `DatabasePolicy` 默认只读：`Query` 只接受单条 `SELECT` / `WITH` 语句，且连接本身也切换为只读
+//!   （SQLite 以只读模式打开，Postgres / MySQL 设置会话级只读事务）
+//! - 行数与序列化后的字节数受上限约束，超出部分截断并标记 `truncated`
+//! - `ListTables` / `DescribeTable` 按方言查询系统目录，不经只读检查
+//! - 审计参数中连接串的密码被隐去
+
+use std::str::FromStr;
+
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::mysql::{MySqlConnection, MySqlRow};
+use sqlx::postgres::{PgConnection, PgRow};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqliteRow};
+use sqlx::{Column, ColumnIndex, Connection, Database, Decode, Executor, IntoArguments, Row, Type, TypeInfo};
+
+use crate::god_mode::GodModeResult;
+
+/// 只读模式下拒绝的关键字（出现在字符串与注释之外时）
+const WRITE_KEYWORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "UPSERT", "REPLACE", "CREATE", "DROP", "ALTER", "TRUNCATE", "GRANT",
+    "REVOKE", "ATTACH", "DETACH", "PRAGMA", "VACUUM", "COPY", "CALL", "EXEC", "EXECUTE", "LOCK", "INTO",
+];
+
+/// 数据库操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DatabaseAction {
+    /// 执行 SQL（`max_rows` 只能收紧策略中的行数上限）
+    Query {
+        url: String,
+        sql: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_rows: Option<usize>,
+    },
+    /// 列出表与视图
+    ListTables { url: String },
+    /// 表的列定义
+    DescribeTable { url: String, table: String },
+}
+
+impl DatabaseAction {
+    /// 操作名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            DatabaseAction::Query { .. } => "db_query",
+            DatabaseAction::ListTables { .. } => "db_list_tables",
+            DatabaseAction::DescribeTable { .. } => "db_describe_table",
+        }
+    }
+
+    /// 审计用参数（连接串隐去密码）
+    pub fn audit_arguments(&self) -> serde_json::Value {
+        match self {
+            DatabaseAction::Query { url, sql, max_rows } => {
+                serde_json::json!({ "url": redact_url(url), "sql": sql, "max_rows": max_rows })
+            }
+            DatabaseAction::ListTables { url } => serde_json::json!({ "url": redact_url(url) }),
+            DatabaseAction::DescribeTable { url, table } => {
+                serde_json::json!({ "url": redact_url(url), "table": table })
+            }
+        }
+    }
+
+    fn url(&self) -> &str {
+        match self {
+            DatabaseAction::Query { url, .. }
+            | DatabaseAction::ListTables { url }
+            | DatabaseAction::DescribeTable { url, .. } => url,
+        }
+    }
+}
+
+/// 数据库策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabasePolicy {
+    /// 只读（拒绝非 SELECT 语句）
+    pub read_only: bool,
+    /// 返回的最大行数
+    pub max_rows: usize,
+    /// 返回行序列化后的最大字节数
+    pub max_bytes: usize,
+}
+
+impl Default for DatabasePolicy {
+    fn default() -> Self {
+        Self {
+            read_only: true,
+            max_rows: 200,
+            max_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl DatabasePolicy {
+    /// 允许写入语句
+    pub fn allowing_writes(mut self) -> Self {
+        self.read_only = false;
+        self
+    }
+
+    /// 检查操作，拒绝时返回原因
+    pub fn check(&self, action: &DatabaseAction) -> Result<(), String> {
+        Dialect::from_url(action.url())?;
+        match action {
+            DatabaseAction::Query { sql, .. } if self.read_only => check_read_only(sql),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 查询结果的列
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryColumn {
+    pub name: String,
+    /// 数据库报告的类型名
+    pub type_name: String,
+}
+
+/// 查询结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryOutput {
+    /// 列（结果为空时为空）
+    pub columns: Vec<QueryColumn>,
+    /// 逐行的值，与 `columns` 一一对应
+    pub rows: Vec<Vec<Value>>,
+    /// 是否因行数或字节数上限截断
+    pub truncated: bool,
+}
+
+/// SQL 方言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Sqlite,
+    Postgres,
+    MySql,
+}
+
+impl Dialect {
+    fn from_url(url: &str) -> Result<Self, String> {
+        let scheme = url.split(':').next().unwrap_or_default().to_ascii_lowercase();
+        match scheme.as_str() {
+            "sqlite" => Ok(Dialect::Sqlite),
+            "postgres" | "postgresql" => Ok(Dialect::Postgres),
+            "mysql" | "mariadb" => Ok(Dialect::MySql),
+            _ => Err(format!("unsupported database url scheme: {}", scheme)),
+        }
+    }
+
+    fn tables_sql(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => {
+                "SELECT name, type FROM sqlite_master \
+                 WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name"
+            }
+            Dialect::Postgres => {
+                "SELECT table_schema::text AS schema, table_name::text AS name, table_type::text AS type \
+                 FROM information_schema.tables \
+                 WHERE table_schema NOT IN ('pg_catalog', 'information_schema') ORDER BY 1, 2"
+            }
+            Dialect::MySql => {
+                "SELECT CAST(table_name AS CHAR) AS name, CAST(table_type AS CHAR) AS type \
+                 FROM information_schema.tables WHERE table_schema = DATABASE() ORDER BY 1"
+            }
+        }
+    }
+
+    fn columns_sql(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => {
+                "SELECT name, type, \"notnull\" = 0 AS nullable, dflt_value AS default_value, \
+                 pk > 0 AS primary_key FROM pragma_table_info(?) ORDER BY cid"
+            }
+            Dialect::Postgres => {
+                "SELECT column_name::text AS name, data_type::text AS type, is_nullable = 'YES' AS nullable, \
+                 column_default::text AS default_value FROM information_schema.columns \
+                 WHERE table_name = $1 AND table_schema NOT IN ('pg_catalog', 'information_schema') \
+                 ORDER BY ordinal_position"
+            }
+            Dialect::MySql => {
+                "SELECT CAST(column_name AS CHAR) AS name, CAST(column_type AS CHAR) AS type, \
+                 is_nullable = 'YES' AS nullable, CAST(column_default AS CHAR) AS default_value, \
+                 column_key = 'PRI' AS primary_key FROM information_schema.columns \
+                 WHERE table_schema = DATABASE() AND table_name = ? ORDER BY ordinal_position"
+            }
+        }
+    }
+}
+
+/// 执行数据库操作（策略检查由调用方完成）
+pub async fn execute(action: &DatabaseAction, policy: &DatabasePolicy) -> GodModeResult {
+    match run(action, policy).await {
+        Ok(output) => GodModeResult {
+            success: true,
+            output: serde_json::to_string(&output).unwrap_or_default(),
+            error: None,
+        },
+        Err(error) => GodModeResult {
+            success: false,
+            output: String::new(),
+            error: Some(error),
+        },
+    }
+}
+
+async fn run(action: &DatabaseAction, policy: &DatabasePolicy) -> Result<QueryOutput, String> {
+    let dialect = Dialect::from_url(action.url())?;
+    let mut limits = (policy.max_rows, policy.max_bytes);
+    let (sql, table) = match action {
+        DatabaseAction::Query { sql, max_rows, .. } => {
+            limits.0 = max_rows.map_or(limits.0, |rows| rows.min(limits.0));
+            (sql.as_str(), None)
+        }
+        DatabaseAction::ListTables { .. } => (dialect.tables_sql(), None),
+        DatabaseAction::DescribeTable { table, .. } => (dialect.columns_sql(), Some(table.as_str())),
+    };
+
+    let url = action.url();
+    let output = match dialect {
+        Dialect::Sqlite => {
+            let options = SqliteConnectOptions::from_str(url)
+                .map_err(db_error)?
+                .read_only(policy.read_only);
+            let mut conn = SqliteConnection::connect_with(&options).await.map_err(db_error)?;
+            let output = fetch::<sqlx::Sqlite>(&mut conn, sql, table, limits).await;
+            let _ = conn.close().await;
+            output
+        }
+        Dialect::Postgres => {
+            let mut conn = PgConnection::connect(url).await.map_err(db_error)?;
+            if policy.read_only {
+                conn.execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+                    .await
+                    .map_err(db_error)?;
+            }
+            let output = fetch::<sqlx::Postgres>(&mut conn, sql, table, limits).await;
+            let _ = conn.close().await;
+            output
+        }
+        Dialect::MySql => {
+            let mut conn = MySqlConnection::connect(url).await.map_err(db_error)?;
+            if policy.read_only {
+                conn.execute("SET SESSION TRANSACTION READ ONLY").await.map_err(db_error)?;
+            }
+            let output = fetch::<sqlx::MySql>(&mut conn, sql, table, limits).await;
+            let _ = conn.close().await;
+            output
+        }
+    };
+    output.map_err(db_error)
+}
+
+/// 逐行读取直到行数或字节数上限
+async fn fetch<DB>(
+    conn: &mut DB::Connection,
+    sql: &str,
+    bind: Option<&str>,
+    (max_rows, max_bytes): (usize, usize),
+) -> Result<QueryOutput, sqlx::Error>
+where
+    DB: Database,
+    DB::Row: JsonRow,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as sqlx::database::HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> &'q str: sqlx::Encode<'q, DB> + Type<DB>,
+{
+    let mut query = sqlx::query::<DB>(sql);
+    if let Some(value) = bind {
+        query = query.bind(value);
+    }
+    let mut rows = query.fetch(&mut *conn);
+    let mut output = QueryOutput::default();
+    let mut bytes = 0;
+    while let Some(row) = rows.try_next().await? {
+        if output.columns.is_empty() {
+            output.columns = row
+                .columns()
+                .iter()
+                .map(|column| QueryColumn {
+                    name: column.name().to_string(),
+                    type_name: column.type_info().name().to_string(),
+                })
+                .collect();
+        }
+        let values: Vec<Value> = (0..row.columns().len()).map(|index| row.cell(index)).collect();
+        bytes += serde_json::to_string(&values).map(|json| json.len()).unwrap_or_default();
+        if output.rows.len() >= max_rows || bytes > max_bytes {
+            output.truncated = true;
+            break;
+        }
+        output.rows.push(values);
+    }
+    Ok(output)
+}
+
+/// 按列解码为 JSON 的行
+trait JsonRow: Row {
+    fn cell(&self, index: usize) -> Value;
+}
+
+/// 按给定顺序逐个尝试类型，都不匹配时记为 `<类型名>`
+macro_rules! impl_json_row {
+    ($($row:ty => [$($ty:ty),+];)+) => {$(
+        impl JsonRow for $row {
+            fn cell(&self, index: usize) -> Value {
+                None$(.or_else(|| decode::<_, $ty>(self, index)))+
+                    .or_else(|| decode_bytes(self, index))
+                    .unwrap_or_else(|| Value::String(format!("<{}>", self.column(index).type_info().name())))
+            }
+        }
+    )+};
+}
+
+impl_json_row! {
+    SqliteRow => [i64, f64, bool, String];
+    PgRow => [
+        i64, i32, i16, f64, f32, bool, String, Value, uuid::Uuid,
+        chrono::DateTime<chrono::Utc>, chrono::NaiveDateTime, chrono::NaiveDate, chrono::NaiveTime
+    ];
+    MySqlRow => [
+        i64, u64, f64, f32, bool, String, Value,
+        chrono::DateTime<chrono::Utc>, chrono::NaiveDateTime, chrono::NaiveDate, chrono::NaiveTime
+    ];
+}
+
+fn decode<'r, R, T>(row: &'r R, index: usize) -> Option<Value>
+where
+    R: Row,
+    usize: ColumnIndex<R>,
+    T: Decode<'r, R::Database> + Type<R::Database> + Serialize,
+{
+    let value = row.try_get::<Option<T>, _>(index).ok()?;
+    Some(serde_json::to_value(value).unwrap_or_default())
+}
+
+/// 二进制值按 UTF-8 解码，否则只记录长度
+fn decode_bytes<'r, R>(row: &'r R, index: usize) -> Option<Value>
+where
+    R: Row,
+    usize: ColumnIndex<R>,
+    Vec<u8>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    Some(match row.try_get::<Option<Vec<u8>>, _>(index).ok()? {
+        None => Value::Null,
+        Some(bytes) => match String::from_utf8(bytes) {
+            Ok(text) => Value::String(text),
+            Err(e) => Value::String(format!("<{} bytes>", e.as_bytes().len())),
+        },
+    })
+}
+
+/// 只读检查：单条 `SELECT` / `WITH` 语句，且字符串与注释之外不含写入关键字
+pub fn check_read_only(sql: &str) -> Result<(), String> {
+    let code = strip_literals(sql);
+    let mut statements = code.split(';').filter(|statement| !statement.trim().is_empty());
+    let Some(statement) = statements.next() else {
+        return Err("empty SQL statement".to_string());
+    };
+    if statements.next().is_some() {
+        return Err("only a single statement is allowed in read-only mode".to_string());
+    }
+    let mut words = statement
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_uppercase());
+    if !matches!(words.next().as_deref(), Some("SELECT" | "WITH")) {
+        return Err("only SELECT statements are allowed in read-only mode".to_string());
+    }
+    match words.find(|word| WRITE_KEYWORDS.contains(&word.as_str())) {
+        Some(keyword) => Err(format!("{} is not allowed in read-only mode", keyword)),
+        None => Ok(()),
+    }
+}
+
+/// 把字符串、带引号的标识符与注释替换为空格
+fn strip_literals(sql: &str) -> String {
+    let mut code = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                // 连续两个引号为转义，循环会把它当作结束后紧接着的新字符串，结果相同
+                for next in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+                code.push(' ');
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+                code.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+                code.push(' ');
+            }
+            c => code.push(c),
+        }
+    }
+    code
+}
+
+/// 隐去连接串中的密码
+fn redact_url(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let Some((credentials, host)) = rest.split_once('@') else {
+        return url.to_string();
+    };
+    match credentials.split_once(':') {
+        Some((user, _)) => format!("{}://{}:***@{}", scheme, user, host),
+        None => url.to_string(),
+    }
+}
+
+fn db_error(e: sqlx::Error) -> String {
+    e.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_only_query_limits_and_introspection() {
+        let dir = std::env::temp_dir().join(format!("nl_db_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let url = format!("sqlite://{}", dir.join("app.db").display());
+        let options = SqliteConnectOptions::from_str(&url).unwrap().create_if_missing(true);
+        let mut conn = SqliteConnection::connect_with(&options).await.unwrap();
+        conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL)")
+            .await
+            .unwrap();
+        conn.execute("INSERT INTO users (name, score) VALUES ('ada', 9.5), ('bob', NULL), ('cy', 7.0)")
+            .await
+            .unwrap();
+        conn.close().await.unwrap();
+
+        let policy = DatabasePolicy::default();
+        let query = |sql: &str, max_rows| DatabaseAction::Query {
+            url: url.clone(),
+            sql: sql.to_string(),
+            max_rows,
+        };
+        let result = execute(&query("SELECT id, name, score FROM users ORDER BY id", Some(2)), &policy).await;
+        let output: QueryOutput = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(output.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["id", "name", "score"]);
+        assert_eq!(
+            output.rows,
+            [vec![Value::from(1), "ada".into(), 9.5.into()], vec![2.into(), "bob".into(), Value::Null]]
+        );
+        assert!(output.truncated);
+
+        for sql in [
+            "DELETE FROM users",
+            "SELECT 1; DROP TABLE users",
+            "WITH gone AS (DELETE FROM users RETURNING *) SELECT * FROM gone",
+        ] {
+            assert!(policy.check(&query(sql, None)).is_err(), "{}", sql);
+        }
+        assert!(policy.check(&query("SELECT 'drop table' AS note -- delete\n FROM users;", None)).is_ok());
+        // 绕过策略检查时连接本身仍是只读的
+        assert!(!execute(&query("DELETE FROM users", None), &policy).await.success);
+
+        let tables = execute(&DatabaseAction::ListTables { url: url.clone() }, &policy).await;
+        assert!(tables.output.contains("\"users\""), "{}", tables.output);
+        let columns = execute(
+            &DatabaseAction::DescribeTable {
+                url: url.clone(),
+                table: "users".to_string(),
+            },
+            &policy,
+        )
+        .await;
+        let columns: QueryOutput = serde_json::from_str(&columns.output).unwrap();
+        assert_eq!(columns.rows.len(), 3);
+        assert_eq!(columns.rows[1][0], "name");
+
+        assert_eq!(redact_url("postgres://app:secret@db:5432/app"), "postgres://app:***@db:5432/app");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
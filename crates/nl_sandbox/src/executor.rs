@@ -1,7 +1,20 @@
 //! 沙箱执行器
 
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use uuid::Uuid;
+
+use nl_core::NeuroLoomError;
+use nl_durable::actor_mesh::ActorId;
+use nl_durable::{CancellationToken, QuotaManager};
+
+use crate::audit::AuditLog;
+use crate::capabilities::WorkerProfile;
 use crate::god_mode::{GodModeAction, GodModeExecutor};
 use crate::micro_vm::{ExecutionResult, MicroVM};
+use crate::scratch::{PromotionApproval, PromotionReport, ScratchManager};
 
 /// 沙箱执行器
 pub struct SandboxExecutor {
@@ -9,6 +22,14 @@ pub struct SandboxExecutor {
     god_mode: GodModeExecutor,
     /// 微型虚拟机池
     vm_pool: Vec<MicroVM>,
+    /// Actor 配额 (仅作用于 `*_for` 入口)
+    quotas: Option<Arc<QuotaManager>>,
+    /// 任务级临时工作区 (仅作用于 `*_in_scratch` 入口)
+    scratch: Option<Arc<ScratchManager>>,
+    /// 各 Actor 的能力画像 (仅作用于 `*_for` 入口)
+    profiles: RwLock<HashMap<ActorId, WorkerProfile>>,
+    /// 未登记画像的 Actor 使用的画像
+    default_profile: WorkerProfile,
 }
 
 impl SandboxExecutor {
@@ -17,9 +38,126 @@ impl SandboxExecutor {
         Self {
             god_mode: GodModeExecutor::new(),
             vm_pool: Vec::new(),
+            quotas: None,
+            scratch: None,
+            profiles: RwLock::new(HashMap::new()),
+            default_profile: WorkerProfile::default(),
         }
     }
 
+    /// 为 God Mode 操作启用审计
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.god_mode = self.god_mode.with_audit(audit);
+        self
+    }
+
+    /// 设置 Git 操作策略（默认禁止推送）
+    pub fn with_git_policy(mut self, policy: crate::git::GitPolicy) -> Self {
+        self.god_mode = self.god_mode.with_git_policy(policy);
+        self
+    }
+
+    /// 设置输入注入策略
+    pub fn with_input_policy(mut self, policy: crate::input::InputPolicy) -> Self {
+        self.god_mode = self.god_mode.with_input_policy(policy);
+        self
+    }
+
+    /// 设置输入注入批准者
+    pub fn with_input_approver(mut self, approver: Arc<dyn crate::input::InputApprover>) -> Self {
+        self.god_mode = self.god_mode.with_input_approver(approver);
+        self
+    }
+
+    /// 设置数据库查询策略
+    pub fn with_database_policy(mut self, policy: crate::database::DatabasePolicy) -> Self {
+        self.god_mode = self.god_mode.with_database_policy(policy);
+        self
+    }
+
+    /// 设置 HTTP 请求策略
+    pub fn with_http_policy(mut self, policy: crate::http::HttpPolicy) -> Self {
+        self.god_mode = self.god_mode.with_http_policy(policy);
+        self
+    }
+
+    /// 设置抓取页面的缓存
+    pub fn with_http_cache(mut self, cache: Arc<crate::http::HttpCache>) -> Self {
+        self.god_mode = self.god_mode.with_http_cache(cache);
+        self
+    }
+
+    /// 设置浏览器会话
+    pub fn with_browser(mut self, browser: Arc<crate::browser::BrowserSession>) -> Self {
+        self.god_mode = self.god_mode.with_browser(browser);
+        self
+    }
+
+    /// 设置命令资源限制
+    pub fn with_resource_policy(mut self, policy: crate::limits::SandboxPolicy) -> Self {
+        self.god_mode = self.god_mode.with_resource_policy(policy);
+        self
+    }
+
+    /// 启用 Actor 配额
+    pub fn with_quotas(mut self, quotas: Arc<QuotaManager>) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
+    /// 设置未登记画像的 Actor 使用的画像（默认授予全部能力）
+    pub fn with_default_profile(mut self, profile: WorkerProfile) -> Self {
+        self.default_profile = profile;
+        self
+    }
+
+    /// 为 Actor 登记能力画像
+    pub fn assign_profile(&self, actor: ActorId, profile: WorkerProfile) {
+        self.profiles.write().unwrap().insert(actor, profile);
+    }
+
+    /// Actor 的能力画像
+    pub fn profile(&self, actor: ActorId) -> WorkerProfile {
+        self.profiles
+            .read()
+            .unwrap()
+            .get(&actor)
+            .cloned()
+            .unwrap_or_else(|| self.default_profile.clone())
+    }
+
+    /// 启用任务级临时工作区
+    pub fn with_scratch(mut self, scratch: Arc<ScratchManager>) -> Self {
+        self.scratch = Some(scratch);
+        self
+    }
+
+    /// 获取临时工作区管理器
+    pub fn scratch(&self) -> Option<&Arc<ScratchManager>> {
+        self.scratch.as_ref()
+    }
+
+    /// 在任务的临时工作区中执行 God Mode 操作（首次使用时创建副本）
+    pub async fn execute_god_mode_in_scratch(
+        &self,
+        task_id: Uuid,
+        action: GodModeAction,
+    ) -> nl_core::Result<crate::god_mode::GodModeResult> {
+        let action = self.scratch_manager()?.open(task_id).await?.confine(action)?;
+        self.god_mode.execute(action).await
+    }
+
+    /// 凭通过的裁决把任务临时工作区的变更写回真实工作区
+    pub async fn promote_scratch(&self, approval: &PromotionApproval) -> nl_core::Result<PromotionReport> {
+        self.scratch_manager()?.promote(approval).await
+    }
+
+    fn scratch_manager(&self) -> nl_core::Result<&Arc<ScratchManager>> {
+        self.scratch
+            .as_ref()
+            .ok_or_else(|| NeuroLoomError::Sandbox("scratch workspaces are not enabled".to_string()))
+    }
+
     /// 执行 God Mode 操作
     pub async fn execute_god_mode(&self, action: GodModeAction) -> nl_core::Result<crate::god_mode::GodModeResult> {
         self.god_mode.execute(action).await
@@ -31,6 +169,102 @@ impl SandboxExecutor {
         vm.execute(code, language).await
     }
 
+    /// 可取消地执行 God Mode 操作，取消时终止正在运行的子进程
+    pub async fn execute_god_mode_cancellable(
+        &self,
+        action: GodModeAction,
+        cancel: &CancellationToken,
+    ) -> nl_core::Result<crate::god_mode::GodModeResult> {
+        let name = action.name();
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(NeuroLoomError::Cancelled(format!("sandbox action {} cancelled", name))),
+            result = self.god_mode.execute(action) => result,
+        }
+    }
+
+    /// 可取消地在隔离环境中执行代码
+    pub async fn execute_isolated_cancellable(
+        &self,
+        code: &str,
+        language: &str,
+        cancel: &CancellationToken,
+    ) -> nl_core::Result<ExecutionResult> {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(NeuroLoomError::Cancelled("isolated execution cancelled".to_string())),
+            result = self.execute_isolated(code, language) => result,
+        }
+    }
+
+    /// 代表指定 Actor 执行 God Mode 操作：先按 Actor 的画像检查能力授权，再计入写入字节与命令运行时长
+    pub async fn execute_god_mode_for(
+        &self,
+        actor: ActorId,
+        action: GodModeAction,
+    ) -> nl_core::Result<crate::god_mode::GodModeResult> {
+        let profile = self.profile(actor);
+        if let Err(reason) = profile.check(&action) {
+            tracing::warn!("Sandbox action denied for actor {}: {}", actor, reason);
+            let label = format!("{}:{}", profile.name, actor);
+            return self.god_mode.deny(&label, &action, reason).await;
+        }
+
+        let Some(quotas) = &self.quotas else {
+            return self.god_mode.execute(action).await;
+        };
+        quotas.check(actor).await?;
+
+        let written = match &action {
+            GodModeAction::WriteFile { content, .. }
+            | GodModeAction::ApplyPatch { diff: content, .. } => content.len() as u64,
+            _ => 0,
+        };
+        let timed = matches!(
+            action,
+            GodModeAction::Execute { .. }
+                | GodModeAction::Git(_)
+                | GodModeAction::QueryDatabase(_)
+                | GodModeAction::HttpFetch(_)
+                | GodModeAction::Browser(_)
+        );
+        let started = Instant::now();
+        let result = self.god_mode.execute(action).await?;
+
+        // 本次操作已完成，超额只影响后续请求
+        if timed {
+            if let Err(e) = quotas.record_sandbox_time(actor, started.elapsed()).await {
+                tracing::warn!("{}", e);
+            }
+        }
+        if result.success && written > 0 {
+            if let Err(e) = quotas.record_bytes_written(actor, written).await {
+                tracing::warn!("{}", e);
+            }
+        }
+        Ok(result)
+    }
+
+    /// 代表指定 Actor 在隔离环境中执行代码，计入沙箱运行时长
+    pub async fn execute_isolated_for(
+        &self,
+        actor: ActorId,
+        code: &str,
+        language: &str,
+    ) -> nl_core::Result<ExecutionResult> {
+        if let Some(quotas) = &self.quotas {
+            quotas.check(actor).await?;
+        }
+        let started = Instant::now();
+        let result = self.execute_isolated(code, language).await?;
+        if let Some(quotas) = &self.quotas {
+            if let Err(e) = quotas.record_sandbox_time(actor, started.elapsed()).await {
+                tracing::warn!("{}", e);
+            }
+        }
+        Ok(result)
+    }
+
     /// 添加虚拟机到池
     pub fn add_vm(&mut self, vm: MicroVM) {
         self.vm_pool.push(vm);
@@ -47,3 +281,60 @@ impl Default for SandboxExecutor {
         Self::new()
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel_kills_running_command() {
+        let executor = SandboxExecutor::new();
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            trigger.cancel();
+        });
+
+        let started = Instant::now();
+        let action = GodModeAction::Execute {
+            command: "sleep".to_string(),
+            args: vec!["30".to_string()],
+            cwd: None,
+        };
+        let result = executor.execute_god_mode_cancellable(action, &cancel).await;
+        assert!(matches!(result, Err(NeuroLoomError::Cancelled(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_profile_denial_is_audited() {
+        let store = Arc::new(tokio::sync::Mutex::new(nl_durable::EventStore::new(Default::default())));
+        let audit = Arc::new(AuditLog::open(store.clone()).await.unwrap());
+        let executor = SandboxExecutor::new()
+            .with_audit(audit.clone())
+            .with_default_profile(WorkerProfile::new("locked"));
+        let reviewer = Uuid::new_v4();
+        executor.assign_profile(reviewer, WorkerProfile::read_only("reviewer"));
+
+        let path = std::env::temp_dir().join(format!("nl-profile-{}.txt", Uuid::new_v4()));
+        let write = GodModeAction::WriteFile {
+            path: path.clone(),
+            content: "hello".to_string(),
+        };
+        let denied = executor.execute_god_mode_for(reviewer, write).await.unwrap();
+        assert!(!denied.success);
+        assert!(denied.error.unwrap().contains("fs-write"));
+        assert!(!path.exists());
+
+        let read = GodModeAction::ReadFile { path: path.clone() };
+        let other = executor.execute_god_mode_for(Uuid::new_v4(), read).await.unwrap();
+        assert!(other.error.unwrap().contains("locked"));
+
+        let entries = AuditLog::load(&*store.lock().await).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].actor.starts_with("reviewer:"));
+        assert!(matches!(entries[0].policy, crate::audit::PolicyDecision::Denied { .. }));
+    }
+}
@@ -0,0 +1,377 @@
+//! Git 操作
+//!
+//! 以 `GodModeAction::Git` 形式执行的 git 操作（基于 libgit2），与其他 God Mode 操作一样
+//! 先经策略检查、再写入审计链：
+//! - `Status` / `Diff` 只读；`CreateBranch` / `Stage` / `Commit` 修改本地仓库
+//! - `Commit` 未提供提交说明时按暂存区变更生成
+//! - `Push` 受 `GitPolicy` 保护：默认禁止推送，启用后仍拒绝受保护分支与未允许的远端，且从不强制推送
+
+use std::path::{Path, PathBuf};
+
+use git2::{
+    build::CheckoutBuilder, Cred, CredentialType, DiffFormat, DiffOptions, IndexAddOption, PushOptions,
+    RemoteCallbacks, Repository, Signature, Status, StatusOptions,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::god_mode::GodModeResult;
+
+/// 生成的提交说明中最多列出的文件数
+const MESSAGE_FILES: usize = 5;
+
+/// Git 操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GitAction {
+    /// 工作区状态
+    Status { repo: PathBuf },
+    /// 差异（`staged` 为真时比较 HEAD 与暂存区，否则比较暂存区与工作区）
+    Diff {
+        repo: PathBuf,
+        #[serde(default)]
+        staged: bool,
+    },
+    /// 从 HEAD 创建分支
+    CreateBranch {
+        repo: PathBuf,
+        name: String,
+        #[serde(default)]
+        checkout: bool,
+    },
+    /// 暂存文件（相对仓库根目录；为空时暂存全部变更）
+    Stage {
+        repo: PathBuf,
+        #[serde(default)]
+        paths: Vec<PathBuf>,
+    },
+    /// 提交暂存区（`message` 为空时自动生成）
+    Commit { repo: PathBuf, message: Option<String> },
+    /// 推送分支
+    Push { repo: PathBuf, remote: String, branch: String },
+}
+
+impl GitAction {
+    /// 操作名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            GitAction::Status { .. } => "git_status",
+            GitAction::Diff { .. } => "git_diff",
+            GitAction::CreateBranch { .. } => "git_create_branch",
+            GitAction::Stage { .. } => "git_stage",
+            GitAction::Commit { .. } => "git_commit",
+            GitAction::Push { .. } => "git_push",
+        }
+    }
+
+    /// 审计用参数
+    pub fn audit_arguments(&self) -> serde_json::Value {
+        match self {
+            GitAction::Status { repo } => serde_json::json!({ "repo": repo }),
+            GitAction::Diff { repo, staged } => serde_json::json!({ "repo": repo, "staged": staged }),
+            GitAction::CreateBranch { repo, name, checkout } => {
+                serde_json::json!({ "repo": repo, "name": name, "checkout": checkout })
+            }
+            GitAction::Stage { repo, paths } => serde_json::json!({ "repo": repo, "paths": paths }),
+            GitAction::Commit { repo, message } => serde_json::json!({ "repo": repo, "message": message }),
+            GitAction::Push { repo, remote, branch } => {
+                serde_json::json!({ "repo": repo, "remote": remote, "branch": branch })
+            }
+        }
+    }
+
+    fn repo(&self) -> &Path {
+        match self {
+            GitAction::Status { repo }
+            | GitAction::Diff { repo, .. }
+            | GitAction::CreateBranch { repo, .. }
+            | GitAction::Stage { repo, .. }
+            | GitAction::Commit { repo, .. }
+            | GitAction::Push { repo, .. } => repo,
+        }
+    }
+}
+
+/// Git 策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitPolicy {
+    /// 是否允许推送
+    pub allow_push: bool,
+    /// 禁止推送的分支
+    pub protected_branches: Vec<String>,
+    /// 允许推送的远端（为空表示不限）
+    pub allowed_remotes: Vec<String>,
+}
+
+impl Default for GitPolicy {
+    fn default() -> Self {
+        Self {
+            allow_push: false,
+            protected_branches: vec!["main".to_string(), "master".to_string()],
+            allowed_remotes: Vec::new(),
+        }
+    }
+}
+
+impl GitPolicy {
+    /// 允许推送到非保护分支
+    pub fn allowing_push(mut self) -> Self {
+        self.allow_push = true;
+        self
+    }
+
+    /// 检查操作，拒绝时返回原因
+    pub fn check(&self, action: &GitAction) -> Result<(), String> {
+        let GitAction::Push { remote, branch, .. } = action else {
+            return Ok(());
+        };
+        if !self.allow_push {
+            return Err("git push is disabled by policy".to_string());
+        }
+        if self.protected_branches.iter().any(|b| b == branch) {
+            return Err(format!("branch {} is protected", branch));
+        }
+        if !self.allowed_remotes.is_empty() && !self.allowed_remotes.contains(remote) {
+            return Err(format!("remote {} is not allowed", remote));
+        }
+        Ok(())
+    }
+}
+
+/// 执行 Git 操作（阻塞调用，由调用方放入阻塞线程池）
+pub fn execute(action: &GitAction) -> GodModeResult {
+    let outcome = Repository::discover(action.repo()).and_then(|repo| match action {
+        GitAction::Status { .. } => status(&repo),
+        GitAction::Diff { staged, .. } => diff(&repo, *staged),
+        GitAction::CreateBranch { name, checkout, .. } => create_branch(&repo, name, *checkout),
+        GitAction::Stage { paths, .. } => stage(&repo, paths),
+        GitAction::Commit { message, .. } => commit(&repo, message.as_deref()),
+        GitAction::Push { remote, branch, .. } => push(&repo, remote, branch),
+    });
+    match outcome {
+        Ok(output) => GodModeResult {
+            success: true,
+            output,
+            error: None,
+        },
+        Err(e) => GodModeResult {
+            success: false,
+            output: String::new(),
+            error: Some(e.message().to_string()),
+        },
+    }
+}
+
+/// 类似 `git status --short --branch` 的输出
+fn status(repo: &Repository) -> Result<String, git2::Error> {
+    let branch = repo
+        .head()
+        .ok()
+        .and_then(|h| h.shorthand().map(String::from))
+        .unwrap_or_else(|| "(no commits)".to_string());
+    let mut lines = vec![format!("## {}", branch)];
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let status = entry.status();
+        if status.is_ignored() {
+            continue;
+        }
+        let index = if status.is_conflicted() {
+            'U'
+        } else if status.intersects(Status::INDEX_NEW) {
+            'A'
+        } else if status.intersects(Status::INDEX_MODIFIED | Status::INDEX_TYPECHANGE) {
+            'M'
+        } else if status.intersects(Status::INDEX_DELETED) {
+            'D'
+        } else if status.intersects(Status::INDEX_RENAMED) {
+            'R'
+        } else if status.is_wt_new() {
+            '?'
+        } else {
+            ' '
+        };
+        let worktree = if status.is_wt_new() {
+            '?'
+        } else if status.intersects(Status::WT_MODIFIED | Status::WT_TYPECHANGE) {
+            'M'
+        } else if status.intersects(Status::WT_DELETED) {
+            'D'
+        } else {
+            ' '
+        };
+        lines.push(format!("{}{} {}", index, worktree, entry.path().unwrap_or_default()));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// 统一格式差异
+fn diff(repo: &Repository, staged: bool) -> Result<String, git2::Error> {
+    let diff = if staged {
+        let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        repo.diff_tree_to_index(head.as_ref(), None, None)?
+    } else {
+        let mut options = DiffOptions::new();
+        options.include_untracked(true).show_untracked_content(true);
+        repo.diff_index_to_workdir(None, Some(&mut options))?
+    };
+    let mut patch = String::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin());
+        }
+        patch.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })?;
+    Ok(patch)
+}
+
+fn create_branch(repo: &Repository, name: &str, checkout: bool) -> Result<String, git2::Error> {
+    let commit = repo.head()?.peel_to_commit()?;
+    repo.branch(name, &commit, false)?;
+    if checkout {
+        repo.set_head(&format!("refs/heads/{}", name))?;
+        repo.checkout_head(Some(CheckoutBuilder::new().safe()))?;
+        return Ok(format!("Switched to new branch {}", name));
+    }
+    Ok(format!("Created branch {} at {}", name, commit.id()))
+}
+
+fn stage(repo: &Repository, paths: &[PathBuf]) -> Result<String, git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("cannot stage in a bare repository"))?
+        .to_path_buf();
+    let mut index = repo.index()?;
+    if paths.is_empty() {
+        index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+        index.update_all(["*"], None)?;
+    } else {
+        for path in paths {
+            if workdir.join(path).exists() {
+                index.add_path(path)?;
+            } else {
+                index.remove_path(path)?;
+            }
+        }
+    }
+    index.write()?;
+    if paths.is_empty() {
+        Ok("Staged all changes".to_string())
+    } else {
+        Ok(format!("Staged {} paths", paths.len()))
+    }
+}
+
+fn commit(repo: &Repository, message: Option<&str>) -> Result<String, git2::Error> {
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree.id()) {
+        return Err(git2::Error::from_str("nothing to commit"));
+    }
+    let message = match message {
+        Some(message) => message.to_string(),
+        None => generate_message(repo)?,
+    };
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("NeuroLoom", "neuroloom@localhost"))?;
+    let parents: Vec<_> = parent.iter().collect();
+    let id = repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents)?;
+    Ok(format!("[{}] {}", &id.to_string()[..7], message.lines().next().unwrap_or_default()))
+}
+
+/// 按暂存区变更生成提交说明
+pub fn generate_message(repo: &Repository) -> Result<String, git2::Error> {
+    let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head.as_ref(), None, None)?;
+    let stats = diff.stats()?;
+    let files: Vec<String> = diff
+        .deltas()
+        .filter_map(|d| {
+            d.new_file()
+                .path()
+                .or(d.old_file().path())
+                .map(|p| p.display().to_string())
+        })
+        .collect();
+
+    let mut subject = format!("Update {}", files[..files.len().min(MESSAGE_FILES)].join(", "));
+    if files.len() > MESSAGE_FILES {
+        subject.push_str(&format!(" and {} more", files.len() - MESSAGE_FILES));
+    }
+    Ok(format!(
+        "{}\n\n{} files changed, {} insertions(+), {} deletions(-)",
+        subject,
+        stats.files_changed(),
+        stats.insertions(),
+        stats.deletions()
+    ))
+}
+
+/// 推送分支（不带 `+`，远端拒绝非快进更新）
+fn push(repo: &Repository, remote: &str, branch: &str) -> Result<String, git2::Error> {
+    let config = repo.config()?;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username.unwrap_or("git"))
+        } else {
+            Cred::credential_helper(&config, url, username)
+        }
+    });
+    callbacks.push_update_reference(|reference, status| match status {
+        Some(reason) => Err(git2::Error::from_str(&format!("{} rejected: {}", reference, reason))),
+        None => Ok(()),
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+
+    let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+    repo.find_remote(remote)?.push(&[refspec.as_str()], Some(&mut options))?;
+    Ok(format!("Pushed {} to {}", branch, remote))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_commit_branch_and_guarded_push() {
+        let dir = std::env::temp_dir().join(format!("nl_git_{}", uuid::Uuid::new_v4()));
+        Repository::init(&dir).unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn main() {}\n").unwrap();
+
+        let status = execute(&GitAction::Status { repo: dir.clone() });
+        assert!(status.output.contains("?? lib.rs"), "{}", status.output);
+
+        assert!(execute(&GitAction::Stage { repo: dir.clone(), paths: Vec::new() }).success);
+        let staged = execute(&GitAction::Diff { repo: dir.clone(), staged: true });
+        assert!(staged.output.contains("+fn main() {}"));
+
+        let commit = execute(&GitAction::Commit { repo: dir.clone(), message: None });
+        assert!(commit.success, "{:?}", commit.error);
+        assert!(commit.output.contains("Update lib.rs"));
+        // 没有新变更时拒绝空提交
+        assert!(!execute(&GitAction::Commit { repo: dir.clone(), message: None }).success);
+
+        let branch = execute(&GitAction::CreateBranch {
+            repo: dir.clone(),
+            name: "feature".to_string(),
+            checkout: true,
+        });
+        assert!(branch.success, "{:?}", branch.error);
+        assert!(execute(&GitAction::Status { repo: dir.clone() }).output.starts_with("## feature"));
+
+        let push = |branch: &str| GitAction::Push {
+            repo: dir.clone(),
+            remote: "origin".to_string(),
+            branch: branch.to_string(),
+        };
+        assert!(GitPolicy::default().check(&push("feature")).is_err());
+        let policy = GitPolicy::default().allowing_push();
+        assert!(policy.check(&push("main")).is_err());
+        assert!(policy.check(&push("feature")).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,8 +1,24 @@
 //! God Mode - 原生文件读写操作
+//!
+//! `ApplyPatch` 按统一 diff 修改文件，逐文件结果以 JSON 报告返回。
+//! 执行前先经策略检查（总开关、`GitPolicy`、`DatabasePolicy`、`HttpPolicy` 与高风险的 `InputPolicy`），被拒绝的操作同样写入审计链；
+//! 输入注入与浏览器中的点击、输入还须经批准者批准，批准者记入审计条目。
+//! `Execute` 按 `SandboxPolicy` 限制整棵进程树的资源，超限时返回 `ResourceExceeded` 错误（同样写入审计链）。
 
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::audit::{AuditLog, PolicyDecision};
+use crate::browser::{BrowserAction, BrowserSession};
+use crate::database::{DatabaseAction, DatabasePolicy};
+use crate::git::{GitAction, GitPolicy};
+use crate::http::{HttpCache, HttpFetch, HttpPolicy};
+use crate::input::{InputAction, InputApprover, InputPolicy, InputRateLimiter};
+use crate::limits::SandboxPolicy;
+use crate::patch::PatchApplier;
 
 /// God Mode 操作
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,12 +33,94 @@ pub enum GodModeAction {
     CreateDir { path: PathBuf },
     /// 列出目录
     ListDir { path: PathBuf },
-    /// 执行命令
-    Execute { command: String, args: Vec<String> },
+    /// 执行命令（`cwd` 为空时使用当前目录）
+    Execute {
+        command: String,
+        args: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cwd: Option<PathBuf>,
+    },
     /// 设置环境变量
     SetEnv { key: String, value: String },
     /// 获取环境变量
     GetEnv { key: String },
+    /// Git 操作
+    Git(GitAction),
+    /// 在 `root` 下应用统一 diff
+    ApplyPatch { root: PathBuf, diff: String },
+    /// 鼠标 / 键盘输入（高风险）
+    Input(InputAction),
+    /// 数据库查询与结构查看
+    QueryDatabase(DatabaseAction),
+    /// HTTP 请求
+    HttpFetch(HttpFetch),
+    /// 无头浏览器操作（点击与输入为高风险）
+    Browser(BrowserAction),
+}
+
+impl GodModeAction {
+    /// 操作名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            GodModeAction::ReadFile { .. } => "read_file",
+            GodModeAction::WriteFile { .. } => "write_file",
+            GodModeAction::DeleteFile { .. } => "delete_file",
+            GodModeAction::CreateDir { .. } => "create_dir",
+            GodModeAction::ListDir { .. } => "list_dir",
+            GodModeAction::Execute { .. } => "execute",
+            GodModeAction::SetEnv { .. } => "set_env",
+            GodModeAction::GetEnv { .. } => "get_env",
+            GodModeAction::Git(git) => git.name(),
+            GodModeAction::ApplyPatch { .. } => "apply_patch",
+            GodModeAction::Input(input) => input.name(),
+            GodModeAction::QueryDatabase(database) => database.name(),
+            GodModeAction::HttpFetch(_) => "http_fetch",
+            GodModeAction::Browser(browser) => browser.name(),
+        }
+    }
+
+    /// 是否属于高风险类别（须单独授权）
+    pub fn is_high_risk(&self) -> bool {
+        match self {
+            GodModeAction::Input(_) => true,
+            GodModeAction::Browser(browser) => browser.is_input(),
+            _ => false,
+        }
+    }
+
+    /// 审计用参数：写入内容与环境变量值只记录摘要
+    pub fn audit_arguments(&self) -> serde_json::Value {
+        let digest = |value: &str| format!("sha256:{:x}", Sha256::digest(value.as_bytes()));
+        match self {
+            GodModeAction::ReadFile { path }
+            | GodModeAction::DeleteFile { path }
+            | GodModeAction::CreateDir { path }
+            | GodModeAction::ListDir { path } => serde_json::json!({ "path": path }),
+            GodModeAction::WriteFile { path, content } => serde_json::json!({
+                "path": path,
+                "content": digest(content),
+                "bytes": content.len(),
+            }),
+            GodModeAction::Execute { command, args, cwd: None } => {
+                serde_json::json!({ "command": command, "args": args })
+            }
+            GodModeAction::Execute { command, args, cwd: Some(cwd) } => {
+                serde_json::json!({ "command": command, "args": args, "cwd": cwd })
+            }
+            GodModeAction::SetEnv { key, value } => serde_json::json!({ "key": key, "value": digest(value) }),
+            GodModeAction::GetEnv { key } => serde_json::json!({ "key": key }),
+            GodModeAction::Git(git) => git.audit_arguments(),
+            GodModeAction::ApplyPatch { root, diff } => serde_json::json!({
+                "root": root,
+                "diff": digest(diff),
+                "bytes": diff.len(),
+            }),
+            GodModeAction::Input(input) => input.audit_arguments(),
+            GodModeAction::QueryDatabase(database) => database.audit_arguments(),
+            GodModeAction::HttpFetch(fetch) => fetch.audit_arguments(),
+            GodModeAction::Browser(browser) => browser.audit_arguments(),
+        }
+    }
 }
 
 /// God Mode 操作结果
@@ -40,12 +138,107 @@ pub struct GodModeResult {
 pub struct GodModeExecutor {
     /// 是否启用 (安全开关)
     enabled: bool,
+    /// 执行者名称 (写入审计条目)
+    actor: String,
+    /// 审计日志 (设置后每个操作都会留痕)
+    audit: Option<Arc<AuditLog>>,
+    /// Git 操作策略
+    git_policy: GitPolicy,
+    /// 输入注入策略
+    input_policy: InputPolicy,
+    /// 输入注入批准者
+    input_approver: Option<Arc<dyn InputApprover>>,
+    /// 输入注入限流
+    input_limiter: InputRateLimiter,
+    /// 命令资源限制
+    resource_policy: SandboxPolicy,
+    /// 数据库查询策略
+    database_policy: DatabasePolicy,
+    /// HTTP 请求策略
+    http_policy: HttpPolicy,
+    /// 抓取页面的缓存
+    http_cache: Option<Arc<HttpCache>>,
+    /// 浏览器会话
+    browser: Option<Arc<BrowserSession>>,
 }
 
 impl GodModeExecutor {
     /// 创建新执行器
     pub fn new() -> Self {
-        Self { enabled: true }
+        Self {
+            enabled: true,
+            actor: "system".to_string(),
+            audit: None,
+            git_policy: GitPolicy::default(),
+            input_policy: InputPolicy::default(),
+            input_approver: None,
+            input_limiter: InputRateLimiter::default(),
+            resource_policy: SandboxPolicy::default(),
+            database_policy: DatabasePolicy::default(),
+            http_policy: HttpPolicy::default(),
+            http_cache: None,
+            browser: None,
+        }
+    }
+
+    /// 设置执行者名称
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = actor.into();
+        self
+    }
+
+    /// 设置审计日志
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// 设置 Git 操作策略
+    pub fn with_git_policy(mut self, policy: GitPolicy) -> Self {
+        self.git_policy = policy;
+        self
+    }
+
+    /// 设置输入注入策略（默认每次都须批准）
+    pub fn with_input_policy(mut self, policy: InputPolicy) -> Self {
+        self.input_policy = policy;
+        self
+    }
+
+    /// 设置输入注入批准者
+    pub fn with_input_approver(mut self, approver: Arc<dyn InputApprover>) -> Self {
+        self.input_approver = Some(approver);
+        self
+    }
+
+    /// 设置命令资源限制（默认不限制）
+    pub fn with_resource_policy(mut self, policy: SandboxPolicy) -> Self {
+        self.resource_policy = policy;
+        self
+    }
+
+    /// 设置数据库查询策略（默认只读）
+    pub fn with_database_policy(mut self, policy: DatabasePolicy) -> Self {
+        self.database_policy = policy;
+        self
+    }
+
+    /// 设置 HTTP 请求策略（默认拒绝所有域名）
+    pub fn with_http_policy(mut self, policy: HttpPolicy) -> Self {
+        self.http_policy = policy;
+        self
+    }
+
+    /// 设置抓取页面的缓存
+    pub fn with_http_cache(mut self, cache: Arc<HttpCache>) -> Self {
+        self.http_cache = Some(cache);
+        self
+    }
+
+    /// 设置浏览器会话（未设置时拒绝浏览器操作）
+    pub fn with_browser(mut self, browser: Arc<BrowserSession>) -> Self {
+        self.browser = Some(browser);
+        self
     }
 
     /// 禁用
@@ -60,26 +253,172 @@ impl GodModeExecutor {
 
     /// 执行操作
     pub async fn execute(&self, action: GodModeAction) -> nl_core::Result<GodModeResult> {
+        let decision = match self.policy(&action) {
+            PolicyDecision::Allowed => self.approval(&action).await,
+            denied => denied,
+        };
+        if let PolicyDecision::Denied { reason } = decision {
+            return self.deny(&self.actor, &action, reason).await;
+        }
+
+        let result = match self.dispatch(&action).await {
+            Err(e @ nl_core::NeuroLoomError::ResourceExceeded { .. }) => {
+                if let Some(audit) = &self.audit {
+                    let killed = GodModeResult {
+                        success: false,
+                        output: String::new(),
+                        error: Some(e.to_string()),
+                    };
+                    audit.record(&self.actor, &action, decision, Some(&killed)).await?;
+                }
+                return Err(e);
+            }
+            result => result?,
+        };
+        if let Some(audit) = &self.audit {
+            audit.record(&self.actor, &action, decision, Some(&result)).await?;
+        }
+        Ok(result)
+    }
+
+    /// 拒绝操作：写入审计链并返回失败结果
+    pub(crate) async fn deny(
+        &self,
+        actor: &str,
+        action: &GodModeAction,
+        reason: String,
+    ) -> nl_core::Result<GodModeResult> {
+        let decision = PolicyDecision::Denied { reason: reason.clone() };
+        if let Some(audit) = &self.audit {
+            audit.record(actor, action, decision, None).await?;
+        }
+        Ok(GodModeResult {
+            success: false,
+            output: String::new(),
+            error: Some(reason),
+        })
+    }
+
+    /// 策略检查
+    fn policy(&self, action: &GodModeAction) -> PolicyDecision {
         if !self.enabled {
-            return Ok(GodModeResult {
-                success: false,
-                output: String::new(),
-                error: Some("God Mode is disabled".to_string()),
-            });
+            return PolicyDecision::Denied {
+                reason: "God Mode is disabled".to_string(),
+            };
+        }
+        let checked = match action {
+            GodModeAction::Git(git) => self.git_policy.check(git),
+            GodModeAction::QueryDatabase(database) => self.database_policy.check(database),
+            GodModeAction::HttpFetch(fetch) => self.http_policy.check(fetch),
+            GodModeAction::Input(input) => self.check_input(input),
+            GodModeAction::Browser(browser) => self.check_browser(browser),
+            _ => Ok(()),
+        };
+        match checked {
+            Ok(()) => PolicyDecision::Allowed,
+            Err(reason) => PolicyDecision::Denied { reason },
+        }
+    }
+
+    fn check_input(&self, input: &InputAction) -> Result<(), String> {
+        self.input_policy
+            .check(input)
+            .and_then(|()| self.input_limiter.try_acquire(self.input_policy.max_actions_per_minute))
+    }
+
+    /// 导航按 HTTP 白名单检查，点击与输入按输入注入检查
+    fn check_browser(&self, action: &BrowserAction) -> Result<(), String> {
+        let Some(browser) = &self.browser else {
+            return Err("browser automation is not configured".to_string());
+        };
+        if let BrowserAction::Navigate { url } = action {
+            return self.http_policy.check(&HttpFetch::get(url.clone()));
+        }
+        match browser.input_action(action)? {
+            Some(input) => self.check_input(&input),
+            None => Ok(()),
+        }
+    }
+
+    /// 高风险操作对应的输入注入
+    fn input_of(&self, action: &GodModeAction) -> Option<InputAction> {
+        match action {
+            GodModeAction::Input(input) => Some(input.clone()),
+            GodModeAction::Browser(browser) => self.browser.as_ref()?.input_action(browser).ok().flatten(),
+            _ => None,
         }
+    }
+
+    /// 高风险操作的批准（未配置批准者时拒绝）
+    async fn approval(&self, action: &GodModeAction) -> PolicyDecision {
+        let Some(input) = self.input_of(action) else {
+            return PolicyDecision::Allowed;
+        };
+        if !self.input_policy.require_approval {
+            return PolicyDecision::Allowed;
+        }
+        let Some(approver) = &self.input_approver else {
+            return PolicyDecision::Denied {
+                reason: "input injection requires approval but no approver is configured".to_string(),
+            };
+        };
+        match approver.approve(&input).await {
+            Ok(by) => PolicyDecision::Approved { by },
+            Err(reason) => PolicyDecision::Denied {
+                reason: format!("approval denied: {}", reason),
+            },
+        }
+    }
 
+    async fn dispatch(&self, action: &GodModeAction) -> nl_core::Result<GodModeResult> {
         match action {
-            GodModeAction::ReadFile { path } => self.read_file(&path).await,
-            GodModeAction::WriteFile { path, content } => self.write_file(&path, &content).await,
-            GodModeAction::DeleteFile { path } => self.delete_file(&path).await,
-            GodModeAction::CreateDir { path } => self.create_dir(&path).await,
-            GodModeAction::ListDir { path } => self.list_dir(&path).await,
-            GodModeAction::Execute { command, args } => self.execute_command(&command, &args).await,
-            GodModeAction::SetEnv { key, value } => self.set_env(&key, &value),
-            GodModeAction::GetEnv { key } => self.get_env(&key),
+            GodModeAction::ReadFile { path } => self.read_file(path).await,
+            GodModeAction::WriteFile { path, content } => self.write_file(path, content).await,
+            GodModeAction::DeleteFile { path } => self.delete_file(path).await,
+            GodModeAction::CreateDir { path } => self.create_dir(path).await,
+            GodModeAction::ListDir { path } => self.list_dir(path).await,
+            GodModeAction::Execute { command, args, cwd } => {
+                self.execute_command(command, args, cwd.as_deref()).await
+            }
+            GodModeAction::SetEnv { key, value } => self.set_env(key, value),
+            GodModeAction::GetEnv { key } => self.get_env(key),
+            GodModeAction::Git(git) => self.git(git.clone()).await,
+            GodModeAction::ApplyPatch { root, diff } => self.apply_patch(root, diff).await,
+            GodModeAction::Input(input) => Ok(crate::input::execute(input).await),
+            GodModeAction::QueryDatabase(database) => {
+                Ok(crate::database::execute(database, &self.database_policy).await)
+            }
+            GodModeAction::HttpFetch(fetch) => {
+                Ok(crate::http::execute(fetch, &self.http_policy, self.http_cache.as_deref()).await)
+            }
+            GodModeAction::Browser(browser) => match &self.browser {
+                Some(session) => Ok(session.execute(browser, &self.http_policy).await),
+                None => Err(nl_core::NeuroLoomError::Sandbox("browser automation is not configured".to_string())),
+            },
+        }
+    }
+
+    async fn apply_patch(&self, root: &Path, diff: &str) -> nl_core::Result<GodModeResult> {
+        match PatchApplier::new(root).apply_text(diff).await {
+            Ok(report) => Ok(GodModeResult {
+                success: report.is_clean(),
+                output: serde_json::to_string(&report)?,
+                error: (!report.is_clean()).then(|| report.summary()),
+            }),
+            Err(e) => Ok(GodModeResult {
+                success: false,
+                output: String::new(),
+                error: Some(e.to_string()),
+            }),
         }
     }
 
+    async fn git(&self, action: GitAction) -> nl_core::Result<GodModeResult> {
+        tokio::task::spawn_blocking(move || crate::git::execute(&action))
+            .await
+            .map_err(|e| nl_core::NeuroLoomError::Sandbox(e.to_string()))
+    }
+
     async fn read_file(&self, path: &Path) -> nl_core::Result<GodModeResult> {
         match tokio::fs::read_to_string(path).await {
             Ok(content) => Ok(GodModeResult {
@@ -161,22 +500,32 @@ impl GodModeExecutor {
         }
     }
 
-    async fn execute_command(&self, command: &str, args: &[String]) -> nl_core::Result<GodModeResult> {
-        let output = tokio::process::Command::new(command)
-            .args(args)
-            .output()
-            .await;
+    async fn execute_command(
+        &self,
+        command: &str,
+        args: &[String],
+        cwd: Option<&Path>,
+    ) -> nl_core::Result<GodModeResult> {
+        // 调用方取消时 future 被丢弃，子进程随之终止
+        let mut process = tokio::process::Command::new(command);
+        if let Some(cwd) = cwd {
+            process.current_dir(cwd);
+        }
+        process.args(args).kill_on_drop(true);
 
-        match output {
+        match crate::limits::output(process, &self.resource_policy).await {
             Ok(output) => {
                 let stdout = String::from_utf8_lossy(&output.stdout).to_string();
                 let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                // 失败时 stdout 仍保留（lint 工具发现问题时以非零码退出，诊断写在 stdout）；无 stdout 时沿用 stderr
+                let success = output.status.success();
                 Ok(GodModeResult {
-                    success: output.status.success(),
-                    output: if output.status.success() { stdout } else { stderr },
-                    error: if output.status.success() { None } else { Some(stderr) },
+                    success,
+                    output: if success || !stdout.is_empty() { stdout } else { stderr.clone() },
+                    error: if success { None } else { Some(stderr) },
                 })
             }
+            Err(e @ nl_core::NeuroLoomError::ResourceExceeded { .. }) => Err(e),
             Err(e) => Ok(GodModeResult {
                 success: false,
                 output: String::new(),
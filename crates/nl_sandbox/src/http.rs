@@ -0,0 +1,447 @@
+//! HTTP 请求
+//!
+//! 以 `GodModeAction::HttpFetch` 形式执行的 HTTP 请求，供 Worker 查阅文档、调用 API：
+//! - `HttpPolicy` 按域名白名单放行（为空时拒绝全部请求），白名单项同时匹配其子域名；
+//!   重定向目标同样须在白名单内，只允许 http / https
+//! - 响应体超过 `max_response_bytes` 时截断，整个请求受 `timeout_secs` 限制
+//! - HTML 响应转换为 Markdown（去掉脚本、样式与导航），便于直接放进提示词
+//! - 配置 `HttpCache` 后，成功的 GET 响应写入缓存目录并作为记忆存入 HAMT（标签取页面标题），
+//!   缓存未过期时直接返回缓存内容
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use nl_memory::hamt::MemoryEntry;
+use nl_memory::HamtIndex;
+
+use crate::god_mode::GodModeResult;
+
+/// 允许的域名列表的环境变量（逗号分隔）
+pub const ALLOWED_DOMAINS_ENV: &str = "NEUROLOOM_HTTP_ALLOWED_DOMAINS";
+
+/// 转换 Markdown 时整段丢弃的元素
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "svg", "nav", "title"];
+
+/// 缓存记忆的标签与摘要字数
+const TAG_CHARS: usize = 20;
+const SUMMARY_CHARS: usize = 200;
+
+/// HTTP 请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpFetch {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl HttpFetch {
+    /// GET 请求
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            method: default_method(),
+            headers: BTreeMap::new(),
+            body: None,
+        }
+    }
+
+    /// 审计用参数：请求头只记录名称，请求体只记录摘要
+    pub fn audit_arguments(&self) -> serde_json::Value {
+        serde_json::json!({
+            "url": self.url,
+            "method": self.method,
+            "headers": self.headers.keys().collect::<Vec<_>>(),
+            "body": self.body.as_ref().map(|body| format!("sha256:{:x}", Sha256::digest(body.as_bytes()))),
+        })
+    }
+
+    fn is_get(&self) -> bool {
+        self.method.eq_ignore_ascii_case("GET")
+    }
+}
+
+/// HTTP 策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpPolicy {
+    /// 允许访问的域名（含子域名；为空表示禁止所有请求）
+    pub allowed_domains: Vec<String>,
+    /// 允许的请求方法
+    pub allowed_methods: Vec<String>,
+    /// 响应体字节上限
+    pub max_response_bytes: usize,
+    /// 请求超时（秒）
+    pub timeout_secs: u64,
+}
+
+impl Default for HttpPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_domains: Vec::new(),
+            allowed_methods: vec!["GET".to_string()],
+            max_response_bytes: 1024 * 1024,
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl HttpPolicy {
+    /// 从 `NEUROLOOM_HTTP_ALLOWED_DOMAINS` 读取白名单，其余取默认值
+    pub fn from_env() -> Self {
+        let domains = std::env::var(ALLOWED_DOMAINS_ENV).unwrap_or_default();
+        Self::default().allowing_domains(domains.split(',').map(str::trim).filter(|d| !d.is_empty()))
+    }
+
+    /// 加入允许的域名
+    pub fn allowing_domains<I, S>(mut self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_domains
+            .extend(domains.into_iter().map(|d| d.into().trim_start_matches("*.").to_ascii_lowercase()));
+        self
+    }
+
+    /// 允许的请求方法
+    pub fn allowing_methods<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_methods = methods.into_iter().map(|m| m.into().to_ascii_uppercase()).collect();
+        self
+    }
+
+    /// 检查请求，拒绝时返回原因
+    pub fn check(&self, fetch: &HttpFetch) -> Result<(), String> {
+        if !self.allowed_methods.iter().any(|m| m.eq_ignore_ascii_case(&fetch.method)) {
+            return Err(format!("HTTP method {} is not allowed", fetch.method));
+        }
+        let url = Url::parse(&fetch.url).map_err(|e| format!("invalid url {}: {}", fetch.url, e))?;
+        self.check_url(&url)
+    }
+
+    fn check_url(&self, url: &Url) -> Result<(), String> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("scheme {} is not allowed", url.scheme()));
+        }
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let allowed = self.allowed_domains.iter().any(|domain| {
+            host == *domain || host.strip_suffix(domain.as_str()).is_some_and(|sub| sub.ends_with('.'))
+        });
+        if !allowed {
+            return Err(format!("domain {} is not in the allowlist", host));
+        }
+        Ok(())
+    }
+}
+
+/// 请求结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FetchOutput {
+    /// 最终地址（跟随重定向后）
+    pub url: String,
+    pub status: u16,
+    pub content_type: String,
+    /// 页面标题（仅 HTML）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// 正文（HTML 已转换为 Markdown）
+    pub content: String,
+    /// 响应体是否因字节上限截断
+    pub truncated: bool,
+    /// 是否来自缓存
+    pub cached: bool,
+}
+
+/// 抓取页面的缓存：正文存放在缓存目录，同时作为记忆存入 HAMT
+pub struct HttpCache {
+    index: Arc<HamtIndex>,
+    dir: PathBuf,
+    ttl: chrono::Duration,
+}
+
+impl HttpCache {
+    /// 正文缓存在 `dir` 下，默认 24 小时过期
+    pub fn new(index: Arc<HamtIndex>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            index,
+            dir: dir.into(),
+            ttl: chrono::Duration::hours(24),
+        }
+    }
+
+    /// 设置过期时间
+    pub fn with_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:x}.json", Sha256::digest(url.as_bytes())))
+    }
+
+    /// 未过期的缓存
+    async fn get(&self, url: &str) -> Option<FetchOutput> {
+        let cached: CachedPage = serde_json::from_slice(&tokio::fs::read(self.path(url)).await.ok()?).ok()?;
+        if chrono::Utc::now() - cached.fetched_at > self.ttl {
+            return None;
+        }
+        Some(FetchOutput {
+            cached: true,
+            ..cached.output
+        })
+    }
+
+    /// 写入缓存并替换该地址原有的记忆
+    async fn put(&self, requested: &str, output: &FetchOutput) -> nl_core::Result<()> {
+        let fetched_at = chrono::Utc::now();
+        let path = self.path(requested);
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let page = CachedPage {
+            fetched_at,
+            output: output.clone(),
+        };
+        tokio::fs::write(&path, serde_json::to_vec(&page)?).await?;
+
+        for stale in self.index.all_entries().into_iter().filter(|e| {
+            e.metadata.get("kind").map(String::as_str) == Some("web_page")
+                && e.metadata.get("url").map(String::as_str) == Some(requested)
+        }) {
+            self.index.remove(&stale.id);
+        }
+        let label = output.title.clone().unwrap_or_else(|| output.url.clone());
+        let mut entry = MemoryEntry::new(
+            truncate_chars(&label, TAG_CHARS),
+            truncate_chars(&output.content, SUMMARY_CHARS),
+        );
+        entry.full_data_path = Some(path.to_string_lossy().into_owned());
+        entry.metadata.insert("kind".to_string(), "web_page".to_string());
+        entry.metadata.insert("url".to_string(), requested.to_string());
+        entry.metadata.insert("fetched_at".to_string(), fetched_at.to_rfc3339());
+        self.index.store(entry);
+        Ok(())
+    }
+}
+
+/// 缓存文件内容
+#[derive(Serialize, Deserialize)]
+struct CachedPage {
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    output: FetchOutput,
+}
+
+/// 执行请求（策略检查由调用方完成）
+pub async fn execute(fetch: &HttpFetch, policy: &HttpPolicy, cache: Option<&HttpCache>) -> GodModeResult {
+    let cacheable = fetch.is_get() && fetch.headers.is_empty();
+    let result = match cache.filter(|_| cacheable) {
+        Some(cache) => match cache.get(&fetch.url).await {
+            Some(output) => Ok(output),
+            None => {
+                let output = request(fetch, policy).await;
+                if let Ok(output) = &output {
+                    if (200..300).contains(&output.status) {
+                        if let Err(e) = cache.put(&fetch.url, output).await {
+                            tracing::warn!("Failed to cache {}: {}", fetch.url, e);
+                        }
+                    }
+                }
+                output
+            }
+        },
+        None => request(fetch, policy).await,
+    };
+    match result {
+        Ok(output) => GodModeResult {
+            success: (200..400).contains(&output.status),
+            error: (!(200..400).contains(&output.status)).then(|| format!("HTTP status {}", output.status)),
+            output: serde_json::to_string(&output).unwrap_or_default(),
+        },
+        Err(error) => GodModeResult {
+            success: false,
+            output: String::new(),
+            error: Some(error),
+        },
+    }
+}
+
+async fn request(fetch: &HttpFetch, policy: &HttpPolicy) -> Result<FetchOutput, String> {
+    let redirect_policy = policy.clone();
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(policy.timeout_secs))
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= 10 {
+                attempt.error("too many redirects")
+            } else if let Err(reason) = redirect_policy.check_url(attempt.url()) {
+                attempt.error(reason)
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let method =
+        reqwest::Method::from_bytes(fetch.method.to_ascii_uppercase().as_bytes()).map_err(|e| e.to_string())?;
+    let mut builder = client.request(method, &fetch.url);
+    for (name, value) in &fetch.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = &fetch.body {
+        builder = builder.body(body.clone());
+    }
+    let mut response = builder.send().await.map_err(|e| e.to_string())?;
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let mut output = FetchOutput {
+        url: response.url().to_string(),
+        status: response.status().as_u16(),
+        content_type,
+        ..Default::default()
+    };
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        let remaining = policy.max_response_bytes - body.len();
+        if chunk.len() > remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            output.truncated = true;
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let text = String::from_utf8_lossy(&body).into_owned();
+    if output.content_type.contains("html") {
+        output.title = html_title(&text);
+        output.content = tokio::task::spawn_blocking(move || html_to_markdown(&text))
+            .await
+            .map_err(|e| e.to_string())?;
+    } else if is_text(&output.content_type) {
+        output.content = text;
+    } else {
+        output.content = format!("<{} bytes of {}>", body.len(), output.content_type);
+    }
+    Ok(output)
+}
+
+fn is_text(content_type: &str) -> bool {
+    content_type.is_empty()
+        || content_type.starts_with("text/")
+        || ["json", "xml", "yaml", "javascript", "toml"].iter().any(|kind| content_type.contains(kind))
+}
+
+/// 整段丢弃元素及其内容
+struct SkipElement;
+
+impl html2md::TagHandler for SkipElement {
+    fn handle(&mut self, _tag: &html2md::Handle, _printer: &mut html2md::StructuredPrinter) {}
+
+    fn after_handle(&mut self, _printer: &mut html2md::StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+impl html2md::TagHandlerFactory for SkipElement {
+    fn instantiate(&self) -> Box<dyn html2md::TagHandler> {
+        Box::new(SkipElement)
+    }
+}
+
+/// HTML 转 Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let handlers: HashMap<String, Box<dyn html2md::TagHandlerFactory>> = SKIPPED_ELEMENTS
+        .iter()
+        .map(|tag| (tag.to_string(), Box::new(SkipElement) as Box<dyn html2md::TagHandlerFactory>))
+        .collect();
+    html2md::parse_html_custom(html, &handlers)
+}
+
+/// `<title>` 的文本
+fn html_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = html[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_allowlist_markdown_and_cache() {
+        let policy = HttpPolicy::default().allowing_domains(["docs.rs", "*.example.com"]);
+        assert!(policy.check(&HttpFetch::get("https://docs.rs/tokio")).is_ok());
+        assert!(policy.check(&HttpFetch::get("https://api.example.com/v1")).is_ok());
+        assert!(policy.check(&HttpFetch::get("https://evil-docs.rs/")).is_err());
+        assert!(policy.check(&HttpFetch::get("file:///etc/passwd")).is_err());
+        assert!(HttpPolicy::default().check(&HttpFetch::get("https://docs.rs/")).is_err());
+        let post = HttpFetch {
+            method: "POST".to_string(),
+            ..HttpFetch::get("https://docs.rs/")
+        };
+        assert!(policy.check(&post).is_err());
+        assert!(policy.clone().allowing_methods(["GET", "POST"]).check(&post).is_ok());
+
+        let html = "<html><head><title> Tokio\n docs </title><style>p { color: red }</style></head>\
+                    <body><nav>Home</nav><h1>Runtime</h1><p>Use <a href=\"/spawn\">spawn</a>.</p>\
+                    <script>alert(1)</script></body></html>";
+        assert_eq!(html_title(html).as_deref(), Some("Tokio docs"));
+        let markdown = html_to_markdown(html);
+        assert!(markdown.contains("Runtime") && markdown.contains("[spawn](/spawn)"), "{}", markdown);
+        assert!(!markdown.contains("alert") && !markdown.contains("color") && !markdown.contains("Home"));
+
+        let dir = std::env::temp_dir().join(format!("nl_http_{}", uuid::Uuid::new_v4()));
+        let index = Arc::new(HamtIndex::new());
+        let cache = HttpCache::new(index.clone(), &dir);
+        let page = FetchOutput {
+            url: "https://docs.rs/tokio".to_string(),
+            status: 200,
+            content_type: "text/html".to_string(),
+            title: Some("Tokio docs".to_string()),
+            content: markdown,
+            ..Default::default()
+        };
+        cache.put("https://docs.rs/tokio", &page).await.unwrap();
+        cache.put("https://docs.rs/tokio", &page).await.unwrap();
+        assert_eq!(index.count(), 1);
+        assert_eq!(index.retrieve_by_tag("Tokio docs").unwrap().metadata["url"], "https://docs.rs/tokio");
+        // 缓存命中时不发出请求，白名单为空也能返回
+        let result = execute(&HttpFetch::get("https://docs.rs/tokio"), &HttpPolicy::default(), Some(&cache)).await;
+        let output: FetchOutput = serde_json::from_str(&result.output).unwrap();
+        assert!(result.success && output.cached && output.content.contains("Runtime"));
+        assert!(HttpCache::new(index, &dir)
+            .with_ttl(chrono::Duration::zero())
+            .get("https://docs.rs/tokio")
+            .await
+            .is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,538 @@
+//! 输入注入
+//!
+//! 以 `GodModeAction::Input` 形式执行的鼠标 / 键盘操作，闭合“感知 → 行动”回路：
+//! - 属于独立的高风险类别，由 `InputPolicy` 单独管控：默认每次操作都须经 `InputApprover` 批准，
+//!   未配置批准者时一律拒绝；另有每分钟次数上限、文本长度上限与禁用组合键
+//! - 操作可携带来自 nl_vision 的目标元素（角色、名称、坐标），与批准者一起写入审计链；
+//!   输入文本只记录摘要与长度
+//! - 各平台通过脚本宿主注入（Windows PowerShell + user32 / SendKeys、macOS JXA + CoreGraphics /
+//!   System Events、Linux xdotool），输入文本经环境变量传递，不拼入脚本
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use nl_vision::ui_elements::UiElement;
+
+use crate::god_mode::GodModeResult;
+
+/// 注入文本所用的环境变量
+const TEXT_ENV: &str = "NL_INPUT_TEXT";
+
+/// 单次注入的超时
+const INJECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 鼠标按键
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton {
+    #[default]
+    Left,
+    Right,
+    Middle,
+}
+
+/// 输入操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InputAction {
+    /// 移动鼠标到屏幕坐标
+    MoveMouse {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        target: Option<UiElement>,
+    },
+    /// 在屏幕坐标点击
+    Click {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        button: MouseButton,
+        #[serde(default)]
+        double: bool,
+        #[serde(default)]
+        target: Option<UiElement>,
+    },
+    /// 向当前焦点输入文本
+    TypeText {
+        text: String,
+        #[serde(default)]
+        target: Option<UiElement>,
+    },
+    /// 组合键（如 `["ctrl", "s"]`）
+    Hotkey {
+        keys: Vec<String>,
+        #[serde(default)]
+        target: Option<UiElement>,
+    },
+}
+
+impl InputAction {
+    /// 点击元素中心
+    pub fn click_element(element: &UiElement) -> Self {
+        let (x, y) = element.bounds.center();
+        InputAction::Click {
+            x,
+            y,
+            button: MouseButton::Left,
+            double: false,
+            target: Some(element.clone()),
+        }
+    }
+
+    /// 操作名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::MoveMouse { .. } => "input_move_mouse",
+            InputAction::Click { .. } => "input_click",
+            InputAction::TypeText { .. } => "input_type_text",
+            InputAction::Hotkey { .. } => "input_hotkey",
+        }
+    }
+
+    /// 目标元素
+    pub fn target(&self) -> Option<&UiElement> {
+        match self {
+            InputAction::MoveMouse { target, .. }
+            | InputAction::Click { target, .. }
+            | InputAction::TypeText { target, .. }
+            | InputAction::Hotkey { target, .. } => target.as_ref(),
+        }
+    }
+
+    /// 审计用参数：文本只记录摘要，附带目标元素元数据
+    pub fn audit_arguments(&self) -> serde_json::Value {
+        let mut arguments = match self {
+            InputAction::MoveMouse { x, y, .. } => serde_json::json!({ "x": x, "y": y }),
+            InputAction::Click { x, y, button, double, .. } => {
+                serde_json::json!({ "x": x, "y": y, "button": button, "double": double })
+            }
+            InputAction::TypeText { text, .. } => serde_json::json!({
+                "text": format!("sha256:{:x}", Sha256::digest(text.as_bytes())),
+                "chars": text.chars().count(),
+            }),
+            InputAction::Hotkey { keys, .. } => serde_json::json!({ "keys": keys }),
+        };
+        if let Some(target) = self.target() {
+            arguments["target"] = serde_json::json!({
+                "index": target.index,
+                "role": target.role.to_string(),
+                "name": target.name,
+                "bounds": target.bounds,
+                "source": target.source,
+            });
+        }
+        arguments
+    }
+}
+
+/// 输入批准者（人工确认、远程审批等）
+#[async_trait]
+pub trait InputApprover: Send + Sync {
+    /// 批准时返回批准者标识，拒绝时返回原因
+    async fn approve(&self, action: &InputAction) -> Result<String, String>;
+}
+
+/// 输入策略（高风险类别）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputPolicy {
+    /// 是否允许输入注入
+    pub enabled: bool,
+    /// 是否每次都须批准
+    pub require_approval: bool,
+    /// 每分钟最多执行的操作数
+    pub max_actions_per_minute: u32,
+    /// 单次输入文本的最大字符数
+    pub max_text_chars: usize,
+    /// 禁用的组合键（小写，`+` 连接，修饰键顺序无关）
+    pub blocked_hotkeys: Vec<String>,
+}
+
+impl Default for InputPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            require_approval: true,
+            max_actions_per_minute: 30,
+            max_text_chars: 2000,
+            blocked_hotkeys: vec![
+                "ctrl+alt+delete".to_string(),
+                "meta+l".to_string(),
+                "ctrl+meta+q".to_string(),
+            ],
+        }
+    }
+}
+
+impl InputPolicy {
+    /// 免批准（仅用于受控环境，如无人值守的测试桌面）
+    pub fn without_approval(mut self) -> Self {
+        self.require_approval = false;
+        self
+    }
+
+    /// 检查操作本身，拒绝时返回原因（频率由 `InputRateLimiter` 另行控制）
+    pub fn check(&self, action: &InputAction) -> Result<(), String> {
+        if !self.enabled {
+            return Err("input injection is disabled by policy".to_string());
+        }
+        match action {
+            InputAction::MoveMouse { x, y, .. } | InputAction::Click { x, y, .. } if *x < 0 || *y < 0 => {
+                Err(format!("coordinates ({}, {}) are off screen", x, y))
+            }
+            InputAction::TypeText { text, .. } if text.chars().count() > self.max_text_chars => Err(format!(
+                "text of {} chars exceeds the {} char limit",
+                text.chars().count(),
+                self.max_text_chars
+            )),
+            InputAction::Hotkey { keys, .. } => {
+                let combo = normalize_combo(keys)?;
+                let blocked = self.blocked_hotkeys.iter().any(|b| {
+                    let parts: Vec<String> = b.split('+').map(str::to_string).collect();
+                    normalize_combo(&parts).is_ok_and(|b| b == combo)
+                });
+                if blocked {
+                    return Err(format!("hotkey {} is blocked by policy", combo.join("+")));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 滑动窗口限流（最近一分钟）
+#[derive(Debug, Default)]
+pub struct InputRateLimiter {
+    recent: std::sync::Mutex<VecDeque<Instant>>,
+}
+
+impl InputRateLimiter {
+    /// 记录一次操作，超过上限时拒绝
+    pub fn try_acquire(&self, max_per_minute: u32) -> Result<(), String> {
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        while recent.front().is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(60)) {
+            recent.pop_front();
+        }
+        if recent.len() >= max_per_minute as usize {
+            return Err(format!("input rate limit of {} actions per minute reached", max_per_minute));
+        }
+        recent.push_back(now);
+        Ok(())
+    }
+}
+
+/// 修饰键
+const MODIFIERS: [&str; 4] = ["ctrl", "alt", "shift", "meta"];
+
+/// 归一化组合键：小写、别名统一、修饰键按固定顺序在前，且恰好一个非修饰键
+fn normalize_combo(keys: &[String]) -> Result<Vec<String>, String> {
+    let mut modifiers = Vec::new();
+    let mut key = None;
+    for raw in keys.iter().flat_map(|k| k.split('+')) {
+        let name = match raw.trim().to_lowercase().as_str() {
+            "control" | "ctrl" => "ctrl".to_string(),
+            "option" | "alt" => "alt".to_string(),
+            "cmd" | "command" | "win" | "super" | "meta" => "meta".to_string(),
+            "escape" | "esc" => "esc".to_string(),
+            "return" | "enter" => "enter".to_string(),
+            "del" | "delete" => "delete".to_string(),
+            "" => continue,
+            other => other.to_string(),
+        };
+        if MODIFIERS.contains(&name.as_str()) {
+            if !modifiers.contains(&name) {
+                modifiers.push(name);
+            }
+        } else if key.replace(name).is_some() {
+            return Err(format!("hotkey {} has more than one non-modifier key", keys.join("+")));
+        }
+    }
+    let Some(key) = key else {
+        return Err("hotkey needs a non-modifier key".to_string());
+    };
+    modifiers.sort_by_key(|m| MODIFIERS.iter().position(|o| o == m));
+    modifiers.push(key);
+    Ok(modifiers)
+}
+
+/// 执行输入操作
+pub async fn execute(action: &InputAction) -> GodModeResult {
+    match inject(action).await {
+        Ok(()) => GodModeResult {
+            success: true,
+            output: describe(action),
+            error: None,
+        },
+        Err(e) => GodModeResult {
+            success: false,
+            output: String::new(),
+            error: Some(e),
+        },
+    }
+}
+
+fn describe(action: &InputAction) -> String {
+    let target = action
+        .target()
+        .map(|t| format!(" on {} \"{}\"", t.role, t.name))
+        .unwrap_or_default();
+    match action {
+        InputAction::MoveMouse { x, y, .. } => format!("Moved mouse to ({}, {}){}", x, y, target),
+        InputAction::Click { x, y, double, .. } => {
+            format!("{} at ({}, {}){}", if *double { "Double-clicked" } else { "Clicked" }, x, y, target)
+        }
+        InputAction::TypeText { text, .. } => format!("Typed {} chars{}", text.chars().count(), target),
+        InputAction::Hotkey { keys, .. } => format!("Pressed {}{}", keys.join("+"), target),
+    }
+}
+
+async fn inject(action: &InputAction) -> Result<(), String> {
+    let mut command = platform::command(action)?;
+    if let InputAction::TypeText { text, .. } = action {
+        command.env(TEXT_ENV, text);
+    }
+    command.kill_on_drop(true);
+    let output = tokio::time::timeout(INJECT_TIMEOUT, command.output())
+        .await
+        .map_err(|_| "input injection timed out".to_string())?
+        .map_err(|e| format!("failed to start input backend: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+
+    const USER32: &str = r#"Add-Type 'using System; using System.Runtime.InteropServices; public static class U { [DllImport("user32.dll")] public static extern bool SetCursorPos(int x, int y); [DllImport("user32.dll")] public static extern void mouse_event(uint f, uint x, uint y, uint d, UIntPtr e); }'"#;
+
+    pub fn command(action: &InputAction) -> Result<tokio::process::Command, String> {
+        let script = match action {
+            InputAction::MoveMouse { x, y, .. } => format!("{}; [U]::SetCursorPos({}, {})", USER32, x, y),
+            InputAction::Click { x, y, button, double, .. } => {
+                let (down, up) = match button {
+                    MouseButton::Left => (0x02, 0x04),
+                    MouseButton::Right => (0x08, 0x10),
+                    MouseButton::Middle => (0x20, 0x40),
+                };
+                let click = format!("[U]::mouse_event({}, 0, 0, 0, [UIntPtr]::Zero); [U]::mouse_event({}, 0, 0, 0, [UIntPtr]::Zero)", down, up);
+                let clicks = if *double { format!("{}; {}", click, click) } else { click };
+                format!("{}; [U]::SetCursorPos({}, {}); {}", USER32, x, y, clicks)
+            }
+            InputAction::TypeText { .. } => format!(
+                "Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.SendKeys]::SendWait(($env:{} -replace '([+^%~(){{}}\\[\\]])', '{{$1}}'))",
+                TEXT_ENV
+            ),
+            InputAction::Hotkey { keys, .. } => format!(
+                "Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.SendKeys]::SendWait('{}')",
+                send_keys(keys)?
+            ),
+        };
+        let mut command = tokio::process::Command::new("powershell");
+        command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+        Ok(command)
+    }
+
+    /// SendKeys 记法（不支持 Windows 键）
+    fn send_keys(keys: &[String]) -> Result<String, String> {
+        let combo = normalize_combo(keys)?;
+        let (key, modifiers) = combo.split_last().expect("combo has a key");
+        let mut notation = String::new();
+        for modifier in modifiers {
+            notation.push_str(match modifier.as_str() {
+                "ctrl" => "^",
+                "alt" => "%",
+                "shift" => "+",
+                _ => return Err("the Windows key cannot be sent with SendKeys".to_string()),
+            });
+        }
+        notation.push_str(&match key.as_str() {
+            "enter" => "{ENTER}".to_string(),
+            "esc" => "{ESC}".to_string(),
+            "tab" => "{TAB}".to_string(),
+            "space" => " ".to_string(),
+            "backspace" => "{BACKSPACE}".to_string(),
+            "delete" => "{DELETE}".to_string(),
+            "up" | "down" | "left" | "right" | "home" | "end" => format!("{{{}}}", key.to_uppercase()),
+            "pageup" => "{PGUP}".to_string(),
+            "pagedown" => "{PGDN}".to_string(),
+            f if f.starts_with('f') && f[1..].parse::<u8>().is_ok() => format!("{{{}}}", f.to_uppercase()),
+            c if c.chars().count() == 1 && c.chars().all(|c| c.is_ascii_alphanumeric()) => c.to_string(),
+            other => return Err(format!("unsupported key: {}", other)),
+        });
+        Ok(notation)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    pub fn command(action: &InputAction) -> Result<tokio::process::Command, String> {
+        let script = match action {
+            InputAction::MoveMouse { x, y, .. } => mouse_script(*x, *y, &[5]),
+            InputAction::Click { x, y, button, double, .. } => {
+                // CGEventType：左键 1/2、右键 3/4、其他键 25/26
+                let (down, up) = match button {
+                    MouseButton::Left => (1, 2),
+                    MouseButton::Right => (3, 4),
+                    MouseButton::Middle => (25, 26),
+                };
+                let mut events = vec![5, down, up];
+                if *double {
+                    events.extend([down, up]);
+                }
+                mouse_script(*x, *y, &events)
+            }
+            InputAction::TypeText { .. } => format!(
+                "ObjC.import('Foundation'); Application('System Events').keystroke($.NSProcessInfo.processInfo.environment.objectForKey('{}').js)",
+                TEXT_ENV
+            ),
+            InputAction::Hotkey { keys, .. } => hotkey_script(keys)?,
+        };
+        let mut command = tokio::process::Command::new("osascript");
+        command.args(["-l", "JavaScript", "-e", &script]);
+        Ok(command)
+    }
+
+    fn mouse_script(x: i32, y: i32, events: &[u32]) -> String {
+        format!(
+            "ObjC.import('CoreGraphics'); const p = $.CGPointMake({}, {}); for (const t of {:?}) {{ $.CGEventPost(0, $.CGEventCreateMouseEvent(null, t, p, t >= 25 ? 2 : (t === 3 || t === 4 ? 1 : 0))); delay(0.03); }}",
+            x, y, events
+        )
+    }
+
+    fn hotkey_script(keys: &[String]) -> Result<String, String> {
+        let combo = normalize_combo(keys)?;
+        let (key, modifiers) = combo.split_last().expect("combo has a key");
+        let using: Vec<&str> = modifiers
+            .iter()
+            .map(|m| match m.as_str() {
+                "ctrl" => "control down",
+                "alt" => "option down",
+                "shift" => "shift down",
+                _ => "command down",
+            })
+            .collect();
+        let code = match key.as_str() {
+            "enter" => Some(36),
+            "tab" => Some(48),
+            "space" => Some(49),
+            "backspace" => Some(51),
+            "esc" => Some(53),
+            "delete" => Some(117),
+            "left" => Some(123),
+            "right" => Some(124),
+            "down" => Some(125),
+            "up" => Some(126),
+            c if c.chars().count() == 1 => None,
+            other => return Err(format!("unsupported key: {}", other)),
+        };
+        let using = serde_json::to_string(&using).unwrap_or_default();
+        Ok(match code {
+            Some(code) => format!("Application('System Events').keyCode({}, {{ using: {} }})", code, using),
+            None => format!(
+                "Application('System Events').keystroke({}, {{ using: {} }})",
+                serde_json::to_string(key).unwrap_or_default(),
+                using
+            ),
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+
+    pub fn command(action: &InputAction) -> Result<tokio::process::Command, String> {
+        let mut command = tokio::process::Command::new("xdotool");
+        match action {
+            InputAction::MoveMouse { x, y, .. } => {
+                command.args(["mousemove", &x.to_string(), &y.to_string()]);
+            }
+            InputAction::Click { x, y, button, double, .. } => {
+                let button = match button {
+                    MouseButton::Left => "1",
+                    MouseButton::Middle => "2",
+                    MouseButton::Right => "3",
+                };
+                let repeat = if *double { "2" } else { "1" };
+                command.args(["mousemove", &x.to_string(), &y.to_string(), "click", "--repeat", repeat, button]);
+            }
+            InputAction::TypeText { text, .. } => {
+                command.args(["type", "--delay", "12", "--", text]);
+            }
+            InputAction::Hotkey { keys, .. } => {
+                command.args(["key", "--", &keysym(keys)?]);
+            }
+        }
+        Ok(command)
+    }
+
+    /// xdotool 键名
+    fn keysym(keys: &[String]) -> Result<String, String> {
+        let combo = normalize_combo(keys)?;
+        let names: Vec<String> = combo
+            .iter()
+            .map(|k| match k.as_str() {
+                "meta" => "super".to_string(),
+                "enter" => "Return".to_string(),
+                "esc" => "Escape".to_string(),
+                "tab" => "Tab".to_string(),
+                "space" => "space".to_string(),
+                "backspace" => "BackSpace".to_string(),
+                "delete" => "Delete".to_string(),
+                "up" | "down" | "left" | "right" | "home" | "end" => {
+                    let mut name = k.clone();
+                    name[..1].make_ascii_uppercase();
+                    name
+                }
+                "pageup" => "Prior".to_string(),
+                "pagedown" => "Next".to_string(),
+                f if f.starts_with('f') && f[1..].parse::<u8>().is_ok() => f.to_uppercase(),
+                other => other.to_string(),
+            })
+            .collect();
+        Ok(names.join("+"))
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+mod platform {
+    use super::*;
+
+    pub fn command(_action: &InputAction) -> Result<tokio::process::Command, String> {
+        Err("input injection is not supported on this platform".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_blocks_hotkeys_and_rate_limits() {
+        let policy = InputPolicy::default();
+        let hotkey = |keys: &[&str]| InputAction::Hotkey {
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+            target: None,
+        };
+        assert!(policy.check(&hotkey(&["ctrl", "s"])).is_ok());
+        assert!(policy.check(&hotkey(&["Delete", "Alt", "Control"])).is_err());
+        assert!(policy.check(&hotkey(&["win+l"])).is_err());
+        assert!(policy.check(&hotkey(&["ctrl"])).is_err());
+
+        let limiter = InputRateLimiter::default();
+        assert!(limiter.try_acquire(2).is_ok());
+        assert!(limiter.try_acquire(2).is_ok());
+        assert!(limiter.try_acquire(2).is_err());
+    }
+}
@@ -0,0 +1,440 @@
+//! 提升事务的预写日志
+//!
+//! `promote` 的回滚只在进程存活时有效；写回中途进程退出会让真实工作区停在部分应用的状态。
+//! 提升开始前在 `<base>/.journal/<task_id>/` 写入日志（JSON Lines，每条记录落盘后才继续）：
+//! - `begin` 记录全部文件操作及新旧内容摘要
+//! - 每个文件操作前先把原内容备份为 `<序号>.orig`，再记录 `intent`；操作完成后记录 `applied`
+//! - 全部完成后记录 `commit` 并删除日志目录；中途失败时先记录 `abort` 再从备份恢复
+//!
+//! 启动时 `ScratchManager::recover` 扫描未提交的日志：按 `RecoveryPolicy` 续做剩余操作
+//! （未中止，且副本内容与尚未改动的目标文件都与日志一致时），否则按相反顺序从备份恢复已开始的操作。
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+
+use crate::scratch::ChangeKind;
+
+/// 日志目录名（位于临时工作区根目录下）
+pub const JOURNAL_DIR: &str = ".journal";
+
+const WAL_FILE: &str = "wal.jsonl";
+
+/// 启动时对未完成提升的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryPolicy {
+    /// 续做剩余操作；副本或目标文件已与日志不一致时回滚
+    #[default]
+    Resume,
+    /// 一律回滚到提升前的状态
+    Rollback,
+}
+
+/// 恢复结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryOutcome {
+    /// 剩余操作已续做完成
+    Resumed,
+    /// 已恢复到提升前的状态
+    RolledBack,
+    /// 日志已提交，只清理了残留
+    Committed,
+}
+
+/// 一次未完成提升的恢复报告
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveredPromotion {
+    pub task_id: Uuid,
+    pub verdict_id: Uuid,
+    pub outcome: RecoveryOutcome,
+    /// 续做或恢复的文件数
+    pub files: usize,
+}
+
+/// 一个文件操作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct JournalOp {
+    /// 相对项目根目录的路径
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    /// 操作前内容的摘要（文件不存在时为 `None`）
+    pub original: Option<String>,
+    /// 写入内容的摘要（删除时为 `None`）
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    Begin {
+        task_id: Uuid,
+        verdict_id: Uuid,
+        source: PathBuf,
+        scratch: PathBuf,
+        ops: Vec<JournalOp>,
+    },
+    Intent {
+        index: usize,
+    },
+    Applied {
+        index: usize,
+    },
+    Commit,
+    Abort,
+}
+
+/// 一次提升的日志
+pub(crate) struct PromotionJournal {
+    dir: PathBuf,
+    wal: File,
+    task_id: Uuid,
+    verdict_id: Uuid,
+    source: PathBuf,
+    scratch: PathBuf,
+    ops: Vec<JournalOp>,
+    /// 已记录 intent 的操作序号
+    started: Vec<usize>,
+    applied: Vec<usize>,
+}
+
+impl PromotionJournal {
+    /// 写入 `begin` 记录
+    pub fn begin(
+        base: &Path,
+        task_id: Uuid,
+        verdict_id: Uuid,
+        source: &Path,
+        scratch: &Path,
+        ops: Vec<JournalOp>,
+    ) -> Result<Self> {
+        let dir = base.join(JOURNAL_DIR).join(task_id.to_string());
+        if dir.exists() {
+            return Err(NeuroLoomError::Sandbox(format!(
+                "task {} has an unfinished promotion journal; recover it first",
+                task_id
+            )));
+        }
+        std::fs::create_dir_all(&dir)?;
+        let wal = OpenOptions::new().create(true).append(true).open(dir.join(WAL_FILE))?;
+        let mut journal = Self {
+            dir,
+            wal,
+            task_id,
+            verdict_id,
+            source: source.to_path_buf(),
+            scratch: scratch.to_path_buf(),
+            ops,
+            started: Vec::new(),
+            applied: Vec::new(),
+        };
+        journal.append(&Record::Begin {
+            task_id,
+            verdict_id,
+            source: journal.source.clone(),
+            scratch: journal.scratch.clone(),
+            ops: journal.ops.clone(),
+        })?;
+        Ok(journal)
+    }
+
+    /// 全部操作
+    pub fn ops(&self) -> &[JournalOp] {
+        &self.ops
+    }
+
+    /// 执行第 `index` 个操作：备份原内容、记录 intent、写回、记录 applied
+    pub fn apply(&mut self, index: usize) -> Result<()> {
+        let op = self.ops[index].clone();
+        let target = self.source.join(&op.path);
+        if !self.started.contains(&index) {
+            if let Some(original) = read_optional(&target)? {
+                write_synced(&self.backup(index), &original)?;
+            }
+            self.append(&Record::Intent { index })?;
+            self.started.push(index);
+        }
+        match op.kind {
+            ChangeKind::Deleted => remove_optional(&target)?,
+            _ => write_atomic(&target, &std::fs::read(self.scratch.join(&op.path))?)?,
+        }
+        self.append(&Record::Applied { index })?;
+        self.applied.push(index);
+        Ok(())
+    }
+
+    /// 记录 `commit` 并删除日志
+    pub fn commit(mut self) -> Result<()> {
+        self.append(&Record::Commit)?;
+        std::fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+
+    /// 按相反顺序恢复已开始的操作并删除日志，返回恢复的文件数
+    pub fn rollback(mut self) -> Result<usize> {
+        // 恢复途中退出时，下次启动不能再续做
+        self.append(&Record::Abort)?;
+        let mut restored = 0;
+        for &index in self.started.iter().rev() {
+            let op = &self.ops[index];
+            let target = self.source.join(&op.path);
+            match op.original {
+                Some(_) => write_atomic(&target, &std::fs::read(self.backup(index))?)?,
+                None => remove_optional(&target)?,
+            }
+            restored += 1;
+        }
+        std::fs::remove_dir_all(&self.dir)?;
+        Ok(restored)
+    }
+
+    fn backup(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("{}.orig", index))
+    }
+
+    fn append(&mut self, record: &Record) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.wal.write_all(&line)?;
+        self.wal.sync_data()?;
+        Ok(())
+    }
+
+    /// 读取日志，没有 `begin` 记录时返回 `None`；最后一行写到一半（进程在写入时退出）时忽略该行
+    fn load(dir: &Path) -> Result<Option<(Self, Option<Record>)>> {
+        let file = match File::open(dir.join(WAL_FILE)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            match serde_json::from_str::<Record>(&line?) {
+                Ok(record) => records.push(record),
+                Err(_) => break,
+            }
+        }
+        let mut records = records.into_iter();
+        let Some(Record::Begin {
+            task_id,
+            verdict_id,
+            source,
+            scratch,
+            ops,
+        }) = records.next()
+        else {
+            return Ok(None);
+        };
+        let wal = OpenOptions::new().append(true).open(dir.join(WAL_FILE))?;
+        let mut journal = Self {
+            dir: dir.to_path_buf(),
+            wal,
+            task_id,
+            verdict_id,
+            source,
+            scratch,
+            ops,
+            started: Vec::new(),
+            applied: Vec::new(),
+        };
+        let mut end = None;
+        for record in records {
+            match record {
+                Record::Intent { index } if index < journal.ops.len() => journal.started.push(index),
+                Record::Applied { index } if index < journal.ops.len() => journal.applied.push(index),
+                record @ (Record::Commit | Record::Abort) => end = Some(record),
+                _ => {}
+            }
+        }
+        Ok(Some((journal, end)))
+    }
+
+    /// 续做的前提：未完成操作的新内容仍在副本中，未开始操作的目标文件仍是原内容
+    fn can_resume(&self) -> Result<bool> {
+        for (index, op) in self.ops.iter().enumerate() {
+            if self.applied.contains(&index) {
+                continue;
+            }
+            if let Some(target) = &op.target {
+                if read_optional(&self.scratch.join(&op.path))?.map(|bytes| digest(&bytes)).as_ref() != Some(target) {
+                    return Ok(false);
+                }
+            }
+            if !self.started.contains(&index) {
+                let current = read_optional(&self.source.join(&op.path))?.map(|bytes| digest(&bytes));
+                if current != op.original {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// 恢复 `base` 下所有未完成的提升
+pub(crate) fn recover(base: &Path, policy: RecoveryPolicy) -> Result<Vec<RecoveredPromotion>> {
+    let root = base.join(JOURNAL_DIR);
+    let entries = match std::fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut recovered = Vec::new();
+    for entry in entries {
+        let dir = entry?.path();
+        let Some((mut journal, end)) = PromotionJournal::load(&dir)? else {
+            // begin 记录落盘前退出：尚未改动任何文件
+            std::fs::remove_dir_all(&dir)?;
+            continue;
+        };
+        let (task_id, verdict_id) = (journal.task_id, journal.verdict_id);
+        let (outcome, files) = if matches!(end, Some(Record::Commit)) {
+            std::fs::remove_dir_all(&dir)?;
+            (RecoveryOutcome::Committed, 0)
+        } else if end.is_none() && policy == RecoveryPolicy::Resume && journal.can_resume()? {
+            let pending: Vec<usize> = (0..journal.ops.len()).filter(|i| !journal.applied.contains(i)).collect();
+            for &index in &pending {
+                journal.apply(index)?;
+            }
+            let scratch = journal.scratch.clone();
+            journal.commit()?;
+            // 与正常提升一致：写回完成后删除副本
+            if scratch.exists() {
+                std::fs::remove_dir_all(&scratch)?;
+            }
+            (RecoveryOutcome::Resumed, pending.len())
+        } else {
+            (RecoveryOutcome::RolledBack, journal.rollback()?)
+        };
+        tracing::warn!(
+            "Recovered interrupted promotion of task {} (verdict {}): {:?}, {} files",
+            task_id,
+            verdict_id,
+            outcome,
+            files
+        );
+        recovered.push(RecoveredPromotion {
+            task_id,
+            verdict_id,
+            outcome,
+            files,
+        });
+    }
+    Ok(recovered)
+}
+
+pub(crate) fn digest(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+pub(crate) fn read_optional(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn remove_optional(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_synced(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// 先写同目录下的临时文件并落盘，再改名
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let staging = path.with_file_name(format!(".{}.nl-promote", name));
+    write_synced(&staging, bytes)?;
+    std::fs::rename(&staging, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(path: &str, kind: ChangeKind, original: Option<&str>, target: Option<&str>) -> JournalOp {
+        JournalOp {
+            path: path.into(),
+            kind,
+            original: original.map(|s| digest(s.as_bytes())),
+            target: target.map(|s| digest(s.as_bytes())),
+        }
+    }
+
+    /// 写回第一个文件后“崩溃”（日志未提交即丢弃）
+    fn interrupted(dir: &Path) -> (PathBuf, PathBuf, Uuid) {
+        let (source, scratch) = (dir.join("project"), dir.join("scratch"));
+        let _ = std::fs::remove_dir_all(dir);
+        for root in [&source, &scratch] {
+            std::fs::create_dir_all(root).unwrap();
+        }
+        std::fs::write(source.join("a.txt"), "a0").unwrap();
+        std::fs::write(source.join("old.txt"), "old").unwrap();
+        std::fs::write(scratch.join("a.txt"), "a1").unwrap();
+        std::fs::write(scratch.join("new.txt"), "new").unwrap();
+        let ops = vec![
+            op("a.txt", ChangeKind::Modified, Some("a0"), Some("a1")),
+            op("new.txt", ChangeKind::Added, None, Some("new")),
+            op("old.txt", ChangeKind::Deleted, Some("old"), None),
+        ];
+        let task = Uuid::new_v4();
+        let mut journal = PromotionJournal::begin(dir, task, Uuid::new_v4(), &source, &scratch, ops).unwrap();
+        journal.apply(0).unwrap();
+        drop(journal);
+        (source, scratch, task)
+    }
+
+    #[test]
+    fn test_recover_resumes_or_rolls_back_interrupted_promotion() {
+        let dir = std::env::temp_dir().join(format!("nl_journal_{}", Uuid::new_v4()));
+        let read = |path: PathBuf| std::fs::read_to_string(path).ok();
+
+        let (source, scratch, task) = interrupted(&dir);
+        assert_eq!(read(source.join("a.txt")).as_deref(), Some("a1"));
+        let recovered = recover(&dir, RecoveryPolicy::Resume).unwrap();
+        assert_eq!(
+            (recovered[0].task_id, recovered[0].outcome, recovered[0].files),
+            (task, RecoveryOutcome::Resumed, 2)
+        );
+        assert_eq!(read(source.join("new.txt")).as_deref(), Some("new"));
+        assert!(!source.join("old.txt").exists() && !scratch.exists());
+        assert!(recover(&dir, RecoveryPolicy::Resume).unwrap().is_empty());
+
+        // 副本已被改动，无法续做：回滚到提升前
+        let (source, scratch, _) = interrupted(&dir);
+        std::fs::write(scratch.join("new.txt"), "changed").unwrap();
+        let recovered = recover(&dir, RecoveryPolicy::Resume).unwrap();
+        assert_eq!((recovered[0].outcome, recovered[0].files), (RecoveryOutcome::RolledBack, 1));
+        assert_eq!(read(source.join("a.txt")).as_deref(), Some("a0"));
+        assert_eq!(read(source.join("old.txt")).as_deref(), Some("old"));
+        assert!(!source.join("new.txt").exists() && scratch.exists());
+
+        let (source, _, _) = interrupted(&dir);
+        assert_eq!(recover(&dir, RecoveryPolicy::Rollback).unwrap()[0].outcome, RecoveryOutcome::RolledBack);
+        assert_eq!(read(source.join("a.txt")).as_deref(), Some("a0"));
+        assert!(!dir.join(JOURNAL_DIR).read_dir().unwrap().any(|_| true));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,10 +1,35 @@
 //! # nl_sandbox - NeuroLoom Sandbox
 //!
-//! 物理执行与安全网：God Mode 原生操作、Micro-VM 验证执行。
+//! 物理执行与安全网：God Mode 原生操作（含 Git、补丁应用、数据库查询、HTTP 请求、无头浏览器与输入注入）、按 Worker 画像的能力授权、命令资源限制、
+//! 任务级临时工作区（含提升的预写日志）、测试运行、Micro-VM 验证执行。
 
+pub mod audit;
+pub mod browser;
+pub mod capabilities;
+pub mod database;
 pub mod god_mode;
+pub mod git;
+pub mod http;
+pub mod input;
+pub mod journal;
+pub mod limits;
 pub mod micro_vm;
+pub mod patch;
+pub mod scratch;
+pub mod test_runner;
 pub mod executor;
 
+pub use audit::{AuditEntry, AuditLog, PolicyDecision};
+pub use browser::{BrowserAction, BrowserConfig, BrowserSession, SnapshotMode};
+pub use capabilities::{Capability, WorkerProfile};
+pub use database::{DatabaseAction, DatabasePolicy, QueryOutput};
 pub use executor::SandboxExecutor;
+pub use git::{GitAction, GitPolicy};
+pub use http::{FetchOutput, HttpCache, HttpFetch, HttpPolicy};
+pub use input::{InputAction, InputApprover, InputPolicy};
+pub use journal::{RecoveredPromotion, RecoveryOutcome, RecoveryPolicy};
+pub use limits::{LimitedResource, SandboxPolicy};
 pub use micro_vm::MicroVM;
+pub use patch::{ApplyReport, HunkRegenerator, PatchApplier, RegenerationRequest};
+pub use scratch::{ChangeKind, PromotionApproval, PromotionReport, ScratchChange, ScratchManager, ScratchWorkspace};
+pub use test_runner::{TestFramework, TestReport, TestRunner};
@@ -0,0 +1,287 @@
+//! Linux cgroup v2 限制
+//!
+//! cgroup v2 不允许组内既有进程又向子组开放控制器。未指定父组时，守护进程先把自己移入所在组下的
+//! `daemon` 叶子组，原来的组（systemd `Delegate=yes` 交给服务的子树）只作为沙箱子组的父组。
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::process::{Child, Command};
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+
+use super::{LimitedResource, SandboxPolicy};
+
+/// cgroup v2 挂载点
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// `cpu.max` 的周期（微秒）
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// 进程退出后删除子组的重试次数
+const REMOVE_ATTEMPTS: u32 = 20;
+
+/// 守护进程移入的叶子组
+const DAEMON_LEAF: &str = "daemon";
+
+/// 委派后的默认父组（每个进程只移动一次）
+static DELEGATED: OnceLock<std::result::Result<PathBuf, String>> = OnceLock::new();
+
+/// 每条命令一个 cgroup 子组
+pub(super) struct Limiter {
+    path: PathBuf,
+    released: bool,
+}
+
+impl Limiter {
+    /// 在父组下创建子组并写入上限
+    pub(super) fn create(policy: &SandboxPolicy) -> Result<Self> {
+        let parent = match &policy.cgroup_parent {
+            Some(parent) => parent.clone(),
+            None => delegate()?,
+        };
+        enable_controllers(&parent, policy)?;
+
+        let limiter = Self {
+            path: parent.join(format!("nl-sandbox-{}", Uuid::new_v4().simple())),
+            released: false,
+        };
+        std::fs::create_dir(&limiter.path).map_err(|e| cgroup_error(&limiter.path, e))?;
+        if let Some(bytes) = policy.max_memory_bytes {
+            limiter.write("memory.max", &bytes.to_string())?;
+            limiter.write("memory.oom.group", "1")?;
+        }
+        if let Some(processes) = policy.max_processes {
+            limiter.write("pids.max", &processes.to_string())?;
+        }
+        if let Some(percent) = policy.cpu_percent {
+            let quota = u64::from(percent.max(1)) * CPU_PERIOD_US / 100;
+            limiter.write("cpu.max", &format!("{} {}", quota, CPU_PERIOD_US))?;
+        }
+        Ok(limiter)
+    }
+
+    /// 子进程在 exec 前把自己写入 `cgroup.procs`，其后派生的进程都在组内
+    pub(super) fn prepare(&self, command: &mut Command) -> Result<()> {
+        let procs = File::options()
+            .write(true)
+            .open(self.path.join("cgroup.procs"))
+            .map_err(|e| cgroup_error(&self.path, e))?;
+        // SAFETY: 闭包在 fork 后的子进程中只调用 write(2)，不分配内存也不获取锁
+        unsafe {
+            command.pre_exec(move || (&procs).write_all(b"0"));
+        }
+        Ok(())
+    }
+
+    /// 进程已在 exec 前加入
+    pub(super) fn attach(&self, _child: &Child) -> Result<()> {
+        Ok(())
+    }
+
+    /// 检查是否超限
+    pub(super) fn breach(&self, policy: &SandboxPolicy) -> Option<NeuroLoomError> {
+        breach_in(&self.path, policy)
+    }
+
+    /// 结束组内全部进程
+    pub(super) fn kill(&self) {
+        if std::fs::write(self.path.join("cgroup.kill"), "1").is_ok() {
+            return;
+        }
+        // 5.14 之前的内核没有 cgroup.kill
+        for pid in std::fs::read_to_string(self.path.join("cgroup.procs")).unwrap_or_default().lines() {
+            let _ = std::process::Command::new("kill").args(["-KILL", pid]).status();
+        }
+    }
+
+    /// 结束残留进程并删除子组
+    pub(super) async fn release(mut self) {
+        self.released = true;
+        self.kill();
+        for _ in 0..REMOVE_ATTEMPTS {
+            match std::fs::remove_dir(&self.path) {
+                Ok(()) => return,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+                Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+            }
+        }
+        tracing::warn!("Failed to remove sandbox cgroup {}", self.path.display());
+    }
+
+    fn write(&self, file: &str, value: &str) -> Result<()> {
+        std::fs::write(self.path.join(file), value).map_err(|e| cgroup_error(&self.path.join(file), e))
+    }
+}
+
+impl Drop for Limiter {
+    /// 调用方取消时不会等到 `release`：结束残留进程，尽力删除子组
+    fn drop(&mut self) {
+        if !self.released {
+            self.kill();
+            let _ = std::fs::remove_dir(&self.path);
+        }
+    }
+}
+
+/// 默认父组：把守护进程移入所在组的 `daemon` 叶子组，返回原来的组
+pub(super) fn delegate() -> Result<PathBuf> {
+    DELEGATED
+        .get_or_init(|| move_into_leaf().map_err(|e| e.to_string()))
+        .clone()
+        .map_err(NeuroLoomError::Sandbox)
+}
+
+fn move_into_leaf() -> Result<PathBuf> {
+    let current = current_cgroup()?;
+    // 已在叶子组中（例如由外部启动脚本移入）
+    if current.file_name().is_some_and(|name| name == DAEMON_LEAF) {
+        if let Some(parent) = current.parent() {
+            return Ok(parent.to_path_buf());
+        }
+    }
+    let leaf = current.join(DAEMON_LEAF);
+    match std::fs::create_dir(&leaf) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(cgroup_error(&leaf, e)),
+    }
+    // 写入 0 移动当前进程（含全部线程）
+    std::fs::write(leaf.join("cgroup.procs"), "0").map_err(|e| cgroup_error(&leaf, e))?;
+    tracing::info!("Moved daemon into cgroup {}", leaf.display());
+    Ok(current)
+}
+
+/// 按策略向子组开放所需的控制器
+fn enable_controllers(parent: &Path, policy: &SandboxPolicy) -> Result<()> {
+    let file = parent.join("cgroup.subtree_control");
+    let enabled = std::fs::read_to_string(&file).map_err(|e| cgroup_error(&file, e))?;
+    let needed = [
+        ("memory", policy.max_memory_bytes.is_some()),
+        ("pids", policy.max_processes.is_some()),
+        ("cpu", policy.cpu_percent.is_some()),
+    ];
+    for (controller, _) in needed.iter().filter(|(c, used)| *used && !enabled.split_whitespace().any(|e| e == *c)) {
+        std::fs::write(&file, format!("+{}", controller)).map_err(|e| {
+            // 组内仍有其他进程（EBUSY）或组未委派给守护进程
+            NeuroLoomError::Sandbox(format!(
+                "cannot enable {} controller in {}: {} (the parent cgroup must be delegated and hold no processes; \
+                 set {})",
+                controller,
+                parent.display(),
+                e,
+                super::CGROUP_ENV
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+/// 守护进程所在的 cgroup v2 组
+fn current_cgroup() -> Result<PathBuf> {
+    // 混合层级下 /sys/fs/cgroup 挂载的是 v1 控制器
+    if !Path::new(CGROUP_ROOT).join("cgroup.controllers").exists() {
+        return Err(NeuroLoomError::Sandbox("cgroup v2 is not available".to_string()));
+    }
+    let membership = std::fs::read_to_string("/proc/self/cgroup")?;
+    let relative = membership
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .ok_or_else(|| NeuroLoomError::Sandbox("cgroup v2 is not available".to_string()))?;
+    Ok(Path::new(CGROUP_ROOT).join(relative.trim_start_matches('/')))
+}
+
+fn cgroup_error(path: &Path, error: std::io::Error) -> NeuroLoomError {
+    NeuroLoomError::Sandbox(format!("cgroup {}: {}", path.display(), error))
+}
+
+/// 按组内计数判断超限：OOM 终止、进程数触顶、累计 CPU 时间超出
+fn breach_in(path: &Path, policy: &SandboxPolicy) -> Option<NeuroLoomError> {
+    if let Some(limit) = policy.max_memory_bytes {
+        if stat(path, "memory.events", "oom_kill").unwrap_or(0) > 0 {
+            let peak = read_u64(&path.join("memory.peak")).unwrap_or(limit);
+            return Some(LimitedResource::Memory.exceeded(limit, peak));
+        }
+    }
+    if let Some(limit) = policy.max_processes {
+        if stat(path, "pids.events", "max").unwrap_or(0) > 0 {
+            let current = read_u64(&path.join("pids.current")).unwrap_or(u64::from(limit));
+            return Some(LimitedResource::Processes.exceeded(u64::from(limit), current.max(u64::from(limit))));
+        }
+    }
+    if let Some(limit) = policy.max_cpu_time {
+        let used_ms = stat(path, "cpu.stat", "usage_usec").unwrap_or(0) / 1000;
+        let limit_ms = limit.as_millis() as u64;
+        if used_ms > limit_ms {
+            return Some(LimitedResource::CpuTime.exceeded(limit_ms, used_ms));
+        }
+    }
+    None
+}
+
+/// 读取 `key value` 格式文件中的计数
+fn stat(path: &Path, file: &str, key: &str) -> Option<u64> {
+    std::fs::read_to_string(path.join(file))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(' ')?.trim().parse().ok())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breach_reads_cgroup_counters() {
+        let dir = std::env::temp_dir().join(format!("nl_cgroup_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("memory.events"), "low 0\nhigh 0\nmax 3\noom 1\noom_kill 0\n").unwrap();
+        std::fs::write(dir.join("pids.events"), "max 0\n").unwrap();
+        std::fs::write(dir.join("cpu.stat"), "usage_usec 1500000\nuser_usec 1200000\n").unwrap();
+        let policy = SandboxPolicy::unlimited()
+            .with_memory_bytes(64 << 20)
+            .with_max_processes(8)
+            .with_cpu_time(Duration::from_secs(2));
+        assert!(breach_in(&dir, &policy).is_none());
+
+        std::fs::write(dir.join("cpu.stat"), "usage_usec 2500000\n").unwrap();
+        assert!(matches!(
+            breach_in(&dir, &policy),
+            Some(NeuroLoomError::ResourceExceeded { ref resource, limit: 2000, used: 2500 }) if resource == "cpu_time"
+        ));
+
+        std::fs::write(dir.join("memory.events"), "max 9\noom 1\noom_kill 1\n").unwrap();
+        std::fs::write(dir.join("memory.peak"), "70000000\n").unwrap();
+        assert!(matches!(
+            breach_in(&dir, &policy),
+            Some(NeuroLoomError::ResourceExceeded { ref resource, used: 70000000, .. }) if resource == "memory"
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_process_limit_is_enforced() {
+        let policy = SandboxPolicy::unlimited().with_max_processes(3);
+        let mut command = Command::new("sh");
+        command.args(["-c", "for i in 1 2 3 4 5 6; do sleep 2 & done; wait"]);
+        match super::super::output(command, &policy).await {
+            // 没有可写的 cgroup v2（容器、混合层级、未委派）时跳过
+            Err(NeuroLoomError::Sandbox(reason)) => eprintln!("skipping cgroup enforcement test: {}", reason),
+            result => assert!(
+                matches!(result, Err(NeuroLoomError::ResourceExceeded { ref resource, limit: 3, .. })
+                    if resource == "processes"),
+                "{:?}",
+                result
+            ),
+        }
+    }
+}
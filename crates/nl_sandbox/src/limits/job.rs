@@ -0,0 +1,195 @@
+//! Windows Job Object 限制
+
+use std::ptr;
+
+use tokio::process::{Child, Command};
+use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+use windows_sys::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, QueryInformationJobObject, SetInformationJobObject,
+    TerminateJobObject, JobObjectAssociateCompletionPortInformation, JobObjectBasicAccountingInformation,
+    JobObjectCpuRateControlInformation, JobObjectExtendedLimitInformation, JOBOBJECTINFOCLASS,
+    JOBOBJECT_ASSOCIATE_COMPLETION_PORT, JOBOBJECT_BASIC_ACCOUNTING_INFORMATION,
+    JOBOBJECT_CPU_RATE_CONTROL_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    JOB_OBJECT_CPU_RATE_CONTROL_ENABLE, JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP, JOB_OBJECT_LIMIT_ACTIVE_PROCESS,
+    JOB_OBJECT_LIMIT_JOB_MEMORY, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+};
+use windows_sys::Win32::System::SystemServices::{
+    JOB_OBJECT_MSG_ACTIVE_PROCESS_LIMIT, JOB_OBJECT_MSG_JOB_MEMORY_LIMIT,
+};
+use windows_sys::Win32::System::IO::{CreateIoCompletionPort, GetQueuedCompletionStatus, OVERLAPPED};
+
+use nl_core::{NeuroLoomError, Result};
+
+use super::{LimitedResource, SandboxPolicy};
+
+/// 超限终止时的退出码
+const KILLED_EXIT_CODE: u32 = 1;
+
+/// 内核句柄，离开作用域时关闭
+struct Handle(HANDLE);
+
+// SAFETY: 作业与完成端口句柄可在任意线程使用
+unsafe impl Send for Handle {}
+unsafe impl Sync for Handle {}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        // SAFETY: 句柄由本模块创建且只关闭一次
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+/// 每条命令一个 Job Object
+///
+/// 进程在启动后才加入作业，此前已派生的子进程不受约束。
+/// 作业设置了 `KILL_ON_JOB_CLOSE`，关闭句柄（包括调用方取消）时结束作业内全部进程。
+pub(super) struct Limiter {
+    job: Handle,
+    port: Handle,
+}
+
+impl Limiter {
+    /// 创建作业、写入上限并关联完成端口
+    pub(super) fn create(policy: &SandboxPolicy) -> Result<Self> {
+        // SAFETY: 参数均为空指针或有效的结构体，返回的句柄由 `Handle` 接管
+        unsafe {
+            let job = CreateJobObjectW(ptr::null(), ptr::null());
+            if job.is_null() {
+                return Err(last_error("CreateJobObjectW"));
+            }
+            let job = Handle(job);
+
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if let Some(bytes) = policy.max_memory_bytes {
+                limits.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                limits.JobMemoryLimit = usize::try_from(bytes).unwrap_or(usize::MAX);
+            }
+            if let Some(processes) = policy.max_processes {
+                limits.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_ACTIVE_PROCESS;
+                limits.BasicLimitInformation.ActiveProcessLimit = processes;
+            }
+            set(&job, JobObjectExtendedLimitInformation, &limits)?;
+
+            if let Some(percent) = policy.cpu_percent {
+                // CpuRate 以全部处理器周期的万分之一计
+                let mut rate: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = std::mem::zeroed();
+                rate.ControlFlags = JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+                rate.Anonymous.CpuRate = (percent.saturating_mul(100) / logical_processors()).clamp(1, 10_000);
+                set(&job, JobObjectCpuRateControlInformation, &rate)?;
+            }
+
+            let port = CreateIoCompletionPort(INVALID_HANDLE_VALUE, ptr::null_mut(), 0, 1);
+            if port.is_null() {
+                return Err(last_error("CreateIoCompletionPort"));
+            }
+            let port = Handle(port);
+            let association = JOBOBJECT_ASSOCIATE_COMPLETION_PORT {
+                CompletionKey: ptr::null_mut(),
+                CompletionPort: port.0,
+            };
+            set(&job, JobObjectAssociateCompletionPortInformation, &association)?;
+            Ok(Self { job, port })
+        }
+    }
+
+    /// 无需在启动前设置
+    pub(super) fn prepare(&self, _command: &mut Command) -> Result<()> {
+        Ok(())
+    }
+
+    /// 把已启动的进程加入作业
+    pub(super) fn attach(&self, child: &Child) -> Result<()> {
+        let Some(process) = child.raw_handle() else {
+            return Ok(());
+        };
+        // SAFETY: 进程句柄在 `child` 存活期间有效
+        if unsafe { AssignProcessToJobObject(self.job.0, process as HANDLE) } == 0 {
+            return Err(last_error("AssignProcessToJobObject"));
+        }
+        Ok(())
+    }
+
+    /// 读取完成端口上的超限消息，累计 CPU 时间按作业计数判断
+    pub(super) fn breach(&self, policy: &SandboxPolicy) -> Option<NeuroLoomError> {
+        loop {
+            let (mut message, mut key, mut overlapped) = (0u32, 0usize, ptr::null_mut::<OVERLAPPED>());
+            // SAFETY: 输出参数均指向本地变量，超时为 0 不阻塞
+            let received =
+                unsafe { GetQueuedCompletionStatus(self.port.0, &mut message, &mut key, &mut overlapped, 0) };
+            if received == 0 {
+                break;
+            }
+            match message {
+                JOB_OBJECT_MSG_JOB_MEMORY_LIMIT => {
+                    let limit = policy.max_memory_bytes.unwrap_or_default();
+                    let peak = self.extended_limits().map(|info| info.PeakJobMemoryUsed as u64);
+                    return Some(LimitedResource::Memory.exceeded(limit, peak.unwrap_or(limit)));
+                }
+                JOB_OBJECT_MSG_ACTIVE_PROCESS_LIMIT => {
+                    let limit = u64::from(policy.max_processes.unwrap_or_default());
+                    return Some(LimitedResource::Processes.exceeded(limit, limit));
+                }
+                _ => {}
+            }
+        }
+        let limit = policy.max_cpu_time?;
+        let accounting = self.accounting()?;
+        // 计数以 100 纳秒为单位
+        let used_ms = (accounting.TotalUserTime + accounting.TotalKernelTime) as u64 / 10_000;
+        let limit_ms = limit.as_millis() as u64;
+        (used_ms > limit_ms).then(|| LimitedResource::CpuTime.exceeded(limit_ms, used_ms))
+    }
+
+    /// 结束作业内全部进程
+    pub(super) fn kill(&self) {
+        // SAFETY: 作业句柄有效
+        unsafe { TerminateJobObject(self.job.0, KILLED_EXIT_CODE) };
+    }
+
+    /// 关闭作业句柄（残留进程随之结束）
+    pub(super) async fn release(self) {}
+
+    fn extended_limits(&self) -> Option<JOBOBJECT_EXTENDED_LIMIT_INFORMATION> {
+        // SAFETY: 结构体为纯数据，全零合法
+        query(&self.job, JobObjectExtendedLimitInformation, unsafe { std::mem::zeroed() })
+    }
+
+    fn accounting(&self) -> Option<JOBOBJECT_BASIC_ACCOUNTING_INFORMATION> {
+        // SAFETY: 结构体为纯数据，全零合法
+        query(&self.job, JobObjectBasicAccountingInformation, unsafe { std::mem::zeroed() })
+    }
+}
+
+fn set<T>(job: &Handle, class: JOBOBJECTINFOCLASS, info: &T) -> Result<()> {
+    // SAFETY: `info` 为与 `class` 对应的结构体
+    let ok = unsafe {
+        SetInformationJobObject(job.0, class, info as *const T as *const _, std::mem::size_of::<T>() as u32)
+    };
+    if ok == 0 {
+        return Err(last_error("SetInformationJobObject"));
+    }
+    Ok(())
+}
+
+fn query<T>(job: &Handle, class: JOBOBJECTINFOCLASS, mut info: T) -> Option<T> {
+    // SAFETY: `info` 为与 `class` 对应的结构体
+    let ok = unsafe {
+        QueryInformationJobObject(
+            job.0,
+            class,
+            &mut info as *mut T as *mut _,
+            std::mem::size_of::<T>() as u32,
+            ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(info)
+}
+
+fn logical_processors() -> u32 {
+    std::thread::available_parallelism().map(|n| n.get() as u32).unwrap_or(1)
+}
+
+fn last_error(call: &str) -> NeuroLoomError {
+    NeuroLoomError::Sandbox(format!("{}: {}", call, std::io::Error::last_os_error()))
+}
@@ -0,0 +1,248 @@
+//! 命令资源限制
+//!
+//! `GodModeAction::Execute` 启动的进程按 `SandboxPolicy` 限制内存、CPU 与进程数：
+//! - Linux：每条命令一个 cgroup v2 子组（`memory.max`、`cpu.max`、`pids.max`），子进程在 exec 前加入，
+//!   之后派生的进程都留在组内；内存超限由 `memory.oom.group` 整组终止，累计 CPU 时间与进程数按计数轮询
+//! - Windows：进程加入 Job Object（作业内存、CPU 速率硬上限、活动进程数），超限消息经完成端口送达，
+//!   累计 CPU 时间按作业计数轮询；启动与加入作业之间派生的进程不受约束
+//! - 其他平台设置了限制时拒绝执行
+//!
+//! 超限后结束整棵进程树并返回 `NeuroLoomError::ResourceExceeded`（超限的资源、上限与观测值）。
+//! cgroup 父组须已委派给守护进程（可写 `cgroup.subtree_control`）且组内没有进程，可由
+//! `NEUROLOOM_SANDBOX_CGROUP` 指定；未指定时守护进程启动时把自己移入所在组的 `daemon` 叶子组，
+//! 以原来的组作为父组（见 [`SandboxPolicy::delegate_cgroup`]）。
+
+#[cfg(target_os = "linux")]
+mod cgroup;
+#[cfg(windows)]
+mod job;
+
+use std::path::PathBuf;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use nl_core::{NeuroLoomError, Result};
+
+/// 超限检查间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 内存上限（MiB）的环境变量
+pub const MEMORY_MB_ENV: &str = "NEUROLOOM_SANDBOX_MEMORY_MB";
+/// CPU 速率上限（百分比，100 为一个核心）的环境变量
+pub const CPU_PERCENT_ENV: &str = "NEUROLOOM_SANDBOX_CPU_PERCENT";
+/// 累计 CPU 时间上限（秒）的环境变量
+pub const CPU_SECONDS_ENV: &str = "NEUROLOOM_SANDBOX_CPU_SECONDS";
+/// 进程数上限的环境变量
+pub const MAX_PROCESSES_ENV: &str = "NEUROLOOM_SANDBOX_MAX_PROCESSES";
+/// cgroup 父组路径的环境变量
+pub const CGROUP_ENV: &str = "NEUROLOOM_SANDBOX_CGROUP";
+
+/// 受限资源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitedResource {
+    /// 内存（字节）
+    Memory,
+    /// 累计 CPU 时间（毫秒）
+    CpuTime,
+    /// 进程数
+    Processes,
+}
+
+impl LimitedResource {
+    /// 名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitedResource::Memory => "memory",
+            LimitedResource::CpuTime => "cpu_time",
+            LimitedResource::Processes => "processes",
+        }
+    }
+
+    /// 超限错误
+    pub fn exceeded(self, limit: u64, used: u64) -> NeuroLoomError {
+        NeuroLoomError::ResourceExceeded {
+            resource: self.as_str().to_string(),
+            limit,
+            used,
+        }
+    }
+}
+
+/// 命令资源策略（未设置的项不限制）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SandboxPolicy {
+    /// 整棵进程树的内存上限（字节）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_bytes: Option<u64>,
+    /// CPU 速率上限（百分比，100 为一个核心），超出时节流而不终止
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_percent: Option<u32>,
+    /// 累计 CPU 时间上限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cpu_time: Option<Duration>,
+    /// 同时存在的进程数上限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_processes: Option<u32>,
+    /// cgroup v2 父组（仅 Linux，默认为守护进程移入叶子组前所在的组）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup_parent: Option<PathBuf>,
+}
+
+impl SandboxPolicy {
+    /// 不限制
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// 从 `NEUROLOOM_SANDBOX_*` 环境变量读取（均未设置时不限制）
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            let value = std::env::var(name).ok()?;
+            match value.trim().parse() {
+                Ok(parsed) => Some(parsed),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid {}={}", name, value);
+                    None
+                }
+            }
+        }
+        Self {
+            max_memory_bytes: var::<u64>(MEMORY_MB_ENV).map(|mb| mb << 20),
+            cpu_percent: var(CPU_PERCENT_ENV),
+            max_cpu_time: var::<u64>(CPU_SECONDS_ENV).map(Duration::from_secs),
+            max_processes: var(MAX_PROCESSES_ENV),
+            cgroup_parent: std::env::var_os(CGROUP_ENV).map(PathBuf::from),
+        }
+    }
+
+    /// 设置内存上限
+    pub fn with_memory_bytes(mut self, bytes: u64) -> Self {
+        self.max_memory_bytes = Some(bytes);
+        self
+    }
+
+    /// 设置 CPU 速率上限
+    pub fn with_cpu_percent(mut self, percent: u32) -> Self {
+        self.cpu_percent = Some(percent);
+        self
+    }
+
+    /// 设置累计 CPU 时间上限
+    pub fn with_cpu_time(mut self, limit: Duration) -> Self {
+        self.max_cpu_time = Some(limit);
+        self
+    }
+
+    /// 设置进程数上限
+    pub fn with_max_processes(mut self, processes: u32) -> Self {
+        self.max_processes = Some(processes);
+        self
+    }
+
+    /// 设置 cgroup 父组
+    pub fn with_cgroup_parent(mut self, parent: impl Into<PathBuf>) -> Self {
+        self.cgroup_parent = Some(parent.into());
+        self
+    }
+
+    /// 未指定 cgroup 父组时把当前进程移入所在组的 `daemon` 叶子组，以原来的组作为父组
+    ///
+    /// 守护进程应在启动时调用；未设置任何限制或不在 Linux 上时不做任何事。
+    pub fn delegate_cgroup(&mut self) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        if self.cgroup_parent.is_none() && !self.is_unlimited() {
+            self.cgroup_parent = Some(cgroup::delegate()?);
+        }
+        Ok(())
+    }
+
+    /// 是否未设置任何限制
+    pub fn is_unlimited(&self) -> bool {
+        self.max_memory_bytes.is_none()
+            && self.cpu_percent.is_none()
+            && self.max_cpu_time.is_none()
+            && self.max_processes.is_none()
+    }
+}
+
+/// 按策略执行命令并收集输出，超限时结束整棵进程树
+pub async fn output(mut command: Command, policy: &SandboxPolicy) -> Result<Output> {
+    if policy.is_unlimited() {
+        return Ok(command.output().await?);
+    }
+    command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let limiter = platform::Limiter::create(policy)?;
+    let result = supervise(command, &limiter, policy).await;
+    limiter.release().await;
+    result
+}
+
+async fn supervise(mut command: Command, limiter: &platform::Limiter, policy: &SandboxPolicy) -> Result<Output> {
+    limiter.prepare(&mut command)?;
+    let child = command.spawn()?;
+    limiter.attach(&child)?;
+
+    let output = child.wait_with_output();
+    tokio::pin!(output);
+    let mut ticker = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            result = &mut output => {
+                // 进程可能正是因超限被终止（如内存超限的整组 OOM）
+                return match limiter.breach(policy) {
+                    Some(exceeded) => Err(exceeded),
+                    None => Ok(result?),
+                };
+            }
+            _ = ticker.tick() => {
+                if let Some(exceeded) = limiter.breach(policy) {
+                    tracing::warn!("Sandbox command killed: {}", exceeded);
+                    limiter.kill();
+                    let _ = output.await;
+                    return Err(exceeded);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+use cgroup as platform;
+#[cfg(windows)]
+use job as platform;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::*;
+
+    /// 不支持资源限制的平台
+    pub(super) struct Limiter;
+
+    impl Limiter {
+        pub(super) fn create(_policy: &SandboxPolicy) -> Result<Self> {
+            Err(NeuroLoomError::Sandbox(
+                "resource limits are not supported on this platform".to_string(),
+            ))
+        }
+
+        pub(super) fn prepare(&self, _command: &mut Command) -> Result<()> {
+            Ok(())
+        }
+
+        pub(super) fn attach(&self, _child: &tokio::process::Child) -> Result<()> {
+            Ok(())
+        }
+
+        pub(super) fn breach(&self, _policy: &SandboxPolicy) -> Option<NeuroLoomError> {
+            None
+        }
+
+        pub(super) fn kill(&self) {}
+
+        pub(super) async fn release(self) {}
+    }
+}
@@ -0,0 +1,682 @@
+//! 统一 diff 解析与应用
+//!
+//! Worker 的产出通常是统一格式 diff（`--- a/x` / `+++ b/x` / `@@ -l,n +l,n @@`）：
+//! - 每个 hunk 先在预期位置精确匹配，再向两侧搜索偏移，最后按 fuzz 级别忽略首尾的上下文行
+//! - 找不到原文时判定为冲突；若改动后的内容已存在则视为已应用，不重复写入
+//! - 应用成功的 hunk 照常写入，失败的 hunk 连同当前文件片段汇总到报告中，
+//!   `apply_with_regeneration` 只把失败的 hunk 交回 Worker 重新生成
+
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use nl_core::{NeuroLoomError, Result};
+
+/// 默认 fuzz 级别（最多忽略首尾各 2 行上下文）
+pub const DEFAULT_FUZZ: usize = 2;
+
+/// 失败 hunk 附带的当前文件片段行数（预期位置前后各取）
+const EXCERPT_LINES: usize = 5;
+
+/// hunk 中的一行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// 一个 hunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hunk {
+    /// 原文起始行（从 1 开始，新建文件为 0）
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    /// 去掉首尾各 `fuzz` 行上下文后的原文与新文
+    fn sides(&self, fuzz: usize) -> (Vec<&str>, Vec<&str>, usize) {
+        let leading = self
+            .lines
+            .iter()
+            .take_while(|l| matches!(l, HunkLine::Context(_)))
+            .count();
+        let trailing = self
+            .lines
+            .iter()
+            .rev()
+            .take_while(|l| matches!(l, HunkLine::Context(_)))
+            .count();
+        let skip_front = leading.min(fuzz);
+        let skip_back = trailing.min(fuzz).min(self.lines.len() - skip_front);
+        let body = &self.lines[skip_front..self.lines.len() - skip_back];
+
+        let mut old = Vec::new();
+        let mut new = Vec::new();
+        for line in body {
+            match line {
+                HunkLine::Context(text) => {
+                    old.push(text.as_str());
+                    new.push(text.as_str());
+                }
+                HunkLine::Remove(text) => old.push(text.as_str()),
+                HunkLine::Add(text) => new.push(text.as_str()),
+            }
+        }
+        (old, new, skip_front)
+    }
+
+    /// 还原为 diff 文本
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "@@ -{},{} +{},{} @@\n",
+            self.old_start, self.old_lines, self.new_start, self.new_lines
+        );
+        for line in &self.lines {
+            let (prefix, content) = match line {
+                HunkLine::Context(t) => (' ', t),
+                HunkLine::Remove(t) => ('-', t),
+                HunkLine::Add(t) => ('+', t),
+            };
+            text.push(prefix);
+            text.push_str(content);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// 单个文件的补丁
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilePatch {
+    /// 原路径（新建文件为空）
+    pub old_path: Option<PathBuf>,
+    /// 新路径（删除文件为空）
+    pub new_path: Option<PathBuf>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// 目标路径
+    pub fn path(&self) -> &Path {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or(Path::new(""))
+    }
+}
+
+/// 解析统一 diff（可包含多个文件）
+pub fn parse(text: &str) -> Result<Vec<FilePatch>> {
+    let mut patches = Vec::new();
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some(old) = line.strip_prefix("--- ") else {
+            continue;
+        };
+        let Some(new) = lines.next().and_then(|l| l.strip_prefix("+++ ")) else {
+            return Err(invalid(format!("missing +++ line after {}", line)));
+        };
+        let mut patch = FilePatch {
+            old_path: diff_path(old),
+            new_path: diff_path(new),
+            hunks: Vec::new(),
+        };
+
+        while let Some(header) = lines.peek().and_then(|l| l.strip_prefix("@@ ")) {
+            let (old_start, old_lines, new_start, new_lines) = parse_header(header)?;
+            lines.next();
+            let mut hunk = Hunk {
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                lines: Vec::new(),
+            };
+            let (mut old_seen, mut new_seen) = (0, 0);
+            while old_seen < old_lines || new_seen < new_lines {
+                let Some(line) = lines.next() else {
+                    return Err(invalid(format!(
+                        "truncated hunk in {}",
+                        patch.path().display()
+                    )));
+                };
+                let parsed = match line.chars().next() {
+                    Some('+') => HunkLine::Add(line[1..].to_string()),
+                    Some('-') => HunkLine::Remove(line[1..].to_string()),
+                    Some(' ') => HunkLine::Context(line[1..].to_string()),
+                    // 部分工具会去掉空上下文行的前导空格
+                    None => HunkLine::Context(String::new()),
+                    Some('\\') => continue,
+                    Some(_) => return Err(invalid(format!("unexpected hunk line: {}", line))),
+                };
+                match parsed {
+                    HunkLine::Add(_) => new_seen += 1,
+                    HunkLine::Remove(_) => old_seen += 1,
+                    HunkLine::Context(_) => {
+                        old_seen += 1;
+                        new_seen += 1;
+                    }
+                }
+                hunk.lines.push(parsed);
+            }
+            // 跳过 `\ No newline at end of file`
+            while lines.peek().is_some_and(|l| l.starts_with('\\')) {
+                lines.next();
+            }
+            patch.hunks.push(hunk);
+        }
+        patches.push(patch);
+    }
+    if patches.is_empty() {
+        return Err(invalid("no file patches found".to_string()));
+    }
+    Ok(patches)
+}
+
+fn invalid(reason: String) -> NeuroLoomError {
+    NeuroLoomError::Sandbox(format!("invalid diff: {}", reason))
+}
+
+/// 解析 `---` / `+++` 行中的路径（去掉 `a/`、`b/` 前缀与时间戳）
+fn diff_path(raw: &str) -> Option<PathBuf> {
+    let path = raw.split('\t').next().unwrap_or_default().trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(PathBuf::from(path))
+}
+
+/// 解析 `-l,n +l,n @@`
+fn parse_header(header: &str) -> Result<(usize, usize, usize, usize)> {
+    let mut ranges = header.split_whitespace();
+    let range = |part: Option<&str>, sign: char| -> Result<(usize, usize)> {
+        let part = part
+            .and_then(|p| p.strip_prefix(sign))
+            .ok_or_else(|| invalid(format!("bad hunk header: @@ {}", header)))?;
+        let (start, len) = part.split_once(',').unwrap_or((part, "1"));
+        match (start.parse(), len.parse()) {
+            (Ok(start), Ok(len)) => Ok((start, len)),
+            _ => Err(invalid(format!("bad hunk header: @@ {}", header))),
+        }
+    };
+    let (old_start, old_lines) = range(ranges.next(), '-')?;
+    let (new_start, new_lines) = range(ranges.next(), '+')?;
+    Ok((old_start, old_lines, new_start, new_lines))
+}
+
+/// 单个 hunk 的应用结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum HunkOutcome {
+    /// 已应用（`offset` 为相对预期位置的行偏移）
+    Applied {
+        index: usize,
+        offset: isize,
+        fuzz: usize,
+    },
+    /// 改动已存在，未重复应用
+    AlreadyApplied { index: usize },
+    /// 冲突
+    Failed { index: usize, reason: String },
+}
+
+/// 文件应用状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Applied,
+    Created,
+    Deleted,
+    /// 部分 hunk 失败（成功的部分已写入）
+    Partial,
+    Failed,
+}
+
+/// 单个文件的应用报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub status: FileStatus,
+    pub hunks: Vec<HunkOutcome>,
+    /// 文件级错误（路径越界、读写失败等）
+    pub error: Option<String>,
+}
+
+/// 应用失败的 hunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedHunk {
+    pub path: PathBuf,
+    pub hunk: Hunk,
+    pub reason: String,
+    /// 预期位置附近的当前文件内容
+    pub excerpt: String,
+}
+
+/// 补丁应用报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApplyReport {
+    pub files: Vec<FileReport>,
+    pub failed: Vec<FailedHunk>,
+}
+
+impl ApplyReport {
+    /// 是否全部应用成功
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty() && self.files.iter().all(|f| f.error.is_none())
+    }
+
+    /// 每个文件一行的摘要
+    pub fn summary(&self) -> String {
+        self.files
+            .iter()
+            .map(|f| {
+                let failed = f
+                    .hunks
+                    .iter()
+                    .filter(|h| matches!(h, HunkOutcome::Failed { .. }))
+                    .count();
+                let mut line = format!(
+                    "{:?} {} ({}/{} hunks)",
+                    f.status,
+                    f.path.display(),
+                    f.hunks.len() - failed,
+                    f.hunks.len()
+                );
+                if let Some(error) = &f.error {
+                    line.push_str(&format!(": {}", error));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 把字符串按行应用 hunk，返回新内容与每个 hunk 的结果
+pub fn apply_hunks(content: &str, hunks: &[Hunk], fuzz: usize) -> (String, Vec<HunkOutcome>) {
+    let trailing_newline = content.is_empty() || content.ends_with('\n');
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let mut outcomes = Vec::new();
+    // 已应用 hunk 引起的行数变化，以及不可再匹配的区域末尾
+    let mut delta: isize = 0;
+    let mut floor = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let expected = (hunk.old_start.saturating_sub(1) as isize + delta).max(0) as usize;
+        let mut outcome = None;
+        for level in 0..=fuzz {
+            let (old, new, skipped) = hunk.sides(level);
+            let anchor = expected + skipped;
+            if let Some(at) = locate(&lines, &old, anchor, floor) {
+                lines.splice(at..at + old.len(), new.iter().map(|s| s.to_string()));
+                delta += new.len() as isize - old.len() as isize;
+                floor = at + new.len();
+                outcome = Some(HunkOutcome::Applied {
+                    index,
+                    offset: at as isize - anchor as isize,
+                    fuzz: level,
+                });
+                break;
+            }
+            if level == 0 && !new.is_empty() && locate(&lines, &new, anchor, floor).is_some() {
+                outcome = Some(HunkOutcome::AlreadyApplied { index });
+                break;
+            }
+        }
+        outcomes.push(outcome.unwrap_or_else(|| HunkOutcome::Failed {
+            index,
+            reason: format!("context not found near line {}", hunk.old_start),
+        }));
+    }
+
+    let mut result = lines.join("\n");
+    if trailing_newline && !lines.is_empty() {
+        result.push('\n');
+    }
+    (result, outcomes)
+}
+
+/// 从 `anchor` 向两侧搜索与 `needle` 完全一致的位置（不早于 `floor`）
+fn locate(lines: &[String], needle: &[&str], anchor: usize, floor: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(anchor.clamp(floor, lines.len().max(floor)));
+    }
+    let last = lines.len().checked_sub(needle.len())?;
+    let matches = |at: usize| {
+        lines[at..at + needle.len()]
+            .iter()
+            .zip(needle)
+            .all(|(a, b)| a == b)
+    };
+    let anchor = anchor.min(last);
+    for distance in 0..=last {
+        let candidates = [anchor.checked_sub(distance), anchor.checked_add(distance)];
+        for at in candidates.into_iter().flatten() {
+            if at >= floor && at <= last && matches(at) {
+                return Some(at);
+            }
+        }
+        if distance > 0 && anchor < distance && anchor + distance > last {
+            break;
+        }
+    }
+    None
+}
+
+/// 失败 hunk 的重新生成请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegenerationRequest {
+    pub failed: Vec<FailedHunk>,
+}
+
+impl RegenerationRequest {
+    /// 给 Worker 的提示
+    pub fn prompt(&self) -> String {
+        let mut prompt = String::from(
+            "The following hunks of your patch did not apply because the files have changed. \
+             Regenerate ONLY these hunks as a unified diff against the current file contents shown.\n",
+        );
+        for failed in &self.failed {
+            prompt.push_str(&format!(
+                "\n--- {path} ({reason})\nFailed hunk:\n{hunk}Current content near the target:\n{excerpt}\n",
+                path = failed.path.display(),
+                reason = failed.reason,
+                hunk = failed.hunk.to_text(),
+                excerpt = failed.excerpt,
+            ));
+        }
+        prompt
+    }
+}
+
+/// 重新生成失败 hunk 的一方（通常是产出补丁的 Worker）
+#[async_trait]
+pub trait HunkRegenerator: Send + Sync {
+    /// 返回只包含重新生成部分的统一 diff
+    async fn regenerate(&self, request: &RegenerationRequest) -> Result<String>;
+}
+
+/// 补丁应用器
+pub struct PatchApplier {
+    /// 补丁路径的根目录（路径不得越出）
+    root: PathBuf,
+    fuzz: usize,
+}
+
+impl PatchApplier {
+    /// 创建应用器
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            fuzz: DEFAULT_FUZZ,
+        }
+    }
+
+    /// 设置 fuzz 级别
+    pub fn with_fuzz(mut self, fuzz: usize) -> Self {
+        self.fuzz = fuzz;
+        self
+    }
+
+    /// 解析并应用 diff 文本
+    pub async fn apply_text(&self, diff: &str) -> Result<ApplyReport> {
+        Ok(self.apply(&parse(diff)?).await)
+    }
+
+    /// 应用补丁
+    pub async fn apply(&self, patches: &[FilePatch]) -> ApplyReport {
+        let mut report = ApplyReport::default();
+        for patch in patches {
+            let path = patch.path().to_path_buf();
+            let file = match self.apply_file(patch, &mut report.failed).await {
+                Ok((status, hunks)) => FileReport {
+                    path,
+                    status,
+                    hunks,
+                    error: None,
+                },
+                Err(e) => FileReport {
+                    path,
+                    status: FileStatus::Failed,
+                    hunks: Vec::new(),
+                    error: Some(e.to_string()),
+                },
+            };
+            report.files.push(file);
+        }
+        report
+    }
+
+    /// 应用补丁，失败的 hunk 交给 `regenerator` 重新生成，最多 `max_rounds` 轮；返回每一轮的报告
+    pub async fn apply_with_regeneration(
+        &self,
+        diff: &str,
+        regenerator: &dyn HunkRegenerator,
+        max_rounds: usize,
+    ) -> Result<Vec<ApplyReport>> {
+        let mut reports = vec![self.apply_text(diff).await?];
+        for _ in 0..max_rounds {
+            let last = reports.last().expect("at least one report");
+            if last.failed.is_empty() {
+                break;
+            }
+            let request = RegenerationRequest {
+                failed: last.failed.clone(),
+            };
+            let regenerated = regenerator.regenerate(&request).await?;
+            reports.push(self.apply_text(&regenerated).await?);
+        }
+        Ok(reports)
+    }
+
+    async fn apply_file(
+        &self,
+        patch: &FilePatch,
+        failed: &mut Vec<FailedHunk>,
+    ) -> Result<(FileStatus, Vec<HunkOutcome>)> {
+        let target = self.resolve(patch.path())?;
+        let existing = match tokio::fs::read_to_string(&target).await {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        if patch.old_path.is_none() && existing.as_deref().is_some_and(|c| !c.is_empty()) {
+            return Err(NeuroLoomError::Sandbox(format!(
+                "{} already exists",
+                patch.path().display()
+            )));
+        }
+        if patch.old_path.is_some() && existing.is_none() {
+            return Err(NeuroLoomError::Sandbox(format!(
+                "{} does not exist",
+                patch.path().display()
+            )));
+        }
+
+        let original = existing.unwrap_or_default();
+        let (content, outcomes) = apply_hunks(&original, &patch.hunks, self.fuzz);
+        for outcome in &outcomes {
+            if let HunkOutcome::Failed { index, reason } = outcome {
+                let hunk = patch.hunks[*index].clone();
+                failed.push(FailedHunk {
+                    path: patch.path().to_path_buf(),
+                    excerpt: excerpt(&content, hunk.old_start),
+                    hunk,
+                    reason: reason.clone(),
+                });
+            }
+        }
+        let applied = outcomes
+            .iter()
+            .filter(|o| !matches!(o, HunkOutcome::Failed { .. }))
+            .count();
+        let status = if applied == 0 && !outcomes.is_empty() {
+            FileStatus::Failed
+        } else if applied < outcomes.len() {
+            FileStatus::Partial
+        } else if patch.new_path.is_none() {
+            FileStatus::Deleted
+        } else if patch.old_path.is_none() {
+            FileStatus::Created
+        } else {
+            FileStatus::Applied
+        };
+
+        match status {
+            FileStatus::Failed => {}
+            FileStatus::Deleted if content.trim().is_empty() => {
+                tokio::fs::remove_file(&target).await?
+            }
+            FileStatus::Deleted => {
+                return Err(NeuroLoomError::Sandbox(format!(
+                    "{} still has content after removing its lines",
+                    patch.path().display()
+                )))
+            }
+            _ if content != original => {
+                if let Some(parent) = target.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(&target, content).await?;
+            }
+            _ => {}
+        }
+        Ok((status, outcomes))
+    }
+
+    /// 解析补丁路径，拒绝绝对路径与越出根目录的路径
+    fn resolve(&self, path: &Path) -> Result<PathBuf> {
+        if path.as_os_str().is_empty()
+            || path
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(NeuroLoomError::Sandbox(format!(
+                "refusing to patch path outside the workspace: {}",
+                path.display()
+            )));
+        }
+        Ok(self.root.join(path))
+    }
+}
+
+/// 预期位置附近的文件片段
+fn excerpt(content: &str, line: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = line.saturating_sub(1 + EXCERPT_LINES).min(lines.len());
+    let end = (line + EXCERPT_LINES).min(lines.len());
+    lines[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, l)| format!("{:>5} | {}", start + i + 1, l))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Regenerate(String);
+
+    #[async_trait]
+    impl HunkRegenerator for Regenerate {
+        async fn regenerate(&self, request: &RegenerationRequest) -> Result<String> {
+            assert_eq!(request.failed.len(), 1);
+            assert!(request.prompt().contains("let total = 0;"));
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_apply_reports_conflicts_and_regenerates() {
+        let root = std::env::temp_dir().join(format!("nl_patch_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        // 文件头部多了两行，hunk 需要偏移；第二个 hunk 的原文已被改动
+        std::fs::write(
+            root.join("lib.rs"),
+            "// header\n// added\nfn a() {\n    1\n}\n\nfn b() {\n    let total = 0;\n    total\n}\n",
+        )
+        .unwrap();
+
+        let diff = "\
+--- a/lib.rs
++++ b/lib.rs
+@@ -1,3 +1,3 @@
+ fn a() {
+-    1
++    2
+ }
+@@ -5,3 +5,3 @@
+ fn b() {
+-    let sum = 0;
++    let sum = 1;
+     sum
+--- /dev/null
++++ b/notes.md
+@@ -0,0 +1,1 @@
++# Notes
+";
+        let fix = "\
+--- a/lib.rs
++++ b/lib.rs
+@@ -7,4 +7,4 @@
+ fn b() {
+-    let total = 0;
++    let total = 1;
+     total
+ }
+";
+        let applier = PatchApplier::new(&root);
+        let reports = applier
+            .apply_with_regeneration(diff, &Regenerate(fix.to_string()), 2)
+            .await
+            .unwrap();
+        assert_eq!(reports.len(), 2);
+
+        let first = &reports[0];
+        assert_eq!(first.files[0].status, FileStatus::Partial);
+        assert!(matches!(
+            first.files[0].hunks[0],
+            HunkOutcome::Applied {
+                offset: 2,
+                fuzz: 0,
+                ..
+            }
+        ));
+        assert_eq!(first.files[1].status, FileStatus::Created);
+        assert!(reports[1].is_clean());
+
+        let content = std::fs::read_to_string(root.join("lib.rs")).unwrap();
+        assert!(content.contains("    2\n") && content.contains("let total = 1;"));
+        assert_eq!(
+            std::fs::read_to_string(root.join("notes.md")).unwrap(),
+            "# Notes\n"
+        );
+
+        // 重复应用同一补丁不会再次修改
+        let again = applier.apply_text(fix).await.unwrap();
+        assert!(matches!(
+            again.files[0].hunks[0],
+            HunkOutcome::AlreadyApplied { .. }
+        ));
+        assert!(applier
+            .apply_text("--- a/../x\n+++ b/../x\n@@ -1 +1 @@\n-a\n+b\n")
+            .await
+            .unwrap()
+            .files[0]
+            .error
+            .is_some());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,488 @@
+//! 任务级临时工作区
+//!
+//! 沙箱操作默认直接作用于真实工作区；为任务打开临时工作区后，操作改在项目副本中执行：
+//! - 项目目录复制到 `<base>/<task_id>/`（跳过 `.git`、`target` 等目录与符号链接），
+//!   `std::fs::copy` 在支持的文件系统上走 reflink / clonefile，即写时复制；复制时记录每个文件的基线摘要
+//! - `confine` 把 God Mode 操作改写到副本中：相对路径与源目录内的绝对路径映射进副本，
+//!   命令以副本为工作目录执行；越出副本的路径、Git 操作与输入注入被拒绝。
+//!   这是路径级约束而非系统级隔离，命令仍可按绝对路径访问副本以外的文件
+//! - 通过的裁决是提升的前提：`promote` 按基线比较出变更，确认真实工作区中对应文件自复制后未被改动，
+//!   再以事务方式写回（先写临时文件再改名，中途失败时恢复已写入的文件）；有冲突时不写入任何文件。
+//!   写回过程记入预写日志（见 `journal`），进程中途退出后由 `ScratchManager::recover` 续做或回滚
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+
+use crate::god_mode::GodModeAction;
+use crate::journal::{self, digest, read_optional, JournalOp, PromotionJournal, RecoveredPromotion, RecoveryPolicy};
+
+/// 不复制、也不参与变更比较的目录
+const SKIPPED_DIRS: &[&str] = &[".git", ".neuroloom", "target", "node_modules"];
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// 副本中相对基线的一处变更
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScratchChange {
+    /// 相对项目根目录的路径
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+/// 提升所需的裁决（由法庭产生）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromotionApproval {
+    pub task_id: Uuid,
+    pub verdict_id: Uuid,
+    pub passed: bool,
+}
+
+/// 提升结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotionReport {
+    pub task_id: Uuid,
+    pub verdict_id: Uuid,
+    /// 是否已写回真实工作区
+    pub promoted: bool,
+    pub changes: Vec<ScratchChange>,
+    /// 复制后在真实工作区中被改动的文件（非空时不写入任何文件）
+    pub conflicts: Vec<PathBuf>,
+}
+
+/// 任务的临时工作区
+#[derive(Debug, Clone)]
+pub struct ScratchWorkspace {
+    task_id: Uuid,
+    source: PathBuf,
+    root: PathBuf,
+    /// 相对路径 → 复制时的内容摘要
+    baseline: Arc<HashMap<PathBuf, String>>,
+}
+
+impl ScratchWorkspace {
+    /// 把 `source` 复制到 `<base>/<task_id>/`
+    pub async fn create(source: impl Into<PathBuf>, base: &Path, task_id: Uuid) -> Result<Self> {
+        let source = source.into();
+        let root = base.join(task_id.to_string());
+        let (copy_source, copy_root, skip) = (source.clone(), root.clone(), base.to_path_buf());
+        let baseline = tokio::task::spawn_blocking(move || -> Result<HashMap<PathBuf, String>> {
+            if copy_root.exists() {
+                std::fs::remove_dir_all(&copy_root)?;
+            }
+            std::fs::create_dir_all(&copy_root)?;
+            let mut baseline = HashMap::new();
+            copy_tree(&copy_source, &copy_root, &skip, Path::new(""), &mut baseline)?;
+            Ok(baseline)
+        })
+        .await
+        .map_err(|e| NeuroLoomError::Sandbox(e.to_string()))??;
+        tracing::debug!("Created scratch workspace for task {} ({} files)", task_id, baseline.len());
+        Ok(Self {
+            task_id,
+            source,
+            root,
+            baseline: Arc::new(baseline),
+        })
+    }
+
+    /// 任务 ID
+    pub fn task_id(&self) -> Uuid {
+        self.task_id
+    }
+
+    /// 副本目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 真实工作区目录
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// 把操作改写到副本中
+    pub fn confine(&self, action: GodModeAction) -> Result<GodModeAction> {
+        Ok(match action {
+            GodModeAction::ReadFile { path } => GodModeAction::ReadFile { path: self.map_path(&path)? },
+            GodModeAction::WriteFile { path, content } => GodModeAction::WriteFile {
+                path: self.map_path(&path)?,
+                content,
+            },
+            GodModeAction::DeleteFile { path } => GodModeAction::DeleteFile { path: self.map_path(&path)? },
+            GodModeAction::CreateDir { path } => GodModeAction::CreateDir { path: self.map_path(&path)? },
+            GodModeAction::ListDir { path } => GodModeAction::ListDir { path: self.map_path(&path)? },
+            GodModeAction::Execute { command, args, cwd } => GodModeAction::Execute {
+                command,
+                args,
+                cwd: Some(match cwd {
+                    Some(cwd) => self.map_path(&cwd)?,
+                    None => self.root.clone(),
+                }),
+            },
+            GodModeAction::ApplyPatch { root, diff } => GodModeAction::ApplyPatch {
+                root: self.map_path(&root)?,
+                diff,
+            },
+            action @ (GodModeAction::SetEnv { .. }
+            | GodModeAction::GetEnv { .. }
+            | GodModeAction::QueryDatabase(_)
+            | GodModeAction::HttpFetch(_)
+            | GodModeAction::Browser(_)) => action,
+            action @ (GodModeAction::Git(_) | GodModeAction::Input(_)) => {
+                return Err(NeuroLoomError::Sandbox(format!(
+                    "{} is not available in scratch workspaces",
+                    action.name()
+                )))
+            }
+        })
+    }
+
+    /// 映射到副本中的路径，拒绝越出项目目录的路径
+    fn map_path(&self, path: &Path) -> Result<PathBuf> {
+        let relative = if path.is_absolute() {
+            path.strip_prefix(&self.root)
+                .or_else(|_| path.strip_prefix(&self.source))
+                .map_err(|_| outside(path))?
+        } else {
+            path
+        };
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(outside(path));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// 副本相对基线的变更（按路径排序）
+    pub async fn changes(&self) -> Result<Vec<ScratchChange>> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.diff_baseline())
+            .await
+            .map_err(|e| NeuroLoomError::Sandbox(e.to_string()))?
+    }
+
+    /// 凭通过的裁决把变更写回真实工作区
+    pub async fn promote(&self, approval: &PromotionApproval) -> Result<PromotionReport> {
+        if approval.task_id != self.task_id {
+            return Err(NeuroLoomError::Sandbox(format!(
+                "verdict {} belongs to task {}, not {}",
+                approval.verdict_id, approval.task_id, self.task_id
+            )));
+        }
+        if !approval.passed {
+            return Err(NeuroLoomError::Sandbox(format!(
+                "verdict {} did not pass; scratch changes of task {} were not promoted",
+                approval.verdict_id, self.task_id
+            )));
+        }
+        let (this, approval) = (self.clone(), *approval);
+        tokio::task::spawn_blocking(move || this.promote_blocking(approval))
+            .await
+            .map_err(|e| NeuroLoomError::Sandbox(e.to_string()))?
+    }
+
+    /// 删除副本
+    pub async fn remove(&self) -> Result<()> {
+        match tokio::fs::remove_dir_all(&self.root).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn diff_baseline(&self) -> Result<Vec<ScratchChange>> {
+        let mut current = HashMap::new();
+        digest_tree(&self.root, Path::new(""), &mut current)?;
+        let mut changes: Vec<ScratchChange> = current
+            .iter()
+            .filter_map(|(path, digest)| {
+                let kind = match self.baseline.get(path) {
+                    None => ChangeKind::Added,
+                    Some(base) if base != digest => ChangeKind::Modified,
+                    Some(_) => return None,
+                };
+                Some(ScratchChange { path: path.clone(), kind })
+            })
+            .chain(
+                self.baseline
+                    .keys()
+                    .filter(|path| !current.contains_key(*path))
+                    .map(|path| ScratchChange {
+                        path: path.clone(),
+                        kind: ChangeKind::Deleted,
+                    }),
+            )
+            .collect();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(changes)
+    }
+
+    fn promote_blocking(&self, approval: PromotionApproval) -> Result<PromotionReport> {
+        let changes = self.diff_baseline()?;
+        let mut conflicts = Vec::new();
+        for change in &changes {
+            let current = read_optional(&self.source.join(&change.path))?.map(|bytes| digest(&bytes));
+            if current.as_ref() != self.baseline.get(&change.path) {
+                conflicts.push(change.path.clone());
+            }
+        }
+        let mut report = PromotionReport {
+            task_id: self.task_id,
+            verdict_id: approval.verdict_id,
+            promoted: false,
+            changes,
+            conflicts,
+        };
+        if !report.conflicts.is_empty() {
+            tracing::warn!(
+                "Scratch changes of task {} conflict with the workspace: {:?}",
+                self.task_id,
+                report.conflicts
+            );
+            return Ok(report);
+        }
+
+        // 先记录全部操作，逐个备份原内容后写回，任一失败即按相反顺序恢复
+        let ops = report
+            .changes
+            .iter()
+            .map(|change| {
+                let target = match change.kind {
+                    ChangeKind::Deleted => None,
+                    _ => Some(digest(&std::fs::read(self.root.join(&change.path))?)),
+                };
+                Ok(JournalOp {
+                    path: change.path.clone(),
+                    kind: change.kind,
+                    original: self.baseline.get(&change.path).cloned(),
+                    target,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let base = self.root.parent().unwrap_or(&self.root);
+        let mut journal =
+            PromotionJournal::begin(base, self.task_id, approval.verdict_id, &self.source, &self.root, ops)?;
+        for index in 0..journal.ops().len() {
+            if let Err(e) = journal.apply(index) {
+                if let Err(restore) = journal.rollback() {
+                    tracing::error!(
+                        "Failed to restore the workspace after aborted promotion of task {}: {}; \
+                         it will be retried by recovery",
+                        self.task_id,
+                        restore
+                    );
+                }
+                return Err(e);
+            }
+        }
+        journal.commit()?;
+        report.promoted = true;
+        tracing::info!(
+            "Promoted {} scratch changes of task {} (verdict {})",
+            report.changes.len(),
+            self.task_id,
+            approval.verdict_id
+        );
+        Ok(report)
+    }
+}
+
+/// 按任务管理临时工作区
+pub struct ScratchManager {
+    source: PathBuf,
+    base: PathBuf,
+    workspaces: Mutex<HashMap<Uuid, Arc<ScratchWorkspace>>>,
+}
+
+impl ScratchManager {
+    /// 为 `source` 创建任务副本，副本存放在 `base` 下
+    pub fn new(source: impl Into<PathBuf>, base: impl Into<PathBuf>) -> Self {
+        Self {
+            source: source.into(),
+            base: base.into(),
+            workspaces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 获取任务的临时工作区，不存在时创建
+    pub async fn open(&self, task_id: Uuid) -> Result<Arc<ScratchWorkspace>> {
+        let mut workspaces = self.workspaces.lock().await;
+        if let Some(workspace) = workspaces.get(&task_id) {
+            return Ok(workspace.clone());
+        }
+        let workspace = Arc::new(ScratchWorkspace::create(&self.source, &self.base, task_id).await?);
+        workspaces.insert(task_id, workspace.clone());
+        Ok(workspace)
+    }
+
+    /// 获取已打开的临时工作区
+    pub async fn get(&self, task_id: Uuid) -> Option<Arc<ScratchWorkspace>> {
+        self.workspaces.lock().await.get(&task_id).cloned()
+    }
+
+    /// 提升任务的变更，成功写回后删除副本；有冲突时保留副本
+    pub async fn promote(&self, approval: &PromotionApproval) -> Result<PromotionReport> {
+        let workspace = self
+            .get(approval.task_id)
+            .await
+            .ok_or_else(|| NeuroLoomError::Sandbox(format!("no scratch workspace for task {}", approval.task_id)))?;
+        let report = workspace.promote(approval).await?;
+        if report.promoted {
+            self.discard(approval.task_id).await?;
+        }
+        Ok(report)
+    }
+
+    /// 处理上次进程退出时未完成的提升（应在打开任何临时工作区之前调用）
+    pub async fn recover(&self, policy: RecoveryPolicy) -> Result<Vec<RecoveredPromotion>> {
+        let base = self.base.clone();
+        tokio::task::spawn_blocking(move || journal::recover(&base, policy))
+            .await
+            .map_err(|e| NeuroLoomError::Sandbox(e.to_string()))?
+    }
+
+    /// 丢弃任务的副本，返回是否存在
+    pub async fn discard(&self, task_id: Uuid) -> Result<bool> {
+        let Some(workspace) = self.workspaces.lock().await.remove(&task_id) else {
+            return Ok(false);
+        };
+        workspace.remove().await?;
+        Ok(true)
+    }
+}
+
+fn outside(path: &Path) -> NeuroLoomError {
+    NeuroLoomError::Sandbox(format!("path escapes the scratch workspace: {}", path.display()))
+}
+
+fn is_skipped(name: &std::ffi::OsStr) -> bool {
+    SKIPPED_DIRS.iter().any(|dir| name == *dir)
+}
+
+/// 递归复制 `source/relative` 到 `root/relative`，跳过 `skip`（副本所在目录）
+fn copy_tree(
+    source: &Path,
+    root: &Path,
+    skip: &Path,
+    relative: &Path,
+    baseline: &mut HashMap<PathBuf, String>,
+) -> Result<()> {
+    for entry in std::fs::read_dir(source.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if is_skipped(&entry.file_name()) || entry.path() == skip {
+                continue;
+            }
+            std::fs::create_dir_all(root.join(&path))?;
+            copy_tree(source, root, skip, &path, baseline)?;
+        } else if file_type.is_file() {
+            std::fs::copy(entry.path(), root.join(&path))?;
+            baseline.insert(path.clone(), digest(&std::fs::read(root.join(&path))?));
+        }
+    }
+    Ok(())
+}
+
+/// 递归计算 `root/relative` 下文件的摘要
+fn digest_tree(root: &Path, relative: &Path, digests: &mut HashMap<PathBuf, String>) -> Result<()> {
+    for entry in std::fs::read_dir(root.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if !is_skipped(&entry.file_name()) {
+                digest_tree(root, &path, digests)?;
+            }
+        } else if file_type.is_file() {
+            digests.insert(path, digest(&std::fs::read(entry.path())?));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changes_stay_in_scratch_until_promoted() {
+        let dir = std::env::temp_dir().join(format!("nl_scratch_{}", Uuid::new_v4()));
+        let source = dir.join("project");
+        std::fs::create_dir_all(source.join("src")).unwrap();
+        std::fs::create_dir_all(source.join("target")).unwrap();
+        std::fs::write(source.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(source.join("README.md"), "# Project\n").unwrap();
+        std::fs::write(source.join("old.txt"), "remove me\n").unwrap();
+        std::fs::write(source.join("target/build.log"), "ignored\n").unwrap();
+
+        let task = Uuid::new_v4();
+        let manager = ScratchManager::new(&source, dir.join("scratch"));
+        let scratch = manager.open(task).await.unwrap();
+        assert!(!scratch.root().join("target").exists());
+
+        let write = scratch
+            .confine(GodModeAction::WriteFile {
+                path: source.join("src/lib.rs"),
+                content: "fn a() { 1 }\n".to_string(),
+            })
+            .unwrap();
+        let GodModeAction::WriteFile { path, content } = write else {
+            unreachable!()
+        };
+        assert!(path.starts_with(scratch.root()));
+        tokio::fs::write(path, content).await.unwrap();
+        tokio::fs::write(scratch.root().join("notes.md"), "notes\n").await.unwrap();
+        tokio::fs::remove_file(scratch.root().join("old.txt")).await.unwrap();
+        assert!(scratch.confine(GodModeAction::ReadFile { path: "../secret".into() }).is_err());
+        assert!(matches!(
+            scratch.confine(GodModeAction::Execute { command: "ls".into(), args: vec![], cwd: None }),
+            Ok(GodModeAction::Execute { cwd: Some(ref cwd), .. }) if cwd == scratch.root()
+        ));
+        assert_eq!(std::fs::read_to_string(source.join("src/lib.rs")).unwrap(), "fn a() {}\n");
+
+        let kinds: Vec<_> = scratch.changes().await.unwrap().into_iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, [ChangeKind::Added, ChangeKind::Deleted, ChangeKind::Modified]);
+
+        let mut approval = PromotionApproval {
+            task_id: task,
+            verdict_id: Uuid::new_v4(),
+            passed: false,
+        };
+        assert!(manager.promote(&approval).await.is_err());
+
+        // 复制后真实工作区中的文件被改动：整体拒绝
+        approval.passed = true;
+        std::fs::write(source.join("src/lib.rs"), "fn b() {}\n").unwrap();
+        let report = manager.promote(&approval).await.unwrap();
+        assert!(!report.promoted);
+        assert_eq!(report.conflicts, [PathBuf::from("src/lib.rs")]);
+        assert!(source.join("old.txt").exists() && !source.join("notes.md").exists());
+
+        std::fs::write(source.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        let report = manager.promote(&approval).await.unwrap();
+        assert!(report.promoted);
+        assert_eq!(std::fs::read_to_string(source.join("src/lib.rs")).unwrap(), "fn a() { 1 }\n");
+        assert!(source.join("notes.md").exists() && !source.join("old.txt").exists());
+        assert!(manager.get(task).await.is_none() && !scratch.root().exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
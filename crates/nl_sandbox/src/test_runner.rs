@@ -0,0 +1,403 @@
+//! 测试运行器
+//!
+//! “能否编译并通过测试”是最强的 Critic。`TestRunner` 在项目目录中运行测试命令，
+//! 把输出解析为结构化的 `TestReport`（通过/失败/跳过数、失败的测试名、截取的输出）：
+//! - `cargo test`：按每个测试二进制的 `test result:` 汇总行累加，失败名取自 `... FAILED`
+//! - `pytest`：解析末尾汇总行与 `FAILED`/`ERROR` 简报
+//! - `npm test`：兼容 Jest（`Tests:` 行）、Vitest 与 Mocha（`passing`/`failing`）的输出
+//!
+//! 编译失败等没有任何汇总行、但进程非零退出的情况同样视为未通过。
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use nl_core::{NeuroLoomError, Result};
+
+/// 报告中保留的输出上限（保留末尾）
+const MAX_OUTPUT_BYTES: usize = 16 * 1024;
+
+/// 默认超时
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 测试框架
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestFramework {
+    Cargo,
+    Pytest,
+    Npm,
+}
+
+impl TestFramework {
+    /// 按项目文件推断框架
+    pub fn detect(root: &Path) -> Option<Self> {
+        if root.join("Cargo.toml").is_file() {
+            Some(TestFramework::Cargo)
+        } else if root.join("package.json").is_file() {
+            Some(TestFramework::Npm)
+        } else if [
+            "pyproject.toml",
+            "pytest.ini",
+            "setup.py",
+            "setup.cfg",
+            "tox.ini",
+        ]
+        .iter()
+        .any(|f| root.join(f).is_file())
+        {
+            Some(TestFramework::Pytest)
+        } else {
+            None
+        }
+    }
+
+    /// 程序与默认参数
+    fn command(&self) -> (&'static str, Vec<&'static str>) {
+        match self {
+            TestFramework::Cargo => ("cargo", vec!["test", "--color", "never"]),
+            TestFramework::Pytest => ("python", vec!["-m", "pytest", "-rfE", "--color=no"]),
+            TestFramework::Npm => ("npm", vec!["test"]),
+        }
+    }
+
+    /// 解析测试输出
+    pub fn parse(&self, output: &str) -> TestCounts {
+        match self {
+            TestFramework::Cargo => parse_cargo(output),
+            TestFramework::Pytest => parse_pytest(output),
+            TestFramework::Npm => parse_npm(output),
+        }
+    }
+}
+
+/// 解析出的测试计数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestCounts {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// 失败的测试名
+    pub failing: Vec<String>,
+    /// 是否找到了汇总行
+    pub summarized: bool,
+}
+
+/// 测试报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestReport {
+    pub framework: TestFramework,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub failing: Vec<String>,
+    /// 进程退出码（超时为空）
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub timed_out: bool,
+    /// 截取的 stdout + stderr（保留末尾）
+    pub output: String,
+}
+
+impl TestReport {
+    /// 是否全部通过（至少运行了一个测试）
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0) && self.failed == 0 && self.passed > 0
+    }
+
+    /// 通过率
+    pub fn pass_rate(&self) -> f64 {
+        let total = self.passed + self.failed;
+        if total == 0 {
+            0.0
+        } else {
+            self.passed as f64 / total as f64
+        }
+    }
+
+    /// 一行摘要
+    pub fn summary(&self) -> String {
+        if self.timed_out {
+            return format!(
+                "{:?} tests timed out after {} ms",
+                self.framework, self.duration_ms
+            );
+        }
+        let mut summary = format!(
+            "{} passed, {} failed, {} skipped",
+            self.passed, self.failed, self.skipped
+        );
+        if self.passed + self.failed == 0 && self.exit_code != Some(0) {
+            summary.push_str(&format!(
+                " (exit code {:?}, tests did not run)",
+                self.exit_code
+            ));
+        }
+        summary
+    }
+}
+
+/// 测试运行器
+#[derive(Debug, Clone)]
+pub struct TestRunner {
+    root: PathBuf,
+    framework: TestFramework,
+    /// 追加到默认命令后的参数（如测试过滤器）
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+impl TestRunner {
+    /// 创建指定框架的运行器
+    pub fn new(root: impl Into<PathBuf>, framework: TestFramework) -> Self {
+        Self {
+            root: root.into(),
+            framework,
+            args: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// 按项目文件推断框架
+    pub fn detect(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let framework = TestFramework::detect(&root).ok_or_else(|| {
+            NeuroLoomError::Sandbox(format!(
+                "no supported test framework found in {}",
+                root.display()
+            ))
+        })?;
+        Ok(Self::new(root, framework))
+    }
+
+    /// 追加命令参数
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// 设置超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 测试框架
+    pub fn framework(&self) -> TestFramework {
+        self.framework
+    }
+
+    /// 运行测试并解析结果
+    pub async fn run(&self) -> Result<TestReport> {
+        let (program, defaults) = self.framework.command();
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(defaults)
+            .args(&self.args)
+            .current_dir(&self.root)
+            .env("CI", "true")
+            .env("NO_COLOR", "1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let started = Instant::now();
+        let child = command.spawn()?;
+        let (exit_code, timed_out, output) =
+            match tokio::time::timeout(self.timeout, child.wait_with_output()).await {
+                Ok(output) => {
+                    let output = output?;
+                    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                    text.push_str(&String::from_utf8_lossy(&output.stderr));
+                    (output.status.code(), false, text)
+                }
+                Err(_) => (None, true, String::new()),
+            };
+
+        let counts = self.framework.parse(&output);
+        let report = TestReport {
+            framework: self.framework,
+            passed: counts.passed,
+            failed: counts.failed.max(counts.failing.len()),
+            skipped: counts.skipped,
+            failing: counts.failing,
+            exit_code,
+            duration_ms: started.elapsed().as_millis() as u64,
+            timed_out,
+            output: tail(&output, MAX_OUTPUT_BYTES),
+        };
+        tracing::info!(
+            "{:?} tests in {}: {}",
+            self.framework,
+            self.root.display(),
+            report.summary()
+        );
+        Ok(report)
+    }
+}
+
+/// 保留末尾 `max` 字节（按字符边界）
+fn tail(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut start = text.len() - max;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("...\n{}", &text[start..])
+}
+
+/// 把 “3 passed, 1 failed” 一类的片段累加到计数
+fn add_counts(counts: &mut TestCounts, text: &str) -> bool {
+    let words: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '|' | '(' | ')' | '='))
+        .filter(|w| !w.is_empty())
+        .collect();
+    let mut found = false;
+    for pair in words.windows(2) {
+        let Ok(n) = pair[0].parse::<usize>() else {
+            continue;
+        };
+        match pair[1].trim_end_matches('.') {
+            "passed" | "passing" => counts.passed += n,
+            "failed" | "failing" | "error" | "errors" => counts.failed += n,
+            "skipped" | "ignored" | "pending" | "todo" | "xfailed" => counts.skipped += n,
+            _ => continue,
+        }
+        found = true;
+    }
+    found
+}
+
+fn parse_cargo(output: &str) -> TestCounts {
+    let mut counts = TestCounts::default();
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(result) = line.strip_prefix("test result:") {
+            counts.summarized |= add_counts(&mut counts, result);
+        } else if let Some(name) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.strip_suffix(" ... FAILED"))
+        {
+            counts.failing.push(name.to_string());
+        }
+    }
+    counts
+}
+
+fn parse_pytest(output: &str) -> TestCounts {
+    let mut counts = TestCounts::default();
+    let mut summary = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(name) = line
+            .strip_prefix("FAILED ")
+            .or_else(|| line.strip_prefix("ERROR "))
+        {
+            let name = name.split(" - ").next().unwrap_or(name).trim();
+            counts.failing.push(name.to_string());
+        } else if line.contains(" in ")
+            && (line.contains(" passed") || line.contains(" failed") || line.contains(" error"))
+        {
+            summary = Some(line);
+        }
+    }
+    if let Some(summary) = summary {
+        counts.summarized =
+            add_counts(&mut counts, summary.split(" in ").next().unwrap_or(summary));
+    }
+    counts
+}
+
+fn parse_npm(output: &str) -> TestCounts {
+    let mut counts = TestCounts::default();
+    let mut mocha = TestCounts::default();
+    for line in output.lines() {
+        let line = line.trim();
+        // Jest: `Tests:       1 failed, 3 passed, 4 total`；Vitest: `Tests  1 failed | 3 passed (4)`
+        if let Some(result) = line
+            .strip_prefix("Tests:")
+            .or_else(|| line.strip_prefix("Tests "))
+        {
+            counts.summarized |= add_counts(&mut counts, result);
+        } else if let Some(name) = line.strip_prefix("✕ ").or_else(|| line.strip_prefix("× ")) {
+            let name = name.rsplit_once(" (").map_or(name, |(n, _)| n).trim();
+            counts.failing.push(name.to_string());
+        } else if let Some(head) = line.split(" (").next().filter(|h| {
+            h.ends_with(" passing") || h.ends_with(" failing") || h.ends_with(" pending")
+        }) {
+            // Mocha: `5 passing (20ms)`
+            mocha.summarized |= add_counts(&mut mocha, head);
+        } else if let Some((index, name)) = line.split_once(") ") {
+            // Mocha 失败列表：`1) suite name:`
+            if index.parse::<usize>().is_ok() && name.ends_with(':') {
+                mocha.failing.push(name.trim_end_matches(':').to_string());
+            }
+        }
+    }
+    if counts.summarized {
+        counts
+    } else {
+        mocha.failing.dedup();
+        mocha
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_framework_output() {
+        let cargo = "\
+running 3 tests
+test tests::adds ... ok
+test tests::overflows ... FAILED
+test tests::slow ... ignored
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.01s
+
+running 2 tests
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+        let counts = TestFramework::Cargo.parse(cargo);
+        assert_eq!((counts.passed, counts.failed, counts.skipped), (3, 1, 1));
+        assert_eq!(counts.failing, vec!["tests::overflows"]);
+
+        let pytest = "\
+FAILED tests/test_math.py::test_div - ZeroDivisionError: division by zero
+ERROR tests/test_db.py::test_conn
+==== 1 failed, 4 passed, 2 skipped, 1 error in 0.31s ====
+";
+        let counts = TestFramework::Pytest.parse(pytest);
+        assert_eq!((counts.passed, counts.failed, counts.skipped), (4, 2, 2));
+        assert_eq!(
+            counts.failing,
+            vec![
+                "tests/test_math.py::test_div",
+                "tests/test_db.py::test_conn"
+            ]
+        );
+
+        let jest = "  ✕ rejects empty input (3 ms)\nTests:       1 failed, 3 passed, 4 total\n";
+        let counts = TestFramework::Npm.parse(jest);
+        assert_eq!((counts.passed, counts.failed), (3, 1));
+        assert_eq!(counts.failing, vec!["rejects empty input"]);
+
+        let mocha = "  5 passing (20ms)\n  1 failing\n\n  1) parser handles unicode:\n     AssertionError\n";
+        let counts = TestFramework::Npm.parse(mocha);
+        assert_eq!((counts.passed, counts.failed), (5, 1));
+        assert_eq!(counts.failing, vec!["parser handles unicode"]);
+
+        // 编译失败：没有汇总行
+        assert!(
+            !TestFramework::Cargo
+                .parse("error[E0425]: cannot find value `x`")
+                .summarized
+        );
+    }
+}
@@ -0,0 +1,488 @@
+//! 帧环形缓冲 - “30 秒前屏幕是什么样子”
+//!
+//! 有界地保存最近的画面，供 Critic 回看自动化过程中的视觉历史：
+//! - 帧按组存放：每组以一个 gzip 压缩的关键帧开头，其后的帧只保存相对该关键帧的字节差异与语义差分
+//! - 差异超过关键帧大小的 `max_delta_ratio`、或组内帧数达到 `keyframe_interval` 时开启新组
+//! - 内存中的帧数据超过 `memory_budget` 时，最早的组整体转存到 `ArchivalManager`（只保留时间与差分元数据），
+//!   回看时按需恢复；帧总数超过 `capacity` 时丢弃最早的组（连同其归档）
+//! - `get_frame_at(时间)` 返回该时刻正在显示的帧（不晚于该时刻的最后一帧），由关键帧与差异重建
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use nl_core::{NeuroLoomError, Result};
+use nl_memory::ArchivalManager;
+
+use crate::delta_diff::FrameDiff;
+
+/// 差异中相距不超过该字节数的变化合并为一段
+const MERGE_GAP: usize = 8;
+
+/// 每段差异的固定开销（偏移与长度）
+const RUN_OVERHEAD: usize = 8;
+
+/// 缓冲配置
+#[derive(Debug, Clone)]
+pub struct FrameBufferConfig {
+    /// 最多保留的帧数
+    pub capacity: usize,
+    /// 每组最多的帧数（含关键帧）
+    pub keyframe_interval: usize,
+    /// 差异大小超过关键帧的该比例时改存关键帧
+    pub max_delta_ratio: f64,
+    /// 内存中帧数据的上限（字节）
+    pub memory_budget: usize,
+}
+
+impl Default for FrameBufferConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1800,
+            keyframe_interval: 60,
+            max_delta_ratio: 0.3,
+            memory_budget: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// 重建的帧
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub data: Vec<u8>,
+    pub diff: FrameDiff,
+}
+
+/// 帧元数据（不含画面，供浏览历史）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameInfo {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub keyframe: bool,
+    /// 所在组是否已转存到归档
+    pub archived: bool,
+    pub diff: FrameDiff,
+}
+
+/// 相对关键帧的字节差异
+#[derive(Debug, Clone, Default)]
+struct FramePatch {
+    len: usize,
+    runs: Vec<(usize, Vec<u8>)>,
+}
+
+impl FramePatch {
+    /// 计算差异；超出 `limit` 字节时放弃并返回 `None`
+    fn between(base: &[u8], frame: &[u8], limit: usize) -> Option<Self> {
+        let mut runs: Vec<(usize, Vec<u8>)> = Vec::new();
+        let mut size = 0;
+        let mut i = 0;
+        while i < frame.len() {
+            if base.get(i) == Some(&frame[i]) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            let mut end = i + 1;
+            let mut same = 0;
+            while end < frame.len() && same <= MERGE_GAP {
+                if base.get(end) == Some(&frame[end]) {
+                    same += 1;
+                } else {
+                    same = 0;
+                }
+                end += 1;
+            }
+            let end = end - same.min(end - start - 1);
+            size += end - start + RUN_OVERHEAD;
+            if size > limit {
+                return None;
+            }
+            runs.push((start, frame[start..end].to_vec()));
+            i = end;
+        }
+        Some(Self { len: frame.len(), runs })
+    }
+
+    fn apply(&self, base: &[u8]) -> Vec<u8> {
+        let mut frame = base.to_vec();
+        frame.resize(self.len, 0);
+        for (offset, bytes) in &self.runs {
+            frame[*offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+        frame
+    }
+
+    fn size(&self) -> usize {
+        self.runs.iter().map(|(_, b)| b.len() + RUN_OVERHEAD).sum()
+    }
+}
+
+/// 组内数据
+enum GroupData {
+    /// 压缩的关键帧与后续帧的差异
+    InMemory { keyframe: Vec<u8>, patches: Vec<FramePatch> },
+    /// 已转存到归档
+    Archived { source_id: Uuid },
+}
+
+/// 帧组
+struct FrameGroup {
+    frames: Vec<(u64, DateTime<Utc>, FrameDiff)>,
+    data: GroupData,
+}
+
+impl FrameGroup {
+    fn memory_size(&self) -> usize {
+        match &self.data {
+            GroupData::InMemory { keyframe, patches } => {
+                keyframe.len() + patches.iter().map(FramePatch::size).sum::<usize>()
+            }
+            GroupData::Archived { .. } => 0,
+        }
+    }
+}
+
+/// 帧环形缓冲
+pub struct FrameRingBuffer {
+    config: FrameBufferConfig,
+    groups: VecDeque<FrameGroup>,
+    /// 当前组关键帧的原始数据（计算差异用）
+    keyframe: Vec<u8>,
+    next_seq: u64,
+    frame_count: usize,
+    memory_bytes: usize,
+    archival: Option<Mutex<ArchivalManager>>,
+}
+
+impl FrameRingBuffer {
+    /// 创建缓冲
+    pub fn new(config: FrameBufferConfig) -> Self {
+        Self {
+            config,
+            groups: VecDeque::new(),
+            keyframe: Vec::new(),
+            next_seq: 0,
+            frame_count: 0,
+            memory_bytes: 0,
+            archival: None,
+        }
+    }
+
+    /// 超出内存预算的旧关键帧组转存到归档（未设置时直接丢弃）
+    pub fn with_archival(mut self, archival: ArchivalManager) -> Self {
+        self.archival = Some(Mutex::new(archival));
+        self
+    }
+
+    /// 记录一帧，返回其序号
+    pub async fn push(&mut self, timestamp: DateTime<Utc>, frame: &[u8], diff: FrameDiff) -> Result<u64> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        let interval = self.config.keyframe_interval.max(1);
+        let limit = (self.keyframe.len() as f64 * self.config.max_delta_ratio) as usize;
+        let patch = match self.groups.back() {
+            Some(group) if group.frames.len() < interval => FramePatch::between(&self.keyframe, frame, limit),
+            _ => None,
+        };
+        match (patch, self.groups.back_mut()) {
+            (Some(patch), Some(group)) => {
+                self.memory_bytes += patch.size();
+                // 当前组从不转存，始终在内存中
+                if let GroupData::InMemory { patches, .. } = &mut group.data {
+                    patches.push(patch);
+                }
+                group.frames.push((seq, timestamp, diff));
+            }
+            _ => {
+                let keyframe = compress(frame)?;
+                self.memory_bytes += keyframe.len();
+                self.keyframe = frame.to_vec();
+                self.groups.push_back(FrameGroup {
+                    frames: vec![(seq, timestamp, diff)],
+                    data: GroupData::InMemory {
+                        keyframe,
+                        patches: Vec::new(),
+                    },
+                });
+            }
+        }
+        self.frame_count += 1;
+
+        self.evict().await?;
+        Ok(seq)
+    }
+
+    /// 超出帧数时丢弃最早的组，超出内存预算时转存最早的内存组（当前组除外）
+    async fn evict(&mut self) -> Result<()> {
+        while self.frame_count > self.config.capacity && self.groups.len() > 1 {
+            let group = self.groups.pop_front().expect("more than one group");
+            self.frame_count -= group.frames.len();
+            self.memory_bytes -= group.memory_size();
+            if let (GroupData::Archived { source_id }, Some(archival)) = (&group.data, &self.archival) {
+                archival.lock().await.remove(source_id).await?;
+            }
+        }
+
+        let budget = self.config.memory_budget;
+        let Some(archival) = &self.archival else {
+            while self.memory_bytes > budget && self.groups.len() > 1 {
+                let group = self.groups.pop_front().expect("more than one group");
+                self.frame_count -= group.frames.len();
+                self.memory_bytes -= group.memory_size();
+            }
+            return Ok(());
+        };
+        let last = self.groups.len().saturating_sub(1);
+        for group in self.groups.iter_mut().take(last) {
+            if self.memory_bytes <= budget {
+                break;
+            }
+            let GroupData::InMemory { keyframe, patches } = &group.data else {
+                continue;
+            };
+            let source_id = Uuid::new_v4();
+            archival.lock().await.archive(source_id, &encode(keyframe, patches)).await?;
+            self.memory_bytes -= group.memory_size();
+            group.data = GroupData::Archived { source_id };
+        }
+        Ok(())
+    }
+
+    /// 该时刻正在显示的帧（早于最早的帧时返回 `None`）
+    pub async fn get_frame_at(&self, timestamp: DateTime<Utc>) -> Result<Option<Frame>> {
+        let Some((group, position)) = self.locate(timestamp) else {
+            return Ok(None);
+        };
+        let (seq, at, diff) = group.frames[position].clone();
+        let data = match &group.data {
+            GroupData::InMemory { keyframe, patches } => reconstruct(keyframe, patches, position)?,
+            GroupData::Archived { source_id } => {
+                let Some(archival) = &self.archival else {
+                    return Ok(None);
+                };
+                let encoded = archival.lock().await.restore(source_id).await?;
+                let (keyframe, patches) = decode(&encoded)?;
+                reconstruct(&keyframe, &patches, position)?
+            }
+        };
+        Ok(Some(Frame {
+            seq,
+            timestamp: at,
+            data,
+            diff,
+        }))
+    }
+
+    /// 定位不晚于该时刻的最后一帧
+    fn locate(&self, timestamp: DateTime<Utc>) -> Option<(&FrameGroup, usize)> {
+        let groups = self.groups.partition_point(|g| g.frames[0].1 <= timestamp);
+        let group = self.groups.get(groups.checked_sub(1)?)?;
+        let position = group.frames.partition_point(|(_, at, _)| *at <= timestamp);
+        Some((group, position - 1))
+    }
+
+    /// 时间范围内的帧元数据
+    pub fn history(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<FrameInfo> {
+        self.groups
+            .iter()
+            .flat_map(|group| {
+                let archived = matches!(group.data, GroupData::Archived { .. });
+                group.frames.iter().enumerate().map(move |(i, (seq, at, diff))| FrameInfo {
+                    seq: *seq,
+                    timestamp: *at,
+                    keyframe: i == 0,
+                    archived,
+                    diff: diff.clone(),
+                })
+            })
+            .filter(|info| info.timestamp >= start && info.timestamp <= end)
+            .collect()
+    }
+
+    /// 保留的帧数
+    pub fn len(&self) -> usize {
+        self.frame_count
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.frame_count == 0
+    }
+
+    /// 内存中帧数据的字节数
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_bytes
+    }
+
+    /// 清空（已归档的组一并删除）
+    pub async fn clear(&mut self) -> Result<()> {
+        if let Some(archival) = &self.archival {
+            let mut archival = archival.lock().await;
+            for group in &self.groups {
+                if let GroupData::Archived { source_id } = &group.data {
+                    archival.remove(source_id).await?;
+                }
+            }
+        }
+        self.groups.clear();
+        self.keyframe.clear();
+        self.frame_count = 0;
+        self.memory_bytes = 0;
+        Ok(())
+    }
+}
+
+fn reconstruct(keyframe: &[u8], patches: &[FramePatch], position: usize) -> Result<Vec<u8>> {
+    let base = decompress(keyframe)?;
+    Ok(match position {
+        0 => base,
+        n => patches[n - 1].apply(&base),
+    })
+}
+
+fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// 组的归档格式：`[关键帧长度][关键帧]`，其后每个差异为 `[帧长度][段数]` 与各段 `[偏移][长度][字节]`（u32 小端）
+fn encode(keyframe: &[u8], patches: &[FramePatch]) -> Vec<u8> {
+    fn put(out: &mut Vec<u8>, n: usize) {
+        out.extend_from_slice(&(n as u32).to_le_bytes());
+    }
+    let mut out = Vec::with_capacity(keyframe.len() + patches.iter().map(FramePatch::size).sum::<usize>() + 4);
+    put(&mut out, keyframe.len());
+    out.extend_from_slice(keyframe);
+    for patch in patches {
+        put(&mut out, patch.len);
+        put(&mut out, patch.runs.len());
+        for (offset, bytes) in &patch.runs {
+            put(&mut out, *offset);
+            put(&mut out, bytes.len());
+            out.extend_from_slice(bytes);
+        }
+    }
+    out
+}
+
+/// 归档数据读取游标
+struct Reader<'a> {
+    data: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.cursor..self.cursor + n)
+            .ok_or_else(|| NeuroLoomError::Vision("corrupt archived frame group".to_string()))?;
+        self.cursor += n;
+        Ok(bytes)
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.cursor >= self.data.len()
+    }
+}
+
+fn decode(data: &[u8]) -> Result<(Vec<u8>, Vec<FramePatch>)> {
+    let mut reader = Reader { data, cursor: 0 };
+    let keyframe_len = reader.number()?;
+    let keyframe = reader.take(keyframe_len)?.to_vec();
+    let mut patches = Vec::new();
+    while !reader.is_empty() {
+        let len = reader.number()?;
+        let count = reader.number()?;
+        let mut runs = Vec::with_capacity(count);
+        for _ in 0..count {
+            let offset = reader.number()?;
+            let size = reader.number()?;
+            runs.push((offset, reader.take(size)?.to_vec()));
+        }
+        patches.push(FramePatch { len, runs });
+    }
+    Ok((keyframe, patches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use nl_memory::archival::ArchivalStrategy;
+
+    fn diff() -> FrameDiff {
+        FrameDiff {
+            significant_change: false,
+            changed_regions: Vec::new(),
+            similarity: 1.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconstructs_frames_across_archived_groups() {
+        let dir = std::env::temp_dir().join(format!("nl_frames_{}", Uuid::new_v4()));
+        let config = FrameBufferConfig {
+            capacity: 12,
+            keyframe_interval: 4,
+            max_delta_ratio: 0.3,
+            // 除当前组外全部转存
+            memory_budget: 1,
+        };
+        let mut buffer = FrameRingBuffer::new(config)
+            .with_archival(ArchivalManager::new(ArchivalStrategy::ByAge(1), dir.to_string_lossy()));
+        let base: Vec<u8> = (0..1024u32).map(|b| (b * 7 % 251) as u8).collect();
+        let frames: Vec<Vec<u8>> = (0..16usize)
+            .map(|i| {
+                let mut frame = base.clone();
+                frame[i * 32..i * 32 + 20].fill(i as u8);
+                frame
+            })
+            .collect();
+        let start = Utc::now();
+        for (i, frame) in frames.iter().enumerate() {
+            buffer.push(start + Duration::seconds(i as i64), frame, diff()).await.unwrap();
+        }
+
+        // 最早的一组被丢弃，其余 3 组中 2 组已转存
+        assert_eq!(buffer.len(), 12);
+        let history = buffer.history(start, start + Duration::seconds(60));
+        assert_eq!(history.len(), 12);
+        assert_eq!(history.iter().filter(|f| f.archived).count(), 8);
+        assert_eq!(history.iter().filter(|f| f.keyframe).count(), 3);
+
+        assert!(buffer.get_frame_at(start + Duration::milliseconds(3500)).await.unwrap().is_none());
+        for i in 4..16 {
+            let at = start + Duration::seconds(i) + Duration::milliseconds(500);
+            let frame = buffer.get_frame_at(at).await.unwrap().unwrap();
+            assert_eq!(frame.seq, i as u64);
+            assert_eq!(frame.data, frames[i as usize]);
+        }
+
+        buffer.clear().await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,10 +1,14 @@
 //! # nl_vision - NeuroLoom Vision Stream
 //!
-//! 视觉流处理：语义帧差分感知器、OCR、防止显存爆炸。
+//! 视觉流处理：语义帧差分感知器、帧环形缓冲、OCR、防止显存爆炸。
 
 pub mod delta_diff;
+pub mod frame_buffer;
 pub mod ocr;
 pub mod stream;
+pub mod ui_elements;
 
 pub use delta_diff::SemanticDiff;
+pub use frame_buffer::{Frame, FrameBufferConfig, FrameRingBuffer};
 pub use stream::VisionStream;
+pub use ui_elements::{ElementProvider, ElementQuery, ElementSnapshot, PlatformElementProvider, UiElement};
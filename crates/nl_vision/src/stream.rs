@@ -1,6 +1,12 @@
 //! 视觉流处理
 
-use crate::delta_diff::SemanticDiff;
+use chrono::{DateTime, Utc};
+
+use nl_core::Result;
+use nl_memory::ArchivalManager;
+
+use crate::delta_diff::{FrameDiff, SemanticDiff};
+use crate::frame_buffer::{Frame, FrameBufferConfig, FrameRingBuffer};
 
 /// 视觉流
 pub struct VisionStream {
@@ -8,6 +14,8 @@ pub struct VisionStream {
     diff: SemanticDiff,
     /// 是否运行中
     running: bool,
+    /// 帧历史
+    buffer: FrameRingBuffer,
 }
 
 impl VisionStream {
@@ -16,9 +24,22 @@ impl VisionStream {
         Self {
             diff: SemanticDiff::default(),
             running: false,
+            buffer: FrameRingBuffer::new(FrameBufferConfig::default()),
         }
     }
 
+    /// 设置帧历史缓冲配置
+    pub fn with_buffer(mut self, config: FrameBufferConfig) -> Self {
+        self.buffer = FrameRingBuffer::new(config);
+        self
+    }
+
+    /// 超出内存预算的旧帧转存到归档
+    pub fn with_archival(mut self, archival: ArchivalManager) -> Self {
+        self.buffer = self.buffer.with_archival(archival);
+        self
+    }
+
     /// 启动流
     pub fn start(&mut self) {
         self.running = true;
@@ -31,10 +52,27 @@ impl VisionStream {
     }
 
     /// 处理帧
-    pub fn process_frame(&mut self, frame: &[u8]) -> crate::delta_diff::FrameDiff {
+    pub fn process_frame(&mut self, frame: &[u8]) -> FrameDiff {
         self.diff.process(frame)
     }
 
+    /// 处理帧并记入帧历史
+    pub async fn capture(&mut self, frame: &[u8]) -> Result<FrameDiff> {
+        let diff = self.diff.process(frame);
+        self.buffer.push(Utc::now(), frame, diff.clone()).await?;
+        Ok(diff)
+    }
+
+    /// 该时刻屏幕上的画面
+    pub async fn get_frame_at(&self, timestamp: DateTime<Utc>) -> Result<Option<Frame>> {
+        self.buffer.get_frame_at(timestamp).await
+    }
+
+    /// 帧历史
+    pub fn buffer(&self) -> &FrameRingBuffer {
+        &self.buffer
+    }
+
     /// 是否运行中
     pub fn is_running(&self) -> bool {
         self.running
@@ -0,0 +1,680 @@
+//! UI 元素定位
+//!
+//! 只靠 OCR 无法可靠地点击：同一段文字可能出现在按钮、标签与菜单里。这里通过系统无障碍接口
+//! （Windows UIA / Linux AT-SPI / macOS AX）枚举前台窗口的元素，得到角色、名称与屏幕坐标，
+//! 再与 OCR 结果合并（无名称的图标按钮借用其上的识别文字），让 Agent 可以确定性地引用
+//! “the Save button” 这样的目标：
+//! - 平台接口通过各自的脚本宿主调用（PowerShell / python3 + gi.Atspi / osascript JXA），输出统一的 JSON
+//! - 各平台的角色名归一化为 `ElementRole`
+//! - `ElementQuery` 解析自然语言引用（可带角色词）或 `#<序号>`；候选按名称匹配程度、可用状态、
+//!   面积与位置排序，结果与调用次数无关
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use nl_core::{NeuroLoomError, Result};
+
+use crate::ocr::OcrResult;
+
+/// 枚举元素的最大数量（与平台脚本中的上限一致）
+const MAX_ELEMENTS: usize = 2000;
+
+/// 屏幕坐标系下的矩形
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoundingBox {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// 中心点（点击位置）
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.width as i32 / 2, self.y + self.height as i32 / 2)
+    }
+
+    /// 面积
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// 是否包含点
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width as i32 && y < self.y + self.height as i32
+    }
+}
+
+impl fmt::Display for BoundingBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({},{} {}x{})", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// 归一化的元素角色
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementRole {
+    Button,
+    TextField,
+    CheckBox,
+    RadioButton,
+    ComboBox,
+    MenuItem,
+    Link,
+    Tab,
+    ListItem,
+    Label,
+    Image,
+    Window,
+    /// 未归一化的平台角色名
+    Other(String),
+}
+
+impl ElementRole {
+    /// 归一化平台角色名（UIA `ControlType.Button`、AX `AXButton`、AT-SPI `push button` 等）
+    pub fn from_platform(role: &str) -> Self {
+        let lower = role.to_lowercase();
+        let key: String = lower
+            .trim_start_matches("controltype.")
+            .trim_start_matches("ax")
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        match key.as_str() {
+            "button" | "pushbutton" | "togglebutton" | "splitbutton" => ElementRole::Button,
+            "edit" | "textfield" | "textarea" | "entry" | "passwordtext" | "searchfield" | "document" => {
+                ElementRole::TextField
+            }
+            "checkbox" => ElementRole::CheckBox,
+            "radiobutton" => ElementRole::RadioButton,
+            "combobox" | "popupbutton" => ElementRole::ComboBox,
+            "menuitem" | "menubaritem" | "checkmenuitem" | "radiomenuitem" => ElementRole::MenuItem,
+            "hyperlink" | "link" => ElementRole::Link,
+            "tabitem" | "pagetab" | "tab" => ElementRole::Tab,
+            "listitem" | "dataitem" | "row" | "tablerow" | "treeitem" | "cell" | "tablecell" => ElementRole::ListItem,
+            "text" | "statictext" | "label" => ElementRole::Label,
+            "image" | "icon" => ElementRole::Image,
+            "window" | "frame" | "dialog" | "sheet" => ElementRole::Window,
+            _ => ElementRole::Other(role.to_string()),
+        }
+    }
+
+    /// 自然语言中的角色词
+    fn from_noun(noun: &str) -> Option<Self> {
+        Some(match noun {
+            "button" => ElementRole::Button,
+            "field" | "text field" | "textbox" | "text box" | "input" => ElementRole::TextField,
+            "checkbox" | "check box" => ElementRole::CheckBox,
+            "radio" | "radio button" | "option" => ElementRole::RadioButton,
+            "dropdown" | "combo box" | "combobox" => ElementRole::ComboBox,
+            "menu item" | "menu" => ElementRole::MenuItem,
+            "link" => ElementRole::Link,
+            "tab" => ElementRole::Tab,
+            "item" | "row" => ElementRole::ListItem,
+            "label" | "text" => ElementRole::Label,
+            "icon" | "image" => ElementRole::Image,
+            "window" | "dialog" => ElementRole::Window,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for ElementRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ElementRole::Button => "button",
+            ElementRole::TextField => "text field",
+            ElementRole::CheckBox => "checkbox",
+            ElementRole::RadioButton => "radio button",
+            ElementRole::ComboBox => "combo box",
+            ElementRole::MenuItem => "menu item",
+            ElementRole::Link => "link",
+            ElementRole::Tab => "tab",
+            ElementRole::ListItem => "list item",
+            ElementRole::Label => "label",
+            ElementRole::Image => "image",
+            ElementRole::Window => "window",
+            ElementRole::Other(role) => role,
+        };
+        f.write_str(name)
+    }
+}
+
+/// 元素来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementSource {
+    /// 无障碍接口
+    Accessibility,
+    /// 仅 OCR（无障碍树中没有对应元素）
+    Ocr,
+    /// 无障碍元素，名称来自 OCR
+    Merged,
+}
+
+/// UI 元素
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiElement {
+    /// 快照内序号（`#<序号>` 引用）
+    pub index: usize,
+    pub role: ElementRole,
+    pub name: String,
+    pub value: Option<String>,
+    pub bounds: BoundingBox,
+    pub enabled: bool,
+    pub focused: bool,
+    pub source: ElementSource,
+    /// 元素范围内识别到的文字（名称之外的补充）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_text: Option<String>,
+}
+
+impl UiElement {
+    /// 供 Agent 阅读的一行描述
+    pub fn describe(&self) -> String {
+        let mut line = format!("#{} {} \"{}\" at {}", self.index, self.role, self.name, self.bounds);
+        if let Some(value) = self.value.as_deref().filter(|v| !v.is_empty()) {
+            line.push_str(&format!(" value=\"{}\"", value));
+        }
+        if let Some(text) = &self.ocr_text {
+            line.push_str(&format!(" text=\"{}\"", text));
+        }
+        if !self.enabled {
+            line.push_str(" (disabled)");
+        }
+        if self.focused {
+            line.push_str(" (focused)");
+        }
+        line
+    }
+}
+
+/// 窗口信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowInfo {
+    pub title: String,
+    pub process: String,
+    pub bounds: BoundingBox,
+}
+
+/// 元素引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElementQuery {
+    /// `#<序号>`
+    Index(usize),
+    /// 名称（可带角色）
+    Named { name: String, role: Option<ElementRole> },
+}
+
+impl ElementQuery {
+    /// 解析 “the Save button”、“"File name" field”、“#12” 之类的引用
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        if let Some(index) = text.strip_prefix('#').and_then(|i| i.parse().ok()) {
+            return ElementQuery::Index(index);
+        }
+        let mut rest = text;
+        for article in ["the ", "a ", "an "] {
+            if rest.len() > article.len() && rest.get(..article.len()).is_some_and(|p| p.eq_ignore_ascii_case(article)) {
+                rest = &rest[article.len()..];
+                break;
+            }
+        }
+        // 角色词取最长的匹配后缀，“radio button” 优先于 “button”
+        let mut role = None;
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        for take in [2, 1] {
+            if words.len() <= take {
+                continue;
+            }
+            let noun = words[words.len() - take..].join(" ").to_lowercase();
+            if let Some(found) = ElementRole::from_noun(&noun) {
+                role = Some(found);
+                for _ in 0..take {
+                    let end = rest.trim_end().rfind(char::is_whitespace).unwrap_or(0);
+                    rest = &rest[..end];
+                }
+                break;
+            }
+        }
+        let name = rest.trim().trim_matches(|c| c == '"' || c == '\'' || c == '“' || c == '”').trim();
+        ElementQuery::Named {
+            name: name.to_string(),
+            role,
+        }
+    }
+}
+
+/// 前台窗口的元素快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElementSnapshot {
+    pub window: Option<WindowInfo>,
+    pub elements: Vec<UiElement>,
+    pub captured_at: DateTime<Utc>,
+}
+
+impl ElementSnapshot {
+    /// 按引用排序的候选（最佳在前）
+    pub fn candidates(&self, query: &ElementQuery) -> Vec<&UiElement> {
+        let (name, role) = match query {
+            ElementQuery::Index(index) => return self.elements.get(*index).into_iter().collect(),
+            ElementQuery::Named { name, role } => (name.to_lowercase(), role),
+        };
+        let mut scored: Vec<(u8, &UiElement)> = self
+            .elements
+            .iter()
+            .filter(|e| role.iter().all(|r| &e.role == r))
+            .filter_map(|e| {
+                let score = [Some(&e.name), e.ocr_text.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .map(|text| name_score(&text.to_lowercase(), &name))
+                    .max()
+                    .unwrap_or(0);
+                (score > 0).then_some((score, e))
+            })
+            .collect();
+        scored.sort_by(|(sa, a), (sb, b)| {
+            sb.cmp(sa)
+                .then(b.enabled.cmp(&a.enabled))
+                .then(a.bounds.area().cmp(&b.bounds.area()))
+                .then((a.bounds.y, a.bounds.x).cmp(&(b.bounds.y, b.bounds.x)))
+                .then(a.index.cmp(&b.index))
+        });
+        scored.into_iter().map(|(_, e)| e).collect()
+    }
+
+    /// 解析引用为唯一元素
+    pub fn resolve(&self, query: &str) -> Result<&UiElement> {
+        let parsed = ElementQuery::parse(query);
+        self.candidates(&parsed)
+            .into_iter()
+            .next()
+            .ok_or_else(|| NeuroLoomError::Vision(format!("no element matches \"{}\"", query)))
+    }
+
+    /// 与 OCR 结果合并
+    ///
+    /// `origin` 为截图左上角的屏幕坐标。识别区域中心落在某个元素内时归入包含它的最小元素：
+    /// 元素没有名称则以识别文字命名，否则记为补充文字；不在任何元素内的文字作为 OCR 标签追加。
+    pub fn merge_ocr(&mut self, ocr: &OcrResult, origin: (i32, i32)) {
+        for region in &ocr.regions {
+            let text = region.text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            let bounds = BoundingBox::new(
+                origin.0 + region.x as i32,
+                origin.1 + region.y as i32,
+                region.width,
+                region.height,
+            );
+            let owner = self
+                .elements
+                .iter_mut()
+                .filter(|e| e.source != ElementSource::Ocr && e.role != ElementRole::Window)
+                .filter(|e| e.bounds.contains(bounds.center()))
+                .min_by_key(|e| e.bounds.area());
+            match owner {
+                Some(element) if element.name.trim().is_empty() => {
+                    element.name = text.to_string();
+                    element.source = ElementSource::Merged;
+                }
+                Some(element) if element.name.trim() != text => match &mut element.ocr_text {
+                    Some(existing) => {
+                        existing.push(' ');
+                        existing.push_str(text);
+                    }
+                    None => element.ocr_text = Some(text.to_string()),
+                },
+                Some(_) => {}
+                None => self.elements.push(UiElement {
+                    index: self.elements.len(),
+                    role: ElementRole::Label,
+                    name: text.to_string(),
+                    value: None,
+                    bounds,
+                    enabled: true,
+                    focused: false,
+                    source: ElementSource::Ocr,
+                    ocr_text: None,
+                }),
+            }
+        }
+    }
+
+    /// 供 Agent 阅读的元素列表（省略无名称且无文字的元素）
+    pub fn describe(&self) -> String {
+        let mut text = match &self.window {
+            Some(window) => format!("Window \"{}\" ({}) at {}\n", window.title, window.process, window.bounds),
+            None => String::new(),
+        };
+        for element in &self.elements {
+            if element.name.trim().is_empty() && element.ocr_text.is_none() {
+                continue;
+            }
+            text.push_str(&element.describe());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// 名称匹配程度：完全相同 3，前缀 2，包含 1
+fn name_score(text: &str, query: &str) -> u8 {
+    let text = text.trim();
+    if query.is_empty() {
+        return 1;
+    }
+    if text == query {
+        3
+    } else if text.starts_with(query) {
+        2
+    } else if text.contains(query) {
+        1
+    } else {
+        0
+    }
+}
+
+/// 元素提供者
+#[async_trait]
+pub trait ElementProvider: Send + Sync {
+    /// 提供者名称
+    fn name(&self) -> &str;
+
+    /// 枚举前台窗口的元素
+    async fn snapshot(&self) -> Result<ElementSnapshot>;
+}
+
+/// 平台脚本输出的窗口
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawWindow {
+    title: String,
+    process: String,
+    x: i32,
+    y: i32,
+    width: i64,
+    height: i64,
+    elements: Vec<RawElement>,
+}
+
+/// 平台脚本输出的元素
+#[derive(Debug, Deserialize)]
+struct RawElement {
+    role: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    value: Option<String>,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    #[serde(default = "enabled_default")]
+    enabled: bool,
+    #[serde(default)]
+    focused: bool,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+impl RawWindow {
+    fn into_snapshot(self) -> ElementSnapshot {
+        let mut elements: Vec<UiElement> = self
+            .elements
+            .into_iter()
+            .filter(|e| e.width >= 1.0 && e.height >= 1.0)
+            .take(MAX_ELEMENTS)
+            .map(|e| UiElement {
+                index: 0,
+                role: ElementRole::from_platform(&e.role),
+                name: e.name.unwrap_or_default().trim().to_string(),
+                value: e.value.filter(|v| !v.is_empty()),
+                bounds: BoundingBox::new(e.x as i32, e.y as i32, e.width as u32, e.height as u32),
+                enabled: e.enabled,
+                focused: e.focused,
+                source: ElementSource::Accessibility,
+                ocr_text: None,
+            })
+            .collect();
+        // 阅读顺序编号：自上而下、自左而右
+        elements.sort_by(|a, b| match a.bounds.y.cmp(&b.bounds.y) {
+            Ordering::Equal => a.bounds.x.cmp(&b.bounds.x),
+            other => other,
+        });
+        for (index, element) in elements.iter_mut().enumerate() {
+            element.index = index;
+        }
+        let window = (!self.title.is_empty() || !self.process.is_empty()).then(|| WindowInfo {
+            title: self.title,
+            process: self.process,
+            bounds: BoundingBox::new(self.x, self.y, self.width.max(0) as u32, self.height.max(0) as u32),
+        });
+        ElementSnapshot {
+            window,
+            elements,
+            captured_at: Utc::now(),
+        }
+    }
+}
+
+/// Windows UI Automation
+#[cfg(target_os = "windows")]
+const UIA_SCRIPT: &str = r#"
+Add-Type -AssemblyName UIAutomationClient, UIAutomationTypes
+Add-Type 'using System; using System.Runtime.InteropServices; public static class Fg { [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow(); }'
+$root = [System.Windows.Automation.AutomationElement]::FromHandle([Fg]::GetForegroundWindow())
+$all = $root.FindAll([System.Windows.Automation.TreeScope]::Descendants, [System.Windows.Automation.Condition]::TrueCondition)
+$out = foreach ($e in $all) {
+  $c = $e.Current; $r = $c.BoundingRectangle
+  if ($r.IsEmpty -or $c.IsOffscreen) { continue }
+  [pscustomobject]@{ role = $c.ControlType.ProgrammaticName; name = $c.Name; x = $r.X; y = $r.Y; width = $r.Width; height = $r.Height; enabled = $c.IsEnabled; focused = $c.HasKeyboardFocus }
+}
+$w = $root.Current.BoundingRectangle
+@{ title = $root.Current.Name; process = (Get-Process -Id $root.Current.ProcessId).ProcessName; x = [int]$w.X; y = [int]$w.Y; width = [int]$w.Width; height = [int]$w.Height; elements = @($out | Select-Object -First 2000) } | ConvertTo-Json -Depth 4 -Compress
+"#;
+
+/// Linux AT-SPI（需要 python3 与 gir1.2-atspi）
+#[cfg(target_os = "linux")]
+const ATSPI_SCRIPT: &str = r#"
+import json, gi
+gi.require_version("Atspi", "2.0")
+from gi.repository import Atspi
+win, proc = None, ""
+desktop = Atspi.get_desktop(0)
+for i in range(desktop.get_child_count()):
+    app = desktop.get_child_at_index(i)
+    for j in range(app.get_child_count() if app else 0):
+        w = app.get_child_at_index(j)
+        if w and w.get_state_set().contains(Atspi.StateType.ACTIVE):
+            win, proc = w, app.get_name()
+out = []
+def walk(el, depth):
+    if depth > 15 or len(out) >= 2000:
+        return
+    for i in range(el.get_child_count()):
+        c = el.get_child_at_index(i)
+        if c is None:
+            continue
+        try:
+            r, st = c.get_extents(Atspi.CoordType.SCREEN), c.get_state_set()
+            if st.contains(Atspi.StateType.SHOWING):
+                out.append({"role": c.get_role_name(), "name": c.get_name(), "x": r.x, "y": r.y, "width": r.width, "height": r.height,
+                            "enabled": st.contains(Atspi.StateType.ENABLED), "focused": st.contains(Atspi.StateType.FOCUSED)})
+        except Exception:
+            pass
+        walk(c, depth + 1)
+if win is None:
+    print(json.dumps({"elements": []}))
+else:
+    walk(win, 0)
+    r = win.get_extents(Atspi.CoordType.SCREEN)
+    print(json.dumps({"title": win.get_name(), "process": proc, "x": r.x, "y": r.y, "width": r.width, "height": r.height, "elements": out}))
+"#;
+
+/// macOS Accessibility（需要为终端 / 守护进程授予辅助功能权限）
+#[cfg(target_os = "macos")]
+const AX_SCRIPT: &str = r#"
+const se = Application("System Events");
+const proc = se.processes.whose({ frontmost: true })[0];
+const win = proc.windows[0];
+const out = [];
+const attr = (f, d) => { try { const v = f(); return v === null || v === undefined ? d : v; } catch (e) { return d; } };
+function walk(el, depth) {
+  if (depth > 15 || out.length >= 2000) return;
+  for (const k of attr(() => el.uiElements(), [])) {
+    const p = attr(() => k.position(), null), s = attr(() => k.size(), null);
+    if (p && s) out.push({ role: attr(() => k.role(), ""), name: attr(() => k.name(), "") || attr(() => k.description(), ""),
+      value: String(attr(() => k.value(), "")), x: p[0], y: p[1], width: s[0], height: s[1],
+      enabled: attr(() => k.enabled(), true), focused: attr(() => k.focused(), false) });
+    walk(k, depth + 1);
+  }
+}
+walk(win, 0);
+const wp = win.position(), ws = win.size();
+JSON.stringify({ title: attr(() => win.name(), ""), process: proc.name(), x: wp[0], y: wp[1], width: ws[0], height: ws[1], elements: out });
+"#;
+
+/// 基于系统无障碍接口的元素提供者
+pub struct PlatformElementProvider {
+    timeout: Duration,
+}
+
+impl PlatformElementProvider {
+    /// 创建提供者（默认超时 10 秒，大型窗口的无障碍树遍历较慢）
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// 设置超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 当前平台的脚本宿主命令
+    fn command() -> Option<tokio::process::Command> {
+        #[cfg(target_os = "windows")]
+        {
+            let mut command = tokio::process::Command::new("powershell");
+            command.args(["-NoProfile", "-NonInteractive", "-Command", UIA_SCRIPT]);
+            Some(command)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let mut command = tokio::process::Command::new("osascript");
+            command.args(["-l", "JavaScript", "-e", AX_SCRIPT]);
+            Some(command)
+        }
+        #[cfg(target_os = "linux")]
+        {
+            let mut command = tokio::process::Command::new("python3");
+            command.args(["-c", ATSPI_SCRIPT]);
+            Some(command)
+        }
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        {
+            None
+        }
+    }
+}
+
+impl Default for PlatformElementProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ElementProvider for PlatformElementProvider {
+    fn name(&self) -> &str {
+        if cfg!(target_os = "windows") {
+            "uia"
+        } else if cfg!(target_os = "macos") {
+            "ax"
+        } else {
+            "atspi"
+        }
+    }
+
+    async fn snapshot(&self) -> Result<ElementSnapshot> {
+        let Some(mut command) = Self::command() else {
+            return Err(NeuroLoomError::Vision(
+                "UI element enumeration is not supported on this platform".to_string(),
+            ));
+        };
+        command.kill_on_drop(true);
+        let output = tokio::time::timeout(self.timeout, command.output())
+            .await
+            .map_err(|_| NeuroLoomError::Vision(format!("{} enumeration timed out", self.name())))??;
+        if !output.status.success() {
+            return Err(NeuroLoomError::Vision(format!(
+                "{} enumeration failed: {}",
+                self.name(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let raw: RawWindow = serde_json::from_slice(&output.stdout)?;
+        Ok(raw.into_snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::TextRegion;
+
+    #[test]
+    fn test_resolve_with_ocr_merge() {
+        let raw: RawWindow = serde_json::from_value(serde_json::json!({
+            "title": "Untitled - Notepad",
+            "process": "notepad",
+            "x": 0, "y": 0, "width": 800, "height": 600,
+            "elements": [
+                { "role": "ControlType.Button", "name": "Save", "x": 300, "y": 500, "width": 80, "height": 24 },
+                { "role": "ControlType.Button", "name": "Save as", "x": 200, "y": 500, "width": 80, "height": 24 },
+                { "role": "AXStaticText", "name": "Save", "x": 10, "y": 10, "width": 40, "height": 16 },
+                { "role": "push button", "name": "", "x": 700, "y": 10, "width": 24, "height": 24 },
+                { "role": "edit", "name": "File name", "x": 10, "y": 40, "width": 300, "height": 24 }
+            ]
+        }))
+        .unwrap();
+        let mut snapshot = raw.into_snapshot();
+        snapshot.merge_ocr(
+            &OcrResult {
+                text: "Close Ready".to_string(),
+                confidence: 0.9,
+                regions: vec![
+                    TextRegion { text: "Close".to_string(), x: 704, y: 14, width: 16, height: 12, confidence: 0.9 },
+                    TextRegion { text: "Ready".to_string(), x: 10, y: 580, width: 40, height: 12, confidence: 0.9 },
+                ],
+            },
+            (0, 0),
+        );
+
+        assert_eq!(snapshot.resolve("the Save button").unwrap().bounds.x, 300);
+        assert_eq!(snapshot.resolve("Save").unwrap().role, ElementRole::Label);
+        assert_eq!(snapshot.resolve("\"File name\" field").unwrap().role, ElementRole::TextField);
+        let close = snapshot.resolve("close button").unwrap();
+        assert_eq!(close.source, ElementSource::Merged);
+        assert_eq!(snapshot.resolve(&format!("#{}", close.index)).unwrap().name, "Close");
+        assert_eq!(snapshot.resolve("Ready").unwrap().source, ElementSource::Ocr);
+        assert!(snapshot.resolve("Open button").is_err());
+    }
+}